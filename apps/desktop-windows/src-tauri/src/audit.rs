@@ -0,0 +1,62 @@
+//! Recording actions in the active profile's audit log
+//!
+//! The log is `audit.bin` in the profile directory, sealed with the device
+//! key like the sessions next to it.
+
+use crate::{profiles, sessions};
+use genomeforge_core::audit::{AuditAction, AuditLog};
+use genomeforge_core::crypto::KeySource;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+
+/// Audit log inside the profile directory
+const AUDIT_LOG: &str = "audit.bin";
+
+/// Held while the log is read and written back, so that two commands
+/// finishing together do not drop each other's entries
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// The active profile's audit log
+pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<AuditLog, String> {
+    let key = sessions::device_key(&sessions::session_dir(app)?)?;
+    AuditLog::open(&log_path(app)?, KeySource::Device(&key))
+}
+
+/// Append an action to the active profile's audit log
+///
+/// Recording is best effort: a log that cannot be written is reported in
+/// the application log rather than failing the action it records.
+pub fn record<R: Runtime, I>(app: &AppHandle<R>, action: AuditAction, subject: &str, details: I)
+where
+    I: IntoIterator<Item = (&'static str, String)>,
+{
+    let details = details
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value));
+    let result = (|| {
+        let _writing = WRITING.lock().map_err(|_| "Audit log lock poisoned")?;
+        let key = sessions::device_key(&sessions::session_dir(app)?)?;
+        let path = log_path(app)?;
+        let mut log = AuditLog::open(&path, KeySource::Device(&key))?;
+        log.append(action, subject, details);
+        log.save(&path, KeySource::Device(&key))
+    })();
+    if let Err(error) = result {
+        tracing::warn!(%error, action = ?action, "failed to record audit entry");
+    }
+}
+
+/// Name of a file as recorded, without the folders it is in
+pub fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+// Helper functions
+
+fn log_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    Ok(profiles::active_dir(app)?.join(AUDIT_LOG))
+}
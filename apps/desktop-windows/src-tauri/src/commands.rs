@@ -2,8 +2,138 @@
 //!
 //! These commands are callable from the frontend via Tauri's invoke system.
 
+use crate::error::GenomeForgeError;
+use crate::events::{
+    self, AnalysisComplete, AnalysisFailed, BatchProgress, BatchStage, ParseProgressEvent,
+    ParseWarning, TaskStarted,
+};
+use crate::export::{self, ExportFormat, ExportInfo};
+use crate::history::{AnalysisDiff, SavedResult};
+use crate::medications::{self, MedicationReview};
+use crate::plugins::{self, PluginEntry, PluginRun};
+use crate::profiles::{self, Parked, Profile, ProfileEntry};
+use crate::reanalysis::FindingChanges;
+use crate::results::{
+    self, ConditionGroup, FindingFilter, FindingSection, FindingSort, NotedFinding, RuleResults,
+    SearchResult, SectionCount,
+};
+use crate::streaming::FindingStream;
+use crate::templates::TemplateEntry;
+use crate::trace::{self, FindingTrace};
+use crate::{
+    audit, databases, history, intake, launch, logging, notes, notify, reanalysis, report,
+    rule_sets, sessions, settings, system, templates, updater, watcher, watchlist, AppState,
+};
+use genomeforge_core::admixture::{self, AncestryEstimate, ReferencePanel};
+use genomeforge_core::alignment::{self, BamFile, PileupOptions, Target};
+use genomeforge_core::annotation::acmg::{self, AcmgCategory, Inheritance, SecondaryFinding};
+use genomeforge_core::annotation::apoe::{self, ApoeCall};
+use genomeforge_core::annotation::blood_type::{self, BloodTypePrediction};
+use genomeforge_core::annotation::carrier::{
+    self, CarrierInheritance, CarrierResult, CarrierStatus,
+};
+use genomeforge_core::annotation::clingen::{DosageMatch, DosageScore};
+use genomeforge_core::annotation::clinvar::{
+    ClinVarDatabase, ClinVarMatch, ClinVarRecord, ClinicalSignificance, ReviewStatus,
+};
+use genomeforge_core::annotation::consent::{ConsentPolicy, FindingCategory};
+use genomeforge_core::annotation::cpic::{DiplotypeCall, Recommendation};
+use genomeforge_core::annotation::custom::CustomFinding;
+use genomeforge_core::annotation::dbsnp::Normalization;
+use genomeforge_core::annotation::delta::ReleaseDelta;
+use genomeforge_core::annotation::fitness;
+use genomeforge_core::annotation::genes;
+use genomeforge_core::annotation::gnomad::{AlleleFrequencies, GnomadDatabase};
+use genomeforge_core::annotation::gwas::{
+    self, EffectDirection, EffectSize, GwasMatch, TraitCategory, GENOME_WIDE_SIGNIFICANCE,
+};
+use genomeforge_core::annotation::haplogroup::HaplogroupReport;
+use genomeforge_core::annotation::hla::{self, HlaCall};
+use genomeforge_core::annotation::manager::{
+    self, DatabaseKind, Installation, InstalledRelease, InstalledReleases, IntegrityCheck,
+    LoadedDatabase, Release,
+};
+use genomeforge_core::annotation::nutrigenomics::{self, NutritionFinding};
+use genomeforge_core::annotation::pack;
+use genomeforge_core::annotation::pharmgkb::{EvidenceLevel, PharmGkbMatch, PhenotypeCategory};
+use genomeforge_core::annotation::probes::{self, MaskStats};
+use genomeforge_core::annotation::strand::Strand;
+use genomeforge_core::annotation::zygosity::{
+    self, FindingZygosity, InheritanceMode, Interpretation, Zygosity,
+};
+use genomeforge_core::annotation::{self as annotation, guidelines, DatabaseSnapshot};
+use genomeforge_core::audit::{AuditAction, AuditEntry, AuditVerification};
+use genomeforge_core::benchmark::{self, BenchmarkReport};
+use genomeforge_core::cache::GenomeCache;
+use genomeforge_core::compare::{self, GenomeComparison};
+use genomeforge_core::completeness::{self, DataQuality};
+use genomeforge_core::confidence::{self, Confidence, Evidence};
+use genomeforge_core::crypto::{Key, KeySource, Zeroizing};
+use genomeforge_core::diagnostics::{self, DiagnosticBundle, LogEntry, LogLevel};
+use genomeforge_core::fingerprint::{FileFingerprint, FingerprintLog};
+use genomeforge_core::genome::{StructuralKind, StructuralVariant};
+use genomeforge_core::imputation::{self, ImputationFilter, ImputationStats};
+use genomeforge_core::kinship::{self, Kinship};
+use genomeforge_core::liftover::{self, LiftoverStats};
+use genomeforge_core::merge::{self, MergeConflict, MergeSource, MergeStats};
+use genomeforge_core::normalize::{self, IndexedFasta, NormalizationStats};
+use genomeforge_core::notes::FindingNote;
+use genomeforge_core::parallel;
+use genomeforge_core::parser::compression::{self, Compression};
+use genomeforge_core::parser::detect::FileFormat;
+use genomeforge_core::parser::plink;
+use genomeforge_core::parser::progress::{ByteCounter, ParseProgress};
+use genomeforge_core::parser::tabix::IndexedVcf;
+use genomeforge_core::parser::{self, ChromosomeCount};
+use genomeforge_core::plugin::{self, AnalysisPlugin, PluginFinding};
+use genomeforge_core::prs::{MissingStrategy, PrsResult, ReferenceDistribution, ScoringFile};
+use genomeforge_core::purge::{self, PurgeReport};
+use genomeforge_core::quality::{self, QualityFilter, QualityIssue, QualityStats};
+use genomeforge_core::reference::{InstalledReference, ReferenceManager};
+use genomeforge_core::report::html;
+use genomeforge_core::report::i18n::Locale;
+use genomeforge_core::report::template::ReportTemplate;
+use genomeforge_core::results_db::{ResultQuery, ResultsStore, Run};
+use genomeforge_core::rules::expr::Expr;
+use genomeforge_core::rules::{self, CompiledRule, RuleSet};
+use genomeforge_core::search::{Page, Query};
+use genomeforge_core::session::{self, SessionEntry};
+use genomeforge_core::settings::Settings;
+use genomeforge_core::sex::{self, Sex, SexCheck, SexWarning};
+use genomeforge_core::stream::{self, SiteFilter, StreamOptions};
+use genomeforge_core::tasks::{self, CancelFlag, TaskId, TaskInfo, TaskKind};
+use genomeforge_core::trio::{self, CoupleRisk, MendelianCheck};
+use genomeforge_core::watchlist::{WatchEntry, WatchResult, Watchlist};
+use genomeforge_core::workload::{self, Machine, WorkloadEstimate};
+use genomeforge_core::{GenomeBuild, LoadedGenome, Region, TaskHandle, Variant};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+/// Files of a batch analyzed at once at most
+const MAX_BATCH_CONCURRENCY: usize = 4;
+
+/// Extension added to encrypted batch reports, except PDFs, which stay
+/// openable by any viewer
+const ENCRYPTED_EXTENSION: &str = "gfenc";
+
+/// Log entries `get_recent_logs` returns by default
+const RECENT_LOGS: usize = 200;
+
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Warning of a parse that keeps only the sites the databases know
+const SITES_ONLY_WARNING: &str = "This file is too large to hold in memory, so only the variants the installed databases annotate are kept. Analysis results are complete, but browsing shows only those variants; load the file again after installing new databases.";
+
+/// Fingerprints of the files loaded before, in the profile directory
+const FINGERPRINT_LOG: &str = "fingerprints.bin";
 
 /// System information
 #[derive(Debug, Serialize)]
@@ -11,7 +141,12 @@ pub struct SystemInfo {
     pub os: String,
     pub os_version: String,
     pub arch: String,
+    /// Physical memory in bytes; 0 when unknown
     pub memory_total: u64,
+    /// Physical memory not in use, in bytes; 0 when unknown
+    pub memory_available: u64,
+    /// Free bytes on the volume holding the app data directory
+    pub disk_free: Option<u64>,
     pub cpu_cores: usize,
 }
 
@@ -20,48 +155,638 @@ pub struct SystemInfo {
 pub struct ParseResult {
     pub success: bool,
     pub variant_count: usize,
-    pub file_type: String,
+    pub file_type: FileFormat,
+    /// Detector confidence for `file_type`, from 0.0 to 1.0
+    pub detection_confidence: f64,
+    pub compression: Compression,
+    /// Format version from the file header, e.g. "VCFv4.2"
+    pub format_version: Option<String>,
+    /// Genotyping array version for consumer exports, e.g. "v5" or "V2.0"
+    pub chip_version: Option<String>,
+    pub genome_build: Option<GenomeBuild>,
+    pub sample_count: usize,
+    pub no_call_count: usize,
+    /// Fraction of variants with a genotype call (0.0 - 1.0)
+    pub call_rate: f64,
+    pub multiallelic_count: usize,
+    /// Structural variants and copy-number changes, held apart from the
+    /// variants
+    pub structural_variant_count: usize,
+    /// Data lines that could not be parsed and were skipped
+    pub skipped_lines: usize,
+    pub chromosome_counts: Vec<ChromosomeCount>,
+    /// Only the variants at sites the databases know were kept, as the
+    /// file was too large to hold
+    pub sites_only: bool,
+    /// Read from the parsed genome cache instead of parsing the file
+    pub from_cache: bool,
+    /// Genetic sex, checked against the declared one when given
+    pub sex: SexCheck,
     pub error: Option<String>,
 }
 
+impl ParseResult {
+    fn new(genome: &LoadedGenome) -> Self {
+        let file = genome.file.clone();
+        let summary = genome.summary.clone();
+        ParseResult {
+            success: true,
+            variant_count: summary.variant_count,
+            file_type: file.format,
+            detection_confidence: file.detection_confidence,
+            compression: file.compression,
+            format_version: file.format_version,
+            chip_version: file.chip_version,
+            genome_build: file.genome_build,
+            // Consumer exports carry one unnamed sample
+            sample_count: file.samples.len().max(1),
+            no_call_count: summary.no_call_count,
+            call_rate: summary.call_rate,
+            multiallelic_count: summary.multiallelic_count,
+            structural_variant_count: genome.structural_variants().len(),
+            skipped_lines: summary.skipped_lines,
+            chromosome_counts: summary.chromosome_counts,
+            sites_only: false,
+            from_cache: false,
+            sex: sex::check(genome, None),
+            error: None,
+        }
+    }
+}
+
 /// Analysis result
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisResultData {
     pub clinical_findings: Vec<ClinicalFinding>,
+    /// ACMG secondary findings, only screened for when requested
+    pub acmg_findings: Vec<AcmgFinding>,
+    /// Carrier status for recessive conditions
+    pub carrier_findings: Vec<CarrierFinding>,
+    /// APOE genotype, only reported with consent to late-onset findings
+    pub apoe: Option<ApoeFinding>,
     pub drug_responses: Vec<DrugResponse>,
+    /// Star-allele diplotypes of the pharmacogenes CPIC defines
+    pub diplotypes: Vec<DiplotypeCall>,
     pub trait_associations: Vec<TraitAssociation>,
+    /// Y-chromosome and mitochondrial haplogroups
+    pub haplogroups: Option<HaplogroupReport>,
+    /// Drug hypersensitivity HLA alleles, typed or tagged by proxy SNPs
+    #[serde(default)]
+    pub hla_risks: Vec<HlaCall>,
+    /// The nutrition and metabolism panel, of lower evidence than the
+    /// clinical findings
+    #[serde(default)]
+    pub nutrition: Vec<NutritionFinding>,
+    /// Deletions and duplications of dosage-sensitive genes and regions
+    #[serde(default)]
+    pub structural_findings: Vec<StructuralFinding>,
+    /// Findings of the enabled analysis plugins
+    #[serde(default)]
+    pub plugin_findings: Vec<PluginFinding>,
+    /// Findings of the annotation table the user imported
+    #[serde(default)]
+    pub custom_findings: Vec<CustomFinding>,
+    /// Calls at the entries of the profile's watchlist, whether or not a
+    /// database reports anything there
+    #[serde(default)]
+    pub watchlist: Vec<WatchResult>,
+    /// Bookmarks and notes on the findings, added to exports that include
+    /// them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<FindingNote>,
+    pub summary: AnalysisSummary,
+}
+
+/// What `analyze_variants` returns
+///
+/// The findings themselves are fetched a page at a time with
+/// `get_findings_page` and `search_findings`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisOverview {
     pub summary: AnalysisSummary,
+    /// Findings in each section
+    pub sections: Vec<SectionCount>,
+    pub apoe: Option<ApoeFinding>,
+    pub haplogroups: Option<HaplogroupReport>,
+    pub watchlist: Vec<WatchResult>,
+}
+
+impl AnalysisOverview {
+    fn new(result: &AnalysisResultData) -> Self {
+        AnalysisOverview {
+            summary: result.summary.clone(),
+            sections: FindingSection::ALL
+                .iter()
+                .map(|&section| SectionCount {
+                    section,
+                    count: results::count(result, section),
+                })
+                .collect(),
+            apoe: result.apoe.clone(),
+            haplogroups: result.haplogroups.clone(),
+            watchlist: result.watchlist.clone(),
+        }
+    }
+}
+
+/// What `load_session` restored
+#[derive(Debug, Serialize)]
+pub struct LoadedSession {
+    pub parse: ParseResult,
+    /// Present when the session was saved after an analysis
+    pub analysis: Option<AnalysisOverview>,
+}
+
+/// Genome loaded by `merge_genomes`, with how it was merged
+#[derive(Debug, Serialize)]
+pub struct MergeResult {
+    pub parse: ParseResult,
+    /// The files merged, in the order given
+    pub sources: Vec<MergeSource>,
+    pub stats: MergeStats,
+    /// Sites the files called differently, and the call kept
+    pub conflicts: Vec<MergeConflict>,
 }
 
+/// Profile made active by `switch_profile`, with what it has loaded
 #[derive(Debug, Serialize)]
+pub struct ProfileSwitch {
+    pub profile: Profile,
+    pub parse: Option<ParseResult>,
+    pub analysis: Option<AnalysisOverview>,
+}
+
+/// A child's genome compared with both parents' by `analyze_trio`
+#[derive(Debug, Serialize)]
+pub struct TrioReport {
+    pub mendelian: MendelianCheck,
+    /// Empty when no ClinVar release is installed
+    pub compound_heterozygous: Vec<CompoundHetFinding>,
+    /// Conditions the parents could pass on to further children
+    pub carrier_couples: Vec<CoupleRisk>,
+    pub residual_risk: String,
+}
+
+/// Pathogenic variants in one recessive gene of the child, by parent
+#[derive(Debug, Serialize)]
+pub struct CompoundHetFinding {
+    pub gene: String,
+    /// Whether a variant came from each parent, so that both copies of
+    /// the gene are affected
+    pub in_trans: bool,
+    pub maternal: Vec<ClinicalFinding>,
+    pub paternal: Vec<ClinicalFinding>,
+    /// Variants whose parent of origin could not be told
+    pub unresolved: Vec<ClinicalFinding>,
+}
+
+/// A deletion or duplication overlapping a gene or region ClinGen scores
+/// as sensitive to losing or gaining a copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuralFinding {
+    pub id: Option<String>,
+    pub kind: StructuralKind,
+    pub chromosome: String,
+    pub start: u64,
+    pub end: u64,
+    pub length: u64,
+    /// Copies of the variant allele, when genotyped
+    pub copies: Option<usize>,
+    /// Total copies of the segment, when the caller gives it
+    pub copy_number: Option<u32>,
+    /// Gene symbol or ClinGen region name
+    pub gene: String,
+    /// Haploinsufficiency score for a deletion, triplosensitivity score
+    /// for a duplication
+    pub score: DosageScore,
+    /// MONDO id of the disease the dosage change causes
+    pub disease: Option<String>,
+    /// Share of the gene or region covered, 0.0 - 1.0
+    pub overlap: f64,
+    /// FILTER values the call failed
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+impl StructuralFinding {
+    fn from_match(found: &DosageMatch) -> Self {
+        let variant = &found.variant;
+        let issues: &[QualityIssue] = if variant.filters.is_empty() {
+            &[]
+        } else {
+            &[QualityIssue::FailedFilter]
+        };
+        StructuralFinding {
+            id: variant.id.clone(),
+            kind: variant.kind,
+            chromosome: variant.chromosome.clone(),
+            start: variant.start,
+            end: variant.end,
+            length: variant.length(),
+            copies: variant.copies,
+            copy_number: variant.copy_number,
+            gene: found.region.name.clone(),
+            score: found.score,
+            disease: found.disease.clone(),
+            overlap: found.overlap,
+            filters: variant.filters.clone(),
+            confidence: Confidence::new(
+                Evidence::Dosage(found.score),
+                confidence::call_reliability(false, issues, Strand::Forward),
+            ),
+        }
+    }
+
+    /// Sufficient evidence that the dosage change causes disease, or
+    /// both copies of a recessive gene lost
+    pub fn is_actionable(&self) -> bool {
+        matches!(
+            self.score,
+            DosageScore::Sufficient | DosageScore::AutosomalRecessive
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClinicalFinding {
     pub rsid: String,
     pub gene: Option<String>,
     pub condition: String,
-    pub significance: String,
+    pub significance: ClinicalSignificance,
+    /// Classification as written in the ClinVar release
+    pub significance_label: String,
+    pub review_status: ReviewStatus,
+    /// ClinVar review stars (0-4)
+    pub review_stars: u8,
+    pub conditions: Vec<String>,
+    /// Ontology terms ClinVar gives the conditions, e.g. "MONDO:0005045"
+    #[serde(default)]
+    pub condition_ids: Vec<String>,
+    pub variation_id: Option<u64>,
+    pub genotype: String,
+    /// Copies of the classified allele carried (1 or 2)
+    pub allele_copies: usize,
+    pub zygosity: Zygosity,
+    pub inheritance: Option<InheritanceMode>,
+    /// Carrier or affected, for pathogenic findings of known inheritance
+    pub interpretation: Option<Interpretation>,
     pub chromosome: Option<String>,
     pub position: Option<u64>,
+    /// gnomAD frequencies of the classified allele
+    pub allele_frequency: Option<AlleleFrequencies>,
+    /// Whether the genotype was imputed rather than measured
+    #[serde(default)]
+    pub imputed: bool,
+    /// What makes the sequencing call borderline, when it is
+    #[serde(default)]
+    pub borderline_quality: Vec<QualityIssue>,
+    /// Strand the database alleles were read from; ambiguous A/T and C/G
+    /// SNPs are taken as reported
+    #[serde(default)]
+    pub strand: Strand,
+    /// Date of the ClinVar release the finding was annotated from
+    #[serde(default)]
+    pub clinvar_release: Option<String>,
+    /// From the review stars and the reliability of the call
+    #[serde(default)]
+    pub confidence: Confidence,
 }
 
-#[derive(Debug, Serialize)]
+impl ClinicalFinding {
+    fn from_match(
+        found: &ClinVarMatch<'_>,
+        zygosity: FindingZygosity,
+        allele_frequency: Option<&AlleleFrequencies>,
+        clinvar_release: Option<&str>,
+    ) -> Self {
+        let record = found.record;
+        let rsid = record
+            .rsid
+            .clone()
+            .or_else(|| found.variant.rsid.clone())
+            .unwrap_or_else(|| format!("{}:{}", record.chromosome, record.position));
+        let imputed = found.variant.is_imputed();
+        let borderline_quality = quality::borderline(found.variant);
+        let reliability = confidence::call_reliability(imputed, &borderline_quality, found.strand);
+
+        ClinicalFinding {
+            rsid,
+            gene: record.genes.first().cloned(),
+            condition: record
+                .conditions
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "Condition not specified".to_string()),
+            significance: record.significance,
+            significance_label: record.significance_label.clone(),
+            review_status: record.review_status,
+            review_stars: record.review_status.stars(),
+            conditions: record.conditions.clone(),
+            condition_ids: record.condition_ids.clone(),
+            variation_id: record.variation_id,
+            genotype: found.variant.genotype.to_string(),
+            allele_copies: found.alternate_copies,
+            zygosity: zygosity.zygosity,
+            inheritance: zygosity.inheritance,
+            interpretation: zygosity.interpretation,
+            chromosome: Some(found.variant.chromosome.clone()),
+            position: Some(found.variant.position),
+            allele_frequency: allele_frequency.cloned(),
+            imputed,
+            borderline_quality,
+            strand: found.strand,
+            clinvar_release: clinvar_release.map(str::to_string),
+            confidence: Confidence::new(
+                Evidence::ClinVar(record.review_status.stars()),
+                reliability,
+            ),
+        }
+    }
+}
+
+/// Reportable variants in one gene of the ACMG secondary findings list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcmgFinding {
+    pub gene: String,
+    pub condition: String,
+    pub category: AcmgCategory,
+    pub inheritance: Inheritance,
+    /// Version of the ACMG SF list, e.g. "3.2"
+    pub acmg_version: String,
+    pub variants: Vec<ClinicalFinding>,
+    /// That of the most confident variant
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+impl AcmgFinding {
+    fn from_finding(finding: &SecondaryFinding<'_>, variants: Vec<ClinicalFinding>) -> Self {
+        AcmgFinding {
+            gene: finding.gene.gene.to_string(),
+            condition: finding.gene.condition.to_string(),
+            category: finding.gene.category,
+            inheritance: finding.gene.inheritance,
+            acmg_version: acmg::VERSION.to_string(),
+            confidence: most_confident(&variants),
+            variants,
+        }
+    }
+}
+
+/// Pathogenic variants in one gene for a recessive condition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarrierFinding {
+    pub gene: String,
+    pub condition: String,
+    pub inheritance: CarrierInheritance,
+    pub status: CarrierStatus,
+    /// Whether the genotype is expected to cause the condition, not just
+    /// to be passed on
+    pub affected: bool,
+    /// Limits of genotyping in general and, where known, for this gene
+    pub residual_risk: Vec<String>,
+    pub variants: Vec<ClinicalFinding>,
+    /// That of the most confident variant
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+impl CarrierFinding {
+    fn from_result(result: &CarrierResult<'_>, variants: Vec<ClinicalFinding>) -> Self {
+        let mut residual_risk = vec![carrier::RESIDUAL_RISK.to_string()];
+        residual_risk.extend(result.gene.caveat.map(str::to_string));
+        CarrierFinding {
+            gene: result.gene.gene.to_string(),
+            condition: result.gene.condition.to_string(),
+            inheritance: result.gene.inheritance,
+            status: result.status,
+            affected: result.status.is_affected(),
+            residual_risk,
+            confidence: most_confident(&variants),
+            variants,
+        }
+    }
+}
+
+/// APOE diplotype and what it means for late-onset Alzheimer's disease
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApoeFinding {
+    #[serde(flatten)]
+    pub call: ApoeCall,
+    pub condition: String,
+    pub caveat: String,
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+impl ApoeFinding {
+    fn from_call(call: ApoeCall) -> Self {
+        ApoeFinding {
+            confidence: confidence::apoe(&call),
+            call,
+            condition: apoe::CONDITION.to_string(),
+            caveat: apoe::CAVEAT.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DrugResponse {
     pub rsid: String,
     pub gene: String,
     pub drug: String,
     pub response: String,
     pub recommendation: String,
+    pub annotation_id: String,
+    pub evidence_level: EvidenceLevel,
+    pub phenotype_categories: Vec<PhenotypeCategory>,
+    pub genotype: String,
+    /// Dosing guideline and drug label sources, e.g. "CPIC" or "FDA label"
+    pub guideline_sources: Vec<String>,
+    pub url: Option<String>,
+    /// Star-allele diplotype of the gene, e.g. "*1/*2"
+    pub diplotype: Option<String>,
+    /// Phenotype of the diplotype, e.g. "Intermediate Metabolizer"
+    pub phenotype: Option<String>,
+    /// CPIC recommendation for the phenotype and drug
+    pub guideline: Option<Recommendation>,
+    /// Whether the genotype, or any call behind the diplotype, was imputed
+    #[serde(default)]
+    pub imputed: bool,
+    /// What makes the sequencing call borderline, when it is
+    #[serde(default)]
+    pub borderline_quality: Vec<QualityIssue>,
+    /// Strand the database alleles were read from; ambiguous A/T and C/G
+    /// SNPs are taken as reported
+    #[serde(default)]
+    pub strand: Strand,
+    /// From the evidence level, or the diplotype a CPIC recommendation
+    /// rests on, and the reliability of the calls
+    #[serde(default)]
+    pub confidence: Confidence,
 }
 
-#[derive(Debug, Serialize)]
+impl DrugResponse {
+    fn from_match(found: &PharmGkbMatch<'_>, diplotypes: &[DiplotypeCall]) -> Self {
+        let annotation = found.annotation;
+        let call = diplotypes
+            .iter()
+            .find(|call| annotation.genes.contains(&call.gene));
+        let drug = annotation.drugs.join(", ");
+        let recommendation = if !annotation.guideline_sources.is_empty() {
+            format!(
+                "Dosing guidance for {} is published by {}. Discuss with your prescriber before changing any medication.",
+                drug,
+                annotation.guideline_sources.join(", ")
+            )
+        } else if annotation.evidence_level.is_actionable() {
+            "Supported by clinical evidence. Discuss with your prescriber before changing any medication.".to_string()
+        } else {
+            "Limited evidence; for information only.".to_string()
+        };
+        let imputed = found.variant.is_imputed();
+        let borderline_quality = quality::borderline(found.variant);
+        let reliability = confidence::call_reliability(imputed, &borderline_quality, found.strand);
+
+        DrugResponse {
+            rsid: annotation.variant.clone(),
+            gene: annotation.genes.join(", "),
+            drug,
+            response: found.allele.text.clone(),
+            recommendation,
+            annotation_id: annotation.id.clone(),
+            evidence_level: annotation.evidence_level,
+            phenotype_categories: annotation.phenotype_categories.clone(),
+            genotype: found.variant.genotype.to_string(),
+            guideline_sources: annotation.guideline_sources.clone(),
+            url: annotation.url.clone(),
+            diplotype: call.map(|call| call.diplotype.clone()),
+            phenotype: call.map(|call| call.phenotype.clone()),
+            guideline: None,
+            imputed,
+            borderline_quality,
+            strand: found.strand,
+            confidence: Confidence::new(Evidence::PharmGkb(annotation.evidence_level), reliability),
+        }
+    }
+
+    /// Response predicted from a gene's diplotype by a CPIC guideline
+    fn from_recommendation(call: &DiplotypeCall, recommendation: &Recommendation) -> Self {
+        DrugResponse {
+            rsid: format!("{}{}", call.gene, call.diplotype),
+            gene: call.gene.clone(),
+            drug: recommendation.drug.clone(),
+            response: recommendation
+                .implication
+                .clone()
+                .unwrap_or_else(|| format!("{} {}", call.gene, call.phenotype)),
+            recommendation: recommendation.recommendation.clone(),
+            annotation_id: format!("CPIC:{}:{}", call.gene, recommendation.drug),
+            // CPIC guidelines are what PharmGKB rates as level 1A evidence
+            evidence_level: EvidenceLevel::Level1A,
+            phenotype_categories: Vec::new(),
+            genotype: call.diplotype.clone(),
+            guideline_sources: vec!["CPIC".to_string()],
+            url: None,
+            diplotype: Some(call.diplotype.clone()),
+            phenotype: Some(call.phenotype.clone()),
+            guideline: Some(recommendation.clone()),
+            imputed: call.sites_imputed > 0,
+            borderline_quality: Vec::new(),
+            strand: Strand::Forward,
+            confidence: call.confidence,
+        }
+    }
+
+    /// Strong evidence backed by a published guideline or label
+    fn is_actionable(&self) -> bool {
+        self.evidence_level.is_actionable() && !self.guideline_sources.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraitAssociation {
     pub rsid: String,
     pub trait_name: String,
-    pub category: String,
+    pub category: TraitCategory,
     pub effect: String,
-    pub confidence: f64,
+    /// From the p-value and effect size and the reliability of the call
+    pub confidence: Confidence,
+    pub effect_direction: EffectDirection,
+    pub effect_size: Option<EffectSize>,
+    pub risk_allele: String,
+    /// Copies of the risk allele carried (1 or 2)
+    pub risk_allele_copies: usize,
+    pub genotype: String,
+    pub p_value: f64,
+    pub genes: Vec<String>,
+    pub pubmed_id: Option<String>,
+    /// Global gnomAD frequency of the risk allele, when it is an ALT allele
+    pub risk_allele_frequency: Option<f64>,
+    /// Whether the genotype was imputed rather than measured
+    #[serde(default)]
+    pub imputed: bool,
+    /// What makes the sequencing call borderline, when it is
+    #[serde(default)]
+    pub borderline_quality: Vec<QualityIssue>,
+    /// Strand the database alleles were read from; ambiguous A/T and C/G
+    /// SNPs are taken as reported
+    #[serde(default)]
+    pub strand: Strand,
 }
 
-#[derive(Debug, Serialize)]
+impl TraitAssociation {
+    fn from_match(found: &GwasMatch<'_>, gnomad: Option<&GnomadDatabase>) -> Self {
+        let association = found.association;
+        let effect = match association.effect {
+            Some(EffectSize::OddsRatio(or)) if or >= 1.0 => {
+                format!("Increased likelihood (odds ratio {:.2})", or)
+            }
+            Some(EffectSize::OddsRatio(or)) => {
+                format!("Decreased likelihood (odds ratio {:.2})", or)
+            }
+            Some(EffectSize::Beta(beta)) if beta >= 0.0 => {
+                format!("Higher values (+{} per allele)", beta)
+            }
+            Some(EffectSize::Beta(beta)) => format!("Lower values ({} per allele)", beta),
+            None => "Associated, effect size not reported".to_string(),
+        };
+        let imputed = found.variant.is_imputed();
+        let borderline_quality = quality::borderline(found.variant);
+        let reliability = confidence::call_reliability(imputed, &borderline_quality, found.strand);
+
+        TraitAssociation {
+            rsid: association.rsid.clone(),
+            trait_name: association.trait_name.clone(),
+            category: association.category,
+            effect,
+            confidence: Confidence::new(Evidence::Gwas(association.confidence()), reliability),
+            effect_direction: association.direction(),
+            effect_size: association.effect,
+            risk_allele: association.risk_allele.clone(),
+            risk_allele_copies: found.risk_allele_copies,
+            genotype: found.variant.genotype.to_string(),
+            p_value: association.p_value,
+            genes: association.genes.clone(),
+            pubmed_id: association.pubmed_id.clone(),
+            risk_allele_frequency: gnomad.and_then(|gnomad| {
+                let risk_allele = found.strand.orient(&association.risk_allele);
+                gnomad
+                    .lookup_rsid(&association.rsid)
+                    .into_iter()
+                    .find(|record| record.alternate == risk_allele)
+                    .map(|record| record.frequencies.global)
+            }),
+            imputed,
+            borderline_quality,
+            strand: found.strand,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisSummary {
     pub total_variants: usize,
     pub analyzed_variants: usize,
@@ -69,6 +794,243 @@ pub struct AnalysisSummary {
     pub drug_count: usize,
     pub trait_count: usize,
     pub actionable_findings: usize,
+    /// Variants whose rsid was looked up in dbSNP from their position
+    pub rsids_resolved: usize,
+    /// Array calls whose alleles were looked up in dbSNP from their rsid
+    pub alleles_resolved: usize,
+    /// Clinical findings left out for exceeding `max_allele_frequency`
+    pub common_variants_suppressed: usize,
+    /// Pathogenic variants in ACMG secondary findings genes that are not
+    /// reported, because screening was not requested or, for recessive
+    /// genes, only one variant was found
+    pub secondary_findings_withheld: usize,
+    /// APOE calls, APOE variants and Alzheimer's disease associations not
+    /// reported for lack of consent to late-onset findings
+    pub late_onset_withheld: usize,
+    /// Categories the consent policy left out of the analysis
+    #[serde(default)]
+    pub excluded_categories: BTreeSet<FindingCategory>,
+    /// ClinVar matches dropped for being in an excluded category
+    #[serde(default)]
+    pub consent_withheld: usize,
+    /// Build of the uploaded genome, from its header or marker positions
+    pub genome_build: Option<GenomeBuild>,
+    /// Lifted, dropped and ambiguous counts when the genome was lifted over
+    pub liftover: Option<LiftoverStats>,
+    /// What was split, trimmed and left-aligned in VCF records
+    pub variant_normalization: Option<NormalizationStats>,
+    /// Imputed and genotyped calls, and the imputed ones left out, for
+    /// genomes from an imputation server
+    #[serde(default)]
+    pub imputation: Option<ImputationStats>,
+    /// Calls left out for their FILTER, QUAL, GQ or depth and those kept
+    /// as borderline, for genomes from a variant caller
+    #[serde(default)]
+    pub quality: Option<QualityStats>,
+    /// The array chip and the calls of its known-unreliable probes left out
+    /// or marked, when the probe mask list is installed
+    #[serde(default)]
+    pub probe_mask: Option<MaskStats>,
+    /// Structural variants and copy-number changes of the genome
+    #[serde(default)]
+    pub structural_variants: usize,
+    /// Genetic sex, checked against the declared one when given
+    #[serde(default)]
+    pub sex: Option<SexCheck>,
+    /// A male's homozygous X and Y calls read as single copies
+    #[serde(default)]
+    pub hemizygous_calls: usize,
+    /// Findings whose database alleles were read from the opposite strand
+    #[serde(default)]
+    pub strand_flipped: usize,
+    /// Findings at A/T or C/G SNPs whose strand could not be told
+    #[serde(default)]
+    pub strand_ambiguous: usize,
+    /// Threads the ClinVar, PharmGKB and GWAS annotation ran on
+    #[serde(default)]
+    pub annotation_threads: usize,
+    /// Wall-clock seconds the annotation took; with `annotation_threads`
+    /// set to 1 this gives the speedup on the machine's `cpu_cores`
+    #[serde(default)]
+    pub annotation_seconds: f64,
+    /// Date of the ClinVar release the clinical findings are up to date with
+    #[serde(default)]
+    pub clinvar_release: Option<String>,
+    /// The analysis plugins run and how each fared
+    #[serde(default)]
+    pub plugins: Vec<PluginRun>,
+    /// The database releases the analysis read, to cite and reproduce it by
+    #[serde(default)]
+    pub databases: Vec<DatabaseRelease>,
+}
+
+/// Variants found by `query_region`
+#[derive(Debug, Serialize)]
+pub struct RegionQueryResult {
+    /// Gene symbol searched for, when the query named one
+    pub gene: Option<String>,
+    pub region: Region,
+    pub genome_build: Option<GenomeBuild>,
+    pub variants: Vec<RegionVariant>,
+    /// APOE findings not reported for lack of consent to late-onset findings
+    pub late_onset_withheld: usize,
+    /// ClinVar matches dropped for being in an excluded category
+    pub consent_withheld: usize,
+}
+
+/// A variant in a queried region with its annotations
+#[derive(Debug, Serialize)]
+pub struct RegionVariant {
+    #[serde(flatten)]
+    pub variant: Variant,
+    pub clinical_findings: Vec<ClinicalFinding>,
+    /// gnomAD frequencies of the first alternate allele carried
+    pub allele_frequency: Option<AlleleFrequencies>,
+}
+
+/// CPIC guidance for a drug given the user's diplotype, from
+/// `get_drug_guideline`
+#[derive(Debug, Serialize)]
+pub struct DrugGuideline {
+    pub gene: String,
+    pub drug: String,
+    /// e.g. "*1/*2"
+    pub diplotype: String,
+    /// e.g. "Intermediate Metabolizer"
+    pub phenotype: String,
+    /// Dosing recommendation with its strength and guideline version
+    pub recommendation: Recommendation,
+    /// Whether it comes from the built-in table rather than the installed
+    /// CPIC release
+    pub bundled: bool,
+}
+
+/// The evidence behind one finding, from `get_finding_details`
+#[derive(Debug, Serialize)]
+pub struct FindingDetails {
+    pub finding: ClinicalFinding,
+    /// The call at the site as read from the file, with its quality
+    pub call: Option<Variant>,
+    /// Every ClinVar record of the site, on any build
+    pub clinvar: Vec<ClinVarEvidence>,
+    /// PubMed IDs of the GWAS Catalog studies that report the site
+    pub pubmed_ids: Vec<String>,
+}
+
+/// A ClinVar record of a finding's site with what it means for the user
+#[derive(Debug, Serialize)]
+pub struct ClinVarEvidence {
+    #[serde(flatten)]
+    pub record: ClinVarRecord,
+    /// ClinVar review stars (0-4)
+    pub review_stars: u8,
+    /// Copies of the classified allele in the call, when it is known
+    pub allele_copies: Option<usize>,
+    /// gnomAD frequencies of the classified allele
+    pub allele_frequency: Option<AlleleFrequencies>,
+}
+
+/// Options for `analyze_variants`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AnalysisOptions {
+    /// Leave out clinical findings whose allele is more frequent than this
+    /// (0.0 - 1.0) in gnomAD, globally or in any population
+    pub max_allele_frequency: Option<f64>,
+    /// Screen the ACMG secondary findings genes; off unless the user opts
+    /// in, as these findings are unrelated to why most people upload
+    pub screen_secondary_findings: bool,
+    /// Report APOE genotype and other late-onset Alzheimer's disease
+    /// findings; off unless the user opts in, since many would rather not
+    /// know
+    pub report_late_onset: bool,
+    /// Uncompressed FASTA of the build the databases are published on, to
+    /// left-align VCF indels against; without it the installed reference of
+    /// that build is used, and without either they are only split and
+    /// trimmed
+    pub reference_fasta: Option<String>,
+    /// Installed reference FASTA files, filled in when the analysis starts
+    #[serde(skip)]
+    pub references: Option<ReferenceManager>,
+    /// Enabled analysis plugins, loaded when the analysis starts
+    #[serde(skip)]
+    pub plugins: Vec<Arc<dyn AnalysisPlugin>>,
+    /// The profile's watchlist, read when the analysis starts
+    #[serde(skip)]
+    pub watchlist: Watchlist,
+    /// Installed database releases, read when the analysis starts
+    #[serde(skip)]
+    pub installed: InstalledReleases,
+    /// Where each section's findings go once it is complete
+    #[serde(skip)]
+    pub stream: Option<Arc<FindingStream>>,
+    /// Threads to annotate on; all CPU cores by default
+    pub threads: Option<usize>,
+    /// Imputed calls to leave out as too uncertain; all are kept by default
+    pub imputation: ImputationFilter,
+    /// Sequencing calls to leave out as possible artifacts; all are kept
+    /// by default
+    pub quality: QualityFilter,
+    /// Sex the user declared, to check the genetic sex against
+    pub declared_sex: Option<Sex>,
+    /// Categories of findings the user opted out of, which are never
+    /// computed
+    pub consent: ConsentPolicy,
+}
+
+/// Options for `compute_prs`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PrsOptions {
+    /// What variants missing from the genome contribute
+    pub missing: MissingStrategy,
+    /// Distribution to rank the score against instead of the one implied
+    /// by the scoring file's allele frequencies
+    pub reference: Option<ReferenceDistribution>,
+}
+
+/// Options for `estimate_ancestry`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AncestryOptions {
+    /// Resamples used for the confidence intervals; 0 skips them
+    pub bootstrap_replicates: Option<usize>,
+}
+
+/// Options for `query_region`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct RegionQueryOptions {
+    /// bgzipped VCF to read through its tabix or CSI index instead of the
+    /// loaded genome; the index is built next to it when missing
+    pub file_path: Option<String>,
+    /// Report APOE findings, as for `analyze_variants`
+    pub report_late_onset: bool,
+    /// Categories of findings left out, as for `analyze_variants`
+    pub consent: ConsentPolicy,
+}
+
+/// A page of the audit log and whether the whole log is intact
+#[derive(Debug, Serialize)]
+pub struct AuditReport {
+    pub entries: Page<AuditEntry>,
+    pub verification: AuditVerification,
+}
+
+/// What `purge_all_data` removed from disk and memory
+#[derive(Debug, Serialize)]
+pub struct DataPurge {
+    #[serde(flatten)]
+    pub report: PurgeReport,
+    /// Size of the files removed
+    pub bytes: u64,
+    /// Genomes unloaded, the active profile's and those of the others
+    pub genomes: usize,
+    /// Genomes overwritten in memory; one still held by a task that has
+    /// not stopped yet is only dropped
+    pub genomes_zeroized: usize,
+    /// Analysis results unloaded
+    pub results: usize,
 }
 
 /// Database status
@@ -76,7 +1038,16 @@ pub struct AnalysisSummary {
 pub struct DatabaseStatus {
     pub clinvar: DatabaseInfo,
     pub pharmgkb: DatabaseInfo,
+    pub cpic: DatabaseInfo,
     pub gwas: DatabaseInfo,
+    pub dbsnp: DatabaseInfo,
+    pub gnomad: DatabaseInfo,
+    pub liftover: DatabaseInfo,
+    pub haplogroups: DatabaseInfo,
+    pub clingen: DatabaseInfo,
+    pub probe_mask: DatabaseInfo,
+    /// The annotation table the user imported
+    pub custom: DatabaseInfo,
 }
 
 #[derive(Debug, Serialize)]
@@ -84,14 +1055,184 @@ pub struct DatabaseInfo {
     pub loaded: bool,
     pub record_count: usize,
     pub last_updated: Option<String>,
+    /// Release version recorded when the database was installed
+    pub version: Option<String>,
+    /// When the release was installed, in seconds since the Unix epoch
+    pub installed_at: Option<u64>,
+    /// Hex SHA-256 of the release file as downloaded or imported
+    pub sha256: Option<String>,
+    /// Outcome of the latest `verify_databases` since the release was
+    /// installed
+    pub integrity: Option<IntegrityCheck>,
+}
+
+/// Outcome of checking one installed database, from `verify_databases`
+#[derive(Debug, Serialize)]
+pub struct DatabaseIntegrity {
+    pub database: DatabaseKind,
+    #[serde(flatten)]
+    pub check: IntegrityCheck,
+    /// Whether the damaged release was downloaded again and installed
+    pub repaired: bool,
+}
+
+impl DatabaseStatus {
+    pub fn get(&self, kind: DatabaseKind) -> &DatabaseInfo {
+        match kind {
+            DatabaseKind::ClinVar => &self.clinvar,
+            DatabaseKind::PharmGkb => &self.pharmgkb,
+            DatabaseKind::Cpic => &self.cpic,
+            DatabaseKind::Gwas => &self.gwas,
+            DatabaseKind::DbSnp => &self.dbsnp,
+            DatabaseKind::Gnomad => &self.gnomad,
+            DatabaseKind::Liftover => &self.liftover,
+            DatabaseKind::Haplogroups => &self.haplogroups,
+            DatabaseKind::ClinGen => &self.clingen,
+            DatabaseKind::ProbeMask => &self.probe_mask,
+            DatabaseKind::Custom => &self.custom,
+        }
+    }
+
+    /// The loaded databases, as an analysis run now would record them
+    pub fn releases(&self) -> Vec<DatabaseRelease> {
+        DatabaseKind::ALL
+            .into_iter()
+            .filter(|&kind| self.get(kind).loaded)
+            .map(|kind| {
+                let info = self.get(kind);
+                DatabaseRelease {
+                    database: kind,
+                    version: info.version.clone(),
+                    release_date: info.last_updated.clone(),
+                    sha256: info.sha256.clone(),
+                    record_count: info.record_count,
+                }
+            })
+            .collect()
+    }
+}
+
+impl DatabaseInfo {
+    fn loaded(
+        record_count: usize,
+        last_updated: Option<&str>,
+        installed: Option<&InstalledRelease>,
+    ) -> Self {
+        DatabaseInfo {
+            loaded: true,
+            record_count,
+            last_updated: last_updated.map(str::to_string),
+            version: installed.and_then(|release| release.version.clone()),
+            installed_at: installed.map(|release| release.installed_at),
+            sha256: installed.map(|release| release.sha256.clone()),
+            integrity: installed.and_then(|release| release.integrity.clone()),
+        }
+    }
+
+    fn missing() -> Self {
+        DatabaseInfo {
+            loaded: false,
+            record_count: 0,
+            last_updated: None,
+            version: None,
+            installed_at: None,
+            sha256: None,
+            integrity: None,
+        }
+    }
+}
+
+/// A database release an analysis read from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseRelease {
+    pub database: DatabaseKind,
+    /// Release version recorded when the database was installed
+    pub version: Option<String>,
+    /// Release date the database itself gives, e.g. ClinVar's
+    pub release_date: Option<String>,
+    /// Hex SHA-256 of the release file as downloaded or imported
+    pub sha256: Option<String>,
+    pub record_count: usize,
+}
+
+impl DatabaseRelease {
+    /// What the release is cited as: its version, or else its date
+    pub fn citation(&self) -> Option<&str> {
+        self.version.as_deref().or(self.release_date.as_deref())
+    }
+}
+
+/// Outcome of updating one database
+#[derive(Debug, Serialize)]
+pub struct DatabaseUpdate {
+    pub database: DatabaseKind,
+    pub version: Option<String>,
+    /// False when the installed release was already current
+    pub updated: bool,
+    pub record_count: usize,
+}
+
+/// What `import_database_pack` installed
+#[derive(Debug, Serialize)]
+pub struct PackImport {
+    /// Version of the pack
+    pub version: String,
+    pub databases: Vec<DatabaseUpdate>,
+    pub references: Vec<InstalledReference>,
 }
 
 /// Export options
 #[derive(Debug, Deserialize)]
 pub struct ExportOptions {
-    pub format: String,
+    pub format: ExportFormat,
     pub include_raw_data: bool,
     pub encrypt: bool,
+    /// Only write the variants with a finding to a VCF export
+    #[serde(default)]
+    pub findings_only: bool,
+    /// Id of the report template; the selected one when missing
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Language tag of the report, e.g. "de"; English when missing or
+    /// unsupported
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Add the profile's bookmarks and notes on the reported findings;
+    /// `batch_analyze`, whose files are not the profile's, leaves them out
+    #[serde(default)]
+    pub include_notes: bool,
+}
+
+/// Options of `batch_analyze`
+#[derive(Debug, Deserialize)]
+pub struct BatchOptions {
+    /// Folder the reports are written to, each named after its genome file
+    pub output_dir: String,
+    /// Files analyzed at once; one by default
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// As for `analyze_variants`, applied to every file
+    #[serde(default)]
+    pub analysis: Option<AnalysisOptions>,
+    pub export: ExportOptions,
+}
+
+/// How one file of a batch went
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchFile {
+    pub file_path: String,
+    /// Where its report was written
+    pub report_path: Option<String>,
+    pub summary: Option<AnalysisSummary>,
+    pub error: Option<String>,
+}
+
+/// What `batch_analyze` did, file by file in the order given
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+    pub task_id: TaskId,
+    pub files: Vec<BatchFile>,
+    pub failed: usize,
 }
 
 /// Get application version
@@ -102,160 +1243,3754 @@ pub fn get_app_version() -> String {
 
 /// Get system information
 #[tauri::command]
-pub fn get_system_info() -> SystemInfo {
+pub fn get_system_info(app: AppHandle) -> SystemInfo {
+    let memory = system::memory();
     SystemInfo {
         os: std::env::consts::OS.to_string(),
-        os_version: get_os_version(),
+        os_version: system::os_version(),
         arch: std::env::consts::ARCH.to_string(),
-        memory_total: get_total_memory(),
+        memory_total: memory.map_or(0, |memory| memory.total),
+        memory_available: memory.map_or(0, |memory| memory.available),
+        disk_free: app
+            .path()
+            .app_data_dir()
+            .ok()
+            .and_then(|dir| system::disk_free(&dir)),
+        cpu_cores: num_cpus(),
+    }
+}
+
+/// Time parsing and annotating generated genomes on this machine
+///
+/// Reports MB/s and variants/s, to set expectations before a large file
+/// is opened. The fixtures are written to the app's cache directory and
+/// removed afterwards; none of the user's data is read.
+#[tauri::command]
+pub async fn run_self_benchmark(app: AppHandle) -> Result<BenchmarkReport, GenomeForgeError> {
+    let dir = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to locate the cache directory: {}", e))?
+        .join("benchmark");
+    let report = tokio::task::spawn_blocking(move || {
+        let report = benchmark::run(&dir, benchmark::DEFAULT_VARIANTS);
+        let _ = std::fs::remove_dir(&dir);
+        report
+    })
+    .await
+    .map_err(|e| format!("Benchmark task failed: {}", e))??;
+    tracing::info!(
+        vcf_mb_per_second = report.vcf.mb_per_second,
+        annotation_variants_per_second = report.annotation.variants_per_second,
+        "ran self-benchmark"
+    );
+    Ok(report)
+}
+
+/// Estimate the variants, memory and time parsing and analysing a genome
+/// file take on this machine
+///
+/// The first variants of the file are parsed and extrapolated from,
+/// against the memory and cores `get_system_info` reports. Warns when the
+/// genome may not fit in the available memory, or is large enough that
+/// parsing it keeps only the sites the databases annotate.
+#[tauri::command]
+pub async fn estimate_workload(file_path: String) -> Result<WorkloadEstimate, GenomeForgeError> {
+    let path = PathBuf::from(&file_path);
+    if !path.is_file() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let memory = system::memory();
+    let machine = Machine {
+        memory_total: memory.map(|memory| memory.total),
+        memory_available: memory.map(|memory| memory.available),
         cpu_cores: num_cpus(),
+    };
+    // The same test a parse makes before streaming the file to its sites
+    let sites_only = file_size(&path).is_some_and(|(size, compression)| {
+        system::parse_memory_estimate(size, compression) > system::memory_budget(memory)
+    });
+    let mut estimate = tokio::task::spawn_blocking(move || workload::estimate(&path, &machine))
+        .await
+        .map_err(|e| format!("Workload estimate failed: {}", e))??;
+    if sites_only {
+        estimate.warnings.push(SITES_ONLY_WARNING.to_string());
     }
+    Ok(estimate)
 }
 
-/// Parse a genome file
+/// Parse a genome file and load it into the variant store
+///
+/// Runs as a `parse` task and emits `parse-progress` events while the file
+/// is read. A file too large to hold within `memory_budget_mb`, by default
+/// half the available memory, is streamed instead: only the variants at
+/// sites the installed databases know are kept, which is all an analysis
+/// needs. Files loaded whole are cached once parsed, so opening the same
+/// file again skips the parse.
+///
+/// `sample` picks whose genotypes to load from a file holding several, as
+/// listed by `list_samples`; the first is loaded by default.
 #[tauri::command]
-pub async fn parse_genome_file(file_path: String) -> Result<ParseResult, String> {
+pub async fn parse_genome_file(
+    app: AppHandle,
+    file_path: String,
+    memory_budget_mb: Option<u64>,
+    sample: Option<String>,
+    declared_sex: Option<Sex>,
+    state: State<'_, AppState>,
+) -> Result<ParseResult, GenomeForgeError> {
     let path = PathBuf::from(&file_path);
 
     if !path.exists() {
-        return Err("File not found".to_string());
+        return Err(GenomeForgeError::FileNotFound(None));
     }
 
-    // Detect file type based on extension and content
-    let file_type = detect_file_type(&path)?;
+    // Only one file is loaded at a time, so a newer parse supersedes
+    state.tasks.cancel_kind(TaskKind::Parse);
+    let task = start_task(&app, &state, TaskKind::Parse);
 
-    // For now, return a placeholder result
-    // In a real implementation, this would parse the actual file
+    let (task_id, cancel) = (task.id(), task.cancel_flag());
+    let memory = system::memory();
+    let budget = memory_budget_mb
+        .map(|mb| mb.saturating_mul(1_000_000))
+        .unwrap_or_else(|| system::memory_budget(memory));
+    let (file, stream) = fit_to_budget(&path, budget, &state.databases.snapshot());
+    let warning = match (&stream, file) {
+        (Some(_), _) => Some(SITES_ONLY_WARNING.to_string()),
+        (None, Some((size, compression))) => system::preflight(size, compression, memory),
+        (None, None) => None,
+    };
+    if let Some(message) = warning {
+        events::PARSE_WARNING.emit(&app, ParseWarning { task_id, message });
+    }
+
+    let sites_only = stream.is_some();
+    let loaded = tokio::task::spawn_blocking(move || {
+        // A genome streamed to its sites is not the whole file, and a
+        // cache that cannot be opened only costs a parse
+        let key = sessions::session_dir(&app)
+            .and_then(|dir| sessions::device_key(&dir))
+            .ok();
+        let cache = match (&stream, &key) {
+            (None, Some(key)) => genome_cache(&app, key.clone()).ok(),
+            _ => None,
+        };
+        let loaded = load_genome(
+            &app,
+            task_id,
+            &path,
+            sample.as_deref(),
+            stream.as_ref(),
+            cache.as_ref(),
+            &cancel,
+        )?;
+        audit::record(
+            &app,
+            AuditAction::FileLoaded,
+            &audit::file_name(&path),
+            loaded_details(&loaded.0),
+        );
+        let file = &loaded.0.file;
+        if let (Some(key), Some(fingerprint)) = (&key, &file.fingerprint) {
+            if let Some(message) = record_fingerprint(&app, key, fingerprint) {
+                events::PARSE_WARNING.emit(&app, ParseWarning { task_id, message });
+            }
+        }
+        // Before anything reads the sex chromosomes
+        let sex = sex::check(&loaded.0, declared_sex);
+        if let Some(message) = sex_warning(&sex) {
+            events::PARSE_WARNING.emit(&app, ParseWarning { task_id, message });
+        }
+        Ok::<_, String>((loaded, sex))
+    })
+    .await
+    .map_err(|e| format!("Parse task failed: {}", e))??;
+    let ((genome, from_cache), sex) = loaded;
+
+    let genome = state.genome.replace(genome);
+    state.results.clear();
+    tracing::info!(
+        format = ?genome.file.format,
+        variants = genome.len(),
+        sites_only,
+        from_cache,
+        "genome loaded"
+    );
     Ok(ParseResult {
-        success: true,
-        variant_count: 0, // Will be populated by actual parsing
-        file_type,
-        error: None,
+        sites_only,
+        from_cache,
+        sex,
+        ..ParseResult::new(&genome)
     })
 }
 
-/// Analyze variants from parsed genome data
+/// Names of the samples in a genome file, for choosing whose genotypes
+/// `parse_genome_file` loads; empty for files of a single person
 #[tauri::command]
-pub async fn analyze_variants(variant_count: usize) -> Result<AnalysisResultData, String> {
-    // This would integrate with the actual analysis engine
-    // For now, return a placeholder structure
-    Ok(AnalysisResultData {
-        clinical_findings: vec![],
-        drug_responses: vec![],
-        trait_associations: vec![],
-        summary: AnalysisSummary {
-            total_variants: variant_count,
-            analyzed_variants: 0,
-            clinical_count: 0,
-            drug_count: 0,
-            trait_count: 0,
-            actionable_findings: 0,
-        },
+pub fn list_samples(file_path: String) -> Result<Vec<String>, GenomeForgeError> {
+    Ok(parser::list_samples(Path::new(&file_path))?)
+}
+
+/// SHA-256, size and path of the loaded genome file as it was loaded
+#[tauri::command]
+pub fn get_file_fingerprint(
+    state: State<'_, AppState>,
+) -> Result<FileFingerprint, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    genome.file.fingerprint.clone().ok_or_else(|| {
+        GenomeForgeError::Failed(
+            "The loaded genome was saved before files were fingerprinted".to_string(),
+        )
     })
 }
 
-/// Export a report
+/// Call rates per chromosome of the loaded genome, how many sites it has
+/// in the genes of each panel, and the analyses its calls are too sparse
+/// for, such as a maternal haplogroup from a file without mtDNA
 #[tauri::command]
-pub async fn export_report(
-    report_id: String,
-    output_path: String,
-    options: ExportOptions,
-) -> Result<String, String> {
-    let path = PathBuf::from(&output_path);
+pub async fn get_data_quality(state: State<'_, AppState>) -> Result<DataQuality, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let quality = tokio::task::spawn_blocking(move || completeness::report(&genome))
+        .await
+        .map_err(|e| format!("Data quality report failed: {}", e))?;
+    Ok(quality)
+}
 
-    // Validate output directory exists
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            return Err("Output directory does not exist".to_string());
+/// Analyze the variants of the loaded genome
+///
+/// Runs as an `analysis` task that can be stopped with `cancel_task`. The
+/// findings are kept for `get_findings_page` and `search_findings`; only
+/// the summary and section counts are returned. They are also sent as
+/// they are found, in `analysis-findings` events followed by one
+/// `analysis-findings-complete` event. Without options, the allele
+/// frequency threshold and consent in the settings apply. When the window
+/// is not in front, a notification announces the results.
+#[tauri::command]
+pub async fn analyze_variants(
+    app: AppHandle,
+    options: Option<AnalysisOptions>,
+    state: State<'_, AppState>,
+) -> Result<AnalysisOverview, GenomeForgeError> {
+    let (genome, options) = prepare_analysis(&app, options, &state)?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+    run_analysis(&app, &state, &task, genome, options).await
+}
+
+/// Analyze the variants of the loaded genome in the background
+///
+/// Like `analyze_variants`, but returns the task id as soon as the
+/// analysis starts; its outcome arrives as an `analysis-complete` or
+/// `analysis-failed` event.
+#[tauri::command]
+pub fn start_analysis(
+    app: AppHandle,
+    options: Option<AnalysisOptions>,
+    state: State<'_, AppState>,
+) -> Result<TaskId, GenomeForgeError> {
+    let (genome, options) = prepare_analysis(&app, options, &state)?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+    let task_id = task.id();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        match run_analysis(&app, &state, &task, genome, options).await {
+            Ok(overview) => {
+                events::ANALYSIS_COMPLETE.emit(&app, AnalysisComplete { task_id, overview })
+            }
+            Err(error) => events::ANALYSIS_FAILED.emit(&app, AnalysisFailed { task_id, error }),
         }
+    });
+    Ok(task_id)
+}
+
+/// Acknowledge the `analysis-findings` batches of `task_id` up to
+/// `sequence`
+///
+/// A frontend that acknowledges the batches it has taken in is sent only a
+/// few ahead of it. False once the stream has ended.
+#[tauri::command]
+pub fn ack_findings(task_id: TaskId, sequence: u64, state: State<'_, AppState>) -> bool {
+    state.finding_streams.acknowledge(task_id, sequence)
+}
+
+/// Analyze several genome files and write a report for each
+///
+/// Each file is parsed, analyzed and exported to `output_dir` under its own
+/// name, `client-a.pdf` for `client-a.txt.gz`; encrypted reports other than
+/// PDFs end in `.gfenc`. Files run one at a time unless `concurrency`
+/// allows more, which share the cores and the memory budget. A
+/// `batch-progress` event follows each file through its stages, and a file
+/// that fails does not stop the rest. The batch is one `batch` task for
+/// `cancel_task`, and leaves the loaded genome and its results as they are.
+#[tauri::command]
+pub async fn batch_analyze(
+    app: AppHandle,
+    files: Vec<String>,
+    options: BatchOptions,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<BatchResult, GenomeForgeError> {
+    if files.is_empty() {
+        return Err(GenomeForgeError::invalid("No files to analyze"));
     }
+    let output_dir = PathBuf::from(&options.output_dir);
+    if !output_dir.is_dir() {
+        return Err(GenomeForgeError::FileNotFound(Some(options.output_dir)));
+    }
+    let passphrase = export_passphrase(options.export.encrypt, passphrase)?;
+    let mut analysis = analysis_options(&app, options.analysis)?;
+    let template = templates::find(
+        &templates::template_dir(&app)?,
+        options.export.template.as_deref(),
+    )?;
+    let concurrency = options
+        .concurrency
+        .unwrap_or(1)
+        .clamp(1, MAX_BATCH_CONCURRENCY.min(num_cpus()));
+    analysis
+        .threads
+        .get_or_insert((num_cpus() / concurrency).max(1));
 
-    // In a real implementation, this would export the actual report
-    Ok(format!(
-        "Report {} exported to {} in {} format",
-        report_id, output_path, options.format
-    ))
+    let files: Vec<PathBuf> = files.into_iter().map(PathBuf::from).collect();
+    let reports = report_paths(
+        &output_dir,
+        &files,
+        options.export.format,
+        passphrase.is_some(),
+    );
+    let task = start_task(&app, &state, TaskKind::Batch);
+    let task_id = task.id();
+    let job = BatchJob {
+        app: app.clone(),
+        task_id,
+        cancel: task.cancel_flag(),
+        databases: state.databases.snapshot(),
+        options: analysis,
+        export: options.export,
+        template,
+        passphrase,
+        budget: system::memory_budget(system::memory()) / concurrency as u64,
+        files,
+        reports,
+        completed: AtomicUsize::new(0),
+    };
+    let files = tokio::task::spawn_blocking(move || {
+        let shards: Vec<Range<usize>> = (0..job.files.len()).map(|i| i..i + 1).collect();
+        parallel::map(&shards, concurrency, |shard| Ok(job.run(shard.start)))
+    })
+    .await
+    .map_err(|e| format!("Batch task failed: {}", e))??;
+    let failed = files.iter().filter(|file| file.error.is_some()).count();
+    tracing::info!(files = files.len(), failed, "batch finished");
+    Ok(BatchResult {
+        task_id,
+        files,
+        failed,
+    })
 }
 
-/// Get database status
+/// Bring the latest result up to date with the ClinVar release installed
+/// since it was made, and report what that changed
+///
+/// Only the variants the records added, removed or reclassified in the new
+/// release can match are annotated again, against both releases, so this
+/// takes a fraction of a full analysis. `options` should be those the
+/// result was made with; by default they come from the settings, as for
+/// `analyze_variants`.
 #[tauri::command]
-pub fn get_database_status() -> DatabaseStatus {
-    // This would check actual database status
-    // For now, return placeholder data
-    DatabaseStatus {
-        clinvar: DatabaseInfo {
-            loaded: false,
-            record_count: 0,
-            last_updated: None,
-        },
-        pharmgkb: DatabaseInfo {
-            loaded: false,
-            record_count: 0,
-            last_updated: None,
-        },
-        gwas: DatabaseInfo {
-            loaded: false,
-            record_count: 0,
-            last_updated: None,
-        },
+pub async fn reanalyze_database_changes(
+    app: AppHandle,
+    options: Option<AnalysisOptions>,
+    state: State<'_, AppState>,
+) -> Result<FindingChanges, GenomeForgeError> {
+    let latest = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let delta = state.clinvar_changes.current().ok_or_else(|| {
+        GenomeForgeError::invalid("ClinVar has not been updated since the last analysis")
+    })?;
+    if delta.from_release() != latest.summary.clinvar_release.as_deref() {
+        return Err(GenomeForgeError::invalid(
+            "These results were not made with the ClinVar release the update replaced; run the analysis again",
+        ));
     }
+    let (genome, options) = prepare_analysis(&app, options, &state)?;
+    let databases = state.databases.snapshot();
+    let task = start_task(&app, &state, TaskKind::Analysis);
+    let cancel = task.cancel_flag();
+
+    let saving = app.clone();
+    let (result, changes) = tokio::task::spawn_blocking(move || {
+        // Positions in the delta are on the build the release is published
+        // on, so a GRCh37 genome is lifted before the variants are picked
+        let lifted;
+        let genome = match (&databases.liftover, liftover::detect_build(&genome)) {
+            (Some(chain), Some(build)) if build == chain.from_build() => {
+                (lifted, _) = chain.lift_genome(&genome, |_| tasks::checkpoint(&cancel))?;
+                &lifted
+            }
+            _ => &*genome,
+        };
+        let touched = delta.touched(genome);
+        let annotate = |clinvar: &Arc<ClinVarDatabase>| {
+            let databases = DatabaseSnapshot {
+                clinvar: Some(Arc::clone(clinvar)),
+                dbsnp: databases.dbsnp.clone(),
+                gnomad: databases.gnomad.clone(),
+                ..DatabaseSnapshot::default()
+            };
+            analyze_genome(&touched, &databases, &options, &cancel)
+        };
+        let before = annotate(delta.previous())?;
+        let after = annotate(delta.current())?;
+        let result = reanalysis::merge(&latest, &before, &after);
+        save_result(&saving, genome, &result);
+        Ok::<_, String>((result, reanalysis::compare(&before, &after)))
+    })
+    .await
+    .map_err(|e| format!("Analysis task failed: {}", e))??;
+
+    state.results.replace(result);
+    state.clinvar_changes.take();
+    audit::record(
+        &app,
+        AuditAction::Analysis,
+        "clinvar update",
+        [
+            ("release", changes.to_release.clone().unwrap_or_default()),
+            ("new", changes.new_findings.len().to_string()),
+            ("reclassified", changes.reclassified.len().to_string()),
+            ("removed", changes.removed_findings.len().to_string()),
+        ],
+    );
+    Ok(changes)
 }
 
-// Helper functions
+/// Compare the latest analysis of the loaded genome with the one before it
+///
+/// Results are saved per genome file, so the earlier analysis may be from
+/// another session. Reports the ClinVar findings that are new, reclassified
+/// or gone, and the drug responses and trait associations added or gone.
+#[tauri::command]
+pub async fn diff_analyses(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<AnalysisDiff, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let latest = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let sha256 = genome
+        .file
+        .fingerprint
+        .as_ref()
+        .map(|fingerprint| fingerprint.sha256.clone())
+        .ok_or_else(|| {
+            GenomeForgeError::Failed(
+                "The loaded genome was saved before files were fingerprinted".to_string(),
+            )
+        })?;
+    let diff = tokio::task::spawn_blocking(move || {
+        let key = sessions::session_dir(&app).and_then(|dir| sessions::device_key(&dir))?;
+        let previous = history::previous(&history::history_dir(&app)?, &key, &sha256)?;
+        Ok::<_, String>(previous.map(|(saved, previous)| history::diff(&saved, &previous, &latest)))
+    })
+    .await
+    .map_err(|e| format!("Diff task failed: {}", e))??;
+    diff.ok_or_else(|| GenomeForgeError::invalid("This genome has not been analyzed before"))
+}
 
-fn get_os_version() -> String {
-    #[cfg(windows)]
-    {
-        // Get Windows version using system command
-        std::process::Command::new("cmd")
-            .args(["/C", "ver"])
-            .output()
-            .ok()
-            .and_then(|o| String::from_utf8(o.stdout).ok())
-            .map(|s| s.trim().to_string())
-            .unwrap_or_else(|| "Windows".to_string())
-    }
+/// A finding of a saved analysis, with the analysis it is from
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedFinding {
+    pub run: Run,
+    pub section: FindingSection,
+    /// Position of the finding within its section
+    pub index: usize,
+    pub finding: serde_json::Value,
+}
 
-    #[cfg(not(windows))]
-    {
-        "Unknown".to_string()
-    }
+/// Analyses saved in the active profile, most recent first
+#[tauri::command]
+pub async fn list_saved_analyses(app: AppHandle) -> Result<Vec<Run>, GenomeForgeError> {
+    let runs = tokio::task::spawn_blocking(move || {
+        let db = saved_analyses(&app)?;
+        db.runs()
+    })
+    .await
+    .map_err(|e| format!("List task failed: {}", e))??;
+    Ok(runs)
 }
 
-fn get_total_memory() -> u64 {
-    // Return 0 for now - would use Windows APIs in production
-    0
+/// One page of the findings of saved analyses that match `query`
+///
+/// Searches every analysis saved in the active profile, by gene, section,
+/// category, significance, genome file and date, most recent first. Only
+/// the analyses with findings on the page are read.
+#[tauri::command]
+pub async fn query_saved_findings(
+    app: AppHandle,
+    query: ResultQuery,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<Page<SavedFinding>, GenomeForgeError> {
+    let page = tokio::task::spawn_blocking(move || {
+        let db = saved_analyses(&app)?;
+        let mut loaded: HashMap<u64, AnalysisResultData> = HashMap::new();
+        Page::new(db.query(&query)?, offset.unwrap_or(0), limit).try_map(|(run, row)| {
+            let section = FindingSection::from_name(&row.section)
+                .ok_or_else(|| format!("Unknown section: {}", row.section))?;
+            let result = match loaded.entry(run.id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(history::load(&*db, run.id)?),
+            };
+            Ok::<_, String>(SavedFinding {
+                run,
+                section,
+                index: row.index,
+                finding: results::finding_value(result, section, row.index)?,
+            })
+        })
+    })
+    .await
+    .map_err(|e| format!("Query task failed: {}", e))??;
+    Ok(page)
 }
 
-fn num_cpus() -> usize {
-    std::thread::available_parallelism()
-        .map(|p| p.get())
-        .unwrap_or(1)
+/// Make a saved analysis the latest result again, to browse and export
+/// without analyzing again
+///
+/// The loaded genome is left as it is, and need not be the one analyzed.
+#[tauri::command]
+pub async fn load_saved_analysis(
+    app: AppHandle,
+    run_id: u64,
+    state: State<'_, AppState>,
+) -> Result<AnalysisOverview, GenomeForgeError> {
+    let result =
+        tokio::task::spawn_blocking(move || history::load(&*saved_analyses(&app)?, run_id))
+            .await
+            .map_err(|e| format!("Load task failed: {}", e))??;
+    let result = state.results.replace(result);
+    // Changes since the release the result was made with are not known
+    state.clinvar_changes.take();
+    Ok(AnalysisOverview::new(&result))
 }
 
-fn detect_file_type(path: &PathBuf) -> Result<String, String> {
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    match extension.as_str() {
-        "vcf" => Ok("vcf".to_string()),
-        "txt" => {
-            // Could be 23andMe or AncestryDNA
-            // In real implementation, would check file contents
-            Ok("23andme".to_string())
-        }
-        "gz" => {
-            // Check if it's .vcf.gz or .txt.gz
-            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-            if stem.ends_with(".vcf") {
-                Ok("vcf".to_string())
-            } else {
-                Ok("23andme".to_string())
-            }
-        }
-        _ => Err(format!("Unsupported file type: {}", extension)),
-    }
+/// Compare two saved analyses, such as those of one genome before and
+/// after a database update, as `diff_analyses` does
+#[tauri::command]
+pub async fn diff_saved_analyses(
+    app: AppHandle,
+    before: u64,
+    after: u64,
+) -> Result<AnalysisDiff, GenomeForgeError> {
+    let diff = tokio::task::spawn_blocking(move || {
+        let db = saved_analyses(&app)?;
+        let earlier = db
+            .run(before)?
+            .ok_or_else(|| format!("Unknown analysis run: {}", before))?;
+        let saved = SavedResult::from(&earlier);
+        let (before, after) = (history::load(&*db, before)?, history::load(&*db, after)?);
+        Ok::<_, String>(history::diff(&saved, &before, &after))
+    })
+    .await
+    .map_err(|e| format!("Diff task failed: {}", e))??;
+    Ok(diff)
+}
+
+/// Delete a saved analysis
+#[tauri::command]
+pub async fn delete_saved_analysis(app: AppHandle, run_id: u64) -> Result<(), GenomeForgeError> {
+    tokio::task::spawn_blocking(move || {
+        let key = sessions::session_dir(&app).and_then(|dir| sessions::device_key(&dir))?;
+        history::delete(&history::history_dir(&app)?, &key, run_id)
+    })
+    .await
+    .map_err(|e| format!("Delete task failed: {}", e))??;
+    Ok(())
+}
+
+/// Star or unstar a finding of the latest analysis
+///
+/// Returns the finding's entry in the profile's notebook, or nothing once
+/// it has neither a star nor a note.
+#[tauri::command]
+pub fn bookmark_finding(
+    app: AppHandle,
+    section: FindingSection,
+    index: usize,
+    bookmarked: bool,
+    state: State<'_, AppState>,
+) -> Result<Option<FindingNote>, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let (finding, label) = results::finding_ref(&result, section, index)?;
+    let entry = notes::update(&app, |book| {
+        Ok(book
+            .bookmark(finding, &label, bookmarked, export::now())
+            .cloned())
+    })?;
+    Ok(entry)
+}
+
+/// Write a note on a finding of the latest analysis in place of any it has;
+/// an empty note removes it
+#[tauri::command]
+pub fn set_finding_note(
+    app: AppHandle,
+    section: FindingSection,
+    index: usize,
+    note: String,
+    state: State<'_, AppState>,
+) -> Result<Option<FindingNote>, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let (finding, label) = results::finding_ref(&result, section, index)?;
+    let entry = notes::update(&app, |book| {
+        Ok(book
+            .set_note(finding, &label, &note, export::now())?
+            .cloned())
+    })?;
+    Ok(entry)
+}
+
+/// The profile's bookmarks and notes, bookmarked findings first, each
+/// with its finding in the latest analysis when that reported it
+#[tauri::command]
+pub fn list_finding_notes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<NotedFinding>, GenomeForgeError> {
+    let book = notes::read(&app)?;
+    let result = state.results.current();
+    Ok(results::noted_findings(result.as_deref(), &book)?)
+}
+
+/// The active profile's watchlist
+#[tauri::command]
+pub fn list_watchlist(app: AppHandle) -> Result<Vec<WatchEntry>, GenomeForgeError> {
+    Ok(watchlist::read(&app)?.entries)
+}
+
+/// Watch an rsid, gene or region, or relabel it if already watched;
+/// returns the watchlist
+#[tauri::command]
+pub fn add_to_watchlist(
+    app: AppHandle,
+    target: String,
+    label: Option<String>,
+) -> Result<Vec<WatchEntry>, GenomeForgeError> {
+    let entry = WatchEntry::new(&target, label.as_deref().unwrap_or_default())
+        .map_err(GenomeForgeError::invalid)?;
+    let list = watchlist::update(&app, |list| {
+        list.add(entry)?;
+        Ok(list.entries.clone())
+    })?;
+    Ok(list)
+}
+
+/// Stop watching a target; returns the watchlist
+#[tauri::command]
+pub fn remove_from_watchlist(
+    app: AppHandle,
+    target: String,
+) -> Result<Vec<WatchEntry>, GenomeForgeError> {
+    let list = watchlist::update(&app, |list| {
+        if !list.remove(&target) {
+            return Err(format!("{} is not on the watchlist", target.trim()));
+        }
+        Ok(list.entries.clone())
+    })?;
+    Ok(list)
+}
+
+/// The calls at the watchlist's entries in the loaded genome, without
+/// analyzing it
+#[tauri::command]
+pub fn check_watchlist(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<WatchResult>, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let list = watchlist::read(&app)?;
+    Ok(list.check(&genome, liftover::detect_build(&genome)))
+}
+
+/// One page of a section of the latest analysis, filtered and sorted
+#[tauri::command]
+pub fn get_findings_page(
+    section: FindingSection,
+    filter: Option<FindingFilter>,
+    sort: Option<FindingSort>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Page<serde_json::Value>, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    Ok(results::findings_page(
+        &result,
+        section,
+        &filter.unwrap_or_default(),
+        sort.unwrap_or_default(),
+        offset.unwrap_or(0),
+        limit,
+    )?)
+}
+
+/// Conditions of the latest analysis's findings, grouped by body system
+///
+/// With a query, only conditions it matches are returned; conditions are
+/// also found by their synonyms and by the names of their body system.
+#[tauri::command]
+pub fn browse_conditions(
+    query: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ConditionGroup>, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let query = query.map(|query| Query::new(&query));
+    Ok(results::condition_groups(&result, query.as_ref()))
+}
+
+/// Search the latest analysis for a gene symbol, rsid, condition or drug
+///
+/// Results are ranked best first and returned a page at a time.
+#[tauri::command]
+pub fn search_findings(
+    query: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Page<SearchResult>, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let page = Page::new(
+        results::search(&result, &Query::new(&query)),
+        offset.unwrap_or(0),
+        limit,
+    );
+    Ok(page.try_map(|hit| results::to_result(&result, hit))?)
+}
+
+/// Findings of the latest analysis meeting a filter expression, such as
+/// `zygosity == "homozygous" and gene in ["BRCA1", "BRCA2"] and af < 0.01`
+#[tauri::command]
+pub fn filter_findings(
+    expression: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<RuleResults, GenomeForgeError> {
+    let expr = Expr::parse(&expression).map_err(GenomeForgeError::invalid)?;
+    let rule = CompiledRule {
+        name: expression,
+        expr,
+    };
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    Ok(results::apply_rules(
+        &result,
+        &[rule],
+        offset.unwrap_or(0),
+        limit,
+    )?)
+}
+
+/// The user's saved filter rule sets
+#[tauri::command]
+pub fn list_rule_sets(app: AppHandle) -> Result<Vec<RuleSet>, GenomeForgeError> {
+    Ok(rules::load_dir(&rule_sets::rule_dir(&app)?)?)
+}
+
+/// Save a rule set, replacing any with the same id
+#[tauri::command]
+pub fn save_rule_set(app: AppHandle, rule_set: RuleSet) -> Result<(), GenomeForgeError> {
+    rule_set.compile().map_err(GenomeForgeError::invalid)?;
+    rules::save(&rule_sets::rule_dir(&app)?, &rule_set)?;
+    Ok(())
+}
+
+/// Delete a saved rule set
+#[tauri::command]
+pub fn delete_rule_set(app: AppHandle, id: String) -> Result<(), GenomeForgeError> {
+    Ok(rules::delete(&rule_sets::rule_dir(&app)?, &id)?)
+}
+
+/// Findings of the latest analysis meeting any rule of a saved set, with
+/// the rules each meets
+#[tauri::command]
+pub fn apply_rule_set(
+    app: AppHandle,
+    id: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<RuleResults, GenomeForgeError> {
+    let rules = rule_sets::find(&rule_sets::rule_dir(&app)?, &id)?.compile()?;
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    Ok(results::apply_rules(
+        &result,
+        &rules,
+        offset.unwrap_or(0),
+        limit,
+    )?)
+}
+
+/// The CPIC recommendation for a drug at the phenotype of the gene's
+/// diplotype in the latest analysis
+///
+/// Recommendations of the installed CPIC release are preferred; the
+/// bundled guidelines are used for drugs it has none for, or when no
+/// release is installed.
+#[tauri::command]
+pub fn get_drug_guideline(
+    gene: String,
+    drug: String,
+    state: State<'_, AppState>,
+) -> Result<DrugGuideline, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let call = result
+        .diplotypes
+        .iter()
+        .find(|call| call.gene.eq_ignore_ascii_case(gene.trim()))
+        .ok_or_else(|| {
+            GenomeForgeError::invalid(format!("No {} diplotype in the latest results", gene))
+        })?;
+    let drug = drug.trim();
+    let installed = state.databases.cpic.current().and_then(|cpic| {
+        cpic.recommendations(&call.gene, &call.phenotype)
+            .into_iter()
+            .find(|recommendation| recommendation.drug.eq_ignore_ascii_case(drug))
+            .cloned()
+    });
+    let bundled = installed.is_none();
+    let recommendation = installed
+        .or_else(|| {
+            guidelines::recommendations(&call.gene, &call.phenotype)
+                .into_iter()
+                .find(|recommendation| recommendation.drug.eq_ignore_ascii_case(drug))
+        })
+        .ok_or_else(|| {
+            GenomeForgeError::invalid(format!(
+                "No CPIC recommendation for {} and a {} {}",
+                drug, call.gene, call.phenotype
+            ))
+        })?;
+
+    Ok(DrugGuideline {
+        gene: call.gene.clone(),
+        drug: recommendation.drug.clone(),
+        diplotype: call.diplotype.clone(),
+        phenotype: call.phenotype.clone(),
+        recommendation,
+        bundled,
+    })
+}
+
+/// Check a medication list against every pharmacogenomic finding of the
+/// latest analysis
+///
+/// Medications may be entered by brand name and with a dose. The list is
+/// returned most urgent first; nothing entered is stored.
+#[tauri::command]
+pub fn review_medications(
+    medications: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MedicationReview>, GenomeForgeError> {
+    if medications
+        .iter()
+        .all(|medication| medication.trim().is_empty())
+    {
+        return Err(GenomeForgeError::invalid("No medications entered"));
+    }
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    Ok(medications::review(&result, &medications))
+}
+
+/// Everything known about a finding of the latest analysis, for its
+/// detail pane
+///
+/// `rsid` is the finding's `rsid`, which is "chromosome:position" for a
+/// site without one. Only findings the analysis reported can be looked up,
+/// so nothing the consent settings withheld is shown here.
+#[tauri::command]
+pub fn get_finding_details(
+    rsid: String,
+    state: State<'_, AppState>,
+) -> Result<FindingDetails, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let finding = results::variant_findings(&result)
+        .find(|finding| finding.rsid == rsid)
+        .cloned()
+        .ok_or_else(|| {
+            GenomeForgeError::invalid(format!("No finding for {} in the latest results", rsid))
+        })?;
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let databases = state.databases.snapshot();
+
+    let call = genome
+        .get_by_rsid(&rsid)
+        .or_else(|| genome.get_at(finding.chromosome.as_deref()?, finding.position?))
+        .cloned();
+    let records = match &databases.clinvar {
+        Some(clinvar) if rsid.starts_with("rs") => clinvar.lookup_rsid(&rsid),
+        Some(clinvar) => clinvar
+            .records()
+            .iter()
+            .filter(|record| {
+                record.variation_id.is_some() && record.variation_id == finding.variation_id
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let gnomad = databases.gnomad.as_deref();
+    let clinvar = records
+        .into_iter()
+        .map(|record| ClinVarEvidence {
+            review_stars: record.review_status.stars(),
+            allele_copies: call.as_ref().and_then(|variant| {
+                annotation::alternate_copies(variant, &record.reference, &record.alternate)
+            }),
+            allele_frequency: gnomad.and_then(|gnomad| record_frequency(gnomad, record)),
+            record: record.clone(),
+        })
+        .collect();
+    let mut pubmed_ids: Vec<String> = databases
+        .gwas
+        .iter()
+        .flat_map(|gwas| gwas.lookup_rsid(&rsid))
+        .filter_map(|association| association.pubmed_id.clone())
+        .collect();
+    pubmed_ids.sort();
+    pubmed_ids.dedup();
+
+    Ok(FindingDetails {
+        finding,
+        call,
+        clinvar,
+        pubmed_ids,
+    })
+}
+
+/// How the engine reached one finding of the latest analysis: the records
+/// it matched, allele and strand decisions and the filters it passed
+///
+/// `index` is the finding's position in its section, as the results list
+/// it.
+#[tauri::command]
+pub fn explain_finding(
+    section: FindingSection,
+    index: usize,
+    state: State<'_, AppState>,
+) -> Result<FindingTrace, GenomeForgeError> {
+    let result = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let databases = state.databases.snapshot();
+    Ok(trace::explain(
+        &result,
+        section,
+        index,
+        databases.clinvar.as_deref(),
+    )?)
+}
+
+/// Save the loaded genome and latest analysis as a named session
+///
+/// Without a passphrase the session is encrypted with this device's key.
+/// A session of the same name is replaced.
+#[tauri::command]
+pub async fn save_session(
+    app: AppHandle,
+    name: String,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SessionEntry, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let results = state.results.current();
+    let dir = sessions::session_dir(&app)?;
+    let path = sessions::session_path(&dir, &name)?;
+    let passphrase = non_empty(passphrase)?;
+
+    Ok(tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let device_key;
+        let key = match &passphrase {
+            Some(passphrase) => KeySource::Passphrase(passphrase),
+            None => {
+                device_key = sessions::device_key(&dir)?;
+                KeySource::Device(&device_key)
+            }
+        };
+        session::write(&path, name.trim(), &genome, results.as_deref(), key)
+    })
+    .await
+    .map_err(|e| format!("Session task failed: {}", e))??)
+}
+
+/// Restore a saved session in place of the loaded genome and results
+#[tauri::command]
+pub async fn load_session(
+    app: AppHandle,
+    name: String,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<LoadedSession, GenomeForgeError> {
+    let dir = sessions::session_dir(&app)?;
+    let path = sessions::session_path(&dir, &name)?;
+    if !path.exists() {
+        return Err(GenomeForgeError::FileNotFound(Some(
+            path.display().to_string(),
+        )));
+    }
+    let passphrase = non_empty(passphrase)?;
+
+    let restored = tokio::task::spawn_blocking(move || {
+        let device_key;
+        let key = match &passphrase {
+            Some(passphrase) => KeySource::Passphrase(passphrase),
+            None => {
+                device_key = sessions::device_key(&dir)?;
+                KeySource::Device(&device_key)
+            }
+        };
+        session::read::<AnalysisResultData>(&path, key)
+    })
+    .await
+    .map_err(|e| format!("Session task failed: {}", e))??;
+
+    state.tasks.cancel_kind(TaskKind::Parse);
+    let genome = state.genome.replace(restored.genome);
+    let mut details = loaded_details(&genome);
+    details.push(("source", "session".to_string()));
+    audit::record(&app, AuditAction::FileLoaded, name.trim(), details);
+    let analysis = match restored.results {
+        Some(result) => Some(AnalysisOverview::new(&state.results.replace(result))),
+        None => {
+            state.results.clear();
+            None
+        }
+    };
+    Ok(LoadedSession {
+        parse: ParseResult::new(&genome),
+        analysis,
+    })
+}
+
+/// Saved sessions, most recent first
+#[tauri::command]
+pub fn list_sessions(app: AppHandle) -> Result<Vec<SessionEntry>, GenomeForgeError> {
+    Ok(session::list(&sessions::session_dir(&app)?)?)
+}
+
+/// Every profile, marking the active one
+#[tauri::command]
+pub fn list_profiles(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<ProfileEntry>, GenomeForgeError> {
+    let active = profiles::active_id(&app)?;
+    let mut entries = profiles::list(&app, &state.profiles)?;
+    for entry in entries
+        .iter_mut()
+        .filter(|entry| entry.profile.id == active)
+    {
+        entry.genome_loaded = state.genome.is_loaded();
+    }
+    Ok(entries)
+}
+
+/// Create a profile without switching to it
+#[tauri::command]
+pub fn create_profile(app: AppHandle, name: String) -> Result<Profile, GenomeForgeError> {
+    Ok(profiles::create(&app, &name)?)
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    app: AppHandle,
+    id: String,
+    name: String,
+) -> Result<Profile, GenomeForgeError> {
+    Ok(profiles::rename(&app, &id, &name)?)
+}
+
+/// Delete a profile that is not active, with all its data
+#[tauri::command]
+pub async fn delete_profile(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<(), GenomeForgeError> {
+    let deleting = id.clone();
+    tokio::task::spawn_blocking(move || profiles::delete(&app, &deleting))
+        .await
+        .map_err(|e| format!("Delete task failed: {}", e))??;
+    state.profiles.unpark(&id);
+    Ok(())
+}
+
+/// Make another profile active
+///
+/// The genome and results of the profile left stay in memory, and those
+/// the other profile had when it was left are restored; parses and
+/// analyses in progress are cancelled.
+#[tauri::command]
+pub fn switch_profile(
+    app: AppHandle,
+    id: String,
+    state: State<'_, AppState>,
+) -> Result<ProfileSwitch, GenomeForgeError> {
+    let current = profiles::active_id(&app)?;
+    let profile = profiles::set_active(&app, &id)?;
+    if id != current {
+        state.tasks.cancel_kind(TaskKind::Parse);
+        state.tasks.cancel_kind(TaskKind::Analysis);
+        let left = Parked {
+            genome: state.genome.take(),
+            results: state.results.take(),
+        };
+        state.profiles.park(&current, left);
+        let restored = state.profiles.unpark(&id);
+        if let Some(genome) = restored.genome {
+            state.genome.put(genome);
+        }
+        if let Some(results) = restored.results {
+            state.results.put(results);
+        }
+    }
+    Ok(ProfileSwitch {
+        profile,
+        parse: state
+            .genome
+            .current()
+            .map(|genome| ParseResult::new(&genome)),
+        analysis: state
+            .results
+            .current()
+            .map(|result| AnalysisOverview::new(&result)),
+    })
+}
+
+/// Compare a child's genome with both parents'
+///
+/// Each argument is the id of a profile with a genome loaded, active or
+/// not. Flags genotypes the child could not have inherited, recessive genes
+/// in which the child has pathogenic variants from each parent, and
+/// recessive conditions both parents carry.
+#[tauri::command]
+pub async fn analyze_trio(
+    app: AppHandle,
+    child: String,
+    mother: String,
+    father: String,
+    state: State<'_, AppState>,
+) -> Result<TrioReport, GenomeForgeError> {
+    let profiles = [
+        ("child", child.clone()),
+        ("mother", mother.clone()),
+        ("father", father.clone()),
+    ];
+    if child == mother || child == father || mother == father {
+        return Err(GenomeForgeError::invalid("Choose three different profiles"));
+    }
+    consent_to(&app, FindingCategory::Paternity)?;
+    let child = profile_genome(&app, &state, &child)?;
+    let mother = profile_genome(&app, &state, &mother)?;
+    let father = profile_genome(&app, &state, &father)?;
+    same_build(&[&child, &mother, &father])?;
+    let databases = state.databases.snapshot();
+    let task = start_task(&app, &state, TaskKind::Analysis);
+
+    let cancel = task.cancel_flag();
+    let report = tokio::task::spawn_blocking(move || {
+        let mendelian =
+            trio::mendelian_check(&child, &mother, &father, |_| tasks::checkpoint(&cancel))?;
+        let mut compound_heterozygous = Vec::new();
+        let mut carrier_couples = Vec::new();
+        if let Some(clinvar) = &databases.clinvar {
+            let threads = num_cpus();
+            let checkpoint = |_| tasks::checkpoint(&cancel);
+            let child_matches = clinvar.annotate_parallel(&child, threads, checkpoint)?;
+            let counts = zygosity::pathogenic_counts(&child_matches);
+            let to_findings = |matches: &[ClinVarMatch<'_>]| {
+                matches
+                    .iter()
+                    .map(|found| {
+                        ClinicalFinding::from_match(
+                            found,
+                            zygosity::interpret(found, &counts),
+                            None,
+                            clinvar.release_date(),
+                        )
+                    })
+                    .collect()
+            };
+            compound_heterozygous = trio::compound_heterozygous(&child_matches, &mother, &father)
+                .iter()
+                .map(|candidate| CompoundHetFinding {
+                    gene: candidate.gene.clone(),
+                    in_trans: candidate.in_trans(),
+                    maternal: to_findings(&candidate.maternal),
+                    paternal: to_findings(&candidate.paternal),
+                    unresolved: to_findings(&candidate.unresolved),
+                })
+                .collect();
+            carrier_couples = trio::carrier_couples(
+                &carrier::screen(&clinvar.annotate_parallel(&mother, threads, checkpoint)?),
+                &carrier::screen(&clinvar.annotate_parallel(&father, threads, checkpoint)?),
+            );
+        }
+        Ok::<_, String>(TrioReport {
+            mendelian,
+            compound_heterozygous,
+            carrier_couples,
+            residual_risk: carrier::RESIDUAL_RISK.to_string(),
+        })
+    })
+    .await
+    .map_err(|e| format!("Trio analysis failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Analysis,
+        "trio",
+        profile_details(&profiles),
+    );
+    Ok(report)
+}
+
+/// Merge several genome files of one person and load the result
+///
+/// The merged genome replaces the loaded one, like parsing a file does.
+#[tauri::command]
+pub async fn merge_genomes(
+    app: AppHandle,
+    file_paths: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<MergeResult, GenomeForgeError> {
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    if let Some(missing) = paths.iter().find(|path| !path.exists()) {
+        return Err(GenomeForgeError::FileNotFound(Some(
+            missing.display().to_string(),
+        )));
+    }
+
+    state.tasks.cancel_kind(TaskKind::Parse);
+    let task = start_task(&app, &state, TaskKind::Parse);
+
+    let (task_id, cancel) = (task.id(), task.cancel_flag());
+    let merged = tokio::task::spawn_blocking(move || {
+        let cache = sessions::session_dir(&app)
+            .and_then(|dir| sessions::device_key(&dir))
+            .and_then(|key| genome_cache(&app, key))
+            .ok();
+        let genomes = paths
+            .iter()
+            .map(|path| {
+                load_genome(&app, task_id, path, None, None, cache.as_ref(), &cancel)
+                    .map(|(genome, _)| genome)
+            })
+            .collect::<Result<Vec<LoadedGenome>, String>>()?;
+        let genomes: Vec<&LoadedGenome> = genomes.iter().collect();
+        let merged = merge::merge(&genomes, |_| tasks::checkpoint(&cancel))?;
+        let names: Vec<String> = paths.iter().map(|path| audit::file_name(path)).collect();
+        audit::record(
+            &app,
+            AuditAction::FileLoaded,
+            &names.join(", "),
+            loaded_details(&merged.genome),
+        );
+        Ok::<_, String>(merged)
+    })
+    .await
+    .map_err(|e| format!("Merge task failed: {}", e))??;
+
+    let genome = state.genome.replace(merged.genome);
+    state.results.clear();
+    Ok(MergeResult {
+        parse: ParseResult::new(&genome),
+        sources: merged.sources,
+        stats: merged.stats,
+        conflicts: merged.conflicts,
+    })
+}
+
+/// Compare the loaded genome with another file of the same person
+///
+/// The other file is parsed for the comparison only and does not replace
+/// the loaded genome.
+#[tauri::command]
+pub async fn compare_genomes(
+    app: AppHandle,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<GenomeComparison, GenomeForgeError> {
+    let path = PathBuf::from(&file_path);
+    if !path.exists() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+
+    let (task_id, cancel) = (task.id(), task.cancel_flag());
+    Ok(tokio::task::spawn_blocking(move || {
+        let cache = sessions::session_dir(&app)
+            .and_then(|dir| sessions::device_key(&dir))
+            .and_then(|key| genome_cache(&app, key))
+            .ok();
+        let (other, _) = load_genome(&app, task_id, &path, None, None, cache.as_ref(), &cancel)?;
+        let comparison = compare::compare(&genome, &other, |_| tasks::checkpoint(&cancel))?;
+        audit::record(
+            &app,
+            AuditAction::Analysis,
+            "comparison",
+            [("file", audit::file_name(&path))],
+        );
+        Ok::<_, String>(comparison)
+    })
+    .await
+    .map_err(|e| format!("Comparison task failed: {}", e))??)
+}
+
+/// Estimate how closely the people two profiles belong to are related
+///
+/// Each argument is the id of a profile with a genome loaded, active or
+/// not.
+#[tauri::command]
+pub async fn estimate_kinship(
+    app: AppHandle,
+    first: String,
+    second: String,
+    state: State<'_, AppState>,
+) -> Result<Kinship, GenomeForgeError> {
+    let profiles = [("first", first.clone()), ("second", second.clone())];
+    if first == second {
+        return Err(GenomeForgeError::invalid("Choose two different profiles"));
+    }
+    consent_to(&app, FindingCategory::Paternity)?;
+    let first = profile_genome(&app, &state, &first)?;
+    let second = profile_genome(&app, &state, &second)?;
+    same_build(&[&first, &second])?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+
+    let cancel = task.cancel_flag();
+    let kinship = tokio::task::spawn_blocking(move || {
+        kinship::estimate(&first, &second, |_| tasks::checkpoint(&cancel))
+    })
+    .await
+    .map_err(|e| format!("Kinship task failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Analysis,
+        "kinship",
+        profile_details(&profiles),
+    );
+    Ok(kinship)
+}
+
+/// Compute a polygenic risk score from a PGS Catalog scoring file
+#[tauri::command]
+pub async fn compute_prs(
+    app: AppHandle,
+    scoring_file_path: String,
+    options: Option<PrsOptions>,
+    state: State<'_, AppState>,
+) -> Result<PrsResult, GenomeForgeError> {
+    let options = options.unwrap_or_default();
+    let path = PathBuf::from(&scoring_file_path);
+    if !path.exists() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+
+    let cancel = task.cancel_flag();
+    let file = audit::file_name(&path);
+    let score = tokio::task::spawn_blocking(move || {
+        let scoring = ScoringFile::load(&path)?;
+        scoring.compute(&genome, options.missing, options.reference.as_ref(), |_| {
+            tasks::checkpoint(&cancel)
+        })
+    })
+    .await
+    .map_err(|e| format!("Score task failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Analysis,
+        "polygenic score",
+        [("file", file)],
+    );
+    Ok(score)
+}
+
+/// Find the genome's variants in a region and annotate them
+///
+/// `query` is a range such as "17:43044295-43125483", a whole chromosome,
+/// or a gene symbol such as "BRCA1".
+#[tauri::command]
+pub async fn query_region(
+    query: String,
+    options: Option<RegionQueryOptions>,
+    state: State<'_, AppState>,
+) -> Result<RegionQueryResult, GenomeForgeError> {
+    let options = options.unwrap_or_default();
+    let genome = match &options.file_path {
+        Some(path) if !Path::new(path).exists() => {
+            return Err(GenomeForgeError::FileNotFound(None))
+        }
+        Some(_) => None,
+        None => Some(state.genome.current().ok_or(GenomeForgeError::NoGenome)?),
+    };
+    let databases = state.databases.snapshot();
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let (build, gene, region, variants) = match (&options.file_path, &genome) {
+            (Some(path), _) => {
+                let vcf = IndexedVcf::open(Path::new(path))?;
+                let build = vcf.genome_build();
+                let (gene, region) = resolve_region(&query, build)?;
+                let variants = vcf.load_region(&region)?;
+                (build, gene, region, variants)
+            }
+            (None, Some(genome)) => {
+                let build = liftover::detect_build(genome);
+                let (gene, region) = resolve_region(&query, build)?;
+                // Let ClinVar match by allele when the build was inferred
+                let mut variants = genome.region(&region);
+                variants.file.genome_build = build;
+                (build, gene, region, variants)
+            }
+            (None, None) => return Err("No genome loaded".to_string()),
+        };
+        annotate_region(
+            &variants,
+            gene,
+            region,
+            build,
+            &databases,
+            options.report_late_onset,
+            &options.consent,
+        )
+    })
+    .await
+    .map_err(|e| format!("Region query failed: {}", e))??)
+}
+
+/// Estimate ancestry proportions against a reference allele-frequency panel
+#[tauri::command]
+pub async fn estimate_ancestry(
+    app: AppHandle,
+    panel_path: String,
+    options: Option<AncestryOptions>,
+    state: State<'_, AppState>,
+) -> Result<AncestryEstimate, GenomeForgeError> {
+    let options = options.unwrap_or_default();
+    let path = PathBuf::from(&panel_path);
+    if !path.exists() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let task = start_task(&app, &state, TaskKind::Analysis);
+
+    let cancel = task.cancel_flag();
+    let file = audit::file_name(&path);
+    let estimate = tokio::task::spawn_blocking(move || {
+        let panel = ReferencePanel::load(&path)?;
+        let replicates = options
+            .bootstrap_replicates
+            .unwrap_or(admixture::DEFAULT_REPLICATES);
+        panel.estimate(&genome, replicates, |_| tasks::checkpoint(&cancel))
+    })
+    .await
+    .map_err(|e| format!("Ancestry task failed: {}", e))??;
+    audit::record(&app, AuditAction::Analysis, "ancestry", [("file", file)]);
+    Ok(estimate)
+}
+
+/// Predict ABO and RhD blood type, and the Kell, Kidd and Duffy antigens
+/// the genome has markers for
+///
+/// Each call carries a confidence, and `missing` lists what the raw data
+/// lacks for the calls left out.
+#[tauri::command]
+pub async fn predict_blood_type(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<BloodTypePrediction, GenomeForgeError> {
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    let prediction = tokio::task::spawn_blocking(move || blood_type::predict(&genome))
+        .await
+        .map_err(|e| format!("Blood type task failed: {}", e))?;
+    audit::record(&app, AuditAction::Analysis, "blood type", Vec::new());
+    Ok(prediction)
+}
+
+/// Cancel a running background task
+///
+/// Returns whether the task was running. The task stops at its next
+/// checkpoint and its command fails with a `cancelled` error.
+#[tauri::command]
+pub fn cancel_task(task_id: TaskId, state: State<'_, AppState>) -> bool {
+    state.tasks.cancel(task_id)
+}
+
+/// List running background tasks
+#[tauri::command]
+pub fn list_tasks(state: State<'_, AppState>) -> Vec<TaskInfo> {
+    state.tasks.list()
+}
+
+/// Export the stored analysis results as a report
+///
+/// With `encrypt` set, JSON is sealed with AES-256-GCM and PDFs are
+/// password protected, under `passphrase`, which is taken apart from the
+/// options so it never ends up in their debug output.
+#[tauri::command]
+pub async fn export_report(
+    app: AppHandle,
+    report_id: String,
+    output_path: String,
+    options: ExportOptions,
+    passphrase: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, GenomeForgeError> {
+    let path = PathBuf::from(&output_path);
+
+    // Validate output directory exists
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            return Err(GenomeForgeError::FileNotFound(Some(
+                parent.display().to_string(),
+            )));
+        }
+    }
+
+    let passphrase = export_passphrase(options.encrypt, passphrase)?;
+    let mut results = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    if options.include_notes {
+        let noted = results::noted_findings(Some(&results), &notes::read(&app)?)?;
+        let mut annotated = (*results).clone();
+        annotated.notes = noted
+            .into_iter()
+            .filter(|noted| noted.index.is_some())
+            .map(|noted| noted.note)
+            .collect();
+        results = Arc::new(annotated);
+    }
+    let genome = if options.include_raw_data || options.format == ExportFormat::Vcf {
+        Some(state.genome.current().ok_or(GenomeForgeError::NoGenome)?)
+    } else {
+        None
+    };
+
+    let template = templates::find(&templates::template_dir(&app)?, options.template.as_deref())?;
+
+    // Array calls need their alleles from dbSNP to be written as VCF
+    let dbsnp = match options.format {
+        ExportFormat::Vcf => state.databases.snapshot().dbsnp,
+        _ => None,
+    };
+
+    let info = ExportInfo {
+        report_id,
+        format: options.format,
+        exported_at: export::now(),
+        locale: report_locale(options.locale.as_deref()),
+    };
+    let findings_only = options.findings_only;
+    tokio::task::spawn_blocking(move || {
+        let resolved = match (&dbsnp, &genome) {
+            (Some(dbsnp), Some(genome)) => Some(dbsnp.normalize(genome, |_| Ok(()))?.0),
+            _ => None,
+        };
+        let variants = resolved
+            .as_ref()
+            .or(genome.as_deref())
+            .map(LoadedGenome::variants);
+        let passphrase = passphrase.as_deref().map(String::as_str);
+        export::export(
+            &path,
+            &info,
+            &results,
+            &template,
+            variants,
+            findings_only,
+            passphrase,
+        )
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Export,
+        &audit::file_name(Path::new(&output_path)),
+        export_details(&options),
+    );
+
+    Ok(format!(
+        "Report exported to {}{}",
+        output_path,
+        if options.encrypt { " (encrypted)" } else { "" }
+    ))
+}
+
+/// Decrypt an encrypted export so it can be opened elsewhere
+#[tauri::command]
+pub async fn decrypt_export(
+    app: AppHandle,
+    input_path: String,
+    output_path: String,
+    passphrase: String,
+) -> Result<ExportInfo, GenomeForgeError> {
+    let input = PathBuf::from(&input_path);
+    if !input.exists() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let passphrase = Zeroizing::new(passphrase);
+
+    let file = audit::file_name(Path::new(&output_path));
+    let info = tokio::task::spawn_blocking(move || {
+        export::decrypt(&input, Path::new(&output_path), &passphrase)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Export,
+        &file,
+        [("decrypted", "true".to_string())],
+    );
+    Ok(info)
+}
+
+/// Report templates to choose from
+#[tauri::command]
+pub fn list_report_templates(app: AppHandle) -> Result<Vec<TemplateEntry>, GenomeForgeError> {
+    Ok(templates::list(&templates::template_dir(&app)?)?)
+}
+
+/// The current results laid out by a template, as an HTML page in the
+/// language of `locale`
+#[tauri::command]
+pub fn preview_report_template(
+    app: AppHandle,
+    template_id: String,
+    locale: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, GenomeForgeError> {
+    let template = templates::find(&templates::template_dir(&app)?, Some(&template_id))?;
+    let results = state.results.current().ok_or(GenomeForgeError::NoResults)?;
+    let info = ExportInfo {
+        report_id: "preview".to_string(),
+        format: ExportFormat::Html,
+        exported_at: export::now(),
+        locale: report_locale(locale.as_deref()),
+    };
+    Ok(html::render(&report::build(&info, &results, &template)))
+}
+
+/// Use a template for exports that name none
+#[tauri::command]
+pub fn select_report_template(app: AppHandle, template_id: String) -> Result<(), GenomeForgeError> {
+    Ok(templates::select(
+        &templates::template_dir(&app)?,
+        &template_id,
+    )?)
+}
+
+/// Save a user template, replacing any with the same id
+#[tauri::command]
+pub fn save_report_template(
+    app: AppHandle,
+    template: ReportTemplate,
+) -> Result<(), GenomeForgeError> {
+    Ok(templates::save(&templates::template_dir(&app)?, &template)?)
+}
+
+/// Analysis plugins installed in the app's data directory
+#[tauri::command]
+pub fn list_plugins(app: AppHandle) -> Result<Vec<PluginEntry>, GenomeForgeError> {
+    Ok(plugins::list(&app)?)
+}
+
+/// Run an installed plugin with later analyses
+#[tauri::command]
+pub fn enable_plugin(app: AppHandle, id: String) -> Result<(), GenomeForgeError> {
+    Ok(plugins::set_enabled(&app, &id, true)?)
+}
+
+/// Stop running a plugin with later analyses
+#[tauri::command]
+pub fn disable_plugin(app: AppHandle, id: String) -> Result<(), GenomeForgeError> {
+    Ok(plugins::set_enabled(&app, &id, false)?)
+}
+
+/// Get database status
+#[tauri::command]
+pub fn get_database_status(app: AppHandle, state: State<'_, AppState>) -> DatabaseStatus {
+    // A missing or unreadable record only hides the version details
+    let installed = databases::database_dir(&app)
+        .and_then(|dir| InstalledReleases::read(&dir))
+        .unwrap_or_default();
+    database_status(state.databases.snapshot(), &installed)
+}
+
+/// Download and install new database releases
+///
+/// Runs as a `database_update` task and emits `database-download-progress`
+/// events. Releases come from the signed manifest at `manifest_url`, or the
+/// one configured at build time; a release whose digest matches the
+/// installed one is not downloaded again. `databases` limits the update to
+/// the named databases; without it every published database is updated.
+/// Interrupted downloads resume where they stopped, and transfers are held
+/// under the `download_limit` setting.
+#[tauri::command]
+pub async fn update_databases(
+    app: AppHandle,
+    manifest_url: Option<String>,
+    databases: Option<Vec<DatabaseKind>>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DatabaseUpdate>, GenomeForgeError> {
+    let dir = databases::database_dir(&app)?;
+    let task = start_task(&app, &state, TaskKind::DatabaseUpdate);
+
+    let client = updater::client()?;
+    let manifest = updater::fetch_manifest(&client, manifest_url.as_deref()).await?;
+    let installed = InstalledReleases::read(&dir)?;
+
+    let mut updates = Vec::new();
+    let kinds = databases.unwrap_or_else(|| {
+        DatabaseKind::ALL
+            .into_iter()
+            .filter(DatabaseKind::is_published)
+            .collect()
+    });
+    for kind in kinds {
+        let release = manifest
+            .release(kind)
+            .ok_or_else(|| {
+                GenomeForgeError::DatabaseMissing(format!(
+                    "No {} release in the manifest",
+                    kind.as_str()
+                ))
+            })?
+            .clone();
+        let current = installed
+            .get(kind)
+            .is_some_and(|record| record.sha256.eq_ignore_ascii_case(&release.sha256))
+            && manager::find_installed(&dir, kind).is_some();
+        if current {
+            updates.push(DatabaseUpdate {
+                database: kind,
+                version: Some(release.version),
+                updated: false,
+                record_count: record_count(&state.databases.snapshot(), kind),
+            });
+            continue;
+        }
+
+        updates.push(download_and_install(&app, &state, &client, &dir, release, &task).await?);
+    }
+
+    Ok(updates)
+}
+
+/// Check the installed databases have not been damaged on disk
+///
+/// Runs as a `database_update` task. Every installed release is hashed
+/// again and compared with the signed manifest at `manifest_url`, or the
+/// one configured at build time, and with the digests kept when it was
+/// installed where the manifest does not list its version or cannot be
+/// fetched. A damaged release is unloaded so no analysis reads it, and
+/// with `repair` downloaded again when the manifest lists one; until it is
+/// replaced, `get_database_status` shows what was found.
+#[tauri::command]
+pub async fn verify_databases(
+    app: AppHandle,
+    manifest_url: Option<String>,
+    repair: bool,
+    state: State<'_, AppState>,
+) -> Result<Vec<DatabaseIntegrity>, GenomeForgeError> {
+    let dir = databases::database_dir(&app)?;
+    let task = start_task(&app, &state, TaskKind::DatabaseUpdate);
+
+    let client = updater::client()?;
+    let manifest = match updater::fetch_manifest(&client, manifest_url.as_deref()).await {
+        Ok(manifest) => Some(manifest),
+        Err(error) => {
+            tracing::warn!(%error, "verifying databases without the release manifest");
+            None
+        }
+    };
+    let checks = {
+        let dir = dir.clone();
+        let manifest = manifest.clone();
+        tokio::task::spawn_blocking(move || manager::verify_installed(&dir, manifest.as_ref()))
+            .await
+            .map_err(|e| format!("Database verification failed: {}", e))??
+    };
+
+    let mut results = Vec::new();
+    for (kind, check) in checks {
+        let mut repaired = false;
+        if check.is_damaged() {
+            tracing::warn!(
+                database = kind.as_str(),
+                status = ?check.status,
+                problem = check.problem.as_deref().unwrap_or_default(),
+                "database damaged"
+            );
+            state.databases.unload(kind);
+            let release = manifest
+                .as_ref()
+                .and_then(|manifest| manifest.release(kind))
+                .filter(|_| repair);
+            if let Some(release) = release {
+                download_and_install(&app, &state, &client, &dir, release.clone(), &task).await?;
+                repaired = true;
+            }
+        }
+        results.push(DatabaseIntegrity {
+            database: kind,
+            check,
+            repaired,
+        });
+    }
+    audit::record(
+        &app,
+        AuditAction::DatabaseUpdate,
+        "verification",
+        [
+            ("checked", results.len().to_string()),
+            (
+                "damaged",
+                results
+                    .iter()
+                    .filter(|result| result.check.is_damaged())
+                    .count()
+                    .to_string(),
+            ),
+            (
+                "repaired",
+                results
+                    .iter()
+                    .filter(|result| result.repaired)
+                    .count()
+                    .to_string(),
+            ),
+        ],
+    );
+    Ok(results)
+}
+
+/// Install a database release from a local file
+///
+/// For air-gapped machines: the release is copied from `file_path`, which
+/// for PharmGKB may also be the zipped tables or a directory of them. The
+/// `custom` database is only ever imported this way, from a lab's TSV or
+/// CSV table of interpretations by rsid. The
+/// file is checked against `sha256`, or a `<file>.sha256` next to it, when
+/// either is available.
+#[tauri::command]
+pub async fn import_database(
+    app: AppHandle,
+    database: DatabaseKind,
+    file_path: String,
+    sha256: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DatabaseUpdate, GenomeForgeError> {
+    let source = PathBuf::from(&file_path);
+
+    if !source.exists() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+
+    let dir = databases::database_dir(&app)?;
+    let expected = match sha256 {
+        Some(sha256) => Some(sha256),
+        None => manager::sidecar_checksum(&source)?,
+    };
+    let previous = state.databases.clinvar.current();
+    let (installation, delta) = tokio::task::spawn_blocking(move || {
+        let staged = manager::stage_local(&dir, database, &source)?;
+        let file_name = database.file_name_for(&source);
+        let installation = manager::install_release(
+            &dir,
+            database,
+            &staged,
+            file_name,
+            None,
+            expected.as_deref(),
+        )?;
+        let delta = release_delta(previous.as_deref(), &installation);
+        Ok::<_, String>((installation, delta))
+    })
+    .await
+    .map_err(|e| format!("Database import failed: {}", e))??;
+
+    Ok(install(&app, &state, database, installation, delta))
+}
+
+/// Install every database and reference FASTA of an offline data pack
+///
+/// For air-gapped machines: `file_path` is a pack on removable media, a
+/// tar archive, optionally gzip or Zstandard compressed, whose manifest is
+/// signed with the release key. Runs as a `database_update` task. The whole pack is
+/// staged and checked against the manifest before any of it is installed;
+/// a release whose digest matches the installed one is left as is.
+#[tauri::command]
+pub async fn import_database_pack(
+    app: AppHandle,
+    file_path: String,
+    state: State<'_, AppState>,
+) -> Result<PackImport, GenomeForgeError> {
+    let source = PathBuf::from(&file_path);
+    if !source.is_file() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let key = updater::release_key()?;
+    let dir = databases::database_dir(&app)?;
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    let task = start_task(&app, &state, TaskKind::DatabaseUpdate);
+    let cancel = task.cancel_flag();
+
+    let extracted = {
+        let dir = dir.clone();
+        let cancel = cancel.clone();
+        tokio::task::spawn_blocking(move || {
+            pack::extract(&source, &dir, key, |_| tasks::checkpoint(&cancel))
+        })
+        .await
+        .map_err(|e| format!("Database pack import failed: {}", e))??
+    };
+    let result = install_pack(&app, &state, &dir, references, &extracted, &cancel).await;
+    extracted.discard();
+    let (databases, references) = result?;
+
+    let version = extracted.manifest.version;
+    tracing::info!(
+        version,
+        databases = databases.len(),
+        references = references.len(),
+        "database pack imported"
+    );
+    audit::record(
+        &app,
+        AuditAction::DatabaseUpdate,
+        "pack",
+        [
+            ("version", version.clone()),
+            ("databases", databases.len().to_string()),
+            ("references", references.len().to_string()),
+        ],
+    );
+    Ok(PackImport {
+        version,
+        databases,
+        references,
+    })
+}
+
+/// Installed reference FASTA files, by build
+#[tauri::command]
+pub fn get_references(app: AppHandle) -> Result<Vec<InstalledReference>, GenomeForgeError> {
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    Ok(references.installed()?)
+}
+
+/// Install a reference FASTA for `build` from a local file
+///
+/// Runs as a `reference_install` task. The file may be gzip or bgzip
+/// compressed and is checked against `sha256`, or a `<file>.sha256` next
+/// to it, when either is available.
+#[tauri::command]
+pub async fn import_reference(
+    app: AppHandle,
+    build: GenomeBuild,
+    file_path: String,
+    sha256: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<InstalledReference, GenomeForgeError> {
+    let source = PathBuf::from(&file_path);
+    if !source.is_file() {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    let expected = match sha256 {
+        Some(sha256) => Some(sha256),
+        None => manager::sidecar_checksum(&source)?,
+    };
+    let task = start_task(&app, &state, TaskKind::ReferenceInstall);
+    let cancel = task.cancel_flag();
+    let installed = tokio::task::spawn_blocking(move || {
+        references.import(build, &source, None, expected.as_deref(), |_| {
+            tasks::checkpoint(&cancel)
+        })
+    })
+    .await
+    .map_err(|e| format!("Reference import failed: {}", e))??;
+
+    record_reference(&app, &installed);
+    Ok(installed)
+}
+
+/// Download and install the reference FASTA listed for `build` in the
+/// signed release manifest
+///
+/// Runs as a `reference_install` task and emits
+/// `reference-download-progress` events.
+#[tauri::command]
+pub async fn download_reference(
+    app: AppHandle,
+    build: GenomeBuild,
+    manifest_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<InstalledReference, GenomeForgeError> {
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    let task = start_task(&app, &state, TaskKind::ReferenceInstall);
+    let cancel = task.cancel_flag();
+
+    let client = updater::client()?;
+    let manifest = updater::fetch_manifest(&client, manifest_url.as_deref()).await?;
+    let release = manifest
+        .reference(build)
+        .ok_or_else(|| {
+            GenomeForgeError::DatabaseMissing(format!("No {:?} reference in the manifest", build))
+        })?
+        .clone();
+    let staged =
+        updater::download_reference(&app, &client, &references, &release, task.id(), &cancel)
+            .await?;
+    let installed = tokio::task::spawn_blocking(move || {
+        let installed = references.import(
+            build,
+            &staged,
+            Some(&release.version),
+            Some(&release.sha256),
+            |_| tasks::checkpoint(&cancel),
+        );
+        let _ = std::fs::remove_file(&staged);
+        installed
+    })
+    .await
+    .map_err(|e| format!("Reference download failed: {}", e))??;
+
+    record_reference(&app, &installed);
+    Ok(installed)
+}
+
+/// Whether the installed reference FASTA of `build` still has the digest
+/// it was installed with
+#[tauri::command]
+pub async fn verify_reference(
+    app: AppHandle,
+    build: GenomeBuild,
+) -> Result<bool, GenomeForgeError> {
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    let intact = tokio::task::spawn_blocking(move || references.verify(build))
+        .await
+        .map_err(|e| format!("Reference check failed: {}", e))??;
+    if !intact {
+        tracing::warn!(build = ?build, "reference fasta changed since it was installed");
+    }
+    Ok(intact)
+}
+
+/// Delete the installed reference FASTA of `build`; whether there was one
+#[tauri::command]
+pub fn remove_reference(app: AppHandle, build: GenomeBuild) -> Result<bool, GenomeForgeError> {
+    let references = ReferenceManager::new(databases::reference_dir(&app)?);
+    Ok(references.remove(build)?)
+}
+
+/// The latest log entries at least as severe as `level`, by default
+/// `info`, most recent first
+#[tauri::command]
+pub fn get_recent_logs(
+    app: AppHandle,
+    limit: Option<usize>,
+    level: Option<LogLevel>,
+) -> Result<Vec<LogEntry>, GenomeForgeError> {
+    let dir = logging::log_dir(&app)?;
+    Ok(diagnostics::recent(
+        &dir,
+        limit.unwrap_or(RECENT_LOGS),
+        level.unwrap_or(LogLevel::Info),
+    )?)
+}
+
+/// Write a zip of the logs and a description of the system to attach to
+/// a bug report
+///
+/// The bundle holds no genome data or results; every line of it is passed
+/// through the log privacy filter again as it is written.
+#[tauri::command]
+pub async fn create_diagnostic_bundle(
+    app: AppHandle,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<DiagnosticBundle, GenomeForgeError> {
+    let path = PathBuf::from(&output_path);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            return Err(GenomeForgeError::FileNotFound(Some(
+                parent.display().to_string(),
+            )));
+        }
+    }
+    let dir = logging::log_dir(&app)?;
+    let system = serde_json::json!({
+        "app_version": get_app_version(),
+        "system": get_system_info(app.clone()),
+        "tasks": state.tasks.list(),
+        "databases": get_database_status(app.clone(), state),
+    });
+    let system = serde_json::to_string_pretty(&system)
+        .map_err(|e| format!("Failed to serialize system information: {}", e))?;
+
+    let file = audit::file_name(&path);
+    let bundle = tokio::task::spawn_blocking(move || {
+        diagnostics::write_bundle(&path, &dir, &[("system.json", system)])
+    })
+    .await
+    .map_err(|e| format!("Diagnostics task failed: {}", e))??;
+    audit::record(
+        &app,
+        AuditAction::Export,
+        &file,
+        [("format", "diagnostics".to_string())],
+    );
+    Ok(bundle)
+}
+
+/// One page of what the active profile's audit log records, oldest first,
+/// with the result of checking its whole hash chain
+#[tauri::command]
+pub async fn get_audit_log(
+    app: AppHandle,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<AuditReport, GenomeForgeError> {
+    let report = tokio::task::spawn_blocking(move || {
+        let log = audit::read(&app)?;
+        // Only the entries on the page are copied out of the log
+        let entries = Page::new(log.entries().iter().collect(), offset.unwrap_or(0), limit)
+            .try_map(|entry| Ok::<_, String>(entry.clone()))?;
+        Ok::<_, String>(AuditReport {
+            verification: log.verify(),
+            entries,
+        })
+    })
+    .await
+    .map_err(|e| format!("Audit task failed: {}", e))??;
+    Ok(report)
+}
+
+/// The user's settings
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> Result<Settings, GenomeForgeError> {
+    Ok(settings::read(&app)?)
+}
+
+/// Replace the user's settings, returning them as saved
+///
+/// Analyses started without options use the allele frequency threshold
+/// and consent given here; database locations take effect the next time
+/// the app starts.
+#[tauri::command]
+pub fn update_settings(app: AppHandle, settings: Settings) -> Result<Settings, GenomeForgeError> {
+    settings.validate().map_err(GenomeForgeError::invalid)?;
+    settings::write(&app, &settings)?;
+    Ok(settings)
+}
+
+/// Import genome files saved into a folder as they arrive, or stop with
+/// no folder, returning the settings as saved
+///
+/// Each new file is announced as an `import-ready` or `import-rejected`
+/// event; files already in the folder are not.
+#[tauri::command]
+pub fn set_watch_folder(
+    app: AppHandle,
+    folder: Option<String>,
+) -> Result<Settings, GenomeForgeError> {
+    let mut settings = settings::read(&app)?;
+    settings.watch_dir = match folder {
+        Some(folder) => {
+            let dir = PathBuf::from(folder);
+            watcher::check_folder(&dir).map_err(GenomeForgeError::invalid)?;
+            Some(dir)
+        }
+        None => None,
+    };
+    settings::write(&app, &settings)?;
+    Ok(settings)
+}
+
+/// Securely delete the genome data of every profile and unload it from
+/// memory
+///
+/// Parses and analyses are cancelled, every profile's genome and results
+/// are unloaded, and every file of every profile — sessions, genome
+/// caches, saved results, report templates, fingerprints and audit logs —
+/// is overwritten and deleted, leaving a new, empty default profile.
+/// Reference databases and the application log, which holds no genetic
+/// data, are kept, as are reports exported outside the app's directories.
+#[tauri::command]
+pub async fn purge_all_data(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<DataPurge, GenomeForgeError> {
+    state.tasks.cancel_kind(TaskKind::Parse);
+    state.tasks.cancel_kind(TaskKind::Analysis);
+    let mut genomes: Vec<Arc<LoadedGenome>> = state.genome.take().into_iter().collect();
+    let mut results = usize::from(state.results.take().is_some());
+    for parked in state.profiles.take_all() {
+        genomes.extend(parked.genome);
+        results += usize::from(parked.results.is_some());
+    }
+    let genome_count = genomes.len();
+    let paths = profiles::data_paths(&app)?;
+
+    let (report, genomes_zeroized) = tokio::task::spawn_blocking(move || {
+        let zeroized = genomes
+            .into_iter()
+            .filter_map(|genome| Arc::try_unwrap(genome).ok())
+            .map(|mut genome| genome.zeroize())
+            .count();
+        (purge::purge(paths.iter().map(PathBuf::as_path)), zeroized)
+    })
+    .await
+    .map_err(|e| format!("Purge task failed: {}", e))?;
+    tracing::info!(
+        files = report.files.len(),
+        failed = report.failed.len(),
+        "purged saved data"
+    );
+    Ok(DataPurge {
+        bytes: report.bytes(),
+        report,
+        genomes: genome_count,
+        genomes_zeroized,
+        results,
+    })
+}
+
+/// Check the files the app was opened with, once the frontend listens
+///
+/// Each is reported like a dropped file, with a `file-ready` or
+/// `file-rejected` event. Returns how many there were; later calls find
+/// none.
+#[tauri::command]
+pub fn open_launch_files(app: AppHandle) -> usize {
+    let paths = launch::take_pending();
+    let count = paths.len();
+    if count > 0 {
+        intake::handle_drop(&app, paths);
+    }
+    count
+}
+
+// Helper functions
+
+fn database_status(databases: DatabaseSnapshot, installed: &InstalledReleases) -> DatabaseStatus {
+    DatabaseStatus {
+        clinvar: databases.clinvar.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(
+                db.len(),
+                db.release_date(),
+                installed.get(DatabaseKind::ClinVar),
+            )
+        }),
+        pharmgkb: databases.pharmgkb.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(
+                db.len(),
+                db.release_date(),
+                installed.get(DatabaseKind::PharmGkb),
+            )
+        }),
+        cpic: databases.cpic.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(db.len(), None, installed.get(DatabaseKind::Cpic))
+        }),
+        gwas: databases.gwas.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(
+                db.len(),
+                db.release_date(),
+                installed.get(DatabaseKind::Gwas),
+            )
+        }),
+        dbsnp: databases.dbsnp.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(
+                db.len(),
+                db.release_date(),
+                installed.get(DatabaseKind::DbSnp),
+            )
+        }),
+        gnomad: databases.gnomad.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(
+                db.len(),
+                db.release_date(),
+                installed.get(DatabaseKind::Gnomad),
+            )
+        }),
+        liftover: databases
+            .liftover
+            .map_or_else(DatabaseInfo::missing, |chain| {
+                DatabaseInfo::loaded(chain.len(), None, installed.get(DatabaseKind::Liftover))
+            }),
+        haplogroups: databases
+            .haplogroups
+            .map_or_else(DatabaseInfo::missing, |db| {
+                DatabaseInfo::loaded(db.len(), None, installed.get(DatabaseKind::Haplogroups))
+            }),
+        clingen: databases.clingen.map_or_else(DatabaseInfo::missing, |db| {
+            DatabaseInfo::loaded(db.len(), None, installed.get(DatabaseKind::ClinGen))
+        }),
+        probe_mask: databases
+            .probe_mask
+            .map_or_else(DatabaseInfo::missing, |mask| {
+                DatabaseInfo::loaded(mask.len(), None, installed.get(DatabaseKind::ProbeMask))
+            }),
+        custom: databases
+            .custom
+            .map_or_else(DatabaseInfo::missing, |table| {
+                DatabaseInfo::loaded(table.len(), None, installed.get(DatabaseKind::Custom))
+            }),
+    }
+}
+
+/// A passphrase, wiped from memory once dropped; an empty one is rejected
+/// rather than treated as none
+fn non_empty(passphrase: Option<String>) -> Result<Option<Zeroizing<String>>, GenomeForgeError> {
+    let passphrase = passphrase.map(Zeroizing::new);
+    match passphrase {
+        Some(passphrase) if passphrase.is_empty() => {
+            Err(GenomeForgeError::invalid("Passphrase must not be empty"))
+        }
+        passphrase => Ok(passphrase),
+    }
+}
+
+/// The passphrase an export is encrypted under, required when it is to be
+/// and dropped when not
+fn export_passphrase(
+    encrypt: bool,
+    passphrase: Option<String>,
+) -> Result<Option<Zeroizing<String>>, GenomeForgeError> {
+    match (encrypt, non_empty(passphrase)?) {
+        (true, None) => Err(GenomeForgeError::invalid(
+            "A passphrase is required to encrypt an export",
+        )),
+        (true, passphrase) => Ok(passphrase),
+        (false, _) => Ok(None),
+    }
+}
+
+/// What the audit log records about an export
+fn export_details(options: &ExportOptions) -> [(&'static str, String); 4] {
+    [
+        ("format", format!("{:?}", options.format).to_lowercase()),
+        ("encrypted", options.encrypt.to_string()),
+        ("raw_data", options.include_raw_data.to_string()),
+        ("notes", options.include_notes.to_string()),
+    ]
+}
+
+/// The locale of a language tag, English when it is missing or unsupported
+fn report_locale(tag: Option<&str>) -> Locale {
+    tag.and_then(Locale::from_tag).unwrap_or_default()
+}
+
+/// Size and compression of the file at `path`
+fn file_size(path: &Path) -> Option<(u64, Compression)> {
+    let size = std::fs::metadata(path).ok()?.len();
+    let (_, compression) = compression::open_reader(path).ok()?;
+    Some((size, compression))
+}
+
+/// Sites a file is streamed to, and within how much memory
+type SiteStream = (SiteFilter, StreamOptions);
+
+/// The size of a file to parse, and the sites of `databases` to stream it
+/// to when loading it whole would need more than `budget` bytes
+fn fit_to_budget(
+    path: &Path,
+    budget: u64,
+    databases: &DatabaseSnapshot,
+) -> (Option<(u64, Compression)>, Option<SiteStream>) {
+    // Reads are piled up at a few sites, however large the file
+    let file = file_size(path).filter(|_| !alignment::is_alignment(path).unwrap_or(false));
+    let needed = file.map(|(size, compression)| system::parse_memory_estimate(size, compression));
+    let stream = needed.filter(|needed| *needed > budget).map(|_| {
+        let options = StreamOptions {
+            memory_budget: budget,
+            ..StreamOptions::default()
+        };
+        (databases.sites(), options)
+    });
+    (file, stream)
+}
+
+fn num_cpus() -> usize {
+    std::thread::available_parallelism()
+        .map(|p| p.get())
+        .unwrap_or(1)
+}
+
+/// Genome loaded in a profile, whether or not it is active
+fn profile_genome(
+    app: &AppHandle,
+    state: &AppState,
+    id: &str,
+) -> Result<Arc<LoadedGenome>, String> {
+    let genome = if id == profiles::active_id(app)? {
+        state.genome.current()
+    } else {
+        state.profiles.genome(id)
+    };
+    genome.ok_or_else(|| format!("No genome loaded in profile {}", id))
+}
+
+/// Check the options of an analysis, taking them from the settings when
+/// none are given, and the genome it is to run on
+fn prepare_analysis(
+    app: &AppHandle,
+    options: Option<AnalysisOptions>,
+    state: &AppState,
+) -> Result<(Arc<LoadedGenome>, AnalysisOptions), GenomeForgeError> {
+    let options = analysis_options(app, options)?;
+    let genome = state.genome.current().ok_or(GenomeForgeError::NoGenome)?;
+    Ok((genome, options))
+}
+
+/// Check the options of an analysis, taking them from the settings when
+/// none are given
+fn analysis_options(
+    app: &AppHandle,
+    options: Option<AnalysisOptions>,
+) -> Result<AnalysisOptions, GenomeForgeError> {
+    let mut options = options.unwrap_or_else(|| {
+        let settings = settings::current(app);
+        AnalysisOptions {
+            max_allele_frequency: settings.max_allele_frequency,
+            screen_secondary_findings: settings.consent.secondary_findings,
+            report_late_onset: settings.consent.late_onset,
+            consent: settings.consent.policy(),
+            ..AnalysisOptions::default()
+        }
+    });
+    if options
+        .max_allele_frequency
+        .is_some_and(|af| !(0.0..=1.0).contains(&af))
+    {
+        return Err(GenomeForgeError::invalid(
+            "max_allele_frequency must be between 0 and 1",
+        ));
+    }
+    options.imputation.validate()?;
+    options.quality.validate()?;
+    if options
+        .reference_fasta
+        .as_ref()
+        .is_some_and(|path| !Path::new(path).exists())
+    {
+        return Err(GenomeForgeError::FileNotFound(None));
+    }
+    options.references = Some(ReferenceManager::new(databases::reference_dir(app)?));
+    options.plugins = plugins::load_enabled(app);
+    options.watchlist = watchlist::read(app)?;
+    // A missing or unreadable record only leaves the versions out of the
+    // result's provenance
+    options.installed = databases::database_dir(app)
+        .and_then(|dir| InstalledReleases::read(&dir))
+        .unwrap_or_default();
+    Ok(options)
+}
+
+/// Run an analysis task, keep its results and announce them
+async fn run_analysis(
+    app: &AppHandle,
+    state: &AppState,
+    task: &TaskHandle,
+    genome: Arc<LoadedGenome>,
+    mut options: AnalysisOptions,
+) -> Result<AnalysisOverview, GenomeForgeError> {
+    let databases = state.databases.snapshot();
+    let cancel = task.cancel_flag();
+    let saving = app.clone();
+    let stream = state.finding_streams.start(app, task.id());
+    options.stream = Some(stream.clone());
+    let analyzed = match tokio::task::spawn_blocking(move || {
+        let result = analyze_genome(&genome, &databases, &options, &cancel)?;
+        save_result(&saving, &genome, &result);
+        Ok::<_, String>(result)
+    })
+    .await
+    {
+        Ok(result) => result.map_err(GenomeForgeError::from),
+        Err(e) => Err(format!("Analysis task failed: {}", e).into()),
+    };
+    // Waits for the last batch to go out, which a slow frontend may hold up
+    let finishing = app.clone();
+    let error = analyzed.as_ref().err().cloned();
+    let _ = tokio::task::spawn_blocking(move || {
+        let streams = &finishing.state::<AppState>().finding_streams;
+        streams.finish(&finishing, &stream, error);
+    })
+    .await;
+    let result = state.results.replace(analyzed?);
+    // A full analysis already has every change of the release it ran on
+    state.clinvar_changes.take();
+    tracing::info!(
+        analyzed = result.summary.analyzed_variants,
+        "analysis finished"
+    );
+    audit::record(
+        app,
+        AuditAction::Analysis,
+        "variants",
+        analysis_details(&result.summary),
+    );
+    notify::analysis_complete(app, result.summary.clinical_count);
+    Ok(AnalysisOverview::new(&result))
+}
+
+/// Refuse an analysis of a category the settings exclude
+fn consent_to(app: &AppHandle, category: FindingCategory) -> Result<(), GenomeForgeError> {
+    settings::current(app)
+        .consent
+        .policy()
+        .require(category)
+        .map_err(GenomeForgeError::invalid)
+}
+
+/// Fail unless the genomes are on one build, as sites are compared by
+/// position
+fn same_build(genomes: &[&LoadedGenome]) -> Result<(), GenomeForgeError> {
+    let mut builds = genomes.iter().map(|genome| liftover::detect_build(genome));
+    let first = builds.next();
+    if builds.any(|build| Some(build) != first) {
+        return Err(GenomeForgeError::invalid(
+            "The genomes are on different reference builds; lift them over to the same build first",
+        ));
+    }
+    Ok(())
+}
+
+fn start_task(app: &AppHandle, state: &AppState, kind: TaskKind) -> TaskHandle {
+    let task = state.tasks.start(kind);
+    tracing::info!(task_id = task.id(), kind = ?kind, "task started");
+    events::TASK_STARTED.emit(
+        app,
+        TaskStarted {
+            task_id: task.id(),
+            kind,
+        },
+    );
+    task
+}
+
+/// What the audit log records about an analysis
+fn analysis_details(summary: &AnalysisSummary) -> [(&'static str, String); 4] {
+    [
+        ("analyzed", summary.analyzed_variants.to_string()),
+        ("clinical", summary.clinical_count.to_string()),
+        ("drug", summary.drug_count.to_string()),
+        ("trait", summary.trait_count.to_string()),
+    ]
+}
+
+/// What the audit log records about a loaded genome
+fn loaded_details(genome: &LoadedGenome) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("format", genome.file.format.as_str().to_string()),
+        ("variants", genome.len().to_string()),
+    ];
+    if let Some(fingerprint) = &genome.file.fingerprint {
+        details.push(("sha256", fingerprint.sha256.clone()));
+    }
+    details
+}
+
+/// The profiles an analysis compared, by role
+fn profile_details(profiles: &[(&'static str, String)]) -> Vec<(&'static str, String)> {
+    profiles
+        .iter()
+        .map(|(role, id)| (*role, format!("profile {}", id)))
+        .collect()
+}
+
+/// Download a release listed in the manifest and install it
+async fn download_and_install(
+    app: &AppHandle,
+    state: &AppState,
+    client: &reqwest::Client,
+    dir: &Path,
+    release: Release,
+    task: &TaskHandle,
+) -> Result<DatabaseUpdate, GenomeForgeError> {
+    let cancel = task.cancel_flag();
+    let staged = updater::download_release(app, client, dir, &release, task.id(), &cancel).await?;
+    tasks::checkpoint(&cancel)?;
+    let kind = release.database;
+    let dir = dir.to_path_buf();
+    let previous = state.databases.clinvar.current();
+    let (installation, delta) = tokio::task::spawn_blocking(move || {
+        let installation = manager::install_release(
+            &dir,
+            kind,
+            &staged,
+            &release.file_name,
+            Some(&release.version),
+            Some(&release.sha256),
+        )?;
+        let delta = release_delta(previous.as_deref(), &installation);
+        Ok::<_, String>((installation, delta))
+    })
+    .await
+    .map_err(|e| format!("Database update failed: {}", e))??;
+    Ok(install(app, state, kind, installation, delta))
+}
+
+/// Install the staged files of a pack, releases first
+async fn install_pack(
+    app: &AppHandle,
+    state: &AppState,
+    dir: &Path,
+    references: ReferenceManager,
+    extracted: &pack::ExtractedPack,
+    cancel: &CancelFlag,
+) -> Result<(Vec<DatabaseUpdate>, Vec<InstalledReference>), GenomeForgeError> {
+    let installed = InstalledReleases::read(dir)?;
+    let mut updates = Vec::new();
+    for (release, staged) in &extracted.releases {
+        tasks::checkpoint(cancel)?;
+        let kind = release.database;
+        let current = installed
+            .get(kind)
+            .is_some_and(|record| record.sha256.eq_ignore_ascii_case(&release.sha256))
+            && manager::find_installed(dir, kind).is_some();
+        if current {
+            updates.push(DatabaseUpdate {
+                database: kind,
+                version: Some(release.version.clone()),
+                updated: false,
+                record_count: record_count(&state.databases.snapshot(), kind),
+            });
+            continue;
+        }
+        let (dir, release, staged) = (dir.to_path_buf(), release.clone(), staged.clone());
+        let previous = state.databases.clinvar.current();
+        let (installation, delta) = tokio::task::spawn_blocking(move || {
+            let installation = manager::install_release(
+                &dir,
+                kind,
+                &staged,
+                &release.file_name,
+                Some(&release.version),
+                Some(&release.sha256),
+            )?;
+            let delta = release_delta(previous.as_deref(), &installation);
+            Ok::<_, String>((installation, delta))
+        })
+        .await
+        .map_err(|e| format!("Database pack import failed: {}", e))??;
+        updates.push(install(app, state, kind, installation, delta));
+    }
+
+    let mut installed_references = Vec::new();
+    for (release, staged) in &extracted.references {
+        let (references, release, staged) = (references.clone(), release.clone(), staged.clone());
+        let cancel = cancel.clone();
+        let installed = tokio::task::spawn_blocking(move || {
+            references.import(
+                release.build,
+                &staged,
+                Some(&release.version),
+                Some(&release.sha256),
+                |_| tasks::checkpoint(&cancel),
+            )
+        })
+        .await
+        .map_err(|e| format!("Database pack import failed: {}", e))??;
+        record_reference(app, &installed);
+        installed_references.push(installed);
+    }
+    Ok((updates, installed_references))
+}
+
+/// Make an installed release available to new analyses
+fn install(
+    app: &AppHandle,
+    state: &AppState,
+    kind: DatabaseKind,
+    installation: Installation,
+    delta: Option<ReleaseDelta>,
+) -> DatabaseUpdate {
+    let record_count = installation.database.len();
+    state.databases.install(installation.database);
+    if let Some(delta) = delta {
+        tracing::info!(
+            added = delta.added(),
+            removed = delta.removed(),
+            changed = delta.changed(),
+            "clinvar records changed"
+        );
+        state.clinvar_changes.replace(delta);
+    }
+    tracing::info!(
+        database = kind.as_str(),
+        records = record_count,
+        "database installed"
+    );
+    let version = installation.record.version.clone().unwrap_or_default();
+    audit::record(
+        app,
+        AuditAction::DatabaseUpdate,
+        kind.as_str(),
+        [("version", version), ("records", record_count.to_string())],
+    );
+    DatabaseUpdate {
+        database: kind,
+        version: installation.record.version,
+        updated: true,
+        record_count,
+    }
+}
+
+/// Log and audit a newly installed reference FASTA
+fn record_reference(app: &AppHandle, installed: &InstalledReference) {
+    tracing::info!(
+        build = ?installed.build,
+        sequences = installed.sequences,
+        "reference installed"
+    );
+    audit::record(
+        app,
+        AuditAction::DatabaseUpdate,
+        &format!("{:?} reference", installed.build),
+        [
+            ("version", installed.version.clone().unwrap_or_default()),
+            ("sha256", installed.sha256.clone()),
+        ],
+    );
+}
+
+/// What a newly installed ClinVar release changed from the one loaded
+/// before it
+fn release_delta(
+    previous: Option<&ClinVarDatabase>,
+    installation: &Installation,
+) -> Option<ReleaseDelta> {
+    match (previous, &installation.database) {
+        (Some(previous), LoadedDatabase::ClinVar(current)) => {
+            Some(ReleaseDelta::between(previous, current))
+        }
+        _ => None,
+    }
+}
+
+fn record_count(databases: &DatabaseSnapshot, kind: DatabaseKind) -> usize {
+    match kind {
+        DatabaseKind::ClinVar => databases.clinvar.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::PharmGkb => databases.pharmgkb.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::Cpic => databases.cpic.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::Gwas => databases.gwas.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::DbSnp => databases.dbsnp.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::Gnomad => databases.gnomad.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::Liftover => databases.liftover.as_ref().map_or(0, |chain| chain.len()),
+        DatabaseKind::Haplogroups => databases.haplogroups.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::ClinGen => databases.clingen.as_ref().map_or(0, |db| db.len()),
+        DatabaseKind::ProbeMask => databases.probe_mask.as_ref().map_or(0, |mask| mask.len()),
+        DatabaseKind::Custom => databases.custom.as_ref().map_or(0, |table| table.len()),
+    }
+}
+
+/// gnomAD frequencies of a ClinVar record's allele, by position on the
+/// release's build and by rsid on any other
+fn record_frequency(gnomad: &GnomadDatabase, record: &ClinVarRecord) -> Option<AlleleFrequencies> {
+    let found = if record.genome_build == gnomad.genome_build() {
+        gnomad.lookup_allele(
+            &record.chromosome,
+            record.position,
+            &record.reference,
+            &record.alternate,
+        )
+    } else {
+        gnomad
+            .lookup_rsid(record.rsid.as_deref()?)
+            .into_iter()
+            .find(|found| found.alternate == record.alternate)
+    };
+    found.map(|found| found.frequencies.clone())
+}
+
+/// Save a result to the profile's saved analyses, to be reloaded and
+/// compared with later ones; a result that cannot be saved is only missing
+/// from them
+fn save_result(app: &AppHandle, genome: &LoadedGenome, result: &AnalysisResultData) {
+    let Some(fingerprint) = &genome.file.fingerprint else {
+        return;
+    };
+    let saved = sessions::session_dir(app)
+        .and_then(|dir| sessions::device_key(&dir))
+        .and_then(|key| {
+            history::record(
+                &history::history_dir(app)?,
+                &key,
+                &fingerprint.sha256,
+                &audit::file_name(&fingerprint.path),
+                result,
+            )
+        });
+    if let Err(error) = saved {
+        tracing::warn!(%error, "analysis result not saved");
+    }
+}
+
+/// The active profile's saved analyses
+fn saved_analyses(app: &AppHandle) -> Result<Box<dyn ResultsStore>, String> {
+    let key = sessions::session_dir(app).and_then(|dir| sessions::device_key(&dir))?;
+    history::open(&history::history_dir(app)?, &key)
+}
+
+/// Parsed genomes cached for the active profile
+fn genome_cache(app: &AppHandle, key: Key) -> Result<GenomeCache, String> {
+    let dir = profiles::active_local_dir(app)?.join("cache");
+    Ok(GenomeCache::new(&dir, key))
+}
+
+/// What to check about the sex chromosomes before relying on them, or
+/// `None` when nothing is; too few sites to tell is not worth a warning
+fn sex_warning(check: &SexCheck) -> Option<String> {
+    let messages: Vec<&str> = check
+        .warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            SexWarning::DeclaredMismatch => Some("The genetic sex of this file is not the one declared; it may be another person's file."),
+            SexWarning::PossibleXxy => Some("The X chromosome calls are heterozygous and the Y chromosome is called, as in XXY or a mix of two samples."),
+            SexWarning::PossibleSingleX => Some("The X chromosome calls show no heterozygosity and the Y chromosome is not called, as in a single X chromosome or loss of heterozygosity."),
+            SexWarning::Inconclusive => None,
+        })
+        .collect();
+    (!messages.is_empty()).then(|| {
+        format!(
+            "{} X-linked findings are read without assuming a sex.",
+            messages.join(" ")
+        )
+    })
+}
+
+/// Record a loaded file's fingerprint, returning a warning when the file
+/// was loaded before with other contents
+///
+/// The log is best effort; a log that cannot be read or written only
+/// loses the warning.
+fn record_fingerprint(app: &AppHandle, key: &Key, fingerprint: &FileFingerprint) -> Option<String> {
+    let path = profiles::active_dir(app).ok()?.join(FINGERPRINT_LOG);
+    let mut log = FingerprintLog::open(&path, KeySource::Device(key)).unwrap_or_default();
+    let previous = log.record(fingerprint.clone());
+    let _ = log.save(&path, KeySource::Device(key));
+    let previous = previous?;
+    let name = fingerprint.path.file_name().map_or_else(
+        || fingerprint.path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    Some(format!(
+        "The contents of {} changed since it was last loaded: its SHA-256 was {} and is now {}. Results may differ from those of earlier analyses of this file.",
+        name, previous.sha256, fingerprint.sha256
+    ))
+}
+
+/// A `batch_analyze` batch, shared by the threads working through it
+struct BatchJob {
+    app: AppHandle,
+    task_id: TaskId,
+    cancel: CancelFlag,
+    databases: DatabaseSnapshot,
+    options: AnalysisOptions,
+    export: ExportOptions,
+    template: ReportTemplate,
+    passphrase: Option<Zeroizing<String>>,
+    /// Memory each file may be parsed in
+    budget: u64,
+    files: Vec<PathBuf>,
+    reports: Vec<PathBuf>,
+    completed: AtomicUsize,
+}
+
+impl BatchJob {
+    /// Parse, analyze and export one file, recording how it went
+    fn run(&self, index: usize) -> BatchFile {
+        let path = &self.files[index];
+        let outcome = self.analyze(index);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+        let (stage, error) = match &outcome {
+            Ok(_) => (BatchStage::Done, None),
+            Err(error) => {
+                tracing::warn!(file = %audit::file_name(path), %error, "batch file failed");
+                (BatchStage::Failed, Some(error.clone()))
+            }
+        };
+        self.progress(index, stage, error.clone());
+        BatchFile {
+            file_path: path.display().to_string(),
+            report_path: outcome
+                .is_ok()
+                .then(|| self.reports[index].display().to_string()),
+            summary: outcome.ok(),
+            error,
+        }
+    }
+
+    fn analyze(&self, index: usize) -> Result<AnalysisSummary, String> {
+        let (path, report) = (&self.files[index], &self.reports[index]);
+        self.progress(index, BatchStage::Parsing, None);
+        tasks::checkpoint(&self.cancel)?;
+        if !path.is_file() {
+            return Err(format!("{} does not exist", path.display()));
+        }
+        let (_, stream) = fit_to_budget(path, self.budget, &self.databases);
+        let (genome, _) = load_genome(
+            &self.app,
+            self.task_id,
+            path,
+            None,
+            stream.as_ref(),
+            None,
+            &self.cancel,
+        )?;
+        let file = audit::file_name(path);
+        audit::record(
+            &self.app,
+            AuditAction::FileLoaded,
+            &file,
+            loaded_details(&genome),
+        );
+
+        self.progress(index, BatchStage::Analyzing, None);
+        let result = analyze_genome(&genome, &self.databases, &self.options, &self.cancel)?;
+        save_result(&self.app, &genome, &result);
+        audit::record(
+            &self.app,
+            AuditAction::Analysis,
+            &file,
+            analysis_details(&result.summary),
+        );
+
+        self.progress(index, BatchStage::Exporting, None);
+        tasks::checkpoint(&self.cancel)?;
+        let format = self.export.format;
+        // Array calls need their alleles from dbSNP to be written as VCF
+        let resolved = match (&self.databases.dbsnp, format) {
+            (Some(dbsnp), ExportFormat::Vcf) => Some(dbsnp.normalize(&genome, |_| Ok(()))?.0),
+            _ => None,
+        };
+        let variants = (self.export.include_raw_data || format == ExportFormat::Vcf)
+            .then(|| resolved.as_ref().unwrap_or(&genome).variants());
+        let info = ExportInfo {
+            report_id: report_stem(path),
+            format,
+            exported_at: export::now(),
+            locale: report_locale(self.export.locale.as_deref()),
+        };
+        export::export(
+            report,
+            &info,
+            &result,
+            &self.template,
+            variants,
+            self.export.findings_only,
+            self.passphrase.as_deref().map(String::as_str),
+        )?;
+        audit::record(
+            &self.app,
+            AuditAction::Export,
+            &audit::file_name(report),
+            export_details(&self.export),
+        );
+        Ok(result.summary)
+    }
+
+    fn progress(&self, index: usize, stage: BatchStage, error: Option<String>) {
+        events::BATCH_PROGRESS.emit(
+            &self.app,
+            BatchProgress {
+                task_id: self.task_id,
+                index,
+                file_path: self.files[index].display().to_string(),
+                stage,
+                completed: self.completed.load(Ordering::Relaxed),
+                total: self.files.len(),
+                error,
+            },
+        );
+    }
+}
+
+/// Where the reports of a batch are written: `dir/<name>.<extension>`,
+/// numbered when two files have the same name
+fn report_paths(
+    dir: &Path,
+    files: &[PathBuf],
+    format: ExportFormat,
+    encrypted: bool,
+) -> Vec<PathBuf> {
+    let extension = if encrypted && format != ExportFormat::Pdf {
+        format!("{}.{}", format.extension(), ENCRYPTED_EXTENSION)
+    } else {
+        format.extension().to_string()
+    };
+    // Windows file names are not case sensitive
+    let mut taken = BTreeSet::new();
+    files
+        .iter()
+        .map(|path| {
+            let stem = report_stem(path);
+            let mut name = format!("{}.{}", stem, extension);
+            for n in 2.. {
+                if taken.insert(name.to_lowercase()) {
+                    break;
+                }
+                name = format!("{}-{}.{}", stem, n, extension);
+            }
+            dir.join(name)
+        })
+        .collect()
+}
+
+/// A genome file's name without its extension, nor that of its compression
+fn report_stem(path: &Path) -> String {
+    let compressed = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "gz" | "bgz"));
+    let name = if compressed {
+        path.file_stem().map(Path::new)
+    } else {
+        Some(path)
+    };
+    name.and_then(Path::file_stem)
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| "genome".to_string())
+}
+
+/// Load a genome file, keeping only the variants at the sites of
+/// `stream` when one is given
+///
+/// With a `cache`, a file parsed before is read from it, and a file parsed
+/// now is added to it; whether the genome came from the cache is returned
+/// with it. Cache failures are ignored, as the file can always be parsed.
+fn load_genome(
+    app: &AppHandle,
+    task_id: TaskId,
+    path: &Path,
+    sample: Option<&str>,
+    stream: Option<&SiteStream>,
+    cache: Option<&GenomeCache>,
+    cancel: &CancelFlag,
+) -> Result<(LoadedGenome, bool), String> {
+    // Alignments are piled up at the clinical sites rather than parsed, and
+    // are too large to hash or worth caching
+    if alignment::is_alignment(path)? {
+        let bam = BamFile::open(path)?;
+        let build = bam.genome_build().ok_or_else(|| {
+            "The reference build of the alignments could not be told from their header".to_string()
+        })?;
+        let genome =
+            bam.load_targets(&Target::clinical(build), &PileupOptions::default(), |_| {
+                tasks::checkpoint(cancel)
+            })?;
+        return Ok((genome, false));
+    }
+    let fingerprint = FileFingerprint::of(path)?;
+    // The cache is keyed by one file's hash, which neither tells samples
+    // apart nor covers the other two files of a PLINK fileset
+    let fileset = plink::Fileset::of(path)
+        .ok()
+        .filter(|_| plink::is_plink(path));
+    let cache = cache.filter(|_| sample.is_none() && fileset.is_none());
+    if let Some(cache) = cache {
+        if let Ok(Some(mut genome)) = cache.get(&fingerprint.sha256) {
+            genome.file.fingerprint = Some(fingerprint);
+            return Ok((genome, true));
+        }
+    }
+    // Progress follows the .bed of a fileset, whichever file was chosen
+    let read_from = fileset.map_or_else(|| path.to_path_buf(), |fileset| fileset.bed);
+    let total_bytes = std::fs::metadata(read_from)
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+    let counter = ByteCounter::default();
+    let mut source = parser::open_sample_counted(path, sample, &counter)?;
+
+    let started = Instant::now();
+    let mut last_emit: Option<Instant> = None;
+    let progress = |records_parsed| {
+        tasks::checkpoint(cancel)?;
+        if last_emit.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL) {
+            last_emit = Some(Instant::now());
+            let progress = ParseProgress::new(
+                counter.get(),
+                total_bytes,
+                records_parsed,
+                started.elapsed(),
+            );
+            // Progress is best effort; a closed window must not fail the parse
+            events::PARSE_PROGRESS.emit(app, ParseProgressEvent { task_id, progress });
+        }
+        Ok(())
+    };
+    let mut genome = match stream {
+        Some((sites, options)) => stream::stream_sites(source.as_mut(), sites, options, progress)
+            .map(|(genome, _)| genome)?,
+        None => LoadedGenome::load_with(source.as_mut(), progress)?,
+    };
+    if let Some(cache) = cache {
+        let _ = cache.put(&fingerprint.sha256, &genome);
+    }
+    genome.file.fingerprint = Some(fingerprint);
+    Ok((genome, false))
+}
+
+/// Gene symbol and region a `query_region` query names
+fn resolve_region(
+    query: &str,
+    build: Option<GenomeBuild>,
+) -> Result<(Option<String>, Region), String> {
+    if let Some(gene) = genes::find(query) {
+        let build = build.ok_or_else(|| {
+            format!(
+                "The genome build is unknown, so {} cannot be located; search a range instead",
+                gene.symbol
+            )
+        })?;
+        let region = gene
+            .region(build)
+            .ok_or_else(|| format!("No coordinates for {} on {:?}", gene.symbol, build))?;
+        return Ok((Some(gene.symbol.to_string()), region));
+    }
+    // A bare word is a gene unless it names a chromosome
+    let region: Region = query.parse()?;
+    let chromosome = region.chromosome.as_str();
+    let is_chromosome = chromosome
+        .parse::<u8>()
+        .is_ok_and(|n| (1..=22).contains(&n))
+        || ["X", "Y", "MT"].contains(&chromosome);
+    if !query.contains(':') && !is_chromosome {
+        return Err(format!(
+            "Unknown gene {}; search a range such as 17:43044295-43125483",
+            query.trim()
+        ));
+    }
+    Ok((None, region))
+}
+
+/// ClinVar and gnomAD annotations of the variants in a region
+fn annotate_region(
+    genome: &LoadedGenome,
+    gene: Option<String>,
+    region: Region,
+    build: Option<GenomeBuild>,
+    databases: &DatabaseSnapshot,
+    report_late_onset: bool,
+    consent: &ConsentPolicy,
+) -> Result<RegionQueryResult, String> {
+    let gnomad = databases.gnomad.as_deref();
+    let mut late_onset_withheld = 0;
+    let mut matches = match &databases.clinvar {
+        Some(clinvar) => clinvar.annotate(genome, |_| Ok(()))?,
+        None => Vec::new(),
+    };
+    let clinvar_release = databases.clinvar.as_ref().and_then(|db| db.release_date());
+    let consent_withheld = consent.retain_matches(&mut matches);
+    if !report_late_onset {
+        let before = matches.len();
+        matches
+            .retain(|found| !apoe::is_apoe_site(found.record.rsid.as_deref(), &found.record.genes));
+        late_onset_withheld = before - matches.len();
+    }
+    let counts = zygosity::pathogenic_counts(&matches);
+    // Grouped once, since a whole chromosome holds millions of variants
+    let mut matches_of: HashMap<*const Variant, Vec<_>> = HashMap::new();
+    for found in &matches {
+        matches_of
+            .entry(found.variant as *const Variant)
+            .or_default()
+            .push(found);
+    }
+
+    let variants = genome
+        .variants()
+        .iter()
+        .map(|variant| {
+            let clinical_findings = matches_of
+                .get(&(variant as *const Variant))
+                .into_iter()
+                .flatten()
+                .map(|found| {
+                    let frequency = gnomad.and_then(|gnomad| {
+                        gnomad.frequency(
+                            variant,
+                            build,
+                            &found.record.reference,
+                            &found.record.alternate,
+                        )
+                    });
+                    ClinicalFinding::from_match(
+                        found,
+                        zygosity::interpret(found, &counts),
+                        frequency,
+                        clinvar_release,
+                    )
+                })
+                .collect();
+            let allele_frequency =
+                gnomad
+                    .zip(variant.reference.as_deref())
+                    .and_then(|(gnomad, reference)| {
+                        let alleles = variant.genotype.alleles();
+                        let alternate = alleles.iter().find(|allele| **allele != reference)?;
+                        gnomad
+                            .frequency(variant, build, reference, alternate)
+                            .cloned()
+                    });
+            RegionVariant {
+                variant: variant.clone(),
+                clinical_findings,
+                allele_frequency,
+            }
+        })
+        .collect();
+
+    Ok(RegionQueryResult {
+        gene,
+        region,
+        genome_build: build,
+        variants,
+        late_onset_withheld,
+        consent_withheld,
+    })
+}
+
+fn analyze_genome(
+    genome: &LoadedGenome,
+    databases: &DatabaseSnapshot,
+    options: &AnalysisOptions,
+    cancel: &CancelFlag,
+) -> Result<AnalysisResultData, String> {
+    let uploaded = genome;
+    // Leave out imputed calls too uncertain to report on, and sequencing
+    // calls that may be artifacts, before anything reads them
+    let mut imputation = None;
+    let filtered;
+    let genome = if imputation::has_imputation_fields(genome) {
+        let stats;
+        (filtered, stats) =
+            imputation::filter_genome(genome, &options.imputation, |_| tasks::checkpoint(cancel))?;
+        imputation = Some(stats);
+        &filtered
+    } else {
+        genome
+    };
+    let mut call_quality = None;
+    let quality_filtered;
+    let genome = if quality::has_quality_fields(genome) {
+        let stats;
+        (quality_filtered, stats) =
+            quality::filter_genome(genome, &options.quality, |_| tasks::checkpoint(cancel))?;
+        call_quality = Some(stats);
+        &quality_filtered
+    } else {
+        genome
+    };
+    let mut probe_mask = None;
+    let masked;
+    let genome = match (&databases.probe_mask, probes::infer_chip(genome)) {
+        (Some(mask), Some(chip)) => {
+            let stats;
+            (masked, stats) = mask.apply(genome, &chip, |_| tasks::checkpoint(cancel))?;
+            probe_mask = Some(stats);
+            &masked
+        }
+        _ => genome,
+    };
+    // X-linked findings take a sex for granted only when nothing about the
+    // sex chromosomes needs checking
+    let sex = sex::check(genome, options.declared_sex);
+    let mut hemizygous_calls = 0;
+    let hemizygous;
+    let genome = if sex.is_consistent() && sex.inferred == Some(Sex::Male) {
+        (hemizygous, hemizygous_calls) = sex::to_hemizygous(genome);
+        &hemizygous
+    } else {
+        genome
+    };
+
+    // The trees carry positions on both builds, and lifting chrM would move
+    // the rCRS positions arrays report, so haplogroups use the genome as
+    // uploaded
+    let consent = &options.consent;
+    let haplogroups = databases
+        .haplogroups
+        .as_ref()
+        .filter(|_| consent.allows(FindingCategory::Paternity))
+        .map(|trees| trees.report(genome));
+
+    // Bring GRCh37 genomes onto the build the databases are published on
+    let genome_build = liftover::detect_build(genome);
+    // Watched sites are looked up in the data as uploaded, so a call left
+    // out by a filter is still reported rather than taken as missing
+    let watchlist = options.watchlist.check(uploaded, genome_build);
+    let mut liftover_stats = None;
+    let lifted;
+    let genome = match (&databases.liftover, genome_build) {
+        (Some(chain), Some(build)) if build == chain.from_build() => {
+            let stats;
+            (lifted, stats) = chain.lift_genome(genome, |_| tasks::checkpoint(cancel))?;
+            liftover_stats = Some(stats);
+            &lifted
+        }
+        _ => genome,
+    };
+
+    // Only the genome as uploaded holds the structural variants; they are
+    // lifted with it, and matched when the dosage map is on the same build
+    let structural_variants: Vec<StructuralVariant> = match (&databases.liftover, liftover_stats) {
+        (Some(chain), Some(_)) => uploaded
+            .structural_variants()
+            .iter()
+            .filter_map(|variant| chain.lift_structural(variant).ok())
+            .collect(),
+        _ => uploaded.structural_variants().to_vec(),
+    };
+    let lifted_build = liftover_stats.map_or(genome_build, |stats| Some(stats.to));
+    let mut structural_findings: Vec<StructuralFinding> = databases
+        .clingen
+        .as_ref()
+        .filter(|map| {
+            map.build()
+                .zip(lifted_build)
+                .is_none_or(|(map, genome)| map == genome)
+        })
+        .map(|map| {
+            map.annotate(&structural_variants)
+                .iter()
+                .map(StructuralFinding::from_match)
+                .collect()
+        })
+        .unwrap_or_default();
+    sort_by_confidence(&mut structural_findings, |finding| finding.confidence);
+    stream_section(options, FindingSection::Structural, &structural_findings);
+
+    // Write VCF records the way ClinVar and gnomAD do, so an indel matches
+    // however the variant caller placed it
+    let mut variant_normalization = None;
+    let aligned;
+    let genome = if genome.variants().iter().any(|v| v.reference.is_some()) {
+        let fasta = match (&options.reference_fasta, &options.references, lifted_build) {
+            (Some(path), _, _) => Some(IndexedFasta::open(Path::new(path))?),
+            (None, Some(references), Some(build)) => references.open(build)?,
+            _ => None,
+        };
+        let reference = fasta
+            .as_ref()
+            .map(|f| f as &dyn normalize::ReferenceSequence);
+        let stats;
+        (aligned, stats) =
+            normalize::normalize_genome(genome, reference, |_| tasks::checkpoint(cancel))?;
+        variant_normalization = Some(stats);
+        &aligned
+    } else {
+        genome
+    };
+
+    // Fill in rsids for VCF sites and alleles for array calls so every
+    // database can match the genome by the key it indexes on
+    let mut normalization = Normalization::default();
+    let normalized;
+    let genome = match &databases.dbsnp {
+        Some(dbsnp) => {
+            (normalized, normalization) = dbsnp.normalize(genome, |_| tasks::checkpoint(cancel))?;
+            &normalized
+        }
+        None => genome,
+    };
+
+    let gnomad = databases.gnomad.as_deref();
+    let build = genome.file.genome_build;
+    let threads = options.threads.unwrap_or_else(num_cpus).max(1);
+    let mut annotation_time = Duration::ZERO;
+
+    let mut clinical_findings = Vec::new();
+    let mut acmg_findings = Vec::new();
+    let mut carrier_findings = Vec::new();
+    let mut common_variants_suppressed = 0;
+    let mut secondary_findings_withheld = 0;
+    let mut late_onset_withheld = 0;
+    let mut consent_withheld = 0;
+    if let Some(clinvar) = &databases.clinvar {
+        let started = Instant::now();
+        let mut matches =
+            clinvar.annotate_parallel(genome, threads, |_| tasks::checkpoint(cancel))?;
+        annotation_time += started.elapsed();
+        // Before anything reads them, so no finding is built from them
+        consent_withheld = consent.retain_matches(&mut matches);
+        if !options.report_late_onset {
+            let before = matches.len();
+            matches.retain(|found| {
+                !apoe::is_apoe_site(found.record.rsid.as_deref(), &found.record.genes)
+            });
+            late_onset_withheld += before - matches.len();
+        }
+        // Counted over every match so that two hits in one recessive gene
+        // are read together, whichever section they end up in
+        let counts = zygosity::pathogenic_counts(&matches);
+        let to_finding = |found: &ClinVarMatch<'_>| {
+            let frequency = gnomad.and_then(|gnomad| {
+                gnomad.frequency(
+                    found.variant,
+                    build,
+                    &found.record.reference,
+                    &found.record.alternate,
+                )
+            });
+            ClinicalFinding::from_match(
+                found,
+                zygosity::interpret(found, &counts),
+                frequency,
+                clinvar.release_date(),
+            )
+        };
+
+        // Pathogenic variants in ACMG genes only appear in their own section
+        let (secondary, matches): (Vec<ClinVarMatch<'_>>, Vec<ClinVarMatch<'_>>) = matches
+            .into_iter()
+            .partition(|found| acmg::secondary_gene(found.record).is_some());
+        if options.screen_secondary_findings {
+            acmg_findings = acmg::screen(&secondary)
+                .iter()
+                .map(|finding| {
+                    let variants = finding.matches.iter().map(to_finding).collect();
+                    AcmgFinding::from_finding(finding, variants)
+                })
+                .collect();
+        }
+        let reported: usize = acmg_findings.iter().map(|f| f.variants.len()).sum();
+        secondary_findings_withheld = secondary.len() - reported;
+
+        // Pathogenic variants in recessive genes are reported with their
+        // carrier status rather than as clinical findings
+        let (carried, matches): (Vec<ClinVarMatch<'_>>, Vec<ClinVarMatch<'_>>) = matches
+            .into_iter()
+            .partition(|found| carrier::carrier_gene(found).is_some());
+        carrier_findings = carrier::screen(&carried)
+            .iter()
+            .map(|result| {
+                let variants = result.matches.iter().map(to_finding).collect();
+                CarrierFinding::from_result(result, variants)
+            })
+            .collect();
+
+        // Benign classifications are expected in every genome and not reported
+        clinical_findings = matches
+            .iter()
+            .filter(|found| !found.record.significance.is_benign())
+            .map(to_finding)
+            .collect();
+        if let Some(max) = options.max_allele_frequency {
+            let before = clinical_findings.len();
+            clinical_findings.retain(|finding| {
+                finding
+                    .allele_frequency
+                    .as_ref()
+                    .is_none_or(|frequency| frequency.max_frequency() <= max)
+            });
+            common_variants_suppressed = before - clinical_findings.len();
+        }
+        sort_clinical_findings(&mut clinical_findings);
+        sort_by_confidence(&mut acmg_findings, |finding| finding.confidence);
+        sort_by_confidence(&mut carrier_findings, |finding| finding.confidence);
+    }
+    stream_section(options, FindingSection::Clinical, &clinical_findings);
+    stream_section(options, FindingSection::SecondaryFindings, &acmg_findings);
+    stream_section(options, FindingSection::Carrier, &carrier_findings);
+
+    let mut diplotypes = Vec::new();
+    let mut drug_responses = Vec::new();
+    let pharmacogenomics = consent.allows(FindingCategory::Pharmacogenomics);
+    if let Some(cpic) = databases.cpic.as_ref().filter(|_| pharmacogenomics) {
+        diplotypes = cpic.call_diplotypes(genome, |_| tasks::checkpoint(cancel))?;
+        for call in &diplotypes {
+            for recommendation in cpic.recommendations_with_bundled(&call.gene, &call.phenotype) {
+                drug_responses.push(DrugResponse::from_recommendation(call, &recommendation));
+            }
+        }
+    }
+    if let Some(pharmgkb) = databases.pharmgkb.as_ref().filter(|_| pharmacogenomics) {
+        let started = Instant::now();
+        let matches = pharmgkb.annotate_parallel(genome, threads, |_| tasks::checkpoint(cancel))?;
+        annotation_time += started.elapsed();
+        // A single-variant annotation is superseded by a diplotype-based
+        // recommendation for the same gene and drug
+        let guided: Vec<(String, String)> = drug_responses
+            .iter()
+            .map(|response| (response.gene.clone(), response.drug.to_ascii_lowercase()))
+            .collect();
+        drug_responses.extend(
+            matches
+                .iter()
+                .map(|found| DrugResponse::from_match(found, &diplotypes))
+                .filter(|response| {
+                    !guided.iter().any(|(gene, drug)| {
+                        response.gene.split(", ").any(|g| g == gene)
+                            && response.drug.to_ascii_lowercase().contains(drug.as_str())
+                    })
+                }),
+        );
+    }
+    sort_by_confidence(&mut diplotypes, |call| call.confidence);
+    drug_responses.sort_by_key(|response| response.evidence_level);
+    sort_by_confidence(&mut drug_responses, |response| response.confidence);
+    stream_section(options, FindingSection::Diplotype, &diplotypes);
+    stream_section(options, FindingSection::DrugResponse, &drug_responses);
+
+    let mut hla_risks = if pharmacogenomics {
+        hla::call(genome)
+    } else {
+        Vec::new()
+    };
+    sort_by_confidence(&mut hla_risks, |call| call.confidence);
+    hla_risks.sort_by_key(|call| !call.is_carrier());
+    stream_section(options, FindingSection::HlaRisk, &hla_risks);
+
+    let mut trait_associations = Vec::new();
+    let traits = consent.allows(FindingCategory::Traits);
+    if let Some(gwas) = databases.gwas.as_ref().filter(|_| traits) {
+        let started = Instant::now();
+        let mut matches =
+            gwas.annotate_parallel(genome, GENOME_WIDE_SIGNIFICANCE, threads, |_| {
+                tasks::checkpoint(cancel)
+            })?;
+        if let Some(gnomad) = gnomad {
+            gwas::settle_strands(&mut matches, gnomad);
+        }
+        annotation_time += started.elapsed();
+        trait_associations = matches
+            .iter()
+            .filter(|found| consent.allows_association(found.association))
+            .map(|found| TraitAssociation::from_match(found, gnomad))
+            .collect();
+        if !options.report_late_onset {
+            let before = trait_associations.len();
+            trait_associations.retain(|association| {
+                !apoe::is_apoe_site(Some(&association.rsid), &association.genes)
+                    && !apoe::is_late_onset_trait(&association.trait_name)
+            });
+            late_onset_withheld += before - trait_associations.len();
+        }
+    }
+    if traits {
+        let panel = fitness::catalog();
+        let mut matches = panel.annotate(genome, 1.0, |_| tasks::checkpoint(cancel))?;
+        if let Some(gnomad) = gnomad {
+            gwas::settle_strands(&mut matches, gnomad);
+        }
+        trait_associations.extend(
+            matches
+                .iter()
+                .filter(|found| consent.allows_association(found.association))
+                .map(|found| TraitAssociation::from_match(found, gnomad)),
+        );
+    }
+
+    sort_by_confidence(&mut trait_associations, |association| {
+        association.confidence
+    });
+    stream_section(options, FindingSection::Trait, &trait_associations);
+    let mut nutrition = if consent.allows(FindingCategory::Nutrigenomics) {
+        nutrigenomics::call(genome)
+    } else {
+        Vec::new()
+    };
+    sort_by_confidence(&mut nutrition, |finding| finding.confidence);
+    stream_section(options, FindingSection::Nutrition, &nutrition);
+
+    // A plugin that fails is reported, not allowed to fail the analysis
+    let mut plugin_findings = Vec::new();
+    let mut plugin_runs = Vec::new();
+    for analysis in &options.plugins {
+        tasks::checkpoint(cancel)?;
+        let manifest = analysis.manifest();
+        let (findings, error) = match plugin::run(analysis.as_ref(), genome, consent) {
+            Ok(findings) => (findings, None),
+            Err(error) => {
+                tracing::warn!(plugin = %manifest.id, %error, "plugin failed");
+                (Vec::new(), Some(error))
+            }
+        };
+        plugin_runs.push(PluginRun {
+            id: manifest.id.clone(),
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            findings: findings.len(),
+            error,
+        });
+        plugin_findings.extend(findings);
+    }
+    sort_by_confidence(&mut plugin_findings, |finding| finding.confidence);
+    stream_section(options, FindingSection::Plugin, &plugin_findings);
+
+    let mut custom_findings = databases
+        .custom
+        .as_ref()
+        .map(|table| table.annotate(genome, consent))
+        .unwrap_or_default();
+    if !options.report_late_onset {
+        let before = custom_findings.len();
+        custom_findings.retain(|finding| {
+            let genes: Vec<String> = finding.gene.iter().cloned().collect();
+            !apoe::is_apoe_site(Some(&finding.rsid), &genes)
+                && !apoe::is_late_onset_trait(&finding.interpretation)
+        });
+        late_onset_withheld += before - custom_findings.len();
+    }
+    sort_by_confidence(&mut custom_findings, |finding| finding.confidence);
+    stream_section(options, FindingSection::Custom, &custom_findings);
+
+    let neurodegenerative = consent.allows(FindingCategory::Neurodegenerative);
+    let apoe = neurodegenerative
+        .then(|| apoe::call(genome))
+        .flatten()
+        .and_then(|call| {
+            if options.report_late_onset {
+                Some(ApoeFinding::from_call(call))
+            } else {
+                late_onset_withheld += 1;
+                None
+            }
+        });
+
+    let analyzed_variants = genome.summary.variant_count - genome.summary.no_call_count;
+    let strands: Vec<Strand> = clinical_findings
+        .iter()
+        .map(|finding| finding.strand)
+        .chain(drug_responses.iter().map(|response| response.strand))
+        .chain(
+            trait_associations
+                .iter()
+                .map(|association| association.strand),
+        )
+        .collect();
+    let actionable_findings = clinical_findings
+        .iter()
+        .filter(|finding| {
+            finding.significance.is_pathogenic()
+                && finding.interpretation != Some(Interpretation::Carrier)
+        })
+        .count()
+        + drug_responses
+            .iter()
+            .filter(|response| response.is_actionable())
+            .count()
+        + acmg_findings.len()
+        + carrier_findings
+            .iter()
+            .filter(|finding| finding.affected)
+            .count()
+        + hla_risks.iter().filter(|call| call.is_carrier()).count()
+        + structural_findings
+            .iter()
+            .filter(|finding| finding.is_actionable())
+            .count()
+        + plugin_findings
+            .iter()
+            .filter(|finding| finding.significance.is_pathogenic())
+            .count()
+        + custom_findings
+            .iter()
+            .filter(|finding| finding.significance.is_pathogenic())
+            .count();
+
+    Ok(AnalysisResultData {
+        summary: AnalysisSummary {
+            total_variants: genome.summary.variant_count,
+            analyzed_variants,
+            clinical_count: clinical_findings.len(),
+            drug_count: drug_responses.len(),
+            trait_count: trait_associations.len(),
+            actionable_findings,
+            rsids_resolved: normalization.rsids_added,
+            alleles_resolved: normalization.alleles_added,
+            common_variants_suppressed,
+            secondary_findings_withheld,
+            late_onset_withheld,
+            excluded_categories: consent.excluded.clone(),
+            consent_withheld,
+            genome_build,
+            liftover: liftover_stats,
+            variant_normalization,
+            imputation,
+            quality: call_quality,
+            probe_mask,
+            structural_variants: uploaded.structural_variants().len(),
+            sex: Some(sex),
+            hemizygous_calls,
+            strand_flipped: strands.iter().filter(|strand| strand.is_flipped()).count(),
+            strand_ambiguous: strands
+                .iter()
+                .filter(|strand| strand.is_ambiguous())
+                .count(),
+            annotation_threads: threads,
+            annotation_seconds: annotation_time.as_secs_f64(),
+            clinvar_release: databases
+                .clinvar
+                .as_ref()
+                .and_then(|db| db.release_date())
+                .map(str::to_string),
+            plugins: plugin_runs,
+            databases: database_status(databases.clone(), &options.installed).releases(),
+        },
+        clinical_findings,
+        acmg_findings,
+        carrier_findings,
+        apoe,
+        drug_responses,
+        diplotypes,
+        trait_associations,
+        haplogroups,
+        hla_risks,
+        nutrition,
+        structural_findings,
+        plugin_findings,
+        custom_findings,
+        watchlist,
+        notes: Vec::new(),
+    })
+}
+
+/// Send a complete section to the frontend, when the analysis is streamed
+fn stream_section<T: Serialize>(
+    options: &AnalysisOptions,
+    section: FindingSection,
+    findings: &[T],
+) {
+    if let Some(stream) = &options.stream {
+        stream.send(section, findings);
+    }
+}
+
+/// Most serious classification first, most confident first within one
+pub(crate) fn sort_clinical_findings(findings: &mut [ClinicalFinding]) {
+    findings.sort_by(|a, b| {
+        a.significance
+            .cmp(&b.significance)
+            .then(b.confidence.score.total_cmp(&a.confidence.score))
+            .then(b.review_stars.cmp(&a.review_stars))
+    });
+}
+
+/// Most confident first, keeping the order of equally confident findings
+fn sort_by_confidence<T>(findings: &mut [T], confidence: impl Fn(&T) -> Confidence) {
+    findings.sort_by(|a, b| confidence(b).score.total_cmp(&confidence(a).score));
+}
+
+/// The confidence of the most confident of a gene's variants
+fn most_confident(variants: &[ClinicalFinding]) -> Confidence {
+    variants
+        .iter()
+        .map(|variant| variant.confidence)
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .unwrap_or_default()
 }
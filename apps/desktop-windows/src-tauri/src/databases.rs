@@ -0,0 +1,78 @@
+//! Locating and loading the offline annotation databases
+//!
+//! Releases live in `<app data>/databases`, or in the directory the
+//! settings name; the settings can also point at a file to load for one
+//! database. They are loaded on a background thread at startup so the
+//! window opens immediately; analyses started before loading finishes
+//! simply run without that database. A release `verify_databases` found
+//! damaged is not loaded again until it is replaced.
+
+use crate::{settings, AppState};
+use genomeforge_core::annotation::manager::{
+    self, DatabaseKind, InstalledReleases, LoadedDatabase,
+};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Directory holding the database releases, unless the settings name
+/// another
+pub fn database_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    if let Some(dir) = settings::current(app).database_dir {
+        return Ok(dir);
+    }
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("databases"))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Directory holding the reference FASTA files
+pub fn reference_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("references"))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Load every installed database into the application state
+///
+/// A database that fails to load is skipped; the errors are returned so
+/// the others still become available.
+pub fn load_installed<R: Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    let dir = match database_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => return vec![e],
+    };
+    let state = app.state::<AppState>();
+    let mut paths = settings::current(app).database_paths;
+    let mut errors = Vec::new();
+    let installed = InstalledReleases::read(&dir).unwrap_or_else(|e| {
+        errors.push(e);
+        InstalledReleases::default()
+    });
+
+    for kind in DatabaseKind::ALL {
+        let configured = paths.remove(&kind);
+        let damaged = installed
+            .get(kind)
+            .and_then(|record| record.integrity.as_ref())
+            .filter(|check| check.is_damaged());
+        if let (None, Some(check)) = (&configured, damaged) {
+            errors.push(format!(
+                "Not loading {}: {}",
+                kind.as_str(),
+                check.problem.as_deref().unwrap_or("the release is damaged")
+            ));
+            continue;
+        }
+        let Some(path) = configured.or_else(|| manager::find_installed(&dir, kind)) else {
+            continue;
+        };
+        match LoadedDatabase::load(kind, &path) {
+            Ok(database) => state.databases.install(database),
+            Err(e) => errors.push(format!("Failed to load {}: {}", path.display(), e)),
+        }
+    }
+
+    errors
+}
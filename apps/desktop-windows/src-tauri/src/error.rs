@@ -0,0 +1,143 @@
+//! Errors returned to the frontend by commands
+//!
+//! Each error serializes as `{ "code": ..., "message": ... }`, with the
+//! `line` of a parse error when it is known, so the frontend can branch on
+//! the code and show the message.
+
+use genomeforge_core::tasks;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt;
+
+/// How core errors from the file system begin
+const IO_PREFIXES: [&str; 8] = [
+    "Failed to read",
+    "Failed to open",
+    "Failed to write",
+    "Failed to create",
+    "Failed to copy",
+    "Failed to move",
+    "Failed to remove",
+    "Failed to delete",
+];
+
+/// Why a command failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenomeForgeError {
+    /// A file could not be read or written
+    Io(String),
+    /// A file was recognized but its contents are malformed
+    Parse { line: Option<usize>, reason: String },
+    /// A file is not in a format GenomeForge reads
+    UnsupportedFormat(String),
+    /// A path given to the command does not exist
+    FileNotFound(Option<String>),
+    /// A database the command needs is not available
+    DatabaseMissing(String),
+    /// The command needs a loaded genome and none is
+    NoGenome,
+    /// The command needs analysis results and there are none
+    NoResults,
+    /// An argument is out of range or inconsistent with the others
+    InvalidInput(String),
+    /// The task was stopped with `cancel_task`
+    Cancelled,
+    /// Any other failure
+    Failed(String),
+}
+
+impl GenomeForgeError {
+    /// Stable code the frontend branches on
+    pub fn code(&self) -> &'static str {
+        match self {
+            GenomeForgeError::Io(_) => "io_error",
+            GenomeForgeError::Parse { .. } => "parse_error",
+            GenomeForgeError::UnsupportedFormat(_) => "unsupported_format",
+            GenomeForgeError::FileNotFound(_) => "file_not_found",
+            GenomeForgeError::DatabaseMissing(_) => "database_missing",
+            GenomeForgeError::NoGenome => "no_genome",
+            GenomeForgeError::NoResults => "no_results",
+            GenomeForgeError::InvalidInput(_) => "invalid_input",
+            GenomeForgeError::Cancelled => "cancelled",
+            GenomeForgeError::Failed(_) => "failed",
+        }
+    }
+
+    /// An [`GenomeForgeError::InvalidInput`] with the given reason
+    pub fn invalid(reason: impl Into<String>) -> Self {
+        GenomeForgeError::InvalidInput(reason.into())
+    }
+}
+
+impl fmt::Display for GenomeForgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeForgeError::Parse {
+                line: Some(line),
+                reason,
+            } => write!(f, "line {}: {}", line, reason),
+            GenomeForgeError::FileNotFound(Some(path)) => write!(f, "File not found: {}", path),
+            GenomeForgeError::FileNotFound(None) => f.write_str("File not found"),
+            GenomeForgeError::NoGenome => f.write_str("No genome loaded"),
+            GenomeForgeError::NoResults => f.write_str("No analysis results"),
+            GenomeForgeError::Cancelled => f.write_str(tasks::CANCELLED),
+            GenomeForgeError::Io(message)
+            | GenomeForgeError::Parse {
+                line: None,
+                reason: message,
+            }
+            | GenomeForgeError::UnsupportedFormat(message)
+            | GenomeForgeError::DatabaseMissing(message)
+            | GenomeForgeError::InvalidInput(message)
+            | GenomeForgeError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for GenomeForgeError {}
+
+impl Serialize for GenomeForgeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let line = match self {
+            GenomeForgeError::Parse { line, .. } => *line,
+            _ => None,
+        };
+        let mut error = serializer.serialize_struct("GenomeForgeError", 3)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        match line {
+            Some(line) => error.serialize_field("line", &line)?,
+            None => error.skip_field("line")?,
+        }
+        error.end()
+    }
+}
+
+/// Classify an error from the core library, which reports errors as strings
+impl From<String> for GenomeForgeError {
+    fn from(message: String) -> Self {
+        if message == tasks::CANCELLED {
+            return GenomeForgeError::Cancelled;
+        }
+        if message.starts_with("Unsupported ") {
+            return GenomeForgeError::UnsupportedFormat(message);
+        }
+        if let Some((line, reason)) = parse_location(&message) {
+            return GenomeForgeError::Parse {
+                line: Some(line),
+                reason: reason.to_string(),
+            };
+        }
+        if IO_PREFIXES.iter().any(|prefix| message.starts_with(prefix)) {
+            return GenomeForgeError::Io(message);
+        }
+        GenomeForgeError::Failed(message)
+    }
+}
+
+// Helper functions
+
+/// Line number and reason of a `line N: reason` parser error
+fn parse_location(message: &str) -> Option<(usize, &str)> {
+    let (line, reason) = message.strip_prefix("line ")?.split_once(": ")?;
+    Some((line.parse().ok()?, reason))
+}
@@ -0,0 +1,685 @@
+//! Events the backend emits to the frontend
+//!
+//! Every event is an [`Event`] here, which names it and types its
+//! payload, so no event can go out with the payload of another. Names are
+//! stable. A payload that changes in a way older listeners would misread
+//! gets a new version, which goes out in its `version` field.
+//!
+//! Each payload carries its TypeScript declaration, and the frontend's
+//! `src/lib/events.ts` is generated from them. The tests fail when that
+//! file is out of date, or when a declaration lists other fields than its
+//! payload serializes; `GENOMEFORGE_UPDATE_BINDINGS=1 cargo test`
+//! rewrites the file.
+
+use crate::commands::AnalysisOverview;
+use crate::error::GenomeForgeError;
+use crate::intake::ParsePlan;
+use crate::results::FindingSection;
+use genomeforge_core::annotation::manager::DatabaseKind;
+use genomeforge_core::parser::progress::ParseProgress;
+use genomeforge_core::tasks::{TaskId, TaskKind};
+use genomeforge_core::GenomeBuild;
+use serde::Serialize;
+use std::marker::PhantomData;
+use tauri::{AppHandle, Emitter, Runtime};
+
+/// Emitted while a genome file is being parsed
+pub const PARSE_PROGRESS: Event<ParseProgressEvent> = Event::new("parse-progress", 1);
+
+/// Emitted before parsing a genome file that may not fit in memory
+pub const PARSE_WARNING: Event<ParseWarning> = Event::new("parse-warning", 1);
+
+/// Emitted when a background task is registered
+pub const TASK_STARTED: Event<TaskStarted> = Event::new("task-started", 1);
+
+/// Emitted when an analysis started by `start_analysis` finishes
+pub const ANALYSIS_COMPLETE: Event<AnalysisComplete> = Event::new("analysis-complete", 1);
+
+/// Emitted when an analysis started by `start_analysis` fails or is
+/// cancelled
+pub const ANALYSIS_FAILED: Event<AnalysisFailed> = Event::new("analysis-failed", 1);
+
+/// Emitted with each batch of findings of an analysis
+pub const FINDINGS: Event<FindingsBatch> = Event::new("analysis-findings", 1);
+
+/// Emitted after the last batch of findings of an analysis
+pub const FINDINGS_COMPLETE: Event<FindingsComplete> = Event::new("analysis-findings-complete", 1);
+
+/// Emitted as each file of a `batch_analyze` batch moves on
+pub const BATCH_PROGRESS: Event<BatchProgress> = Event::new("batch-progress", 1);
+
+/// Emitted while a database release is being downloaded
+pub const DOWNLOAD_PROGRESS: Event<DownloadProgress> = Event::new("database-download-progress", 1);
+
+/// Emitted while a reference FASTA is being downloaded
+pub const REFERENCE_DOWNLOAD_PROGRESS: Event<ReferenceDownloadProgress> =
+    Event::new("reference-download-progress", 1);
+
+/// Emitted for a dropped file that can be parsed
+pub const FILE_READY: Event<ParsePlan> = Event::new("file-ready", 1);
+
+/// Emitted for a dropped file that cannot be parsed
+pub const FILE_REJECTED: Event<FileRejected> = Event::new("file-rejected", 1);
+
+/// Emitted for a new file in the watch folder that can be parsed
+pub const IMPORT_READY: Event<ParsePlan> = Event::new("import-ready", 1);
+
+/// Emitted for a new file in the watch folder that cannot be parsed
+pub const IMPORT_REJECTED: Event<FileRejected> = Event::new("import-rejected", 1);
+
+/// Emitted to tell the frontend to open the results view
+pub const SHOW_RESULTS: Event<()> = Event::new("show-results", 1);
+
+/// A payload with the version of its event, which goes out beside its
+/// fields
+#[derive(Clone, Serialize)]
+struct Versioned<P> {
+    version: u32,
+    #[serde(flatten)]
+    payload: P,
+}
+
+/// An event, with the type of its payload
+#[derive(Debug)]
+pub struct Event<P> {
+    pub name: &'static str,
+    pub version: u32,
+    payload: PhantomData<fn(P)>,
+}
+
+impl<P: Payload> Event<P> {
+    const fn new(name: &'static str, version: u32) -> Self {
+        Event {
+            name,
+            version,
+            payload: PhantomData,
+        }
+    }
+
+    /// Emit the event to every window
+    pub fn emit<R: Runtime>(&self, app: &AppHandle<R>, payload: P) {
+        let versioned = Versioned {
+            version: self.version,
+            payload,
+        };
+        let _ = app.emit(self.name, versioned);
+    }
+}
+
+/// What an event carries, with how TypeScript sees it
+// The declarations are read by the tests, which generate the bindings
+#[cfg_attr(not(test), allow(dead_code))]
+pub trait Payload: Serialize + Clone {
+    /// Name of the TypeScript type
+    const TYPE: &'static str;
+    /// Declaration of the TypeScript type
+    const TYPESCRIPT: &'static str;
+}
+
+/// Payload of a `task-started` event
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStarted {
+    pub task_id: TaskId,
+    pub kind: TaskKind,
+}
+
+impl Payload for TaskStarted {
+    const TYPE: &'static str = "TaskStarted";
+    const TYPESCRIPT: &'static str = "export interface TaskStarted {
+  task_id: number;
+  kind: TaskKind;
+}";
+}
+
+/// Payload of an `analysis-complete` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisComplete {
+    pub task_id: TaskId,
+    pub overview: AnalysisOverview,
+}
+
+impl Payload for AnalysisComplete {
+    const TYPE: &'static str = "AnalysisComplete";
+    const TYPESCRIPT: &'static str = "export interface AnalysisComplete {
+  task_id: number;
+  /** What `analyze_variants` returns */
+  overview: Record<string, unknown>;
+}";
+}
+
+/// Payload of an `analysis-failed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisFailed {
+    pub task_id: TaskId,
+    pub error: GenomeForgeError,
+}
+
+impl Payload for AnalysisFailed {
+    const TYPE: &'static str = "AnalysisFailed";
+    const TYPESCRIPT: &'static str = "export interface AnalysisFailed {
+  task_id: number;
+  error: CommandError;
+}";
+}
+
+/// A finding, with where `get_findings_page` lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamedFinding {
+    pub section: FindingSection,
+    pub index: usize,
+    pub finding: serde_json::Value,
+}
+
+/// Payload of an `analysis-findings` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FindingsBatch {
+    pub task_id: TaskId,
+    /// Counting from 0, for `ack_findings`
+    pub sequence: u64,
+    pub findings: Vec<StreamedFinding>,
+}
+
+impl Payload for FindingsBatch {
+    const TYPE: &'static str = "FindingsBatch";
+    const TYPESCRIPT: &'static str = "export interface StreamedFinding {
+  section: FindingSection;
+  /** Where `get_findings_page` lists it */
+  index: number;
+  finding: unknown;
+}
+
+export interface FindingsBatch {
+  task_id: number;
+  /** Counting from 0, for `ack_findings` */
+  sequence: number;
+  findings: StreamedFinding[];
+}";
+}
+
+/// Payload of an `analysis-findings-complete` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FindingsComplete {
+    pub task_id: TaskId,
+    pub batches: u64,
+    pub findings: usize,
+    /// Why the analysis stopped short, leaving its findings incomplete
+    pub error: Option<GenomeForgeError>,
+}
+
+impl Payload for FindingsComplete {
+    const TYPE: &'static str = "FindingsComplete";
+    const TYPESCRIPT: &'static str = "export interface FindingsComplete {
+  task_id: number;
+  batches: number;
+  findings: number;
+  /** Why the analysis stopped short, leaving its findings incomplete */
+  error: CommandError | null;
+}";
+}
+
+/// Stage a file of a batch has reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStage {
+    Parsing,
+    Analyzing,
+    Exporting,
+    Done,
+    Failed,
+}
+
+/// Payload of a `batch-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub task_id: TaskId,
+    /// Position of the file in the batch
+    pub index: usize,
+    pub file_path: String,
+    pub stage: BatchStage,
+    /// Files of the batch done or failed so far
+    pub completed: usize,
+    pub total: usize,
+    /// Why the file failed
+    pub error: Option<String>,
+}
+
+impl Payload for BatchProgress {
+    const TYPE: &'static str = "BatchProgress";
+    const TYPESCRIPT: &'static str = "export interface BatchProgress {
+  task_id: number;
+  /** Position of the file in the batch */
+  index: number;
+  file_path: string;
+  stage: BatchStage;
+  /** Files of the batch done or failed so far */
+  completed: number;
+  total: number;
+  /** Why the file failed */
+  error: string | null;
+}";
+}
+
+/// Payload of a `parse-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseProgressEvent {
+    pub task_id: TaskId,
+    #[serde(flatten)]
+    pub progress: ParseProgress,
+}
+
+impl Payload for ParseProgressEvent {
+    const TYPE: &'static str = "ParseProgress";
+    const TYPESCRIPT: &'static str = "export interface ParseProgress {
+  task_id: number;
+  bytes_read: number;
+  /** Size of the file on disk, compressed or not */
+  total_bytes: number;
+  records_parsed: number;
+  /** Fraction of the file read (0.0 - 1.0) */
+  fraction: number;
+  elapsed_seconds: number;
+  /** Estimated seconds remaining, once enough of the file has been read */
+  eta_seconds: number | null;
+}";
+}
+
+/// Payload of a `parse-warning` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseWarning {
+    pub task_id: TaskId,
+    pub message: String,
+}
+
+impl Payload for ParseWarning {
+    const TYPE: &'static str = "ParseWarning";
+    const TYPESCRIPT: &'static str = "export interface ParseWarning {
+  task_id: number;
+  message: string;
+}";
+}
+
+/// Payload of a `database-download-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub task_id: TaskId,
+    pub database: DatabaseKind,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+    /// Attempt at the download, counting from 1
+    pub attempt: u32,
+}
+
+impl Payload for DownloadProgress {
+    const TYPE: &'static str = "DownloadProgress";
+    const TYPESCRIPT: &'static str = "export interface DownloadProgress {
+  task_id: number;
+  database: DatabaseKind;
+  bytes_downloaded: number;
+  total_bytes: number | null;
+  /** Attempt at the download, counting from 1 */
+  attempt: number;
+}";
+}
+
+/// Payload of a `reference-download-progress` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferenceDownloadProgress {
+    pub task_id: TaskId,
+    pub build: GenomeBuild,
+    pub bytes_downloaded: u64,
+    pub total_bytes: Option<u64>,
+    /// Attempt at the download, counting from 1
+    pub attempt: u32,
+}
+
+impl Payload for ReferenceDownloadProgress {
+    const TYPE: &'static str = "ReferenceDownloadProgress";
+    const TYPESCRIPT: &'static str = "export interface ReferenceDownloadProgress {
+  task_id: number;
+  build: GenomeBuild;
+  bytes_downloaded: number;
+  total_bytes: number | null;
+  /** Attempt at the download, counting from 1 */
+  attempt: number;
+}";
+}
+
+impl Payload for ParsePlan {
+    const TYPE: &'static str = "ParsePlan";
+    const TYPESCRIPT: &'static str = "export interface ParsePlan {
+  path: string;
+  file_type: FileFormat;
+  compression: Compression;
+  /** How sure format detection is, from 0.0 to 1.0 */
+  confidence: number;
+  /** Bytes on disk, every file of a PLINK fileset together */
+  size: number;
+  /** Rough seconds the parse takes; unknown for aligned reads */
+  estimated_seconds: number | null;
+  /** Rough memory the parsed genome needs */
+  memory_needed: number | null;
+  /** Whether only the sites the databases annotate will be kept */
+  sites_only: boolean;
+  /** Samples of a multi-sample file, one of which has to be chosen */
+  samples: string[];
+  warning: string | null;
+}";
+}
+
+/// Payload of a `file-rejected` or `import-rejected` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRejected {
+    pub path: String,
+    pub error: GenomeForgeError,
+}
+
+impl Payload for FileRejected {
+    const TYPE: &'static str = "FileRejected";
+    const TYPESCRIPT: &'static str = "export interface FileRejected {
+  path: string;
+  error: CommandError;
+}";
+}
+
+impl Payload for () {
+    const TYPE: &'static str = "ShowResults";
+    const TYPESCRIPT: &'static str = "export type ShowResults = Record<never, never>;";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use genomeforge_core::parser::compression::Compression;
+    use genomeforge_core::parser::detect::FileFormat;
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+
+    /// The generated bindings, checked in with the frontend
+    const BINDINGS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../src/lib/events.ts");
+
+    #[test]
+    fn bindings_are_up_to_date() {
+        let bindings = typescript();
+        if std::env::var_os("GENOMEFORGE_UPDATE_BINDINGS").is_some() {
+            std::fs::write(BINDINGS_PATH, &bindings).unwrap();
+        }
+        let checked_in = std::fs::read_to_string(BINDINGS_PATH).unwrap_or_default();
+        assert!(
+            checked_in == bindings,
+            "src/lib/events.ts is out of date; run GENOMEFORGE_UPDATE_BINDINGS=1 cargo test"
+        );
+    }
+
+    #[test]
+    fn declarations_list_the_fields_payloads_serialize() {
+        let error = || GenomeForgeError::NoGenome;
+        let summary = serde_json::json!({
+            "total_variants": 0,
+            "analyzed_variants": 0,
+            "clinical_count": 0,
+            "drug_count": 0,
+            "trait_count": 0,
+            "actionable_findings": 0,
+            "rsids_resolved": 0,
+            "alleles_resolved": 0,
+            "common_variants_suppressed": 0,
+            "secondary_findings_withheld": 0,
+            "late_onset_withheld": 0,
+        });
+        let overview = AnalysisOverview {
+            summary: serde_json::from_value(summary).unwrap(),
+            sections: Vec::new(),
+            apoe: None,
+            haplogroups: None,
+            watchlist: Vec::new(),
+        };
+        let finding = StreamedFinding {
+            section: FindingSection::Clinical,
+            index: 0,
+            finding: serde_json::Value::Null,
+        };
+        let plan = ParsePlan {
+            path: String::new(),
+            file_type: FileFormat::Vcf,
+            compression: Compression::None,
+            confidence: 1.0,
+            size: 0,
+            estimated_seconds: None,
+            memory_needed: None,
+            sites_only: false,
+            samples: Vec::new(),
+            warning: None,
+        };
+        check(TaskStarted {
+            task_id: 1,
+            kind: TaskKind::Parse,
+        });
+        check(AnalysisComplete {
+            task_id: 1,
+            overview,
+        });
+        check(AnalysisFailed {
+            task_id: 1,
+            error: error(),
+        });
+        check_declared("StreamedFinding", FindingsBatch::TYPESCRIPT, &finding);
+        check(FindingsBatch {
+            task_id: 1,
+            sequence: 0,
+            findings: vec![finding],
+        });
+        check(FindingsComplete {
+            task_id: 1,
+            batches: 0,
+            findings: 0,
+            error: Some(error()),
+        });
+        check(BatchProgress {
+            task_id: 1,
+            index: 0,
+            file_path: String::new(),
+            stage: BatchStage::Parsing,
+            completed: 0,
+            total: 1,
+            error: None,
+        });
+        check(ParseProgressEvent {
+            task_id: 1,
+            progress: ParseProgress::new(0, 0, 0, Duration::ZERO),
+        });
+        check(ParseWarning {
+            task_id: 1,
+            message: String::new(),
+        });
+        check(DownloadProgress {
+            task_id: 1,
+            database: DatabaseKind::ClinVar,
+            bytes_downloaded: 0,
+            total_bytes: None,
+            attempt: 1,
+        });
+        check(ReferenceDownloadProgress {
+            task_id: 1,
+            build: GenomeBuild::GRCh38,
+            bytes_downloaded: 0,
+            total_bytes: None,
+            attempt: 1,
+        });
+        check(plan);
+        check(FileRejected {
+            path: String::new(),
+            error: error(),
+        });
+        check(());
+    }
+
+    /// What the TypeScript bindings need of an event
+    struct Binding {
+        name: &'static str,
+        version: u32,
+        payload: &'static str,
+        declaration: &'static str,
+    }
+
+    fn binding<P: Payload>(event: &Event<P>) -> Binding {
+        Binding {
+            name: event.name,
+            version: event.version,
+            payload: P::TYPE,
+            declaration: P::TYPESCRIPT,
+        }
+    }
+
+    /// TypeScript declarations of every event and its payload
+    fn typescript() -> String {
+        let events = [
+            binding(&PARSE_PROGRESS),
+            binding(&PARSE_WARNING),
+            binding(&TASK_STARTED),
+            binding(&ANALYSIS_COMPLETE),
+            binding(&ANALYSIS_FAILED),
+            binding(&FINDINGS),
+            binding(&FINDINGS_COMPLETE),
+            binding(&BATCH_PROGRESS),
+            binding(&DOWNLOAD_PROGRESS),
+            binding(&REFERENCE_DOWNLOAD_PROGRESS),
+            binding(&FILE_READY),
+            binding(&FILE_REJECTED),
+            binding(&IMPORT_READY),
+            binding(&IMPORT_REJECTED),
+            binding(&SHOW_RESULTS),
+        ];
+        let unions = [
+            (
+                "TaskKind",
+                union(&[
+                    TaskKind::Parse,
+                    TaskKind::Analysis,
+                    TaskKind::DatabaseUpdate,
+                    TaskKind::ReferenceInstall,
+                    TaskKind::Batch,
+                ]),
+            ),
+            ("FindingSection", union(&FindingSection::ALL)),
+            (
+                "BatchStage",
+                union(&[
+                    BatchStage::Parsing,
+                    BatchStage::Analyzing,
+                    BatchStage::Exporting,
+                    BatchStage::Done,
+                    BatchStage::Failed,
+                ]),
+            ),
+            ("DatabaseKind", union(&DatabaseKind::ALL)),
+            (
+                "GenomeBuild",
+                union(&[
+                    GenomeBuild::GRCh36,
+                    GenomeBuild::GRCh37,
+                    GenomeBuild::GRCh38,
+                ]),
+            ),
+            (
+                "FileFormat",
+                union(&[
+                    FileFormat::Vcf,
+                    FileFormat::TwentyThreeAndMe,
+                    FileFormat::AncestryDna,
+                    FileFormat::MyHeritage,
+                    FileFormat::FamilyTreeDna,
+                    FileFormat::Bam,
+                    FileFormat::Plink,
+                    FileFormat::Unknown,
+                ]),
+            ),
+            (
+                "Compression",
+                union(&[Compression::None, Compression::Gzip, Compression::Bgzip]),
+            ),
+        ];
+
+        let mut out = String::from(
+            "// Generated from src-tauri/src/events.rs by its tests; do not edit.\n\n\
+             import { listen, type EventCallback, type UnlistenFn } from '@tauri-apps/api/event';\n\
+             import type { CommandError } from './errors';\n",
+        );
+        for (name, values) in unions {
+            out.push_str(&format!("\nexport type {} = {};\n", name, values));
+        }
+        let mut declared = Vec::new();
+        for event in &events {
+            if !declared.contains(&event.payload) {
+                declared.push(event.payload);
+                out.push_str(&format!("\n{}\n", event.declaration));
+            }
+        }
+        out.push_str(
+            "\n/** Every payload goes out with the version of its event */\n\
+             export type Versioned<T> = T & { version: number };\n\
+             \n/** Payload of each event, as `listen` receives it */\n\
+             export interface EventPayloads {\n",
+        );
+        for event in &events {
+            out.push_str(&format!(
+                "  '{}': Versioned<{}>;\n",
+                event.name, event.payload
+            ));
+        }
+        out.push_str(
+            "}\n\nexport type EventName = keyof EventPayloads;\n\
+             \n/** Version of the payload each event carries */\n\
+             export const EVENT_VERSIONS: Record<EventName, number> = {\n",
+        );
+        for event in &events {
+            out.push_str(&format!("  '{}': {},\n", event.name, event.version));
+        }
+        out.push_str(
+            "};\n\
+             \n/** Listen for an event, with its payload typed */\n\
+             export function listenTo<E extends EventName>(\n  \
+             event: E,\n  \
+             handler: EventCallback<EventPayloads[E]>,\n\
+             ): Promise<UnlistenFn> {\n  \
+             return listen(event, handler);\n\
+             }\n",
+        );
+        out
+    }
+
+    /// TypeScript union of the serialized forms of `values`
+    fn union<T: Serialize>(values: &[T]) -> String {
+        values
+            .iter()
+            .filter_map(|value| serde_json::to_string(value).ok())
+            .map(|value| value.replace('"', "'"))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    fn check<P: Payload>(payload: P) {
+        check_declared(P::TYPE, P::TYPESCRIPT, &payload);
+    }
+
+    /// Compare the fields `value` serializes with those the interface
+    /// `name` in `typescript` declares
+    fn check_declared<T: Serialize>(name: &str, typescript: &str, value: &T) {
+        let serialized: BTreeSet<String> = match serde_json::to_value(value).unwrap() {
+            serde_json::Value::Object(fields) => fields.into_iter().map(|(key, _)| key).collect(),
+            _ => BTreeSet::new(),
+        };
+        let opening = format!("export interface {} {{\n", name);
+        let declared: BTreeSet<String> = match typescript.split_once(&opening) {
+            Some((_, body)) => body
+                .lines()
+                .take_while(|line| *line != "}")
+                .filter_map(|line| line.strip_prefix("  "))
+                .filter(|line| !line.starts_with(['/', '*', ' ']))
+                .filter_map(|line| line.split_once(':'))
+                .map(|(field, _)| field.trim_end_matches('?').to_string())
+                .collect(),
+            None => BTreeSet::new(),
+        };
+        assert_eq!(declared, serialized, "fields of {}", name);
+    }
+}
@@ -0,0 +1,215 @@
+//! Writing analysis results to a file the user chose
+//!
+//! Encrypted JSON, FHIR, VCF, HTML, Markdown and CSV/TSV exports are sealed with
+//! [`crypto::write_file`] under a key stretched from the user's passphrase,
+//! so they can be shared or backed up and opened again with
+//! `decrypt_export`. An encrypted PDF uses the PDF's own password
+//! protection instead, so any viewer can open it. The passphrase is passed
+//! to the export commands on its own, outside the options, and is never
+//! logged or stored.
+//!
+//! PDFs embed Segoe UI, or Arial where it is missing, from the Windows
+//! fonts directory, and fall back to Helvetica.
+
+use crate::commands::AnalysisResultData;
+use crate::{fhir, report, tabular, vcf};
+use genomeforge_core::crypto::{self, KeySource, Zeroizing};
+use genomeforge_core::report::delimited::{self, Delimiter};
+use genomeforge_core::report::font::TrueTypeFont;
+use genomeforge_core::report::i18n::Locale;
+use genomeforge_core::report::pdf::{self, Fonts, PdfOptions};
+use genomeforge_core::report::template::ReportTemplate;
+use genomeforge_core::report::vcf::VcfOptions;
+use genomeforge_core::report::{html, markdown};
+use genomeforge_core::Variant;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind recorded in the header of encrypted exports
+const KIND: &str = "export";
+
+/// Sample name in VCF exports, which never name the person
+const SAMPLE: &str = "SAMPLE";
+
+/// Regular and bold font files to embed in PDFs, in order of preference
+const FONT_FILES: [(&str, &str); 2] = [
+    ("segoeui.ttf", "segoeuib.ttf"),
+    ("arial.ttf", "arialbd.ttf"),
+];
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Pdf,
+    /// A standalone page that loads nothing from the network
+    Html,
+    #[serde(alias = "md")]
+    Markdown,
+    /// A zip archive of one comma-separated table per finding category
+    Csv,
+    /// As `Csv`, tab-separated
+    Tsv,
+    /// A FHIR R4 Bundle following the HL7 Genomics Reporting IG
+    Fhir,
+    /// The genome's variants with their findings as INFO tags
+    Vcf,
+}
+
+impl ExportFormat {
+    /// Extension of an export file, as written unencrypted
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json | ExportFormat::Fhir => "json",
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Html => "html",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv | ExportFormat::Tsv => "zip",
+            ExportFormat::Vcf => "vcf",
+        }
+    }
+}
+
+/// Readable header of an encrypted export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportInfo {
+    pub report_id: String,
+    pub format: ExportFormat,
+    /// Seconds since the Unix epoch
+    pub exported_at: u64,
+    /// Language of the report
+    #[serde(default)]
+    pub locale: Locale,
+}
+
+/// What a JSON export holds
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    report_id: &'a str,
+    app_version: &'static str,
+    exported_at: u64,
+    results: &'a AnalysisResultData,
+    /// Every parsed variant, when raw data was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variants: Option<&'a [Variant]>,
+}
+
+/// Write the results to `path`, protected by `passphrase` when one is given
+///
+/// `variants` are the raw data of a JSON export and the records of a VCF
+/// export, which `findings_only` restricts to the annotated ones. PDF,
+/// HTML and Markdown reports are laid out by `template`, in the language of
+/// `info`.
+pub fn export(
+    path: &Path,
+    info: &ExportInfo,
+    results: &AnalysisResultData,
+    template: &ReportTemplate,
+    variants: Option<&[Variant]>,
+    findings_only: bool,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    if variants.is_some() && !matches!(info.format, ExportFormat::Json | ExportFormat::Vcf) {
+        return Err("Raw data can only be included in JSON exports".to_string());
+    }
+    let contents = match info.format {
+        ExportFormat::Json => {
+            let report = JsonReport {
+                report_id: &info.report_id,
+                app_version: env!("CARGO_PKG_VERSION"),
+                exported_at: info.exported_at,
+                results,
+                variants,
+            };
+            serde_json::to_vec_pretty(&report)
+                .map_err(|e| format!("Failed to serialize report: {}", e))?
+        }
+        ExportFormat::Fhir => serde_json::to_vec_pretty(&fhir::bundle(info, results)?)
+            .map_err(|e| format!("Failed to serialize report: {}", e))?,
+        ExportFormat::Vcf => {
+            let variants = variants.ok_or_else(|| "No genome loaded".to_string())?;
+            let options = VcfOptions {
+                sample: SAMPLE,
+                genome_build: results.summary.genome_build,
+                created: info.exported_at,
+                source: concat!("GenomeForge ", env!("CARGO_PKG_VERSION")),
+                annotated_only: findings_only,
+            };
+            let mut out = Vec::new();
+            genomeforge_core::report::vcf::write(
+                &mut out,
+                variants,
+                &vcf::annotations(results),
+                &options,
+            )?;
+            out
+        }
+        ExportFormat::Html => html::render(&report::build(info, results, template)).into_bytes(),
+        ExportFormat::Markdown => {
+            markdown::render(&report::build(info, results, template)).into_bytes()
+        }
+        ExportFormat::Csv => delimited::archive(&tabular::tables(results), Delimiter::Comma)?,
+        ExportFormat::Tsv => delimited::archive(&tabular::tables(results), Delimiter::Tab)?,
+        ExportFormat::Pdf => {
+            let fonts = system_fonts();
+            let options = PdfOptions {
+                fonts: fonts.as_ref(),
+                password: passphrase,
+            };
+            let document = pdf::render(&report::build(info, results, template), &options)?;
+            return write(path, &document);
+        }
+    };
+
+    let contents = Zeroizing::new(contents);
+    match passphrase {
+        Some(passphrase) => {
+            let key = KeySource::Passphrase(passphrase);
+            crypto::write_file(path, KIND, info, &contents, key).map(|_| ())
+        }
+        None => write(path, &contents),
+    }
+}
+
+/// Decrypt an encrypted export to `output`
+pub fn decrypt(input: &Path, output: &Path, passphrase: &str) -> Result<ExportInfo, String> {
+    let (envelope, contents) =
+        crypto::read_file::<ExportInfo>(input, KIND, KeySource::Passphrase(passphrase))?;
+    write(output, &contents)?;
+    Ok(envelope.metadata)
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// Helper functions
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    std::fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The first pair of report fonts installed
+fn system_fonts() -> Option<Fonts> {
+    let dir = std::env::var_os("WINDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\Windows"))
+        .join("Fonts");
+    let load = |file: &str| {
+        std::fs::read(dir.join(file))
+            .ok()
+            .and_then(|data| TrueTypeFont::parse(data).ok())
+    };
+    FONT_FILES.iter().find_map(|(regular, bold)| {
+        Some(Fonts {
+            regular: load(regular)?,
+            bold: load(bold)?,
+        })
+    })
+}
@@ -0,0 +1,67 @@
+//! FHIR genomics reports of an analysis
+//!
+//! Clinical findings become variants with a diagnostic implication, and
+//! drug responses therapeutic implications of the variant or diplotype
+//! they were called from. Traits and haplogroups are left out, as the
+//! Genomics Reporting IG has no profiles for them.
+
+use crate::commands::{AnalysisResultData, ClinicalFinding, DrugResponse};
+use crate::export::ExportInfo;
+use genomeforge_core::fhir::{self, DrugImplication, GenomicsReport, VariantCall};
+use serde_json::Value;
+
+/// The results as a validated FHIR R4 Bundle
+pub fn bundle(info: &ExportInfo, results: &AnalysisResultData) -> Result<Value, String> {
+    let summary = &results.summary;
+    let report = GenomicsReport {
+        id: info.report_id.clone(),
+        issued: info.exported_at,
+        // Positions are in the build the genome was lifted to, if it was
+        genome_build: summary
+            .liftover
+            .as_ref()
+            .map(|stats| stats.to)
+            .or(summary.genome_build),
+        variants: results.clinical_findings.iter().map(variant).collect(),
+        implications: results.drug_responses.iter().map(implication).collect(),
+        conclusion: Some(format!(
+            "{} clinical findings, {} actionable, and {} drug responses among {} analyzed variants.",
+            summary.clinical_count,
+            summary.actionable_findings,
+            summary.drug_count,
+            summary.analyzed_variants
+        )),
+    };
+    let bundle = fhir::bundle(&report);
+    fhir::validate(&bundle).map_err(|e| format!("Invalid FHIR bundle: {}", e))?;
+    Ok(bundle)
+}
+
+// Helper functions
+
+fn variant(finding: &ClinicalFinding) -> VariantCall {
+    VariantCall {
+        rsid: Some(finding.rsid.clone()),
+        gene: finding.gene.clone(),
+        chromosome: finding.chromosome.clone(),
+        position: finding.position,
+        zygosity: Some(finding.zygosity),
+        significance: Some(finding.significance),
+        conditions: finding.conditions.clone(),
+    }
+}
+
+fn implication(response: &DrugResponse) -> DrugImplication {
+    let guideline = response.guideline.as_ref();
+    DrugImplication {
+        drug: response.drug.clone(),
+        gene: response.gene.clone(),
+        rsid: Some(response.rsid.clone()),
+        diplotype: response.diplotype.clone(),
+        phenotype: response.phenotype.clone(),
+        implication: guideline
+            .and_then(|guideline| guideline.implication.clone())
+            .unwrap_or_else(|| response.response.clone()),
+        evidence: Some(format!("PharmGKB {}", response.evidence_level.as_str())),
+    }
+}
@@ -0,0 +1,204 @@
+//! Earlier analyses of each genome
+//!
+//! Every result is saved to a [`ResultsDb`] in the `history` directory of
+//! the active profile, sealed under the device key like the genome cache
+//! and keyed by the SHA-256 of the genome file, so past analyses can be
+//! searched, reloaded and compared. The database is handed out as a
+//! [`ResultsStore`], so only [`open`] knows how it is kept. `diff_analyses` compares the latest
+//! analysis of a genome with the one before it, so after a database
+//! update the user sees what changed since they last looked. The one
+//! result per genome saved before the database is still read as the
+//! analysis before the first one saved to it.
+
+use crate::commands::{AnalysisResultData, DrugResponse, TraitAssociation};
+use crate::reanalysis::{self, FindingChanges};
+use crate::{export, profiles, results};
+use genomeforge_core::crypto::{self, Key, KeySource, Zeroizing};
+use genomeforge_core::results_db::{NewRun, ResultsDb, ResultsStore, Run};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+
+/// Extension of results saved before the results database
+pub const EXTENSION: &str = "gfresult";
+
+/// Held while the database is changed, as batches save results from
+/// several threads and the index is rewritten whole
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// Kind recorded in the header of results saved before the database
+const KIND: &str = "analysis-result";
+
+/// Readable description of a saved result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedResult {
+    /// Seconds since the Unix epoch
+    pub analyzed_at: u64,
+    pub clinvar_release: Option<String>,
+}
+
+impl From<&Run> for SavedResult {
+    fn from(run: &Run) -> Self {
+        SavedResult {
+            analyzed_at: run.analyzed_at,
+            clinvar_release: run.clinvar_release.clone(),
+        }
+    }
+}
+
+/// What changed between the previous analysis of a genome and the latest
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisDiff {
+    /// When the previous analysis ran, in seconds since the Unix epoch
+    pub previous_analyzed_at: u64,
+    /// New, reclassified and removed ClinVar findings, in any section
+    pub findings: FindingChanges,
+    pub new_drug_responses: Vec<DrugResponse>,
+    pub removed_drug_responses: Vec<DrugResponse>,
+    pub new_trait_associations: Vec<TraitAssociation>,
+    pub removed_trait_associations: Vec<TraitAssociation>,
+}
+
+/// Directory holding the active profile's saved results
+pub fn history_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_local_dir(app).map(|dir| dir.join("history"))
+}
+
+/// The results database in `dir`
+pub fn open(dir: &Path, key: &Key) -> Result<Box<dyn ResultsStore>, String> {
+    Ok(Box::new(ResultsDb::open(dir, key.clone())?))
+}
+
+/// Save a result of the genome with this hash, named `source_name`
+pub fn record(
+    dir: &Path,
+    key: &Key,
+    source_hash: &str,
+    source_name: &str,
+    result: &AnalysisResultData,
+) -> Result<Run, String> {
+    let plaintext = Zeroizing::new(
+        serde_json::to_vec(result).map_err(|e| format!("Failed to serialize result: {}", e))?,
+    );
+    let _writing = WRITING
+        .lock()
+        .map_err(|_| "Results database lock poisoned")?;
+    open(dir, key)?.insert(NewRun {
+        source_hash,
+        source_name,
+        analyzed_at: export::now(),
+        clinvar_release: result.summary.clinvar_release.clone(),
+        findings: results::finding_rows(result),
+        result: &plaintext,
+    })
+}
+
+/// The result saved with a run
+pub fn load(db: &dyn ResultsStore, id: u64) -> Result<AnalysisResultData, String> {
+    serde_json::from_slice(&db.result(id)?).map_err(|e| format!("Saved result is corrupt: {}", e))
+}
+
+/// Remove a saved run
+pub fn delete(dir: &Path, key: &Key, id: u64) -> Result<(), String> {
+    let _writing = WRITING
+        .lock()
+        .map_err(|_| "Results database lock poisoned")?;
+    open(dir, key)?.delete(id)
+}
+
+/// The result saved before the latest one of the genome with this hash
+pub fn previous(
+    dir: &Path,
+    key: &Key,
+    source_hash: &str,
+) -> Result<Option<(SavedResult, AnalysisResultData)>, String> {
+    let db = open(dir, key)?;
+    let mut runs = db
+        .runs()?
+        .into_iter()
+        .filter(|run| run.source_hash.eq_ignore_ascii_case(source_hash))
+        .skip(1);
+    if let Some(run) = runs.next() {
+        return Ok(Some((SavedResult::from(&run), load(&*db, run.id)?)));
+    }
+    let legacy = legacy_path(dir, source_hash)?;
+    if !legacy.exists() {
+        return Ok(None);
+    }
+    let (envelope, plaintext) =
+        crypto::read_file::<SavedResult>(&legacy, KIND, KeySource::Device(key))?;
+    let result = serde_json::from_slice(&plaintext)
+        .map_err(|e| format!("Saved result is corrupt: {}", e))?;
+    Ok(Some((envelope.metadata, result)))
+}
+
+/// Compare the latest result of a genome with the previous one
+pub fn diff(
+    previous: &SavedResult,
+    before: &AnalysisResultData,
+    after: &AnalysisResultData,
+) -> AnalysisDiff {
+    let drug_key = |response: &DrugResponse| {
+        (
+            response.annotation_id.clone(),
+            response.gene.clone(),
+            response.drug.clone(),
+        )
+    };
+    let trait_key = |association: &TraitAssociation| {
+        (
+            association.rsid.clone(),
+            association.trait_name.clone(),
+            association.pubmed_id.clone(),
+        )
+    };
+    let (new_drug_responses, removed_drug_responses) =
+        added_and_removed(&before.drug_responses, &after.drug_responses, drug_key);
+    let (new_trait_associations, removed_trait_associations) = added_and_removed(
+        &before.trait_associations,
+        &after.trait_associations,
+        trait_key,
+    );
+    AnalysisDiff {
+        previous_analyzed_at: previous.analyzed_at,
+        findings: reanalysis::compare(before, after),
+        new_drug_responses,
+        removed_drug_responses,
+        new_trait_associations,
+        removed_trait_associations,
+    }
+}
+
+// Helper functions
+
+/// Latest result of a genome as saved before the results database
+fn legacy_path(dir: &Path, source_hash: &str) -> Result<PathBuf, String> {
+    if source_hash.is_empty() || !source_hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid file hash: {:?}", source_hash));
+    }
+    Ok(dir.join(format!("{}.{}", source_hash, EXTENSION)))
+}
+
+/// Entries only in `after`, and entries only in `before`
+fn added_and_removed<T, K, F>(before: &[T], after: &[T], key: F) -> (Vec<T>, Vec<T>)
+where
+    T: Clone,
+    K: Eq + std::hash::Hash,
+    F: Fn(&T) -> K,
+{
+    let earlier: HashSet<K> = before.iter().map(&key).collect();
+    let later: HashSet<K> = after.iter().map(&key).collect();
+    let added = after
+        .iter()
+        .filter(|entry| !earlier.contains(&key(entry)))
+        .cloned()
+        .collect();
+    let removed = before
+        .iter()
+        .filter(|entry| !later.contains(&key(entry)))
+        .cloned()
+        .collect();
+    (added, removed)
+}
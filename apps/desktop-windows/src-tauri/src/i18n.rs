@@ -0,0 +1,704 @@
+//! String catalogs of generated reports
+//!
+//! English is the reference catalog every other one falls back to. Keys
+//! starting with `label.` name enum values by their serialized name, e.g.
+//! `label.likely_pathogenic`.
+
+use genomeforge_core::report::i18n::{Catalog, Locale, Translator};
+
+const EN: &Catalog = &[
+    ("report.title", "Genome Analysis Report"),
+    ("report.subtitle", "Confidential: contains personal genetic information"),
+    ("report.report", "Report"),
+    ("report.generated", "Generated"),
+    ("report.genome_build", "Genome build"),
+    ("report.software", "Software"),
+    ("report.footer", "GenomeForge report {{report_id}} · Not a diagnostic test"),
+    ("report.page_numbers", "Page {{page}} of {{pages}}"),
+    ("report.unknown", "Unknown"),
+    ("report.disclaimer", "This report is for research and educational use only. It is not a diagnostic test, and its findings should be confirmed by a clinical laboratory and discussed with a doctor or genetic counselor before any medical decision is made."),
+    ("summary.title", "Summary"),
+    ("summary.total_variants", "Variants in file"),
+    ("summary.analyzed_variants", "Variants analyzed"),
+    ("summary.clinical", "Clinical findings"),
+    ("summary.actionable", "Actionable findings"),
+    ("summary.drugs", "Drug responses"),
+    ("summary.traits", "Trait associations"),
+    ("summary.nutrition", "Nutrigenomics"),
+    ("summary.structural", "Deletions and duplications"),
+    ("summary.plugins", "Plugin findings"),
+    ("summary.custom", "Custom annotations"),
+    ("summary.category", "Category"),
+    ("summary.findings", "Findings"),
+    ("summary.clinvar", "Clinical variants (ClinVar)"),
+    ("summary.acmg", "ACMG secondary findings"),
+    ("summary.carrier", "Carrier status"),
+    ("summary.diplotypes", "Pharmacogene diplotypes"),
+    ("summary.secondary_withheld", "{{count}} variants in ACMG secondary findings genes are not reported, because screening was not requested or not enough variants were found for the gene's inheritance."),
+    ("summary.late_onset_withheld", "{{count}} late-onset findings, such as APOE, are not reported without consent to see them."),
+    ("summary.common_suppressed", "{{count}} clinical findings are left out as too common in the population to be the likely cause of a rare condition."),
+    ("clinical.title", "Clinical findings"),
+    ("clinical.introduction", "Variants in your genome that ClinVar classifies, most significant first."),
+    ("clinical.none", "No ClinVar-classified variants were found."),
+    ("acmg.title", "ACMG secondary findings"),
+    ("acmg.introduction", "Reportable variants in the genes of the ACMG secondary findings list, version {{version}}, for which medical follow-up is recommended."),
+    ("apoe.title", "APOE"),
+    ("apoe.diplotype", "Diplotype"),
+    ("apoe.e4_copies", "e4 copies"),
+    ("apoe.risk", "Risk"),
+    ("apoe.condition", "Condition"),
+    ("apoe.genotypes", "Genotypes"),
+    ("apoe.alternative", "The genotypes also fit {{diplotype}}, which genotype data cannot tell apart."),
+    ("carrier.title", "Carrier status"),
+    ("pgx.title", "Pharmacogenomics"),
+    ("pgx.introduction", "How your genotypes may affect your response to medications. Do not start, stop or change any medication without talking to your prescriber."),
+    ("pgx.diplotypes", "Pharmacogene diplotypes"),
+    ("pgx.responses", "Drug responses"),
+    ("pgx.hla", "Drug hypersensitivity HLA alleles"),
+    ("pgx.hla_carrier", "You carry {{allele}}, which puts you at high risk of {{reaction}} on {{drugs}}."),
+    ("pgx.none", "No drug response annotations matched your genotypes."),
+    ("traits.title", "Trait associations"),
+    ("traits.introduction", "Genome-wide association study findings for variants you carry. Each describes a small shift in likelihood across a population, not a prediction for you."),
+    ("traits.none", "No trait associations matched your genotypes."),
+    ("nutrition.title", "Nutrigenomics"),
+    ("nutrition.caveat", "These nutrition and metabolism markers rest on weaker evidence than the clinical findings and are not a diagnosis. Diet, lifestyle and other genes usually matter more; talk to a doctor or dietitian before changing your diet because of them."),
+    ("structural.title", "Deletions and duplications"),
+    ("structural.caveat", "These deletions and duplications overlap genes or regions where ClinGen found evidence that a lost or extra copy causes disease. Structural variant calls from sequencing are often wrong and need confirming with a clinical test such as a chromosomal microarray before being acted on."),
+    ("plugins.title", "Plugin findings"),
+    ("plugins.caveat", "These findings come from analysis plugins installed by the user, not from GenomeForge. Their reasoning has not been reviewed by GenomeForge; check them with the plugin author and a clinical test before acting on them."),
+    ("custom.title", "Custom annotations"),
+    ("custom.caveat", "These findings come from an annotation table imported by the user or their laboratory, not from the public databases. GenomeForge has not reviewed its curation; confirm them with whoever maintains the table."),
+    ("notes.title", "Notes"),
+    ("notes.introduction", "Findings of this report bookmarked or annotated for discussion."),
+    ("notes.bookmarked", "Bookmarked"),
+    ("watchlist.title", "Watchlist"),
+    ("watchlist.introduction", "Variants and regions on your watchlist, reported whatever the databases say about them. A variant not in the data was not tested, which says nothing about whether you carry it."),
+    ("watchlist.omitted", "{{count}} more"),
+    ("haplogroups.title", "Haplogroups"),
+    ("haplogroups.paternal", "Paternal (Y chromosome)"),
+    ("haplogroups.maternal", "Maternal (mitochondrial)"),
+    ("haplogroups.not_called", "Not called"),
+    ("haplogroups.call", "{{haplogroup}} ({{genotyped}} of {{total}} markers genotyped)"),
+    ("methodology.title", "Methodology"),
+    ("methodology.local", "Your raw data file was parsed on this computer, and every analysis ran locally; no genetic data left the device."),
+    ("methodology.clinical", "Clinical findings are variants classified in ClinVar, with their review status and, where available, gnomAD population frequencies. Zygosity and the condition's inheritance decide whether a finding means being affected or a carrier."),
+    ("methodology.secondary", "Secondary findings follow the ACMG recommendations for reporting medically actionable genes. Carrier status covers recessive conditions commonly included in carrier screening."),
+    ("methodology.drugs", "Drug responses combine PharmGKB clinical annotations with star-allele diplotypes called for CPIC genes and the matching CPIC dosing recommendations."),
+    ("methodology.traits", "Trait associations are GWAS Catalog associations reaching genome-wide significance, together with a bundled panel of fitness markers from candidate-gene studies and their meta-analyses."),
+    ("methodology.nutrition", "Nutrigenomic findings come from a curated panel of common variants in genes such as MTHFR, LCT, ALDH2, CYP1A2 and FTO, each labeled with the strength of its evidence."),
+    ("methodology.structural", "Structural variants in a VCF are kept apart from the small variants, and the deletions and duplications among them are matched to the ClinGen dosage sensitivity map of genes and regions."),
+    ("methodology.dbsnp", "dbSNP lookups"),
+    ("methodology.dbsnp_value", "{{rsids}} rsids and {{alleles}} alleles resolved"),
+    ("methodology.liftover", "Liftover"),
+    ("methodology.liftover_value", "{{from}} to {{to}}: {{lifted}} lifted, {{dropped}} dropped, {{ambiguous}} ambiguous"),
+    ("methodology.normalization", "Normalization"),
+    ("methodology.normalization_value", "{{split}} records split, {{trimmed}} trimmed, {{aligned}} indels left-aligned"),
+    ("methodology.imputation", "Imputation"),
+    ("methodology.imputation_value", "{{imputed}} imputed and {{typed}} genotyped calls; {{excluded}} low-confidence imputed calls left out"),
+    ("genotype.imputed", "imputed"),
+    ("methodology.quality", "Call quality"),
+    ("methodology.quality_value", "{{assessed}} sequencing calls assessed; {{excluded}} left out ({{filter}} failed FILTER, {{qual}} low QUAL, {{gq}} low GQ, {{depth}} low depth); {{borderline}} borderline calls kept and marked"),
+    ("methodology.probe_mask", "Unreliable probes"),
+    ("methodology.probe_mask_value", "{{chip}} chip: {{excluded}} calls from probes known to call badly left out; {{flagged}} kept and marked"),
+    ("genotype.borderline", "borderline: {{issues}}"),
+    ("genotype.strand_ambiguous", "strand ambiguous"),
+    ("methodology.strand", "Strand"),
+    ("methodology.strand_value", "{{flipped}} findings read from the opposite strand; {{ambiguous}} at A/T or C/G SNPs whose strand could not be told, taken as reported"),
+    ("label.failed_filter", "Failed filter"),
+    ("label.low_site_quality", "Low QUAL"),
+    ("label.low_genotype_quality", "Low GQ"),
+    ("label.low_depth", "Low depth"),
+    ("label.unreliable_probe", "Unreliable probe"),
+    ("label.deletion", "Deletion"),
+    ("label.duplication", "Duplication"),
+    ("label.sufficient", "Sufficient evidence"),
+    ("label.emerging", "Some evidence"),
+    ("methodology.sex", "Genetic sex"),
+    ("methodology.sex_value", "{{sex}} (X heterozygosity {{x}}%, Y sites called {{y}}%); {{hemizygous}} X and Y calls read as single copies"),
+    ("methodology.sex_undetermined", "Undetermined"),
+    ("label.female", "Female"),
+    ("label.male", "Male"),
+    ("label.declared_mismatch", "differs from the declared sex, possibly another person's file"),
+    ("label.possible_xxy", "heterozygous X with Y calls, as in XXY or mixed samples"),
+    ("label.possible_single_x", "X without heterozygosity or Y calls, as in a single X"),
+    ("label.inconclusive", "too few or unclear sex chromosome calls to tell"),
+    ("label.called", "Called"),
+    ("label.no_call", "No call"),
+    ("label.missing", "Not in the data"),
+    ("label.unresolved", "Not located, the genome build being unknown"),
+    ("limitations.title", "Limitations"),
+    ("limitations.coverage", "Genotyping arrays test a fixed set of positions. Most variants in any gene are not tested, so not finding a variant does not mean you do not have one."),
+    ("limitations.false_positives", "Array calls of rare variants are often false positives. Any clinically significant finding should be confirmed by a clinical laboratory before it is acted on."),
+    ("limitations.releases", "Classifications change as evidence accumulates. Findings reflect the database releases installed when the analysis ran."),
+    ("limitations.ancestry", "Most studies behind trait associations and drug responses were done in people of European ancestry, and may apply less well to others."),
+    ("limitations.environment", "Genetics is only one factor. Family history, lifestyle and environment matter as much or more for most conditions and traits."),
+    ("provenance.title", "Provenance"),
+    ("provenance.intro", "The findings were read from the database releases below. Reproducing the analysis takes GenomeForge {{version}} with the same releases, which their SHA-256 digests identify exactly."),
+    ("provenance.unrecorded", "These results were saved before GenomeForge recorded the database releases behind an analysis. Run the analysis again to cite them."),
+    ("provenance.plugin", "Plugin {{name}}"),
+    ("column.gene", "Gene"),
+    ("column.variant", "Variant"),
+    ("column.variants", "Variants"),
+    ("column.genotype", "Genotype"),
+    ("column.significance", "Significance"),
+    ("column.review", "Review"),
+    ("column.condition", "Condition"),
+    ("column.finding", "Finding"),
+    ("column.plugin", "Plugin"),
+    ("column.source", "Source"),
+    ("column.bookmark", "Bookmark"),
+    ("column.note", "Note"),
+    ("column.watched", "Watched"),
+    ("column.reason", "Reason"),
+    ("column.calls", "Calls"),
+    ("column.category", "Category"),
+    ("column.inheritance", "Inheritance"),
+    ("column.status", "Status"),
+    ("column.diplotype", "Diplotype"),
+    ("column.phenotype", "Phenotype"),
+    ("column.activity_score", "Activity score"),
+    ("column.drug", "Drug"),
+    ("column.evidence", "Evidence"),
+    ("column.response", "Response"),
+    ("column.recommendation", "Recommendation"),
+    ("column.trait", "Trait"),
+    ("column.effect", "Effect"),
+    ("column.p_value", "p-value"),
+    ("column.allele", "Allele"),
+    ("column.copies", "Copies"),
+    ("column.location", "Location"),
+    ("column.copy_number", "Copy number"),
+    ("column.dosage_score", "Dosage evidence"),
+    ("column.coverage", "Covered"),
+    ("column.database", "Database"),
+    ("column.version", "Version"),
+    ("column.release_date", "Release date"),
+    ("column.records", "Records"),
+    ("column.sha256", "SHA-256"),
+    ("database.clinvar", "ClinVar"),
+    ("database.pharmgkb", "PharmGKB"),
+    ("database.cpic", "CPIC"),
+    ("database.gwas", "GWAS Catalog"),
+    ("database.dbsnp", "dbSNP"),
+    ("database.gnomad", "gnomAD"),
+    ("database.liftover", "GRCh37 to GRCh38 liftover chain"),
+    ("database.haplogroups", "Haplogroup trees"),
+    ("database.clingen", "ClinGen dosage sensitivity"),
+    ("database.probe_mask", "Unreliable array probes"),
+    ("database.custom", "Custom annotations"),
+    ("review.stars", "{{stars}} of 4 stars"),
+    ("label.pathogenic", "Pathogenic"),
+    ("label.likely_pathogenic", "Likely pathogenic"),
+    ("label.risk_factor", "Risk factor"),
+    ("label.drug_response", "Drug response"),
+    ("label.conflicting", "Conflicting interpretations"),
+    ("label.uncertain_significance", "Uncertain significance"),
+    ("label.likely_benign", "Likely benign"),
+    ("label.benign", "Benign"),
+    ("label.other", "Other"),
+    ("label.heterozygous", "Heterozygous"),
+    ("label.homozygous", "Homozygous"),
+    ("label.hemizygous", "Hemizygous"),
+    ("label.carrier", "Carrier"),
+    ("label.possible_compound_heterozygous", "Possible compound heterozygous"),
+    ("label.dominant", "Dominant"),
+    ("label.recessive", "Recessive"),
+    ("label.x_linked", "X-linked"),
+    ("label.autosomal_recessive", "Autosomal recessive"),
+    ("label.x_linked_recessive", "X-linked recessive"),
+    ("label.cancer", "Cancer"),
+    ("label.cardiovascular", "Cardiovascular"),
+    ("label.inborn_error_of_metabolism", "Inborn error of metabolism"),
+    ("label.miscellaneous", "Miscellaneous"),
+    ("label.metabolic", "Metabolic"),
+    ("label.neurological", "Neurological"),
+    ("label.psychiatric", "Psychiatric"),
+    ("label.immune", "Immune"),
+    ("label.anthropometric", "Anthropometric"),
+    ("label.appearance", "Appearance"),
+    ("label.lifestyle", "Lifestyle"),
+    ("label.fitness", "Fitness"),
+    ("label.typed", "HLA typing"),
+    ("label.proxy", "Proxy SNP"),
+    ("label.established", "Established"),
+    ("label.moderate", "Moderate"),
+    ("label.limited", "Limited"),
+    ("label.folate", "Folate"),
+    ("label.lactose", "Lactose"),
+    ("label.alcohol", "Alcohol"),
+    ("label.caffeine", "Caffeine"),
+    ("label.body_weight", "Body weight"),
+    ("label.vitamin_d", "Vitamin D"),
+    ("label.reduced", "Reduced"),
+    ("label.typical", "Typical"),
+    ("label.increased", "Increased"),
+    ("label.high", "High"),
+];
+
+const ES: &Catalog = &[
+    ("report.title", "Informe de análisis genómico"),
+    ("report.subtitle", "Confidencial: contiene información genética personal"),
+    ("report.report", "Informe"),
+    ("report.generated", "Generado"),
+    ("report.genome_build", "Versión del genoma"),
+    ("report.software", "Software"),
+    ("report.footer", "Informe GenomeForge {{report_id}} · No es una prueba diagnóstica"),
+    ("report.page_numbers", "Página {{page}} de {{pages}}"),
+    ("report.unknown", "Desconocida"),
+    ("report.disclaimer", "Este informe tiene fines exclusivamente educativos y de investigación. No es una prueba diagnóstica: sus resultados deben confirmarse en un laboratorio clínico y comentarse con un médico o asesor genético antes de tomar cualquier decisión médica."),
+    ("summary.title", "Resumen"),
+    ("summary.total_variants", "Variantes en el archivo"),
+    ("summary.analyzed_variants", "Variantes analizadas"),
+    ("summary.clinical", "Hallazgos clínicos"),
+    ("summary.actionable", "Hallazgos accionables"),
+    ("summary.drugs", "Respuestas a fármacos"),
+    ("summary.traits", "Asociaciones con rasgos"),
+    ("summary.nutrition", "Nutrigenómica"),
+    ("summary.structural", "Deleciones y duplicaciones"),
+    ("summary.plugins", "Hallazgos de complementos"),
+    ("summary.custom", "Anotaciones personalizadas"),
+    ("summary.category", "Categoría"),
+    ("summary.findings", "Hallazgos"),
+    ("summary.clinvar", "Variantes clínicas (ClinVar)"),
+    ("summary.acmg", "Hallazgos secundarios ACMG"),
+    ("summary.carrier", "Estado de portador"),
+    ("summary.diplotypes", "Diplotipos farmacogenéticos"),
+    ("summary.secondary_withheld", "{{count}} variantes en genes de hallazgos secundarios ACMG no se informan, porque no se solicitó el cribado o no se encontraron suficientes variantes para el tipo de herencia del gen."),
+    ("summary.late_onset_withheld", "{{count}} hallazgos de aparición tardía, como APOE, no se informan sin consentimiento para verlos."),
+    ("summary.common_suppressed", "{{count}} hallazgos clínicos se omiten por ser demasiado frecuentes en la población para ser la causa probable de una enfermedad rara."),
+    ("clinical.title", "Hallazgos clínicos"),
+    ("clinical.introduction", "Variantes de su genoma clasificadas en ClinVar, de mayor a menor relevancia."),
+    ("clinical.none", "No se encontraron variantes clasificadas en ClinVar."),
+    ("acmg.title", "Hallazgos secundarios ACMG"),
+    ("acmg.introduction", "Variantes notificables en los genes de la lista de hallazgos secundarios del ACMG, versión {{version}}, para las que se recomienda seguimiento médico."),
+    ("apoe.title", "APOE"),
+    ("apoe.diplotype", "Diplotipo"),
+    ("apoe.e4_copies", "Copias de e4"),
+    ("apoe.risk", "Riesgo"),
+    ("apoe.condition", "Enfermedad"),
+    ("apoe.genotypes", "Genotipos"),
+    ("apoe.alternative", "Los genotipos también son compatibles con {{diplotype}}, que los datos de genotipado no permiten distinguir."),
+    ("carrier.title", "Estado de portador"),
+    ("pgx.title", "Farmacogenómica"),
+    ("pgx.introduction", "Cómo pueden influir sus genotipos en su respuesta a los medicamentos. No empiece, suspenda ni cambie ningún medicamento sin consultar a quien se lo recetó."),
+    ("pgx.diplotypes", "Diplotipos farmacogenéticos"),
+    ("pgx.responses", "Respuestas a fármacos"),
+    ("pgx.hla", "Alelos HLA de hipersensibilidad a fármacos"),
+    ("pgx.hla_carrier", "Es portador de {{allele}}, que le expone a un riesgo alto de {{reaction}} con {{drugs}}."),
+    ("pgx.none", "Ninguna anotación de respuesta a fármacos coincide con sus genotipos."),
+    ("traits.title", "Asociaciones con rasgos"),
+    ("traits.introduction", "Resultados de estudios de asociación del genoma completo para variantes que usted porta. Cada uno describe un pequeño cambio de probabilidad en una población, no una predicción sobre usted."),
+    ("traits.none", "Ninguna asociación con rasgos coincide con sus genotipos."),
+    ("nutrition.title", "Nutrigenómica"),
+    ("nutrition.caveat", "Estos marcadores de nutrición y metabolismo se basan en pruebas más débiles que los hallazgos clínicos y no son un diagnóstico. La dieta, el estilo de vida y otros genes suelen importar más; consulte a un médico o dietista antes de cambiar su dieta por ellos."),
+    ("structural.title", "Deleciones y duplicaciones"),
+    ("structural.caveat", "Estas deleciones y duplicaciones se solapan con genes o regiones en los que ClinGen encontró pruebas de que perder o ganar una copia causa enfermedad. Las llamadas de variantes estructurales a partir de la secuenciación suelen ser erróneas y deben confirmarse con una prueba clínica, como un microarray cromosómico, antes de actuar en consecuencia."),
+    ("plugins.title", "Hallazgos de complementos"),
+    ("plugins.caveat", "Estos hallazgos proceden de complementos de análisis instalados por el usuario, no de GenomeForge. GenomeForge no ha revisado su razonamiento; compruébelos con el autor del complemento y con una prueba clínica antes de actuar en consecuencia."),
+    ("custom.title", "Anotaciones personalizadas"),
+    ("custom.caveat", "Estos hallazgos proceden de una tabla de anotaciones importada por el usuario o su laboratorio, no de las bases de datos públicas. GenomeForge no ha revisado su curación; confírmelos con quien mantiene la tabla."),
+    ("notes.title", "Notas"),
+    ("notes.introduction", "Hallazgos de este informe marcados o anotados para comentarlos."),
+    ("notes.bookmarked", "Marcado"),
+    ("watchlist.title", "Lista de seguimiento"),
+    ("watchlist.introduction", "Variantes y regiones de su lista de seguimiento, informadas con independencia de lo que digan las bases de datos. Una variante ausente de los datos no se analizó, lo que no indica si usted la porta."),
+    ("watchlist.omitted", "{{count}} más"),
+    ("haplogroups.title", "Haplogrupos"),
+    ("haplogroups.paternal", "Paterno (cromosoma Y)"),
+    ("haplogroups.maternal", "Materno (mitocondrial)"),
+    ("haplogroups.not_called", "No determinado"),
+    ("haplogroups.call", "{{haplogroup}} ({{genotyped}} de {{total}} marcadores genotipados)"),
+    ("methodology.title", "Metodología"),
+    ("methodology.local", "Su archivo de datos sin procesar se leyó en este equipo y todos los análisis se realizaron localmente; ningún dato genético salió del dispositivo."),
+    ("methodology.clinical", "Los hallazgos clínicos son variantes clasificadas en ClinVar, con su estado de revisión y, cuando están disponibles, las frecuencias poblacionales de gnomAD. La cigosidad y el tipo de herencia de la enfermedad determinan si un hallazgo indica estar afectado o ser portador."),
+    ("methodology.secondary", "Los hallazgos secundarios siguen las recomendaciones del ACMG para informar sobre genes médicamente accionables. El estado de portador abarca enfermedades recesivas habituales en el cribado de portadores."),
+    ("methodology.drugs", "Las respuestas a fármacos combinan las anotaciones clínicas de PharmGKB con los diplotipos de alelos estrella determinados para genes CPIC y las recomendaciones de dosificación de CPIC correspondientes."),
+    ("methodology.traits", "Las asociaciones con rasgos son asociaciones del GWAS Catalog que alcanzan significación a escala genómica, junto con un panel incluido de marcadores de forma física procedentes de estudios de genes candidatos y sus metaanálisis."),
+    ("methodology.nutrition", "Los hallazgos nutrigenómicos proceden de un panel seleccionado de variantes comunes en genes como MTHFR, LCT, ALDH2, CYP1A2 y FTO, cada una con la solidez de sus pruebas."),
+    ("methodology.structural", "Las variantes estructurales de un VCF se separan de las variantes pequeñas, y sus deleciones y duplicaciones se comparan con el mapa de sensibilidad a la dosis de genes y regiones de ClinGen."),
+    ("methodology.dbsnp", "Consultas a dbSNP"),
+    ("methodology.dbsnp_value", "{{rsids}} rsids y {{alleles}} alelos resueltos"),
+    ("methodology.liftover", "Conversión de coordenadas"),
+    ("methodology.liftover_value", "De {{from}} a {{to}}: {{lifted}} convertidas, {{dropped}} descartadas, {{ambiguous}} ambiguas"),
+    ("methodology.normalization", "Normalización"),
+    ("methodology.normalization_value", "{{split}} registros divididos, {{trimmed}} recortados, {{aligned}} indels alineados a la izquierda"),
+    ("methodology.imputation", "Imputación"),
+    ("methodology.imputation_value", "{{imputed}} llamadas imputadas y {{typed}} genotipadas; {{excluded}} llamadas imputadas de baja confianza excluidas"),
+    ("genotype.imputed", "imputado"),
+    ("methodology.quality", "Calidad de las llamadas"),
+    ("methodology.quality_value", "{{assessed}} llamadas de secuenciación evaluadas; {{excluded}} excluidas ({{filter}} por FILTER, {{qual}} por QUAL baja, {{gq}} por GQ baja, {{depth}} por baja profundidad); {{borderline}} llamadas dudosas conservadas y marcadas"),
+    ("methodology.probe_mask", "Sondas poco fiables"),
+    ("methodology.probe_mask_value", "Chip {{chip}}: {{excluded}} llamadas de sondas conocidas por fallar excluidas; {{flagged}} conservadas y marcadas"),
+    ("genotype.borderline", "dudosa: {{issues}}"),
+    ("genotype.strand_ambiguous", "cadena ambigua"),
+    ("methodology.strand", "Cadena"),
+    ("methodology.strand_value", "{{flipped}} hallazgos leídos de la cadena opuesta; {{ambiguous}} en SNP A/T o C/G cuya cadena no pudo determinarse, tomados tal como se publicaron"),
+    ("label.failed_filter", "Filtro no superado"),
+    ("label.low_site_quality", "QUAL baja"),
+    ("label.low_genotype_quality", "GQ baja"),
+    ("label.low_depth", "Baja profundidad"),
+    ("label.unreliable_probe", "Sonda poco fiable"),
+    ("label.deletion", "Deleción"),
+    ("label.duplication", "Duplicación"),
+    ("label.sufficient", "Pruebas suficientes"),
+    ("label.emerging", "Algunas pruebas"),
+    ("methodology.sex", "Sexo genético"),
+    ("methodology.sex_value", "{{sex}} (heterocigosidad de X {{x}} %, sitios de Y llamados {{y}} %); {{hemizygous}} llamadas de X e Y leídas como copias únicas"),
+    ("methodology.sex_undetermined", "No determinado"),
+    ("label.female", "Femenino"),
+    ("label.male", "Masculino"),
+    ("label.declared_mismatch", "distinto del sexo declarado, posiblemente el archivo de otra persona"),
+    ("label.possible_xxy", "X heterocigoto con llamadas de Y, como en XXY o muestras mezcladas"),
+    ("label.possible_single_x", "X sin heterocigosidad ni llamadas de Y, como con un solo X"),
+    ("label.inconclusive", "llamadas de cromosomas sexuales escasas o poco claras para determinarlo"),
+    ("label.called", "Llamada"),
+    ("label.no_call", "Sin llamada"),
+    ("label.missing", "Ausente de los datos"),
+    ("label.unresolved", "No localizada, al desconocerse el ensamblaje del genoma"),
+    ("limitations.title", "Limitaciones"),
+    ("limitations.coverage", "Los chips de genotipado analizan un conjunto fijo de posiciones. La mayoría de las variantes de cualquier gen no se analizan, así que no encontrar una variante no significa que usted no la tenga."),
+    ("limitations.false_positives", "Las determinaciones de variantes raras en chips son a menudo falsos positivos. Cualquier hallazgo clínicamente relevante debe confirmarse en un laboratorio clínico antes de actuar."),
+    ("limitations.releases", "Las clasificaciones cambian a medida que se acumula evidencia. Los hallazgos reflejan las versiones de las bases de datos instaladas cuando se realizó el análisis."),
+    ("limitations.ancestry", "La mayoría de los estudios sobre asociaciones con rasgos y respuestas a fármacos se realizaron en personas de ascendencia europea y pueden ser menos aplicables a otras."),
+    ("limitations.environment", "La genética es solo un factor. Los antecedentes familiares, el estilo de vida y el entorno importan igual o más en la mayoría de las enfermedades y rasgos."),
+    ("provenance.title", "Procedencia"),
+    ("provenance.intro", "Los hallazgos se obtuvieron de las versiones de bases de datos que siguen. Reproducir el análisis requiere GenomeForge {{version}} con las mismas versiones, que sus resúmenes SHA-256 identifican con exactitud."),
+    ("provenance.unrecorded", "Estos resultados se guardaron antes de que GenomeForge registrara las versiones de bases de datos de cada análisis. Vuelva a ejecutar el análisis para citarlas."),
+    ("provenance.plugin", "Complemento {{name}}"),
+    ("column.gene", "Gen"),
+    ("column.variant", "Variante"),
+    ("column.variants", "Variantes"),
+    ("column.genotype", "Genotipo"),
+    ("column.significance", "Significado"),
+    ("column.review", "Revisión"),
+    ("column.condition", "Enfermedad"),
+    ("column.finding", "Hallazgo"),
+    ("column.plugin", "Complemento"),
+    ("column.source", "Fuente"),
+    ("column.bookmark", "Marcador"),
+    ("column.note", "Nota"),
+    ("column.watched", "Seguimiento"),
+    ("column.reason", "Motivo"),
+    ("column.calls", "Llamadas"),
+    ("column.category", "Categoría"),
+    ("column.inheritance", "Herencia"),
+    ("column.status", "Estado"),
+    ("column.diplotype", "Diplotipo"),
+    ("column.phenotype", "Fenotipo"),
+    ("column.activity_score", "Puntuación de actividad"),
+    ("column.drug", "Fármaco"),
+    ("column.evidence", "Evidencia"),
+    ("column.response", "Respuesta"),
+    ("column.recommendation", "Recomendación"),
+    ("column.trait", "Rasgo"),
+    ("column.effect", "Efecto"),
+    ("column.p_value", "Valor p"),
+    ("column.allele", "Alelo"),
+    ("column.copies", "Copias"),
+    ("column.location", "Ubicación"),
+    ("column.copy_number", "Número de copias"),
+    ("column.dosage_score", "Pruebas de dosis"),
+    ("column.coverage", "Cubierto"),
+    ("column.database", "Base de datos"),
+    ("column.version", "Versión"),
+    ("column.release_date", "Fecha de publicación"),
+    ("column.records", "Registros"),
+    ("column.sha256", "SHA-256"),
+    ("database.clinvar", "ClinVar"),
+    ("database.pharmgkb", "PharmGKB"),
+    ("database.cpic", "CPIC"),
+    ("database.gwas", "Catálogo GWAS"),
+    ("database.dbsnp", "dbSNP"),
+    ("database.gnomad", "gnomAD"),
+    ("database.liftover", "Cadena de conversión de GRCh37 a GRCh38"),
+    ("database.haplogroups", "Árboles de haplogrupos"),
+    ("database.clingen", "Sensibilidad a la dosis de ClinGen"),
+    ("database.probe_mask", "Sondas de chip poco fiables"),
+    ("database.custom", "Anotaciones personalizadas"),
+    ("review.stars", "{{stars}} de 4 estrellas"),
+    ("label.pathogenic", "Patogénica"),
+    ("label.likely_pathogenic", "Probablemente patogénica"),
+    ("label.risk_factor", "Factor de riesgo"),
+    ("label.drug_response", "Respuesta a fármacos"),
+    ("label.conflicting", "Interpretaciones contradictorias"),
+    ("label.uncertain_significance", "Significado incierto"),
+    ("label.likely_benign", "Probablemente benigna"),
+    ("label.benign", "Benigna"),
+    ("label.other", "Otra"),
+    ("label.heterozygous", "Heterocigoto"),
+    ("label.homozygous", "Homocigoto"),
+    ("label.hemizygous", "Hemicigoto"),
+    ("label.carrier", "Portador"),
+    ("label.possible_compound_heterozygous", "Posible heterocigoto compuesto"),
+    ("label.dominant", "Dominante"),
+    ("label.recessive", "Recesiva"),
+    ("label.x_linked", "Ligada al X"),
+    ("label.autosomal_recessive", "Autosómica recesiva"),
+    ("label.x_linked_recessive", "Recesiva ligada al X"),
+    ("label.cancer", "Cáncer"),
+    ("label.cardiovascular", "Cardiovascular"),
+    ("label.inborn_error_of_metabolism", "Error congénito del metabolismo"),
+    ("label.miscellaneous", "Varios"),
+    ("label.metabolic", "Metabólico"),
+    ("label.neurological", "Neurológico"),
+    ("label.psychiatric", "Psiquiátrico"),
+    ("label.immune", "Inmunitario"),
+    ("label.anthropometric", "Antropométrico"),
+    ("label.appearance", "Apariencia"),
+    ("label.lifestyle", "Estilo de vida"),
+    ("label.fitness", "Forma física"),
+    ("label.typed", "Tipificación HLA"),
+    ("label.proxy", "SNP indicador"),
+    ("label.established", "Establecida"),
+    ("label.moderate", "Moderada"),
+    ("label.limited", "Limitada"),
+    ("label.folate", "Folato"),
+    ("label.lactose", "Lactosa"),
+    ("label.alcohol", "Alcohol"),
+    ("label.caffeine", "Cafeína"),
+    ("label.body_weight", "Peso corporal"),
+    ("label.vitamin_d", "Vitamina D"),
+    ("label.reduced", "Reducido"),
+    ("label.typical", "Habitual"),
+    ("label.increased", "Aumentado"),
+    ("label.high", "Alto"),
+];
+
+const DE: &Catalog = &[
+    ("report.title", "Genomanalyse-Bericht"),
+    ("report.subtitle", "Vertraulich: enthält persönliche genetische Informationen"),
+    ("report.report", "Bericht"),
+    ("report.generated", "Erstellt"),
+    ("report.genome_build", "Genomversion"),
+    ("report.software", "Software"),
+    ("report.footer", "GenomeForge-Bericht {{report_id}} · Kein diagnostischer Test"),
+    ("report.page_numbers", "Seite {{page}} von {{pages}}"),
+    ("report.unknown", "Unbekannt"),
+    ("report.disclaimer", "Dieser Bericht dient ausschließlich Forschungs- und Bildungszwecken. Er ist kein diagnostischer Test; seine Befunde sollten von einem klinischen Labor bestätigt und mit einer Ärztin, einem Arzt oder einer genetischen Beratung besprochen werden, bevor medizinische Entscheidungen getroffen werden."),
+    ("summary.title", "Zusammenfassung"),
+    ("summary.total_variants", "Varianten in der Datei"),
+    ("summary.analyzed_variants", "Analysierte Varianten"),
+    ("summary.clinical", "Klinische Befunde"),
+    ("summary.actionable", "Handlungsrelevante Befunde"),
+    ("summary.drugs", "Arzneimittelwirkungen"),
+    ("summary.traits", "Merkmalsassoziationen"),
+    ("summary.nutrition", "Nutrigenomik"),
+    ("summary.structural", "Deletionen und Duplikationen"),
+    ("summary.plugins", "Befunde von Plugins"),
+    ("summary.custom", "Eigene Annotationen"),
+    ("summary.category", "Kategorie"),
+    ("summary.findings", "Befunde"),
+    ("summary.clinvar", "Klinische Varianten (ClinVar)"),
+    ("summary.acmg", "ACMG-Zusatzbefunde"),
+    ("summary.carrier", "Anlageträgerschaft"),
+    ("summary.diplotypes", "Pharmakogen-Diplotypen"),
+    ("summary.secondary_withheld", "{{count}} Varianten in Genen der ACMG-Zusatzbefunde werden nicht berichtet, weil das Screening nicht gewünscht war oder für den Erbgang des Gens nicht genug Varianten gefunden wurden."),
+    ("summary.late_onset_withheld", "{{count}} spät manifestierende Befunde wie APOE werden ohne Einwilligung nicht berichtet."),
+    ("summary.common_suppressed", "{{count}} klinische Befunde werden ausgelassen, da sie in der Bevölkerung zu häufig sind, um wahrscheinlich die Ursache einer seltenen Erkrankung zu sein."),
+    ("clinical.title", "Klinische Befunde"),
+    ("clinical.introduction", "Von ClinVar klassifizierte Varianten in Ihrem Genom, die bedeutsamsten zuerst."),
+    ("clinical.none", "Es wurden keine von ClinVar klassifizierten Varianten gefunden."),
+    ("acmg.title", "ACMG-Zusatzbefunde"),
+    ("acmg.introduction", "Berichtspflichtige Varianten in den Genen der ACMG-Liste für Zusatzbefunde, Version {{version}}, für die eine medizinische Nachsorge empfohlen wird."),
+    ("apoe.title", "APOE"),
+    ("apoe.diplotype", "Diplotyp"),
+    ("apoe.e4_copies", "e4-Kopien"),
+    ("apoe.risk", "Risiko"),
+    ("apoe.condition", "Erkrankung"),
+    ("apoe.genotypes", "Genotypen"),
+    ("apoe.alternative", "Die Genotypen passen auch zu {{diplotype}}, was sich anhand von Genotypisierungsdaten nicht unterscheiden lässt."),
+    ("carrier.title", "Anlageträgerschaft"),
+    ("pgx.title", "Pharmakogenomik"),
+    ("pgx.introduction", "Wie Ihre Genotypen Ihr Ansprechen auf Medikamente beeinflussen können. Beginnen, beenden oder ändern Sie keine Medikation, ohne mit der verschreibenden Person zu sprechen."),
+    ("pgx.diplotypes", "Pharmakogen-Diplotypen"),
+    ("pgx.responses", "Arzneimittelwirkungen"),
+    ("pgx.hla", "HLA-Allele der Arzneimittelüberempfindlichkeit"),
+    ("pgx.hla_carrier", "Sie tragen {{allele}} und haben damit unter {{drugs}} ein hohes Risiko für {{reaction}}."),
+    ("pgx.none", "Keine Annotationen zur Arzneimittelwirkung passen zu Ihren Genotypen."),
+    ("traits.title", "Merkmalsassoziationen"),
+    ("traits.introduction", "Ergebnisse genomweiter Assoziationsstudien für Varianten, die Sie tragen. Jedes beschreibt eine kleine Verschiebung der Wahrscheinlichkeit in einer Bevölkerung, keine Vorhersage für Sie."),
+    ("traits.none", "Keine Merkmalsassoziationen passen zu Ihren Genotypen."),
+    ("nutrition.title", "Nutrigenomik"),
+    ("nutrition.caveat", "Diese Marker für Ernährung und Stoffwechsel beruhen auf schwächerer Evidenz als die klinischen Befunde und sind keine Diagnose. Ernährung, Lebensstil und andere Gene sind meist wichtiger; sprechen Sie mit einer Ärztin, einem Arzt oder einer Ernährungsfachkraft, bevor Sie deshalb Ihre Ernährung ändern."),
+    ("structural.title", "Deletionen und Duplikationen"),
+    ("structural.caveat", "Diese Deletionen und Duplikationen überlappen Gene oder Regionen, für die ClinGen Evidenz gefunden hat, dass eine fehlende oder zusätzliche Kopie Krankheiten verursacht. Aufrufe struktureller Varianten aus der Sequenzierung sind oft falsch und müssen mit einem klinischen Test wie einem chromosomalen Microarray bestätigt werden, bevor man danach handelt."),
+    ("plugins.title", "Befunde von Plugins"),
+    ("plugins.caveat", "Diese Befunde stammen von Analyse-Plugins, die der Nutzer installiert hat, nicht von GenomeForge. GenomeForge hat ihre Begründung nicht geprüft; prüfen Sie sie mit dem Autor des Plugins und einem klinischen Test, bevor Sie danach handeln."),
+    ("custom.title", "Eigene Annotationen"),
+    ("custom.caveat", "Diese Befunde stammen aus einer Annotationstabelle, die der Nutzer oder sein Labor importiert hat, nicht aus den öffentlichen Datenbanken. GenomeForge hat ihre Kuratierung nicht geprüft; bestätigen Sie sie bei dem, der die Tabelle pflegt."),
+    ("notes.title", "Notizen"),
+    ("notes.introduction", "Befunde dieses Berichts, die zur Besprechung markiert oder kommentiert wurden."),
+    ("notes.bookmarked", "Markiert"),
+    ("watchlist.title", "Beobachtungsliste"),
+    ("watchlist.introduction", "Varianten und Regionen Ihrer Beobachtungsliste, berichtet unabhängig davon, was die Datenbanken über sie sagen. Eine Variante, die in den Daten fehlt, wurde nicht getestet; das sagt nichts darüber, ob Sie sie tragen."),
+    ("watchlist.omitted", "{{count}} weitere"),
+    ("haplogroups.title", "Haplogruppen"),
+    ("haplogroups.paternal", "Väterlich (Y-Chromosom)"),
+    ("haplogroups.maternal", "Mütterlich (mitochondrial)"),
+    ("haplogroups.not_called", "Nicht bestimmt"),
+    ("haplogroups.call", "{{haplogroup}} ({{genotyped}} von {{total}} Markern genotypisiert)"),
+    ("methodology.title", "Methodik"),
+    ("methodology.local", "Ihre Rohdatendatei wurde auf diesem Computer eingelesen und alle Analysen liefen lokal; keine genetischen Daten haben das Gerät verlassen."),
+    ("methodology.clinical", "Klinische Befunde sind in ClinVar klassifizierte Varianten mit ihrem Prüfstatus und, soweit verfügbar, gnomAD-Populationsfrequenzen. Zygotie und Erbgang der Erkrankung entscheiden, ob ein Befund Betroffenheit oder Anlageträgerschaft bedeutet."),
+    ("methodology.secondary", "Zusatzbefunde folgen den ACMG-Empfehlungen zum Berichten medizinisch handlungsrelevanter Gene. Die Anlageträgerschaft umfasst rezessive Erkrankungen, die üblicherweise im Trägerscreening untersucht werden."),
+    ("methodology.drugs", "Arzneimittelwirkungen verbinden klinische Annotationen von PharmGKB mit den für CPIC-Gene bestimmten Sternallel-Diplotypen und den passenden CPIC-Dosierungsempfehlungen."),
+    ("methodology.traits", "Merkmalsassoziationen sind Assoziationen aus dem GWAS Catalog, die genomweite Signifikanz erreichen, ergänzt um ein mitgeliefertes Panel von Fitnessmarkern aus Kandidatengenstudien und deren Metaanalysen."),
+    ("methodology.nutrition", "Nutrigenomische Befunde stammen aus einem kuratierten Panel häufiger Varianten in Genen wie MTHFR, LCT, ALDH2, CYP1A2 und FTO, jeweils mit der Stärke ihrer Evidenz."),
+    ("methodology.structural", "Strukturelle Varianten in einer VCF werden getrennt von den kleinen Varianten geführt, und ihre Deletionen und Duplikationen werden mit der ClinGen-Karte der Dosissensitivität von Genen und Regionen abgeglichen."),
+    ("methodology.dbsnp", "dbSNP-Abfragen"),
+    ("methodology.dbsnp_value", "{{rsids}} rsIDs und {{alleles}} Allele aufgelöst"),
+    ("methodology.liftover", "Koordinatenumrechnung"),
+    ("methodology.liftover_value", "{{from}} nach {{to}}: {{lifted}} umgerechnet, {{dropped}} verworfen, {{ambiguous}} mehrdeutig"),
+    ("methodology.normalization", "Normalisierung"),
+    ("methodology.normalization_value", "{{split}} Einträge aufgeteilt, {{trimmed}} gekürzt, {{aligned}} Indels linksbündig ausgerichtet"),
+    ("methodology.imputation", "Imputation"),
+    ("methodology.imputation_value", "{{imputed}} imputierte und {{typed}} genotypisierte Aufrufe; {{excluded}} imputierte Aufrufe geringer Konfidenz ausgelassen"),
+    ("genotype.imputed", "imputiert"),
+    ("methodology.quality", "Aufrufqualität"),
+    ("methodology.quality_value", "{{assessed}} Sequenzierungsaufrufe geprüft; {{excluded}} ausgelassen ({{filter}} FILTER nicht bestanden, {{qual}} niedrige QUAL, {{gq}} niedrige GQ, {{depth}} geringe Tiefe); {{borderline}} grenzwertige Aufrufe behalten und markiert"),
+    ("methodology.probe_mask", "Unzuverlässige Sonden"),
+    ("methodology.probe_mask_value", "Chip {{chip}}: {{excluded}} Aufrufe von Sonden mit bekannt schlechten Aufrufen ausgelassen; {{flagged}} behalten und markiert"),
+    ("genotype.borderline", "grenzwertig: {{issues}}"),
+    ("genotype.strand_ambiguous", "Strang unklar"),
+    ("methodology.strand", "Strang"),
+    ("methodology.strand_value", "{{flipped}} Befunde vom Gegenstrang gelesen; {{ambiguous}} an A/T- oder C/G-SNPs, deren Strang nicht bestimmbar war, wie veröffentlicht übernommen"),
+    ("label.failed_filter", "Filter nicht bestanden"),
+    ("label.low_site_quality", "Niedrige QUAL"),
+    ("label.low_genotype_quality", "Niedrige GQ"),
+    ("label.low_depth", "Geringe Tiefe"),
+    ("label.unreliable_probe", "Unzuverlässige Sonde"),
+    ("label.deletion", "Deletion"),
+    ("label.duplication", "Duplikation"),
+    ("label.sufficient", "Ausreichende Evidenz"),
+    ("label.emerging", "Einige Evidenz"),
+    ("methodology.sex", "Genetisches Geschlecht"),
+    ("methodology.sex_value", "{{sex}} (X-Heterozygotie {{x}} %, Y-Positionen bestimmt {{y}} %); {{hemizygous}} X- und Y-Genotypen als Einzelkopien gelesen"),
+    ("methodology.sex_undetermined", "Nicht bestimmt"),
+    ("label.female", "Weiblich"),
+    ("label.male", "Männlich"),
+    ("label.declared_mismatch", "weicht vom angegebenen Geschlecht ab, möglicherweise die Datei einer anderen Person"),
+    ("label.possible_xxy", "heterozygotes X mit Y-Genotypen, wie bei XXY oder vermischten Proben"),
+    ("label.possible_single_x", "X ohne Heterozygotie und ohne Y-Genotypen, wie bei einem einzelnen X"),
+    ("label.inconclusive", "zu wenige oder unklare Genotypen der Geschlechtschromosomen"),
+    ("label.called", "Aufgerufen"),
+    ("label.no_call", "Kein Aufruf"),
+    ("label.missing", "Nicht in den Daten"),
+    ("label.unresolved", "Nicht verortet, da die Genomversion unbekannt ist"),
+    ("limitations.title", "Einschränkungen"),
+    ("limitations.coverage", "Genotypisierungs-Chips untersuchen eine feste Auswahl von Positionen. Die meisten Varianten eines Gens werden nicht untersucht; dass keine Variante gefunden wurde, heißt also nicht, dass Sie keine tragen."),
+    ("limitations.false_positives", "Chip-Ergebnisse für seltene Varianten sind häufig falsch positiv. Jeder klinisch bedeutsame Befund sollte von einem klinischen Labor bestätigt werden, bevor danach gehandelt wird."),
+    ("limitations.releases", "Klassifikationen ändern sich mit zunehmender Evidenz. Die Befunde spiegeln die Datenbankversionen wider, die bei der Analyse installiert waren."),
+    ("limitations.ancestry", "Die meisten Studien zu Merkmalsassoziationen und Arzneimittelwirkungen wurden an Menschen europäischer Abstammung durchgeführt und sind auf andere möglicherweise weniger übertragbar."),
+    ("limitations.environment", "Genetik ist nur ein Faktor. Familienanamnese, Lebensstil und Umwelt sind bei den meisten Erkrankungen und Merkmalen ebenso wichtig oder wichtiger."),
+    ("provenance.title", "Herkunft"),
+    ("provenance.intro", "Die Befunde stammen aus den unten aufgeführten Datenbankversionen. Um die Analyse zu reproduzieren, braucht es GenomeForge {{version}} mit denselben Versionen, die ihre SHA-256-Prüfsummen eindeutig bestimmen."),
+    ("provenance.unrecorded", "Diese Ergebnisse wurden gespeichert, bevor GenomeForge die Datenbankversionen einer Analyse festhielt. Führen Sie die Analyse erneut aus, um sie anzugeben."),
+    ("provenance.plugin", "Plugin {{name}}"),
+    ("column.gene", "Gen"),
+    ("column.variant", "Variante"),
+    ("column.variants", "Varianten"),
+    ("column.genotype", "Genotyp"),
+    ("column.significance", "Bedeutung"),
+    ("column.review", "Prüfung"),
+    ("column.condition", "Erkrankung"),
+    ("column.finding", "Befund"),
+    ("column.plugin", "Plugin"),
+    ("column.source", "Quelle"),
+    ("column.bookmark", "Lesezeichen"),
+    ("column.note", "Notiz"),
+    ("column.watched", "Beobachtet"),
+    ("column.reason", "Grund"),
+    ("column.calls", "Aufrufe"),
+    ("column.category", "Kategorie"),
+    ("column.inheritance", "Erbgang"),
+    ("column.status", "Status"),
+    ("column.diplotype", "Diplotyp"),
+    ("column.phenotype", "Phänotyp"),
+    ("column.activity_score", "Aktivitätswert"),
+    ("column.drug", "Arzneimittel"),
+    ("column.evidence", "Evidenz"),
+    ("column.response", "Wirkung"),
+    ("column.recommendation", "Empfehlung"),
+    ("column.trait", "Merkmal"),
+    ("column.effect", "Effekt"),
+    ("column.p_value", "p-Wert"),
+    ("column.allele", "Allel"),
+    ("column.copies", "Kopien"),
+    ("column.location", "Position"),
+    ("column.copy_number", "Kopienzahl"),
+    ("column.dosage_score", "Dosis-Evidenz"),
+    ("column.coverage", "Abgedeckt"),
+    ("column.database", "Datenbank"),
+    ("column.version", "Version"),
+    ("column.release_date", "Veröffentlicht"),
+    ("column.records", "Einträge"),
+    ("column.sha256", "SHA-256"),
+    ("database.clinvar", "ClinVar"),
+    ("database.pharmgkb", "PharmGKB"),
+    ("database.cpic", "CPIC"),
+    ("database.gwas", "GWAS-Katalog"),
+    ("database.dbsnp", "dbSNP"),
+    ("database.gnomad", "gnomAD"),
+    ("database.liftover", "Liftover-Kette von GRCh37 nach GRCh38"),
+    ("database.haplogroups", "Haplogruppen-Stammbäume"),
+    ("database.clingen", "ClinGen-Dosissensitivität"),
+    ("database.probe_mask", "Unzuverlässige Array-Sonden"),
+    ("database.custom", "Eigene Annotationen"),
+    ("review.stars", "{{stars}} von 4 Sternen"),
+    ("label.pathogenic", "Pathogen"),
+    ("label.likely_pathogenic", "Wahrscheinlich pathogen"),
+    ("label.risk_factor", "Risikofaktor"),
+    ("label.drug_response", "Arzneimittelwirkung"),
+    ("label.conflicting", "Widersprüchliche Interpretationen"),
+    ("label.uncertain_significance", "Unklare Bedeutung"),
+    ("label.likely_benign", "Wahrscheinlich benigne"),
+    ("label.benign", "Benigne"),
+    ("label.other", "Sonstige"),
+    ("label.heterozygous", "Heterozygot"),
+    ("label.homozygous", "Homozygot"),
+    ("label.hemizygous", "Hemizygot"),
+    ("label.carrier", "Anlageträger"),
+    ("label.possible_compound_heterozygous", "Möglicherweise compound-heterozygot"),
+    ("label.dominant", "Dominant"),
+    ("label.recessive", "Rezessiv"),
+    ("label.x_linked", "X-chromosomal"),
+    ("label.autosomal_recessive", "Autosomal-rezessiv"),
+    ("label.x_linked_recessive", "X-chromosomal-rezessiv"),
+    ("label.cancer", "Krebs"),
+    ("label.cardiovascular", "Herz-Kreislauf"),
+    ("label.inborn_error_of_metabolism", "Angeborene Stoffwechselstörung"),
+    ("label.miscellaneous", "Verschiedenes"),
+    ("label.metabolic", "Stoffwechsel"),
+    ("label.neurological", "Neurologisch"),
+    ("label.psychiatric", "Psychiatrisch"),
+    ("label.immune", "Immunsystem"),
+    ("label.anthropometric", "Körpermaße"),
+    ("label.appearance", "Aussehen"),
+    ("label.lifestyle", "Lebensstil"),
+    ("label.fitness", "Fitness"),
+    ("label.typed", "HLA-Typisierung"),
+    ("label.proxy", "Stellvertreter-SNP"),
+    ("label.established", "Gesichert"),
+    ("label.moderate", "Mäßig"),
+    ("label.limited", "Begrenzt"),
+    ("label.folate", "Folat"),
+    ("label.lactose", "Laktose"),
+    ("label.alcohol", "Alkohol"),
+    ("label.caffeine", "Koffein"),
+    ("label.body_weight", "Körpergewicht"),
+    ("label.vitamin_d", "Vitamin D"),
+    ("label.reduced", "Verringert"),
+    ("label.typical", "Durchschnittlich"),
+    ("label.increased", "Erhöht"),
+    ("label.high", "Hoch"),
+];
+
+/// The translator of a locale's report text
+pub fn translator(locale: Locale) -> Translator<'static> {
+    let catalog = match locale {
+        Locale::En => EN,
+        Locale::Es => ES,
+        Locale::De => DE,
+    };
+    Translator::new(locale, catalog, EN)
+}
@@ -0,0 +1,131 @@
+//! Genome files dropped onto the window
+//!
+//! Each dropped file is checked before anything is parsed: it must be a
+//! file, in a format GenomeForge reads. What parsing it involves is sent
+//! to the frontend as a `file-ready` event, or why it cannot be parsed as
+//! a `file-rejected` event, so the upload page can start from either.
+
+use crate::error::GenomeForgeError;
+use crate::events::{self, FileRejected};
+use crate::{audit, system};
+use genomeforge_core::alignment;
+use genomeforge_core::parser;
+use genomeforge_core::parser::compression::Compression;
+use genomeforge_core::parser::detect::{self, FileFormat};
+use genomeforge_core::parser::plink::Fileset;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+/// Detection below this confidence is reported with a warning
+const LOW_CONFIDENCE: f64 = 0.6;
+
+/// What parsing a file involves, the payload of a `file-ready` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsePlan {
+    pub path: String,
+    pub file_type: FileFormat,
+    pub compression: Compression,
+    /// How sure format detection is, from 0.0 to 1.0
+    pub confidence: f64,
+    /// Bytes on disk, every file of a PLINK fileset together
+    pub size: u64,
+    /// Rough seconds the parse takes; unknown for aligned reads, which are
+    /// only read at the sites genotyped
+    pub estimated_seconds: Option<f64>,
+    /// Rough memory the parsed genome needs
+    pub memory_needed: Option<u64>,
+    /// Whether the file is too large to hold, so only the sites the
+    /// databases annotate will be kept
+    pub sites_only: bool,
+    /// Samples of a multi-sample file, one of which has to be chosen
+    pub samples: Vec<String>,
+    pub warning: Option<String>,
+}
+
+/// Check dropped files off the main thread and report on each
+///
+/// The three files of a PLINK fileset dropped together are reported once.
+pub fn handle_drop<R: Runtime>(app: &AppHandle<R>, paths: Vec<PathBuf>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut filesets: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            if let Ok(fileset) = Fileset::of(&path) {
+                if filesets.contains(&fileset.bed) {
+                    continue;
+                }
+                filesets.push(fileset.bed);
+            }
+            match plan(&path) {
+                Ok(plan) => events::FILE_READY.emit(&app, plan),
+                Err(error) => events::FILE_REJECTED.emit(
+                    &app,
+                    FileRejected {
+                        path: path.display().to_string(),
+                        error,
+                    },
+                ),
+            }
+        }
+    });
+}
+
+/// Check a file and work out what parsing it involves
+pub fn plan(path: &Path) -> Result<ParsePlan, GenomeForgeError> {
+    let metadata = fs::metadata(path)
+        .map_err(|_| GenomeForgeError::FileNotFound(Some(audit::file_name(path))))?;
+    if !metadata.is_file() {
+        return Err(GenomeForgeError::invalid(format!(
+            "{} is a folder; drop the genome file itself",
+            audit::file_name(path)
+        )));
+    }
+    let detection = detect::detect_format(path)?;
+    if detection.format == FileFormat::Unknown {
+        return Err(GenomeForgeError::UnsupportedFormat(format!(
+            "Unsupported file: {} is not in a format GenomeForge reads",
+            audit::file_name(path)
+        )));
+    }
+    let size = match Fileset::of(path) {
+        Ok(fileset) if detection.format == FileFormat::Plink => {
+            [fileset.bed, fileset.bim, fileset.fam]
+                .iter()
+                .filter_map(|part| fs::metadata(part).ok())
+                .map(|part| part.len())
+                .sum()
+        }
+        _ => metadata.len(),
+    };
+    let reads = alignment::is_alignment(path)?;
+    let memory_needed =
+        (!reads).then(|| system::parse_memory_estimate(size, detection.compression));
+    let memory = system::memory();
+    let sites_only = memory_needed.is_some_and(|needed| needed > system::memory_budget(memory));
+    let warning = if detection.confidence < LOW_CONFIDENCE {
+        Some(format!(
+            "This looks like a {} file, but GenomeForge is not sure; check the variant count once it is parsed.",
+            detection.format.as_str()
+        ))
+    } else if reads || sites_only {
+        None
+    } else {
+        system::preflight(size, detection.compression, memory)
+    };
+
+    Ok(ParsePlan {
+        path: path.display().to_string(),
+        file_type: detection.format,
+        compression: detection.compression,
+        confidence: detection.confidence,
+        size,
+        estimated_seconds: (!reads)
+            .then(|| system::parse_time_estimate(size, detection.compression)),
+        memory_needed,
+        sites_only,
+        samples: parser::list_samples(path)?,
+        warning,
+    })
+}
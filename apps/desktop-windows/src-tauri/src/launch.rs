@@ -0,0 +1,350 @@
+//! Genome files the app is launched with
+//!
+//! Opening an associated file from Explorer starts GenomeForge with the
+//! file's path as an argument; a `genomeforge://open?path=...` link starts
+//! it with the link. The paths wait until the frontend is listening and
+//! asks for them, then go through the same checks as dropped files.
+//!
+//! Only one instance runs per user session, so sensitive data is never
+//! held by two processes: a later launch passes its files to the running
+//! window over a named pipe and exits.
+
+use crate::{intake, notify};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Scheme of links that open a file in GenomeForge
+pub const URI_SCHEME: &str = "genomeforge";
+
+/// Extensions GenomeForge is offered for in Explorer's "Open with" menu:
+/// VCF, compressed VCF, and the text exports of 23andMe and AncestryDNA
+pub const OPEN_WITH_EXTENSIONS: [&str; 3] = [".vcf", ".gz", ".txt"];
+
+/// Files from the command line the frontend has not taken up yet
+static PENDING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Most bytes of paths one launch may pass to the running app
+const MAX_FORWARDED: u64 = 64 * 1024;
+
+/// Times a later launch tries to reach an instance still starting up
+#[cfg(windows)]
+const FORWARD_ATTEMPTS: u32 = 20;
+
+/// Wait between those tries
+#[cfg(windows)]
+const FORWARD_RETRY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Files named by command-line arguments, in plain paths or
+/// `genomeforge://` links; flags and malformed links are skipped
+///
+/// Relative paths are made absolute, since the running app they may be
+/// passed to has a working directory of its own.
+pub fn file_arguments<I: IntoIterator<Item = String>>(args: I) -> Vec<PathBuf> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    args.into_iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .filter_map(|arg| match arg.strip_prefix(URI_SCHEME) {
+            Some(link) if link.starts_with("://") => link_path(&link[3..]),
+            _ => Some(PathBuf::from(arg)),
+        })
+        .map(|path| cwd.join(path))
+        .collect()
+}
+
+/// Keep the files this process was started with for the frontend
+pub fn remember(paths: Vec<PathBuf>) {
+    if let Ok(mut pending) = PENDING.lock() {
+        pending.extend(paths);
+    }
+}
+
+/// Hand over the files the app was started with, once
+pub fn take_pending() -> Vec<PathBuf> {
+    PENDING
+        .lock()
+        .map(|mut pending| std::mem::take(&mut *pending))
+        .unwrap_or_default()
+}
+
+/// Bring the window forward and open files another launch passed on
+pub fn forwarded<R: Runtime>(app: &AppHandle<R>, paths: Vec<PathBuf>) {
+    if let Some(window) = app.get_webview_window(notify::MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    if !paths.is_empty() {
+        intake::handle_drop(app, paths);
+    }
+}
+
+/// Whether this is the first instance of the app in the user's session
+///
+/// The first instance holds a named mutex until it exits. Should the
+/// mutex not be created at all, the launch goes ahead rather than fail.
+#[cfg(windows)]
+pub fn first_instance() -> bool {
+    win::first_instance()
+}
+
+/// Pass files to the instance of the app that is already running, which
+/// brings its window forward even when there are none
+///
+/// An instance that has only just started is not listening yet, so it is
+/// tried again for a few seconds. Returns whether it took them.
+#[cfg(windows)]
+pub fn forward(paths: &[PathBuf]) -> bool {
+    use std::io::Write;
+
+    let Ok(message) = serde_json::to_vec(paths) else {
+        return false;
+    };
+    for attempt in 0..FORWARD_ATTEMPTS {
+        if attempt > 0 {
+            std::thread::sleep(FORWARD_RETRY);
+        }
+        let sent = std::fs::OpenOptions::new()
+            .write(true)
+            .open(pipe_name())
+            .and_then(|mut pipe| pipe.write_all(&message));
+        if sent.is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Tell the user a launch could not reach the running instance, in a
+/// message box since this launch has no window or log of its own
+#[cfg(windows)]
+pub fn alert(message: &str) {
+    win::alert(message)
+}
+
+/// Take files from later launches for as long as the app runs
+#[cfg(windows)]
+pub fn listen<R: Runtime>(app: &AppHandle<R>) {
+    use std::io::Read;
+
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        let mut pipe = match win::accept(&pipe_name()) {
+            Ok(pipe) => pipe,
+            Err(error) => {
+                tracing::warn!(%error, "stopped listening for later launches");
+                return;
+            }
+        };
+        let mut message = Vec::new();
+        if Read::by_ref(&mut pipe)
+            .take(MAX_FORWARDED)
+            .read_to_end(&mut message)
+            .is_err()
+        {
+            continue;
+        }
+        match serde_json::from_slice::<Vec<PathBuf>>(&message) {
+            Ok(paths) => forwarded(&app, paths),
+            Err(error) => tracing::warn!(%error, "ignored a malformed launch message"),
+        }
+    });
+}
+
+/// Register the file associations and link scheme for the current user
+#[cfg(windows)]
+pub fn register(exe: &std::path::Path) -> Result<(), String> {
+    win::register(&exe.display().to_string())
+}
+
+// Helper functions
+
+/// Pipe of the current user's instance; pipe names are shared by every
+/// session on the machine
+#[cfg(windows)]
+fn pipe_name() -> String {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    format!(r"\\.\pipe\GenomeForge-{}", user)
+}
+
+/// The `path` parameter of an `open?path=...` link
+fn link_path(link: &str) -> Option<PathBuf> {
+    let query = link.trim_end_matches('/').strip_prefix("open")?;
+    let query = query.strip_prefix('/').unwrap_or(query).strip_prefix('?')?;
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("path="))
+        .and_then(percent_decode)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'%' => {
+                let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+            }
+            b'+' => {
+                bytes.push(b' ');
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(windows)]
+mod win {
+    use super::{OPEN_WITH_EXTENSIONS, URI_SCHEME};
+    use std::ffi::OsStr;
+    use std::fs::File;
+    use std::iter::once;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{FromRawHandle, RawHandle};
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::ERROR_ALREADY_EXISTS;
+    use windows::Win32::Storage::FileSystem::PIPE_ACCESS_INBOUND;
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+    use windows::Win32::System::Registry::{
+        RegCloseKey, RegCreateKeyExW, RegSetValueExW, HKEY, HKEY_CURRENT_USER, KEY_WRITE,
+        REG_OPTION_NON_VOLATILE, REG_SZ,
+    };
+    use windows::Win32::System::Threading::CreateMutexW;
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONWARNING, MB_OK};
+
+    /// Per-user classes, which need no administrator rights to write
+    const CLASSES: &str = r"Software\Classes";
+
+    /// Program identifier the file types are associated through
+    const PROG_ID: &str = "GenomeForge.GenomeFile";
+
+    /// Mutex held by the running instance; `Local` names are per session
+    const INSTANCE_MUTEX: &str = r"Local\GenomeForge-instance";
+
+    pub fn first_instance() -> bool {
+        let name = wide(INSTANCE_MUTEX);
+        // The handle is left open, so the mutex lasts as long as the process
+        match unsafe { CreateMutexW(None, false, PCWSTR(name.as_ptr())) } {
+            Ok(_) => {
+                std::io::Error::last_os_error().raw_os_error()
+                    != Some(ERROR_ALREADY_EXISTS.0 as i32)
+            }
+            Err(_) => true,
+        }
+    }
+
+    pub fn alert(message: &str) {
+        let text = wide(message);
+        let caption = wide("GenomeForge");
+        unsafe {
+            MessageBoxW(
+                None,
+                PCWSTR(text.as_ptr()),
+                PCWSTR(caption.as_ptr()),
+                MB_OK | MB_ICONWARNING,
+            )
+        };
+    }
+
+    /// Associations are added to the "Open with" lists without taking over
+    /// the default app of `.gz` and `.txt` files
+    pub fn register(exe: &str) -> Result<(), String> {
+        let command = format!("\"{}\" \"%1\"", exe);
+        let prog_id = format!(r"{}\{}", CLASSES, PROG_ID);
+        set_value(&prog_id, None, "Genome data file")?;
+        set_value(
+            &format!(r"{}\DefaultIcon", prog_id),
+            None,
+            &format!("{},0", exe),
+        )?;
+        set_value(&format!(r"{}\shell\open\command", prog_id), None, &command)?;
+        for extension in OPEN_WITH_EXTENSIONS {
+            let key = format!(r"{}\{}\OpenWithProgids", CLASSES, extension);
+            set_value(&key, Some(PROG_ID), "")?;
+        }
+
+        let scheme = format!(r"{}\{}", CLASSES, URI_SCHEME);
+        set_value(&scheme, None, "URL:GenomeForge")?;
+        set_value(&scheme, Some("URL Protocol"), "")?;
+        set_value(&format!(r"{}\shell\open\command", scheme), None, &command)
+    }
+
+    /// Wait for the next launch to connect to the pipe
+    pub fn accept(name: &str) -> Result<File, String> {
+        let name = wide(name);
+        let handle = unsafe {
+            CreateNamedPipeW(
+                PCWSTR(name.as_ptr()),
+                PIPE_ACCESS_INBOUND,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                0,
+                super::MAX_FORWARDED as u32,
+                0,
+                None,
+            )
+        };
+        if handle.is_invalid() {
+            return Err(format!(
+                "Failed to create pipe: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        // The file owns the handle from here on and closes it when dropped
+        let pipe = unsafe { File::from_raw_handle(handle.0 as RawHandle) };
+        // Fails when the launch connected before this call, which is fine;
+        // any other failure shows up when the pipe is read
+        let _ = unsafe { ConnectNamedPipe(handle, None) };
+        Ok(pipe)
+    }
+
+    fn set_value(key: &str, name: Option<&str>, value: &str) -> Result<(), String> {
+        let key_name = wide(key);
+        let mut handle = HKEY::default();
+        unsafe {
+            RegCreateKeyExW(
+                HKEY_CURRENT_USER,
+                PCWSTR(key_name.as_ptr()),
+                0,
+                PCWSTR::null(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_WRITE,
+                None,
+                &mut handle,
+                None,
+            )
+        }
+        .map_err(|e| format!("Failed to create registry key {}: {}", key, e))?;
+
+        let value_name = name.map(wide);
+        let data: Vec<u8> = wide(value).iter().flat_map(|c| c.to_le_bytes()).collect();
+        let written = unsafe {
+            RegSetValueExW(
+                handle,
+                value_name
+                    .as_ref()
+                    .map_or(PCWSTR::null(), |name| PCWSTR(name.as_ptr())),
+                0,
+                REG_SZ,
+                Some(&data),
+            )
+        };
+        let _ = unsafe { RegCloseKey(handle) };
+        written.map_err(|e| format!("Failed to write registry value under {}: {}", key, e))
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        OsStr::new(text).encode_wide().chain(once(0)).collect()
+    }
+}
@@ -2,16 +2,66 @@
 //!
 //! Tauri-based desktop application for privacy-first genetic analysis.
 
+use genomeforge_core::annotation::delta::ReleaseDelta;
+use genomeforge_core::annotation::{AnnotationDatabases, DatabaseSlot};
+use genomeforge_core::{GenomeStore, TaskRegistry};
+use profiles::ParkedProfiles;
+use results::ResultStore;
 use serde::{Deserialize, Serialize};
+use streaming::FindingStreams;
 use tauri::Manager;
 
+mod audit;
 mod commands;
+mod databases;
+mod error;
+mod events;
+mod export;
+mod fhir;
+mod history;
+mod i18n;
+mod intake;
+mod launch;
+mod logging;
+mod medications;
+mod notes;
+mod notify;
+mod plugins;
+mod profiles;
+mod reanalysis;
+mod report;
+mod results;
+mod rule_sets;
+mod sessions;
+mod settings;
+mod streaming;
+mod system;
+mod tabular;
+mod templates;
+mod trace;
+mod updater;
+mod vcf;
+mod watcher;
+mod watchlist;
 
 /// Application state shared across windows
 #[derive(Default)]
 pub struct AppState {
-    /// Whether genome data has been loaded
-    pub genome_loaded: std::sync::atomic::AtomicBool,
+    /// Parsed variants of the currently loaded genome
+    pub genome: GenomeStore,
+    /// Cancellable background tasks in progress
+    pub tasks: TaskRegistry,
+    /// Offline annotation databases loaded so far
+    pub databases: AnnotationDatabases,
+    /// Result of the latest analysis of the loaded genome
+    pub results: ResultStore,
+    /// What changed in ClinVar since the release installed before the
+    /// latest one, until the result is brought up to date with it
+    pub clinvar_changes: DatabaseSlot<ReleaseDelta>,
+    /// Genomes and results of the profiles not active
+    pub profiles: ParkedProfiles,
+    /// Findings of analyses in progress on their way to the frontend
+    pub finding_streams: FindingStreams,
 }
 
 /// Result type for genome analysis
@@ -26,6 +76,17 @@ pub struct AnalysisResult {
 /// Configuration for the application
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // A launch while the app is open hands its files to the open window
+    // and exits, rather than load a second copy of the user's data
+    let launch_files = launch::file_arguments(std::env::args().skip(1));
+    #[cfg(windows)]
+    if !launch::first_instance() {
+        if !launch::forward(&launch_files) {
+            launch::alert("GenomeForge is already running but did not respond");
+        }
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -33,8 +94,23 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .setup(|app| {
+            if let Err(error) = logging::init(app.handle()) {
+                // Without a logger, stderr is the only place left to say so
+                eprintln!("{}", error);
+            }
+
             // Initialize app state
             app.manage(AppState::default());
+            launch::remember(launch_files);
+
+            // Databases can take a while to index, so load them off the main thread
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                for error in databases::load_installed(&handle) {
+                    tracing::warn!(%error, "database failed to load");
+                }
+            });
+            watcher::start(app.handle());
 
             // Set up Windows-specific features
             #[cfg(windows)]
@@ -44,22 +120,115 @@ pub fn run() {
 
             Ok(())
         })
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Focused(true) => notify::window_focused(window.app_handle()),
+            tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, .. }) => {
+                intake::handle_drop(window.app_handle(), paths.clone())
+            }
+            _ => {}
+        })
         .invoke_handler(tauri::generate_handler![
             commands::get_app_version,
             commands::get_system_info,
+            commands::run_self_benchmark,
+            commands::estimate_workload,
             commands::parse_genome_file,
+            commands::list_samples,
+            commands::get_file_fingerprint,
+            commands::get_data_quality,
             commands::analyze_variants,
+            commands::start_analysis,
+            commands::ack_findings,
+            commands::batch_analyze,
+            commands::reanalyze_database_changes,
+            commands::diff_analyses,
+            commands::list_saved_analyses,
+            commands::query_saved_findings,
+            commands::load_saved_analysis,
+            commands::diff_saved_analyses,
+            commands::delete_saved_analysis,
+            commands::bookmark_finding,
+            commands::set_finding_note,
+            commands::list_finding_notes,
+            commands::list_watchlist,
+            commands::add_to_watchlist,
+            commands::remove_from_watchlist,
+            commands::check_watchlist,
+            commands::analyze_trio,
+            commands::compare_genomes,
+            commands::merge_genomes,
+            commands::browse_conditions,
+            commands::search_findings,
+            commands::filter_findings,
+            commands::list_rule_sets,
+            commands::save_rule_set,
+            commands::delete_rule_set,
+            commands::apply_rule_set,
+            commands::get_finding_details,
+            commands::explain_finding,
+            commands::get_drug_guideline,
+            commands::review_medications,
+            commands::get_findings_page,
+            commands::save_session,
+            commands::load_session,
+            commands::list_sessions,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::switch_profile,
+            commands::compute_prs,
+            commands::query_region,
+            commands::estimate_ancestry,
+            commands::predict_blood_type,
+            commands::estimate_kinship,
             commands::export_report,
+            commands::decrypt_export,
+            commands::list_report_templates,
+            commands::preview_report_template,
+            commands::select_report_template,
+            commands::save_report_template,
+            commands::list_plugins,
+            commands::enable_plugin,
+            commands::disable_plugin,
             commands::get_database_status,
+            commands::cancel_task,
+            commands::list_tasks,
+            commands::update_databases,
+            commands::verify_databases,
+            commands::import_database,
+            commands::import_database_pack,
+            commands::get_references,
+            commands::import_reference,
+            commands::download_reference,
+            commands::verify_reference,
+            commands::remove_reference,
+            commands::get_recent_logs,
+            commands::create_diagnostic_bundle,
+            commands::get_audit_log,
+            commands::purge_all_data,
+            commands::get_settings,
+            commands::update_settings,
+            commands::set_watch_folder,
+            commands::open_launch_files,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
 #[cfg(windows)]
-fn setup_windows<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<(), Box<dyn std::error::Error>> {
-    // Windows-specific setup can go here
-    // For example: Windows notification configuration, etc.
-    let _ = app; // Silence unused variable warning for now
+fn setup_windows<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    launch::listen(app);
+    // Registered on every start, so the associations follow the exe if it moves
+    match std::env::current_exe() {
+        Ok(exe) => {
+            if let Err(error) = launch::register(&exe) {
+                tracing::warn!(%error, "failed to register file associations");
+            }
+        }
+        Err(error) => tracing::warn!(%error, "failed to locate the executable"),
+    }
     Ok(())
 }
@@ -0,0 +1,121 @@
+//! Application log
+//!
+//! `tracing` events are written to `<app log dir>/genomeforge.log` as JSON
+//! lines, scrubbed of genetic data first (see
+//! [`genomeforge_core::diagnostics`]). Spans are not recorded.
+
+use genomeforge_core::diagnostics::{LogEntry, LogFile, LogLevel};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Least severe level written; debug builds also log debug events
+const MAX_LEVEL: Level = if cfg!(debug_assertions) {
+    Level::DEBUG
+} else {
+    Level::INFO
+};
+
+/// Directory holding the logs
+pub fn log_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_log_dir()
+        .map_err(|e| format!("Failed to resolve log directory: {}", e))
+}
+
+/// Send `tracing` events to the application log
+///
+/// Without a log directory the app runs unlogged; the error is returned
+/// for the caller to report elsewhere.
+pub fn init<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let log = LogFile::open(&log_dir(app)?)?;
+    let logger = Logger {
+        log: Mutex::new(log),
+        next_span: AtomicU64::new(1),
+    };
+    tracing::subscriber::set_global_default(logger)
+        .map_err(|e| format!("Failed to install logger: {}", e))
+}
+
+/// Writes events to the log file
+struct Logger {
+    log: Mutex<LogFile>,
+    next_span: AtomicU64,
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= MAX_LEVEL
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let entry = LogEntry::new(
+            level(metadata.level()),
+            metadata.target(),
+            &fields.message,
+            fields.values,
+        );
+        // A log that cannot be written must not take the app down with it
+        if let Ok(mut log) = self.log.lock() {
+            let _ = log.append(&entry);
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// Message and other fields of an event
+#[derive(Default)]
+struct Fields {
+    message: String,
+    values: Vec<(String, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format!("{:?}", value));
+    }
+}
+
+impl Fields {
+    fn push(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = value,
+            name => self.values.push((name.to_string(), value)),
+        }
+    }
+}
+
+// Helper functions
+
+fn level(level: &Level) -> LogLevel {
+    match *level {
+        Level::ERROR => LogLevel::Error,
+        Level::WARN => LogLevel::Warn,
+        Level::INFO => LogLevel::Info,
+        Level::DEBUG => LogLevel::Debug,
+        Level::TRACE => LogLevel::Trace,
+    }
+}
@@ -0,0 +1,102 @@
+//! Reviewing a medication list against the latest analysis
+//!
+//! Every drug response of the latest result, whether from a CPIC
+//! recommendation for a diplotype or a PharmGKB annotation of one variant,
+//! is matched to the medications the user entered. The medications are
+//! then listed most urgent first, so a drug to avoid at the user's
+//! phenotype is at the top whichever gene it involves. Carrying an HLA
+//! allele that makes a medication dangerous always puts it at the top.
+
+use crate::commands::{AnalysisResultData, DrugResponse};
+use genomeforge_core::annotation::hla::HlaCall;
+use genomeforge_core::annotation::medications::{self, ReviewPriority};
+use serde::Serialize;
+
+/// One entered medication and the drug responses found for it
+#[derive(Debug, Serialize)]
+pub struct MedicationReview {
+    /// As the user entered it
+    pub medication: String,
+    /// Generic name it was matched by
+    pub drug: String,
+    /// Most urgent priority of its responses; unset when no gene tested
+    /// is known to affect it
+    pub priority: Option<ReviewPriority>,
+    /// Genes behind the responses
+    pub genes: Vec<String>,
+    /// Most urgent first
+    pub responses: Vec<ReviewedResponse>,
+    /// HLA risk alleles carried that make the drug dangerous
+    pub hla_risks: Vec<HlaCall>,
+}
+
+/// A drug response with the priority it was given
+#[derive(Debug, Serialize)]
+pub struct ReviewedResponse {
+    pub priority: ReviewPriority,
+    #[serde(flatten)]
+    pub response: DrugResponse,
+}
+
+/// Review a medication list, most urgent first
+///
+/// Medications naming the same generic drug are reviewed once. Those
+/// without responses keep the order they were entered in, after the rest.
+pub fn review(result: &AnalysisResultData, entered: &[String]) -> Vec<MedicationReview> {
+    let mut reviews: Vec<MedicationReview> = Vec::new();
+    for medication in entered {
+        let drug = medications::generic_name(medication);
+        if drug.is_empty() || reviews.iter().any(|review| review.drug == drug) {
+            continue;
+        }
+        let mut responses: Vec<ReviewedResponse> = result
+            .drug_responses
+            .iter()
+            .filter(|response| medications::names_drug(&response.drug, &drug))
+            .map(|response| ReviewedResponse {
+                priority: priority(response),
+                response: response.clone(),
+            })
+            .collect();
+        responses.sort_by_key(|reviewed| reviewed.priority);
+        let hla_risks: Vec<HlaCall> = result
+            .hla_risks
+            .iter()
+            .filter(|call| call.is_carrier())
+            .filter(|call| call.drugs.contains(&drug))
+            .cloned()
+            .collect();
+        let mut genes: Vec<String> = responses
+            .iter()
+            .flat_map(|reviewed| reviewed.response.gene.split(", "))
+            .chain(hla_risks.iter().map(|call| call.gene.as_str()))
+            .map(str::to_string)
+            .collect();
+        genes.sort();
+        genes.dedup();
+        reviews.push(MedicationReview {
+            medication: medication.trim().to_string(),
+            drug,
+            priority: if hla_risks.is_empty() {
+                responses.first().map(|reviewed| reviewed.priority)
+            } else {
+                Some(ReviewPriority::High)
+            },
+            genes,
+            responses,
+            hla_risks,
+        });
+    }
+    // Stable, so equal priorities keep the order they were entered in
+    reviews.sort_by_key(|review| (review.priority.is_none(), review.priority));
+    reviews
+}
+
+// Helper functions
+
+fn priority(response: &DrugResponse) -> ReviewPriority {
+    response.guideline.as_ref().map_or_else(
+        || ReviewPriority::of_evidence(response.evidence_level),
+        ReviewPriority::of_recommendation,
+    )
+}
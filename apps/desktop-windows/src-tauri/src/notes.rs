@@ -0,0 +1,41 @@
+//! Bookmarks and notes on the active profile's findings
+//!
+//! The notebook is a file in the profile's directory, sealed under the
+//! device key. Entries are made on findings of the latest analysis and
+//! follow them into later ones; exports include them when asked to.
+
+use crate::{profiles, sessions};
+use genomeforge_core::crypto::KeySource;
+use genomeforge_core::notes::{self, NoteBook};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+
+/// Held while the notebook is changed, so two edits at once both land
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// The active profile's notebook
+pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<NoteBook, String> {
+    let key = sessions::device_key(&sessions::session_dir(app)?)?;
+    NoteBook::open(&notebook_path(app)?, KeySource::Device(&key))
+}
+
+/// Change the active profile's notebook and save it
+pub fn update<R: Runtime, T>(
+    app: &AppHandle<R>,
+    change: impl FnOnce(&mut NoteBook) -> Result<T, String>,
+) -> Result<T, String> {
+    let _writing = WRITING.lock().map_err(|_| "Notebook lock poisoned")?;
+    let key = sessions::device_key(&sessions::session_dir(app)?)?;
+    let path = notebook_path(app)?;
+    let mut book = NoteBook::open(&path, KeySource::Device(&key))?;
+    let changed = change(&mut book)?;
+    book.save(&path, KeySource::Device(&key))?;
+    Ok(changed)
+}
+
+// Helper functions
+
+fn notebook_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_dir(app).map(|dir| dir.join(notes::FILE_NAME))
+}
@@ -0,0 +1,54 @@
+//! Native notifications for work finished in the background
+//!
+//! A finished analysis is announced with a toast when the window is not
+//! in front. Toasts on Windows carry no click callback; clicking one
+//! brings the app's window forward, so the first time the window gains
+//! focus after a toast, it is told to show the results.
+
+use crate::events;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+/// Label of the application window
+pub const MAIN_WINDOW: &str = "main";
+
+/// Set while a toast about finished results has not been followed up
+static RESULTS_WAITING: AtomicBool = AtomicBool::new(false);
+
+/// Announce a finished analysis, unless the user is looking at the app
+pub fn analysis_complete<R: Runtime>(app: &AppHandle<R>, clinical_findings: usize) {
+    let focused = app
+        .get_webview_window(MAIN_WINDOW)
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false);
+    if focused {
+        return;
+    }
+    let body = match clinical_findings {
+        1 => "Analysis complete: 1 clinical finding".to_string(),
+        n => format!("Analysis complete: {} clinical findings", n),
+    };
+    match app
+        .notification()
+        .builder()
+        .title("GenomeForge")
+        .body(body)
+        .show()
+    {
+        Ok(()) => RESULTS_WAITING.store(true, Ordering::SeqCst),
+        Err(error) => tracing::warn!(%error, "failed to show notification"),
+    }
+}
+
+/// Follow up a toast once the window is brought forward
+pub fn window_focused<R: Runtime>(app: &AppHandle<R>) {
+    if !RESULTS_WAITING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Some(window) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    events::SHOW_RESULTS.emit(app, ());
+}
@@ -0,0 +1,141 @@
+//! Installed analysis plugins
+//!
+//! Plugins live in `<app data>/plugins`, one directory each, and run with
+//! every analysis once enabled; the settings keep which are. A plugin that
+//! fails to load or to run is skipped, so a broken one cannot stop an
+//! analysis.
+
+use crate::settings;
+use genomeforge_core::plugin::{AnalysisPlugin, PluginManifest, WasmPlugin};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// An installed plugin, from `list_plugins`
+#[derive(Debug, Serialize)]
+pub struct PluginEntry {
+    /// Name of the plugin's directory
+    pub directory: String,
+    /// Unset when the manifest cannot be read
+    pub manifest: Option<PluginManifest>,
+    pub enabled: bool,
+    /// Why the plugin cannot be loaded, when it cannot
+    pub error: Option<String>,
+}
+
+/// How a plugin fared in an analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRun {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// Findings kept after the consent policy
+    pub findings: usize,
+    /// Why the plugin failed, when it did
+    pub error: Option<String>,
+}
+
+/// Directory holding the installed plugins
+pub fn plugin_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("plugins"))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Every installed plugin, by directory name
+pub fn list<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<PluginEntry>, String> {
+    let enabled = settings::current(app).enabled_plugins;
+    let mut entries: Vec<PluginEntry> = installed(&plugin_dir(app)?)?
+        .into_iter()
+        .map(|(directory, loaded)| match loaded {
+            Ok(plugin) => PluginEntry {
+                directory,
+                enabled: enabled.contains(&plugin.manifest().id),
+                manifest: Some(plugin.manifest().clone()),
+                error: None,
+            },
+            Err(error) => PluginEntry {
+                directory,
+                manifest: None,
+                enabled: false,
+                error: Some(error),
+            },
+        })
+        .collect();
+    entries.sort_by(|a, b| a.directory.cmp(&b.directory));
+    Ok(entries)
+}
+
+/// The enabled plugins that load
+pub fn load_enabled<R: Runtime>(app: &AppHandle<R>) -> Vec<Arc<dyn AnalysisPlugin>> {
+    let enabled = settings::current(app).enabled_plugins;
+    if enabled.is_empty() {
+        return Vec::new();
+    }
+    let installed = match plugin_dir(app).and_then(|dir| installed(&dir)) {
+        Ok(installed) => installed,
+        Err(error) => {
+            tracing::warn!(%error, "plugins not loaded");
+            return Vec::new();
+        }
+    };
+    installed
+        .into_iter()
+        .filter_map(|(directory, loaded)| match loaded {
+            Ok(plugin) if enabled.contains(&plugin.manifest().id) => {
+                Some(Arc::new(plugin) as Arc<dyn AnalysisPlugin>)
+            }
+            Ok(_) => None,
+            Err(error) => {
+                tracing::warn!(%directory, %error, "plugin not loaded");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Enable or disable an installed plugin for later analyses
+pub fn set_enabled<R: Runtime>(app: &AppHandle<R>, id: &str, enabled: bool) -> Result<(), String> {
+    let mut settings = settings::read(app)?;
+    if enabled {
+        let found = installed(&plugin_dir(app)?)?
+            .into_iter()
+            .find(|(_, loaded)| {
+                loaded
+                    .as_ref()
+                    .is_ok_and(|plugin| plugin.manifest().id == id)
+            });
+        if found.is_none() {
+            return Err(format!("No plugin {} is installed", id));
+        }
+        settings.enabled_plugins.insert(id.to_string());
+    } else {
+        settings.enabled_plugins.remove(id);
+    }
+    settings::write(app, &settings)
+}
+
+// Helper functions
+
+/// A plugin directory's name with the plugin it holds, or why it cannot
+/// be loaded
+type Installed = (String, Result<WasmPlugin, String>);
+
+fn installed(dir: &Path) -> Result<Vec<Installed>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    Ok(entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let directory = entry.file_name().to_string_lossy().into_owned();
+            (directory, WasmPlugin::load(&entry.path()))
+        })
+        .collect())
+}
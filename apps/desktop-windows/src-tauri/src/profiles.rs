@@ -0,0 +1,351 @@
+//! Profiles of the people whose genomes are analyzed
+//!
+//! A family sharing one installation keeps each person's data apart: every
+//! profile has its own sessions, report templates and file fingerprints in
+//! `<app data>/profiles/<id>`, and its own genome cache in the local app
+//! data directory. Sessions are sealed with a device key kept in the
+//! profile's session directory, so no profile can read another's. The
+//! active profile's id is remembered in `<app data>/profiles/active`.
+//!
+//! Data saved before profiles existed is moved into the default profile
+//! the first time it is used. Genomes and results of profiles that are not
+//! active stay in memory, so switching back does not reload them.
+
+use crate::commands::AnalysisResultData;
+use genomeforge_core::crypto;
+use genomeforge_core::LoadedGenome;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Id of the profile that exists from the start
+pub const DEFAULT_ID: &str = "default";
+
+/// Name of the default profile until it is renamed
+const DEFAULT_NAME: &str = "Default";
+
+/// Description of a profile inside its directory
+const PROFILE_FILE: &str = "profile.json";
+
+/// File in the profile root holding the active profile's id
+const ACTIVE_FILE: &str = "active";
+
+/// Longest profile name accepted
+const MAX_NAME_LEN: usize = 64;
+
+/// Profile data that lived directly in the app data directory before
+/// profiles existed
+const LEGACY_DATA: [&str; 3] = ["sessions", "report-templates", "fingerprints.bin"];
+
+/// Profile data that lived directly in the local app data directory
+const LEGACY_LOCAL_DATA: [&str; 1] = ["cache"];
+
+/// A person whose data is kept apart from everyone else's
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Lowercase letters and digits, fixed when the profile is created
+    pub id: String,
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+/// A profile as listed to the user
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileEntry {
+    #[serde(flatten)]
+    pub profile: Profile,
+    pub active: bool,
+    /// Whether a genome is loaded in the profile
+    pub genome_loaded: bool,
+}
+
+/// Genome and results of a profile that is not active
+#[derive(Debug, Default)]
+pub struct Parked {
+    pub genome: Option<Arc<LoadedGenome>>,
+    pub results: Option<Arc<AnalysisResultData>>,
+}
+
+/// Genomes and results of the profiles that are not active
+#[derive(Debug, Default)]
+pub struct ParkedProfiles {
+    parked: Mutex<HashMap<String, Parked>>,
+}
+
+impl ParkedProfiles {
+    /// Keep a profile's genome and results while another is active
+    pub fn park(&self, id: &str, parked: Parked) {
+        self.lock().insert(id.to_string(), parked);
+    }
+
+    /// Take back what was parked for a profile
+    pub fn unpark(&self, id: &str) -> Parked {
+        self.lock().remove(id).unwrap_or_default()
+    }
+
+    /// The genome loaded in a profile that is not active
+    pub fn genome(&self, id: &str) -> Option<Arc<LoadedGenome>> {
+        self.lock().get(id).and_then(|parked| parked.genome.clone())
+    }
+
+    /// Take what was parked for every profile
+    pub fn take_all(&self) -> Vec<Parked> {
+        self.lock().drain().map(|(_, parked)| parked).collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Parked>> {
+        self.parked.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Directory holding every profile
+pub fn profile_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("profiles"))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Id of the active profile; the default one unless another was chosen
+pub fn active_id<R: Runtime>(app: &AppHandle<R>) -> Result<String, String> {
+    let root = profile_root(app)?;
+    let id = fs::read_to_string(root.join(ACTIVE_FILE))
+        .map(|id| id.trim().to_string())
+        .unwrap_or_default();
+    // A profile deleted behind the app's back falls back to the default
+    if valid_id(&id) && root.join(&id).join(PROFILE_FILE).exists() {
+        return Ok(id);
+    }
+    ensure_default(app)?;
+    Ok(DEFAULT_ID.to_string())
+}
+
+/// Directory of the active profile's data
+pub fn active_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    Ok(profile_root(app)?.join(active_id(app)?))
+}
+
+/// Directory of the active profile's cached data, which need not roam
+pub fn active_local_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    Ok(local_root(app)?.join(active_id(app)?))
+}
+
+/// Everywhere profile data is saved: the directories of every profile
+/// and their caches, and data left from before profiles existed
+pub fn data_paths<R: Runtime>(app: &AppHandle<R>) -> Result<Vec<PathBuf>, String> {
+    let root = profile_root(app)?;
+    let local = local_root(app)?;
+    let mut paths = Vec::new();
+    if let Some(data) = root.parent() {
+        paths.extend(LEGACY_DATA.iter().map(|name| data.join(name)));
+    }
+    if let Some(data) = local.parent() {
+        paths.extend(LEGACY_LOCAL_DATA.iter().map(|name| data.join(name)));
+    }
+    paths.push(root);
+    paths.push(local);
+    Ok(paths)
+}
+
+/// Every profile, sorted by name
+pub fn list<R: Runtime>(
+    app: &AppHandle<R>,
+    parked: &ParkedProfiles,
+) -> Result<Vec<ProfileEntry>, String> {
+    let active = active_id(app)?;
+    let root = profile_root(app)?;
+    let entries =
+        fs::read_dir(&root).map_err(|e| format!("Failed to read {}: {}", root.display(), e))?;
+    let mut profiles: Vec<ProfileEntry> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|dir| read_profile(&dir).ok())
+        .map(|profile| ProfileEntry {
+            active: profile.id == active,
+            genome_loaded: parked.genome(&profile.id).is_some(),
+            profile,
+        })
+        .collect();
+    profiles.sort_by(|a, b| {
+        a.profile
+            .name
+            .to_lowercase()
+            .cmp(&b.profile.name.to_lowercase())
+    });
+    Ok(profiles)
+}
+
+/// Create a profile; it is not made active
+pub fn create<R: Runtime>(app: &AppHandle<R>, name: &str) -> Result<Profile, String> {
+    let name = valid_name(name)?;
+    let root = profile_root(app)?;
+    ensure_default(app)?;
+    let id = loop {
+        let id: String = crypto::random_bytes::<4>()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        if !root.join(&id).exists() {
+            break id;
+        }
+    };
+    let profile = Profile {
+        id,
+        name,
+        created_at: now(),
+    };
+    write_profile(&root.join(&profile.id), &profile)?;
+    Ok(profile)
+}
+
+/// Give a profile another name
+pub fn rename<R: Runtime>(app: &AppHandle<R>, id: &str, name: &str) -> Result<Profile, String> {
+    let name = valid_name(name)?;
+    let dir = profile_dir(app, id)?;
+    let mut profile = read_profile(&dir)?;
+    profile.name = name;
+    write_profile(&dir, &profile)?;
+    Ok(profile)
+}
+
+/// Delete a profile and everything saved in it
+///
+/// The active profile cannot be deleted; switch to another first.
+pub fn delete<R: Runtime>(app: &AppHandle<R>, id: &str) -> Result<(), String> {
+    let dir = profile_dir(app, id)?;
+    if id == active_id(app)? {
+        return Err(
+            "The active profile cannot be deleted; switch to another profile first".to_string(),
+        );
+    }
+    if id == DEFAULT_ID {
+        return Err("The default profile cannot be deleted".to_string());
+    }
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete profile: {}", e))?;
+    let local = local_root(app)?.join(id);
+    if local.exists() {
+        fs::remove_dir_all(&local).map_err(|e| format!("Failed to delete profile cache: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Remember a profile as the active one
+pub fn set_active<R: Runtime>(app: &AppHandle<R>, id: &str) -> Result<Profile, String> {
+    let profile = read_profile(&profile_dir(app, id)?)?;
+    let root = profile_root(app)?;
+    fs::write(root.join(ACTIVE_FILE), id)
+        .map_err(|e| format!("Failed to switch profile: {}", e))?;
+    Ok(profile)
+}
+
+// Helper functions
+
+fn local_root<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_local_data_dir()
+        .map(|dir| dir.join("profiles"))
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// Directory of an existing profile
+fn profile_dir<R: Runtime>(app: &AppHandle<R>, id: &str) -> Result<PathBuf, String> {
+    if id == DEFAULT_ID {
+        ensure_default(app)?;
+    }
+    let dir = profile_root(app)?.join(id);
+    if !valid_id(id) || !dir.join(PROFILE_FILE).exists() {
+        return Err(format!("Unknown profile: {}", id));
+    }
+    Ok(dir)
+}
+
+/// Create the default profile, moving data saved before profiles into it
+fn ensure_default<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let root = profile_root(app)?;
+    let dir = root.join(DEFAULT_ID);
+    if dir.join(PROFILE_FILE).exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let data = root.parent().map(Path::to_path_buf).unwrap_or_default();
+    move_legacy(&data, &dir, &LEGACY_DATA)?;
+    let local = local_root(app)?;
+    move_legacy(
+        local.parent().unwrap_or(&local),
+        &local.join(DEFAULT_ID),
+        &LEGACY_LOCAL_DATA,
+    )?;
+    write_profile(
+        &dir,
+        &Profile {
+            id: DEFAULT_ID.to_string(),
+            name: DEFAULT_NAME.to_string(),
+            created_at: now(),
+        },
+    )
+}
+
+fn move_legacy(from: &Path, to: &Path, names: &[&str]) -> Result<(), String> {
+    for name in names {
+        let old = from.join(name);
+        if old.exists() && !to.join(name).exists() {
+            fs::create_dir_all(to)
+                .and_then(|_| fs::rename(&old, to.join(name)))
+                .map_err(|e| {
+                    format!(
+                        "Failed to move {} into the default profile: {}",
+                        old.display(),
+                        e
+                    )
+                })?;
+        }
+    }
+    Ok(())
+}
+
+fn read_profile(dir: &Path) -> Result<Profile, String> {
+    let json = fs::read_to_string(dir.join(PROFILE_FILE))
+        .map_err(|e| format!("Failed to read profile: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Profile is corrupt: {}", e))
+}
+
+fn write_profile(dir: &Path, profile: &Profile) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(profile)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(dir.join(PROFILE_FILE), json))
+        .map_err(|e| format!("Failed to save profile: {}", e))
+}
+
+/// Also a safe directory name
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+}
+
+fn valid_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!(
+            "Profile name must be at most {} characters",
+            MAX_NAME_LEN
+        ));
+    }
+    Ok(name.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,177 @@
+//! Bringing a result up to date with a new ClinVar release
+//!
+//! After a ClinVar update only the variants the changed records can match
+//! are annotated again, once against each release. The findings the old
+//! release gave them are swapped out of the latest result for those the
+//! new one gives, and comparing the two tells the user what the update
+//! found: new findings, reclassified ones and findings no longer reported.
+
+use crate::commands::{self, AnalysisResultData, ClinicalFinding, DatabaseRelease};
+use crate::results::variant_findings;
+use genomeforge_core::annotation::manager::DatabaseKind;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// What a ClinVar update changed in the findings
+#[derive(Debug, Clone, Serialize)]
+pub struct FindingChanges {
+    /// Release dates of the older and newer release, as YYYY-MM-DD
+    pub from_release: Option<String>,
+    pub to_release: Option<String>,
+    /// Findings the new release reports and the old one did not
+    pub new_findings: Vec<ClinicalFinding>,
+    pub reclassified: Vec<Reclassification>,
+    /// Findings the new release no longer reports
+    pub removed_findings: Vec<ClinicalFinding>,
+}
+
+/// A finding whose classification or review status changed
+#[derive(Debug, Clone, Serialize)]
+pub struct Reclassification {
+    pub before: ClinicalFinding,
+    pub after: ClinicalFinding,
+}
+
+/// Identity of a finding between two annotations of the same genome
+type FindingKey = (Option<String>, Option<u64>, Option<u64>, String);
+
+/// The latest result with the findings `before` gave the touched variants
+/// replaced by those of `after`
+///
+/// ACMG and carrier findings are reported per gene, and every variant of a
+/// touched gene was annotated again, so those genes are replaced whole.
+/// Counts of what was withheld are adjusted by the difference between the
+/// two annotations.
+pub fn merge(
+    latest: &AnalysisResultData,
+    before: &AnalysisResultData,
+    after: &AnalysisResultData,
+) -> AnalysisResultData {
+    let stale: HashSet<FindingKey> = before.clinical_findings.iter().map(key).collect();
+    let mut clinical_findings: Vec<ClinicalFinding> = latest
+        .clinical_findings
+        .iter()
+        .filter(|finding| !stale.contains(&key(finding)))
+        .chain(&after.clinical_findings)
+        .cloned()
+        .collect();
+    commands::sort_clinical_findings(&mut clinical_findings);
+
+    let acmg_genes: HashSet<&str> = before
+        .acmg_findings
+        .iter()
+        .chain(&after.acmg_findings)
+        .map(|finding| finding.gene.as_str())
+        .collect();
+    let acmg_findings = latest
+        .acmg_findings
+        .iter()
+        .filter(|finding| !acmg_genes.contains(finding.gene.as_str()))
+        .chain(&after.acmg_findings)
+        .cloned()
+        .collect();
+    let carrier_genes: HashSet<&str> = before
+        .carrier_findings
+        .iter()
+        .chain(&after.carrier_findings)
+        .map(|finding| finding.gene.as_str())
+        .collect();
+    let carrier_findings = latest
+        .carrier_findings
+        .iter()
+        .filter(|finding| !carrier_genes.contains(finding.gene.as_str()))
+        .chain(&after.carrier_findings)
+        .cloned()
+        .collect();
+
+    let adjust = |count: fn(&AnalysisResultData) -> usize| {
+        (count(latest) + count(after)).saturating_sub(count(before))
+    };
+    let mut summary = latest.summary.clone();
+    summary.clinical_count = clinical_findings.len();
+    summary.actionable_findings = adjust(|result| result.summary.actionable_findings);
+    summary.common_variants_suppressed = adjust(|result| result.summary.common_variants_suppressed);
+    summary.secondary_findings_withheld =
+        adjust(|result| result.summary.secondary_findings_withheld);
+    summary.late_onset_withheld = adjust(|result| result.summary.late_onset_withheld);
+    summary.consent_withheld = adjust(|result| result.summary.consent_withheld);
+    summary.clinvar_release = after.summary.clinvar_release.clone();
+    // The other databases are as they were for the latest result
+    let is_clinvar = |release: &DatabaseRelease| release.database == DatabaseKind::ClinVar;
+    summary.databases.retain(|release| !is_clinvar(release));
+    let clinvar = after
+        .summary
+        .databases
+        .iter()
+        .find(|release| is_clinvar(release));
+    summary.databases.splice(0..0, clinvar.cloned());
+
+    AnalysisResultData {
+        clinical_findings,
+        acmg_findings,
+        carrier_findings,
+        apoe: latest.apoe.clone(),
+        drug_responses: latest.drug_responses.clone(),
+        diplotypes: latest.diplotypes.clone(),
+        trait_associations: latest.trait_associations.clone(),
+        haplogroups: latest.haplogroups.clone(),
+        hla_risks: latest.hla_risks.clone(),
+        nutrition: latest.nutrition.clone(),
+        structural_findings: latest.structural_findings.clone(),
+        plugin_findings: latest.plugin_findings.clone(),
+        custom_findings: latest.custom_findings.clone(),
+        watchlist: latest.watchlist.clone(),
+        notes: latest.notes.clone(),
+        summary,
+    }
+}
+
+/// The findings of every section `after` adds, reclassifies or drops
+/// compared with `before`
+pub fn compare(before: &AnalysisResultData, after: &AnalysisResultData) -> FindingChanges {
+    let earlier: HashMap<FindingKey, &ClinicalFinding> =
+        variant_findings(before).map(|f| (key(f), f)).collect();
+    let later: HashMap<FindingKey, &ClinicalFinding> =
+        variant_findings(after).map(|f| (key(f), f)).collect();
+
+    let mut new_findings = Vec::new();
+    let mut reclassified = Vec::new();
+    for finding in variant_findings(after) {
+        match earlier.get(&key(finding)) {
+            None => new_findings.push(finding.clone()),
+            Some(old)
+                if old.significance_label != finding.significance_label
+                    || old.review_status != finding.review_status =>
+            {
+                reclassified.push(Reclassification {
+                    before: (*old).clone(),
+                    after: finding.clone(),
+                });
+            }
+            Some(_) => {}
+        }
+    }
+    let removed_findings = variant_findings(before)
+        .filter(|finding| !later.contains_key(&key(finding)))
+        .cloned()
+        .collect();
+
+    FindingChanges {
+        from_release: before.summary.clinvar_release.clone(),
+        to_release: after.summary.clinvar_release.clone(),
+        new_findings,
+        reclassified,
+        removed_findings,
+    }
+}
+
+// Helper functions
+
+fn key(finding: &ClinicalFinding) -> FindingKey {
+    (
+        finding.chromosome.clone(),
+        finding.position,
+        finding.variation_id,
+        finding.rsid.clone(),
+    )
+}
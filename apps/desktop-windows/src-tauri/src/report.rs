@@ -0,0 +1,1013 @@
+//! The report model of an analysis
+//!
+//! Builds the sections every export format renders: a summary, one section
+//! per finding category, and the methodology and limitations behind them.
+//! Which of them a report holds, and in which order, is up to its
+//! template, except that the provenance appendix naming the database
+//! releases behind the findings ends every report whose template leaves
+//! it out. Text comes from the string catalog of the export's locale,
+//! while condition names and other database text stay as annotated.
+
+use crate::commands::{AnalysisResultData, ClinicalFinding, DrugResponse, StructuralFinding};
+use crate::export::ExportInfo;
+use crate::i18n;
+use crate::results::serialized_name;
+use genomeforge_core::annotation::acmg;
+use genomeforge_core::annotation::haplogroup::HaplogroupCall;
+use genomeforge_core::annotation::strand::Strand;
+use genomeforge_core::genome::StructuralKind;
+use genomeforge_core::quality::QualityIssue;
+use genomeforge_core::report::i18n::{Locale, Translator};
+use genomeforge_core::report::template::ReportTemplate;
+use genomeforge_core::report::{self, Block, Fact, Report, Section, Table};
+use serde::Serialize;
+
+/// Ids of the sections templates can include
+pub const SECTIONS: [&str; 17] = [
+    "summary",
+    "clinical",
+    "acmg",
+    "apoe",
+    "carrier",
+    "pharmacogenomics",
+    "traits",
+    "nutrigenomics",
+    "structural",
+    "plugins",
+    "custom",
+    "watchlist",
+    "notes",
+    "haplogroups",
+    "methodology",
+    "limitations",
+    "provenance",
+];
+
+/// The report of an analysis in the export's locale, laid out by
+/// `template`
+pub fn build(info: &ExportInfo, results: &AnalysisResultData, template: &ReportTemplate) -> Report {
+    let t = i18n::translator(info.locale);
+    let generated = date(info.exported_at);
+    let build = genome_build(&t, results);
+    let version = env!("CARGO_PKG_VERSION");
+    let values = [
+        ("report_id", info.report_id.as_str()),
+        ("date", generated.as_str()),
+        ("genome_build", build.as_str()),
+        ("version", version),
+    ];
+    let report = Report {
+        title: t.text("report.title").to_string(),
+        subtitle: Some(t.text("report.subtitle").to_string()),
+        details: vec![
+            Fact::new(t.text("report.report"), &info.report_id),
+            Fact::new(t.text("report.generated"), &generated),
+            Fact::new(t.text("report.genome_build"), &build),
+            Fact::new(
+                t.text("report.software"),
+                format!("GenomeForge {}", version),
+            ),
+        ],
+        sections: Vec::new(),
+        footer: Some(t.format("report.footer", &values)),
+        language: Some(info.locale.tag().to_string()),
+        page_numbers: Some(t.text("report.page_numbers").to_string()),
+    };
+    let mut report = template.apply(report, info.locale, &values, |id| sections(&t, id, results));
+    if !template
+        .sections
+        .iter()
+        .any(|section| section.id == "provenance")
+    {
+        report.sections.push(provenance(&t, results));
+    }
+    report
+}
+
+/// Name of an enum variant in the translator's locale, e.g. "Likely
+/// pathogenic"; variants missing from the catalogs are spelled out from
+/// their serialized name
+pub fn label<T: Serialize>(t: &Translator, value: &T) -> String {
+    let name = serialized_name(value).unwrap_or_default();
+    if let Some(text) = t.get(&format!("label.{}", name)) {
+        return text.to_string();
+    }
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+// Helper functions
+
+/// The sections of an id in [`SECTIONS`]; none when there is nothing to
+/// report
+fn sections(t: &Translator, id: &str, results: &AnalysisResultData) -> Vec<Section> {
+    match id {
+        "summary" => vec![summary(t, results)],
+        "clinical" => vec![clinical(t, results)],
+        "acmg" => acmg(t, results).into_iter().collect(),
+        "apoe" => apoe(t, results).into_iter().collect(),
+        "carrier" => carrier(t, results).into_iter().collect(),
+        "pharmacogenomics" => vec![pharmacogenomics(t, results)],
+        "traits" => vec![traits(t, results)],
+        "nutrigenomics" => nutrigenomics(t, results).into_iter().collect(),
+        "structural" => structural(t, results).into_iter().collect(),
+        "plugins" => plugins(t, results).into_iter().collect(),
+        "custom" => custom(t, results).into_iter().collect(),
+        "watchlist" => watchlist(t, results).into_iter().collect(),
+        "notes" => notes(t, results).into_iter().collect(),
+        "haplogroups" => ancestry(t, results).into_iter().collect(),
+        "methodology" => vec![methodology(t, results)],
+        "limitations" => vec![limitations(t)],
+        "provenance" => vec![provenance(t, results)],
+        _ => Vec::new(),
+    }
+}
+
+fn summary(t: &Translator, results: &AnalysisResultData) -> Section {
+    let summary = &results.summary;
+    let mut section = Section::new(t.text("summary.title"));
+    section.push(Block::Notice {
+        text: t.text("report.disclaimer").to_string(),
+    });
+    section.push(Block::Facts {
+        facts: [
+            ("summary.total_variants", summary.total_variants),
+            ("summary.analyzed_variants", summary.analyzed_variants),
+            ("summary.clinical", summary.clinical_count),
+            ("summary.actionable", summary.actionable_findings),
+            ("summary.drugs", summary.drug_count),
+            ("summary.traits", summary.trait_count),
+        ]
+        .into_iter()
+        .map(|(key, n)| Fact::new(t.text(key), t.number(n)))
+        .collect(),
+    });
+
+    let mut categories = Table::new([t.text("summary.category"), t.text("summary.findings")]);
+    for (key, found) in [
+        ("summary.clinvar", results.clinical_findings.len()),
+        ("summary.acmg", results.acmg_findings.len()),
+        ("summary.carrier", results.carrier_findings.len()),
+        ("summary.diplotypes", results.diplotypes.len()),
+        ("summary.drugs", results.drug_responses.len()),
+        ("summary.traits", results.trait_associations.len()),
+        ("summary.nutrition", results.nutrition.len()),
+        ("summary.structural", results.structural_findings.len()),
+        ("summary.plugins", results.plugin_findings.len()),
+        ("summary.custom", results.custom_findings.len()),
+    ] {
+        categories.push_row([t.text(key).to_string(), t.number(found)]);
+    }
+    section.push(Block::Table(categories));
+
+    let mut withheld = Vec::new();
+    for (key, n) in [
+        (
+            "summary.secondary_withheld",
+            summary.secondary_findings_withheld,
+        ),
+        ("summary.late_onset_withheld", summary.late_onset_withheld),
+        (
+            "summary.common_suppressed",
+            summary.common_variants_suppressed,
+        ),
+    ] {
+        if n > 0 {
+            withheld.push(t.format(key, &[("count", &t.number(n))]));
+        }
+    }
+    if !withheld.is_empty() {
+        section.push(Block::Notice {
+            text: withheld.join("\n"),
+        });
+    }
+    section
+}
+
+fn clinical(t: &Translator, results: &AnalysisResultData) -> Section {
+    let mut section = Section::new(t.text("clinical.title"));
+    section.push(paragraph(t.text("clinical.introduction")));
+    let mut findings: Vec<&ClinicalFinding> = results.clinical_findings.iter().collect();
+    findings.sort_by_key(|finding| finding.significance);
+    if findings.is_empty() {
+        section.push(paragraph(t.text("clinical.none")));
+    } else {
+        section.push(Block::Table(variant_table(t, findings)));
+    }
+    section
+}
+
+fn acmg(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.acmg_findings.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("acmg.title"));
+    section.push(paragraph(
+        &t.format("acmg.introduction", &[("version", acmg::VERSION)]),
+    ));
+    for finding in &results.acmg_findings {
+        section.push(Block::Subheading {
+            text: format!("{}: {}", finding.gene, finding.condition),
+        });
+        section.push(Block::Facts {
+            facts: vec![
+                Fact::new(t.text("column.category"), label(t, &finding.category)),
+                Fact::new(t.text("column.inheritance"), label(t, &finding.inheritance)),
+            ],
+        });
+        section.push(Block::Table(variant_table(
+            t,
+            finding.variants.iter().collect(),
+        )));
+    }
+    Some(section)
+}
+
+fn apoe(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    let apoe = results.apoe.as_ref()?;
+    let mut section = Section::new(t.text("apoe.title"));
+    section.push(Block::Facts {
+        facts: vec![
+            Fact::new(t.text("apoe.diplotype"), greek(&apoe.call.diplotype)),
+            Fact::new(t.text("apoe.e4_copies"), apoe.call.e4_copies.to_string()),
+            Fact::new(t.text("apoe.risk"), label(t, &apoe.call.risk)),
+            Fact::new(t.text("apoe.condition"), &apoe.condition),
+            Fact::new(
+                t.text("apoe.genotypes"),
+                format!(
+                    "rs429358 {}, rs7412 {}",
+                    apoe.call.rs429358, apoe.call.rs7412
+                ),
+            ),
+        ],
+    });
+    if let Some(alternative) = &apoe.call.alternative {
+        section.push(paragraph(
+            &t.format("apoe.alternative", &[("diplotype", &greek(alternative))]),
+        ));
+    }
+    section.push(Block::Notice {
+        text: apoe.caveat.clone(),
+    });
+    Some(section)
+}
+
+fn carrier(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.carrier_findings.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("carrier.title"));
+    let mut table = Table::new([
+        t.text("column.gene"),
+        t.text("column.condition"),
+        t.text("column.inheritance"),
+        t.text("column.status"),
+        t.text("column.variants"),
+    ]);
+    for finding in &results.carrier_findings {
+        table.push_row([
+            finding.gene.clone(),
+            finding.condition.clone(),
+            label(t, &finding.inheritance),
+            label(t, &finding.status),
+            rsids(&finding.variants),
+        ]);
+    }
+    section.push(Block::Table(table));
+
+    let mut caveats: Vec<&String> = Vec::new();
+    for caveat in results
+        .carrier_findings
+        .iter()
+        .flat_map(|finding| &finding.residual_risk)
+    {
+        if !caveats.contains(&caveat) {
+            caveats.push(caveat);
+        }
+    }
+    for caveat in caveats {
+        section.push(Block::Notice {
+            text: caveat.clone(),
+        });
+    }
+    Some(section)
+}
+
+fn pharmacogenomics(t: &Translator, results: &AnalysisResultData) -> Section {
+    let mut section = Section::new(t.text("pgx.title"));
+    section.push(paragraph(t.text("pgx.introduction")));
+
+    if !results.hla_risks.is_empty() {
+        section.push(Block::Subheading {
+            text: t.text("pgx.hla").to_string(),
+        });
+        for call in results.hla_risks.iter().filter(|call| call.is_carrier()) {
+            let carrier = t.format(
+                "pgx.hla_carrier",
+                &[
+                    ("allele", &call.allele),
+                    ("drugs", &call.drugs.join(", ")),
+                    ("reaction", &call.reaction),
+                ],
+            );
+            section.push(Block::Notice {
+                text: format!("{} {}", carrier, call.recommendation),
+            });
+        }
+        let mut table = Table::new([
+            t.text("column.allele"),
+            t.text("column.genotype"),
+            t.text("column.copies"),
+            t.text("column.evidence"),
+            t.text("column.drug"),
+        ]);
+        for call in &results.hla_risks {
+            table.push_row([
+                call.allele.clone(),
+                genotype_cell(t, &call.genotype, call.imputed),
+                t.number(call.copies),
+                label(t, &call.evidence),
+                call.drugs.join(", "),
+            ]);
+        }
+        section.push(Block::Table(table));
+        for caveat in results.hla_risks.iter().flat_map(|call| &call.caveat) {
+            section.push(paragraph(caveat));
+        }
+    }
+
+    if !results.diplotypes.is_empty() {
+        section.push(Block::Subheading {
+            text: t.text("pgx.diplotypes").to_string(),
+        });
+        let mut table = Table::new([
+            t.text("column.gene"),
+            t.text("column.diplotype"),
+            t.text("column.phenotype"),
+            t.text("column.activity_score"),
+        ]);
+        for call in &results.diplotypes {
+            table.push_row([
+                call.gene.clone(),
+                call.diplotype.clone(),
+                call.phenotype.clone(),
+                call.activity_score
+                    .map_or_else(String::new, |score| t.locale.decimal(score)),
+            ]);
+        }
+        section.push(Block::Table(table));
+    }
+
+    section.push(Block::Subheading {
+        text: t.text("pgx.responses").to_string(),
+    });
+    if results.drug_responses.is_empty() {
+        section.push(paragraph(t.text("pgx.none")));
+    } else {
+        let mut table = Table::new([
+            t.text("column.drug"),
+            t.text("column.gene"),
+            t.text("column.genotype"),
+            t.text("column.evidence"),
+            t.text("column.response"),
+            t.text("column.recommendation"),
+        ]);
+        let mut responses: Vec<_> = results.drug_responses.iter().collect();
+        responses.sort_by(|a, b| {
+            a.evidence_level
+                .cmp(&b.evidence_level)
+                .then_with(|| a.drug.cmp(&b.drug))
+        });
+        for response in responses {
+            table.push_row([
+                response.drug.clone(),
+                response.gene.clone(),
+                call_cell(
+                    t,
+                    genotype_cell(t, &response.genotype, response.imputed),
+                    &response.borderline_quality,
+                    response.strand,
+                ),
+                response.evidence_level.as_str().to_string(),
+                response.response.clone(),
+                recommendation_cell(response),
+            ]);
+        }
+        section.push(Block::Table(table));
+    }
+    section
+}
+
+fn traits(t: &Translator, results: &AnalysisResultData) -> Section {
+    let mut section = Section::new(t.text("traits.title"));
+    section.push(paragraph(t.text("traits.introduction")));
+    if results.trait_associations.is_empty() {
+        section.push(paragraph(t.text("traits.none")));
+        return section;
+    }
+    let mut table = Table::new([
+        t.text("column.trait"),
+        t.text("column.category"),
+        t.text("column.variant"),
+        t.text("column.genotype"),
+        t.text("column.effect"),
+        t.text("column.p_value"),
+    ]);
+    let mut associations: Vec<_> = results.trait_associations.iter().collect();
+    associations.sort_by(|a, b| a.category.as_str().cmp(b.category.as_str()));
+    for association in associations {
+        table.push_row([
+            association.trait_name.clone(),
+            label(t, &association.category),
+            association.rsid.clone(),
+            call_cell(
+                t,
+                genotype_cell(t, &association.genotype, association.imputed),
+                &association.borderline_quality,
+                association.strand,
+            ),
+            association.effect.clone(),
+            format!("{:.1e}", association.p_value),
+        ]);
+    }
+    section.push(Block::Table(table));
+    section
+}
+
+/// Kept apart from the clinical sections and always led by the caveat
+/// that its evidence is weaker
+fn nutrigenomics(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.nutrition.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("nutrition.title"));
+    section.push(Block::Notice {
+        text: t.text("nutrition.caveat").to_string(),
+    });
+    let mut table = Table::new([
+        t.text("column.gene"),
+        t.text("column.variant"),
+        t.text("column.category"),
+        t.text("column.genotype"),
+        t.text("column.evidence"),
+        t.text("column.effect"),
+    ]);
+    for finding in &results.nutrition {
+        table.push_row([
+            finding.gene.clone(),
+            if finding.name == finding.rsid {
+                finding.rsid.clone()
+            } else {
+                format!("{} ({})", finding.name, finding.rsid)
+            },
+            label(t, &finding.area),
+            genotype_cell(t, &finding.genotype, finding.imputed),
+            label(t, &finding.evidence),
+            finding.effect.clone(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+/// Led by the caveat that calls from short reads need confirming
+fn structural(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.structural_findings.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("structural.title"));
+    section.push(Block::Notice {
+        text: t.text("structural.caveat").to_string(),
+    });
+    let mut table = Table::new([
+        t.text("column.gene"),
+        t.text("column.variant"),
+        t.text("column.location"),
+        t.text("column.copy_number"),
+        t.text("column.dosage_score"),
+        t.text("column.coverage"),
+        t.text("column.condition"),
+    ]);
+    for finding in &results.structural_findings {
+        let kind = label(t, &finding.kind);
+        table.push_row([
+            finding.gene.clone(),
+            match &finding.id {
+                Some(id) => format!("{} ({})", kind, id),
+                None => kind,
+            },
+            format!(
+                "{}:{}-{}",
+                finding.chromosome,
+                t.number(finding.start as usize),
+                t.number(finding.end as usize)
+            ),
+            copy_number(finding).map_or_else(String::new, |copies| copies.to_string()),
+            label(t, &finding.score),
+            format!("{:.0}%", finding.overlap * 100.0),
+            finding.disease.clone().unwrap_or_default(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+fn plugins(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.plugin_findings.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("plugins.title"));
+    section.push(Block::Notice {
+        text: t.text("plugins.caveat").to_string(),
+    });
+    let mut table = Table::new([
+        t.text("column.finding"),
+        t.text("column.gene"),
+        t.text("column.variant"),
+        t.text("column.genotype"),
+        t.text("column.significance"),
+        t.text("column.plugin"),
+    ]);
+    for finding in &results.plugin_findings {
+        table.push_row([
+            finding.title.clone(),
+            finding.genes.join(", "),
+            finding.rsid.clone().unwrap_or_default(),
+            finding.genotype.clone().unwrap_or_default(),
+            label(t, &finding.significance),
+            finding.plugin.clone(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+fn custom(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.custom_findings.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("custom.title"));
+    section.push(Block::Notice {
+        text: t.text("custom.caveat").to_string(),
+    });
+    let mut table = Table::new([
+        t.text("column.gene"),
+        t.text("column.variant"),
+        t.text("column.genotype"),
+        t.text("column.finding"),
+        t.text("column.significance"),
+        t.text("column.source"),
+    ]);
+    for finding in &results.custom_findings {
+        table.push_row([
+            finding.gene.clone().unwrap_or_default(),
+            finding.rsid.clone(),
+            finding.genotype.clone(),
+            finding.interpretation.clone(),
+            label(t, &finding.significance),
+            finding.source.clone().unwrap_or_default(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+fn watchlist(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.watchlist.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("watchlist.title"));
+    section.push(paragraph(t.text("watchlist.introduction")));
+    let mut table = Table::new([
+        t.text("column.watched"),
+        t.text("column.reason"),
+        t.text("column.status"),
+        t.text("column.calls"),
+    ]);
+    for watched in &results.watchlist {
+        let mut calls: Vec<String> = watched
+            .calls
+            .iter()
+            .map(|call| {
+                let site = call.rsid.clone().unwrap_or_else(|| {
+                    format!("{}:{}", call.chromosome, t.number(call.position as usize))
+                });
+                format!("{} {}", site, call.genotype)
+            })
+            .collect();
+        if watched.omitted > 0 {
+            calls.push(t.format(
+                "watchlist.omitted",
+                &[("count", &t.number(watched.omitted))],
+            ));
+        }
+        table.push_row([
+            watched.entry.target.clone(),
+            watched.entry.label.clone(),
+            label(t, &watched.status),
+            calls.join(", "),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+fn notes(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    if results.notes.is_empty() {
+        return None;
+    }
+    let mut section = Section::new(t.text("notes.title"));
+    section.push(paragraph(t.text("notes.introduction")));
+    let mut table = Table::new([
+        t.text("column.finding"),
+        t.text("column.bookmark"),
+        t.text("column.note"),
+    ]);
+    for entry in &results.notes {
+        let bookmark = if entry.bookmarked {
+            t.text("notes.bookmarked")
+        } else {
+            ""
+        };
+        table.push_row([
+            entry.label.clone(),
+            bookmark.to_string(),
+            entry.note.clone(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    Some(section)
+}
+
+fn ancestry(t: &Translator, results: &AnalysisResultData) -> Option<Section> {
+    let report = results.haplogroups.as_ref()?;
+    let mut section = Section::new(t.text("haplogroups.title"));
+    let describe = |call: Option<&HaplogroupCall>| {
+        call.map_or_else(
+            || t.text("haplogroups.not_called").to_string(),
+            |call| {
+                t.format(
+                    "haplogroups.call",
+                    &[
+                        ("haplogroup", &call.haplogroup),
+                        ("genotyped", &t.number(call.markers_genotyped)),
+                        ("total", &t.number(call.markers_total)),
+                    ],
+                )
+            },
+        )
+    };
+    section.push(Block::Facts {
+        facts: vec![
+            Fact::new(
+                t.text("haplogroups.paternal"),
+                describe(report.paternal.as_ref()),
+            ),
+            Fact::new(
+                t.text("haplogroups.maternal"),
+                describe(report.maternal.as_ref()),
+            ),
+        ],
+    });
+    Some(section)
+}
+
+fn methodology(t: &Translator, results: &AnalysisResultData) -> Section {
+    let summary = &results.summary;
+    let mut section = Section::new(t.text("methodology.title"));
+    section.new_page = true;
+    for key in [
+        "methodology.local",
+        "methodology.clinical",
+        "methodology.secondary",
+        "methodology.drugs",
+        "methodology.traits",
+        "methodology.nutrition",
+        "methodology.structural",
+    ] {
+        section.push(paragraph(t.text(key)));
+    }
+
+    let mut facts = Vec::new();
+    if summary.rsids_resolved > 0 || summary.alleles_resolved > 0 {
+        facts.push(Fact::new(
+            t.text("methodology.dbsnp"),
+            t.format(
+                "methodology.dbsnp_value",
+                &[
+                    ("rsids", &t.number(summary.rsids_resolved)),
+                    ("alleles", &t.number(summary.alleles_resolved)),
+                ],
+            ),
+        ));
+    }
+    if let Some(liftover) = &summary.liftover {
+        facts.push(Fact::new(
+            t.text("methodology.liftover"),
+            t.format(
+                "methodology.liftover_value",
+                &[
+                    ("from", &format!("{:?}", liftover.from)),
+                    ("to", &format!("{:?}", liftover.to)),
+                    ("lifted", &t.number(liftover.lifted)),
+                    ("dropped", &t.number(liftover.dropped)),
+                    ("ambiguous", &t.number(liftover.ambiguous)),
+                ],
+            ),
+        ));
+    }
+    if let Some(normalization) = &summary.variant_normalization {
+        facts.push(Fact::new(
+            t.text("methodology.normalization"),
+            t.format(
+                "methodology.normalization_value",
+                &[
+                    ("split", &t.number(normalization.records_split)),
+                    ("trimmed", &t.number(normalization.records_trimmed)),
+                    ("aligned", &t.number(normalization.indels_left_aligned)),
+                ],
+            ),
+        ));
+    }
+    if let Some(imputation) = &summary.imputation {
+        facts.push(Fact::new(
+            t.text("methodology.imputation"),
+            t.format(
+                "methodology.imputation_value",
+                &[
+                    ("imputed", &t.number(imputation.imputed)),
+                    ("typed", &t.number(imputation.typed)),
+                    ("excluded", &t.number(imputation.excluded())),
+                ],
+            ),
+        ));
+    }
+    if summary.strand_flipped + summary.strand_ambiguous > 0 {
+        facts.push(Fact::new(
+            t.text("methodology.strand"),
+            t.format(
+                "methodology.strand_value",
+                &[
+                    ("flipped", &t.number(summary.strand_flipped)),
+                    ("ambiguous", &t.number(summary.strand_ambiguous)),
+                ],
+            ),
+        ));
+    }
+    if let Some(quality) = &summary.quality {
+        facts.push(Fact::new(
+            t.text("methodology.quality"),
+            t.format(
+                "methodology.quality_value",
+                &[
+                    ("assessed", &t.number(quality.assessed)),
+                    ("excluded", &t.number(quality.excluded())),
+                    ("filter", &t.number(quality.excluded_failed_filter)),
+                    ("qual", &t.number(quality.excluded_low_site_quality)),
+                    ("gq", &t.number(quality.excluded_low_genotype_quality)),
+                    ("depth", &t.number(quality.excluded_low_depth)),
+                    ("borderline", &t.number(quality.borderline)),
+                ],
+            ),
+        ));
+    }
+    if let Some(mask) = &summary.probe_mask {
+        let chip = mask
+            .chip
+            .as_ref()
+            .map_or_else(String::new, |chip| chip.to_string());
+        facts.push(Fact::new(
+            t.text("methodology.probe_mask"),
+            t.format(
+                "methodology.probe_mask_value",
+                &[
+                    ("chip", &chip),
+                    ("excluded", &t.number(mask.excluded)),
+                    ("flagged", &t.number(mask.flagged)),
+                ],
+            ),
+        ));
+    }
+    if let Some(check) = &summary.sex {
+        let sex = match &check.inferred {
+            Some(sex) => label(t, sex),
+            None => t.text("methodology.sex_undetermined").to_string(),
+        };
+        let percent = |share: Option<f64>| {
+            share.map_or_else(
+                || "-".to_string(),
+                |share| t.number((share * 100.0).round() as usize),
+            )
+        };
+        let mut value = t.format(
+            "methodology.sex_value",
+            &[
+                ("sex", &sex),
+                ("x", &percent(check.x_heterozygosity)),
+                ("y", &percent(check.y_call_rate)),
+                ("hemizygous", &t.number(summary.hemizygous_calls)),
+            ],
+        );
+        for warning in &check.warnings {
+            value.push_str("; ");
+            value.push_str(&label(t, warning));
+        }
+        facts.push(Fact::new(t.text("methodology.sex"), value));
+    }
+    if !facts.is_empty() {
+        section.push(Block::Facts { facts });
+    }
+    section
+}
+
+fn limitations(t: &Translator) -> Section {
+    let mut section = Section::new(t.text("limitations.title"));
+    for key in [
+        "limitations.coverage",
+        "limitations.false_positives",
+        "limitations.releases",
+        "limitations.ancestry",
+        "limitations.environment",
+    ] {
+        section.push(paragraph(t.text(key)));
+    }
+    section
+}
+
+fn provenance(t: &Translator, results: &AnalysisResultData) -> Section {
+    let summary = &results.summary;
+    let mut section = Section::new(t.text("provenance.title"));
+    section.new_page = true;
+    if summary.databases.is_empty() {
+        section.push(paragraph(t.text("provenance.unrecorded")));
+        return section;
+    }
+    section.push(paragraph(&t.format(
+        "provenance.intro",
+        &[("version", env!("CARGO_PKG_VERSION"))],
+    )));
+    let mut table = Table::new([
+        t.text("column.database"),
+        t.text("column.version"),
+        t.text("column.release_date"),
+        t.text("column.records"),
+        t.text("column.sha256"),
+    ]);
+    for release in &summary.databases {
+        table.push_row([
+            t.text(&format!("database.{}", release.database.as_str()))
+                .to_string(),
+            release.version.clone().unwrap_or_default(),
+            release.release_date.clone().unwrap_or_default(),
+            t.number(release.record_count),
+            release.sha256.clone().unwrap_or_default(),
+        ]);
+    }
+    for run in &summary.plugins {
+        table.push_row([
+            t.format("provenance.plugin", &[("name", &run.name)]),
+            run.version.clone(),
+        ]);
+    }
+    section.push(Block::Table(table));
+    section
+}
+
+fn variant_table(t: &Translator, findings: Vec<&ClinicalFinding>) -> Table {
+    let mut table = Table::new([
+        t.text("column.gene"),
+        t.text("column.variant"),
+        t.text("column.genotype"),
+        t.text("column.significance"),
+        t.text("column.review"),
+        t.text("column.condition"),
+    ]);
+    for finding in findings {
+        // ClinVar's own wording is kept in English reports
+        let significance = match t.locale {
+            Locale::En => finding.significance_label.clone(),
+            _ => label(t, &finding.significance),
+        };
+        let genotype = if finding.imputed {
+            format!(
+                "{} ({}, {})",
+                finding.genotype,
+                label(t, &finding.zygosity).to_lowercase(),
+                t.text("genotype.imputed")
+            )
+        } else {
+            format!(
+                "{} ({})",
+                finding.genotype,
+                label(t, &finding.zygosity).to_lowercase()
+            )
+        };
+        table.push_row([
+            finding.gene.clone().unwrap_or_default(),
+            finding.rsid.clone(),
+            call_cell(t, genotype, &finding.borderline_quality, finding.strand),
+            significance,
+            t.format(
+                "review.stars",
+                &[("stars", &finding.review_stars.to_string())],
+            ),
+            finding.condition.clone(),
+        ]);
+    }
+    table
+}
+
+/// A genotype, marked when it was imputed rather than measured
+/// Copies of the segment as called, or from the genotype for a diploid
+/// chromosome
+fn copy_number(finding: &StructuralFinding) -> Option<u32> {
+    let copies = finding.copies.and_then(|copies| u32::try_from(copies).ok());
+    finding.copy_number.or(match finding.kind {
+        StructuralKind::Deletion => copies.map(|copies| 2u32.saturating_sub(copies)),
+        StructuralKind::Duplication => copies.map(|copies| 2 + copies),
+        _ => None,
+    })
+}
+
+fn genotype_cell(t: &Translator, genotype: &str, imputed: bool) -> String {
+    if imputed {
+        format!("{} ({})", genotype, t.text("genotype.imputed"))
+    } else {
+        genotype.to_string()
+    }
+}
+
+/// A genotype cell, marked when the call behind it is of borderline
+/// sequencing quality or at a SNP of unknown strand, e.g.
+/// "AG (borderline: Low depth)"
+fn call_cell(t: &Translator, cell: String, issues: &[QualityIssue], strand: Strand) -> String {
+    let mut marks = Vec::new();
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(|issue| label(t, issue)).collect();
+        marks.push(t.format("genotype.borderline", &[("issues", &issues.join(", "))]));
+    }
+    if strand.is_ambiguous() {
+        marks.push(t.text("genotype.strand_ambiguous").to_string());
+    }
+    if marks.is_empty() {
+        cell
+    } else {
+        format!("{} ({})", cell, marks.join("; "))
+    }
+}
+
+/// A recommendation with the strength and version of the CPIC guideline
+/// it comes from, e.g. "... (CPIC 2022 update, Strong)"
+fn recommendation_cell(response: &DrugResponse) -> String {
+    let Some(guideline) = &response.guideline else {
+        return response.recommendation.clone();
+    };
+    let source: Vec<&str> = guideline
+        .version
+        .as_deref()
+        .into_iter()
+        .chain(guideline.classification.as_deref())
+        .collect();
+    if source.is_empty() {
+        response.recommendation.clone()
+    } else {
+        format!("{} (CPIC {})", response.recommendation, source.join(", "))
+    }
+}
+
+fn paragraph(text: &str) -> Block {
+    Block::Paragraph {
+        text: text.to_string(),
+    }
+}
+
+fn rsids(variants: &[ClinicalFinding]) -> String {
+    variants
+        .iter()
+        .map(|variant| variant.rsid.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Spell out ε, which not every report font can show, e.g. "e3/e4"
+fn greek(text: &str) -> String {
+    text.replace('ε', "e")
+}
+
+fn genome_build(t: &Translator, results: &AnalysisResultData) -> String {
+    results.summary.genome_build.map_or_else(
+        || t.text("report.unknown").to_string(),
+        |build| format!("{:?}", build),
+    )
+}
+
+/// UTC date and time of seconds since the Unix epoch, e.g.
+/// "2026-10-14 07:10 UTC"
+fn date(secs: u64) -> String {
+    let timestamp = report::timestamp(secs);
+    format!("{} {} UTC", &timestamp[..10], &timestamp[11..16])
+}
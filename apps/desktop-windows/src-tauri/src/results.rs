@@ -0,0 +1,1327 @@
+//! Results of the latest analysis
+//!
+//! The frontend searches and pages through findings with commands instead
+//! of holding the whole result set, so the latest result is kept here until
+//! the next analysis or until another genome is loaded.
+
+use crate::commands::{
+    AcmgFinding, AnalysisResultData, CarrierFinding, ClinicalFinding, DrugResponse,
+    StructuralFinding, TraitAssociation,
+};
+use genomeforge_core::annotation::clingen::DosageScore;
+use genomeforge_core::annotation::clinvar::ClinicalSignificance;
+use genomeforge_core::annotation::cpic::DiplotypeCall;
+use genomeforge_core::annotation::custom::CustomFinding;
+use genomeforge_core::annotation::gnomad::AlleleFrequencies;
+use genomeforge_core::annotation::hla::{HlaCall, HlaEvidence};
+use genomeforge_core::annotation::nutrigenomics::{NutritionEvidence, NutritionFinding};
+use genomeforge_core::annotation::ontology::{self, BodySystem, ConditionTerm};
+use genomeforge_core::notes::{FindingNote, FindingRef, NoteBook};
+use genomeforge_core::parser::chromosome_sort_key;
+use genomeforge_core::plugin::PluginFinding;
+use genomeforge_core::results_db::FindingRow;
+use genomeforge_core::rules::{expr, CompiledRule};
+use genomeforge_core::search::{Page, Query, SearchField, SearchMatch};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Holds the result of the latest analysis
+#[derive(Debug, Default)]
+pub struct ResultStore {
+    latest: Mutex<Option<Arc<AnalysisResultData>>>,
+}
+
+impl ResultStore {
+    /// Keep a new result in place of the previous one
+    pub fn replace(&self, result: AnalysisResultData) -> Arc<AnalysisResultData> {
+        let result = Arc::new(result);
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(result.clone());
+        result
+    }
+
+    /// Keep a result that is already shared, such as one taken earlier
+    pub fn put(&self, result: Arc<AnalysisResultData>) {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+    }
+
+    /// Drop the result, returning it
+    pub fn take(&self) -> Option<Arc<AnalysisResultData>> {
+        self.latest.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    pub fn current(&self) -> Option<Arc<AnalysisResultData>> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn clear(&self) {
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Section of the results a finding comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingSection {
+    Clinical,
+    SecondaryFindings,
+    Carrier,
+    DrugResponse,
+    Diplotype,
+    Trait,
+    /// HLA alleles that make drugs dangerous
+    HlaRisk,
+    Nutrition,
+    /// Deletions and duplications of dosage-sensitive genes
+    Structural,
+    /// Findings of the enabled analysis plugins
+    Plugin,
+    /// Findings of the annotation table the user imported
+    Custom,
+}
+
+impl FindingSection {
+    pub const ALL: [FindingSection; 11] = [
+        FindingSection::Clinical,
+        FindingSection::SecondaryFindings,
+        FindingSection::Carrier,
+        FindingSection::DrugResponse,
+        FindingSection::Diplotype,
+        FindingSection::Trait,
+        FindingSection::HlaRisk,
+        FindingSection::Nutrition,
+        FindingSection::Structural,
+        FindingSection::Plugin,
+        FindingSection::Custom,
+    ];
+
+    /// The section with this serialized name, e.g. "drug_response"
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|section| serialized_name(section).as_deref() == Some(name))
+    }
+}
+
+/// Number of findings in one section
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionCount {
+    pub section: FindingSection,
+    pub count: usize,
+}
+
+/// Narrows the findings returned by `get_findings_page`
+///
+/// Values are compared with the names the findings are serialized with,
+/// ignoring case.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FindingFilter {
+    /// ClinVar classifications to keep, e.g. "pathogenic"; sections
+    /// without one have no matches
+    pub significance: Vec<String>,
+    /// e.g. "cardiovascular" for traits or "toxicity" for drug responses
+    pub category: Option<String>,
+    /// Gene symbol
+    pub gene: Option<String>,
+}
+
+/// What findings are ordered by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// The order the analysis produced them in: most confident first, and
+    /// clinical findings by classification before that
+    #[default]
+    Default,
+    Gene,
+    /// Most severe ClinVar classification first
+    Significance,
+    /// Genomic order
+    Position,
+    /// Strongest first: review stars, PharmGKB level or GWAS confidence
+    Evidence,
+    /// Most confident first, weighing the evidence by the reliability of
+    /// the calls
+    Confidence,
+}
+
+/// Order of the findings returned by `get_findings_page`
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct FindingSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+/// A finding matching a `search_findings` query
+#[derive(Debug, Serialize)]
+pub struct SearchResult {
+    pub section: FindingSection,
+    /// Position of the finding within its section
+    pub index: usize,
+    #[serde(flatten)]
+    pub matched: SearchMatch,
+    pub finding: serde_json::Value,
+}
+
+/// A bookmark or note, with the finding of the latest analysis it is on
+#[derive(Debug, Clone, Serialize)]
+pub struct NotedFinding {
+    #[serde(flatten)]
+    pub note: FindingNote,
+    /// Position of the finding within its section; missing when the latest
+    /// analysis did not report it
+    pub index: Option<usize>,
+    pub finding: Option<serde_json::Value>,
+}
+
+/// Findings meeting filter rules, from `apply_rule_set` and
+/// `filter_findings`
+#[derive(Debug, Serialize)]
+pub struct RuleResults {
+    /// Findings each rule matched, in the order of the rules
+    pub rules: Vec<RuleCount>,
+    /// Fields the rules name that no finding has, most likely misspelled
+    pub unknown_fields: Vec<String>,
+    /// Findings meeting any of the rules, section by section
+    pub findings: Page<RuleMatch>,
+}
+
+/// Findings one rule matched
+#[derive(Debug, Serialize)]
+pub struct RuleCount {
+    pub rule: String,
+    pub matches: usize,
+}
+
+/// A finding meeting filter rules
+#[derive(Debug, Serialize)]
+pub struct RuleMatch {
+    pub section: FindingSection,
+    /// Position of the finding within its section
+    pub index: usize,
+    /// Names of the rules it meets
+    pub rules: Vec<String>,
+    /// The finding as the rules saw it
+    pub finding: serde_json::Value,
+}
+
+/// Where a match was found
+#[derive(Debug, Clone, Copy)]
+pub struct SearchHit {
+    pub section: FindingSection,
+    pub index: usize,
+    pub matched: SearchMatch,
+}
+
+/// Findings of one body system, from `browse_conditions`
+#[derive(Debug, Serialize)]
+pub struct ConditionGroup {
+    /// Unset for conditions the ontology table does not cover
+    pub system: Option<BodySystem>,
+    pub label: String,
+    /// HPO term of the system's organ abnormality
+    pub hpo_id: Option<String>,
+    pub conditions: Vec<ConditionEntry>,
+}
+
+/// One condition and the findings reported for it
+#[derive(Debug, Serialize)]
+pub struct ConditionEntry {
+    /// The MONDO name, or the ClinVar name of an unmapped condition
+    pub name: String,
+    /// MONDO identifier
+    pub mondo_id: Option<String>,
+    pub omim_ids: Vec<String>,
+    pub findings: Vec<ClinicalFinding>,
+}
+
+/// Findings matching a query, best first
+pub fn search(result: &AnalysisResultData, query: &Query) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    let mut add = |section, index, fields: &[(SearchField, &str)]| {
+        if let Some(matched) = query.score(fields) {
+            hits.push(SearchHit {
+                section,
+                index,
+                matched,
+            });
+        }
+    };
+
+    for (index, finding) in result.clinical_findings.iter().enumerate() {
+        let mut fields = vec![(SearchField::Rsid, finding.rsid.as_str())];
+        fields.extend(
+            finding
+                .gene
+                .as_deref()
+                .map(|gene| (SearchField::Gene, gene)),
+        );
+        fields.extend(
+            finding
+                .conditions
+                .iter()
+                .map(|condition| (SearchField::Condition, condition.as_str())),
+        );
+        fields.extend(
+            finding
+                .conditions
+                .iter()
+                .filter_map(|condition| condition_term(finding, condition))
+                .flat_map(ontology::search_names)
+                .map(|name| (SearchField::Condition, name)),
+        );
+        add(FindingSection::Clinical, index, &fields);
+    }
+    for (index, finding) in result.acmg_findings.iter().enumerate() {
+        let mut fields = vec![
+            (SearchField::Gene, finding.gene.as_str()),
+            (SearchField::Condition, finding.condition.as_str()),
+        ];
+        fields.extend(
+            ontology::map_condition(&finding.condition, &[])
+                .into_iter()
+                .flat_map(ontology::search_names)
+                .map(|name| (SearchField::Condition, name)),
+        );
+        fields.extend(
+            finding
+                .variants
+                .iter()
+                .map(|variant| (SearchField::Rsid, variant.rsid.as_str())),
+        );
+        add(FindingSection::SecondaryFindings, index, &fields);
+    }
+    for (index, finding) in result.carrier_findings.iter().enumerate() {
+        let mut fields = vec![
+            (SearchField::Gene, finding.gene.as_str()),
+            (SearchField::Condition, finding.condition.as_str()),
+        ];
+        fields.extend(
+            ontology::map_condition(&finding.condition, &[])
+                .into_iter()
+                .flat_map(ontology::search_names)
+                .map(|name| (SearchField::Condition, name)),
+        );
+        fields.extend(
+            finding
+                .variants
+                .iter()
+                .map(|variant| (SearchField::Rsid, variant.rsid.as_str())),
+        );
+        add(FindingSection::Carrier, index, &fields);
+    }
+    for (index, finding) in result.drug_responses.iter().enumerate() {
+        let fields = [
+            (SearchField::Gene, finding.gene.as_str()),
+            (SearchField::Rsid, finding.rsid.as_str()),
+            (SearchField::Drug, finding.drug.as_str()),
+        ];
+        add(FindingSection::DrugResponse, index, &fields);
+    }
+    for (index, call) in result.diplotypes.iter().enumerate() {
+        add(
+            FindingSection::Diplotype,
+            index,
+            &[(SearchField::Gene, call.gene.as_str())],
+        );
+    }
+    for (index, association) in result.trait_associations.iter().enumerate() {
+        let mut fields = vec![
+            (SearchField::Rsid, association.rsid.as_str()),
+            (SearchField::Condition, association.trait_name.as_str()),
+        ];
+        fields.extend(
+            association
+                .genes
+                .iter()
+                .map(|gene| (SearchField::Gene, gene.as_str())),
+        );
+        add(FindingSection::Trait, index, &fields);
+    }
+    for (index, call) in result.hla_risks.iter().enumerate() {
+        let mut fields = vec![
+            (SearchField::Gene, call.gene.as_str()),
+            (SearchField::Gene, call.allele.as_str()),
+            (SearchField::Condition, call.reaction.as_str()),
+        ];
+        fields.extend(
+            call.drugs
+                .iter()
+                .map(|drug| (SearchField::Drug, drug.as_str())),
+        );
+        add(FindingSection::HlaRisk, index, &fields);
+    }
+    for (index, finding) in result.nutrition.iter().enumerate() {
+        let fields = [
+            (SearchField::Gene, finding.gene.as_str()),
+            (SearchField::Rsid, finding.rsid.as_str()),
+            (SearchField::Condition, finding.name.as_str()),
+        ];
+        add(FindingSection::Nutrition, index, &fields);
+    }
+    for (index, finding) in result.structural_findings.iter().enumerate() {
+        let mut fields = vec![(SearchField::Gene, finding.gene.as_str())];
+        fields.extend(
+            finding
+                .disease
+                .as_deref()
+                .map(|disease| (SearchField::Condition, disease)),
+        );
+        add(FindingSection::Structural, index, &fields);
+    }
+    for (index, finding) in result.plugin_findings.iter().enumerate() {
+        let mut fields: Vec<(SearchField, &str)> = finding
+            .genes
+            .iter()
+            .map(|gene| (SearchField::Gene, gene.as_str()))
+            .collect();
+        fields.extend(
+            finding
+                .rsid
+                .as_deref()
+                .map(|rsid| (SearchField::Rsid, rsid)),
+        );
+        fields.push((SearchField::Condition, finding.title.as_str()));
+        add(FindingSection::Plugin, index, &fields);
+    }
+    for (index, finding) in result.custom_findings.iter().enumerate() {
+        let mut fields = vec![
+            (SearchField::Rsid, finding.rsid.as_str()),
+            (SearchField::Condition, finding.interpretation.as_str()),
+        ];
+        fields.extend(
+            finding
+                .gene
+                .as_deref()
+                .map(|gene| (SearchField::Gene, gene)),
+        );
+        add(FindingSection::Custom, index, &fields);
+    }
+
+    // Stable, so equal scores keep the order the results list them in
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.matched.score));
+    hits
+}
+
+/// The finding a hit points to, as sent to the frontend
+pub fn to_result(result: &AnalysisResultData, hit: SearchHit) -> Result<SearchResult, String> {
+    Ok(SearchResult {
+        section: hit.section,
+        index: hit.index,
+        finding: finding_value(result, hit.section, hit.index)?,
+        matched: hit.matched,
+    })
+}
+
+/// A finding of a section, as sent to the frontend
+pub fn finding_value(
+    result: &AnalysisResultData,
+    section: FindingSection,
+    index: usize,
+) -> Result<serde_json::Value, String> {
+    match section {
+        FindingSection::Clinical => value_at(&result.clinical_findings, index),
+        FindingSection::SecondaryFindings => value_at(&result.acmg_findings, index),
+        FindingSection::Carrier => value_at(&result.carrier_findings, index),
+        FindingSection::DrugResponse => value_at(&result.drug_responses, index),
+        FindingSection::Diplotype => value_at(&result.diplotypes, index),
+        FindingSection::Trait => value_at(&result.trait_associations, index),
+        FindingSection::HlaRisk => value_at(&result.hla_risks, index),
+        FindingSection::Nutrition => value_at(&result.nutrition, index),
+        FindingSection::Structural => value_at(&result.structural_findings, index),
+        FindingSection::Plugin => value_at(&result.plugin_findings, index),
+        FindingSection::Custom => value_at(&result.custom_findings, index),
+    }
+}
+
+/// Which finding of a section a bookmark or note is put on, and what it
+/// is listed as
+pub fn finding_ref(
+    result: &AnalysisResultData,
+    section: FindingSection,
+    index: usize,
+) -> Result<(FindingRef, String), String> {
+    section_refs(result, section)
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("No finding at index {}", index))
+}
+
+/// The entries of a notebook, each with its finding when `result` has it,
+/// bookmarked findings first
+pub fn noted_findings(
+    result: Option<&AnalysisResultData>,
+    book: &NoteBook,
+) -> Result<Vec<NotedFinding>, String> {
+    let mut found: HashMap<FindingRef, (FindingSection, usize)> = HashMap::new();
+    for section in FindingSection::ALL {
+        let refs = result.map(|result| section_refs(result, section));
+        for (index, (finding, _)) in refs.into_iter().flatten().enumerate() {
+            found.entry(finding).or_insert((section, index));
+        }
+    }
+    let mut noted = Vec::new();
+    for note in book.entries() {
+        let at = found.get(&note.finding).copied();
+        let finding = match (result, at) {
+            (Some(result), Some((section, index))) => Some(finding_value(result, section, index)?),
+            _ => None,
+        };
+        noted.push(NotedFinding {
+            note: note.clone(),
+            index: at.map(|(_, index)| index),
+            finding,
+        });
+    }
+    noted.sort_by_key(|noted| !noted.note.bookmarked);
+    Ok(noted)
+}
+
+/// What the results database indexes of every finding of a result
+pub fn finding_rows(result: &AnalysisResultData) -> Vec<FindingRow> {
+    let mut rows = Vec::new();
+    for section in FindingSection::ALL {
+        match section {
+            FindingSection::Clinical => index_rows(&mut rows, &result.clinical_findings, section),
+            FindingSection::SecondaryFindings => {
+                index_rows(&mut rows, &result.acmg_findings, section)
+            }
+            FindingSection::Carrier => index_rows(&mut rows, &result.carrier_findings, section),
+            FindingSection::DrugResponse => index_rows(&mut rows, &result.drug_responses, section),
+            FindingSection::Diplotype => index_rows(&mut rows, &result.diplotypes, section),
+            FindingSection::Trait => index_rows(&mut rows, &result.trait_associations, section),
+            FindingSection::HlaRisk => index_rows(&mut rows, &result.hla_risks, section),
+            FindingSection::Nutrition => index_rows(&mut rows, &result.nutrition, section),
+            FindingSection::Structural => {
+                index_rows(&mut rows, &result.structural_findings, section)
+            }
+            FindingSection::Plugin => index_rows(&mut rows, &result.plugin_findings, section),
+            FindingSection::Custom => index_rows(&mut rows, &result.custom_findings, section),
+        }
+    }
+    rows
+}
+
+/// Findings in a section
+pub fn count(result: &AnalysisResultData, section: FindingSection) -> usize {
+    match section {
+        FindingSection::Clinical => result.clinical_findings.len(),
+        FindingSection::SecondaryFindings => result.acmg_findings.len(),
+        FindingSection::Carrier => result.carrier_findings.len(),
+        FindingSection::DrugResponse => result.drug_responses.len(),
+        FindingSection::Diplotype => result.diplotypes.len(),
+        FindingSection::Trait => result.trait_associations.len(),
+        FindingSection::HlaRisk => result.hla_risks.len(),
+        FindingSection::Nutrition => result.nutrition.len(),
+        FindingSection::Structural => result.structural_findings.len(),
+        FindingSection::Plugin => result.plugin_findings.len(),
+        FindingSection::Custom => result.custom_findings.len(),
+    }
+}
+
+/// Findings on single variants, in whichever section they are reported
+pub fn variant_findings(result: &AnalysisResultData) -> impl Iterator<Item = &ClinicalFinding> {
+    result
+        .clinical_findings
+        .iter()
+        .chain(result.acmg_findings.iter().flat_map(|f| &f.variants))
+        .chain(result.carrier_findings.iter().flat_map(|f| &f.variants))
+}
+
+/// The conditions of the findings on single variants, by body system
+///
+/// ClinVar names that map to the same MONDO term are listed once under
+/// it. Systems follow the order of [`BodySystem::ALL`], with unmapped
+/// conditions last, and conditions are listed by name. A finding naming
+/// several conditions is listed under each.
+pub fn condition_groups(result: &AnalysisResultData, query: Option<&Query>) -> Vec<ConditionGroup> {
+    // Keyed by MONDO identifier, or by ClinVar name when unmapped
+    let mut systems: BTreeMap<Option<BodySystem>, BTreeMap<String, ConditionEntry>> =
+        BTreeMap::new();
+    for finding in variant_findings(result) {
+        for condition in &finding.conditions {
+            let term = condition_term(finding, condition);
+            if let Some(query) = query {
+                let mut fields = vec![(SearchField::Condition, condition.as_str())];
+                fields.extend(
+                    term.into_iter()
+                        .flat_map(ontology::search_names)
+                        .map(|name| (SearchField::Condition, name)),
+                );
+                if query.score(&fields).is_none() {
+                    continue;
+                }
+            }
+            let entry = systems
+                .entry(term.map(|term| term.system))
+                .or_default()
+                .entry(term.map_or_else(|| condition.to_lowercase(), |term| term.id.to_string()))
+                .or_insert_with(|| ConditionEntry {
+                    name: term.map_or_else(|| condition.clone(), |term| term.name.to_string()),
+                    mondo_id: term.map(|term| term.id.to_string()),
+                    omim_ids: term
+                        .map(|term| term.omim.iter().map(|id| id.to_string()).collect())
+                        .unwrap_or_default(),
+                    findings: Vec::new(),
+                });
+            if !entry
+                .findings
+                .iter()
+                .any(|listed| listed.rsid == finding.rsid)
+            {
+                entry.findings.push(finding.clone());
+            }
+        }
+    }
+
+    let mut groups: Vec<ConditionGroup> = systems
+        .into_iter()
+        .map(|(system, conditions)| {
+            let mut conditions: Vec<ConditionEntry> = conditions.into_values().collect();
+            conditions.sort_by_key(|entry| entry.name.to_lowercase());
+            ConditionGroup {
+                system,
+                label: system
+                    .map_or("Other conditions", |system| system.label())
+                    .to_string(),
+                hpo_id: system.map(|system| system.hpo_id().to_string()),
+                conditions,
+            }
+        })
+        .collect();
+    // Stable, and the map sorts unmapped conditions first
+    groups.sort_by_key(|group| group.system.is_none());
+    groups
+}
+
+/// One page of a section's findings after filtering and sorting
+pub fn findings_page(
+    result: &AnalysisResultData,
+    section: FindingSection,
+    filter: &FindingFilter,
+    sort: FindingSort,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<Page<serde_json::Value>, String> {
+    match section {
+        FindingSection::Clinical => page(&result.clinical_findings, filter, sort, offset, limit),
+        FindingSection::SecondaryFindings => {
+            page(&result.acmg_findings, filter, sort, offset, limit)
+        }
+        FindingSection::Carrier => page(&result.carrier_findings, filter, sort, offset, limit),
+        FindingSection::DrugResponse => page(&result.drug_responses, filter, sort, offset, limit),
+        FindingSection::Diplotype => page(&result.diplotypes, filter, sort, offset, limit),
+        FindingSection::Trait => page(&result.trait_associations, filter, sort, offset, limit),
+        FindingSection::HlaRisk => page(&result.hla_risks, filter, sort, offset, limit),
+        FindingSection::Nutrition => page(&result.nutrition, filter, sort, offset, limit),
+        FindingSection::Structural => {
+            page(&result.structural_findings, filter, sort, offset, limit)
+        }
+        FindingSection::Plugin => page(&result.plugin_findings, filter, sort, offset, limit),
+        FindingSection::Custom => page(&result.custom_findings, filter, sort, offset, limit),
+    }
+}
+
+/// The findings of every section that meet any of the rules
+///
+/// Rules see a finding as the frontend does, with its `section` and
+/// `genes` added and, for clinical findings, `af`: the highest gnomAD
+/// frequency globally or in any population.
+pub fn apply_rules(
+    result: &AnalysisResultData,
+    rules: &[CompiledRule],
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<RuleResults, String> {
+    let mut fields: Vec<&str> = Vec::new();
+    for field in rules.iter().flat_map(|rule| rule.expr.fields()) {
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    let mut found = vec![false; fields.len()];
+    let mut counts = vec![0; rules.len()];
+    let mut matches = Vec::new();
+    for section in FindingSection::ALL {
+        for (index, finding) in rule_views(result, section)?.into_iter().enumerate() {
+            for (found, field) in found.iter_mut().zip(&fields) {
+                *found |= expr::field(&finding, field).is_some();
+            }
+            let mut met = Vec::new();
+            for (count, rule) in counts.iter_mut().zip(rules) {
+                if rule.expr.matches(&finding) {
+                    *count += 1;
+                    met.push(rule.name.clone());
+                }
+            }
+            if !met.is_empty() {
+                matches.push(RuleMatch {
+                    section,
+                    index,
+                    rules: met,
+                    finding,
+                });
+            }
+        }
+    }
+    Ok(RuleResults {
+        rules: rules
+            .iter()
+            .zip(counts)
+            .map(|(rule, matches)| RuleCount {
+                rule: rule.name.clone(),
+                matches,
+            })
+            .collect(),
+        unknown_fields: fields
+            .iter()
+            .zip(found)
+            .filter(|(_, found)| !found)
+            .map(|(field, _)| field.to_string())
+            .collect(),
+        findings: Page::new(matches, offset, limit),
+    })
+}
+
+/// What findings are filtered and sorted on
+trait Finding: Serialize {
+    /// What tells the finding apart from the others of its section, in
+    /// this analysis and later ones
+    fn key(&self) -> String;
+
+    /// What the finding is listed as beside its bookmark or note
+    fn label(&self) -> String;
+
+    fn genes(&self) -> Vec<&str>;
+
+    /// ClinVar classifications of the finding's variants
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        Vec::new()
+    }
+
+    fn categories(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn location(&self) -> Option<(&str, u64)> {
+        None
+    }
+
+    /// Higher is stronger
+    fn evidence(&self) -> Option<f64> {
+        None
+    }
+
+    /// [`Confidence`](genomeforge_core::confidence::Confidence) score
+    fn confidence(&self) -> Option<f64> {
+        None
+    }
+
+    /// Highest gnomAD frequency of the allele
+    fn allele_frequency(&self) -> Option<f64> {
+        None
+    }
+
+    fn matches(&self, filter: &FindingFilter) -> bool {
+        let significance = filter.significance.is_empty()
+            || self.significances().iter().any(|significance| {
+                serialized_name(significance).is_some_and(|name| {
+                    filter
+                        .significance
+                        .iter()
+                        .any(|wanted| wanted.eq_ignore_ascii_case(&name))
+                })
+            });
+        let category = filter.category.as_ref().is_none_or(|wanted| {
+            self.categories()
+                .iter()
+                .any(|category| category.eq_ignore_ascii_case(wanted))
+        });
+        let gene = filter.gene.as_ref().is_none_or(|wanted| {
+            self.genes()
+                .iter()
+                .any(|gene| gene.eq_ignore_ascii_case(wanted.trim()))
+        });
+        significance && category && gene
+    }
+}
+
+impl Finding for ClinicalFinding {
+    fn key(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.chromosome.as_deref().unwrap_or_default(),
+            self.position.unwrap_or_default(),
+            self.variation_id.unwrap_or_default(),
+            self.rsid
+        )
+    }
+
+    fn label(&self) -> String {
+        match &self.gene {
+            Some(gene) => format!("{} {}: {}", gene, self.rsid, self.condition),
+            None => format!("{}: {}", self.rsid, self.condition),
+        }
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        self.gene.as_deref().into_iter().collect()
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        vec![self.significance]
+    }
+
+    fn location(&self) -> Option<(&str, u64)> {
+        self.chromosome.as_deref().zip(self.position)
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(self.review_stars as f64)
+    }
+
+    fn allele_frequency(&self) -> Option<f64> {
+        self.allele_frequency
+            .as_ref()
+            .map(AlleleFrequencies::max_frequency)
+    }
+}
+
+impl Finding for AcmgFinding {
+    fn key(&self) -> String {
+        format!("{}|{}", self.gene, self.condition)
+    }
+
+    fn label(&self) -> String {
+        format!("{}: {}", self.gene, self.condition)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        self.variants
+            .iter()
+            .map(|variant| variant.significance)
+            .collect()
+    }
+
+    fn categories(&self) -> Vec<String> {
+        serialized_name(&self.category).into_iter().collect()
+    }
+
+    fn location(&self) -> Option<(&str, u64)> {
+        self.variants.first().and_then(Finding::location)
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        strongest(&self.variants)
+    }
+}
+
+impl Finding for CarrierFinding {
+    fn key(&self) -> String {
+        format!("{}|{}", self.gene, self.condition)
+    }
+
+    fn label(&self) -> String {
+        format!("{}: {}", self.gene, self.condition)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        self.variants
+            .iter()
+            .map(|variant| variant.significance)
+            .collect()
+    }
+
+    fn location(&self) -> Option<(&str, u64)> {
+        self.variants.first().and_then(Finding::location)
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        strongest(&self.variants)
+    }
+}
+
+impl Finding for DrugResponse {
+    fn key(&self) -> String {
+        format!("{}|{}|{}", self.annotation_id, self.gene, self.drug)
+    }
+
+    fn label(&self) -> String {
+        format!("{}: {}", self.gene, self.drug)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        self.gene.split(", ").collect()
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        self.phenotype_categories
+            .iter()
+            .filter_map(serialized_name)
+            .collect()
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        // Levels are declared strongest first
+        Some(-(self.evidence_level as u8 as f64))
+    }
+}
+
+impl Finding for DiplotypeCall {
+    fn key(&self) -> String {
+        self.gene.clone()
+    }
+
+    fn label(&self) -> String {
+        format!("{} {}", self.gene, self.diplotype)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+}
+
+impl Finding for TraitAssociation {
+    fn key(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.rsid,
+            self.trait_name,
+            self.pubmed_id.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn label(&self) -> String {
+        format!("{}: {}", self.rsid, self.trait_name)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        self.genes.iter().map(String::as_str).collect()
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        serialized_name(&self.category).into_iter().collect()
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(self.confidence.evidence)
+    }
+}
+
+impl Finding for HlaCall {
+    fn key(&self) -> String {
+        format!("{}|{}", self.allele, self.marker)
+    }
+
+    fn label(&self) -> String {
+        format!("{}: {}", self.allele, self.drugs.join(", "))
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        serialized_name(&self.evidence).into_iter().collect()
+    }
+
+    /// Carriers first, typed calls before proxies
+    fn evidence(&self) -> Option<f64> {
+        let typed = if self.evidence == HlaEvidence::Typed {
+            1.0
+        } else {
+            0.0
+        };
+        Some(self.copies.min(1) as f64 * 2.0 + typed)
+    }
+}
+
+impl Finding for NutritionFinding {
+    fn key(&self) -> String {
+        format!("{}|{}|{}", self.gene, self.rsid, self.name)
+    }
+
+    fn label(&self) -> String {
+        format!("{} {}: {}", self.gene, self.rsid, self.name)
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        serialized_name(&self.area).into_iter().collect()
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(match self.evidence {
+            NutritionEvidence::Established => 3.0,
+            NutritionEvidence::Moderate => 2.0,
+            NutritionEvidence::Limited => 1.0,
+        })
+    }
+}
+
+impl Finding for StructuralFinding {
+    fn key(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}",
+            self.gene,
+            serialized_name(&self.kind).unwrap_or_default(),
+            self.chromosome,
+            self.start,
+            self.end
+        )
+    }
+
+    fn label(&self) -> String {
+        format!(
+            "{} {}",
+            self.gene,
+            serialized_name(&self.kind).unwrap_or_default()
+        )
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        vec![self.gene.as_str()]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        serialized_name(&self.kind).into_iter().collect()
+    }
+
+    fn location(&self) -> Option<(&str, u64)> {
+        Some((self.chromosome.as_str(), self.start))
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(match self.score {
+            DosageScore::Sufficient | DosageScore::AutosomalRecessive => 3.0,
+            DosageScore::Emerging => 2.0,
+            DosageScore::Little => 1.0,
+            DosageScore::NoEvidence | DosageScore::Unlikely => 0.0,
+        })
+    }
+}
+
+impl Finding for PluginFinding {
+    fn key(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.plugin,
+            self.title,
+            self.rsid.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn label(&self) -> String {
+        self.title.clone()
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        self.genes.iter().map(String::as_str).collect()
+    }
+
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        vec![self.significance]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    /// The plugin that reported it
+    fn categories(&self) -> Vec<String> {
+        vec![self.plugin.clone()]
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(self.evidence)
+    }
+}
+
+impl Finding for CustomFinding {
+    fn key(&self) -> String {
+        format!("{}|{}|{}", self.rsid, self.genotype, self.interpretation)
+    }
+
+    fn label(&self) -> String {
+        match &self.gene {
+            Some(gene) => format!("{} {}", gene, self.rsid),
+            None => self.rsid.clone(),
+        }
+    }
+
+    fn genes(&self) -> Vec<&str> {
+        self.gene.as_deref().into_iter().collect()
+    }
+
+    fn significances(&self) -> Vec<ClinicalSignificance> {
+        vec![self.significance]
+    }
+
+    fn confidence(&self) -> Option<f64> {
+        Some(self.confidence.score)
+    }
+
+    fn categories(&self) -> Vec<String> {
+        self.category.iter().filter_map(serialized_name).collect()
+    }
+
+    fn evidence(&self) -> Option<f64> {
+        Some(self.confidence.evidence)
+    }
+}
+
+// Helper functions
+
+/// The reference and label of every finding of a section
+fn section_refs(result: &AnalysisResultData, section: FindingSection) -> Vec<(FindingRef, String)> {
+    match section {
+        FindingSection::Clinical => refs(&result.clinical_findings, section),
+        FindingSection::SecondaryFindings => refs(&result.acmg_findings, section),
+        FindingSection::Carrier => refs(&result.carrier_findings, section),
+        FindingSection::DrugResponse => refs(&result.drug_responses, section),
+        FindingSection::Diplotype => refs(&result.diplotypes, section),
+        FindingSection::Trait => refs(&result.trait_associations, section),
+        FindingSection::HlaRisk => refs(&result.hla_risks, section),
+        FindingSection::Nutrition => refs(&result.nutrition, section),
+        FindingSection::Structural => refs(&result.structural_findings, section),
+        FindingSection::Plugin => refs(&result.plugin_findings, section),
+        FindingSection::Custom => refs(&result.custom_findings, section),
+    }
+}
+
+fn refs<T: Finding>(findings: &[T], section: FindingSection) -> Vec<(FindingRef, String)> {
+    let section = serialized_name(&section).unwrap_or_default();
+    findings
+        .iter()
+        .map(|finding| {
+            let finding_ref = FindingRef {
+                section: section.clone(),
+                key: finding.key(),
+            };
+            (finding_ref, finding.label())
+        })
+        .collect()
+}
+
+fn value_at<T: Serialize>(findings: &[T], index: usize) -> Result<serde_json::Value, String> {
+    let finding = findings
+        .get(index)
+        .ok_or_else(|| format!("No finding at index {}", index))?;
+    serde_json::to_value(finding).map_err(|e| format!("Failed to serialize finding: {}", e))
+}
+
+fn index_rows<T: Finding>(rows: &mut Vec<FindingRow>, findings: &[T], section: FindingSection) {
+    let section_name = serialized_name(&section).unwrap_or_default();
+    rows.extend(findings.iter().enumerate().map(|(index, finding)| {
+        FindingRow {
+            section: section_name.clone(),
+            index,
+            genes: finding.genes().into_iter().map(str::to_string).collect(),
+            significances: finding
+                .significances()
+                .iter()
+                .filter_map(serialized_name)
+                .collect(),
+            categories: finding.categories(),
+        }
+    }));
+}
+
+/// The findings of a section as filter rules see them
+fn rule_views(
+    result: &AnalysisResultData,
+    section: FindingSection,
+) -> Result<Vec<serde_json::Value>, String> {
+    match section {
+        FindingSection::Clinical => rule_view(&result.clinical_findings, section),
+        FindingSection::SecondaryFindings => rule_view(&result.acmg_findings, section),
+        FindingSection::Carrier => rule_view(&result.carrier_findings, section),
+        FindingSection::DrugResponse => rule_view(&result.drug_responses, section),
+        FindingSection::Diplotype => rule_view(&result.diplotypes, section),
+        FindingSection::Trait => rule_view(&result.trait_associations, section),
+        FindingSection::HlaRisk => rule_view(&result.hla_risks, section),
+        FindingSection::Nutrition => rule_view(&result.nutrition, section),
+        FindingSection::Structural => rule_view(&result.structural_findings, section),
+        FindingSection::Plugin => rule_view(&result.plugin_findings, section),
+        FindingSection::Custom => rule_view(&result.custom_findings, section),
+    }
+}
+
+fn rule_view<T: Finding>(
+    findings: &[T],
+    section: FindingSection,
+) -> Result<Vec<serde_json::Value>, String> {
+    findings
+        .iter()
+        .map(|finding| {
+            let mut value = serde_json::to_value(finding)
+                .map_err(|e| format!("Failed to serialize finding: {}", e))?;
+            if let serde_json::Value::Object(fields) = &mut value {
+                fields.insert("section".to_string(), serialized_name(&section).into());
+                fields
+                    .entry("genes")
+                    .or_insert_with(|| finding.genes().into());
+                if let Some(af) = finding.allele_frequency() {
+                    fields.entry("af").or_insert_with(|| af.into());
+                }
+            }
+            Ok(value)
+        })
+        .collect()
+}
+
+fn page<T: Finding>(
+    findings: &[T],
+    filter: &FindingFilter,
+    sort: FindingSort,
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<Page<serde_json::Value>, String> {
+    let mut matching: Vec<&T> = findings
+        .iter()
+        .filter(|finding| finding.matches(filter))
+        .collect();
+    match sort.key {
+        SortKey::Default if sort.descending => matching.reverse(),
+        SortKey::Default => {}
+        // Stable, so ties keep the order the analysis produced
+        _ => matching.sort_by(|a, b| compare(*a, *b, sort)),
+    }
+    Page::new(matching, offset, limit).try_map(|finding| {
+        serde_json::to_value(finding).map_err(|e| format!("Failed to serialize finding: {}", e))
+    })
+}
+
+/// Findings lacking the sort key go last in either direction
+fn compare<T: Finding>(a: &T, b: &T, sort: FindingSort) -> Ordering {
+    fn present<K: PartialOrd>(a: Option<K>, b: Option<K>, descending: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let order = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                if descending {
+                    order.reverse()
+                } else {
+                    order
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    let descending = sort.descending;
+    match sort.key {
+        SortKey::Default => Ordering::Equal,
+        SortKey::Gene => present(
+            a.genes().first().map(|gene| gene.to_ascii_uppercase()),
+            b.genes().first().map(|gene| gene.to_ascii_uppercase()),
+            descending,
+        ),
+        SortKey::Significance => present(
+            a.significances().into_iter().min(),
+            b.significances().into_iter().min(),
+            descending,
+        ),
+        SortKey::Position => present(
+            a.location()
+                .map(|(chromosome, position)| (chromosome_sort_key(chromosome), position)),
+            b.location()
+                .map(|(chromosome, position)| (chromosome_sort_key(chromosome), position)),
+            descending,
+        ),
+        // Strongest first unless descending
+        SortKey::Evidence => present(
+            a.evidence().map(|evidence| -evidence),
+            b.evidence().map(|evidence| -evidence),
+            descending,
+        ),
+        SortKey::Confidence => present(
+            a.confidence().map(|score| -score),
+            b.confidence().map(|score| -score),
+            descending,
+        ),
+    }
+}
+
+/// Name a unit enum variant is serialized with, e.g. "likely_pathogenic"
+pub fn serialized_name<T: Serialize>(value: &T) -> Option<String> {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => Some(name),
+        _ => None,
+    }
+}
+
+/// Ontology term of one of a finding's conditions
+///
+/// ClinVar's identifiers are not tied to a condition once split, so they
+/// are only used for findings naming a single condition.
+fn condition_term(finding: &ClinicalFinding, condition: &str) -> Option<&'static ConditionTerm> {
+    let ids: &[String] = if finding.conditions.len() == 1 {
+        &finding.condition_ids
+    } else {
+        &[]
+    };
+    ontology::map_condition(condition, ids)
+}
+
+/// Most review stars of any of the variants
+fn strongest(variants: &[ClinicalFinding]) -> Option<f64> {
+    variants
+        .iter()
+        .filter_map(Finding::evidence)
+        .reduce(f64::max)
+}
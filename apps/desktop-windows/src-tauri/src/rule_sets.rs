@@ -0,0 +1,22 @@
+//! Filter rule sets saved by the user
+//!
+//! Each set is a JSON file in the `rule-sets` directory of the active
+//! profile, and is applied to the latest analysis with `apply_rule_set`.
+
+use crate::profiles;
+use genomeforge_core::rules::{self, RuleSet};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+/// Directory holding the active profile's rule sets
+pub fn rule_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_dir(app).map(|dir| dir.join("rule-sets"))
+}
+
+/// The rule set with this id
+pub fn find(dir: &Path, id: &str) -> Result<RuleSet, String> {
+    rules::load_dir(dir)?
+        .into_iter()
+        .find(|set| set.id == id)
+        .ok_or_else(|| format!("Unknown rule set: {}", id))
+}
@@ -0,0 +1,144 @@
+//! Locating saved sessions and the key that protects them
+//!
+//! Sessions live in the `sessions` directory of the active profile, one
+//! file per name. Sessions
+//! saved without a passphrase are encrypted with a random device key,
+//! which is kept next to them protected by Windows DPAPI, so only the same
+//! Windows user can decrypt it.
+
+use crate::profiles;
+use genomeforge_core::crypto::{Key, Zeroizing, KEY_LEN};
+use genomeforge_core::session;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+/// DPAPI-protected device key inside the session directory
+const DEVICE_KEY_FILE: &str = "device.key";
+
+/// Directory holding the active profile's session files
+pub fn session_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_dir(app).map(|dir| dir.join("sessions"))
+}
+
+/// File a session of this name is saved to
+///
+/// Characters other than letters, digits, `-` and `_` are replaced, so
+/// a name cannot point outside the session directory.
+pub fn session_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Session name must not be empty".to_string());
+    }
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Ok(dir.join(format!("{}.{}", stem, session::EXTENSION)))
+}
+
+/// The device key, created the first time a session is saved
+pub fn device_key(dir: &Path) -> Result<Key, String> {
+    let path = dir.join(DEVICE_KEY_FILE);
+    match fs::read(&path) {
+        Ok(protected) => {
+            let bytes = Zeroizing::new(dpapi::unprotect(&protected)?);
+            let bytes: [u8; KEY_LEN] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| "Device key is corrupt".to_string())?;
+            Ok(Key::from_bytes(bytes))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let key = Key::generate();
+            let protected = dpapi::protect(key.as_bytes())?;
+            fs::create_dir_all(dir)
+                .and_then(|_| fs::write(&path, protected))
+                .map_err(|e| format!("Failed to store device key: {}", e))?;
+            Ok(key)
+        }
+        Err(e) => Err(format!("Failed to read device key: {}", e)),
+    }
+}
+
+#[cfg(windows)]
+mod dpapi {
+    use windows::Win32::Foundation::{LocalFree, HLOCAL};
+    use windows::Win32::Security::Cryptography::{
+        CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    };
+
+    /// Encrypt for the current Windows user
+    pub fn protect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = blob(data);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptProtectData(
+                &input,
+                None,
+                None,
+                None,
+                None,
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        }
+        .map_err(|e| format!("Failed to protect device key: {}", e))?;
+        Ok(take(output))
+    }
+
+    pub fn unprotect(data: &[u8]) -> Result<Vec<u8>, String> {
+        let input = blob(data);
+        let mut output = CRYPT_INTEGER_BLOB::default();
+        unsafe {
+            CryptUnprotectData(
+                &input,
+                None,
+                None,
+                None,
+                None,
+                CRYPTPROTECT_UI_FORBIDDEN,
+                &mut output,
+            )
+        }
+        .map_err(|e| format!("Failed to unprotect device key: {}", e))?;
+        Ok(take(output))
+    }
+
+    fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+        CRYPT_INTEGER_BLOB {
+            cbData: data.len() as u32,
+            // DPAPI only reads the input
+            pbData: data.as_ptr() as *mut u8,
+        }
+    }
+
+    /// Copy out a buffer DPAPI allocated and free it
+    fn take(output: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+        let data =
+            unsafe { std::slice::from_raw_parts(output.pbData, output.cbData as usize).to_vec() };
+        // LocalFree reports success as a null handle, which the wrapper
+        // turns into an error
+        let _ = unsafe { LocalFree(HLOCAL(output.pbData.cast())) };
+        data
+    }
+}
+
+#[cfg(not(windows))]
+mod dpapi {
+    const UNSUPPORTED: &str = "Sessions need a passphrase on this platform";
+
+    pub fn protect(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn unprotect(_data: &[u8]) -> Result<Vec<u8>, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
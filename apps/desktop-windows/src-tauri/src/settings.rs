@@ -0,0 +1,30 @@
+//! The user's settings, kept in `<app config>/settings.json`
+
+use genomeforge_core::settings::Settings;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Directory holding the settings file
+pub fn settings_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to resolve config directory: {}", e))
+}
+
+/// The saved settings, migrated to the current schema
+pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<Settings, String> {
+    Settings::load(&settings_dir(app)?)
+}
+
+/// The saved settings, or the defaults when they cannot be read
+pub fn current<R: Runtime>(app: &AppHandle<R>) -> Settings {
+    read(app).unwrap_or_else(|error| {
+        tracing::warn!(%error, "using default settings");
+        Settings::default()
+    })
+}
+
+/// Save the settings
+pub fn write<R: Runtime>(app: &AppHandle<R>, settings: &Settings) -> Result<(), String> {
+    settings.save(&settings_dir(app)?)
+}
@@ -0,0 +1,155 @@
+//! Findings sent to the frontend as an analysis finds them
+//!
+//! A large genome takes a while to analyze, so the results page need not
+//! stay empty until `analyze_variants` returns: the findings of each
+//! section go out in `analysis-findings` events as soon as the section is
+//! complete, batched by [`Batcher`], and an `analysis-findings-complete`
+//! event follows the last. A thread of its own does the emitting, fed
+//! through a bounded channel. A frontend that acknowledges batches with
+//! `ack_findings` holds it to [`batch::MAX_IN_FLIGHT`] batches ahead; once
+//! the channel fills behind it, the analysis waits too.
+
+use crate::error::GenomeForgeError;
+use crate::events::{self, FindingsBatch, FindingsComplete, StreamedFinding};
+use crate::results::FindingSection;
+use genomeforge_core::batch::{self, Batcher, Window};
+use genomeforge_core::tasks::TaskId;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use tauri::AppHandle;
+
+/// Findings queued for the emitting thread at most
+const CHANNEL_CAPACITY: usize = 2 * batch::BATCH_SIZE;
+
+/// The streams in progress, by task
+#[derive(Debug, Default)]
+pub struct FindingStreams {
+    windows: Mutex<HashMap<TaskId, Arc<Window>>>,
+}
+
+impl FindingStreams {
+    /// Start streaming the findings of the analysis `task_id`
+    pub fn start(&self, app: &AppHandle, task_id: TaskId) -> Arc<FindingStream> {
+        let window = Arc::new(Window::default());
+        self.lock().insert(task_id, window.clone());
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        let emitting = app.clone();
+        let emitter = std::thread::spawn(move || {
+            let mut batcher = Batcher::default();
+            let mut findings = 0;
+            let emit = |findings: Vec<StreamedFinding>| {
+                let sequence = window.next_sequence();
+                events::FINDINGS.emit(
+                    &emitting,
+                    FindingsBatch {
+                        task_id,
+                        sequence,
+                        findings,
+                    },
+                );
+            };
+            loop {
+                let received = match batcher.wait_time(Instant::now()) {
+                    Some(wait) => receiver.recv_timeout(wait),
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                let due = match received {
+                    Ok(finding) => {
+                        findings += 1;
+                        batcher.push(finding, Instant::now())
+                    }
+                    Err(RecvTimeoutError::Timeout) => batcher.poll(Instant::now()),
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Some(batch) = due {
+                    emit(batch);
+                }
+            }
+            if !batcher.is_empty() {
+                emit(batcher.take());
+            }
+            (findings, window.sent())
+        });
+        Arc::new(FindingStream {
+            task_id,
+            sender: Mutex::new(Some(sender)),
+            emitter: Mutex::new(Some(emitter)),
+        })
+    }
+
+    /// Record that the frontend has taken in the batches of `task_id` up
+    /// to `sequence`; false when the stream has ended
+    pub fn acknowledge(&self, task_id: TaskId, sequence: u64) -> bool {
+        match self.lock().get(&task_id) {
+            Some(window) => {
+                window.acknowledge(sequence);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Send what is left of a stream and announce its end
+    pub fn finish(&self, app: &AppHandle, stream: &FindingStream, error: Option<GenomeForgeError>) {
+        drop(lock(&stream.sender).take());
+        let emitter = lock(&stream.emitter).take();
+        let (findings, batches) = emitter
+            .and_then(|emitter| emitter.join().ok())
+            .unwrap_or_default();
+        self.lock().remove(&stream.task_id);
+        events::FINDINGS_COMPLETE.emit(
+            app,
+            FindingsComplete {
+                task_id: stream.task_id,
+                batches,
+                findings,
+                error,
+            },
+        );
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<TaskId, Arc<Window>>> {
+        lock(&self.windows)
+    }
+}
+
+/// The findings of one analysis on their way to the frontend
+#[derive(Debug)]
+pub struct FindingStream {
+    task_id: TaskId,
+    sender: Mutex<Option<SyncSender<StreamedFinding>>>,
+    emitter: Mutex<Option<JoinHandle<(usize, u64)>>>,
+}
+
+impl FindingStream {
+    /// Send the findings of a section, complete and in their final order
+    pub fn send<T: Serialize>(&self, section: FindingSection, findings: &[T]) {
+        let Some(sender) = lock(&self.sender).clone() else {
+            return;
+        };
+        for (index, finding) in findings.iter().enumerate() {
+            let Ok(finding) = serde_json::to_value(finding) else {
+                continue;
+            };
+            let streamed = StreamedFinding {
+                section,
+                index,
+                finding,
+            };
+            if sender.send(streamed).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// Helper functions
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    // Every value is replaced whole, so a poisoned lock is consistent
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,181 @@
+//! Memory, disk and OS details of the machine
+//!
+//! Read through the Win32 API on Windows; elsewhere they are unknown. The
+//! memory figures also decide whether a genome file is large enough that
+//! parsing it may exhaust the available memory, and so must be streamed.
+
+use genomeforge_core::parser::compression::Compression;
+use genomeforge_core::stream::DEFAULT_MEMORY_BUDGET;
+use std::path::Path;
+
+/// Bytes in memory per byte of uncompressed genome text, roughly, once
+/// the variants are parsed and indexed
+const MEMORY_PER_TEXT_BYTE: u64 = 10;
+
+/// How much gzip shrinks genome text, roughly
+const COMPRESSION_RATIO: u64 = 5;
+
+/// Share of the available memory a parse may use before a warning
+const MEMORY_HEADROOM: f64 = 0.8;
+
+/// Bytes of uncompressed genome text parsed per second, roughly
+const PARSE_BYTES_PER_SECOND: f64 = 40_000_000.0;
+
+/// Physical memory in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memory {
+    pub total: u64,
+    pub available: u64,
+}
+
+/// Physical memory, if it can be read
+pub fn memory() -> Option<Memory> {
+    #[cfg(windows)]
+    {
+        win::memory()
+    }
+
+    #[cfg(not(windows))]
+    {
+        None
+    }
+}
+
+/// OS name and version, e.g. "Windows 11 (10.0.22631)"
+pub fn os_version() -> String {
+    #[cfg(windows)]
+    {
+        win::os_version().unwrap_or_else(|| "Windows".to_string())
+    }
+
+    #[cfg(not(windows))]
+    {
+        "Unknown".to_string()
+    }
+}
+
+/// Free bytes on the volume holding `path`, if they can be read
+///
+/// The path need not exist yet; the nearest existing ancestor is asked.
+pub fn disk_free(path: &Path) -> Option<u64> {
+    let existing = path.ancestors().find(|dir| dir.exists())?;
+    #[cfg(windows)]
+    {
+        win::disk_free(existing)
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = existing;
+        None
+    }
+}
+
+/// Memory a parse may use: half of what is available, or
+/// [`DEFAULT_MEMORY_BUDGET`] when that is unknown
+pub fn memory_budget(memory: Option<Memory>) -> u64 {
+    memory.map_or(DEFAULT_MEMORY_BUDGET, |memory| memory.available / 2)
+}
+
+/// Rough memory a parse of a file of `size` bytes needs
+pub fn parse_memory_estimate(size: u64, compression: Compression) -> u64 {
+    text_size(size, compression).saturating_mul(MEMORY_PER_TEXT_BYTE)
+}
+
+/// Rough seconds a parse of a file of `size` bytes takes
+pub fn parse_time_estimate(size: u64, compression: Compression) -> f64 {
+    text_size(size, compression) as f64 / PARSE_BYTES_PER_SECOND
+}
+
+/// A warning when parsing a file of `size` bytes may not fit in memory
+pub fn preflight(size: u64, compression: Compression, memory: Option<Memory>) -> Option<String> {
+    let memory = memory?;
+    let needed = parse_memory_estimate(size, compression);
+    if (needed as f64) <= memory.available as f64 * MEMORY_HEADROOM {
+        return None;
+    }
+    let advice = if needed > memory.total {
+        "This computer may not have enough memory for it; importing a region or a smaller file avoids running out."
+    } else {
+        "Closing other applications before continuing makes it less likely to run out of memory."
+    };
+    Some(format!(
+        "Parsing this file needs about {} of memory, and {} of {} is available. {}",
+        gigabytes(needed),
+        gigabytes(memory.available),
+        gigabytes(memory.total),
+        advice
+    ))
+}
+
+// Helper functions
+
+/// Size of a file's text once decompressed, roughly
+fn text_size(size: u64, compression: Compression) -> u64 {
+    match compression {
+        Compression::None => size,
+        Compression::Gzip | Compression::Bgzip => size.saturating_mul(COMPRESSION_RATIO),
+    }
+}
+
+fn gigabytes(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / 1e9)
+}
+
+#[cfg(windows)]
+mod win {
+    use super::Memory;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows::core::PCWSTR;
+    use windows::Wdk::System::SystemServices::RtlGetVersion;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    use windows::Win32::System::SystemInformation::{
+        GlobalMemoryStatusEx, MEMORYSTATUSEX, OSVERSIONINFOW,
+    };
+
+    /// First build number of Windows 11, which still reports version 10.0
+    const WINDOWS_11_BUILD: u32 = 22000;
+
+    pub fn memory() -> Option<Memory> {
+        let mut status = MEMORYSTATUSEX {
+            dwLength: std::mem::size_of::<MEMORYSTATUSEX>() as u32,
+            ..Default::default()
+        };
+        unsafe { GlobalMemoryStatusEx(&mut status) }.ok()?;
+        Some(Memory {
+            total: status.ullTotalPhys,
+            available: status.ullAvailPhys,
+        })
+    }
+
+    /// Unlike GetVersionEx, RtlGetVersion reports the real version to
+    /// applications without a compatibility manifest
+    pub fn os_version() -> Option<String> {
+        let mut info = OSVERSIONINFOW {
+            dwOSVersionInfoSize: std::mem::size_of::<OSVERSIONINFOW>() as u32,
+            ..Default::default()
+        };
+        unsafe { RtlGetVersion(&mut info) }.ok().ok()?;
+        let name = match (info.dwMajorVersion, info.dwBuildNumber) {
+            (10, build) if build >= WINDOWS_11_BUILD => "Windows 11",
+            (10, _) => "Windows 10",
+            _ => "Windows",
+        };
+        Some(format!(
+            "{} ({}.{}.{})",
+            name, info.dwMajorVersion, info.dwMinorVersion, info.dwBuildNumber
+        ))
+    }
+
+    pub fn disk_free(path: &Path) -> Option<u64> {
+        let wide: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut free = 0u64;
+        unsafe { GetDiskFreeSpaceExW(PCWSTR(wide.as_ptr()), Some(&mut free), None, None) }.ok()?;
+        Some(free)
+    }
+}
@@ -0,0 +1,432 @@
+//! One row per finding, for spreadsheets and statistics tools
+//!
+//! Column names and their order are a stable schema: columns may be added
+//! at the end, but never renamed, removed or reordered, so scripts reading
+//! the exports keep working. Values are machine-readable: enums as their
+//! snake_case names, lists separated by `;`, missing values left empty.
+
+use crate::commands::{
+    AnalysisResultData, AnalysisSummary, ClinicalFinding, DrugResponse, StructuralFinding,
+    TraitAssociation,
+};
+use crate::results::serialized_name;
+use genomeforge_core::annotation::custom::CustomFinding;
+use genomeforge_core::annotation::gwas::EffectSize;
+use genomeforge_core::annotation::nutrigenomics::NutritionFinding;
+use genomeforge_core::notes::FindingNote;
+use genomeforge_core::plugin::PluginFinding;
+use genomeforge_core::report::Table;
+use genomeforge_core::watchlist::{WatchResult, WatchedCall};
+use serde::Serialize;
+
+pub const CLINICAL_COLUMNS: [&str; 19] = [
+    "rsid",
+    "gene",
+    "chromosome",
+    "position",
+    "genotype",
+    "zygosity",
+    "allele_copies",
+    "significance",
+    "significance_label",
+    "review_status",
+    "review_stars",
+    "condition",
+    "conditions",
+    "inheritance",
+    "interpretation",
+    "clinvar_variation_id",
+    "gnomad_af",
+    "confidence",
+    "confidence_level",
+];
+
+pub const DRUG_COLUMNS: [&str; 15] = [
+    "rsid",
+    "gene",
+    "drug",
+    "genotype",
+    "diplotype",
+    "phenotype",
+    "evidence_level",
+    "phenotype_categories",
+    "response",
+    "recommendation",
+    "guideline_sources",
+    "annotation_id",
+    "url",
+    "confidence",
+    "confidence_level",
+];
+
+pub const TRAIT_COLUMNS: [&str; 15] = [
+    "rsid",
+    "trait",
+    "category",
+    "genes",
+    "genotype",
+    "risk_allele",
+    "risk_allele_copies",
+    "effect_direction",
+    "effect_type",
+    "effect_size",
+    "p_value",
+    "confidence",
+    "risk_allele_frequency",
+    "pubmed_id",
+    "confidence_level",
+];
+
+pub const NUTRITION_COLUMNS: [&str; 10] = [
+    "rsid",
+    "gene",
+    "variant",
+    "area",
+    "genotype",
+    "effect_allele_copies",
+    "evidence",
+    "effect",
+    "confidence",
+    "confidence_level",
+];
+
+pub const STRUCTURAL_COLUMNS: [&str; 15] = [
+    "id",
+    "type",
+    "chromosome",
+    "start",
+    "end",
+    "length",
+    "allele_copies",
+    "copy_number",
+    "gene",
+    "dosage_score",
+    "disease",
+    "overlap",
+    "filters",
+    "confidence",
+    "confidence_level",
+];
+
+pub const PLUGIN_COLUMNS: [&str; 11] = [
+    "plugin",
+    "title",
+    "genes",
+    "rsid",
+    "genotype",
+    "significance",
+    "categories",
+    "evidence",
+    "references",
+    "confidence",
+    "confidence_level",
+];
+
+/// Columns of the table of findings from the imported annotation table
+pub const CUSTOM_COLUMNS: [&str; 10] = [
+    "rsid",
+    "gene",
+    "genotype",
+    "interpretation",
+    "significance",
+    "category",
+    "reference",
+    "source",
+    "confidence",
+    "confidence_level",
+];
+
+/// Columns of the watchlist table, one row per call and one for each
+/// entry without any
+pub const WATCHLIST_COLUMNS: [&str; 8] = [
+    "target",
+    "label",
+    "status",
+    "rsid",
+    "chromosome",
+    "position",
+    "genotype",
+    "omitted",
+];
+
+/// Columns of the bookmarks and notes table
+pub const NOTE_COLUMNS: [&str; 5] = ["section", "finding", "bookmarked", "note", "updated_at"];
+
+/// Columns of the provenance table, one row per database release the
+/// analysis read and one per plugin run, as "plugin:<id>"
+pub const PROVENANCE_COLUMNS: [&str; 5] = [
+    "source",
+    "version",
+    "release_date",
+    "sha256",
+    "record_count",
+];
+
+/// The finding tables of an analysis, named by category, with those of an
+/// imported annotation table and the watchlist when there are any, its
+/// notes when the export includes them, and the releases they were read
+/// from
+pub fn tables(results: &AnalysisResultData) -> Vec<(&'static str, Table)> {
+    let mut tables = vec![
+        ("clinical", clinical(&results.clinical_findings)),
+        ("drug_responses", drugs(&results.drug_responses)),
+        ("traits", traits(&results.trait_associations)),
+        ("nutrigenomics", nutrition(&results.nutrition)),
+        ("structural", structural(&results.structural_findings)),
+        ("plugins", plugins(&results.plugin_findings)),
+    ];
+    if !results.custom_findings.is_empty() {
+        tables.push(("custom", custom(&results.custom_findings)));
+    }
+    if !results.watchlist.is_empty() {
+        tables.push(("watchlist", watchlist(&results.watchlist)));
+    }
+    if !results.notes.is_empty() {
+        tables.push(("notes", notes(&results.notes)));
+    }
+    tables.push(("provenance", provenance(&results.summary)));
+    tables
+}
+
+// Helper functions
+
+fn clinical(findings: &[ClinicalFinding]) -> Table {
+    let mut table = Table::new(CLINICAL_COLUMNS);
+    for finding in findings {
+        table.push_row([
+            finding.rsid.clone(),
+            finding.gene.clone().unwrap_or_default(),
+            finding.chromosome.clone().unwrap_or_default(),
+            optional(finding.position),
+            finding.genotype.clone(),
+            name(&finding.zygosity),
+            finding.allele_copies.to_string(),
+            name(&finding.significance),
+            finding.significance_label.clone(),
+            name(&finding.review_status),
+            finding.review_stars.to_string(),
+            finding.condition.clone(),
+            finding.conditions.join(";"),
+            finding.inheritance.as_ref().map(name).unwrap_or_default(),
+            finding
+                .interpretation
+                .as_ref()
+                .map(name)
+                .unwrap_or_default(),
+            optional(finding.variation_id),
+            optional(finding.allele_frequency.as_ref().map(|af| af.global)),
+            finding.confidence.score.to_string(),
+            name(&finding.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn drugs(responses: &[DrugResponse]) -> Table {
+    let mut table = Table::new(DRUG_COLUMNS);
+    for response in responses {
+        let categories: Vec<&str> = response
+            .phenotype_categories
+            .iter()
+            .map(|category| category.as_str())
+            .collect();
+        table.push_row([
+            response.rsid.clone(),
+            response.gene.clone(),
+            response.drug.clone(),
+            response.genotype.clone(),
+            response.diplotype.clone().unwrap_or_default(),
+            response.phenotype.clone().unwrap_or_default(),
+            response.evidence_level.as_str().to_string(),
+            categories.join(";"),
+            response.response.clone(),
+            response.recommendation.clone(),
+            response.guideline_sources.join(";"),
+            response.annotation_id.clone(),
+            response.url.clone().unwrap_or_default(),
+            response.confidence.score.to_string(),
+            name(&response.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn traits(associations: &[TraitAssociation]) -> Table {
+    let mut table = Table::new(TRAIT_COLUMNS);
+    for association in associations {
+        let (effect_type, effect_size) = match association.effect_size {
+            Some(EffectSize::OddsRatio(or)) => ("odds_ratio", or.to_string()),
+            Some(EffectSize::Beta(beta)) => ("beta", beta.to_string()),
+            None => ("", String::new()),
+        };
+        table.push_row([
+            association.rsid.clone(),
+            association.trait_name.clone(),
+            name(&association.category),
+            association.genes.join(";"),
+            association.genotype.clone(),
+            association.risk_allele.clone(),
+            association.risk_allele_copies.to_string(),
+            name(&association.effect_direction),
+            effect_type.to_string(),
+            effect_size,
+            association.p_value.to_string(),
+            association.confidence.score.to_string(),
+            optional(association.risk_allele_frequency),
+            association.pubmed_id.clone().unwrap_or_default(),
+            name(&association.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn nutrition(findings: &[NutritionFinding]) -> Table {
+    let mut table = Table::new(NUTRITION_COLUMNS);
+    for finding in findings {
+        table.push_row([
+            finding.rsid.clone(),
+            finding.gene.clone(),
+            finding.name.clone(),
+            name(&finding.area),
+            finding.genotype.clone(),
+            finding.effect_copies.to_string(),
+            name(&finding.evidence),
+            finding.effect.clone(),
+            finding.confidence.score.to_string(),
+            name(&finding.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn structural(findings: &[StructuralFinding]) -> Table {
+    let mut table = Table::new(STRUCTURAL_COLUMNS);
+    for finding in findings {
+        table.push_row([
+            finding.id.clone().unwrap_or_default(),
+            name(&finding.kind),
+            finding.chromosome.clone(),
+            finding.start.to_string(),
+            finding.end.to_string(),
+            finding.length.to_string(),
+            optional(finding.copies),
+            optional(finding.copy_number),
+            finding.gene.clone(),
+            name(&finding.score),
+            finding.disease.clone().unwrap_or_default(),
+            finding.overlap.to_string(),
+            finding.filters.join(";"),
+            finding.confidence.score.to_string(),
+            name(&finding.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn plugins(findings: &[PluginFinding]) -> Table {
+    let mut table = Table::new(PLUGIN_COLUMNS);
+    for finding in findings {
+        table.push_row([
+            finding.plugin.clone(),
+            finding.title.clone(),
+            finding.genes.join(";"),
+            finding.rsid.clone().unwrap_or_default(),
+            finding.genotype.clone().unwrap_or_default(),
+            name(&finding.significance),
+            finding
+                .categories
+                .iter()
+                .map(name)
+                .collect::<Vec<_>>()
+                .join(";"),
+            finding.evidence.to_string(),
+            finding.references.join(";"),
+            finding.confidence.score.to_string(),
+            name(&finding.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn custom(findings: &[CustomFinding]) -> Table {
+    let mut table = Table::new(CUSTOM_COLUMNS);
+    for finding in findings {
+        table.push_row([
+            finding.rsid.clone(),
+            optional(finding.gene.as_ref()),
+            finding.genotype.clone(),
+            finding.interpretation.clone(),
+            name(&finding.significance),
+            finding.category.as_ref().map(name).unwrap_or_default(),
+            optional(finding.reference.as_ref()),
+            optional(finding.source.as_ref()),
+            finding.confidence.score.to_string(),
+            name(&finding.confidence.level),
+        ]);
+    }
+    table
+}
+
+fn watchlist(results: &[WatchResult]) -> Table {
+    let mut table = Table::new(WATCHLIST_COLUMNS);
+    for watched in results {
+        let row = |call: Option<&WatchedCall>| {
+            [
+                watched.entry.target.clone(),
+                watched.entry.label.clone(),
+                name(&watched.status),
+                optional(call.and_then(|call| call.rsid.as_ref())),
+                optional(call.map(|call| &call.chromosome)),
+                optional(call.map(|call| call.position)),
+                optional(call.map(|call| &call.genotype)),
+                watched.omitted.to_string(),
+            ]
+        };
+        if watched.calls.is_empty() {
+            table.push_row(row(None));
+        }
+        for call in &watched.calls {
+            table.push_row(row(Some(call)));
+        }
+    }
+    table
+}
+
+fn notes(entries: &[FindingNote]) -> Table {
+    let mut table = Table::new(NOTE_COLUMNS);
+    for entry in entries {
+        table.push_row([
+            entry.finding.section.clone(),
+            entry.label.clone(),
+            entry.bookmarked.to_string(),
+            entry.note.clone(),
+            entry.updated_at.to_string(),
+        ]);
+    }
+    table
+}
+
+fn provenance(summary: &AnalysisSummary) -> Table {
+    let mut table = Table::new(PROVENANCE_COLUMNS);
+    for release in &summary.databases {
+        table.push_row([
+            release.database.as_str().to_string(),
+            optional(release.version.as_ref()),
+            optional(release.release_date.as_ref()),
+            optional(release.sha256.as_ref()),
+            release.record_count.to_string(),
+        ]);
+    }
+    for run in &summary.plugins {
+        table.push_row([format!("plugin:{}", run.id), run.version.clone()]);
+    }
+    table
+}
+
+fn name<T: Serialize>(value: &T) -> String {
+    serialized_name(value).unwrap_or_default()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
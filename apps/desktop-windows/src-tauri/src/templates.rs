@@ -0,0 +1,114 @@
+//! Report templates shipped with the app and saved by the user
+//!
+//! The built-in templates are compiled in, so they update with the app.
+//! User templates are JSON files in the `report-templates` directory of
+//! the active profile; one saved under a built-in template's id
+//! replaces it. The template used when an export names none is
+//! remembered in the same directory.
+
+use crate::{profiles, report};
+use genomeforge_core::report::template::{self, ReportTemplate};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+const BUILT_IN: [&str; 3] = [
+    include_str!("../templates/clinical-summary.json"),
+    include_str!("../templates/full-technical.json"),
+    include_str!("../templates/wellness.json"),
+];
+
+/// Template used until the user selects another
+pub const DEFAULT: &str = "full-technical";
+
+/// File in the template directory holding the selected template's id
+const SELECTED_FILE: &str = "selected";
+
+/// A template as listed to the user
+#[derive(Debug, Serialize)]
+pub struct TemplateEntry {
+    #[serde(flatten)]
+    pub template: ReportTemplate,
+    /// Shipped with the app rather than saved by the user
+    pub built_in: bool,
+    /// Used for exports that name no template
+    pub selected: bool,
+}
+
+/// Directory holding the active profile's templates
+pub fn template_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_dir(app).map(|dir| dir.join("report-templates"))
+}
+
+/// Every template, built-in ones first
+pub fn list(dir: &Path) -> Result<Vec<TemplateEntry>, String> {
+    let selected = selected_id(dir);
+    let saved = template::load_dir(dir)?;
+    let built_in: Vec<ReportTemplate> = built_in()?
+        .into_iter()
+        .filter(|template| !saved.iter().any(|saved| saved.id == template.id))
+        .collect();
+    let mut entries: Vec<TemplateEntry> = built_in
+        .into_iter()
+        .map(|template| (template, true))
+        .chain(saved.into_iter().map(|template| (template, false)))
+        .map(|(template, built_in)| TemplateEntry {
+            selected: template.id == selected,
+            template,
+            built_in,
+        })
+        .collect();
+    // Exports fall back to the default when the selected one was deleted
+    if !entries.iter().any(|entry| entry.selected) {
+        for entry in &mut entries {
+            entry.selected = entry.template.id == DEFAULT;
+        }
+    }
+    Ok(entries)
+}
+
+/// The template with this id, or the selected one
+pub fn find(dir: &Path, id: Option<&str>) -> Result<ReportTemplate, String> {
+    let templates: Vec<ReportTemplate> =
+        list(dir)?.into_iter().map(|entry| entry.template).collect();
+    let by_id = |id: &str| templates.iter().find(|template| template.id == id);
+    let template = match id {
+        Some(id) => by_id(id).ok_or_else(|| format!("Unknown report template: {}", id))?,
+        // The selected template may have been deleted since
+        None => by_id(&selected_id(dir))
+            .or_else(|| by_id(DEFAULT))
+            .ok_or_else(|| "No report templates".to_string())?,
+    };
+    template.validate(&report::SECTIONS)?;
+    Ok(template.clone())
+}
+
+/// Use this template for exports that name none
+pub fn select(dir: &Path, id: &str) -> Result<(), String> {
+    find(dir, Some(id))?;
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(dir.join(SELECTED_FILE), id))
+        .map_err(|e| format!("Failed to save selected template: {}", e))
+}
+
+/// Save a user template
+pub fn save(dir: &Path, template: &ReportTemplate) -> Result<(), String> {
+    template.validate(&report::SECTIONS)?;
+    template::save(dir, template).map(|_| ())
+}
+
+// Helper functions
+
+fn built_in() -> Result<Vec<ReportTemplate>, String> {
+    BUILT_IN
+        .iter()
+        .map(|json| ReportTemplate::from_json(json))
+        .collect()
+}
+
+fn selected_id(dir: &Path) -> String {
+    fs::read_to_string(dir.join(SELECTED_FILE))
+        .map(|id| id.trim().to_string())
+        .unwrap_or_else(|_| DEFAULT.to_string())
+}
@@ -0,0 +1,1001 @@
+//! Reasoning traces: why a finding was reported
+//!
+//! A trace retells, step by step, how the engine reached a finding of the
+//! latest analysis: the database records it matched, how the call's
+//! alleles were matched to theirs and on which strand, the filters the
+//! call went through on the way and the evidence the conclusion rests on.
+//! It is put together from the finding and the analysis summary, so it
+//! describes the analysis as it ran even when the databases have been
+//! updated since; the installed ClinVar release is only read for the
+//! alleles of the matched record. Each record names the release it was
+//! read from, as the analysis recorded it, so the finding can be cited.
+
+use crate::commands::{
+    AcmgFinding, AnalysisResultData, AnalysisSummary, CarrierFinding, ClinicalFinding,
+    DrugResponse, StructuralFinding, TraitAssociation,
+};
+use crate::results::{serialized_name, FindingSection};
+use genomeforge_core::annotation::clinvar::ClinVarDatabase;
+use genomeforge_core::annotation::cpic::DiplotypeCall;
+use genomeforge_core::annotation::custom::CustomFinding;
+use genomeforge_core::annotation::gwas::GENOME_WIDE_SIGNIFICANCE;
+use genomeforge_core::annotation::hla::{HlaCall, HlaEvidence};
+use genomeforge_core::annotation::manager::DatabaseKind;
+use genomeforge_core::annotation::nutrigenomics::NutritionFinding;
+use genomeforge_core::annotation::strand::Strand;
+use genomeforge_core::confidence::Confidence;
+use genomeforge_core::plugin::PluginFinding;
+use genomeforge_core::quality::QualityIssue;
+use serde::Serialize;
+
+/// How the engine reached one finding, from `explain_finding`
+#[derive(Debug, Serialize)]
+pub struct FindingTrace {
+    pub section: FindingSection,
+    pub index: usize,
+    /// What the finding reports, e.g. "BRCA1 rs80357906: Pathogenic"
+    pub title: String,
+    /// Database records the finding was read from
+    pub records: Vec<RecordRef>,
+    /// In the order the engine took them
+    pub steps: Vec<TraceStep>,
+    pub confidence: Confidence,
+    /// Traces of the variants of a finding reported per gene
+    pub variants: Vec<FindingTrace>,
+}
+
+/// Where a record a finding was read from comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSource {
+    ClinVar,
+    DbSnp,
+    PharmGkb,
+    Cpic,
+    GwasCatalog,
+    ClinGen,
+    /// The ACMG secondary findings gene list
+    Acmg,
+    /// A panel built into the engine, such as the HLA proxies
+    BuiltIn,
+    /// An installed analysis plugin, by its id
+    Plugin,
+    /// The annotation table the user imported, by the entry's reference
+    Custom,
+}
+
+impl RecordSource {
+    /// The installed database of the source; none for the sources that
+    /// come with the engine or a plugin
+    pub fn database(self) -> Option<DatabaseKind> {
+        match self {
+            RecordSource::ClinVar => Some(DatabaseKind::ClinVar),
+            RecordSource::DbSnp => Some(DatabaseKind::DbSnp),
+            RecordSource::PharmGkb => Some(DatabaseKind::PharmGkb),
+            RecordSource::Cpic => Some(DatabaseKind::Cpic),
+            RecordSource::GwasCatalog => Some(DatabaseKind::Gwas),
+            RecordSource::ClinGen => Some(DatabaseKind::ClinGen),
+            RecordSource::Custom => Some(DatabaseKind::Custom),
+            RecordSource::Acmg | RecordSource::BuiltIn | RecordSource::Plugin => None,
+        }
+    }
+}
+
+/// A database record, by its identifier in the source
+#[derive(Debug, Serialize)]
+pub struct RecordRef {
+    pub source: RecordSource,
+    pub id: String,
+    pub url: Option<String>,
+    /// Version of the source the record was read from, or the date of its
+    /// release; the ACMG list's version and a plugin's are given too
+    pub release: Option<String>,
+    /// Hex SHA-256 of the installed release file
+    pub sha256: Option<String>,
+}
+
+/// What part of the analysis a step belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStage {
+    /// Liftover and the like, done to the whole genome first
+    Preprocessing,
+    /// How the site was found in the database
+    SiteMatch,
+    /// How the call's alleles were matched to the record's
+    AlleleMatch,
+    Strand,
+    /// Sequencing quality, imputation and probe mask filters
+    CallQuality,
+    /// Population frequency of the allele
+    Frequency,
+    /// What the match was taken to mean
+    Interpretation,
+    /// The evidence the conclusion rests on
+    Evidence,
+}
+
+/// How a step bore on the finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    /// A check the finding met
+    Passed,
+    /// A change made to the data or the database alleles
+    Applied,
+    /// A check that lowered the confidence in the finding
+    Caution,
+    /// Context for the finding
+    Noted,
+}
+
+/// One decision behind a finding
+#[derive(Debug, Serialize)]
+pub struct TraceStep {
+    pub stage: TraceStage,
+    pub outcome: StepOutcome,
+    pub detail: String,
+}
+
+/// The trace of one finding of a section, by its position in the section
+///
+/// `clinvar` is the installed release, read for the alleles of matched
+/// ClinVar records.
+pub fn explain(
+    result: &AnalysisResultData,
+    section: FindingSection,
+    index: usize,
+    clinvar: Option<&ClinVarDatabase>,
+) -> Result<FindingTrace, String> {
+    let summary = &result.summary;
+    let missing = || format!("No finding {} in the {:?} section", index, section);
+    let mut trace = match section {
+        FindingSection::Clinical => clinical(
+            summary,
+            result.clinical_findings.get(index).ok_or_else(missing)?,
+            clinvar,
+        ),
+        FindingSection::SecondaryFindings => acmg(
+            summary,
+            result.acmg_findings.get(index).ok_or_else(missing)?,
+            clinvar,
+        ),
+        FindingSection::Carrier => carrier(
+            summary,
+            result.carrier_findings.get(index).ok_or_else(missing)?,
+            clinvar,
+        ),
+        FindingSection::DrugResponse => drug(
+            summary,
+            result.drug_responses.get(index).ok_or_else(missing)?,
+        ),
+        FindingSection::Diplotype => {
+            diplotype(summary, result.diplotypes.get(index).ok_or_else(missing)?)
+        }
+        FindingSection::Trait => association(
+            summary,
+            result.trait_associations.get(index).ok_or_else(missing)?,
+        ),
+        FindingSection::HlaRisk => hla(summary, result.hla_risks.get(index).ok_or_else(missing)?),
+        FindingSection::Nutrition => {
+            nutrition(summary, result.nutrition.get(index).ok_or_else(missing)?)
+        }
+        FindingSection::Structural => {
+            structural(result.structural_findings.get(index).ok_or_else(missing)?)
+        }
+        FindingSection::Plugin => plugin(result.plugin_findings.get(index).ok_or_else(missing)?),
+        FindingSection::Custom => custom(result.custom_findings.get(index).ok_or_else(missing)?),
+    };
+    trace.section = section;
+    trace.index = index;
+    cite(&mut trace, summary);
+    Ok(trace)
+}
+
+// Helper functions
+
+fn step(stage: TraceStage, outcome: StepOutcome, detail: impl Into<String>) -> TraceStep {
+    TraceStep {
+        stage,
+        outcome,
+        detail: detail.into(),
+    }
+}
+
+fn record(source: RecordSource, id: impl Into<String>) -> RecordRef {
+    RecordRef {
+        source,
+        id: id.into(),
+        url: None,
+        release: None,
+        sha256: None,
+    }
+}
+
+/// Fill in the releases the records of a trace and its variants were read
+/// from; results saved before releases were recorded only have ClinVar's
+/// date
+fn cite(trace: &mut FindingTrace, summary: &AnalysisSummary) {
+    for record in trace
+        .records
+        .iter_mut()
+        .filter(|record| record.release.is_none())
+    {
+        if record.source == RecordSource::Plugin {
+            record.release = summary
+                .plugins
+                .iter()
+                .find(|run| run.id == record.id)
+                .map(|run| run.version.clone());
+            continue;
+        }
+        let Some(kind) = record.source.database() else {
+            continue;
+        };
+        match summary
+            .databases
+            .iter()
+            .find(|release| release.database == kind)
+        {
+            Some(release) => {
+                record.release = release.citation().map(str::to_string);
+                record.sha256 = release.sha256.clone();
+            }
+            None if kind == DatabaseKind::ClinVar => {
+                record.release = summary.clinvar_release.clone();
+            }
+            None => {}
+        }
+    }
+    for variant in &mut trace.variants {
+        cite(variant, summary);
+    }
+}
+
+fn new_trace(
+    title: String,
+    records: Vec<RecordRef>,
+    steps: Vec<TraceStep>,
+    confidence: Confidence,
+) -> FindingTrace {
+    FindingTrace {
+        section: FindingSection::Clinical,
+        index: 0,
+        title,
+        records,
+        steps,
+        confidence,
+        variants: Vec::new(),
+    }
+}
+
+/// An enum value in words, e.g. "likely pathogenic"
+fn words<T: Serialize>(value: &T) -> String {
+    serialized_name(value).map_or_else(String::new, |name| name.replace('_', " "))
+}
+
+/// Steps done to the whole genome before anything was matched
+fn preprocessing(summary: &AnalysisSummary) -> Vec<TraceStep> {
+    let mut steps = Vec::new();
+    if let Some(liftover) = &summary.liftover {
+        steps.push(step(
+            TraceStage::Preprocessing,
+            StepOutcome::Applied,
+            format!(
+                "Positions lifted from {:?} to {:?} before annotation",
+                liftover.from, liftover.to
+            ),
+        ));
+    }
+    steps
+}
+
+/// Strand, quality, imputation and probe mask steps of one call
+fn call_steps(
+    summary: &AnalysisSummary,
+    imputed: bool,
+    issues: &[QualityIssue],
+    strand: Strand,
+) -> Vec<TraceStep> {
+    let mut steps = vec![strand_step(strand)];
+    let sequencing: Vec<String> = issues
+        .iter()
+        .filter(|issue| **issue != QualityIssue::UnreliableProbe)
+        .map(words)
+        .collect();
+    if !sequencing.is_empty() {
+        steps.push(step(
+            TraceStage::CallQuality,
+            StepOutcome::Caution,
+            format!(
+                "Kept by the quality filter but borderline: {}",
+                sequencing.join(", ")
+            ),
+        ));
+    } else if summary.quality.is_some() {
+        steps.push(step(
+            TraceStage::CallQuality,
+            StepOutcome::Passed,
+            "Passed the sequencing quality filter",
+        ));
+    }
+    if let Some(mask) = &summary.probe_mask {
+        let chip = mask
+            .chip
+            .as_ref()
+            .map_or_else(|| "the file's chip".to_string(), |chip| chip.to_string());
+        steps.push(if issues.contains(&QualityIssue::UnreliableProbe) {
+            step(
+                TraceStage::CallQuality,
+                StepOutcome::Caution,
+                format!("The probe mask flags this probe as unreliable on {}", chip),
+            )
+        } else {
+            step(
+                TraceStage::CallQuality,
+                StepOutcome::Passed,
+                format!("Not on the probe mask for {}", chip),
+            )
+        });
+    }
+    if imputed {
+        let detail = if summary.imputation.is_some() {
+            "Imputed rather than genotyped; kept by the imputation filter"
+        } else {
+            "Imputed rather than genotyped"
+        };
+        steps.push(step(TraceStage::CallQuality, StepOutcome::Caution, detail));
+    }
+    steps
+}
+
+fn strand_step(strand: Strand) -> TraceStep {
+    let (outcome, detail) = match strand {
+        Strand::Forward => (
+            StepOutcome::Passed,
+            "The database alleles match the call as reported",
+        ),
+        Strand::Flipped => (
+            StepOutcome::Applied,
+            "Only the complemented database alleles match the call, so they were read from the opposite strand",
+        ),
+        Strand::FrequencyForward => (
+            StepOutcome::Applied,
+            "A/T or C/G SNP placed on the call's strand by its gnomAD allele frequencies",
+        ),
+        Strand::FrequencyFlipped => (
+            StepOutcome::Applied,
+            "A/T or C/G SNP placed on the opposite strand by its gnomAD allele frequencies",
+        ),
+        Strand::Ambiguous => (
+            StepOutcome::Caution,
+            "A/T or C/G SNP whose strand could not be told; the alleles were taken as reported",
+        ),
+    };
+    step(TraceStage::Strand, outcome, detail)
+}
+
+fn confidence_step(confidence: &Confidence) -> TraceStep {
+    step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        format!(
+            "{} confidence ({:.2}): evidence {:.2} times call reliability {:.2}",
+            capitalized(&words(&confidence.level)),
+            confidence.score,
+            confidence.evidence,
+            confidence.call
+        ),
+    )
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+fn clinical(
+    summary: &AnalysisSummary,
+    finding: &ClinicalFinding,
+    clinvar: Option<&ClinVarDatabase>,
+) -> FindingTrace {
+    let mut records = Vec::new();
+    if let Some(id) = finding.variation_id {
+        records.push(RecordRef {
+            url: Some(format!(
+                "https://www.ncbi.nlm.nih.gov/clinvar/variation/{}/",
+                id
+            )),
+            ..record(RecordSource::ClinVar, id.to_string())
+        });
+    }
+    if finding.rsid.starts_with("rs") {
+        records.push(record(RecordSource::DbSnp, finding.rsid.clone()));
+    }
+
+    let mut steps = preprocessing(summary);
+    let site = match (&finding.chromosome, finding.position) {
+        (Some(chromosome), Some(position)) => format!("{}:{}", chromosome, position),
+        _ => finding.rsid.clone(),
+    };
+    steps.push(step(
+        TraceStage::SiteMatch,
+        StepOutcome::Passed,
+        format!("ClinVar record matched at {} ({})", site, finding.rsid),
+    ));
+    let matched = clinvar.and_then(|clinvar| {
+        let id = finding.variation_id?;
+        clinvar
+            .lookup_rsid(&finding.rsid)
+            .into_iter()
+            .find(|record| record.variation_id == Some(id))
+    });
+    let allele = matched.map_or_else(String::new, |record| {
+        format!(" {}>{}", record.reference, record.alternate)
+    });
+    steps.push(step(
+        TraceStage::AlleleMatch,
+        StepOutcome::Passed,
+        format!(
+            "Genotype {} carries {} {} of the classified allele{}",
+            finding.genotype,
+            finding.allele_copies,
+            if finding.allele_copies == 1 {
+                "copy"
+            } else {
+                "copies"
+            },
+            allele
+        ),
+    ));
+    steps.extend(call_steps(
+        summary,
+        finding.imputed,
+        &finding.borderline_quality,
+        finding.strand,
+    ));
+    steps.push(match &finding.allele_frequency {
+        Some(frequency) => step(
+            TraceStage::Frequency,
+            StepOutcome::Passed,
+            format!(
+                "gnomAD allele frequency {:.5}, at most {:.5} in any population",
+                frequency.global,
+                frequency.max_frequency()
+            ),
+        ),
+        None => step(
+            TraceStage::Frequency,
+            StepOutcome::Noted,
+            "No gnomAD frequency for the allele",
+        ),
+    });
+    let mut meaning = vec![words(&finding.zygosity)];
+    meaning.extend(finding.inheritance.as_ref().map(words));
+    meaning.extend(finding.interpretation.as_ref().map(words));
+    steps.push(step(
+        TraceStage::Interpretation,
+        StepOutcome::Noted,
+        capitalized(&meaning.join(", ")),
+    ));
+    let release = finding
+        .clinvar_release
+        .as_deref()
+        .map_or_else(String::new, |date| format!(", ClinVar release {}", date));
+    steps.push(step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        format!(
+            "{} of 4 review stars ({}){}",
+            finding.review_stars,
+            words(&finding.review_status),
+            release
+        ),
+    ));
+    steps.push(confidence_step(&finding.confidence));
+
+    let gene = finding.gene.as_deref().unwrap_or(&finding.rsid);
+    new_trace(
+        format!("{} {}: {}", gene, finding.rsid, finding.significance_label),
+        records,
+        steps,
+        finding.confidence,
+    )
+}
+
+fn acmg(
+    summary: &AnalysisSummary,
+    finding: &AcmgFinding,
+    clinvar: Option<&ClinVarDatabase>,
+) -> FindingTrace {
+    let steps = vec![
+        step(
+            TraceStage::Interpretation,
+            StepOutcome::Noted,
+            format!(
+                "{} is on the ACMG secondary findings list v{} ({}, {} inheritance)",
+                finding.gene,
+                finding.acmg_version,
+                words(&finding.category),
+                words(&finding.inheritance)
+            ),
+        ),
+        confidence_step(&finding.confidence),
+    ];
+    let mut trace = new_trace(
+        format!("{}: {}", finding.gene, finding.condition),
+        vec![RecordRef {
+            release: Some(finding.acmg_version.clone()),
+            ..record(RecordSource::Acmg, finding.gene.clone())
+        }],
+        steps,
+        finding.confidence,
+    );
+    trace.variants = finding
+        .variants
+        .iter()
+        .map(|variant| clinical(summary, variant, clinvar))
+        .collect();
+    trace
+}
+
+fn carrier(
+    summary: &AnalysisSummary,
+    finding: &CarrierFinding,
+    clinvar: Option<&ClinVarDatabase>,
+) -> FindingTrace {
+    let outcome = if finding.affected {
+        "expected to cause the condition"
+    } else {
+        "expected to be passed on rather than to cause the condition"
+    };
+    let steps = vec![
+        step(
+            TraceStage::Interpretation,
+            StepOutcome::Noted,
+            format!(
+                "{} pathogenic {} in {} read together: {}, {}",
+                finding.variants.len(),
+                if finding.variants.len() == 1 {
+                    "variant"
+                } else {
+                    "variants"
+                },
+                finding.gene,
+                words(&finding.status),
+                outcome
+            ),
+        ),
+        confidence_step(&finding.confidence),
+    ];
+    let mut trace = new_trace(
+        format!(
+            "{}: {} ({})",
+            finding.gene,
+            words(&finding.status),
+            finding.condition
+        ),
+        Vec::new(),
+        steps,
+        finding.confidence,
+    );
+    trace.variants = finding
+        .variants
+        .iter()
+        .map(|variant| clinical(summary, variant, clinvar))
+        .collect();
+    trace
+}
+
+fn drug(summary: &AnalysisSummary, response: &DrugResponse) -> FindingTrace {
+    let mut records = Vec::new();
+    let mut steps = preprocessing(summary);
+    match (
+        &response.diplotype,
+        &response.phenotype,
+        &response.guideline,
+    ) {
+        (Some(diplotype), Some(phenotype), Some(_)) => {
+            records.push(record(RecordSource::Cpic, response.annotation_id.clone()));
+            steps.push(step(
+                TraceStage::SiteMatch,
+                StepOutcome::Passed,
+                format!(
+                    "{} diplotype {} called from the gene's star-allele sites",
+                    response.gene, diplotype
+                ),
+            ));
+            steps.push(step(
+                TraceStage::Interpretation,
+                StepOutcome::Noted,
+                format!("{} recommendation for a {}", response.drug, phenotype),
+            ));
+            if response.imputed {
+                steps.push(step(
+                    TraceStage::CallQuality,
+                    StepOutcome::Caution,
+                    "Some calls behind the diplotype were imputed",
+                ));
+            }
+        }
+        _ => {
+            records.push(RecordRef {
+                url: response.url.clone(),
+                ..record(RecordSource::PharmGkb, response.annotation_id.clone())
+            });
+            steps.push(step(
+                TraceStage::SiteMatch,
+                StepOutcome::Passed,
+                format!(
+                    "PharmGKB clinical annotation {} matched by {}",
+                    response.annotation_id, response.rsid
+                ),
+            ));
+            steps.push(step(
+                TraceStage::AlleleMatch,
+                StepOutcome::Passed,
+                format!(
+                    "Genotype {} matches an annotated genotype of the site",
+                    response.genotype
+                ),
+            ));
+            steps.extend(call_steps(
+                summary,
+                response.imputed,
+                &response.borderline_quality,
+                response.strand,
+            ));
+        }
+    }
+    let sources = if response.guideline_sources.is_empty() {
+        "no dosing guideline or drug label".to_string()
+    } else {
+        response.guideline_sources.join(", ")
+    };
+    steps.push(step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        format!(
+            "PharmGKB level {}; {}",
+            response.evidence_level.as_str(),
+            sources
+        ),
+    ));
+    steps.push(confidence_step(&response.confidence));
+    new_trace(
+        format!(
+            "{} and {}: {}",
+            response.gene, response.drug, response.response
+        ),
+        records,
+        steps,
+        response.confidence,
+    )
+}
+
+fn diplotype(summary: &AnalysisSummary, call: &DiplotypeCall) -> FindingTrace {
+    let mut steps = preprocessing(summary);
+    let complete = call.sites_genotyped == call.sites_total;
+    steps.push(step(
+        TraceStage::SiteMatch,
+        if complete {
+            StepOutcome::Passed
+        } else {
+            StepOutcome::Caution
+        },
+        format!(
+            "{} of {} allele-defining sites genotyped; alleles with a site missing were not considered",
+            call.sites_genotyped, call.sites_total
+        ),
+    ));
+    steps.push(if call.alternatives.is_empty() {
+        step(
+            TraceStage::AlleleMatch,
+            StepOutcome::Passed,
+            "No other diplotype fits the genotypes as well",
+        )
+    } else {
+        step(
+            TraceStage::AlleleMatch,
+            StepOutcome::Caution,
+            format!(
+                "Also fits {}; the one defined by the most variant sites was called",
+                call.alternatives.join(", ")
+            ),
+        )
+    });
+    if call.sites_imputed > 0 {
+        steps.push(step(
+            TraceStage::CallQuality,
+            StepOutcome::Caution,
+            format!("{} of the genotyped sites imputed", call.sites_imputed),
+        ));
+    }
+    let activity = call
+        .activity_score
+        .map_or_else(String::new, |score| format!(", activity score {}", score));
+    steps.push(step(
+        TraceStage::Interpretation,
+        StepOutcome::Noted,
+        format!("Phenotype {}{}", call.phenotype, activity),
+    ));
+    steps.push(confidence_step(&call.confidence));
+    new_trace(
+        format!("{} {}: {}", call.gene, call.diplotype, call.phenotype),
+        vec![record(RecordSource::Cpic, call.gene.clone())],
+        steps,
+        call.confidence,
+    )
+}
+
+fn association(summary: &AnalysisSummary, association: &TraitAssociation) -> FindingTrace {
+    let records = association
+        .pubmed_id
+        .iter()
+        .map(|id| RecordRef {
+            url: Some(format!("https://pubmed.ncbi.nlm.nih.gov/{}/", id)),
+            ..record(RecordSource::GwasCatalog, id.clone())
+        })
+        .collect();
+    let mut steps = preprocessing(summary);
+    steps.push(if association.p_value <= GENOME_WIDE_SIGNIFICANCE {
+        step(
+            TraceStage::SiteMatch,
+            StepOutcome::Passed,
+            format!(
+                "Association at {} with p = {:e}, past genome-wide significance",
+                association.rsid, association.p_value
+            ),
+        )
+    } else {
+        step(
+            TraceStage::SiteMatch,
+            StepOutcome::Noted,
+            format!(
+                "Association at {} with p = {:e}, from the built-in panel",
+                association.rsid, association.p_value
+            ),
+        )
+    });
+    steps.push(step(
+        TraceStage::AlleleMatch,
+        StepOutcome::Passed,
+        format!(
+            "Genotype {} carries {} of the risk allele {}",
+            association.genotype,
+            if association.risk_allele_copies == 1 {
+                "one copy".to_string()
+            } else {
+                format!("{} copies", association.risk_allele_copies)
+            },
+            association.risk_allele
+        ),
+    ));
+    steps.extend(call_steps(
+        summary,
+        association.imputed,
+        &association.borderline_quality,
+        association.strand,
+    ));
+    if let Some(frequency) = association.risk_allele_frequency {
+        steps.push(step(
+            TraceStage::Frequency,
+            StepOutcome::Noted,
+            format!("gnomAD frequency of the risk allele {:.4}", frequency),
+        ));
+    }
+    steps.push(step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        association.effect.clone(),
+    ));
+    steps.push(confidence_step(&association.confidence));
+    new_trace(
+        format!("{} ({})", association.trait_name, association.rsid),
+        records,
+        steps,
+        association.confidence,
+    )
+}
+
+fn hla(summary: &AnalysisSummary, call: &HlaCall) -> FindingTrace {
+    let mut steps = preprocessing(summary);
+    steps.push(match call.evidence {
+        HlaEvidence::Typed => step(
+            TraceStage::SiteMatch,
+            StepOutcome::Passed,
+            format!("Typed record {} of the allele", call.marker),
+        ),
+        HlaEvidence::Proxy => step(
+            TraceStage::SiteMatch,
+            StepOutcome::Caution,
+            format!(
+                "Proxy SNP {} in linkage with the allele{}",
+                call.marker,
+                call.caveat
+                    .as_deref()
+                    .map_or_else(String::new, |caveat| format!(": {}", caveat))
+            ),
+        ),
+    });
+    steps.push(step(
+        TraceStage::AlleleMatch,
+        StepOutcome::Passed,
+        format!(
+            "Genotype {} gives {} {} of {}",
+            call.genotype,
+            call.copies,
+            if call.copies == 1 { "copy" } else { "copies" },
+            call.allele
+        ),
+    ));
+    if call.imputed {
+        steps.push(step(
+            TraceStage::CallQuality,
+            StepOutcome::Caution,
+            "Imputed rather than genotyped",
+        ));
+    }
+    steps.push(confidence_step(&call.confidence));
+    new_trace(
+        format!("{}: {}", call.allele, call.reaction),
+        vec![record(RecordSource::BuiltIn, call.marker.clone())],
+        steps,
+        call.confidence,
+    )
+}
+
+fn nutrition(summary: &AnalysisSummary, finding: &NutritionFinding) -> FindingTrace {
+    let mut steps = preprocessing(summary);
+    steps.push(step(
+        TraceStage::SiteMatch,
+        StepOutcome::Passed,
+        format!(
+            "Panel marker {} {} found by {}",
+            finding.gene, finding.name, finding.rsid
+        ),
+    ));
+    steps.push(step(
+        TraceStage::AlleleMatch,
+        StepOutcome::Passed,
+        format!(
+            "Genotype {} carries {} of the effect allele {}",
+            finding.genotype, finding.effect_copies, finding.effect_allele
+        ),
+    ));
+    if finding.imputed {
+        steps.push(step(
+            TraceStage::CallQuality,
+            StepOutcome::Caution,
+            "Imputed rather than genotyped",
+        ));
+    }
+    steps.push(step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        format!("{} evidence", capitalized(&words(&finding.evidence))),
+    ));
+    steps.push(confidence_step(&finding.confidence));
+    new_trace(
+        format!("{} {}: {}", finding.gene, finding.name, finding.effect),
+        vec![record(RecordSource::BuiltIn, finding.rsid.clone())],
+        steps,
+        finding.confidence,
+    )
+}
+
+fn structural(finding: &StructuralFinding) -> FindingTrace {
+    let mut records = vec![record(RecordSource::ClinGen, finding.gene.clone())];
+    records.extend(
+        finding
+            .disease
+            .iter()
+            .map(|disease| record(RecordSource::ClinGen, disease.clone())),
+    );
+    let mut steps = vec![step(
+        TraceStage::SiteMatch,
+        StepOutcome::Passed,
+        format!(
+            "{} at {}:{}-{} covers {:.0}% of {}",
+            capitalized(&words(&finding.kind)),
+            finding.chromosome,
+            finding.start,
+            finding.end,
+            finding.overlap * 100.0,
+            finding.gene
+        ),
+    )];
+    steps.push(if finding.filters.is_empty() {
+        step(
+            TraceStage::CallQuality,
+            StepOutcome::Passed,
+            "The call passed its FILTER",
+        )
+    } else {
+        step(
+            TraceStage::CallQuality,
+            StepOutcome::Caution,
+            format!("The call failed FILTER {}", finding.filters.join(", ")),
+        )
+    });
+    steps.push(step(
+        TraceStage::Evidence,
+        StepOutcome::Noted,
+        format!("ClinGen dosage score: {}", words(&finding.score)),
+    ));
+    steps.push(confidence_step(&finding.confidence));
+    new_trace(
+        format!("{} of {}", capitalized(&words(&finding.kind)), finding.gene),
+        records,
+        steps,
+        finding.confidence,
+    )
+}
+
+fn plugin(finding: &PluginFinding) -> FindingTrace {
+    let records = vec![record(RecordSource::Plugin, finding.plugin.clone())];
+    let site = match (&finding.rsid, &finding.genotype) {
+        (Some(rsid), Some(genotype)) => format!("{} called {}", rsid, genotype),
+        (Some(rsid), None) => format!("{}, which the genome does not call", rsid),
+        (None, _) => "no single site".to_string(),
+    };
+    let mut steps = vec![step(
+        TraceStage::SiteMatch,
+        StepOutcome::Noted,
+        format!("Reported by plugin {} from {}", finding.plugin, site),
+    )];
+    steps.push(step(
+        TraceStage::Interpretation,
+        StepOutcome::Noted,
+        format!(
+            "The plugin classed it as {}; GenomeForge does not check its reasoning",
+            words(&finding.significance)
+        ),
+    ));
+    if !finding.references.is_empty() {
+        steps.push(step(
+            TraceStage::Evidence,
+            StepOutcome::Noted,
+            format!("Cited: {}", finding.references.join(", ")),
+        ));
+    }
+    steps.push(confidence_step(&finding.confidence));
+    new_trace(finding.title.clone(), records, steps, finding.confidence)
+}
+
+fn custom(finding: &CustomFinding) -> FindingTrace {
+    let id = finding
+        .reference
+        .clone()
+        .unwrap_or_else(|| finding.rsid.clone());
+    let records = vec![record(RecordSource::Custom, id)];
+    let mut steps = vec![step(
+        TraceStage::SiteMatch,
+        StepOutcome::Noted,
+        format!(
+            "{} called {}, matched by rsid in the imported annotation table",
+            finding.rsid, finding.genotype
+        ),
+    )];
+    let curator = finding
+        .source
+        .as_ref()
+        .map_or_else(String::new, |source| format!(" by {}", source));
+    steps.push(step(
+        TraceStage::Interpretation,
+        StepOutcome::Noted,
+        format!(
+            "Classed as {}{}; GenomeForge does not check the table's curation",
+            words(&finding.significance),
+            curator
+        ),
+    ));
+    steps.push(confidence_step(&finding.confidence));
+    let title = match &finding.gene {
+        Some(gene) => format!("{} {}: {}", gene, finding.rsid, finding.interpretation),
+        None => format!("{}: {}", finding.rsid, finding.interpretation),
+    };
+    new_trace(title, records, steps, finding.confidence)
+}
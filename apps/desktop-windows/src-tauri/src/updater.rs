@@ -0,0 +1,331 @@
+//! Downloading database releases
+//!
+//! Updates only run when the user asks for them. The release manifest is
+//! fetched over HTTPS and must carry a valid signature from the release
+//! key built into the application; every file it lists is then checked
+//! against its SHA-256 digest before it replaces the installed release.
+//! Reference FASTA files listed in the manifest are downloaded the same
+//! way.
+//!
+//! Downloads resume with range requests: what arrived before a dropped
+//! connection is kept, failed attempts are retried with growing waits, and
+//! transfers are held under the `download_limit` setting. Database releases
+//! keep their partial files across restarts, so a download given up on
+//! picks up where it stopped the next time it is asked for.
+
+use crate::events::{self, DownloadProgress, ReferenceDownloadProgress};
+use crate::settings;
+use genomeforge_core::annotation::manager::{self, Release, ReleaseManifest};
+use genomeforge_core::download::{self, Backoff, Throttle};
+use genomeforge_core::reference::{ReferenceManager, ReferenceRelease};
+use genomeforge_core::tasks::{self, CancelFlag, TaskId};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tokio::io::AsyncWriteExt;
+
+/// Hex-encoded Ed25519 key that signs release manifests
+///
+/// Supplied at build time; builds without it can only import releases
+/// from local files.
+const RELEASE_KEY: Option<&str> = option_env!("GENOMEFORGE_RELEASE_KEY");
+
+/// Manifest used when the caller does not name one
+const MANIFEST_URL: Option<&str> = option_env!("GENOMEFORGE_RELEASE_MANIFEST_URL");
+
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// HTTPS client for release downloads
+pub fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .https_only(true)
+        .user_agent(concat!("GenomeForge/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+/// Hex-encoded key that release manifests and data packs must be signed
+/// with
+pub fn release_key() -> Result<&'static str, String> {
+    RELEASE_KEY.ok_or_else(|| {
+        "This build has no release signing key; import databases from local files instead"
+            .to_string()
+    })
+}
+
+/// Fetch the release manifest and check its signature
+///
+/// The signature is read from `<manifest url>.sig`.
+pub async fn fetch_manifest(
+    client: &reqwest::Client,
+    url: Option<&str>,
+) -> Result<ReleaseManifest, String> {
+    let key = release_key()?;
+    let url = url
+        .or(MANIFEST_URL)
+        .ok_or_else(|| "No release manifest configured".to_string())?;
+
+    let manifest = fetch(client, url).await?;
+    let signature = fetch(client, &format!("{}.sig", url)).await?;
+    let signature =
+        String::from_utf8(signature).map_err(|_| "Invalid manifest signature".to_string())?;
+
+    ReleaseManifest::parse_signed(&manifest, &signature, key)
+}
+
+/// Download a release into the staging directory
+///
+/// Returns the staged file for [`manager::install_release`]. A partial
+/// download is kept when it fails or is cancelled, for the next attempt to
+/// resume.
+pub async fn download_release(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    dir: &Path,
+    release: &Release,
+    task_id: TaskId,
+    cancel: &CancelFlag,
+) -> Result<PathBuf, String> {
+    let staged = manager::download_path(dir, release)?;
+    let progress = |bytes_downloaded, total_bytes, attempt| {
+        events::DOWNLOAD_PROGRESS.emit(
+            app,
+            DownloadProgress {
+                task_id,
+                database: release.database,
+                bytes_downloaded,
+                total_bytes,
+                attempt,
+            },
+        );
+    };
+    download_to(
+        client,
+        &staged,
+        &release.url,
+        release.size,
+        settings::current(app).download_limit,
+        cancel,
+        progress,
+    )
+    .await?;
+    Ok(staged)
+}
+
+/// Download a reference FASTA into the reference staging directory
+///
+/// Returns the staged file for [`ReferenceManager::import`], which checks
+/// its digest. A partial download is removed when it fails or is
+/// cancelled.
+pub async fn download_reference(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    references: &ReferenceManager,
+    release: &ReferenceRelease,
+    task_id: TaskId,
+    cancel: &CancelFlag,
+) -> Result<PathBuf, String> {
+    let staged = references.staging_path()?;
+    let progress = |bytes_downloaded, total_bytes, attempt| {
+        events::REFERENCE_DOWNLOAD_PROGRESS.emit(
+            app,
+            ReferenceDownloadProgress {
+                task_id,
+                build: release.build,
+                bytes_downloaded,
+                total_bytes,
+                attempt,
+            },
+        );
+    };
+    let result = download_to(
+        client,
+        &staged,
+        &release.url,
+        release.size,
+        settings::current(app).download_limit,
+        cancel,
+        progress,
+    )
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&staged).await;
+    }
+    result.map(|()| staged)
+}
+
+// Helper functions
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Vec<u8>, String> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))
+}
+
+/// Why a download attempt stopped short
+enum Failure {
+    /// Worth another attempt: the connection dropped, or the server is busy
+    Transient(String),
+    Fatal(String),
+}
+
+impl From<String> for Failure {
+    fn from(error: String) -> Self {
+        Failure::Fatal(error)
+    }
+}
+
+/// Download `url` to `path`, reporting the bytes downloaded so far, the
+/// expected total and the attempt to `progress`
+///
+/// Bytes already at `path` are kept and only the rest is requested. Failed
+/// attempts are retried up to [`download::MAX_ATTEMPTS`] times, and the
+/// transfer is held to `limit` bytes per second on average.
+async fn download_to(
+    client: &reqwest::Client,
+    path: &Path,
+    url: &str,
+    size: Option<u64>,
+    limit: Option<u64>,
+    cancel: &CancelFlag,
+    progress: impl Fn(u64, Option<u64>, u32),
+) -> Result<(), String> {
+    let backoff = Backoff::default();
+    let mut attempt = 1;
+    loop {
+        let result = download_attempt(client, path, url, size, limit, cancel, |bytes, total| {
+            progress(bytes, total, attempt)
+        })
+        .await;
+        match result {
+            Ok(()) => return Ok(()),
+            Err(Failure::Transient(error)) if attempt < download::MAX_ATTEMPTS => {
+                attempt += 1;
+                tracing::warn!(url, attempt, %error, "retrying download");
+                pause(backoff.delay(attempt), cancel).await?;
+            }
+            Err(Failure::Transient(error) | Failure::Fatal(error)) => return Err(error),
+        }
+    }
+}
+
+/// One attempt at [`download_to`], appending to what is at `path`
+async fn download_attempt(
+    client: &reqwest::Client,
+    path: &Path,
+    url: &str,
+    size: Option<u64>,
+    limit: Option<u64>,
+    cancel: &CancelFlag,
+    progress: impl Fn(u64, Option<u64>),
+) -> Result<(), Failure> {
+    tasks::checkpoint(cancel)?;
+    let failed =
+        |e: reqwest::Error| Failure::Transient(format!("Failed to download {}: {}", url, e));
+    let write_failed = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    let mut offset = tokio::fs::metadata(path).await.map_or(0, |meta| meta.len());
+    if size.is_some_and(|size| offset > size) {
+        tokio::fs::remove_file(path).await.map_err(write_failed)?;
+        offset = 0;
+    }
+    if offset > 0 && size == Some(offset) {
+        progress(offset, size);
+        return Ok(());
+    }
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(reqwest::header::RANGE, download::range_header(offset));
+    }
+    let mut response = request.send().await.map_err(failed)?;
+    let status = response.status().as_u16();
+    if status == 416 && offset > 0 {
+        // Nothing is left past the end of the file, unless it is not the
+        // file the server has; start over in that case
+        if size.is_none() {
+            progress(offset, Some(offset));
+            return Ok(());
+        }
+        tokio::fs::remove_file(path).await.map_err(write_failed)?;
+        return Err(Failure::Transient(format!("{} changed on the server", url)));
+    }
+    if download::is_retryable(status) {
+        return Err(Failure::Transient(format!(
+            "Failed to download {}: HTTP status {}",
+            url, status
+        )));
+    }
+    let content_range = response
+        .headers()
+        .get(reqwest::header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok());
+    let start = download::resume_offset(status, content_range, offset)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let total_bytes = size.or_else(|| response.content_length().map(|length| start + length));
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(start > 0)
+        .write(true)
+        .truncate(start == 0)
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut bytes_downloaded = start;
+    progress(bytes_downloaded, total_bytes);
+    let mut throttle = Throttle::new(limit);
+    let started = Instant::now();
+    let mut last_emit = Instant::now();
+
+    while let Some(chunk) = response.chunk().await.map_err(failed)? {
+        tasks::checkpoint(cancel)?;
+        file.write_all(&chunk).await.map_err(write_failed)?;
+        bytes_downloaded += chunk.len() as u64;
+
+        if size.is_some_and(|size| bytes_downloaded > size) {
+            drop(file);
+            let _ = tokio::fs::remove_file(path).await;
+            return Err(Failure::Fatal(format!("{} is larger than listed", url)));
+        }
+        if last_emit.elapsed() >= PROGRESS_INTERVAL {
+            last_emit = Instant::now();
+            progress(bytes_downloaded, total_bytes);
+        }
+        let wait = throttle.record(chunk.len() as u64, started.elapsed());
+        if !wait.is_zero() {
+            file.flush().await.map_err(write_failed)?;
+            pause(wait, cancel).await?;
+        }
+    }
+
+    file.flush().await.map_err(write_failed)?;
+    progress(bytes_downloaded, total_bytes);
+    if size.is_some_and(|size| bytes_downloaded < size) {
+        return Err(Failure::Transient(format!(
+            "Download of {} ended early",
+            url
+        )));
+    }
+    Ok(())
+}
+
+/// Sleep for `duration`, waking up to check for cancellation
+async fn pause(duration: Duration, cancel: &CancelFlag) -> Result<(), String> {
+    let until = Instant::now() + duration;
+    loop {
+        tasks::checkpoint(cancel)?;
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(());
+        }
+        tokio::time::sleep(left.min(PROGRESS_INTERVAL)).await;
+    }
+}
@@ -0,0 +1,65 @@
+//! INFO annotations of the variants behind an analysis
+//!
+//! Findings are attached to the variant they were called from by rsid,
+//! one value per finding, so the values of related tags such as
+//! `PGKB_DRUG` and `PGKB_LEVEL` line up.
+
+use crate::commands::AnalysisResultData;
+use genomeforge_core::report::vcf::Annotation;
+use std::collections::HashMap;
+
+/// Annotations of every variant with a finding, by rsid
+pub fn annotations(results: &AnalysisResultData) -> HashMap<String, Annotation> {
+    let mut annotations: HashMap<String, Annotation> = HashMap::new();
+    for finding in &results.clinical_findings {
+        let annotation = annotations.entry(finding.rsid.clone()).or_default();
+        if let Some(gene) = &finding.gene {
+            add_gene(annotation, gene);
+        }
+        annotation
+            .add("CLNSIG", finding.significance_label.as_str())
+            .add("CLNREVSTAT", finding.review_status.as_str())
+            .add("CLNDN", finding.conditions.join("|"));
+        if let Some(id) = finding.variation_id {
+            annotation.add("CLNVID", id.to_string());
+        }
+    }
+    for response in &results.drug_responses {
+        let annotation = annotations.entry(response.rsid.clone()).or_default();
+        add_gene(annotation, &response.gene);
+        annotation
+            .add("PGKB_DRUG", response.drug.as_str())
+            .add("PGKB_LEVEL", response.evidence_level.as_str())
+            .add("PGKB_ID", response.annotation_id.as_str());
+        if let Some(phenotype) = &response.phenotype {
+            add_unique(annotation, "PGX_PHENOTYPE", phenotype);
+        }
+    }
+    for association in &results.trait_associations {
+        let annotation = annotations.entry(association.rsid.clone()).or_default();
+        for gene in &association.genes {
+            add_gene(annotation, gene);
+        }
+        annotation
+            .add("GWAS_TRAIT", association.trait_name.as_str())
+            .add("GWAS_RISK_ALLELE", association.risk_allele.as_str())
+            .add("GWAS_PVALUE", format!("{:e}", association.p_value));
+    }
+    annotations
+}
+
+// Helper functions
+
+fn add_gene(annotation: &mut Annotation, gene: &str) {
+    add_unique(annotation, "GENE", gene);
+}
+
+fn add_unique(annotation: &mut Annotation, id: &'static str, value: &str) {
+    let present = annotation
+        .info
+        .iter()
+        .any(|(existing, values)| *existing == id && values.iter().any(|v| v == value));
+    if !present {
+        annotation.add(id, value);
+    }
+}
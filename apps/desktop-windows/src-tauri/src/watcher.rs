@@ -0,0 +1,124 @@
+//! Automatic import from a watched folder
+//!
+//! With a watch folder set, genome files saved into it — by a clinic's
+//! download script, say — go through the same checks as dropped files and
+//! are announced as an `import-ready` event with what parsing them
+//! involves, or an `import-rejected` event with why they cannot be parsed.
+//! Nothing is parsed until the frontend asks, so files arriving during an
+//! analysis wait their turn. The folder is polled, and a change of the
+//! setting takes effect at the next poll.
+
+use crate::events::{self, FileRejected};
+use crate::intake;
+use crate::settings;
+use genomeforge_core::parser::plink::Fileset;
+use genomeforge_core::watch::FolderWatch;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Runtime};
+
+/// Time between polls of the watch folder
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watch the configured folder for the life of the app
+pub fn start<R: Runtime>(app: &AppHandle<R>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut watch: Option<FolderWatch> = None;
+        loop {
+            poll(&app, &mut watch);
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Check a folder can be watched, for the `set_watch_folder` command
+pub fn check_folder(dir: &Path) -> Result<(), String> {
+    if !dir.is_absolute() {
+        return Err("The watch folder must be an absolute path".to_string());
+    }
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+    FolderWatch::new(dir).map(|_| ())
+}
+
+// Helper functions
+
+fn poll<R: Runtime>(app: &AppHandle<R>, watch: &mut Option<FolderWatch>) {
+    // Unreadable settings leave the folder as it was, rather than log a
+    // warning every poll
+    let Ok(settings) = settings::read(app) else {
+        return;
+    };
+    let Some(dir) = settings.watch_dir else {
+        *watch = None;
+        return;
+    };
+    if watch.as_ref().is_none_or(|watch| watch.dir() != dir) {
+        *watch = match FolderWatch::new(&dir) {
+            Ok(new) => {
+                tracing::info!(dir = %dir.display(), "watching folder for genome files");
+                Some(new)
+            }
+            Err(error) => {
+                if watch.is_some() {
+                    tracing::warn!(%error, "watch folder unavailable");
+                }
+                None
+            }
+        };
+        return;
+    }
+    let Some(current) = watch.as_mut() else {
+        return;
+    };
+    match current.poll() {
+        Ok(paths) => {
+            let mut announced: Vec<PathBuf> = Vec::new();
+            for path in paths
+                .into_iter()
+                .filter_map(|path| import_path(current, path))
+            {
+                if !announced.contains(&path) {
+                    announce(app, &path);
+                    announced.push(path);
+                }
+            }
+        }
+        Err(error) => {
+            tracing::warn!(%error, "watch folder unavailable");
+            *watch = None;
+        }
+    }
+}
+
+/// What a settled file is imported as: itself, or the `.bed` of a PLINK
+/// fileset once all three of its files are there and settled
+fn import_path(watch: &FolderWatch, path: PathBuf) -> Option<PathBuf> {
+    let plink = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "bed" | "bim" | "fam"));
+    if !plink {
+        return Some(path);
+    }
+    let fileset = Fileset::of(&path).ok()?;
+    let settled = [&fileset.bed, &fileset.bim, &fileset.fam]
+        .iter()
+        .all(|part| !watch.settling(part));
+    settled.then_some(fileset.bed)
+}
+
+fn announce<R: Runtime>(app: &AppHandle<R>, path: &Path) {
+    match intake::plan(path) {
+        Ok(plan) => events::IMPORT_READY.emit(app, plan),
+        Err(error) => events::IMPORT_REJECTED.emit(
+            app,
+            FileRejected {
+                path: path.display().to_string(),
+                error,
+            },
+        ),
+    }
+}
@@ -0,0 +1,41 @@
+//! The active profile's variant watchlist
+//!
+//! The list is a file in the profile's directory, sealed under the device
+//! key, and read when an analysis starts so its results report the calls
+//! at every entry.
+
+use crate::{profiles, sessions};
+use genomeforge_core::crypto::KeySource;
+use genomeforge_core::watchlist::{self, Watchlist};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Runtime};
+
+/// Held while the watchlist is changed, so two edits at once both land
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// The active profile's watchlist
+pub fn read<R: Runtime>(app: &AppHandle<R>) -> Result<Watchlist, String> {
+    let key = sessions::device_key(&sessions::session_dir(app)?)?;
+    Watchlist::open(&watchlist_path(app)?, KeySource::Device(&key))
+}
+
+/// Change the active profile's watchlist and save it
+pub fn update<R: Runtime, T>(
+    app: &AppHandle<R>,
+    change: impl FnOnce(&mut Watchlist) -> Result<T, String>,
+) -> Result<T, String> {
+    let _writing = WRITING.lock().map_err(|_| "Watchlist lock poisoned")?;
+    let key = sessions::device_key(&sessions::session_dir(app)?)?;
+    let path = watchlist_path(app)?;
+    let mut list = Watchlist::open(&path, KeySource::Device(&key))?;
+    let changed = change(&mut list)?;
+    list.save(&path, KeySource::Device(&key))?;
+    Ok(changed)
+}
+
+// Helper functions
+
+fn watchlist_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    profiles::active_dir(app).map(|dir| dir.join(watchlist::FILE_NAME))
+}
@@ -1,4 +1,4 @@
-import { Routes, Route } from 'react-router-dom';
+import { Routes, Route, useNavigate } from 'react-router-dom';
 import { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import Layout from './components/Layout';
@@ -7,18 +7,22 @@ import UploadPage from './pages/UploadPage';
 import AnalysisPage from './pages/AnalysisPage';
 import ReportsPage from './pages/ReportsPage';
 import SettingsPage from './pages/SettingsPage';
+import { listenTo } from './lib/events';
 
 interface SystemInfo {
   os: string;
   os_version: string;
   arch: string;
   memory_total: number;
+  memory_available: number;
+  disk_free: number | null;
   cpu_cores: number;
 }
 
 export default function App() {
   const [appVersion, setAppVersion] = useState<string>('');
   const [systemInfo, setSystemInfo] = useState<SystemInfo | null>(null);
+  const navigate = useNavigate();
 
   useEffect(() => {
     // Load app info on startup
@@ -26,6 +30,23 @@ export default function App() {
     invoke<SystemInfo>('get_system_info').then(setSystemInfo).catch(console.error);
   }, []);
 
+  useEffect(() => {
+    // Sent when the window is brought forward from an "analysis complete" notification
+    const unlisten = [
+      listenTo('show-results', () => navigate('/analysis')),
+      // Files dropped onto the window are taken up by the upload page
+      listenTo('file-ready', ({ payload }) => navigate('/upload', { state: { plan: payload } })),
+      listenTo('file-rejected', ({ payload }) => navigate('/upload', { state: { rejected: payload } })),
+    ];
+    // Files the app was opened with, once something is listening for them
+    Promise.all(unlisten)
+      .then(() => invoke('open_launch_files'))
+      .catch(console.error);
+    return () => {
+      unlisten.forEach((pending) => pending.then((stop) => stop()));
+    };
+  }, [navigate]);
+
   return (
     <Layout appVersion={appVersion} systemInfo={systemInfo}>
       <Routes>
@@ -15,6 +15,8 @@ interface SystemInfo {
   os_version: string;
   arch: string;
   memory_total: number;
+  memory_available: number;
+  disk_free: number | null;
   cpu_cores: number;
 }
 
@@ -89,6 +91,12 @@ export default function Layout({ children, appVersion, systemInfo }: LayoutProps
               {systemInfo.os_version || 'Windows'} ({systemInfo.arch})
             </div>
           )}
+          {systemInfo && systemInfo.memory_total > 0 && (
+            <div className="truncate mt-0.5">
+              {(systemInfo.memory_available / 1e9).toFixed(1)} of {(systemInfo.memory_total / 1e9).toFixed(1)} GB
+              memory free
+            </div>
+          )}
         </div>
       </aside>
 
@@ -0,0 +1,37 @@
+/** Codes of the errors commands fail with */
+export type ErrorCode =
+  | 'io_error'
+  | 'parse_error'
+  | 'unsupported_format'
+  | 'file_not_found'
+  | 'database_missing'
+  | 'no_genome'
+  | 'no_results'
+  | 'invalid_input'
+  | 'cancelled'
+  | 'failed';
+
+/** Error a command rejects with */
+export interface CommandError {
+  code: ErrorCode;
+  message: string;
+  /** Line of the file a parse error was found on */
+  line?: number;
+}
+
+export function isCommandError(err: unknown): err is CommandError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+/** Code of a rejected command, or undefined for other errors */
+export function errorCode(err: unknown): ErrorCode | undefined {
+  return isCommandError(err) ? err.code : undefined;
+}
+
+/** Message to show for an error from a command or anything else */
+export function errorMessage(err: unknown, fallback = 'Something went wrong'): string {
+  if (isCommandError(err)) return err.message;
+  if (err instanceof Error) return err.message;
+  if (typeof err === 'string') return err;
+  return fallback;
+}
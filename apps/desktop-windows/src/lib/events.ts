@@ -0,0 +1,182 @@
+// Generated from src-tauri/src/events.rs by its tests; do not edit.
+
+import { listen, type EventCallback, type UnlistenFn } from '@tauri-apps/api/event';
+import type { CommandError } from './errors';
+
+export type TaskKind = 'parse' | 'analysis' | 'database_update' | 'reference_install' | 'batch';
+
+export type FindingSection = 'clinical' | 'secondary_findings' | 'carrier' | 'drug_response' | 'diplotype' | 'trait' | 'hla_risk' | 'nutrition' | 'structural' | 'plugin' | 'custom';
+
+export type BatchStage = 'parsing' | 'analyzing' | 'exporting' | 'done' | 'failed';
+
+export type DatabaseKind = 'clinvar' | 'pharmgkb' | 'cpic' | 'gwas' | 'dbsnp' | 'gnomad' | 'liftover' | 'haplogroups' | 'clingen' | 'probemask' | 'custom';
+
+export type GenomeBuild = 'GRCh36' | 'GRCh37' | 'GRCh38';
+
+export type FileFormat = 'vcf' | '23andme' | 'ancestrydna' | 'myheritage' | 'ftdna' | 'bam' | 'plink' | 'unknown';
+
+export type Compression = 'none' | 'gzip' | 'bgzip';
+
+export interface ParseProgress {
+  task_id: number;
+  bytes_read: number;
+  /** Size of the file on disk, compressed or not */
+  total_bytes: number;
+  records_parsed: number;
+  /** Fraction of the file read (0.0 - 1.0) */
+  fraction: number;
+  elapsed_seconds: number;
+  /** Estimated seconds remaining, once enough of the file has been read */
+  eta_seconds: number | null;
+}
+
+export interface ParseWarning {
+  task_id: number;
+  message: string;
+}
+
+export interface TaskStarted {
+  task_id: number;
+  kind: TaskKind;
+}
+
+export interface AnalysisComplete {
+  task_id: number;
+  /** What `analyze_variants` returns */
+  overview: Record<string, unknown>;
+}
+
+export interface AnalysisFailed {
+  task_id: number;
+  error: CommandError;
+}
+
+export interface StreamedFinding {
+  section: FindingSection;
+  /** Where `get_findings_page` lists it */
+  index: number;
+  finding: unknown;
+}
+
+export interface FindingsBatch {
+  task_id: number;
+  /** Counting from 0, for `ack_findings` */
+  sequence: number;
+  findings: StreamedFinding[];
+}
+
+export interface FindingsComplete {
+  task_id: number;
+  batches: number;
+  findings: number;
+  /** Why the analysis stopped short, leaving its findings incomplete */
+  error: CommandError | null;
+}
+
+export interface BatchProgress {
+  task_id: number;
+  /** Position of the file in the batch */
+  index: number;
+  file_path: string;
+  stage: BatchStage;
+  /** Files of the batch done or failed so far */
+  completed: number;
+  total: number;
+  /** Why the file failed */
+  error: string | null;
+}
+
+export interface DownloadProgress {
+  task_id: number;
+  database: DatabaseKind;
+  bytes_downloaded: number;
+  total_bytes: number | null;
+  /** Attempt at the download, counting from 1 */
+  attempt: number;
+}
+
+export interface ReferenceDownloadProgress {
+  task_id: number;
+  build: GenomeBuild;
+  bytes_downloaded: number;
+  total_bytes: number | null;
+  /** Attempt at the download, counting from 1 */
+  attempt: number;
+}
+
+export interface ParsePlan {
+  path: string;
+  file_type: FileFormat;
+  compression: Compression;
+  /** How sure format detection is, from 0.0 to 1.0 */
+  confidence: number;
+  /** Bytes on disk, every file of a PLINK fileset together */
+  size: number;
+  /** Rough seconds the parse takes; unknown for aligned reads */
+  estimated_seconds: number | null;
+  /** Rough memory the parsed genome needs */
+  memory_needed: number | null;
+  /** Whether only the sites the databases annotate will be kept */
+  sites_only: boolean;
+  /** Samples of a multi-sample file, one of which has to be chosen */
+  samples: string[];
+  warning: string | null;
+}
+
+export interface FileRejected {
+  path: string;
+  error: CommandError;
+}
+
+export type ShowResults = Record<never, never>;
+
+/** Every payload goes out with the version of its event */
+export type Versioned<T> = T & { version: number };
+
+/** Payload of each event, as `listen` receives it */
+export interface EventPayloads {
+  'parse-progress': Versioned<ParseProgress>;
+  'parse-warning': Versioned<ParseWarning>;
+  'task-started': Versioned<TaskStarted>;
+  'analysis-complete': Versioned<AnalysisComplete>;
+  'analysis-failed': Versioned<AnalysisFailed>;
+  'analysis-findings': Versioned<FindingsBatch>;
+  'analysis-findings-complete': Versioned<FindingsComplete>;
+  'batch-progress': Versioned<BatchProgress>;
+  'database-download-progress': Versioned<DownloadProgress>;
+  'reference-download-progress': Versioned<ReferenceDownloadProgress>;
+  'file-ready': Versioned<ParsePlan>;
+  'file-rejected': Versioned<FileRejected>;
+  'import-ready': Versioned<ParsePlan>;
+  'import-rejected': Versioned<FileRejected>;
+  'show-results': Versioned<ShowResults>;
+}
+
+export type EventName = keyof EventPayloads;
+
+/** Version of the payload each event carries */
+export const EVENT_VERSIONS: Record<EventName, number> = {
+  'parse-progress': 1,
+  'parse-warning': 1,
+  'task-started': 1,
+  'analysis-complete': 1,
+  'analysis-failed': 1,
+  'analysis-findings': 1,
+  'analysis-findings-complete': 1,
+  'batch-progress': 1,
+  'database-download-progress': 1,
+  'reference-download-progress': 1,
+  'file-ready': 1,
+  'file-rejected': 1,
+  'import-ready': 1,
+  'import-rejected': 1,
+  'show-results': 1,
+};
+
+/** Listen for an event, with its payload typed */
+export function listenTo<E extends EventName>(
+  event: E,
+  handler: EventCallback<EventPayloads[E]>,
+): Promise<UnlistenFn> {
+  return listen(event, handler);
+}
@@ -1,19 +1,198 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Database, Key, Trash2, Folder, Info, Shield, Download, Upload, ExternalLink } from 'lucide-react';
+import { save } from '@tauri-apps/plugin-dialog';
+import { Database, Key, Trash2, Folder, Info, Shield, Download, Upload, ExternalLink, User, Plus, LifeBuoy, Gauge } from 'lucide-react';
 import { useAppStore } from '@/store/app';
+import { errorMessage } from '@/lib/errors';
+
+interface ProfileEntry {
+  id: string;
+  name: string;
+  created_at: number;
+  active: boolean;
+  genome_loaded: boolean;
+}
+
+interface DiagnosticBundle {
+  path: string;
+  files: string[];
+  log_entries: number;
+  size: number;
+}
+
+interface DataPurge {
+  files: { path: string; bytes: number; overwritten: boolean }[];
+  failed: { path: string; reason: string }[];
+  bytes: number;
+  genomes: number;
+}
+
+interface Throughput {
+  mb_per_second: number;
+  variants_per_second: number;
+}
+
+interface BenchmarkReport {
+  vcf: Throughput;
+  twenty_three_and_me: Throughput;
+  annotation: Throughput;
+}
+
+type FindingCategory =
+  | 'neurodegenerative'
+  | 'carrier_status'
+  | 'secondary_findings'
+  | 'pharmacogenomics'
+  | 'traits'
+  | 'nutrigenomics'
+  | 'paternity';
+
+interface Settings {
+  consent: { secondary_findings: boolean; late_onset: boolean; excluded: FindingCategory[] };
+  [key: string]: unknown;
+}
+
+const FINDING_CATEGORIES: { id: FindingCategory; label: string }[] = [
+  { id: 'neurodegenerative', label: "Neurodegenerative disease risk (Alzheimer's, Parkinson's, Huntington's)" },
+  { id: 'carrier_status', label: 'Carrier status for recessive conditions' },
+  { id: 'secondary_findings', label: 'ACMG secondary findings' },
+  { id: 'pharmacogenomics', label: 'Drug responses' },
+  { id: 'traits', label: 'Trait associations' },
+  { id: 'nutrigenomics', label: 'Nutrigenomics (nutrition and metabolism markers)' },
+  { id: 'paternity', label: 'Paternity-relevant markers (haplogroups, relative comparisons)' },
+];
+
+interface ProfileSwitch {
+  profile: ProfileEntry;
+  parse: { file_type: string; variant_count: number } | null;
+}
 
 export default function SettingsPage() {
-  const { hasGenomeData, databaseStatus, clearAllData } = useAppStore();
+  const { hasGenomeData, databaseStatus, clearAllData, clearGenomeData, setGenomeData } = useAppStore();
   const [appVersion, setAppVersion] = useState('');
   const [showClearConfirm, setShowClearConfirm] = useState(false);
+  const [profiles, setProfiles] = useState<ProfileEntry[]>([]);
+  const [newProfile, setNewProfile] = useState('');
+  const [profileError, setProfileError] = useState<string | null>(null);
+  const [diagnostics, setDiagnostics] = useState<string | null>(null);
+  const [purgeResult, setPurgeResult] = useState<string | null>(null);
+  const [benchmark, setBenchmark] = useState<string | null>(null);
+  const [settings, setSettings] = useState<Settings | null>(null);
+  const [settingsError, setSettingsError] = useState<string | null>(null);
+
+  const refreshProfiles = () =>
+    invoke<ProfileEntry[]>('list_profiles')
+      .then(setProfiles)
+      .catch((err) => setProfileError(errorMessage(err)));
 
   useEffect(() => {
     invoke<string>('get_app_version').then(setAppVersion);
+    refreshProfiles();
+    invoke<Settings>('get_settings')
+      .then(setSettings)
+      .catch((err) => setSettingsError(errorMessage(err)));
   }, []);
 
-  const handleClearData = () => {
-    clearAllData();
+  const handleToggleCategory = async (category: FindingCategory) => {
+    if (!settings) return;
+    const excluded = settings.consent.excluded.includes(category)
+      ? settings.consent.excluded.filter((c) => c !== category)
+      : [...settings.consent.excluded, category];
+    try {
+      const saved = await invoke<Settings>('update_settings', {
+        settings: { ...settings, consent: { ...settings.consent, excluded } },
+      });
+      setSettings(saved);
+      setSettingsError(null);
+    } catch (err) {
+      setSettingsError(errorMessage(err));
+    }
+  };
+
+  const handleCreateProfile = async () => {
+    if (!newProfile.trim()) return;
+    try {
+      await invoke('create_profile', { name: newProfile });
+      setNewProfile('');
+      setProfileError(null);
+      await refreshProfiles();
+    } catch (err) {
+      setProfileError(errorMessage(err));
+    }
+  };
+
+  const handleSwitchProfile = async (id: string) => {
+    try {
+      const result = await invoke<ProfileSwitch>('switch_profile', { id });
+      clearGenomeData();
+      if (result.parse) {
+        setGenomeData({
+          source: result.parse.file_type,
+          filename: result.profile.name,
+          variantCount: result.parse.variant_count,
+          uploadedAt: new Date().toISOString(),
+        });
+      }
+      setProfileError(null);
+      await refreshProfiles();
+    } catch (err) {
+      setProfileError(errorMessage(err));
+    }
+  };
+
+  const handleDeleteProfile = async (id: string) => {
+    try {
+      await invoke('delete_profile', { id });
+      setProfileError(null);
+      await refreshProfiles();
+    } catch (err) {
+      setProfileError(errorMessage(err));
+    }
+  };
+
+  const handleBenchmark = async () => {
+    setBenchmark('Measuring…');
+    try {
+      const report = await invoke<BenchmarkReport>('run_self_benchmark');
+      // A whole-genome VCF is around 1 GB; estimate from the measured rate
+      const wholeGenome = Math.round(1000 / report.vcf.mb_per_second);
+      setBenchmark(
+        `VCF ${report.vcf.mb_per_second.toFixed(1)} MB/s, 23andMe ${report.twenty_three_and_me.mb_per_second.toFixed(1)} MB/s, ` +
+          `annotation ${Math.round(report.annotation.variants_per_second).toLocaleString()} variants/s ` +
+          `(about ${wholeGenome} s for a 1 GB VCF)`
+      );
+    } catch (err) {
+      setBenchmark(errorMessage(err));
+    }
+  };
+
+  const handleDiagnosticBundle = async () => {
+    const outputPath = await save({
+      defaultPath: 'genomeforge-diagnostics.zip',
+      filters: [{ name: 'Zip archive', extensions: ['zip'] }],
+    });
+    if (!outputPath) return;
+    try {
+      const bundle = await invoke<DiagnosticBundle>('create_diagnostic_bundle', { outputPath });
+      setDiagnostics(`Saved ${bundle.log_entries} log entries to ${bundle.path}`);
+    } catch (err) {
+      setDiagnostics(errorMessage(err));
+    }
+  };
+
+  const handleClearData = async () => {
+    try {
+      const purge = await invoke<DataPurge>('purge_all_data');
+      clearAllData();
+      setPurgeResult(
+        purge.failed.length === 0
+          ? `Securely deleted ${purge.files.length} files (${purge.bytes} bytes)`
+          : `Deleted ${purge.files.length} files; ${purge.failed.length} could not be removed`
+      );
+      await refreshProfiles();
+    } catch (err) {
+      setPurgeResult(errorMessage(err));
+    }
     setShowClearConfirm(false);
   };
 
@@ -47,6 +226,69 @@ export default function SettingsPage() {
         </div>
       </div>
 
+      {/* Profiles */}
+      <section className="mb-6">
+        <h2 className="text-lg font-semibold text-gray-800 dark:text-white mb-3">Profiles</h2>
+        <div className="card-win divide-y divide-gray-100 dark:divide-gray-800">
+          {profiles.map((profile) => (
+            <div key={profile.id} className="p-4 flex items-center gap-3">
+              <div className="w-9 h-9 bg-primary-100 dark:bg-primary-900/30 rounded flex items-center justify-center">
+                <User className="text-primary-600" size={18} />
+              </div>
+              <div className="flex-1">
+                <div className="font-medium text-gray-800 dark:text-white">{profile.name}</div>
+                <div className="text-sm text-gray-500">
+                  {profile.active ? 'Active' : 'Inactive'}
+                  {profile.genome_loaded ? ' · Genome loaded' : ''}
+                </div>
+              </div>
+              {!profile.active && (
+                <>
+                  <button className="btn-win" onClick={() => handleSwitchProfile(profile.id)}>Switch</button>
+                  {profile.id !== 'default' && (
+                    <button className="btn-win" onClick={() => handleDeleteProfile(profile.id)} aria-label={`Delete ${profile.name}`}>
+                      <Trash2 className="text-red-600" size={16} />
+                    </button>
+                  )}
+                </>
+              )}
+            </div>
+          ))}
+          <div className="p-4 flex items-center gap-3">
+            <input
+              className="flex-1 px-3 py-1.5 border border-gray-300 dark:border-gray-700 rounded bg-white dark:bg-gray-900 text-gray-800 dark:text-white"
+              placeholder="New profile name"
+              value={newProfile}
+              onChange={(e) => setNewProfile(e.target.value)}
+            />
+            <button className="btn-win flex items-center gap-1" onClick={handleCreateProfile}>
+              <Plus size={16} /> Add
+            </button>
+          </div>
+        </div>
+        {profileError && <p className="text-sm text-red-600 mt-2">{profileError}</p>}
+      </section>
+
+      {/* Finding Categories */}
+      <section className="mb-6">
+        <h2 className="text-lg font-semibold text-gray-800 dark:text-white mb-3">Findings</h2>
+        <div className="card-win divide-y divide-gray-100 dark:divide-gray-800">
+          {FINDING_CATEGORIES.map((category) => (
+            <label key={category.id} className="p-4 flex items-center gap-3 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={!settings?.consent.excluded.includes(category.id)}
+                disabled={!settings}
+                onChange={() => handleToggleCategory(category.id)}
+              />
+              <span className="flex-1 text-gray-800 dark:text-white">{category.label}</span>
+            </label>
+          ))}
+        </div>
+        <p className="text-sm text-gray-500 mt-2">Unchecked categories are never analyzed or saved.</p>
+        {settingsError && <p className="text-sm text-red-600 mt-2">{settingsError}</p>}
+      </section>
+
       {/* Security Section */}
       <section className="mb-6">
         <h2 className="text-lg font-semibold text-gray-800 dark:text-white mb-3">Security</h2>
@@ -104,7 +346,7 @@ export default function SettingsPage() {
             </div>
             <div className="flex-1 text-left">
               <div className="font-medium text-red-600">Clear All Data</div>
-              <div className="text-sm text-gray-500">Remove all genetic data and settings</div>
+              <div className="text-sm text-gray-500">{purgeResult ?? 'Remove all genetic data and settings'}</div>
             </div>
           </button>
         </div>
@@ -132,6 +374,24 @@ export default function SettingsPage() {
             </div>
             <ExternalLink className="text-gray-400" size={18} />
           </button>
+          <button className="w-full p-4 flex items-center gap-3 hover:bg-gray-50 dark:hover:bg-gray-800/50 transition-colors" onClick={handleDiagnosticBundle}>
+            <div className="w-9 h-9 bg-blue-100 dark:bg-blue-900/30 rounded flex items-center justify-center">
+              <LifeBuoy className="text-blue-600" size={18} />
+            </div>
+            <div className="flex-1 text-left">
+              <div className="font-medium text-gray-800 dark:text-white">Diagnostic Bundle</div>
+              <div className="text-sm text-gray-500">{diagnostics ?? 'Save logs for a bug report, without any genetic data'}</div>
+            </div>
+          </button>
+          <button className="w-full p-4 flex items-center gap-3 hover:bg-gray-50 dark:hover:bg-gray-800/50 transition-colors" onClick={handleBenchmark}>
+            <div className="w-9 h-9 bg-purple-100 dark:bg-purple-900/30 rounded flex items-center justify-center">
+              <Gauge className="text-purple-600" size={18} />
+            </div>
+            <div className="flex-1 text-left">
+              <div className="font-medium text-gray-800 dark:text-white">Performance Check</div>
+              <div className="text-sm text-gray-500">{benchmark ?? 'Measure how fast this computer parses and annotates genome files'}</div>
+            </div>
+          </button>
         </div>
       </section>
 
@@ -1,8 +1,11 @@
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
+import { useLocation } from 'react-router-dom';
 import { Upload, FileText, Check, AlertCircle, RefreshCw } from 'lucide-react';
 import { open } from '@tauri-apps/plugin-dialog';
 import { invoke } from '@tauri-apps/api/core';
 import { useAppStore } from '@/store/app';
+import { errorCode, errorMessage } from '@/lib/errors';
+import { listenTo, type FileRejected, type ParsePlan } from '@/lib/events';
 
 interface ParseResult {
   success: boolean;
@@ -19,34 +22,73 @@ export default function UploadPage() {
   const [progress, setProgress] = useState(0);
   const [message, setMessage] = useState('');
   const [error, setError] = useState<string | null>(null);
+  const [warning, setWarning] = useState<string | null>(null);
+  const [taskId, setTaskId] = useState<number | null>(null);
+  // A file holding several people's genotypes waits for one to be chosen
+  const [choice, setChoice] = useState<{ filePath: string; samples: string[]; sample: string } | null>(null);
+  const location = useLocation();
+
+  useEffect(() => {
+    const dropped = location.state as { plan?: ParsePlan; rejected?: FileRejected } | null;
+    if (dropped?.plan) {
+      const { path, samples } = dropped.plan;
+      if (samples.length > 1) {
+        setChoice({ filePath: path, samples, sample: samples[0] });
+      } else {
+        processFile(path);
+      }
+    } else if (dropped?.rejected) {
+      setError(errorMessage(dropped.rejected.error, 'This file cannot be read'));
+      setStage('error');
+    }
+  }, [location.state]);
 
   const handleSelectFile = async () => {
     try {
       const selected = await open({
         multiple: false,
-        filters: [{ name: 'Genetic Data', extensions: ['txt', 'vcf', 'gz'] }],
+        filters: [{ name: 'Genetic Data', extensions: ['txt', 'vcf', 'gz', 'bam', 'bed', 'bim', 'fam'] }],
       });
 
       if (selected) {
-        await processFile(selected as string);
+        const filePath = selected as string;
+        const samples = await invoke<string[]>('list_samples', { filePath });
+        if (samples.length > 1) {
+          setChoice({ filePath, samples, sample: samples[0] });
+        } else {
+          await processFile(filePath);
+        }
       }
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Failed to select file');
+      setError(errorMessage(err, 'Failed to select file'));
       setStage('error');
     }
   };
 
-  const processFile = async (filePath: string) => {
+  const processFile = async (filePath: string, sample?: string) => {
+    setChoice(null);
     setStage('parsing');
     setProgress(10);
     setMessage('Reading file...');
     setError(null);
+    setWarning(null);
+
+    const unlistenTasks = await listenTo('task-started', ({ payload }) => setTaskId(payload.task_id));
+    // Parsing takes the progress bar from 10% to 50%
+    const unlistenProgress = await listenTo('parse-progress', ({ payload }) => {
+      setProgress(Math.round(10 + payload.fraction * 40));
+      const eta = payload.eta_seconds !== null ? ` (about ${Math.ceil(payload.eta_seconds)}s left)` : '';
+      setMessage(`Parsed ${payload.records_parsed.toLocaleString()} variants${eta}`);
+    });
+    const unlistenWarning = await listenTo('parse-warning', ({ payload }) => setWarning(payload.message));
 
     try {
-      setProgress(30);
       setMessage('Parsing genetic data...');
 
-      const parseResult = await invoke<ParseResult>('parse_genome_file', { filePath });
+      const parseResult = await invoke<ParseResult>('parse_genome_file', { filePath, sample }).finally(() => {
+        unlistenProgress();
+        unlistenWarning();
+      });
 
       if (!parseResult.success) {
         throw new Error(parseResult.error || 'Failed to parse file');
@@ -63,7 +105,7 @@ export default function UploadPage() {
           drug_count: number;
           trait_count: number;
         };
-      }>('analyze_variants', { variantCount: parseResult.variant_count });
+      }>('analyze_variants');
 
       setProgress(100);
       setMessage('Complete!');
@@ -85,8 +127,22 @@ export default function UploadPage() {
         traitAssociations: analysisResult.summary.trait_count,
       });
     } catch (err) {
-      setError(err instanceof Error ? err.message : 'Processing failed');
+      // A cancelled parse goes back to the start rather than failing
+      if (errorCode(err) === 'cancelled') {
+        handleReset();
+        return;
+      }
+      setError(errorMessage(err, 'Processing failed'));
       setStage('error');
+    } finally {
+      unlistenTasks();
+      setTaskId(null);
+    }
+  };
+
+  const handleCancel = async () => {
+    if (taskId !== null) {
+      await invoke<boolean>('cancel_task', { taskId });
     }
   };
 
@@ -95,6 +151,7 @@ export default function UploadPage() {
     setProgress(0);
     setMessage('');
     setError(null);
+    setWarning(null);
   };
 
   return (
@@ -127,6 +184,38 @@ export default function UploadPage() {
         </div>
       )}
 
+      {warning && stage !== 'idle' && (
+        <div className="bg-yellow-50 dark:bg-yellow-900/20 rounded p-4 mb-6 flex items-center gap-3 border border-yellow-200 dark:border-yellow-800">
+          <AlertCircle className="text-yellow-600" size={24} />
+          <div className="text-sm text-yellow-800 dark:text-yellow-400">{warning}</div>
+        </div>
+      )}
+
+      {choice && stage === 'idle' && (
+        <div className="card-win p-4 mb-6 flex items-center gap-3">
+          <div className="flex-1">
+            <div className="font-medium text-gray-800 dark:text-white">Choose a sample</div>
+            <div className="text-sm text-gray-500">
+              This file holds {choice.samples.length.toLocaleString()} samples; only one is analyzed.
+            </div>
+          </div>
+          <select
+            value={choice.sample}
+            onChange={(e) => setChoice({ ...choice, sample: e.target.value })}
+            className="px-3 py-1.5 border border-gray-300 dark:border-gray-700 rounded bg-white dark:bg-gray-900 text-gray-800 dark:text-white"
+          >
+            {choice.samples.map((sample) => (
+              <option key={sample} value={sample}>
+                {sample}
+              </option>
+            ))}
+          </select>
+          <button onClick={() => processFile(choice.filePath, choice.sample)} className="btn-win btn-win-accent">
+            Load
+          </button>
+        </div>
+      )}
+
       {/* Upload Area */}
       <div
         className={`card-win p-8 mb-6 text-center cursor-pointer transition-all ${
@@ -167,6 +256,11 @@ export default function UploadPage() {
               </div>
               <div className="text-sm text-gray-500 mt-2">{progress}%</div>
             </div>
+            {(stage === 'parsing' || stage === 'analyzing') && taskId !== null && (
+              <button onClick={handleCancel} className="btn-win mt-4 pointer-events-auto">
+                Cancel
+              </button>
+            )}
           </div>
         )}
       </div>
@@ -180,6 +274,8 @@ export default function UploadPage() {
               { name: '23andMe', extensions: '.txt, .txt.gz', description: 'Raw data export from 23andMe' },
               { name: 'AncestryDNA', extensions: '.txt, .txt.gz', description: 'Raw data export from AncestryDNA' },
               { name: 'VCF Format', extensions: '.vcf, .vcf.gz', description: 'Variant Call Format files' },
+              { name: 'BAM', extensions: '.bam', description: 'Aligned reads, genotyped at key clinical sites' },
+              { name: 'PLINK', extensions: '.bed + .bim + .fam', description: 'Binary filesets; pick any of the three files' },
             ].map((format) => (
               <div key={format.name} className="card-win p-4 flex items-start gap-3">
                 <FileText className="text-primary-600 mt-0.5" size={20} />
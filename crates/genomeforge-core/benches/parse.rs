@@ -0,0 +1,44 @@
+//! Parse and annotation throughput on generated genomes
+//!
+//! Run with `cargo bench`; the number of variants can be given as the
+//! first argument, e.g. `cargo bench --bench parse -- 2000000`. Each stage
+//! is run several times and the best run reported, which is the least
+//! disturbed by whatever else the machine is doing.
+
+use genomeforge_core::benchmark::{self, BenchmarkReport, Throughput};
+use tempfile::TempDir;
+
+/// Runs of each measurement
+const RUNS: usize = 5;
+
+/// Variants when none are given; around the size of a 30x exome VCF
+const VARIANTS: usize = 1_000_000;
+
+fn main() {
+    let variants = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(VARIANTS);
+    let dir = TempDir::new().expect("temporary directory");
+    let runs: Vec<BenchmarkReport> = (0..RUNS)
+        .map(|_| benchmark::run(dir.path(), variants).expect("benchmark run"))
+        .collect();
+
+    println!("{} variants, best of {} runs", variants, RUNS);
+    print_best("parse VCF", runs.iter().map(|run| run.vcf));
+    print_best(
+        "parse 23andMe",
+        runs.iter().map(|run| run.twenty_three_and_me),
+    );
+    print_best("annotate ClinVar", runs.iter().map(|run| run.annotation));
+}
+
+fn print_best<I: Iterator<Item = Throughput>>(stage: &str, runs: I) {
+    let best = runs
+        .min_by(|a, b| a.seconds.total_cmp(&b.seconds))
+        .expect("at least one run");
+    println!(
+        "{:<18}{:>10.1} MB/s{:>14.0} variants/s{:>9.3} s",
+        stage, best.mb_per_second, best.variants_per_second, best.seconds
+    );
+}
@@ -0,0 +1,432 @@
+//! Ancestry composition from a reference allele-frequency panel
+//!
+//! The panel gives, for each SNP, the frequency of one allele in a set of
+//! reference populations. Ancestry proportions are estimated by supervised
+//! projection as in ADMIXTURE with fixed frequencies: the genome's
+//! genotypes are treated as draws from a mixture of the populations and an
+//! EM algorithm finds the mixture weights of highest likelihood. Confidence
+//! intervals come from resampling the panel sites with replacement.
+//!
+//! Panels are tab-separated with a header row. Every column besides the
+//! site columns is a population, named "Continent/Region" or just
+//! "Continent", and holds the frequency of `allele` in it. A leading
+//! `##genome_build=GRCh38` line enables matching by position:
+//!
+//! ```text
+//! ##genome_build=GRCh38
+//! rsid       chromosome  position  allele  other_allele  European/Northwestern  European/Southern  African/West
+//! rs1426654  15          48134287  A       G             0.99                   0.97               0.02
+//! ```
+//!
+//! A/T and C/G SNPs are left out, since a strand mix-up would go unnoticed
+//! and bias the estimate.
+
+use crate::annotation::normalize_rsid;
+use crate::annotation::tsv::TsvReader;
+use crate::genome::{reverse_complement, GenomeBuild, Variant};
+use crate::parser::{compression, detect_genome_build, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+use std::io::BufRead;
+use std::path::Path;
+
+const SITE_COLUMNS: [&str; 5] = ["rsid", "chromosome", "position", "allele", "other_allele"];
+
+/// Fewest genotyped sites an estimate is made from
+pub const MIN_SITES: usize = 100;
+
+/// Bootstrap replicates used when the caller does not choose
+pub const DEFAULT_REPLICATES: usize = 100;
+
+/// Coverage of the confidence intervals
+const CONFIDENCE: f64 = 0.95;
+
+/// Frequencies are kept this far from 0 and 1 so one unexpected allele
+/// cannot rule a population out
+const FREQUENCY_FLOOR: f64 = 0.001;
+
+const MAX_ITERATIONS: usize = 1000;
+const TOLERANCE: f64 = 1e-7;
+
+/// Seed of the bootstrap, fixed so that estimates are reproducible
+const BOOTSTRAP_SEED: u64 = 0x6765_6e6f_6d65_6667;
+
+/// A reference population of the panel
+#[derive(Debug, Clone, Serialize)]
+pub struct Population {
+    pub name: String,
+    pub continent: String,
+}
+
+/// One SNP of the panel
+#[derive(Debug, Clone, Serialize)]
+pub struct PanelSite {
+    pub rsid: Option<String>,
+    pub chromosome: Option<String>,
+    pub position: Option<u64>,
+    pub allele: String,
+    pub other_allele: String,
+    /// Frequency of `allele` in each population, in panel column order
+    pub frequencies: Vec<f64>,
+}
+
+/// Allele frequencies of reference populations
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferencePanel {
+    pub genome_build: Option<GenomeBuild>,
+    pub populations: Vec<Population>,
+    pub sites: Vec<PanelSite>,
+}
+
+/// Estimated share of one population or continent
+#[derive(Debug, Clone, Serialize)]
+pub struct AncestryProportion {
+    pub name: String,
+    /// Continent a regional population belongs to
+    pub continent: Option<String>,
+    /// 0.0 - 1.0
+    pub proportion: f64,
+    /// Bounds of the 95% bootstrap interval
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Ancestry composition of a genome
+#[derive(Debug, Clone, Serialize)]
+pub struct AncestryEstimate {
+    pub continental: Vec<AncestryProportion>,
+    pub regional: Vec<AncestryProportion>,
+    /// Panel sites genotyped and used
+    pub sites_used: usize,
+    pub sites_total: usize,
+    /// Sites left out for being A/T or C/G SNPs
+    pub palindromic_skipped: usize,
+    /// Sites whose call matched neither panel allele on either strand
+    pub allele_mismatches: usize,
+    pub bootstrap_replicates: usize,
+}
+
+impl ReferencePanel {
+    /// Load a panel, optionally gzip-compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Read a panel from any buffered reader
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        let mut genome_build = None;
+        let mut line = String::new();
+        loop {
+            let buffer = reader
+                .fill_buf()
+                .map_err(|e| format!("Failed to read reference panel: {}", e))?;
+            if !buffer.starts_with(b"##") {
+                break;
+            }
+            line.clear();
+            reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read reference panel: {}", e))?;
+            if let Some(("genome_build", build)) = line
+                .trim_start_matches('#')
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+            {
+                genome_build = detect_genome_build(&[build.to_string()]);
+            }
+        }
+
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&["allele", "other_allele"])
+            .map_err(|e| format!("Not a reference panel: {}", e))?;
+        let mut columns = reader.column_names();
+        columns.retain(|name| !name.is_empty() && !SITE_COLUMNS.contains(&name.as_str()));
+        if columns.is_empty() {
+            return Err("Reference panel has no population columns".to_string());
+        }
+        let populations: Vec<Population> = columns
+            .iter()
+            .map(|column| match column.split_once('/') {
+                Some((continent, region)) => Population {
+                    name: region.trim().to_string(),
+                    continent: continent.trim().to_string(),
+                },
+                None => Population {
+                    name: column.clone(),
+                    continent: column.clone(),
+                },
+            })
+            .collect();
+
+        let mut sites = Vec::new();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("Reference panel {}", e))?;
+            let frequencies = columns
+                .iter()
+                .map(|column| {
+                    row.require(column)?
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|f| (0.0..=1.0).contains(f))
+                        .ok_or_else(|| {
+                            format!("line {}: invalid frequency for {}", row.line_number, column)
+                        })
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            let rsid = row.get("rsid").and_then(normalize_rsid);
+            let chromosome = row.get("chromosome").map(normalize_chromosome);
+            let position = row.get("position").and_then(|p| p.parse().ok());
+            if rsid.is_none() && (chromosome.is_none() || position.is_none()) {
+                continue;
+            }
+            sites.push(PanelSite {
+                rsid,
+                chromosome,
+                position,
+                allele: row.require("allele")?.to_ascii_uppercase(),
+                other_allele: row.require("other_allele")?.to_ascii_uppercase(),
+                frequencies,
+            });
+        }
+
+        Ok(ReferencePanel {
+            genome_build,
+            populations,
+            sites,
+        })
+    }
+
+    /// Number of sites in the panel
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    /// Whether the panel has no sites
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    /// Estimate a genome's ancestry proportions
+    ///
+    /// Fails when fewer than [`MIN_SITES`] panel sites are genotyped.
+    /// `checkpoint` is called every [`CHECKPOINT_INTERVAL`] sites while
+    /// matching and once per bootstrap replicate.
+    pub fn estimate<F>(
+        &self,
+        genome: &LoadedGenome,
+        bootstrap_replicates: usize,
+        mut checkpoint: F,
+    ) -> Result<AncestryEstimate, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let same_build = match (genome.file.genome_build, self.genome_build) {
+            (Some(genome_build), Some(panel_build)) => genome_build == panel_build,
+            _ => false,
+        };
+        let mut observed: Vec<(f64, Vec<f64>)> = Vec::new();
+        let mut palindromic_skipped = 0;
+        let mut allele_mismatches = 0;
+        for (index, site) in self.sites.iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            if reverse_complement(&site.allele) == site.other_allele {
+                palindromic_skipped += 1;
+                continue;
+            }
+            let Some(variant) = find_variant(genome, site, same_build) else {
+                continue;
+            };
+            match allele_dosage(variant, site) {
+                Some(dosage) => observed.push((
+                    dosage as f64,
+                    site.frequencies
+                        .iter()
+                        .map(|f| f.clamp(FREQUENCY_FLOOR, 1.0 - FREQUENCY_FLOOR))
+                        .collect(),
+                )),
+                None if variant.genotype.is_no_call() => {}
+                None => allele_mismatches += 1,
+            }
+        }
+        if observed.len() < MIN_SITES {
+            return Err(format!(
+                "Only {} reference panel sites are genotyped; at least {} are needed",
+                observed.len(),
+                MIN_SITES
+            ));
+        }
+
+        let populations = self.populations.len();
+        let uniform = vec![1.0 / populations as f64; populations];
+        let estimate = fit(&observed, &vec![1; observed.len()], uniform);
+
+        let mut random = SplitMix64(BOOTSTRAP_SEED);
+        let mut replicates = Vec::with_capacity(bootstrap_replicates);
+        for replicate in 0..bootstrap_replicates {
+            checkpoint(replicate)?;
+            let mut weights = vec![0u32; observed.len()];
+            for _ in 0..observed.len() {
+                weights[random.below(observed.len())] += 1;
+            }
+            replicates.push(fit(&observed, &weights, estimate.clone()));
+        }
+
+        let regional = self
+            .populations
+            .iter()
+            .enumerate()
+            .map(|(k, population)| {
+                let (lower, upper) =
+                    interval(replicates.iter().map(|q| q[k]).collect(), estimate[k]);
+                AncestryProportion {
+                    name: population.name.clone(),
+                    continent: Some(population.continent.clone()),
+                    proportion: estimate[k],
+                    lower,
+                    upper,
+                }
+            })
+            .collect();
+
+        let mut continents: Vec<&str> = Vec::new();
+        for population in &self.populations {
+            if !continents.contains(&population.continent.as_str()) {
+                continents.push(&population.continent);
+            }
+        }
+        let continental = continents
+            .into_iter()
+            .map(|continent| {
+                let share = |q: &[f64]| -> f64 {
+                    self.populations
+                        .iter()
+                        .zip(q)
+                        .filter(|(population, _)| population.continent == continent)
+                        .map(|(_, share)| share)
+                        .sum()
+                };
+                let proportion = share(&estimate);
+                let (lower, upper) =
+                    interval(replicates.iter().map(|q| share(q)).collect(), proportion);
+                AncestryProportion {
+                    name: continent.to_string(),
+                    continent: None,
+                    proportion,
+                    lower,
+                    upper,
+                }
+            })
+            .collect();
+
+        Ok(AncestryEstimate {
+            continental,
+            regional,
+            sites_used: observed.len(),
+            sites_total: self.sites.len(),
+            palindromic_skipped,
+            allele_mismatches,
+            bootstrap_replicates,
+        })
+    }
+}
+
+// Helper functions
+
+fn find_variant<'a>(
+    genome: &'a LoadedGenome,
+    site: &PanelSite,
+    same_build: bool,
+) -> Option<&'a Variant> {
+    if same_build {
+        if let (Some(chromosome), Some(position)) = (&site.chromosome, site.position) {
+            if let Some(variant) = genome.get_at(chromosome, position) {
+                return Some(variant);
+            }
+        }
+    }
+    site.rsid
+        .as_deref()
+        .and_then(|rsid| genome.get_by_rsid(rsid))
+}
+
+/// Copies of the panel allele in a diploid call, reading it from the
+/// opposite strand when only the complemented alleles fit
+fn allele_dosage(variant: &Variant, site: &PanelSite) -> Option<usize> {
+    let fits = |alleles: &[&str]| {
+        alleles.len() == 2
+            && alleles
+                .iter()
+                .all(|allele| *allele == site.allele || *allele == site.other_allele)
+    };
+    if fits(&variant.genotype.alleles()) {
+        return Some(variant.genotype.allele_count(&site.allele));
+    }
+    let flipped = variant.genotype.complemented();
+    fits(&flipped.alleles()).then(|| flipped.allele_count(&site.allele))
+}
+
+/// Mixture weights of highest likelihood, by EM from `start`
+///
+/// `weights` counts how often each site is drawn in a bootstrap replicate.
+fn fit(observed: &[(f64, Vec<f64>)], weights: &[u32], start: Vec<f64>) -> Vec<f64> {
+    let mut q = start;
+    let total: f64 = weights.iter().map(|&w| 2.0 * f64::from(w)).sum();
+    for _ in 0..MAX_ITERATIONS {
+        let mut next = vec![0.0; q.len()];
+        for ((dosage, frequencies), &weight) in observed.iter().zip(weights) {
+            if weight == 0 {
+                continue;
+            }
+            let carried: f64 = q.iter().zip(frequencies).map(|(q, p)| q * p).sum();
+            let other: f64 = q.iter().zip(frequencies).map(|(q, p)| q * (1.0 - p)).sum();
+            for (k, p) in frequencies.iter().enumerate() {
+                next[k] += f64::from(weight)
+                    * (dosage * q[k] * p / carried + (2.0 - dosage) * q[k] * (1.0 - p) / other);
+            }
+        }
+        next.iter_mut().for_each(|share| *share /= total);
+        let change = next
+            .iter()
+            .zip(&q)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        q = next;
+        if change < TOLERANCE {
+            break;
+        }
+    }
+    q
+}
+
+/// Percentile bootstrap interval, or the estimate itself without replicates
+fn interval(mut values: Vec<f64>, estimate: f64) -> (f64, f64) {
+    if values.is_empty() {
+        return (estimate, estimate);
+    }
+    values.sort_by(f64::total_cmp);
+    let tail = (1.0 - CONFIDENCE) / 2.0;
+    let at = |fraction: f64| values[((values.len() - 1) as f64 * fraction).round() as usize];
+    (at(tail).min(estimate), at(1.0 - tail).max(estimate))
+}
+
+/// Small deterministic generator for the bootstrap (Steele et al. 2014)
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform index below `bound`
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
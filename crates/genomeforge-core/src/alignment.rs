@@ -0,0 +1,569 @@
+//! Genotypes called from aligned reads
+//!
+//! Clinical sequencing often comes back as a BAM file of aligned reads
+//! rather than called genotypes. Calling a whole genome from reads is a job
+//! for a variant caller, but genotypes at a curated list of clinically
+//! relevant sites can be read straight off a pileup: count the bases the
+//! reads show at each site and call the alleles most of them agree on.
+//!
+//! Reads are skipped when unmapped, secondary, supplementary, duplicates or
+//! failing quality checks, or when mapped with low confidence; bases are
+//! skipped when their quality is low. A `.bai` index next to the BAM lets
+//! only the blocks covering the sites be read; without one the whole file
+//! is scanned.
+//!
+//! CRAM files store reads as differences from a reference FASTA with
+//! codecs this crate does not decode; they are recognized and refused with
+//! a hint to convert them to BAM.
+
+use crate::genome::{GenomeBuild, GenomeFile, Genotype, Variant};
+use crate::parser::bgzf;
+use crate::parser::compression::Compression;
+use crate::parser::detect::FileFormat;
+use crate::parser::tabix::{Chunk, RegionIndex};
+use crate::parser::{normalize_chromosome, SummaryBuilder};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, Read};
+use std::path::{Path, PathBuf};
+
+const BAM_MAGIC: &[u8; 4] = b"BAM\x01";
+const CRAM_MAGIC: &[u8; 4] = b"CRAM";
+
+/// Unmapped, secondary, failing quality checks, duplicate, supplementary
+const SKIPPED_FLAGS: u16 = 0x4 | 0x100 | 0x200 | 0x400 | 0x800;
+
+/// Length of chromosome 1 on each build, from the BAM header
+const CHROMOSOME_1_LENGTHS: [(GenomeBuild, u64); 2] = [
+    (GenomeBuild::GRCh37, 249_250_621),
+    (GenomeBuild::GRCh38, 248_956_422),
+];
+
+/// Share of reads an allele needs to be called
+const MIN_ALLELE_FRACTION: f64 = 0.2;
+
+/// Clinically relevant SNVs: rsid, gene, chromosome, GRCh37 and GRCh38
+/// positions, and forward-strand reference and alternate alleles
+const CLINICAL_SITES: [(&str, &str, &str, u64, u64, &str, &str); 17] = [
+    ("rs429358", "APOE", "19", 45411941, 44908684, "T", "C"),
+    ("rs7412", "APOE", "19", 45412079, 44908822, "C", "T"),
+    ("rs1800562", "HFE", "6", 26093141, 26092913, "G", "A"),
+    ("rs1799945", "HFE", "6", 26091179, 26090951, "C", "G"),
+    ("rs6025", "F5", "1", 169519049, 169549811, "C", "T"),
+    ("rs1799963", "F2", "11", 46761055, 46739505, "G", "A"),
+    ("rs1801133", "MTHFR", "1", 11856378, 11796321, "G", "A"),
+    ("rs334", "HBB", "11", 5248232, 5227002, "T", "A"),
+    ("rs4244285", "CYP2C19", "10", 96541616, 94781859, "G", "A"),
+    ("rs12248560", "CYP2C19", "10", 96521657, 94761900, "C", "T"),
+    ("rs1799853", "CYP2C9", "10", 96702047, 94942290, "C", "T"),
+    ("rs1057910", "CYP2C9", "10", 96741053, 94981296, "A", "C"),
+    ("rs3892097", "CYP2D6", "22", 42524947, 42128945, "C", "T"),
+    ("rs9923231", "VKORC1", "16", 31107689, 31096368, "C", "T"),
+    ("rs4149056", "SLCO1B1", "12", 21331549, 21178615, "T", "C"),
+    ("rs3918290", "DPYD", "1", 97915614, 97450058, "C", "T"),
+    ("rs1142345", "TPMT", "6", 18130918, 18130687, "T", "C"),
+];
+
+/// A single-base site to call a genotype at
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Target {
+    pub rsid: Option<String>,
+    pub chromosome: String,
+    /// 1-based
+    pub position: u64,
+    pub reference: Option<String>,
+    pub alternate: Option<String>,
+}
+
+impl Target {
+    /// The curated clinically relevant sites, on a build
+    pub fn clinical(build: GenomeBuild) -> Vec<Target> {
+        CLINICAL_SITES
+            .iter()
+            .filter_map(
+                |&(rsid, _, chromosome, grch37, grch38, reference, alternate)| {
+                    let position = match build {
+                        GenomeBuild::GRCh37 => grch37,
+                        GenomeBuild::GRCh38 => grch38,
+                        GenomeBuild::GRCh36 => return None,
+                    };
+                    Some(Target {
+                        rsid: Some(rsid.to_string()),
+                        chromosome: chromosome.to_string(),
+                        position,
+                        reference: Some(reference.to_string()),
+                        alternate: Some(alternate.to_string()),
+                    })
+                },
+            )
+            .collect()
+    }
+}
+
+/// Thresholds for counting a read's base
+#[derive(Debug, Clone, Copy)]
+pub struct PileupOptions {
+    pub min_mapping_quality: u8,
+    pub min_base_quality: u8,
+    /// Fewest counted bases for a call; fewer is a no-call
+    pub min_depth: usize,
+}
+
+impl Default for PileupOptions {
+    fn default() -> Self {
+        PileupOptions {
+            min_mapping_quality: 20,
+            min_base_quality: 20,
+            min_depth: 8,
+        }
+    }
+}
+
+/// Bases counted at a target and the genotype called from them
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteCall {
+    pub target: Target,
+    /// Reads showing A, C, G and T
+    pub counts: [usize; 4],
+    pub depth: usize,
+    pub genotype: Genotype,
+}
+
+/// A BAM file, read through its index when it has one
+#[derive(Debug)]
+pub struct BamFile {
+    path: PathBuf,
+    /// Chromosome names and lengths from the header
+    references: Vec<(String, u64)>,
+    /// Header text, holding `@HD`, `@SQ` and `@RG` lines
+    header: String,
+    index: Option<RegionIndex>,
+}
+
+impl BamFile {
+    /// Read the header of a BAM file and the `.bai` index next to it
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if is_cram(path)? {
+            return Err("CRAM files are not supported; convert them to BAM with `samtools view -b -T reference.fa` first".to_string());
+        }
+        let mut reader = bgzf::open(path)?;
+        let mut magic = [0u8; 4];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| "Not a BAM file".to_string())?;
+        if &magic != BAM_MAGIC {
+            return Err("Not a BAM file".to_string());
+        }
+        let header_length = read_length(&mut reader)?;
+        let header = String::from_utf8_lossy(&read_bytes(&mut reader, header_length)?)
+            .trim_end_matches('\0')
+            .to_string();
+        let mut references = Vec::new();
+        for _ in 0..read_length(&mut reader)? {
+            let name_length = read_length(&mut reader)?;
+            let name = read_bytes(&mut reader, name_length)?;
+            let name = String::from_utf8_lossy(&name)
+                .trim_end_matches('\0')
+                .to_string();
+            references.push((name, read_length(&mut reader)? as u64));
+        }
+
+        let names: Vec<String> = references.iter().map(|(name, _)| name.clone()).collect();
+        let index = match find_bai(path) {
+            Some(bai) => Some(RegionIndex::read_bai(&bai, &names)?),
+            None => None,
+        };
+        Ok(BamFile {
+            path: path.to_path_buf(),
+            references,
+            header,
+            index,
+        })
+    }
+
+    /// Chromosome names as written in the header
+    pub fn chromosomes(&self) -> Vec<&str> {
+        self.references
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Whether a `.bai` index was found
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Build the reads are aligned to, from the length of chromosome 1
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        let length = self
+            .references
+            .iter()
+            .find(|(name, _)| normalize_chromosome(name) == "1")
+            .map(|(_, length)| *length)?;
+        CHROMOSOME_1_LENGTHS
+            .iter()
+            .find(|(_, known)| *known == length)
+            .map(|(build, _)| *build)
+    }
+
+    /// Sample names from the `@RG` header lines
+    pub fn samples(&self) -> Vec<String> {
+        let mut samples: Vec<String> = Vec::new();
+        for line in self.header.lines().filter(|line| line.starts_with("@RG")) {
+            if let Some(sample) = line.split('\t').find_map(|tag| tag.strip_prefix("SM:")) {
+                if !samples.iter().any(|known| known == sample) {
+                    samples.push(sample.to_string());
+                }
+            }
+        }
+        samples
+    }
+
+    /// Count the bases reads show at each target and call a genotype
+    ///
+    /// Calls are returned in the order of `targets`. `checkpoint` is called
+    /// with the number of reads read every [`CHECKPOINT_INTERVAL`] reads and
+    /// stops the pileup when it returns an error.
+    pub fn pileup<F>(
+        &self,
+        targets: &[Target],
+        options: &PileupOptions,
+        mut checkpoint: F,
+    ) -> Result<Vec<SiteCall>, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        // Targets on each reference, sorted by 0-based position
+        let by_name: HashMap<String, usize> = self
+            .references
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (normalize_chromosome(name), index))
+            .collect();
+        let mut sites: HashMap<usize, Vec<(u64, usize)>> = HashMap::new();
+        for (index, target) in targets.iter().enumerate() {
+            if let Some(&reference) = by_name.get(&normalize_chromosome(&target.chromosome)) {
+                if target.position > 0 {
+                    sites
+                        .entry(reference)
+                        .or_default()
+                        .push((target.position - 1, index));
+                }
+            }
+        }
+        for positions in sites.values_mut() {
+            positions.sort_unstable();
+        }
+
+        let mut counts = vec![[0usize; 4]; targets.len()];
+        let mut reader = bgzf::open(&self.path)?;
+        let mut record = Vec::new();
+        let mut read = 0;
+        match &self.index {
+            Some(index) => {
+                for chunk in self.chunks(index, targets) {
+                    reader
+                        .seek_virtual(chunk.start)
+                        .map_err(|e| format!("Failed to read BAM: {}", e))?;
+                    while reader.virtual_position() < chunk.end {
+                        if !read_record(&mut reader, &mut record)? {
+                            break;
+                        }
+                        if read % CHECKPOINT_INTERVAL == 0 {
+                            checkpoint(read)?;
+                        }
+                        read += 1;
+                        count_bases(&record, &sites, options, &mut counts);
+                    }
+                }
+            }
+            None => {
+                skip_header(&mut reader)?;
+                while read_record(&mut reader, &mut record)? {
+                    if read % CHECKPOINT_INTERVAL == 0 {
+                        checkpoint(read)?;
+                    }
+                    read += 1;
+                    count_bases(&record, &sites, options, &mut counts);
+                }
+            }
+        }
+        checkpoint(read)?;
+
+        Ok(targets
+            .iter()
+            .zip(counts)
+            .map(|(target, counts)| {
+                let depth = counts.iter().sum();
+                SiteCall {
+                    target: target.clone(),
+                    counts,
+                    depth,
+                    genotype: call_genotype(&counts, options.min_depth),
+                }
+            })
+            .collect())
+    }
+
+    /// Call genotypes at the targets, as a genome of their own
+    pub fn load_targets<F>(
+        &self,
+        targets: &[Target],
+        options: &PileupOptions,
+        checkpoint: F,
+    ) -> Result<LoadedGenome, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut builder = SummaryBuilder::default();
+        let variants: Vec<Variant> = self
+            .pileup(targets, options, checkpoint)?
+            .into_iter()
+            .map(|call| Variant {
+                rsid: call.target.rsid,
+                chromosome: normalize_chromosome(&call.target.chromosome),
+                position: call.target.position,
+                reference: call.target.reference,
+                alternates: call.target.alternate.into_iter().collect(),
+                genotype: call.genotype,
+                quality: None,
+            })
+            .inspect(|variant| builder.add(variant))
+            .collect();
+        let file = GenomeFile {
+            format: FileFormat::Bam,
+            compression: Compression::Bgzip,
+            detection_confidence: 1.0,
+            format_version: self.format_version(),
+            chip_version: None,
+            genome_build: self.genome_build(),
+            samples: self.samples(),
+            fingerprint: None,
+        };
+        Ok(LoadedGenome::from_variants(
+            file,
+            builder.finish(0),
+            variants,
+        ))
+    }
+
+    /// SAM version from the `@HD` header line
+    fn format_version(&self) -> Option<String> {
+        let line = self.header.lines().find(|line| line.starts_with("@HD"))?;
+        line.split('\t')
+            .find_map(|tag| tag.strip_prefix("VN:"))
+            .map(|version| format!("SAM {}", version))
+    }
+
+    /// Chunks of the file holding reads over any target, sorted and merged
+    fn chunks(&self, index: &RegionIndex, targets: &[Target]) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = targets
+            .iter()
+            .flat_map(|target| index.chunks(&target.chromosome, target.position, target.position))
+            .collect();
+        chunks.sort();
+        let mut merged: Vec<Chunk> = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            match merged.last_mut() {
+                Some(last) if chunk.start <= last.end => last.end = last.end.max(chunk.end),
+                _ => merged.push(chunk),
+            }
+        }
+        merged
+    }
+}
+
+/// Whether a file holds aligned reads rather than genotypes
+pub fn is_alignment(path: &Path) -> Result<bool, String> {
+    if is_cram(path)? {
+        return Ok(true);
+    }
+    let mut magic = [0u8; 4];
+    let reader = bgzf::open(path)?;
+    Ok(reader.take(4).read(&mut magic).unwrap_or(0) == 4 && &magic == BAM_MAGIC)
+}
+
+/// Genotype called from the A, C, G and T counts at a site
+///
+/// Alleles seen in at least a fifth of the reads are called; a site with
+/// three such alleles, or fewer than `min_depth` reads, is a no-call.
+pub fn call_genotype(counts: &[usize; 4], min_depth: usize) -> Genotype {
+    let depth: usize = counts.iter().sum();
+    if depth == 0 || depth < min_depth {
+        return Genotype::NoCall;
+    }
+    let called: Vec<usize> = (0..4)
+        .filter(|&base| counts[base] as f64 / depth as f64 >= MIN_ALLELE_FRACTION)
+        .collect();
+    let base = |index: usize| ["A", "C", "G", "T"][index].to_string();
+    match called.as_slice() {
+        [only] => Genotype::Diploid {
+            first: base(*only),
+            second: base(*only),
+            phased: false,
+        },
+        [first, second] => Genotype::Diploid {
+            first: base(*first),
+            second: base(*second),
+            phased: false,
+        },
+        _ => Genotype::NoCall,
+    }
+}
+
+// Helper functions
+
+fn is_cram(path: &Path) -> Result<bool, String> {
+    let mut magic = [0u8; 4];
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    Ok(file.take(4).read(&mut magic).unwrap_or(0) == 4 && &magic == CRAM_MAGIC)
+}
+
+/// `reads.bam.bai` or `reads.bai`, whichever exists
+fn find_bai(path: &Path) -> Option<PathBuf> {
+    let mut appended = path.as_os_str().to_owned();
+    appended.push(".bai");
+    [PathBuf::from(appended), path.with_extension("bai")]
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+fn read_length(reader: &mut impl Read) -> Result<usize, String> {
+    let mut bytes = [0u8; 4];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| format!("Failed to read BAM header: {}", e))?;
+    usize::try_from(i32::from_le_bytes(bytes)).map_err(|_| "BAM header is corrupt".to_string())
+}
+
+fn read_bytes(reader: &mut impl Read, length: usize) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .take(length as u64)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read BAM header: {}", e))?;
+    if bytes.len() != length {
+        return Err("BAM header is truncated".to_string());
+    }
+    Ok(bytes)
+}
+
+/// Move past the header to the first read
+fn skip_header(reader: &mut impl Read) -> Result<(), String> {
+    read_bytes(reader, 4)?;
+    let length = read_length(reader)?;
+    read_bytes(reader, length)?;
+    for _ in 0..read_length(reader)? {
+        let length = read_length(reader)?;
+        read_bytes(reader, length + 4)?;
+    }
+    Ok(())
+}
+
+/// Read the next alignment record into `record`; false at the end
+fn read_record(reader: &mut impl BufRead, record: &mut Vec<u8>) -> Result<bool, String> {
+    let failed = |e: io::Error| format!("Failed to read BAM: {}", e);
+    if reader.fill_buf().map_err(failed)?.is_empty() {
+        return Ok(false);
+    }
+    let mut size = [0u8; 4];
+    reader.read_exact(&mut size).map_err(failed)?;
+    let size = usize::try_from(i32::from_le_bytes(size))
+        .map_err(|_| "BAM record is corrupt".to_string())?;
+    record.resize(size, 0);
+    reader.read_exact(record).map_err(failed)?;
+    Ok(true)
+}
+
+/// Add the bases of one record at the targets it covers
+fn count_bases(
+    record: &[u8],
+    sites: &HashMap<usize, Vec<(u64, usize)>>,
+    options: &PileupOptions,
+    counts: &mut [[usize; 4]],
+) {
+    let field = |at: usize, width: usize| -> Option<u64> {
+        let bytes = record.get(at..at + width)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0u64, |value, &b| value << 8 | b as u64),
+        )
+    };
+    let (Some(reference), Some(start)) = (field(0, 4), field(4, 4)) else {
+        return;
+    };
+    // Unmapped reads have a reference id and position of -1
+    let (Ok(reference), Ok(start)) = (i32::try_from(reference), i32::try_from(start)) else {
+        return;
+    };
+    let Some(positions) = usize::try_from(reference).ok().and_then(|r| sites.get(&r)) else {
+        return;
+    };
+    let (Some(name_length), Some(mapping_quality), Some(cigar_ops), Some(flags), Some(length)) = (
+        field(8, 1),
+        field(9, 1),
+        field(12, 2),
+        field(14, 2),
+        field(16, 4),
+    ) else {
+        return;
+    };
+    if flags as u16 & SKIPPED_FLAGS != 0
+        || (mapping_quality as u8) < options.min_mapping_quality
+        || start < 0
+    {
+        return;
+    }
+    let cigar_at = 32 + name_length as usize;
+    let sequence_at = cigar_at + 4 * cigar_ops as usize;
+    let quality_at = sequence_at + (length as usize).div_ceil(2);
+    if record.len() < quality_at + length as usize {
+        return;
+    }
+
+    let mut on_reference = start as u64;
+    let mut in_read = 0usize;
+    for op in 0..cigar_ops as usize {
+        let Some(value) = field(cigar_at + 4 * op, 4) else {
+            return;
+        };
+        let (span, kind) = (value >> 4, value & 0xf);
+        match kind {
+            // M, = and X align read bases to the reference
+            0 | 7 | 8 => {
+                let first = positions.partition_point(|(position, _)| *position < on_reference);
+                for &(position, target) in &positions[first..] {
+                    if position >= on_reference + span {
+                        break;
+                    }
+                    let offset = in_read + (position - on_reference) as usize;
+                    if record[quality_at + offset] < options.min_base_quality {
+                        continue;
+                    }
+                    let packed = record[sequence_at + offset / 2];
+                    let code = if offset.is_multiple_of(2) {
+                        packed >> 4
+                    } else {
+                        packed & 0xf
+                    };
+                    // 4-bit codes of A, C, G and T
+                    if let Some(base) = [1, 2, 4, 8].iter().position(|&c| c == code) {
+                        counts[target][base] += 1;
+                    }
+                }
+                on_reference += span;
+                in_read += span as usize;
+            }
+            // I and S consume read bases only
+            1 | 4 => in_read += span as usize,
+            // D and N skip reference bases
+            2 | 3 => on_reference += span,
+            _ => {}
+        }
+    }
+}
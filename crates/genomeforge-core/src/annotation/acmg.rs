@@ -0,0 +1,281 @@
+//! ACMG secondary findings screening
+//!
+//! The American College of Medical Genetics and Genomics lists genes in
+//! which pathogenic and likely pathogenic variants are medically
+//! actionable enough to report even when nobody was looking for them. This
+//! is version 3.2 of the list (Miller et al., Genet Med 2023). Findings in
+//! these genes are incidental for most users, so they are only reported to
+//! those who ask for them.
+
+use super::clinvar::{ClinVarMatch, ClinVarRecord};
+use serde::{Deserialize, Serialize};
+
+/// Version of the ACMG SF list below
+pub const VERSION: &str = "3.2";
+
+/// Area of medicine a gene belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcmgCategory {
+    Cancer,
+    Cardiovascular,
+    InbornErrorOfMetabolism,
+    Miscellaneous,
+}
+
+/// Inheritance that decides how many variants make a finding reportable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inheritance {
+    /// One variant is reportable
+    Dominant,
+    /// Two variants are needed: one homozygous or two different ones
+    Recessive,
+    /// One variant is reportable
+    XLinked,
+}
+
+/// One gene of the ACMG SF list
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AcmgGene {
+    pub gene: &'static str,
+    pub condition: &'static str,
+    pub category: AcmgCategory,
+    pub inheritance: Inheritance,
+    /// Set when only this variant is reportable, as for HFE p.C282Y
+    pub only_rsid: Option<&'static str>,
+}
+
+/// Reportable variants in one ACMG gene
+#[derive(Debug, Clone)]
+pub struct SecondaryFinding<'a> {
+    pub gene: &'static AcmgGene,
+    pub matches: Vec<ClinVarMatch<'a>>,
+}
+
+const fn entry(
+    gene: &'static str,
+    condition: &'static str,
+    category: AcmgCategory,
+    inheritance: Inheritance,
+) -> AcmgGene {
+    AcmgGene {
+        gene,
+        condition,
+        category,
+        inheritance,
+        only_rsid: None,
+    }
+}
+
+use AcmgCategory::{Cancer, Cardiovascular, InbornErrorOfMetabolism, Miscellaneous};
+use Inheritance::{Dominant, Recessive, XLinked};
+
+// Conditions shared by several genes
+const LYNCH: &str = "Lynch syndrome";
+const PARAGANGLIOMA: &str = "Hereditary paraganglioma-pheochromocytoma syndrome";
+const HBOC: &str = "Hereditary breast and ovarian cancer";
+const JUVENILE_POLYPOSIS: &str = "Juvenile polyposis syndrome";
+const TUBEROUS_SCLEROSIS: &str = "Tuberous sclerosis complex";
+const AORTIC_ANEURYSM: &str = "Familial thoracic aortic aneurysm";
+const LOEYS_DIETZ: &str = "Loeys-Dietz syndrome";
+const HCM: &str = "Hypertrophic cardiomyopathy";
+const DCM: &str = "Dilated cardiomyopathy";
+const ARVC: &str = "Arrhythmogenic right ventricular cardiomyopathy";
+const LONG_QT: &str = "Long QT syndrome";
+const CPVT: &str = "Catecholaminergic polymorphic ventricular tachycardia";
+const HYPERCHOLESTEROLEMIA: &str = "Familial hypercholesterolemia";
+const HHT: &str = "Hereditary hemorrhagic telangiectasia";
+const MALIGNANT_HYPERTHERMIA: &str = "Malignant hyperthermia susceptibility";
+
+/// Genes of ACMG SF v3.2
+pub const GENES: [AcmgGene; 81] = [
+    entry("APC", "Familial adenomatous polyposis", Cancer, Dominant),
+    entry("BMPR1A", JUVENILE_POLYPOSIS, Cancer, Dominant),
+    entry("BRCA1", HBOC, Cancer, Dominant),
+    entry("BRCA2", HBOC, Cancer, Dominant),
+    entry("MAX", PARAGANGLIOMA, Cancer, Dominant),
+    entry(
+        "MEN1",
+        "Multiple endocrine neoplasia type 1",
+        Cancer,
+        Dominant,
+    ),
+    entry("MLH1", LYNCH, Cancer, Dominant),
+    entry("MSH2", LYNCH, Cancer, Dominant),
+    entry("MSH6", LYNCH, Cancer, Dominant),
+    entry("MUTYH", "MUTYH-associated polyposis", Cancer, Recessive),
+    entry("NF2", "Neurofibromatosis type 2", Cancer, Dominant),
+    entry("PALB2", "Hereditary breast cancer", Cancer, Dominant),
+    entry("PMS2", LYNCH, Cancer, Dominant),
+    entry("PTEN", "PTEN hamartoma tumor syndrome", Cancer, Dominant),
+    entry("RB1", "Retinoblastoma", Cancer, Dominant),
+    entry(
+        "RET",
+        "Multiple endocrine neoplasia type 2",
+        Cancer,
+        Dominant,
+    ),
+    entry("SDHAF2", PARAGANGLIOMA, Cancer, Dominant),
+    entry("SDHB", PARAGANGLIOMA, Cancer, Dominant),
+    entry("SDHC", PARAGANGLIOMA, Cancer, Dominant),
+    entry("SDHD", PARAGANGLIOMA, Cancer, Dominant),
+    entry("SMAD4", JUVENILE_POLYPOSIS, Cancer, Dominant),
+    entry("STK11", "Peutz-Jeghers syndrome", Cancer, Dominant),
+    entry("TMEM127", PARAGANGLIOMA, Cancer, Dominant),
+    entry("TP53", "Li-Fraumeni syndrome", Cancer, Dominant),
+    entry("TSC1", TUBEROUS_SCLEROSIS, Cancer, Dominant),
+    entry("TSC2", TUBEROUS_SCLEROSIS, Cancer, Dominant),
+    entry("VHL", "Von Hippel-Lindau syndrome", Cancer, Dominant),
+    entry("WT1", "WT1-related Wilms tumor", Cancer, Dominant),
+    entry("ACTA2", AORTIC_ANEURYSM, Cardiovascular, Dominant),
+    entry(
+        "COL3A1",
+        "Vascular Ehlers-Danlos syndrome",
+        Cardiovascular,
+        Dominant,
+    ),
+    entry("FBN1", "Marfan syndrome", Cardiovascular, Dominant),
+    entry("MYH11", AORTIC_ANEURYSM, Cardiovascular, Dominant),
+    entry("SMAD3", LOEYS_DIETZ, Cardiovascular, Dominant),
+    entry("TGFBR1", LOEYS_DIETZ, Cardiovascular, Dominant),
+    entry("TGFBR2", LOEYS_DIETZ, Cardiovascular, Dominant),
+    entry("ACTC1", HCM, Cardiovascular, Dominant),
+    entry("BAG3", DCM, Cardiovascular, Dominant),
+    entry("DES", DCM, Cardiovascular, Dominant),
+    entry("DSC2", ARVC, Cardiovascular, Dominant),
+    entry("DSG2", ARVC, Cardiovascular, Dominant),
+    entry("DSP", ARVC, Cardiovascular, Dominant),
+    entry("FLNC", DCM, Cardiovascular, Dominant),
+    entry("LMNA", DCM, Cardiovascular, Dominant),
+    entry("MYBPC3", HCM, Cardiovascular, Dominant),
+    entry("MYH7", HCM, Cardiovascular, Dominant),
+    entry("MYL2", HCM, Cardiovascular, Dominant),
+    entry("MYL3", HCM, Cardiovascular, Dominant),
+    entry("PKP2", ARVC, Cardiovascular, Dominant),
+    entry("PRKAG2", HCM, Cardiovascular, Dominant),
+    entry("RBM20", DCM, Cardiovascular, Dominant),
+    entry("TMEM43", ARVC, Cardiovascular, Dominant),
+    entry("TNNC1", DCM, Cardiovascular, Dominant),
+    entry("TNNI3", HCM, Cardiovascular, Dominant),
+    entry("TNNT2", HCM, Cardiovascular, Dominant),
+    entry("TPM1", HCM, Cardiovascular, Dominant),
+    entry("TTN", DCM, Cardiovascular, Dominant),
+    entry("CALM1", LONG_QT, Cardiovascular, Dominant),
+    entry("CALM2", LONG_QT, Cardiovascular, Dominant),
+    entry("CALM3", LONG_QT, Cardiovascular, Dominant),
+    entry("CASQ2", CPVT, Cardiovascular, Recessive),
+    entry("KCNH2", LONG_QT, Cardiovascular, Dominant),
+    entry("KCNQ1", LONG_QT, Cardiovascular, Dominant),
+    entry("RYR2", CPVT, Cardiovascular, Dominant),
+    entry(
+        "SCN5A",
+        "Long QT syndrome and Brugada syndrome",
+        Cardiovascular,
+        Dominant,
+    ),
+    entry("TRDN", CPVT, Cardiovascular, Recessive),
+    entry("APOB", HYPERCHOLESTEROLEMIA, Cardiovascular, Dominant),
+    entry("LDLR", HYPERCHOLESTEROLEMIA, Cardiovascular, Dominant),
+    entry("PCSK9", HYPERCHOLESTEROLEMIA, Cardiovascular, Dominant),
+    entry("GLA", "Fabry disease", Cardiovascular, XLinked),
+    entry(
+        "BTD",
+        "Biotinidase deficiency",
+        InbornErrorOfMetabolism,
+        Recessive,
+    ),
+    entry("GAA", "Pompe disease", InbornErrorOfMetabolism, Recessive),
+    entry(
+        "OTC",
+        "Ornithine transcarbamylase deficiency",
+        InbornErrorOfMetabolism,
+        XLinked,
+    ),
+    entry("ACVRL1", HHT, Miscellaneous, Dominant),
+    entry("ENG", HHT, Miscellaneous, Dominant),
+    entry("ATP7B", "Wilson disease", Miscellaneous, Recessive),
+    AcmgGene {
+        only_rsid: Some("rs1800562"),
+        ..entry(
+            "HFE",
+            "Hereditary hemochromatosis",
+            Miscellaneous,
+            Recessive,
+        )
+    },
+    entry(
+        "HNF1A",
+        "Maturity-onset diabetes of the young",
+        Miscellaneous,
+        Dominant,
+    ),
+    entry(
+        "RPE65",
+        "RPE65-related retinopathy",
+        Miscellaneous,
+        Recessive,
+    ),
+    entry("RYR1", MALIGNANT_HYPERTHERMIA, Miscellaneous, Dominant),
+    entry("CACNA1S", MALIGNANT_HYPERTHERMIA, Miscellaneous, Dominant),
+    entry(
+        "TTR",
+        "Hereditary transthyretin amyloidosis",
+        Miscellaneous,
+        Dominant,
+    ),
+];
+
+/// The ACMG SF entry for a gene symbol
+pub fn gene(symbol: &str) -> Option<&'static AcmgGene> {
+    GENES
+        .iter()
+        .find(|entry| entry.gene.eq_ignore_ascii_case(symbol))
+}
+
+/// ACMG gene a pathogenic or likely pathogenic record falls in
+pub fn secondary_gene(record: &ClinVarRecord) -> Option<&'static AcmgGene> {
+    if !record.significance.is_pathogenic() {
+        return None;
+    }
+    record.genes.iter().find_map(|symbol| gene(symbol))
+}
+
+/// Reportable findings among a genome's ClinVar matches
+///
+/// Dominant and X-linked genes are reported on any pathogenic or likely
+/// pathogenic variant. Recessive genes need two: a homozygous variant, or
+/// two different variants that may be on opposite copies, since phase is
+/// rarely known.
+pub fn screen<'a>(matches: &[ClinVarMatch<'a>]) -> Vec<SecondaryFinding<'a>> {
+    let mut findings: Vec<SecondaryFinding<'a>> = Vec::new();
+    for found in matches {
+        let Some(gene) = secondary_gene(found.record) else {
+            continue;
+        };
+        if gene
+            .only_rsid
+            .is_some_and(|rsid| found.record.rsid.as_deref() != Some(rsid))
+        {
+            continue;
+        }
+        match findings
+            .iter_mut()
+            .find(|finding| finding.gene.gene == gene.gene)
+        {
+            Some(finding) => finding.matches.push(*found),
+            None => findings.push(SecondaryFinding {
+                gene,
+                matches: vec![*found],
+            }),
+        }
+    }
+
+    findings.retain(|finding| match finding.gene.inheritance {
+        Inheritance::Dominant | Inheritance::XLinked => true,
+        Inheritance::Recessive => {
+            finding.matches.len() > 1 || finding.matches.iter().any(|m| m.alternate_copies > 1)
+        }
+    });
+    findings
+}
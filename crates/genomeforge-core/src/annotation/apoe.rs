@@ -0,0 +1,180 @@
+//! APOE ε2/ε3/ε4 genotype
+//!
+//! The three common APOE alleles differ at two SNPs. rs429358 carries C on
+//! ε4 and T otherwise; rs7412 carries T on ε2 and C otherwise. ε4 is the
+//! strongest common genetic risk factor for late-onset Alzheimer's disease,
+//! which many people would rather not learn about, so callers only report
+//! it with consent.
+//!
+//! Without phase, carrying one copy of each minor allele could be ε2/ε4 or
+//! the far rarer ε1/ε3; ε2/ε4 is called and ε1/ε3 listed as the
+//! alternative.
+
+use crate::genome::{GenomeBuild, Variant};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// SNP whose C allele marks ε4 (and ε1)
+pub const RS429358: &str = "rs429358";
+
+/// SNP whose T allele marks ε2 (and ε1)
+pub const RS7412: &str = "rs7412";
+
+/// Gene symbol the two SNPs are annotated with in other databases
+pub const GENE: &str = "APOE";
+
+/// Condition the genotype is reported for
+pub const CONDITION: &str = "Late-onset Alzheimer's disease";
+
+/// What the genotype does and does not say
+pub const CAVEAT: &str = "APOE genotype changes the chance of developing Alzheimer's disease but does not decide it: many people with ε4 never develop it and many without it do.";
+
+/// Positions of rs429358 and rs7412 on chromosome 19
+const POSITIONS: [(GenomeBuild, u64, u64); 2] = [
+    (GenomeBuild::GRCh37, 45411941, 45412079),
+    (GenomeBuild::GRCh38, 44908684, 44908822),
+];
+
+/// An APOE allele
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApoeAllele {
+    E1,
+    E2,
+    E3,
+    E4,
+}
+
+impl fmt::Display for ApoeAllele {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ApoeAllele::E1 => "ε1",
+            ApoeAllele::E2 => "ε2",
+            ApoeAllele::E3 => "ε3",
+            ApoeAllele::E4 => "ε4",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Late-onset Alzheimer's risk of a diplotype relative to ε3/ε3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApoeRisk {
+    Reduced,
+    Typical,
+    Increased,
+    /// Two copies of ε4
+    High,
+}
+
+/// APOE diplotype of a genome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApoeCall {
+    pub alleles: [ApoeAllele; 2],
+    /// e.g. "ε3/ε4"
+    pub diplotype: String,
+    /// The other diplotype the genotypes fit, when phase matters
+    pub alternative: Option<String>,
+    pub e4_copies: usize,
+    pub risk: ApoeRisk,
+    /// Calls as reported, e.g. "CT"
+    pub rs429358: String,
+    pub rs7412: String,
+}
+
+/// Call the APOE diplotype, if both SNPs are genotyped
+pub fn call(genome: &LoadedGenome) -> Option<ApoeCall> {
+    let positions = POSITIONS
+        .iter()
+        .find(|(build, _, _)| genome.file.genome_build == Some(*build));
+    let first = find_variant(genome, RS429358, positions.map(|p| p.1))?;
+    let second = find_variant(genome, RS7412, positions.map(|p| p.2))?;
+    let e4_sites = c_and_t_counts(first)?.0;
+    let e2_sites = c_and_t_counts(second)?.1;
+
+    // ε4 haplotypes carry rs429358 C and ε2 haplotypes rs7412 T; one
+    // haplotype carrying both would be ε1
+    let (alleles, alternative) = match (e4_sites, e2_sites) {
+        (0, 0) => ([ApoeAllele::E3, ApoeAllele::E3], None),
+        (0, 1) => ([ApoeAllele::E2, ApoeAllele::E3], None),
+        (0, _) => ([ApoeAllele::E2, ApoeAllele::E2], None),
+        (1, 0) => ([ApoeAllele::E3, ApoeAllele::E4], None),
+        (1, 1) => (
+            [ApoeAllele::E2, ApoeAllele::E4],
+            Some([ApoeAllele::E1, ApoeAllele::E3]),
+        ),
+        (1, _) => ([ApoeAllele::E1, ApoeAllele::E2], None),
+        (_, 0) => ([ApoeAllele::E4, ApoeAllele::E4], None),
+        (_, 1) => ([ApoeAllele::E1, ApoeAllele::E4], None),
+        (_, _) => ([ApoeAllele::E1, ApoeAllele::E1], None),
+    };
+    let e4_copies = alleles.iter().filter(|a| **a == ApoeAllele::E4).count();
+    let e2_copies = alleles.iter().filter(|a| **a == ApoeAllele::E2).count();
+    let risk = match (e4_copies, e2_copies) {
+        (0, 0) => ApoeRisk::Typical,
+        (0, _) => ApoeRisk::Reduced,
+        (1, _) => ApoeRisk::Increased,
+        _ => ApoeRisk::High,
+    };
+
+    let label = |[a, b]: [ApoeAllele; 2]| format!("{}/{}", a, b);
+    Some(ApoeCall {
+        alleles,
+        diplotype: label(alleles),
+        alternative: alternative.map(label),
+        e4_copies,
+        risk,
+        rs429358: first.genotype.to_string(),
+        rs7412: second.genotype.to_string(),
+    })
+}
+
+/// Add the two APOE SNPs, on both builds, to `sites`
+pub fn add_sites(sites: &mut SiteFilter) {
+    sites.add_rsid(RS429358);
+    sites.add_rsid(RS7412);
+    for (_, rs429358, rs7412) in POSITIONS {
+        sites.add_position("19", rs429358);
+        sites.add_position("19", rs7412);
+    }
+}
+
+/// Whether a database record concerns the APOE genotype
+pub fn is_apoe_site(rsid: Option<&str>, genes: &[String]) -> bool {
+    rsid.is_some_and(|rsid| rsid == RS429358 || rsid == RS7412)
+        || genes.iter().any(|gene| gene.eq_ignore_ascii_case(GENE))
+}
+
+/// Whether a trait name is the condition APOE genotype reports on
+pub fn is_late_onset_trait(name: &str) -> bool {
+    name.to_ascii_lowercase().contains("alzheimer")
+}
+
+// Helper functions
+
+fn find_variant<'a>(
+    genome: &'a LoadedGenome,
+    rsid: &str,
+    position: Option<u64>,
+) -> Option<&'a Variant> {
+    genome
+        .get_by_rsid(rsid)
+        .or_else(|| position.and_then(|position| genome.get_at("19", position)))
+        .filter(|variant| !variant.genotype.is_no_call())
+}
+
+/// Copies of C and T in a diploid call, complementing A/G calls reported on
+/// the minus strand
+fn c_and_t_counts(variant: &Variant) -> Option<(usize, usize)> {
+    let genotype = &variant.genotype;
+    let genotype = if genotype.allele_count("A") + genotype.allele_count("G") == 2 {
+        genotype.complemented()
+    } else {
+        genotype.clone()
+    };
+    let counts = (genotype.allele_count("C"), genotype.allele_count("T"));
+    (counts.0 + counts.1 == 2).then_some(counts)
+}
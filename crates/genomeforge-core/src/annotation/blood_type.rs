@@ -0,0 +1,479 @@
+//! Blood group prediction
+//!
+//! ABO is read from three sites of the ABO gene. The O allele is a
+//! single-base deletion, rs8176719 (c.261delG), which arrays report as D
+//! and I and VCFs as the shorter allele; rs505922, whose T allele is in
+//! near-complete linkage with it, stands in when the deletion was not
+//! genotyped. The B allele differs from A at rs8176746 (c.796C>A) and
+//! rs8176747 (c.803G>C).
+//!
+//! RhD negativity almost always comes from a deletion of the whole RHD
+//! gene, which no SNP genotypes. It is inferred from probe dropout: when
+//! every probe inside RHD failed while the array genotyped the rest of the
+//! genome, both copies are likely deleted. One deleted copy cannot be
+//! seen, and data holding only variant sites has no probes to fail, so the
+//! call is never more than moderately confident. Genomes loaded with a
+//! site filter keep none of the gene's probes.
+//!
+//! Kell, Kidd and Duffy antigens are each read from one SNP when the data
+//! has it.
+
+use crate::genome::{GenomeBuild, Region, Variant};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// O allele deletion, c.261delG
+pub const RS8176719: &str = "rs8176719";
+
+/// SNP whose T allele tags the O allele
+pub const RS505922: &str = "rs505922";
+
+/// B allele SNPs, c.796C>A and c.803G>C
+pub const B_MARKERS: [(&str, &str, &str); 2] = [("rs8176746", "T", "G"), ("rs8176747", "G", "C")];
+
+/// Chromosome 9 positions of rs8176719, rs8176746 and rs8176747 by build
+const ABO_POSITIONS: [(GenomeBuild, [u64; 3]); 2] = [
+    (GenomeBuild::GRCh37, [136132908, 136131322, 136131315]),
+    (GenomeBuild::GRCh38, [133257521, 133255935, 133255928]),
+];
+
+/// The RHD gene on chromosome 1 by build
+const RHD_REGIONS: [(GenomeBuild, u64, u64); 2] = [
+    (GenomeBuild::GRCh37, 25598981, 25656936),
+    (GenomeBuild::GRCh38, 25272393, 25330445),
+];
+
+/// Probes inside RHD needed to read dropout from
+const MIN_RHD_PROBES: usize = 3;
+
+/// Confidence lost for each imputed marker a call rests on
+const IMPUTED_PENALTY: f64 = 0.8;
+
+/// ABO blood group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AboGroup {
+    A,
+    B,
+    AB,
+    O,
+}
+
+impl fmt::Display for AboGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AboGroup::A => "A",
+            AboGroup::B => "B",
+            AboGroup::AB => "AB",
+            AboGroup::O => "O",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Predicted ABO group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AboCall {
+    pub group: AboGroup,
+    /// Alleles carried, e.g. "AO"
+    pub genotype: String,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    /// rsids the call was read from
+    pub markers: Vec<String>,
+    pub notes: Vec<String>,
+}
+
+/// Predicted RhD status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RhCall {
+    pub d_positive: bool,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    /// Probes inside RHD with a call, of those in the data
+    pub probes_called: usize,
+    pub probes_total: usize,
+    pub note: String,
+}
+
+/// A predicted antigen phenotype of another blood group system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AntigenCall {
+    pub system: String,
+    pub gene: String,
+    /// e.g. "Jk(a+b-)" or "K-k+"
+    pub phenotype: String,
+    pub marker: String,
+    pub genotype: String,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+}
+
+/// Everything predicted about a genome's blood groups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloodTypePrediction {
+    /// ABO group and RhD status together, e.g. "A+"
+    pub blood_type: Option<String>,
+    pub abo: Option<AboCall>,
+    pub rh: Option<RhCall>,
+    pub antigens: Vec<AntigenCall>,
+    /// What the data lacks to predict the rest
+    pub missing: Vec<String>,
+}
+
+/// How the antigens of a system are written
+#[derive(Debug, Clone, Copy)]
+enum Notation {
+    /// "Jk(a+b-)"
+    Bracketed(&'static str),
+    /// "K-k+"
+    Plain,
+}
+
+/// A system of two antigens told apart by one SNP
+#[derive(Debug, Clone, Copy)]
+struct AntigenMarker {
+    system: &'static str,
+    gene: &'static str,
+    rsid: &'static str,
+    /// Plus-strand allele of each antigen
+    alleles: [(&'static str, &'static str); 2],
+    notation: Notation,
+    /// A SNP whose allele silences the second antigen on red cells
+    silencer: Option<(&'static str, &'static str, &'static str)>,
+}
+
+const ANTIGEN_MARKERS: [AntigenMarker; 3] = [
+    AntigenMarker {
+        system: "Kell",
+        gene: "KEL",
+        rsid: "rs8176058",
+        alleles: [("A", "K"), ("G", "k")],
+        notation: Notation::Plain,
+        silencer: None,
+    },
+    AntigenMarker {
+        system: "Kidd",
+        gene: "SLC14A1",
+        rsid: "rs1058396",
+        alleles: [("G", "a"), ("A", "b")],
+        notation: Notation::Bracketed("Jk"),
+        silencer: None,
+    },
+    AntigenMarker {
+        system: "Duffy",
+        gene: "ACKR1",
+        rsid: "rs12075",
+        alleles: [("G", "a"), ("A", "b")],
+        notation: Notation::Bracketed("Fy"),
+        // The GATA box variant behind Fy(a-b-) in people of African
+        // ancestry sits on FY*B
+        silencer: Some(("rs2814778", "C", "T")),
+    },
+];
+
+/// Predict every blood group the genome's markers allow
+pub fn predict(genome: &LoadedGenome) -> BloodTypePrediction {
+    let mut missing = Vec::new();
+    let abo = call_abo(genome, &mut missing);
+    let rh = call_rh(genome, &mut missing);
+    let antigens = ANTIGEN_MARKERS
+        .iter()
+        .filter_map(|marker| {
+            let call = call_antigen(genome, marker);
+            if call.is_none() {
+                missing.push(format!(
+                    "{} ({}): {} not genotyped",
+                    marker.system, marker.gene, marker.rsid
+                ));
+            }
+            call
+        })
+        .collect();
+    let blood_type = abo
+        .as_ref()
+        .zip(rh.as_ref())
+        .map(|(abo, rh)| format!("{}{}", abo.group, if rh.d_positive { "+" } else { "-" }));
+    BloodTypePrediction {
+        blood_type,
+        abo,
+        rh,
+        antigens,
+        missing,
+    }
+}
+
+/// Add the ABO and antigen markers to `sites`
+///
+/// RHD probes are only kept in genomes loaded whole.
+pub fn add_sites(sites: &mut SiteFilter) {
+    sites.add_rsid(RS8176719);
+    sites.add_rsid(RS505922);
+    for (rsid, _, _) in B_MARKERS {
+        sites.add_rsid(rsid);
+    }
+    for (_, positions) in ABO_POSITIONS {
+        for position in positions {
+            sites.add_position("9", position);
+        }
+    }
+    for marker in &ANTIGEN_MARKERS {
+        sites.add_rsid(marker.rsid);
+        if let Some((rsid, _, _)) = marker.silencer {
+            sites.add_rsid(rsid);
+        }
+    }
+}
+
+// Helper functions
+
+fn call_abo(genome: &LoadedGenome, missing: &mut Vec<String>) -> Option<AboCall> {
+    let mut markers = Vec::new();
+    let mut notes = Vec::new();
+    let mut confidence = 0.95;
+
+    let (o_copies, imputed) = match abo_variant(genome, RS8176719, 0)
+        .and_then(|variant| Some((o_copies(variant)?, variant.is_imputed())))
+    {
+        Some(found) => {
+            markers.push(RS8176719.to_string());
+            found
+        }
+        None => {
+            let proxy = called(genome.get_by_rsid(RS505922)).and_then(|variant| {
+                Some((
+                    variant.genotype.biallelic_count("T", "C")?,
+                    variant.is_imputed(),
+                ))
+            });
+            let Some(found) = proxy else {
+                missing.push(format!(
+                    "ABO: neither the O allele deletion {} nor its proxy {} was genotyped",
+                    RS8176719, RS505922
+                ));
+                return None;
+            };
+            markers.push(RS505922.to_string());
+            notes.push(format!(
+                "The O allele was read from {}, which tags it in most but not all people.",
+                RS505922
+            ));
+            confidence = 0.75;
+            found
+        }
+    };
+    if imputed {
+        confidence *= IMPUTED_PENALTY;
+    }
+    let non_o = 2usize.saturating_sub(o_copies);
+
+    let mut b_calls = Vec::new();
+    for (index, (rsid, b_allele, a_allele)) in B_MARKERS.iter().enumerate() {
+        let Some(variant) = abo_variant(genome, rsid, index + 1) else {
+            continue;
+        };
+        if let Some(copies) = variant.genotype.biallelic_count(b_allele, a_allele) {
+            markers.push(rsid.to_string());
+            if variant.is_imputed() {
+                confidence *= IMPUTED_PENALTY;
+            }
+            b_calls.push(copies);
+        }
+    }
+    let b_copies = match b_calls.as_slice() {
+        _ if non_o == 0 => 0,
+        [] => {
+            missing.push(format!(
+                "ABO: neither B allele SNP ({} or {}) was genotyped, so A and B cannot be told apart",
+                B_MARKERS[0].0, B_MARKERS[1].0
+            ));
+            return None;
+        }
+        [copies] => {
+            confidence -= 0.1;
+            *copies
+        }
+        [first, second, ..] => {
+            if first != second {
+                confidence *= 0.5;
+                notes.push("The two B allele SNPs disagree; the call uses the first.".to_string());
+            }
+            *first
+        }
+    };
+    // B SNPs are read on the alleles that are not O
+    let b_copies = b_copies.min(non_o);
+    let a_copies = non_o - b_copies;
+
+    let genotype = "A".repeat(a_copies) + &"B".repeat(b_copies) + &"O".repeat(o_copies.min(2));
+    let group = match (a_copies > 0, b_copies > 0) {
+        (true, true) => AboGroup::AB,
+        (true, false) => AboGroup::A,
+        (false, true) => AboGroup::B,
+        (false, false) => AboGroup::O,
+    };
+    if group == AboGroup::A {
+        notes.push("A1 and A2 subgroups are not told apart.".to_string());
+    }
+    Some(AboCall {
+        group,
+        genotype,
+        confidence: round(confidence),
+        markers,
+        notes,
+    })
+}
+
+fn call_rh(genome: &LoadedGenome, missing: &mut Vec<String>) -> Option<RhCall> {
+    let Some(&(_, start, end)) = RHD_REGIONS
+        .iter()
+        .find(|(build, _, _)| genome.file.genome_build == Some(*build))
+    else {
+        missing.push(
+            "RhD: the genome build is unknown, so the RHD gene cannot be located".to_string(),
+        );
+        return None;
+    };
+    let region = Region::new("1", start, end);
+    let probes: Vec<&Variant> = genome
+        .variants()
+        .iter()
+        .filter(|variant| region.contains(variant))
+        .collect();
+    let total = probes.len();
+    let called = probes
+        .iter()
+        .filter(|variant| !variant.genotype.is_no_call())
+        .count();
+    if total < MIN_RHD_PROBES {
+        missing.push(format!(
+            "RhD: the data has {} of the {} probes inside RHD needed",
+            total, MIN_RHD_PROBES
+        ));
+        return None;
+    }
+    let (d_positive, confidence, note) = if called == 0 {
+        (
+            false,
+            0.6,
+            "Every probe inside RHD failed, as it does when both copies of the gene are deleted.",
+        )
+    } else if called * 2 > total {
+        (
+            true,
+            0.7,
+            "Probes inside RHD were genotyped, so at least one copy of the gene is present. Weak and partial D variants are not detected.",
+        )
+    } else {
+        missing.push(
+            "RhD: probes inside RHD partly failed, which reads neither as present nor deleted"
+                .to_string(),
+        );
+        return None;
+    };
+    Some(RhCall {
+        d_positive,
+        confidence,
+        probes_called: called,
+        probes_total: total,
+        note: note.to_string(),
+    })
+}
+
+fn call_antigen(genome: &LoadedGenome, marker: &AntigenMarker) -> Option<AntigenCall> {
+    let variant = called(genome.get_by_rsid(marker.rsid))?;
+    let [(first_allele, first), (second_allele, second)] = marker.alleles;
+    let first_copies = variant
+        .genotype
+        .biallelic_count(first_allele, second_allele)?;
+    let mut second_copies = variant.genotype.alleles().len() - first_copies;
+    let mut confidence: f64 = 0.85;
+    if let Some((rsid, allele, other)) = marker.silencer {
+        match called(genome.get_by_rsid(rsid)) {
+            Some(silencer) => {
+                let silenced = silencer
+                    .genotype
+                    .biallelic_count(allele, other)
+                    .unwrap_or(0);
+                second_copies = second_copies.saturating_sub(silenced);
+            }
+            None => confidence -= 0.15,
+        }
+    }
+    if variant.is_imputed() {
+        confidence *= IMPUTED_PENALTY;
+    }
+    let sign = |copies: usize| if copies > 0 { "+" } else { "-" };
+    let phenotype = match marker.notation {
+        Notation::Bracketed(prefix) => format!(
+            "{}({}{}{}{})",
+            prefix,
+            first,
+            sign(first_copies),
+            second,
+            sign(second_copies)
+        ),
+        Notation::Plain => format!(
+            "{}{}{}{}",
+            first,
+            sign(first_copies),
+            second,
+            sign(second_copies)
+        ),
+    };
+    Some(AntigenCall {
+        system: marker.system.to_string(),
+        gene: marker.gene.to_string(),
+        phenotype,
+        marker: marker.rsid.to_string(),
+        genotype: variant.genotype.to_string(),
+        confidence: round(confidence),
+    })
+}
+
+/// An ABO marker by rsid, or by its position in the genome's build
+fn abo_variant<'a>(genome: &'a LoadedGenome, rsid: &str, index: usize) -> Option<&'a Variant> {
+    called(genome.get_by_rsid(rsid).or_else(|| {
+        ABO_POSITIONS
+            .iter()
+            .find(|(build, _)| genome.file.genome_build == Some(*build))
+            .and_then(|(_, positions)| genome.get_at("9", positions[index]))
+    }))
+}
+
+/// Copies of the O allele deletion: D in array calls, and the shorter
+/// allele of a VCF record
+fn o_copies(variant: &Variant) -> Option<usize> {
+    let shortest = variant
+        .reference
+        .iter()
+        .chain(&variant.alternates)
+        .map(String::len)
+        .min();
+    let longest = variant
+        .reference
+        .iter()
+        .chain(&variant.alternates)
+        .map(String::len)
+        .max();
+    variant
+        .genotype
+        .alleles()
+        .iter()
+        .map(|allele| match *allele {
+            "D" => Some(true),
+            "I" => Some(false),
+            _ if shortest != longest => Some(Some(allele.len()) == shortest),
+            _ => None,
+        })
+        .try_fold(0, |copies, is_o| Some(copies + usize::from(is_o?)))
+}
+
+fn called(variant: Option<&Variant>) -> Option<&Variant> {
+    variant.filter(|variant| !variant.genotype.is_no_call())
+}
+
+fn round(confidence: f64) -> f64 {
+    (confidence.clamp(0.0, 1.0) * 100.0).round() / 100.0
+}
@@ -0,0 +1,200 @@
+//! Carrier screening for recessive conditions
+//!
+//! One pathogenic variant in a gene for a recessive condition makes
+//! someone a carrier, not affected. This module groups ClinVar matches in
+//! such genes and works out from zygosity whether the genome carries one
+//! copy, two copies, or on the X chromosome a single hemizygous copy.
+//!
+//! Genes on the ACMG secondary findings list are left to
+//! [`acmg`](super::acmg), whose findings are only reported on request.
+
+use super::clinvar::ClinVarMatch;
+use crate::genome::Genotype;
+use serde::{Deserialize, Serialize};
+
+/// What every carrier result, positive or negative, has to be read with
+pub const RESIDUAL_RISK: &str = "Genotyping covers only some of the known disease variants in each gene, and not finding one does not rule out being a carrier. Carrier screening for family planning should be confirmed by a clinical laboratory.";
+
+/// How a condition is inherited
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CarrierInheritance {
+    AutosomalRecessive,
+    XLinkedRecessive,
+}
+
+/// A gene screened for carrier status
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CarrierGene {
+    pub gene: &'static str,
+    pub condition: &'static str,
+    pub inheritance: CarrierInheritance,
+    /// Why detection is especially limited for this gene
+    pub caveat: Option<&'static str>,
+}
+
+/// Zygosity of the pathogenic variants found in one gene
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CarrierStatus {
+    /// One copy of one pathogenic variant
+    Carrier,
+    /// Two copies of the same pathogenic variant
+    Homozygous,
+    /// Two different pathogenic variants; affected if they sit on opposite
+    /// copies of the gene, which genotype data cannot tell
+    PossibleCompoundHeterozygous,
+    /// A pathogenic variant on the only X chromosome
+    Hemizygous,
+}
+
+impl CarrierStatus {
+    /// Genotypes expected to cause the condition
+    pub fn is_affected(&self) -> bool {
+        matches!(self, CarrierStatus::Homozygous | CarrierStatus::Hemizygous)
+    }
+}
+
+/// Pathogenic variants found in one carrier screening gene
+#[derive(Debug, Clone)]
+pub struct CarrierResult<'a> {
+    pub gene: &'static CarrierGene,
+    pub status: CarrierStatus,
+    pub matches: Vec<ClinVarMatch<'a>>,
+}
+
+const fn recessive(gene: &'static str, condition: &'static str) -> CarrierGene {
+    CarrierGene {
+        gene,
+        condition,
+        inheritance: CarrierInheritance::AutosomalRecessive,
+        caveat: None,
+    }
+}
+
+const fn x_linked(gene: &'static str, condition: &'static str) -> CarrierGene {
+    CarrierGene {
+        gene,
+        condition,
+        inheritance: CarrierInheritance::XLinkedRecessive,
+        caveat: None,
+    }
+}
+
+const DELETIONS: &str =
+    "Most disease alleles of this gene are deletions, which SNP data cannot detect.";
+
+/// Genes screened for carrier status
+pub const GENES: [CarrierGene; 31] = [
+    CarrierGene {
+        caveat: Some("More than 2,000 CFTR variants are known and genotyping arrays test few of them; detection is lowest outside European ancestry."),
+        ..recessive("CFTR", "Cystic fibrosis")
+    },
+    CarrierGene {
+        caveat: Some("Beta thalassemia variants differ between populations and many are not on consumer arrays."),
+        ..recessive("HBB", "Sickle cell disease and beta thalassemia")
+    },
+    CarrierGene {
+        caveat: Some(DELETIONS),
+        ..recessive("HBA1", "Alpha thalassemia")
+    },
+    CarrierGene {
+        caveat: Some(DELETIONS),
+        ..recessive("HBA2", "Alpha thalassemia")
+    },
+    CarrierGene {
+        caveat: Some("Outside Ashkenazi Jewish ancestry many carriers are only found by enzyme testing."),
+        ..recessive("HEXA", "Tay-Sachs disease")
+    },
+    CarrierGene {
+        caveat: Some("Most carriers have one deleted copy of SMN1, which SNP data cannot detect."),
+        ..recessive("SMN1", "Spinal muscular atrophy")
+    },
+    recessive("GBA1", "Gaucher disease"),
+    recessive("ASPA", "Canavan disease"),
+    recessive("FANCC", "Fanconi anemia type C"),
+    recessive("BLM", "Bloom syndrome"),
+    recessive("ELP1", "Familial dysautonomia"),
+    recessive("MCOLN1", "Mucolipidosis type IV"),
+    recessive("SMPD1", "Niemann-Pick disease types A and B"),
+    recessive("PAH", "Phenylketonuria"),
+    recessive("GALT", "Classic galactosemia"),
+    recessive("ACADM", "Medium-chain acyl-CoA dehydrogenase deficiency"),
+    recessive("G6PC1", "Glycogen storage disease type Ia"),
+    recessive("DHCR7", "Smith-Lemli-Opitz syndrome"),
+    CarrierGene {
+        caveat: Some("Common CYP21A2 variants arise from its pseudogene CYP21A1P and are often miscalled by arrays."),
+        ..recessive("CYP21A2", "Congenital adrenal hyperplasia")
+    },
+    recessive("SERPINA1", "Alpha-1 antitrypsin deficiency"),
+    recessive("GJB2", "GJB2-related hearing loss"),
+    recessive("SLC26A4", "Pendred syndrome"),
+    recessive("MEFV", "Familial Mediterranean fever"),
+    recessive("PKHD1", "Autosomal recessive polycystic kidney disease"),
+    recessive("ABCA4", "Stargardt disease"),
+    recessive("USH2A", "Usher syndrome type 2A"),
+    x_linked("DMD", "Duchenne and Becker muscular dystrophy"),
+    x_linked("F8", "Hemophilia A"),
+    x_linked("F9", "Hemophilia B"),
+    x_linked("G6PD", "G6PD deficiency"),
+    CarrierGene {
+        caveat: Some("Fragile X is caused by a repeat expansion, which SNP data cannot detect."),
+        ..x_linked("FMR1", "Fragile X syndrome")
+    },
+];
+
+/// The carrier screening entry for a gene symbol
+pub fn gene(symbol: &str) -> Option<&'static CarrierGene> {
+    GENES
+        .iter()
+        .find(|entry| entry.gene.eq_ignore_ascii_case(symbol))
+}
+
+/// Carrier screening gene a match falls in, if it is pathogenic
+pub fn carrier_gene(found: &ClinVarMatch<'_>) -> Option<&'static CarrierGene> {
+    if !found.record.significance.is_pathogenic() {
+        return None;
+    }
+    found.record.genes.iter().find_map(|symbol| gene(symbol))
+}
+
+/// Carrier status in every screened gene with a pathogenic variant
+pub fn screen<'a>(matches: &[ClinVarMatch<'a>]) -> Vec<CarrierResult<'a>> {
+    let mut grouped: Vec<(&'static CarrierGene, Vec<ClinVarMatch<'a>>)> = Vec::new();
+    for found in matches {
+        let Some(gene) = carrier_gene(found) else {
+            continue;
+        };
+        match grouped
+            .iter_mut()
+            .find(|(entry, _)| entry.gene == gene.gene)
+        {
+            Some((_, found_in_gene)) => found_in_gene.push(*found),
+            None => grouped.push((gene, vec![*found])),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(gene, matches)| {
+            let hemizygous = gene.inheritance == CarrierInheritance::XLinkedRecessive
+                && matches
+                    .iter()
+                    .any(|found| matches!(found.variant.genotype, Genotype::Haploid(_)));
+            let status = if hemizygous {
+                CarrierStatus::Hemizygous
+            } else if matches.iter().any(|found| found.alternate_copies > 1) {
+                CarrierStatus::Homozygous
+            } else if matches.len() > 1 {
+                CarrierStatus::PossibleCompoundHeterozygous
+            } else {
+                CarrierStatus::Carrier
+            };
+            CarrierResult {
+                gene,
+                status,
+                matches,
+            }
+        })
+        .collect()
+}
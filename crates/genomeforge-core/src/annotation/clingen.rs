@@ -0,0 +1,249 @@
+//! ClinGen dosage sensitivity map
+//!
+//! ClinGen scores genes and recurrent regions for the evidence that losing
+//! a copy (haploinsufficiency) or gaining one (triplosensitivity) causes
+//! disease, and publishes its gene and region curation lists as TSV files
+//! led by `#` comments naming the build. A genome's deletions are matched
+//! against the haploinsufficiency scores of the genes and regions they
+//! overlap, and its duplications against the triplosensitivity scores.
+
+use super::tsv::TsvReader;
+use crate::genome::{GenomeBuild, Region, StructuralKind, StructuralVariant};
+use crate::parser::{compression, detect_genome_build};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Cursor, Read};
+use std::path::Path;
+
+/// Columns both curation lists have
+const REQUIRED_COLUMNS: [&str; 3] = [
+    "Genomic Location",
+    "Haploinsufficiency Score",
+    "Triplosensitivity Score",
+];
+
+/// Columns naming a gene or a region, in the gene and region lists
+const NAME_COLUMNS: [&str; 2] = ["Gene Symbol", "ISCA Region Name"];
+
+/// A ClinGen dosage sensitivity score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DosageScore {
+    /// 0: no evidence available
+    NoEvidence,
+    /// 1: little evidence for dosage pathogenicity
+    Little,
+    /// 2: some evidence for dosage pathogenicity
+    Emerging,
+    /// 3: sufficient evidence for dosage pathogenicity
+    Sufficient,
+    /// 30: gene associated with autosomal recessive phenotype
+    AutosomalRecessive,
+    /// 40: dosage sensitivity unlikely
+    Unlikely,
+}
+
+impl DosageScore {
+    /// Parse a score as the curation lists write it
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim() {
+            "0" => Some(DosageScore::NoEvidence),
+            "1" => Some(DosageScore::Little),
+            "2" => Some(DosageScore::Emerging),
+            "3" => Some(DosageScore::Sufficient),
+            "30" => Some(DosageScore::AutosomalRecessive),
+            "40" => Some(DosageScore::Unlikely),
+            _ => None,
+        }
+    }
+
+    /// Whether there is some or sufficient evidence that the dosage
+    /// change causes disease
+    pub fn is_sensitive(self) -> bool {
+        matches!(self, DosageScore::Emerging | DosageScore::Sufficient)
+    }
+}
+
+/// A gene or region ClinGen scored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DosageRegion {
+    /// Gene symbol, or region name such as "1p36 microdeletion region"
+    pub name: String,
+    pub region: Region,
+    pub haploinsufficiency: Option<DosageScore>,
+    pub triplosensitivity: Option<DosageScore>,
+    /// MONDO id of the disease the loss of a copy causes
+    pub haploinsufficiency_disease: Option<String>,
+    /// MONDO id of the disease the gain of a copy causes
+    pub triplosensitivity_disease: Option<String>,
+}
+
+/// A structural variant overlapping a dosage-sensitive gene or region
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DosageMatch {
+    pub variant: StructuralVariant,
+    pub region: DosageRegion,
+    /// Haploinsufficiency score for a deletion, triplosensitivity score
+    /// for a duplication
+    pub score: DosageScore,
+    pub disease: Option<String>,
+    /// Share of the gene or region the variant covers, 0.0 - 1.0
+    pub overlap: f64,
+}
+
+impl DosageMatch {
+    /// Whether the variant covers the whole gene or region
+    pub fn is_whole(&self) -> bool {
+        self.overlap >= 1.0
+    }
+}
+
+/// Scored genes and regions by chromosome
+#[derive(Debug, Default)]
+pub struct ClinGenDatabase {
+    /// Build of the coordinates, from the comment lines
+    build: Option<GenomeBuild>,
+    /// Per chromosome, sorted by start
+    regions: HashMap<String, Vec<DosageRegion>>,
+}
+
+impl ClinGenDatabase {
+    /// Load a gene or region curation list, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Read a curation list from any buffered reader
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        // Comment lines lead up to the header, which starts with # as well
+        let mut comments = Vec::new();
+        let header = loop {
+            let mut line = String::new();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read ClinGen dosage map: {}", e))?;
+            if read == 0 {
+                return Err("Not a ClinGen dosage map: no header row".to_string());
+            }
+            let trimmed = line.trim_start_matches('#');
+            if NAME_COLUMNS.iter().any(|name| trimmed.starts_with(name)) {
+                break line;
+            }
+            comments.push(line);
+        };
+        let mut reader = TsvReader::new(Cursor::new(header).chain(reader))?;
+        reader
+            .require_columns(&REQUIRED_COLUMNS)
+            .map_err(|e| format!("Not a ClinGen dosage map: {}", e))?;
+        let name_column = NAME_COLUMNS
+            .into_iter()
+            .find(|name| reader.has_column(name))
+            .ok_or("Not a ClinGen dosage map: no gene or region names")?;
+
+        let mut database = ClinGenDatabase {
+            build: detect_genome_build(&comments),
+            regions: HashMap::new(),
+        };
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("ClinGen dosage map {}", e))?;
+            // Genes ClinGen has not placed are listed as "tbd"
+            let Some(region) = row
+                .get("Genomic Location")
+                .filter(|location| location.contains(':'))
+                .and_then(|location| location.parse::<Region>().ok())
+            else {
+                continue;
+            };
+            let score = |column: &str| row.get(column).and_then(DosageScore::parse);
+            let disease = |column: &str| row.get(column).map(str::to_string);
+            database
+                .regions
+                .entry(region.chromosome.clone())
+                .or_default()
+                .push(DosageRegion {
+                    name: row.require(name_column)?.to_string(),
+                    region,
+                    haploinsufficiency: score("Haploinsufficiency Score"),
+                    triplosensitivity: score("Triplosensitivity Score"),
+                    haploinsufficiency_disease: disease("Haploinsufficiency Disease ID"),
+                    triplosensitivity_disease: disease("Triplosensitivity Disease ID"),
+                });
+        }
+        for regions in database.regions.values_mut() {
+            regions.sort_by_key(|scored| scored.region.start);
+        }
+        Ok(database)
+    }
+
+    /// Number of genes and regions with a location
+    pub fn len(&self) -> usize {
+        self.regions.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Build the coordinates are on, when the comment lines name it
+    pub fn build(&self) -> Option<GenomeBuild> {
+        self.build
+    }
+
+    /// Genes and regions overlapping a stretch of a chromosome
+    pub fn overlapping(&self, region: &Region) -> impl Iterator<Item = &DosageRegion> {
+        let start = region.start;
+        let scored = self
+            .regions
+            .get(&region.chromosome)
+            .map_or(&[][..], Vec::as_slice);
+        let before_end = scored.partition_point(|scored| scored.region.start <= region.end);
+        scored[..before_end]
+            .iter()
+            .filter(move |scored| scored.region.end >= start)
+    }
+
+    /// Deletions and duplications the sample carries that overlap a gene
+    /// or region sensitive to them
+    ///
+    /// Losing both copies of a gene associated with a recessive phenotype
+    /// is matched too.
+    pub fn annotate(&self, variants: &[StructuralVariant]) -> Vec<DosageMatch> {
+        let mut matches = Vec::new();
+        for variant in variants.iter().filter(|variant| variant.is_carried()) {
+            let region = variant.region();
+            for scored in self.overlapping(&region) {
+                let (score, disease) = match variant.kind {
+                    StructuralKind::Deletion => (
+                        scored.haploinsufficiency,
+                        &scored.haploinsufficiency_disease,
+                    ),
+                    StructuralKind::Duplication => {
+                        (scored.triplosensitivity, &scored.triplosensitivity_disease)
+                    }
+                    _ => break,
+                };
+                let Some(score) = score else {
+                    continue;
+                };
+                let recessive_loss = score == DosageScore::AutosomalRecessive
+                    && variant.kind == StructuralKind::Deletion
+                    && variant.is_homozygous();
+                if !score.is_sensitive() && !recessive_loss {
+                    continue;
+                }
+                let covered =
+                    region.end.min(scored.region.end) - region.start.max(scored.region.start) + 1;
+                let length = scored.region.end - scored.region.start + 1;
+                matches.push(DosageMatch {
+                    variant: variant.clone(),
+                    region: scored.clone(),
+                    score,
+                    disease: disease.clone(),
+                    overlap: covered as f64 / length as f64,
+                });
+            }
+        }
+        matches
+    }
+}
@@ -0,0 +1,655 @@
+//! ClinVar clinical significance annotation
+//!
+//! Loads an offline ClinVar release, either the `clinvar.vcf.gz` VCF or the
+//! `variant_summary.txt.gz` TSV, into memory and indexes it by rsid and by
+//! (build, chromosome, position, ref, alt). Array exports without alleles
+//! are matched by rsid; VCF inputs are matched by allele first.
+
+use super::strand::{self, Strand};
+use super::tsv::TsvReader;
+use super::{alternate_copies, format_file_date, is_allele_sequence, normalize_rsid};
+use crate::genome::{GenomeBuild, Variant};
+use crate::parallel;
+use crate::parser::vcf::{VcfReader, VcfRecord};
+use crate::parser::{compression, detect_genome_build, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::ops::Range;
+use std::path::Path;
+
+/// Columns of `variant_summary.txt` needed to build a record
+const SUMMARY_COLUMNS: [&str; 7] = [
+    "Assembly",
+    "Chromosome",
+    "ReviewStatus",
+    "PhenotypeList",
+    "RS# (dbSNP)",
+    "GeneSymbol",
+    "VariationID",
+];
+
+/// Condition names ClinVar uses when no condition was asserted
+const PLACEHOLDER_CONDITIONS: [&str; 2] = ["not provided", "not specified"];
+
+/// Germline classification of a variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClinicalSignificance {
+    Pathogenic,
+    LikelyPathogenic,
+    RiskFactor,
+    DrugResponse,
+    Conflicting,
+    UncertainSignificance,
+    LikelyBenign,
+    Benign,
+    Other,
+}
+
+impl ClinicalSignificance {
+    /// Parse a ClinVar classification such as "Pathogenic/Likely_pathogenic"
+    ///
+    /// Combined classifications map to the less certain term, so
+    /// "Pathogenic/Likely pathogenic" is reported as likely pathogenic.
+    pub fn parse(raw: &str) -> ClinicalSignificance {
+        let text = raw.replace('_', " ").to_ascii_lowercase();
+        let primary = text.split([',', ';', '|']).next().unwrap_or("").trim();
+
+        if primary.starts_with("conflicting") {
+            ClinicalSignificance::Conflicting
+        } else if primary.contains("likely pathogenic") {
+            ClinicalSignificance::LikelyPathogenic
+        } else if primary.starts_with("pathogenic") {
+            ClinicalSignificance::Pathogenic
+        } else if primary.starts_with("uncertain") {
+            ClinicalSignificance::UncertainSignificance
+        } else if primary.contains("likely benign") {
+            ClinicalSignificance::LikelyBenign
+        } else if primary.starts_with("benign") {
+            ClinicalSignificance::Benign
+        } else if primary.starts_with("drug response") {
+            ClinicalSignificance::DrugResponse
+        } else if primary.starts_with("risk factor") || primary.starts_with("association") {
+            ClinicalSignificance::RiskFactor
+        } else {
+            ClinicalSignificance::Other
+        }
+    }
+
+    /// Human readable label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClinicalSignificance::Pathogenic => "Pathogenic",
+            ClinicalSignificance::LikelyPathogenic => "Likely pathogenic",
+            ClinicalSignificance::RiskFactor => "Risk factor",
+            ClinicalSignificance::DrugResponse => "Drug response",
+            ClinicalSignificance::Conflicting => "Conflicting interpretations",
+            ClinicalSignificance::UncertainSignificance => "Uncertain significance",
+            ClinicalSignificance::LikelyBenign => "Likely benign",
+            ClinicalSignificance::Benign => "Benign",
+            ClinicalSignificance::Other => "Other",
+        }
+    }
+
+    /// Pathogenic or likely pathogenic
+    pub fn is_pathogenic(&self) -> bool {
+        matches!(
+            self,
+            ClinicalSignificance::Pathogenic | ClinicalSignificance::LikelyPathogenic
+        )
+    }
+
+    /// Benign or likely benign
+    pub fn is_benign(&self) -> bool {
+        matches!(
+            self,
+            ClinicalSignificance::Benign | ClinicalSignificance::LikelyBenign
+        )
+    }
+}
+
+/// How thoroughly a classification was reviewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewStatus {
+    PracticeGuideline,
+    ExpertPanel,
+    MultipleSubmitters,
+    SingleSubmitter,
+    ConflictingClassifications,
+    NoAssertionCriteria,
+    NoClassification,
+}
+
+impl ReviewStatus {
+    /// Parse a ClinVar review status such as
+    /// "criteria_provided,_multiple_submitters,_no_conflicts"
+    pub fn parse(raw: &str) -> ReviewStatus {
+        let text = raw.replace('_', " ").to_ascii_lowercase();
+        if text.contains("practice guideline") {
+            ReviewStatus::PracticeGuideline
+        } else if text.contains("expert panel") {
+            ReviewStatus::ExpertPanel
+        } else if text.contains("multiple submitters") {
+            ReviewStatus::MultipleSubmitters
+        } else if text.contains("conflicting") {
+            ReviewStatus::ConflictingClassifications
+        } else if text.contains("single submitter") {
+            ReviewStatus::SingleSubmitter
+        } else if text.contains("no assertion criteria") {
+            ReviewStatus::NoAssertionCriteria
+        } else {
+            ReviewStatus::NoClassification
+        }
+    }
+
+    /// ClinVar's zero to four star rating
+    pub fn stars(&self) -> u8 {
+        match self {
+            ReviewStatus::PracticeGuideline => 4,
+            ReviewStatus::ExpertPanel => 3,
+            ReviewStatus::MultipleSubmitters => 2,
+            ReviewStatus::SingleSubmitter | ReviewStatus::ConflictingClassifications => 1,
+            ReviewStatus::NoAssertionCriteria | ReviewStatus::NoClassification => 0,
+        }
+    }
+
+    /// Human readable label
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewStatus::PracticeGuideline => "Practice guideline",
+            ReviewStatus::ExpertPanel => "Reviewed by expert panel",
+            ReviewStatus::MultipleSubmitters => "Multiple submitters, no conflicts",
+            ReviewStatus::SingleSubmitter => "Single submitter",
+            ReviewStatus::ConflictingClassifications => "Conflicting classifications",
+            ReviewStatus::NoAssertionCriteria => "No assertion criteria provided",
+            ReviewStatus::NoClassification => "No classification provided",
+        }
+    }
+}
+
+/// One classified allele from a ClinVar release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClinVarRecord {
+    pub variation_id: Option<u64>,
+    pub rsid: Option<String>,
+    pub genome_build: Option<GenomeBuild>,
+    /// Normalized chromosome name
+    pub chromosome: String,
+    pub position: u64,
+    pub reference: String,
+    pub alternate: String,
+    pub genes: Vec<String>,
+    pub significance: ClinicalSignificance,
+    /// Classification as written in the release, e.g. "Pathogenic, low penetrance"
+    pub significance_label: String,
+    pub review_status: ReviewStatus,
+    pub conditions: Vec<String>,
+    /// Ontology and database terms for the conditions, e.g. "MONDO:0011450"
+    /// or "OMIM:604370"
+    #[serde(default)]
+    pub condition_ids: Vec<String>,
+    /// Classifications submitted, with how many submitters gave each, when
+    /// the submissions conflict; e.g. "Pathogenic (3)"
+    #[serde(default)]
+    pub conflicting_classifications: Vec<String>,
+    /// Submitters of a classification, from the variant summary
+    #[serde(default)]
+    pub submitter_count: Option<u32>,
+    /// When the classification was last evaluated, as written in the
+    /// variant summary
+    #[serde(default)]
+    pub last_evaluated: Option<String>,
+}
+
+/// A ClinVar record matched against a genotype
+#[derive(Debug, Clone, Copy)]
+pub struct ClinVarMatch<'a> {
+    pub record: &'a ClinVarRecord,
+    pub variant: &'a Variant,
+    /// Copies of the classified allele carried (1 or 2)
+    pub alternate_copies: usize,
+    /// Strand the record's alleles were read from
+    pub strand: Strand,
+}
+
+type AlleleKey = (GenomeBuild, String, u64, String, String);
+
+/// Indexed ClinVar release
+#[derive(Debug, Default)]
+pub struct ClinVarDatabase {
+    records: Vec<ClinVarRecord>,
+    by_rsid: HashMap<String, Vec<usize>>,
+    by_allele: HashMap<AlleleKey, Vec<usize>>,
+    release_date: Option<String>,
+}
+
+impl ClinVarDatabase {
+    /// Load a ClinVar VCF or `variant_summary` TSV, gzipped or not
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (mut reader, _) = compression::open_reader(path)?;
+        let is_vcf = reader
+            .fill_buf()
+            .map_err(|e| format!("Failed to read ClinVar file: {}", e))?
+            .starts_with(b"##fileformat=VCF");
+
+        if is_vcf {
+            Self::from_vcf(reader)
+        } else {
+            Self::from_tsv(reader)
+        }
+    }
+
+    /// Load the `clinvar.vcf` release
+    pub fn from_vcf<R: BufRead>(reader: R) -> Result<Self, String> {
+        let reader = VcfReader::new(reader)?;
+        let header = reader.header();
+        let meta: Vec<String> = header
+            .other
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        let build = detect_genome_build(&meta);
+        let release_date = header
+            .other
+            .iter()
+            .find(|(key, _)| key == "fileDate")
+            .map(|(_, date)| format_file_date(date));
+
+        let mut records = Vec::new();
+        for record in reader {
+            let record = record.map_err(|e| format!("ClinVar {}", e))?;
+            records.extend(vcf_records(&record, build));
+        }
+
+        Ok(Self::from_records(records, release_date))
+    }
+
+    /// Load the `variant_summary.txt` release
+    pub fn from_tsv<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&SUMMARY_COLUMNS)
+            .map_err(|e| format!("Not a ClinVar variant summary: {}", e))?;
+        let significance_column = ["ClinicalSignificance", "GermlineClassification"]
+            .into_iter()
+            .find(|column| reader.has_column(column))
+            .ok_or("Not a ClinVar variant summary: Missing columns: ClinicalSignificance")?;
+
+        let mut records = Vec::new();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("ClinVar {}", e))?;
+
+            let build = match row.get("Assembly") {
+                Some("GRCh38") => GenomeBuild::GRCh38,
+                Some("GRCh37") => GenomeBuild::GRCh37,
+                Some("NCBI36") => GenomeBuild::GRCh36,
+                _ => continue,
+            };
+            // Newer releases add VCF-style normalized alleles
+            let (position, reference, alternate) = match (
+                row.get("PositionVCF"),
+                row.get("ReferenceAlleleVCF"),
+                row.get("AlternateAlleleVCF"),
+            ) {
+                (Some(pos), Some(reference), Some(alt)) => (pos, reference, alt),
+                _ => match (
+                    row.get("Start"),
+                    row.get("ReferenceAllele"),
+                    row.get("AlternateAllele"),
+                ) {
+                    (Some(pos), Some(reference), Some(alt)) => (pos, reference, alt),
+                    _ => continue,
+                },
+            };
+            let Ok(position) = position.parse::<u64>() else {
+                continue;
+            };
+            if !is_allele_sequence(reference) || !is_allele_sequence(alternate) {
+                continue;
+            }
+            let Some(significance_label) = row.get(significance_column) else {
+                continue;
+            };
+
+            records.push(ClinVarRecord {
+                variation_id: row.get("VariationID").and_then(|id| id.parse().ok()),
+                rsid: row.get("RS# (dbSNP)").and_then(normalize_rsid),
+                genome_build: Some(build),
+                chromosome: normalize_chromosome(row.require("Chromosome")?),
+                position,
+                reference: reference.to_ascii_uppercase(),
+                alternate: alternate.to_ascii_uppercase(),
+                genes: split_names(row.get("GeneSymbol").unwrap_or(""), &[';']),
+                significance: ClinicalSignificance::parse(significance_label),
+                significance_label: significance_label.to_string(),
+                review_status: ReviewStatus::parse(row.get("ReviewStatus").unwrap_or("")),
+                conditions: split_names(row.get("PhenotypeList").unwrap_or(""), &['|', ';']),
+                condition_ids: condition_ids(row.get("PhenotypeIDS").unwrap_or("")),
+                conflicting_classifications: Vec::new(),
+                submitter_count: row.get("NumberSubmitters").and_then(|n| n.parse().ok()),
+                last_evaluated: row
+                    .get("LastEvaluated")
+                    .filter(|date| !date.is_empty() && *date != "-")
+                    .map(str::to_string),
+            });
+        }
+
+        Ok(Self::from_records(records, None))
+    }
+
+    /// Index records that were already parsed
+    pub fn from_records(records: Vec<ClinVarRecord>, release_date: Option<String>) -> Self {
+        let mut by_rsid: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_allele: HashMap<AlleleKey, Vec<usize>> = HashMap::new();
+
+        for (index, record) in records.iter().enumerate() {
+            if let Some(rsid) = &record.rsid {
+                by_rsid.entry(rsid.clone()).or_default().push(index);
+            }
+            if let Some(build) = record.genome_build {
+                let key = (
+                    build,
+                    record.chromosome.clone(),
+                    record.position,
+                    record.reference.clone(),
+                    record.alternate.clone(),
+                );
+                by_allele.entry(key).or_default().push(index);
+            }
+        }
+
+        Self {
+            records,
+            by_rsid,
+            by_allele,
+            release_date,
+        }
+    }
+
+    /// Add the site of every record to `sites`
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for record in &self.records {
+            if let Some(rsid) = &record.rsid {
+                sites.add_rsid(rsid);
+            }
+            sites.add_position(&record.chromosome, record.position);
+        }
+    }
+
+    /// Every classified allele, in release order
+    pub fn records(&self) -> &[ClinVarRecord] {
+        &self.records
+    }
+
+    /// Number of classified alleles
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether the release holds no records
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Release date from the VCF header, as YYYY-MM-DD
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    /// Records for an rsid, across all alleles and builds
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<&ClinVarRecord> {
+        self.collect(self.by_rsid.get(rsid))
+    }
+
+    /// Records for one allele on a given build
+    pub fn lookup_allele(
+        &self,
+        build: GenomeBuild,
+        chromosome: &str,
+        position: u64,
+        reference: &str,
+        alternate: &str,
+    ) -> Vec<&ClinVarRecord> {
+        let key = (
+            build,
+            normalize_chromosome(chromosome),
+            position,
+            reference.to_ascii_uppercase(),
+            alternate.to_ascii_uppercase(),
+        );
+        self.collect(self.by_allele.get(&key))
+    }
+
+    /// Match every variant of a genome carrying a classified allele
+    ///
+    /// `checkpoint` is called every [`CHECKPOINT_INTERVAL`] variants and
+    /// stops the annotation when it returns an error.
+    pub fn annotate<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<Vec<ClinVarMatch<'a>>, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        self.annotate_range(genome, 0..genome.len(), &mut checkpoint)
+    }
+
+    /// Like [`ClinVarDatabase::annotate`], on up to `threads` threads with
+    /// the genome sharded by chromosome
+    pub fn annotate_parallel<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        threads: usize,
+        checkpoint: F,
+    ) -> Result<Vec<ClinVarMatch<'a>>, String>
+    where
+        F: Fn(usize) -> Result<(), String> + Sync,
+    {
+        let shards = parallel::chromosome_shards(genome.variants());
+        let matches = parallel::map(&shards, threads, |shard| {
+            self.annotate_range(genome, shard, &mut |index| checkpoint(index))
+        })?;
+        Ok(matches.into_iter().flatten().collect())
+    }
+
+    /// Match the variants of a genome with these indexes
+    fn annotate_range<'a>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        range: Range<usize>,
+        checkpoint: &mut dyn FnMut(usize) -> Result<(), String>,
+    ) -> Result<Vec<ClinVarMatch<'a>>, String> {
+        let build = genome.file.genome_build;
+        let mut matches = Vec::new();
+
+        for (index, variant) in genome.variants()[range.clone()]
+            .iter()
+            .enumerate()
+            .map(|(offset, variant)| (range.start + offset, variant))
+        {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            if variant.genotype.is_no_call() {
+                continue;
+            }
+            let found = matches.len();
+
+            if let (Some(reference), Some(build)) = (&variant.reference, build) {
+                let mut alternates: Vec<&str> = variant.genotype.alleles();
+                alternates.retain(|allele| allele != reference);
+                alternates.dedup();
+                for alternate in alternates {
+                    let records = self.lookup_allele(
+                        build,
+                        &variant.chromosome,
+                        variant.position,
+                        reference,
+                        alternate,
+                    );
+                    matches.extend(records.into_iter().map(|record| ClinVarMatch {
+                        record,
+                        variant,
+                        alternate_copies: variant.genotype.allele_count(alternate),
+                        strand: Strand::Forward,
+                    }));
+                }
+            }
+
+            // Fall back to the rsid when the alleles did not match, e.g. for
+            // array exports or a VCF on another build
+            if matches.len() == found {
+                if let Some(rsid) = &variant.rsid {
+                    let records = self.preferred_build(self.lookup_rsid(rsid), build);
+                    // Strand is judged over every allele of the rsid, so a
+                    // call of another ALT allele is not taken for a flip.
+                    // ClinVar alleles are on the plus strand, as are the
+                    // calls of most arrays, so A/T and C/G SNPs are taken as
+                    // reported.
+                    let alleles: Vec<&str> = records
+                        .iter()
+                        .flat_map(|record| [record.reference.as_str(), record.alternate.as_str()])
+                        .collect();
+                    let strand = match strand::resolve(variant, &alleles) {
+                        Strand::Ambiguous => Strand::Forward,
+                        strand => strand,
+                    };
+                    let mut seen = HashSet::new();
+                    for record in records {
+                        let copies = alternate_copies(
+                            variant,
+                            &strand.orient(&record.reference),
+                            &strand.orient(&record.alternate),
+                        )
+                        .unwrap_or(0);
+                        let first = record.variation_id.is_none_or(|id| seen.insert(id));
+                        if copies > 0 && first {
+                            matches.push(ClinVarMatch {
+                                record,
+                                variant,
+                                alternate_copies: copies,
+                                strand,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn collect(&self, indexes: Option<&Vec<usize>>) -> Vec<&ClinVarRecord> {
+        indexes
+            .map(|indexes| indexes.iter().map(|&i| &self.records[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Order records so those on the genome's build come first
+    fn preferred_build<'a>(
+        &self,
+        mut records: Vec<&'a ClinVarRecord>,
+        build: Option<GenomeBuild>,
+    ) -> Vec<&'a ClinVarRecord> {
+        records.sort_by_key(|record| record.genome_build != build);
+        records
+    }
+}
+
+// Helper functions
+
+/// One record per ALT allele of a ClinVar VCF line
+fn vcf_records(record: &VcfRecord, build: Option<GenomeBuild>) -> Vec<ClinVarRecord> {
+    let Some(significance_label) = record.info_value("CLNSIG") else {
+        return Vec::new();
+    };
+    let significance_label = significance_label.replace('_', " ");
+    let genes = record
+        .info_value("GENEINFO")
+        .map(|info| {
+            info.split('|')
+                .filter_map(|gene| gene.split(':').next())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let conditions = split_names(
+        &record.info_value("CLNDN").unwrap_or("").replace('_', " "),
+        &['|'],
+    );
+    let condition_ids = condition_ids(record.info_value("CLNDISDB").unwrap_or(""));
+    let conflicting_classifications: Vec<String> = record
+        .info_value("CLNSIGCONF")
+        .map(|conflicts| {
+            conflicts
+                .split('|')
+                .map(|conflict| conflict.replace('_', " ").replace('(', " ("))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    record
+        .alternates
+        .iter()
+        .filter(|alternate| is_allele_sequence(alternate))
+        .map(|alternate| ClinVarRecord {
+            variation_id: record.id.as_deref().and_then(|id| id.parse().ok()),
+            rsid: record.info_value("RS").and_then(normalize_rsid),
+            genome_build: build,
+            chromosome: normalize_chromosome(&record.chromosome),
+            position: record.position,
+            reference: record.reference.clone(),
+            alternate: alternate.to_ascii_uppercase(),
+            genes: Vec::clone(&genes),
+            significance: ClinicalSignificance::parse(&significance_label),
+            significance_label: significance_label.clone(),
+            review_status: ReviewStatus::parse(record.info_value("CLNREVSTAT").unwrap_or("")),
+            conditions: conditions.clone(),
+            condition_ids: condition_ids.clone(),
+            conflicting_classifications: conflicting_classifications.clone(),
+            submitter_count: None,
+            last_evaluated: None,
+        })
+        .collect()
+}
+
+/// Split a list of names, dropping ClinVar's placeholder entries
+fn split_names(raw: &str, separators: &[char]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in raw.split(separators).map(str::trim) {
+        let placeholder = PLACEHOLDER_CONDITIONS
+            .iter()
+            .any(|p| name.eq_ignore_ascii_case(p));
+        if !name.is_empty() && name != "-" && !placeholder && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Condition terms of `CLNDISDB` or `PhenotypeIDS`, one group per condition,
+/// with the doubled prefixes ClinVar writes removed: "MONDO:MONDO:0011450"
+/// becomes "MONDO:0011450" and "Human_Phenotype_Ontology:HP:0001250"
+/// becomes "HP:0001250"
+fn condition_ids(raw: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for term in raw.split(['|', ';', ',']).map(str::trim) {
+        let Some((database, id)) = term.split_once(':') else {
+            continue;
+        };
+        let id = if id.contains(':') {
+            id.to_string()
+        } else {
+            format!("{}:{}", database, id)
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
@@ -0,0 +1,423 @@
+//! ClinVar releases stored on disk with sorted indexes
+//!
+//! A full ClinVar release indexed in memory takes over a gigabyte. A
+//! [`ClinVarStore`] keeps the records in one file instead, with two sorted
+//! indexes, one by (build, chromosome, position) and one by rsid. A lookup
+//! is a binary search over an index, so it reads O(log n) entries from
+//! disk and memory use does not grow with the release.
+//! [`ClinVarStore::subset`] gathers the records a genome can match into an
+//! ordinary [`ClinVarDatabase`], so the analyses run on it unchanged.
+//!
+//! The file begins with the schema version it was written with. A store of
+//! another version is not read; [`ClinVarStore::open_or_build`] rebuilds
+//! it from the release instead, which is the migration between schemas.
+
+use super::clinvar::{ClinVarDatabase, ClinVarRecord};
+use super::normalize_rsid;
+use crate::genome::GenomeBuild;
+use crate::parser::normalize_chromosome;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Name of the store built from the installed ClinVar release
+pub const STORE_FILE: &str = "clinvar.gfdb";
+
+/// Version of the file layout and record fields stores are written with
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// First bytes of every store
+const MAGIC: &[u8; 4] = b"GFCV";
+
+/// Magic, version, record count, then the offset and length of each index
+const HEADER_LEN: u64 = 4 + 4 + 8 + 4 * 8;
+
+/// Build, chromosome code, position, record offset
+const ALLELE_ENTRY_LEN: u64 = 1 + 1 + 8 + 8;
+
+/// rsid number, record offset
+const RSID_ENTRY_LEN: u64 = 8 + 8;
+
+/// Records of a ClinVar release, read from disk as they are looked up
+#[derive(Debug)]
+pub struct ClinVarStore {
+    file: Mutex<File>,
+    records: u64,
+    alleles: Index,
+    rsids: Index,
+    release_date: Option<String>,
+}
+
+/// Where a sorted index of fixed-size entries lies in the file
+#[derive(Debug, Clone, Copy)]
+struct Index {
+    offset: u64,
+    entries: u64,
+}
+
+impl ClinVarStore {
+    /// Write the records of a loaded release as a store at `path`
+    ///
+    /// The file is written next to `path` and renamed into place, so a
+    /// failed write leaves any previous store intact.
+    pub fn write(path: &Path, database: &ClinVarDatabase) -> Result<(), String> {
+        let partial = path.with_extension("gfdb.partial");
+        write_store(&partial, database)
+            .and_then(|_| fs::rename(&partial, path).map_err(|e| e.to_string()))
+            .map_err(|e| {
+                let _ = fs::remove_file(&partial);
+                format!("Failed to write {}: {}", path.display(), e)
+            })
+    }
+
+    /// Open a store written with the current schema
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let version = read_version(&mut file)?;
+        if version != SCHEMA_VERSION {
+            return Err(format!(
+                "{} has schema {}, not {}; rebuild it from the release",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            ));
+        }
+        let mut header = [0u8; (HEADER_LEN - 8) as usize];
+        file.read_exact(&mut header)
+            .map_err(|e| format!("ClinVar store is corrupt: {}", e))?;
+        let field = |n: usize| u64::from_le_bytes(header[n * 8..n * 8 + 8].try_into().unwrap());
+        let records = field(0);
+        let alleles = Index {
+            offset: field(1),
+            entries: field(2) / ALLELE_ENTRY_LEN,
+        };
+        let rsids = Index {
+            offset: field(3),
+            entries: field(4) / RSID_ENTRY_LEN,
+        };
+
+        let mut store = ClinVarStore {
+            file: Mutex::new(file),
+            records,
+            alleles,
+            rsids,
+            release_date: None,
+        };
+        // The release date follows the indexes
+        store.release_date = store.read_trailer(rsids.offset + rsids.entries * RSID_ENTRY_LEN)?;
+        Ok(store)
+    }
+
+    /// Open the store at `path`, first building it from `release` when it
+    /// is missing, of another schema or older than the release
+    pub fn open_or_build(path: &Path, release: &Path) -> Result<Self, String> {
+        if !needs_build(path, release) {
+            if let Ok(store) = Self::open(path) {
+                return Ok(store);
+            }
+        }
+        let database = ClinVarDatabase::load(release)?;
+        Self::write(path, &database)?;
+        Self::open(path)
+    }
+
+    /// Number of classified alleles
+    pub fn len(&self) -> usize {
+        self.records as usize
+    }
+
+    /// Whether the release holds no records
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Release date of the release the store was built from
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    /// Records for an rsid, across all alleles and builds
+    pub fn lookup_rsid(&self, rsid: &str) -> Result<Vec<ClinVarRecord>, String> {
+        let offsets = self.rsid_offsets(rsid)?;
+        self.read_records(&offsets)
+    }
+
+    /// Records for one allele on a given build
+    pub fn lookup_allele(
+        &self,
+        build: GenomeBuild,
+        chromosome: &str,
+        position: u64,
+        reference: &str,
+        alternate: &str,
+    ) -> Result<Vec<ClinVarRecord>, String> {
+        let chromosome = normalize_chromosome(chromosome);
+        let offsets = self.allele_offsets(build, &chromosome, position)?;
+        let mut records = self.read_records(&offsets)?;
+        records.retain(|record| {
+            record.chromosome == chromosome
+                && record.reference.eq_ignore_ascii_case(reference)
+                && record.alternate.eq_ignore_ascii_case(alternate)
+        });
+        Ok(records)
+    }
+
+    /// The records any variant of a genome could match, by allele at its
+    /// site or by its rsid, as a database to annotate it with
+    ///
+    /// `checkpoint` is called with the number of variants looked up so far
+    /// and stops the lookup when it returns an error.
+    pub fn subset<F>(
+        &self,
+        genome: &LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<ClinVarDatabase, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let build = genome.file.genome_build;
+        let mut offsets = Vec::new();
+        for (index, variant) in genome.variants().iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            if variant.genotype.is_no_call() {
+                continue;
+            }
+            if let Some(build) = build {
+                offsets.extend(self.allele_offsets(
+                    build,
+                    &variant.chromosome,
+                    variant.position,
+                )?);
+            }
+            if let Some(rsid) = &variant.rsid {
+                offsets.extend(self.rsid_offsets(rsid)?);
+            }
+        }
+        offsets.sort_unstable();
+        offsets.dedup();
+        let records = self.read_records(&offsets)?;
+        Ok(ClinVarDatabase::from_records(
+            records,
+            self.release_date.clone(),
+        ))
+    }
+
+    fn allele_offsets(
+        &self,
+        build: GenomeBuild,
+        chromosome: &str,
+        position: u64,
+    ) -> Result<Vec<u64>, String> {
+        let key = allele_key(build, chromosome, position);
+        self.find(self.alleles, ALLELE_ENTRY_LEN, |entry| {
+            let found = (
+                entry[0],
+                entry[1],
+                u64::from_le_bytes(entry[2..10].try_into().unwrap()),
+            );
+            let offset = u64::from_le_bytes(entry[10..18].try_into().unwrap());
+            (found.cmp(&key), offset)
+        })
+    }
+
+    fn rsid_offsets(&self, rsid: &str) -> Result<Vec<u64>, String> {
+        let Some(number) = rsid_number(rsid) else {
+            return Ok(Vec::new());
+        };
+        self.find(self.rsids, RSID_ENTRY_LEN, |entry| {
+            let found = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+            let offset = u64::from_le_bytes(entry[8..16].try_into().unwrap());
+            (found.cmp(&number), offset)
+        })
+    }
+
+    /// Record offsets of every entry of a sorted index equal to a key
+    ///
+    /// `compare` reads one entry, ordering its key against the one sought
+    /// and giving the record offset it points to.
+    fn find<F>(&self, index: Index, entry_len: u64, compare: F) -> Result<Vec<u64>, String>
+    where
+        F: Fn(&[u8]) -> (std::cmp::Ordering, u64),
+    {
+        let mut file = self.lock();
+        let mut entry = vec![0u8; entry_len as usize];
+        let mut read = |at: u64| -> Result<(std::cmp::Ordering, u64), String> {
+            file.seek(SeekFrom::Start(index.offset + at * entry_len))
+                .and_then(|_| file.read_exact(&mut entry))
+                .map_err(|e| format!("Failed to read ClinVar store: {}", e))?;
+            Ok(compare(&entry))
+        };
+
+        // Lower bound: the first entry not less than the key
+        let (mut low, mut high) = (0, index.entries);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if read(middle)?.0 == std::cmp::Ordering::Less {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let mut offsets = Vec::new();
+        for at in low..index.entries {
+            let (ordering, offset) = read(at)?;
+            if ordering != std::cmp::Ordering::Equal {
+                break;
+            }
+            offsets.push(offset);
+        }
+        Ok(offsets)
+    }
+
+    fn read_records(&self, offsets: &[u64]) -> Result<Vec<ClinVarRecord>, String> {
+        let mut file = self.lock();
+        offsets
+            .iter()
+            .map(|&offset| {
+                let bytes = read_entry(&mut file, offset)?;
+                serde_json::from_slice(&bytes)
+                    .map_err(|e| format!("ClinVar store is corrupt: {}", e))
+            })
+            .collect()
+    }
+
+    fn read_trailer(&self, offset: u64) -> Result<Option<String>, String> {
+        let bytes = read_entry(&mut self.lock(), offset)?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("ClinVar store is corrupt: {}", e))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, File> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Schema version of the store at `path`, if it is a store at all
+pub fn schema_version(path: &Path) -> Option<u32> {
+    File::open(path)
+        .ok()
+        .and_then(|mut file| read_version(&mut file).ok())
+}
+
+// Helper functions
+
+/// Whether the store at `path` has to be (re)built from `release`
+fn needs_build(path: &Path, release: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    schema_version(path) != Some(SCHEMA_VERSION)
+        || matches!((modified(path), modified(release)), (Some(store), Some(release)) if store < release)
+}
+
+fn write_store(path: &Path, database: &ClinVarDatabase) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let mut out = BufWriter::new(file);
+    let io = |e: std::io::Error| e.to_string();
+    out.write_all(&[0u8; HEADER_LEN as usize]).map_err(io)?;
+
+    let mut offset = HEADER_LEN;
+    let mut alleles = Vec::new();
+    let mut rsids = Vec::new();
+    for record in database.records() {
+        if let Some(build) = record.genome_build {
+            alleles.push((
+                allele_key(build, &record.chromosome, record.position),
+                offset,
+            ));
+        }
+        if let Some(number) = record.rsid.as_deref().and_then(rsid_number) {
+            rsids.push((number, offset));
+        }
+        let json = serde_json::to_vec(record).map_err(|e| e.to_string())?;
+        offset += write_entry(&mut out, &json).map_err(io)?;
+    }
+    alleles.sort_unstable();
+    rsids.sort_unstable();
+
+    let allele_offset = offset;
+    for ((build, chromosome, position), record) in &alleles {
+        out.write_all(&[*build, *chromosome]).map_err(io)?;
+        out.write_all(&position.to_le_bytes()).map_err(io)?;
+        out.write_all(&record.to_le_bytes()).map_err(io)?;
+    }
+    let rsid_offset = allele_offset + alleles.len() as u64 * ALLELE_ENTRY_LEN;
+    for (number, record) in &rsids {
+        out.write_all(&number.to_le_bytes()).map_err(io)?;
+        out.write_all(&record.to_le_bytes()).map_err(io)?;
+    }
+    let trailer = serde_json::to_vec(&database.release_date()).map_err(|e| e.to_string())?;
+    write_entry(&mut out, &trailer).map_err(io)?;
+
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    for field in [
+        database.len() as u64,
+        allele_offset,
+        alleles.len() as u64 * ALLELE_ENTRY_LEN,
+        rsid_offset,
+        rsids.len() as u64 * RSID_ENTRY_LEN,
+    ] {
+        header.extend_from_slice(&field.to_le_bytes());
+    }
+    let mut file = out.into_inner().map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(0)).map_err(io)?;
+    file.write_all(&header).map_err(io)?;
+    file.sync_all().map_err(io)
+}
+
+/// Write a length-prefixed entry, returning the bytes written
+fn write_entry<W: Write>(out: &mut W, bytes: &[u8]) -> std::io::Result<u64> {
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)?;
+    Ok(4 + bytes.len() as u64)
+}
+
+fn read_entry(file: &mut File, offset: u64) -> Result<Vec<u8>, String> {
+    let corrupt = |e: std::io::Error| format!("ClinVar store is corrupt: {}", e);
+    let mut len = [0u8; 4];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut len))
+        .map_err(corrupt)?;
+    let mut bytes = vec![0u8; u32::from_le_bytes(len) as usize];
+    file.read_exact(&mut bytes).map_err(corrupt)?;
+    Ok(bytes)
+}
+
+fn read_version(file: &mut File) -> Result<u32, String> {
+    let mut start = [0u8; 8];
+    file.read_exact(&mut start)
+        .map_err(|e| format!("Not a ClinVar store: {}", e))?;
+    if &start[..4] != MAGIC {
+        return Err("Not a ClinVar store".to_string());
+    }
+    Ok(u32::from_le_bytes(start[4..].try_into().unwrap()))
+}
+
+/// Sort key of a site: chromosomes in karyotype order, with any other
+/// contig sharing code 0 and told apart when the records are read
+fn allele_key(build: GenomeBuild, chromosome: &str, position: u64) -> (u8, u8, u64) {
+    let build = match build {
+        GenomeBuild::GRCh36 => 36,
+        GenomeBuild::GRCh37 => 37,
+        GenomeBuild::GRCh38 => 38,
+    };
+    let chromosome = match normalize_chromosome(chromosome).as_str() {
+        "X" => 23,
+        "Y" => 24,
+        "MT" => 25,
+        other => other
+            .parse::<u8>()
+            .ok()
+            .filter(|n| (1..=22).contains(n))
+            .unwrap_or(0),
+    };
+    (build, chromosome, position)
+}
+
+fn rsid_number(rsid: &str) -> Option<u64> {
+    normalize_rsid(rsid)?[2..].parse().ok()
+}
@@ -0,0 +1,171 @@
+//! Categories of findings a user has opted out of
+//!
+//! A [`ConsentPolicy`] lists whole categories to leave out of an analysis.
+//! It is applied as the analysis runs, before findings are built from
+//! database matches, so an excluded category is never computed and never
+//! reaches results, reports or saved sessions.
+
+use super::carrier;
+use super::clinvar::ClinVarMatch;
+use super::gwas::GwasAssociation;
+use super::{acmg, apoe};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Genes of neurodegenerative conditions, most of them without treatment
+pub const NEURODEGENERATIVE_GENES: [&str; 20] = [
+    "APOE", "APP", "PSEN1", "PSEN2", "MAPT", "GRN", "C9orf72", "TARDBP", "FUS", "SOD1", "SNCA",
+    "LRRK2", "PRKN", "PINK1", "PARK7", "GBA1", "HTT", "PRNP", "ATXN2", "ATXN3",
+];
+
+/// Words in the names of neurodegenerative conditions and traits
+pub const NEURODEGENERATIVE_TERMS: [&str; 9] = [
+    "alzheimer",
+    "dementia",
+    "parkinson",
+    "huntington",
+    "amyotrophic lateral sclerosis",
+    "frontotemporal",
+    "prion",
+    "spinocerebellar ataxia",
+    "neurodegenerat",
+];
+
+/// A kind of finding that can be left out as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingCategory {
+    /// Risk of Alzheimer's, Parkinson's, Huntington's and other
+    /// neurodegenerative diseases, APOE included
+    Neurodegenerative,
+    /// Carrier status for recessive conditions
+    CarrierStatus,
+    /// ACMG secondary findings
+    SecondaryFindings,
+    /// Drug responses and star-allele diplotypes
+    Pharmacogenomics,
+    /// GWAS trait associations
+    Traits,
+    /// The nutrition and metabolism panel
+    Nutrigenomics,
+    /// Markers that can reveal biological parentage: Y-chromosome and
+    /// mitochondrial haplogroups and comparisons with relatives' genomes
+    Paternity,
+}
+
+impl FindingCategory {
+    pub const ALL: [FindingCategory; 7] = [
+        FindingCategory::Neurodegenerative,
+        FindingCategory::CarrierStatus,
+        FindingCategory::SecondaryFindings,
+        FindingCategory::Pharmacogenomics,
+        FindingCategory::Traits,
+        FindingCategory::Nutrigenomics,
+        FindingCategory::Paternity,
+    ];
+}
+
+/// Which categories of findings the user does not want
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsentPolicy {
+    pub excluded: BTreeSet<FindingCategory>,
+}
+
+impl ConsentPolicy {
+    /// A policy leaving out the given categories
+    pub fn excluding<I: IntoIterator<Item = FindingCategory>>(categories: I) -> Self {
+        ConsentPolicy {
+            excluded: categories.into_iter().collect(),
+        }
+    }
+
+    /// Whether findings of a category may be computed
+    pub fn allows(&self, category: FindingCategory) -> bool {
+        !self.excluded.contains(&category)
+    }
+
+    /// An error for a whole analysis of an excluded category
+    pub fn require(&self, category: FindingCategory) -> Result<(), String> {
+        if self.allows(category) {
+            return Ok(());
+        }
+        Err(format!(
+            "{} findings are excluded by your consent settings",
+            label(category)
+        ))
+    }
+
+    /// Category of a ClinVar match the policy excludes, if any
+    pub fn excludes_match(&self, found: &ClinVarMatch<'_>) -> Option<FindingCategory> {
+        let record = found.record;
+        if !self.allows(FindingCategory::Neurodegenerative)
+            && (neurodegenerative_gene(&record.genes)
+                || record
+                    .conditions
+                    .iter()
+                    .any(|condition| neurodegenerative_trait(condition)))
+        {
+            return Some(FindingCategory::Neurodegenerative);
+        }
+        if !self.allows(FindingCategory::SecondaryFindings)
+            && acmg::secondary_gene(record).is_some()
+        {
+            return Some(FindingCategory::SecondaryFindings);
+        }
+        if !self.allows(FindingCategory::CarrierStatus)
+            && acmg::secondary_gene(record).is_none()
+            && carrier::carrier_gene(found).is_some()
+        {
+            return Some(FindingCategory::CarrierStatus);
+        }
+        None
+    }
+
+    /// Drop the ClinVar matches of excluded categories, returning how many
+    pub fn retain_matches(&self, matches: &mut Vec<ClinVarMatch<'_>>) -> usize {
+        let before = matches.len();
+        matches.retain(|found| self.excludes_match(found).is_none());
+        before - matches.len()
+    }
+
+    /// Whether a GWAS association may be reported
+    pub fn allows_association(&self, association: &GwasAssociation) -> bool {
+        self.allows(FindingCategory::Traits)
+            && (self.allows(FindingCategory::Neurodegenerative)
+                || !(neurodegenerative_gene(&association.genes)
+                    || apoe::is_apoe_site(Some(&association.rsid), &association.genes)
+                    || neurodegenerative_trait(&association.trait_name)))
+    }
+}
+
+/// Whether any of the genes is one of [`NEURODEGENERATIVE_GENES`]
+pub fn neurodegenerative_gene(genes: &[String]) -> bool {
+    genes.iter().any(|gene| {
+        NEURODEGENERATIVE_GENES
+            .iter()
+            .any(|listed| gene.eq_ignore_ascii_case(listed))
+    })
+}
+
+/// Whether a condition or trait name is of a neurodegenerative disease
+pub fn neurodegenerative_trait(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    NEURODEGENERATIVE_TERMS
+        .iter()
+        .any(|term| name.contains(term))
+}
+
+// Helper functions
+
+fn label(category: FindingCategory) -> &'static str {
+    match category {
+        FindingCategory::Neurodegenerative => "Neurodegenerative disease",
+        FindingCategory::CarrierStatus => "Carrier status",
+        FindingCategory::SecondaryFindings => "Secondary",
+        FindingCategory::Pharmacogenomics => "Pharmacogenomic",
+        FindingCategory::Traits => "Trait",
+        FindingCategory::Nutrigenomics => "Nutrigenomic",
+        FindingCategory::Paternity => "Parentage",
+    }
+}
@@ -0,0 +1,800 @@
+//! CPIC star-allele calling
+//!
+//! Genes such as CYP2C19 and CYP2D6 are described by star alleles, each a
+//! combination of variants, so one SNP on its own says little about drug
+//! response. A CPIC release directory holds for every gene the allele
+//! definition table (`<GENE>_allele_definition_table.tsv`) and allele
+//! functionality table (`<GENE>_allele_functionality_reference.tsv`) saved
+//! as tab-separated text, plus optionally `cpic_recommendations.tsv` with
+//! the guideline recommendation for each phenotype and drug. The bundled
+//! [`guidelines`] cover drugs a release has none for.
+//!
+//! Gene deletions and duplications such as CYP2D6*5 are not visible in SNP
+//! data, so every call assumes two copies of the gene.
+
+use super::guidelines;
+use super::tsv::TsvReader;
+use super::{normalize_rsid, refseq_chromosome};
+use crate::confidence::{self, Confidence};
+use crate::genome::{reverse_complement, GenomeBuild, Variant};
+use crate::parser::{compression, detect_genome_build};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// File name ending of a gene's allele definition table
+pub const DEFINITION_SUFFIX: &str = "_allele_definition_table.tsv";
+
+/// File name ending of a gene's allele functionality table
+pub const FUNCTIONALITY_SUFFIX: &str = "_allele_functionality_reference.tsv";
+
+/// Guideline recommendations by gene, phenotype and drug
+pub const RECOMMENDATIONS_FILE: &str = "cpic_recommendations.tsv";
+
+/// Columns of `cpic_recommendations.tsv`
+const RECOMMENDATION_COLUMNS: [&str; 4] = ["Gene", "Drug", "Phenotype", "Recommendation"];
+
+/// Genes whose phenotype follows from the summed allele activity values
+const ACTIVITY_SCORE_GENES: [&str; 3] = ["CYP2D6", "CYP2C9", "DPYD"];
+
+/// Transporter genes, whose phenotypes describe function not metabolism
+const TRANSPORTER_GENES: [&str; 1] = ["SLCO1B1"];
+
+/// Whether a file belongs in a CPIC release directory
+pub fn is_table_name(name: &str) -> bool {
+    name.ends_with(DEFINITION_SUFFIX)
+        || name.ends_with(FUNCTIONALITY_SUFFIX)
+        || name == RECOMMENDATIONS_FILE
+}
+
+/// Whether a directory holds at least one allele definition table
+pub fn has_definitions(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|entries| {
+        entries.flatten().any(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .ends_with(DEFINITION_SUFFIX)
+        })
+    })
+}
+
+/// Clinical function CPIC assigns to an allele, highest activity first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlleleFunction {
+    Increased,
+    Normal,
+    Decreased,
+    No,
+    /// Uncertain or unknown function
+    Uncertain,
+}
+
+impl AlleleFunction {
+    /// Parse a CPIC status such as "Decreased function"
+    pub fn parse(raw: &str) -> Option<AlleleFunction> {
+        let lower = raw.trim().to_ascii_lowercase();
+        if lower.is_empty() {
+            None
+        } else if lower.contains("uncertain") || lower.contains("unknown") {
+            Some(AlleleFunction::Uncertain)
+        } else if lower.starts_with("increased") {
+            Some(AlleleFunction::Increased)
+        } else if lower.starts_with("normal") {
+            Some(AlleleFunction::Normal)
+        } else if lower.contains("decreased") {
+            Some(AlleleFunction::Decreased)
+        } else if lower.starts_with("no function") {
+            Some(AlleleFunction::No)
+        } else {
+            Some(AlleleFunction::Uncertain)
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlleleFunction::Increased => "Increased function",
+            AlleleFunction::Normal => "Normal function",
+            AlleleFunction::Decreased => "Decreased function",
+            AlleleFunction::No => "No function",
+            AlleleFunction::Uncertain => "Uncertain function",
+        }
+    }
+}
+
+/// One variant position of an allele definition table
+#[derive(Debug, Clone, Serialize)]
+pub struct DefinitionSite {
+    pub rsid: Option<String>,
+    pub chromosome: Option<String>,
+    pub position: Option<u64>,
+    /// Base of the reference allele, the first allele of the table
+    pub reference: String,
+    /// A single-nucleotide site that genotypes can be compared against;
+    /// indels and multi-base changes are not
+    pub comparable: bool,
+}
+
+/// A star allele and the bases that define it
+#[derive(Debug, Clone, Serialize)]
+pub struct StarAllele {
+    /// e.g. "*2"
+    pub name: String,
+    /// Base at each site, possibly an IUPAC code; `None` where the allele
+    /// carries the reference
+    pub variants: Vec<Option<String>>,
+    pub function: Option<AlleleFunction>,
+    /// CPIC activity value, for genes scored by activity
+    pub activity_value: Option<f64>,
+}
+
+/// Allele definitions of one gene
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneDefinition {
+    pub gene: String,
+    /// Build of the site positions
+    pub genome_build: Option<GenomeBuild>,
+    pub sites: Vec<DefinitionSite>,
+    /// Alleles in table order; the first is the reference allele
+    pub alleles: Vec<StarAllele>,
+}
+
+/// A CPIC recommendation for one phenotype and drug
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recommendation {
+    pub gene: String,
+    pub drug: String,
+    pub phenotype: String,
+    pub implication: Option<String>,
+    pub recommendation: String,
+    /// Strength of the recommendation, e.g. "Strong" or "Moderate"
+    pub classification: Option<String>,
+    /// Title of the guideline the recommendation comes from
+    #[serde(default)]
+    pub guideline: Option<String>,
+    /// Publication of the guideline, e.g. "2022 update"
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+/// Diplotype called for one gene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiplotypeCall {
+    pub gene: String,
+    /// Both alleles, e.g. "*1/*2"
+    pub diplotype: String,
+    pub alleles: [String; 2],
+    pub functions: [Option<AlleleFunction>; 2],
+    /// Sum of the allele activity values, when both are known
+    pub activity_score: Option<f64>,
+    /// e.g. "Intermediate Metabolizer"; "Indeterminate" when the allele
+    /// functions do not determine one
+    pub phenotype: String,
+    /// Other diplotypes that fit the genotypes equally well
+    pub alternatives: Vec<String>,
+    /// Comparable sites of the gene found genotyped in the genome
+    pub sites_genotyped: usize,
+    pub sites_total: usize,
+    /// Genotyped sites whose calls were imputed rather than measured
+    #[serde(default)]
+    pub sites_imputed: usize,
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+/// Indexed CPIC allele definitions and recommendations
+#[derive(Debug, Default)]
+pub struct CpicDatabase {
+    genes: Vec<GeneDefinition>,
+    recommendations: Vec<Recommendation>,
+    by_gene: HashMap<String, Vec<usize>>,
+}
+
+impl GeneDefinition {
+    /// Parse an allele definition table
+    ///
+    /// The table starts with header rows: "GENE: <name>", a row labelled
+    /// with the RefSeq chromosome accession whose cells hold HGVS genomic
+    /// positions such as "g.94781859G>A", and an "rsID" row. Allele rows
+    /// follow, the first being the reference allele. An empty cell means
+    /// the allele has the reference base at that site.
+    pub fn from_table(text: &str, gene: Option<&str>) -> Result<Self, String> {
+        let rows: Vec<Vec<&str>> = text
+            .lines()
+            .map(|line| {
+                line.split('\t')
+                    .map(|cell| cell.trim().trim_matches('"'))
+                    .collect()
+            })
+            .collect();
+
+        let mut name = gene.map(str::to_string);
+        let mut chromosome = None;
+        let mut genome_build = None;
+        let mut positions: Vec<Option<u64>> = Vec::new();
+        let mut rsid_row = None;
+        for (index, row) in rows.iter().enumerate() {
+            let label = row[0];
+            if let Some(gene) = label.strip_prefix("GENE:") {
+                name = Some(gene.trim().to_string());
+            } else if label.contains("NC_") {
+                chromosome = label
+                    .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .find(|token| token.starts_with("NC_"))
+                    .and_then(refseq_chromosome);
+                genome_build = detect_genome_build(&[label.to_string()]);
+                positions = row.iter().map(|cell| genomic_position(cell)).collect();
+            } else if label.eq_ignore_ascii_case("rsid") {
+                rsid_row = Some(index);
+                break;
+            }
+        }
+        let rsid_row =
+            rsid_row.ok_or("Not a CPIC allele definition table: no rsID row".to_string())?;
+        let gene = name
+            .filter(|name| !name.is_empty())
+            .ok_or("Not a CPIC allele definition table: no gene name".to_string())?;
+        let rsids = &rows[rsid_row];
+
+        // Skip label rows without bases, and stop at the notes below the table
+        let allele_rows: Vec<&Vec<&str>> = rows[rsid_row + 1..]
+            .iter()
+            .take_while(|row| {
+                !row[0].is_empty() && !row[0].to_ascii_uppercase().starts_with("NOTE")
+            })
+            .filter(|row| row[1..].iter().any(|cell| !cell.is_empty()))
+            .collect();
+        let reference = allele_rows
+            .first()
+            .ok_or(format!("No alleles in the {} definition table", gene))?;
+        let columns: Vec<usize> = (1..reference.len())
+            .filter(|&column| !reference[column].is_empty())
+            .collect();
+
+        let alleles: Vec<StarAllele> = allele_rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| StarAllele {
+                name: row[0].to_string(),
+                variants: columns
+                    .iter()
+                    .map(|&column| {
+                        let cell = row.get(column).copied().unwrap_or("");
+                        (index > 0 && !cell.is_empty() && cell != reference[column])
+                            .then(|| cell.to_ascii_uppercase())
+                    })
+                    .collect(),
+                function: None,
+                activity_value: None,
+            })
+            .collect();
+
+        let sites = columns
+            .iter()
+            .enumerate()
+            .map(|(site, &column)| {
+                let reference = reference[column].to_ascii_uppercase();
+                let comparable = is_base(&reference)
+                    && alleles.iter().all(|allele| {
+                        allele.variants[site]
+                            .as_deref()
+                            .is_none_or(|base| iupac_bases(base).is_some())
+                    });
+                DefinitionSite {
+                    rsid: rsids.get(column).and_then(|cell| normalize_rsid(cell)),
+                    chromosome: chromosome.clone(),
+                    position: positions.get(column).copied().flatten(),
+                    reference,
+                    comparable,
+                }
+            })
+            .collect();
+
+        Ok(GeneDefinition {
+            gene,
+            genome_build,
+            sites,
+            alleles,
+        })
+    }
+
+    /// Add functions and activity values from an allele functionality table
+    ///
+    /// Title rows above the "Allele" header row are skipped.
+    pub fn read_functionality(&mut self, text: &str) -> Result<(), String> {
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if line.split('\t').next().map(str::trim) == Some("Allele") {
+                break;
+            }
+            offset += line.len();
+        }
+        if offset == text.len() {
+            return Err("Not a CPIC allele functionality table: no Allele column".to_string());
+        }
+        let mut reader = TsvReader::new(&text.as_bytes()[offset..])?;
+        let function_column = reader
+            .column_starting_with("Allele Clinical Function")
+            .or_else(|| reader.column_starting_with("Allele Function"))
+            .ok_or("Not a CPIC allele functionality table: no function column".to_string())?;
+        let activity_column = reader.column_starting_with("Activity Value");
+
+        let by_name: HashMap<String, usize> = self
+            .alleles
+            .iter()
+            .enumerate()
+            .map(|(index, allele)| (allele.name.clone(), index))
+            .collect();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("CPIC {}", e))?;
+            let Some(&index) = row.get("Allele").and_then(|name| by_name.get(name)) else {
+                continue;
+            };
+            let allele = &mut self.alleles[index];
+            allele.function = row.get(&function_column).and_then(AlleleFunction::parse);
+            allele.activity_value = activity_column
+                .as_deref()
+                .and_then(|column| row.get(column))
+                .and_then(|value| value.parse().ok());
+        }
+
+        Ok(())
+    }
+
+    /// Call the diplotype that best explains a genome's genotypes
+    ///
+    /// An allele is only considered when every site defining it is
+    /// genotyped. Of the allele pairs consistent with every genotyped
+    /// site, the one defined by the most variant sites wins, so that *1/*1
+    /// is only called when nothing more specific fits. Returns `None` when
+    /// no site is genotyped or no pair fits.
+    pub fn call(&self, genome: &LoadedGenome) -> Option<DiplotypeCall> {
+        let same_build = match (genome.file.genome_build, self.genome_build) {
+            (Some(genome_build), Some(table_build)) => genome_build == table_build,
+            _ => false,
+        };
+        let observed: Vec<Option<(char, char)>> = (0..self.sites.len())
+            .map(|site| self.observe(genome, site, same_build))
+            .collect();
+        let sites_genotyped = observed.iter().flatten().count();
+        if sites_genotyped == 0 {
+            return None;
+        }
+        let sites_imputed = observed
+            .iter()
+            .enumerate()
+            .filter(|(site, seen)| {
+                seen.is_some()
+                    && find_variant(genome, &self.sites[*site], same_build)
+                        .is_some_and(Variant::is_imputed)
+            })
+            .count();
+
+        let callable: Vec<usize> = (0..self.alleles.len())
+            .filter(|&index| {
+                self.alleles[index]
+                    .variants
+                    .iter()
+                    .zip(&observed)
+                    .all(|(base, seen)| base.is_none() || seen.is_some())
+            })
+            .collect();
+        let mut best: Vec<(usize, usize)> = Vec::new();
+        let mut best_score = 0;
+        for (position, &first) in callable.iter().enumerate() {
+            for &second in &callable[position..] {
+                if !self.fits(first, second, &observed) {
+                    continue;
+                }
+                let score = self.defining_sites(first) + self.defining_sites(second);
+                if best.is_empty() || score > best_score {
+                    best = vec![(first, second)];
+                    best_score = score;
+                } else if score == best_score {
+                    best.push((first, second));
+                }
+            }
+        }
+
+        let (&(first, second), others) = best.split_first()?;
+        let (first, second) = (&self.alleles[first], &self.alleles[second]);
+        let (activity_score, phenotype) = phenotype(&self.gene, first, second);
+        let mut call = DiplotypeCall {
+            gene: self.gene.clone(),
+            diplotype: format!("{}/{}", first.name, second.name),
+            alleles: [first.name.clone(), second.name.clone()],
+            functions: [first.function, second.function],
+            activity_score,
+            phenotype,
+            alternatives: others
+                .iter()
+                .map(|&(a, b)| format!("{}/{}", self.alleles[a].name, self.alleles[b].name))
+                .collect(),
+            sites_genotyped,
+            sites_total: self.sites.len(),
+            sites_imputed,
+            confidence: Confidence::default(),
+        };
+        call.confidence = confidence::diplotype(&call);
+        Some(call)
+    }
+
+    /// Called bases at a site, read from the opposite strand when only
+    /// the complemented call fits the table
+    fn observe(
+        &self,
+        genome: &LoadedGenome,
+        site: usize,
+        same_build: bool,
+    ) -> Option<(char, char)> {
+        let definition = &self.sites[site];
+        if !definition.comparable {
+            return None;
+        }
+        let variant = find_variant(genome, definition, same_build)?;
+        let possible = self.possible_bases(site);
+        let fits = |bases: &[&str]| -> Option<(char, char)> {
+            let called: Vec<char> = bases
+                .iter()
+                .filter(|base| base.len() == 1)
+                .filter_map(|base| base.chars().next())
+                .collect();
+            let (first, second) = match called.as_slice() {
+                [haploid] if bases.len() == 1 => (*haploid, *haploid),
+                [first, second] => (*first, *second),
+                _ => return None,
+            };
+            (possible.contains(first) && possible.contains(second)).then_some((first, second))
+        };
+
+        fits(&variant.genotype.alleles()).or_else(|| {
+            let reference = definition.reference.as_str();
+            let palindromic = self.alleles.iter().any(|allele| {
+                allele.variants[site]
+                    .as_deref()
+                    .is_some_and(|base| reverse_complement(base) == reference)
+            });
+            let complemented = variant.genotype.complemented();
+            (!palindromic)
+                .then(|| fits(&complemented.alleles()))
+                .flatten()
+        })
+    }
+
+    /// Every base any allele may carry at a site
+    fn possible_bases(&self, site: usize) -> String {
+        let mut bases = self.sites[site].reference.clone();
+        for allele in &self.alleles {
+            if let Some(expanded) = allele.variants[site].as_deref().and_then(iupac_bases) {
+                bases.push_str(expanded);
+            }
+        }
+        bases
+    }
+
+    /// Whether two alleles explain every observed genotype
+    fn fits(&self, first: usize, second: usize, observed: &[Option<(char, char)>]) -> bool {
+        observed.iter().enumerate().all(|(site, seen)| {
+            let Some((a, b)) = *seen else {
+                return true;
+            };
+            let carries = |allele: usize, base: char| {
+                let code = self.alleles[allele].variants[site]
+                    .as_deref()
+                    .unwrap_or(&self.sites[site].reference);
+                iupac_bases(code).is_some_and(|bases| bases.contains(base))
+            };
+            (carries(first, a) && carries(second, b)) || (carries(first, b) && carries(second, a))
+        })
+    }
+
+    fn defining_sites(&self, allele: usize) -> usize {
+        self.alleles[allele].variants.iter().flatten().count()
+    }
+}
+
+impl CpicDatabase {
+    /// Load every gene's tables and the recommendations from a directory
+    pub fn load_dir(dir: &Path) -> Result<Self, String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let mut names: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(DEFINITION_SUFFIX))
+            .collect();
+        names.sort();
+        if names.is_empty() {
+            return Err(format!(
+                "No CPIC allele definition tables in {}",
+                dir.display()
+            ));
+        }
+
+        let mut genes = Vec::with_capacity(names.len());
+        for name in names {
+            let gene = name.trim_end_matches(DEFINITION_SUFFIX);
+            let path = dir.join(&name);
+            let mut definition = GeneDefinition::from_table(&read_table(&path)?, Some(gene))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let functionality = dir.join(format!("{}{}", gene, FUNCTIONALITY_SUFFIX));
+            if functionality.is_file() {
+                definition
+                    .read_functionality(&read_table(&functionality)?)
+                    .map_err(|e| format!("{}: {}", functionality.display(), e))?;
+            }
+            genes.push(definition);
+        }
+
+        let recommendations = dir.join(RECOMMENDATIONS_FILE);
+        let recommendations = if recommendations.is_file() {
+            read_recommendations(&recommendations)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self::from_parts(genes, recommendations))
+    }
+
+    /// Index already parsed definitions and recommendations
+    pub fn from_parts(genes: Vec<GeneDefinition>, recommendations: Vec<Recommendation>) -> Self {
+        let mut by_gene: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, recommendation) in recommendations.iter().enumerate() {
+            by_gene
+                .entry(recommendation.gene.to_ascii_uppercase())
+                .or_default()
+                .push(index);
+        }
+        CpicDatabase {
+            genes,
+            recommendations,
+            by_gene,
+        }
+    }
+
+    /// Add every site of the allele definitions to `sites`
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for site in self.genes.iter().flat_map(|gene| &gene.sites) {
+            if let Some(rsid) = &site.rsid {
+                sites.add_rsid(rsid);
+            }
+            if let (Some(chromosome), Some(position)) = (&site.chromosome, site.position) {
+                sites.add_position(chromosome, position);
+            }
+        }
+    }
+
+    /// Number of star alleles defined across all genes
+    pub fn len(&self) -> usize {
+        self.genes.iter().map(|gene| gene.alleles.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genes.is_empty()
+    }
+
+    pub fn genes(&self) -> &[GeneDefinition] {
+        &self.genes
+    }
+
+    pub fn gene(&self, name: &str) -> Option<&GeneDefinition> {
+        self.genes
+            .iter()
+            .find(|gene| gene.gene.eq_ignore_ascii_case(name))
+    }
+
+    /// Diplotype calls for every gene genotyped in a genome
+    ///
+    /// `checkpoint` is called with the gene index before each gene.
+    pub fn call_diplotypes<F>(
+        &self,
+        genome: &LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<Vec<DiplotypeCall>, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut calls = Vec::new();
+        for (index, gene) in self.genes.iter().enumerate() {
+            checkpoint(index)?;
+            calls.extend(gene.call(genome));
+        }
+        Ok(calls)
+    }
+
+    /// Recommendations for a gene's phenotype
+    ///
+    /// A "Likely" phenotype falls back to the recommendations for the
+    /// confirmed phenotype when the table has none of its own.
+    pub fn recommendations(&self, gene: &str, phenotype: &str) -> Vec<&Recommendation> {
+        let Some(indexes) = self.by_gene.get(&gene.to_ascii_uppercase()) else {
+            return Vec::new();
+        };
+        let matching = |phenotype: &str| -> Vec<&Recommendation> {
+            indexes
+                .iter()
+                .map(|&index| &self.recommendations[index])
+                .filter(|recommendation| is_phenotype(&recommendation.phenotype, gene, phenotype))
+                .collect()
+        };
+        let found = matching(phenotype);
+        match phenotype.strip_prefix("Likely ") {
+            Some(confirmed) if found.is_empty() => matching(confirmed),
+            _ => found,
+        }
+    }
+
+    /// Recommendations for a gene's phenotype, completed with the bundled
+    /// guidelines for drugs the release has none for
+    pub fn recommendations_with_bundled(&self, gene: &str, phenotype: &str) -> Vec<Recommendation> {
+        let mut found: Vec<Recommendation> = self
+            .recommendations(gene, phenotype)
+            .into_iter()
+            .cloned()
+            .collect();
+        for bundled in guidelines::recommendations(gene, phenotype) {
+            if !found
+                .iter()
+                .any(|recommendation| recommendation.drug.eq_ignore_ascii_case(&bundled.drug))
+            {
+                found.push(bundled);
+            }
+        }
+        found
+    }
+}
+
+// Helper functions
+
+fn read_table(path: &Path) -> Result<String, String> {
+    let (mut reader, _) =
+        compression::open_reader(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(text)
+}
+
+fn read_recommendations(path: &Path) -> Result<Vec<Recommendation>, String> {
+    let (reader, _) =
+        compression::open_reader(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = TsvReader::new(reader).map_err(|e| format!("{}: {}", path.display(), e))?;
+    reader
+        .require_columns(&RECOMMENDATION_COLUMNS)
+        .map_err(|e| format!("Not a CPIC recommendation table: {}", e))?;
+
+    let mut recommendations = Vec::new();
+    while let Some(row) = reader.next_row() {
+        let row = row.map_err(|e| format!("CPIC {}", e))?;
+        recommendations.push(Recommendation {
+            gene: row.require("Gene")?.to_string(),
+            drug: row.require("Drug")?.to_string(),
+            phenotype: row.require("Phenotype")?.to_string(),
+            implication: row.get("Implication").map(str::to_string),
+            recommendation: row.require("Recommendation")?.to_string(),
+            classification: row.get("Classification").map(str::to_string),
+            guideline: row.get("Guideline").map(str::to_string),
+            version: row.get("Version").map(str::to_string),
+        });
+    }
+    Ok(recommendations)
+}
+
+fn find_variant<'a>(
+    genome: &'a LoadedGenome,
+    site: &DefinitionSite,
+    same_build: bool,
+) -> Option<&'a Variant> {
+    let variant = site
+        .rsid
+        .as_deref()
+        .and_then(|rsid| genome.get_by_rsid(rsid))
+        .or_else(|| match (same_build, &site.chromosome, site.position) {
+            (true, Some(chromosome), Some(position)) => genome.get_at(chromosome, position),
+            _ => None,
+        })?;
+    (!variant.genotype.is_no_call()).then_some(variant)
+}
+
+/// Activity score and phenotype of a diplotype
+fn phenotype(gene: &str, first: &StarAllele, second: &StarAllele) -> (Option<f64>, String) {
+    let activity_score = first
+        .activity_value
+        .zip(second.activity_value)
+        .map(|(a, b)| a + b);
+    let gene = gene.to_ascii_uppercase();
+
+    if ACTIVITY_SCORE_GENES.contains(&gene.as_str()) {
+        let phenotype = match (gene.as_str(), activity_score) {
+            (_, None) => "Indeterminate",
+            ("CYP2D6", Some(0.0)) => "Poor Metabolizer",
+            ("CYP2D6", Some(score)) if score < 1.25 => "Intermediate Metabolizer",
+            ("CYP2D6", Some(score)) if score <= 2.25 => "Normal Metabolizer",
+            ("CYP2D6", Some(_)) => "Ultrarapid Metabolizer",
+            (_, Some(score)) if score < 1.0 => "Poor Metabolizer",
+            (_, Some(score)) if score < 2.0 => "Intermediate Metabolizer",
+            (_, Some(_)) => "Normal Metabolizer",
+        };
+        return (activity_score, phenotype.to_string());
+    }
+
+    use AlleleFunction::{Decreased, Increased, No, Normal};
+    let (Some(a), Some(b)) = (first.function, second.function) else {
+        return (activity_score, "Indeterminate".to_string());
+    };
+    let metabolizer = match (a.min(b), a.max(b)) {
+        (Increased, Increased) => "Ultrarapid Metabolizer",
+        (Increased, Normal) => "Rapid Metabolizer",
+        (Normal, Normal) => "Normal Metabolizer",
+        (Increased | Normal, Decreased) => "Likely Intermediate Metabolizer",
+        (Increased | Normal, No) => "Intermediate Metabolizer",
+        (Decreased, Decreased | No) => "Likely Poor Metabolizer",
+        (No, No) => "Poor Metabolizer",
+        _ => "Indeterminate",
+    };
+    let phenotype = if TRANSPORTER_GENES.contains(&gene.as_str()) {
+        match metabolizer {
+            "Ultrarapid Metabolizer" | "Rapid Metabolizer" => "Increased Function",
+            "Normal Metabolizer" => "Normal Function",
+            "Likely Intermediate Metabolizer" | "Intermediate Metabolizer" => "Decreased Function",
+            "Likely Poor Metabolizer" | "Poor Metabolizer" => "Poor Function",
+            other => other,
+        }
+    } else {
+        metabolizer
+    };
+    (activity_score, phenotype.to_string())
+}
+
+/// Position of an HGVS genomic description such as "g.94781859G>A"
+fn genomic_position(cell: &str) -> Option<u64> {
+    let digits: String = cell
+        .strip_prefix("g.")?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+fn is_base(allele: &str) -> bool {
+    matches!(allele, "A" | "C" | "G" | "T")
+}
+
+/// Bases an IUPAC nucleotide code stands for
+fn iupac_bases(code: &str) -> Option<&'static str> {
+    match code {
+        "A" => Some("A"),
+        "C" => Some("C"),
+        "G" => Some("G"),
+        "T" => Some("T"),
+        "R" => Some("AG"),
+        "Y" => Some("CT"),
+        "S" => Some("CG"),
+        "W" => Some("AT"),
+        "K" => Some("GT"),
+        "M" => Some("AC"),
+        "B" => Some("CGT"),
+        "D" => Some("AGT"),
+        "H" => Some("ACT"),
+        "V" => Some("ACG"),
+        "N" => Some("ACGT"),
+        _ => None,
+    }
+}
+
+/// Whether a listed phenotype, which may start with the gene as in
+/// "CYP2C19 Poor Metabolizer", is the given one
+pub(crate) fn is_phenotype(listed: &str, gene: &str, phenotype: &str) -> bool {
+    let listed = strip_prefix_ignore_case(listed, gene).unwrap_or(listed);
+    listed.trim().eq_ignore_ascii_case(phenotype)
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &text[prefix.len()..])
+}
@@ -0,0 +1,271 @@
+//! Annotation tables supplied by the user
+//!
+//! A lab layers its own curation over the public databases with a table of
+//! interpretations by rsid, tab- or comma-separated with a header row:
+//!
+//! ```text
+//! rsid        genotype  gene   interpretation                        significance  reference
+//! rs80357906  *         BRCA1  Founder variant, confirmed by Sanger  pathogenic    LAB-2023-114
+//! rs1801133   TT        MTHFR  Reduced enzyme activity               risk_factor
+//! ```
+//!
+//! Only `rsid` and `interpretation` are required, and column names are
+//! matched ignoring case. `genotype` limits an entry to one call, in either
+//! allele order, or applies it to any call when empty or `*`, compared as
+//! written on the forward strand. `significance` is a ClinVar-style
+//! classification, `category` the consent category a finding falls under,
+//! `evidence` the strength of the curation from 0 to 1, and `source` who
+//! made it.
+
+use super::clinvar::ClinicalSignificance;
+use super::consent::{self, ConsentPolicy, FindingCategory};
+use super::normalize_rsid;
+use crate::confidence::{self, Confidence, Evidence};
+use crate::genome::{Genotype, Variant};
+use crate::parser::{compression, csv};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Evidence strength of entries that do not state one; nothing is known of
+/// how they were curated
+pub const DEFAULT_EVIDENCE: f64 = 0.5;
+
+const REQUIRED_COLUMNS: [&str; 2] = ["rsid", "interpretation"];
+
+/// One interpretation of the table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomEntry {
+    pub rsid: String,
+    /// Uppercase alleles in sorted order; `None` for any call
+    pub genotype: Option<Vec<String>>,
+    pub gene: Option<String>,
+    pub interpretation: String,
+    pub significance: ClinicalSignificance,
+    pub category: Option<FindingCategory>,
+    /// Strength of the curation, 0.0 - 1.0
+    pub evidence: f64,
+    pub reference: Option<String>,
+    pub source: Option<String>,
+}
+
+impl CustomEntry {
+    /// Whether the entry applies to a variant's call
+    pub fn matches(&self, variant: &Variant) -> bool {
+        !variant.genotype.is_no_call()
+            && self
+                .genotype
+                .as_ref()
+                .is_none_or(|alleles| *alleles == sorted_alleles(variant.genotype.alleles()))
+    }
+}
+
+/// A genome's call matching an entry of the table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomFinding {
+    pub rsid: String,
+    pub gene: Option<String>,
+    /// The call, e.g. "AG"
+    pub genotype: String,
+    pub interpretation: String,
+    pub significance: ClinicalSignificance,
+    pub category: Option<FindingCategory>,
+    pub reference: Option<String>,
+    pub source: Option<String>,
+    pub confidence: Confidence,
+}
+
+/// A user-supplied annotation table, by rsid
+#[derive(Debug, Default)]
+pub struct CustomAnnotations {
+    entries: HashMap<String, Vec<CustomEntry>>,
+}
+
+impl CustomAnnotations {
+    /// Load a table, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Read a table from any buffered reader
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|e| format!("Failed to read header: {}", e))?;
+        let header = header.trim_end_matches(['\r', '\n']);
+        let header = header.strip_prefix('#').unwrap_or(header);
+        let tabs = header.contains('\t');
+        let columns: HashMap<String, usize> = split(header, tabs)
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name.to_ascii_lowercase(), index))
+            .collect();
+        let missing: Vec<&str> = REQUIRED_COLUMNS
+            .into_iter()
+            .filter(|name| !columns.contains_key(*name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Not a custom annotation table: Missing columns: {}",
+                missing.join(", ")
+            ));
+        }
+
+        let mut table = CustomAnnotations::default();
+        let mut line = String::new();
+        let mut line_number = 1;
+        loop {
+            line.clear();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| format!("line {}: {}", line_number + 1, e))?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = split(line.trim_end_matches(['\r', '\n']), tabs);
+            let get = |column: &str| {
+                columns
+                    .get(column)
+                    .and_then(|&index| fields.get(index))
+                    .map(String::as_str)
+                    .filter(|value| !value.is_empty())
+            };
+            let entry = parse_entry(get).map_err(|e| format!("line {}: {}", line_number, e))?;
+            table
+                .entries
+                .entry(entry.rsid.clone())
+                .or_default()
+                .push(entry);
+        }
+        Ok(table)
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Add the sites the table covers, for streaming parses
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for rsid in self.entries.keys() {
+            sites.add_rsid(rsid);
+        }
+    }
+
+    /// The findings of the table in a genome, leaving out those of
+    /// categories `policy` excludes, in table order per site
+    pub fn annotate(&self, genome: &LoadedGenome, policy: &ConsentPolicy) -> Vec<CustomFinding> {
+        let mut findings = Vec::new();
+        for variant in genome.variants() {
+            let Some(entries) = variant
+                .rsid
+                .as_deref()
+                .and_then(|rsid| self.entries.get(&rsid.to_ascii_lowercase()))
+            else {
+                continue;
+            };
+            for entry in entries.iter().filter(|entry| entry.matches(variant)) {
+                let genes: Vec<String> = entry.gene.iter().cloned().collect();
+                let allowed = entry
+                    .category
+                    .is_none_or(|category| policy.allows(category))
+                    && (policy.allows(FindingCategory::Neurodegenerative)
+                        || !(consent::neurodegenerative_gene(&genes)
+                            || consent::neurodegenerative_trait(&entry.interpretation)));
+                if !allowed {
+                    continue;
+                }
+                findings.push(CustomFinding {
+                    rsid: entry.rsid.clone(),
+                    gene: entry.gene.clone(),
+                    genotype: variant.genotype.to_string(),
+                    interpretation: entry.interpretation.clone(),
+                    significance: entry.significance,
+                    category: entry.category,
+                    reference: entry.reference.clone(),
+                    source: entry.source.clone(),
+                    confidence: Confidence::new(
+                        Evidence::Custom(entry.evidence),
+                        confidence::variant_reliability(variant),
+                    ),
+                });
+            }
+        }
+        findings
+    }
+}
+
+// Helper functions
+
+fn split(line: &str, tabs: bool) -> Vec<String> {
+    if tabs {
+        line.split('\t')
+            .map(|field| field.trim().to_string())
+            .collect()
+    } else {
+        csv::split_line(line)
+    }
+}
+
+fn parse_entry<'a>(get: impl Fn(&str) -> Option<&'a str>) -> Result<CustomEntry, String> {
+    let raw_rsid = get("rsid").ok_or("missing rsid")?;
+    let rsid = normalize_rsid(&raw_rsid.to_ascii_lowercase())
+        .ok_or_else(|| format!("invalid rsid {}", raw_rsid))?;
+    let interpretation = get("interpretation").ok_or("missing interpretation")?;
+    let genotype = match get("genotype").filter(|genotype| *genotype != "*") {
+        None => None,
+        Some(raw) => {
+            let genotype: Genotype = raw.parse()?;
+            if genotype.is_no_call() {
+                return Err(format!("invalid genotype {}", raw));
+            }
+            Some(sorted_alleles(genotype.alleles()))
+        }
+    };
+    let category = match get("category") {
+        None => None,
+        Some(raw) => Some(
+            serde_json::from_value(raw.to_ascii_lowercase().into())
+                .map_err(|_| format!("unknown category {}", raw))?,
+        ),
+    };
+    let evidence = match get("evidence") {
+        None => DEFAULT_EVIDENCE,
+        Some(raw) => raw
+            .parse::<f64>()
+            .ok()
+            .filter(|evidence| (0.0..=1.0).contains(evidence))
+            .ok_or_else(|| format!("evidence must be between 0 and 1, not {}", raw))?,
+    };
+    Ok(CustomEntry {
+        rsid,
+        genotype,
+        gene: get("gene").map(str::to_string),
+        interpretation: interpretation.to_string(),
+        significance: get("significance")
+            .map_or(ClinicalSignificance::Other, ClinicalSignificance::parse),
+        category,
+        evidence,
+        reference: get("reference").map(str::to_string),
+        source: get("source").map(str::to_string),
+    })
+}
+
+fn sorted_alleles(alleles: Vec<&str>) -> Vec<String> {
+    let mut alleles: Vec<String> = alleles.into_iter().map(str::to_ascii_uppercase).collect();
+    alleles.sort();
+    alleles
+}
@@ -0,0 +1,291 @@
+//! dbSNP rsid and position resolution
+//!
+//! Consumer arrays report rsids and positions but no reference allele,
+//! while VCFs from sequencing pipelines often leave the ID column empty.
+//! A locally stored dbSNP subset fills in whichever half is missing, so
+//! that every database can match on the key it indexes by.
+
+use super::{format_file_date, is_allele_sequence, normalize_rsid, refseq_chromosome};
+use crate::genome::{GenomeBuild, Variant};
+use crate::parser::vcf::VcfReader;
+use crate::parser::{compression, detect_genome_build, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+/// A dbSNP reference SNP at one position
+#[derive(Debug, Clone, Serialize)]
+pub struct DbSnpRecord {
+    pub rsid: String,
+    pub chromosome: String,
+    pub position: u64,
+    pub reference: String,
+    pub alternates: Vec<String>,
+}
+
+/// What resolving a genome against dbSNP filled in
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Normalization {
+    /// Variants that gained an rsid from their position and alleles
+    pub rsids_added: usize,
+    /// Variants that gained reference and alternate alleles from their rsid
+    pub alleles_added: usize,
+}
+
+/// Indexed dbSNP subset for one genome build
+#[derive(Debug, Default)]
+pub struct DbSnpIndex {
+    genome_build: Option<GenomeBuild>,
+    release_date: Option<String>,
+    records: Vec<DbSnpRecord>,
+    by_rsid: HashMap<String, Vec<usize>>,
+    by_position: HashMap<(String, u64), Vec<usize>>,
+}
+
+impl DbSnpIndex {
+    /// Load a dbSNP VCF, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_vcf(reader)
+    }
+
+    /// Load dbSNP records from VCF text
+    ///
+    /// RefSeq chromosome accessions such as `NC_000001.11` are translated
+    /// to chromosome names; records on other contigs are skipped.
+    pub fn from_vcf<R: BufRead>(reader: R) -> Result<Self, String> {
+        let reader = VcfReader::new(reader)?;
+        let header = reader.header();
+        let meta: Vec<String> = header
+            .other
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        let genome_build = detect_genome_build(&meta);
+        let release_date = header
+            .other
+            .iter()
+            .find(|(key, _)| key == "fileDate")
+            .map(|(_, date)| format_file_date(date));
+
+        let mut records = Vec::new();
+        for record in reader {
+            let record = record.map_err(|e| format!("dbSNP {}", e))?;
+            let rsid = record
+                .id
+                .as_deref()
+                .and_then(normalize_rsid)
+                .or_else(|| record.info_value("RS").and_then(normalize_rsid));
+            let (Some(rsid), Some(chromosome)) = (rsid, refseq_chromosome(&record.chromosome))
+            else {
+                continue;
+            };
+            if !is_allele_sequence(&record.reference) {
+                continue;
+            }
+            records.push(DbSnpRecord {
+                rsid,
+                chromosome,
+                position: record.position,
+                reference: record.reference.to_ascii_uppercase(),
+                alternates: record
+                    .alternates
+                    .iter()
+                    .filter(|alternate| is_allele_sequence(alternate))
+                    .map(|alternate| alternate.to_ascii_uppercase())
+                    .collect(),
+            });
+        }
+
+        Ok(Self::from_records(records, genome_build, release_date))
+    }
+
+    /// Build the index from already parsed records
+    pub fn from_records(
+        records: Vec<DbSnpRecord>,
+        genome_build: Option<GenomeBuild>,
+        release_date: Option<String>,
+    ) -> Self {
+        let mut by_rsid: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_position: HashMap<(String, u64), Vec<usize>> = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            by_rsid.entry(record.rsid.clone()).or_default().push(index);
+            by_position
+                .entry((record.chromosome.clone(), record.position))
+                .or_default()
+                .push(index);
+        }
+
+        DbSnpIndex {
+            genome_build,
+            release_date,
+            records,
+            by_rsid,
+            by_position,
+        }
+    }
+
+    /// Every record, in release order
+    pub fn records(&self) -> &[DbSnpRecord] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Build the positions refer to, from the VCF `##reference` line
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        self.genome_build
+    }
+
+    /// Release date from the VCF `fileDate` header, as YYYY-MM-DD
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    /// Records for an rsid, usually one per position it maps to
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<&DbSnpRecord> {
+        self.records_at(self.by_rsid.get(rsid))
+    }
+
+    /// Records at a position
+    pub fn lookup_position(&self, chromosome: &str, position: u64) -> Vec<&DbSnpRecord> {
+        let key = (normalize_chromosome(chromosome), position);
+        self.records_at(self.by_position.get(&key))
+    }
+
+    /// rsid of the site at a position with the given alleles
+    ///
+    /// The reference must match and, when alternates are given, at least
+    /// one of them must be a known alternate at that site.
+    pub fn resolve_rsid(
+        &self,
+        chromosome: &str,
+        position: u64,
+        reference: &str,
+        alternates: &[String],
+    ) -> Option<&str> {
+        self.lookup_position(chromosome, position)
+            .into_iter()
+            .find(|record| {
+                record.reference.eq_ignore_ascii_case(reference)
+                    && (alternates.is_empty()
+                        || alternates.iter().any(|alternate| {
+                            record
+                                .alternates
+                                .iter()
+                                .any(|known| known.eq_ignore_ascii_case(alternate))
+                        }))
+            })
+            .map(|record| record.rsid.as_str())
+    }
+
+    /// Fill in the rsid or alleles a variant is missing
+    ///
+    /// `same_build` says whether the variant's coordinates are on this
+    /// index's build; when they are not, only the rsid is trusted. Array
+    /// calls only gain alleles when every called base is a known allele of
+    /// a single-nucleotide site, so D/I indel calls and calls reported on
+    /// the opposite strand are left alone. Returns whether the rsid and
+    /// the alleles were filled in.
+    pub fn resolve(&self, variant: &mut Variant, same_build: bool) -> (bool, bool) {
+        let has_rsid = variant.rsid.as_deref().and_then(normalize_rsid).is_some();
+        let mut rsid_added = false;
+        if !has_rsid && same_build {
+            if let Some(reference) = &variant.reference {
+                if let Some(rsid) = self.resolve_rsid(
+                    &variant.chromosome,
+                    variant.position,
+                    reference,
+                    &variant.alternates,
+                ) {
+                    variant.rsid = Some(rsid.to_string());
+                    rsid_added = true;
+                }
+            }
+        }
+
+        let mut alleles_added = false;
+        if variant.reference.is_none() && !variant.genotype.is_no_call() {
+            let rsid = variant.rsid.as_deref().and_then(normalize_rsid);
+            let record = rsid.and_then(|rsid| {
+                self.lookup_rsid(&rsid).into_iter().find(|record| {
+                    record.chromosome == variant.chromosome
+                        && (!same_build || record.position == variant.position)
+                })
+            });
+            if let Some(record) = record.filter(|record| calls_fit(variant, record)) {
+                variant.reference = Some(record.reference.clone());
+                variant.alternates = record
+                    .alternates
+                    .iter()
+                    .filter(|alternate| alternate.len() == 1)
+                    .cloned()
+                    .collect();
+                alleles_added = true;
+            }
+        }
+
+        (rsid_added, alleles_added)
+    }
+
+    /// Copy of `genome` with missing rsids and alleles filled in
+    ///
+    /// `checkpoint` is called with the number of variants resolved every
+    /// [`CHECKPOINT_INTERVAL`] records.
+    pub fn normalize<F>(
+        &self,
+        genome: &LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<(LoadedGenome, Normalization), String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let same_build = match (genome.file.genome_build, self.genome_build) {
+            (Some(genome_build), Some(build)) => genome_build == build,
+            _ => true,
+        };
+        let mut normalization = Normalization::default();
+        let mut variants = Vec::with_capacity(genome.len());
+
+        for (index, variant) in genome.variants().iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            let mut variant = variant.clone();
+            let (rsid_added, alleles_added) = self.resolve(&mut variant, same_build);
+            normalization.rsids_added += usize::from(rsid_added);
+            normalization.alleles_added += usize::from(alleles_added);
+            variants.push(variant);
+        }
+
+        let genome =
+            LoadedGenome::from_variants(genome.file.clone(), genome.summary.clone(), variants);
+        Ok((genome, normalization))
+    }
+
+    fn records_at(&self, indexes: Option<&Vec<usize>>) -> Vec<&DbSnpRecord> {
+        indexes
+            .map(|indexes| indexes.iter().map(|&i| &self.records[i]).collect())
+            .unwrap_or_default()
+    }
+}
+
+// Helper functions
+
+/// Whether every called base is an allele of a single-nucleotide site
+fn calls_fit(variant: &Variant, record: &DbSnpRecord) -> bool {
+    if record.reference.len() != 1 {
+        return false;
+    }
+    variant.genotype.alleles().iter().all(|allele| {
+        *allele == record.reference || record.alternates.iter().any(|alt| alt == allele)
+    })
+}
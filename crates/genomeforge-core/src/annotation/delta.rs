@@ -0,0 +1,218 @@
+//! Changes between two ClinVar releases
+//!
+//! Each release reclassifies a small share of the alleles in the one
+//! before it. [`ReleaseDelta`] finds the records added, removed and changed
+//! between two releases, so a genome annotated against the older one is
+//! brought up to date by annotating only the variants those records can
+//! match, against each release.
+//!
+//! Zygosity, carrier status and ACMG screening read the variants of a gene
+//! together, so a gene with any changed record is annotated again in full:
+//! the delta keeps both releases' records for every gene and site it
+//! touches.
+
+use super::clinvar::{ClinVarDatabase, ClinVarRecord};
+use crate::genome::GenomeBuild;
+use crate::liftover;
+use crate::parser::SummaryBuilder;
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Identity of a record across releases
+type RecordKey<'a> = (
+    Option<GenomeBuild>,
+    &'a str,
+    u64,
+    &'a str,
+    &'a str,
+    Option<u64>,
+);
+
+/// What changed between two ClinVar releases, and the records of both at
+/// the genes and sites that changed
+#[derive(Debug)]
+pub struct ReleaseDelta {
+    added: usize,
+    removed: usize,
+    changed: usize,
+    previous: Arc<ClinVarDatabase>,
+    current: Arc<ClinVarDatabase>,
+    sites: SiteFilter,
+}
+
+impl ReleaseDelta {
+    /// Compare the release a genome was annotated against with a newer one
+    pub fn between(previous: &ClinVarDatabase, current: &ClinVarDatabase) -> Self {
+        let before = index(previous.records());
+        let after = index(current.records());
+
+        let mut touched = Touched::default();
+        let (mut added, mut removed, mut changed) = (0, 0, 0);
+        for (key, record) in &after {
+            match before.get(key) {
+                None => {
+                    added += 1;
+                    touched.add(record);
+                }
+                Some(old) if !same_classification(old, record) => {
+                    changed += 1;
+                    touched.add(old);
+                    touched.add(record);
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, record) in &before {
+            if !after.contains_key(key) {
+                removed += 1;
+                touched.add(record);
+            }
+        }
+
+        let previous = touched.subset(previous);
+        let current = touched.subset(current);
+        let mut sites = SiteFilter::default();
+        previous.add_sites(&mut sites);
+        current.add_sites(&mut sites);
+        ReleaseDelta {
+            added,
+            removed,
+            changed,
+            previous: Arc::new(previous),
+            current: Arc::new(current),
+            sites,
+        }
+    }
+
+    /// Records only in the newer release
+    pub fn added(&self) -> usize {
+        self.added
+    }
+
+    /// Records only in the older release
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Records in both releases whose classification, review status,
+    /// conditions or genes differ
+    pub fn changed(&self) -> usize {
+        self.changed
+    }
+
+    /// Whether the two releases classify every allele alike
+    pub fn is_empty(&self) -> bool {
+        self.added + self.removed + self.changed == 0
+    }
+
+    /// Release date of the older release
+    pub fn from_release(&self) -> Option<&str> {
+        self.previous.release_date()
+    }
+
+    /// Release date of the newer release
+    pub fn to_release(&self) -> Option<&str> {
+        self.current.release_date()
+    }
+
+    /// Records of the older release at the touched genes and sites
+    pub fn previous(&self) -> &Arc<ClinVarDatabase> {
+        &self.previous
+    }
+
+    /// Records of the newer release at the touched genes and sites
+    pub fn current(&self) -> &Arc<ClinVarDatabase> {
+        &self.current
+    }
+
+    /// The variants of a genome either release's records there can match,
+    /// as a genome of their own
+    ///
+    /// Variants are picked by rsid and by position as given in the genome.
+    /// The genome's build is kept even when it was detected from marker
+    /// sites that are not among the variants picked.
+    pub fn touched(&self, genome: &LoadedGenome) -> LoadedGenome {
+        let variants: Vec<_> = genome
+            .variants()
+            .iter()
+            .filter(|variant| self.sites.contains(variant))
+            .cloned()
+            .collect();
+        let mut builder = SummaryBuilder::default();
+        for variant in &variants {
+            builder.add(variant);
+        }
+        let mut file = genome.file.clone();
+        file.genome_build = liftover::detect_build(genome);
+        LoadedGenome::from_variants(file, builder.finish(0), variants)
+    }
+}
+
+// Helper functions
+
+fn index(records: &[ClinVarRecord]) -> HashMap<RecordKey<'_>, &ClinVarRecord> {
+    records
+        .iter()
+        .map(|record| {
+            let key = (
+                record.genome_build,
+                record.chromosome.as_str(),
+                record.position,
+                record.reference.as_str(),
+                record.alternate.as_str(),
+                record.variation_id,
+            );
+            (key, record)
+        })
+        .collect()
+}
+
+fn same_classification(a: &ClinVarRecord, b: &ClinVarRecord) -> bool {
+    a.significance_label == b.significance_label
+        && a.review_status == b.review_status
+        && a.conditions == b.conditions
+        && a.genes == b.genes
+        && a.rsid == b.rsid
+}
+
+/// Genes and sites of the records that changed
+#[derive(Default)]
+struct Touched {
+    genes: HashSet<String>,
+    rsids: HashSet<String>,
+    positions: HashSet<(String, u64)>,
+}
+
+impl Touched {
+    fn add(&mut self, record: &ClinVarRecord) {
+        self.genes.extend(record.genes.iter().cloned());
+        if let Some(rsid) = &record.rsid {
+            self.rsids.insert(rsid.clone());
+        }
+        self.positions
+            .insert((record.chromosome.clone(), record.position));
+    }
+
+    fn contains(&self, record: &ClinVarRecord) -> bool {
+        record.genes.iter().any(|gene| self.genes.contains(gene))
+            || record
+                .rsid
+                .as_ref()
+                .is_some_and(|rsid| self.rsids.contains(rsid))
+            || self
+                .positions
+                .contains(&(record.chromosome.clone(), record.position))
+    }
+
+    fn subset(&self, release: &ClinVarDatabase) -> ClinVarDatabase {
+        let records = release
+            .records()
+            .iter()
+            .filter(|record| self.contains(record))
+            .cloned()
+            .collect();
+        ClinVarDatabase::from_records(records, release.release_date().map(str::to_string))
+    }
+}
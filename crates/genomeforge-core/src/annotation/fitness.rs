@@ -0,0 +1,110 @@
+//! Athletic performance, injury and recovery markers
+//!
+//! Fitness variants come from candidate-gene studies and their
+//! meta-analyses rather than the GWAS Catalog, and none reaches
+//! genome-wide significance, so they are bundled here as trait
+//! associations of their own category. Each carries the effect size and
+//! PubMed ID of the study it was taken from; effects are small and say
+//! far less than training does.
+//!
+//! The ACE insertion/deletion polymorphism cannot be genotyped by arrays
+//! and is read from rs4343, whose A allele tags the insertion.
+
+use super::gwas::{EffectSize, GwasAssociation, GwasCatalog, TraitCategory};
+use crate::stream::SiteFilter;
+
+/// A bundled fitness association
+#[derive(Debug, Clone, Copy)]
+pub struct FitnessMarker {
+    pub rsid: &'static str,
+    /// Plus-strand allele the effect is reported for
+    pub risk_allele: &'static str,
+    pub trait_name: &'static str,
+    pub genes: &'static [&'static str],
+    pub effect: EffectSize,
+    pub p_value: f64,
+    pub pubmed_id: &'static str,
+}
+
+/// Markers of the panel
+pub const MARKERS: [FitnessMarker; 5] = [
+    FitnessMarker {
+        rsid: "rs1815739",
+        risk_allele: "C",
+        trait_name: "Sprint and power performance (ACTN3 R577X)",
+        genes: &["ACTN3"],
+        effect: EffectSize::OddsRatio(1.21),
+        p_value: 1e-4,
+        pubmed_id: "12879365",
+    },
+    FitnessMarker {
+        rsid: "rs4343",
+        risk_allele: "A",
+        trait_name: "Endurance performance (ACE insertion allele)",
+        genes: &["ACE"],
+        effect: EffectSize::OddsRatio(1.35),
+        p_value: 1e-3,
+        pubmed_id: "23358679",
+    },
+    FitnessMarker {
+        rsid: "rs12722",
+        risk_allele: "T",
+        trait_name: "Achilles tendinopathy",
+        genes: &["COL5A1"],
+        effect: EffectSize::OddsRatio(1.6),
+        p_value: 4e-3,
+        pubmed_id: "18927158",
+    },
+    FitnessMarker {
+        rsid: "rs1800012",
+        risk_allele: "A",
+        trait_name: "Anterior cruciate ligament rupture (COL1A1 Sp1)",
+        genes: &["COL1A1"],
+        effect: EffectSize::OddsRatio(0.45),
+        p_value: 2e-3,
+        pubmed_id: "19910511",
+    },
+    FitnessMarker {
+        rsid: "rs1800795",
+        risk_allele: "C",
+        trait_name: "Muscle damage after strenuous exercise (IL6 -174G>C)",
+        genes: &["IL6"],
+        effect: EffectSize::OddsRatio(1.5),
+        p_value: 1e-2,
+        pubmed_id: "18463891",
+    },
+];
+
+/// The panel as a catalog, matched like GWAS associations
+///
+/// Match it with a `max_p_value` of 1.0: the studies report nominal
+/// significance only.
+pub fn catalog() -> GwasCatalog {
+    let associations = MARKERS
+        .iter()
+        .map(|marker| GwasAssociation {
+            rsid: marker.rsid.to_string(),
+            risk_allele: marker.risk_allele.to_string(),
+            trait_name: marker.trait_name.to_string(),
+            mapped_trait: None,
+            category: TraitCategory::Fitness,
+            p_value: marker.p_value,
+            effect: Some(marker.effect),
+            risk_allele_frequency: None,
+            genes: marker.genes.iter().map(|gene| gene.to_string()).collect(),
+            chromosome: None,
+            position: None,
+            pubmed_id: Some(marker.pubmed_id.to_string()),
+            study: None,
+            added: None,
+        })
+        .collect();
+    GwasCatalog::from_associations(associations)
+}
+
+/// Add the markers to `sites`
+pub fn add_sites(sites: &mut SiteFilter) {
+    for marker in &MARKERS {
+        sites.add_rsid(marker.rsid);
+    }
+}
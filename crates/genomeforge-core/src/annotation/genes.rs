@@ -0,0 +1,93 @@
+//! Coordinates of commonly searched genes
+//!
+//! A small built-in table so that a gene symbol can be turned into a region
+//! without an installed annotation database. Spans run from the first to
+//! the last base of the gene's longest reference transcript; regulatory
+//! variants just outside them, such as the VKORC1 and CYP2C19 promoter
+//! SNPs, need a wider region.
+
+use crate::genome::{GenomeBuild, Region};
+use serde::Serialize;
+
+/// Where a gene lies on each supported build
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct GeneLocation {
+    pub symbol: &'static str,
+    pub chromosome: &'static str,
+    pub grch37: (u64, u64),
+    pub grch38: (u64, u64),
+}
+
+impl GeneLocation {
+    /// Span of the gene on a build; GRCh36 is not covered
+    pub fn region(&self, build: GenomeBuild) -> Option<Region> {
+        let (start, end) = match build {
+            GenomeBuild::GRCh37 => self.grch37,
+            GenomeBuild::GRCh38 => self.grch38,
+            GenomeBuild::GRCh36 => return None,
+        };
+        Some(Region::new(self.chromosome, start, end))
+    }
+}
+
+const fn gene(
+    symbol: &'static str,
+    chromosome: &'static str,
+    grch37: (u64, u64),
+    grch38: (u64, u64),
+) -> GeneLocation {
+    GeneLocation {
+        symbol,
+        chromosome,
+        grch37,
+        grch38,
+    }
+}
+
+/// Genes that can be searched by symbol
+pub const GENES: [GeneLocation; 32] = [
+    gene("APC", "5", (112043195, 112181936), (112707498, 112846239)),
+    gene("APOB", "2", (21224301, 21266945), (21001429, 21044073)),
+    gene("APOE", "19", (45409039, 45412650), (44905796, 44909393)),
+    gene("BRCA1", "17", (41196312, 41277500), (43044295, 43125483)),
+    gene("BRCA2", "13", (32889617, 32973809), (32315508, 32400268)),
+    gene("CFTR", "7", (117120079, 117308718), (117480025, 117668665)),
+    gene("CYP2C19", "10", (96522463, 96612671), (94762681, 94852914)),
+    gene("CYP2C9", "10", (96698415, 96749848), (94938658, 94990091)),
+    gene("CYP2D6", "22", (42522501, 42526883), (42126499, 42130881)),
+    gene("DMD", "X", (31115794, 33357558), (31097677, 33339441)),
+    gene("DPYD", "1", (97543299, 98386605), (97077743, 97921049)),
+    gene("F2", "11", (46740763, 46761056), (46719213, 46739506)),
+    gene("F5", "1", (169481189, 169555719), (169511951, 169586481)),
+    gene("G6PD", "X", (153759605, 153775787), (154531390, 154547572)),
+    gene("GBA1", "1", (155204243, 155214418), (155234452, 155244627)),
+    gene("HBA1", "16", (226679, 227521), (176680, 177522)),
+    gene("HBB", "11", (5246694, 5250625), (5225464, 5229395)),
+    gene("HFE", "6", (26087509, 26098571), (26087281, 26098343)),
+    gene("LCT", "2", (136545420, 136594754), (135787850, 135837184)),
+    gene("LDLR", "19", (11200138, 11244496), (11089462, 11133820)),
+    gene("MLH1", "3", (37034823, 37092409), (36993332, 37050918)),
+    gene("MTHFR", "1", (11845780, 11866512), (11785723, 11806455)),
+    gene("MUTYH", "1", (45794835, 45806112), (45329163, 45340440)),
+    gene("MYBPC3", "11", (47352957, 47374253), (47331406, 47352702)),
+    gene("PCSK9", "1", (55505221, 55530525), (55039548, 55064852)),
+    gene("RYR1", "19", (38924331, 39078204), (38433691, 38587564)),
+    gene("SLCO1B1", "12", (21281127, 21392730), (21128193, 21239796)),
+    gene("SMN1", "5", (70220768, 70248842), (70924941, 70953015)),
+    gene("TP53", "17", (7565097, 7590856), (7661779, 7687538)),
+    gene("TPMT", "6", (18128542, 18155536), (18128311, 18155305)),
+    gene("TTR", "18", (29171729, 29178986), (31591766, 31599023)),
+    gene("VKORC1", "16", (31102175, 31106118), (31090854, 31094797)),
+];
+
+/// Look up a gene by symbol, ignoring case
+pub fn find(symbol: &str) -> Option<&'static GeneLocation> {
+    GENES
+        .iter()
+        .find(|gene| gene.symbol.eq_ignore_ascii_case(symbol.trim()))
+}
+
+/// Span of a gene on a build
+pub fn locate(symbol: &str, build: GenomeBuild) -> Option<Region> {
+    find(symbol).and_then(|gene| gene.region(build))
+}
@@ -0,0 +1,349 @@
+//! gnomAD population allele frequencies
+//!
+//! Reads a local summary of the gnomAD sites VCF: one line per site with
+//! the `AF` INFO field for every ALT allele plus the `AF_<population>`
+//! fields for the genetic ancestry groups gnomAD reports.
+
+use super::{format_file_date, is_allele_sequence, normalize_rsid};
+use crate::genome::{GenomeBuild, Variant};
+use crate::parser::vcf::{VcfReader, VcfRecord};
+use crate::parser::{compression, detect_genome_build, normalize_chromosome};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+/// gnomAD genetic ancestry group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Population {
+    /// African/African American
+    Afr,
+    /// Admixed American
+    Amr,
+    /// Ashkenazi Jewish
+    Asj,
+    /// East Asian
+    Eas,
+    /// Finnish
+    Fin,
+    /// Middle Eastern
+    Mid,
+    /// Non-Finnish European
+    Nfe,
+    /// South Asian
+    Sas,
+}
+
+impl Population {
+    pub const ALL: [Population; 8] = [
+        Population::Afr,
+        Population::Amr,
+        Population::Asj,
+        Population::Eas,
+        Population::Fin,
+        Population::Mid,
+        Population::Nfe,
+        Population::Sas,
+    ];
+
+    /// Suffix of the population's INFO field, e.g. "nfe" for `AF_nfe`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Population::Afr => "afr",
+            Population::Amr => "amr",
+            Population::Asj => "asj",
+            Population::Eas => "eas",
+            Population::Fin => "fin",
+            Population::Mid => "mid",
+            Population::Nfe => "nfe",
+            Population::Sas => "sas",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Population::Afr => "African/African American",
+            Population::Amr => "Admixed American",
+            Population::Asj => "Ashkenazi Jewish",
+            Population::Eas => "East Asian",
+            Population::Fin => "Finnish",
+            Population::Mid => "Middle Eastern",
+            Population::Nfe => "Non-Finnish European",
+            Population::Sas => "South Asian",
+        }
+    }
+}
+
+/// Frequency of one allele in one population
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PopulationFrequency {
+    pub population: Population,
+    pub frequency: f64,
+}
+
+/// Frequencies of one ALT allele
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlleleFrequencies {
+    /// Frequency across all gnomAD samples
+    pub global: f64,
+    /// Populations the release reports a frequency for
+    pub populations: Vec<PopulationFrequency>,
+}
+
+impl AlleleFrequencies {
+    /// Frequency in one population, if reported
+    pub fn population(&self, population: Population) -> Option<f64> {
+        self.populations
+            .iter()
+            .find(|entry| entry.population == population)
+            .map(|entry| entry.frequency)
+    }
+
+    /// Highest frequency globally or in any population
+    ///
+    /// A variant common in any one population is unlikely to cause a rare
+    /// disease, so this is the frequency compared against AF thresholds.
+    pub fn max_frequency(&self) -> f64 {
+        self.populations
+            .iter()
+            .map(|entry| entry.frequency)
+            .fold(self.global, f64::max)
+    }
+}
+
+/// One ALT allele of a gnomAD site
+#[derive(Debug, Clone, Serialize)]
+pub struct GnomadRecord {
+    pub rsid: Option<String>,
+    pub chromosome: String,
+    pub position: u64,
+    pub reference: String,
+    pub alternate: String,
+    pub frequencies: AlleleFrequencies,
+}
+
+/// Indexed gnomAD frequency summary for one genome build
+#[derive(Debug, Default)]
+pub struct GnomadDatabase {
+    genome_build: Option<GenomeBuild>,
+    release_date: Option<String>,
+    records: Vec<GnomadRecord>,
+    by_allele: HashMap<(String, u64, String, String), usize>,
+    by_rsid: HashMap<String, Vec<usize>>,
+}
+
+impl GnomadDatabase {
+    /// Load a gnomAD sites VCF, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_vcf(reader)
+    }
+
+    /// Load frequencies from VCF text
+    ///
+    /// Alleles without an `AF` value are skipped.
+    pub fn from_vcf<R: BufRead>(reader: R) -> Result<Self, String> {
+        let reader = VcfReader::new(reader)?;
+        let header = reader.header();
+        let meta: Vec<String> = header
+            .other
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        let genome_build = detect_genome_build(&meta);
+        let release_date = header
+            .other
+            .iter()
+            .find(|(key, _)| key == "fileDate")
+            .map(|(_, date)| format_file_date(date));
+
+        let mut records = Vec::new();
+        for record in reader {
+            let record = record.map_err(|e| format!("gnomAD {}", e))?;
+            records.extend(vcf_records(&record));
+        }
+
+        Ok(Self::from_records(records, genome_build, release_date))
+    }
+
+    /// Build the index from already parsed records
+    pub fn from_records(
+        records: Vec<GnomadRecord>,
+        genome_build: Option<GenomeBuild>,
+        release_date: Option<String>,
+    ) -> Self {
+        let mut by_allele = HashMap::with_capacity(records.len());
+        let mut by_rsid: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, record) in records.iter().enumerate() {
+            by_allele
+                .entry(allele_key(
+                    &record.chromosome,
+                    record.position,
+                    &record.reference,
+                    &record.alternate,
+                ))
+                .or_insert(index);
+            if let Some(rsid) = &record.rsid {
+                by_rsid.entry(rsid.clone()).or_default().push(index);
+            }
+        }
+
+        GnomadDatabase {
+            genome_build,
+            release_date,
+            records,
+            by_allele,
+            by_rsid,
+        }
+    }
+
+    /// Every record, in release order
+    pub fn records(&self) -> &[GnomadRecord] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Build the positions refer to, from the VCF `##reference` line
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        self.genome_build
+    }
+
+    /// Release date from the VCF `fileDate` header, as YYYY-MM-DD
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    /// Record for an exact allele
+    pub fn lookup_allele(
+        &self,
+        chromosome: &str,
+        position: u64,
+        reference: &str,
+        alternate: &str,
+    ) -> Option<&GnomadRecord> {
+        self.by_allele
+            .get(&allele_key(chromosome, position, reference, alternate))
+            .map(|&index| &self.records[index])
+    }
+
+    /// Records for every ALT allele listed under an rsid
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<&GnomadRecord> {
+        self.by_rsid
+            .get(rsid)
+            .map(|indexes| indexes.iter().map(|&i| &self.records[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Frequencies of `alternate` at a genome variant's site
+    ///
+    /// Matches by position when the genome is on the release's build and
+    /// falls back to the rsid otherwise. Array indels reported as D/I are
+    /// not resolved to alleles here, so `alternate` must be spelled out.
+    pub fn frequency(
+        &self,
+        variant: &Variant,
+        build: Option<GenomeBuild>,
+        reference: &str,
+        alternate: &str,
+    ) -> Option<&AlleleFrequencies> {
+        let same_build = match (build, self.genome_build) {
+            (Some(genome_build), Some(release_build)) => genome_build == release_build,
+            _ => true,
+        };
+        if same_build {
+            let found =
+                self.lookup_allele(&variant.chromosome, variant.position, reference, alternate);
+            if let Some(record) = found {
+                return Some(&record.frequencies);
+            }
+        }
+        let rsid = variant.rsid.as_deref().and_then(normalize_rsid)?;
+        self.lookup_rsid(&rsid)
+            .into_iter()
+            .find(|record| {
+                record.reference.eq_ignore_ascii_case(reference)
+                    && record.alternate.eq_ignore_ascii_case(alternate)
+            })
+            .map(|record| &record.frequencies)
+    }
+}
+
+// Helper functions
+
+/// One record per ALT allele of a gnomAD VCF line
+fn vcf_records(record: &VcfRecord) -> Vec<GnomadRecord> {
+    let Some(global) = record.info_value("AF").map(per_allele) else {
+        return Vec::new();
+    };
+    let populations: Vec<(Population, Vec<Option<f64>>)> = Population::ALL
+        .into_iter()
+        .filter_map(|population| {
+            let key = format!("AF_{}", population.code());
+            record
+                .info_value(&key)
+                .map(|af| (population, per_allele(af)))
+        })
+        .collect();
+    let rsid = record
+        .id
+        .as_deref()
+        .and_then(|ids| ids.split(';').find_map(normalize_rsid));
+
+    record
+        .alternates
+        .iter()
+        .enumerate()
+        .filter(|(_, alternate)| is_allele_sequence(alternate))
+        .filter_map(|(allele, alternate)| {
+            let global = global.get(allele).copied().flatten()?;
+            let populations = populations
+                .iter()
+                .filter_map(|(population, values)| {
+                    let frequency = values.get(allele).copied().flatten()?;
+                    Some(PopulationFrequency {
+                        population: *population,
+                        frequency,
+                    })
+                })
+                .collect();
+            Some(GnomadRecord {
+                rsid: rsid.clone(),
+                chromosome: normalize_chromosome(&record.chromosome),
+                position: record.position,
+                reference: record.reference.to_ascii_uppercase(),
+                alternate: alternate.to_ascii_uppercase(),
+                frequencies: AlleleFrequencies {
+                    global,
+                    populations,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Values of a `Number=A` INFO field; "." entries are missing
+fn per_allele(raw: &str) -> Vec<Option<f64>> {
+    raw.split(',').map(|value| value.parse().ok()).collect()
+}
+
+fn allele_key(
+    chromosome: &str,
+    position: u64,
+    reference: &str,
+    alternate: &str,
+) -> (String, u64, String, String) {
+    (
+        normalize_chromosome(chromosome),
+        position,
+        reference.to_ascii_uppercase(),
+        alternate.to_ascii_uppercase(),
+    )
+}
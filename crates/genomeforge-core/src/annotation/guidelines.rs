@@ -0,0 +1,348 @@
+//! Bundled CPIC guideline recommendations
+//!
+//! A CPIC release only carries recommendations when its directory has a
+//! `cpic_recommendations.tsv`, and many installed releases hold the allele
+//! tables alone. This built-in table holds the therapeutic recommendations
+//! of the CPIC guidelines for the genes the calling supports, keyed by gene
+//! and phenotype as [`CpicDatabase::recommendations`] is, so a called
+//! diplotype still comes with dosing guidance. Release recommendations
+//! take precedence over these for the same drug.
+//!
+//! [`CpicDatabase::recommendations`]: super::cpic::CpicDatabase::recommendations
+
+use super::cpic::{self, Recommendation};
+
+/// A published CPIC guideline
+#[derive(Debug, Clone, Copy)]
+pub struct Guideline {
+    pub title: &'static str,
+    /// Publication the recommendations come from, e.g. "2022 update"
+    pub version: &'static str,
+}
+
+/// One recommendation of the table
+#[derive(Debug, Clone, Copy)]
+pub struct BundledRecommendation {
+    pub gene: &'static str,
+    pub drug: &'static str,
+    pub phenotype: &'static str,
+    pub implication: &'static str,
+    pub recommendation: &'static str,
+    /// "Strong", "Moderate" or "Optional"
+    pub strength: &'static str,
+    pub guideline: &'static Guideline,
+}
+
+impl BundledRecommendation {
+    pub fn to_recommendation(&self) -> Recommendation {
+        Recommendation {
+            gene: self.gene.to_string(),
+            drug: self.drug.to_string(),
+            phenotype: self.phenotype.to_string(),
+            implication: Some(self.implication.to_string()),
+            recommendation: self.recommendation.to_string(),
+            classification: Some(self.strength.to_string()),
+            guideline: Some(self.guideline.title.to_string()),
+            version: Some(self.guideline.version.to_string()),
+        }
+    }
+}
+
+const fn row(
+    gene: &'static str,
+    drug: &'static str,
+    phenotype: &'static str,
+    implication: &'static str,
+    recommendation: &'static str,
+    strength: &'static str,
+    guideline: &'static Guideline,
+) -> BundledRecommendation {
+    BundledRecommendation {
+        gene,
+        drug,
+        phenotype,
+        implication,
+        recommendation,
+        strength,
+        guideline,
+    }
+}
+
+const CLOPIDOGREL: Guideline = Guideline {
+    title: "CPIC guideline for CYP2C19 genotype and clopidogrel therapy",
+    version: "2022 update",
+};
+const SSRI: Guideline = Guideline {
+    title: "CPIC guideline for CYP2D6, CYP2C19, CYP2B6, SLC6A4 and HTR2A genotypes and serotonin reuptake inhibitor antidepressants",
+    version: "2023 update",
+};
+const OPIOIDS: Guideline = Guideline {
+    title: "CPIC guideline for CYP2D6, OPRM1 and COMT genotypes and select opioid therapy",
+    version: "2021",
+};
+const NSAIDS: Guideline = Guideline {
+    title: "CPIC guideline for CYP2C9 genotype and nonsteroidal anti-inflammatory drugs",
+    version: "2020",
+};
+const FLUOROPYRIMIDINES: Guideline = Guideline {
+    title: "CPIC guideline for DPYD genotype and fluoropyrimidine dosing",
+    version: "2017 update",
+};
+const STATINS: Guideline = Guideline {
+    title: "CPIC guideline for SLCO1B1, ABCG2 and CYP2C9 genotypes and statin-associated musculoskeletal symptoms",
+    version: "2022 update",
+};
+
+const CLOPIDOGREL_ALTERNATIVE: &str = "Avoid standard dose clopidogrel if possible. Use prasugrel or ticagrelor at standard dose if there is no contraindication.";
+const CLOPIDOGREL_STANDARD: &str = "If considering clopidogrel, use at standard dose (75 mg/day).";
+const STANDARD_STARTING_DOSE: &str = "Initiate therapy with the recommended starting dose.";
+const CODEINE_AVOID_TOXICITY: &str = "Avoid codeine because of the potential for serious toxicity. If opioid use is warranted, consider a non-tramadol opioid.";
+const CODEINE_STANDARD: &str = "Use the label recommended age- or weight-specific dosing.";
+const FLUOROURACIL_STANDARD: &str = "Based on genotype, there is no indication to change dose or therapy. Use the label recommended dosage and administration.";
+const SIMVASTATIN_STANDARD: &str =
+    "Prescribe the desired starting dose and adjust doses based on disease-specific guidelines.";
+const SIMVASTATIN_ALTERNATIVE: &str = "Prescribe an alternative statin depending on the desired potency. If simvastatin therapy is warranted, limit the dose to less than 20 mg/day.";
+
+/// Recommendations of the bundled guidelines
+pub const RECOMMENDATIONS: [BundledRecommendation; 24] = [
+    row(
+        "CYP2C19",
+        "clopidogrel",
+        "Poor Metabolizer",
+        "Significantly reduced clopidogrel active metabolite formation; increased risk for cardiovascular and cerebrovascular events.",
+        CLOPIDOGREL_ALTERNATIVE,
+        "Strong",
+        &CLOPIDOGREL,
+    ),
+    row(
+        "CYP2C19",
+        "clopidogrel",
+        "Intermediate Metabolizer",
+        "Reduced clopidogrel active metabolite formation; increased risk for cardiovascular and cerebrovascular events.",
+        CLOPIDOGREL_ALTERNATIVE,
+        "Moderate",
+        &CLOPIDOGREL,
+    ),
+    row(
+        "CYP2C19",
+        "clopidogrel",
+        "Normal Metabolizer",
+        "Normal clopidogrel active metabolite formation.",
+        CLOPIDOGREL_STANDARD,
+        "Strong",
+        &CLOPIDOGREL,
+    ),
+    row(
+        "CYP2C19",
+        "clopidogrel",
+        "Rapid Metabolizer",
+        "Normal or increased clopidogrel active metabolite formation.",
+        CLOPIDOGREL_STANDARD,
+        "Strong",
+        &CLOPIDOGREL,
+    ),
+    row(
+        "CYP2C19",
+        "clopidogrel",
+        "Ultrarapid Metabolizer",
+        "Normal or increased clopidogrel active metabolite formation.",
+        CLOPIDOGREL_STANDARD,
+        "Strong",
+        &CLOPIDOGREL,
+    ),
+    row(
+        "CYP2C19",
+        "citalopram",
+        "Poor Metabolizer",
+        "Greatly increased exposure to citalopram compared with normal metabolizers; higher risk of side effects.",
+        "Consider an antidepressant not predominantly metabolized by CYP2C19. If citalopram is clinically appropriate, consider a lower starting dose, slower titration and a 50% reduction of the standard maintenance dose.",
+        "Moderate",
+        &SSRI,
+    ),
+    row(
+        "CYP2C19",
+        "citalopram",
+        "Intermediate Metabolizer",
+        "Increased exposure to citalopram compared with normal metabolizers.",
+        "Initiate therapy with the recommended starting dose. Consider a slower titration schedule and a lower maintenance dose.",
+        "Optional",
+        &SSRI,
+    ),
+    row(
+        "CYP2C19",
+        "citalopram",
+        "Normal Metabolizer",
+        "Normal metabolism of citalopram.",
+        STANDARD_STARTING_DOSE,
+        "Strong",
+        &SSRI,
+    ),
+    row(
+        "CYP2C19",
+        "citalopram",
+        "Rapid Metabolizer",
+        "Slightly decreased exposure to citalopram compared with normal metabolizers.",
+        STANDARD_STARTING_DOSE,
+        "Moderate",
+        &SSRI,
+    ),
+    row(
+        "CYP2C19",
+        "citalopram",
+        "Ultrarapid Metabolizer",
+        "Decreased exposure to citalopram compared with normal metabolizers; higher risk of treatment failure.",
+        "Consider an antidepressant not predominantly metabolized by CYP2C19.",
+        "Moderate",
+        &SSRI,
+    ),
+    row(
+        "CYP2D6",
+        "codeine",
+        "Ultrarapid Metabolizer",
+        "Increased formation of morphine following codeine administration, leading to a higher risk of toxicity.",
+        CODEINE_AVOID_TOXICITY,
+        "Strong",
+        &OPIOIDS,
+    ),
+    row(
+        "CYP2D6",
+        "codeine",
+        "Normal Metabolizer",
+        "Expected morphine formation.",
+        CODEINE_STANDARD,
+        "Strong",
+        &OPIOIDS,
+    ),
+    row(
+        "CYP2D6",
+        "codeine",
+        "Intermediate Metabolizer",
+        "Reduced morphine formation.",
+        "Use the label recommended age- or weight-specific dosing. If there is no response and opioid use is warranted, consider a non-tramadol opioid.",
+        "Moderate",
+        &OPIOIDS,
+    ),
+    row(
+        "CYP2D6",
+        "codeine",
+        "Poor Metabolizer",
+        "Greatly reduced morphine formation following codeine administration, leading to insufficient pain relief.",
+        "Avoid codeine because of the possibility of diminished analgesia. If opioid use is warranted, consider a non-tramadol opioid.",
+        "Strong",
+        &OPIOIDS,
+    ),
+    row(
+        "CYP2C9",
+        "celecoxib",
+        "Normal Metabolizer",
+        "Normal metabolism.",
+        STANDARD_STARTING_DOSE,
+        "Strong",
+        &NSAIDS,
+    ),
+    row(
+        "CYP2C9",
+        "celecoxib",
+        "Intermediate Metabolizer",
+        "Moderately reduced metabolism; higher plasma concentrations may increase the probability of toxicities.",
+        "Initiate therapy with the lowest recommended starting dose and titrate upward to clinical effect or the maximum recommended dose with caution.",
+        "Moderate",
+        &NSAIDS,
+    ),
+    row(
+        "CYP2C9",
+        "celecoxib",
+        "Poor Metabolizer",
+        "Significantly reduced metabolism and prolonged elimination half-life; higher plasma concentrations may increase the probability and duration of toxicities.",
+        "Initiate therapy with 25-50% of the lowest recommended starting dose and titrate upward to clinical effect or 25-50% of the maximum recommended dose with caution.",
+        "Moderate",
+        &NSAIDS,
+    ),
+    row(
+        "DPYD",
+        "fluorouracil",
+        "Normal Metabolizer",
+        "Normal DPD activity and normal risk for fluoropyrimidine toxicity.",
+        FLUOROURACIL_STANDARD,
+        "Strong",
+        &FLUOROPYRIMIDINES,
+    ),
+    row(
+        "DPYD",
+        "fluorouracil",
+        "Intermediate Metabolizer",
+        "Decreased DPD activity and increased risk for severe or even fatal drug toxicity when treated with fluoropyrimidine drugs.",
+        "Reduce the starting dose by 50%, followed by titration of the dose based on toxicity or therapeutic drug monitoring.",
+        "Moderate",
+        &FLUOROPYRIMIDINES,
+    ),
+    row(
+        "DPYD",
+        "fluorouracil",
+        "Poor Metabolizer",
+        "Complete DPD deficiency and increased risk for severe or even fatal drug toxicity when treated with fluoropyrimidine drugs.",
+        "Avoid use of 5-fluorouracil or 5-fluorouracil prodrug-based regimens.",
+        "Strong",
+        &FLUOROPYRIMIDINES,
+    ),
+    row(
+        "SLCO1B1",
+        "simvastatin",
+        "Normal Function",
+        "Typical simvastatin exposure and myopathy risk.",
+        SIMVASTATIN_STANDARD,
+        "Strong",
+        &STATINS,
+    ),
+    row(
+        "SLCO1B1",
+        "simvastatin",
+        "Increased Function",
+        "Decreased simvastatin acid exposure compared with normal function.",
+        SIMVASTATIN_STANDARD,
+        "Strong",
+        &STATINS,
+    ),
+    row(
+        "SLCO1B1",
+        "simvastatin",
+        "Decreased Function",
+        "Increased simvastatin acid exposure compared with normal function; increased risk of myopathy.",
+        SIMVASTATIN_ALTERNATIVE,
+        "Strong",
+        &STATINS,
+    ),
+    row(
+        "SLCO1B1",
+        "simvastatin",
+        "Poor Function",
+        "Highly increased simvastatin acid exposure compared with normal and decreased function; highly increased risk of myopathy.",
+        SIMVASTATIN_ALTERNATIVE,
+        "Strong",
+        &STATINS,
+    ),
+];
+
+/// Bundled recommendations for a gene's phenotype
+///
+/// Phenotypes are matched as by [`CpicDatabase::recommendations`],
+/// including the fallback from a "Likely" phenotype.
+///
+/// [`CpicDatabase::recommendations`]: super::cpic::CpicDatabase::recommendations
+pub fn recommendations(gene: &str, phenotype: &str) -> Vec<Recommendation> {
+    let matching = |phenotype: &str| -> Vec<Recommendation> {
+        RECOMMENDATIONS
+            .iter()
+            .filter(|row| {
+                row.gene.eq_ignore_ascii_case(gene)
+                    && cpic::is_phenotype(row.phenotype, gene, phenotype)
+            })
+            .map(BundledRecommendation::to_recommendation)
+            .collect()
+    };
+    let found = matching(phenotype);
+    match phenotype.strip_prefix("Likely ") {
+        Some(confirmed) if found.is_empty() => matching(confirmed),
+        _ => found,
+    }
+}
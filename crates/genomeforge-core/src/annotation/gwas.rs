@@ -0,0 +1,594 @@
+//! GWAS Catalog trait association annotation
+//!
+//! Loads the GWAS Catalog associations TSV and matches the reported risk
+//! allele of each association against the user's genotype. Only genotypes
+//! carrying the risk allele are reported, with an effect direction taken
+//! from the odds ratio or beta and a confidence derived from the p-value.
+
+use super::gnomad::GnomadDatabase;
+use super::normalize_rsid;
+use super::strand::{self, Strand};
+use super::tsv::TsvReader;
+use crate::genome::Variant;
+use crate::parallel;
+use crate::parser::{compression, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// Columns of the associations file needed to build an association
+const ASSOCIATION_COLUMNS: [&str; 5] = [
+    "DISEASE/TRAIT",
+    "STRONGEST SNP-RISK ALLELE",
+    "P-VALUE",
+    "OR or BETA",
+    "95% CI (TEXT)",
+];
+
+/// Conventional genome-wide significance threshold
+pub const GENOME_WIDE_SIGNIFICANCE: f64 = 5e-8;
+
+/// -log10 p-value treated as fully confident
+const CONFIDENT_MLOG_P: f64 = 30.0;
+
+/// Trait categories and the keywords that select them, checked in order
+const CATEGORY_KEYWORDS: [(TraitCategory, &[&str]); 9] = [
+    (
+        TraitCategory::Cancer,
+        &[
+            "cancer",
+            "carcinoma",
+            "melanoma",
+            "lymphoma",
+            "leukemia",
+            "tumor",
+            "glioma",
+        ],
+    ),
+    (
+        TraitCategory::Cardiovascular,
+        &[
+            "heart",
+            "coronary",
+            "cardiac",
+            "blood pressure",
+            "hypertension",
+            "atrial",
+            "stroke",
+            "myocardial",
+            "aortic",
+        ],
+    ),
+    (
+        TraitCategory::Metabolic,
+        &[
+            "diabetes",
+            "glucose",
+            "insulin",
+            "cholesterol",
+            "lipid",
+            "triglyceride",
+            "obesity",
+            "metabolic",
+            "urate",
+            "gout",
+        ],
+    ),
+    (
+        TraitCategory::Neurological,
+        &[
+            "alzheimer",
+            "parkinson",
+            "epilepsy",
+            "migraine",
+            "dementia",
+            "sclerosis",
+            "neuro",
+        ],
+    ),
+    (
+        TraitCategory::Psychiatric,
+        &[
+            "schizophrenia",
+            "bipolar",
+            "depress",
+            "anxiety",
+            "autism",
+            "adhd",
+            "attention deficit",
+        ],
+    ),
+    (
+        TraitCategory::Immune,
+        &[
+            "asthma",
+            "arthritis",
+            "lupus",
+            "crohn",
+            "colitis",
+            "psoriasis",
+            "celiac",
+            "allerg",
+            "immune",
+        ],
+    ),
+    (
+        TraitCategory::Anthropometric,
+        &[
+            "height",
+            "body mass",
+            "bmi",
+            "weight",
+            "waist",
+            "hip circumference",
+        ],
+    ),
+    (
+        TraitCategory::Appearance,
+        &[
+            "hair",
+            "eye color",
+            "eye colour",
+            "skin",
+            "freckl",
+            "balding",
+        ],
+    ),
+    (
+        TraitCategory::Lifestyle,
+        &[
+            "smoking",
+            "alcohol",
+            "coffee",
+            "caffeine",
+            "sleep",
+            "chronotype",
+            "education",
+        ],
+    ),
+];
+
+/// Broad grouping of a trait for display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitCategory {
+    Cancer,
+    Cardiovascular,
+    Metabolic,
+    Neurological,
+    Psychiatric,
+    Immune,
+    Anthropometric,
+    Appearance,
+    Lifestyle,
+    /// Athletic performance, sports injuries and recovery
+    Fitness,
+    Other,
+}
+
+impl TraitCategory {
+    /// Tag a trait name by keyword
+    pub fn classify(trait_name: &str) -> TraitCategory {
+        let lower = trait_name.to_ascii_lowercase();
+        CATEGORY_KEYWORDS
+            .iter()
+            .find(|(_, keywords)| keywords.iter().any(|keyword| lower.contains(keyword)))
+            .map_or(TraitCategory::Other, |(category, _)| *category)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraitCategory::Cancer => "Cancer",
+            TraitCategory::Cardiovascular => "Cardiovascular",
+            TraitCategory::Metabolic => "Metabolic",
+            TraitCategory::Neurological => "Neurological",
+            TraitCategory::Psychiatric => "Psychiatric",
+            TraitCategory::Immune => "Immune",
+            TraitCategory::Anthropometric => "Anthropometric",
+            TraitCategory::Appearance => "Appearance",
+            TraitCategory::Lifestyle => "Lifestyle",
+            TraitCategory::Fitness => "Fitness",
+            TraitCategory::Other => "Other",
+        }
+    }
+}
+
+/// Reported effect size of an association
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum EffectSize {
+    OddsRatio(f64),
+    /// Signed per-allele effect on a quantitative trait
+    Beta(f64),
+}
+
+/// Whether the risk allele raises or lowers the trait
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectDirection {
+    Increased,
+    Decreased,
+    Unknown,
+}
+
+impl EffectSize {
+    pub fn direction(&self) -> EffectDirection {
+        match *self {
+            EffectSize::OddsRatio(or) if or > 1.0 => EffectDirection::Increased,
+            EffectSize::OddsRatio(or) if or < 1.0 => EffectDirection::Decreased,
+            EffectSize::Beta(beta) if beta > 0.0 => EffectDirection::Increased,
+            EffectSize::Beta(beta) if beta < 0.0 => EffectDirection::Decreased,
+            _ => EffectDirection::Unknown,
+        }
+    }
+}
+
+/// One association from the GWAS Catalog
+#[derive(Debug, Clone, Serialize)]
+pub struct GwasAssociation {
+    pub rsid: String,
+    pub risk_allele: String,
+    pub trait_name: String,
+    /// EFO trait label, present in the "with added ontology" file
+    pub mapped_trait: Option<String>,
+    pub category: TraitCategory,
+    pub p_value: f64,
+    pub effect: Option<EffectSize>,
+    pub risk_allele_frequency: Option<f64>,
+    pub genes: Vec<String>,
+    pub chromosome: Option<String>,
+    pub position: Option<u64>,
+    pub pubmed_id: Option<String>,
+    pub study: Option<String>,
+    /// Date the association was added, as YYYY-MM-DD
+    pub added: Option<String>,
+}
+
+impl GwasAssociation {
+    pub fn direction(&self) -> EffectDirection {
+        self.effect
+            .map_or(EffectDirection::Unknown, |effect| effect.direction())
+    }
+
+    /// Confidence from 0.0 to 1.0 in the reported association
+    ///
+    /// Seventy percent comes from the p-value, scaled so that genome-wide
+    /// significance scores about 0.25 and p = 1e-30 or lower scores 1.0.
+    /// The rest comes from the effect size: an odds ratio of 2 (or 0.5)
+    /// scores fully, and betas, whose units vary by study, score half.
+    pub fn confidence(&self) -> f64 {
+        let mlog_p = if self.p_value > 0.0 {
+            -self.p_value.log10()
+        } else {
+            CONFIDENT_MLOG_P
+        };
+        let significance = (mlog_p / CONFIDENT_MLOG_P).clamp(0.0, 1.0);
+        let size = match self.effect {
+            Some(EffectSize::OddsRatio(or)) if or > 0.0 => {
+                (or.ln().abs() / 2f64.ln()).clamp(0.0, 1.0)
+            }
+            Some(EffectSize::Beta(_)) => 0.5,
+            _ => 0.0,
+        };
+        0.7 * significance + 0.3 * size
+    }
+}
+
+/// A GWAS association whose risk allele a genome carries
+#[derive(Debug, Clone, Copy)]
+pub struct GwasMatch<'a> {
+    pub association: &'a GwasAssociation,
+    pub variant: &'a Variant,
+    /// Copies of the risk allele carried (1 or 2)
+    pub risk_allele_copies: usize,
+    /// Strand the risk allele was read from
+    pub strand: Strand,
+}
+
+/// Indexed GWAS Catalog associations
+#[derive(Debug, Default)]
+pub struct GwasCatalog {
+    associations: Vec<GwasAssociation>,
+    by_rsid: HashMap<String, Vec<usize>>,
+}
+
+impl GwasCatalog {
+    /// Load the associations TSV, gzipped or not
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&ASSOCIATION_COLUMNS)
+            .map_err(|e| format!("Not a GWAS Catalog associations file: {}", e))?;
+
+        let mut associations = Vec::new();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("GWAS Catalog {}", e))?;
+
+            // Haplotype and interaction rows name several SNPs
+            let Some((rsid, risk_allele)) = row
+                .get("STRONGEST SNP-RISK ALLELE")
+                .and_then(parse_risk_allele)
+            else {
+                continue;
+            };
+            let Some(p_value) = row.get("P-VALUE").and_then(|p| p.parse::<f64>().ok()) else {
+                continue;
+            };
+            let trait_name = row.require("DISEASE/TRAIT")?.to_string();
+            let mapped_trait = row.get("MAPPED_TRAIT").map(str::to_string);
+            let category = TraitCategory::classify(mapped_trait.as_deref().unwrap_or(&trait_name));
+
+            associations.push(GwasAssociation {
+                rsid,
+                risk_allele,
+                category,
+                p_value,
+                effect: parse_effect(row.get("OR or BETA"), row.get("95% CI (TEXT)")),
+                risk_allele_frequency: row
+                    .get("RISK ALLELE FREQUENCY")
+                    .and_then(|f| f.parse().ok()),
+                genes: row
+                    .get("MAPPED_GENE")
+                    .or_else(|| row.get("REPORTED GENE(S)"))
+                    .map(split_genes)
+                    .unwrap_or_default(),
+                chromosome: row.get("CHR_ID").map(normalize_chromosome),
+                position: row.get("CHR_POS").and_then(|p| p.parse().ok()),
+                pubmed_id: row.get("PUBMEDID").map(str::to_string),
+                study: row.get("STUDY").map(str::to_string),
+                added: row.get("DATE ADDED TO CATALOG").map(str::to_string),
+                trait_name,
+                mapped_trait,
+            });
+        }
+
+        Ok(Self::from_associations(associations))
+    }
+
+    /// Index associations that were already parsed
+    pub fn from_associations(associations: Vec<GwasAssociation>) -> Self {
+        let mut by_rsid: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, association) in associations.iter().enumerate() {
+            by_rsid
+                .entry(association.rsid.clone())
+                .or_default()
+                .push(index);
+        }
+        Self {
+            associations,
+            by_rsid,
+        }
+    }
+
+    /// Add the rsid of every association to `sites`
+    ///
+    /// Associations are matched by rsid only, so their positions are not
+    /// needed.
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for association in &self.associations {
+            sites.add_rsid(&association.rsid);
+        }
+    }
+
+    /// Number of single-SNP associations
+    pub fn len(&self) -> usize {
+        self.associations.len()
+    }
+
+    /// Whether the catalog holds no associations
+    pub fn is_empty(&self) -> bool {
+        self.associations.is_empty()
+    }
+
+    /// Date the newest association was added
+    pub fn release_date(&self) -> Option<&str> {
+        self.associations
+            .iter()
+            .filter_map(|association| association.added.as_deref())
+            .max()
+    }
+
+    /// Associations reported for an rsid
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<&GwasAssociation> {
+        self.by_rsid
+            .get(rsid)
+            .map(|indexes| indexes.iter().map(|&i| &self.associations[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Match every association at or below `max_p_value` whose risk allele
+    /// the genome carries
+    ///
+    /// When several studies report the same rsid and trait only the most
+    /// significant one is kept. `checkpoint` is called every
+    /// [`CHECKPOINT_INTERVAL`] associations and stops the annotation when
+    /// it returns an error.
+    pub fn annotate<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        max_p_value: f64,
+        mut checkpoint: F,
+    ) -> Result<Vec<GwasMatch<'a>>, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let range = 0..self.associations.len();
+        let best = self.annotate_range(genome, max_p_value, range, &mut checkpoint)?;
+        Ok(by_p_value(best))
+    }
+
+    /// Like [`GwasCatalog::annotate`], on up to `threads` threads with the
+    /// associations split into chunks
+    pub fn annotate_parallel<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        max_p_value: f64,
+        threads: usize,
+        checkpoint: F,
+    ) -> Result<Vec<GwasMatch<'a>>, String>
+    where
+        F: Fn(usize) -> Result<(), String> + Sync,
+    {
+        let chunks = parallel::chunks(self.associations.len(), threads);
+        let found = parallel::map(&chunks, threads, |chunk| {
+            self.annotate_range(genome, max_p_value, chunk, &mut |index| checkpoint(index))
+        })?;
+        // Merged in chunk order, so ties go to the earlier study as they do
+        // sequentially
+        let mut best = HashMap::new();
+        for chunk in found {
+            for (key, found) in chunk {
+                keep_best(&mut best, key, found);
+            }
+        }
+        Ok(by_p_value(best))
+    }
+
+    /// The most significant match per rsid and trait among the
+    /// associations with these indexes
+    fn annotate_range<'a>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        max_p_value: f64,
+        range: Range<usize>,
+        checkpoint: &mut dyn FnMut(usize) -> Result<(), String>,
+    ) -> Result<HashMap<(&'a str, &'a str), GwasMatch<'a>>, String> {
+        let mut best: HashMap<(&str, &str), GwasMatch<'a>> = HashMap::new();
+
+        for (index, association) in self.associations[range.clone()]
+            .iter()
+            .enumerate()
+            .map(|(offset, association)| (range.start + offset, association))
+        {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            if association.p_value > max_p_value {
+                continue;
+            }
+            let Some(variant) = genome.get_by_rsid(&association.rsid) else {
+                continue;
+            };
+            let strand = strand::resolve(variant, &[&association.risk_allele]);
+            let copies = variant
+                .genotype
+                .allele_count(&strand.orient(&association.risk_allele));
+            if copies == 0 {
+                continue;
+            }
+
+            let found = GwasMatch {
+                association,
+                variant,
+                risk_allele_copies: copies,
+                strand,
+            };
+            keep_best(
+                &mut best,
+                (&association.rsid, &association.trait_name),
+                found,
+            );
+        }
+
+        Ok(best)
+    }
+}
+
+/// Settle the strand of A/T and C/G matches by the risk allele frequency,
+/// recounting the risk allele of those read from the opposite strand and
+/// dropping the ones that then carry none
+pub fn settle_strands(matches: &mut Vec<GwasMatch<'_>>, gnomad: &GnomadDatabase) {
+    matches.retain_mut(|found| {
+        let association = found.association;
+        found.strand = strand::resolve_by_frequency(
+            found.strand,
+            found.variant,
+            &association.risk_allele,
+            association.risk_allele_frequency,
+            gnomad,
+        );
+        if found.strand != Strand::Ambiguous {
+            found.risk_allele_copies = found
+                .variant
+                .genotype
+                .allele_count(&found.strand.orient(&association.risk_allele));
+        }
+        found.risk_allele_copies > 0
+    });
+}
+
+// Helper functions
+
+/// Keep `found` unless a match as significant is already kept
+fn keep_best<'a>(
+    best: &mut HashMap<(&'a str, &'a str), GwasMatch<'a>>,
+    key: (&'a str, &'a str),
+    found: GwasMatch<'a>,
+) {
+    match best.entry(key) {
+        Entry::Vacant(entry) => {
+            entry.insert(found);
+        }
+        Entry::Occupied(mut entry) => {
+            if found.association.p_value < entry.get().association.p_value {
+                entry.insert(found);
+            }
+        }
+    }
+}
+
+fn by_p_value<'a>(best: HashMap<(&'a str, &'a str), GwasMatch<'a>>) -> Vec<GwasMatch<'a>> {
+    let mut matches: Vec<GwasMatch<'a>> = best.into_values().collect();
+    matches.sort_by(|a, b| a.association.p_value.total_cmp(&b.association.p_value));
+    matches
+}
+
+/// "rs7903146-T" to ("rs7903146", "T"); unknown alleles ("?") are rejected
+fn parse_risk_allele(raw: &str) -> Option<(String, String)> {
+    if raw.contains([';', ',', 'x']) {
+        return None;
+    }
+    let (rsid, allele) = raw.trim().rsplit_once('-')?;
+    let allele = allele.trim().to_ascii_uppercase();
+    if allele.is_empty() || !allele.chars().all(|c| matches!(c, 'A' | 'C' | 'G' | 'T')) {
+        return None;
+    }
+    Some((normalize_rsid(rsid)?, allele))
+}
+
+/// Odds ratios are reported bare; betas carry "increase" or "decrease" in
+/// the confidence interval text and are always written as positive numbers
+fn parse_effect(value: Option<&str>, interval: Option<&str>) -> Option<EffectSize> {
+    let value = value?.parse::<f64>().ok()?;
+    let interval = interval.unwrap_or("").to_ascii_lowercase();
+    if interval.contains("decrease") {
+        Some(EffectSize::Beta(-value.abs()))
+    } else if interval.contains("increase") {
+        Some(EffectSize::Beta(value.abs()))
+    } else if value > 0.0 {
+        Some(EffectSize::OddsRatio(value))
+    } else {
+        None
+    }
+}
+
+fn split_genes(raw: &str) -> Vec<String> {
+    let mut genes: Vec<String> = Vec::new();
+    // Intergenic SNPs list the flanking genes as "UP - DOWN"
+    let names = raw.split(" - ").flat_map(|part| part.split([',', ';']));
+    for gene in names.map(str::trim) {
+        if !gene.is_empty()
+            && gene != "NR"
+            && gene != "intergenic"
+            && !genes.iter().any(|g| g == gene)
+        {
+            genes.push(gene.to_string());
+        }
+    }
+    genes
+}
@@ -0,0 +1,448 @@
+//! Y-chromosome and mitochondrial haplogroups
+//!
+//! A haplogroup tree lists the markers that define each branch: ISOGG SNPs
+//! on the Y chromosome, PhyloTree mutations on mtDNA. Both trees ship in
+//! one tab-separated file with a header row; the chromosome column decides
+//! which tree a row belongs to:
+//!
+//! ```text
+//! haplogroup  parent  marker  rsid       chromosome  grch37_position  grch38_position  ancestral  derived
+//! R1b1a1b     R1b1a1  M269    rs9786153  Y           .                .                C          T
+//! H           HV      G2706A  .          MT          2706             2706             G          A
+//! ```
+//!
+//! Markers are looked up by rsid, then by the position on the genome's
+//! build. Haplogroups may have no markers of their own, and a parent that
+//! never appears in the haplogroup column is a root.
+//!
+//! A haplogroup is called by walking from the root to the branch whose
+//! markers best agree with the genome: every derived call on the path counts
+//! for it and every ancestral call against it.
+
+use super::normalize_rsid;
+use super::tsv::TsvReader;
+use crate::genome::{reverse_complement, GenomeBuild, Genotype};
+use crate::parser::{compression, normalize_chromosome};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+const TREE_COLUMNS: [&str; 5] = ["haplogroup", "parent", "chromosome", "ancestral", "derived"];
+
+/// Share of Y variants that must be called for the sample to have Y data
+///
+/// Arrays report Y markers for female samples too, almost all as no-calls
+/// with a few spurious calls where X-linked probes cross-hybridize.
+const Y_CALL_RATE: f64 = 0.5;
+
+/// Which parent a haplogroup is inherited from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lineage {
+    /// Y chromosome, father to son
+    Paternal,
+    /// Mitochondrial DNA, mother to child
+    Maternal,
+}
+
+impl Lineage {
+    fn from_chromosome(chromosome: &str) -> Option<Self> {
+        match normalize_chromosome(chromosome).as_str() {
+            "Y" => Some(Lineage::Paternal),
+            "MT" => Some(Lineage::Maternal),
+            _ => None,
+        }
+    }
+
+    fn chromosome(&self) -> &'static str {
+        match self {
+            Lineage::Paternal => "Y",
+            Lineage::Maternal => "MT",
+        }
+    }
+}
+
+/// A SNP defining a branch of the tree
+#[derive(Debug, Clone, Serialize)]
+pub struct Marker {
+    /// e.g. "M343" or "G2706A"
+    pub name: String,
+    pub rsid: Option<String>,
+    pub grch37_position: Option<u64>,
+    pub grch38_position: Option<u64>,
+    pub ancestral: String,
+    pub derived: String,
+}
+
+/// One haplogroup of a tree
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub parent: Option<String>,
+    pub markers: Vec<Marker>,
+}
+
+/// The haplogroup tree of one lineage
+#[derive(Debug, Clone)]
+pub struct HaplogroupTree {
+    pub lineage: Lineage,
+    nodes: Vec<TreeNode>,
+    parents: Vec<Option<usize>>,
+    by_name: HashMap<String, usize>,
+}
+
+/// Whether a sample has usable Y-chromosome data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YData {
+    /// The file has no Y-chromosome variants
+    Absent,
+    /// Y variants are present but mostly no-calls, as for female samples
+    NoCalls,
+    Present,
+}
+
+impl YData {
+    /// Y-chromosome data of a genome, from its per-chromosome call rates
+    pub fn of(genome: &LoadedGenome) -> Self {
+        match genome
+            .summary
+            .chromosome_counts
+            .iter()
+            .find(|count| count.chromosome == "Y")
+        {
+            None => YData::Absent,
+            Some(count) if count.variant_count == 0 => YData::Absent,
+            Some(count) if count.call_rate < Y_CALL_RATE => YData::NoCalls,
+            Some(_) => YData::Present,
+        }
+    }
+}
+
+/// Haplogroup assigned to one lineage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HaplogroupCall {
+    pub lineage: Lineage,
+    pub haplogroup: String,
+    /// Haplogroups from the root of the tree down to the call
+    pub path: Vec<String>,
+    /// Markers on the path the genome carries the derived allele of
+    pub derived_markers: Vec<String>,
+    /// Markers on the path the genome carries the ancestral allele of
+    pub conflicting_markers: Vec<String>,
+    /// Markers of the whole tree that the genome has a call for
+    pub markers_genotyped: usize,
+    pub markers_total: usize,
+}
+
+/// Paternal and maternal haplogroups of a genome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HaplogroupReport {
+    pub y_data: YData,
+    /// Only called when the sample has Y data
+    pub paternal: Option<HaplogroupCall>,
+    pub maternal: Option<HaplogroupCall>,
+}
+
+/// Y-chromosome and mitochondrial haplogroup trees
+#[derive(Debug, Clone)]
+pub struct HaplogroupDatabase {
+    pub paternal: HaplogroupTree,
+    pub maternal: HaplogroupTree,
+}
+
+impl HaplogroupDatabase {
+    /// Load a tree file, optionally gzip-compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Read a tree file from any buffered reader
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&TREE_COLUMNS)
+            .map_err(|e| format!("Not a haplogroup tree file: {}", e))?;
+
+        let mut paternal = TreeBuilder::default();
+        let mut maternal = TreeBuilder::default();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("Haplogroup tree {}", e))?;
+            let chromosome = row.require("chromosome")?;
+            let builder = match Lineage::from_chromosome(chromosome) {
+                Some(Lineage::Paternal) => &mut paternal,
+                Some(Lineage::Maternal) => &mut maternal,
+                None => {
+                    return Err(format!(
+                        "line {}: chromosome {} is neither Y nor MT",
+                        row.line_number, chromosome
+                    ))
+                }
+            };
+            let position = |column: &str| {
+                row.get(column)
+                    .filter(|raw| *raw != ".")
+                    .map(|raw| {
+                        raw.parse::<u64>()
+                            .map_err(|_| format!("line {}: invalid {}", row.line_number, column))
+                    })
+                    .transpose()
+            };
+            let marker = match row.get("marker").filter(|name| *name != ".") {
+                Some(name) => Some(Marker {
+                    name: name.to_string(),
+                    rsid: row.get("rsid").and_then(normalize_rsid),
+                    grch37_position: position("grch37_position")?,
+                    grch38_position: position("grch38_position")?,
+                    ancestral: row.require("ancestral")?.to_ascii_uppercase(),
+                    derived: row.require("derived")?.to_ascii_uppercase(),
+                }),
+                None => None,
+            };
+            let parent = row.get("parent").filter(|parent| *parent != ".");
+            builder.add(row.require("haplogroup")?, parent, marker);
+        }
+
+        Ok(Self {
+            paternal: paternal.finish(Lineage::Paternal),
+            maternal: maternal.finish(Lineage::Maternal),
+        })
+    }
+
+    /// Number of markers in both trees
+    pub fn len(&self) -> usize {
+        self.paternal.marker_count() + self.maternal.marker_count()
+    }
+
+    /// Whether the trees define no markers
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Haplogroups of both lineages
+    pub fn report(&self, genome: &LoadedGenome) -> HaplogroupReport {
+        let y_data = YData::of(genome);
+        HaplogroupReport {
+            y_data,
+            paternal: (y_data == YData::Present)
+                .then(|| self.paternal.call(genome))
+                .flatten(),
+            maternal: self.maternal.call(genome),
+        }
+    }
+
+    /// Add every marker of both trees to `sites`
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for tree in [&self.paternal, &self.maternal] {
+            for marker in tree.nodes.iter().flat_map(|node| &node.markers) {
+                if let Some(rsid) = &marker.rsid {
+                    sites.add_rsid(rsid);
+                }
+                for position in [marker.grch37_position, marker.grch38_position]
+                    .into_iter()
+                    .flatten()
+                {
+                    sites.add_position(tree.lineage.chromosome(), position);
+                }
+            }
+        }
+    }
+}
+
+impl HaplogroupTree {
+    /// Every haplogroup of the tree
+    pub fn nodes(&self) -> &[TreeNode] {
+        &self.nodes
+    }
+
+    /// A haplogroup by name
+    pub fn node(&self, name: &str) -> Option<&TreeNode> {
+        self.by_name.get(name).map(|&index| &self.nodes[index])
+    }
+
+    /// Number of markers in the tree
+    pub fn marker_count(&self) -> usize {
+        self.nodes.iter().map(|node| node.markers.len()).sum()
+    }
+
+    /// The haplogroup best supported by the genome's calls, if any marker
+    /// below the root is derived
+    ///
+    /// Ties go to the deeper haplogroup.
+    pub fn call(&self, genome: &LoadedGenome) -> Option<HaplogroupCall> {
+        let build = genome.file.genome_build;
+        let mut markers_genotyped = 0;
+        let states: Vec<Vec<Option<bool>>> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.markers
+                    .iter()
+                    .map(|marker| {
+                        let derived = self.is_derived(genome, marker, build);
+                        markers_genotyped += usize::from(derived.is_some());
+                        derived
+                    })
+                    .collect()
+            })
+            .collect();
+        let support = |node: usize| -> i64 {
+            states[node]
+                .iter()
+                .flatten()
+                .map(|&derived| if derived { 1 } else { -1 })
+                .sum()
+        };
+
+        let mut best: Option<(i64, usize, usize)> = None;
+        for node in 0..self.nodes.len() {
+            if support(node) <= 0 {
+                continue;
+            }
+            let path = self.path(node);
+            let score: i64 = path.iter().map(|&n| support(n)).sum();
+            if best.is_none_or(|(best_score, depth, _)| (score, path.len()) > (best_score, depth)) {
+                best = Some((score, path.len(), node));
+            }
+        }
+
+        let (_, _, node) = best?;
+        let path = self.path(node);
+        let mut derived_markers = Vec::new();
+        let mut conflicting_markers = Vec::new();
+        for &n in &path {
+            for (marker, state) in self.nodes[n].markers.iter().zip(&states[n]) {
+                match state {
+                    Some(true) => derived_markers.push(marker.name.clone()),
+                    Some(false) => conflicting_markers.push(marker.name.clone()),
+                    None => {}
+                }
+            }
+        }
+        Some(HaplogroupCall {
+            lineage: self.lineage,
+            haplogroup: self.nodes[node].name.clone(),
+            path: path.iter().map(|&n| self.nodes[n].name.clone()).collect(),
+            derived_markers,
+            conflicting_markers,
+            markers_genotyped,
+            markers_total: self.marker_count(),
+        })
+    }
+
+    /// Indices from the root down to a node
+    ///
+    /// A parent loop in a malformed tree ends the walk instead of hanging.
+    fn path(&self, node: usize) -> Vec<usize> {
+        let mut path = vec![node];
+        let mut current = node;
+        while let Some(parent) = self.parents[current] {
+            if path.contains(&parent) {
+                break;
+            }
+            path.push(parent);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// Whether the genome carries the derived allele of a marker, read from
+    /// the opposite strand when only the complemented call fits
+    fn is_derived(
+        &self,
+        genome: &LoadedGenome,
+        marker: &Marker,
+        build: Option<GenomeBuild>,
+    ) -> Option<bool> {
+        let position = match build {
+            Some(GenomeBuild::GRCh37) => marker.grch37_position,
+            Some(GenomeBuild::GRCh38) => marker.grch38_position,
+            _ => None,
+        };
+        let variant = marker
+            .rsid
+            .as_deref()
+            .and_then(|rsid| genome.get_by_rsid(rsid))
+            .or_else(|| position.and_then(|p| genome.get_at(self.lineage.chromosome(), p)))?;
+
+        let state = |genotype: &Genotype| -> Option<bool> {
+            let allele = match genotype.alleles().as_slice() {
+                [allele] => allele.to_ascii_uppercase(),
+                // Arrays may report haploid calls twice; a heterozygous
+                // call is heteroplasmy or a genotyping error
+                [first, second] if first.eq_ignore_ascii_case(second) => first.to_ascii_uppercase(),
+                _ => return None,
+            };
+            if allele == marker.derived {
+                Some(true)
+            } else if allele == marker.ancestral {
+                Some(false)
+            } else {
+                None
+            }
+        };
+
+        state(&variant.genotype).or_else(|| {
+            let palindromic = reverse_complement(&marker.ancestral) == marker.derived;
+            (!palindromic)
+                .then(|| state(&variant.genotype.complemented()))
+                .flatten()
+        })
+    }
+}
+
+/// Collects the rows of one tree
+#[derive(Default)]
+struct TreeBuilder {
+    nodes: Vec<TreeNode>,
+    by_name: HashMap<String, usize>,
+}
+
+impl TreeBuilder {
+    fn add(&mut self, name: &str, parent: Option<&str>, marker: Option<Marker>) {
+        let index = self.node(name);
+        if let Some(parent) = parent {
+            self.node(parent);
+            self.nodes[index]
+                .parent
+                .get_or_insert_with(|| parent.to_string());
+        }
+        self.nodes[index].markers.extend(marker);
+    }
+
+    fn node(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.by_name.get(name) {
+            return index;
+        }
+        self.nodes.push(TreeNode {
+            name: name.to_string(),
+            parent: None,
+            markers: Vec::new(),
+        });
+        self.by_name.insert(name.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn finish(self, lineage: Lineage) -> HaplogroupTree {
+        let parents = self
+            .nodes
+            .iter()
+            .map(|node| {
+                node.parent
+                    .as_ref()
+                    .and_then(|parent| self.by_name.get(parent).copied())
+            })
+            .collect();
+        HaplogroupTree {
+            lineage,
+            nodes: self.nodes,
+            parents,
+            by_name: self.by_name,
+        }
+    }
+}
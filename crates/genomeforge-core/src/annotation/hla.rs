@@ -0,0 +1,265 @@
+//! HLA alleles behind drug hypersensitivity
+//!
+//! Carriers of HLA-B*57:01 are at high risk of a hypersensitivity reaction
+//! to abacavir, and carriers of HLA-B*15:02 of Stevens-Johnson syndrome and
+//! toxic epidermal necrolysis on carbamazepine. Both reactions can be fatal
+//! and CPIC recommends against the drugs for carriers, so these are
+//! reported as findings of their own rather than as drug responses.
+//!
+//! A VCF from HLA imputation or sequencing-based typing holds the alleles
+//! directly, as records named after them such as "HLA_B*57:01" or
+//! "HLA_B_5701". Other data only has SNPs in linkage with the alleles;
+//! such a proxy is only as good as the linkage in the user's ancestry, so
+//! a typed allele is always preferred.
+
+use crate::confidence::{self, Confidence, Evidence};
+use crate::genome::{GenomeBuild, Variant};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+
+/// A SNP in linkage with an HLA allele
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ProxySnp {
+    pub rsid: &'static str,
+    /// Base on the plus strand that tags the HLA allele
+    pub tag_allele: &'static str,
+    pub other_allele: &'static str,
+    /// Chromosome 6 positions by build
+    pub positions: &'static [(GenomeBuild, u64)],
+    /// Ancestry the linkage was validated in
+    pub validated_in: &'static str,
+}
+
+/// An HLA allele that makes a drug dangerous
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HlaRiskAllele {
+    /// e.g. "HLA-B*57:01"
+    pub allele: &'static str,
+    pub gene: &'static str,
+    pub drugs: &'static [&'static str],
+    pub reaction: &'static str,
+    /// CPIC recommendation for carriers
+    pub recommendation: &'static str,
+    pub proxy: ProxySnp,
+}
+
+/// HLA alleles checked
+pub const RISK_ALLELES: [HlaRiskAllele; 2] = [
+    HlaRiskAllele {
+        allele: "HLA-B*57:01",
+        gene: "HLA-B",
+        drugs: &["abacavir"],
+        reaction: "abacavir hypersensitivity",
+        recommendation: "Abacavir is not recommended.",
+        proxy: ProxySnp {
+            rsid: "rs2395029",
+            tag_allele: "G",
+            other_allele: "T",
+            positions: &[(GenomeBuild::GRCh37, 31431780), (GenomeBuild::GRCh38, 31464003)],
+            validated_in: "European",
+        },
+    },
+    HlaRiskAllele {
+        allele: "HLA-B*15:02",
+        gene: "HLA-B",
+        drugs: &["carbamazepine", "oxcarbazepine", "phenytoin", "fosphenytoin"],
+        reaction: "Stevens-Johnson syndrome and toxic epidermal necrolysis",
+        recommendation: "Do not use carbamazepine or oxcarbazepine in patients who have not taken them before. Avoid phenytoin and fosphenytoin.",
+        proxy: ProxySnp {
+            rsid: "rs144012689",
+            tag_allele: "A",
+            other_allele: "T",
+            positions: &[],
+            validated_in: "Han Chinese",
+        },
+    },
+];
+
+/// What an HLA call was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HlaEvidence {
+    /// A record of the allele itself
+    Typed,
+    /// A SNP in linkage with it
+    Proxy,
+}
+
+/// Whether a genome carries one risk allele
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HlaCall {
+    pub allele: String,
+    pub gene: String,
+    pub drugs: Vec<String>,
+    pub reaction: String,
+    pub recommendation: String,
+    /// Copies of the allele carried
+    pub copies: usize,
+    pub evidence: HlaEvidence,
+    /// ID of the typed record or rsid of the proxy SNP
+    pub marker: String,
+    pub genotype: String,
+    pub imputed: bool,
+    /// Limits of a proxy call
+    pub caveat: Option<String>,
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+impl HlaCall {
+    pub fn is_carrier(&self) -> bool {
+        self.copies > 0
+    }
+}
+
+/// Call every risk allele a genome has a typed record or proxy SNP for
+pub fn call(genome: &LoadedGenome) -> Vec<HlaCall> {
+    let typed: Vec<(String, &Variant)> = genome
+        .variants()
+        .iter()
+        .filter(|variant| !variant.genotype.is_no_call())
+        .filter_map(|variant| Some((typed_allele(variant.rsid.as_deref()?)?, variant)))
+        .collect();
+
+    RISK_ALLELES
+        .iter()
+        .filter_map(|risk| {
+            typed
+                .iter()
+                .find(|(allele, _)| allele == risk.allele)
+                .and_then(|(_, variant)| typed_call(risk, variant))
+                .or_else(|| proxy_call(risk, genome))
+        })
+        .collect()
+}
+
+/// Add the proxy SNPs and the usual names of typed records to `sites`
+///
+/// Typed records named otherwise are only found in genomes loaded whole.
+pub fn add_sites(sites: &mut SiteFilter) {
+    for risk in &RISK_ALLELES {
+        sites.add_rsid(risk.proxy.rsid);
+        for (_, position) in risk.proxy.positions {
+            sites.add_position("6", *position);
+        }
+        let name = risk.allele.trim_start_matches("HLA-");
+        let (gene, fields) = name.split_once('*').unwrap_or((name, ""));
+        for id in [
+            format!("HLA_{}*{}", gene, fields),
+            format!("HLA-{}*{}", gene, fields),
+            format!("HLA_{}_{}", gene, fields.replace(':', "")),
+        ] {
+            sites.add_rsid(&id);
+        }
+    }
+}
+
+/// The two-field allele a typed record is named after, e.g.
+/// "HLA-B*57:01" for "HLA_B*57:01:01" or "HLA_B_5701"
+pub fn typed_allele(id: &str) -> Option<String> {
+    let rest = id.get(..3)?.eq_ignore_ascii_case("HLA").then(|| &id[3..])?;
+    let rest = rest.strip_prefix(['_', '-'])?;
+    let split = rest.find(['*', '_'])?;
+    let (gene, fields) = (&rest[..split], &rest[split + 1..]);
+    if gene.is_empty() || !gene.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let (first, second) = if fields.contains(':') {
+        let mut parts = fields.split(':');
+        (parts.next()?, parts.next()?)
+    } else if fields.len() == 4 {
+        (&fields[..2], &fields[2..])
+    } else {
+        return None;
+    };
+    let digits = |field: &str| !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit());
+    if !digits(first) || !digits(second) {
+        return None;
+    }
+    Some(format!(
+        "HLA-{}*{}:{}",
+        gene.to_ascii_uppercase(),
+        first,
+        second
+    ))
+}
+
+// Helper functions
+
+fn typed_call(risk: &HlaRiskAllele, variant: &Variant) -> Option<HlaCall> {
+    // Imputation panels mark presence with "P", others with the alternate
+    let present = if variant.alternates.iter().any(|allele| allele == "P")
+        || variant.reference.as_deref() == Some("P")
+    {
+        "P"
+    } else {
+        variant.alternates.first()?.as_str()
+    };
+    let marker = variant.rsid.clone().unwrap_or_default();
+    Some(new_call(
+        risk,
+        variant.genotype.allele_count(present),
+        HlaEvidence::Typed,
+        marker,
+        variant,
+        None,
+    ))
+}
+
+fn proxy_call(risk: &HlaRiskAllele, genome: &LoadedGenome) -> Option<HlaCall> {
+    let proxy = &risk.proxy;
+    let variant = genome
+        .get_by_rsid(proxy.rsid)
+        .or_else(|| {
+            proxy
+                .positions
+                .iter()
+                .find(|(build, _)| genome.file.genome_build == Some(*build))
+                .and_then(|(_, position)| genome.get_at("6", *position))
+        })
+        .filter(|variant| !variant.genotype.is_no_call())?;
+    let copies = variant
+        .genotype
+        .biallelic_count(proxy.tag_allele, proxy.other_allele)?;
+    let caveat = format!(
+        "{} tags {} reliably only in people of {} ancestry; confirm with HLA typing before acting on it.",
+        proxy.rsid, risk.allele, proxy.validated_in
+    );
+    let marker = proxy.rsid.to_string();
+    Some(new_call(
+        risk,
+        copies,
+        HlaEvidence::Proxy,
+        marker,
+        variant,
+        Some(caveat),
+    ))
+}
+
+fn new_call(
+    risk: &HlaRiskAllele,
+    copies: usize,
+    evidence: HlaEvidence,
+    marker: String,
+    variant: &Variant,
+    caveat: Option<String>,
+) -> HlaCall {
+    HlaCall {
+        allele: risk.allele.to_string(),
+        gene: risk.gene.to_string(),
+        drugs: risk.drugs.iter().map(|drug| drug.to_string()).collect(),
+        reaction: risk.reaction.to_string(),
+        recommendation: risk.recommendation.to_string(),
+        copies,
+        evidence,
+        marker,
+        genotype: variant.genotype.to_string(),
+        imputed: variant.is_imputed(),
+        caveat,
+        confidence: Confidence::new(
+            Evidence::Hla(evidence),
+            confidence::variant_reliability(variant),
+        ),
+    }
+}
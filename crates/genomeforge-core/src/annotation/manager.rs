@@ -0,0 +1,919 @@
+//! Installing and verifying database releases
+//!
+//! Releases are described by a signed [`ReleaseManifest`]. Whatever
+//! fetches a release (the desktop app downloads it, air-gapped users copy
+//! it from removable media) hands the file to [`install_release`], which
+//! checks its SHA-256 digest, makes sure it parses, and only then moves it
+//! into the database directory, replacing the previous release. The
+//! digests are kept, so [`verify_installed`] can later tell whether a
+//! release has been damaged on disk.
+
+use super::clingen::ClinGenDatabase;
+use super::clinvar::ClinVarDatabase;
+use super::cpic::{self, CpicDatabase};
+use super::custom::CustomAnnotations;
+use super::dbsnp::DbSnpIndex;
+use super::gnomad::GnomadDatabase;
+use super::gwas::GwasCatalog;
+use super::haplogroup::HaplogroupDatabase;
+use super::pharmgkb::{self, PharmGkbDatabase};
+use super::probes::ProbeMask;
+use super::AnnotationDatabases;
+use crate::genome::GenomeBuild;
+use crate::liftover::Liftover;
+use crate::reference::ReferenceRelease;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Record of installed releases inside the database directory
+const INSTALLED_FILE: &str = "installed.json";
+
+/// Scratch directory for releases being verified
+const STAGING_DIR: &str = ".staging";
+
+/// Extension of the downloads in the staging directory
+const DOWNLOAD_EXTENSION: &str = "download";
+
+/// Tables extracted from the PharmGKB clinical annotations archive
+const PHARMGKB_TABLES: [&str; 3] = [
+    pharmgkb::ANNOTATIONS_FILE,
+    pharmgkb::ALLELES_FILE,
+    pharmgkb::EVIDENCE_FILE,
+];
+
+/// Databases that can be installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseKind {
+    ClinVar,
+    PharmGkb,
+    /// Star-allele definition, functionality and recommendation tables
+    Cpic,
+    Gwas,
+    DbSnp,
+    Gnomad,
+    /// GRCh37 to GRCh38 chain file
+    Liftover,
+    /// Y-chromosome and mitochondrial haplogroup trees
+    Haplogroups,
+    /// ClinGen gene or region dosage sensitivity curation list
+    ClinGen,
+    /// Array probes known to call unreliably, per chip
+    ProbeMask,
+    /// Interpretations by rsid from a table the user or their lab supplies
+    Custom,
+}
+
+impl DatabaseKind {
+    pub const ALL: [DatabaseKind; 11] = [
+        DatabaseKind::ClinVar,
+        DatabaseKind::PharmGkb,
+        DatabaseKind::Cpic,
+        DatabaseKind::Gwas,
+        DatabaseKind::DbSnp,
+        DatabaseKind::Gnomad,
+        DatabaseKind::Liftover,
+        DatabaseKind::Haplogroups,
+        DatabaseKind::ClinGen,
+        DatabaseKind::ProbeMask,
+        DatabaseKind::Custom,
+    ];
+
+    /// Whether releases of the database are published in the manifest;
+    /// custom tables are only ever imported
+    pub fn is_published(&self) -> bool {
+        *self != DatabaseKind::Custom
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DatabaseKind::ClinVar => "clinvar",
+            DatabaseKind::PharmGkb => "pharmgkb",
+            DatabaseKind::Cpic => "cpic",
+            DatabaseKind::Gwas => "gwas",
+            DatabaseKind::DbSnp => "dbsnp",
+            DatabaseKind::Gnomad => "gnomad",
+            DatabaseKind::Liftover => "liftover",
+            DatabaseKind::Haplogroups => "haplogroups",
+            DatabaseKind::ClinGen => "clingen",
+            DatabaseKind::ProbeMask => "probe_mask",
+            DatabaseKind::Custom => "custom",
+        }
+    }
+
+    /// File names accepted for the release, in order of preference
+    ///
+    /// PharmGKB and CPIC are installed as directories of tables.
+    pub fn file_names(&self) -> &'static [&'static str] {
+        match self {
+            DatabaseKind::ClinVar => &[
+                "clinvar.vcf.gz",
+                "clinvar.vcf",
+                "variant_summary.txt.gz",
+                "variant_summary.txt",
+            ],
+            DatabaseKind::PharmGkb => &["pharmgkb"],
+            DatabaseKind::Cpic => &["cpic"],
+            DatabaseKind::Gwas => &[
+                "gwas_catalog_associations_ontology.tsv",
+                "gwas_catalog_associations.tsv",
+                "gwas_catalog_associations.tsv.gz",
+                "gwas_catalog.tsv",
+            ],
+            DatabaseKind::DbSnp => &["dbsnp.vcf.gz", "dbsnp.vcf"],
+            DatabaseKind::Gnomad => &["gnomad.vcf.gz", "gnomad.vcf"],
+            DatabaseKind::Liftover => &["hg19ToHg38.over.chain.gz", "hg19ToHg38.over.chain"],
+            DatabaseKind::Haplogroups => &["haplogroups.tsv.gz", "haplogroups.tsv"],
+            DatabaseKind::ClinGen => &["clingen_dosage.tsv.gz", "clingen_dosage.tsv"],
+            DatabaseKind::ProbeMask => &["probe_mask.tsv.gz", "probe_mask.tsv"],
+            DatabaseKind::Custom => &[
+                "custom_annotations.tsv.gz",
+                "custom_annotations.tsv",
+                "custom_annotations.csv.gz",
+                "custom_annotations.csv",
+            ],
+        }
+    }
+
+    /// Name to install a local release under, chosen from its file name
+    pub fn file_name_for(&self, source: &Path) -> &'static str {
+        let name = source
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if let Some(known) = self.file_names().iter().find(|known| **known == name) {
+            return known;
+        }
+        let compressed = name.ends_with(".gz");
+        match self {
+            DatabaseKind::ClinVar => match (name.contains(".vcf"), compressed) {
+                (true, true) => "clinvar.vcf.gz",
+                (true, false) => "clinvar.vcf",
+                (false, true) => "variant_summary.txt.gz",
+                (false, false) => "variant_summary.txt",
+            },
+            DatabaseKind::PharmGkb => "pharmgkb",
+            DatabaseKind::Cpic => "cpic",
+            DatabaseKind::Gwas if compressed => "gwas_catalog_associations.tsv.gz",
+            DatabaseKind::Gwas => "gwas_catalog_associations.tsv",
+            DatabaseKind::DbSnp if compressed => "dbsnp.vcf.gz",
+            DatabaseKind::DbSnp => "dbsnp.vcf",
+            DatabaseKind::Gnomad if compressed => "gnomad.vcf.gz",
+            DatabaseKind::Gnomad => "gnomad.vcf",
+            DatabaseKind::Liftover if compressed => "hg19ToHg38.over.chain.gz",
+            DatabaseKind::Liftover => "hg19ToHg38.over.chain",
+            DatabaseKind::Haplogroups if compressed => "haplogroups.tsv.gz",
+            DatabaseKind::Haplogroups => "haplogroups.tsv",
+            DatabaseKind::ClinGen if compressed => "clingen_dosage.tsv.gz",
+            DatabaseKind::ClinGen => "clingen_dosage.tsv",
+            DatabaseKind::ProbeMask if compressed => "probe_mask.tsv.gz",
+            DatabaseKind::ProbeMask => "probe_mask.tsv",
+            DatabaseKind::Custom => match (name.contains(".csv"), compressed) {
+                (true, true) => "custom_annotations.csv.gz",
+                (true, false) => "custom_annotations.csv",
+                (false, true) => "custom_annotations.tsv.gz",
+                (false, false) => "custom_annotations.tsv",
+            },
+        }
+    }
+}
+
+/// A database loaded from disk, ready to be placed in its slot
+#[derive(Debug)]
+pub enum LoadedDatabase {
+    ClinVar(ClinVarDatabase),
+    PharmGkb(PharmGkbDatabase),
+    Cpic(CpicDatabase),
+    Gwas(GwasCatalog),
+    DbSnp(DbSnpIndex),
+    Gnomad(GnomadDatabase),
+    Liftover(Liftover),
+    Haplogroups(HaplogroupDatabase),
+    ClinGen(ClinGenDatabase),
+    ProbeMask(ProbeMask),
+    Custom(CustomAnnotations),
+}
+
+impl LoadedDatabase {
+    /// Load a release of the given kind from a file or table directory
+    pub fn load(kind: DatabaseKind, path: &Path) -> Result<Self, String> {
+        match kind {
+            DatabaseKind::ClinVar => ClinVarDatabase::load(path).map(LoadedDatabase::ClinVar),
+            DatabaseKind::PharmGkb => {
+                PharmGkbDatabase::load_dir(path).map(LoadedDatabase::PharmGkb)
+            }
+            DatabaseKind::Cpic => CpicDatabase::load_dir(path).map(LoadedDatabase::Cpic),
+            DatabaseKind::Gwas => GwasCatalog::load(path).map(LoadedDatabase::Gwas),
+            DatabaseKind::DbSnp => DbSnpIndex::load(path).map(LoadedDatabase::DbSnp),
+            DatabaseKind::Gnomad => GnomadDatabase::load(path).map(LoadedDatabase::Gnomad),
+            DatabaseKind::Liftover => {
+                Liftover::load(path, GenomeBuild::GRCh37, GenomeBuild::GRCh38)
+                    .map(LoadedDatabase::Liftover)
+            }
+            DatabaseKind::Haplogroups => {
+                HaplogroupDatabase::load(path).map(LoadedDatabase::Haplogroups)
+            }
+            DatabaseKind::ClinGen => ClinGenDatabase::load(path).map(LoadedDatabase::ClinGen),
+            DatabaseKind::ProbeMask => ProbeMask::load(path).map(LoadedDatabase::ProbeMask),
+            DatabaseKind::Custom => CustomAnnotations::load(path).map(LoadedDatabase::Custom),
+        }
+    }
+
+    /// Number of records in the release
+    pub fn len(&self) -> usize {
+        match self {
+            LoadedDatabase::ClinVar(db) => db.len(),
+            LoadedDatabase::PharmGkb(db) => db.len(),
+            LoadedDatabase::Cpic(db) => db.len(),
+            LoadedDatabase::Gwas(db) => db.len(),
+            LoadedDatabase::DbSnp(db) => db.len(),
+            LoadedDatabase::Gnomad(db) => db.len(),
+            LoadedDatabase::Liftover(chain) => chain.len(),
+            LoadedDatabase::Haplogroups(db) => db.len(),
+            LoadedDatabase::ClinGen(db) => db.len(),
+            LoadedDatabase::ProbeMask(mask) => mask.len(),
+            LoadedDatabase::Custom(table) => table.len(),
+        }
+    }
+
+    /// Whether the release holds no records
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl AnnotationDatabases {
+    /// Make a loaded release available to new analyses
+    pub fn install(&self, database: LoadedDatabase) {
+        match database {
+            LoadedDatabase::ClinVar(db) => {
+                self.clinvar.replace(db);
+            }
+            LoadedDatabase::PharmGkb(db) => {
+                self.pharmgkb.replace(db);
+            }
+            LoadedDatabase::Cpic(db) => {
+                self.cpic.replace(db);
+            }
+            LoadedDatabase::Gwas(db) => {
+                self.gwas.replace(db);
+            }
+            LoadedDatabase::DbSnp(db) => {
+                self.dbsnp.replace(db);
+            }
+            LoadedDatabase::Gnomad(db) => {
+                self.gnomad.replace(db);
+            }
+            LoadedDatabase::Liftover(chain) => {
+                self.liftover.replace(chain);
+            }
+            LoadedDatabase::Haplogroups(db) => {
+                self.haplogroups.replace(db);
+            }
+            LoadedDatabase::ClinGen(db) => {
+                self.clingen.replace(db);
+            }
+            LoadedDatabase::ProbeMask(mask) => {
+                self.probe_mask.replace(mask);
+            }
+            LoadedDatabase::Custom(table) => {
+                self.custom.replace(table);
+            }
+        }
+    }
+
+    /// Stop offering a database to new analyses
+    pub fn unload(&self, kind: DatabaseKind) {
+        match kind {
+            DatabaseKind::ClinVar => drop(self.clinvar.take()),
+            DatabaseKind::PharmGkb => drop(self.pharmgkb.take()),
+            DatabaseKind::Cpic => drop(self.cpic.take()),
+            DatabaseKind::Gwas => drop(self.gwas.take()),
+            DatabaseKind::DbSnp => drop(self.dbsnp.take()),
+            DatabaseKind::Gnomad => drop(self.gnomad.take()),
+            DatabaseKind::Liftover => drop(self.liftover.take()),
+            DatabaseKind::Haplogroups => drop(self.haplogroups.take()),
+            DatabaseKind::ClinGen => drop(self.clingen.take()),
+            DatabaseKind::ProbeMask => drop(self.probe_mask.take()),
+            DatabaseKind::Custom => drop(self.custom.take()),
+        }
+    }
+}
+
+/// One downloadable release listed in a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Release {
+    pub database: DatabaseKind,
+    pub version: String,
+    pub url: String,
+    /// Name to install the release under, one of [`DatabaseKind::file_names`]
+    pub file_name: String,
+    /// Lowercase hex SHA-256 digest of the file
+    pub sha256: String,
+    pub size: Option<u64>,
+}
+
+/// Signed list of the current database releases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    pub releases: Vec<Release>,
+    #[serde(default)]
+    pub references: Vec<ReferenceRelease>,
+}
+
+impl ReleaseManifest {
+    /// Parse a manifest after checking its Ed25519 signature
+    ///
+    /// `signature` is the hex-encoded signature over the exact manifest
+    /// bytes and `public_key` the hex-encoded release signing key.
+    pub fn parse_signed(
+        manifest: &[u8],
+        signature: &str,
+        public_key: &str,
+    ) -> Result<Self, String> {
+        verify_signature(manifest, signature, public_key)?;
+        serde_json::from_slice(manifest).map_err(|e| format!("Invalid release manifest: {}", e))
+    }
+
+    /// Release listed for a database, if any
+    pub fn release(&self, kind: DatabaseKind) -> Option<&Release> {
+        self.releases
+            .iter()
+            .find(|release| release.database == kind)
+    }
+
+    /// Reference FASTA listed for a build, if any
+    pub fn reference(&self, build: GenomeBuild) -> Option<&ReferenceRelease> {
+        self.references
+            .iter()
+            .find(|reference| reference.build == build)
+    }
+}
+
+/// Details kept about an installed release
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledRelease {
+    pub version: Option<String>,
+    pub file_name: String,
+    pub sha256: String,
+    /// Seconds since the Unix epoch
+    pub installed_at: u64,
+    /// Digests of the tables of a PharmGKB or CPIC release, by file name,
+    /// as installed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tables: BTreeMap<String, String>,
+    /// Outcome of the latest [`verify_installed`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityCheck>,
+}
+
+/// Whether an installed release is as it was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    Intact,
+    /// A file differs from its digest
+    Corrupt,
+    /// The release is recorded but its files are gone
+    Missing,
+    /// No digest to check against, as for releases installed by hand
+    Unverifiable,
+}
+
+/// Outcome of checking an installed release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityCheck {
+    pub status: IntegrityStatus,
+    /// What differs, e.g. "clinvar.vcf.gz: expected 3a7f..., got 91c2..."
+    pub problem: Option<String>,
+    /// Whether the digest came from the signed manifest rather than the
+    /// record kept at install
+    pub against_manifest: bool,
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+}
+
+impl IntegrityCheck {
+    /// Whether the release should not be read
+    pub fn is_damaged(&self) -> bool {
+        matches!(
+            self.status,
+            IntegrityStatus::Corrupt | IntegrityStatus::Missing
+        )
+    }
+}
+
+/// Installed releases by database, stored as `installed.json`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InstalledReleases {
+    #[serde(flatten)]
+    pub releases: HashMap<DatabaseKind, InstalledRelease>,
+}
+
+impl InstalledReleases {
+    /// Read the record from a database directory; missing means empty
+    pub fn read(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(INSTALLED_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    fn write(&self, dir: &Path) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        let staged = dir.join(format!("{}.tmp", INSTALLED_FILE));
+        std::fs::write(&staged, json)
+            .map_err(|e| format!("Failed to write release record: {}", e))?;
+        std::fs::rename(&staged, dir.join(INSTALLED_FILE))
+            .map_err(|e| format!("Failed to write release record: {}", e))
+    }
+
+    pub fn get(&self, kind: DatabaseKind) -> Option<&InstalledRelease> {
+        self.releases.get(&kind)
+    }
+}
+
+/// Result of installing a release
+#[derive(Debug)]
+pub struct Installation {
+    pub path: PathBuf,
+    pub record: InstalledRelease,
+    pub database: LoadedDatabase,
+}
+
+/// Path of the preferred installed release of a database, if any
+pub fn find_installed(dir: &Path, kind: DatabaseKind) -> Option<PathBuf> {
+    kind.file_names()
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| match kind {
+            DatabaseKind::PharmGkb => path.join(pharmgkb::ANNOTATIONS_FILE).is_file(),
+            DatabaseKind::Cpic => cpic::has_definitions(path),
+            _ => path.is_file(),
+        })
+}
+
+/// Directory to stage downloads in before they are verified
+///
+/// It lives inside the database directory so that the final move is a
+/// rename on the same volume.
+pub fn staging_dir(dir: &Path) -> Result<PathBuf, String> {
+    let staging = dir.join(STAGING_DIR);
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    Ok(staging)
+}
+
+/// Unique path inside the staging directory
+pub fn staging_path(dir: &Path, kind: DatabaseKind) -> Result<PathBuf, String> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let unique = format!(
+        "{}-{}-{}.part",
+        kind.as_str(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    Ok(staging_dir(dir)?.join(unique))
+}
+
+/// Path in the staging directory to download a release to
+///
+/// The name follows from the release's digest, so a download cut off
+/// midway is found and resumed by the next attempt, even after a restart.
+/// Partial downloads of other releases of the database are removed.
+pub fn download_path(dir: &Path, release: &Release) -> Result<PathBuf, String> {
+    let digest = release.sha256.trim().to_ascii_lowercase();
+    if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid digest for {}", release.database.as_str()));
+    }
+    let staging = staging_dir(dir)?;
+    let prefix = format!("{}-", release.database.as_str());
+    let name = format!("{}{}.{}", prefix, &digest[..16], DOWNLOAD_EXTENSION);
+    if let Ok(entries) = std::fs::read_dir(&staging) {
+        for entry in entries.flatten() {
+            let other = entry.file_name().to_string_lossy().into_owned();
+            if other != name
+                && other.starts_with(&prefix)
+                && other.ends_with(&format!(".{}", DOWNLOAD_EXTENSION))
+            {
+                discard(&entry.path());
+            }
+        }
+    }
+    Ok(staging.join(name))
+}
+
+/// Copy a local release into the staging directory for [`install_release`]
+///
+/// `source` is a release file, or for PharmGKB and CPIC a directory
+/// holding the tables. The original is left untouched.
+pub fn stage_local(dir: &Path, kind: DatabaseKind, source: &Path) -> Result<PathBuf, String> {
+    let staged = staging_path(dir, kind)?;
+    let failed = |e: std::io::Error| format!("Failed to copy {}: {}", source.display(), e);
+
+    if source.is_dir() {
+        if !matches!(kind, DatabaseKind::PharmGkb | DatabaseKind::Cpic) {
+            return Err(format!("Expected a file for {}", kind.as_str()));
+        }
+        std::fs::create_dir_all(&staged).map_err(failed)?;
+        for entry in std::fs::read_dir(source).map_err(failed)? {
+            let entry = entry.map_err(failed)?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if is_release_table(kind, &name) && entry.path().is_file() {
+                std::fs::copy(entry.path(), staged.join(&name)).map_err(failed)?;
+            }
+        }
+    } else {
+        std::fs::copy(source, &staged).map_err(failed)?;
+    }
+
+    Ok(staged)
+}
+
+/// Checksum published next to a file as `<file>.sha256`, if present
+///
+/// Accepts both a bare digest and the `sha256sum` output format.
+pub fn sidecar_checksum(path: &Path) -> Result<Option<String>, String> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".sha256");
+    let sidecar = PathBuf::from(sidecar);
+    if !sidecar.is_file() {
+        return Ok(None);
+    }
+    let contents = std::fs::read_to_string(&sidecar)
+        .map_err(|e| format!("Failed to read {}: {}", sidecar.display(), e))?;
+    contents
+        .split_whitespace()
+        .next()
+        .map(|digest| Some(digest.to_string()))
+        .ok_or_else(|| format!("{} is empty", sidecar.display()))
+}
+
+/// Lowercase hex SHA-256 digest of a file
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Fail unless a file has the expected SHA-256 digest
+pub fn verify_checksum(path: &Path, expected: &str) -> Result<String, String> {
+    let actual = sha256_file(path)?;
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(actual)
+    } else {
+        Err(format!(
+            "Checksum mismatch: expected {}, got {}",
+            expected.trim(),
+            actual
+        ))
+    }
+}
+
+/// Check a hex-encoded Ed25519 signature over `message`
+pub fn verify_signature(message: &[u8], signature: &str, public_key: &str) -> Result<(), String> {
+    let key: [u8; 32] = decode_hex(public_key, "public key")?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|e| format!("Invalid public key: {}", e))?;
+    let signature: [u8; 64] = decode_hex(signature, "signature")?;
+    key.verify_strict(message, &Signature::from_bytes(&signature))
+        .map_err(|_| "Signature verification failed".to_string())
+}
+
+/// Verify a staged release and move it into the database directory
+///
+/// `staged` must come from [`staging_path`] or [`stage_local`] and is
+/// consumed. It is checked against `expected_sha256` when given and must
+/// load as a `kind` release; a release that fails either check is deleted
+/// and the current one stays in place. PharmGKB releases may be the zipped
+/// table archive or a directory of extracted tables.
+pub fn install_release(
+    dir: &Path,
+    kind: DatabaseKind,
+    staged: &Path,
+    file_name: &str,
+    version: Option<&str>,
+    expected_sha256: Option<&str>,
+) -> Result<Installation, String> {
+    if !staged.starts_with(dir.join(STAGING_DIR)) {
+        return Err("Release was not staged in the database directory".to_string());
+    }
+    if !kind.file_names().contains(&file_name) {
+        return Err(format!(
+            "Unexpected file name for {}: {}",
+            kind.as_str(),
+            file_name
+        ));
+    }
+    let sha256 = match (staged.is_file(), expected_sha256) {
+        (true, Some(expected)) => verify_checksum(staged, expected),
+        (true, None) => sha256_file(staged),
+        (false, Some(_)) => Err("Checksums can only be verified for files".to_string()),
+        (false, None) => Ok(String::new()),
+    };
+    let sha256 = match sha256 {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            discard(staged);
+            return Err(e);
+        }
+    };
+
+    let (candidate, database) = match stage_and_load(kind, staged) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            discard(staged);
+            return Err(format!("Release failed validation: {}", e));
+        }
+    };
+    if database.is_empty() {
+        discard(&candidate);
+        return Err("Release failed validation: no records".to_string());
+    }
+
+    let target = dir.join(file_name);
+    let tables = match table_digests(&candidate) {
+        Ok(tables) => tables,
+        Err(e) => {
+            discard(&candidate);
+            return Err(e);
+        }
+    };
+    swap_into_place(&candidate, &target)?;
+
+    // Drop releases under other names so the new one is preferred on load
+    for other in kind.file_names().iter().filter(|name| **name != file_name) {
+        discard(&dir.join(other));
+    }
+
+    let record = InstalledRelease {
+        version: version.map(str::to_string),
+        file_name: file_name.to_string(),
+        sha256,
+        installed_at: unix_now(),
+        tables,
+        integrity: None,
+    };
+    let mut installed = InstalledReleases::read(dir)?;
+    installed.releases.insert(kind, record.clone());
+    installed.write(dir)?;
+
+    Ok(Installation {
+        path: target,
+        record,
+        database,
+    })
+}
+
+/// Check every installed release against the digests it was installed
+/// with, and record the outcome in the release record
+///
+/// A release whose version the signed `manifest` lists is checked against
+/// the manifest's digest instead, so a release record that was changed
+/// along with the file does not hide the damage.
+pub fn verify_installed(
+    dir: &Path,
+    manifest: Option<&ReleaseManifest>,
+) -> Result<Vec<(DatabaseKind, IntegrityCheck)>, String> {
+    let mut installed = InstalledReleases::read(dir)?;
+    let mut checks = Vec::new();
+    for kind in DatabaseKind::ALL {
+        let path = find_installed(dir, kind);
+        let record = installed.releases.get_mut(&kind);
+        if record.is_none() && path.is_none() {
+            continue;
+        }
+        let listed = manifest.and_then(|manifest| manifest.release(kind));
+        let check = verify_release(path.as_deref(), record.as_deref(), listed);
+        if let Some(record) = record {
+            record.integrity = Some(check.clone());
+        }
+        checks.push((kind, check));
+    }
+    installed.write(dir)?;
+    Ok(checks)
+}
+
+// Helper functions
+
+fn verify_release(
+    path: Option<&Path>,
+    record: Option<&InstalledRelease>,
+    listed: Option<&Release>,
+) -> IntegrityCheck {
+    let check = |status, problem: Option<String>, against_manifest| IntegrityCheck {
+        status,
+        problem,
+        against_manifest,
+        checked_at: unix_now(),
+    };
+    let Some(path) = path else {
+        let file = record.map_or("", |record| record.file_name.as_str());
+        return check(
+            IntegrityStatus::Missing,
+            Some(format!("{} is missing", file)),
+            false,
+        );
+    };
+    let Some(record) = record else {
+        return check(IntegrityStatus::Unverifiable, None, false);
+    };
+
+    let mut expected: Vec<(PathBuf, &str)> = Vec::new();
+    let mut against_manifest = false;
+    if path.is_dir() {
+        expected.extend(
+            record
+                .tables
+                .iter()
+                .map(|(name, digest)| (path.join(name), digest.as_str())),
+        );
+    } else {
+        let manifest_digest = listed
+            .filter(|release| {
+                record.version.as_deref() == Some(release.version.as_str())
+                    && release.file_name == record.file_name
+            })
+            .map(|release| release.sha256.as_str());
+        against_manifest = manifest_digest.is_some();
+        let digest = manifest_digest.unwrap_or(&record.sha256);
+        if !digest.is_empty() {
+            expected.push((path.to_path_buf(), digest));
+        }
+    }
+    if expected.is_empty() {
+        return check(IntegrityStatus::Unverifiable, None, false);
+    }
+    for (file, digest) in expected {
+        let name = file
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        if !file.is_file() {
+            return check(
+                IntegrityStatus::Corrupt,
+                Some(format!("{} is missing", name)),
+                against_manifest,
+            );
+        }
+        let actual = match sha256_file(&file) {
+            Ok(actual) => actual,
+            Err(e) => return check(IntegrityStatus::Corrupt, Some(e), against_manifest),
+        };
+        if !actual.eq_ignore_ascii_case(digest.trim()) {
+            return check(
+                IntegrityStatus::Corrupt,
+                Some(format!(
+                    "{}: expected {}, got {}",
+                    name,
+                    digest.trim(),
+                    actual
+                )),
+                against_manifest,
+            );
+        }
+    }
+    check(IntegrityStatus::Intact, None, against_manifest)
+}
+
+/// Digests of the files of a directory release, by name; none for a file
+fn table_digests(candidate: &Path) -> Result<BTreeMap<String, String>, String> {
+    let mut tables = BTreeMap::new();
+    if !candidate.is_dir() {
+        return Ok(tables);
+    }
+    let failed = |e: std::io::Error| format!("Failed to read release: {}", e);
+    for entry in std::fs::read_dir(candidate).map_err(failed)? {
+        let entry = entry.map_err(failed)?;
+        if entry.path().is_file() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            tables.insert(name, sha256_file(&entry.path())?);
+        }
+    }
+    Ok(tables)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Prepare the staged release for loading and load it
+fn stage_and_load(kind: DatabaseKind, staged: &Path) -> Result<(PathBuf, LoadedDatabase), String> {
+    let candidate = match kind {
+        DatabaseKind::PharmGkb | DatabaseKind::Cpic if staged.is_file() => {
+            let extracted = staged.with_extension("tables");
+            let result = extract_tables(staged, &extracted, |name| is_release_table(kind, name));
+            discard(staged);
+            result?;
+            extracted
+        }
+        _ => staged.to_path_buf(),
+    };
+    let database = LoadedDatabase::load(kind, &candidate).inspect_err(|_| discard(&candidate))?;
+    Ok((candidate, database))
+}
+
+/// Whether a file is one of the tables of a directory release
+fn is_release_table(kind: DatabaseKind, name: &str) -> bool {
+    match kind {
+        DatabaseKind::PharmGkb => PHARMGKB_TABLES.contains(&name),
+        DatabaseKind::Cpic => cpic::is_table_name(name),
+        _ => false,
+    }
+}
+
+/// Extract the tables from a zip archive, wherever they sit in it
+fn extract_tables<F>(archive: &Path, target: &Path, is_table: F) -> Result<(), String>
+where
+    F: Fn(&str) -> bool,
+{
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut zip = zip::ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {}", e))?;
+    std::fs::create_dir_all(target).map_err(|e| format!("Failed to extract archive: {}", e))?;
+
+    for index in 0..zip.len() {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| format!("Invalid zip archive: {}", e))?;
+        let Some(name) = entry
+            .enclosed_name()
+            .and_then(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+        else {
+            continue;
+        };
+        if !is_table(&name) {
+            continue;
+        }
+        let mut out = File::create(target.join(&name))
+            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+        std::io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    }
+
+    Ok(())
+}
+
+/// Replace `target` with `candidate`
+///
+/// Files are replaced with a single rename. Directories cannot be renamed
+/// over, so the old directory is moved aside first and restored if the
+/// second rename fails.
+fn swap_into_place(candidate: &Path, target: &Path) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Failed to install release: {}", e);
+
+    if candidate.is_file() && !target.is_dir() {
+        return std::fs::rename(candidate, target).map_err(failed);
+    }
+
+    let previous = target.with_extension("previous");
+    discard(&previous);
+    let had_previous = target.exists();
+    if had_previous {
+        std::fs::rename(target, &previous).map_err(failed)?;
+    }
+    if let Err(e) = std::fs::rename(candidate, target) {
+        if had_previous {
+            let _ = std::fs::rename(&previous, target);
+        }
+        return Err(failed(e));
+    }
+    discard(&previous);
+    Ok(())
+}
+
+/// Best-effort removal of a file or directory
+fn discard(path: &Path) {
+    let _ = if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+}
+
+fn decode_hex<const N: usize>(raw: &str, what: &str) -> Result<[u8; N], String> {
+    let bytes = hex::decode(raw.trim()).map_err(|_| format!("Invalid {}: not hex", what))?;
+    bytes
+        .try_into()
+        .map_err(|_| format!("Invalid {}: expected {} bytes", what, N))
+}
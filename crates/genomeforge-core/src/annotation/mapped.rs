@@ -0,0 +1,658 @@
+//! Memory-mapped releases of the largest databases
+//!
+//! dbSNP and gnomAD releases run to gigabytes once indexed in memory. A
+//! [`MappedDatabase`] is the same release written as one compact binary
+//! file of fixed-size site entries sorted by (chromosome, position), an
+//! rsid index sorted by number, and a pool of allele text. Opening one
+//! maps the file read-only and reads nothing else, so it is immediate; a
+//! lookup is a binary search that touches only the pages it needs, which
+//! the operating system pages in and out as memory allows.
+//!
+//! [`MappedDatabase::subset`] decodes the records a genome can match into
+//! an ordinary in-memory database, so the analyses run on it unchanged.
+//! Sites on contigs other than 1-22, X, Y and MT are left out.
+
+use super::dbsnp::{DbSnpIndex, DbSnpRecord};
+use super::gnomad::{
+    AlleleFrequencies, GnomadDatabase, GnomadRecord, Population, PopulationFrequency,
+};
+use super::manager::{DatabaseKind, LoadedDatabase};
+use super::normalize_rsid;
+use crate::genome::GenomeBuild;
+use crate::parser::normalize_chromosome;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Version of the file layout releases are mapped with
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// First bytes of every mapped release
+const MAGIC: &[u8; 4] = b"GFMA";
+
+const HEADER_LEN: usize = 64;
+
+/// Chromosome, allele length, position, allele offset, rsid number
+const SITE_LEN: usize = 1 + 1 + 2 + 4 + 4 + 4;
+
+/// rsid number, site index
+const RSID_LEN: usize = 4 + 4;
+
+/// Global frequency and one per [`Population`], as f32; NaN when the
+/// release reports none
+const FREQUENCIES_LEN: usize = 4 * (1 + Population::ALL.len());
+
+/// A dbSNP or gnomAD release mapped read-only into memory
+#[derive(Debug)]
+pub struct MappedDatabase {
+    map: sys::Mapping,
+    kind: DatabaseKind,
+    genome_build: Option<GenomeBuild>,
+    release_date: Option<String>,
+    /// Bytes of each site entry, with what its kind adds
+    site_len: usize,
+    sites: Range<usize>,
+    rsids: Range<usize>,
+    pool: Range<usize>,
+}
+
+/// A record read from a mapped release
+#[derive(Debug, Clone)]
+pub enum MappedRecord {
+    DbSnp(DbSnpRecord),
+    Gnomad(GnomadRecord),
+}
+
+impl MappedDatabase {
+    /// Write a loaded dbSNP release in the mapped format
+    pub fn write_dbsnp(path: &Path, database: &DbSnpIndex) -> Result<(), String> {
+        let sites = database.records().iter().map(|record| RawSite {
+            chromosome: &record.chromosome,
+            position: record.position,
+            alleles: format!("{}\t{}", record.reference, record.alternates.join(",")),
+            rsid: Some(record.rsid.as_str()),
+            payload: Vec::new(),
+        });
+        let header = Header {
+            kind: DatabaseKind::DbSnp,
+            genome_build: database.genome_build(),
+            release_date: database.release_date(),
+            payload_len: 0,
+        };
+        write_file(path, header, sites)
+    }
+
+    /// Write a loaded gnomAD release in the mapped format
+    pub fn write_gnomad(path: &Path, database: &GnomadDatabase) -> Result<(), String> {
+        let sites = database.records().iter().map(|record| RawSite {
+            chromosome: &record.chromosome,
+            position: record.position,
+            alleles: format!("{}\t{}", record.reference, record.alternate),
+            rsid: record.rsid.as_deref(),
+            payload: encode_frequencies(&record.frequencies),
+        });
+        let header = Header {
+            kind: DatabaseKind::Gnomad,
+            genome_build: database.genome_build(),
+            release_date: database.release_date(),
+            payload_len: FREQUENCIES_LEN,
+        };
+        write_file(path, header, sites)
+    }
+
+    /// Map a release written with the current schema
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let map = sys::Mapping::new(&file)
+            .map_err(|e| format!("Failed to map {}: {}", path.display(), e))?;
+        let bytes = map.bytes();
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(format!("{} is not a mapped database", path.display()));
+        }
+        let version = u32_at(bytes, 4);
+        if version != SCHEMA_VERSION {
+            return Err(format!(
+                "{} has schema {}, not {}; rebuild it from the release",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            ));
+        }
+        let kind = match bytes[8] {
+            1 => DatabaseKind::DbSnp,
+            2 => DatabaseKind::Gnomad,
+            other => return Err(format!("Unknown mapped database kind {}", other)),
+        };
+        let genome_build = match bytes[9] {
+            36 => Some(GenomeBuild::GRCh36),
+            37 => Some(GenomeBuild::GRCh37),
+            38 => Some(GenomeBuild::GRCh38),
+            _ => None,
+        };
+        // Lookups decode the payload of each entry as its kind lays it out
+        let payload_len = u16_at(bytes, 10) as usize;
+        let expected = match kind {
+            DatabaseKind::Gnomad => FREQUENCIES_LEN,
+            _ => 0,
+        };
+        if payload_len != expected {
+            return Err(format!("{} is corrupt", path.display()));
+        }
+        let site_len = SITE_LEN + payload_len;
+        let range = |at: usize| -> Result<Range<usize>, String> {
+            let start = u64_at(bytes, at) as usize;
+            let end = start.checked_add(u64_at(bytes, at + 8) as usize);
+            match end {
+                Some(end) if start >= HEADER_LEN && end <= bytes.len() => Ok(start..end),
+                _ => Err(format!("{} is truncated or corrupt", path.display())),
+            }
+        };
+        let sites = range(16)?;
+        let rsids = range(32)?;
+        let pool = range(48)?;
+        if sites.len() % site_len != 0 || rsids.len() % RSID_LEN != 0 {
+            return Err(format!("{} is corrupt", path.display()));
+        }
+        let date_len = u16_at(bytes, 12) as usize;
+        let release_date = (date_len > 0)
+            .then(|| bytes.get(pool.start..pool.start + date_len))
+            .flatten()
+            .map(|date| String::from_utf8_lossy(date).into_owned());
+
+        Ok(MappedDatabase {
+            kind,
+            genome_build,
+            release_date,
+            site_len,
+            sites,
+            rsids,
+            pool,
+            map,
+        })
+    }
+
+    /// Which database the release is of
+    pub fn kind(&self) -> DatabaseKind {
+        self.kind
+    }
+
+    /// Number of sites, one per ALT allele for gnomAD
+    pub fn len(&self) -> usize {
+        self.sites.len() / self.site_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Build the positions refer to
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        self.genome_build
+    }
+
+    /// Release date of the release it was written from, as YYYY-MM-DD
+    pub fn release_date(&self) -> Option<&str> {
+        self.release_date.as_deref()
+    }
+
+    /// Records at a position
+    pub fn lookup_position(&self, chromosome: &str, position: u64) -> Vec<MappedRecord> {
+        self.sites_at(chromosome, position)
+            .filter_map(|site| self.record(site))
+            .collect()
+    }
+
+    /// Records listed under an rsid
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<MappedRecord> {
+        self.sites_for_rsid(rsid)
+            .into_iter()
+            .filter_map(|site| self.record(site))
+            .collect()
+    }
+
+    /// The records any variant of a genome could match, by its site or
+    /// its rsid, as a database to place in its slot
+    ///
+    /// `checkpoint` is called with the number of variants looked up so far
+    /// and stops the lookup when it returns an error.
+    pub fn subset<F>(
+        &self,
+        genome: &LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<LoadedDatabase, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut sites = Vec::new();
+        for (index, variant) in genome.variants().iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            sites.extend(self.sites_at(&variant.chromosome, variant.position));
+            if let Some(rsid) = &variant.rsid {
+                sites.extend(self.sites_for_rsid(rsid));
+            }
+        }
+        sites.sort_unstable();
+        sites.dedup();
+
+        let records = sites.into_iter().filter_map(|site| self.record(site));
+        let release_date = self.release_date.clone();
+        Ok(match self.kind {
+            DatabaseKind::DbSnp => {
+                let records = records
+                    .filter_map(|record| match record {
+                        MappedRecord::DbSnp(record) => Some(record),
+                        MappedRecord::Gnomad(_) => None,
+                    })
+                    .collect();
+                LoadedDatabase::DbSnp(DbSnpIndex::from_records(
+                    records,
+                    self.genome_build,
+                    release_date,
+                ))
+            }
+            _ => {
+                let records = records
+                    .filter_map(|record| match record {
+                        MappedRecord::Gnomad(record) => Some(record),
+                        MappedRecord::DbSnp(_) => None,
+                    })
+                    .collect();
+                LoadedDatabase::Gnomad(GnomadDatabase::from_records(
+                    records,
+                    self.genome_build,
+                    release_date,
+                ))
+            }
+        })
+    }
+
+    /// Indexes of the sites at a position
+    fn sites_at(&self, chromosome: &str, position: u64) -> Range<usize> {
+        let Some(key) = site_key(chromosome, position) else {
+            return 0..0;
+        };
+        let bytes = &self.map.bytes()[self.sites.clone()];
+        let key_of = |index: usize| {
+            let entry = &bytes[index * self.site_len..];
+            (entry[0], u32_at(entry, 4))
+        };
+        let start = partition_point(self.len(), |index| key_of(index) < key);
+        let end = partition_point(self.len(), |index| key_of(index) <= key);
+        start..end
+    }
+
+    /// Indexes of the sites listed under an rsid
+    fn sites_for_rsid(&self, rsid: &str) -> Vec<usize> {
+        let Some(number) = rsid_number(rsid) else {
+            return Vec::new();
+        };
+        let bytes = &self.map.bytes()[self.rsids.clone()];
+        let count = bytes.len() / RSID_LEN;
+        let entry = |index: usize| &bytes[index * RSID_LEN..(index + 1) * RSID_LEN];
+        let start = partition_point(count, |index| u32_at(entry(index), 0) < number);
+        (start..count)
+            .take_while(|&index| u32_at(entry(index), 0) == number)
+            .map(|index| u32_at(entry(index), 4) as usize)
+            .collect()
+    }
+
+    /// Decode one site entry
+    fn record(&self, site: usize) -> Option<MappedRecord> {
+        let bytes = self.map.bytes();
+        let start = self.sites.start + site * self.site_len;
+        let entry = bytes.get(start..start + self.site_len)?;
+        let chromosome = chromosome_name(entry[0])?.to_string();
+        let position = u32_at(entry, 4) as u64;
+        let alleles_start = self.pool.start + u32_at(entry, 8) as usize;
+        let alleles = bytes.get(alleles_start..alleles_start + u16_at(entry, 2) as usize)?;
+        let (reference, alternates) = std::str::from_utf8(alleles).ok()?.split_once('\t')?;
+        let rsid = match u32_at(entry, 12) {
+            0 => None,
+            number => Some(format!("rs{}", number)),
+        };
+
+        Some(match self.kind {
+            DatabaseKind::DbSnp => MappedRecord::DbSnp(DbSnpRecord {
+                rsid: rsid?,
+                chromosome,
+                position,
+                reference: reference.to_string(),
+                alternates: alternates.split(',').map(str::to_string).collect(),
+            }),
+            _ => MappedRecord::Gnomad(GnomadRecord {
+                rsid,
+                chromosome,
+                position,
+                reference: reference.to_string(),
+                alternate: alternates.to_string(),
+                frequencies: decode_frequencies(&entry[SITE_LEN..]),
+            }),
+        })
+    }
+}
+
+// Helper functions
+
+/// What precedes the sites of a mapped release
+struct Header<'a> {
+    kind: DatabaseKind,
+    genome_build: Option<GenomeBuild>,
+    release_date: Option<&'a str>,
+    payload_len: usize,
+}
+
+/// A record to write, before its alleles are placed in the pool
+struct RawSite<'a> {
+    chromosome: &'a str,
+    position: u64,
+    alleles: String,
+    rsid: Option<&'a str>,
+    payload: Vec<u8>,
+}
+
+/// Write the sites sorted, indexed and pooled to `path`, by way of a
+/// partial file renamed into place
+fn write_file<'a, I>(path: &Path, header: Header<'_>, sites: I) -> Result<(), String>
+where
+    I: Iterator<Item = RawSite<'a>>,
+{
+    let partial = path.with_extension("partial");
+    let written = write_partial(&partial, header, sites)
+        .and_then(|_| fs::rename(&partial, path).map_err(|e| e.to_string()));
+    written.map_err(|e| {
+        let _ = fs::remove_file(&partial);
+        format!("Failed to write {}: {}", path.display(), e)
+    })
+}
+
+fn write_partial<'a, I>(path: &Path, header: Header<'_>, sites: I) -> Result<(), String>
+where
+    I: Iterator<Item = RawSite<'a>>,
+{
+    let date = header.release_date.unwrap_or("").as_bytes();
+    let mut pool: Vec<u8> = date.to_vec();
+    let mut entries: Vec<((u8, u32), Vec<u8>)> = Vec::new();
+    for site in sites {
+        let (Some(key), Ok(alleles_len)) = (
+            site_key(site.chromosome, site.position),
+            u16::try_from(site.alleles.len()),
+        ) else {
+            continue;
+        };
+        let offset = u32::try_from(pool.len()).map_err(|_| "allele pool exceeds 4 GB")?;
+        pool.extend_from_slice(site.alleles.as_bytes());
+        let rsid = site.rsid.and_then(rsid_number).unwrap_or(0);
+
+        let mut entry = Vec::with_capacity(SITE_LEN + header.payload_len);
+        entry.extend_from_slice(&[key.0, 0]);
+        entry.extend_from_slice(&alleles_len.to_le_bytes());
+        entry.extend_from_slice(&key.1.to_le_bytes());
+        entry.extend_from_slice(&offset.to_le_bytes());
+        entry.extend_from_slice(&rsid.to_le_bytes());
+        entry.extend_from_slice(&site.payload);
+        entries.push((key, entry));
+    }
+    entries.sort_by_key(|entry| entry.0);
+
+    let mut rsids: Vec<(u32, u32)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, entry))| {
+            let number = u32_at(entry, 12);
+            (number != 0).then_some((number, index as u32))
+        })
+        .collect();
+    rsids.sort_unstable();
+
+    let site_len = SITE_LEN + header.payload_len;
+    let sites_start = HEADER_LEN as u64;
+    let sites_len = (entries.len() * site_len) as u64;
+    let rsids_len = (rsids.len() * RSID_LEN) as u64;
+    let mut head = Vec::with_capacity(HEADER_LEN);
+    head.extend_from_slice(MAGIC);
+    head.extend_from_slice(&SCHEMA_VERSION.to_le_bytes());
+    head.push(match header.kind {
+        DatabaseKind::DbSnp => 1,
+        _ => 2,
+    });
+    head.push(match header.genome_build {
+        Some(GenomeBuild::GRCh36) => 36,
+        Some(GenomeBuild::GRCh37) => 37,
+        Some(GenomeBuild::GRCh38) => 38,
+        None => 0,
+    });
+    head.extend_from_slice(&(header.payload_len as u16).to_le_bytes());
+    head.extend_from_slice(&(date.len() as u16).to_le_bytes());
+    head.extend_from_slice(&[0, 0]);
+    for field in [
+        sites_start,
+        sites_len,
+        sites_start + sites_len,
+        rsids_len,
+        sites_start + sites_len + rsids_len,
+        pool.len() as u64,
+    ] {
+        head.extend_from_slice(&field.to_le_bytes());
+    }
+
+    let io = |e: std::io::Error| e.to_string();
+    let mut out = BufWriter::new(File::create(path).map_err(io)?);
+    out.write_all(&head).map_err(io)?;
+    for (_, entry) in &entries {
+        out.write_all(entry).map_err(io)?;
+    }
+    for (number, index) in &rsids {
+        out.write_all(&number.to_le_bytes()).map_err(io)?;
+        out.write_all(&index.to_le_bytes()).map_err(io)?;
+    }
+    out.write_all(&pool).map_err(io)?;
+    out.into_inner()
+        .map_err(|e| e.to_string())?
+        .sync_all()
+        .map_err(io)
+}
+
+/// Chromosome code and position of a site on 1-22, X, Y or MT
+fn site_key(chromosome: &str, position: u64) -> Option<(u8, u32)> {
+    let code = match normalize_chromosome(chromosome).as_str() {
+        "X" => 23,
+        "Y" => 24,
+        "MT" => 25,
+        other => other.parse::<u8>().ok().filter(|n| (1..=22).contains(n))?,
+    };
+    Some((code, u32::try_from(position).ok()?))
+}
+
+fn chromosome_name(code: u8) -> Option<&'static str> {
+    const NAMES: [&str; 25] = [
+        "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16",
+        "17", "18", "19", "20", "21", "22", "X", "Y", "MT",
+    ];
+    NAMES.get(usize::from(code).checked_sub(1)?).copied()
+}
+
+fn rsid_number(rsid: &str) -> Option<u32> {
+    normalize_rsid(rsid)?[2..].parse().ok()
+}
+
+fn encode_frequencies(frequencies: &AlleleFrequencies) -> Vec<u8> {
+    std::iter::once(Some(frequencies.global))
+        .chain(
+            Population::ALL
+                .iter()
+                .map(|population| frequencies.population(*population)),
+        )
+        .flat_map(|frequency| (frequency.unwrap_or(f64::NAN) as f32).to_le_bytes())
+        .collect()
+}
+
+fn decode_frequencies(payload: &[u8]) -> AlleleFrequencies {
+    let value = |n: usize| f32::from_le_bytes(payload[n * 4..n * 4 + 4].try_into().unwrap());
+    AlleleFrequencies {
+        global: value(0) as f64,
+        populations: Population::ALL
+            .iter()
+            .enumerate()
+            .filter(|(n, _)| !value(n + 1).is_nan())
+            .map(|(n, population)| PopulationFrequency {
+                population: *population,
+                frequency: value(n + 1) as f64,
+            })
+            .collect(),
+    }
+}
+
+/// First index in `0..count` for which `below` is false
+fn partition_point<F: Fn(usize) -> bool>(count: usize, below: F) -> usize {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if below(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    /// A whole file mapped read-only, unmapped when dropped
+    #[derive(Debug)]
+    pub struct Mapping {
+        address: *mut libc::c_void,
+        len: usize,
+    }
+
+    // The mapping is read-only, so sharing it between threads is sound
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        pub fn new(file: &File) -> io::Result<Self> {
+            let len = file.metadata()?.len() as usize;
+            if len == 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "empty file"));
+            }
+            let address = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if address == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Mapping { address, len })
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            // Releases are replaced by renaming a new file into place, so
+            // the mapped file itself is never written while mapped
+            unsafe { std::slice::from_raw_parts(self.address as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.address, self.len) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Memory::{
+        CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, FILE_MAP_READ,
+        MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READONLY,
+    };
+
+    /// A whole file mapped read-only, unmapped when dropped
+    #[derive(Debug)]
+    pub struct Mapping {
+        address: *mut core::ffi::c_void,
+        len: usize,
+    }
+
+    // The mapping is read-only, so sharing it between threads is sound
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        pub fn new(file: &File) -> io::Result<Self> {
+            let len = file.metadata()?.len() as usize;
+            if len == 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "empty file"));
+            }
+            let mapping = unsafe {
+                CreateFileMappingW(
+                    file.as_raw_handle(),
+                    std::ptr::null(),
+                    PAGE_READONLY,
+                    0,
+                    0,
+                    std::ptr::null(),
+                )
+            };
+            if mapping.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            // The view keeps the mapping alive once its handle is closed
+            let view = unsafe { MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, 0) };
+            unsafe { CloseHandle(mapping) };
+            if view.Value.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Mapping {
+                address: view.Value,
+                len,
+            })
+        }
+
+        pub fn bytes(&self) -> &[u8] {
+            // Windows refuses to replace a file while it is mapped, so the
+            // bytes cannot change underneath
+            unsafe { std::slice::from_raw_parts(self.address as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            unsafe {
+                UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                    Value: self.address,
+                })
+            };
+        }
+    }
+}
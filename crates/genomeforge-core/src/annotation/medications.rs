@@ -0,0 +1,123 @@
+//! Medication review
+//!
+//! Users enter the medications they take as they know them, often by brand
+//! name and with a dose. Names are reduced to the generic drug names CPIC
+//! and PharmGKB use, and each drug response found for them is given a
+//! priority, so that a list covering every pharmacogene at once can be
+//! read from the top: a strong guideline to avoid a drug at the user's
+//! phenotype comes before a dose adjustment, which comes before standard
+//! dosing.
+
+use super::cpic::Recommendation;
+use super::pharmgkb::EvidenceLevel;
+use serde::{Deserialize, Serialize};
+
+/// How urgently a medication should be reviewed, most urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewPriority {
+    /// A strong guideline recommends another drug or a changed dose
+    High,
+    /// A guideline recommends a change, or actionable PharmGKB evidence
+    /// without one links the genotype to the response
+    Moderate,
+    /// The guideline recommends standard dosing
+    Low,
+    /// Weaker evidence, for information only
+    Informational,
+}
+
+impl ReviewPriority {
+    /// Priority of a CPIC recommendation
+    pub fn of_recommendation(recommendation: &Recommendation) -> ReviewPriority {
+        let text = recommendation.recommendation.to_ascii_lowercase();
+        let changes_therapy = CHANGE_WORDS.iter().any(|word| text.contains(word));
+        let strong = recommendation
+            .classification
+            .as_deref()
+            .is_some_and(|strength| strength.eq_ignore_ascii_case("strong"));
+        match (changes_therapy, strong) {
+            (true, true) => ReviewPriority::High,
+            (true, false) => ReviewPriority::Moderate,
+            (false, _) => ReviewPriority::Low,
+        }
+    }
+
+    /// Priority of a PharmGKB annotation no guideline covers
+    pub fn of_evidence(level: EvidenceLevel) -> ReviewPriority {
+        if level.is_actionable() {
+            ReviewPriority::Moderate
+        } else {
+            ReviewPriority::Informational
+        }
+    }
+}
+
+/// Words of a recommendation that departs from standard dosing
+const CHANGE_WORDS: [&str; 7] = [
+    "avoid",
+    "alternative",
+    "reduce",
+    "reduction",
+    "lower",
+    "limit",
+    "% of",
+];
+
+/// Dosage forms left off medication names
+const FORMS: [&str; 6] = ["tablet", "tablets", "capsule", "capsules", "oral", "er"];
+
+/// Brand names of drugs with pharmacogenomic guidance, and their generic
+/// names
+const BRAND_NAMES: [(&str, &str); 24] = [
+    ("plavix", "clopidogrel"),
+    ("celexa", "citalopram"),
+    ("cipramil", "citalopram"),
+    ("lexapro", "escitalopram"),
+    ("cipralex", "escitalopram"),
+    ("zoloft", "sertraline"),
+    ("prozac", "fluoxetine"),
+    ("paxil", "paroxetine"),
+    ("tylenol with codeine", "codeine"),
+    ("ultram", "tramadol"),
+    ("celebrex", "celecoxib"),
+    ("advil", "ibuprofen"),
+    ("motrin", "ibuprofen"),
+    ("mobic", "meloxicam"),
+    ("zocor", "simvastatin"),
+    ("lipitor", "atorvastatin"),
+    ("crestor", "rosuvastatin"),
+    ("coumadin", "warfarin"),
+    ("jantoven", "warfarin"),
+    ("adrucil", "fluorouracil"),
+    ("xeloda", "capecitabine"),
+    ("dilantin", "phenytoin"),
+    ("vfend", "voriconazole"),
+    ("prilosec", "omeprazole"),
+];
+
+/// Generic name of a medication as a user entered it
+///
+/// Case, a trailing dose or form such as "75 mg", "#3" or "tablet" and
+/// brand names are taken off, so "Plavix 75mg" becomes "clopidogrel".
+pub fn generic_name(entered: &str) -> String {
+    let lower = entered.trim().to_lowercase();
+    let words: Vec<&str> = lower
+        .split_whitespace()
+        .take_while(|word| !word.starts_with(|c: char| c.is_ascii_digit() || c == '#'))
+        .filter(|word| !FORMS.contains(word))
+        .collect();
+    let name = words.join(" ");
+    BRAND_NAMES
+        .iter()
+        .find(|(brand, _)| *brand == name)
+        .map_or(name, |(_, generic)| generic.to_string())
+}
+
+/// Whether a drug field, which may list several drugs as "a, b", names a
+/// generic drug
+pub fn names_drug(field: &str, generic: &str) -> bool {
+    field
+        .split([',', ';', '/'])
+        .any(|drug| drug.trim().eq_ignore_ascii_case(generic))
+}
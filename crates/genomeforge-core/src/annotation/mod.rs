@@ -0,0 +1,249 @@
+//! Offline annotation databases
+//!
+//! Each database module loads a locally stored release into an in-memory
+//! index and matches it against a [`LoadedGenome`](crate::LoadedGenome).
+//! Nothing here downloads data; releases are read from disk, and
+//! [`manager`] verifies and installs new ones handed to it.
+
+pub mod acmg;
+pub mod apoe;
+pub mod blood_type;
+pub mod carrier;
+pub mod clingen;
+pub mod clinvar;
+pub mod clinvar_store;
+pub mod consent;
+pub mod cpic;
+pub mod custom;
+pub mod dbsnp;
+pub mod delta;
+pub mod fitness;
+pub mod genes;
+pub mod gnomad;
+pub mod guidelines;
+pub mod gwas;
+pub mod haplogroup;
+pub mod hla;
+pub mod manager;
+pub mod mapped;
+pub mod medications;
+pub mod nutrigenomics;
+pub mod ontology;
+pub mod pack;
+pub mod pharmgkb;
+pub mod probes;
+pub mod strand;
+pub mod tsv;
+pub mod zygosity;
+
+use crate::genome::Variant;
+use crate::liftover::Liftover;
+use crate::parser::normalize_chromosome;
+use crate::stream::SiteFilter;
+use clingen::ClinGenDatabase;
+use clinvar::ClinVarDatabase;
+use cpic::CpicDatabase;
+use custom::CustomAnnotations;
+use dbsnp::DbSnpIndex;
+use gnomad::GnomadDatabase;
+use gwas::GwasCatalog;
+use haplogroup::HaplogroupDatabase;
+use pharmgkb::PharmGkbDatabase;
+use probes::ProbeMask;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Holds one loaded database, shared with running analyses
+#[derive(Debug)]
+pub struct DatabaseSlot<T> {
+    loaded: Mutex<Option<Arc<T>>>,
+}
+
+impl<T> Default for DatabaseSlot<T> {
+    fn default() -> Self {
+        Self {
+            loaded: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> DatabaseSlot<T> {
+    /// Replace the loaded database, returning a handle to it
+    pub fn replace(&self, database: T) -> Arc<T> {
+        let database = Arc::new(database);
+        *self.lock() = Some(Arc::clone(&database));
+        database
+    }
+
+    /// Handle to the loaded database, if any
+    pub fn current(&self) -> Option<Arc<T>> {
+        self.lock().clone()
+    }
+
+    /// Drop the database, returning it
+    pub fn take(&self) -> Option<Arc<T>> {
+        self.lock().take()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Arc<T>>> {
+        self.loaded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Every annotation database the application knows about
+#[derive(Debug, Default)]
+pub struct AnnotationDatabases {
+    pub clinvar: DatabaseSlot<ClinVarDatabase>,
+    pub pharmgkb: DatabaseSlot<PharmGkbDatabase>,
+    /// Star-allele definitions and guideline recommendations
+    pub cpic: DatabaseSlot<CpicDatabase>,
+    pub gwas: DatabaseSlot<GwasCatalog>,
+    pub dbsnp: DatabaseSlot<DbSnpIndex>,
+    pub gnomad: DatabaseSlot<GnomadDatabase>,
+    /// GRCh37 to GRCh38 chain for genomes on the older build
+    pub liftover: DatabaseSlot<Liftover>,
+    /// Y-chromosome and mitochondrial haplogroup trees
+    pub haplogroups: DatabaseSlot<HaplogroupDatabase>,
+    /// ClinGen dosage sensitivity of genes and regions
+    pub clingen: DatabaseSlot<ClinGenDatabase>,
+    /// Array probes known to call unreliably
+    pub probe_mask: DatabaseSlot<ProbeMask>,
+    /// Interpretations from a table the user supplies
+    pub custom: DatabaseSlot<CustomAnnotations>,
+}
+
+impl AnnotationDatabases {
+    /// Handles to the databases loaded right now, for one analysis run
+    pub fn snapshot(&self) -> DatabaseSnapshot {
+        DatabaseSnapshot {
+            clinvar: self.clinvar.current(),
+            pharmgkb: self.pharmgkb.current(),
+            cpic: self.cpic.current(),
+            gwas: self.gwas.current(),
+            dbsnp: self.dbsnp.current(),
+            gnomad: self.gnomad.current(),
+            liftover: self.liftover.current(),
+            haplogroups: self.haplogroups.current(),
+            clingen: self.clingen.current(),
+            probe_mask: self.probe_mask.current(),
+            custom: self.custom.current(),
+        }
+    }
+}
+
+/// The databases an analysis runs against
+///
+/// Taken once when the analysis starts so that a database reloaded midway
+/// does not mix two releases into one result.
+#[derive(Debug, Clone, Default)]
+pub struct DatabaseSnapshot {
+    pub clinvar: Option<Arc<ClinVarDatabase>>,
+    pub pharmgkb: Option<Arc<PharmGkbDatabase>>,
+    pub cpic: Option<Arc<CpicDatabase>>,
+    pub gwas: Option<Arc<GwasCatalog>>,
+    pub dbsnp: Option<Arc<DbSnpIndex>>,
+    pub gnomad: Option<Arc<GnomadDatabase>>,
+    pub liftover: Option<Arc<Liftover>>,
+    pub haplogroups: Option<Arc<HaplogroupDatabase>>,
+    pub clingen: Option<Arc<ClinGenDatabase>>,
+    pub probe_mask: Option<Arc<ProbeMask>>,
+    pub custom: Option<Arc<CustomAnnotations>>,
+}
+
+impl DatabaseSnapshot {
+    /// Every site an analysis against these databases can report on
+    ///
+    /// Covers the sites the reports look up themselves, such as APOE and
+    /// the SNPs the genome build is detected from.
+    pub fn sites(&self) -> SiteFilter {
+        let mut sites = SiteFilter::default();
+        if let Some(clinvar) = &self.clinvar {
+            clinvar.add_sites(&mut sites);
+        }
+        if let Some(pharmgkb) = &self.pharmgkb {
+            pharmgkb.add_sites(&mut sites);
+        }
+        if let Some(cpic) = &self.cpic {
+            cpic.add_sites(&mut sites);
+        }
+        if let Some(gwas) = &self.gwas {
+            gwas.add_sites(&mut sites);
+        }
+        if let Some(haplogroups) = &self.haplogroups {
+            haplogroups.add_sites(&mut sites);
+        }
+        if let Some(custom) = &self.custom {
+            custom.add_sites(&mut sites);
+        }
+        apoe::add_sites(&mut sites);
+        blood_type::add_sites(&mut sites);
+        hla::add_sites(&mut sites);
+        fitness::add_sites(&mut sites);
+        nutrigenomics::add_sites(&mut sites);
+        crate::liftover::add_marker_sites(&mut sites);
+        sites
+    }
+}
+
+/// Normalize "rs123" or a bare dbSNP number to the "rs123" form
+pub fn normalize_rsid(raw: &str) -> Option<String> {
+    let digits = raw.trim().trim_start_matches("rs");
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) || digits == "0" {
+        return None;
+    }
+    Some(format!("rs{}", digits))
+}
+
+/// Whether an allele is spelled out in nucleotides
+pub fn is_allele_sequence(allele: &str) -> bool {
+    !allele.is_empty()
+        && allele
+            .chars()
+            .all(|c| matches!(c.to_ascii_uppercase(), 'A' | 'C' | 'G' | 'T' | 'N'))
+}
+
+/// Copies of `alternate` carried by a genotype, or `None` for a no-call
+///
+/// Consumer arrays have no reference allele column and report indels as
+/// D (deletion) and I (insertion), so those calls are translated using the
+/// lengths of the database alleles.
+pub fn alternate_copies(variant: &Variant, reference: &str, alternate: &str) -> Option<usize> {
+    if variant.genotype.is_no_call() {
+        return None;
+    }
+    if variant.reference.is_none() && reference.len() != alternate.len() {
+        let indel = if alternate.len() > reference.len() {
+            "I"
+        } else {
+            "D"
+        };
+        if variant.genotype.allele_count("D") + variant.genotype.allele_count("I") > 0 {
+            return Some(variant.genotype.allele_count(indel));
+        }
+    }
+    Some(variant.genotype.allele_count(alternate))
+}
+
+/// VCF `fileDate` "20240107" to "2024-01-07"
+pub(crate) fn format_file_date(date: &str) -> String {
+    if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()) {
+        format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
+    } else {
+        date.to_string()
+    }
+}
+
+/// Chromosome name for a RefSeq accession or a plain chromosome column
+pub(crate) fn refseq_chromosome(raw: &str) -> Option<String> {
+    let Some(accession) = raw.strip_prefix("NC_") else {
+        return (!raw.starts_with("NT_") && !raw.starts_with("NW_"))
+            .then(|| normalize_chromosome(raw));
+    };
+    let number: u32 = accession.split('.').next()?.parse().ok()?;
+    match number {
+        1..=22 => Some(number.to_string()),
+        23 => Some("X".to_string()),
+        24 => Some("Y".to_string()),
+        12920 => Some("MT".to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,249 @@
+//! Nutrition and metabolism panel
+//!
+//! A curated set of common variants known to change how the body handles
+//! folate, lactose, alcohol, caffeine, body weight and vitamin D. Unlike
+//! clinical findings none of them causes disease: each is a shift in
+//! metabolism that diet can matter for, and several rest on association
+//! studies alone. Findings carry the strength of their evidence and are
+//! reported apart from clinical ones.
+//!
+//! Markers are matched by rsid, or by chromosome and position in the
+//! genome's build, and calls reported on the minus strand are complemented.
+
+use crate::confidence::{self, Confidence, Evidence};
+use crate::genome::{GenomeBuild, Variant};
+use crate::store::LoadedGenome;
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+
+/// What every nutrigenomic finding does and does not say
+pub const CAVEAT: &str = "Nutrigenomic findings rest on weaker evidence than clinical findings and are not a diagnosis. Diet, lifestyle and other genes usually matter more; talk to a doctor or dietitian before changing your diet because of them.";
+
+/// Part of diet or metabolism a marker concerns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NutrientArea {
+    Folate,
+    Lactose,
+    Alcohol,
+    Caffeine,
+    BodyWeight,
+    VitaminD,
+}
+
+/// How well the effect of a marker is established, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NutritionEvidence {
+    /// A functional effect replicated across populations
+    Established,
+    /// A measured effect whose consequences for health are debated
+    Moderate,
+    /// Small effects from association studies
+    Limited,
+}
+
+/// A variant of the panel
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NutritionMarker {
+    pub gene: &'static str,
+    pub rsid: &'static str,
+    /// Common name of the variant, e.g. "C677T"
+    pub name: &'static str,
+    pub area: NutrientArea,
+    /// Plus-strand base whose copies the effects count
+    pub effect_allele: &'static str,
+    pub other_allele: &'static str,
+    pub chromosome: &'static str,
+    pub positions: &'static [(GenomeBuild, u64)],
+    pub evidence: NutritionEvidence,
+    /// What zero, one and two copies of the effect allele mean
+    pub effects: [&'static str; 3],
+}
+
+/// Markers of the panel
+pub const MARKERS: [NutritionMarker; 7] = [
+    NutritionMarker {
+        gene: "MTHFR",
+        rsid: "rs1801133",
+        name: "C677T",
+        area: NutrientArea::Folate,
+        effect_allele: "A",
+        other_allele: "G",
+        chromosome: "1",
+        positions: &[(GenomeBuild::GRCh37, 11856378), (GenomeBuild::GRCh38, 11796321)],
+        evidence: NutritionEvidence::Moderate,
+        effects: [
+            "Typical MTHFR enzyme activity.",
+            "MTHFR enzyme activity about two thirds of typical, rarely of consequence with enough folate in the diet.",
+            "MTHFR enzyme activity about a third of typical, with raised homocysteine when folate intake is low.",
+        ],
+    },
+    NutritionMarker {
+        gene: "MTHFR",
+        rsid: "rs1801131",
+        name: "A1298C",
+        area: NutrientArea::Folate,
+        effect_allele: "G",
+        other_allele: "T",
+        chromosome: "1",
+        positions: &[(GenomeBuild::GRCh37, 11854476), (GenomeBuild::GRCh38, 11794419)],
+        evidence: NutritionEvidence::Limited,
+        effects: [
+            "Typical MTHFR enzyme activity.",
+            "Slightly reduced MTHFR enzyme activity.",
+            "Mildly reduced MTHFR enzyme activity, mostly of note together with C677T.",
+        ],
+    },
+    NutritionMarker {
+        gene: "LCT",
+        rsid: "rs4988235",
+        name: "-13910C>T",
+        area: NutrientArea::Lactose,
+        effect_allele: "G",
+        other_allele: "A",
+        chromosome: "2",
+        positions: &[(GenomeBuild::GRCh37, 136608646), (GenomeBuild::GRCh38, 135851076)],
+        evidence: NutritionEvidence::Established,
+        effects: [
+            "Lactase persistence: likely to digest lactose as an adult.",
+            "Lactase persistence: likely to digest lactose as an adult.",
+            "Lactase non-persistence in people of European ancestry: lactase activity likely falls after childhood, which can cause lactose intolerance. Other ancestries have other persistence variants.",
+        ],
+    },
+    NutritionMarker {
+        gene: "ALDH2",
+        rsid: "rs671",
+        name: "Glu504Lys (ALDH2*2)",
+        area: NutrientArea::Alcohol,
+        effect_allele: "A",
+        other_allele: "G",
+        chromosome: "12",
+        positions: &[(GenomeBuild::GRCh37, 112241766), (GenomeBuild::GRCh38, 111803962)],
+        evidence: NutritionEvidence::Established,
+        effects: [
+            "Typical ALDH2 activity.",
+            "Reduced ALDH2 activity: acetaldehyde builds up after drinking, causing flushing, and regular drinking raises the risk of esophageal cancer more than usual.",
+            "Almost no ALDH2 activity: alcohol usually causes strong flushing, nausea and a racing heart.",
+        ],
+    },
+    NutritionMarker {
+        gene: "CYP1A2",
+        rsid: "rs762551",
+        name: "-163C>A (CYP1A2*1F)",
+        area: NutrientArea::Caffeine,
+        effect_allele: "C",
+        other_allele: "A",
+        chromosome: "15",
+        positions: &[(GenomeBuild::GRCh37, 75041917), (GenomeBuild::GRCh38, 74749576)],
+        evidence: NutritionEvidence::Moderate,
+        effects: [
+            "Fast caffeine metabolism.",
+            "Slower caffeine metabolism.",
+            "Slow caffeine metabolism; in some studies heavy coffee drinking went with higher blood pressure in slow metabolizers.",
+        ],
+    },
+    NutritionMarker {
+        gene: "FTO",
+        rsid: "rs9939609",
+        name: "rs9939609",
+        area: NutrientArea::BodyWeight,
+        effect_allele: "A",
+        other_allele: "T",
+        chromosome: "16",
+        positions: &[(GenomeBuild::GRCh37, 53820527), (GenomeBuild::GRCh38, 53786615)],
+        evidence: NutritionEvidence::Limited,
+        effects: [
+            "No copies of the FTO risk allele.",
+            "About 1.5 kg more body weight on average; physical activity lessens the effect.",
+            "About 3 kg more body weight on average; physical activity lessens the effect.",
+        ],
+    },
+    NutritionMarker {
+        gene: "GC",
+        rsid: "rs2282679",
+        name: "rs2282679",
+        area: NutrientArea::VitaminD,
+        effect_allele: "G",
+        other_allele: "T",
+        chromosome: "4",
+        positions: &[],
+        evidence: NutritionEvidence::Moderate,
+        effects: [
+            "Typical vitamin D binding protein levels.",
+            "Somewhat lower blood vitamin D levels on average.",
+            "Lower blood vitamin D levels on average; worth measuring when deficiency is suspected.",
+        ],
+    },
+];
+
+/// A genotyped marker of the panel and what it means
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NutritionFinding {
+    pub gene: String,
+    pub rsid: String,
+    pub name: String,
+    pub area: NutrientArea,
+    pub genotype: String,
+    pub effect_allele: String,
+    pub effect_copies: usize,
+    pub effect: String,
+    pub evidence: NutritionEvidence,
+    pub imputed: bool,
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+/// The findings of every marker the genome has a call for, in panel order
+pub fn call(genome: &LoadedGenome) -> Vec<NutritionFinding> {
+    MARKERS
+        .iter()
+        .filter_map(|marker| {
+            let variant = find(genome, marker)?;
+            let copies = variant
+                .genotype
+                .biallelic_count(marker.effect_allele, marker.other_allele)?;
+            Some(NutritionFinding {
+                gene: marker.gene.to_string(),
+                rsid: marker.rsid.to_string(),
+                name: marker.name.to_string(),
+                area: marker.area,
+                genotype: variant.genotype.to_string(),
+                effect_allele: marker.effect_allele.to_string(),
+                effect_copies: copies,
+                effect: marker.effects[copies.min(2)].to_string(),
+                evidence: marker.evidence,
+                imputed: variant.is_imputed(),
+                confidence: Confidence::new(
+                    Evidence::Nutrition(marker.evidence),
+                    confidence::variant_reliability(variant),
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Add the markers to `sites`
+pub fn add_sites(sites: &mut SiteFilter) {
+    for marker in &MARKERS {
+        sites.add_rsid(marker.rsid);
+        for (_, position) in marker.positions {
+            sites.add_position(marker.chromosome, *position);
+        }
+    }
+}
+
+// Helper functions
+
+fn find<'a>(genome: &'a LoadedGenome, marker: &NutritionMarker) -> Option<&'a Variant> {
+    genome
+        .get_by_rsid(marker.rsid)
+        .or_else(|| {
+            marker
+                .positions
+                .iter()
+                .find(|(build, _)| genome.file.genome_build == Some(*build))
+                .and_then(|(_, position)| genome.get_at(marker.chromosome, *position))
+        })
+        .filter(|variant| !variant.genotype.is_no_call())
+}
@@ -0,0 +1,536 @@
+//! Condition ontology
+//!
+//! ClinVar names a condition however its submitters wrote it, so the same
+//! disease turns up as "Hypertrophic cardiomyopathy 1", "Familial
+//! hypertrophic cardiomyopathy" or only as an OMIM number. This built-in
+//! table maps the conditions behind most reportable findings to their
+//! MONDO term, with the OMIM entries MONDO cross-references and the names
+//! people search for them by. Each term belongs to a body system, given by
+//! its top-level HPO organ abnormality, so findings can be grouped the way
+//! a clinic would refer them and "heart disease" finds the
+//! cardiomyopathies.
+
+use serde::{Deserialize, Serialize};
+
+/// Organ system a condition mainly affects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BodySystem {
+    Cardiovascular,
+    Neoplasm,
+    Nervous,
+    Metabolism,
+    Blood,
+    Immune,
+    Respiratory,
+    Digestive,
+    Genitourinary,
+    Musculoskeletal,
+    Endocrine,
+    Eye,
+    Ear,
+    Skin,
+}
+
+impl BodySystem {
+    pub const ALL: [BodySystem; 14] = [
+        BodySystem::Cardiovascular,
+        BodySystem::Neoplasm,
+        BodySystem::Nervous,
+        BodySystem::Metabolism,
+        BodySystem::Blood,
+        BodySystem::Immune,
+        BodySystem::Respiratory,
+        BodySystem::Digestive,
+        BodySystem::Genitourinary,
+        BodySystem::Musculoskeletal,
+        BodySystem::Endocrine,
+        BodySystem::Eye,
+        BodySystem::Ear,
+        BodySystem::Skin,
+    ];
+
+    /// HPO term of the organ abnormality
+    pub fn hpo_id(&self) -> &'static str {
+        match self {
+            BodySystem::Cardiovascular => "HP:0001626",
+            BodySystem::Neoplasm => "HP:0002664",
+            BodySystem::Nervous => "HP:0000707",
+            BodySystem::Metabolism => "HP:0001939",
+            BodySystem::Blood => "HP:0001871",
+            BodySystem::Immune => "HP:0002715",
+            BodySystem::Respiratory => "HP:0002086",
+            BodySystem::Digestive => "HP:0025031",
+            BodySystem::Genitourinary => "HP:0000119",
+            BodySystem::Musculoskeletal => "HP:0033127",
+            BodySystem::Endocrine => "HP:0000818",
+            BodySystem::Eye => "HP:0000478",
+            BodySystem::Ear => "HP:0000598",
+            BodySystem::Skin => "HP:0001574",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BodySystem::Cardiovascular => "Heart and blood vessels",
+            BodySystem::Neoplasm => "Cancer",
+            BodySystem::Nervous => "Brain and nerves",
+            BodySystem::Metabolism => "Metabolism",
+            BodySystem::Blood => "Blood",
+            BodySystem::Immune => "Immune system",
+            BodySystem::Respiratory => "Lungs and airways",
+            BodySystem::Digestive => "Digestive system",
+            BodySystem::Genitourinary => "Kidneys and urinary tract",
+            BodySystem::Musculoskeletal => "Muscles and bones",
+            BodySystem::Endocrine => "Hormones",
+            BodySystem::Eye => "Eyes",
+            BodySystem::Ear => "Hearing",
+            BodySystem::Skin => "Skin",
+        }
+    }
+
+    /// Everyday names for conditions of the system, searched along with
+    /// the names of its conditions
+    pub fn synonyms(&self) -> &'static [&'static str] {
+        match self {
+            BodySystem::Cardiovascular => &["heart disease", "cardiac", "cardiovascular"],
+            BodySystem::Neoplasm => &["cancer", "tumor", "hereditary cancer"],
+            BodySystem::Nervous => &["neurological", "brain disease", "dementia"],
+            BodySystem::Metabolism => &["metabolic disease", "inborn error of metabolism"],
+            BodySystem::Blood => &["blood disorder", "anemia", "clotting"],
+            BodySystem::Immune => &["immune deficiency", "autoimmune"],
+            BodySystem::Respiratory => &["lung disease", "breathing"],
+            BodySystem::Digestive => &["liver disease", "gut", "digestive"],
+            BodySystem::Genitourinary => &["kidney disease", "renal"],
+            BodySystem::Musculoskeletal => &["muscle disease", "bone disease", "connective tissue"],
+            BodySystem::Endocrine => &["hormone", "diabetes", "thyroid"],
+            BodySystem::Eye => &["vision loss", "blindness"],
+            BodySystem::Ear => &["deafness", "hearing loss"],
+            BodySystem::Skin => &["skin disease", "dermatological"],
+        }
+    }
+}
+
+/// A condition of the table
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ConditionTerm {
+    /// MONDO identifier, e.g. "MONDO:0005045"
+    pub id: &'static str,
+    pub name: &'static str,
+    pub system: BodySystem,
+    /// OMIM entries of the condition, e.g. "OMIM:192600"
+    pub omim: &'static [&'static str],
+    /// Other names ClinVar submitters and users give it
+    pub synonyms: &'static [&'static str],
+}
+
+impl ConditionTerm {
+    /// Whether a ClinVar condition name names this term
+    fn is_named(&self, normalized: &str) -> bool {
+        std::iter::once(self.name)
+            .chain(self.synonyms.iter().copied())
+            .any(|name| normalize(name) == normalized)
+    }
+}
+
+const fn term(
+    id: &'static str,
+    name: &'static str,
+    system: BodySystem,
+    omim: &'static [&'static str],
+    synonyms: &'static [&'static str],
+) -> ConditionTerm {
+    ConditionTerm {
+        id,
+        name,
+        system,
+        omim,
+        synonyms,
+    }
+}
+
+use BodySystem::{
+    Blood, Cardiovascular, Digestive, Ear, Endocrine, Eye, Genitourinary, Metabolism,
+    Musculoskeletal, Neoplasm, Nervous, Respiratory,
+};
+
+/// Conditions mapped to MONDO
+pub const TERMS: [ConditionTerm; 44] = [
+    term(
+        "MONDO:0005045",
+        "Hypertrophic cardiomyopathy",
+        Cardiovascular,
+        &["OMIM:192600"],
+        &["Familial hypertrophic cardiomyopathy", "HCM"],
+    ),
+    term(
+        "MONDO:0005021",
+        "Dilated cardiomyopathy",
+        Cardiovascular,
+        &["OMIM:115200"],
+        &["Familial dilated cardiomyopathy", "DCM"],
+    ),
+    term(
+        "MONDO:0016587",
+        "Arrhythmogenic right ventricular cardiomyopathy",
+        Cardiovascular,
+        &["OMIM:107970"],
+        &["Arrhythmogenic cardiomyopathy", "ARVC"],
+    ),
+    term(
+        "MONDO:0002442",
+        "Long QT syndrome",
+        Cardiovascular,
+        &["OMIM:192500"],
+        &["Romano-Ward syndrome", "LQTS"],
+    ),
+    term(
+        "MONDO:0015263",
+        "Brugada syndrome",
+        Cardiovascular,
+        &["OMIM:601144"],
+        &[],
+    ),
+    term(
+        "MONDO:0005439",
+        "Familial hypercholesterolemia",
+        Cardiovascular,
+        &["OMIM:143890"],
+        &["Hypercholesterolemia", "High cholesterol"],
+    ),
+    term(
+        "MONDO:0007947",
+        "Marfan syndrome",
+        Cardiovascular,
+        &["OMIM:154700"],
+        &[],
+    ),
+    term(
+        "MONDO:0019180",
+        "Hereditary hemorrhagic telangiectasia",
+        Cardiovascular,
+        &["OMIM:187300"],
+        &["Osler-Weber-Rendu disease"],
+    ),
+    term(
+        "MONDO:0010526",
+        "Fabry disease",
+        Cardiovascular,
+        &["OMIM:301500"],
+        &["Alpha-galactosidase A deficiency"],
+    ),
+    term(
+        "MONDO:0003582",
+        "Hereditary breast and ovarian cancer syndrome",
+        Neoplasm,
+        &["OMIM:604370", "OMIM:612555"],
+        &[
+            "Hereditary breast ovarian cancer syndrome",
+            "Hereditary breast and ovarian cancer",
+            "Familial cancer of breast",
+            "HBOC",
+        ],
+    ),
+    term(
+        "MONDO:0005835",
+        "Lynch syndrome",
+        Neoplasm,
+        &["OMIM:120435"],
+        &["Hereditary nonpolyposis colorectal cancer", "HNPCC"],
+    ),
+    term(
+        "MONDO:0018875",
+        "Li-Fraumeni syndrome",
+        Neoplasm,
+        &["OMIM:151623"],
+        &[],
+    ),
+    term(
+        "MONDO:0021056",
+        "Familial adenomatous polyposis",
+        Neoplasm,
+        &["OMIM:175100"],
+        &["Familial adenomatous polyposis 1", "FAP"],
+    ),
+    term(
+        "MONDO:0008380",
+        "Retinoblastoma",
+        Neoplasm,
+        &["OMIM:180200"],
+        &[],
+    ),
+    term(
+        "MONDO:0008667",
+        "Von Hippel-Lindau disease",
+        Neoplasm,
+        &["OMIM:193300"],
+        &["Von Hippel-Lindau syndrome", "VHL syndrome"],
+    ),
+    term(
+        "MONDO:0007540",
+        "Multiple endocrine neoplasia type 1",
+        Neoplasm,
+        &["OMIM:131100"],
+        &["MEN1"],
+    ),
+    term(
+        "MONDO:0006100",
+        "Peutz-Jeghers syndrome",
+        Neoplasm,
+        &["OMIM:175200"],
+        &[],
+    ),
+    term(
+        "MONDO:0004975",
+        "Alzheimer disease",
+        Nervous,
+        &["OMIM:104300"],
+        &["Alzheimer's disease", "Dementia of the Alzheimer type"],
+    ),
+    term(
+        "MONDO:0005180",
+        "Parkinson disease",
+        Nervous,
+        &["OMIM:168600"],
+        &["Parkinson's disease"],
+    ),
+    term(
+        "MONDO:0007739",
+        "Huntington disease",
+        Nervous,
+        &["OMIM:143100"],
+        &["Huntington's chorea"],
+    ),
+    term(
+        "MONDO:0010100",
+        "Tay-Sachs disease",
+        Nervous,
+        &["OMIM:272800"],
+        &["GM2 gangliosidosis type 1"],
+    ),
+    term(
+        "MONDO:0001516",
+        "Spinal muscular atrophy",
+        Nervous,
+        &["OMIM:253300"],
+        &["SMA"],
+    ),
+    term(
+        "MONDO:0009861",
+        "Phenylketonuria",
+        Metabolism,
+        &["OMIM:261600"],
+        &["PKU", "Phenylalanine hydroxylase deficiency"],
+    ),
+    term(
+        "MONDO:0018150",
+        "Gaucher disease",
+        Metabolism,
+        &["OMIM:230800"],
+        &[],
+    ),
+    term(
+        "MONDO:0009290",
+        "Pompe disease",
+        Metabolism,
+        &["OMIM:232300"],
+        &[
+            "Glycogen storage disease type II",
+            "Acid maltase deficiency",
+        ],
+    ),
+    term(
+        "MONDO:0010200",
+        "Wilson disease",
+        Metabolism,
+        &["OMIM:277900"],
+        &["Hepatolenticular degeneration"],
+    ),
+    term(
+        "MONDO:0006507",
+        "Hereditary hemochromatosis",
+        Metabolism,
+        &["OMIM:235200"],
+        &["Hemochromatosis", "Iron overload"],
+    ),
+    term(
+        "MONDO:0009665",
+        "Biotinidase deficiency",
+        Metabolism,
+        &["OMIM:253260"],
+        &[],
+    ),
+    term(
+        "MONDO:0010703",
+        "Ornithine transcarbamylase deficiency",
+        Metabolism,
+        &["OMIM:311250"],
+        &["OTC deficiency"],
+    ),
+    term(
+        "MONDO:0011382",
+        "Sickle cell anemia",
+        Blood,
+        &["OMIM:603903"],
+        &["Sickle cell disease", "Hb SS disease"],
+    ),
+    term(
+        "MONDO:0019402",
+        "Beta-thalassemia",
+        Blood,
+        &["OMIM:613985"],
+        &["Beta thalassemia"],
+    ),
+    term(
+        "MONDO:0008560",
+        "Factor V Leiden thrombophilia",
+        Blood,
+        &["OMIM:188055"],
+        &["Thrombophilia due to activated protein C resistance"],
+    ),
+    term(
+        "MONDO:0010480",
+        "G6PD deficiency",
+        Blood,
+        &["OMIM:300908"],
+        &["Glucose-6-phosphate dehydrogenase deficiency"],
+    ),
+    term(
+        "MONDO:0009061",
+        "Cystic fibrosis",
+        Respiratory,
+        &["OMIM:219700"],
+        &["Mucoviscidosis", "CF"],
+    ),
+    term(
+        "MONDO:0013282",
+        "Alpha-1-antitrypsin deficiency",
+        Respiratory,
+        &["OMIM:613490"],
+        &["Alpha 1-antitrypsin deficiency", "AATD"],
+    ),
+    term(
+        "MONDO:0005130",
+        "Celiac disease",
+        Digestive,
+        &["OMIM:212750"],
+        &["Coeliac disease"],
+    ),
+    term(
+        "MONDO:0004691",
+        "Autosomal dominant polycystic kidney disease",
+        Genitourinary,
+        &["OMIM:173900"],
+        &["Polycystic kidney disease", "ADPKD"],
+    ),
+    term(
+        "MONDO:0010679",
+        "Duchenne muscular dystrophy",
+        Musculoskeletal,
+        &["OMIM:310200"],
+        &["DMD"],
+    ),
+    term(
+        "MONDO:0017314",
+        "Vascular Ehlers-Danlos syndrome",
+        Musculoskeletal,
+        &["OMIM:130050"],
+        &["Ehlers-Danlos syndrome, type 4"],
+    ),
+    term(
+        "MONDO:0018493",
+        "Malignant hyperthermia susceptibility",
+        Musculoskeletal,
+        &["OMIM:145600"],
+        &["Malignant hyperthermia"],
+    ),
+    term(
+        "MONDO:0005148",
+        "Type 2 diabetes mellitus",
+        Endocrine,
+        &["OMIM:125853"],
+        &["Type 2 diabetes", "Diabetes mellitus type 2"],
+    ),
+    term(
+        "MONDO:0005147",
+        "Type 1 diabetes mellitus",
+        Endocrine,
+        &["OMIM:222100"],
+        &["Type 1 diabetes", "Diabetes mellitus type 1"],
+    ),
+    term(
+        "MONDO:0019200",
+        "Retinitis pigmentosa",
+        Eye,
+        &["OMIM:268000"],
+        &[],
+    ),
+    term(
+        "MONDO:0019497",
+        "Nonsyndromic genetic hearing loss",
+        Ear,
+        &["OMIM:220290"],
+        &[
+            "Nonsyndromic hearing loss and deafness",
+            "Deafness, autosomal recessive 1A",
+        ],
+    ),
+];
+
+/// Term with a MONDO or OMIM identifier, e.g. "MONDO:0005045" or
+/// "OMIM:192600"
+pub fn term_by_id(id: &str) -> Option<&'static ConditionTerm> {
+    TERMS.iter().find(|term| {
+        term.id.eq_ignore_ascii_case(id)
+            || term.omim.iter().any(|omim| omim.eq_ignore_ascii_case(id))
+    })
+}
+
+/// Term a ClinVar condition maps to
+///
+/// The name is looked up first, ignoring case, punctuation and a trailing
+/// subtype such as "1" or "type 2A", so "Hypertrophic cardiomyopathy 1"
+/// maps to hypertrophic cardiomyopathy. `ids` are the record's ontology
+/// identifiers and are tried when the name is not in the table.
+pub fn map_condition(name: &str, ids: &[String]) -> Option<&'static ConditionTerm> {
+    let normalized = normalize(name);
+    let subtype_removed = without_subtype(&normalized);
+    TERMS
+        .iter()
+        .find(|term| term.is_named(&normalized))
+        .or_else(|| TERMS.iter().find(|term| term.is_named(subtype_removed)))
+        .or_else(|| ids.iter().find_map(|id| term_by_id(id)))
+}
+
+/// Searchable names of a term: its own, its synonyms and its body
+/// system's
+pub fn search_names(term: &ConditionTerm) -> impl Iterator<Item = &'static str> {
+    std::iter::once(term.name)
+        .chain(term.synonyms.iter().copied())
+        .chain(std::iter::once(term.system.label()))
+        .chain(term.system.synonyms().iter().copied())
+}
+
+// Helper functions
+
+/// Lower-case words separated by single spaces
+fn normalize(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A normalized name without the subtype numbering at its end
+fn without_subtype(normalized: &str) -> &str {
+    let mut name = normalized;
+    while let Some((rest, last)) = name.rsplit_once(' ') {
+        if last.chars().any(|c| c.is_ascii_digit()) || last == "type" {
+            name = rest;
+        } else {
+            break;
+        }
+    }
+    name
+}
@@ -0,0 +1,559 @@
+//! Offline database packs
+//!
+//! Air-gapped machines cannot reach the release manifest, so releases
+//! travel on removable media as a data pack: one tar archive, optionally
+//! gzip or Zstandard compressed, that opens with a signed `pack.json` and its
+//! `pack.json.sig` and then holds the files the manifest lists. The
+//! signature is checked with the same release key as online manifests
+//! before anything else is read, and [`extract`] stages every file and
+//! checks its digest before the caller installs any of them, so a pack
+//! damaged on its way to the machine changes nothing.
+
+use super::manager::{self, verify_signature, Release};
+use crate::parser::zstd::{self, ZstdDecoder};
+use crate::reference::ReferenceRelease;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest, the first member of a pack
+pub const MANIFEST_NAME: &str = "pack.json";
+
+/// Name of the manifest's signature, the second member of a pack
+pub const SIGNATURE_NAME: &str = "pack.json.sig";
+
+/// Newest pack format this build reads
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Size of a tar block
+const BLOCK_SIZE: usize = 512;
+
+/// Longest manifest or signature read, in bytes
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Bytes copied between two cancellation checks
+const COPY_CHUNK: usize = 1024 * 1024;
+
+/// gzip member magic bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Signed list of what a pack holds
+///
+/// The `url` of every release and reference is the path of its file inside
+/// the pack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackManifest {
+    /// Pack format, at most [`FORMAT_VERSION`]
+    pub format: u32,
+    /// Version of the pack as a whole, e.g. "2024.06"
+    pub version: String,
+    #[serde(default)]
+    pub created: Option<String>,
+    #[serde(default)]
+    pub releases: Vec<Release>,
+    #[serde(default)]
+    pub references: Vec<ReferenceRelease>,
+}
+
+impl PackManifest {
+    /// Parse a manifest after checking its Ed25519 signature, as
+    /// [`manager::ReleaseManifest::parse_signed`] does
+    pub fn parse_signed(
+        manifest: &[u8],
+        signature: &str,
+        public_key: &str,
+    ) -> Result<Self, String> {
+        verify_signature(manifest, signature.trim(), public_key)?;
+        let manifest: PackManifest = serde_json::from_slice(manifest)
+            .map_err(|e| format!("Invalid pack manifest: {}", e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.format == 0 || self.format > FORMAT_VERSION {
+            return Err(format!(
+                "Pack format {} is not supported; update GenomeForge to import it",
+                self.format
+            ));
+        }
+        let mut kinds = HashSet::new();
+        let mut paths = HashSet::new();
+        for release in &self.releases {
+            if !release.database.is_published() {
+                return Err(format!(
+                    "Packs cannot carry {} databases",
+                    release.database.as_str()
+                ));
+            }
+            if !kinds.insert(release.database) {
+                return Err(format!(
+                    "Pack lists {} more than once",
+                    release.database.as_str()
+                ));
+            }
+            if !paths.insert(release.url.as_str()) {
+                return Err(format!("Pack lists {} more than once", release.url));
+            }
+        }
+        let mut builds = HashSet::new();
+        for reference in &self.references {
+            if !builds.insert(reference.build) {
+                return Err(format!(
+                    "Pack lists the {:?} reference more than once",
+                    reference.build
+                ));
+            }
+            if !paths.insert(reference.url.as_str()) {
+                return Err(format!("Pack lists {} more than once", reference.url));
+            }
+        }
+        if paths.is_empty() {
+            return Err("Pack holds no databases".to_string());
+        }
+        Ok(())
+    }
+
+    /// Number of files the pack holds
+    pub fn len(&self) -> usize {
+        self.releases.len() + self.references.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The files of a pack, staged and checked against the manifest
+#[derive(Debug)]
+pub struct ExtractedPack {
+    pub manifest: PackManifest,
+    /// Each release with its staged file, for
+    /// [`manager::install_release`]
+    pub releases: Vec<(Release, PathBuf)>,
+    /// Each reference FASTA with its staged file, for
+    /// [`crate::reference::ReferenceManager::import`]
+    pub references: Vec<(ReferenceRelease, PathBuf)>,
+}
+
+impl ExtractedPack {
+    /// Remove the staged files not yet installed
+    pub fn discard(&self) {
+        let staged = self.releases.iter().map(|(_, path)| path);
+        for path in staged.chain(self.references.iter().map(|(_, path)| path)) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Stage the files of the pack at `path` in the staging directory of the
+/// database directory `dir`
+///
+/// The manifest must be signed by `public_key`. `checkpoint` is called with
+/// the bytes of the pack read so far and stops the extraction when it
+/// returns an error. Nothing is left staged when extraction fails.
+pub fn extract<F>(
+    path: &Path,
+    dir: &Path,
+    public_key: &str,
+    mut checkpoint: F,
+) -> Result<ExtractedPack, String>
+where
+    F: FnMut(u64) -> Result<(), String>,
+{
+    let mut archive = TarReader::new(open(path)?);
+    let manifest = read_small(&mut archive, MANIFEST_NAME)?;
+    let signature = read_small(&mut archive, SIGNATURE_NAME)?;
+    let signature =
+        String::from_utf8(signature).map_err(|_| "Invalid pack signature".to_string())?;
+    let manifest = PackManifest::parse_signed(&manifest, &signature, public_key)?;
+
+    let mut extracted = ExtractedPack {
+        manifest,
+        releases: Vec::new(),
+        references: Vec::new(),
+    };
+    let result = extract_files(&mut archive, dir, &mut extracted, &mut checkpoint);
+    if let Err(e) = result {
+        extracted.discard();
+        return Err(e);
+    }
+    Ok(extracted)
+}
+
+/// Write a pack to `out`, for release tooling
+///
+/// `manifest` is written as is, so its signature stays valid, followed by
+/// `signature` and the `files`, each under the path the manifest lists it
+/// at.
+pub fn write(
+    out: &Path,
+    manifest: &[u8],
+    signature: &str,
+    files: &[(&str, &Path)],
+) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Failed to write {}: {}", out.display(), e);
+    let mut writer = BufWriter::new(File::create(out).map_err(failed)?);
+    write_entry(&mut writer, MANIFEST_NAME, manifest.len() as u64, manifest)?;
+    write_entry(
+        &mut writer,
+        SIGNATURE_NAME,
+        signature.len() as u64,
+        signature.as_bytes(),
+    )?;
+    for (name, source) in files {
+        let file = File::open(source)
+            .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+        let size = file
+            .metadata()
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?
+            .len();
+        write_entry(&mut writer, name, size, BufReader::new(file))?;
+    }
+    // An archive ends with two empty blocks
+    writer.write_all(&[0; 2 * BLOCK_SIZE]).map_err(failed)?;
+    writer.flush().map_err(failed)
+}
+
+/// A member of a tar archive
+struct TarEntry {
+    name: String,
+    size: u64,
+}
+
+/// Reads the regular files of a ustar, GNU or pax tar archive in order
+struct TarReader<R> {
+    reader: R,
+    /// Content and padding of the current entry not yet read
+    unread: u64,
+    read: u64,
+}
+
+impl<R: Read> TarReader<R> {
+    fn new(reader: R) -> Self {
+        TarReader {
+            reader,
+            unread: 0,
+            read: 0,
+        }
+    }
+
+    /// The next regular file, skipping what is left of the previous one
+    fn next_entry(&mut self) -> Result<Option<TarEntry>, String> {
+        self.skip()?;
+        let mut long_name = None;
+        loop {
+            let mut header = [0u8; BLOCK_SIZE];
+            if !self.read_block(&mut header)? || header.iter().all(|&b| b == 0) {
+                return Ok(None);
+            }
+            check_header(&header)?;
+            let size = parse_number(&header[124..136])?;
+            let name = long_name.take().unwrap_or_else(|| header_name(&header));
+            self.unread = size.div_ceil(BLOCK_SIZE as u64) * BLOCK_SIZE as u64;
+            match header[156] {
+                b'0' | 0 | b'7' => return Ok(Some(TarEntry { name, size })),
+                b'5' => self.skip()?,
+                // GNU long name of the next entry
+                b'L' => long_name = Some(self.read_text(size)?),
+                // pax extended header, of which only the path matters here
+                b'x' => long_name = pax_path(&self.read_text(size)?),
+                b'g' => self.skip()?,
+                other => {
+                    return Err(format!(
+                        "Unsupported entry {} of type {:?} in pack",
+                        name, other as char
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Reader over the content of the current entry of `size` bytes
+    fn content(&mut self, size: u64) -> impl Read + '_ {
+        self.unread -= size;
+        self.read += size;
+        (&mut self.reader).take(size)
+    }
+
+    fn read_text(&mut self, size: u64) -> Result<String, String> {
+        if size > MAX_MANIFEST_SIZE {
+            return Err("Pack entry header is too long".to_string());
+        }
+        let mut text = Vec::new();
+        self.content(size)
+            .read_to_end(&mut text)
+            .map_err(|e| format!("Failed to read pack: {}", e))?;
+        self.skip()?;
+        Ok(String::from_utf8_lossy(&text)
+            .trim_end_matches('\0')
+            .to_string())
+    }
+
+    fn skip(&mut self) -> Result<(), String> {
+        let unread = std::mem::take(&mut self.unread);
+        let skipped = std::io::copy(&mut (&mut self.reader).take(unread), &mut std::io::sink())
+            .map_err(|e| format!("Failed to read pack: {}", e))?;
+        self.read += skipped;
+        if skipped < unread {
+            return Err("Pack is truncated".to_string());
+        }
+        Ok(())
+    }
+
+    /// Read a block; false at the end of the stream
+    fn read_block(&mut self, block: &mut [u8; BLOCK_SIZE]) -> Result<bool, String> {
+        let mut filled = 0;
+        while filled < BLOCK_SIZE {
+            match self.reader.read(&mut block[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err("Pack is truncated".to_string()),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(format!("Failed to read pack: {}", e)),
+            }
+        }
+        self.read += BLOCK_SIZE as u64;
+        Ok(true)
+    }
+}
+
+// Helper functions
+
+fn open(path: &Path) -> Result<Box<dyn Read>, String> {
+    let failed = |e: std::io::Error| format!("Failed to open {}: {}", path.display(), e);
+    let mut file = BufReader::new(File::open(path).map_err(failed)?);
+    let mut magic = [0u8; 4];
+    let read = file.read(&mut magic).map_err(failed)?;
+    let file = BufReader::new(File::open(path).map_err(failed)?);
+    if magic[..read].starts_with(&GZIP_MAGIC) {
+        Ok(Box::new(flate2::read::MultiGzDecoder::new(file)))
+    } else if magic[..read] == zstd::MAGIC {
+        Ok(Box::new(ZstdDecoder::new(file)))
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+/// Read the manifest or its signature, which must come next
+fn read_small<R: Read>(archive: &mut TarReader<R>, expected: &str) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .next_entry()
+        .map_err(|e| format!("Not a data pack: {}", e))?
+        .ok_or_else(|| format!("Not a data pack: {} is missing", expected))?;
+    if entry.name != expected {
+        return Err(format!(
+            "Not a data pack: expected {}, found {}",
+            expected, entry.name
+        ));
+    }
+    if entry.size > MAX_MANIFEST_SIZE {
+        return Err(format!("{} is too large", expected));
+    }
+    let mut content = Vec::new();
+    archive
+        .content(entry.size)
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Failed to read {}: {}", expected, e))?;
+    Ok(content)
+}
+
+fn extract_files<R: Read>(
+    archive: &mut TarReader<R>,
+    dir: &Path,
+    extracted: &mut ExtractedPack,
+    checkpoint: &mut impl FnMut(u64) -> Result<(), String>,
+) -> Result<(), String> {
+    let manifest = extracted.manifest.clone();
+    let mut seen = HashSet::new();
+    while let Some(entry) = archive.next_entry()? {
+        checkpoint(archive.read)?;
+        if !seen.insert(entry.name.clone()) {
+            return Err(format!("Pack holds {} more than once", entry.name));
+        }
+        let release = manifest
+            .releases
+            .iter()
+            .find(|release| release.url == entry.name);
+        let reference = manifest
+            .references
+            .iter()
+            .find(|reference| reference.url == entry.name);
+        let (sha256, size, staged) = match (release, reference) {
+            (Some(release), _) => {
+                let staged = manager::staging_path(dir, release.database)?;
+                extracted.releases.push((release.clone(), staged.clone()));
+                (&release.sha256, release.size, staged)
+            }
+            (None, Some(reference)) => {
+                let staged = manager::staging_dir(dir)?.join(format!(
+                    "reference-{:?}-{}.part",
+                    reference.build,
+                    std::process::id()
+                ));
+                extracted
+                    .references
+                    .push((reference.clone(), staged.clone()));
+                (&reference.sha256, reference.size, staged)
+            }
+            (None, None) => return Err(format!("Pack holds unlisted file {}", entry.name)),
+        };
+        if size.is_some_and(|size| size != entry.size) {
+            return Err(format!("{} is not the size listed", entry.name));
+        }
+        let digest = copy_to(archive, entry.size, &staged, checkpoint)?;
+        if !digest.eq_ignore_ascii_case(sha256.trim()) {
+            return Err(format!("Checksum mismatch for {}", entry.name));
+        }
+    }
+
+    let missing = manifest
+        .releases
+        .iter()
+        .map(|release| &release.url)
+        .chain(manifest.references.iter().map(|reference| &reference.url))
+        .find(|url| !seen.contains(*url));
+    match missing {
+        Some(url) => Err(format!("Pack is missing {}", url)),
+        None => Ok(()),
+    }
+}
+
+/// Copy an entry's content to `target`, returning its SHA-256 digest
+fn copy_to<R: Read>(
+    archive: &mut TarReader<R>,
+    size: u64,
+    target: &Path,
+    checkpoint: &mut impl FnMut(u64) -> Result<(), String>,
+) -> Result<String, String> {
+    let failed = |e: std::io::Error| format!("Failed to write {}: {}", target.display(), e);
+    let mut file = BufWriter::new(File::create(target).map_err(failed)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; COPY_CHUNK];
+    let start = archive.read;
+    let mut copied = 0;
+    let mut content = archive.content(size);
+    loop {
+        let read = content
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read pack: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read]).map_err(failed)?;
+        copied += read as u64;
+        checkpoint(start + copied)?;
+    }
+    if copied < size {
+        return Err("Pack is truncated".to_string());
+    }
+    file.flush().map_err(failed)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn check_header(header: &[u8; BLOCK_SIZE]) -> Result<(), String> {
+    let expected = parse_number(&header[148..156])?;
+    // The checksum is summed with its own field taken as spaces
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(index, &byte)| {
+            if (148..156).contains(&index) {
+                b' ' as u64
+            } else {
+                byte as u64
+            }
+        })
+        .sum();
+    if sum != expected {
+        return Err("Pack is corrupt: bad tar header checksum".to_string());
+    }
+    Ok(())
+}
+
+/// An octal field, or a GNU base-256 one for values too large for octal
+fn parse_number(field: &[u8]) -> Result<u64, String> {
+    if field.first().is_some_and(|byte| byte & 0x80 != 0) {
+        return Ok(field[1..]
+            .iter()
+            .fold(0u64, |value, &byte| value << 8 | byte as u64));
+    }
+    let text = String::from_utf8_lossy(field);
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| "Pack is corrupt: bad tar header".to_string())
+}
+
+fn header_name(header: &[u8; BLOCK_SIZE]) -> String {
+    let field = |range: std::ops::Range<usize>| {
+        let bytes = &header[range];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let name = field(0..100);
+    let prefix = if &header[257..262] == b"ustar" {
+        field(345..500)
+    } else {
+        String::new()
+    };
+    let name = if prefix.is_empty() {
+        name
+    } else {
+        format!("{}/{}", prefix, name)
+    };
+    name.trim_start_matches("./").to_string()
+}
+
+/// The `path` record of a pax extended header
+fn pax_path(records: &str) -> Option<String> {
+    records.lines().find_map(|record| {
+        let (_, record) = record.split_once(' ')?;
+        record
+            .strip_prefix("path=")
+            .map(|path| path.trim_start_matches("./").to_string())
+    })
+}
+
+fn write_entry<W: Write>(
+    writer: &mut W,
+    name: &str,
+    size: u64,
+    mut content: impl Read,
+) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Failed to write pack: {}", e);
+    if name.is_empty() || name.len() > 100 {
+        return Err(format!("Pack paths hold 1 to 100 bytes: {}", name));
+    }
+    let mut header = [0u8; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..107].copy_from_slice(b"0000644");
+    header[108..115].copy_from_slice(b"0000000");
+    header[116..123].copy_from_slice(b"0000000");
+    if size < 8u64.pow(11) {
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+    } else {
+        header[124] = 0x80;
+        header[128..136].copy_from_slice(&size.to_be_bytes());
+    }
+    header[136..147].copy_from_slice(b"00000000000");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[148..156].copy_from_slice(b"        ");
+    let sum: u64 = header.iter().map(|&byte| byte as u64).sum();
+    header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+    writer.write_all(&header).map_err(failed)?;
+
+    let copied = std::io::copy(&mut (&mut content).take(size), writer).map_err(failed)?;
+    if copied != size {
+        return Err(format!("{} changed while it was packed", name));
+    }
+    let padding = size.div_ceil(BLOCK_SIZE as u64) * BLOCK_SIZE as u64 - size;
+    writer.write_all(&vec![0; padding as usize]).map_err(failed)
+}
@@ -0,0 +1,509 @@
+//! PharmGKB drug-response annotation
+//!
+//! Loads the PharmGKB clinical annotation release: `clinical_annotations.tsv`
+//! with one row per variant-drug association, `clinical_ann_alleles.tsv`
+//! with the phenotype text for each genotype, and optionally
+//! `clinical_ann_evidence.tsv`, which names the dosing guidelines and drug
+//! labels backing an annotation.
+
+use super::normalize_rsid;
+use super::strand::{self, Strand};
+use super::tsv::TsvReader;
+use crate::genome::{Genotype, Variant};
+use crate::parallel;
+use crate::parser::compression;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// Annotation table of the release
+pub const ANNOTATIONS_FILE: &str = "clinical_annotations.tsv";
+
+/// Per-genotype phenotype table of the release
+pub const ALLELES_FILE: &str = "clinical_ann_alleles.tsv";
+
+/// Supporting evidence table of the release
+pub const EVIDENCE_FILE: &str = "clinical_ann_evidence.tsv";
+
+/// Columns of `clinical_annotations.tsv` needed to build an annotation
+const ANNOTATION_COLUMNS: [&str; 7] = [
+    "Clinical Annotation ID",
+    "Variant/Haplotypes",
+    "Gene",
+    "Level of Evidence",
+    "Phenotype Category",
+    "Drug(s)",
+    "Phenotype(s)",
+];
+
+/// Columns of `clinical_ann_alleles.tsv`
+const ALLELE_COLUMNS: [&str; 3] = [
+    "Clinical Annotation ID",
+    "Genotype/Allele",
+    "Annotation Text",
+];
+
+/// Organisations whose dosing guidelines PharmGKB annotates
+const GUIDELINE_SOURCES: [&str; 5] = ["CPIC", "DPWG", "CPNDS", "RNPGx", "AusNZ"];
+
+/// Drug label agencies PharmGKB annotates
+const LABEL_SOURCES: [&str; 6] = ["FDA", "EMA", "PMDA", "HCSC", "Swissmedic", "NMPA"];
+
+/// PharmGKB level of evidence, strongest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EvidenceLevel {
+    #[serde(rename = "1A")]
+    Level1A,
+    #[serde(rename = "1B")]
+    Level1B,
+    #[serde(rename = "2A")]
+    Level2A,
+    #[serde(rename = "2B")]
+    Level2B,
+    #[serde(rename = "3")]
+    Level3,
+    #[serde(rename = "4")]
+    Level4,
+}
+
+impl EvidenceLevel {
+    /// Parse "1A", "2B", "3" and so on
+    pub fn parse(raw: &str) -> Option<EvidenceLevel> {
+        match raw.trim().to_ascii_uppercase().as_str() {
+            "1A" => Some(EvidenceLevel::Level1A),
+            "1B" => Some(EvidenceLevel::Level1B),
+            "2A" => Some(EvidenceLevel::Level2A),
+            "2B" => Some(EvidenceLevel::Level2B),
+            "3" => Some(EvidenceLevel::Level3),
+            "4" => Some(EvidenceLevel::Level4),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EvidenceLevel::Level1A => "1A",
+            EvidenceLevel::Level1B => "1B",
+            EvidenceLevel::Level2A => "2A",
+            EvidenceLevel::Level2B => "2B",
+            EvidenceLevel::Level3 => "3",
+            EvidenceLevel::Level4 => "4",
+        }
+    }
+
+    /// Levels 1 and 2, which PharmGKB considers clinically meaningful
+    pub fn is_actionable(&self) -> bool {
+        *self <= EvidenceLevel::Level2B
+    }
+}
+
+/// What aspect of drug response an annotation describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhenotypeCategory {
+    Efficacy,
+    Toxicity,
+    Dosage,
+    Metabolism,
+    Pharmacodynamics,
+    Other,
+}
+
+impl PhenotypeCategory {
+    /// Parse a PharmGKB category such as "Metabolism/PK"
+    pub fn parse(raw: &str) -> PhenotypeCategory {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "efficacy" => PhenotypeCategory::Efficacy,
+            "toxicity" => PhenotypeCategory::Toxicity,
+            "dosage" => PhenotypeCategory::Dosage,
+            "metabolism/pk" | "metabolism" => PhenotypeCategory::Metabolism,
+            "pd" | "pharmacodynamics" => PhenotypeCategory::Pharmacodynamics,
+            _ => PhenotypeCategory::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhenotypeCategory::Efficacy => "Efficacy",
+            PhenotypeCategory::Toxicity => "Toxicity",
+            PhenotypeCategory::Dosage => "Dosage",
+            PhenotypeCategory::Metabolism => "Metabolism/PK",
+            PhenotypeCategory::Pharmacodynamics => "Pharmacodynamics",
+            PhenotypeCategory::Other => "Other",
+        }
+    }
+}
+
+/// Phenotype text for one genotype of an annotation
+#[derive(Debug, Clone, Serialize)]
+pub struct AlleleAnnotation {
+    /// Genotype as written in the release, e.g. "AG" or "*1/*3"
+    pub genotype: String,
+    pub text: String,
+    pub function: Option<String>,
+}
+
+/// One PharmGKB clinical annotation
+#[derive(Debug, Clone, Serialize)]
+pub struct ClinicalAnnotation {
+    pub id: String,
+    /// Variant or haplotypes as written in the release
+    pub variant: String,
+    /// Set when the annotation is about a single rsid
+    pub rsid: Option<String>,
+    pub genes: Vec<String>,
+    pub evidence_level: EvidenceLevel,
+    pub phenotype_categories: Vec<PhenotypeCategory>,
+    pub drugs: Vec<String>,
+    pub phenotypes: Vec<String>,
+    pub alleles: Vec<AlleleAnnotation>,
+    /// Dosing guideline and drug label sources, e.g. "CPIC" or "FDA label"
+    pub guideline_sources: Vec<String>,
+    /// Date of the latest change, as YYYY-MM-DD
+    pub updated: Option<String>,
+    pub url: Option<String>,
+}
+
+/// A PharmGKB annotation matched against a genotype
+#[derive(Debug, Clone, Copy)]
+pub struct PharmGkbMatch<'a> {
+    pub annotation: &'a ClinicalAnnotation,
+    pub allele: &'a AlleleAnnotation,
+    pub variant: &'a Variant,
+    /// Strand the annotated genotypes were read from
+    pub strand: Strand,
+}
+
+/// Indexed PharmGKB clinical annotation release
+#[derive(Debug, Default)]
+pub struct PharmGkbDatabase {
+    annotations: Vec<ClinicalAnnotation>,
+    by_rsid: HashMap<String, Vec<usize>>,
+    by_gene: HashMap<String, Vec<usize>>,
+}
+
+impl PharmGkbDatabase {
+    /// Load the release tables from an extracted `clinicalAnnotations` directory
+    pub fn load_dir(dir: &Path) -> Result<Self, String> {
+        let mut annotations = read_annotations(&dir.join(ANNOTATIONS_FILE))?;
+        let by_id: HashMap<String, usize> = annotations
+            .iter()
+            .enumerate()
+            .map(|(index, annotation)| (annotation.id.clone(), index))
+            .collect();
+
+        read_alleles(&dir.join(ALLELES_FILE), &mut annotations, &by_id)?;
+
+        let evidence = dir.join(EVIDENCE_FILE);
+        if evidence.is_file() {
+            read_evidence(&evidence, &mut annotations, &by_id)?;
+        }
+
+        Ok(Self::from_annotations(annotations))
+    }
+
+    /// Index annotations that were already parsed
+    pub fn from_annotations(annotations: Vec<ClinicalAnnotation>) -> Self {
+        let mut by_rsid: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_gene: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, annotation) in annotations.iter().enumerate() {
+            if let Some(rsid) = &annotation.rsid {
+                by_rsid.entry(rsid.clone()).or_default().push(index);
+            }
+            for gene in &annotation.genes {
+                by_gene.entry(gene.clone()).or_default().push(index);
+            }
+        }
+
+        Self {
+            annotations,
+            by_rsid,
+            by_gene,
+        }
+    }
+
+    /// Add the rsid of every single-variant annotation to `sites`
+    pub fn add_sites(&self, sites: &mut SiteFilter) {
+        for rsid in self.annotations.iter().filter_map(|a| a.rsid.as_deref()) {
+            sites.add_rsid(rsid);
+        }
+    }
+
+    /// Number of clinical annotations
+    pub fn len(&self) -> usize {
+        self.annotations.len()
+    }
+
+    /// Whether the release holds no annotations
+    pub fn is_empty(&self) -> bool {
+        self.annotations.is_empty()
+    }
+
+    /// Date of the most recently changed annotation
+    pub fn release_date(&self) -> Option<&str> {
+        self.annotations
+            .iter()
+            .filter_map(|annotation| annotation.updated.as_deref())
+            .max()
+    }
+
+    /// Annotations about a single rsid
+    pub fn lookup_rsid(&self, rsid: &str) -> Vec<&ClinicalAnnotation> {
+        self.collect(self.by_rsid.get(rsid))
+    }
+
+    /// Annotations involving a gene, including haplotype annotations
+    pub fn lookup_gene(&self, gene: &str) -> Vec<&ClinicalAnnotation> {
+        self.collect(self.by_gene.get(gene))
+    }
+
+    /// Match the genotype of every annotated rsid present in a genome
+    ///
+    /// `checkpoint` is called every [`CHECKPOINT_INTERVAL`] annotations and
+    /// stops the annotation when it returns an error.
+    pub fn annotate<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<Vec<PharmGkbMatch<'a>>, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        self.annotate_range(genome, 0..self.annotations.len(), &mut checkpoint)
+    }
+
+    /// Like [`PharmGkbDatabase::annotate`], on up to `threads` threads with
+    /// the annotations split into chunks
+    pub fn annotate_parallel<'a, F>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        threads: usize,
+        checkpoint: F,
+    ) -> Result<Vec<PharmGkbMatch<'a>>, String>
+    where
+        F: Fn(usize) -> Result<(), String> + Sync,
+    {
+        let chunks = parallel::chunks(self.annotations.len(), threads);
+        let matches = parallel::map(&chunks, threads, |chunk| {
+            self.annotate_range(genome, chunk, &mut |index| checkpoint(index))
+        })?;
+        Ok(matches.into_iter().flatten().collect())
+    }
+
+    /// Match the annotations with these indexes
+    fn annotate_range<'a>(
+        &'a self,
+        genome: &'a LoadedGenome,
+        range: Range<usize>,
+        checkpoint: &mut dyn FnMut(usize) -> Result<(), String>,
+    ) -> Result<Vec<PharmGkbMatch<'a>>, String> {
+        let mut matches = Vec::new();
+
+        for (index, annotation) in self.annotations[range.clone()]
+            .iter()
+            .enumerate()
+            .map(|(offset, annotation)| (range.start + offset, annotation))
+        {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            let Some(variant) = annotation
+                .rsid
+                .as_deref()
+                .and_then(|rsid| genome.get_by_rsid(rsid))
+            else {
+                continue;
+            };
+
+            if variant.genotype.is_no_call() {
+                continue;
+            }
+            let genotypes: Vec<Genotype> = annotation
+                .alleles
+                .iter()
+                .filter_map(|allele| allele.genotype.parse().ok())
+                .collect();
+            let mut annotated: Vec<&str> = genotypes.iter().flat_map(Genotype::alleles).collect();
+            annotated.sort_unstable();
+            annotated.dedup();
+            let strand = strand::resolve(variant, &annotated);
+            let called = if strand.is_flipped() {
+                sorted_alleles(&variant.genotype.complemented())
+            } else {
+                sorted_alleles(&variant.genotype)
+            };
+            let allele = annotation.alleles.iter().find(|allele| {
+                allele
+                    .genotype
+                    .parse::<Genotype>()
+                    .is_ok_and(|genotype| sorted_alleles(&genotype) == called)
+            });
+            if let Some(allele) = allele {
+                matches.push(PharmGkbMatch {
+                    annotation,
+                    allele,
+                    variant,
+                    strand,
+                });
+            }
+        }
+
+        Ok(matches)
+    }
+
+    fn collect(&self, indexes: Option<&Vec<usize>>) -> Vec<&ClinicalAnnotation> {
+        indexes
+            .map(|indexes| indexes.iter().map(|&i| &self.annotations[i]).collect())
+            .unwrap_or_default()
+    }
+}
+
+// Helper functions
+
+fn open_table(path: &Path) -> Result<TsvReader<Box<dyn std::io::BufRead + Send>>, String> {
+    let (reader, _) =
+        compression::open_reader(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    TsvReader::new(reader).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn read_annotations(path: &Path) -> Result<Vec<ClinicalAnnotation>, String> {
+    let mut reader = open_table(path)?;
+    reader
+        .require_columns(&ANNOTATION_COLUMNS)
+        .map_err(|e| format!("Not a PharmGKB clinical annotation table: {}", e))?;
+
+    let mut annotations = Vec::new();
+    while let Some(row) = reader.next_row() {
+        let row = row.map_err(|e| format!("PharmGKB {}", e))?;
+        let Some(evidence_level) = row.get("Level of Evidence").and_then(EvidenceLevel::parse)
+        else {
+            continue;
+        };
+        let variant = row.require("Variant/Haplotypes")?;
+
+        annotations.push(ClinicalAnnotation {
+            id: row.require("Clinical Annotation ID")?.to_string(),
+            variant: variant.to_string(),
+            rsid: variant
+                .starts_with("rs")
+                .then(|| normalize_rsid(variant))
+                .flatten(),
+            genes: split_list(row.get("Gene")),
+            evidence_level,
+            phenotype_categories: split_list(row.get("Phenotype Category"))
+                .iter()
+                .map(|category| PhenotypeCategory::parse(category))
+                .collect(),
+            drugs: split_list(row.get("Drug(s)")),
+            phenotypes: split_list(row.get("Phenotype(s)")),
+            alleles: Vec::new(),
+            guideline_sources: Vec::new(),
+            updated: row
+                .get("Latest History Date (YYYY-MM-DD)")
+                .map(str::to_string),
+            url: row.get("URL").map(str::to_string),
+        });
+    }
+
+    Ok(annotations)
+}
+
+fn read_alleles(
+    path: &Path,
+    annotations: &mut [ClinicalAnnotation],
+    by_id: &HashMap<String, usize>,
+) -> Result<(), String> {
+    let mut reader = open_table(path)?;
+    reader
+        .require_columns(&ALLELE_COLUMNS)
+        .map_err(|e| format!("Not a PharmGKB allele table: {}", e))?;
+
+    while let Some(row) = reader.next_row() {
+        let row = row.map_err(|e| format!("PharmGKB {}", e))?;
+        let Some(&index) = by_id.get(row.require("Clinical Annotation ID")?) else {
+            continue;
+        };
+        let (Some(genotype), Some(text)) = (row.get("Genotype/Allele"), row.get("Annotation Text"))
+        else {
+            continue;
+        };
+        annotations[index].alleles.push(AlleleAnnotation {
+            genotype: genotype.to_string(),
+            text: text.to_string(),
+            function: row.get("Allele Function").map(str::to_string),
+        });
+    }
+
+    Ok(())
+}
+
+fn read_evidence(
+    path: &Path,
+    annotations: &mut [ClinicalAnnotation],
+    by_id: &HashMap<String, usize>,
+) -> Result<(), String> {
+    let mut reader = open_table(path)?;
+    reader
+        .require_columns(&["Clinical Annotation ID", "Evidence Type", "Summary"])
+        .map_err(|e| format!("Not a PharmGKB evidence table: {}", e))?;
+
+    while let Some(row) = reader.next_row() {
+        let row = row.map_err(|e| format!("PharmGKB {}", e))?;
+        let Some(&index) = by_id.get(row.require("Clinical Annotation ID")?) else {
+            continue;
+        };
+        let summary = row.get("Summary").unwrap_or("");
+        let source = match row.get("Evidence Type") {
+            Some("Guideline Annotation") => named_source(summary, &GUIDELINE_SOURCES),
+            Some("Label Annotation") => {
+                named_source(summary, &LABEL_SOURCES).map(|agency| format!("{} label", agency))
+            }
+            _ => None,
+        };
+
+        let sources = &mut annotations[index].guideline_sources;
+        if let Some(source) = source {
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// First known organisation named in an evidence summary
+fn named_source(summary: &str, sources: &[&str]) -> Option<String> {
+    sources
+        .iter()
+        .find(|source| {
+            summary
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|w| w == **source)
+        })
+        .map(|source| source.to_string())
+}
+
+fn split_list(raw: Option<&str>) -> Vec<String> {
+    raw.unwrap_or("")
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Alleles in a fixed order, so "AG" and "GA" compare equal
+fn sorted_alleles(genotype: &Genotype) -> Vec<String> {
+    let mut alleles: Vec<String> = genotype
+        .alleles()
+        .into_iter()
+        .map(str::to_ascii_uppercase)
+        .collect();
+    alleles.sort();
+    alleles
+}
@@ -0,0 +1,264 @@
+//! Chip identification and known-unreliable probe masking
+//!
+//! Some array probes call badly: they cross-hybridize, sit on a common
+//! variant next to the target, or report rare clinical variants that are
+//! mostly false positives. A mask list names them per vendor and chip
+//! version, one probe per row of a tab-separated file:
+//!
+//! ```text
+//! vendor   version  probe     action   reason
+//! 23andme  v5       i4000377  exclude  Calls BRCA1 185delAG in samples without it
+//! *        *        rs1234    flag     Probe overlaps a common indel
+//! ```
+//!
+//! `vendor` is a file format as the parsers name it and `version` a chip
+//! version as the file states it, or `*` for any. Probes to exclude become
+//! no-calls before analysis; probes to flag keep their call, marked with
+//! the [`FLAG_FILTER`] status that [`crate::quality::borderline`] reports
+//! as [`crate::quality::QualityIssue::UnreliableProbe`].
+
+use super::tsv::TsvReader;
+use crate::genome::{CallQuality, Genotype};
+use crate::parser::compression;
+use crate::parser::detect::FileFormat;
+use crate::parser::SummaryBuilder;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+
+/// FILTER status given to the calls of flagged probes
+pub const FLAG_FILTER: &str = "unreliable_probe";
+
+const MASK_COLUMNS: [&str; 4] = ["vendor", "version", "probe", "action"];
+
+/// Consumer array formats, whose probes a mask list can name
+const ARRAY_FORMATS: [FileFormat; 4] = [
+    FileFormat::TwentyThreeAndMe,
+    FileFormat::AncestryDna,
+    FileFormat::MyHeritage,
+    FileFormat::FamilyTreeDna,
+];
+
+/// The genotyping chip a file came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chip {
+    pub vendor: FileFormat,
+    /// Lowercase chip version, e.g. "v5" or "v2.0", when the file states it
+    pub version: Option<String>,
+}
+
+/// Vendor and version, e.g. "23andMe v5"
+impl fmt::Display for Chip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let vendor = match self.vendor {
+            FileFormat::TwentyThreeAndMe => "23andMe",
+            FileFormat::AncestryDna => "AncestryDNA",
+            FileFormat::MyHeritage => "MyHeritage",
+            FileFormat::FamilyTreeDna => "FamilyTreeDNA",
+            other => other.as_str(),
+        };
+        match &self.version {
+            Some(version) => write!(f, "{} {}", vendor, version),
+            None => f.write_str(vendor),
+        }
+    }
+}
+
+/// The chip of a consumer array genome, with the version its header
+/// states; `None` for sequencing and other formats
+pub fn infer_chip(genome: &LoadedGenome) -> Option<Chip> {
+    let vendor = genome.file.format;
+    ARRAY_FORMATS.contains(&vendor).then(|| Chip {
+        vendor,
+        version: genome
+            .file
+            .chip_version
+            .as_deref()
+            .map(|version| version.trim().to_ascii_lowercase())
+            .filter(|version| !version.is_empty()),
+    })
+}
+
+/// What to do with the calls of a masked probe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeAction {
+    /// Keep the call but mark findings resting on it
+    Flag,
+    /// Turn the call into a no-call
+    Exclude,
+}
+
+/// One probe of the mask list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskedProbe {
+    /// `None` for every vendor
+    pub vendor: Option<FileFormat>,
+    /// Lowercase chip version; `None` for every version
+    pub version: Option<String>,
+    /// rsid or vendor probe id, as the export names the site
+    pub probe: String,
+    pub action: ProbeAction,
+    pub reason: Option<String>,
+}
+
+impl MaskedProbe {
+    /// Whether the probe is masked on a chip
+    pub fn applies_to(&self, chip: &Chip) -> bool {
+        self.vendor.is_none_or(|vendor| vendor == chip.vendor)
+            && self
+                .version
+                .as_ref()
+                .is_none_or(|version| chip.version.as_ref() == Some(version))
+    }
+}
+
+/// Counts from masking a genome
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaskStats {
+    pub chip: Option<Chip>,
+    /// Calls turned into no-calls
+    pub excluded: usize,
+    /// Calls kept and flagged
+    pub flagged: usize,
+}
+
+/// Known-unreliable probes by lowercase probe id
+#[derive(Debug, Default)]
+pub struct ProbeMask {
+    probes: HashMap<String, Vec<MaskedProbe>>,
+}
+
+impl ProbeMask {
+    /// Load a mask list, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Read a mask list from any buffered reader
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&MASK_COLUMNS)
+            .map_err(|e| format!("Not a probe mask list: {}", e))?;
+        let mut mask = ProbeMask::default();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("Probe mask {}", e))?;
+            let any = |raw: &str| raw.is_empty() || raw == "*";
+            let vendor = row.require("vendor")?;
+            let vendor = if any(vendor) {
+                None
+            } else {
+                let format = ARRAY_FORMATS
+                    .into_iter()
+                    .find(|format| format.as_str().eq_ignore_ascii_case(vendor))
+                    .ok_or_else(|| {
+                        format!("line {}: unknown vendor {}", row.line_number, vendor)
+                    })?;
+                Some(format)
+            };
+            let version = row
+                .get("version")
+                .filter(|version| !any(version))
+                .map(str::to_ascii_lowercase);
+            let action = match row.require("action")? {
+                "exclude" => ProbeAction::Exclude,
+                "flag" => ProbeAction::Flag,
+                other => {
+                    return Err(format!(
+                        "line {}: unknown action {}",
+                        row.line_number, other
+                    ))
+                }
+            };
+            let probe = row.require("probe")?.to_ascii_lowercase();
+            mask.probes
+                .entry(probe.clone())
+                .or_default()
+                .push(MaskedProbe {
+                    vendor,
+                    version,
+                    probe,
+                    action,
+                    reason: row.get("reason").map(str::to_string),
+                });
+        }
+        Ok(mask)
+    }
+
+    /// Number of masked probes
+    pub fn len(&self) -> usize {
+        self.probes.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The entry masking a probe on a chip, exclusions first
+    pub fn lookup(&self, chip: &Chip, probe: &str) -> Option<&MaskedProbe> {
+        self.probes
+            .get(&probe.trim().to_ascii_lowercase())?
+            .iter()
+            .filter(|masked| masked.applies_to(chip))
+            .max_by_key(|masked| masked.action)
+    }
+
+    /// Exclude and flag the calls of masked probes on the chip a genome
+    /// came from, as given by [`infer_chip`]
+    ///
+    /// `checkpoint` is called with the number of variants masked every
+    /// [`CHECKPOINT_INTERVAL`] variants.
+    pub fn apply<F>(
+        &self,
+        genome: &LoadedGenome,
+        chip: &Chip,
+        mut checkpoint: F,
+    ) -> Result<(LoadedGenome, MaskStats), String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut stats = MaskStats {
+            chip: Some(chip.clone()),
+            ..MaskStats::default()
+        };
+        let mut builder = SummaryBuilder::default();
+        let mut variants = Vec::with_capacity(genome.len());
+        for (index, variant) in genome.variants().iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            let mut variant = variant.clone();
+            let masked = variant
+                .rsid
+                .as_deref()
+                .and_then(|probe| self.lookup(chip, probe));
+            if let Some(masked) = masked.filter(|_| !variant.genotype.is_no_call()) {
+                match masked.action {
+                    ProbeAction::Exclude => {
+                        variant.genotype = Genotype::NoCall;
+                        stats.excluded += 1;
+                    }
+                    ProbeAction::Flag => {
+                        let quality = variant.quality.get_or_insert_with(CallQuality::default);
+                        quality.filters.push(FLAG_FILTER.to_string());
+                        stats.flagged += 1;
+                    }
+                }
+            }
+            builder.add(&variant);
+            variants.push(variant);
+        }
+        checkpoint(genome.len())?;
+
+        let summary = builder.finish(genome.summary.skipped_lines);
+        Ok((
+            LoadedGenome::from_variants(genome.file.clone(), summary, variants),
+            stats,
+        ))
+    }
+}
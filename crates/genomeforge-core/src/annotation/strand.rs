@@ -0,0 +1,175 @@
+//! Strand resolution for alleles matched by rsid
+//!
+//! Consumer arrays report genotypes on the plus strand, but PharmGKB
+//! genotypes and GWAS Catalog risk alleles are sometimes given on the
+//! minus strand, and some array exports report minus-strand calls. When
+//! only the complements of a database's alleles fit a call, the database
+//! alleles are read from the opposite strand.
+//!
+//! A/T and C/G SNPs look the same on both strands. Their strand is settled
+//! by comparing the frequency the database reports for its allele with
+//! gnomAD's plus-strand frequency, and left [`Strand::Ambiguous`] when
+//! either is too close to 50% to tell; ambiguous matches are taken as
+//! reported and flagged.
+
+use super::gnomad::GnomadDatabase;
+use super::normalize_rsid;
+use crate::genome::{reverse_complement, Variant};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Minor allele frequency above which an A/T or C/G SNP's strand cannot
+/// be told from frequencies
+pub const MAX_PALINDROMIC_FREQUENCY: f64 = 0.42;
+
+/// How a database's alleles were put on the strand of a genome's call
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strand {
+    /// The alleles fit the call as reported
+    #[default]
+    Forward,
+    /// Only the complemented alleles fit the call
+    Flipped,
+    /// An A/T or C/G SNP whose frequencies put it on the call's strand
+    FrequencyForward,
+    /// An A/T or C/G SNP whose frequencies put it on the opposite strand
+    FrequencyFlipped,
+    /// An A/T or C/G SNP whose strand cannot be told; taken as reported
+    Ambiguous,
+}
+
+impl Strand {
+    /// Whether the database alleles are read from the opposite strand
+    pub fn is_flipped(self) -> bool {
+        matches!(self, Strand::Flipped | Strand::FrequencyFlipped)
+    }
+
+    pub fn is_ambiguous(self) -> bool {
+        self == Strand::Ambiguous
+    }
+
+    /// A database allele on the strand of the call
+    pub fn orient(self, allele: &str) -> String {
+        if self.is_flipped() {
+            reverse_complement(allele)
+        } else {
+            allele.to_string()
+        }
+    }
+}
+
+/// Strand of a database's `alleles` for a variant's call
+///
+/// The site's alleles are the called ones, with the reference and
+/// alternates once a VCF or dbSNP gives them. Indels and D/I calls are
+/// taken as reported.
+pub fn resolve(variant: &Variant, alleles: &[&str]) -> Strand {
+    let called = bases(variant.genotype.alleles());
+    let database = bases(alleles.iter().copied());
+    let (Some(called), Some(database)) = (called, database) else {
+        return Strand::Forward;
+    };
+    if called.is_empty() || database.is_empty() {
+        return Strand::Forward;
+    }
+    let mut site = called.clone();
+    if let Some(reference) = &variant.reference {
+        let known = std::iter::once(reference).chain(&variant.alternates);
+        site.extend(bases(known.map(String::as_str)).unwrap_or_default());
+    }
+
+    let union: BTreeSet<String> = database.union(&site).cloned().collect();
+    if is_palindromic(&union) {
+        return Strand::Ambiguous;
+    }
+    // Every called allele must be a database allele, or with a single
+    // database allele, that allele one of the site's
+    let fits = |database: &BTreeSet<String>| {
+        if database.len() >= 2 {
+            called.is_subset(database)
+        } else {
+            database.is_subset(&site)
+        }
+    };
+    if !fits(&database) && fits(&complemented(&database)) {
+        Strand::Flipped
+    } else {
+        Strand::Forward
+    }
+}
+
+/// Settle the strand of an A/T or C/G SNP by the frequency
+/// `database_frequency` the database reports for `allele`, against gnomAD's
+/// plus-strand frequency at the variant's rsid
+///
+/// gnomAD's alleles also reveal A/T and C/G sites that a homozygous call
+/// does not, so a strand taken as forward can turn out ambiguous. Other
+/// sites keep `strand`.
+pub fn resolve_by_frequency(
+    strand: Strand,
+    variant: &Variant,
+    allele: &str,
+    database_frequency: Option<f64>,
+    gnomad: &GnomadDatabase,
+) -> Strand {
+    let Some(rsid) = variant.rsid.as_deref().and_then(normalize_rsid) else {
+        return strand;
+    };
+    let records = gnomad.lookup_rsid(&rsid);
+    let known = records
+        .iter()
+        .flat_map(|record| [record.reference.as_str(), record.alternate.as_str()]);
+    let site = variant.genotype.alleles().into_iter().chain([allele]);
+    if !bases(site.chain(known)).is_some_and(|site| is_palindromic(&site)) {
+        return strand;
+    }
+
+    let allele = allele.to_ascii_uppercase();
+    let plus_frequency = records.iter().find_map(|record| {
+        if record.alternate.eq_ignore_ascii_case(&allele) {
+            Some(record.frequencies.global)
+        } else if record.reference.eq_ignore_ascii_case(&allele) {
+            Some(1.0 - record.frequencies.global)
+        } else {
+            None
+        }
+    });
+    let (Some(database_frequency), Some(plus_frequency)) = (database_frequency, plus_frequency)
+    else {
+        return Strand::Ambiguous;
+    };
+    let too_common = |frequency: f64| frequency.min(1.0 - frequency) > MAX_PALINDROMIC_FREQUENCY;
+    if too_common(database_frequency) || too_common(plus_frequency) {
+        Strand::Ambiguous
+    } else if (database_frequency > 0.5) == (plus_frequency > 0.5) {
+        Strand::FrequencyForward
+    } else {
+        Strand::FrequencyFlipped
+    }
+}
+
+// Helper functions
+
+/// Uppercase single-base alleles, or `None` when any is not one
+fn bases<'a>(alleles: impl IntoIterator<Item = &'a str>) -> Option<BTreeSet<String>> {
+    alleles
+        .into_iter()
+        .map(|allele| {
+            let allele = allele.to_ascii_uppercase();
+            matches!(allele.as_str(), "A" | "C" | "G" | "T").then_some(allele)
+        })
+        .collect()
+}
+
+fn complemented(alleles: &BTreeSet<String>) -> BTreeSet<String> {
+    alleles
+        .iter()
+        .map(|allele| reverse_complement(allele))
+        .collect()
+}
+
+/// Whether the alleles are exactly A/T or C/G
+fn is_palindromic(alleles: &BTreeSet<String>) -> bool {
+    alleles.len() == 2 && complemented(alleles) == *alleles
+}
@@ -0,0 +1,131 @@
+//! Reader for tab-separated annotation releases
+//!
+//! ClinVar, PharmGKB and the GWAS Catalog all publish TSV files with a
+//! single header row. Columns are looked up by name so that column
+//! reordering between releases does not break the loaders.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// Streaming reader over the rows of a headed TSV file
+pub struct TsvReader<R: BufRead> {
+    reader: R,
+    columns: HashMap<String, usize>,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> TsvReader<R> {
+    /// Read the header row; a leading `#` on it is ignored
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|e| format!("Failed to read header: {}", e))?;
+        let header = header.trim_end_matches(['\r', '\n']);
+        let header = header.strip_prefix('#').unwrap_or(header);
+        if header.is_empty() {
+            return Err("File is empty".to_string());
+        }
+
+        let columns = header
+            .split('\t')
+            .enumerate()
+            .map(|(index, name)| (name.trim().to_string(), index))
+            .collect();
+
+        Ok(Self {
+            reader,
+            columns,
+            line: String::new(),
+            line_number: 1,
+        })
+    }
+
+    /// Whether the header has a column with this name
+    pub fn has_column(&self, name: &str) -> bool {
+        self.columns.contains_key(name)
+    }
+
+    /// Column names in header order
+    pub fn column_names(&self) -> Vec<String> {
+        let mut columns: Vec<(&String, &usize)> = self.columns.iter().collect();
+        columns.sort_by_key(|(_, index)| **index);
+        columns.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Name of the first column starting with `prefix`
+    ///
+    /// For columns whose name carries a qualifier that changes between
+    /// releases, such as "Allele Clinical Functional Status (Required)".
+    pub fn column_starting_with(&self, prefix: &str) -> Option<String> {
+        self.columns
+            .iter()
+            .filter(|(name, _)| name.starts_with(prefix))
+            .min_by_key(|(_, index)| **index)
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Fail with a readable error unless every named column is present
+    pub fn require_columns(&self, names: &[&str]) -> Result<(), String> {
+        let missing: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| !self.has_column(name))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Missing columns: {}", missing.join(", ")))
+        }
+    }
+
+    /// Read the next data row
+    pub fn next_row(&mut self) -> Option<Result<TsvRow<'_>, String>> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(format!("line {}: {}", self.line_number + 1, e))),
+            }
+            if !self.line.trim().is_empty() {
+                break;
+            }
+        }
+
+        Some(Ok(TsvRow {
+            fields: self
+                .line
+                .trim_end_matches(['\r', '\n'])
+                .split('\t')
+                .collect(),
+            columns: &self.columns,
+            line_number: self.line_number,
+        }))
+    }
+}
+
+/// One data row of a [`TsvReader`]
+pub struct TsvRow<'a> {
+    fields: Vec<&'a str>,
+    columns: &'a HashMap<String, usize>,
+    pub line_number: usize,
+}
+
+impl<'a> TsvRow<'a> {
+    /// Value of a named column, `None` when absent or empty
+    pub fn get(&self, column: &str) -> Option<&'a str> {
+        let index = *self.columns.get(column)?;
+        self.fields
+            .get(index)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Value of a named column that must be present
+    pub fn require(&self, column: &str) -> Result<&'a str, String> {
+        self.get(column)
+            .ok_or_else(|| format!("line {}: missing {}", self.line_number, column))
+    }
+}
@@ -0,0 +1,161 @@
+//! Zygosity and inheritance of clinical findings
+//!
+//! A pathogenic variant means different things depending on how many
+//! copies are carried and how the condition is inherited: one copy of a
+//! variant for a recessive condition makes a carrier, one copy for a
+//! dominant condition is enough to be affected.
+//!
+//! ClinVar does not record the mode of inheritance, so it is taken from
+//! the curated [`acmg`] and [`carrier`] gene lists, then from condition
+//! names such as "Deafness, autosomal recessive 1A".
+
+use super::clinvar::{ClinVarMatch, ClinVarRecord};
+use super::{acmg, carrier};
+use crate::genome::Genotype;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Copies of the classified allele carried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Zygosity {
+    Heterozygous,
+    Homozygous,
+    /// The only copy of an X or Y chromosome region; mitochondrial calls
+    /// are single-allele too and count as homozygous
+    Hemizygous,
+}
+
+impl Zygosity {
+    /// Zygosity of a matched call
+    pub fn of(found: &ClinVarMatch<'_>) -> Self {
+        match &found.variant.genotype {
+            Genotype::Haploid(_) if found.variant.chromosome == "MT" => Zygosity::Homozygous,
+            Genotype::Haploid(_) => Zygosity::Hemizygous,
+            _ if found.alternate_copies > 1 => Zygosity::Homozygous,
+            _ => Zygosity::Heterozygous,
+        }
+    }
+}
+
+/// How a condition is passed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InheritanceMode {
+    AutosomalDominant,
+    AutosomalRecessive,
+    XLinkedDominant,
+    XLinkedRecessive,
+    Mitochondrial,
+}
+
+/// What a genotype means for the person carrying it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpretation {
+    /// Enough copies to cause the condition, subject to its penetrance
+    Affected,
+    /// One copy of a variant for a recessive condition, with another
+    /// pathogenic variant in the same gene that may be on the other copy
+    PossiblyAffected,
+    /// One copy of a variant for a recessive condition
+    Carrier,
+}
+
+/// Zygosity-aware reading of one finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FindingZygosity {
+    pub zygosity: Zygosity,
+    pub inheritance: Option<InheritanceMode>,
+    /// Only for pathogenic and likely pathogenic findings whose mode of
+    /// inheritance is known
+    pub interpretation: Option<Interpretation>,
+}
+
+/// Mode of inheritance of the condition a record is classified for
+pub fn inheritance(record: &ClinVarRecord) -> Option<InheritanceMode> {
+    if record.chromosome == "MT" {
+        return Some(InheritanceMode::Mitochondrial);
+    }
+    for symbol in &record.genes {
+        if let Some(gene) = acmg::gene(symbol) {
+            return Some(match gene.inheritance {
+                acmg::Inheritance::Dominant => InheritanceMode::AutosomalDominant,
+                acmg::Inheritance::Recessive => InheritanceMode::AutosomalRecessive,
+                acmg::Inheritance::XLinked => InheritanceMode::XLinkedRecessive,
+            });
+        }
+        if let Some(gene) = carrier::gene(symbol) {
+            return Some(match gene.inheritance {
+                carrier::CarrierInheritance::AutosomalRecessive => {
+                    InheritanceMode::AutosomalRecessive
+                }
+                carrier::CarrierInheritance::XLinkedRecessive => InheritanceMode::XLinkedRecessive,
+            });
+        }
+    }
+    record.conditions.iter().find_map(|condition| {
+        let condition = condition.to_ascii_lowercase();
+        if condition.contains("autosomal dominant") {
+            Some(InheritanceMode::AutosomalDominant)
+        } else if condition.contains("autosomal recessive") {
+            Some(InheritanceMode::AutosomalRecessive)
+        } else if condition.contains("x-linked dominant") {
+            Some(InheritanceMode::XLinkedDominant)
+        } else if condition.contains("x-linked") {
+            Some(InheritanceMode::XLinkedRecessive)
+        } else {
+            None
+        }
+    })
+}
+
+/// Pathogenic and likely pathogenic matches per gene
+pub fn pathogenic_counts(matches: &[ClinVarMatch<'_>]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for found in matches {
+        if found.record.significance.is_pathogenic() {
+            for gene in &found.record.genes {
+                *counts.entry(gene.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Zygosity, inheritance and interpretation of a match
+///
+/// `pathogenic_counts` comes from [`pathogenic_counts`] over all of the
+/// genome's matches, so that two variants in one recessive gene are read
+/// together.
+pub fn interpret(
+    found: &ClinVarMatch<'_>,
+    pathogenic_counts: &HashMap<String, usize>,
+) -> FindingZygosity {
+    let zygosity = Zygosity::of(found);
+    let inheritance = inheritance(found.record);
+    let second_hit = found
+        .record
+        .genes
+        .iter()
+        .any(|gene| pathogenic_counts.get(gene).copied().unwrap_or(0) > 1);
+
+    let interpretation = inheritance
+        .filter(|_| found.record.significance.is_pathogenic())
+        .map(|mode| match (mode, zygosity) {
+            (InheritanceMode::AutosomalRecessive, Zygosity::Heterozygous)
+            | (InheritanceMode::XLinkedRecessive, Zygosity::Heterozygous) => {
+                if second_hit {
+                    Interpretation::PossiblyAffected
+                } else {
+                    Interpretation::Carrier
+                }
+            }
+            _ => Interpretation::Affected,
+        });
+    FindingZygosity {
+        zygosity,
+        inheritance,
+        interpretation,
+    }
+}
@@ -0,0 +1,215 @@
+//! Audit log of what was done with a profile's data
+//!
+//! Every file load, analysis, export and database update is appended to an
+//! [`AuditLog`], which entries are never removed from. Each entry carries
+//! the SHA-256 of its own contents and of the entry before it, so editing,
+//! dropping or reordering an earlier entry breaks the chain from there on;
+//! the hash of the last entry is kept in the file header, so dropping
+//! entries from the end shows too. The log names the files a user opened,
+//! so it is sealed with [`crypto::write_file`].
+
+use crate::crypto::{self, KeySource};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind recorded in the header of audit logs
+const KIND: &str = "audit";
+
+/// Hash the first entry chains from
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// What was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    FileLoaded,
+    Analysis,
+    Export,
+    DatabaseUpdate,
+}
+
+/// One recorded action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, from 0
+    pub sequence: u64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub action: AuditAction,
+    /// What the action was done to: a file name, an analysis or a database
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, String>,
+    /// Hash of the entry before, or [`GENESIS`]
+    pub previous_hash: String,
+    /// Hex SHA-256 of the entry's other fields
+    pub hash: String,
+}
+
+impl AuditEntry {
+    /// The hash the entry should carry
+    pub fn expected_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.sequence.to_le_bytes());
+        hasher.update(self.timestamp.to_le_bytes());
+        // Fields are length-prefixed so one cannot run into the next
+        let mut field = |value: &str| {
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        };
+        field(action_name(self.action));
+        field(&self.subject);
+        for (name, value) in &self.details {
+            field(name);
+            field(value);
+        }
+        field(&self.previous_hash);
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Result of checking a log's hash chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries: usize,
+    /// Sequence number of the first entry that does not fit the chain
+    pub first_invalid: Option<u64>,
+    pub reason: Option<String>,
+}
+
+/// Actions recorded for one profile, oldest first
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    /// Hash of the last entry according to the file header
+    recorded_head: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogInfo {
+    entries: usize,
+    head: String,
+}
+
+impl AuditLog {
+    /// Read the log at `path`; a missing file is an empty log
+    pub fn open(path: &Path, key: KeySource<'_>) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let (envelope, plaintext) = crypto::read_file::<LogInfo>(path, KIND, key)?;
+        let entries: Vec<AuditEntry> = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Audit log is corrupt: {}", e))?;
+        Ok(AuditLog {
+            entries,
+            recorded_head: Some(envelope.metadata.head),
+        })
+    }
+
+    /// Write the log to `path`
+    pub fn save(&self, path: &Path, key: KeySource<'_>) -> Result<(), String> {
+        let plaintext = serde_json::to_vec(&self.entries)
+            .map_err(|e| format!("Failed to serialize audit log: {}", e))?;
+        let info = LogInfo {
+            entries: self.entries.len(),
+            head: self.head().to_string(),
+        };
+        crypto::write_file(path, KIND, &info, &plaintext, key).map(|_| ())
+    }
+
+    /// Record an action at the end of the log
+    pub fn append<I>(&mut self, action: AuditAction, subject: &str, details: I) -> &AuditEntry
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut entry = AuditEntry {
+            sequence: self.entries.len() as u64,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+            action,
+            subject: subject.to_string(),
+            details: details.into_iter().collect(),
+            previous_hash: self.head().to_string(),
+            hash: String::new(),
+        };
+        entry.hash = entry.expected_hash();
+        self.recorded_head = Some(entry.hash.clone());
+        self.entries.push(entry);
+        &self.entries[self.entries.len() - 1]
+    }
+
+    /// Check every entry against the one before it and the last against
+    /// the file header
+    pub fn verify(&self) -> AuditVerification {
+        let invalid = |sequence: u64, reason: &str| AuditVerification {
+            valid: false,
+            entries: self.entries.len(),
+            first_invalid: Some(sequence),
+            reason: Some(reason.to_string()),
+        };
+        let mut previous = GENESIS;
+        for (index, entry) in self.entries.iter().enumerate() {
+            let index = index as u64;
+            if entry.sequence != index {
+                return invalid(index, "entries are missing or out of order");
+            }
+            if entry.previous_hash != previous {
+                return invalid(index, "entry does not follow the one before");
+            }
+            if entry.hash != entry.expected_hash() {
+                return invalid(index, "entry was modified");
+            }
+            previous = &entry.hash;
+        }
+        if self
+            .recorded_head
+            .as_deref()
+            .is_some_and(|head| head != previous)
+        {
+            return invalid(
+                self.entries.len() as u64,
+                "entries were removed from the end",
+            );
+        }
+        AuditVerification {
+            valid: true,
+            entries: self.entries.len(),
+            first_invalid: None,
+            reason: None,
+        }
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Hash of the last entry, or [`GENESIS`] for an empty log
+    pub fn head(&self) -> &str {
+        self.entries.last().map_or(GENESIS, |entry| &entry.hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// Helper functions
+
+fn action_name(action: AuditAction) -> &'static str {
+    match action {
+        AuditAction::FileLoaded => "file_loaded",
+        AuditAction::Analysis => "analysis",
+        AuditAction::Export => "export",
+        AuditAction::DatabaseUpdate => "database_update",
+    }
+}
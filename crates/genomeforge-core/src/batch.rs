@@ -0,0 +1,160 @@
+//! Delivering results in batches as they are found
+//!
+//! A long analysis hands its findings to the frontend as it goes instead
+//! of all at the end. A [`Batcher`] groups them so that neither a flood of
+//! tiny messages nor one huge one crosses to the frontend: a batch goes out
+//! once it holds enough items or its first item has waited long enough. A
+//! [`Window`] keeps the sender from running too far ahead of a receiver
+//! that acknowledges the batches it has taken in; one that never does is
+//! not waited for, and one that stops is only waited for once.
+
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Items in a full batch
+pub const BATCH_SIZE: usize = 500;
+
+/// Longest an item waits for its batch to fill
+pub const BATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Batches sent ahead of the receiver's acknowledgements at most
+pub const MAX_IN_FLIGHT: u64 = 8;
+
+/// Longest a sender waits for an acknowledgement
+pub const ACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Groups items into batches by count and age
+#[derive(Debug)]
+pub struct Batcher<T> {
+    items: Vec<T>,
+    size: usize,
+    interval: Duration,
+    /// When the first item of the current batch arrived
+    opened: Option<Instant>,
+}
+
+impl<T> Batcher<T> {
+    pub fn new(size: usize, interval: Duration) -> Self {
+        Batcher {
+            items: Vec::new(),
+            size: size.max(1),
+            interval,
+            opened: None,
+        }
+    }
+
+    /// Add an item at `now`, returning the batch when it is due
+    pub fn push(&mut self, item: T, now: Instant) -> Option<Vec<T>> {
+        self.opened.get_or_insert(now);
+        self.items.push(item);
+        self.poll(now)
+    }
+
+    /// The current batch when it is full or has waited long enough at `now`
+    pub fn poll(&mut self, now: Instant) -> Option<Vec<T>> {
+        let full = self.items.len() >= self.size;
+        let expired = self
+            .opened
+            .is_some_and(|opened| now.duration_since(opened) >= self.interval);
+        (full || expired).then(|| self.take())
+    }
+
+    /// How long after `now` the current batch falls due; none while empty
+    pub fn wait_time(&self, now: Instant) -> Option<Duration> {
+        self.opened
+            .map(|opened| self.interval.saturating_sub(now.duration_since(opened)))
+    }
+
+    /// Whatever the current batch holds, due or not
+    pub fn take(&mut self) -> Vec<T> {
+        self.opened = None;
+        std::mem::take(&mut self.items)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<T> Default for Batcher<T> {
+    fn default() -> Self {
+        Self::new(BATCH_SIZE, BATCH_INTERVAL)
+    }
+}
+
+/// Numbers batches and holds the sender back until the receiver catches up
+#[derive(Debug)]
+pub struct Window {
+    state: Mutex<WindowState>,
+    acknowledged: Condvar,
+    max_in_flight: u64,
+    timeout: Duration,
+}
+
+#[derive(Debug, Default)]
+struct WindowState {
+    /// Sequence number of the next batch
+    next: u64,
+    /// Highest sequence number the receiver has acknowledged
+    acknowledged: Option<u64>,
+    /// Whether the receiver let a wait run out, so it is no longer waited
+    /// for
+    stalled: bool,
+}
+
+impl Window {
+    pub fn new(max_in_flight: u64, timeout: Duration) -> Self {
+        Window {
+            state: Mutex::new(WindowState::default()),
+            acknowledged: Condvar::new(),
+            max_in_flight: max_in_flight.max(1),
+            timeout,
+        }
+    }
+
+    /// Sequence number for the next batch, counting from 0, once it may be
+    /// sent
+    pub fn next_sequence(&self) -> u64 {
+        let held_back = |state: &mut WindowState| {
+            !state.stalled
+                && state.acknowledged.is_some_and(|acknowledged| {
+                    state.next.saturating_sub(acknowledged + 1) >= self.max_in_flight
+                })
+        };
+        let mut state = self.lock();
+        if held_back(&mut state) {
+            let (guard, wait) = self
+                .acknowledged
+                .wait_timeout_while(state, self.timeout, held_back)
+                .unwrap_or_else(|e| e.into_inner());
+            state = guard;
+            state.stalled = wait.timed_out();
+        }
+        let sequence = state.next;
+        state.next += 1;
+        sequence
+    }
+
+    /// Record that the receiver has taken in every batch up to `sequence`
+    pub fn acknowledge(&self, sequence: u64) {
+        let mut state = self.lock();
+        state.acknowledged = Some(state.acknowledged.map_or(sequence, |a| a.max(sequence)));
+        state.stalled = false;
+        self.acknowledged.notify_all();
+    }
+
+    /// Batches numbered so far
+    pub fn sent(&self) -> u64 {
+        self.lock().next
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WindowState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self::new(MAX_IN_FLIGHT, ACK_TIMEOUT)
+    }
+}
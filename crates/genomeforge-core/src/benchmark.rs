@@ -0,0 +1,255 @@
+//! Parse and annotation throughput on synthetic genomes
+//!
+//! The fixtures are generated rather than shipped: a VCF and a 23andMe
+//! export of any number of variants, spread over the autosomes and with a
+//! share of no-calls. Generation is deterministic, so every run measures
+//! the same files. [`run`] times parsing both and matching one of
+//! them against a ClinVar release built from its own sites.
+
+use crate::annotation::clinvar::{
+    ClinVarDatabase, ClinVarRecord, ClinicalSignificance, ReviewStatus,
+};
+use crate::parser;
+use crate::store::LoadedGenome;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Variants in the fixtures of a self-benchmark
+pub const DEFAULT_VARIANTS: usize = 200_000;
+
+/// Every this many sites of a genome get a ClinVar record
+pub const CLINVAR_EVERY: usize = 50;
+
+const BASES: [&str; 4] = ["A", "C", "G", "T"];
+
+/// Bytes and variants got through in a stretch of time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Throughput {
+    pub bytes: u64,
+    pub variants: usize,
+    pub seconds: f64,
+    pub mb_per_second: f64,
+    pub variants_per_second: f64,
+}
+
+impl Throughput {
+    pub fn new(bytes: u64, variants: usize, elapsed: Duration) -> Self {
+        // A floor keeps rates finite on a clock too coarse to see the work
+        let seconds = elapsed.as_secs_f64().max(1e-9);
+        Throughput {
+            bytes,
+            variants,
+            seconds,
+            mb_per_second: bytes as f64 / 1e6 / seconds,
+            variants_per_second: variants as f64 / seconds,
+        }
+    }
+}
+
+/// Throughput of each stage on this machine
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchmarkReport {
+    pub vcf: Throughput,
+    pub twenty_three_and_me: Throughput,
+    /// Matching the parsed VCF against ClinVar; bytes are those of the file
+    pub annotation: Throughput,
+    /// ClinVar matches found, one per [`CLINVAR_EVERY`] called sites
+    pub clinvar_matches: usize,
+}
+
+/// Write a single-sample GRCh38 VCF of `variants` sites
+pub fn synthetic_vcf<W: Write>(out: W, variants: usize) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    writeln!(out, "##fileformat=VCFv4.2")?;
+    writeln!(out, "##reference=GRCh38")?;
+    writeln!(
+        out,
+        "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">"
+    )?;
+    writeln!(
+        out,
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSYNTHETIC"
+    )?;
+    let mut random = Random::default();
+    for index in 0..variants {
+        let (chromosome, position) = site(index, variants);
+        let value = random.next();
+        let reference = (value % 4) as usize;
+        let alternate = (reference + 1 + (value / 4 % 3) as usize) % 4;
+        writeln!(
+            out,
+            "chr{}\t{}\trs{}\t{}\t{}\t50\tPASS\t.\tGT\t{}",
+            chromosome,
+            position,
+            index + 1,
+            BASES[reference],
+            BASES[alternate],
+            vcf_genotype(value)
+        )?;
+    }
+    out.flush()
+}
+
+/// Write a 23andMe export of `variants` sites on GRCh37
+pub fn synthetic_23andme<W: Write>(out: W, variants: usize) -> io::Result<()> {
+    let mut out = BufWriter::new(out);
+    writeln!(out, "# This data file generated by 23andMe (synthetic)")?;
+    writeln!(
+        out,
+        "# More information on reference human assembly build 37 (a.k.a. GRCh37):"
+    )?;
+    writeln!(out, "# rsid\tchromosome\tposition\tgenotype")?;
+    let mut random = Random::default();
+    for index in 0..variants {
+        let (chromosome, position) = site(index, variants);
+        let value = random.next();
+        let genotype = match value % 20 {
+            0 => "--".to_string(),
+            _ => format!(
+                "{}{}",
+                BASES[(value / 20 % 4) as usize],
+                BASES[(value / 80 % 4) as usize]
+            ),
+        };
+        writeln!(
+            out,
+            "rs{}\t{}\t{}\t{}",
+            index + 1,
+            chromosome,
+            position,
+            genotype
+        )?;
+    }
+    out.flush()
+}
+
+/// A ClinVar release classifying an allele carried at every
+/// [`CLINVAR_EVERY`]th called site of a genome
+pub fn synthetic_clinvar(genome: &LoadedGenome) -> ClinVarDatabase {
+    let records = genome
+        .variants()
+        .iter()
+        .filter(|variant| !variant.genotype.is_no_call())
+        .step_by(CLINVAR_EVERY)
+        .filter_map(|variant| {
+            let alleles = variant.genotype.alleles();
+            let reference = variant.reference.clone()?;
+            let alternate = alleles.iter().find(|allele| **allele != reference)?;
+            Some(ClinVarRecord {
+                variation_id: None,
+                rsid: variant.rsid.clone(),
+                genome_build: genome.file.genome_build,
+                chromosome: variant.chromosome.clone(),
+                position: variant.position,
+                reference,
+                alternate: alternate.to_string(),
+                genes: Vec::new(),
+                significance: ClinicalSignificance::Pathogenic,
+                significance_label: "Pathogenic".to_string(),
+                review_status: ReviewStatus::parse("criteria provided, single submitter"),
+                conditions: vec!["Synthetic condition".to_string()],
+                condition_ids: Vec::new(),
+                conflicting_classifications: Vec::new(),
+                submitter_count: None,
+                last_evaluated: None,
+            })
+        })
+        .collect();
+    ClinVarDatabase::from_records(records, None)
+}
+
+/// Generate fixtures of `variants` sites in `dir` and time each stage
+///
+/// The fixtures are removed again, whether or not the run succeeds.
+pub fn run(dir: &Path, variants: usize) -> Result<BenchmarkReport, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let vcf = dir.join("synthetic.vcf");
+    let twenty_three_and_me = dir.join("synthetic_23andme.txt");
+    let report = write_fixture(&vcf, variants, synthetic_vcf)
+        .and_then(|_| write_fixture(&twenty_three_and_me, variants, synthetic_23andme))
+        .and_then(|_| measure(&vcf, &twenty_three_and_me));
+    let _ = fs::remove_file(&vcf);
+    let _ = fs::remove_file(&twenty_three_and_me);
+    report
+}
+
+// Helper functions
+
+fn measure(vcf: &Path, twenty_three_and_me: &Path) -> Result<BenchmarkReport, String> {
+    let (genome, vcf) = parse(vcf)?;
+    let (_, twenty_three_and_me) = parse(twenty_three_and_me)?;
+
+    let clinvar = synthetic_clinvar(&genome);
+    let start = Instant::now();
+    let matches = clinvar.annotate(&genome, |_| Ok(()))?;
+    let annotation = Throughput::new(vcf.bytes, genome.len(), start.elapsed());
+
+    Ok(BenchmarkReport {
+        vcf,
+        twenty_three_and_me,
+        annotation,
+        clinvar_matches: matches.len(),
+    })
+}
+
+/// Parse a file into memory, timing it
+fn parse(path: &Path) -> Result<(LoadedGenome, Throughput), String> {
+    let bytes = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    let start = Instant::now();
+    let mut source = parser::open_genome(path)?;
+    let genome = LoadedGenome::load(source.as_mut())?;
+    let throughput = Throughput::new(bytes, genome.len(), start.elapsed());
+    Ok((genome, throughput))
+}
+
+fn write_fixture(
+    path: &Path,
+    variants: usize,
+    generate: fn(File, usize) -> io::Result<()>,
+) -> Result<(), String> {
+    File::create(path)
+        .and_then(|file| generate(file, variants))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Chromosome and position of the `index`th of `count` sites, spread
+/// evenly over the autosomes
+fn site(index: usize, count: usize) -> (usize, u64) {
+    let per_chromosome = count.div_ceil(22).max(1);
+    let chromosome = index / per_chromosome + 1;
+    let position = 10_000 + (index % per_chromosome) as u64 * 1_000;
+    (chromosome, position)
+}
+
+/// Mostly homozygous reference, as in a real sample, and a few no-calls
+fn vcf_genotype(value: u64) -> &'static str {
+    match value / 12 % 100 {
+        0..=1 => "./.",
+        2..=21 => "1/1",
+        22..=51 => "0/1",
+        _ => "0/0",
+    }
+}
+
+/// xorshift64, enough to vary the fixtures without a dependency
+struct Random(u64);
+
+impl Default for Random {
+    fn default() -> Self {
+        Random(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
@@ -0,0 +1,499 @@
+//! Cache of parsed genome files
+//!
+//! Parsing a 600,000-variant array export takes seconds every launch;
+//! reading it back from the cache takes a fraction of that. Entries are
+//! named by the SHA-256 of the source file, as
+//! [`crate::fingerprint::sha256_file`] computes it, so a changed file
+//! misses, and record the [`PARSER_VERSION`] and crate version they were
+//! written with, so entries an older parser wrote are ignored and rebuilt.
+//!
+//! Variants are packed in a compact binary layout: rsids as numbers,
+//! chromosomes as indexes into a table, positions as deltas, all as
+//! variable-length integers; the few structural variants go in the JSON
+//! header. The packed genome is gzip compressed and
+//! sealed with [`crypto::write_file`] like a session, under the device key,
+//! since it holds the whole genome.
+
+use crate::crypto::{self, Key, KeySource, Zeroizing};
+use crate::genome::{CallQuality, GenomeFile, Genotype, StructuralVariant, Variant};
+use crate::parser::ParseSummary;
+use crate::store::LoadedGenome;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Extension of cache entries
+pub const EXTENSION: &str = "gfcache";
+
+/// Version of what the parsers produce and of the packed layout; bump it
+/// when either changes so existing entries are rebuilt
+pub const PARSER_VERSION: u32 = 4;
+
+/// Entries kept; the least recently written are removed beyond this
+pub const MAX_ENTRIES: usize = 8;
+
+/// Kind recorded in the header of cache entries
+const KIND: &str = "genome-cache";
+
+/// Version of this crate, as a second guard against stale entries
+const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Readable description of a cache entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheInfo {
+    pub parser_version: u32,
+    pub core_version: String,
+    /// Hex SHA-256 of the source file
+    pub source_hash: String,
+    pub variant_count: usize,
+}
+
+impl CacheInfo {
+    /// Whether the entry was written by this parser for this file
+    fn is_current(&self, source_hash: &str) -> bool {
+        self.parser_version == PARSER_VERSION
+            && self.core_version == CORE_VERSION
+            && self.source_hash == source_hash
+    }
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    file: &'a GenomeFile,
+    summary: &'a ParseSummary,
+    structural: &'a [StructuralVariant],
+}
+
+#[derive(Deserialize)]
+struct OwnedHeader {
+    file: GenomeFile,
+    summary: ParseSummary,
+    structural: Vec<StructuralVariant>,
+}
+
+/// Parsed genomes saved in a directory
+pub struct GenomeCache {
+    dir: PathBuf,
+    key: Key,
+}
+
+impl GenomeCache {
+    /// A cache in `dir`, sealed with `key`; the directory is created on
+    /// the first write
+    pub fn new(dir: &Path, key: Key) -> Self {
+        GenomeCache {
+            dir: dir.to_path_buf(),
+            key,
+        }
+    }
+
+    /// File the entry for a source hash is saved to
+    pub fn entry_path(&self, source_hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", source_hash, EXTENSION))
+    }
+
+    /// The genome parsed from the file with this hash, if cached
+    ///
+    /// Stale, corrupt and undecryptable entries are removed and count as
+    /// a miss, so a cache problem never stops a file from loading.
+    pub fn get(&self, source_hash: &str) -> Result<Option<LoadedGenome>, String> {
+        if !valid_hash(source_hash) {
+            return Err(format!("Invalid file hash: {:?}", source_hash));
+        }
+        let path = self.entry_path(source_hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let genome = crypto::read_file::<CacheInfo>(&path, KIND, KeySource::Device(&self.key))
+            .ok()
+            .filter(|(envelope, _)| envelope.metadata.is_current(source_hash))
+            .and_then(|(_, packed)| unpack(&packed).ok());
+        if genome.is_none() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(genome)
+    }
+
+    /// Save the genome parsed from the file with this hash
+    pub fn put(&self, source_hash: &str, genome: &LoadedGenome) -> Result<PathBuf, String> {
+        if !valid_hash(source_hash) {
+            return Err(format!("Invalid file hash: {:?}", source_hash));
+        }
+        let info = CacheInfo {
+            parser_version: PARSER_VERSION,
+            core_version: CORE_VERSION.to_string(),
+            source_hash: source_hash.to_string(),
+            variant_count: genome.len(),
+        };
+        let packed = pack(genome)?;
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        let path = self.entry_path(source_hash);
+        crypto::write_file(&path, KIND, &info, &packed, KeySource::Device(&self.key))?;
+        self.prune(&path)?;
+        Ok(path)
+    }
+
+    /// Remove every entry, returning how many there were
+    pub fn clear(&self) -> Result<usize, String> {
+        let entries = self.entries()?;
+        for (path, _) in &entries {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(entries.len())
+    }
+
+    /// Entries with when they were written, most recent first
+    fn entries(&self) -> Result<Vec<(PathBuf, std::time::SystemTime)>, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("Failed to read {}: {}", self.dir.display(), e)),
+        };
+        let mut entries: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+            .filter_map(|path| {
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect();
+        entries.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+        Ok(entries)
+    }
+
+    /// Remove the oldest entries beyond [`MAX_ENTRIES`], never `keep`
+    fn prune(&self, keep: &Path) -> Result<(), String> {
+        let entries = self.entries()?;
+        let stale = entries
+            .iter()
+            .filter(|(path, _)| path != keep)
+            .skip(MAX_ENTRIES.saturating_sub(1));
+        for (path, _) in stale {
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+// Helper functions
+
+/// Also a safe file name
+fn valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Layout: header JSON length and JSON, chromosome table, variant count,
+/// then the variants
+fn pack(genome: &LoadedGenome) -> Result<Zeroizing<Vec<u8>>, String> {
+    let mut out = Zeroizing::new(Vec::with_capacity(genome.len() * 12));
+    let header = serde_json::to_vec(&Header {
+        file: &genome.file,
+        summary: &genome.summary,
+        structural: genome.structural_variants(),
+    })
+    .map_err(|e| format!("Failed to serialize genome: {}", e))?;
+    write_bytes(&mut out, &header);
+
+    let mut chromosomes: Vec<&str> = Vec::new();
+    for variant in genome.variants() {
+        if !chromosomes.contains(&variant.chromosome.as_str()) {
+            chromosomes.push(&variant.chromosome);
+        }
+    }
+    write_varint(&mut out, chromosomes.len() as u64);
+    for chromosome in &chromosomes {
+        write_bytes(&mut out, chromosome.as_bytes());
+    }
+
+    write_varint(&mut out, genome.len() as u64);
+    let mut previous = 0u64;
+    for variant in genome.variants() {
+        match variant.rsid.as_deref() {
+            None => write_varint(&mut out, 0),
+            Some(rsid) => match rsid.strip_prefix("rs").and_then(rs_number) {
+                Some(number) => {
+                    write_varint(&mut out, 1);
+                    write_varint(&mut out, number);
+                }
+                None => {
+                    write_varint(&mut out, 2);
+                    write_bytes(&mut out, rsid.as_bytes());
+                }
+            },
+        }
+        let chromosome = chromosomes
+            .iter()
+            .position(|c| *c == variant.chromosome)
+            .unwrap_or_default();
+        write_varint(&mut out, chromosome as u64);
+        write_varint(
+            &mut out,
+            zigzag(variant.position.wrapping_sub(previous) as i64),
+        );
+        previous = variant.position;
+        write_optional(&mut out, variant.reference.as_deref());
+        write_varint(&mut out, variant.alternates.len() as u64);
+        for alternate in &variant.alternates {
+            write_bytes(&mut out, alternate.as_bytes());
+        }
+        match &variant.genotype {
+            Genotype::NoCall => write_varint(&mut out, 0),
+            Genotype::Haploid(allele) => {
+                write_varint(&mut out, 1);
+                write_bytes(&mut out, allele.as_bytes());
+            }
+            Genotype::Diploid {
+                first,
+                second,
+                phased,
+            } => {
+                write_varint(&mut out, if *phased { 3 } else { 2 });
+                write_bytes(&mut out, first.as_bytes());
+                write_bytes(&mut out, second.as_bytes());
+            }
+        }
+        write_quality(&mut out, variant.quality.as_ref());
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    encoder
+        .write_all(&out)
+        .map_err(|e| format!("Failed to compress genome: {}", e))?;
+    Ok(Zeroizing::new(encoder.finish().map_err(|e| {
+        format!("Failed to compress genome: {}", e)
+    })?))
+}
+
+fn unpack(compressed: &[u8]) -> Result<LoadedGenome, String> {
+    let mut packed = Zeroizing::new(Vec::new());
+    GzDecoder::new(compressed)
+        .read_to_end(&mut packed)
+        .map_err(|_| corrupt())?;
+    let mut input = &packed[..];
+
+    let header: OwnedHeader =
+        serde_json::from_slice(read_bytes(&mut input)?).map_err(|_| corrupt())?;
+    let chromosomes = (0..read_varint(&mut input)?)
+        .map(|_| read_string(&mut input))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let count = read_varint(&mut input)? as usize;
+    let mut variants = Vec::with_capacity(count.min(packed.len()));
+    let mut previous = 0u64;
+    for _ in 0..count {
+        let rsid = match read_varint(&mut input)? {
+            0 => None,
+            1 => Some(format!("rs{}", read_varint(&mut input)?)),
+            2 => Some(read_string(&mut input)?),
+            _ => return Err(corrupt()),
+        };
+        let chromosome = chromosomes
+            .get(read_varint(&mut input)? as usize)
+            .ok_or_else(corrupt)?
+            .clone();
+        let position = previous.wrapping_add(unzigzag(read_varint(&mut input)?) as u64);
+        previous = position;
+        let reference = read_optional(&mut input)?;
+        let alternates = (0..read_varint(&mut input)?)
+            .map(|_| read_string(&mut input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let genotype = match read_varint(&mut input)? {
+            0 => Genotype::NoCall,
+            1 => Genotype::Haploid(read_string(&mut input)?),
+            tag @ (2 | 3) => Genotype::Diploid {
+                first: read_string(&mut input)?,
+                second: read_string(&mut input)?,
+                phased: tag == 3,
+            },
+            _ => return Err(corrupt()),
+        };
+        variants.push(Variant {
+            rsid,
+            chromosome,
+            position,
+            reference,
+            alternates,
+            genotype,
+            quality: read_quality(&mut input)?,
+        });
+    }
+    if !input.is_empty() {
+        return Err(corrupt());
+    }
+    Ok(
+        LoadedGenome::from_variants(header.file, header.summary, variants)
+            .with_structural_variants(header.structural),
+    )
+}
+
+fn corrupt() -> String {
+    "Genome cache entry is corrupt".to_string()
+}
+
+/// The number of "rs<digits>", if writing it back gives the same rsid
+fn rs_number(digits: &str) -> Option<u64> {
+    let number: u64 = digits.parse().ok()?;
+    (number.to_string() == digits).then_some(number)
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Length plus one, so that 0 is `None`
+fn write_optional(out: &mut Vec<u8>, value: Option<&str>) {
+    match value {
+        None => write_varint(out, 0),
+        Some(value) => {
+            write_varint(out, value.len() as u64 + 1);
+            out.extend_from_slice(value.as_bytes());
+        }
+    }
+}
+
+/// Bit 0 marks a quality as present, bit 1 an imputed call, and bits 2-6
+/// which of the scores follow as little-endian `f32`s. Bit 7 marks a depth
+/// following as a varint and bit 8 failed filters following as one
+/// `;`-separated string.
+fn write_quality(out: &mut Vec<u8>, quality: Option<&CallQuality>) {
+    let Some(quality) = quality else {
+        write_varint(out, 0);
+        return;
+    };
+    let scores = [
+        quality.info_score,
+        quality.probability,
+        quality.dosage,
+        quality.site_quality,
+        quality.genotype_quality,
+    ];
+    let mut flags = 1 | u64::from(quality.imputed) << 1;
+    for (bit, score) in scores.iter().enumerate() {
+        if score.is_some() {
+            flags |= 1 << (bit + 2);
+        }
+    }
+    if quality.depth.is_some() {
+        flags |= 1 << 7;
+    }
+    if !quality.filters.is_empty() {
+        flags |= 1 << 8;
+    }
+    write_varint(out, flags);
+    for score in scores.into_iter().flatten() {
+        out.extend_from_slice(&score.to_le_bytes());
+    }
+    if let Some(depth) = quality.depth {
+        write_varint(out, u64::from(depth));
+    }
+    if !quality.filters.is_empty() {
+        write_optional(out, Some(&quality.filters.join(";")));
+    }
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or_else(corrupt)?;
+        *input = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(corrupt())
+}
+
+fn read_slice<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if input.len() < len {
+        return Err(corrupt());
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8]) -> Result<&'a [u8], String> {
+    let len = read_varint(input)? as usize;
+    read_slice(input, len)
+}
+
+fn read_string(input: &mut &[u8]) -> Result<String, String> {
+    String::from_utf8(read_bytes(input)?.to_vec()).map_err(|_| corrupt())
+}
+
+fn read_quality(input: &mut &[u8]) -> Result<Option<CallQuality>, String> {
+    let flags = read_varint(input)?;
+    if flags == 0 {
+        return Ok(None);
+    }
+    let mut score = |bit: u32| -> Result<Option<f32>, String> {
+        if flags & (1 << bit) == 0 {
+            return Ok(None);
+        }
+        let bytes = read_slice(input, 4)?;
+        Ok(Some(f32::from_le_bytes(
+            bytes.try_into().map_err(|_| corrupt())?,
+        )))
+    };
+    let (info_score, probability, dosage) = (score(2)?, score(3)?, score(4)?);
+    let (site_quality, genotype_quality) = (score(5)?, score(6)?);
+    let depth = if flags & (1 << 7) != 0 {
+        Some(u32::try_from(read_varint(input)?).map_err(|_| corrupt())?)
+    } else {
+        None
+    };
+    let filters = if flags & (1 << 8) != 0 {
+        read_optional(input)?
+            .map(|filters| filters.split(';').map(str::to_string).collect())
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    Ok(Some(CallQuality {
+        imputed: flags & 2 != 0,
+        info_score,
+        probability,
+        dosage,
+        filters,
+        site_quality,
+        genotype_quality,
+        depth,
+    }))
+}
+
+fn read_optional(input: &mut &[u8]) -> Result<Option<String>, String> {
+    match read_varint(input)? as usize {
+        0 => Ok(None),
+        len => {
+            let bytes = read_slice(input, len - 1)?;
+            String::from_utf8(bytes.to_vec())
+                .map(Some)
+                .map_err(|_| corrupt())
+        }
+    }
+}
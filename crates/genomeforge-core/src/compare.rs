@@ -0,0 +1,171 @@
+//! Comparison of two genome files of one person
+//!
+//! People tested by more than one company can check the files agree. Sites
+//! are matched by rsid, which does not depend on the build or on the order
+//! of the alleles in a call, so a 23andMe file can be set against an
+//! AncestryDNA one. Where both files call a site, the calls should be the
+//! same; a few discordant calls are genotyping errors, a high rate on one
+//! chromosome points to a problem with that part of the array, and a high
+//! rate everywhere to files of two different people.
+
+use crate::genome::Genotype;
+use crate::parser::{chromosome_sort_key, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Discordant sites listed in full; the rest are only counted
+pub const MAX_LISTED_DISCORDANT: usize = 1_000;
+
+/// How two genome files compare
+#[derive(Debug, Clone, Serialize)]
+pub struct GenomeComparison {
+    pub first_variants: usize,
+    pub second_variants: usize,
+    /// rsids present in both files
+    pub shared_rsids: usize,
+    /// Shared rsids called in both files
+    pub compared: usize,
+    pub concordant: usize,
+    pub discordant: usize,
+    /// Shared rsids with a no-call in either file
+    pub no_calls: usize,
+    /// Fraction of compared sites with the same call (0.0 - 1.0)
+    pub concordance_rate: f64,
+    /// In karyotype order
+    pub by_chromosome: Vec<ChromosomeComparison>,
+    /// The first [`MAX_LISTED_DISCORDANT`] discordant sites
+    pub discordant_sites: Vec<Discordance>,
+}
+
+/// Agreement of the calls on one chromosome
+#[derive(Debug, Clone, Serialize)]
+pub struct ChromosomeComparison {
+    pub chromosome: String,
+    pub compared: usize,
+    pub discordant: usize,
+    /// Fraction of compared sites on the chromosome that differ (0.0 - 1.0)
+    pub discordance_rate: f64,
+}
+
+/// A site the two files call differently
+#[derive(Debug, Clone, Serialize)]
+pub struct Discordance {
+    pub rsid: String,
+    pub chromosome: String,
+    pub position: u64,
+    pub first: Genotype,
+    pub second: Genotype,
+}
+
+/// Compare every rsid of `first` with the same rsid in `second`
+///
+/// `checkpoint` is called with the number of variants of `first` compared
+/// every [`CHECKPOINT_INTERVAL`] variants and stops the comparison when it
+/// returns an error.
+pub fn compare<F>(
+    first: &LoadedGenome,
+    second: &LoadedGenome,
+    mut checkpoint: F,
+) -> Result<GenomeComparison, String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut shared_rsids = 0;
+    let mut no_calls = 0;
+    let mut discordant_sites = Vec::new();
+    // (compared, discordant) per chromosome
+    let mut chromosomes: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for (index, variant) in first.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        let Some(rsid) = variant.rsid.as_deref() else {
+            continue;
+        };
+        let Some(other) = second.get_by_rsid(rsid) else {
+            continue;
+        };
+        shared_rsids += 1;
+        if variant.genotype.is_no_call() || other.genotype.is_no_call() {
+            no_calls += 1;
+            continue;
+        }
+
+        let entry = chromosomes
+            .entry(normalize_chromosome(&variant.chromosome))
+            .or_default();
+        entry.0 += 1;
+        if same_call(&variant.genotype, &other.genotype) {
+            continue;
+        }
+        entry.1 += 1;
+        if discordant_sites.len() < MAX_LISTED_DISCORDANT {
+            discordant_sites.push(Discordance {
+                rsid: rsid.to_string(),
+                chromosome: variant.chromosome.clone(),
+                position: variant.position,
+                first: variant.genotype.clone(),
+                second: other.genotype.clone(),
+            });
+        }
+    }
+    checkpoint(first.len())?;
+
+    let mut by_chromosome: Vec<ChromosomeComparison> = chromosomes
+        .into_iter()
+        .map(
+            |(chromosome, (compared, discordant))| ChromosomeComparison {
+                chromosome,
+                compared,
+                discordant,
+                discordance_rate: rate(discordant, compared),
+            },
+        )
+        .collect();
+    by_chromosome.sort_by(|a, b| {
+        chromosome_sort_key(&a.chromosome).cmp(&chromosome_sort_key(&b.chromosome))
+    });
+    let compared: usize = by_chromosome.iter().map(|c| c.compared).sum();
+    let discordant: usize = by_chromosome.iter().map(|c| c.discordant).sum();
+    Ok(GenomeComparison {
+        first_variants: first.len(),
+        second_variants: second.len(),
+        shared_rsids,
+        compared,
+        concordant: compared - discordant,
+        discordant,
+        no_calls,
+        concordance_rate: rate(compared - discordant, compared),
+        by_chromosome,
+        discordant_sites,
+    })
+}
+
+/// Whether two calls name the same alleles, in either order
+///
+/// A single allele matches two copies of it, as some companies report male
+/// X and Y calls as homozygous.
+pub fn same_call(a: &Genotype, b: &Genotype) -> bool {
+    let mut a = a.alleles();
+    let mut b = b.alleles();
+    a.sort_unstable();
+    b.sort_unstable();
+    if a.len() != b.len() {
+        a.dedup();
+        b.dedup();
+        return a.len() == 1 && a == b;
+    }
+    a == b
+}
+
+// Helper functions
+
+fn rate(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
@@ -0,0 +1,308 @@
+//! Chromosome coverage and data completeness
+//!
+//! What a file covers decides which analyses it supports: arrays from some
+//! vendors leave out mitochondrial DNA, female samples have no Y calls, and
+//! only sequencing VCFs carry structural variants. The completeness report
+//! gives call rates per chromosome, how many sites each gene of a panel
+//! has, and which analyses the calls are too sparse for.
+//!
+//! Gene panels are limited to the genes the built-in coordinate table
+//! locates, and are only reported on GRCh37 and GRCh38.
+
+use crate::annotation::genes::{self, GeneLocation};
+use crate::annotation::{acmg, carrier};
+use crate::genome::GenomeBuild;
+use crate::parser::{call_rate, ChromosomeTally};
+use crate::sex::{MIN_X_SITES, MIN_Y_CALL_RATE, MIN_Y_SITES};
+use crate::store::LoadedGenome;
+use serde::{Deserialize, Serialize};
+
+/// Fewest called mitochondrial sites for a maternal haplogroup to be
+/// called from
+pub const MIN_MT_SITES: usize = 10;
+
+/// Chromosomes every human genome has, in karyotype order, less Y
+const EXPECTED_CHROMOSOMES: [&str; 24] = [
+    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16", "17",
+    "18", "19", "20", "21", "22", "X", "MT",
+];
+
+/// Pharmacogenes with CPIC guidelines and a built-in location
+const PHARMACOGENES: [&str; 9] = [
+    "CYP2C19", "CYP2C9", "CYP2D6", "DPYD", "G6PD", "RYR1", "SLCO1B1", "TPMT", "VKORC1",
+];
+
+/// Calls on one chromosome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChromosomeQuality {
+    pub chromosome: String,
+    pub variant_count: usize,
+    pub no_call_count: usize,
+    /// Fraction of variants with a genotype call (0.0 - 1.0)
+    pub call_rate: f64,
+    pub no_call_rate: f64,
+}
+
+/// A group of genes some analysis reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenePanel {
+    /// Genes with CPIC dosing guidelines
+    Pharmacogenes,
+    /// Genes of the ACMG secondary findings list
+    SecondaryFindings,
+    /// Genes screened for carrier status
+    CarrierScreening,
+}
+
+impl GenePanel {
+    pub const ALL: [GenePanel; 3] = [
+        GenePanel::Pharmacogenes,
+        GenePanel::SecondaryFindings,
+        GenePanel::CarrierScreening,
+    ];
+
+    /// Genes of the panel the built-in coordinate table locates
+    pub fn genes(self) -> Vec<&'static GeneLocation> {
+        genes::GENES
+            .iter()
+            .filter(|gene| match self {
+                GenePanel::Pharmacogenes => PHARMACOGENES.contains(&gene.symbol),
+                GenePanel::SecondaryFindings => acmg::gene(gene.symbol).is_some(),
+                GenePanel::CarrierScreening => carrier::gene(gene.symbol).is_some(),
+            })
+            .collect()
+    }
+}
+
+/// Sites a genome has in one gene
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneCoverage {
+    pub gene: String,
+    pub sites: usize,
+    pub called_sites: usize,
+}
+
+/// How well a genome covers the genes of a panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelCoverage {
+    pub panel: GenePanel,
+    pub genes: Vec<GeneCoverage>,
+}
+
+impl PanelCoverage {
+    /// Genes with at least one called site
+    pub fn genes_covered(&self) -> usize {
+        self.genes
+            .iter()
+            .filter(|gene| gene.called_sites > 0)
+            .count()
+    }
+}
+
+/// An analysis that needs calls a file may not have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Analysis {
+    /// From mitochondrial calls
+    MaternalHaplogroup,
+    /// From Y chromosome calls
+    PaternalHaplogroup,
+    /// From X heterozygosity and Y calls
+    GeneticSex,
+    /// From VCF structural variant records
+    StructuralVariants,
+}
+
+/// Why the calls do not support an analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unavailable {
+    /// The file has no sites on the chromosome the analysis reads
+    NoSites,
+    /// Too few of the sites are called
+    TooFewCalls,
+    /// Most sites are no-calls, as an array reports the Y chromosome of a
+    /// female sample
+    LowCallRate,
+    /// The file has no structural variant records
+    NoStructuralRecords,
+}
+
+/// Whether a genome's calls support an analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisAvailability {
+    pub analysis: Analysis,
+    /// Set when the analysis is unavailable
+    pub reason: Option<Unavailable>,
+}
+
+impl AnalysisAvailability {
+    pub fn is_available(&self) -> bool {
+        self.reason.is_none()
+    }
+}
+
+/// Completeness of a genome's calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataQuality {
+    pub variant_count: usize,
+    pub no_call_count: usize,
+    pub call_rate: f64,
+    pub no_call_rate: f64,
+    /// In karyotype order
+    pub chromosomes: Vec<ChromosomeQuality>,
+    /// Chromosomes of every genome without a single call
+    pub missing_chromosomes: Vec<String>,
+    /// Empty when the build is unknown or GRCh36
+    pub panels: Vec<PanelCoverage>,
+    pub analyses: Vec<AnalysisAvailability>,
+}
+
+/// The completeness report of a genome
+pub fn report(genome: &LoadedGenome) -> DataQuality {
+    let mut tally = ChromosomeTally::default();
+    for variant in genome.variants() {
+        tally.add(&variant.chromosome, !variant.genotype.is_no_call());
+    }
+    let (variant_count, no_call_count) = (tally.total(), tally.no_calls());
+    let chromosomes: Vec<ChromosomeQuality> = tally
+        .into_counts()
+        .into_iter()
+        .map(|count| ChromosomeQuality {
+            no_call_rate: no_call_rate(count.variant_count, count.no_call_count),
+            chromosome: count.chromosome,
+            variant_count: count.variant_count,
+            no_call_count: count.no_call_count,
+            call_rate: count.call_rate,
+        })
+        .collect();
+    let called = |chromosome: &str| {
+        chromosomes
+            .iter()
+            .find(|quality| quality.chromosome == chromosome)
+            .map_or(0, |quality| quality.variant_count - quality.no_call_count)
+    };
+    let missing_chromosomes = EXPECTED_CHROMOSOMES
+        .iter()
+        .filter(|chromosome| called(chromosome) == 0)
+        .map(|chromosome| chromosome.to_string())
+        .collect();
+    let analyses = availability(genome, &chromosomes);
+
+    DataQuality {
+        variant_count,
+        no_call_count,
+        call_rate: call_rate(variant_count, no_call_count),
+        no_call_rate: no_call_rate(variant_count, no_call_count),
+        missing_chromosomes,
+        panels: panel_coverage(genome),
+        analyses,
+        chromosomes,
+    }
+}
+
+// Helper functions
+
+fn no_call_rate(variant_count: usize, no_call_count: usize) -> f64 {
+    if variant_count == 0 {
+        0.0
+    } else {
+        no_call_count as f64 / variant_count as f64
+    }
+}
+
+fn availability(
+    genome: &LoadedGenome,
+    chromosomes: &[ChromosomeQuality],
+) -> Vec<AnalysisAvailability> {
+    let counts = |chromosome: &str| {
+        chromosomes
+            .iter()
+            .find(|quality| quality.chromosome == chromosome)
+            .map_or((0, 0), |quality| {
+                (
+                    quality.variant_count,
+                    quality.variant_count - quality.no_call_count,
+                )
+            })
+    };
+    let (mt_sites, mt_called) = counts("MT");
+    let (y_sites, y_called) = counts("Y");
+    let (_, x_called) = counts("X");
+
+    let maternal = match (mt_sites, mt_called) {
+        (0, _) => Some(Unavailable::NoSites),
+        (_, called) if called < MIN_MT_SITES => Some(Unavailable::TooFewCalls),
+        _ => None,
+    };
+    let paternal = if y_sites == 0 {
+        Some(Unavailable::NoSites)
+    } else if y_sites < MIN_Y_SITES {
+        Some(Unavailable::TooFewCalls)
+    } else if (y_called as f64) < y_sites as f64 * MIN_Y_CALL_RATE {
+        Some(Unavailable::LowCallRate)
+    } else {
+        None
+    };
+    let sex = if x_called == 0 && y_sites == 0 {
+        Some(Unavailable::NoSites)
+    } else if x_called < MIN_X_SITES && y_sites < MIN_Y_SITES {
+        Some(Unavailable::TooFewCalls)
+    } else {
+        None
+    };
+    let structural = genome
+        .structural_variants()
+        .is_empty()
+        .then_some(Unavailable::NoStructuralRecords);
+
+    [
+        (Analysis::MaternalHaplogroup, maternal),
+        (Analysis::PaternalHaplogroup, paternal),
+        (Analysis::GeneticSex, sex),
+        (Analysis::StructuralVariants, structural),
+    ]
+    .into_iter()
+    .map(|(analysis, reason)| AnalysisAvailability { analysis, reason })
+    .collect()
+}
+
+fn panel_coverage(genome: &LoadedGenome) -> Vec<PanelCoverage> {
+    let Some(build) = genome
+        .file
+        .genome_build
+        .filter(|build| *build != GenomeBuild::GRCh36)
+    else {
+        return Vec::new();
+    };
+    GenePanel::ALL
+        .into_iter()
+        .map(|panel| {
+            let genes = panel.genes();
+            let regions: Vec<_> = genes.iter().filter_map(|gene| gene.region(build)).collect();
+            let mut coverage: Vec<GeneCoverage> = genes
+                .iter()
+                .map(|gene| GeneCoverage {
+                    gene: gene.symbol.to_string(),
+                    sites: 0,
+                    called_sites: 0,
+                })
+                .collect();
+            for variant in genome.variants() {
+                for (region, gene) in regions.iter().zip(&mut coverage) {
+                    if region.chromosome == variant.chromosome
+                        && (region.start..=region.end).contains(&variant.position)
+                    {
+                        gene.sites += 1;
+                        gene.called_sites += usize::from(!variant.genotype.is_no_call());
+                    }
+                }
+            }
+            PanelCoverage {
+                panel,
+                genes: coverage,
+            }
+        })
+        .collect()
+}
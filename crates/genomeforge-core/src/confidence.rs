@@ -0,0 +1,225 @@
+//! Confidence in a finding
+//!
+//! Every finding carries one score from 0.0 to 1.0 so that findings from
+//! different sources rank on the same scale. The score is the strength of
+//! the evidence behind the finding, from ClinVar review stars, a PharmGKB
+//! level or GWAS significance, times the reliability of the calls it rests
+//! on. Imputed calls, borderline sequencing calls, probes known to call
+//! badly on the file's chip and A/T or C/G SNPs of unknown strand each
+//! lower the reliability.
+
+use crate::annotation::apoe::ApoeCall;
+use crate::annotation::clingen::DosageScore;
+use crate::annotation::cpic::DiplotypeCall;
+use crate::annotation::hla::HlaEvidence;
+use crate::annotation::nutrigenomics::NutritionEvidence;
+use crate::annotation::pharmgkb::EvidenceLevel;
+use crate::annotation::strand::Strand;
+use crate::genome::Variant;
+use crate::quality::{self, QualityIssue};
+use serde::{Deserialize, Serialize};
+
+/// Scores from this on are [`ConfidenceLevel::High`]
+pub const HIGH_CONFIDENCE: f64 = 0.7;
+
+/// Scores from this on are [`ConfidenceLevel::Moderate`]
+pub const MODERATE_CONFIDENCE: f64 = 0.4;
+
+/// Evidence strength of 0 to 4 ClinVar review stars
+const CLINVAR_STARS: [f64; 5] = [0.2, 0.5, 0.75, 0.9, 1.0];
+
+/// Reliability left of an imputed call
+const IMPUTED: f64 = 0.8;
+
+/// Reliability left of a call on a probe flagged as unreliable
+const UNRELIABLE_PROBE: f64 = 0.5;
+
+/// Reliability left of a call that failed a FILTER
+const FAILED_FILTER: f64 = 0.6;
+
+/// Reliability left of a call with a low QUAL, GQ or depth, for each
+const LOW_QUALITY: f64 = 0.8;
+
+/// Reliability left of a call at an A/T or C/G SNP of unknown strand, or
+/// of a diplotype other diplotypes fit as well
+const AMBIGUOUS: f64 = 0.8;
+
+/// What a finding's evidence is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Evidence {
+    /// ClinVar review stars (0-4)
+    ClinVar(u8),
+    PharmGkb(EvidenceLevel),
+    /// The strength of a GWAS association, as
+    /// [`GwasAssociation::confidence`](crate::annotation::gwas::GwasAssociation::confidence)
+    /// gives it
+    Gwas(f64),
+    /// ClinGen dosage sensitivity of a deleted or duplicated gene
+    Dosage(DosageScore),
+    Nutrition(NutritionEvidence),
+    Hla(HlaEvidence),
+    /// An effect a published guideline or consensus rests on, such as a
+    /// CPIC recommendation or the APOE risk alleles
+    Established,
+    /// The strength an analysis plugin gives its own finding
+    Plugin(f64),
+    /// The strength a user-supplied annotation table gives an entry
+    Custom(f64),
+}
+
+impl Evidence {
+    /// Strength of the evidence, 0.0 - 1.0
+    pub fn strength(self) -> f64 {
+        match self {
+            Evidence::ClinVar(stars) => CLINVAR_STARS[usize::from(stars.min(4))],
+            Evidence::PharmGkb(level) => match level {
+                EvidenceLevel::Level1A => 1.0,
+                EvidenceLevel::Level1B => 0.9,
+                EvidenceLevel::Level2A => 0.75,
+                EvidenceLevel::Level2B => 0.6,
+                EvidenceLevel::Level3 => 0.35,
+                EvidenceLevel::Level4 => 0.15,
+            },
+            Evidence::Gwas(confidence) => confidence.clamp(0.0, 1.0),
+            Evidence::Dosage(score) => match score {
+                DosageScore::Sufficient | DosageScore::AutosomalRecessive => 1.0,
+                DosageScore::Emerging => 0.6,
+                DosageScore::Little => 0.3,
+                DosageScore::NoEvidence | DosageScore::Unlikely => 0.1,
+            },
+            Evidence::Nutrition(evidence) => match evidence {
+                NutritionEvidence::Established => 0.9,
+                NutritionEvidence::Moderate => 0.6,
+                NutritionEvidence::Limited => 0.3,
+            },
+            Evidence::Hla(HlaEvidence::Typed) => 1.0,
+            Evidence::Hla(HlaEvidence::Proxy) => 0.7,
+            Evidence::Established => 1.0,
+            Evidence::Plugin(strength) | Evidence::Custom(strength) => strength.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Banded confidence, for display
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceLevel {
+    #[default]
+    Low,
+    Moderate,
+    High,
+}
+
+/// How far a finding can be relied on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredConfidence")]
+pub struct Confidence {
+    /// `evidence` times `call`, 0.0 - 1.0
+    pub score: f64,
+    /// Strength of the evidence behind the finding, 0.0 - 1.0
+    pub evidence: f64,
+    /// Reliability of the calls the finding rests on, 0.0 - 1.0
+    pub call: f64,
+    pub level: ConfidenceLevel,
+}
+
+impl Confidence {
+    /// The confidence of evidence read from calls of the given reliability
+    pub fn new(evidence: Evidence, call: f64) -> Self {
+        Self::from_parts(evidence.strength(), call)
+    }
+
+    fn from_parts(evidence: f64, call: f64) -> Self {
+        let score = evidence * call.clamp(0.0, 1.0);
+        let level = if score >= HIGH_CONFIDENCE {
+            ConfidenceLevel::High
+        } else if score >= MODERATE_CONFIDENCE {
+            ConfidenceLevel::Moderate
+        } else {
+            ConfidenceLevel::Low
+        };
+        Confidence {
+            score,
+            evidence,
+            call: call.clamp(0.0, 1.0),
+            level,
+        }
+    }
+}
+
+/// Reliability of a call from what is known against it
+pub fn call_reliability(imputed: bool, issues: &[QualityIssue], strand: Strand) -> f64 {
+    let mut reliability = if imputed { IMPUTED } else { 1.0 };
+    for issue in issues {
+        reliability *= match issue {
+            QualityIssue::UnreliableProbe => UNRELIABLE_PROBE,
+            QualityIssue::FailedFilter => FAILED_FILTER,
+            QualityIssue::LowSiteQuality
+            | QualityIssue::LowGenotypeQuality
+            | QualityIssue::LowDepth => LOW_QUALITY,
+        };
+    }
+    if strand.is_ambiguous() {
+        reliability *= AMBIGUOUS;
+    }
+    reliability
+}
+
+/// Reliability of a variant's call read on the forward strand
+pub fn variant_reliability(variant: &Variant) -> f64 {
+    call_reliability(
+        variant.is_imputed(),
+        &quality::borderline(variant),
+        Strand::Forward,
+    )
+}
+
+/// Confidence in a diplotype call: CPIC's allele definitions, read from
+/// the share of the gene's sites genotyped
+pub fn diplotype(call: &DiplotypeCall) -> Confidence {
+    let mut reliability = if call.sites_total == 0 {
+        0.0
+    } else {
+        call.sites_genotyped as f64 / call.sites_total as f64
+    };
+    if call.sites_imputed > 0 {
+        reliability *= IMPUTED;
+    }
+    if !call.alternatives.is_empty() {
+        reliability *= AMBIGUOUS;
+    }
+    Confidence::new(Evidence::Established, reliability)
+}
+
+/// Confidence in an APOE diplotype, lower when the phase of the two SNPs
+/// leaves another diplotype possible
+pub fn apoe(call: &ApoeCall) -> Confidence {
+    let reliability = if call.alternative.is_some() {
+        AMBIGUOUS
+    } else {
+        1.0
+    };
+    Confidence::new(Evidence::Established, reliability)
+}
+
+// Helper functions
+
+/// A confidence as stored, including results from before it was more than
+/// the GWAS score of traits
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredConfidence {
+    Full { evidence: f64, call: f64 },
+    Score(f64),
+}
+
+impl From<StoredConfidence> for Confidence {
+    fn from(stored: StoredConfidence) -> Self {
+        match stored {
+            StoredConfidence::Full { evidence, call } => Confidence::from_parts(evidence, call),
+            StoredConfidence::Score(score) => Confidence::from_parts(score, 1.0),
+        }
+    }
+}
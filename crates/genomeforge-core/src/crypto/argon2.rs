@@ -0,0 +1,467 @@
+//! Argon2id password hashing (RFC 9106) and the BLAKE2b hash it is built on
+//!
+//! Lanes are filled one after another rather than on separate threads;
+//! the result is the same, and key derivation happens rarely enough that
+//! the extra time does not matter.
+
+use zeroize::Zeroize;
+
+/// Argon2 version 1.3
+const VERSION: u32 = 0x13;
+
+/// Argon2 type number of Argon2id
+const ARGON2ID: u32 = 2;
+
+/// Slices a pass over a lane is split into
+const SYNC_POINTS: usize = 4;
+
+/// 64-bit words in a 1 KiB block
+const BLOCK_WORDS: usize = 128;
+
+/// Length of a BLAKE2b-512 digest
+const BLAKE2B_OUT: usize = 64;
+
+type Block = [u64; BLOCK_WORDS];
+
+/// Cost settings, as stored alongside a salt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    /// Memory in KiB, which is also the number of blocks
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Argon2id tag of `output.len()` bytes
+///
+/// `secret` and `associated` are the optional key and associated data
+/// inputs of RFC 9106; both may be empty.
+pub fn hash(
+    params: Params,
+    password: &[u8],
+    salt: &[u8],
+    secret: &[u8],
+    associated: &[u8],
+    output: &mut [u8],
+) -> Result<(), String> {
+    let lanes = params.parallelism as usize;
+    if lanes == 0 || params.iterations == 0 || salt.len() < 8 || output.len() < 4 {
+        return Err("Invalid key derivation settings".to_string());
+    }
+    // Whole segments in each lane, and at least two blocks per segment
+    let blocks = (params.memory_kib as usize).max(2 * SYNC_POINTS * lanes);
+    let blocks = blocks - blocks % (SYNC_POINTS * lanes);
+    let lane_length = blocks / lanes;
+    let segment_length = lane_length / SYNC_POINTS;
+
+    let mut h0 = Blake2b::new(BLAKE2B_OUT);
+    for value in [
+        params.parallelism,
+        output.len() as u32,
+        params.memory_kib,
+        params.iterations,
+        VERSION,
+        ARGON2ID,
+    ] {
+        h0.update(&value.to_le_bytes());
+    }
+    for input in [password, salt, secret, associated] {
+        h0.update(&(input.len() as u32).to_le_bytes());
+        h0.update(input);
+    }
+    let mut seed = [0u8; BLAKE2B_OUT + 8];
+    h0.finalize_into(&mut seed[..BLAKE2B_OUT]);
+
+    let mut memory = Memory {
+        blocks: vec![[0u64; BLOCK_WORDS]; blocks],
+        lanes,
+        lane_length,
+        segment_length,
+        total_blocks: blocks,
+        iterations: params.iterations as usize,
+    };
+    let mut bytes = [0u8; BLOCK_WORDS * 8];
+    for lane in 0..lanes {
+        for column in 0..2 {
+            seed[BLAKE2B_OUT..BLAKE2B_OUT + 4].copy_from_slice(&(column as u32).to_le_bytes());
+            seed[BLAKE2B_OUT + 4..].copy_from_slice(&(lane as u32).to_le_bytes());
+            variable_hash(&seed, &mut bytes);
+            let block = &mut memory.blocks[lane * lane_length + column];
+            for (word, chunk) in block.iter_mut().zip(bytes.chunks_exact(8)) {
+                *word = u64::from_le_bytes(chunk.try_into().unwrap());
+            }
+        }
+    }
+    seed.zeroize();
+
+    for pass in 0..memory.iterations {
+        for slice in 0..SYNC_POINTS {
+            for lane in 0..lanes {
+                memory.fill_segment(pass, lane, slice);
+            }
+        }
+    }
+
+    let mut last = memory.blocks[lane_length - 1];
+    for lane in 1..lanes {
+        let block = &memory.blocks[lane * lane_length + lane_length - 1];
+        for (word, other) in last.iter_mut().zip(block) {
+            *word ^= other;
+        }
+    }
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip(&last) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    variable_hash(&bytes, output);
+
+    last.zeroize();
+    bytes.zeroize();
+    for block in &mut memory.blocks {
+        block.zeroize();
+    }
+    Ok(())
+}
+
+struct Memory {
+    blocks: Vec<Block>,
+    lanes: usize,
+    lane_length: usize,
+    segment_length: usize,
+    total_blocks: usize,
+    iterations: usize,
+}
+
+impl Memory {
+    fn fill_segment(&mut self, pass: usize, lane: usize, slice: usize) {
+        // Argon2id addresses independently of the data for the first half
+        // of the first pass, resisting side channels, and from the data
+        // after that, resisting trade-off attacks
+        let independent = pass == 0 && slice < SYNC_POINTS / 2;
+        let zero = [0u64; BLOCK_WORDS];
+        let mut input = [0u64; BLOCK_WORDS];
+        let mut addresses = [0u64; BLOCK_WORDS];
+        if independent {
+            input[..6].copy_from_slice(&[
+                pass as u64,
+                lane as u64,
+                slice as u64,
+                self.total_blocks as u64,
+                self.iterations as u64,
+                ARGON2ID as u64,
+            ]);
+        }
+
+        let start = if pass == 0 && slice == 0 {
+            if independent {
+                next_addresses(&mut addresses, &mut input, &zero);
+            }
+            2
+        } else {
+            0
+        };
+        let lane_start = lane * self.lane_length;
+        for index in start..self.segment_length {
+            let column = slice * self.segment_length + index;
+            let current = lane_start + column;
+            let previous = if column == 0 {
+                lane_start + self.lane_length - 1
+            } else {
+                current - 1
+            };
+
+            let pseudo_random = if independent {
+                if index % BLOCK_WORDS == 0 {
+                    next_addresses(&mut addresses, &mut input, &zero);
+                }
+                addresses[index % BLOCK_WORDS]
+            } else {
+                self.blocks[previous][0]
+            };
+            let reference_lane = if pass == 0 && slice == 0 {
+                lane
+            } else {
+                (pseudo_random >> 32) as usize % self.lanes
+            };
+            let reference = reference_lane * self.lane_length
+                + self.reference_index(
+                    pass,
+                    slice,
+                    index,
+                    pseudo_random as u32,
+                    reference_lane == lane,
+                );
+
+            let next = compress(&self.blocks[previous], &self.blocks[reference]);
+            let block = &mut self.blocks[current];
+            if pass == 0 {
+                *block = next;
+            } else {
+                for (word, new) in block.iter_mut().zip(next) {
+                    *word ^= new;
+                }
+            }
+        }
+    }
+
+    /// Column of the block a new block is mixed with (RFC 9106, 3.4.2)
+    fn reference_index(
+        &self,
+        pass: usize,
+        slice: usize,
+        index: usize,
+        pseudo_random: u32,
+        same_lane: bool,
+    ) -> usize {
+        let finished = if pass == 0 {
+            slice * self.segment_length
+        } else {
+            self.lane_length - self.segment_length
+        };
+        // Blocks of other lanes in the current segment are not ready, and
+        // the block being replaced cannot be used
+        let area = if same_lane {
+            finished + index - 1
+        } else if index == 0 {
+            finished - 1
+        } else {
+            finished
+        } as u64;
+
+        let x = (pseudo_random as u64 * pseudo_random as u64) >> 32;
+        let relative = area - 1 - ((area * x) >> 32);
+        let start = if pass == 0 || slice == SYNC_POINTS - 1 {
+            0
+        } else {
+            (slice + 1) * self.segment_length
+        };
+        (start + relative as usize) % self.lane_length
+    }
+}
+
+fn next_addresses(addresses: &mut Block, input: &mut Block, zero: &Block) {
+    input[6] += 1;
+    *addresses = compress(zero, &compress(zero, input));
+}
+
+/// The compression function G of RFC 9106
+fn compress(x: &Block, y: &Block) -> Block {
+    let mut r = [0u64; BLOCK_WORDS];
+    for (word, (a, b)) in r.iter_mut().zip(x.iter().zip(y)) {
+        *word = a ^ b;
+    }
+    let mut q = r;
+    for row in 0..8 {
+        let mut indices = [0usize; 16];
+        for (i, index) in indices.iter_mut().enumerate() {
+            *index = row * 16 + i;
+        }
+        permute(&mut q, &indices);
+    }
+    for column in 0..8 {
+        let mut indices = [0usize; 16];
+        for (i, index) in indices.iter_mut().enumerate() {
+            *index = (i / 2) * 16 + column * 2 + i % 2;
+        }
+        permute(&mut q, &indices);
+    }
+    for (word, original) in q.iter_mut().zip(r) {
+        *word ^= original;
+    }
+    q
+}
+
+/// The permutation P, applied to the sixteen words at `indices`
+fn permute(block: &mut Block, indices: &[usize; 16]) {
+    let mut v = [0u64; 16];
+    for (word, &index) in v.iter_mut().zip(indices) {
+        *word = block[index];
+    }
+    for [a, b, c, d] in [
+        [0, 4, 8, 12],
+        [1, 5, 9, 13],
+        [2, 6, 10, 14],
+        [3, 7, 11, 15],
+        [0, 5, 10, 15],
+        [1, 6, 11, 12],
+        [2, 7, 8, 13],
+        [3, 4, 9, 14],
+    ] {
+        mix(&mut v, a, b, c, d);
+    }
+    for (word, &index) in v.iter().zip(indices) {
+        block[index] = *word;
+    }
+}
+
+/// BLAKE2b's G with the multiplications Argon2 adds
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize) {
+    fn add(x: u64, y: u64) -> u64 {
+        x.wrapping_add(y).wrapping_add(
+            2u64.wrapping_mul(x & 0xffff_ffff)
+                .wrapping_mul(y & 0xffff_ffff),
+        )
+    }
+    v[a] = add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = add(v[a], v[b]);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = add(v[c], v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// The variable-length hash H' of RFC 9106
+fn variable_hash(input: &[u8], output: &mut [u8]) {
+    let length = (output.len() as u32).to_le_bytes();
+    if output.len() <= BLAKE2B_OUT {
+        let mut hasher = Blake2b::new(output.len());
+        hasher.update(&length);
+        hasher.update(input);
+        hasher.finalize_into(output);
+        return;
+    }
+
+    let mut v = [0u8; BLAKE2B_OUT];
+    let mut hasher = Blake2b::new(BLAKE2B_OUT);
+    hasher.update(&length);
+    hasher.update(input);
+    hasher.finalize_into(&mut v);
+    let mut written = 0;
+    // Half of each intermediate digest is output until the last fits whole
+    while output.len() - written > BLAKE2B_OUT {
+        output[written..written + 32].copy_from_slice(&v[..32]);
+        written += 32;
+        let mut hasher = Blake2b::new(BLAKE2B_OUT.min(output.len() - written));
+        hasher.update(&v);
+        let length = hasher.output_len;
+        hasher.finalize_into(&mut v[..length]);
+    }
+    let remaining = output.len() - written;
+    output[written..].copy_from_slice(&v[..remaining]);
+    v.zeroize();
+}
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Unkeyed BLAKE2b (RFC 7693) with an output of 1 to 64 bytes
+struct Blake2b {
+    h: [u64; 8],
+    buffer: [u8; 128],
+    buffered: usize,
+    /// Bytes compressed so far
+    counter: u128,
+    output_len: usize,
+}
+
+impl Blake2b {
+    fn new(output_len: usize) -> Self {
+        let mut h = IV;
+        h[0] ^= 0x0101_0000 ^ output_len as u64;
+        Blake2b {
+            h,
+            buffer: [0; 128],
+            buffered: 0,
+            counter: 0,
+            output_len,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            // The last block is compressed differently, so a full buffer
+            // waits until more data arrives
+            if self.buffered == self.buffer.len() {
+                self.counter += self.buffer.len() as u128;
+                self.compress(false);
+                self.buffered = 0;
+            }
+            let count = data.len().min(self.buffer.len() - self.buffered);
+            self.buffer[self.buffered..self.buffered + count].copy_from_slice(&data[..count]);
+            self.buffered += count;
+            data = &data[count..];
+        }
+    }
+
+    fn finalize_into(mut self, output: &mut [u8]) {
+        self.counter += self.buffered as u128;
+        self.buffer[self.buffered..].fill(0);
+        self.compress(true);
+        let mut bytes = [0u8; BLAKE2B_OUT];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(self.h) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        output.copy_from_slice(&bytes[..self.output_len]);
+        bytes.zeroize();
+        self.h.zeroize();
+        self.buffer.zeroize();
+    }
+
+    fn compress(&mut self, last: bool) {
+        let mut m = [0u64; 16];
+        for (word, chunk) in m.iter_mut().zip(self.buffer.chunks_exact(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        let mut v = [0u64; 16];
+        v[..8].copy_from_slice(&self.h);
+        v[8..].copy_from_slice(&IV);
+        v[12] ^= self.counter as u64;
+        v[13] ^= (self.counter >> 64) as u64;
+        if last {
+            v[14] = !v[14];
+        }
+
+        for round in 0..12 {
+            let s = &SIGMA[round % 10];
+            for (i, [a, b, c, d]) in [
+                [0, 4, 8, 12],
+                [1, 5, 9, 13],
+                [2, 6, 10, 14],
+                [3, 7, 11, 15],
+                [0, 5, 10, 15],
+                [1, 6, 11, 12],
+                [2, 7, 8, 13],
+                [3, 4, 9, 14],
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[s[2 * i]]);
+                v[d] = (v[d] ^ v[a]).rotate_right(32);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(24);
+                v[a] = v[a].wrapping_add(v[b]).wrapping_add(m[s[2 * i + 1]]);
+                v[d] = (v[d] ^ v[a]).rotate_right(16);
+                v[c] = v[c].wrapping_add(v[d]);
+                v[b] = (v[b] ^ v[c]).rotate_right(63);
+            }
+        }
+        for i in 0..8 {
+            self.h[i] ^= v[i] ^ v[i + 8];
+        }
+        m.zeroize();
+        v.zeroize();
+    }
+}
@@ -0,0 +1,338 @@
+//! Authenticated encryption of everything the applications write to disk
+//!
+//! Data is sealed with AES-256-GCM under a fresh random nonce, which is
+//! stored in front of the ciphertext. Keys come either from a passphrase,
+//! stretched with Argon2id and a random salt, or from a random device key
+//! that the platform keeps safe, such as one protected with DPAPI. Keys
+//! and the intermediate values of key derivation are wiped from memory
+//! once dropped.
+//!
+//! Files are written with [`write_file`]: a short plaintext header says
+//! what the file holds and how its key is obtained, and is authenticated
+//! along with the sealed contents, so neither can be altered unnoticed.
+//!
+//! Layout: [`MAGIC`], header length (u32, little-endian), header JSON,
+//! sealed contents.
+
+pub mod argon2;
+
+use aes_gcm::aead::{Aead, AeadCore, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use zeroize::Zeroize;
+
+pub use zeroize::Zeroizing;
+
+/// Length of an AES-256 key
+pub const KEY_LEN: usize = 32;
+
+/// Length of the salt a passphrase is stretched with
+pub const SALT_LEN: usize = 16;
+
+/// Length of an AES-GCM nonce
+pub const NONCE_LEN: usize = 12;
+
+/// First bytes of an encrypted file, ending in the format version
+pub const MAGIC: [u8; 8] = *b"GFCRYPT\x01";
+
+/// Largest header accepted, well above any real one
+const MAX_HEADER_LEN: usize = 64 * 1024;
+
+/// Argon2id cost settings, stored with each salt so they can be raised
+/// without breaking existing files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// Highest costs a file may ask for: its header is read before it can
+    /// be authenticated, and Argon2 sets aside all of its memory up front
+    pub const MAX: KdfParams = KdfParams {
+        memory_kib: 1024 * 1024,
+        iterations: 10,
+        parallelism: 16,
+    };
+
+    /// Whether deriving a key at these costs is sound and affordable
+    ///
+    /// Files asking for more than [`KdfParams::MAX`] are refused, even ones
+    /// that opened before the maxima were set; the app only writes
+    /// [`KdfParams::default`].
+    pub fn check(&self) -> Result<(), String> {
+        let max = KdfParams::MAX;
+        if self.parallelism == 0 || self.iterations == 0 {
+            return Err("Invalid key derivation settings".to_string());
+        }
+        if self.memory_kib > max.memory_kib
+            || self.iterations > max.iterations
+            || self.parallelism > max.parallelism
+        {
+            return Err("Key derivation settings exceed the supported maximum".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    /// OWASP's recommended minimum: 19 MiB, two passes, one lane
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// A 256-bit encryption key, zeroed when dropped
+#[derive(Clone)]
+pub struct Key([u8; KEY_LEN]);
+
+impl Key {
+    pub fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Key(bytes)
+    }
+
+    /// A new key from the operating system's random number generator
+    pub fn generate() -> Self {
+        Key(random_bytes())
+    }
+
+    /// Stretch a passphrase with Argon2id
+    pub fn derive(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<Self, String> {
+        let mut key = Key([0; KEY_LEN]);
+        let params = argon2::Params {
+            memory_kib: params.memory_kib,
+            iterations: params.iterations,
+            parallelism: params.parallelism,
+        };
+        argon2::hash(params, passphrase.as_bytes(), salt, &[], &[], &mut key.0)?;
+        Ok(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+}
+
+impl Drop for Key {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+/// How the key of encrypted data is obtained
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Protection {
+    /// Derived from a passphrase with this hex-encoded salt
+    Passphrase { salt: String, kdf: KdfParams },
+    /// A key kept by the operating system for this user, such as one
+    /// protected with DPAPI
+    DeviceKey,
+}
+
+/// What data is encrypted or decrypted with
+#[derive(Debug, Clone, Copy)]
+pub enum KeySource<'a> {
+    Passphrase(&'a str),
+    Device(&'a Key),
+}
+
+impl KeySource<'_> {
+    /// A key for new data, with a fresh salt for a passphrase
+    pub fn new_key(self) -> Result<(Protection, Key), String> {
+        match self {
+            KeySource::Passphrase(passphrase) => {
+                let salt: [u8; SALT_LEN] = random_bytes();
+                let kdf = KdfParams::default();
+                let key = Key::derive(passphrase, &salt, kdf)?;
+                let salt = hex::encode(salt);
+                Ok((Protection::Passphrase { salt, kdf }, key))
+            }
+            KeySource::Device(key) => Ok((Protection::DeviceKey, key.clone())),
+        }
+    }
+
+    /// The key data protected as described was encrypted with
+    pub fn unlock(self, protection: &Protection) -> Result<Key, String> {
+        match (protection, self) {
+            (Protection::Passphrase { salt, kdf }, KeySource::Passphrase(passphrase)) => {
+                kdf.check()?;
+                let salt = hex::decode(salt).map_err(|_| "Salt is corrupt".to_string())?;
+                Key::derive(passphrase, &salt, *kdf)
+            }
+            (Protection::DeviceKey, KeySource::Device(key)) => Ok(key.clone()),
+            (Protection::Passphrase { .. }, KeySource::Device(_)) => {
+                Err("This file is protected with a passphrase".to_string())
+            }
+            (Protection::DeviceKey, KeySource::Passphrase(_)) => {
+                Err("This file is protected with this device's key".to_string())
+            }
+        }
+    }
+}
+
+/// Plaintext header of an encrypted file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<M> {
+    /// What the file holds, e.g. "session"
+    pub kind: String,
+    pub protection: Protection,
+    pub metadata: M,
+}
+
+/// Random bytes from the operating system, e.g. for a salt
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    aes_gcm::aead::rand_core::RngCore::fill_bytes(&mut OsRng, &mut bytes);
+    bytes
+}
+
+/// Encrypt `plaintext`; `associated` is authenticated but not encrypted
+pub fn seal(key: &Key, plaintext: &[u8], associated: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = Aes256Gcm::new(key.as_bytes().into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: associated,
+            },
+        )
+        .map_err(|_| "Encryption failed".to_string())?;
+
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt what [`seal`] produced with the same key and associated data
+pub fn open(key: &Key, sealed: &[u8], associated: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if sealed.len() < NONCE_LEN {
+        return Err("Encrypted data is truncated".to_string());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(key.as_bytes().into());
+    cipher
+        .decrypt(
+            nonce.into(),
+            Payload {
+                msg: ciphertext,
+                aad: associated,
+            },
+        )
+        .map(Zeroizing::new)
+        // A wrong key and tampered data look the same
+        .map_err(|_| "Decryption failed: wrong passphrase or corrupted data".to_string())
+}
+
+/// Encrypt `plaintext` into a file, replacing any file already at `path`
+pub fn write_file<M: Serialize>(
+    path: &Path,
+    kind: &str,
+    metadata: &M,
+    plaintext: &[u8],
+    key: KeySource<'_>,
+) -> Result<Protection, String> {
+    let (protection, key) = key.new_key()?;
+    let envelope = Envelope {
+        kind: kind.to_string(),
+        protection,
+        metadata,
+    };
+    let header =
+        serde_json::to_vec(&envelope).map_err(|e| format!("Failed to serialize header: {}", e))?;
+    let sealed = seal(&key, plaintext, &header)?;
+
+    let mut contents = Vec::with_capacity(MAGIC.len() + 4 + header.len() + sealed.len());
+    contents.extend_from_slice(&MAGIC);
+    contents.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    contents.extend_from_slice(&header);
+    contents.extend_from_slice(&sealed);
+
+    // Write next to the target first so a failed write keeps the old file
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = Path::new(&partial);
+    fs::File::create(partial)
+        .and_then(|mut file| file.write_all(&contents).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(partial, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(partial);
+            format!("Failed to write {}: {}", path.display(), e)
+        })?;
+    Ok(envelope.protection)
+}
+
+/// Read the header of an encrypted file without decrypting it
+pub fn read_header<M: DeserializeOwned>(path: &Path, kind: &str) -> Result<Envelope<M>, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut prefix = [0u8; MAGIC.len() + 4];
+    file.read_exact(&mut prefix).map_err(|_| not_kind(kind))?;
+    let mut header = vec![0u8; header_len(&prefix, kind)?];
+    file.read_exact(&mut header)
+        .map_err(|_| "File is truncated".to_string())?;
+    parse_header(&header, kind)
+}
+
+/// Decrypt a file written by [`write_file`]
+pub fn read_file<M: DeserializeOwned>(
+    path: &Path,
+    kind: &str,
+    key: KeySource<'_>,
+) -> Result<(Envelope<M>, Zeroizing<Vec<u8>>), String> {
+    let contents = fs::read(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let prefix = contents
+        .get(..MAGIC.len() + 4)
+        .ok_or_else(|| not_kind(kind))?;
+    let header_end = prefix.len() + header_len(prefix, kind)?;
+    let header = contents
+        .get(prefix.len()..header_end)
+        .ok_or_else(|| "File is truncated".to_string())?;
+    let envelope = parse_header(header, kind)?;
+    let key = key.unlock(&envelope.protection)?;
+    let plaintext = open(&key, &contents[header_end..], header)?;
+    Ok((envelope, plaintext))
+}
+
+// Helper functions
+
+fn header_len(prefix: &[u8], kind: &str) -> Result<usize, String> {
+    if prefix[..MAGIC.len()] != MAGIC {
+        return Err(not_kind(kind));
+    }
+    let len = u32::from_le_bytes(prefix[MAGIC.len()..].try_into().unwrap()) as usize;
+    if len > MAX_HEADER_LEN {
+        return Err("File header is corrupt".to_string());
+    }
+    Ok(len)
+}
+
+fn parse_header<M: DeserializeOwned>(header: &[u8], kind: &str) -> Result<Envelope<M>, String> {
+    let envelope: Envelope<M> =
+        serde_json::from_slice(header).map_err(|e| format!("File header is corrupt: {}", e))?;
+    if envelope.kind != kind {
+        return Err(not_kind(kind));
+    }
+    Ok(envelope)
+}
+
+fn not_kind(kind: &str) -> String {
+    format!("Not a GenomeForge {} file", kind)
+}
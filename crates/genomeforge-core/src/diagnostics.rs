@@ -0,0 +1,382 @@
+//! Application logs and diagnostic bundles
+//!
+//! The desktop apps keep a log of what they did so problems can be
+//! reported, but a log must never tell what a genome says. Every entry is
+//! passed through [`scrub`] before it is written, and again before it
+//! leaves the machine in a diagnostic bundle: rsids, coordinates, alleles,
+//! HGVS names and file paths become placeholders, and the values of
+//! [`SENSITIVE_FIELDS`] are dropped whole.
+//!
+//! Logs are JSON lines in `genomeforge.log`, which is moved to
+//! `genomeforge.log.1` once it grows past [`MAX_LOG_BYTES`]; the
+//! [`KEPT_LOGS`] most recent files are kept.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Name of the log being written
+pub const LOG_FILE: &str = "genomeforge.log";
+
+/// Size past which the log is rotated
+pub const MAX_LOG_BYTES: u64 = 5_000_000;
+
+/// Number of rotated logs kept besides the one being written
+pub const KEPT_LOGS: usize = 3;
+
+/// Fields whose values are never logged
+pub const SENSITIVE_FIELDS: [&str; 9] = [
+    "genotype",
+    "genotypes",
+    "alleles",
+    "rsid",
+    "sequence",
+    "sample",
+    "passphrase",
+    "path",
+    "file_path",
+];
+
+/// Placeholder for the value of a sensitive field
+pub const REDACTED: &str = "[redacted]";
+
+/// First file of a diagnostic bundle, saying what it leaves out
+const BUNDLE_README: &str = "GenomeForge diagnostic bundle\n\n\
+This archive holds application logs and a description of the system they\n\
+were written on. It contains no genome data, analysis results, sessions or\n\
+passphrases. Identifiers of variants, genomic positions, alleles and file\n\
+paths were replaced by placeholders such as [rsid] and [path] before the\n\
+archive was written.\n";
+
+/// Severity of a log entry, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub level: LogLevel,
+    /// Module the entry was logged from
+    pub target: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl LogEntry {
+    /// An entry logged now, with its message and fields scrubbed
+    pub fn new<I>(level: LogLevel, target: &str, message: &str, fields: I) -> Self
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        LogEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+            fields: fields.into_iter().collect(),
+        }
+        .scrubbed()
+    }
+
+    /// The entry with the privacy filter applied
+    pub fn scrubbed(mut self) -> Self {
+        self.message = scrub(&self.message);
+        for (name, value) in self.fields.iter_mut() {
+            *value = if is_sensitive(name) {
+                REDACTED.to_string()
+            } else {
+                scrub(value)
+            };
+        }
+        self
+    }
+}
+
+/// A log rotated by size
+pub struct LogFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    file: Option<File>,
+    size: u64,
+}
+
+impl LogFile {
+    /// Append to the log in `dir`, creating the directory if needed
+    pub fn open(dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(LogFile {
+            dir: dir.to_path_buf(),
+            max_bytes: MAX_LOG_BYTES,
+            file: None,
+            size: 0,
+        })
+    }
+
+    /// Rotate once the log grows past `max_bytes` instead
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Write an entry as one JSON line, rotating the log first if it is full
+    pub fn append(&mut self, entry: &LogEntry) -> Result<(), String> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize log entry: {}", e))?;
+        line.push('\n');
+        if self.file.is_none() {
+            let path = self.dir.join(LOG_FILE);
+            self.size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        }
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(self.dir.join(LOG_FILE))
+                    .map_err(|e| format!("Failed to open log: {}", e))?,
+            ),
+        };
+        file.write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to write log: {}", e))?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file = None;
+        let _ = fs::remove_file(rotated(&self.dir, KEPT_LOGS));
+        for index in (0..KEPT_LOGS).rev() {
+            let from = rotated(&self.dir, index);
+            if from.exists() {
+                fs::rename(&from, rotated(&self.dir, index + 1))
+                    .map_err(|e| format!("Failed to rotate log: {}", e))?;
+            }
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// The log files in `dir`, oldest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    (0..=KEPT_LOGS)
+        .rev()
+        .map(|index| rotated(dir, index))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// The latest entries of the logs in `dir` at least as severe as
+/// `min_level`, most recent first
+pub fn recent(dir: &Path, limit: usize, min_level: LogLevel) -> Result<Vec<LogEntry>, String> {
+    let mut entries = Vec::new();
+    for path in log_files(dir).iter().rev() {
+        let mut lines = read_lines(path)?;
+        lines.reverse();
+        let matching = lines
+            .iter()
+            .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+            .filter(|entry| entry.level <= min_level);
+        for entry in matching {
+            if entries.len() == limit {
+                return Ok(entries);
+            }
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// What went into a diagnostic bundle
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticBundle {
+    pub path: PathBuf,
+    /// Files in the archive, in order
+    pub files: Vec<String>,
+    pub log_entries: usize,
+    /// Size of the archive in bytes
+    pub size: u64,
+}
+
+/// Write a zip of the logs in `log_dir` and the named `attachments`,
+/// passing every line through the privacy filter on the way in
+pub fn write_bundle(
+    path: &Path,
+    log_dir: &Path,
+    attachments: &[(&str, String)],
+) -> Result<DiagnosticBundle, String> {
+    let mut contents = vec![("README.txt".to_string(), BUNDLE_README.to_string())];
+    let mut log_entries = 0;
+    for log in log_files(log_dir) {
+        let mut text = String::new();
+        for line in read_lines(&log)? {
+            let line = match serde_json::from_str::<LogEntry>(&line) {
+                Ok(entry) => serde_json::to_string(&entry.scrubbed())
+                    .map_err(|e| format!("Failed to serialize log entry: {}", e))?,
+                Err(_) => scrub(&line),
+            };
+            text.push_str(&line);
+            text.push('\n');
+            log_entries += 1;
+        }
+        let name = log.file_name().unwrap_or_default().to_string_lossy();
+        contents.push((format!("logs/{}", name), text));
+    }
+    for (name, text) in attachments {
+        contents.push((name.to_string(), scrub(text)));
+    }
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, text) in &contents {
+        zip.start_file(name.as_str(), SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+        zip.write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+    }
+    let bytes = zip
+        .finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| format!("Failed to write archive: {}", e))?;
+    fs::write(path, &bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(DiagnosticBundle {
+        path: path.to_path_buf(),
+        files: contents.into_iter().map(|(name, _)| name).collect(),
+        log_entries,
+        size: bytes.len() as u64,
+    })
+}
+
+/// Replace whatever in `text` could tell about a genome or its owner
+///
+/// Words are replaced by `[rsid]`, `[position]`, `[variant]`,
+/// `[genotype]` or `[path]` when they look like one; everything else,
+/// including the punctuation around them, is kept.
+pub fn scrub(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_whitespace() || "\"'`,;()[]{}<=".contains(c) {
+            out.push_str(&scrub_word(&word));
+            word.clear();
+            out.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    out.push_str(&scrub_word(&word));
+    out
+}
+
+// Helper functions
+
+fn rotated(dir: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => dir.join(LOG_FILE),
+        index => dir.join(format!("{}.{}", LOG_FILE, index)),
+    }
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    BufReader::new(file)
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn is_sensitive(field: &str) -> bool {
+    SENSITIVE_FIELDS
+        .iter()
+        .any(|name| field.eq_ignore_ascii_case(name))
+}
+
+/// The placeholder for one word, keeping trailing sentence punctuation
+fn scrub_word(word: &str) -> String {
+    let core = word.trim_end_matches(['.', ':', '!', '?']);
+    let suffix = &word[core.len()..];
+    let placeholder = if is_rsid(core) {
+        "[rsid]"
+    } else if is_hgvs(core) {
+        "[variant]"
+    } else if is_position(core) {
+        "[position]"
+    } else if is_genotype(core) {
+        "[genotype]"
+    } else if core.contains(['/', '\\']) {
+        "[path]"
+    } else {
+        return word.to_string();
+    };
+    format!("{}{}", placeholder, suffix)
+}
+
+/// `rs123`, or `i123456` as 23andMe names sites it added
+fn is_rsid(word: &str) -> bool {
+    let lower = word.to_ascii_lowercase();
+    let digits =
+        |rest: &str, min: usize| rest.len() >= min && rest.bytes().all(|b| b.is_ascii_digit());
+    lower.strip_prefix("rs").is_some_and(|rest| digits(rest, 1))
+        || lower.strip_prefix('i').is_some_and(|rest| digits(rest, 4))
+}
+
+/// `c.68_69delAG`, `p.Arg117His`, `NM_000492.4:c.350G>A` and the like
+fn is_hgvs(word: &str) -> bool {
+    ["NM_", "NC_", "NP_", "NR_", "NG_"]
+        .iter()
+        .any(|prefix| word.starts_with(prefix))
+        || ["c.", "g.", "m.", "n.", "p.", "r."]
+            .iter()
+            .any(|prefix| word.len() > 2 && word.starts_with(prefix))
+}
+
+/// `17:43044295`, `chr1:100-200` or `1:100:A:G`
+fn is_position(word: &str) -> bool {
+    let Some((chromosome, rest)) = word.split_once(':') else {
+        return false;
+    };
+    let chromosome = chromosome
+        .strip_prefix("chr")
+        .or_else(|| chromosome.strip_prefix("CHR"))
+        .unwrap_or(chromosome);
+    let named = chromosome
+        .parse::<u8>()
+        .is_ok_and(|n| (1..=22).contains(&n))
+        || ["X", "Y", "M", "MT"].contains(&chromosome);
+    named && rest.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// `AG`, `A/G`, `A>G`, `0|1` and other runs of alleles or allele indexes
+fn is_genotype(word: &str) -> bool {
+    let bases = |part: &str| !part.is_empty() && part.bytes().all(|b| b"ACGT".contains(&b));
+    let indexes =
+        |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+    if word.contains(['/', '|', '>']) {
+        let parts: Vec<&str> = word.split(['/', '|', '>']).collect();
+        return parts.iter().all(|part| bases(part))
+            || (parts.len() == 2 && parts.iter().all(|part| indexes(part) && part.len() == 1));
+    }
+    word.len() >= 2 && bases(word)
+}
@@ -0,0 +1,113 @@
+//! Resuming, retrying and throttling downloads
+//!
+//! Database releases run to hundreds of megabytes, more than a flaky
+//! connection reliably carries in one go. Whatever does the transfer (the
+//! desktop app, with its HTTP client) keeps what arrived, asks for the rest
+//! with a range request, waits out failures with [`Backoff`] and holds its
+//! rate under the user's cap with a [`Throttle`]. The pieces here decide
+//! those steps; none of them does I/O.
+
+use std::time::Duration;
+
+/// Attempts at a download before giving up
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Smallest bandwidth cap accepted, in bytes per second
+pub const MIN_RATE_LIMIT: u64 = 16 * 1024;
+
+/// Exponentially growing waits between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Wait after the first failure
+    pub initial: Duration,
+    /// Longest wait
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Backoff {
+    /// Wait before attempt `attempt`, counting from 1; none before the first
+    pub fn delay(&self, attempt: u32) -> Duration {
+        if attempt <= 1 {
+            return Duration::ZERO;
+        }
+        let factor = 1u32.checked_shl(attempt - 2).unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Paces a transfer to at most `rate` bytes per second on average
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    rate: Option<u64>,
+    transferred: u64,
+}
+
+impl Throttle {
+    /// Without a rate, nothing is held back
+    pub fn new(rate: Option<u64>) -> Self {
+        Throttle {
+            rate: rate.filter(|rate| *rate > 0),
+            transferred: 0,
+        }
+    }
+
+    /// Count `bytes` more transferred, `elapsed` after the transfer started,
+    /// and return how long to pause before reading on
+    pub fn record(&mut self, bytes: u64, elapsed: Duration) -> Duration {
+        self.transferred += bytes;
+        let Some(rate) = self.rate else {
+            return Duration::ZERO;
+        };
+        let due = Duration::from_secs_f64(self.transferred as f64 / rate as f64);
+        due.saturating_sub(elapsed)
+    }
+}
+
+/// Value of the `Range` header asking for everything from `offset` on
+pub fn range_header(offset: u64) -> String {
+    format!("bytes={}-", offset)
+}
+
+/// Where the body of a response to a request for the bytes from
+/// `requested` on starts: `requested` for a partial response that honours
+/// the range, 0 for a full response from a server that ignores it
+pub fn resume_offset(
+    status: u16,
+    content_range: Option<&str>,
+    requested: u64,
+) -> Result<u64, String> {
+    match status {
+        200 => Ok(0),
+        206 => {
+            let start = content_range
+                .and_then(|range| range.trim().strip_prefix("bytes "))
+                .and_then(|range| range.split(['-', '/']).next())
+                .and_then(|start| start.trim().parse::<u64>().ok())
+                .ok_or_else(|| format!("Invalid Content-Range: {:?}", content_range))?;
+            if start == requested {
+                Ok(start)
+            } else {
+                Err(format!(
+                    "Server resumed at byte {} instead of {}",
+                    start, requested
+                ))
+            }
+        }
+        _ => Err(format!("Unexpected HTTP status {}", status)),
+    }
+}
+
+/// Whether a response with `status` is worth asking again for: timeouts,
+/// rate limiting and server errors
+pub fn is_retryable(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500..=599)
+}
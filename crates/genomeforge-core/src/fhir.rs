@@ -0,0 +1,560 @@
+//! FHIR R4 export following the HL7 Genomics Reporting implementation guide
+//!
+//! A report becomes a collection Bundle holding a Patient placeholder, one
+//! `variant` Observation per variant, a `genotype` Observation per star
+//! allele diplotype, `diagnostic-implication` and `therapeutic-implication`
+//! Observations derived from them, and a `genomics-report`
+//! DiagnosticReport listing every Observation as a result. The Patient
+//! carries no identifying details; the receiving system links the report
+//! to its own record.
+//!
+//! [`validate`] checks a bundle against the constraints of the IG profiles
+//! used here: required elements, profile codes and that every reference
+//! resolves inside the bundle. It does not replace a full FHIR validator.
+
+use crate::annotation::clinvar::ClinicalSignificance;
+use crate::annotation::zygosity::Zygosity;
+use crate::crypto;
+use crate::genome::GenomeBuild;
+use crate::report;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+
+const PROFILE_BASE: &str = "http://hl7.org/fhir/uv/genomics-reporting/StructureDefinition/";
+const TBD_CODES: &str = "http://hl7.org/fhir/uv/genomics-reporting/CodeSystem/tbd-codes-cs";
+const LOINC: &str = "http://loinc.org";
+const DBSNP: &str = "http://www.ncbi.nlm.nih.gov/projects/SNP";
+const REFSEQ: &str = "http://www.ncbi.nlm.nih.gov/refseq";
+
+/// Versions of the RefSeq accessions NC_000001 to NC_000024 (chromosomes
+/// 1-22, X and Y) in GRCh37; GRCh38 is one version later for each
+const GRCH37_REFSEQ_VERSIONS: [u8; 24] = [
+    10, 11, 11, 11, 9, 11, 13, 10, 11, 10, 9, 11, 10, 8, 9, 9, 10, 9, 9, 10, 8, 10, 10, 9,
+];
+
+/// Mitochondrial reference, the same in both builds
+const MITOCHONDRIAL_REFSEQ: &str = "NC_012920.1";
+
+/// A variant carried by the subject
+#[derive(Debug, Clone, Default)]
+pub struct VariantCall {
+    pub rsid: Option<String>,
+    pub gene: Option<String>,
+    pub chromosome: Option<String>,
+    /// 1-based position
+    pub position: Option<u64>,
+    pub zygosity: Option<Zygosity>,
+    /// ClinVar classification, reported as a diagnostic implication
+    pub significance: Option<ClinicalSignificance>,
+    pub conditions: Vec<String>,
+}
+
+/// Predicted response to a drug
+#[derive(Debug, Clone, Default)]
+pub struct DrugImplication {
+    pub drug: String,
+    pub gene: String,
+    /// Variant the prediction is based on
+    pub rsid: Option<String>,
+    /// Star-allele diplotype the prediction is based on, e.g. "*1/*2"
+    pub diplotype: Option<String>,
+    /// e.g. "Intermediate Metabolizer"
+    pub phenotype: Option<String>,
+    pub implication: String,
+    /// e.g. "PharmGKB 1A"
+    pub evidence: Option<String>,
+}
+
+/// What a genomics report bundle is built from
+#[derive(Debug, Clone, Default)]
+pub struct GenomicsReport {
+    pub id: String,
+    /// Seconds since the Unix epoch
+    pub issued: u64,
+    pub genome_build: Option<GenomeBuild>,
+    pub variants: Vec<VariantCall>,
+    pub implications: Vec<DrugImplication>,
+    /// Summary shown as the report's conclusion
+    pub conclusion: Option<String>,
+}
+
+/// The report as a FHIR R4 Bundle
+pub fn bundle(report: &GenomicsReport) -> Value {
+    let issued = report::timestamp(report.issued);
+    let patient = urn();
+    let subject = json!({ "reference": patient });
+    let mut entries = vec![entry(
+        &patient,
+        json!({ "resourceType": "Patient", "id": id_of(&patient) }),
+    )];
+    let mut results = Vec::new();
+    let mut push = |entries: &mut Vec<Value>, url: String, resource: Value| {
+        results.push(json!({ "reference": url }));
+        entries.push(entry(&url, resource));
+    };
+
+    let mut variant_urls: HashMap<String, String> = HashMap::new();
+    for variant in &report.variants {
+        let url = urn();
+        if let Some(rsid) = &variant.rsid {
+            variant_urls.insert(rsid.clone(), url.clone());
+        }
+        push(
+            &mut entries,
+            url.clone(),
+            variant_observation(variant, report.genome_build, &subject, &issued, &url),
+        );
+        if let Some(significance) = variant.significance {
+            let implication = urn();
+            push(
+                &mut entries,
+                implication.clone(),
+                diagnostic_implication(
+                    variant,
+                    significance,
+                    &subject,
+                    &issued,
+                    &url,
+                    &implication,
+                ),
+            );
+        }
+    }
+
+    let mut genotype_urls: HashMap<(String, String), String> = HashMap::new();
+    for implication in &report.implications {
+        // Base each prediction on its diplotype, else on its variant
+        let derived_from = match (&implication.diplotype, &implication.rsid) {
+            (Some(diplotype), _) => {
+                let key = (implication.gene.clone(), diplotype.clone());
+                match genotype_urls.get(&key) {
+                    Some(url) => url.clone(),
+                    None => {
+                        let url = urn();
+                        let genotype =
+                            genotype_observation(implication, diplotype, &subject, &issued, &url);
+                        push(&mut entries, url.clone(), genotype);
+                        genotype_urls.insert(key, url.clone());
+                        url
+                    }
+                }
+            }
+            (None, Some(rsid)) => match variant_urls.get(rsid) {
+                Some(url) => url.clone(),
+                None => {
+                    let url = urn();
+                    let variant = VariantCall {
+                        rsid: Some(rsid.clone()),
+                        gene: Some(implication.gene.clone()),
+                        ..VariantCall::default()
+                    };
+                    push(
+                        &mut entries,
+                        url.clone(),
+                        variant_observation(&variant, report.genome_build, &subject, &issued, &url),
+                    );
+                    variant_urls.insert(rsid.clone(), url.clone());
+                    url
+                }
+            },
+            (None, None) => continue,
+        };
+        let url = urn();
+        push(
+            &mut entries,
+            url.clone(),
+            therapeutic_implication(implication, &subject, &issued, &derived_from, &url),
+        );
+    }
+
+    let report_url = urn();
+    let mut diagnostic_report = json!({
+        "resourceType": "DiagnosticReport",
+        "id": id_of(&report_url),
+        "meta": { "profile": [profile("genomics-report")] },
+        "identifier": [{ "system": "urn:genomeforge:report", "value": report.id }],
+        "status": "final",
+        "category": [genetics_category()],
+        "code": loinc("51969-4", "Genetic analysis report"),
+        "subject": subject,
+        "issued": issued,
+        "result": results,
+    });
+    if let Some(conclusion) = &report.conclusion {
+        diagnostic_report["conclusion"] = json!(conclusion);
+    }
+    entries.push(entry(&report_url, diagnostic_report));
+
+    json!({
+        "resourceType": "Bundle",
+        "id": id_of(&urn()),
+        "meta": { "lastUpdated": issued },
+        "type": "collection",
+        "timestamp": issued,
+        "entry": entries,
+    })
+}
+
+/// Check a bundle against the profiles used by [`bundle`]
+pub fn validate(bundle: &Value) -> Result<(), String> {
+    if bundle["resourceType"] != "Bundle" {
+        return Err("Not a FHIR Bundle".to_string());
+    }
+    let entries = bundle["entry"]
+        .as_array()
+        .ok_or_else(|| "Bundle has no entries".to_string())?;
+    let urls: HashSet<&str> = entries
+        .iter()
+        .filter_map(|entry| entry["fullUrl"].as_str())
+        .collect();
+    if urls.len() != entries.len() {
+        return Err("Every entry needs a unique fullUrl".to_string());
+    }
+    let resolves = |reference: &Value| {
+        reference["reference"]
+            .as_str()
+            .is_some_and(|url| urls.contains(url))
+    };
+
+    let mut reports = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        let resource = &entry["resource"];
+        let context = |problem: &str| format!("Entry {}: {}", index, problem);
+        let profile = resource["meta"]["profile"][0]
+            .as_str()
+            .and_then(|url| url.strip_prefix(PROFILE_BASE));
+        match resource["resourceType"].as_str() {
+            Some("Patient") => continue,
+            Some("DiagnosticReport") => {
+                reports += 1;
+                if profile != Some("genomics-report") {
+                    return Err(context(
+                        "DiagnosticReport lacks the genomics-report profile",
+                    ));
+                }
+                if !resource["result"]
+                    .as_array()
+                    .is_some_and(|results| results.iter().all(resolves))
+                {
+                    return Err(context("a result does not resolve"));
+                }
+            }
+            Some("Observation") => {
+                let code = match profile {
+                    Some("variant") => "69548-6",
+                    Some("genotype") => "84413-4",
+                    Some("diagnostic-implication") => "diagnostic-implication",
+                    Some("therapeutic-implication") => "therapeutic-implication",
+                    _ => return Err(context("Observation has no genomics profile")),
+                };
+                if resource["code"]["coding"][0]["code"] != code {
+                    return Err(context("Observation code does not match its profile"));
+                }
+                if !resource["category"]
+                    .as_array()
+                    .is_some_and(|categories| categories.contains(&genetics_category()))
+                {
+                    return Err(context("Observation lacks the genetics category"));
+                }
+                let implication = matches!(
+                    profile,
+                    Some("diagnostic-implication" | "therapeutic-implication")
+                );
+                if implication
+                    && !resource["derivedFrom"]
+                        .as_array()
+                        .is_some_and(|sources| !sources.is_empty() && sources.iter().all(resolves))
+                {
+                    return Err(context(
+                        "implication is not derived from a bundled observation",
+                    ));
+                }
+                if !implication && !resource["valueCodeableConcept"].is_object() {
+                    return Err(context("Observation has no value"));
+                }
+            }
+            _ => return Err(context("unexpected resource type")),
+        }
+        if resource["status"] != "final" {
+            return Err(context("status must be final"));
+        }
+        if !resolves(&resource["subject"]) {
+            return Err(context("subject does not resolve"));
+        }
+    }
+    if reports != 1 {
+        return Err("Bundle must hold exactly one DiagnosticReport".to_string());
+    }
+    Ok(())
+}
+
+/// RefSeq accession of a chromosome in a build, e.g. "NC_000001.11"
+pub fn refseq_accession(chromosome: &str, build: GenomeBuild) -> Option<String> {
+    let offset = match build {
+        GenomeBuild::GRCh37 => 0,
+        GenomeBuild::GRCh38 => 1,
+        GenomeBuild::GRCh36 => return None,
+    };
+    let number = match chromosome {
+        "X" => 23,
+        "Y" => 24,
+        "MT" => return Some(MITOCHONDRIAL_REFSEQ.to_string()),
+        other => other
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=22).contains(n))?,
+    };
+    let version = GRCH37_REFSEQ_VERSIONS[number - 1] + offset;
+    Some(format!("NC_{:06}.{}", number, version))
+}
+
+// Helper functions
+
+fn variant_observation(
+    variant: &VariantCall,
+    build: Option<GenomeBuild>,
+    subject: &Value,
+    issued: &str,
+    url: &str,
+) -> Value {
+    let mut components = Vec::new();
+    if let Some(gene) = &variant.gene {
+        components.push(component(
+            "48018-6",
+            "Gene studied [ID]",
+            json!({ "text": gene }),
+        ));
+    }
+    if let Some(rsid) = &variant.rsid {
+        components.push(component(
+            "81255-2",
+            "dbSNP [ID]",
+            json!({ "coding": [{ "system": DBSNP, "code": rsid }] }),
+        ));
+    }
+    if let Some(zygosity) = variant.zygosity {
+        let (code, display) = match zygosity {
+            Zygosity::Heterozygous => ("LA6706-1", "Heterozygous"),
+            Zygosity::Homozygous => ("LA6705-3", "Homozygous"),
+            Zygosity::Hemizygous => ("LA6707-9", "Hemizygous"),
+        };
+        components.push(component("53034-5", "Allelic state", loinc(code, display)));
+    }
+    let accession = build
+        .zip(variant.chromosome.as_deref())
+        .and_then(|(build, chromosome)| refseq_accession(chromosome, build));
+    if let (Some(accession), Some(position)) = (accession, variant.position) {
+        components.push(component(
+            "48013-7",
+            "Genomic reference sequence [ID]",
+            json!({ "coding": [{ "system": REFSEQ, "code": accession }] }),
+        ));
+        components.push(component(
+            "92822-6",
+            "Genomic coordinate system [Type]",
+            loinc("LA30102-0", "1-based character counting"),
+        ));
+        let mut range = component_code("81254-5", "Genomic allele start-end");
+        range["valueRange"] = json!({ "low": { "value": position } });
+        components.push(range);
+    }
+
+    let mut observation = observation(
+        "variant",
+        loinc("69548-6", "Genetic variant assessment"),
+        subject,
+        issued,
+        url,
+    );
+    observation["valueCodeableConcept"] = loinc("LA9633-4", "Present");
+    observation["component"] = json!(components);
+    observation
+}
+
+fn genotype_observation(
+    implication: &DrugImplication,
+    diplotype: &str,
+    subject: &Value,
+    issued: &str,
+    url: &str,
+) -> Value {
+    let mut observation = observation(
+        "genotype",
+        loinc("84413-4", "Genotype display name"),
+        subject,
+        issued,
+        url,
+    );
+    observation["valueCodeableConcept"] =
+        json!({ "text": format!("{}{}", implication.gene, diplotype) });
+    observation["component"] = json!([component(
+        "48018-6",
+        "Gene studied [ID]",
+        json!({ "text": implication.gene })
+    )]);
+    observation
+}
+
+fn diagnostic_implication(
+    variant: &VariantCall,
+    significance: ClinicalSignificance,
+    subject: &Value,
+    issued: &str,
+    derived_from: &str,
+    url: &str,
+) -> Value {
+    let value = match significance {
+        ClinicalSignificance::Pathogenic => loinc("LA6668-3", "Pathogenic"),
+        ClinicalSignificance::LikelyPathogenic => loinc("LA26332-9", "Likely pathogenic"),
+        ClinicalSignificance::UncertainSignificance => loinc("LA26333-7", "Uncertain significance"),
+        ClinicalSignificance::LikelyBenign => loinc("LA26334-5", "Likely benign"),
+        ClinicalSignificance::Benign => loinc("LA6675-8", "Benign"),
+        other => json!({ "text": other.as_str() }),
+    };
+    let mut components = vec![component(
+        "53037-8",
+        "Genetic variation clinical significance [Imp]",
+        value,
+    )];
+    for condition in &variant.conditions {
+        components.push(component(
+            "81259-4",
+            "Associated phenotype",
+            json!({ "text": condition }),
+        ));
+    }
+
+    let mut observation = observation(
+        "diagnostic-implication",
+        tbd("diagnostic-implication", "Diagnostic Implication"),
+        subject,
+        issued,
+        url,
+    );
+    observation["derivedFrom"] = json!([{ "reference": derived_from }]);
+    observation["component"] = json!(components);
+    observation
+}
+
+fn therapeutic_implication(
+    implication: &DrugImplication,
+    subject: &Value,
+    issued: &str,
+    derived_from: &str,
+    url: &str,
+) -> Value {
+    let mut components = vec![
+        component(
+            "51963-7",
+            "Medication assessed [ID]",
+            json!({ "text": implication.drug }),
+        ),
+        component(
+            "51961-1",
+            "Genetic variation's effect on drug efficacy",
+            json!({ "text": implication.implication }),
+        ),
+    ];
+    if let Some(phenotype) = &implication.phenotype {
+        components.push(component(
+            "53040-2",
+            "Genetic variation's effect on drug metabolism",
+            json!({ "text": phenotype }),
+        ));
+    }
+    if let Some(evidence) = &implication.evidence {
+        components.push(component(
+            "93044-6",
+            "Level of evidence",
+            json!({ "text": evidence }),
+        ));
+    }
+
+    let mut observation = observation(
+        "therapeutic-implication",
+        tbd("therapeutic-implication", "Therapeutic Implication"),
+        subject,
+        issued,
+        url,
+    );
+    observation["derivedFrom"] = json!([{ "reference": derived_from }]);
+    observation["component"] = json!(components);
+    observation
+}
+
+fn observation(profile_name: &str, code: Value, subject: &Value, issued: &str, url: &str) -> Value {
+    json!({
+        "resourceType": "Observation",
+        "id": id_of(url),
+        "meta": { "profile": [profile(profile_name)] },
+        "status": "final",
+        "category": [
+            {
+                "coding": [{
+                    "system": "http://terminology.hl7.org/CodeSystem/observation-category",
+                    "code": "laboratory"
+                }]
+            },
+            genetics_category()
+        ],
+        "code": code,
+        "subject": subject,
+        "issued": issued,
+    })
+}
+
+fn genetics_category() -> Value {
+    json!({
+        "coding": [{
+            "system": "http://terminology.hl7.org/CodeSystem/v2-0074",
+            "code": "GE",
+            "display": "Genetics"
+        }]
+    })
+}
+
+fn component(code: &str, display: &str, value: Value) -> Value {
+    let mut component = component_code(code, display);
+    component["valueCodeableConcept"] = value;
+    component
+}
+
+fn component_code(code: &str, display: &str) -> Value {
+    json!({ "code": loinc(code, display) })
+}
+
+fn loinc(code: &str, display: &str) -> Value {
+    json!({ "coding": [{ "system": LOINC, "code": code, "display": display }] })
+}
+
+fn tbd(code: &str, display: &str) -> Value {
+    json!({ "coding": [{ "system": TBD_CODES, "code": code, "display": display }] })
+}
+
+fn profile(name: &str) -> String {
+    format!("{}{}", PROFILE_BASE, name)
+}
+
+fn entry(url: &str, resource: Value) -> Value {
+    json!({ "fullUrl": url, "resource": resource })
+}
+
+/// A random version 4 UUID as a URN
+fn urn() -> String {
+    let mut bytes: [u8; 16] = crypto::random_bytes();
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "urn:uuid:{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn id_of(urn: &str) -> &str {
+    urn.trim_start_matches("urn:uuid:")
+}
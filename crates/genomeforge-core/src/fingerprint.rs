@@ -0,0 +1,138 @@
+//! Fingerprints of genome files
+//!
+//! The SHA-256 of a file's contents identifies the exact data an analysis
+//! ran on, so a result can be checked against an archived copy of the file.
+//! A [`FingerprintLog`] remembers the fingerprint each file had when it was
+//! last loaded, so loading it again after its contents changed is noticed.
+//! The log names the files a user opened, so it is sealed with
+//! [`crypto::write_file`] like everything else written to disk.
+
+use crate::crypto::{self, KeySource};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind recorded in the header of fingerprint logs
+const KIND: &str = "fingerprints";
+
+/// A file's contents at the time it was read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub path: PathBuf,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+    pub size: u64,
+    /// Seconds since the Unix epoch
+    pub recorded_at: u64,
+}
+
+impl FileFingerprint {
+    /// Hash the file at `path`
+    pub fn of(path: &Path) -> Result<Self, String> {
+        let (sha256, size) = hash_file(path)?;
+        Ok(FileFingerprint {
+            path: path.to_path_buf(),
+            sha256,
+            size,
+            recorded_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+        })
+    }
+
+    /// Whether both fingerprints are of the same contents
+    pub fn same_contents(&self, other: &FileFingerprint) -> bool {
+        self.sha256 == other.sha256 && self.size == other.size
+    }
+}
+
+/// Hex SHA-256 of a file's contents
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    hash_file(path).map(|(sha256, _)| sha256)
+}
+
+/// Fingerprints of the files loaded before, by path
+#[derive(Debug, Default)]
+pub struct FingerprintLog {
+    entries: BTreeMap<PathBuf, FileFingerprint>,
+}
+
+#[derive(Serialize)]
+struct LogInfo {
+    entries: usize,
+}
+
+impl FingerprintLog {
+    /// Read the log at `path`; a missing file is an empty log
+    pub fn open(path: &Path, key: KeySource<'_>) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let (_, plaintext) = crypto::read_file::<serde_json::Value>(path, KIND, key)?;
+        let entries: Vec<FileFingerprint> = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Fingerprint log is corrupt: {}", e))?;
+        Ok(FingerprintLog {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+        })
+    }
+
+    /// Write the log to `path`
+    pub fn save(&self, path: &Path, key: KeySource<'_>) -> Result<(), String> {
+        let entries: Vec<&FileFingerprint> = self.entries.values().collect();
+        let plaintext = serde_json::to_vec(&entries)
+            .map_err(|e| format!("Failed to serialize fingerprints: {}", e))?;
+        let info = LogInfo {
+            entries: entries.len(),
+        };
+        crypto::write_file(path, KIND, &info, &plaintext, key).map(|_| ())
+    }
+
+    /// The fingerprint last recorded for a file
+    pub fn get(&self, path: &Path) -> Option<&FileFingerprint> {
+        self.entries.get(path)
+    }
+
+    /// Record a file's fingerprint, returning the one it replaces when the
+    /// contents changed since
+    pub fn record(&mut self, fingerprint: FileFingerprint) -> Option<FileFingerprint> {
+        let previous = self.entries.insert(fingerprint.path.clone(), fingerprint)?;
+        let current = &self.entries[&previous.path];
+        (!previous.same_contents(current)).then_some(previous)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// Helper functions
+
+fn hash_file(path: &Path) -> Result<(String, u64), String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    let mut size = 0u64;
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), size))
+}
@@ -0,0 +1,457 @@
+//! Core genome data types
+//!
+//! Every parser produces [`Variant`] values regardless of the input format,
+//! so the analysis engine never needs to know where a genotype came from.
+
+use crate::fingerprint::FileFingerprint;
+use crate::parser::compression::Compression;
+use crate::parser::detect::FileFormat;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Reference genome assembly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GenomeBuild {
+    GRCh36,
+    GRCh37,
+    GRCh38,
+}
+
+/// A genotype call at a single site
+///
+/// Serialized as a compact string: "AG" for array calls, "A/AT" when any
+/// allele is longer than one base, "|" instead of "/" when phased, and
+/// "--" for a no-call. A haploid allele longer than one base is followed
+/// by "/", as in "AT/", so it reads back as one allele rather than two.
+/// Calls the compact form would misread, such as "-A", are written with
+/// the separator.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Genotype {
+    NoCall,
+    /// A single allele, e.g. on male X/Y or mitochondrial DNA
+    Haploid(String),
+    Diploid {
+        first: String,
+        second: String,
+        phased: bool,
+    },
+}
+
+impl Genotype {
+    /// Parse a consumer array call such as "AG", "A", "DI" or "--"
+    pub fn from_array_call(call: &str) -> Genotype {
+        let call = call.trim().to_ascii_uppercase();
+        let mut chars = call.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('-'), _, _) | (None, _, _) => Genotype::NoCall,
+            (Some(a), None, _) => Genotype::Haploid(a.to_string()),
+            (Some(a), Some(b), None) => Genotype::Diploid {
+                first: a.to_string(),
+                second: b.to_string(),
+                phased: false,
+            },
+            _ => Genotype::NoCall,
+        }
+    }
+
+    /// Resolve a VCF `GT` value ("0/1", "1|1", "./.") against the site's alleles
+    pub fn from_vcf(gt: &str, reference: &str, alternates: &[String]) -> Genotype {
+        let phased = gt.contains('|');
+        let alleles: Option<Vec<String>> = gt
+            .split(['/', '|'])
+            .map(|index| match index {
+                "." => None,
+                "0" => Some(reference.to_string()),
+                n => n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| alternates.get(n.wrapping_sub(1)))
+                    .cloned(),
+            })
+            .collect();
+
+        match alleles.as_deref() {
+            Some([allele]) => Genotype::Haploid(allele.clone()),
+            Some([first, second]) => Genotype::Diploid {
+                first: first.clone(),
+                second: second.clone(),
+                phased,
+            },
+            _ => Genotype::NoCall,
+        }
+    }
+
+    /// Whether no genotype was called
+    pub fn is_no_call(&self) -> bool {
+        matches!(self, Genotype::NoCall)
+    }
+
+    /// Called alleles in order
+    pub fn alleles(&self) -> Vec<&str> {
+        match self {
+            Genotype::NoCall => Vec::new(),
+            Genotype::Haploid(allele) => vec![allele.as_str()],
+            Genotype::Diploid { first, second, .. } => vec![first.as_str(), second.as_str()],
+        }
+    }
+
+    /// Both alleles of a diploid call
+    pub fn diploid(&self) -> Option<(&str, &str)> {
+        match self {
+            Genotype::Diploid { first, second, .. } => Some((first, second)),
+            _ => None,
+        }
+    }
+
+    /// Two different alleles
+    pub fn is_heterozygous(&self) -> bool {
+        matches!(self, Genotype::Diploid { first, second, .. } if first != second)
+    }
+
+    /// Two copies of the same allele
+    pub fn is_homozygous(&self) -> bool {
+        matches!(self, Genotype::Diploid { first, second, .. } if first == second)
+    }
+
+    /// Number of copies of `allele` in the call
+    pub fn allele_count(&self, allele: &str) -> usize {
+        self.alleles().iter().filter(|a| **a == allele).count()
+    }
+
+    /// Copies of `allele` in a call of a biallelic SNP whose other allele
+    /// is `other`, complemented when it was reported on the minus strand;
+    /// none when the call has other alleles
+    pub fn biallelic_count(&self, allele: &str, other: &str) -> Option<usize> {
+        let count = |genotype: &Genotype| {
+            let copies = genotype.allele_count(allele);
+            (copies + genotype.allele_count(other) == genotype.alleles().len()).then_some(copies)
+        };
+        count(self).or_else(|| count(&self.complemented()))
+    }
+
+    /// The same call read from the opposite strand
+    pub fn complemented(&self) -> Genotype {
+        match self {
+            Genotype::NoCall => Genotype::NoCall,
+            Genotype::Haploid(allele) => Genotype::Haploid(reverse_complement(allele)),
+            Genotype::Diploid {
+                first,
+                second,
+                phased,
+            } => Genotype::Diploid {
+                first: reverse_complement(first),
+                second: reverse_complement(second),
+                phased: *phased,
+            },
+        }
+    }
+}
+
+/// Reverse complement of an allele; D/I indel calls are left as they are
+pub fn reverse_complement(allele: &str) -> String {
+    allele
+        .chars()
+        .rev()
+        .map(|base| match base {
+            'A' => 'T',
+            'T' => 'A',
+            'C' => 'G',
+            'G' => 'C',
+            'a' => 't',
+            't' => 'a',
+            'c' => 'g',
+            'g' => 'c',
+            other => other,
+        })
+        .collect()
+}
+
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Genotype::NoCall => write!(f, "--"),
+            Genotype::Haploid(allele) => write!(f, "{}", allele),
+            Genotype::Diploid {
+                first,
+                second,
+                phased,
+            } => {
+                if *phased {
+                    write!(f, "{}|{}", first, second)
+                } else if first.len() == 1 && second.len() == 1 {
+                    write!(f, "{}{}", first, second)
+                } else {
+                    write!(f, "{}/{}", first, second)
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Genotype {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(allele) = s.strip_suffix('/') {
+            if !allele.contains(['/', '|']) {
+                return Ok(Genotype::Haploid(allele.to_string()));
+            }
+        }
+        if s.contains(['/', '|']) {
+            let phased = s.contains('|');
+            let (first, second) = s
+                .split_once(['/', '|'])
+                .ok_or_else(|| format!("Invalid genotype: {}", s))?;
+            return Ok(Genotype::Diploid {
+                first: first.to_string(),
+                second: second.to_string(),
+                phased,
+            });
+        }
+        Ok(Genotype::from_array_call(s))
+    }
+}
+
+impl Serialize for Genotype {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let compact = self.to_string();
+        // The display form of a call is kept only when it reads back as it
+        let encoded = if compact.parse::<Genotype>().as_ref() == Ok(self) {
+            compact
+        } else {
+            match self {
+                Genotype::Haploid(allele) => format!("{}/", allele),
+                Genotype::Diploid {
+                    first,
+                    second,
+                    phased,
+                } => format!("{}{}{}", first, if *phased { '|' } else { '/' }, second),
+                Genotype::NoCall => compact,
+            }
+        };
+        serializer.serialize_str(&encoded)
+    }
+}
+
+impl<'de> Deserialize<'de> for Genotype {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A genotyped site from any supported input format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub rsid: Option<String>,
+    /// Normalized chromosome name ("1"-"22", "X", "Y", "MT")
+    pub chromosome: String,
+    pub position: u64,
+    /// Reference allele; consumer arrays do not report one
+    pub reference: Option<String>,
+    pub alternates: Vec<String>,
+    pub genotype: Genotype,
+    /// How sure the caller was of the genotype, for formats that say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<CallQuality>,
+}
+
+impl Variant {
+    /// Whether the site lists more than one alternate allele
+    pub fn is_multiallelic(&self) -> bool {
+        self.alternates.len() > 1
+    }
+
+    /// Whether the genotype was imputed rather than measured
+    pub fn is_imputed(&self) -> bool {
+        self.quality.as_ref().is_some_and(|quality| quality.imputed)
+    }
+}
+
+/// Confidence a VCF gives in one genotype call
+///
+/// Imputation servers such as Michigan and TOPMed, and tools such as
+/// Beagle and IMPUTE, mark which sites were imputed from a reference panel
+/// and how well, and give each sample's genotype probabilities and dosage.
+/// Sequencing variant callers give each site's FILTER and QUAL and each
+/// call's genotype quality and read depth.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallQuality {
+    /// Inferred from a reference panel rather than genotyped directly
+    pub imputed: bool,
+    /// Estimated imputation accuracy of the site (`R2`, `DR2` or `INFO`),
+    /// 0.0 - 1.0
+    pub info_score: Option<f32>,
+    /// Probability of the most likely genotype, from `GP`
+    pub probability: Option<f32>,
+    /// Expected number of alternate alleles, from `DS`
+    pub dosage: Option<f32>,
+    /// FILTER values the site failed; empty when it passed or was not
+    /// filtered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<String>,
+    /// Phred-scaled confidence in the site, from `QUAL`
+    #[serde(default)]
+    pub site_quality: Option<f32>,
+    /// Phred-scaled confidence in the genotype, from `GQ`
+    #[serde(default)]
+    pub genotype_quality: Option<f32>,
+    /// Reads covering the call, from the sample's `DP` or else the site's
+    #[serde(default)]
+    pub depth: Option<u32>,
+}
+
+/// Kind of a structural variant, from its symbolic allele or `SVTYPE`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuralKind {
+    Deletion,
+    Duplication,
+    Inversion,
+    Insertion,
+    /// A copy-number change the record does not say the direction of
+    CopyNumber,
+    /// One end of a rearrangement joined to another locus
+    Breakend,
+}
+
+/// A structural variant or copy-number change called in a VCF sample
+///
+/// Kept apart from the [`Variant`]s, since a symbolic allele such as
+/// `<DEL>` has no bases for the SNV and indel analyses to match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuralVariant {
+    pub id: Option<String>,
+    pub kind: StructuralKind,
+    /// Normalized chromosome name
+    pub chromosome: String,
+    /// First affected base, after the padding base symbolic records start
+    /// with; 1-based
+    pub start: u64,
+    /// Last affected base, inclusive
+    pub end: u64,
+    /// Copies of the variant allele in the sample's `GT`, when called
+    pub copies: Option<usize>,
+    /// Total copies of the segment, from `CN`
+    pub copy_number: Option<u32>,
+    /// FILTER values the site failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<String>,
+}
+
+impl StructuralVariant {
+    /// Affected bases
+    pub fn length(&self) -> u64 {
+        self.end.saturating_sub(self.start) + 1
+    }
+
+    /// Whether the sample carries the variant, by its genotype or else a
+    /// copy number other than two
+    pub fn is_carried(&self) -> bool {
+        match self.copies {
+            Some(copies) => copies > 0,
+            None => self.copy_number.is_some_and(|copies| copies != 2),
+        }
+    }
+
+    /// Whether both copies of the segment are affected: a homozygous call,
+    /// or for a deletion, no copies left
+    pub fn is_homozygous(&self) -> bool {
+        self.copies.is_some_and(|copies| copies >= 2)
+            || (self.kind == StructuralKind::Deletion && self.copy_number == Some(0))
+    }
+
+    pub fn region(&self) -> Region {
+        Region::new(&self.chromosome, self.start, self.end)
+    }
+}
+
+/// A stretch of one chromosome, 1-based and inclusive
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Region {
+    /// Normalized chromosome name
+    pub chromosome: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Region {
+    pub fn new(chromosome: &str, start: u64, end: u64) -> Self {
+        Region {
+            chromosome: crate::parser::normalize_chromosome(chromosome),
+            start,
+            end,
+        }
+    }
+
+    /// Whether a variant's position falls in the region
+    pub fn contains(&self, variant: &Variant) -> bool {
+        variant.chromosome == self.chromosome && (self.start..=self.end).contains(&variant.position)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}-{}", self.chromosome, self.start, self.end)
+    }
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parse "chr17:43044295-43125483", "17:43,044,295" or a whole
+    /// chromosome such as "MT"
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid region '{}'", raw);
+        let raw_trimmed = raw.trim();
+        let (chromosome, range) = match raw_trimmed.split_once(':') {
+            Some((chromosome, range)) => (chromosome, Some(range)),
+            None => (raw_trimmed, None),
+        };
+        if chromosome.is_empty() {
+            return Err(invalid());
+        }
+        let number = |value: &str| -> Result<u64, String> {
+            value
+                .trim()
+                .replace(',', "")
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(invalid)
+        };
+        let (start, end) = match range.map(|range| range.split_once('-')) {
+            None => (1, u64::MAX),
+            Some(Some((start, end))) => (number(start)?, number(end)?),
+            Some(None) => {
+                let position = number(range.unwrap_or_default())?;
+                (position, position)
+            }
+        };
+        if end < start {
+            return Err(invalid());
+        }
+        Ok(Region::new(chromosome, start, end))
+    }
+}
+
+/// Metadata describing a genome file being parsed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenomeFile {
+    pub format: FileFormat,
+    pub compression: Compression,
+    /// Detector confidence for `format`, from 0.0 to 1.0
+    pub detection_confidence: f64,
+    /// Format version from the file header, e.g. "VCFv4.2"
+    pub format_version: Option<String>,
+    /// Genotyping array version for consumer exports, e.g. "v5" or "V2.0"
+    pub chip_version: Option<String>,
+    pub genome_build: Option<GenomeBuild>,
+    /// Sample names; consumer exports hold a single unnamed sample
+    pub samples: Vec<String>,
+    /// Contents of the file when it was loaded, once the application has
+    /// hashed it; parsers leave it unset
+    #[serde(default)]
+    pub fingerprint: Option<FileFingerprint>,
+}
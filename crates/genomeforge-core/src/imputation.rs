@@ -0,0 +1,158 @@
+//! Filtering of imputed genotypes
+//!
+//! Imputation servers fill in millions of sites an array never measured by
+//! matching its calls against a reference panel. Most imputed calls are
+//! accurate, but those at poorly imputed sites or with a spread-out
+//! genotype probability are guesses. [`filter_genome`] turns such calls
+//! into no-calls before analysis, so that no finding rests on them.
+
+use crate::genome::{Genotype, Variant};
+use crate::parser::SummaryBuilder;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+
+/// Which imputed calls to drop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImputationFilter {
+    /// Drop imputed calls at sites imputed less accurately than this
+    /// (`R2`, `DR2` or `INFO`, 0.0 - 1.0); Michigan suggests 0.3
+    pub min_info_score: Option<f64>,
+    /// Drop calls whose most likely genotype is less probable than this
+    /// (0.0 - 1.0)
+    pub min_probability: Option<f64>,
+    /// Drop every imputed call, keeping only the genotyped sites
+    pub exclude_imputed: bool,
+}
+
+impl ImputationFilter {
+    /// Whether the filter drops anything
+    pub fn is_active(&self) -> bool {
+        self.exclude_imputed || self.min_info_score.is_some() || self.min_probability.is_some()
+    }
+
+    /// Check that both thresholds are fractions
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, threshold) in [
+            ("min_info_score", self.min_info_score),
+            ("min_probability", self.min_probability),
+        ] {
+            if threshold.is_some_and(|t| !(0.0..=1.0).contains(&t)) {
+                return Err(format!("{} must be between 0 and 1", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Why a call is dropped, or `None` when it is kept
+    pub fn rejects(&self, variant: &Variant) -> Option<Rejection> {
+        let quality = variant.quality.as_ref()?;
+        if variant.genotype.is_no_call() {
+            return None;
+        }
+        if quality.imputed && self.exclude_imputed {
+            return Some(Rejection::Imputed);
+        }
+        let below = |value: Option<f32>, min: Option<f64>| {
+            value
+                .zip(min)
+                .is_some_and(|(value, min)| f64::from(value) < min)
+        };
+        if quality.imputed && below(quality.info_score, self.min_info_score) {
+            return Some(Rejection::LowInfoScore);
+        }
+        if below(quality.probability, self.min_probability) {
+            return Some(Rejection::LowProbability);
+        }
+        None
+    }
+}
+
+/// Reason a call is dropped by an [`ImputationFilter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rejection {
+    Imputed,
+    LowInfoScore,
+    LowProbability,
+}
+
+/// Counts from filtering a genome
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ImputationStats {
+    /// Called variants that were imputed, before filtering
+    pub imputed: usize,
+    /// Called variants that were genotyped directly
+    pub typed: usize,
+    pub excluded_imputed: usize,
+    pub excluded_low_info_score: usize,
+    pub excluded_low_probability: usize,
+}
+
+impl ImputationStats {
+    /// Calls turned into no-calls
+    pub fn excluded(&self) -> usize {
+        self.excluded_imputed + self.excluded_low_info_score + self.excluded_low_probability
+    }
+}
+
+/// Whether any variant of the genome carries imputation fields
+pub fn has_imputation_fields(genome: &LoadedGenome) -> bool {
+    genome.variants().iter().any(|variant| {
+        variant.quality.as_ref().is_some_and(|quality| {
+            quality.imputed
+                || quality.info_score.is_some()
+                || quality.probability.is_some()
+                || quality.dosage.is_some()
+        })
+    })
+}
+
+/// Count imputed and genotyped calls, turning those the filter rejects
+/// into no-calls
+///
+/// The sites stay in the genome, so analyses see them as not called.
+/// `checkpoint` is called with the number of variants filtered every
+/// [`CHECKPOINT_INTERVAL`] variants.
+pub fn filter_genome<F>(
+    genome: &LoadedGenome,
+    filter: &ImputationFilter,
+    mut checkpoint: F,
+) -> Result<(LoadedGenome, ImputationStats), String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut stats = ImputationStats::default();
+    let mut builder = SummaryBuilder::default();
+    let mut variants = Vec::with_capacity(genome.len());
+    for (index, variant) in genome.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        let mut variant = variant.clone();
+        if !variant.genotype.is_no_call() {
+            if variant.is_imputed() {
+                stats.imputed += 1;
+            } else {
+                stats.typed += 1;
+            }
+        }
+        if let Some(rejection) = filter.rejects(&variant) {
+            match rejection {
+                Rejection::Imputed => stats.excluded_imputed += 1,
+                Rejection::LowInfoScore => stats.excluded_low_info_score += 1,
+                Rejection::LowProbability => stats.excluded_low_probability += 1,
+            }
+            variant.genotype = Genotype::NoCall;
+        }
+        builder.add(&variant);
+        variants.push(variant);
+    }
+    checkpoint(genome.len())?;
+
+    let summary = builder.finish(genome.summary.skipped_lines);
+    Ok((
+        LoadedGenome::from_variants(genome.file.clone(), summary, variants),
+        stats,
+    ))
+}
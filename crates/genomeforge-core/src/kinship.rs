@@ -0,0 +1,167 @@
+//! Relatedness of two genomes
+//!
+//! Two genomes are compared at every autosomal site called in both. The
+//! share of sites where they have no allele in common (IBS0), one (IBS1) or
+//! both (IBS2) and the KING-robust kinship coefficient together tell how
+//! closely the two people are related: the coefficient is halved with each
+//! degree of relationship, and parents and children, unlike siblings, always
+//! share an allele. Everything is computed from the genotypes alone, on
+//! this computer.
+//!
+//! The estimator assumes both people share an ancestry; between people of
+//! different ancestries it reads low, so distant relatives may be missed.
+//! The genomes must be on the same build, since sites are matched by
+//! position before rsid.
+
+use crate::parser::is_autosome;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+
+/// Fewest sites called in both genomes for an estimate
+pub const MIN_SITES: usize = 1_000;
+
+/// Lower kinship bounds of each degree of relationship, from KING
+const DUPLICATE: f64 = 0.354;
+const FIRST_DEGREE: f64 = 0.177;
+const SECOND_DEGREE: f64 = 0.0884;
+const THIRD_DEGREE: f64 = 0.0442;
+
+/// IBS0 rate below which first-degree relatives are parent and child;
+/// above genotyping error, below what siblings show on consumer arrays
+const PARENT_CHILD_IBS0: f64 = 0.0025;
+
+/// Most likely relationship of two people
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relationship {
+    /// The same person, or identical twins
+    Duplicate,
+    ParentChild,
+    FullSiblings,
+    /// E.g. grandparent, aunt or uncle, half sibling
+    SecondDegree,
+    /// E.g. first cousin
+    ThirdDegree,
+    Unrelated,
+}
+
+/// Relatedness of two genomes
+#[derive(Debug, Clone, Serialize)]
+pub struct Kinship {
+    /// Autosomal sites called in both genomes with at most two alleles
+    pub sites_compared: usize,
+    pub ibs0: usize,
+    pub ibs1: usize,
+    pub ibs2: usize,
+    /// Fraction of compared sites sharing no allele (0.0 - 1.0)
+    pub ibs0_rate: f64,
+    /// KING-robust kinship coefficient; 0.5 for duplicates, 0.25 for
+    /// first-degree relatives, near 0 for unrelated people
+    pub kinship: f64,
+    pub relationship: Relationship,
+}
+
+/// Estimate how closely the people two genomes belong to are related
+///
+/// `checkpoint` is called with the number of variants of `first` compared
+/// every [`CHECKPOINT_INTERVAL`] variants and stops the estimate when it
+/// returns an error.
+pub fn estimate<F>(
+    first: &LoadedGenome,
+    second: &LoadedGenome,
+    mut checkpoint: F,
+) -> Result<Kinship, String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut ibs = [0usize; 3];
+    // Sites heterozygous in both, in the first and in the second
+    let mut both_het = 0usize;
+    let mut first_het = 0usize;
+    let mut second_het = 0usize;
+
+    for (index, variant) in first.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        if !is_autosome(&variant.chromosome) {
+            continue;
+        }
+        let Some(other) = second.same_site(variant) else {
+            continue;
+        };
+        let (Some(a), Some(b)) = (variant.genotype.diploid(), other.genotype.diploid()) else {
+            continue;
+        };
+        let mut alleles = vec![a.0, a.1, b.0, b.1];
+        alleles.sort_unstable();
+        alleles.dedup();
+        if alleles.len() > 2 {
+            continue;
+        }
+
+        let shared = shared_alleles(a, b);
+        ibs[shared] += 1;
+        let (a_het, b_het) = (a.0 != a.1, b.0 != b.1);
+        both_het += usize::from(a_het && b_het);
+        first_het += usize::from(a_het);
+        second_het += usize::from(b_het);
+    }
+    checkpoint(first.len())?;
+
+    let sites_compared: usize = ibs.iter().sum();
+    if sites_compared < MIN_SITES {
+        return Err(format!(
+            "Only {} sites are called in both genomes; at least {} are needed",
+            sites_compared, MIN_SITES
+        ));
+    }
+    // Opposite homozygotes are exactly the sites sharing no allele
+    let kinship = if first_het + second_het == 0 {
+        0.0
+    } else {
+        (both_het as f64 - 2.0 * ibs[0] as f64) / (first_het + second_het) as f64
+    };
+    let ibs0_rate = ibs[0] as f64 / sites_compared as f64;
+    Ok(Kinship {
+        sites_compared,
+        ibs0: ibs[0],
+        ibs1: ibs[1],
+        ibs2: ibs[2],
+        ibs0_rate,
+        kinship,
+        relationship: classify(kinship, ibs0_rate),
+    })
+}
+
+/// Relationship implied by a kinship coefficient and IBS0 rate
+pub fn classify(kinship: f64, ibs0_rate: f64) -> Relationship {
+    if kinship > DUPLICATE {
+        Relationship::Duplicate
+    } else if kinship > FIRST_DEGREE {
+        if ibs0_rate < PARENT_CHILD_IBS0 {
+            Relationship::ParentChild
+        } else {
+            Relationship::FullSiblings
+        }
+    } else if kinship > SECOND_DEGREE {
+        Relationship::SecondDegree
+    } else if kinship > THIRD_DEGREE {
+        Relationship::ThirdDegree
+    } else {
+        Relationship::Unrelated
+    }
+}
+
+// Helper functions
+
+/// Alleles two biallelic diploid calls have in common (0-2)
+fn shared_alleles(a: (&str, &str), b: (&str, &str)) -> usize {
+    if (a.0 == b.0 && a.1 == b.1) || (a.0 == b.1 && a.1 == b.0) {
+        2
+    } else if a.0 == b.0 || a.0 == b.1 || a.1 == b.0 || a.1 == b.1 {
+        1
+    } else {
+        0
+    }
+}
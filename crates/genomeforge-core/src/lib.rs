@@ -0,0 +1,54 @@
+//! GenomeForge core
+//!
+//! Genome parsing engine, variant store and annotation databases shared by
+//! the desktop applications, command-line tools and tests. Everything here
+//! runs locally; nothing in this crate performs network access.
+
+pub mod admixture;
+pub mod alignment;
+pub mod annotation;
+pub mod audit;
+pub mod batch;
+pub mod benchmark;
+pub mod cache;
+pub mod compare;
+pub mod completeness;
+pub mod confidence;
+pub mod crypto;
+pub mod diagnostics;
+pub mod download;
+pub mod fhir;
+pub mod fingerprint;
+pub mod genome;
+pub mod imputation;
+pub mod kinship;
+pub mod liftover;
+pub mod merge;
+pub mod normalize;
+pub mod notes;
+pub mod parallel;
+pub mod parser;
+pub mod plugin;
+pub mod prs;
+pub mod purge;
+pub mod quality;
+pub mod reference;
+pub mod report;
+pub mod results_db;
+pub mod rules;
+pub mod search;
+pub mod session;
+pub mod settings;
+pub mod sex;
+pub mod store;
+pub mod stream;
+pub mod tasks;
+pub mod trio;
+pub mod watch;
+pub mod watchlist;
+pub mod workload;
+
+pub use genome::{GenomeBuild, GenomeFile, Genotype, Region, Variant};
+pub use parser::{open_genome, summarize, ParseSummary, VariantSource};
+pub use store::{GenomeStore, LoadedGenome};
+pub use tasks::{TaskHandle, TaskRegistry};
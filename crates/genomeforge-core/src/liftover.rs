@@ -0,0 +1,410 @@
+//! Genome build detection and coordinate liftover
+//!
+//! Most annotation releases are published on GRCh38 while many consumer
+//! exports still use GRCh37. [`Liftover`] converts coordinates with a UCSC
+//! chain file such as `hg19ToHg38.over.chain.gz`, and [`detect_build`]
+//! works out which build a genome is on when its header does not say.
+
+use crate::genome::{reverse_complement, GenomeBuild, StructuralVariant, Variant};
+use crate::parser::{compression, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use crate::stream::SiteFilter;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+/// Well-known SNPs with their GRCh37 and GRCh38 positions
+const BUILD_MARKERS: [(&str, &str, u64, u64); 10] = [
+    ("rs1801133", "1", 11856378, 11796321),
+    ("rs1800562", "6", 26093141, 26092913),
+    ("rs7903146", "10", 114758349, 112998590),
+    ("rs4149056", "12", 21331549, 21178615),
+    ("rs12913832", "15", 28365618, 28120472),
+    ("rs9923231", "16", 31107689, 31096368),
+    ("rs429358", "19", 45411941, 44908684),
+    ("rs7412", "19", 45412079, 44908822),
+    ("rs1333049", "9", 22125503, 22125504),
+    ("rs1800497", "11", 113270828, 113400106),
+];
+
+/// Where a position ends up on the target build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lifted {
+    Mapped {
+        chromosome: String,
+        position: u64,
+        /// The target sequence runs the other way, so alleles flip strand
+        reverse: bool,
+    },
+    /// The position is not covered by the chain file
+    Unmapped,
+    /// The position maps to more than one place
+    Ambiguous,
+}
+
+/// Counts from lifting one genome
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LiftoverStats {
+    pub from: GenomeBuild,
+    pub to: GenomeBuild,
+    pub lifted: usize,
+    /// Variants on positions the target build does not cover
+    pub dropped: usize,
+    /// Variants whose position maps to more than one place
+    pub ambiguous: usize,
+}
+
+/// One ungapped alignment block of a chain
+#[derive(Debug, Clone)]
+struct Block {
+    /// 0-based, half-open source interval
+    start: u64,
+    end: u64,
+    target_chromosome: String,
+    /// 0-based target position of `start` on the target strand
+    target_start: u64,
+    target_size: u64,
+    reverse: bool,
+}
+
+/// Coordinate mapping between two builds, read from a chain file
+#[derive(Debug)]
+pub struct Liftover {
+    from: GenomeBuild,
+    to: GenomeBuild,
+    /// Blocks per source chromosome, sorted by start
+    blocks: HashMap<String, Vec<Block>>,
+    /// Per chromosome, the furthest end of any block up to each index,
+    /// bounding how far back a lookup has to scan
+    reach: HashMap<String, Vec<u64>>,
+}
+
+impl Liftover {
+    /// Load a UCSC chain file, optionally gzip compressed
+    pub fn load(path: &Path, from: GenomeBuild, to: GenomeBuild) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_chain(reader, from, to)
+    }
+
+    /// Parse chain file text
+    pub fn from_chain<R: BufRead>(
+        reader: R,
+        from: GenomeBuild,
+        to: GenomeBuild,
+    ) -> Result<Self, String> {
+        let mut blocks: HashMap<String, Vec<Block>> = HashMap::new();
+        let mut chain: Option<ChainHeader> = None;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read chain file: {}", e))?;
+            let line_number = index + 1;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() || fields[0].starts_with('#') {
+                continue;
+            }
+
+            if fields[0] == "chain" {
+                chain = Some(
+                    ChainHeader::parse(&fields)
+                        .ok_or_else(|| format!("Invalid chain header on line {}", line_number))?,
+                );
+                continue;
+            }
+
+            let header = chain.as_mut().ok_or_else(|| {
+                format!(
+                    "Alignment data before a chain header on line {}",
+                    line_number
+                )
+            })?;
+            let numbers: Option<Vec<u64>> = fields.iter().map(|f| f.parse().ok()).collect();
+            let (size, gaps) = match numbers.as_deref() {
+                Some([size]) => (*size, None),
+                Some([size, source_gap, target_gap]) => (*size, Some((*source_gap, *target_gap))),
+                _ => return Err(format!("Invalid alignment data on line {}", line_number)),
+            };
+
+            blocks
+                .entry(header.source_chromosome.clone())
+                .or_default()
+                .push(Block {
+                    start: header.source_position,
+                    end: header.source_position + size,
+                    target_chromosome: header.target_chromosome.clone(),
+                    target_start: header.target_position,
+                    target_size: header.target_size,
+                    reverse: header.reverse,
+                });
+            header.source_position += size;
+            header.target_position += size;
+            match gaps {
+                Some((source_gap, target_gap)) => {
+                    header.source_position += source_gap;
+                    header.target_position += target_gap;
+                }
+                // The last block closes the chain
+                None => chain = None,
+            }
+        }
+
+        let mut reach = HashMap::with_capacity(blocks.len());
+        for (chromosome, chromosome_blocks) in blocks.iter_mut() {
+            chromosome_blocks.sort_by_key(|block| block.start);
+            let ends = chromosome_blocks
+                .iter()
+                .scan(0, |furthest, block| {
+                    *furthest = block.end.max(*furthest);
+                    Some(*furthest)
+                })
+                .collect();
+            reach.insert(chromosome.clone(), ends);
+        }
+
+        Ok(Liftover {
+            from,
+            to,
+            blocks,
+            reach,
+        })
+    }
+
+    /// Number of alignment blocks
+    pub fn len(&self) -> usize {
+        self.blocks.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn from_build(&self) -> GenomeBuild {
+        self.from
+    }
+
+    pub fn to_build(&self) -> GenomeBuild {
+        self.to
+    }
+
+    /// Map a 1-based position to the target build
+    pub fn lift(&self, chromosome: &str, position: u64) -> Lifted {
+        let chromosome = normalize_chromosome(chromosome);
+        let (Some(blocks), Some(reach)) =
+            (self.blocks.get(&chromosome), self.reach.get(&chromosome))
+        else {
+            return Lifted::Unmapped;
+        };
+        let Some(offset) = position.checked_sub(1) else {
+            return Lifted::Unmapped;
+        };
+
+        // Blocks of different chains may overlap, so scan back over every
+        // block that could still reach the position
+        let candidates = blocks.partition_point(|block| block.start <= offset);
+        let mut hits = blocks[..candidates]
+            .iter()
+            .zip(&reach[..candidates])
+            .rev()
+            .take_while(|(_, &furthest)| furthest > offset)
+            .map(|(block, _)| block)
+            .filter(|block| offset < block.end);
+        let Some(block) = hits.next() else {
+            return Lifted::Unmapped;
+        };
+        if hits.next().is_some() {
+            return Lifted::Ambiguous;
+        }
+
+        let target = block.target_start + (offset - block.start);
+        let position = if block.reverse {
+            block.target_size - target
+        } else {
+            target + 1
+        };
+        Lifted::Mapped {
+            chromosome: block.target_chromosome.clone(),
+            position,
+            reverse: block.reverse,
+        }
+    }
+
+    /// Move a variant to the target build
+    ///
+    /// Alleles are reverse complemented when the target runs the other way.
+    /// Returns the outcome so that dropped and ambiguous variants can be
+    /// counted.
+    pub fn lift_variant(&self, variant: &Variant) -> Result<Variant, Lifted> {
+        match self.lift(&variant.chromosome, variant.position) {
+            Lifted::Mapped {
+                chromosome,
+                position,
+                reverse,
+            } => {
+                let mut lifted = variant.clone();
+                lifted.chromosome = chromosome;
+                lifted.position = position;
+                if reverse {
+                    lifted.reference = variant.reference.as_deref().map(reverse_complement);
+                    lifted.alternates = variant
+                        .alternates
+                        .iter()
+                        .map(|allele| reverse_complement(allele))
+                        .collect();
+                    lifted.genotype = variant.genotype.complemented();
+                }
+                Ok(lifted)
+            }
+            other => Err(other),
+        }
+    }
+
+    /// Move a structural variant to the target build by both of its ends
+    ///
+    /// Ends that land on different chromosomes, or a span that changes
+    /// length tenfold, count as ambiguous.
+    pub fn lift_structural(
+        &self,
+        variant: &StructuralVariant,
+    ) -> Result<StructuralVariant, Lifted> {
+        let lift = |position| match self.lift(&variant.chromosome, position) {
+            Lifted::Mapped {
+                chromosome,
+                position,
+                ..
+            } => Ok((chromosome, position)),
+            other => Err(other),
+        };
+        let (chromosome, first) = lift(variant.start)?;
+        let (end_chromosome, last) = lift(variant.end)?;
+        let (start, end) = (first.min(last), first.max(last));
+        let (length, lifted_length) = (variant.end - variant.start + 1, end - start + 1);
+        if chromosome != end_chromosome
+            || lifted_length > length.saturating_mul(10)
+            || length > lifted_length.saturating_mul(10)
+        {
+            return Err(Lifted::Ambiguous);
+        }
+        Ok(StructuralVariant {
+            chromosome,
+            start,
+            end,
+            ..variant.clone()
+        })
+    }
+
+    /// Copy of a genome on the target build
+    ///
+    /// Variants that cannot be placed unambiguously are left out.
+    /// `checkpoint` is called with the number of variants processed every
+    /// [`CHECKPOINT_INTERVAL`] records.
+    pub fn lift_genome<F>(
+        &self,
+        genome: &LoadedGenome,
+        mut checkpoint: F,
+    ) -> Result<(LoadedGenome, LiftoverStats), String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut stats = LiftoverStats {
+            from: self.from,
+            to: self.to,
+            lifted: 0,
+            dropped: 0,
+            ambiguous: 0,
+        };
+        let mut variants = Vec::with_capacity(genome.len());
+
+        for (index, variant) in genome.variants().iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            match self.lift_variant(variant) {
+                Ok(lifted) => {
+                    stats.lifted += 1;
+                    variants.push(lifted);
+                }
+                Err(Lifted::Ambiguous) => stats.ambiguous += 1,
+                Err(_) => stats.dropped += 1,
+            }
+        }
+
+        let mut file = genome.file.clone();
+        file.genome_build = Some(self.to);
+        let genome = LoadedGenome::from_variants(file, genome.summary.clone(), variants);
+        Ok((genome, stats))
+    }
+}
+
+/// Add the SNPs [`detect_build`] votes with to `sites`
+pub fn add_marker_sites(sites: &mut SiteFilter) {
+    for (rsid, chromosome, position37, position38) in BUILD_MARKERS {
+        sites.add_rsid(rsid);
+        sites.add_position(chromosome, position37);
+        sites.add_position(chromosome, position38);
+    }
+}
+
+/// Build a genome is on, from its header or from well-known SNP positions
+///
+/// Header information wins. Otherwise each marker SNP found at its GRCh37
+/// or GRCh38 position counts as a vote, and the build with more votes is
+/// returned.
+pub fn detect_build(genome: &LoadedGenome) -> Option<GenomeBuild> {
+    if let Some(build) = genome.file.genome_build {
+        return Some(build);
+    }
+
+    let (mut grch37, mut grch38) = (0, 0);
+    for (rsid, chromosome, position37, position38) in BUILD_MARKERS {
+        let found = |position: u64| {
+            genome
+                .get_at(chromosome, position)
+                .is_some_and(|variant| variant.rsid.as_deref().is_none_or(|id| id == rsid))
+        };
+        match genome.get_by_rsid(rsid) {
+            Some(variant) if variant.position == position37 => grch37 += 1,
+            Some(variant) if variant.position == position38 => grch38 += 1,
+            Some(_) => {}
+            None => {
+                grch37 += usize::from(found(position37));
+                grch38 += usize::from(found(position38));
+            }
+        }
+    }
+
+    match grch37.cmp(&grch38) {
+        std::cmp::Ordering::Greater => Some(GenomeBuild::GRCh37),
+        std::cmp::Ordering::Less => Some(GenomeBuild::GRCh38),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+// Helper functions
+
+/// Fields of a `chain` line and the running positions within the chain
+#[derive(Debug)]
+struct ChainHeader {
+    source_chromosome: String,
+    source_position: u64,
+    target_chromosome: String,
+    target_position: u64,
+    target_size: u64,
+    reverse: bool,
+}
+
+impl ChainHeader {
+    /// `chain score tName tSize tStrand tStart tEnd qName qSize qStrand qStart qEnd id`
+    fn parse(fields: &[&str]) -> Option<Self> {
+        if fields.len() < 12 || fields[4] != "+" {
+            return None;
+        }
+        Some(ChainHeader {
+            source_chromosome: normalize_chromosome(fields[2]),
+            source_position: fields[5].parse().ok()?,
+            target_chromosome: normalize_chromosome(fields[7]),
+            target_position: fields[10].parse().ok()?,
+            target_size: fields[8].parse().ok()?,
+            reverse: fields[9] == "-",
+        })
+    }
+}
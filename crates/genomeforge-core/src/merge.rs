@@ -0,0 +1,333 @@
+//! Merging genome files of one person
+//!
+//! Each company's array genotypes a different set of sites, so combining a
+//! person's files gives more sites than any one of them. Sites are matched
+//! by rsid, then by position. Where the files disagree, the call most files
+//! agree on wins; a tie goes to the file with the higher call rate, the
+//! best measure of quality consumer exports offer. The provenance of every
+//! merged site records which files called it and which call was kept.
+//!
+//! The files must be on the same build, and should all be of one person:
+//! merging two people's files yields a genome of neither.
+
+use crate::compare::same_call;
+use crate::genome::{GenomeBuild, GenomeFile, Genotype, Variant};
+use crate::parser::detect::FileFormat;
+use crate::parser::{chromosome_sort_key, normalize_chromosome, SummaryBuilder};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most files merged at once; provenance keeps one bit per file
+pub const MAX_SOURCES: usize = 32;
+
+/// Conflicting sites listed in full; the rest are only counted
+pub const MAX_LISTED_CONFLICTS: usize = 1_000;
+
+/// A file that went into a merged genome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeSource {
+    pub format: FileFormat,
+    pub chip_version: Option<String>,
+    /// Hex SHA-256 of the file, when it was fingerprinted
+    pub sha256: Option<String>,
+    pub variant_count: usize,
+    pub call_rate: f64,
+}
+
+/// How the files that called a site agreed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Agreement {
+    /// No file called the site
+    NoCall,
+    /// Only one file called the site
+    Single,
+    Unanimous,
+    /// Most files agreed on the call kept
+    Majority,
+    /// As many files disagreed; the call of the file with the highest call
+    /// rate was kept
+    Tie,
+}
+
+/// Where a merged site's call came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SiteProvenance {
+    /// Index of the file whose call was kept
+    pub source: usize,
+    /// Bit `i` is set when file `i` called the site
+    pub called_by: u32,
+    pub agreement: Agreement,
+}
+
+impl SiteProvenance {
+    /// Indexes of the files that called the site
+    pub fn sources(&self) -> Vec<usize> {
+        (0..MAX_SOURCES)
+            .filter(|i| self.called_by & (1 << i) != 0)
+            .collect()
+    }
+}
+
+/// A site the files called differently
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeConflict {
+    pub rsid: Option<String>,
+    pub chromosome: String,
+    pub position: u64,
+    /// The call of each file that called the site, by file index
+    pub calls: Vec<(usize, Genotype)>,
+    pub kept: Genotype,
+    pub agreement: Agreement,
+}
+
+/// Counts of how merged sites were resolved
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeStats {
+    pub sites: usize,
+    pub no_call: usize,
+    pub single: usize,
+    pub unanimous: usize,
+    pub majority: usize,
+    pub tie: usize,
+    /// Sites each file called that no other did, by file index
+    pub unique_by_source: Vec<usize>,
+}
+
+/// Genome merged from several files, with the provenance of every site
+#[derive(Debug)]
+pub struct Merged {
+    pub genome: LoadedGenome,
+    /// Provenance of each variant of `genome`, in the same order
+    pub provenance: Vec<SiteProvenance>,
+    pub sources: Vec<MergeSource>,
+    pub stats: MergeStats,
+    /// The first [`MAX_LISTED_CONFLICTS`] sites the files disagreed on
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A merged site before its call is chosen
+struct Site {
+    variant: Variant,
+    /// Index of the first file listing the site
+    listed_by: usize,
+    calls: Vec<(usize, Genotype)>,
+}
+
+/// Merge genome files of one person into one genome
+///
+/// `checkpoint` is called with the number of variants read every
+/// [`CHECKPOINT_INTERVAL`] variants and stops the merge when it returns an
+/// error.
+pub fn merge<F>(genomes: &[&LoadedGenome], mut checkpoint: F) -> Result<Merged, String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    if genomes.len() < 2 {
+        return Err("Choose at least two files to merge".to_string());
+    }
+    if genomes.len() > MAX_SOURCES {
+        return Err(format!("At most {} files can be merged", MAX_SOURCES));
+    }
+    let build = common_build(genomes)?;
+
+    let mut sites: Vec<Site> = Vec::new();
+    let mut by_rsid: HashMap<String, usize> = HashMap::new();
+    let mut by_position: HashMap<(String, u64), usize> = HashMap::new();
+    let mut read = 0;
+    for (source, genome) in genomes.iter().enumerate() {
+        for variant in genome.variants() {
+            if read % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(read)?;
+            }
+            read += 1;
+            let position = (normalize_chromosome(&variant.chromosome), variant.position);
+            let found = variant
+                .rsid
+                .as_ref()
+                .and_then(|rsid| by_rsid.get(rsid))
+                .or_else(|| by_position.get(&position))
+                .copied();
+            let index = match found {
+                Some(index) => index,
+                None => {
+                    sites.push(Site {
+                        variant: variant.clone(),
+                        listed_by: source,
+                        calls: Vec::new(),
+                    });
+                    by_position.insert(position, sites.len() - 1);
+                    sites.len() - 1
+                }
+            };
+            if let Some(rsid) = &variant.rsid {
+                by_rsid.entry(rsid.clone()).or_insert(index);
+            }
+            // A file listing a site twice keeps its first call, as loading does
+            let site = &mut sites[index];
+            if site.variant.rsid.is_none() {
+                site.variant.rsid = variant.rsid.clone();
+            }
+            if !variant.genotype.is_no_call() && site.calls.iter().all(|(s, _)| *s != source) {
+                site.calls.push((source, variant.genotype.clone()));
+            }
+        }
+    }
+    checkpoint(read)?;
+
+    // Files ranked by call rate, best first, to break ties
+    let mut rank: Vec<usize> = (0..genomes.len()).collect();
+    rank.sort_by(|a, b| {
+        genomes[*b]
+            .summary
+            .call_rate
+            .total_cmp(&genomes[*a].summary.call_rate)
+    });
+    let rank_of = |source: usize| rank.iter().position(|s| *s == source).unwrap_or(usize::MAX);
+
+    sites.sort_by(|a, b| {
+        chromosome_sort_key(&a.variant.chromosome)
+            .cmp(&chromosome_sort_key(&b.variant.chromosome))
+            .then(a.variant.position.cmp(&b.variant.position))
+    });
+    let mut stats = MergeStats {
+        sites: sites.len(),
+        unique_by_source: vec![0; genomes.len()],
+        ..MergeStats::default()
+    };
+    let mut conflicts = Vec::new();
+    let mut provenance = Vec::with_capacity(sites.len());
+    let mut variants = Vec::with_capacity(sites.len());
+    for site in sites {
+        let (resolved, variant) = resolve(site, &rank_of, &mut conflicts);
+        match resolved.agreement {
+            Agreement::NoCall => stats.no_call += 1,
+            Agreement::Single => {
+                stats.single += 1;
+                stats.unique_by_source[resolved.source] += 1;
+            }
+            Agreement::Unanimous => stats.unanimous += 1,
+            Agreement::Majority => stats.majority += 1,
+            Agreement::Tie => stats.tie += 1,
+        }
+        provenance.push(resolved);
+        variants.push(variant);
+    }
+
+    let mut builder = SummaryBuilder::default();
+    for variant in &variants {
+        builder.add(variant);
+    }
+    let file = GenomeFile {
+        genome_build: build,
+        fingerprint: None,
+        ..genomes[rank[0]].file.clone()
+    };
+    Ok(Merged {
+        genome: LoadedGenome::from_variants(file, builder.finish(0), variants),
+        provenance,
+        sources: genomes.iter().map(|genome| source_of(genome)).collect(),
+        stats,
+        conflicts,
+    })
+}
+
+// Helper functions
+
+/// The build every file is on, failing when two files name different ones
+fn common_build(genomes: &[&LoadedGenome]) -> Result<Option<GenomeBuild>, String> {
+    let mut builds = genomes.iter().filter_map(|genome| genome.file.genome_build);
+    let first = builds.next();
+    if builds.any(|build| Some(build) != first) {
+        return Err(
+            "The files are on different reference builds; lift them over to the same build first"
+                .to_string(),
+        );
+    }
+    Ok(first)
+}
+
+fn source_of(genome: &LoadedGenome) -> MergeSource {
+    MergeSource {
+        format: genome.file.format,
+        chip_version: genome.file.chip_version.clone(),
+        sha256: genome.file.fingerprint.as_ref().map(|fp| fp.sha256.clone()),
+        variant_count: genome.len(),
+        call_rate: genome.summary.call_rate,
+    }
+}
+
+/// Choose a site's call, recording a conflict when the files disagree
+fn resolve(
+    site: Site,
+    rank_of: &impl Fn(usize) -> usize,
+    conflicts: &mut Vec<MergeConflict>,
+) -> (SiteProvenance, Variant) {
+    let Site {
+        mut variant,
+        listed_by,
+        calls,
+    } = site;
+    let called_by = calls.iter().fold(0u32, |bits, (s, _)| bits | (1 << s));
+
+    // Files grouped by the call they agree on
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (index, (_, genotype)) in calls.iter().enumerate() {
+        match groups
+            .iter_mut()
+            .find(|group| same_call(&calls[group[0]].1, genotype))
+        {
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
+        }
+    }
+    let best_rank = |group: &Vec<usize>| {
+        group
+            .iter()
+            .map(|&index| rank_of(calls[index].0))
+            .min()
+            .unwrap_or(usize::MAX)
+    };
+    groups.sort_by(|a, b| b.len().cmp(&a.len()).then(best_rank(a).cmp(&best_rank(b))));
+
+    let Some(winner) = groups.first() else {
+        variant.genotype = Genotype::NoCall;
+        let provenance = SiteProvenance {
+            source: listed_by,
+            called_by,
+            agreement: Agreement::NoCall,
+        };
+        return (provenance, variant);
+    };
+    let agreement = match (calls.len(), groups.len()) {
+        (1, _) => Agreement::Single,
+        (_, 1) => Agreement::Unanimous,
+        _ if winner.len() > groups[1].len() => Agreement::Majority,
+        _ => Agreement::Tie,
+    };
+    let chosen = winner
+        .iter()
+        .copied()
+        .min_by_key(|&index| rank_of(calls[index].0))
+        .expect("groups are never empty");
+    let (source, genotype) = calls[chosen].clone();
+
+    if groups.len() > 1 && conflicts.len() < MAX_LISTED_CONFLICTS {
+        conflicts.push(MergeConflict {
+            rsid: variant.rsid.clone(),
+            chromosome: variant.chromosome.clone(),
+            position: variant.position,
+            calls: calls.clone(),
+            kept: genotype.clone(),
+            agreement,
+        });
+    }
+    variant.genotype = genotype;
+    let provenance = SiteProvenance {
+        source,
+        called_by,
+        agreement,
+    };
+    (provenance, variant)
+}
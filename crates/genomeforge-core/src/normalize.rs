@@ -0,0 +1,410 @@
+//! Variant normalization for VCF input
+//!
+//! The same indel can be written several ways in a VCF: with extra shared
+//! bases, or anywhere along a repeat. ClinVar and gnomAD publish each
+//! variant in one form, split into biallelic records, with redundant bases
+//! trimmed and indels shifted as far left as the reference allows. A VCF
+//! record in any other form would silently fail to match, so VCF genomes
+//! are brought into that form first:
+//!
+//! 1. Multi-allelic records are split into one record per alternate allele.
+//!    Genotype calls of the other alternates become the reference allele.
+//! 2. Bases shared at the end, then at the start, of both alleles are
+//!    trimmed, keeping at least one base in each.
+//! 3. With a reference sequence, indels are shifted left through repeats.
+//!
+//! Array calls, which have no reference allele, pass through unchanged.
+
+use crate::annotation::is_allele_sequence;
+use crate::genome::{Genotype, Variant};
+use crate::parser::normalize_chromosome;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Bases of a reference genome
+pub trait ReferenceSequence: Send + Sync {
+    /// Bases from `start` to `end`, 1-based and inclusive, in upper case
+    ///
+    /// `None` when the chromosome is unknown or the range runs past its end.
+    fn bases(&self, chromosome: &str, start: u64, end: u64) -> Result<Option<String>, String>;
+}
+
+/// What normalization changed
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct NormalizationStats {
+    /// Multi-allelic records split into biallelic ones
+    pub records_split: usize,
+    /// Records whose alleles had redundant bases trimmed
+    pub records_trimmed: usize,
+    /// Indels shifted left through a repeat
+    pub indels_left_aligned: usize,
+    /// Records whose reference allele disagrees with the reference
+    /// sequence, left as they were
+    pub reference_mismatches: usize,
+}
+
+/// Normalize every VCF record of a genome
+///
+/// Without a `reference`, records are split and trimmed but not shifted.
+/// `checkpoint` is called every [`CHECKPOINT_INTERVAL`] variants.
+pub fn normalize_genome<F>(
+    genome: &LoadedGenome,
+    reference: Option<&dyn ReferenceSequence>,
+    mut checkpoint: F,
+) -> Result<(LoadedGenome, NormalizationStats), String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut stats = NormalizationStats::default();
+    let mut variants = Vec::with_capacity(genome.len());
+    for (index, variant) in genome.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        variants.extend(normalize_variant(variant, reference, &mut stats)?);
+    }
+    let normalized =
+        LoadedGenome::from_variants(genome.file.clone(), genome.summary.clone(), variants);
+    Ok((normalized, stats))
+}
+
+/// Biallelic, trimmed and left-aligned records for one variant
+pub fn normalize_variant(
+    variant: &Variant,
+    reference: Option<&dyn ReferenceSequence>,
+    stats: &mut NormalizationStats,
+) -> Result<Vec<Variant>, String> {
+    let Some(reference_allele) = &variant.reference else {
+        return Ok(vec![variant.clone()]);
+    };
+    if variant.alternates.is_empty() {
+        return Ok(vec![variant.clone()]);
+    }
+    if variant.is_multiallelic() {
+        stats.records_split += 1;
+    }
+    if let Some(reference) = reference {
+        let end = variant.position + reference_allele.len() as u64 - 1;
+        let expected = reference.bases(&variant.chromosome, variant.position, end)?;
+        if expected.is_some_and(|bases| !bases.eq_ignore_ascii_case(reference_allele)) {
+            stats.reference_mismatches += 1;
+            return Ok(vec![variant.clone()]);
+        }
+    }
+
+    let mut records = Vec::with_capacity(variant.alternates.len());
+    for alternate in &variant.alternates {
+        let mut position = variant.position;
+        let (mut new_reference, mut new_alternate) = (
+            reference_allele.to_ascii_uppercase(),
+            alternate.to_ascii_uppercase(),
+        );
+        if is_allele_sequence(&new_reference) && is_allele_sequence(&new_alternate) {
+            let original = (position, new_reference.clone(), new_alternate.clone());
+            trim(&mut position, &mut new_reference, &mut new_alternate);
+            let trimmed = (position, new_reference.clone(), new_alternate.clone());
+            if let Some(reference) = reference {
+                left_align(
+                    reference,
+                    &variant.chromosome,
+                    &mut position,
+                    &mut new_reference,
+                    &mut new_alternate,
+                )?;
+            }
+            if trimmed != original {
+                stats.records_trimmed += 1;
+            }
+            if (position, &new_reference, &new_alternate) != (trimmed.0, &trimmed.1, &trimmed.2) {
+                stats.indels_left_aligned += 1;
+            }
+        }
+
+        let rename = |allele: &String| -> String {
+            if allele == alternate {
+                new_alternate.clone()
+            } else {
+                new_reference.clone()
+            }
+        };
+        let genotype = match &variant.genotype {
+            Genotype::NoCall => Genotype::NoCall,
+            Genotype::Haploid(allele) => Genotype::Haploid(rename(allele)),
+            Genotype::Diploid {
+                first,
+                second,
+                phased,
+            } => Genotype::Diploid {
+                first: rename(first),
+                second: rename(second),
+                phased: *phased,
+            },
+        };
+        records.push(Variant {
+            rsid: variant.rsid.clone(),
+            chromosome: variant.chromosome.clone(),
+            position,
+            reference: Some(new_reference.clone()),
+            alternates: vec![new_alternate.clone()],
+            genotype,
+            quality: variant.quality.clone(),
+        });
+    }
+    Ok(records)
+}
+
+/// A FASTA file with a samtools `.fai` index next to it
+///
+/// The index is built in memory when the `.fai` file is missing. Only
+/// uncompressed FASTA can be read at random.
+#[derive(Debug)]
+pub struct IndexedFasta {
+    path: PathBuf,
+    file: Mutex<File>,
+    contigs: HashMap<String, FaiEntry>,
+}
+
+/// One line of a `.fai` index
+#[derive(Debug, Clone, Copy)]
+struct FaiEntry {
+    length: u64,
+    offset: u64,
+    line_bases: u64,
+    line_width: u64,
+}
+
+impl IndexedFasta {
+    /// Open a FASTA file, reading or building its index
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let mut magic = [0u8; 2];
+        let mut file =
+            File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        if file.read(&mut magic).map_err(|e| e.to_string())? == 2 && magic == [0x1f, 0x8b] {
+            return Err("Compressed reference FASTA is not supported; decompress it first".into());
+        }
+
+        let mut index = path.as_os_str().to_owned();
+        index.push(".fai");
+        let index = PathBuf::from(index);
+        let contigs = if index.is_file() {
+            read_fai(&index)?
+        } else {
+            build_fai(path)?
+                .into_iter()
+                .map(|(name, entry)| (normalize_chromosome(&name), entry))
+                .collect()
+        };
+        if contigs.is_empty() {
+            return Err(format!("{} holds no sequences", path.display()));
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            contigs,
+        })
+    }
+
+    /// Path of the FASTA file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Names of the sequences, normalized like variant chromosomes
+    pub fn chromosomes(&self) -> Vec<&str> {
+        self.contigs.keys().map(String::as_str).collect()
+    }
+
+    /// Number of bases in a sequence, if the FASTA holds it
+    pub fn length(&self, chromosome: &str) -> Option<u64> {
+        self.contigs
+            .get(&normalize_chromosome(chromosome))
+            .map(|entry| entry.length)
+    }
+}
+
+/// Write a samtools `.fai` index next to a FASTA file, returning its path
+pub fn write_index(path: &Path) -> Result<PathBuf, String> {
+    let mut index = path.as_os_str().to_owned();
+    index.push(".fai");
+    let index = PathBuf::from(index);
+    let mut lines = String::new();
+    for (name, entry) in build_fai(path)? {
+        lines.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            name, entry.length, entry.offset, entry.line_bases, entry.line_width
+        ));
+    }
+    std::fs::write(&index, lines)
+        .map_err(|e| format!("Failed to write {}: {}", index.display(), e))?;
+    Ok(index)
+}
+
+impl ReferenceSequence for IndexedFasta {
+    fn bases(&self, chromosome: &str, start: u64, end: u64) -> Result<Option<String>, String> {
+        let Some(entry) = self.contigs.get(&normalize_chromosome(chromosome)) else {
+            return Ok(None);
+        };
+        if start == 0 || end < start || end > entry.length {
+            return Ok(None);
+        }
+        let file_offset = |base: u64| {
+            let base = base - 1;
+            entry.offset + base / entry.line_bases * entry.line_width + base % entry.line_bases
+        };
+        let (first, last) = (file_offset(start), file_offset(end));
+
+        let mut raw = vec![0u8; (last - first + 1) as usize];
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.seek(SeekFrom::Start(first))
+            .and_then(|_| file.read_exact(&mut raw))
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?;
+        let bases: String = raw
+            .iter()
+            .filter(|b| !b.is_ascii_whitespace())
+            .map(|b| b.to_ascii_uppercase() as char)
+            .collect();
+        Ok(Some(bases))
+    }
+}
+
+// Helper functions
+
+/// Drop bases shared at the end, then at the start, keeping one in each
+fn trim(position: &mut u64, reference: &mut String, alternate: &mut String) {
+    while reference.len() > 1 && alternate.len() > 1 && same_last_base(reference, alternate) {
+        reference.pop();
+        alternate.pop();
+    }
+    while reference.len() > 1 && alternate.len() > 1 && reference[..1] == alternate[..1] {
+        reference.remove(0);
+        alternate.remove(0);
+        *position += 1;
+    }
+}
+
+/// Shift an indel left while its last bases agree (Tan et al. 2015)
+fn left_align(
+    sequence: &dyn ReferenceSequence,
+    chromosome: &str,
+    position: &mut u64,
+    reference: &mut String,
+    alternate: &mut String,
+) -> Result<(), String> {
+    if reference.len() == alternate.len() {
+        return Ok(());
+    }
+    loop {
+        let mut changed = false;
+        if same_last_base(reference, alternate) {
+            reference.pop();
+            alternate.pop();
+            changed = true;
+        }
+        if reference.is_empty() || alternate.is_empty() {
+            if *position <= 1 {
+                break;
+            }
+            let Some(base) = sequence.bases(chromosome, *position - 1, *position - 1)? else {
+                break;
+            };
+            reference.insert_str(0, &base);
+            alternate.insert_str(0, &base);
+            *position -= 1;
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+    while reference.len() > 1 && alternate.len() > 1 && reference[..1] == alternate[..1] {
+        reference.remove(0);
+        alternate.remove(0);
+        *position += 1;
+    }
+    Ok(())
+}
+
+/// Whether both alleles end in the same base
+fn same_last_base(reference: &str, alternate: &str) -> bool {
+    !reference.is_empty() && reference.as_bytes().last() == alternate.as_bytes().last()
+}
+
+fn read_fai(path: &Path) -> Result<HashMap<String, FaiEntry>, String> {
+    let failed = |e: std::io::Error| format!("Failed to read {}: {}", path.display(), e);
+    let reader = BufReader::new(File::open(path).map_err(failed)?);
+    let mut contigs = HashMap::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(failed)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        let field = |index: usize| -> Result<u64, String> {
+            columns
+                .get(index)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| format!("Invalid FASTA index line {}", number + 1))
+        };
+        let entry = FaiEntry {
+            length: field(1)?,
+            offset: field(2)?,
+            line_bases: field(3)?,
+            line_width: field(4)?,
+        };
+        if entry.line_bases == 0 || entry.line_width < entry.line_bases {
+            return Err(format!("Invalid FASTA index line {}", number + 1));
+        }
+        contigs.insert(normalize_chromosome(columns[0]), entry);
+    }
+    Ok(contigs)
+}
+
+/// Index a FASTA file the way `samtools faidx` does, keeping the sequence
+/// names and their order
+fn build_fai(path: &Path) -> Result<Vec<(String, FaiEntry)>, String> {
+    let failed = |e: std::io::Error| format!("Failed to read {}: {}", path.display(), e);
+    let mut reader = BufReader::new(File::open(path).map_err(failed)?);
+    let mut contigs = Vec::new();
+    let mut current: Option<(String, FaiEntry)> = None;
+    let mut offset = 0u64;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line).map_err(failed)? as u64;
+        if read == 0 {
+            break;
+        }
+        offset += read;
+        if line.starts_with(b">") {
+            contigs.extend(current.take());
+            let header = String::from_utf8_lossy(&line[1..]);
+            let name = header.split_whitespace().next().unwrap_or_default();
+            current = Some((
+                name.to_string(),
+                FaiEntry {
+                    length: 0,
+                    offset,
+                    line_bases: 0,
+                    line_width: 0,
+                },
+            ));
+            continue;
+        }
+        if let Some((_, entry)) = current.as_mut() {
+            let bases = line.iter().filter(|b| !b.is_ascii_whitespace()).count() as u64;
+            if entry.line_bases == 0 {
+                entry.line_bases = bases;
+                entry.line_width = read;
+            }
+            entry.length += bases;
+        }
+    }
+    contigs.extend(current);
+    contigs.retain(|(_, entry)| entry.line_bases > 0);
+    Ok(contigs)
+}
@@ -0,0 +1,163 @@
+//! Bookmarks and notes on findings
+//!
+//! A counselor stars findings to come back to and writes notes on them to
+//! discuss with the client. Each entry is attached to a finding by its
+//! section and a key that identifies it whichever analysis reported it,
+//! such as its variant and condition, so it carries over to the next
+//! analysis. Notes are the user's own words about a person's genome, so the
+//! [`NoteBook`] is sealed with [`crypto::write_file`] like the fingerprint
+//! log.
+
+use crate::crypto::{self, KeySource, Zeroizing};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File name of a profile's notebook
+pub const FILE_NAME: &str = "notes.gfnotes";
+
+/// Longest note kept, in characters
+pub const MAX_NOTE_LENGTH: usize = 10_000;
+
+/// Kind recorded in the header of notebooks
+const KIND: &str = "finding-notes";
+
+/// Which finding an entry is on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FindingRef {
+    /// Part of the results the finding is in, e.g. "clinical"
+    pub section: String,
+    /// What identifies the finding within its section
+    pub key: String,
+}
+
+/// A bookmark or note on a finding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FindingNote {
+    #[serde(flatten)]
+    pub finding: FindingRef,
+    /// What the finding was shown as when the entry was last changed
+    pub label: String,
+    pub bookmarked: bool,
+    /// Free text; empty without a note
+    #[serde(default)]
+    pub note: String,
+    /// Seconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+/// A profile's bookmarks and notes
+#[derive(Debug, Default)]
+pub struct NoteBook {
+    entries: BTreeMap<FindingRef, FindingNote>,
+}
+
+#[derive(Serialize)]
+struct BookInfo {
+    entries: usize,
+}
+
+impl NoteBook {
+    /// Read the notebook at `path`; a missing file is an empty notebook
+    pub fn open(path: &Path, key: KeySource<'_>) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let (_, plaintext) = crypto::read_file::<serde_json::Value>(path, KIND, key)?;
+        let entries: Vec<FindingNote> = serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Notebook is corrupt: {}", e))?;
+        Ok(NoteBook {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.finding.clone(), entry))
+                .collect(),
+        })
+    }
+
+    /// Write the notebook to `path`
+    pub fn save(&self, path: &Path, key: KeySource<'_>) -> Result<(), String> {
+        let entries: Vec<&FindingNote> = self.entries.values().collect();
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(&entries)
+                .map_err(|e| format!("Failed to serialize notes: {}", e))?,
+        );
+        let info = BookInfo {
+            entries: entries.len(),
+        };
+        crypto::write_file(path, KIND, &info, &plaintext, key).map(|_| ())
+    }
+
+    pub fn get(&self, finding: &FindingRef) -> Option<&FindingNote> {
+        self.entries.get(finding)
+    }
+
+    /// Every entry, by section and key
+    pub fn entries(&self) -> impl Iterator<Item = &FindingNote> {
+        self.entries.values()
+    }
+
+    /// Star or unstar a finding, returning its entry unless it has neither
+    /// star nor note left
+    pub fn bookmark(
+        &mut self,
+        finding: FindingRef,
+        label: &str,
+        bookmarked: bool,
+        now: u64,
+    ) -> Option<&FindingNote> {
+        self.change(finding, label, now, |entry| entry.bookmarked = bookmarked)
+    }
+
+    /// Replace the note on a finding, an empty one removing it; returns
+    /// the entry unless it has neither star nor note left
+    pub fn set_note(
+        &mut self,
+        finding: FindingRef,
+        label: &str,
+        note: &str,
+        now: u64,
+    ) -> Result<Option<&FindingNote>, String> {
+        let note = note.trim();
+        if note.chars().count() > MAX_NOTE_LENGTH {
+            return Err(format!(
+                "Notes are limited to {} characters",
+                MAX_NOTE_LENGTH
+            ));
+        }
+        Ok(self.change(finding, label, now, |entry| entry.note = note.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn change(
+        &mut self,
+        finding: FindingRef,
+        label: &str,
+        now: u64,
+        edit: impl FnOnce(&mut FindingNote),
+    ) -> Option<&FindingNote> {
+        let mut entry = self
+            .entries
+            .remove(&finding)
+            .unwrap_or_else(|| FindingNote {
+                finding: finding.clone(),
+                label: String::new(),
+                bookmarked: false,
+                note: String::new(),
+                updated_at: now,
+            });
+        edit(&mut entry);
+        if !entry.bookmarked && entry.note.is_empty() {
+            return None;
+        }
+        entry.label = label.to_string();
+        entry.updated_at = now;
+        Some(self.entries.entry(finding).or_insert(entry))
+    }
+}
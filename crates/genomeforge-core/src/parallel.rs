@@ -0,0 +1,102 @@
+//! Parallel annotation
+//!
+//! Matching a genome against a database is independent for every variant,
+//! so the work is split into shards that a pool of scoped threads takes in
+//! turn, the next free thread picking up the next shard. Genomes are
+//! sharded by chromosome; database records, which are looked up in the
+//! genome, in even chunks. Results are put back together in shard order,
+//! so a parallel annotation returns exactly what a sequential one would.
+
+use crate::genome::Variant;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Smallest shard worth handing to a thread
+pub const MIN_SHARD_LEN: usize = 10_000;
+
+/// Shards per thread, so threads finishing early find more work
+const SHARDS_PER_THREAD: usize = 4;
+
+/// Threads the machine can run at once
+pub fn available_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Index ranges of `variants`, one per run of a chromosome
+///
+/// Runs shorter than [`MIN_SHARD_LEN`] are merged with their neighbours,
+/// so an unsorted file does not turn into millions of tiny shards.
+pub fn chromosome_shards(variants: &[Variant]) -> Vec<Range<usize>> {
+    let mut shards: Vec<Range<usize>> = Vec::new();
+    let mut start = 0;
+    for index in 1..=variants.len() {
+        let boundary =
+            index == variants.len() || variants[index].chromosome != variants[index - 1].chromosome;
+        if !boundary {
+            continue;
+        }
+        match shards.last_mut() {
+            Some(last) if last.len() < MIN_SHARD_LEN || index - start < MIN_SHARD_LEN => {
+                last.end = index;
+            }
+            _ => shards.push(start..index),
+        }
+        start = index;
+    }
+    shards
+}
+
+/// `len` items split into even ranges for `threads` threads
+pub fn chunks(len: usize, threads: usize) -> Vec<Range<usize>> {
+    let count = (threads * SHARDS_PER_THREAD).clamp(1, len.div_ceil(MIN_SHARD_LEN).max(1));
+    let size = len.div_ceil(count).max(1);
+    (0..len)
+        .step_by(size)
+        .map(|start| start..(start + size).min(len))
+        .collect()
+}
+
+/// Run `work` on every shard with up to `threads` threads
+///
+/// Results are in shard order. The first error stops the remaining shards
+/// and is returned, as is the error of the earliest failing shard when
+/// several fail.
+pub fn map<T, F>(shards: &[Range<usize>], threads: usize, work: F) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(Range<usize>) -> Result<T, String> + Sync,
+{
+    let threads = threads.clamp(1, shards.len().max(1));
+    if threads == 1 {
+        return shards.iter().map(|shard| work(shard.clone())).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<Result<T, String>>>> =
+        Mutex::new((0..shards.len()).map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while !failed.load(Ordering::Relaxed) {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(shard) = shards.get(index) else {
+                        break;
+                    };
+                    let result = work(shard.clone());
+                    failed.fetch_or(result.is_err(), Ordering::Relaxed);
+                    results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    // Shards are only skipped after another failed, whose error is found
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .flatten()
+        .collect()
+}
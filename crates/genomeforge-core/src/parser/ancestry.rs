@@ -0,0 +1,93 @@
+//! AncestryDNA raw data parser
+//!
+//! AncestryDNA exports are tab-separated with a `#` comment block, a column
+//! header of `rsid chromosome position allele1 allele2`, and the two alleles
+//! in separate columns. Chromosomes are numbered 1-26, where 23-26 stand for
+//! X, Y, the pseudoautosomal region and mitochondrial DNA.
+
+use super::raw::{array_variant, is_allele, is_snp_id, RawDataFormat};
+use crate::genome::Variant;
+
+/// Column header written by AncestryDNA
+const COLUMN_HEADER: [&str; 5] = ["rsid", "chromosome", "position", "allele1", "allele2"];
+
+/// `rsid<TAB>chromosome<TAB>position<TAB>allele1<TAB>allele2`
+pub struct AncestryDna;
+
+impl RawDataFormat for AncestryDna {
+    const NAME: &'static str = "AncestryDNA";
+
+    fn is_column_header(line: &str) -> bool {
+        let columns: Vec<String> = line
+            .split(['\t', ','])
+            .map(|c| c.trim().to_ascii_lowercase())
+            .collect();
+        columns == COLUMN_HEADER
+    }
+
+    fn parse_line(line: &str) -> Option<Variant> {
+        // Older exports and the web parser fixtures use commas
+        let separator = if line.contains('\t') { '\t' } else { ',' };
+        let mut fields = line.split(separator).map(str::trim);
+        let rsid = fields.next()?;
+        let chromosome = fields.next()?;
+        let position = fields.next()?;
+        let allele1 = fields.next()?.to_ascii_uppercase();
+        let allele2 = fields.next()?.to_ascii_uppercase();
+
+        if !is_snp_id(rsid) {
+            return None;
+        }
+        let chromosome = map_chromosome(chromosome)?;
+        let position = position.parse::<u64>().ok()?;
+
+        // A no-call is "0" in either allele column
+        let genotype = if allele1 == "0" || allele2 == "0" {
+            "--".to_string()
+        } else if allele1.len() == 1
+            && allele2.len() == 1
+            && allele1.chars().chain(allele2.chars()).all(is_allele)
+        {
+            format!("{}{}", allele1, allele2)
+        } else {
+            return None;
+        };
+
+        Some(array_variant(rsid, &chromosome, position, &genotype))
+    }
+
+    fn chip_version(comments: &[String]) -> Option<String> {
+        detect_array_version(comments)
+    }
+}
+
+/// Whether the first lines of a file look like an AncestryDNA export
+pub fn is_ancestry_header(comments: &[String], first_line: Option<&str>) -> bool {
+    comments.iter().any(|c| c.contains("AncestryDNA"))
+        || first_line.is_some_and(AncestryDna::is_column_header)
+}
+
+/// Array version from the "array version: V2.0" comment
+pub fn detect_array_version(comments: &[String]) -> Option<String> {
+    comments.iter().find_map(|comment| {
+        let (_, version) = comment.split_once("array version:")?;
+        Some(version.trim().to_string())
+    })
+}
+
+// Helper functions
+
+/// Translate AncestryDNA's numeric sex and mitochondrial chromosome codes
+fn map_chromosome(raw: &str) -> Option<String> {
+    let chromosome = match raw {
+        "23" => "X",
+        "24" => "Y",
+        // Pseudoautosomal SNPs sit on both X and Y; report them against X
+        "25" => "X",
+        "26" => "MT",
+        other if other.parse::<u8>().is_ok_and(|n| (1..=22).contains(&n)) => other,
+        "X" | "Y" | "MT" => raw,
+        _ => return None,
+    };
+    Some(chromosome.to_string())
+}
@@ -0,0 +1,266 @@
+//! BGZF (blocked gzip) reading and writing
+//!
+//! BGZF files are concatenated gzip members of at most 64 KiB each, as
+//! written by `bgzip`. A position in the uncompressed stream is addressed
+//! by a virtual offset: the file offset of the block holding it, shifted
+//! left 16 bits, plus the offset within the decompressed block. Tabix and
+//! CSI indexes store these so a reader can seek straight to a region.
+
+use super::compression::{self, Compression};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Crc;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Most uncompressed bytes put in one block, leaving room for the
+/// compressed data to fit even when it does not shrink
+const BLOCK_DATA_SIZE: usize = 0xff00;
+
+/// Length of a BGZF member header including the "BC" subfield
+const HEADER_SIZE: usize = 18;
+
+/// CRC32 and input size after the compressed data
+const FOOTER_SIZE: usize = 8;
+
+/// Empty block every BGZF file ends with
+const EOF_BLOCK: [u8; 28] = [
+    0x1f, 0x8b, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x06, 0x00, 0x42, 0x43, 0x02, 0x00,
+    0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// A position in a BGZF file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtualOffset(pub u64);
+
+impl VirtualOffset {
+    pub fn new(block: u64, within_block: u16) -> Self {
+        Self(block << 16 | within_block as u64)
+    }
+
+    /// File offset of the block
+    pub fn block(self) -> u64 {
+        self.0 >> 16
+    }
+
+    /// Offset within the decompressed block
+    pub fn within_block(self) -> u16 {
+        self.0 as u16
+    }
+}
+
+/// Reads the decompressed stream of a BGZF file
+pub struct BgzfReader<R: Read> {
+    inner: R,
+    /// File offset of the current block
+    block_offset: u64,
+    /// File offset of the next block
+    next_block_offset: u64,
+    data: Vec<u8>,
+    position: usize,
+    compressed: Vec<u8>,
+}
+
+impl<R: Read> BgzfReader<R> {
+    /// Read from the start of `inner`
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            block_offset: 0,
+            next_block_offset: 0,
+            data: Vec::new(),
+            position: 0,
+            compressed: Vec::new(),
+        }
+    }
+
+    /// Virtual offset of the next byte to be read
+    pub fn virtual_position(&self) -> VirtualOffset {
+        // A fully read block is the same place as the start of the next
+        if self.position == self.data.len() {
+            VirtualOffset::new(self.next_block_offset, 0)
+        } else {
+            VirtualOffset::new(self.block_offset, self.position as u16)
+        }
+    }
+
+    /// Decompress the next block; false at the end of the file
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut header = [0u8; HEADER_SIZE];
+        match self.inner.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        if compression::detect_compression(&header) != Compression::Bgzip {
+            return Err(invalid("not a BGZF block"));
+        }
+        let block_size = u16::from_le_bytes([header[16], header[17]]) as usize + 1;
+        if block_size < HEADER_SIZE + FOOTER_SIZE {
+            return Err(invalid("BGZF block is too short"));
+        }
+        self.compressed.resize(block_size - HEADER_SIZE, 0);
+        self.inner.read_exact(&mut self.compressed)?;
+
+        let (deflated, footer) = self
+            .compressed
+            .split_at(self.compressed.len() - FOOTER_SIZE);
+        let crc = u32::from_le_bytes(footer[..4].try_into().unwrap());
+        let size = u32::from_le_bytes(footer[4..].try_into().unwrap()) as usize;
+        self.data.clear();
+        self.data.reserve(size);
+        DeflateDecoder::new(deflated).read_to_end(&mut self.data)?;
+        let mut check = Crc::new();
+        check.update(&self.data);
+        if self.data.len() != size || check.sum() != crc {
+            return Err(invalid("BGZF block is corrupt"));
+        }
+
+        self.block_offset = self.next_block_offset;
+        self.next_block_offset += block_size as u64;
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read + Seek> BgzfReader<R> {
+    /// Move to a virtual offset, e.g. one taken from an index
+    pub fn seek_virtual(&mut self, offset: VirtualOffset) -> io::Result<()> {
+        if offset.block() != self.block_offset || self.data.is_empty() {
+            self.inner.seek(SeekFrom::Start(offset.block()))?;
+            self.next_block_offset = offset.block();
+            self.data.clear();
+            self.position = 0;
+            if !self.read_block()? && offset.within_block() > 0 {
+                return Err(invalid("virtual offset is past the end of the file"));
+            }
+        }
+        if offset.within_block() as usize > self.data.len() {
+            return Err(invalid("virtual offset is past the end of its block"));
+        }
+        self.position = offset.within_block() as usize;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for BgzfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<R: Read> BufRead for BgzfReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // Skip empty blocks, such as the end-of-file marker
+        while self.position == self.data.len() {
+            if !self.read_block()? {
+                break;
+            }
+        }
+        Ok(&self.data[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.data.len());
+    }
+}
+
+/// Writes a BGZF file
+pub struct BgzfWriter<W: Write> {
+    inner: W,
+    /// File offset of the block being filled
+    block_offset: u64,
+    data: Vec<u8>,
+}
+
+impl<W: Write> BgzfWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            block_offset: 0,
+            data: Vec::with_capacity(BLOCK_DATA_SIZE),
+        }
+    }
+
+    /// Virtual offset the next byte written will have
+    pub fn virtual_position(&self) -> VirtualOffset {
+        VirtualOffset::new(self.block_offset, self.data.len() as u16)
+    }
+
+    /// Write the last block and the end-of-file marker
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        self.inner.write_all(&EOF_BLOCK)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&self.data)?;
+        let deflated = encoder.finish()?;
+        let block_size = HEADER_SIZE + deflated.len() + FOOTER_SIZE;
+        let block_size_field = u16::try_from(block_size - 1)
+            .map_err(|_| invalid("BGZF block does not fit in 64 KiB"))?;
+        let mut crc = Crc::new();
+        crc.update(&self.data);
+
+        self.inner.write_all(&EOF_BLOCK[..16])?;
+        self.inner.write_all(&block_size_field.to_le_bytes())?;
+        self.inner.write_all(&deflated)?;
+        self.inner.write_all(&crc.sum().to_le_bytes())?;
+        self.inner
+            .write_all(&(self.data.len() as u32).to_le_bytes())?;
+        self.block_offset += block_size as u64;
+        self.data.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for BgzfWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(BLOCK_DATA_SIZE - self.data.len());
+        self.data.extend_from_slice(&buf[..count]);
+        if self.data.len() == BLOCK_DATA_SIZE {
+            self.write_block()?;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.inner.flush()
+    }
+}
+
+/// Recompress a plain or gzip file with BGZF so that it can be indexed
+pub fn compress_file(source: &Path, destination: &Path) -> Result<(), String> {
+    let (mut reader, _) = compression::open_reader(source)?;
+    let file = File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let mut writer = BgzfWriter::new(BufWriter::new(file));
+    io::copy(&mut reader, &mut writer)
+        .and_then(|_| writer.finish())
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+    Ok(())
+}
+
+/// Open a BGZF file for reading
+pub fn open(path: &Path) -> Result<BgzfReader<BufReader<File>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    Ok(BgzfReader::new(BufReader::new(file)))
+}
+
+// Helper functions
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
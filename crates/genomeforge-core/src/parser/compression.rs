@@ -0,0 +1,92 @@
+//! Transparent decompression for genome files
+//!
+//! Compression is detected from the gzip magic bytes rather than the file
+//! extension. BGZF (blocked gzip, as written by `bgzip`) is reported
+//! separately from plain gzip, since only BGZF files can be indexed for
+//! random access (see [`super::tabix`]).
+
+use super::progress::{ByteCounter, CountingReader};
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// gzip member magic bytes
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// FLG.FEXTRA bit in the gzip member header
+const FLAG_EXTRA: u8 = 0x04;
+
+/// Buffer size for reading genome files
+const READ_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Compression applied to a genome file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    None,
+    Gzip,
+    Bgzip,
+}
+
+/// Classify a file from its first bytes
+pub fn detect_compression(header: &[u8]) -> Compression {
+    if header.len() < 2 || header[..2] != GZIP_MAGIC {
+        return Compression::None;
+    }
+
+    // BGZF members carry a "BC" extra subfield holding the block size
+    let has_extra = header.get(3).map(|flags| flags & FLAG_EXTRA != 0);
+    let subfield = header.get(12..14);
+    if has_extra == Some(true) && subfield == Some(b"BC") {
+        Compression::Bgzip
+    } else {
+        Compression::Gzip
+    }
+}
+
+/// Open a genome file for buffered reading, decompressing if needed
+pub fn open_reader(path: &Path) -> Result<(Box<dyn BufRead + Send>, Compression), String> {
+    open_counted_reader(path, &ByteCounter::default())
+}
+
+/// Like [`open_reader`], recording the raw bytes read from disk in `counter`
+pub fn open_counted_reader(
+    path: &Path,
+    counter: &ByteCounter,
+) -> Result<(Box<dyn BufRead + Send>, Compression), String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let mut header = [0u8; 18];
+    let read =
+        read_prefix(&mut file, &mut header).map_err(|e| format!("Failed to read file: {}", e))?;
+    let compression = detect_compression(&header[..read]);
+
+    // Reopen so the decoder sees the stream from the first byte
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let file = CountingReader::new(file, counter.clone());
+    let reader: Box<dyn BufRead + Send> = match compression {
+        Compression::None => Box::new(BufReader::with_capacity(READ_BUFFER_SIZE, file)),
+        // BGZF is a series of concatenated gzip members
+        Compression::Gzip | Compression::Bgzip => Box::new(BufReader::with_capacity(
+            READ_BUFFER_SIZE,
+            MultiGzDecoder::new(BufReader::new(file)),
+        )),
+    };
+
+    Ok((reader, compression))
+}
+
+// Helper functions
+
+fn read_prefix(file: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match file.read(&mut buf[total..])? {
+            0 => break,
+            n => total += n,
+        }
+    }
+    Ok(total)
+}
@@ -0,0 +1,85 @@
+//! CSV helpers for the `RSID,CHROMOSOME,POSITION,RESULT` layout
+//!
+//! MyHeritage and FamilyTreeDNA both export this layout, differing only in
+//! quoting, header comments and a few chromosome names.
+
+use super::raw::{array_variant, is_allele, is_snp_id};
+use crate::genome::Variant;
+
+/// Column names shared by MyHeritage and FamilyTreeDNA exports
+const RESULT_COLUMNS: [&str; 4] = ["RSID", "CHROMOSOME", "POSITION", "RESULT"];
+
+/// Split a CSV line, honouring double-quoted fields and `""` escapes
+pub fn split_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current).trim().to_string()),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current.trim().to_string());
+
+    fields
+}
+
+/// Whether a line is the `RSID,CHROMOSOME,POSITION,RESULT` header, quoted or not
+pub fn is_result_header(line: &str) -> bool {
+    let fields = split_line(line);
+    fields.len() == RESULT_COLUMNS.len()
+        && fields
+            .iter()
+            .zip(RESULT_COLUMNS)
+            .all(|(field, column)| field.eq_ignore_ascii_case(column))
+}
+
+/// Parse one `rsid,chromosome,position,genotype` data line
+pub fn parse_result_line(line: &str) -> Option<Variant> {
+    let fields = split_line(line);
+    let [rsid, chromosome, position, genotype] = fields.get(..4)? else {
+        return None;
+    };
+
+    if !is_snp_id(rsid) {
+        return None;
+    }
+    let chromosome = map_chromosome(chromosome)?;
+    let position = position.parse::<u64>().ok()?;
+
+    let genotype = genotype.to_ascii_uppercase();
+    let genotype = match genotype.as_str() {
+        "" | "--" | "00" | "NC" => "--".to_string(),
+        g if (1..=2).contains(&g.len()) && g.chars().all(is_allele) => genotype,
+        _ => return None,
+    };
+
+    Some(array_variant(rsid, &chromosome, position, &genotype))
+}
+
+// Helper functions
+
+/// Accept "chr"-prefixed names and FTDNA's "XY" pseudoautosomal code
+fn map_chromosome(raw: &str) -> Option<String> {
+    let name = raw
+        .strip_prefix("chr")
+        .or_else(|| raw.strip_prefix("CHR"))
+        .unwrap_or(raw)
+        .to_ascii_uppercase();
+    let chromosome = match name.as_str() {
+        "XY" => "X",
+        "M" | "MT" => "MT",
+        "X" | "Y" => name.as_str(),
+        n if n.parse::<u8>().is_ok_and(|n| (1..=22).contains(&n)) => n,
+        _ => return None,
+    };
+    Some(chromosome.to_string())
+}
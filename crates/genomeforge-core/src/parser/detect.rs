@@ -0,0 +1,210 @@
+//! Content-based file format detection
+//!
+//! The first lines of a file are sampled (after transparent decompression)
+//! and scored against every supported format. Header evidence such as a
+//! vendor comment or column header counts for most of the score; the rest
+//! comes from how many sampled data lines the format's parser accepts.
+
+use super::ancestry::{self, AncestryDna};
+use super::compression::{self, Compression};
+use super::ftdna::{self, FamilyTreeDna};
+use super::myheritage::{self, MyHeritage};
+use super::plink;
+use super::raw::RawDataFormat;
+use super::twenty_three_and_me::{self, TwentyThreeAndMe};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::Path;
+
+/// Maximum number of lines read while sniffing
+const SNIFF_MAX_LINES: usize = 250;
+
+/// Number of data lines sampled for the parse ratio
+const SNIFF_DATA_LINES: usize = 20;
+
+/// Weight given to header evidence; data lines make up the remainder
+const HEADER_WEIGHT: f64 = 0.6;
+
+/// Scores below this are reported as unknown
+const MIN_CONFIDENCE: f64 = 0.3;
+
+/// Supported genome file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileFormat {
+    #[serde(rename = "vcf")]
+    Vcf,
+    #[serde(rename = "23andme")]
+    TwentyThreeAndMe,
+    #[serde(rename = "ancestrydna")]
+    AncestryDna,
+    #[serde(rename = "myheritage")]
+    MyHeritage,
+    #[serde(rename = "ftdna")]
+    FamilyTreeDna,
+    /// Aligned reads, from which genotypes are called at chosen sites
+    #[serde(rename = "bam")]
+    Bam,
+    /// A PLINK `.bed`/`.bim`/`.fam` fileset, named by any of its files
+    #[serde(rename = "plink")]
+    Plink,
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl FileFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileFormat::Vcf => "vcf",
+            FileFormat::TwentyThreeAndMe => "23andme",
+            FileFormat::AncestryDna => "ancestrydna",
+            FileFormat::MyHeritage => "myheritage",
+            FileFormat::FamilyTreeDna => "ftdna",
+            FileFormat::Bam => "bam",
+            FileFormat::Plink => "plink",
+            FileFormat::Unknown => "unknown",
+        }
+    }
+}
+
+/// Result of sniffing a file
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Detection {
+    pub format: FileFormat,
+    pub compression: Compression,
+    /// How sure the detector is, from 0.0 to 1.0
+    pub confidence: f64,
+}
+
+/// Leading lines of a file, split into comments and data
+#[derive(Debug, Default)]
+pub struct FileSample {
+    pub first_line: Option<String>,
+    pub comments: Vec<String>,
+    pub data_lines: Vec<String>,
+}
+
+impl FileSample {
+    /// Read the comment block and the first few data lines
+    pub fn read<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        let mut sample = FileSample::default();
+        let mut line = String::new();
+
+        for _ in 0..SNIFF_MAX_LINES {
+            line.clear();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read file: {}", e))?;
+            if read == 0 {
+                break;
+            }
+            let trimmed = line.trim_end_matches(['\r', '\n']);
+            if trimmed.trim().is_empty() {
+                continue;
+            }
+            if sample.first_line.is_none() {
+                sample.first_line = Some(trimmed.to_string());
+            }
+
+            match trimmed.strip_prefix('#') {
+                Some(comment) => sample.comments.push(comment.trim().to_string()),
+                None => {
+                    sample.data_lines.push(trimmed.to_string());
+                    if sample.data_lines.len() >= SNIFF_DATA_LINES {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(sample)
+    }
+}
+
+/// Detect the format of a file on disk
+pub fn detect_format(path: &Path) -> Result<Detection, String> {
+    // The binary .bed holds no text to sample
+    if plink::is_plink(path) {
+        return Ok(Detection {
+            format: FileFormat::Plink,
+            compression: Compression::None,
+            confidence: 1.0,
+        });
+    }
+    let (reader, compression) = compression::open_reader(path)?;
+    let sample = FileSample::read(reader)?;
+    let (format, confidence) = classify(&sample);
+
+    Ok(Detection {
+        format,
+        compression,
+        confidence,
+    })
+}
+
+/// Score a sample against every format and pick the best match
+pub fn classify(sample: &FileSample) -> (FileFormat, f64) {
+    let first_line = sample.first_line.as_deref().unwrap_or("");
+    if first_line.starts_with("##fileformat=VCF") {
+        return (FileFormat::Vcf, 1.0);
+    }
+
+    let first_data = sample.data_lines.first().map(String::as_str);
+    let comments = &sample.comments;
+
+    // Candidates in priority order; earlier entries win ties
+    let candidates = [
+        (
+            FileFormat::AncestryDna,
+            ancestry::is_ancestry_header(comments, first_data),
+            data_ratio::<AncestryDna>(sample),
+        ),
+        (
+            FileFormat::MyHeritage,
+            myheritage::is_myheritage_header(comments, first_data),
+            data_ratio::<MyHeritage>(sample),
+        ),
+        (
+            FileFormat::FamilyTreeDna,
+            ftdna::is_ftdna_header(comments, first_data),
+            data_ratio::<FamilyTreeDna>(sample),
+        ),
+        (
+            FileFormat::TwentyThreeAndMe,
+            twenty_three_and_me::is_twenty_three_and_me_header(comments),
+            data_ratio::<TwentyThreeAndMe>(sample),
+        ),
+    ];
+
+    let mut best = (FileFormat::Unknown, 0.0);
+    for (format, header_match, ratio) in candidates {
+        let header_score = if header_match { HEADER_WEIGHT } else { 0.0 };
+        let score = header_score + (1.0 - HEADER_WEIGHT) * ratio;
+        if score > best.1 {
+            best = (format, score);
+        }
+    }
+
+    if best.1 < MIN_CONFIDENCE {
+        return (FileFormat::Unknown, best.1);
+    }
+    best
+}
+
+// Helper functions
+
+/// Fraction of sampled data lines (excluding column headers) the format parses
+fn data_ratio<F: RawDataFormat>(sample: &FileSample) -> f64 {
+    let lines: Vec<&String> = sample
+        .data_lines
+        .iter()
+        .filter(|line| !F::is_column_header(line))
+        .collect();
+    if lines.is_empty() {
+        return 0.0;
+    }
+    let parsed = lines
+        .iter()
+        .filter(|line| F::parse_line(line).is_some())
+        .count();
+    parsed as f64 / lines.len() as f64
+}
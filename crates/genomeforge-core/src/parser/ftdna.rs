@@ -0,0 +1,30 @@
+//! FamilyTreeDNA raw data parser
+//!
+//! FamilyTreeDNA Family Finder exports have no comment block; the file
+//! opens directly with an unquoted `RSID,CHROMOSOME,POSITION,RESULT`
+//! header. Pseudoautosomal SNPs are reported on chromosome "XY".
+
+use super::csv;
+use super::raw::RawDataFormat;
+use crate::genome::Variant;
+
+/// `rsid,chromosome,position,genotype`
+pub struct FamilyTreeDna;
+
+impl RawDataFormat for FamilyTreeDna {
+    const NAME: &'static str = "FamilyTreeDNA";
+
+    fn is_column_header(line: &str) -> bool {
+        csv::is_result_header(line)
+    }
+
+    fn parse_line(line: &str) -> Option<Variant> {
+        csv::parse_result_line(line)
+    }
+}
+
+/// Whether the first lines of a file look like a FamilyTreeDNA export
+pub fn is_ftdna_header(comments: &[String], first_line: Option<&str>) -> bool {
+    comments.is_empty()
+        && first_line.is_some_and(|line| !line.starts_with('"') && csv::is_result_header(line))
+}
@@ -0,0 +1,300 @@
+//! Genome file parsers
+//!
+//! Parsers stream records from disk one line at a time so that large files
+//! never have to be held in memory. [`open_genome`] detects the format of a
+//! file and returns a [`VariantSource`] yielding normalized [`Variant`]s.
+
+pub mod ancestry;
+pub mod bgzf;
+pub mod compression;
+pub mod csv;
+pub mod detect;
+pub mod ftdna;
+pub mod myheritage;
+pub mod plink;
+pub mod progress;
+pub mod raw;
+pub mod tabix;
+pub mod twenty_three_and_me;
+pub mod vcf;
+pub mod zstd;
+
+use crate::genome::{GenomeBuild, GenomeFile, StructuralVariant, Variant};
+use ancestry::AncestryDna;
+use detect::FileFormat;
+use ftdna::FamilyTreeDna;
+use myheritage::MyHeritage;
+use plink::{Fileset, PlinkVariants};
+use progress::ByteCounter;
+use raw::RawDataReader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use twenty_three_and_me::TwentyThreeAndMe;
+use vcf::{VcfReader, VcfVariants};
+
+/// A stream of variants parsed from a genome file
+pub trait VariantSource: Iterator<Item = Result<Variant, String>> {
+    /// Metadata gathered from the file header
+    fn genome_file(&self) -> &GenomeFile;
+
+    /// Data lines that could not be parsed and were skipped so far
+    fn skipped_lines(&self) -> usize {
+        0
+    }
+
+    /// Structural variants set aside instead of yielded, once the source
+    /// has been drained; only VCFs have any
+    fn take_structural_variants(&mut self) -> Vec<StructuralVariant> {
+        Vec::new()
+    }
+}
+
+/// Detect the format of a file and open a matching variant source
+pub fn open_genome(path: &Path) -> Result<Box<dyn VariantSource + Send>, String> {
+    open_genome_counted(path, &ByteCounter::default())
+}
+
+/// Like [`open_genome`], recording the raw bytes read from disk in `counter`
+pub fn open_genome_counted(
+    path: &Path,
+    counter: &ByteCounter,
+) -> Result<Box<dyn VariantSource + Send>, String> {
+    open_sample_counted(path, None, counter)
+}
+
+/// Like [`open_genome_counted`], reading the genotypes of the named sample
+/// of a file holding several rather than the first
+pub fn open_sample_counted(
+    path: &Path,
+    sample: Option<&str>,
+    counter: &ByteCounter,
+) -> Result<Box<dyn VariantSource + Send>, String> {
+    let detection = detect::detect_format(path)?;
+    if sample.is_some() && !matches!(detection.format, FileFormat::Vcf | FileFormat::Plink) {
+        return Err(format!(
+            "{} files hold a single sample",
+            detection.format.as_str()
+        ));
+    }
+    // PLINK filesets open their own files
+    let reader = || compression::open_counted_reader(path, counter).map(|(reader, _)| reader);
+
+    let source: Box<dyn VariantSource + Send> = match detection.format {
+        FileFormat::Vcf => Box::new(VcfVariants::with_sample(
+            VcfReader::new(reader()?)?,
+            &detection,
+            sample,
+        )?),
+        FileFormat::TwentyThreeAndMe => Box::new(RawDataReader::<_, TwentyThreeAndMe>::new(
+            reader()?,
+            &detection,
+        )?),
+        FileFormat::AncestryDna => {
+            Box::new(RawDataReader::<_, AncestryDna>::new(reader()?, &detection)?)
+        }
+        FileFormat::MyHeritage => {
+            Box::new(RawDataReader::<_, MyHeritage>::new(reader()?, &detection)?)
+        }
+        FileFormat::FamilyTreeDna => {
+            Box::new(RawDataReader::<_, FamilyTreeDna>::new(reader()?, &detection)?)
+        }
+        FileFormat::Bam => {
+            return Err("Alignment files hold reads, not genotypes; call genotypes from them with alignment::BamFile".to_string())
+        }
+        FileFormat::Plink => Box::new(PlinkVariants::open(path, sample, counter)?),
+        FileFormat::Unknown => {
+            return Err("Unsupported file type: contents not recognized".to_string())
+        }
+    };
+
+    Ok(source)
+}
+
+/// Names of the samples in a file; consumer exports hold a single unnamed
+/// one, so list none
+pub fn list_samples(path: &Path) -> Result<Vec<String>, String> {
+    let detection = detect::detect_format(path)?;
+    match detection.format {
+        FileFormat::Plink => Fileset::of(path)?.samples(),
+        FileFormat::Vcf => {
+            let (reader, _) = compression::open_reader(path)?;
+            Ok(VcfReader::new(reader)?.header().samples.clone())
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Counts collected while streaming a whole file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseSummary {
+    pub variant_count: usize,
+    pub no_call_count: usize,
+    /// Fraction of variants with a genotype call (0.0 - 1.0)
+    pub call_rate: f64,
+    pub multiallelic_count: usize,
+    pub skipped_lines: usize,
+    pub chromosome_counts: Vec<ChromosomeCount>,
+}
+
+/// Drain a variant source, collecting counts and per-chromosome call rates
+pub fn summarize(source: &mut dyn VariantSource) -> Result<ParseSummary, String> {
+    let mut builder = SummaryBuilder::default();
+    for variant in &mut *source {
+        builder.add(&variant?);
+    }
+    Ok(builder.finish(source.skipped_lines()))
+}
+
+/// Accumulates a [`ParseSummary`] one variant at a time
+#[derive(Debug, Default)]
+pub struct SummaryBuilder {
+    tally: ChromosomeTally,
+    multiallelic_count: usize,
+}
+
+impl SummaryBuilder {
+    /// Record one parsed variant
+    pub fn add(&mut self, variant: &Variant) {
+        if variant.is_multiallelic() {
+            self.multiallelic_count += 1;
+        }
+        self.tally
+            .add(&variant.chromosome, !variant.genotype.is_no_call());
+    }
+
+    /// Finish the summary once the source is drained
+    pub fn finish(self, skipped_lines: usize) -> ParseSummary {
+        let variant_count = self.tally.total();
+        let no_call_count = self.tally.no_calls();
+        ParseSummary {
+            variant_count,
+            no_call_count,
+            call_rate: call_rate(variant_count, no_call_count),
+            multiallelic_count: self.multiallelic_count,
+            skipped_lines,
+            chromosome_counts: self.tally.into_counts(),
+        }
+    }
+}
+
+/// Variant counts for a single chromosome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChromosomeCount {
+    pub chromosome: String,
+    pub variant_count: usize,
+    pub no_call_count: usize,
+    /// Fraction of variants with a genotype call (0.0 - 1.0)
+    pub call_rate: f64,
+}
+
+/// Accumulates per-chromosome variant counts while a file is streamed
+#[derive(Debug, Default)]
+pub struct ChromosomeTally {
+    /// (variants, no-calls) per chromosome
+    counts: HashMap<String, (usize, usize)>,
+}
+
+impl ChromosomeTally {
+    /// Record one variant on the given chromosome
+    pub fn add(&mut self, chromosome: &str, called: bool) {
+        let name = normalize_chromosome(chromosome);
+        let entry = self.counts.entry(name).or_insert((0, 0));
+        entry.0 += 1;
+        if !called {
+            entry.1 += 1;
+        }
+    }
+
+    /// Total number of variants recorded
+    pub fn total(&self) -> usize {
+        self.counts.values().map(|(total, _)| total).sum()
+    }
+
+    /// Total number of no-calls recorded
+    pub fn no_calls(&self) -> usize {
+        self.counts.values().map(|(_, no_calls)| no_calls).sum()
+    }
+
+    /// Counts in karyotype order (1-22, X, Y, MT, then anything else)
+    pub fn into_counts(self) -> Vec<ChromosomeCount> {
+        let mut counts: Vec<ChromosomeCount> = self
+            .counts
+            .into_iter()
+            .map(
+                |(chromosome, (variant_count, no_call_count))| ChromosomeCount {
+                    chromosome,
+                    variant_count,
+                    no_call_count,
+                    call_rate: call_rate(variant_count, no_call_count),
+                },
+            )
+            .collect();
+        counts.sort_by(|a, b| {
+            chromosome_sort_key(&a.chromosome).cmp(&chromosome_sort_key(&b.chromosome))
+        });
+        counts
+    }
+}
+
+/// Strip common prefixes so "chr1", "CHR1" and "1" are treated alike
+pub fn normalize_chromosome(raw: &str) -> String {
+    let trimmed = raw.trim();
+    let stripped = trimmed
+        .strip_prefix("chr")
+        .or_else(|| trimmed.strip_prefix("CHR"))
+        .or_else(|| trimmed.strip_prefix("Chr"))
+        .unwrap_or(trimmed);
+
+    match stripped.to_ascii_uppercase().as_str() {
+        "M" | "MT" => "MT".to_string(),
+        "X" => "X".to_string(),
+        "Y" => "Y".to_string(),
+        _ => stripped.to_string(),
+    }
+}
+
+/// Whether a chromosome is one of the 22 autosomes
+pub fn is_autosome(chromosome: &str) -> bool {
+    normalize_chromosome(chromosome)
+        .parse::<u8>()
+        .is_ok_and(|n| (1..=22).contains(&n))
+}
+
+/// Find the reference assembly mentioned in a file's header comments
+pub fn detect_genome_build(comments: &[String]) -> Option<GenomeBuild> {
+    comments.iter().find_map(|comment| {
+        let lower = comment.to_ascii_lowercase();
+        if lower.contains("build 38") || lower.contains("grch38") {
+            Some(GenomeBuild::GRCh38)
+        } else if lower.contains("build 37") || lower.contains("grch37") {
+            Some(GenomeBuild::GRCh37)
+        } else if lower.contains("build 36") || lower.contains("ncbi36") {
+            Some(GenomeBuild::GRCh36)
+        } else {
+            None
+        }
+    })
+}
+
+/// Fraction of variants that were called
+pub fn call_rate(variant_count: usize, no_call_count: usize) -> f64 {
+    if variant_count == 0 {
+        return 0.0;
+    }
+    (variant_count - no_call_count) as f64 / variant_count as f64
+}
+
+/// Sort key putting chromosomes 1-22 first, then X, Y and MT, then the rest
+/// by name
+pub fn chromosome_sort_key(chromosome: &str) -> (u8, u32, String) {
+    if let Ok(n) = chromosome.parse::<u32>() {
+        return (0, n, String::new());
+    }
+    match chromosome {
+        "X" => (1, 0, String::new()),
+        "Y" => (2, 0, String::new()),
+        "MT" => (3, 0, String::new()),
+        other => (4, 0, other.to_string()),
+    }
+}
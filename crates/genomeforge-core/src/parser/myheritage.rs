@@ -0,0 +1,33 @@
+//! MyHeritage raw data parser
+//!
+//! MyHeritage exports start with `#` comments naming the service, followed
+//! by a quoted `"RSID","CHROMOSOME","POSITION","RESULT"` header and quoted
+//! data fields.
+
+use super::csv;
+use super::raw::RawDataFormat;
+use crate::genome::Variant;
+
+/// `"rsid","chromosome","position","genotype"`
+pub struct MyHeritage;
+
+impl RawDataFormat for MyHeritage {
+    const NAME: &'static str = "MyHeritage";
+
+    fn is_column_header(line: &str) -> bool {
+        csv::is_result_header(line)
+    }
+
+    fn parse_line(line: &str) -> Option<Variant> {
+        csv::parse_result_line(line)
+    }
+}
+
+/// Whether the first lines of a file look like a MyHeritage export
+pub fn is_myheritage_header(comments: &[String], first_line: Option<&str>) -> bool {
+    if comments.iter().any(|c| c.contains("MyHeritage")) {
+        return true;
+    }
+    // Without comments, the quoted header is the distinguishing feature
+    first_line.is_some_and(|line| line.starts_with('"') && csv::is_result_header(line))
+}
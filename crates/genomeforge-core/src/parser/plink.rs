@@ -0,0 +1,255 @@
+//! PLINK binary fileset parser
+//!
+//! PLINK keeps a genotype matrix in three files sharing one name: `.fam`
+//! lists the samples, `.bim` the variants, and `.bed` packs two bits per
+//! sample for every variant in `.bim` order. Only the variant-major layout
+//! PLINK has written since version 1.0 is read.
+//!
+//! Genotypes are read for one sample at a time, the first unless another
+//! is chosen. The `.bim` allele columns say which allele is minor rather
+//! than which is the reference, so no reference allele is reported.
+
+use super::compression;
+use super::detect::FileFormat;
+use super::progress::ByteCounter;
+use super::{normalize_chromosome, VariantSource};
+use crate::genome::{GenomeFile, Genotype, Variant};
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+/// First bytes of a `.bed` file in variant-major layout
+pub const BED_MAGIC: [u8; 3] = [0x6c, 0x1b, 0x01];
+
+/// Numeric chromosome codes PLINK writes for the sex chromosomes and
+/// mitochondria; 25 is the pseudoautosomal part of X
+const CHROMOSOME_CODES: [(&str, &str); 4] = [("23", "X"), ("24", "Y"), ("25", "X"), ("26", "MT")];
+
+/// Paths of the three files of a fileset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fileset {
+    pub bed: PathBuf,
+    pub bim: PathBuf,
+    pub fam: PathBuf,
+}
+
+impl Fileset {
+    /// The fileset any one of its three files belongs to
+    pub fn of(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        if !matches!(extension.as_deref(), Some("bed" | "bim" | "fam")) {
+            return Err(format!("{} is not part of a PLINK fileset", path.display()));
+        }
+        let fileset = Fileset {
+            bed: path.with_extension("bed"),
+            bim: path.with_extension("bim"),
+            fam: path.with_extension("fam"),
+        };
+        for part in [&fileset.bed, &fileset.bim, &fileset.fam] {
+            if !part.exists() {
+                return Err(format!(
+                    "The PLINK fileset is missing {}",
+                    part.file_name().unwrap_or_default().to_string_lossy()
+                ));
+            }
+        }
+        Ok(fileset)
+    }
+
+    /// Individual ids of the samples, in `.fam` order
+    pub fn samples(&self) -> Result<Vec<String>, String> {
+        let (reader, _) = compression::open_reader(&self.fam)?;
+        let mut samples = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read PLINK .fam file: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let id = line
+                .split_whitespace()
+                .nth(1)
+                .ok_or_else(|| format!(".fam line {}: missing individual id", index + 1))?;
+            samples.push(id.to_string());
+        }
+        if samples.is_empty() {
+            return Err("PLINK .fam file lists no samples".to_string());
+        }
+        Ok(samples)
+    }
+}
+
+/// Whether a file is part of a PLINK fileset, in either `.bed` layout
+pub fn is_plink(path: &Path) -> bool {
+    let Ok(fileset) = Fileset::of(path) else {
+        return false;
+    };
+    let mut magic = [0u8; 2];
+    compression::open_reader(&fileset.bed)
+        .is_ok_and(|(mut reader, _)| reader.read_exact(&mut magic).is_ok())
+        && magic == BED_MAGIC[..2]
+}
+
+/// Streams the genotypes of one sample of a fileset
+pub struct PlinkVariants {
+    bim: Box<dyn BufRead + Send>,
+    bed: Box<dyn BufRead + Send>,
+    genome_file: GenomeFile,
+    /// Byte and bit offset of the sample within each variant's block
+    byte: usize,
+    shift: u8,
+    block: Vec<u8>,
+    line: String,
+    line_number: usize,
+    skipped_lines: usize,
+}
+
+impl PlinkVariants {
+    /// Open a fileset for the named sample, or the first one
+    pub fn open(path: &Path, sample: Option<&str>, counter: &ByteCounter) -> Result<Self, String> {
+        let fileset = Fileset::of(path)?;
+        let samples = fileset.samples()?;
+        let index = match sample {
+            Some(name) => samples
+                .iter()
+                .position(|id| id == name)
+                .ok_or_else(|| format!("Sample {} is not in the PLINK fileset", name))?,
+            None => 0,
+        };
+
+        let (mut bed, compression) = compression::open_counted_reader(&fileset.bed, counter)?;
+        let mut magic = [0u8; 3];
+        bed.read_exact(&mut magic)
+            .map_err(|e| format!("Failed to read PLINK .bed file: {}", e))?;
+        if magic[..2] != BED_MAGIC[..2] {
+            return Err("Not a PLINK .bed file".to_string());
+        }
+        if magic[2] != BED_MAGIC[2] {
+            return Err(
+                "PLINK .bed file is in the sample-major layout of PLINK 0.99; rewrite it with plink --make-bed"
+                    .to_string(),
+            );
+        }
+        let (bim, _) = compression::open_reader(&fileset.bim)?;
+
+        let genome_file = GenomeFile {
+            format: FileFormat::Plink,
+            compression,
+            detection_confidence: 1.0,
+            format_version: None,
+            chip_version: None,
+            genome_build: None,
+            samples,
+            fingerprint: None,
+        };
+        let block_len = genome_file.samples.len().div_ceil(4);
+        Ok(Self {
+            bim,
+            bed,
+            genome_file,
+            byte: index / 4,
+            shift: (index % 4) as u8 * 2,
+            block: vec![0; block_len],
+            line: String::new(),
+            line_number: 0,
+            skipped_lines: 0,
+        })
+    }
+}
+
+impl Iterator for PlinkVariants {
+    type Item = Result<Variant, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.bim.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(format!("Failed to read PLINK .bim file: {}", e))),
+            }
+            self.line_number += 1;
+            if self.line.trim().is_empty() {
+                continue;
+            }
+
+            // Every .bim line has a block in the .bed, even one skipped here
+            if let Err(e) = self.bed.read_exact(&mut self.block) {
+                return Some(Err(format!(
+                    "PLINK .bed file ends before .bim line {}: {}",
+                    self.line_number, e
+                )));
+            }
+            let code = (self.block[self.byte] >> self.shift) & 0b11;
+            match parse_bim_line(&self.line, code) {
+                Some(variant) => return Some(Ok(variant)),
+                None => self.skipped_lines += 1,
+            }
+        }
+    }
+}
+
+impl VariantSource for PlinkVariants {
+    fn genome_file(&self) -> &GenomeFile {
+        &self.genome_file
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.skipped_lines
+    }
+}
+
+/// Genotype of a two-bit `.bed` code, given the `.bim` allele columns
+///
+/// `00` is homozygous for the first allele, `10` heterozygous, `11`
+/// homozygous for the second, and `01` missing. PLINK writes `0` for an
+/// allele not seen, so calls that would need it are no-calls.
+pub fn decode_genotype(code: u8, first: &str, second: &str) -> Genotype {
+    let (a, b) = match code {
+        0b00 => (first, first),
+        0b10 => (first, second),
+        0b11 => (second, second),
+        _ => return Genotype::NoCall,
+    };
+    if a == "0" || b == "0" {
+        return Genotype::NoCall;
+    }
+    Genotype::Diploid {
+        first: a.to_string(),
+        second: b.to_string(),
+        phased: false,
+    }
+}
+
+// Helper functions
+
+/// `chromosome id centimorgans position allele1 allele2`; variants PLINK
+/// could not place, on chromosome or position 0, are skipped
+fn parse_bim_line(line: &str, code: u8) -> Option<Variant> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [chromosome, id, _, position, first, second] = fields[..] else {
+        return None;
+    };
+    let position: u64 = position.parse().ok().filter(|&p| p > 0)?;
+    if chromosome == "0" {
+        return None;
+    }
+    let chromosome = CHROMOSOME_CODES
+        .iter()
+        .find(|(code, _)| *code == chromosome)
+        .map_or(chromosome, |(_, name)| name);
+
+    Some(Variant {
+        rsid: (id != ".").then(|| id.to_string()),
+        chromosome: normalize_chromosome(chromosome),
+        position,
+        reference: None,
+        alternates: Vec::new(),
+        genotype: decode_genotype(
+            code,
+            &first.to_ascii_uppercase(),
+            &second.to_ascii_uppercase(),
+        ),
+        quality: None,
+    })
+}
@@ -0,0 +1,91 @@
+//! Progress reporting for long-running parses
+//!
+//! Parsing a whole-genome VCF can take minutes. The file reader counts the
+//! raw bytes it pulls from disk through a shared [`ByteCounter`], which lets
+//! callers report [`ParseProgress`] against the file size even when the
+//! file is compressed.
+
+use serde::Serialize;
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Shared count of bytes read from a file
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    /// Bytes read so far
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn add(&self, bytes: usize) {
+        self.0.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// Reader that records every byte it passes through in a [`ByteCounter`]
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    counter: ByteCounter,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub fn new(inner: R, counter: ByteCounter) -> Self {
+        Self { inner, counter }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.counter.add(read);
+        Ok(read)
+    }
+}
+
+/// Snapshot of how far a parse has progressed
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseProgress {
+    pub bytes_read: u64,
+    /// Size of the file on disk, compressed or not
+    pub total_bytes: u64,
+    pub records_parsed: usize,
+    /// Fraction of the file read (0.0 - 1.0)
+    pub fraction: f64,
+    pub elapsed_seconds: f64,
+    /// Estimated seconds remaining, once enough of the file has been read
+    pub eta_seconds: Option<f64>,
+}
+
+impl ParseProgress {
+    /// Estimate the remaining time from the read rate so far
+    pub fn new(
+        bytes_read: u64,
+        total_bytes: u64,
+        records_parsed: usize,
+        elapsed: Duration,
+    ) -> Self {
+        let fraction = if total_bytes == 0 {
+            0.0
+        } else {
+            (bytes_read as f64 / total_bytes as f64).min(1.0)
+        };
+        let elapsed_seconds = elapsed.as_secs_f64();
+
+        // Early estimates swing wildly, so wait for the first percent
+        let eta_seconds = (fraction >= 0.01).then(|| elapsed_seconds * (1.0 - fraction) / fraction);
+
+        ParseProgress {
+            bytes_read,
+            total_bytes,
+            records_parsed,
+            fraction,
+            elapsed_seconds,
+            eta_seconds,
+        }
+    }
+}
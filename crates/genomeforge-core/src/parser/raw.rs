@@ -0,0 +1,175 @@
+//! Shared reader for consumer raw data exports
+//!
+//! 23andMe, AncestryDNA and similar services all export one SNP per line
+//! below an optional `#` comment block. Each format module implements
+//! [`RawDataFormat`] to describe its column layout, and [`RawDataReader`]
+//! takes care of streaming, comment handling and skipped-line accounting.
+
+use super::detect::Detection;
+use super::{detect_genome_build, normalize_chromosome, VariantSource};
+use crate::genome::{GenomeFile, Genotype, Variant};
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+/// Column layout of a raw data export
+pub trait RawDataFormat {
+    /// Human readable format name used in error messages
+    const NAME: &'static str;
+
+    /// Whether a non-comment line is the column header
+    fn is_column_header(line: &str) -> bool;
+
+    /// Parse one data line, returning `None` if it is malformed
+    fn parse_line(line: &str) -> Option<Variant>;
+
+    /// Genotyping array version named in the comment block
+    fn chip_version(_comments: &[String]) -> Option<String> {
+        None
+    }
+}
+
+/// Streaming reader for a raw data export
+pub struct RawDataReader<R: BufRead, F: RawDataFormat> {
+    reader: R,
+    genome_file: GenomeFile,
+    comments: Vec<String>,
+    /// First data line, read while scanning the header
+    pending: Option<String>,
+    line: String,
+    skipped_lines: usize,
+    format: PhantomData<F>,
+}
+
+impl<R: BufRead, F: RawDataFormat> RawDataReader<R, F> {
+    /// Read the comment header and position the reader at the first SNP
+    pub fn new(mut reader: R, detection: &Detection) -> Result<Self, String> {
+        let block = read_comment_block(&mut reader)
+            .map_err(|e| format!("Failed to read {} file: {}", F::NAME, e))?;
+
+        if block.comments.is_empty() && block.first_line.is_none() {
+            return Err(format!("{} file is empty", F::NAME));
+        }
+
+        let genome_file = GenomeFile {
+            format: detection.format,
+            compression: detection.compression,
+            detection_confidence: detection.confidence,
+            format_version: None,
+            chip_version: F::chip_version(&block.comments),
+            genome_build: detect_genome_build(&block.comments),
+            samples: Vec::new(),
+            fingerprint: None,
+        };
+
+        Ok(Self {
+            reader,
+            genome_file,
+            comments: block.comments,
+            pending: block.first_line,
+            line: String::new(),
+            skipped_lines: 0,
+            format: PhantomData,
+        })
+    }
+
+    /// Lines of the `#` comment block, without the leading `#`
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+}
+
+impl<R: BufRead, F: RawDataFormat> Iterator for RawDataReader<R, F> {
+    type Item = Result<Variant, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => {
+                    self.line.clear();
+                    match self.reader.read_line(&mut self.line) {
+                        Ok(0) => return None,
+                        Ok(_) => self.line.trim_end_matches(['\r', '\n']).to_string(),
+                        Err(e) => {
+                            return Some(Err(format!("Failed to read {} file: {}", F::NAME, e)))
+                        }
+                    }
+                }
+            };
+
+            if line.trim().is_empty() || line.starts_with('#') || F::is_column_header(&line) {
+                continue;
+            }
+            match F::parse_line(&line) {
+                Some(variant) => return Some(Ok(variant)),
+                None => self.skipped_lines += 1,
+            }
+        }
+    }
+}
+
+impl<R: BufRead, F: RawDataFormat> VariantSource for RawDataReader<R, F> {
+    fn genome_file(&self) -> &GenomeFile {
+        &self.genome_file
+    }
+
+    fn skipped_lines(&self) -> usize {
+        self.skipped_lines
+    }
+}
+
+/// Build a variant from the rsid/chromosome/position/call columns
+pub fn array_variant(rsid: &str, chromosome: &str, position: u64, call: &str) -> Variant {
+    Variant {
+        rsid: Some(rsid.to_string()),
+        chromosome: normalize_chromosome(chromosome),
+        position,
+        reference: None,
+        alternates: Vec::new(),
+        genotype: Genotype::from_array_call(call),
+        quality: None,
+    }
+}
+
+/// `rs123` or vendor internal ids such as `i123`
+pub fn is_snp_id(id: &str) -> bool {
+    let digits = id.strip_prefix("rs").or_else(|| id.strip_prefix('i'));
+    matches!(digits, Some(d) if !d.is_empty() && d.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Nucleotides plus D/I for the indel calls on some arrays
+pub fn is_allele(c: char) -> bool {
+    matches!(c, 'A' | 'C' | 'G' | 'T' | 'D' | 'I')
+}
+
+// Helper functions
+
+/// Comment block and the first data line that follows it
+#[derive(Debug, Default)]
+struct CommentBlock {
+    comments: Vec<String>,
+    first_line: Option<String>,
+}
+
+fn read_comment_block<R: BufRead>(reader: &mut R) -> std::io::Result<CommentBlock> {
+    let mut block = CommentBlock::default();
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        match trimmed.strip_prefix('#') {
+            Some(comment) => block.comments.push(comment.trim().to_string()),
+            None if trimmed.trim().is_empty() => continue,
+            None => {
+                block.first_line = Some(trimmed.to_string());
+                break;
+            }
+        }
+    }
+
+    Ok(block)
+}
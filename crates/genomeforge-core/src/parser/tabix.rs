@@ -0,0 +1,780 @@
+//! Tabix and CSI indexes for random access to bgzipped VCFs
+//!
+//! A whole-genome VCF is too large to load, so region queries read only the
+//! BGZF blocks an index points them to. The genome is divided into nested
+//! bins (the UCSC binning scheme); the index lists, per chromosome and bin,
+//! the chunks of the file holding records that fall in it, plus the first
+//! record overlapping each 16 kb window so that earlier chunks can be
+//! skipped.
+//!
+//! `.tbi` files are read and written as tabix does. `.csi` files, which
+//! allow chromosomes longer than 512 Mb, are read, and written when a
+//! record lies beyond the tabix limit. A missing or outdated index is built
+//! by scanning the file once.
+
+use super::bgzf::{self, BgzfWriter, VirtualOffset};
+use super::compression::{self, Compression};
+use super::detect::FileFormat;
+use super::vcf::{self, VcfHeader, VcfReader, VcfRecord};
+use super::{normalize_chromosome, SummaryBuilder};
+use crate::genome::{GenomeBuild, GenomeFile, Region, Variant};
+use crate::store::LoadedGenome;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const TABIX_MAGIC: &[u8; 4] = b"TBI\x01";
+const CSI_MAGIC: &[u8; 4] = b"CSI\x01";
+const BAI_MAGIC: &[u8; 4] = b"BAI\x01";
+
+/// Size of the smallest bin and of linear index windows, as a power of two
+const MIN_SHIFT: u32 = 14;
+
+/// Levels of bins below the root in a tabix index
+const TABIX_DEPTH: u32 = 5;
+
+/// Tabix preset values describing VCF columns
+const VCF_FORMAT: i32 = 2;
+const VCF_SEQUENCE_COLUMN: i32 = 1;
+const VCF_BEGIN_COLUMN: i32 = 2;
+const VCF_END_COLUMN: i32 = 0;
+const VCF_META_CHAR: i32 = b'#' as i32;
+
+/// Kind of index file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexFormat {
+    /// `.tbi`, for chromosomes up to 512 Mb
+    Tabix,
+    /// `.csi`
+    Csi,
+}
+
+impl IndexFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            IndexFormat::Tabix => "tbi",
+            IndexFormat::Csi => "csi",
+        }
+    }
+}
+
+/// A stretch of a BGZF file, from `start` up to but excluding `end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Chunk {
+    pub start: VirtualOffset,
+    pub end: VirtualOffset,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bin {
+    /// First record overlapping the start of the bin, kept by CSI
+    first_offset: VirtualOffset,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Reference {
+    bins: BTreeMap<u32, Bin>,
+    /// First record overlapping each 16 kb window, kept by tabix
+    windows: Vec<VirtualOffset>,
+}
+
+/// Index of a bgzipped VCF
+#[derive(Debug, Clone)]
+pub struct RegionIndex {
+    format: IndexFormat,
+    min_shift: u32,
+    depth: u32,
+    /// Chromosome names as written in the VCF
+    names: Vec<String>,
+    references: Vec<Reference>,
+    /// Normalized chromosome name to position in `names`
+    by_chromosome: HashMap<String, usize>,
+}
+
+impl RegionIndex {
+    /// Read a `.tbi` or `.csi` file
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        bgzf::open(path)?
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        parse_index(&bytes).map_err(|e| format!("Invalid index {}: {}", path.display(), e))
+    }
+
+    /// Read a `.bai` index of a BAM file, whose header names the
+    /// chromosomes
+    ///
+    /// BAI files lay out bins and windows as tabix does, so they are read as
+    /// tabix indexes.
+    pub fn read_bai(path: &Path, names: &[String]) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let invalid = |e: String| format!("Invalid index {}: {}", path.display(), e);
+        let mut input = Input {
+            bytes: &bytes,
+            position: 0,
+        };
+        if input.take(4).map_err(invalid)? != BAI_MAGIC {
+            return Err(invalid("not a BAI index".to_string()));
+        }
+        if input.count().map_err(invalid)? != names.len() {
+            return Err(invalid(
+                "chromosome names do not match the references".to_string(),
+            ));
+        }
+        let references = parse_references(&mut input, names.len(), IndexFormat::Tabix, TABIX_DEPTH)
+            .map_err(invalid)?;
+        Ok(RegionIndex {
+            format: IndexFormat::Tabix,
+            min_shift: MIN_SHIFT,
+            depth: TABIX_DEPTH,
+            names: names.to_vec(),
+            references,
+            by_chromosome: names
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (normalize_chromosome(name), index))
+                .collect(),
+        })
+    }
+
+    /// Index a bgzipped VCF by reading it through once
+    pub fn build(vcf_path: &Path) -> Result<Self, String> {
+        let builder = scan(vcf_path, TABIX_DEPTH)?;
+        if builder.max_end <= bin_capacity(MIN_SHIFT, TABIX_DEPTH) {
+            return Ok(builder.finish(IndexFormat::Tabix));
+        }
+        // Chromosomes too long for tabix need deeper CSI bins; every bin
+        // number changes with the depth, so the file is scanned again
+        let mut depth = TABIX_DEPTH;
+        while builder.max_end > bin_capacity(MIN_SHIFT, depth) {
+            depth += 1;
+        }
+        Ok(scan(vcf_path, depth)?.finish(IndexFormat::Csi))
+    }
+
+    /// Save in the index's format
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let mut writer = BgzfWriter::new(BufWriter::new(file));
+        writer
+            .write_all(&self.to_bytes())
+            .and_then(|_| writer.finish())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    pub fn format(&self) -> IndexFormat {
+        self.format
+    }
+
+    /// Chromosome names as written in the VCF
+    pub fn chromosomes(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Chunks that may hold records overlapping `start` to `end`, 1-based
+    /// and inclusive, sorted and merged
+    pub fn chunks(&self, chromosome: &str, start: u64, end: u64) -> Vec<Chunk> {
+        let Some(reference) = self
+            .by_chromosome
+            .get(&normalize_chromosome(chromosome))
+            .map(|&index| &self.references[index])
+        else {
+            return Vec::new();
+        };
+        if end < start || start == 0 {
+            return Vec::new();
+        }
+        let (begin, end) = (start - 1, end);
+
+        // Chunks ending before the first record overlapping the start
+        // cannot hold anything in the region
+        let min_offset = match self.format {
+            IndexFormat::Tabix => {
+                let window = (begin >> self.min_shift) as usize;
+                reference.windows.get(window).copied().unwrap_or_default()
+            }
+            IndexFormat::Csi => {
+                let mut bin = region_bin(begin, begin + 1, self.min_shift, self.depth);
+                loop {
+                    if let Some(found) = reference.bins.get(&bin) {
+                        break found.first_offset;
+                    }
+                    if bin == 0 {
+                        break VirtualOffset::default();
+                    }
+                    bin = (bin - 1) >> 3;
+                }
+            }
+        };
+
+        let mut chunks: Vec<Chunk> = overlapping_bins(begin, end, self.min_shift, self.depth)
+            .into_iter()
+            .filter_map(|bin| reference.bins.get(&bin))
+            .flat_map(|bin| bin.chunks.iter().copied())
+            .filter(|chunk| chunk.end > min_offset)
+            .collect();
+        chunks.sort();
+        let mut merged: Vec<Chunk> = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            match merged.last_mut() {
+                Some(last) if chunk.start <= last.end => last.end = last.end.max(chunk.end),
+                _ => merged.push(chunk),
+            }
+        }
+        merged
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut names = Vec::new();
+        for name in &self.names {
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
+        }
+        let mut header = Vec::new();
+        for value in [
+            VCF_FORMAT,
+            VCF_SEQUENCE_COLUMN,
+            VCF_BEGIN_COLUMN,
+            VCF_END_COLUMN,
+            VCF_META_CHAR,
+            0,
+            names.len() as i32,
+        ] {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+        header.extend_from_slice(&names);
+
+        let mut out = Vec::new();
+        match self.format {
+            IndexFormat::Tabix => {
+                out.extend_from_slice(TABIX_MAGIC);
+                out.extend_from_slice(&(self.names.len() as i32).to_le_bytes());
+                out.extend_from_slice(&header);
+            }
+            IndexFormat::Csi => {
+                out.extend_from_slice(CSI_MAGIC);
+                out.extend_from_slice(&(self.min_shift as i32).to_le_bytes());
+                out.extend_from_slice(&(self.depth as i32).to_le_bytes());
+                out.extend_from_slice(&(header.len() as i32).to_le_bytes());
+                out.extend_from_slice(&header);
+                out.extend_from_slice(&(self.names.len() as i32).to_le_bytes());
+            }
+        }
+        for reference in &self.references {
+            out.extend_from_slice(&(reference.bins.len() as i32).to_le_bytes());
+            for (number, bin) in &reference.bins {
+                out.extend_from_slice(&number.to_le_bytes());
+                if self.format == IndexFormat::Csi {
+                    out.extend_from_slice(&bin.first_offset.0.to_le_bytes());
+                }
+                out.extend_from_slice(&(bin.chunks.len() as i32).to_le_bytes());
+                for chunk in &bin.chunks {
+                    out.extend_from_slice(&chunk.start.0.to_le_bytes());
+                    out.extend_from_slice(&chunk.end.0.to_le_bytes());
+                }
+            }
+            if self.format == IndexFormat::Tabix {
+                out.extend_from_slice(&(reference.windows.len() as i32).to_le_bytes());
+                for offset in &reference.windows {
+                    out.extend_from_slice(&offset.0.to_le_bytes());
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A bgzipped VCF read through its index
+#[derive(Debug, Clone)]
+pub struct IndexedVcf {
+    path: PathBuf,
+    header: VcfHeader,
+    genome_file: GenomeFile,
+    index: RegionIndex,
+}
+
+impl IndexedVcf {
+    /// Open a bgzipped VCF, building and saving its index if there is none
+    /// or the VCF has changed since it was written
+    ///
+    /// When the index cannot be saved next to the VCF it is kept in memory.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let (reader, compression) = compression::open_reader(path)?;
+        if compression != Compression::Bgzip {
+            return Err("VCF must be compressed with bgzip to be indexed".to_string());
+        }
+        let header = VcfReader::new(reader)?.header().clone();
+
+        let index = match find_index(path) {
+            Some(index_path) => RegionIndex::read(&index_path)?,
+            None => {
+                let index = RegionIndex::build(path)?;
+                let _ = index.write(&index_path(path, index.format()));
+                index
+            }
+        };
+        let genome_file = GenomeFile {
+            format: FileFormat::Vcf,
+            compression,
+            detection_confidence: 1.0,
+            format_version: Some(header.file_format.clone()),
+            chip_version: None,
+            genome_build: header.genome_build(),
+            samples: header.samples.clone(),
+            fingerprint: None,
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            header,
+            genome_file,
+            index,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &VcfHeader {
+        &self.header
+    }
+
+    pub fn index(&self) -> &RegionIndex {
+        &self.index
+    }
+
+    pub fn genome_file(&self) -> &GenomeFile {
+        &self.genome_file
+    }
+
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        self.genome_file.genome_build
+    }
+
+    /// Records overlapping `start` to `end`, 1-based and inclusive
+    pub fn query(&self, chromosome: &str, start: u64, end: u64) -> Result<Vec<VcfRecord>, String> {
+        let chromosome = normalize_chromosome(chromosome);
+        let chunks = self.index.chunks(&chromosome, start, end);
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut reader = bgzf::open(&self.path)?;
+        let mut records = Vec::new();
+        let mut line = String::new();
+        'chunks: for chunk in chunks {
+            reader
+                .seek_virtual(chunk.start)
+                .map_err(|e| format!("Failed to read VCF: {}", e))?;
+            while reader.virtual_position() < chunk.end {
+                line.clear();
+                let read = reader
+                    .read_line(&mut line)
+                    .map_err(|e| format!("Failed to read VCF: {}", e))?;
+                if read == 0 {
+                    break;
+                }
+                let trimmed = line.trim_end_matches(['\r', '\n']);
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    continue;
+                }
+                let record = vcf::parse_record(trimmed, self.header.samples.len(), 0)?;
+                if normalize_chromosome(&record.chromosome) != chromosome {
+                    continue;
+                }
+                // Records are sorted, so nothing later can overlap
+                if record.position > end {
+                    break 'chunks;
+                }
+                if record.end() >= start {
+                    records.push(record);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Variants of the first sample in a region, as a genome of their own
+    pub fn load_region(&self, region: &Region) -> Result<LoadedGenome, String> {
+        let mut builder = SummaryBuilder::default();
+        let variants: Vec<Variant> = self
+            .query(&region.chromosome, region.start, region.end)?
+            .iter()
+            .filter(|record| !record.is_structural())
+            .map(|record| record.to_variant(0))
+            .filter(|variant| region.contains(variant))
+            .inspect(|variant| builder.add(variant))
+            .collect();
+        Ok(LoadedGenome::from_variants(
+            self.genome_file.clone(),
+            builder.finish(0),
+            variants,
+        ))
+    }
+}
+
+/// Where an index of `vcf_path` in `format` is kept
+pub fn index_path(vcf_path: &Path, format: IndexFormat) -> PathBuf {
+    let mut path = vcf_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(format.extension());
+    PathBuf::from(path)
+}
+
+/// An index next to the VCF written no earlier than the VCF itself
+pub fn find_index(vcf_path: &Path) -> Option<PathBuf> {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    let vcf_modified = modified(vcf_path);
+    [IndexFormat::Tabix, IndexFormat::Csi]
+        .into_iter()
+        .map(|format| index_path(vcf_path, format))
+        .find(|path| path.is_file() && modified(path) >= vcf_modified)
+}
+
+// Helper functions
+
+fn scan(vcf_path: &Path, depth: u32) -> Result<IndexBuilder, String> {
+    let mut reader = bgzf::open(vcf_path)?;
+    let mut builder = IndexBuilder::new(depth);
+    let mut line = String::new();
+    let mut line_number = 0;
+    loop {
+        let start = reader.virtual_position();
+        line.clear();
+        let read = reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read VCF: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (chromosome, begin, end) = record_span(trimmed)
+            .ok_or_else(|| format!("line {}: malformed VCF record", line_number))?;
+        let chunk = Chunk {
+            start,
+            end: reader.virtual_position(),
+        };
+        builder
+            .add(chromosome, begin, end, chunk)
+            .map_err(|e| format!("line {}: {}", line_number, e))?;
+    }
+    Ok(builder)
+}
+
+/// Collects bins and windows while a sorted VCF is scanned
+struct IndexBuilder {
+    depth: u32,
+    names: Vec<String>,
+    references: Vec<Reference>,
+    by_chromosome: HashMap<String, usize>,
+    last_begin: u64,
+    max_end: u64,
+}
+
+impl IndexBuilder {
+    fn new(depth: u32) -> Self {
+        Self {
+            depth,
+            names: Vec::new(),
+            references: Vec::new(),
+            by_chromosome: HashMap::new(),
+            last_begin: 0,
+            max_end: 0,
+        }
+    }
+
+    /// Add a record spanning `begin` to `end`, 0-based half-open
+    fn add(&mut self, chromosome: &str, begin: u64, end: u64, chunk: Chunk) -> Result<(), String> {
+        let normalized = normalize_chromosome(chromosome);
+        let current = self.names.len().checked_sub(1);
+        match self.by_chromosome.get(&normalized) {
+            Some(&index) if Some(index) == current => {
+                if begin < self.last_begin {
+                    return Err("positions decrease; the VCF must be sorted".to_string());
+                }
+            }
+            Some(_) => {
+                return Err(format!(
+                    "chromosome {} appears again after others; the VCF must be sorted",
+                    chromosome
+                ))
+            }
+            None => {
+                self.by_chromosome.insert(normalized, self.names.len());
+                self.names.push(chromosome.to_string());
+                self.references.push(Reference::default());
+            }
+        }
+        self.last_begin = begin;
+        self.max_end = self.max_end.max(end);
+
+        let reference = self.references.last_mut().expect("reference was added");
+        let bin = reference
+            .bins
+            .entry(region_bin(begin, end, MIN_SHIFT, self.depth))
+            .or_default();
+        match bin.chunks.last_mut() {
+            Some(last) if last.end == chunk.start => last.end = chunk.end,
+            _ => bin.chunks.push(chunk),
+        }
+        let last_window = ((end.max(begin + 1) - 1) >> MIN_SHIFT) as usize;
+        if reference.windows.len() <= last_window {
+            reference
+                .windows
+                .resize(last_window + 1, VirtualOffset(u64::MAX));
+        }
+        for window in &mut reference.windows[(begin >> MIN_SHIFT) as usize..=last_window] {
+            if window.0 == u64::MAX {
+                *window = chunk.start;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self, format: IndexFormat) -> RegionIndex {
+        for reference in &mut self.references {
+            // Windows no record overlaps point at the previous one
+            let mut previous = VirtualOffset::default();
+            for window in reference.windows.iter_mut() {
+                if window.0 == u64::MAX {
+                    *window = previous;
+                }
+                previous = *window;
+            }
+            for (&number, bin) in reference.bins.iter_mut() {
+                let window = (bin_start(number, MIN_SHIFT, self.depth) >> MIN_SHIFT) as usize;
+                bin.first_offset = reference
+                    .windows
+                    .get(window)
+                    .copied()
+                    .unwrap_or(bin.chunks[0].start);
+            }
+        }
+        RegionIndex {
+            format,
+            min_shift: MIN_SHIFT,
+            depth: self.depth,
+            names: self.names,
+            references: self.references,
+            by_chromosome: self.by_chromosome,
+        }
+    }
+}
+
+/// Chromosome and 0-based half-open span of a data line
+fn record_span(line: &str) -> Option<(&str, u64, u64)> {
+    let mut columns = line.split('\t');
+    let chromosome = columns.next()?;
+    let position: u64 = columns.next()?.parse().ok()?;
+    let reference = columns.nth(1)?;
+    let info = columns.nth(3).unwrap_or(".");
+    let end = info.split(';').find_map(|entry| entry.strip_prefix("END="));
+    let begin = position.checked_sub(1)?;
+    Some((chromosome, begin, vcf::record_end(position, reference, end)))
+}
+
+/// Smallest bin holding the 0-based half-open span
+fn region_bin(begin: u64, end: u64, min_shift: u32, depth: u32) -> u32 {
+    let end = end.max(begin + 1) - 1;
+    let mut shift = min_shift;
+    for level in (1..=depth).rev() {
+        if begin >> shift == end >> shift {
+            return level_offset(level) + (begin >> shift) as u32;
+        }
+        shift += 3;
+    }
+    0
+}
+
+/// Bins at every level that overlap the 0-based half-open span
+fn overlapping_bins(begin: u64, end: u64, min_shift: u32, depth: u32) -> Vec<u32> {
+    let end = end.max(begin + 1) - 1;
+    let mut bins = Vec::new();
+    for level in 0..=depth {
+        let shift = min_shift + 3 * (depth - level);
+        let offset = level_offset(level);
+        bins.extend((begin >> shift) as u32 + offset..=(end >> shift) as u32 + offset);
+    }
+    bins
+}
+
+/// First bin number of a level
+fn level_offset(level: u32) -> u32 {
+    (((1u64 << (3 * level)) - 1) / 7) as u32
+}
+
+/// Length of sequence the bins cover
+fn bin_capacity(min_shift: u32, depth: u32) -> u64 {
+    1 << (min_shift + 3 * depth)
+}
+
+/// 0-based first position covered by a bin
+fn bin_start(bin: u32, min_shift: u32, depth: u32) -> u64 {
+    let level = (0..=depth)
+        .rev()
+        .find(|&level| bin >= level_offset(level))
+        .unwrap_or(0);
+    ((bin - level_offset(level)) as u64) << (min_shift + 3 * (depth - level))
+}
+
+fn parse_index(bytes: &[u8]) -> Result<RegionIndex, String> {
+    let mut input = Input { bytes, position: 0 };
+    let magic = input.take(4)?;
+    let (format, min_shift, depth) = if magic == TABIX_MAGIC {
+        (IndexFormat::Tabix, MIN_SHIFT, TABIX_DEPTH)
+    } else if magic == CSI_MAGIC {
+        let min_shift = input.count()? as u32;
+        let depth = input.count()? as u32;
+        // Bin numbers of deeper levels would not fit in 32 bits
+        if depth > 9 || min_shift + 3 * depth > 62 {
+            return Err("unsupported bin sizes".to_string());
+        }
+        (IndexFormat::Csi, min_shift, depth)
+    } else {
+        return Err("not a tabix or CSI index".to_string());
+    };
+
+    let mut reference_count = if format == IndexFormat::Tabix {
+        input.count()?
+    } else {
+        0
+    };
+    // CSI files may carry a header of a different size, or none
+    let header_size = match format {
+        IndexFormat::Csi => Some(input.count()?),
+        IndexFormat::Tabix => None,
+    };
+    let mut names = Vec::new();
+    if header_size.is_none_or(|size| size >= 28) {
+        let file_format = input.i32()?;
+        if file_format & 0xffff != VCF_FORMAT {
+            return Err("index is not for a VCF".to_string());
+        }
+        // Column numbers, comment character and skipped lines
+        input.take(20)?;
+        let names_size = input.count()?;
+        for name in input.take(names_size)?.split(|&b| b == 0) {
+            if !name.is_empty() {
+                names.push(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+        if let Some(size) = header_size {
+            input.take(size.saturating_sub(28 + names_size))?;
+        }
+    } else if let Some(size) = header_size {
+        input.take(size)?;
+    }
+    if format == IndexFormat::Csi {
+        reference_count = input.count()?;
+    }
+    if names.len() != reference_count {
+        return Err("chromosome names do not match the references".to_string());
+    }
+
+    let references = parse_references(&mut input, reference_count, format, depth)?;
+
+    let by_chromosome = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (normalize_chromosome(name), index))
+        .collect();
+    Ok(RegionIndex {
+        format,
+        min_shift,
+        depth,
+        names,
+        references,
+        by_chromosome,
+    })
+}
+
+/// Bins and windows of each reference
+fn parse_references(
+    input: &mut Input<'_>,
+    count: usize,
+    format: IndexFormat,
+    depth: u32,
+) -> Result<Vec<Reference>, String> {
+    // Bins past the last level hold samtools statistics, not chunks
+    let pseudo_bin = level_offset(depth + 1);
+    let mut references = Vec::with_capacity(count);
+    for _ in 0..count {
+        let mut reference = Reference::default();
+        for _ in 0..input.count()? {
+            let number = input.u32()?;
+            let first_offset = match format {
+                IndexFormat::Csi => VirtualOffset(input.u64()?),
+                IndexFormat::Tabix => VirtualOffset::default(),
+            };
+            let mut chunks = Vec::new();
+            for _ in 0..input.count()? {
+                chunks.push(Chunk {
+                    start: VirtualOffset(input.u64()?),
+                    end: VirtualOffset(input.u64()?),
+                });
+            }
+            if number != pseudo_bin {
+                reference.bins.insert(
+                    number,
+                    Bin {
+                        first_offset,
+                        chunks,
+                    },
+                );
+            }
+        }
+        if format == IndexFormat::Tabix {
+            for _ in 0..input.count()? {
+                reference.windows.push(VirtualOffset(input.u64()?));
+            }
+        }
+        references.push(reference);
+    }
+    Ok(references)
+}
+
+/// Little-endian fields of an index file
+struct Input<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Input<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "index is truncated".to_string())?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    /// A non-negative count or size
+    fn count(&mut self) -> Result<usize, String> {
+        usize::try_from(self.i32()?).map_err(|_| "negative count in index".to_string())
+    }
+}
@@ -0,0 +1,108 @@
+//! 23andMe raw data parser
+//!
+//! 23andMe exports are tab-separated text with a block of `#` comments at
+//! the top followed by `rsid chromosome position genotype` lines. The
+//! comments carry the reference assembly, which is needed before positions
+//! can be matched against any database.
+
+use super::raw::{array_variant, is_allele, is_snp_id, RawDataFormat};
+use crate::genome::Variant;
+use serde::Serialize;
+
+/// Number of comment lines above which a header is treated as v5 (see
+/// `detect23andMeVersion` in the TypeScript parser)
+const V5_COMMENT_LINES: usize = 10;
+
+/// Number of comment lines above which a header is treated as v4
+const V4_COMMENT_LINES: usize = 5;
+
+/// 23andMe genotyping chip version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChipVersion {
+    V3,
+    V4,
+    V5,
+}
+
+impl ChipVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChipVersion::V3 => "v3",
+            ChipVersion::V4 => "v4",
+            ChipVersion::V5 => "v5",
+        }
+    }
+}
+
+/// `rsid<TAB>chromosome<TAB>position<TAB>genotype`
+pub struct TwentyThreeAndMe;
+
+impl RawDataFormat for TwentyThreeAndMe {
+    const NAME: &'static str = "23andMe";
+
+    fn is_column_header(line: &str) -> bool {
+        line.to_ascii_lowercase().starts_with("rsid\t")
+    }
+
+    fn parse_line(line: &str) -> Option<Variant> {
+        let mut fields = line.split('\t').map(str::trim);
+        let rsid = fields.next()?;
+        let chromosome = fields.next()?;
+        let position = fields.next()?;
+        let genotype = fields.next()?;
+
+        if !is_snp_id(rsid) {
+            return None;
+        }
+        let position = position.parse::<u64>().ok()?;
+
+        let genotype = genotype.to_ascii_uppercase();
+        let genotype = match genotype.as_str() {
+            "" | "--" | "00" => "--".to_string(),
+            g if (1..=2).contains(&g.len()) && g.chars().all(is_allele) => genotype,
+            _ => return None,
+        };
+
+        Some(array_variant(rsid, chromosome, position, &genotype))
+    }
+
+    fn chip_version(comments: &[String]) -> Option<String> {
+        detect_chip_version(comments).map(|v| v.as_str().to_string())
+    }
+}
+
+/// Whether the comment block looks like it came from 23andMe
+pub fn is_twenty_three_and_me_header(comments: &[String]) -> bool {
+    comments.iter().any(|c| c.contains("23andMe"))
+}
+
+/// Infer the chip version from the comment block
+pub fn detect_chip_version(comments: &[String]) -> Option<ChipVersion> {
+    if !is_twenty_three_and_me_header(comments) {
+        return None;
+    }
+
+    // Newer exports state the chip explicitly
+    for comment in comments {
+        let lower = comment.to_ascii_lowercase();
+        if lower.contains("chip") || lower.contains("version") {
+            if lower.contains("v5") {
+                return Some(ChipVersion::V5);
+            } else if lower.contains("v4") {
+                return Some(ChipVersion::V4);
+            } else if lower.contains("v3") {
+                return Some(ChipVersion::V3);
+            }
+        }
+    }
+
+    // Fall back to the header length heuristic used by the web parser
+    if comments.len() >= V5_COMMENT_LINES {
+        Some(ChipVersion::V5)
+    } else if comments.len() > V4_COMMENT_LINES {
+        Some(ChipVersion::V4)
+    } else {
+        Some(ChipVersion::V3)
+    }
+}
@@ -0,0 +1,665 @@
+//! VCF (Variant Call Format) parser
+//!
+//! Streams VCF 4.2/4.3 files record by record. Header meta-information
+//! (INFO, FORMAT, FILTER and contig definitions) is parsed up front; data
+//! lines are decoded lazily as the reader is iterated.
+
+use super::detect::Detection;
+use super::{detect_genome_build, normalize_chromosome, VariantSource};
+use crate::genome::{
+    CallQuality, GenomeBuild, GenomeFile, Genotype, StructuralKind, StructuralVariant, Variant,
+};
+use std::io::BufRead;
+
+/// Column names every VCF header line must start with
+const FIXED_COLUMNS: [&str; 8] = [
+    "#CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER", "INFO",
+];
+
+/// INFO keys imputation tools give a site's estimated accuracy under:
+/// Minimac (Michigan and TOPMed servers), Beagle and IMPUTE
+const IMPUTATION_SCORES: [&str; 3] = ["R2", "DR2", "INFO"];
+
+/// INFO flags marking a site as imputed or as genotyped directly
+const IMPUTED_FLAGS: [&str; 2] = ["IMPUTED", "IMP"];
+const TYPED_FLAGS: [&str; 2] = ["TYPED", "TYPED_ONLY"];
+
+/// Symbolic alleles gVCFs stand for any unobserved allele with; not
+/// structural variants
+const GVCF_PLACEHOLDERS: [&str; 2] = ["<*>", "<NON_REF>"];
+
+/// An INFO, FORMAT or FILTER definition from the meta-information lines
+#[derive(Debug, Clone)]
+pub struct HeaderField {
+    pub id: String,
+    pub number: Option<String>,
+    pub kind: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A `##contig` definition
+#[derive(Debug, Clone)]
+pub struct Contig {
+    pub id: String,
+    pub length: Option<u64>,
+}
+
+/// Parsed VCF header
+#[derive(Debug, Clone, Default)]
+pub struct VcfHeader {
+    /// Value of `##fileformat`, e.g. "VCFv4.2"
+    pub file_format: String,
+    pub info: Vec<HeaderField>,
+    pub format: Vec<HeaderField>,
+    pub filters: Vec<HeaderField>,
+    pub contigs: Vec<Contig>,
+    /// Sample names from the `#CHROM` line
+    pub samples: Vec<String>,
+    /// Any other `##key=value` lines
+    pub other: Vec<(String, String)>,
+}
+
+/// A single VCF data line
+#[derive(Debug, Clone)]
+pub struct VcfRecord {
+    pub chromosome: String,
+    pub position: u64,
+    pub id: Option<String>,
+    pub reference: String,
+    pub alternates: Vec<String>,
+    pub quality: Option<f64>,
+    pub filters: Vec<String>,
+    /// INFO entries in file order; flags have no value
+    pub info: Vec<(String, Option<String>)>,
+    pub format: Vec<String>,
+    /// Per-sample values, aligned with `format`
+    pub samples: Vec<Vec<String>>,
+}
+
+impl VcfHeader {
+    /// Build named by the `##reference` or `##assembly` lines
+    pub fn genome_build(&self) -> Option<GenomeBuild> {
+        let meta: Vec<String> = self
+            .other
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        detect_genome_build(&meta)
+    }
+}
+
+impl VcfRecord {
+    /// Last reference base the record covers, from `END` for symbolic
+    /// alleles and from the reference allele otherwise
+    pub fn end(&self) -> u64 {
+        record_end(self.position, &self.reference, self.info_value("END"))
+    }
+
+    /// Whether the site lists more than one alternate allele
+    pub fn is_multiallelic(&self) -> bool {
+        self.alternates.len() > 1
+    }
+
+    /// Whether the record is a structural variant or copy-number change:
+    /// one with an `SVTYPE`, or with a symbolic or breakend alternate
+    /// other than the gVCF placeholders
+    pub fn is_structural(&self) -> bool {
+        self.info_value("SVTYPE").is_some()
+            || self.alternates.iter().any(|alternate| {
+                !GVCF_PLACEHOLDERS.contains(&alternate.as_str())
+                    && (alternate.starts_with('<') || alternate.contains(['[', ']']))
+            })
+    }
+
+    /// Convert a structural record for one sample; `None` for kinds of
+    /// structural variant that are not known
+    pub fn to_structural(&self, sample: usize) -> Option<StructuralVariant> {
+        let copy_number = self
+            .sample_value(sample, "CN")
+            .or_else(|| self.info_value("CN"))
+            .and_then(|cn| cn.parse::<u32>().ok());
+        let kind = match structural_kind(self)? {
+            StructuralKind::CopyNumber => match copy_number {
+                Some(0 | 1) => StructuralKind::Deletion,
+                Some(3..) => StructuralKind::Duplication,
+                _ => StructuralKind::CopyNumber,
+            },
+            kind => kind,
+        };
+        // Symbolic records start at the base before the event
+        let start = match kind {
+            StructuralKind::Insertion | StructuralKind::Breakend => self.position,
+            _ => self.position + 1,
+        };
+        let copies = self
+            .sample_value(sample, "GT")
+            .map(|gt| gt.split(['/', '|']).collect::<Vec<_>>())
+            .filter(|alleles| !alleles.contains(&"."))
+            .map(|alleles| alleles.iter().filter(|allele| **allele != "0").count());
+        Some(StructuralVariant {
+            id: self.id.clone(),
+            kind,
+            chromosome: normalize_chromosome(&self.chromosome),
+            start,
+            end: self.end().max(start),
+            copies,
+            copy_number,
+            filters: self
+                .filters
+                .iter()
+                .filter(|filter| filter.as_str() != "PASS")
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Genotype of one sample; sites without samples have no call
+    pub fn genotype(&self, sample: usize) -> Genotype {
+        match self.sample_value(sample, "GT") {
+            Some(gt) => Genotype::from_vcf(gt, &self.reference, &self.alternates),
+            None => Genotype::NoCall,
+        }
+    }
+
+    /// Convert to the format-independent variant type for one sample
+    pub fn to_variant(&self, sample: usize) -> Variant {
+        Variant {
+            rsid: self.id.clone(),
+            chromosome: normalize_chromosome(&self.chromosome),
+            position: self.position,
+            reference: Some(self.reference.clone()),
+            alternates: self.alternates.clone(),
+            genotype: self.genotype(sample),
+            quality: self.call_quality(sample),
+        }
+    }
+
+    /// Imputation marks and scores for one sample, when the record has any
+    ///
+    /// A site with an accuracy score is taken to be imputed unless flagged
+    /// as typed. `GP` is read as probabilities, as imputation tools write
+    /// it, or as Phred-scaled when any value exceeds 1.
+    pub fn call_quality(&self, sample: usize) -> Option<CallQuality> {
+        let flagged = |flags: &[&str]| {
+            self.info
+                .iter()
+                .any(|(key, _)| flags.contains(&key.as_str()))
+        };
+        let typed = flagged(&TYPED_FLAGS);
+        let info_score = IMPUTATION_SCORES
+            .iter()
+            .find_map(|key| self.info_value(key))
+            .and_then(|score| score.parse::<f32>().ok());
+        let probabilities: Vec<f32> = self
+            .sample_value(sample, "GP")
+            .map(|gp| gp.split(',').filter_map(|p| p.parse().ok()).collect())
+            .unwrap_or_default();
+        let phred = probabilities.iter().any(|&p| p > 1.0);
+        let probability = probabilities
+            .into_iter()
+            .map(|p| if phred { 10f32.powf(-p / 10.0) } else { p })
+            .reduce(f32::max);
+        let dosage = self
+            .sample_value(sample, "DS")
+            .and_then(|ds| ds.parse::<f32>().ok());
+        let filters: Vec<String> = self
+            .filters
+            .iter()
+            .filter(|filter| filter.as_str() != "PASS")
+            .cloned()
+            .collect();
+        let site_quality = self.quality.map(|quality| quality as f32);
+        let genotype_quality = self
+            .sample_value(sample, "GQ")
+            .and_then(|gq| gq.parse::<f32>().ok());
+        let depth = self
+            .sample_value(sample, "DP")
+            .or_else(|| self.info_value("DP"))
+            .and_then(|dp| dp.parse::<u32>().ok());
+
+        let imputed = flagged(&IMPUTED_FLAGS) || (info_score.is_some() && !typed);
+        if !imputed
+            && !typed
+            && probability.is_none()
+            && dosage.is_none()
+            && filters.is_empty()
+            && site_quality.is_none()
+            && genotype_quality.is_none()
+            && depth.is_none()
+        {
+            return None;
+        }
+        Some(CallQuality {
+            imputed,
+            info_score,
+            probability,
+            dosage,
+            filters,
+            site_quality,
+            genotype_quality,
+            depth,
+        })
+    }
+
+    /// Look up an INFO value by key
+    pub fn info_value(&self, key: &str) -> Option<&str> {
+        self.info
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Look up a FORMAT value for a sample by key
+    pub fn sample_value(&self, sample: usize, key: &str) -> Option<&str> {
+        let index = self.format.iter().position(|k| k == key)?;
+        self.samples
+            .get(sample)
+            .and_then(|values| values.get(index))
+            .map(String::as_str)
+    }
+}
+
+/// Streaming VCF reader
+pub struct VcfReader<R: BufRead> {
+    reader: R,
+    header: VcfHeader,
+    line_number: usize,
+    line: String,
+}
+
+impl<R: BufRead> VcfReader<R> {
+    /// Read the header and position the reader at the first data line
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = VcfHeader::default();
+        let mut line = String::new();
+        let mut line_number = 0;
+
+        loop {
+            line.clear();
+            let read = reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read VCF header: {}", e))?;
+            if read == 0 {
+                return Err("VCF header is missing the #CHROM line".to_string());
+            }
+            line_number += 1;
+            let trimmed = line.trim_end_matches(['\r', '\n']);
+
+            if line_number == 1 {
+                match trimmed.strip_prefix("##fileformat=") {
+                    Some(version) if version.starts_with("VCFv4") => {
+                        header.file_format = version.to_string();
+                        continue;
+                    }
+                    Some(version) => {
+                        return Err(format!("Unsupported VCF version: {}", version));
+                    }
+                    None => return Err("Missing ##fileformat line".to_string()),
+                }
+            }
+
+            if let Some(meta) = trimmed.strip_prefix("##") {
+                parse_meta_line(meta, &mut header, line_number)?;
+            } else if trimmed.starts_with("#CHROM") {
+                header.samples = parse_column_line(trimmed, line_number)?;
+                break;
+            } else {
+                return Err(format!(
+                    "line {}: expected header line, found data",
+                    line_number
+                ));
+            }
+        }
+
+        Ok(Self {
+            reader,
+            header,
+            line_number,
+            line,
+        })
+    }
+
+    /// The parsed header
+    pub fn header(&self) -> &VcfHeader {
+        &self.header
+    }
+}
+
+impl<R: BufRead> Iterator for VcfReader<R> {
+    type Item = Result<VcfRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(format!("Failed to read VCF: {}", e))),
+            }
+            self.line_number += 1;
+
+            let trimmed = self.line.trim_end_matches(['\r', '\n']);
+            if trimmed.is_empty() {
+                continue;
+            }
+            return Some(parse_record(
+                trimmed,
+                self.header.samples.len(),
+                self.line_number,
+            ));
+        }
+    }
+}
+
+/// Adapts a [`VcfReader`] into a [`VariantSource`] for one sample
+pub struct VcfVariants<R: BufRead> {
+    reader: VcfReader<R>,
+    genome_file: GenomeFile,
+    sample: usize,
+    structural: Vec<StructuralVariant>,
+}
+
+impl<R: BufRead> VcfVariants<R> {
+    /// Read the genotypes of the first sample
+    pub fn new(reader: VcfReader<R>, detection: &Detection) -> Self {
+        let header = reader.header();
+        let genome_file = GenomeFile {
+            format: detection.format,
+            compression: detection.compression,
+            detection_confidence: detection.confidence,
+            format_version: Some(header.file_format.clone()),
+            chip_version: None,
+            genome_build: header.genome_build(),
+            samples: header.samples.clone(),
+            fingerprint: None,
+        };
+
+        Self {
+            reader,
+            genome_file,
+            sample: 0,
+            structural: Vec::new(),
+        }
+    }
+
+    /// Read the genotypes of the named sample, or the first one
+    pub fn with_sample(
+        reader: VcfReader<R>,
+        detection: &Detection,
+        sample: Option<&str>,
+    ) -> Result<Self, String> {
+        let mut variants = Self::new(reader, detection);
+        if let Some(name) = sample {
+            variants.sample = variants
+                .genome_file
+                .samples
+                .iter()
+                .position(|id| id == name)
+                .ok_or_else(|| format!("Sample {} is not in the VCF", name))?;
+        }
+        Ok(variants)
+    }
+}
+
+impl<R: BufRead> Iterator for VcfVariants<R> {
+    type Item = Result<Variant, String>;
+
+    /// Structural records are set aside rather than yielded
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.reader.next()? {
+                Ok(record) if record.is_structural() => {
+                    self.structural.extend(record.to_structural(self.sample));
+                }
+                record => return Some(record.map(|r| r.to_variant(self.sample))),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> VariantSource for VcfVariants<R> {
+    fn genome_file(&self) -> &GenomeFile {
+        &self.genome_file
+    }
+
+    fn take_structural_variants(&mut self) -> Vec<StructuralVariant> {
+        std::mem::take(&mut self.structural)
+    }
+}
+
+// Helper functions
+
+fn parse_meta_line(meta: &str, header: &mut VcfHeader, line_number: usize) -> Result<(), String> {
+    let (key, value) = meta
+        .split_once('=')
+        .ok_or_else(|| format!("line {}: malformed meta-information line", line_number))?;
+
+    match key {
+        "INFO" | "FORMAT" | "FILTER" => {
+            let fields = parse_structured(value, line_number)?;
+            let field = HeaderField {
+                id: required(&fields, "ID", line_number)?,
+                number: optional(&fields, "Number"),
+                kind: optional(&fields, "Type"),
+                description: optional(&fields, "Description"),
+            };
+            match key {
+                "INFO" => header.info.push(field),
+                "FORMAT" => header.format.push(field),
+                _ => header.filters.push(field),
+            }
+        }
+        "contig" => {
+            let fields = parse_structured(value, line_number)?;
+            header.contigs.push(Contig {
+                id: required(&fields, "ID", line_number)?,
+                length: optional(&fields, "length").and_then(|l| l.parse().ok()),
+            });
+        }
+        _ => header.other.push((key.to_string(), value.to_string())),
+    }
+
+    Ok(())
+}
+
+/// Parse `<ID=x,Number=1,Description="a, b">` into key/value pairs
+fn parse_structured(value: &str, line_number: usize) -> Result<Vec<(String, String)>, String> {
+    let inner = value
+        .strip_prefix('<')
+        .and_then(|v| v.strip_suffix('>'))
+        .ok_or_else(|| format!("line {}: expected <...> structured value", line_number))?;
+
+    let mut fields = Vec::new();
+    let mut key = String::new();
+    let mut current = String::new();
+    let mut in_key = true;
+    let mut in_quotes = false;
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            '"' => in_quotes = !in_quotes,
+            '=' if in_key => in_key = false,
+            ',' if !in_quotes => {
+                fields.push((std::mem::take(&mut key), std::mem::take(&mut current)));
+                in_key = true;
+            }
+            _ if in_key => key.push(c),
+            _ => current.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("line {}: unterminated quoted string", line_number));
+    }
+    if !key.is_empty() {
+        fields.push((key, current));
+    }
+
+    Ok(fields)
+}
+
+fn required(fields: &[(String, String)], key: &str, line_number: usize) -> Result<String, String> {
+    optional(fields, key)
+        .ok_or_else(|| format!("line {}: missing {} in definition", line_number, key))
+}
+
+fn optional(fields: &[(String, String)], key: &str) -> Option<String> {
+    fields
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.clone())
+}
+
+fn parse_column_line(line: &str, line_number: usize) -> Result<Vec<String>, String> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < FIXED_COLUMNS.len() || columns[..FIXED_COLUMNS.len()] != FIXED_COLUMNS {
+        return Err(format!(
+            "line {}: malformed #CHROM header line",
+            line_number
+        ));
+    }
+
+    // Sample columns are only present when a FORMAT column is
+    match columns.get(FIXED_COLUMNS.len()) {
+        Some(&"FORMAT") => Ok(columns[FIXED_COLUMNS.len() + 1..]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()),
+        Some(other) => Err(format!(
+            "line {}: expected FORMAT column, found {}",
+            line_number, other
+        )),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Kind of a structural record, from `SVTYPE` or its first alternate
+fn structural_kind(record: &VcfRecord) -> Option<StructuralKind> {
+    let alternate = record
+        .alternates
+        .iter()
+        .find(|alternate| !GVCF_PLACEHOLDERS.contains(&alternate.as_str()))?;
+    if alternate.contains(['[', ']']) {
+        return Some(StructuralKind::Breakend);
+    }
+    // Subtypes such as <DEL:ME:ALU> and <DUP:TANDEM> are of their type
+    let symbolic = alternate
+        .strip_prefix('<')
+        .and_then(|name| name.strip_suffix('>'))
+        .and_then(|name| name.split(':').next());
+    let name = record.info_value("SVTYPE").or(symbolic)?;
+    match name.to_ascii_uppercase().as_str() {
+        "DEL" => Some(StructuralKind::Deletion),
+        "DUP" => Some(StructuralKind::Duplication),
+        "INV" => Some(StructuralKind::Inversion),
+        "INS" => Some(StructuralKind::Insertion),
+        "BND" => Some(StructuralKind::Breakend),
+        "CNV" => Some(StructuralKind::CopyNumber),
+        // <CN0>, <CN1>, ... as 1000 Genomes writes copy numbers
+        cn => match cn.strip_prefix("CN")?.parse::<u32>().ok()? {
+            0 | 1 => Some(StructuralKind::Deletion),
+            2 => None,
+            _ => Some(StructuralKind::Duplication),
+        },
+    }
+}
+
+/// Last reference base covered by a record at `position`
+pub(super) fn record_end(position: u64, reference: &str, end: Option<&str>) -> u64 {
+    let from_reference = position + reference.len().max(1) as u64 - 1;
+    end.and_then(|end| end.parse::<u64>().ok())
+        .map_or(from_reference, |end| end.max(from_reference))
+}
+
+pub(super) fn parse_record(
+    line: &str,
+    sample_count: usize,
+    line_number: usize,
+) -> Result<VcfRecord, String> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    let expected = if sample_count > 0 {
+        FIXED_COLUMNS.len() + 1 + sample_count
+    } else {
+        FIXED_COLUMNS.len()
+    };
+    if columns.len() < expected {
+        return Err(format!(
+            "line {}: expected {} columns, found {}",
+            line_number,
+            expected,
+            columns.len()
+        ));
+    }
+
+    let position = columns[1]
+        .parse::<u64>()
+        .map_err(|_| format!("line {}: invalid position '{}'", line_number, columns[1]))?;
+
+    let reference = columns[3].to_ascii_uppercase();
+    if reference.is_empty() || reference == "." {
+        return Err(format!("line {}: missing reference allele", line_number));
+    }
+
+    let quality = match columns[5] {
+        "." => None,
+        q => Some(
+            q.parse::<f64>()
+                .map_err(|_| format!("line {}: invalid QUAL '{}'", line_number, q))?,
+        ),
+    };
+
+    let info = match columns[7] {
+        "." => Vec::new(),
+        raw => raw
+            .split(';')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('=') {
+                Some((k, v)) => (k.to_string(), Some(v.to_string())),
+                None => (entry.to_string(), None),
+            })
+            .collect(),
+    };
+
+    let (format, samples) = if sample_count > 0 {
+        let format: Vec<String> = columns[8].split(':').map(|s| s.to_string()).collect();
+        let samples = columns[9..9 + sample_count]
+            .iter()
+            .map(|s| s.split(':').map(|v| v.to_string()).collect())
+            .collect();
+        (format, samples)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    Ok(VcfRecord {
+        chromosome: columns[0].to_string(),
+        position,
+        id: dot_to_none(columns[2]),
+        reference,
+        alternates: split_dotted(columns[4], ','),
+        quality,
+        filters: split_dotted(columns[6], ';'),
+        info,
+        format,
+        samples,
+    })
+}
+
+fn dot_to_none(value: &str) -> Option<String> {
+    match value {
+        "." | "" => None,
+        v => Some(v.to_string()),
+    }
+}
+
+fn split_dotted(value: &str, separator: char) -> Vec<String> {
+    match value {
+        "." | "" => Vec::new(),
+        v => v.split(separator).map(|s| s.to_string()).collect(),
+    }
+}
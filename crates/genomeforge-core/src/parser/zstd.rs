@@ -0,0 +1,1039 @@
+//! Zstandard decompression (RFC 8878)
+//!
+//! Covers what the `zstd` tool writes: raw, RLE and compressed blocks,
+//! Huffman-coded literals, FSE-coded sequences, skippable frames and the
+//! optional XXH64 content checksum. Dictionaries are not supported. Frames
+//! are decoded a block at a time and only the window a frame asks for is
+//! kept behind the reader, so memory stays bounded however large the
+//! stream is.
+
+use std::io::{self, Read};
+
+/// Magic bytes every Zstandard frame starts with
+pub const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Skippable frames start with 0x184D2A50 to 0x184D2A5F, little-endian
+const SKIPPABLE_MAGIC: u32 = 0x184d_2a50;
+
+/// Largest window accepted, the default limit of the reference decoder
+const MAX_WINDOW: u64 = 1 << 27;
+
+/// Largest block, compressed or not
+const MAX_BLOCK: usize = 128 * 1024;
+
+/// Repeat offsets at the start of every frame
+const INITIAL_OFFSETS: [usize; 3] = [1, 4, 8];
+
+/// Longest Huffman code of literals
+const MAX_HUFFMAN_BITS: u32 = 11;
+
+/// Highest accuracy log of the FSE tables of literal lengths, match
+/// lengths, offsets and Huffman weights
+const MAX_LITERALS_LOG: u32 = 9;
+const MAX_MATCH_LOG: u32 = 9;
+const MAX_OFFSETS_LOG: u32 = 8;
+const MAX_WEIGHTS_LOG: u32 = 6;
+
+/// Highest literal length, match length and offset codes
+const MAX_LITERALS_CODE: usize = 35;
+const MAX_MATCH_CODE: usize = 52;
+const MAX_OFFSET_CODE: usize = 31;
+
+/// Default distributions of the codes, with their accuracy logs
+const LITERALS_DEFAULT: (u32, &[i16]) = (
+    6,
+    &[
+        4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 3, 2, 1, 1, 1,
+        1, 1, -1, -1, -1, -1,
+    ],
+);
+const MATCH_DEFAULT: (u32, &[i16]) = (
+    6,
+    &[
+        1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1, -1, -1,
+    ],
+);
+const OFFSETS_DEFAULT: (u32, &[i16]) = (
+    5,
+    &[
+        1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
+    ],
+);
+
+/// Base value and extra bits of literal length codes 16 and up; the
+/// codes below stand for themselves
+const LITERALS_LENGTHS: [(u32, u32); 20] = [
+    (16, 1),
+    (18, 1),
+    (20, 1),
+    (22, 1),
+    (24, 2),
+    (28, 2),
+    (32, 3),
+    (40, 3),
+    (48, 4),
+    (64, 6),
+    (128, 7),
+    (256, 8),
+    (512, 9),
+    (1024, 10),
+    (2048, 11),
+    (4096, 12),
+    (8192, 13),
+    (16384, 14),
+    (32768, 15),
+    (65536, 16),
+];
+
+/// Base value and extra bits of match length codes 32 and up; the codes
+/// below stand for themselves plus 3
+const MATCH_LENGTHS: [(u32, u32); 21] = [
+    (35, 1),
+    (37, 1),
+    (39, 1),
+    (41, 1),
+    (43, 2),
+    (47, 2),
+    (51, 3),
+    (59, 3),
+    (67, 4),
+    (83, 4),
+    (99, 5),
+    (131, 7),
+    (259, 8),
+    (515, 9),
+    (1027, 10),
+    (2051, 11),
+    (4099, 12),
+    (8195, 13),
+    (16387, 14),
+    (32771, 15),
+    (65539, 16),
+];
+
+/// Reads the decompressed contents of concatenated Zstandard frames
+pub struct ZstdDecoder<R: Read> {
+    inner: R,
+    frame: Option<Frame>,
+    /// Decompressed bytes, the window of the frame followed by what has
+    /// not been read yet
+    data: Vec<u8>,
+    position: usize,
+    compressed: Vec<u8>,
+}
+
+/// What decoding a frame carries from one block to the next
+struct Frame {
+    window_size: usize,
+    content_size: Option<u64>,
+    decoded: u64,
+    checksum: Option<Xxh64>,
+    offsets: [usize; 3],
+    huffman: Option<HuffmanTable>,
+    literals_table: Option<FseTable>,
+    match_table: Option<FseTable>,
+    offsets_table: Option<FseTable>,
+}
+
+impl<R: Read> ZstdDecoder<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            frame: None,
+            data: Vec::new(),
+            position: 0,
+            compressed: Vec::new(),
+        }
+    }
+
+    /// Decompress the next block; false at the end of the stream
+    fn read_block(&mut self) -> io::Result<bool> {
+        let mut frame = match self.frame.take() {
+            Some(frame) => frame,
+            None => match self.read_frame_header()? {
+                Some(frame) => {
+                    self.data.clear();
+                    self.position = 0;
+                    frame
+                }
+                None => return Ok(false),
+            },
+        };
+        // Keep only the window behind the reader, trimming once it has
+        // doubled so the bytes are not moved on every block
+        if self.data.len() > 2 * frame.window_size.max(MAX_BLOCK) {
+            let trimmed = self.data.len() - frame.window_size;
+            self.data.drain(..trimmed);
+            self.position -= trimmed;
+        }
+
+        let mut header = [0u8; 3];
+        self.inner.read_exact(&mut header).map_err(truncated)?;
+        let header = u32::from_le_bytes([header[0], header[1], header[2], 0]);
+        let last = header & 1 == 1;
+        let size = (header >> 3) as usize;
+        if size > MAX_BLOCK.min(frame.window_size.max(1)) && header >> 1 & 3 != 1 {
+            return Err(invalid("block is too large"));
+        }
+        let start = self.data.len();
+        match header >> 1 & 3 {
+            0 => {
+                self.data.resize(start + size, 0);
+                self.inner
+                    .read_exact(&mut self.data[start..])
+                    .map_err(truncated)?;
+            }
+            1 => {
+                if size > MAX_BLOCK {
+                    return Err(invalid("block is too large"));
+                }
+                let mut byte = [0u8; 1];
+                self.inner.read_exact(&mut byte).map_err(truncated)?;
+                self.data.resize(start + size, byte[0]);
+            }
+            2 => {
+                self.compressed.resize(size, 0);
+                self.inner
+                    .read_exact(&mut self.compressed)
+                    .map_err(truncated)?;
+                decode_block(&self.compressed, &mut frame, &mut self.data)?;
+            }
+            _ => return Err(invalid("reserved block type")),
+        }
+
+        frame.decoded += (self.data.len() - start) as u64;
+        if let Some(checksum) = &mut frame.checksum {
+            checksum.update(&self.data[start..]);
+        }
+        if frame
+            .content_size
+            .is_some_and(|size| frame.decoded > size || last && frame.decoded != size)
+        {
+            return Err(invalid("frame is not the size it declares"));
+        }
+        if last {
+            if let Some(checksum) = &frame.checksum {
+                let mut expected = [0u8; 4];
+                self.inner.read_exact(&mut expected).map_err(truncated)?;
+                if checksum.digest() as u32 != u32::from_le_bytes(expected) {
+                    return Err(invalid("checksum mismatch"));
+                }
+            }
+        } else {
+            self.frame = Some(frame);
+        }
+        Ok(true)
+    }
+
+    /// Read up to the first block of the next frame, passing over
+    /// skippable frames; `None` at the end of the stream
+    fn read_frame_header(&mut self) -> io::Result<Option<Frame>> {
+        loop {
+            let mut magic = [0u8; 4];
+            let mut filled = 0;
+            while filled < magic.len() {
+                match self.inner.read(&mut magic[filled..]) {
+                    Ok(0) if filled == 0 => return Ok(None),
+                    Ok(0) => return Err(truncated(io::ErrorKind::UnexpectedEof.into())),
+                    Ok(read) => filled += read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            let number = u32::from_le_bytes(magic);
+            if number & !0xf == SKIPPABLE_MAGIC {
+                let mut size = [0u8; 4];
+                self.inner.read_exact(&mut size).map_err(truncated)?;
+                let size = u32::from_le_bytes(size) as u64;
+                let skipped = io::copy(&mut (&mut self.inner).take(size), &mut io::sink())?;
+                if skipped < size {
+                    return Err(truncated(io::ErrorKind::UnexpectedEof.into()));
+                }
+                continue;
+            }
+            if magic != MAGIC {
+                return Err(invalid("not a Zstandard frame"));
+            }
+            return self.read_frame_descriptor().map(Some);
+        }
+    }
+
+    fn read_frame_descriptor(&mut self) -> io::Result<Frame> {
+        let mut byte = [0u8; 1];
+        self.inner.read_exact(&mut byte).map_err(truncated)?;
+        let descriptor = byte[0];
+        if descriptor & 0x08 != 0 {
+            return Err(invalid("reserved frame header bit is set"));
+        }
+        let single_segment = descriptor & 0x20 != 0;
+        let dictionary_len = [0, 1, 2, 4][(descriptor & 3) as usize];
+        let content_size_len = match descriptor >> 6 {
+            0 if single_segment => 1,
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+
+        let mut window_size = None;
+        if !single_segment {
+            self.inner.read_exact(&mut byte).map_err(truncated)?;
+            let base = 1u64 << (10 + (byte[0] >> 3));
+            window_size = Some(base + base / 8 * (byte[0] & 7) as u64);
+        }
+        let mut field = [0u8; 8];
+        self.inner
+            .read_exact(&mut field[..dictionary_len])
+            .map_err(truncated)?;
+        if u64::from_le_bytes(field) != 0 {
+            return Err(invalid("frames that need a dictionary are not supported"));
+        }
+        let mut field = [0u8; 8];
+        self.inner
+            .read_exact(&mut field[..content_size_len])
+            .map_err(truncated)?;
+        let content_size = match content_size_len {
+            0 => None,
+            2 => Some(u64::from_le_bytes(field) + 256),
+            _ => Some(u64::from_le_bytes(field)),
+        };
+        let window_size = window_size
+            .or(content_size)
+            .ok_or_else(|| invalid("frame header is corrupt"))?;
+        if window_size > MAX_WINDOW {
+            return Err(invalid("frame window is too large"));
+        }
+
+        Ok(Frame {
+            window_size: window_size as usize,
+            content_size,
+            decoded: 0,
+            checksum: (descriptor & 0x04 != 0).then(Xxh64::new),
+            offsets: INITIAL_OFFSETS,
+            huffman: None,
+            literals_table: None,
+            match_table: None,
+            offsets_table: None,
+        })
+    }
+}
+
+impl<R: Read> Read for ZstdDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Skip empty blocks and frames
+        while self.position == self.data.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+        let available = &self.data[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+// Helper functions
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Zstandard data is corrupt: {}", message),
+    )
+}
+
+fn truncated(e: io::Error) -> io::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        invalid("stream is truncated")
+    } else {
+        e
+    }
+}
+
+/// Decode a compressed block onto the end of `out`, which holds the
+/// window of the frame before it
+fn decode_block(block: &[u8], frame: &mut Frame, out: &mut Vec<u8>) -> io::Result<()> {
+    let (literals, used) = decode_literals(block, &mut frame.huffman)?;
+    let block = &block[used..];
+    let start = out.len();
+
+    let (count, used) = match *block {
+        [] => return Err(invalid("sequences section is missing")),
+        [0, ..] => (0, 1),
+        [byte, ..] if byte < 128 => (byte as usize, 1),
+        [byte, next, ..] if byte < 255 => (((byte as usize - 128) << 8) + next as usize, 2),
+        [255, low, high, ..] => (low as usize + ((high as usize) << 8) + 0x7f00, 3),
+        _ => return Err(invalid("sequences section is truncated")),
+    };
+    if count == 0 {
+        if used != block.len() {
+            return Err(invalid("sequences section is corrupt"));
+        }
+        out.extend_from_slice(&literals);
+        return Ok(());
+    }
+
+    let block = &block[used..];
+    let modes = *block
+        .first()
+        .ok_or_else(|| invalid("sequences section is truncated"))?;
+    if modes & 3 != 0 {
+        return Err(invalid("reserved sequence mode bits are set"));
+    }
+    let mut at = 1;
+    for (table, mode, default, max_log, max_symbol) in [
+        (
+            &mut frame.literals_table,
+            modes >> 6,
+            LITERALS_DEFAULT,
+            MAX_LITERALS_LOG,
+            MAX_LITERALS_CODE,
+        ),
+        (
+            &mut frame.offsets_table,
+            modes >> 4 & 3,
+            OFFSETS_DEFAULT,
+            MAX_OFFSETS_LOG,
+            MAX_OFFSET_CODE,
+        ),
+        (
+            &mut frame.match_table,
+            modes >> 2 & 3,
+            MATCH_DEFAULT,
+            MAX_MATCH_LOG,
+            MAX_MATCH_CODE,
+        ),
+    ] {
+        match mode {
+            0 => *table = Some(FseTable::new(default.0, default.1)?),
+            1 => {
+                let symbol = *block
+                    .get(at)
+                    .ok_or_else(|| invalid("sequences section is truncated"))?;
+                if symbol as usize > max_symbol {
+                    return Err(invalid("sequence code is out of range"));
+                }
+                *table = Some(FseTable::single(symbol));
+                at += 1;
+            }
+            2 => {
+                let (log, counts, used) = read_distribution(&block[at..], max_log, max_symbol)?;
+                *table = Some(FseTable::new(log, &counts)?);
+                at += used;
+            }
+            _ => {
+                if table.is_none() {
+                    return Err(invalid("repeated sequence table is missing"));
+                }
+            }
+        }
+    }
+
+    let literals_table = frame.literals_table.as_ref().unwrap();
+    let offsets_table = frame.offsets_table.as_ref().unwrap();
+    let match_table = frame.match_table.as_ref().unwrap();
+    let mut bits = BackwardBits::new(&block[at..])?;
+    let mut literals_state = bits.read(literals_table.log) as usize;
+    let mut offsets_state = bits.read(offsets_table.log) as usize;
+    let mut match_state = bits.read(match_table.log) as usize;
+    let mut literals_used = 0;
+    let offsets = &mut frame.offsets;
+    for index in 0..count {
+        let offset_code = offsets_table.entries[offsets_state].symbol as u32;
+        let literals_code = literals_table.entries[literals_state].symbol as usize;
+        let match_code = match_table.entries[match_state].symbol as usize;
+
+        let offset_value = (1u64 << offset_code) + bits.read(offset_code);
+        let match_length = if match_code < 32 {
+            match_code + 3
+        } else {
+            let (base, extra) = MATCH_LENGTHS[match_code - 32];
+            (base as u64 + bits.read(extra)) as usize
+        };
+        let literals_length = if literals_code < 16 {
+            literals_code
+        } else {
+            let (base, extra) = LITERALS_LENGTHS[literals_code - 16];
+            (base as u64 + bits.read(extra)) as usize
+        };
+
+        let offset = if offset_value > 3 {
+            let offset = offset_value as usize - 3;
+            *offsets = [offset, offsets[0], offsets[1]];
+            offset
+        } else {
+            let repeat = offset_value as usize - 1 + (literals_length == 0) as usize;
+            if repeat == 0 {
+                offsets[0]
+            } else {
+                let offset = if repeat == 3 {
+                    offsets[0] - 1
+                } else {
+                    offsets[repeat]
+                };
+                if repeat > 1 {
+                    offsets[2] = offsets[1];
+                }
+                offsets[1] = offsets[0];
+                offsets[0] = offset;
+                offset
+            }
+        };
+
+        let literal_end = literals_used + literals_length;
+        let run = literals
+            .get(literals_used..literal_end)
+            .ok_or_else(|| invalid("sequence reads past its literals"))?;
+        out.extend_from_slice(run);
+        literals_used = literal_end;
+        if offset == 0 || offset > out.len() {
+            return Err(invalid("match reaches before the start of the frame"));
+        }
+        if out.len() - start + match_length > MAX_BLOCK {
+            return Err(invalid("block is too large"));
+        }
+        let from = out.len() - offset;
+        if offset >= match_length {
+            out.extend_from_within(from..from + match_length);
+        } else {
+            // Overlapping matches repeat what they have just copied
+            for at in from..from + match_length {
+                out.push(out[at]);
+            }
+        }
+
+        if index + 1 < count {
+            literals_state = literals_table.next(literals_state, &mut bits);
+            match_state = match_table.next(match_state, &mut bits);
+            offsets_state = offsets_table.next(offsets_state, &mut bits);
+        }
+    }
+    if !bits.finished() {
+        return Err(invalid("sequences bitstream is corrupt"));
+    }
+    out.extend_from_slice(&literals[literals_used..]);
+    if out.len() - start > MAX_BLOCK {
+        return Err(invalid("block is too large"));
+    }
+    Ok(())
+}
+
+/// Decode the literals section at the start of a block, returning the
+/// literals and the bytes the section took
+fn decode_literals(
+    block: &[u8],
+    huffman: &mut Option<HuffmanTable>,
+) -> io::Result<(Vec<u8>, usize)> {
+    let short = || invalid("literals section is truncated");
+    let first = *block.first().ok_or_else(short)?;
+    let kind = first & 3;
+    let size_format = first >> 2 & 3;
+    let header = |len: usize| -> io::Result<u64> {
+        let bytes = block.get(..len).ok_or_else(short)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0u64, |value, &byte| value << 8 | byte as u64))
+    };
+
+    if kind < 2 {
+        let (size, used) = match size_format {
+            0 | 2 => ((first >> 3) as usize, 1),
+            1 => ((header(2)? >> 4) as usize, 2),
+            _ => ((header(3)? >> 4) as usize, 3),
+        };
+        if size > MAX_BLOCK {
+            return Err(invalid("literals section is too large"));
+        }
+        if kind == 0 {
+            let literals = block.get(used..used + size).ok_or_else(short)?;
+            return Ok((literals.to_vec(), used + size));
+        }
+        let byte = *block.get(used).ok_or_else(short)?;
+        return Ok((vec![byte; size], used + 1));
+    }
+
+    let (used, bits, streams) = match size_format {
+        0 => (3, 10, 1),
+        1 => (3, 10, 4),
+        2 => (4, 14, 4),
+        _ => (5, 18, 4),
+    };
+    let value = header(used)?;
+    let mask = (1u64 << bits) - 1;
+    let size = (value >> 4 & mask) as usize;
+    let compressed_size = (value >> (4 + bits) & mask) as usize;
+    if size > MAX_BLOCK {
+        return Err(invalid("literals section is too large"));
+    }
+    let mut data = block.get(used..used + compressed_size).ok_or_else(short)?;
+    if kind == 2 {
+        let (table, tree_size) = HuffmanTable::read(data)?;
+        *huffman = Some(table);
+        data = &data[tree_size..];
+    }
+    let table = huffman
+        .as_ref()
+        .ok_or_else(|| invalid("repeated Huffman table is missing"))?;
+
+    let mut literals = Vec::with_capacity(size);
+    if streams == 1 {
+        table.decode(data, size, &mut literals)?;
+    } else {
+        if data.len() < 6 {
+            return Err(short());
+        }
+        let jump = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+        let sizes = [jump(0), jump(2), jump(4)];
+        let mut rest = &data[6..];
+        let each = size.div_ceil(4);
+        for (index, stream_size) in sizes.iter().enumerate() {
+            if *stream_size > rest.len() || each * (index + 1) > size {
+                return Err(invalid("literals streams are corrupt"));
+            }
+            let (stream, tail) = rest.split_at(*stream_size);
+            table.decode(stream, each, &mut literals)?;
+            rest = tail;
+        }
+        table.decode(rest, size - 3 * each, &mut literals)?;
+    }
+    Ok((literals, used + compressed_size))
+}
+
+/// Read an FSE table description, returning its accuracy log, the
+/// normalized count of each symbol and the bytes it took
+fn read_distribution(
+    data: &[u8],
+    max_log: u32,
+    max_symbol: usize,
+) -> io::Result<(u32, Vec<i16>, usize)> {
+    let mut bits = ForwardBits { data, position: 0 };
+    let log = bits.read(4) as u32 + 5;
+    if log > max_log {
+        return Err(invalid("FSE accuracy is too high"));
+    }
+    let mut remaining = (1i32 << log) + 1;
+    let mut threshold = 1i32 << log;
+    let mut width = log + 1;
+    let mut counts = Vec::new();
+    while remaining > 1 {
+        if counts.len() > max_symbol {
+            return Err(invalid("FSE table has too many symbols"));
+        }
+        let max = 2 * threshold - 1 - remaining;
+        let low = bits.peek(width - 1) as i32;
+        let value = if low < max {
+            bits.skip(width - 1);
+            low
+        } else {
+            let value = bits.peek(width) as i32;
+            bits.skip(width);
+            if value >= threshold {
+                value - max
+            } else {
+                value
+            }
+        };
+        let count = value - 1;
+        remaining -= count.abs();
+        if remaining < 1 {
+            return Err(invalid("FSE table is corrupt"));
+        }
+        counts.push(count as i16);
+        if count == 0 {
+            // Runs of symbols that never occur are counted in twos
+            loop {
+                let repeat = bits.read(2);
+                counts.extend(std::iter::repeat_n(0, repeat as usize));
+                if repeat != 3 {
+                    break;
+                }
+            }
+        }
+        while remaining < threshold {
+            width -= 1;
+            threshold >>= 1;
+        }
+    }
+    if remaining != 1 || counts.len() > max_symbol + 1 || bits.position > data.len() * 8 {
+        return Err(invalid("FSE table is corrupt"));
+    }
+    Ok((log, counts, bits.position.div_ceil(8)))
+}
+
+/// A finite state entropy decoding table
+#[derive(Clone)]
+struct FseTable {
+    log: u32,
+    entries: Vec<FseEntry>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FseEntry {
+    symbol: u8,
+    bits: u8,
+    baseline: u16,
+}
+
+impl FseTable {
+    /// Spread symbols over the table by their normalized counts, those of
+    /// count -1 taking one cell each at the end
+    fn new(log: u32, counts: &[i16]) -> io::Result<Self> {
+        let size = 1usize << log;
+        let mut entries = vec![FseEntry::default(); size];
+        let mut next = vec![0u32; counts.len()];
+        let mut high = size;
+        for (symbol, &count) in counts.iter().enumerate() {
+            if count == -1 {
+                high -= 1;
+                entries[high].symbol = symbol as u8;
+                next[symbol] = 1;
+            } else {
+                next[symbol] = count.max(0) as u32;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut position = 0;
+        for (symbol, &count) in counts.iter().enumerate() {
+            for _ in 0..count.max(0) {
+                entries[position].symbol = symbol as u8;
+                loop {
+                    position = (position + step) & (size - 1);
+                    if position < high {
+                        break;
+                    }
+                }
+            }
+        }
+        if position != 0 {
+            return Err(invalid("FSE table is corrupt"));
+        }
+        for entry in &mut entries {
+            let state = next[entry.symbol as usize];
+            next[entry.symbol as usize] += 1;
+            let bits = log - state.ilog2();
+            entry.bits = bits as u8;
+            entry.baseline = ((state << bits) as usize - size) as u16;
+        }
+        Ok(Self { log, entries })
+    }
+
+    /// A table that always gives `symbol` and reads no bits
+    fn single(symbol: u8) -> Self {
+        Self {
+            log: 0,
+            entries: vec![FseEntry {
+                symbol,
+                bits: 0,
+                baseline: 0,
+            }],
+        }
+    }
+
+    fn next(&self, state: usize, bits: &mut BackwardBits<'_>) -> usize {
+        let entry = self.entries[state];
+        entry.baseline as usize + bits.read(entry.bits as u32) as usize
+    }
+}
+
+/// Huffman decoding table of literals, indexed by the next `max_bits`
+/// bits of a stream
+struct HuffmanTable {
+    max_bits: u32,
+    /// Symbol and code length of each index
+    entries: Vec<(u8, u8)>,
+}
+
+impl HuffmanTable {
+    /// Read a tree description, returning the table and the bytes it took
+    fn read(data: &[u8]) -> io::Result<(Self, usize)> {
+        let short = || invalid("Huffman tree description is truncated");
+        let header = *data.first().ok_or_else(short)? as usize;
+        let (mut weights, used) = if header < 128 {
+            let stream = data.get(1..1 + header).ok_or_else(short)?;
+            (decode_weights(stream)?, 1 + header)
+        } else {
+            let count = header - 127;
+            let packed = data.get(1..1 + count.div_ceil(2)).ok_or_else(short)?;
+            let weights = (0..count)
+                .map(|index| packed[index / 2] >> (if index % 2 == 0 { 4 } else { 0 }) & 0xf)
+                .collect();
+            (weights, 1 + count.div_ceil(2))
+        };
+
+        // The weight of the last symbol makes the total a power of two
+        let mut total = 0u32;
+        for &weight in &weights {
+            if weight as u32 > MAX_HUFFMAN_BITS {
+                return Err(invalid("Huffman weight is out of range"));
+            }
+            if weight > 0 {
+                total += 1 << (weight - 1);
+            }
+        }
+        if total == 0 || weights.len() > 255 {
+            return Err(invalid("Huffman tree is corrupt"));
+        }
+        let max_bits = total.ilog2() + 1;
+        let left = (1 << max_bits) - total;
+        if max_bits > MAX_HUFFMAN_BITS || !left.is_power_of_two() {
+            return Err(invalid("Huffman tree is corrupt"));
+        }
+        weights.push(left.ilog2() as u8 + 1);
+
+        // Codes go to the longest first, in symbol order within a length
+        let mut entries = vec![(0u8, 0u8); 1 << max_bits];
+        let mut position = 0;
+        for weight in 1..=max_bits as u8 {
+            for (symbol, _) in weights.iter().enumerate().filter(|(_, &w)| w == weight) {
+                let cells = 1 << (weight - 1);
+                let bits = max_bits as u8 + 1 - weight;
+                entries[position..position + cells].fill((symbol as u8, bits));
+                position += cells;
+            }
+        }
+        Ok((Self { max_bits, entries }, used))
+    }
+
+    /// Decode `count` literals from one stream onto the end of `out`
+    fn decode(&self, stream: &[u8], count: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let mut bits = BackwardBits::new(stream)?;
+        for _ in 0..count {
+            let (symbol, length) = self.entries[bits.peek(self.max_bits) as usize];
+            bits.skip(length as u32);
+            out.push(symbol);
+        }
+        if !bits.finished() {
+            return Err(invalid("literals stream is corrupt"));
+        }
+        Ok(())
+    }
+}
+
+/// Huffman weights compressed with FSE, decoded with two interleaved
+/// states until the stream runs out
+fn decode_weights(stream: &[u8]) -> io::Result<Vec<u8>> {
+    let (log, counts, used) = read_distribution(stream, MAX_WEIGHTS_LOG, 255)?;
+    let table = FseTable::new(log, &counts)?;
+    let mut bits = BackwardBits::new(&stream[used..])?;
+    let mut states = [bits.read(log) as usize, bits.read(log) as usize];
+    let mut weights = Vec::new();
+    for turn in 0.. {
+        if weights.len() >= 255 {
+            return Err(invalid("Huffman tree is corrupt"));
+        }
+        let current = turn % 2;
+        weights.push(table.entries[states[current]].symbol);
+        states[current] = table.next(states[current], &mut bits);
+        if bits.overflowed() {
+            weights.push(table.entries[states[1 - current]].symbol);
+            break;
+        }
+    }
+    Ok(weights)
+}
+
+/// Bits of a stream read from its start, least significant first
+struct ForwardBits<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl ForwardBits<'_> {
+    /// The next `count` bits, zeros past the end
+    fn peek(&self, count: u32) -> u64 {
+        let mut value = 0;
+        for bit in 0..count as usize {
+            let at = self.position + bit;
+            let set = self
+                .data
+                .get(at / 8)
+                .is_some_and(|byte| byte >> (at % 8) & 1 == 1);
+            value |= (set as u64) << bit;
+        }
+        value
+    }
+
+    fn skip(&mut self, count: u32) {
+        self.position += count as usize;
+    }
+
+    fn read(&mut self, count: u32) -> u64 {
+        let value = self.peek(count);
+        self.skip(count);
+        value
+    }
+}
+
+/// Bits of a stream read from its end, most significant first, after the
+/// marker bit that ends it
+struct BackwardBits<'a> {
+    data: &'a [u8],
+    /// Bits left before the start of the stream; negative once read past it
+    position: isize,
+}
+
+impl<'a> BackwardBits<'a> {
+    fn new(data: &'a [u8]) -> io::Result<Self> {
+        match data.last() {
+            Some(&last) if last != 0 => Ok(Self {
+                data,
+                position: data.len() as isize * 8 - last.leading_zeros() as isize - 1,
+            }),
+            _ => Err(invalid("bitstream has no end marker")),
+        }
+    }
+
+    /// The next `count` bits, at most 56, zeros past the start
+    fn peek(&self, count: u32) -> u64 {
+        let end = self.position;
+        if count == 0 || end <= 0 {
+            return 0;
+        }
+        let start = end - count as isize;
+        let from = start.max(0) as usize;
+        let mut word = [0u8; 8];
+        let bytes = self.data.get(from / 8..).unwrap_or(&[]);
+        let len = bytes.len().min(8);
+        word[..len].copy_from_slice(&bytes[..len]);
+        let available = (end - from as isize).max(0) as u32;
+        let value = u64::from_le_bytes(word) >> (from % 8) & ((1u64 << available) - 1);
+        // Bits before the start of the stream read as zeros
+        value << (from as isize - start)
+    }
+
+    fn skip(&mut self, count: u32) {
+        self.position -= count as isize;
+    }
+
+    fn read(&mut self, count: u32) -> u64 {
+        let value = self.peek(count);
+        self.skip(count);
+        value
+    }
+
+    fn overflowed(&self) -> bool {
+        self.position < 0
+    }
+
+    fn finished(&self) -> bool {
+        self.position == 0
+    }
+}
+
+/// XXH64 with a seed of zero, of which frames store the low 32 bits
+struct Xxh64 {
+    accumulators: [u64; 4],
+    buffer: [u8; 32],
+    buffered: usize,
+    total: u64,
+}
+
+const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+impl Xxh64 {
+    fn new() -> Self {
+        Self {
+            accumulators: [
+                PRIME_1.wrapping_add(PRIME_2),
+                PRIME_2,
+                0,
+                0u64.wrapping_sub(PRIME_1),
+            ],
+            buffer: [0; 32],
+            buffered: 0,
+            total: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total += data.len() as u64;
+        if self.buffered > 0 {
+            let taken = data.len().min(32 - self.buffered);
+            self.buffer[self.buffered..self.buffered + taken].copy_from_slice(&data[..taken]);
+            self.buffered += taken;
+            data = &data[taken..];
+            if self.buffered < 32 {
+                return;
+            }
+            let stripe = self.buffer;
+            self.stripe(&stripe);
+            self.buffered = 0;
+        }
+        let mut stripes = data.chunks_exact(32);
+        for stripe in &mut stripes {
+            self.stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    fn stripe(&mut self, stripe: &[u8]) {
+        for (accumulator, lane) in self.accumulators.iter_mut().zip(stripe.chunks_exact(8)) {
+            *accumulator = xxh_round(*accumulator, u64::from_le_bytes(lane.try_into().unwrap()));
+        }
+    }
+
+    fn digest(&self) -> u64 {
+        let [v1, v2, v3, v4] = self.accumulators;
+        let mut hash = if self.total >= 32 {
+            let mut hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for accumulator in self.accumulators {
+                hash = (hash ^ xxh_round(0, accumulator))
+                    .wrapping_mul(PRIME_1)
+                    .wrapping_add(PRIME_4);
+            }
+            hash
+        } else {
+            PRIME_5
+        };
+        hash = hash.wrapping_add(self.total);
+
+        let mut rest = &self.buffer[..self.buffered];
+        while rest.len() >= 8 {
+            let lane = u64::from_le_bytes(rest[..8].try_into().unwrap());
+            hash = (hash ^ xxh_round(0, lane))
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let lane = u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64;
+            hash = (hash ^ lane.wrapping_mul(PRIME_1))
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash = (hash ^ (byte as u64).wrapping_mul(PRIME_5))
+                .rotate_left(11)
+                .wrapping_mul(PRIME_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ hash >> 32
+    }
+}
+
+fn xxh_round(accumulator: u64, lane: u64) -> u64 {
+    accumulator
+        .wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
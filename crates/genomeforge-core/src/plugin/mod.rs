@@ -0,0 +1,320 @@
+//! Third-party analysis plugins
+//!
+//! A plugin adds findings the built-in panels do not cover, such as a rare
+//! disease panel, without changes to the engine. It implements
+//! [`AnalysisPlugin`]: given the normalized variants of a genome it
+//! returns typed [`PluginFinding`]s, which [`run`] checks against the
+//! consent policy and scores like any other finding.
+//!
+//! Plugins from outside the application are WebAssembly modules run in
+//! the sandbox of [`wasm`], each installed as a directory holding a
+//! [`MANIFEST_FILE`] describing it and the module it names:
+//!
+//! ```text
+//! plugins/
+//!   rare-disease-panel/
+//!     plugin.json
+//!     plugin.wasm
+//! ```
+//!
+//! The module exports its `memory` and two functions. `alloc(len: i32) ->
+//! i32` returns the address of `len` bytes for the host to write a JSON
+//! [`PluginInput`] to; `analyze(ptr: i32, len: i32) -> i64` then returns
+//! the address of its JSON output in the high 32 bits and the length in
+//! the low 32 bits. The output is an object holding a `findings` array of
+//! [`PluginFinding`]s, or an `error` message.
+
+pub mod wasm;
+
+use crate::annotation::clinvar::ClinicalSignificance;
+use crate::annotation::consent::{self, ConsentPolicy, FindingCategory};
+use crate::confidence::{self, Confidence, Evidence};
+use crate::genome::{GenomeBuild, Variant};
+use crate::store::LoadedGenome;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use wasm::{Instance, Module, ValType, Value};
+
+/// Version of the plugin interface this engine implements
+pub const API_VERSION: u32 = 1;
+
+/// Name of the manifest in a plugin's directory
+pub const MANIFEST_FILE: &str = "plugin.json";
+
+/// Instructions a plugin may execute in one analysis
+pub const FUEL: u64 = 2_000_000_000;
+
+/// Most findings a plugin may report
+pub const MAX_FINDINGS: usize = 10_000;
+
+/// What a plugin is, from its `plugin.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    /// Lowercase letters, digits, `-` and `_`, e.g. "rare-disease-panel"
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// Version of the plugin interface it was written for
+    pub api_version: u32,
+    /// rsids of the sites it reads; every call is passed when empty
+    #[serde(default)]
+    pub sites: Vec<String>,
+    /// File name of the WebAssembly module in the plugin's directory
+    #[serde(default = "default_module")]
+    pub module: String,
+}
+
+impl PluginManifest {
+    /// Check that the manifest can be used by this engine
+    pub fn validate(&self) -> Result<(), String> {
+        let id_valid = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !id_valid {
+            return Err(format!("Invalid plugin id {:?}", self.id));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Plugin {} has no name", self.id));
+        }
+        if self.api_version != API_VERSION {
+            return Err(format!(
+                "Plugin {} was written for plugin interface version {}; this version of GenomeForge runs version {}",
+                self.id, self.api_version, API_VERSION
+            ));
+        }
+        let module_valid = !self.module.is_empty()
+            && !self.module.contains(['/', '\\'])
+            && self.module != "."
+            && self.module != "..";
+        if !module_valid {
+            return Err(format!(
+                "Plugin {} must name a module file in its own directory",
+                self.id
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What a plugin is given, as JSON
+#[derive(Debug, Serialize)]
+pub struct PluginInput<'a> {
+    pub api_version: u32,
+    pub build: Option<GenomeBuild>,
+    /// Called variants, limited to the manifest's sites when it lists any
+    pub variants: Vec<&'a Variant>,
+}
+
+/// A finding a plugin reports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginFinding {
+    /// Id of the plugin; set by the host
+    #[serde(default)]
+    pub plugin: String,
+    /// What is found, e.g. "Familial hypercholesterolemia risk variant"
+    pub title: String,
+    /// Explanation for the report
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub genes: Vec<String>,
+    /// Site the finding rests on
+    #[serde(default)]
+    pub rsid: Option<String>,
+    /// Genotype at `rsid`; set by the host
+    #[serde(default)]
+    pub genotype: Option<String>,
+    pub significance: ClinicalSignificance,
+    /// Consent categories the finding falls under; it is dropped when the
+    /// policy excludes any of them
+    #[serde(default)]
+    pub categories: Vec<FindingCategory>,
+    /// Strength of the evidence behind the finding, 0.0 - 1.0
+    #[serde(default)]
+    pub evidence: f64,
+    /// Publications or database records, e.g. "PMID:12345" or a URL
+    #[serde(default)]
+    pub references: Vec<String>,
+    /// Set by the host from `evidence` and the call at `rsid`
+    #[serde(default)]
+    pub confidence: Confidence,
+}
+
+/// An analysis module the engine can run
+pub trait AnalysisPlugin: Send + Sync + fmt::Debug {
+    fn manifest(&self) -> &PluginManifest;
+
+    /// Findings from the normalized variants of a genome
+    fn analyze(&self, input: &PluginInput<'_>) -> Result<Vec<PluginFinding>, String>;
+}
+
+/// Run a plugin on a genome, keeping the findings the consent policy
+/// allows
+///
+/// Findings come back with the plugin's id, the genotype they rest on and
+/// their confidence filled in.
+pub fn run(
+    plugin: &dyn AnalysisPlugin,
+    genome: &LoadedGenome,
+    policy: &ConsentPolicy,
+) -> Result<Vec<PluginFinding>, String> {
+    let manifest = plugin.manifest();
+    let variants = if manifest.sites.is_empty() {
+        genome
+            .variants()
+            .iter()
+            .filter(|variant| !variant.genotype.is_no_call())
+            .collect()
+    } else {
+        manifest
+            .sites
+            .iter()
+            .filter_map(|rsid| genome.get_by_rsid(rsid))
+            .filter(|variant| !variant.genotype.is_no_call())
+            .collect()
+    };
+    let input = PluginInput {
+        api_version: API_VERSION,
+        build: genome.file.genome_build,
+        variants,
+    };
+    let mut findings = plugin
+        .analyze(&input)
+        .map_err(|e| format!("Plugin {} failed: {}", manifest.id, e))?;
+    if findings.len() > MAX_FINDINGS {
+        return Err(format!(
+            "Plugin {} reported more than {} findings",
+            manifest.id, MAX_FINDINGS
+        ));
+    }
+
+    findings.retain(|finding| {
+        !finding.title.trim().is_empty()
+            && finding
+                .categories
+                .iter()
+                .all(|category| policy.allows(*category))
+            && (policy.allows(FindingCategory::Neurodegenerative)
+                || !consent::neurodegenerative_gene(&finding.genes))
+    });
+    for finding in &mut findings {
+        let call = finding
+            .rsid
+            .as_deref()
+            .and_then(|rsid| genome.get_by_rsid(rsid));
+        finding.plugin = manifest.id.clone();
+        finding.genotype = call.map(|variant| variant.genotype.to_string());
+        finding.confidence = Confidence::new(
+            Evidence::Plugin(finding.evidence),
+            call.map_or(1.0, confidence::variant_reliability),
+        );
+    }
+    Ok(findings)
+}
+
+/// A plugin compiled to WebAssembly
+#[derive(Debug)]
+pub struct WasmPlugin {
+    manifest: PluginManifest,
+    module: Module,
+}
+
+impl WasmPlugin {
+    /// Load the plugin installed in `dir`
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(MANIFEST_FILE);
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let manifest: PluginManifest = serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid plugin manifest {}: {}", path.display(), e))?;
+        manifest.validate()?;
+        let path = dir.join(&manifest.module);
+        let bytes =
+            fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::new(manifest, &bytes)
+    }
+
+    /// A plugin from its manifest and module bytes, checking that the
+    /// module exports what the interface needs
+    pub fn new(manifest: PluginManifest, module: &[u8]) -> Result<Self, String> {
+        let module =
+            Module::decode(module).map_err(|e| format!("Plugin {}: {}", manifest.id, e))?;
+        let exports = [
+            ("alloc", vec![ValType::I32], vec![ValType::I32]),
+            (
+                "analyze",
+                vec![ValType::I32, ValType::I32],
+                vec![ValType::I64],
+            ),
+        ];
+        for (name, params, results) in exports {
+            let valid = module
+                .exported_function(name)
+                .is_some_and(|ty| ty.params == params && ty.results == results);
+            if !valid {
+                return Err(format!(
+                    "Plugin {} does not export the {} function",
+                    manifest.id, name
+                ));
+            }
+        }
+        if !module.exports_memory("memory") {
+            return Err(format!("Plugin {} does not export its memory", manifest.id));
+        }
+        Ok(WasmPlugin { manifest, module })
+    }
+}
+
+impl AnalysisPlugin for WasmPlugin {
+    fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    fn analyze(&self, input: &PluginInput<'_>) -> Result<Vec<PluginFinding>, String> {
+        let input = serde_json::to_vec(input)
+            .map_err(|e| format!("Failed to serialize plugin input: {}", e))?;
+        let len = i32::try_from(input.len()).map_err(|_| "Too many variants for a plugin")?;
+
+        let mut instance = Instance::new(&self.module, FUEL)?;
+        let address = match instance.invoke("alloc", &[Value::I32(len)])?[..] {
+            [Value::I32(address)] => address,
+            _ => return Err("alloc returned no address".to_string()),
+        };
+        instance.write(address as u32, &input)?;
+        let output = match instance.invoke("analyze", &[Value::I32(address), Value::I32(len)])?[..]
+        {
+            [Value::I64(output)] => output as u64,
+            _ => return Err("analyze returned no output".to_string()),
+        };
+        let output = instance.read((output >> 32) as u32, output as u32)?;
+
+        let output: PluginOutput =
+            serde_json::from_slice(output).map_err(|e| format!("Invalid plugin output: {}", e))?;
+        match output.error {
+            Some(error) => Err(error),
+            None => Ok(output.findings),
+        }
+    }
+}
+
+// Helper functions
+
+fn default_module() -> String {
+    "plugin.wasm".to_string()
+}
+
+#[derive(Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    findings: Vec<PluginFinding>,
+    #[serde(default)]
+    error: Option<String>,
+}
@@ -0,0 +1,1613 @@
+//! A sandboxed WebAssembly interpreter for analysis plugins
+//!
+//! Plugins are WebAssembly 1.0 modules, with the sign extension,
+//! saturating conversion, bulk memory and multi-value extensions that
+//! compilers enable by default. A module runs in isolation: it may import
+//! nothing, so it has no way to reach files, the network or the clock,
+//! and sees only its own linear memory. Memory is capped at
+//! [`MAX_MEMORY_PAGES`], calls nest at most [`MAX_CALL_DEPTH`] deep, and
+//! an instance has a fuel budget of instructions it may execute, so a
+//! faulty or hostile module traps instead of hanging or exhausting the
+//! host. Traps come back as errors; nothing a module does can panic the
+//! host.
+//!
+//! Modules are not type-checked before they run: code that would fail
+//! validation traps when it misuses the operand stack instead.
+
+use std::collections::HashMap;
+
+/// Size of a memory page
+pub const PAGE_SIZE: usize = 65536;
+
+/// Most memory an instance may have, 256 MiB
+pub const MAX_MEMORY_PAGES: u32 = 4096;
+
+/// Deepest calls may nest
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+/// Most values the operand stack, or the locals of all active calls, may
+/// hold
+const MAX_STACK_VALUES: usize = 1 << 20;
+
+/// Most locals one function may declare
+const MAX_LOCALS: u64 = 50_000;
+
+/// Most table entries a module may declare
+const MAX_TABLE_SIZE: u32 = 1 << 20;
+
+const MAGIC: &[u8; 4] = b"\0asm";
+const VERSION: [u8; 4] = [1, 0, 0, 0];
+
+/// Type of a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ValType {
+    fn decode(byte: u8) -> Result<Self, String> {
+        match byte {
+            0x7f => Ok(ValType::I32),
+            0x7e => Ok(ValType::I64),
+            0x7d => Ok(ValType::F32),
+            0x7c => Ok(ValType::F64),
+            other => Err(format!("unsupported value type 0x{:02x}", other)),
+        }
+    }
+}
+
+/// A value passed to or returned from a function
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Value {
+    pub fn ty(self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::I32,
+            Value::I64(_) => ValType::I64,
+            Value::F32(_) => ValType::F32,
+            Value::F64(_) => ValType::F64,
+        }
+    }
+
+    /// The value as the interpreter stores it
+    fn bits(self) -> u64 {
+        match self {
+            Value::I32(value) => value as u32 as u64,
+            Value::I64(value) => value as u64,
+            Value::F32(value) => value.to_bits() as u64,
+            Value::F64(value) => value.to_bits(),
+        }
+    }
+
+    fn from_bits(ty: ValType, bits: u64) -> Self {
+        match ty {
+            ValType::I32 => Value::I32(bits as u32 as i32),
+            ValType::I64 => Value::I64(bits as i64),
+            ValType::F32 => Value::F32(f32::from_bits(bits as u32)),
+            ValType::F64 => Value::F64(f64::from_bits(bits)),
+        }
+    }
+}
+
+/// Parameter and result types of a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+/// A decoded module, ready to be instantiated
+#[derive(Debug)]
+pub struct Module {
+    types: Vec<FuncType>,
+    functions: Vec<Function>,
+    /// Function of each table entry
+    table: Vec<Option<u32>>,
+    /// Minimum and maximum pages
+    memory: Option<(u32, Option<u32>)>,
+    globals: Vec<Global>,
+    exports: HashMap<String, Export>,
+    data: Vec<DataSegment>,
+    start: Option<u32>,
+}
+
+impl Module {
+    /// Decode a binary module
+    pub fn decode(bytes: &[u8]) -> Result<Module, String> {
+        if bytes.len() < 8 || &bytes[..4] != MAGIC {
+            return Err("Not a WebAssembly module".to_string());
+        }
+        if bytes[4..8] != VERSION {
+            return Err("Unsupported WebAssembly version".to_string());
+        }
+        decode_sections(&bytes[8..]).map_err(|e| format!("Invalid WebAssembly module: {}", e))
+    }
+
+    /// Type of an exported function
+    pub fn exported_function(&self, name: &str) -> Option<&FuncType> {
+        match self.exports.get(name)? {
+            Export::Function(index) => Some(self.function_type(*index)),
+            _ => None,
+        }
+    }
+
+    /// Whether the module exports its memory under `name`
+    pub fn exports_memory(&self, name: &str) -> bool {
+        matches!(self.exports.get(name), Some(Export::Memory))
+    }
+
+    fn function_type(&self, function: u32) -> &FuncType {
+        &self.types[self.functions[function as usize].ty as usize]
+    }
+}
+
+/// A module's memory, globals and remaining fuel
+pub struct Instance<'m> {
+    module: &'m Module,
+    memory: Vec<u8>,
+    max_pages: u32,
+    globals: Vec<u64>,
+    /// Data segments dropped, as all active ones are once copied in
+    dropped: Vec<bool>,
+    fuel: u64,
+}
+
+impl<'m> Instance<'m> {
+    /// Instantiate a module with a budget of `fuel` instructions, running
+    /// its start function
+    pub fn new(module: &'m Module, fuel: u64) -> Result<Self, String> {
+        let (min_pages, max_pages) = module.memory.unwrap_or((0, Some(0)));
+        let mut instance = Instance {
+            module,
+            memory: vec![0; min_pages as usize * PAGE_SIZE],
+            max_pages: max_pages.unwrap_or(MAX_MEMORY_PAGES).min(MAX_MEMORY_PAGES),
+            globals: module.globals.iter().map(|global| global.init).collect(),
+            dropped: vec![false; module.data.len()],
+            fuel,
+        };
+        for (index, segment) in module.data.iter().enumerate() {
+            if let Some(offset) = segment.offset {
+                let start = offset as usize;
+                instance
+                    .memory
+                    .get_mut(start..start + segment.bytes.len())
+                    .ok_or("data segment does not fit in memory")?
+                    .copy_from_slice(&segment.bytes);
+                instance.dropped[index] = true;
+            }
+        }
+        if let Some(start) = module.start {
+            instance.execute(start, Vec::new())?;
+        }
+        Ok(instance)
+    }
+
+    /// Call an exported function
+    pub fn invoke(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>, String> {
+        let Some(Export::Function(function)) = self.module.exports.get(name) else {
+            return Err(format!("The module exports no function {}", name));
+        };
+        let ty = self.module.function_type(*function);
+        if !args
+            .iter()
+            .map(|arg| arg.ty())
+            .eq(ty.params.iter().copied())
+        {
+            return Err(format!("Wrong arguments for {}", name));
+        }
+        let results = self.execute(*function, args.iter().map(|arg| arg.bits()).collect())?;
+        Ok(ty
+            .results
+            .iter()
+            .zip(results)
+            .map(|(ty, bits)| Value::from_bits(*ty, bits))
+            .collect())
+    }
+
+    /// Instructions left to execute
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
+
+    /// `len` bytes of memory from `address`
+    pub fn read(&self, address: u32, len: u32) -> Result<&[u8], String> {
+        let start = address as usize;
+        self.memory
+            .get(start..start + len as usize)
+            .ok_or_else(|| trap("out of bounds memory access"))
+    }
+
+    /// Copy bytes into memory at `address`
+    pub fn write(&mut self, address: u32, bytes: &[u8]) -> Result<(), String> {
+        let start = address as usize;
+        self.memory
+            .get_mut(start..start + bytes.len())
+            .ok_or_else(|| trap("out of bounds memory access"))?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn execute(&mut self, function: u32, args: Vec<u64>) -> Result<Vec<u64>, String> {
+        let module = self.module;
+        let mut stack = args;
+        let mut locals = Vec::new();
+        let mut labels: Vec<Label> = Vec::new();
+        let mut callers: Vec<Frame> = Vec::new();
+        let mut frame = enter(module, function, &mut stack, &mut locals, &labels)?;
+        let mut code = &module.functions[function as usize].code[..];
+        let mut pc = 0;
+
+        loop {
+            if self.fuel == 0 {
+                return Err(trap("ran out of fuel"));
+            }
+            self.fuel -= 1;
+            let mut returning = false;
+            match code
+                .get(pc)
+                .ok_or_else(|| trap("ran past the end of a function"))?
+            {
+                Op::Unreachable => return Err(trap("unreachable executed")),
+                Op::Nop => pc += 1,
+                Op::Block {
+                    params,
+                    results,
+                    end,
+                } => {
+                    labels.push(Label {
+                        arity: *results,
+                        height: block_height(&stack, *params)?,
+                        target: *end as usize + 1,
+                        is_loop: false,
+                    });
+                    pc += 1;
+                }
+                Op::Loop { params } => {
+                    if stack.len() > MAX_STACK_VALUES {
+                        return Err(trap("operand stack exhausted"));
+                    }
+                    labels.push(Label {
+                        arity: *params,
+                        height: block_height(&stack, *params)?,
+                        target: pc + 1,
+                        is_loop: true,
+                    });
+                    pc += 1;
+                }
+                Op::If {
+                    params,
+                    results,
+                    else_at,
+                    end,
+                } => {
+                    let condition = pop(&mut stack)? as u32;
+                    labels.push(Label {
+                        arity: *results,
+                        height: block_height(&stack, *params)?,
+                        target: *end as usize + 1,
+                        is_loop: false,
+                    });
+                    pc = if condition != 0 {
+                        pc + 1
+                    } else if else_at == end {
+                        *end as usize
+                    } else {
+                        *else_at as usize + 1
+                    };
+                }
+                Op::Else { end } => pc = *end as usize,
+                Op::End => {
+                    if labels.len() > frame.labels {
+                        labels.pop();
+                        pc += 1;
+                    } else {
+                        returning = true;
+                    }
+                }
+                Op::Br(depth) => match branch(&mut labels, &mut stack, frame.labels, *depth)? {
+                    Some(target) => pc = target,
+                    None => returning = true,
+                },
+                Op::BrIf(depth) => {
+                    if pop(&mut stack)? as u32 == 0 {
+                        pc += 1;
+                    } else {
+                        match branch(&mut labels, &mut stack, frame.labels, *depth)? {
+                            Some(target) => pc = target,
+                            None => returning = true,
+                        }
+                    }
+                }
+                Op::BrTable(targets, default) => {
+                    let index = pop(&mut stack)? as u32 as usize;
+                    let depth = targets.get(index).unwrap_or(default);
+                    match branch(&mut labels, &mut stack, frame.labels, *depth)? {
+                        Some(target) => pc = target,
+                        None => returning = true,
+                    }
+                }
+                Op::Return => returning = true,
+                op @ (Op::Call(_) | Op::CallIndirect(_)) => {
+                    let callee = match op {
+                        Op::Call(callee) => *callee,
+                        Op::CallIndirect(ty) => {
+                            let index = pop(&mut stack)? as u32 as usize;
+                            let callee = module
+                                .table
+                                .get(index)
+                                .ok_or_else(|| trap("undefined table element"))?
+                                .ok_or_else(|| trap("uninitialized table element"))?;
+                            if *module.function_type(callee) != module.types[*ty as usize] {
+                                return Err(trap("indirect call type mismatch"));
+                            }
+                            callee
+                        }
+                        _ => unreachable!(),
+                    };
+                    if callers.len() + 1 >= MAX_CALL_DEPTH {
+                        return Err(trap("call stack exhausted"));
+                    }
+                    frame.pc = pc + 1;
+                    let callee_frame = enter(module, callee, &mut stack, &mut locals, &labels)?;
+                    callers.push(std::mem::replace(&mut frame, callee_frame));
+                    code = &module.functions[callee as usize].code;
+                    pc = 0;
+                }
+                Op::Drop => {
+                    pop(&mut stack)?;
+                    pc += 1;
+                }
+                Op::Select => {
+                    let condition = pop(&mut stack)? as u32;
+                    let second = pop(&mut stack)?;
+                    let first = pop(&mut stack)?;
+                    stack.push(if condition != 0 { first } else { second });
+                    pc += 1;
+                }
+                Op::LocalGet(index) => {
+                    stack.push(*local(&mut locals, frame.locals, *index)?);
+                    pc += 1;
+                }
+                Op::LocalSet(index) => {
+                    let value = pop(&mut stack)?;
+                    *local(&mut locals, frame.locals, *index)? = value;
+                    pc += 1;
+                }
+                Op::LocalTee(index) => {
+                    let value = *stack.last().ok_or_else(underflow)?;
+                    *local(&mut locals, frame.locals, *index)? = value;
+                    pc += 1;
+                }
+                Op::GlobalGet(index) => {
+                    stack.push(self.globals[*index as usize]);
+                    pc += 1;
+                }
+                Op::GlobalSet(index) => {
+                    self.globals[*index as usize] = pop(&mut stack)?;
+                    pc += 1;
+                }
+                Op::Load(opcode, offset) => {
+                    let address = effective_address(&mut stack, *offset)?;
+                    let value = self.load(*opcode, address)?;
+                    stack.push(value);
+                    pc += 1;
+                }
+                Op::Store(opcode, offset) => {
+                    let value = pop(&mut stack)?;
+                    let address = effective_address(&mut stack, *offset)?;
+                    self.store(*opcode, address, value)?;
+                    pc += 1;
+                }
+                Op::MemorySize => {
+                    stack.push((self.memory.len() / PAGE_SIZE) as u64);
+                    pc += 1;
+                }
+                Op::MemoryGrow => {
+                    let delta = pop(&mut stack)? as u32;
+                    let pages = (self.memory.len() / PAGE_SIZE) as u32;
+                    match pages.checked_add(delta) {
+                        Some(grown) if grown <= self.max_pages && module.memory.is_some() => {
+                            self.memory.resize(grown as usize * PAGE_SIZE, 0);
+                            stack.push(pages as u64);
+                        }
+                        _ => stack.push(u32::MAX as u64),
+                    }
+                    pc += 1;
+                }
+                Op::Const(bits) => {
+                    stack.push(*bits);
+                    pc += 1;
+                }
+                Op::Numeric(opcode) => {
+                    numeric(*opcode, &mut stack)?;
+                    pc += 1;
+                }
+                Op::Saturating(opcode) => {
+                    saturating(*opcode, &mut stack)?;
+                    pc += 1;
+                }
+                Op::MemoryInit(segment) => {
+                    let len = pop(&mut stack)? as u32 as usize;
+                    let source = pop(&mut stack)? as u32 as usize;
+                    let destination = pop(&mut stack)? as u32 as usize;
+                    let segment = *segment as usize;
+                    let bytes = match module.data.get(segment) {
+                        Some(_) if self.dropped[segment] => &[][..],
+                        Some(data) => &data.bytes[..],
+                        None => return Err(trap("unknown data segment")),
+                    };
+                    let bytes = bytes
+                        .get(source..source + len)
+                        .ok_or_else(|| trap("out of bounds memory access"))?;
+                    self.memory
+                        .get_mut(destination..destination + len)
+                        .ok_or_else(|| trap("out of bounds memory access"))?
+                        .copy_from_slice(bytes);
+                    pc += 1;
+                }
+                Op::DataDrop(segment) => {
+                    *self
+                        .dropped
+                        .get_mut(*segment as usize)
+                        .ok_or_else(|| trap("unknown data segment"))? = true;
+                    pc += 1;
+                }
+                Op::MemoryCopy => {
+                    let len = pop(&mut stack)? as u32 as usize;
+                    let source = pop(&mut stack)? as u32 as usize;
+                    let destination = pop(&mut stack)? as u32 as usize;
+                    if source + len > self.memory.len() || destination + len > self.memory.len() {
+                        return Err(trap("out of bounds memory access"));
+                    }
+                    self.memory.copy_within(source..source + len, destination);
+                    pc += 1;
+                }
+                Op::MemoryFill => {
+                    let len = pop(&mut stack)? as u32 as usize;
+                    let value = pop(&mut stack)? as u8;
+                    let destination = pop(&mut stack)? as u32 as usize;
+                    self.memory
+                        .get_mut(destination..destination + len)
+                        .ok_or_else(|| trap("out of bounds memory access"))?
+                        .fill(value);
+                    pc += 1;
+                }
+            }
+
+            if returning {
+                let results = module.function_type(frame.function).results.len();
+                keep_top(&mut stack, frame.stack, results)?;
+                locals.truncate(frame.locals);
+                labels.truncate(frame.labels);
+                match callers.pop() {
+                    Some(caller) => {
+                        frame = caller;
+                        code = &module.functions[frame.function as usize].code;
+                        pc = frame.pc;
+                    }
+                    None => return Ok(stack),
+                }
+            }
+        }
+    }
+
+    fn load(&self, opcode: u8, address: usize) -> Result<u64, String> {
+        let size = match opcode {
+            0x29 | 0x2b => 8,
+            0x28 | 0x2a | 0x34 | 0x35 => 4,
+            0x2e | 0x2f | 0x32 | 0x33 => 2,
+            _ => 1,
+        };
+        let bytes = self
+            .memory
+            .get(address..address + size)
+            .ok_or_else(|| trap("out of bounds memory access"))?;
+        let mut buffer = [0; 8];
+        buffer[..size].copy_from_slice(bytes);
+        let raw = u64::from_le_bytes(buffer);
+        Ok(match opcode {
+            0x2c => raw as u8 as i8 as i32 as u32 as u64,
+            0x2e => raw as u16 as i16 as i32 as u32 as u64,
+            0x30 => raw as u8 as i8 as i64 as u64,
+            0x32 => raw as u16 as i16 as i64 as u64,
+            0x34 => raw as u32 as i32 as i64 as u64,
+            _ => raw,
+        })
+    }
+
+    fn store(&mut self, opcode: u8, address: usize, value: u64) -> Result<(), String> {
+        let size = match opcode {
+            0x37 | 0x39 => 8,
+            0x36 | 0x38 | 0x3e => 4,
+            0x3b | 0x3d => 2,
+            _ => 1,
+        };
+        self.memory
+            .get_mut(address..address + size)
+            .ok_or_else(|| trap("out of bounds memory access"))?
+            .copy_from_slice(&value.to_le_bytes()[..size]);
+        Ok(())
+    }
+}
+
+// Helper functions
+
+fn trap(reason: &str) -> String {
+    format!("WebAssembly trap: {}", reason)
+}
+
+fn underflow() -> String {
+    trap("operand stack underflow")
+}
+
+#[derive(Debug)]
+struct Function {
+    ty: u32,
+    /// Locals declared after the parameters
+    locals: u32,
+    code: Vec<Op>,
+}
+
+#[derive(Debug)]
+struct Global {
+    mutable: bool,
+    init: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Export {
+    Function(u32),
+    Table,
+    Memory,
+    Global,
+}
+
+#[derive(Debug)]
+struct DataSegment {
+    /// Where an active segment is copied to; `None` for a passive one
+    offset: Option<u32>,
+    bytes: Vec<u8>,
+}
+
+/// An instruction, with the positions of its block's `else` and `end`
+/// resolved
+#[derive(Debug)]
+enum Op {
+    Unreachable,
+    Nop,
+    Block {
+        params: u32,
+        results: u32,
+        end: u32,
+    },
+    Loop {
+        params: u32,
+    },
+    If {
+        params: u32,
+        results: u32,
+        /// The `else`, or `end` when there is none
+        else_at: u32,
+        end: u32,
+    },
+    Else {
+        end: u32,
+    },
+    End,
+    Br(u32),
+    BrIf(u32),
+    BrTable(Box<[u32]>, u32),
+    Return,
+    Call(u32),
+    CallIndirect(u32),
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    /// Opcode and offset
+    Load(u8, u32),
+    Store(u8, u32),
+    MemorySize,
+    MemoryGrow,
+    Const(u64),
+    /// Comparisons, arithmetic and conversions, by opcode
+    Numeric(u8),
+    /// Saturating conversions, by their 0xfc sub-opcode
+    Saturating(u8),
+    MemoryInit(u32),
+    DataDrop(u32),
+    MemoryCopy,
+    MemoryFill,
+}
+
+/// Where a branch to a block goes
+#[derive(Debug, Clone, Copy)]
+struct Label {
+    /// Values the branch carries
+    arity: u32,
+    /// Operand stack height at the start of the block
+    height: usize,
+    /// Instruction to continue at
+    target: usize,
+    is_loop: bool,
+}
+
+/// A function call in progress
+#[derive(Debug)]
+struct Frame {
+    function: u32,
+    /// Instruction to continue at once the call it made returns
+    pc: usize,
+    /// Where its locals and labels start
+    locals: usize,
+    labels: usize,
+    /// Operand stack height below its values
+    stack: usize,
+}
+
+/// Move a call's arguments from the stack into its locals
+fn enter(
+    module: &Module,
+    function: u32,
+    stack: &mut Vec<u64>,
+    locals: &mut Vec<u64>,
+    labels: &[Label],
+) -> Result<Frame, String> {
+    let params = module.function_type(function).params.len();
+    let height = stack.len().checked_sub(params).ok_or_else(underflow)?;
+    let base = locals.len();
+    locals.extend(stack.drain(height..));
+    locals.resize(
+        base + params + module.functions[function as usize].locals as usize,
+        0,
+    );
+    if locals.len() > MAX_STACK_VALUES || stack.len() > MAX_STACK_VALUES {
+        return Err(trap("call stack exhausted"));
+    }
+    Ok(Frame {
+        function,
+        pc: 0,
+        locals: base,
+        labels: labels.len(),
+        stack: height,
+    })
+}
+
+/// Take a branch `depth` labels out; `None` when it leaves the function
+fn branch(
+    labels: &mut Vec<Label>,
+    stack: &mut Vec<u64>,
+    base: usize,
+    depth: u32,
+) -> Result<Option<usize>, String> {
+    let depth = depth as usize;
+    if depth >= labels.len() - base {
+        return Ok(None);
+    }
+    let index = labels.len() - 1 - depth;
+    let label = labels[index];
+    keep_top(stack, label.height, label.arity as usize)?;
+    labels.truncate(if label.is_loop { index + 1 } else { index });
+    Ok(Some(label.target))
+}
+
+/// Drop the values between `height` and the top `count`
+fn keep_top(stack: &mut Vec<u64>, height: usize, count: usize) -> Result<(), String> {
+    let top = stack
+        .len()
+        .checked_sub(count)
+        .filter(|top| *top >= height)
+        .ok_or_else(underflow)?;
+    stack.drain(height..top);
+    Ok(())
+}
+
+fn block_height(stack: &[u64], params: u32) -> Result<usize, String> {
+    stack
+        .len()
+        .checked_sub(params as usize)
+        .ok_or_else(underflow)
+}
+
+fn local(locals: &mut [u64], base: usize, index: u32) -> Result<&mut u64, String> {
+    locals
+        .get_mut(base + index as usize)
+        .ok_or_else(|| trap("unknown local"))
+}
+
+fn pop(stack: &mut Vec<u64>) -> Result<u64, String> {
+    stack.pop().ok_or_else(underflow)
+}
+
+fn effective_address(stack: &mut Vec<u64>, offset: u32) -> Result<usize, String> {
+    Ok(pop(stack)? as u32 as usize + offset as usize)
+}
+
+fn unary(stack: &mut Vec<u64>, f: impl FnOnce(u64) -> Result<u64, String>) -> Result<(), String> {
+    let value = pop(stack)?;
+    stack.push(f(value)?);
+    Ok(())
+}
+
+fn binary(
+    stack: &mut Vec<u64>,
+    f: impl FnOnce(u64, u64) -> Result<u64, String>,
+) -> Result<(), String> {
+    let second = pop(stack)?;
+    let first = pop(stack)?;
+    stack.push(f(first, second)?);
+    Ok(())
+}
+
+fn compare(stack: &mut Vec<u64>, f: impl FnOnce(u64, u64) -> bool) -> Result<(), String> {
+    binary(stack, |a, b| Ok(f(a, b) as u64))
+}
+
+fn f32_bits(value: f32) -> u64 {
+    value.to_bits() as u64
+}
+
+fn as_f32(bits: u64) -> f32 {
+    f32::from_bits(bits as u32)
+}
+
+/// Minimum as WebAssembly defines it: NaN if either is, -0 below +0
+fn wasm_min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_negative() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.min(b)
+    }
+}
+
+fn wasm_max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_positive() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.max(b)
+    }
+}
+
+/// A float truncated towards zero, trapping unless it is in `min..max`
+fn truncate(value: f64, min: f64, max: f64) -> Result<f64, String> {
+    if value.is_nan() {
+        return Err(trap("invalid conversion to integer"));
+    }
+    let truncated = value.trunc();
+    if truncated < min || truncated >= max {
+        return Err(trap("integer overflow"));
+    }
+    Ok(truncated)
+}
+
+const I32_RANGE: (f64, f64) = (-2147483648.0, 2147483648.0);
+const U32_RANGE: (f64, f64) = (0.0, 4294967296.0);
+const I64_RANGE: (f64, f64) = (-9223372036854775808.0, 9223372036854775808.0);
+const U64_RANGE: (f64, f64) = (0.0, 18446744073709551616.0);
+
+fn numeric(opcode: u8, stack: &mut Vec<u64>) -> Result<(), String> {
+    let divide_by_zero = || trap("integer divide by zero");
+    let overflow = || trap("integer overflow");
+    match opcode {
+        0x45 => unary(stack, |a| Ok((a as u32 == 0) as u64)),
+        0x46..=0x4f => compare(stack, |a, b| {
+            let (a, b) = (a as u32, b as u32);
+            let (sa, sb) = (a as i32, b as i32);
+            match opcode {
+                0x46 => a == b,
+                0x47 => a != b,
+                0x48 => sa < sb,
+                0x49 => a < b,
+                0x4a => sa > sb,
+                0x4b => a > b,
+                0x4c => sa <= sb,
+                0x4d => a <= b,
+                0x4e => sa >= sb,
+                _ => a >= b,
+            }
+        }),
+        0x50 => unary(stack, |a| Ok((a == 0) as u64)),
+        0x51..=0x5a => compare(stack, |a, b| {
+            let (sa, sb) = (a as i64, b as i64);
+            match opcode {
+                0x51 => a == b,
+                0x52 => a != b,
+                0x53 => sa < sb,
+                0x54 => a < b,
+                0x55 => sa > sb,
+                0x56 => a > b,
+                0x57 => sa <= sb,
+                0x58 => a <= b,
+                0x59 => sa >= sb,
+                _ => a >= b,
+            }
+        }),
+        0x5b..=0x60 => compare(stack, |a, b| {
+            let (a, b) = (as_f32(a), as_f32(b));
+            match opcode {
+                0x5b => a == b,
+                0x5c => a != b,
+                0x5d => a < b,
+                0x5e => a > b,
+                0x5f => a <= b,
+                _ => a >= b,
+            }
+        }),
+        0x61..=0x66 => compare(stack, |a, b| {
+            let (a, b) = (f64::from_bits(a), f64::from_bits(b));
+            match opcode {
+                0x61 => a == b,
+                0x62 => a != b,
+                0x63 => a < b,
+                0x64 => a > b,
+                0x65 => a <= b,
+                _ => a >= b,
+            }
+        }),
+        0x67..=0x69 => unary(stack, |a| {
+            let a = a as u32;
+            Ok(match opcode {
+                0x67 => a.leading_zeros(),
+                0x68 => a.trailing_zeros(),
+                _ => a.count_ones(),
+            } as u64)
+        }),
+        0x6a..=0x78 => binary(stack, |a, b| {
+            let (a, b) = (a as u32, b as u32);
+            let (sa, sb) = (a as i32, b as i32);
+            let value = match opcode {
+                0x6a => a.wrapping_add(b),
+                0x6b => a.wrapping_sub(b),
+                0x6c => a.wrapping_mul(b),
+                0x6d => {
+                    if b == 0 {
+                        return Err(divide_by_zero());
+                    }
+                    sa.checked_div(sb).ok_or_else(overflow)? as u32
+                }
+                0x6e => a.checked_div(b).ok_or_else(divide_by_zero)?,
+                0x6f => {
+                    if b == 0 {
+                        return Err(divide_by_zero());
+                    }
+                    sa.wrapping_rem(sb) as u32
+                }
+                0x70 => a.checked_rem(b).ok_or_else(divide_by_zero)?,
+                0x71 => a & b,
+                0x72 => a | b,
+                0x73 => a ^ b,
+                0x74 => a.wrapping_shl(b),
+                0x75 => sa.wrapping_shr(b) as u32,
+                0x76 => a.wrapping_shr(b),
+                0x77 => a.rotate_left(b % 32),
+                _ => a.rotate_right(b % 32),
+            };
+            Ok(value as u64)
+        }),
+        0x79..=0x7b => unary(stack, |a| {
+            Ok(match opcode {
+                0x79 => a.leading_zeros(),
+                0x7a => a.trailing_zeros(),
+                _ => a.count_ones(),
+            } as u64)
+        }),
+        0x7c..=0x8a => binary(stack, |a, b| {
+            let (sa, sb) = (a as i64, b as i64);
+            let shift = (b % 64) as u32;
+            Ok(match opcode {
+                0x7c => a.wrapping_add(b),
+                0x7d => a.wrapping_sub(b),
+                0x7e => a.wrapping_mul(b),
+                0x7f => {
+                    if b == 0 {
+                        return Err(divide_by_zero());
+                    }
+                    sa.checked_div(sb).ok_or_else(overflow)? as u64
+                }
+                0x80 => a.checked_div(b).ok_or_else(divide_by_zero)?,
+                0x81 => {
+                    if b == 0 {
+                        return Err(divide_by_zero());
+                    }
+                    sa.wrapping_rem(sb) as u64
+                }
+                0x82 => a.checked_rem(b).ok_or_else(divide_by_zero)?,
+                0x83 => a & b,
+                0x84 => a | b,
+                0x85 => a ^ b,
+                0x86 => a << shift,
+                0x87 => (sa >> shift) as u64,
+                0x88 => a >> shift,
+                0x89 => a.rotate_left(shift),
+                _ => a.rotate_right(shift),
+            })
+        }),
+        0x8b => unary(stack, |a| Ok(a & 0x7fff_ffff)),
+        0x8c => unary(stack, |a| Ok((a as u32 ^ 0x8000_0000) as u64)),
+        0x8d..=0x91 => unary(stack, |a| {
+            let a = as_f32(a);
+            Ok(f32_bits(match opcode {
+                0x8d => a.ceil(),
+                0x8e => a.floor(),
+                0x8f => a.trunc(),
+                0x90 => a.round_ties_even(),
+                _ => a.sqrt(),
+            }))
+        }),
+        0x92..=0x98 => binary(stack, |a, b| {
+            let (a, b) = (as_f32(a), as_f32(b));
+            Ok(f32_bits(match opcode {
+                0x92 => a + b,
+                0x93 => a - b,
+                0x94 => a * b,
+                0x95 => a / b,
+                0x96 => wasm_min(a as f64, b as f64) as f32,
+                0x97 => wasm_max(a as f64, b as f64) as f32,
+                _ => a.copysign(b),
+            }))
+        }),
+        0x99 => unary(stack, |a| Ok(a & 0x7fff_ffff_ffff_ffff)),
+        0x9a => unary(stack, |a| Ok(a ^ 0x8000_0000_0000_0000)),
+        0x9b..=0x9f => unary(stack, |a| {
+            let a = f64::from_bits(a);
+            Ok(match opcode {
+                0x9b => a.ceil(),
+                0x9c => a.floor(),
+                0x9d => a.trunc(),
+                0x9e => a.round_ties_even(),
+                _ => a.sqrt(),
+            }
+            .to_bits())
+        }),
+        0xa0..=0xa6 => binary(stack, |a, b| {
+            let (a, b) = (f64::from_bits(a), f64::from_bits(b));
+            Ok(match opcode {
+                0xa0 => a + b,
+                0xa1 => a - b,
+                0xa2 => a * b,
+                0xa3 => a / b,
+                0xa4 => wasm_min(a, b),
+                0xa5 => wasm_max(a, b),
+                _ => a.copysign(b),
+            }
+            .to_bits())
+        }),
+        0xa7 => unary(stack, |a| Ok(a as u32 as u64)),
+        0xa8..=0xab | 0xae..=0xb1 => unary(stack, |a| {
+            let value = match opcode {
+                0xa8 | 0xa9 | 0xae | 0xaf => as_f32(a) as f64,
+                _ => f64::from_bits(a),
+            };
+            Ok(match opcode {
+                0xa8 | 0xaa => truncate(value, I32_RANGE.0, I32_RANGE.1)? as i32 as u32 as u64,
+                0xa9 | 0xab => truncate(value, U32_RANGE.0, U32_RANGE.1)? as u32 as u64,
+                0xae | 0xb0 => truncate(value, I64_RANGE.0, I64_RANGE.1)? as i64 as u64,
+                _ => truncate(value, U64_RANGE.0, U64_RANGE.1)? as u64,
+            })
+        }),
+        0xac => unary(stack, |a| Ok(a as u32 as i32 as i64 as u64)),
+        0xad => unary(stack, |a| Ok(a as u32 as u64)),
+        0xb2 => unary(stack, |a| Ok(f32_bits(a as u32 as i32 as f32))),
+        0xb3 => unary(stack, |a| Ok(f32_bits(a as u32 as f32))),
+        0xb4 => unary(stack, |a| Ok(f32_bits(a as i64 as f32))),
+        0xb5 => unary(stack, |a| Ok(f32_bits(a as f32))),
+        0xb6 => unary(stack, |a| Ok(f32_bits(f64::from_bits(a) as f32))),
+        0xb7 => unary(stack, |a| Ok((a as u32 as i32 as f64).to_bits())),
+        0xb8 => unary(stack, |a| Ok((a as u32 as f64).to_bits())),
+        0xb9 => unary(stack, |a| Ok((a as i64 as f64).to_bits())),
+        0xba => unary(stack, |a| Ok((a as f64).to_bits())),
+        0xbb => unary(stack, |a| Ok((as_f32(a) as f64).to_bits())),
+        // Reinterpretations leave the bits as they are
+        0xbc..=0xbf => {
+            stack.last().ok_or_else(underflow)?;
+            Ok(())
+        }
+        0xc0 => unary(stack, |a| Ok(a as u8 as i8 as i32 as u32 as u64)),
+        0xc1 => unary(stack, |a| Ok(a as u16 as i16 as i32 as u32 as u64)),
+        0xc2 => unary(stack, |a| Ok(a as u8 as i8 as i64 as u64)),
+        0xc3 => unary(stack, |a| Ok(a as u16 as i16 as i64 as u64)),
+        0xc4 => unary(stack, |a| Ok(a as u32 as i32 as i64 as u64)),
+        other => Err(trap(&format!("unknown instruction 0x{:02x}", other))),
+    }
+}
+
+/// Float to integer conversions that saturate instead of trapping, as
+/// Rust's `as` casts do
+fn saturating(opcode: u8, stack: &mut Vec<u64>) -> Result<(), String> {
+    unary(stack, |a| {
+        let value = if opcode % 4 < 2 {
+            as_f32(a) as f64
+        } else {
+            f64::from_bits(a)
+        };
+        Ok(match opcode {
+            0 | 2 => value as i32 as u32 as u64,
+            1 | 3 => value as u32 as u64,
+            4 | 6 => value as i64 as u64,
+            _ => value as u64,
+        })
+    })
+}
+
+/// Reads the binary encoding
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or("unexpected end of module")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + len)
+            .ok_or("unexpected end of module")?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    /// An unsigned LEB128 integer
+    fn u32(&mut self) -> Result<u32, String> {
+        let mut value = 0u64;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| "integer too large".to_string());
+            }
+        }
+        Err("integer too long".to_string())
+    }
+
+    /// A signed LEB128 integer of `bits` bits
+    fn signed(&mut self, bits: u32) -> Result<i64, String> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                break;
+            }
+            if shift >= bits {
+                return Err("integer too long".to_string());
+            }
+        }
+        if bits < 64 && !(-(1i64 << (bits - 1))..1i64 << (bits - 1)).contains(&value) {
+            return Err("integer too large".to_string());
+        }
+        Ok(value)
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.bytes(len)?.to_vec()).map_err(|_| "name is not UTF-8".to_string())
+    }
+
+    /// The count of a vector, bounded by the bytes left so a corrupt count
+    /// cannot make the decoder allocate without limit
+    fn count(&mut self) -> Result<usize, String> {
+        let count = self.u32()? as usize;
+        if count > self.bytes.len() - self.position.min(self.bytes.len()) {
+            return Err("vector longer than the module".to_string());
+        }
+        Ok(count)
+    }
+
+    fn limits(&mut self) -> Result<(u32, Option<u32>), String> {
+        match self.byte()? {
+            0 => Ok((self.u32()?, None)),
+            1 => Ok((self.u32()?, Some(self.u32()?))),
+            _ => Err("shared and 64-bit memories are not supported".to_string()),
+        }
+    }
+}
+
+fn decode_sections(bytes: &[u8]) -> Result<Module, String> {
+    let mut reader = Reader::new(bytes);
+    let mut module = Module {
+        types: Vec::new(),
+        functions: Vec::new(),
+        table: Vec::new(),
+        memory: None,
+        globals: Vec::new(),
+        exports: HashMap::new(),
+        data: Vec::new(),
+        start: None,
+    };
+    let mut function_types = Vec::new();
+    let mut bodies = Vec::new();
+
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let len = reader.u32()? as usize;
+        let mut section = Reader::new(reader.bytes(len)?);
+        match id {
+            0 | 12 => continue,
+            1 => {
+                for _ in 0..section.count()? {
+                    if section.byte()? != 0x60 {
+                        return Err("malformed function type".to_string());
+                    }
+                    let mut types = || -> Result<Vec<ValType>, String> {
+                        (0..section.count()?)
+                            .map(|_| ValType::decode(section.byte()?))
+                            .collect()
+                    };
+                    let params = types()?;
+                    let results = types()?;
+                    module.types.push(FuncType { params, results });
+                }
+            }
+            2 => {
+                if section.count()? > 0 {
+                    let from = section.name()?;
+                    let name = section.name()?;
+                    return Err(format!(
+                        "the module imports {}.{}, and plugins may import nothing",
+                        from, name
+                    ));
+                }
+            }
+            3 => {
+                for _ in 0..section.count()? {
+                    let ty = section.u32()?;
+                    if ty as usize >= module.types.len() {
+                        return Err("unknown function type".to_string());
+                    }
+                    function_types.push(ty);
+                }
+            }
+            4 => {
+                for _ in 0..section.count()? {
+                    if section.byte()? != 0x70 {
+                        return Err("only function tables are supported".to_string());
+                    }
+                    let (min, _) = section.limits()?;
+                    if !module.table.is_empty() || min > MAX_TABLE_SIZE {
+                        return Err("unsupported table".to_string());
+                    }
+                    module.table = vec![None; min as usize];
+                }
+            }
+            5 => {
+                for _ in 0..section.count()? {
+                    let (min, max) = section.limits()?;
+                    if module.memory.is_some() {
+                        return Err("more than one memory".to_string());
+                    }
+                    if min > MAX_MEMORY_PAGES {
+                        return Err(format!(
+                            "needs {} MiB of memory, more than plugins may use",
+                            min as usize * PAGE_SIZE / (1024 * 1024)
+                        ));
+                    }
+                    module.memory = Some((min, max));
+                }
+            }
+            6 => {
+                for _ in 0..section.count()? {
+                    ValType::decode(section.byte()?)?;
+                    let mutable = section.byte()? == 1;
+                    let init = const_expr(&mut section, &module.globals)?;
+                    module.globals.push(Global { mutable, init });
+                }
+            }
+            7 => {
+                for _ in 0..section.count()? {
+                    let name = section.name()?;
+                    let kind = section.byte()?;
+                    let index = section.u32()?;
+                    let export = match kind {
+                        0 if (index as usize) < function_types.len() => Export::Function(index),
+                        1 => Export::Table,
+                        2 => Export::Memory,
+                        3 => Export::Global,
+                        _ => return Err(format!("invalid export {}", name)),
+                    };
+                    module.exports.insert(name, export);
+                }
+            }
+            8 => module.start = Some(section.u32()?),
+            9 => {
+                for _ in 0..section.count()? {
+                    element_segment(&mut section, &mut module)?;
+                }
+            }
+            10 => {
+                for _ in 0..section.count()? {
+                    let len = section.u32()? as usize;
+                    bodies.push(section.bytes(len)?);
+                }
+            }
+            11 => {
+                for _ in 0..section.count()? {
+                    let offset = match section.u32()? {
+                        0 => Some(const_expr(&mut section, &module.globals)? as u32),
+                        1 => None,
+                        2 => {
+                            section.u32()?;
+                            Some(const_expr(&mut section, &module.globals)? as u32)
+                        }
+                        _ => return Err("malformed data segment".to_string()),
+                    };
+                    let len = section.u32()? as usize;
+                    let bytes = section.bytes(len)?.to_vec();
+                    module.data.push(DataSegment { offset, bytes });
+                }
+            }
+            other => return Err(format!("unknown section {}", other)),
+        }
+        if !section.is_empty() {
+            return Err(format!("section {} is longer than its contents", id));
+        }
+    }
+
+    if bodies.len() != function_types.len() {
+        return Err("function and code sections differ in length".to_string());
+    }
+    let function_count = function_types.len();
+    for (ty, body) in function_types.into_iter().zip(bodies) {
+        let params = module.types[ty as usize].params.len() as u64;
+        let (locals, code) = decode_body(body, params, function_count, &module)?;
+        module.functions.push(Function { ty, locals, code });
+    }
+    if let Some(start) = module.start {
+        if start as usize >= module.functions.len()
+            || *module.function_type(start)
+                != (FuncType {
+                    params: vec![],
+                    results: vec![],
+                })
+        {
+            return Err("invalid start function".to_string());
+        }
+    }
+    if module
+        .table
+        .iter()
+        .flatten()
+        .any(|f| *f as usize >= function_count)
+    {
+        return Err("table element names an unknown function".to_string());
+    }
+    Ok(module)
+}
+
+/// A constant expression, as the bits of its value
+fn const_expr(reader: &mut Reader<'_>, globals: &[Global]) -> Result<u64, String> {
+    let value = match reader.byte()? {
+        0x41 => reader.signed(32)? as i32 as u32 as u64,
+        0x42 => reader.signed(64)? as u64,
+        0x43 => u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap_or_default()) as u64,
+        0x44 => u64::from_le_bytes(reader.bytes(8)?.try_into().unwrap_or_default()),
+        0x23 => {
+            globals
+                .get(reader.u32()? as usize)
+                .ok_or("unknown global")?
+                .init
+        }
+        other => return Err(format!("unsupported constant instruction 0x{:02x}", other)),
+    };
+    if reader.byte()? != 0x0b {
+        return Err("constant expression does not end".to_string());
+    }
+    Ok(value)
+}
+
+fn element_segment(reader: &mut Reader<'_>, module: &mut Module) -> Result<(), String> {
+    let flags = reader.u32()?;
+    if flags > 7 {
+        return Err("malformed element segment".to_string());
+    }
+    let offset = if flags & 1 == 0 {
+        if flags & 2 != 0 && reader.u32()? != 0 {
+            return Err("unknown table".to_string());
+        }
+        Some(const_expr(reader, &module.globals)? as u32 as usize)
+    } else {
+        None
+    };
+    if flags & 3 != 0 {
+        // Element kind or reference type
+        reader.byte()?;
+    }
+    let mut functions = Vec::new();
+    for _ in 0..reader.count()? {
+        functions.push(if flags & 4 == 0 {
+            Some(reader.u32()?)
+        } else {
+            let function = match reader.byte()? {
+                0xd2 => Some(reader.u32()?),
+                0xd0 => {
+                    reader.byte()?;
+                    None
+                }
+                _ => return Err("unsupported element expression".to_string()),
+            };
+            if reader.byte()? != 0x0b {
+                return Err("element expression does not end".to_string());
+            }
+            function
+        });
+    }
+    if let Some(offset) = offset {
+        module
+            .table
+            .get_mut(offset..offset + functions.len())
+            .ok_or("element segment does not fit in the table")?
+            .copy_from_slice(&functions);
+    }
+    Ok(())
+}
+
+/// A block's parameter and result counts
+fn block_type(reader: &mut Reader<'_>, types: &[FuncType]) -> Result<(u32, u32), String> {
+    let start = reader.position;
+    match reader.byte()? {
+        0x40 => Ok((0, 0)),
+        0x7c..=0x7f => Ok((0, 1)),
+        _ => {
+            reader.position = start;
+            let ty = types
+                .get(reader.signed(33)? as usize)
+                .ok_or("unknown block type")?;
+            Ok((ty.params.len() as u32, ty.results.len() as u32))
+        }
+    }
+}
+
+/// An open block while decoding, to resolve its `else` and `end`
+struct OpenBlock {
+    start: usize,
+    else_at: Option<usize>,
+}
+
+fn decode_body(
+    body: &[u8],
+    params: u64,
+    function_count: usize,
+    module: &Module,
+) -> Result<(u32, Vec<Op>), String> {
+    let mut reader = Reader::new(body);
+    let mut declared = 0u64;
+    for _ in 0..reader.count()? {
+        declared += reader.u32()? as u64;
+        ValType::decode(reader.byte()?)?;
+        if declared > MAX_LOCALS {
+            return Err("too many locals".to_string());
+        }
+    }
+    let local_count = params + declared;
+    let local = |index: u32| -> Result<u32, String> {
+        if (index as u64) < local_count {
+            Ok(index)
+        } else {
+            Err("unknown local".to_string())
+        }
+    };
+    let global = |index: u32| -> Result<&Global, String> {
+        module
+            .globals
+            .get(index as usize)
+            .ok_or_else(|| "unknown global".to_string())
+    };
+
+    let mut code = Vec::new();
+    let mut open: Vec<OpenBlock> = Vec::new();
+    loop {
+        let opcode = reader.byte()?;
+        let depth = |depth: u32, open: &[OpenBlock]| -> Result<u32, String> {
+            if depth as usize <= open.len() {
+                Ok(depth)
+            } else {
+                Err("branch to an unknown label".to_string())
+            }
+        };
+        let op = match opcode {
+            0x00 => Op::Unreachable,
+            0x01 => Op::Nop,
+            0x02..=0x04 => {
+                let (params, results) = block_type(&mut reader, &module.types)?;
+                open.push(OpenBlock {
+                    start: code.len(),
+                    else_at: None,
+                });
+                match opcode {
+                    0x02 => Op::Block {
+                        params,
+                        results,
+                        end: 0,
+                    },
+                    0x03 => Op::Loop { params },
+                    _ => Op::If {
+                        params,
+                        results,
+                        else_at: 0,
+                        end: 0,
+                    },
+                }
+            }
+            0x05 => {
+                let block = open.last_mut().ok_or("else outside a block")?;
+                if !matches!(code[block.start], Op::If { .. }) || block.else_at.is_some() {
+                    return Err("else outside an if".to_string());
+                }
+                block.else_at = Some(code.len());
+                Op::Else { end: 0 }
+            }
+            0x0b => {
+                let end = code.len() as u32;
+                match open.pop() {
+                    None => {
+                        code.push(Op::End);
+                        break;
+                    }
+                    Some(block) => {
+                        let else_at = block.else_at.map_or(end, |at| at as u32);
+                        match &mut code[block.start] {
+                            Op::Block { end: block_end, .. } => *block_end = end,
+                            Op::If {
+                                else_at: if_else,
+                                end: if_end,
+                                ..
+                            } => {
+                                *if_else = else_at;
+                                *if_end = end;
+                            }
+                            _ => {}
+                        }
+                        if let Some(at) = block.else_at {
+                            code[at] = Op::Else { end };
+                        }
+                        Op::End
+                    }
+                }
+            }
+            0x0c => Op::Br(depth(reader.u32()?, &open)?),
+            0x0d => Op::BrIf(depth(reader.u32()?, &open)?),
+            0x0e => {
+                let targets = (0..reader.count()?)
+                    .map(|_| depth(reader.u32()?, &open))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Op::BrTable(targets.into(), depth(reader.u32()?, &open)?)
+            }
+            0x0f => Op::Return,
+            0x10 => {
+                let function = reader.u32()?;
+                if function as usize >= function_count {
+                    return Err("call to an unknown function".to_string());
+                }
+                Op::Call(function)
+            }
+            0x11 => {
+                let ty = reader.u32()?;
+                if ty as usize >= module.types.len() || reader.u32()? != 0 {
+                    return Err("malformed indirect call".to_string());
+                }
+                Op::CallIndirect(ty)
+            }
+            0x1a => Op::Drop,
+            0x1b => Op::Select,
+            0x1c => {
+                for _ in 0..reader.count()? {
+                    ValType::decode(reader.byte()?)?;
+                }
+                Op::Select
+            }
+            0x20 => Op::LocalGet(local(reader.u32()?)?),
+            0x21 => Op::LocalSet(local(reader.u32()?)?),
+            0x22 => Op::LocalTee(local(reader.u32()?)?),
+            0x23 => {
+                let index = reader.u32()?;
+                global(index)?;
+                Op::GlobalGet(index)
+            }
+            0x24 => {
+                let index = reader.u32()?;
+                if !global(index)?.mutable {
+                    return Err("write to an immutable global".to_string());
+                }
+                Op::GlobalSet(index)
+            }
+            0x28..=0x3e => {
+                // Alignment is only a hint
+                reader.u32()?;
+                let offset = reader.u32()?;
+                if opcode <= 0x35 {
+                    Op::Load(opcode, offset)
+                } else {
+                    Op::Store(opcode, offset)
+                }
+            }
+            0x3f | 0x40 => {
+                if reader.u32()? != 0 {
+                    return Err("unknown memory".to_string());
+                }
+                if opcode == 0x3f {
+                    Op::MemorySize
+                } else {
+                    Op::MemoryGrow
+                }
+            }
+            0x41 => Op::Const(reader.signed(32)? as i32 as u32 as u64),
+            0x42 => Op::Const(reader.signed(64)? as u64),
+            0x43 => Op::Const(
+                u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap_or_default()) as u64,
+            ),
+            0x44 => Op::Const(u64::from_le_bytes(
+                reader.bytes(8)?.try_into().unwrap_or_default(),
+            )),
+            0x45..=0xc4 => Op::Numeric(opcode),
+            0xfc => match reader.u32()? {
+                sub @ 0..=7 => Op::Saturating(sub as u8),
+                8 => {
+                    let segment = reader.u32()?;
+                    reader.u32()?;
+                    Op::MemoryInit(segment)
+                }
+                9 => Op::DataDrop(reader.u32()?),
+                10 => {
+                    reader.u32()?;
+                    reader.u32()?;
+                    Op::MemoryCopy
+                }
+                11 => {
+                    reader.u32()?;
+                    Op::MemoryFill
+                }
+                other => return Err(format!("unsupported instruction 0xfc {}", other)),
+            },
+            other => return Err(format!("unsupported instruction 0x{:02x}", other)),
+        };
+        code.push(op);
+    }
+    if !reader.is_empty() {
+        return Err("code after the end of a function".to_string());
+    }
+    Ok((declared as u32, code))
+}
@@ -0,0 +1,442 @@
+//! Polygenic risk scores from PGS Catalog scoring files
+//!
+//! A score is the sum over its variants of the effect weight times the
+//! number of effect alleles carried. How a variant is matched and what
+//! happens when it cannot be scored:
+//!
+//! - Variants are matched by position when the scoring file and the genome
+//!   are on the same build, and by rsid otherwise.
+//! - When the called alleles are neither the effect nor the other allele
+//!   but their complements are, the call is read from the opposite strand.
+//!   A/T and C/G SNPs look the same on both strands and are taken as
+//!   reported.
+//! - Variants that are not genotyped, are no-calls, or carry alleles that
+//!   match neither strand are missing. [`MissingStrategy`] decides whether
+//!   they add nothing or their population mean.
+
+use crate::annotation::normalize_rsid;
+use crate::annotation::tsv::TsvReader;
+use crate::genome::{reverse_complement, GenomeBuild, Variant};
+use crate::parser::{compression, detect_genome_build, normalize_chromosome};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::io::BufRead;
+use std::path::Path;
+
+const EFFECT_COLUMNS: [&str; 2] = ["effect_allele", "effect_weight"];
+
+/// One weighted variant of a score
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreVariant {
+    pub rsid: Option<String>,
+    pub chromosome: Option<String>,
+    pub position: Option<u64>,
+    pub effect_allele: String,
+    pub other_allele: Option<String>,
+    pub effect_weight: f64,
+    /// Effect allele frequency in the development population
+    pub effect_allele_frequency: Option<f64>,
+}
+
+/// A PGS Catalog scoring file
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoringFile {
+    /// e.g. "PGS000001"
+    pub pgs_id: Option<String>,
+    pub name: Option<String>,
+    pub trait_reported: Option<String>,
+    /// Build of the positions used for matching
+    pub genome_build: Option<GenomeBuild>,
+    pub variants: Vec<ScoreVariant>,
+}
+
+/// What a missing variant contributes to the score
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingStrategy {
+    /// Nothing; the score only covers the variants that were genotyped
+    #[default]
+    Skip,
+    /// Twice the effect allele frequency times the weight, the expected
+    /// dosage in the population; variants without a frequency add nothing
+    Mean,
+}
+
+/// Population distribution a raw score is ranked against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ReferenceDistribution {
+    Normal {
+        mean: f64,
+        standard_deviation: f64,
+    },
+    /// Scores of a reference panel
+    Empirical {
+        scores: Vec<f64>,
+    },
+}
+
+impl ReferenceDistribution {
+    /// Percentile (0-100) of a raw score
+    pub fn percentile(&self, score: f64) -> Option<f64> {
+        match self {
+            ReferenceDistribution::Normal {
+                mean,
+                standard_deviation,
+            } => {
+                if *standard_deviation <= 0.0 {
+                    return None;
+                }
+                let z = (score - mean) / standard_deviation;
+                Some(100.0 * 0.5 * (1.0 + erf(z / std::f64::consts::SQRT_2)))
+            }
+            ReferenceDistribution::Empirical { scores } => {
+                if scores.is_empty() {
+                    return None;
+                }
+                let below = scores.iter().filter(|s| **s < score).count() as f64;
+                let equal = scores.iter().filter(|s| **s == score).count() as f64;
+                Some(100.0 * (below + equal / 2.0) / scores.len() as f64)
+            }
+        }
+    }
+}
+
+/// How much of a score could be computed from the genome
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Coverage {
+    pub total_variants: usize,
+    pub matched: usize,
+    /// Matched variants read from the opposite strand
+    pub strand_flipped: usize,
+    /// Matched A/T or C/G variants whose strand cannot be checked
+    pub palindromic: usize,
+    /// Variants not present in the genome
+    pub not_genotyped: usize,
+    pub no_calls: usize,
+    /// Genotyped variants whose alleles match neither strand
+    pub allele_mismatches: usize,
+    /// Matched variants scored by their imputed dosage
+    pub imputed_dosages: usize,
+    /// Matched variants as a fraction of the score's variants
+    pub fraction: f64,
+}
+
+/// A computed score
+#[derive(Debug, Clone, Serialize)]
+pub struct PrsResult {
+    pub pgs_id: Option<String>,
+    pub trait_reported: Option<String>,
+    pub raw_score: f64,
+    /// Percentile against the reference distribution, when one is known
+    pub percentile: Option<f64>,
+    pub missing_strategy: MissingStrategy,
+    pub coverage: Coverage,
+}
+
+impl ScoringFile {
+    /// Load a scoring file, optionally gzip compressed
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let (reader, _) = compression::open_reader(path)?;
+        Self::from_reader(reader)
+    }
+
+    /// Parse scoring file text
+    ///
+    /// Harmonized files carry `hm_rsID`, `hm_chr` and `hm_pos` columns for
+    /// the build named in `#HmPOS_build`, which are used in preference to
+    /// the author-reported ones.
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        let mut meta = Vec::new();
+        let mut line = String::new();
+        loop {
+            let buffer = reader
+                .fill_buf()
+                .map_err(|e| format!("Failed to read scoring file: {}", e))?;
+            if !buffer.starts_with(b"#") {
+                break;
+            }
+            line.clear();
+            reader
+                .read_line(&mut line)
+                .map_err(|e| format!("Failed to read scoring file: {}", e))?;
+            let comment = line.trim_start_matches('#').trim();
+            if let Some((key, value)) = comment.split_once('=') {
+                meta.push((key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        let value = |key: &str| {
+            meta.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+                .filter(|v| !v.is_empty() && v != "NR")
+        };
+
+        let mut reader = TsvReader::new(reader)?;
+        reader
+            .require_columns(&EFFECT_COLUMNS)
+            .map_err(|e| format!("Not a PGS Catalog scoring file: {}", e))?;
+        let harmonized = reader.has_column("hm_pos");
+        let build_key = if harmonized {
+            "HmPOS_build"
+        } else {
+            "genome_build"
+        };
+        let genome_build = value(build_key).and_then(|build| match build.as_str() {
+            "hg19" => Some(GenomeBuild::GRCh37),
+            "hg38" => Some(GenomeBuild::GRCh38),
+            _ => detect_genome_build(&[build]),
+        });
+        let (rsid_column, chromosome_column, position_column) = if harmonized {
+            ("hm_rsID", "hm_chr", "hm_pos")
+        } else {
+            ("rsID", "chr_name", "chr_position")
+        };
+
+        let mut variants = Vec::new();
+        while let Some(row) = reader.next_row() {
+            let row = row.map_err(|e| format!("Scoring file {}", e))?;
+            let effect_allele = row.require("effect_allele")?.to_ascii_uppercase();
+            let effect_weight = row
+                .require("effect_weight")?
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid effect_weight on line {}", row.line_number))?;
+            let rsid = row
+                .get(rsid_column)
+                .or_else(|| row.get("rsID"))
+                .and_then(normalize_rsid);
+            let chromosome = row.get(chromosome_column).map(normalize_chromosome);
+            let position = row.get(position_column).and_then(|p| p.parse().ok());
+            if rsid.is_none() && (chromosome.is_none() || position.is_none()) {
+                continue;
+            }
+            variants.push(ScoreVariant {
+                rsid,
+                chromosome,
+                position,
+                effect_allele,
+                other_allele: row
+                    .get("other_allele")
+                    .or_else(|| row.get("hm_inferOtherAllele"))
+                    .map(str::to_ascii_uppercase),
+                effect_weight,
+                effect_allele_frequency: row
+                    .get("allelefrequency_effect")
+                    .and_then(|f| f.parse().ok())
+                    .filter(|f: &f64| (0.0..=1.0).contains(f)),
+            });
+        }
+
+        Ok(ScoringFile {
+            pgs_id: value("pgs_id"),
+            name: value("pgs_name"),
+            trait_reported: value("trait_reported"),
+            genome_build,
+            variants,
+        })
+    }
+
+    /// Score a genome
+    ///
+    /// Without a `reference`, the percentile is taken from the normal
+    /// distribution implied by the effect allele frequencies of the
+    /// variants that contributed, assuming Hardy-Weinberg equilibrium. It
+    /// is left out when any of them has no frequency. An imputed call with
+    /// a `DS` dosage contributes the dosage rather than the copies of its
+    /// best-guess genotype, so an uncertain call counts for less.
+    /// `checkpoint` is called with the number of variants scored every
+    /// [`CHECKPOINT_INTERVAL`] variants.
+    pub fn compute<F>(
+        &self,
+        genome: &LoadedGenome,
+        missing: MissingStrategy,
+        reference: Option<&ReferenceDistribution>,
+        mut checkpoint: F,
+    ) -> Result<PrsResult, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let same_build = match (genome.file.genome_build, self.genome_build) {
+            (Some(genome_build), Some(score_build)) => genome_build == score_build,
+            _ => false,
+        };
+        let mut coverage = Coverage {
+            total_variants: self.variants.len(),
+            ..Coverage::default()
+        };
+        let mut raw_score = 0.0;
+        // Mean and variance of the score in a population at HWE
+        let mut expected = Some((0.0, 0.0));
+
+        for (index, score_variant) in self.variants.iter().enumerate() {
+            if index % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(index)?;
+            }
+            let frequency = score_variant.effect_allele_frequency;
+            let weight = score_variant.effect_weight;
+
+            let dosage = match find_variant(genome, score_variant, same_build) {
+                None => {
+                    coverage.not_genotyped += 1;
+                    None
+                }
+                Some(variant) if variant.genotype.is_no_call() => {
+                    coverage.no_calls += 1;
+                    None
+                }
+                Some(variant) => match effect_dosage(variant, score_variant) {
+                    Some((dosage, orientation)) => {
+                        coverage.matched += 1;
+                        match orientation {
+                            Orientation::Flipped => coverage.strand_flipped += 1,
+                            Orientation::Palindromic => coverage.palindromic += 1,
+                            Orientation::Direct => {}
+                        }
+                        let imputed = (orientation != Orientation::Flipped)
+                            .then(|| imputed_dosage(variant, &score_variant.effect_allele))
+                            .flatten();
+                        if imputed.is_some() {
+                            coverage.imputed_dosages += 1;
+                        }
+                        Some(imputed.unwrap_or(dosage as f64))
+                    }
+                    None => {
+                        coverage.allele_mismatches += 1;
+                        None
+                    }
+                },
+            };
+
+            match (dosage, missing, frequency) {
+                (Some(dosage), _, _) => {
+                    raw_score += dosage * weight;
+                    expected = match (expected, frequency) {
+                        (Some((mean, variance)), Some(p)) => Some((
+                            mean + 2.0 * p * weight,
+                            variance + 2.0 * p * (1.0 - p) * weight * weight,
+                        )),
+                        _ => None,
+                    };
+                }
+                // An imputed variant adds the same amount to every score,
+                // shifting the mean without widening the distribution
+                (None, MissingStrategy::Mean, Some(p)) => {
+                    raw_score += 2.0 * p * weight;
+                    expected = expected.map(|(mean, variance)| (mean + 2.0 * p * weight, variance));
+                }
+                (None, _, _) => {}
+            }
+        }
+
+        coverage.fraction = if coverage.total_variants == 0 {
+            0.0
+        } else {
+            coverage.matched as f64 / coverage.total_variants as f64
+        };
+        let derived = expected.map(|(mean, variance)| ReferenceDistribution::Normal {
+            mean,
+            standard_deviation: variance.sqrt(),
+        });
+        let percentile = reference
+            .or(derived.as_ref())
+            .and_then(|distribution| distribution.percentile(raw_score));
+
+        Ok(PrsResult {
+            pgs_id: self.pgs_id.clone(),
+            trait_reported: self.trait_reported.clone(),
+            raw_score,
+            percentile,
+            missing_strategy: missing,
+            coverage,
+        })
+    }
+}
+
+// Helper functions
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Orientation {
+    Direct,
+    Flipped,
+    Palindromic,
+}
+
+fn find_variant<'a>(
+    genome: &'a LoadedGenome,
+    score_variant: &ScoreVariant,
+    same_build: bool,
+) -> Option<&'a Variant> {
+    if same_build {
+        if let (Some(chromosome), Some(position)) =
+            (&score_variant.chromosome, score_variant.position)
+        {
+            if let Some(variant) = genome.get_at(chromosome, position) {
+                return Some(variant);
+            }
+        }
+    }
+    score_variant
+        .rsid
+        .as_deref()
+        .and_then(|rsid| genome.get_by_rsid(rsid))
+}
+
+/// Effect allele copies in a call, reading it from the opposite strand
+/// when only the complemented alleles fit
+fn effect_dosage(variant: &Variant, score_variant: &ScoreVariant) -> Option<(usize, Orientation)> {
+    let effect = score_variant.effect_allele.as_str();
+    let alleles = variant.genotype.alleles();
+    let expected: Vec<&str> = match &score_variant.other_allele {
+        Some(other) => vec![effect, other.as_str()],
+        None => vec![effect],
+    };
+    let fits = |alleles: &[&str]| match &score_variant.other_allele {
+        Some(_) => alleles.iter().all(|allele| expected.contains(allele)),
+        // Without the other allele any call can be scored directly
+        None => true,
+    };
+
+    let palindromic = score_variant
+        .other_allele
+        .as_deref()
+        .is_some_and(|other| reverse_complement(other) == effect);
+    if fits(&alleles) {
+        let orientation = if palindromic {
+            Orientation::Palindromic
+        } else {
+            Orientation::Direct
+        };
+        return Some((variant.genotype.allele_count(effect), orientation));
+    }
+
+    let flipped = variant.genotype.complemented();
+    let flipped_alleles = flipped.alleles();
+    if score_variant.other_allele.is_some() && fits(&flipped_alleles) {
+        return Some((flipped.allele_count(effect), Orientation::Flipped));
+    }
+    None
+}
+
+/// Expected copies of the effect allele from the `DS` dosage of an
+/// imputed biallelic call, which counts alternate alleles
+fn imputed_dosage(variant: &Variant, effect: &str) -> Option<f64> {
+    let quality = variant.quality.as_ref().filter(|quality| quality.imputed)?;
+    let dosage = f64::from(quality.dosage?);
+    match variant.alternates.as_slice() {
+        [alternate] if alternate == effect => Some(dosage),
+        [_] if variant.reference.as_deref() == Some(effect) => Some(2.0 - dosage),
+        _ => None,
+    }
+}
+
+/// Error function, Abramowitz and Stegun 7.1.26 (error below 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    sign * (1.0 - polynomial * (-x * x).exp())
+}
@@ -0,0 +1,130 @@
+//! Securely removing saved genome data
+//!
+//! Files are overwritten with zeros and flushed to disk before they are
+//! deleted, so the plaintext of anything saved unsealed does not linger in
+//! the freed blocks. Solid-state drives and copy-on-write file systems may
+//! still keep the old blocks elsewhere; the overwrite is a best effort on
+//! top of the encryption everything saved is under, not a replacement.
+
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Bytes overwritten per write
+const CHUNK: usize = 64 * 1024;
+
+/// A file that was deleted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RemovedFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Whether the contents were overwritten before the file was deleted
+    pub overwritten: bool,
+}
+
+/// A file or directory that could not be deleted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// What a purge removed and what it could not
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PurgeReport {
+    pub files: Vec<RemovedFile>,
+    pub directories: Vec<PathBuf>,
+    pub failed: Vec<PurgeFailure>,
+}
+
+impl PurgeReport {
+    /// Size of every file removed
+    pub fn bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes).sum()
+    }
+
+    /// Whether everything asked for was removed
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Remove a file, or a directory and everything in it
+    ///
+    /// A path that does not exist is skipped. Failures are recorded and the
+    /// rest of a directory is still removed.
+    pub fn remove(&mut self, path: &Path) {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => return,
+        };
+        if !metadata.is_dir() {
+            self.remove_file(path, metadata.len(), metadata.is_file());
+            return;
+        }
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                for entry in entries.filter_map(Result::ok) {
+                    self.remove(&entry.path());
+                }
+            }
+            Err(e) => self.fail(path, format!("Failed to read directory: {}", e)),
+        }
+        match fs::remove_dir(path) {
+            Ok(()) => self.directories.push(path.to_path_buf()),
+            Err(e) => {
+                // Whatever kept the directory from being emptied is recorded already
+                if !self.failed.iter().any(|f| f.path.starts_with(path)) {
+                    self.fail(path, format!("Failed to remove directory: {}", e));
+                }
+            }
+        }
+    }
+
+    fn remove_file(&mut self, path: &Path, bytes: u64, regular: bool) {
+        // Links are deleted without touching what they point to
+        let overwritten = regular && overwrite(path, bytes).is_ok();
+        match fs::remove_file(path) {
+            Ok(()) => self.files.push(RemovedFile {
+                path: path.to_path_buf(),
+                bytes,
+                overwritten,
+            }),
+            Err(e) => self.fail(path, format!("Failed to remove file: {}", e)),
+        }
+    }
+
+    fn fail(&mut self, path: &Path, reason: String) {
+        self.failed.push(PurgeFailure {
+            path: path.to_path_buf(),
+            reason,
+        });
+    }
+}
+
+/// Remove every path given, reporting each file and directory removed
+pub fn purge<'a, I>(paths: I) -> PurgeReport
+where
+    I: IntoIterator<Item = &'a Path>,
+{
+    let mut report = PurgeReport::default();
+    for path in paths {
+        report.remove(path);
+    }
+    report
+}
+
+// Helper functions
+
+/// Write zeros over a file's contents in place and flush them to disk
+fn overwrite(path: &Path, bytes: u64) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; CHUNK];
+    let mut left = bytes;
+    while left > 0 {
+        let n = left.min(CHUNK as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()
+}
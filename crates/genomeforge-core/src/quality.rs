@@ -0,0 +1,244 @@
+//! Quality filtering of sequencing calls
+//!
+//! Variant callers mark every site with a FILTER status and a QUAL score,
+//! and every call with a genotype quality (GQ) and read depth (DP). Calls
+//! that failed a filter or fall below the thresholds in a [`QualityFilter`]
+//! are turned into no-calls by [`filter_genome`], the way imputed calls
+//! are by [`crate::imputation`]. Calls that are kept but fall short of
+//! the usual limits are [`borderline`], so findings resting on them can
+//! say so rather than let a sequencing artifact pass as a result.
+
+use crate::annotation::probes;
+use crate::genome::{Genotype, Variant};
+use crate::parser::SummaryBuilder;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// QUAL below which a kept call is borderline
+pub const BORDERLINE_SITE_QUALITY: f32 = 30.0;
+
+/// GQ below which a kept call is borderline
+pub const BORDERLINE_GENOTYPE_QUALITY: f32 = 20.0;
+
+/// DP below which a kept call is borderline
+pub const BORDERLINE_DEPTH: u32 = 10;
+
+/// Which sequencing calls to drop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityFilter {
+    /// Drop calls at sites that failed any FILTER
+    pub require_pass: bool,
+    /// Drop calls at sites with a lower QUAL
+    pub min_site_quality: Option<f64>,
+    /// Drop calls with a lower GQ
+    pub min_genotype_quality: Option<f64>,
+    /// Drop calls covered by fewer reads
+    pub min_depth: Option<u32>,
+}
+
+impl QualityFilter {
+    /// Whether the filter drops anything
+    pub fn is_active(&self) -> bool {
+        self.require_pass
+            || self.min_site_quality.is_some()
+            || self.min_genotype_quality.is_some()
+            || self.min_depth.is_some()
+    }
+
+    /// Check that the Phred-scaled thresholds are not negative
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, threshold) in [
+            ("min_site_quality", self.min_site_quality),
+            ("min_genotype_quality", self.min_genotype_quality),
+        ] {
+            if threshold.is_some_and(|t| !t.is_finite() || t < 0.0) {
+                return Err(format!("{} must be zero or more", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Why a call is dropped, or `None` when it is kept
+    pub fn rejects(&self, variant: &Variant) -> Option<QualityIssue> {
+        let quality = variant.quality.as_ref()?;
+        if variant.genotype.is_no_call() {
+            return None;
+        }
+        if self.require_pass && !quality.filters.is_empty() {
+            return Some(QualityIssue::FailedFilter);
+        }
+        let below = |value: Option<f32>, min: Option<f64>| {
+            value
+                .zip(min)
+                .is_some_and(|(value, min)| f64::from(value) < min)
+        };
+        if below(quality.site_quality, self.min_site_quality) {
+            return Some(QualityIssue::LowSiteQuality);
+        }
+        if below(quality.genotype_quality, self.min_genotype_quality) {
+            return Some(QualityIssue::LowGenotypeQuality);
+        }
+        if quality
+            .depth
+            .zip(self.min_depth)
+            .is_some_and(|(depth, min)| depth < min)
+        {
+            return Some(QualityIssue::LowDepth);
+        }
+        None
+    }
+}
+
+/// A reason a call is dropped or borderline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    FailedFilter,
+    /// On a probe the mask list flags as unreliable on the file's chip
+    UnreliableProbe,
+    LowSiteQuality,
+    LowGenotypeQuality,
+    LowDepth,
+}
+
+/// Counts from filtering a genome
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityStats {
+    /// Called variants with any quality field, before filtering
+    pub assessed: usize,
+    pub excluded_failed_filter: usize,
+    pub excluded_low_site_quality: usize,
+    pub excluded_low_genotype_quality: usize,
+    pub excluded_low_depth: usize,
+    /// Calls excluded for each failed FILTER value
+    pub excluded_by_filter: BTreeMap<String, usize>,
+    /// Kept calls that are [`borderline`]
+    pub borderline: usize,
+}
+
+impl QualityStats {
+    /// Calls turned into no-calls
+    pub fn excluded(&self) -> usize {
+        self.excluded_failed_filter
+            + self.excluded_low_site_quality
+            + self.excluded_low_genotype_quality
+            + self.excluded_low_depth
+    }
+}
+
+/// Whether any variant of the genome carries sequencing quality fields
+pub fn has_quality_fields(genome: &LoadedGenome) -> bool {
+    genome.variants().iter().any(|variant| {
+        variant.quality.as_ref().is_some_and(|quality| {
+            !quality.filters.is_empty()
+                || quality.site_quality.is_some()
+                || quality.genotype_quality.is_some()
+                || quality.depth.is_some()
+        })
+    })
+}
+
+/// Count assessed and borderline calls, turning those the filter rejects
+/// into no-calls
+///
+/// The sites stay in the genome, so analyses see them as not called.
+/// `checkpoint` is called with the number of variants filtered every
+/// [`CHECKPOINT_INTERVAL`] variants.
+pub fn filter_genome<F>(
+    genome: &LoadedGenome,
+    filter: &QualityFilter,
+    mut checkpoint: F,
+) -> Result<(LoadedGenome, QualityStats), String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut stats = QualityStats::default();
+    let mut builder = SummaryBuilder::default();
+    let mut variants = Vec::with_capacity(genome.len());
+    for (index, variant) in genome.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        let mut variant = variant.clone();
+        if let Some(quality) = variant.quality.as_ref() {
+            if !variant.genotype.is_no_call() {
+                stats.assessed += 1;
+            }
+            match filter.rejects(&variant) {
+                Some(issue) => {
+                    match issue {
+                        QualityIssue::FailedFilter | QualityIssue::UnreliableProbe => {
+                            stats.excluded_failed_filter += 1
+                        }
+                        QualityIssue::LowSiteQuality => stats.excluded_low_site_quality += 1,
+                        QualityIssue::LowGenotypeQuality => {
+                            stats.excluded_low_genotype_quality += 1
+                        }
+                        QualityIssue::LowDepth => stats.excluded_low_depth += 1,
+                    }
+                    for name in &quality.filters {
+                        *stats.excluded_by_filter.entry(name.clone()).or_default() += 1;
+                    }
+                    variant.genotype = Genotype::NoCall;
+                }
+                None if !variant.genotype.is_no_call() && !borderline(&variant).is_empty() => {
+                    stats.borderline += 1;
+                }
+                None => {}
+            }
+        }
+        builder.add(&variant);
+        variants.push(variant);
+    }
+    checkpoint(genome.len())?;
+
+    let summary = builder.finish(genome.summary.skipped_lines);
+    Ok((
+        LoadedGenome::from_variants(genome.file.clone(), summary, variants),
+        stats,
+    ))
+}
+
+/// What makes a variant's call borderline: a FILTER it failed, a probe
+/// [`probes`] flags as unreliable, or a QUAL, GQ or depth under the usual limits of [`BORDERLINE_SITE_QUALITY`],
+/// [`BORDERLINE_GENOTYPE_QUALITY`] and [`BORDERLINE_DEPTH`]; empty for
+/// calls without sequencing quality fields
+pub fn borderline(variant: &Variant) -> Vec<QualityIssue> {
+    let Some(quality) = variant.quality.as_ref() else {
+        return Vec::new();
+    };
+    let mut issues = Vec::new();
+    if quality
+        .filters
+        .iter()
+        .any(|name| name != probes::FLAG_FILTER)
+    {
+        issues.push(QualityIssue::FailedFilter);
+    }
+    if quality
+        .filters
+        .iter()
+        .any(|name| name == probes::FLAG_FILTER)
+    {
+        issues.push(QualityIssue::UnreliableProbe);
+    }
+    if quality
+        .site_quality
+        .is_some_and(|qual| qual < BORDERLINE_SITE_QUALITY)
+    {
+        issues.push(QualityIssue::LowSiteQuality);
+    }
+    if quality
+        .genotype_quality
+        .is_some_and(|gq| gq < BORDERLINE_GENOTYPE_QUALITY)
+    {
+        issues.push(QualityIssue::LowGenotypeQuality);
+    }
+    if quality.depth.is_some_and(|depth| depth < BORDERLINE_DEPTH) {
+        issues.push(QualityIssue::LowDepth);
+    }
+    issues
+}
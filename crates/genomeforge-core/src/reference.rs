@@ -0,0 +1,322 @@
+//! Reference genome FASTA management
+//!
+//! Normalization, liftover checks and alignment reading need the bases of
+//! the reference genome variants were called against. References are kept
+//! per build in one directory as uncompressed FASTA, since only that can be
+//! read at random, each with a samtools `.fai` index next to it and its
+//! SHA-256 digest recorded in `references.json`.
+//!
+//! An import is checked against the digest published for the file,
+//! decompressed, indexed and checked to be the build it is imported as
+//! before it replaces the installed reference of that build; a failed
+//! import leaves the installed one in place.
+
+use crate::annotation::manager::{sha256_file, verify_checksum};
+use crate::genome::GenomeBuild;
+use crate::normalize::{self, IndexedFasta};
+use crate::parser::compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Record of the installed references, inside the reference directory
+pub const RECORD_FILE: &str = "references.json";
+
+/// Directory imports and downloads are staged in, inside the reference
+/// directory so the final move is a rename on the same volume
+const STAGING_DIR: &str = ".staging";
+
+/// Length of chromosome 1 in each build, which tells the builds apart
+const CHROMOSOME_1_LENGTHS: [(GenomeBuild, u64); 3] = [
+    (GenomeBuild::GRCh36, 247_249_719),
+    (GenomeBuild::GRCh37, 249_250_621),
+    (GenomeBuild::GRCh38, 248_956_422),
+];
+
+/// Bytes copied between two checkpoints during an import
+const COPY_CHUNK: usize = 1 << 20;
+
+/// A downloadable reference FASTA listed in a
+/// [`ReleaseManifest`](crate::annotation::manager::ReleaseManifest)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceRelease {
+    pub build: GenomeBuild,
+    pub version: String,
+    pub url: String,
+    /// Lowercase hex SHA-256 digest of the file as downloaded
+    pub sha256: String,
+    pub size: Option<u64>,
+}
+
+/// Details kept about an installed reference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstalledReference {
+    pub build: GenomeBuild,
+    pub file_name: String,
+    pub version: Option<String>,
+    /// Lowercase hex SHA-256 digest of the installed, uncompressed FASTA
+    pub sha256: String,
+    /// Digest of the file it was imported from, which is what sources
+    /// publish
+    pub source_sha256: String,
+    pub sequences: usize,
+    pub bases: u64,
+    /// Seconds since the Unix epoch
+    pub installed_at: u64,
+}
+
+/// Installed references by build, stored as [`RECORD_FILE`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReferenceRecord {
+    #[serde(flatten)]
+    references: HashMap<GenomeBuild, InstalledReference>,
+}
+
+/// The reference FASTA files in a directory
+#[derive(Debug, Clone)]
+pub struct ReferenceManager {
+    dir: PathBuf,
+}
+
+impl ReferenceManager {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where the FASTA of a build is installed
+    pub fn path(&self, build: GenomeBuild) -> PathBuf {
+        self.dir.join(file_name(build))
+    }
+
+    /// Installed references, by build
+    pub fn installed(&self) -> Result<Vec<InstalledReference>, String> {
+        let mut installed: Vec<InstalledReference> =
+            self.read_record()?.references.into_values().collect();
+        installed.sort_by_key(|reference| reference.build as u8);
+        Ok(installed)
+    }
+
+    /// The installed reference of a build, if any
+    pub fn get(&self, build: GenomeBuild) -> Result<Option<InstalledReference>, String> {
+        let mut record = self.read_record()?;
+        Ok(record
+            .references
+            .remove(&build)
+            .filter(|_| self.path(build).is_file()))
+    }
+
+    /// Open the reference of a build for sequence lookups; `None` when none
+    /// is installed
+    pub fn open(&self, build: GenomeBuild) -> Result<Option<IndexedFasta>, String> {
+        if self.get(build)?.is_none() {
+            return Ok(None);
+        }
+        IndexedFasta::open(&self.path(build)).map(Some)
+    }
+
+    /// Unique path inside the staging directory, for downloads to import
+    pub fn staging_path(&self) -> Result<PathBuf, String> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let staging = self.dir.join(STAGING_DIR);
+        std::fs::create_dir_all(&staging)
+            .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+        Ok(staging.join(format!(
+            "reference-{}-{}.part",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    /// Install a FASTA file, optionally gzip or bgzip compressed, as the
+    /// reference of `build`
+    ///
+    /// The file is checked against `expected_sha256` when given. The
+    /// original is left untouched. `checkpoint` is called with the
+    /// uncompressed bytes copied so far, about every megabyte; an error from it aborts the
+    /// import.
+    pub fn import<F>(
+        &self,
+        build: GenomeBuild,
+        source: &Path,
+        version: Option<&str>,
+        expected_sha256: Option<&str>,
+        checkpoint: F,
+    ) -> Result<InstalledReference, String>
+    where
+        F: FnMut(u64) -> Result<(), String>,
+    {
+        let source_sha256 = match expected_sha256 {
+            Some(expected) => verify_checksum(source, expected)?,
+            None => sha256_file(source)?,
+        };
+        let staged = self.staging_path()?;
+        let staged_index = index_path(&staged);
+        let result = stage(build, source, &staged, checkpoint);
+        let (sha256, sequences, bases) = match result {
+            Ok(staged) => staged,
+            Err(e) => {
+                let _ = std::fs::remove_file(&staged);
+                let _ = std::fs::remove_file(&staged_index);
+                return Err(e);
+            }
+        };
+
+        let target = self.path(build);
+        let moved = std::fs::rename(&staged, &target)
+            .and_then(|()| std::fs::rename(&staged_index, index_path(&target)));
+        if let Err(e) = moved {
+            let _ = std::fs::remove_file(&staged);
+            let _ = std::fs::remove_file(&staged_index);
+            return Err(format!("Failed to install reference: {}", e));
+        }
+
+        let installed = InstalledReference {
+            build,
+            file_name: file_name(build),
+            version: version.map(str::to_string),
+            sha256,
+            source_sha256,
+            sequences,
+            bases,
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        let mut record = self.read_record()?;
+        record.references.insert(build, installed.clone());
+        self.write_record(&record)?;
+        Ok(installed)
+    }
+
+    /// Whether the installed FASTA of a build still has the digest it was
+    /// installed with
+    ///
+    /// A missing index is rebuilt. Fails when no reference is installed.
+    pub fn verify(&self, build: GenomeBuild) -> Result<bool, String> {
+        let installed = self
+            .get(build)?
+            .ok_or_else(|| format!("No {:?} reference is installed", build))?;
+        let path = self.path(build);
+        if !sha256_file(&path)?.eq_ignore_ascii_case(&installed.sha256) {
+            return Ok(false);
+        }
+        if !index_path(&path).is_file() {
+            normalize::write_index(&path)?;
+        }
+        Ok(true)
+    }
+
+    /// Delete the reference of a build; whether one was installed
+    pub fn remove(&self, build: GenomeBuild) -> Result<bool, String> {
+        let mut record = self.read_record()?;
+        let removed = record.references.remove(&build).is_some();
+        let path = self.path(build);
+        for file in [index_path(&path), path] {
+            match std::fs::remove_file(&file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(format!("Failed to remove {}: {}", file.display(), e)),
+            }
+        }
+        if removed {
+            self.write_record(&record)?;
+        }
+        Ok(removed)
+    }
+
+    fn read_record(&self) -> Result<ReferenceRecord, String> {
+        let path = self.dir.join(RECORD_FILE);
+        if !path.exists() {
+            return Ok(ReferenceRecord::default());
+        }
+        let json = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+
+    fn write_record(&self, record: &ReferenceRecord) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(record).map_err(|e| e.to_string())?;
+        let staged = self.dir.join(format!("{}.tmp", RECORD_FILE));
+        std::fs::write(&staged, json)
+            .and_then(|()| std::fs::rename(&staged, self.dir.join(RECORD_FILE)))
+            .map_err(|e| format!("Failed to write reference record: {}", e))
+    }
+}
+
+/// The build whose chromosome 1 has the length of the FASTA's, if any
+pub fn detect_build(fasta: &IndexedFasta) -> Option<GenomeBuild> {
+    let length = fasta.length("1")?;
+    CHROMOSOME_1_LENGTHS
+        .iter()
+        .find(|(_, known)| *known == length)
+        .map(|(build, _)| *build)
+}
+
+// Helper functions
+
+fn file_name(build: GenomeBuild) -> String {
+    format!("{:?}.fa", build)
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    let mut index = path.as_os_str().to_owned();
+    index.push(".fai");
+    PathBuf::from(index)
+}
+
+/// Decompress and index a FASTA into `staged`, returning its digest,
+/// sequence count and base count
+fn stage<F>(
+    build: GenomeBuild,
+    source: &Path,
+    staged: &Path,
+    mut checkpoint: F,
+) -> Result<(String, usize, u64), String>
+where
+    F: FnMut(u64) -> Result<(), String>,
+{
+    let (mut reader, _) = compression::open_reader(source)?;
+    let failed = |e: std::io::Error| format!("Failed to write {}: {}", staged.display(), e);
+    let mut writer = BufWriter::new(File::create(staged).map_err(failed)?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; COPY_CHUNK];
+    let mut copied = 0u64;
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read]).map_err(failed)?;
+        copied += read as u64;
+        checkpoint(copied)?;
+    }
+    writer.flush().map_err(failed)?;
+    drop(writer);
+
+    normalize::write_index(staged)?;
+    let fasta = IndexedFasta::open(staged)?;
+    if let Some(detected) = detect_build(&fasta).filter(|detected| *detected != build) {
+        return Err(format!(
+            "The FASTA is a {:?} reference, not {:?}",
+            detected, build
+        ));
+    }
+    let chromosomes = fasta.chromosomes();
+    let bases = chromosomes
+        .iter()
+        .filter_map(|chromosome| fasta.length(chromosome))
+        .sum();
+    Ok((hex::encode(hasher.finalize()), chromosomes.len(), bases))
+}
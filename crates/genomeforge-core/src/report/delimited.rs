@@ -0,0 +1,96 @@
+//! Comma- and tab-separated tables
+//!
+//! CSV follows RFC 4180 and starts with a byte order mark, which Excel
+//! needs to read it as UTF-8. TSV has no quoting, so tabs and line breaks
+//! inside cells become spaces. Several tables are written as one zip
+//! archive with a file per table.
+
+use super::Table;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Separator between the cells of a row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    /// File extension of tables with this delimiter
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Delimiter::Comma => "csv",
+            Delimiter::Tab => "tsv",
+        }
+    }
+}
+
+/// A table with a header row
+pub fn render(table: &Table, delimiter: Delimiter) -> String {
+    let mut out = String::new();
+    if delimiter == Delimiter::Comma {
+        out.push('\u{FEFF}');
+    }
+    for row in std::iter::once(&table.columns).chain(&table.rows) {
+        let cells: Vec<String> = row.iter().map(|cell| field(cell, delimiter)).collect();
+        out.push_str(&cells.join(match delimiter {
+            Delimiter::Comma => ",",
+            Delimiter::Tab => "\t",
+        }));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// A zip archive holding each table as `<name>.<extension>`
+pub fn archive(tables: &[(&str, Table)], delimiter: Delimiter) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, table) in tables {
+        let file = format!("{}.{}", name, delimiter.extension());
+        zip.start_file(file, SimpleFileOptions::default())
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+        zip.write_all(render(table, delimiter).as_bytes())
+            .map_err(|e| format!("Failed to write archive: {}", e))?;
+    }
+    zip.finish()
+        .map(Cursor::into_inner)
+        .map_err(|e| format!("Failed to write archive: {}", e))
+}
+
+// Helper functions
+
+fn field(cell: &str, delimiter: Delimiter) -> String {
+    let cell = defuse(cell);
+    match delimiter {
+        Delimiter::Comma => {
+            if cell.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        }
+        Delimiter::Tab => cell.replace(['\t', '\r', '\n'], " "),
+    }
+}
+
+/// Keep spreadsheets from running a cell as a formula
+///
+/// Cells starting with `=`, `+`, `@` or a `-` that does not start a number
+/// get a leading apostrophe, which Excel hides.
+fn defuse(cell: &str) -> String {
+    let mut chars = cell.chars();
+    let formula = match chars.next() {
+        Some('=' | '+' | '@') => true,
+        Some('-') => chars
+            .next()
+            .is_some_and(|c| !c.is_ascii_digit() && c != '.'),
+        _ => false,
+    };
+    if formula {
+        format!("'{}", cell)
+    } else {
+        cell.to_string()
+    }
+}
@@ -0,0 +1,336 @@
+//! Font metrics for laying out report text
+//!
+//! Text is set in single-byte Windows-1252 ("WinAnsi") encoding, which
+//! covers English and the Western European languages. A report either
+//! embeds TrueType fonts, read here from the font file, or falls back to
+//! Helvetica, one of the standard PDF fonts every viewer provides.
+
+/// First character code with a width
+pub const FIRST_CHAR: u8 = 32;
+
+/// Character set in place of one WinAnsi cannot encode
+const REPLACEMENT: u8 = b'?';
+
+/// Code points of WinAnsi 0x80..=0x9F, 0 where the code is unused
+const WIN_ANSI_HIGH: [u16; 32] = [
+    0x20AC, 0, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0, 0x017D, 0, 0, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC,
+    0x2122, 0x0161, 0x203A, 0x0153, 0, 0x017E, 0x0178,
+];
+
+/// Helvetica widths of ASCII 32..=126, in thousandths of an em
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Helvetica-Bold widths of ASCII 32..=126
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Width assumed for standard-font characters outside ASCII
+const HELVETICA_OTHER: u16 = 556;
+
+/// Widths of character codes 32..=255, in thousandths of an em
+pub type Widths = [u16; 224];
+
+/// A TrueType font to embed
+#[derive(Debug, Clone)]
+pub struct TrueTypeFont {
+    pub data: Vec<u8>,
+    /// PostScript name, e.g. "SegoeUI"
+    pub name: String,
+    pub widths: Widths,
+    /// Metrics in thousandths of an em
+    pub ascent: i32,
+    pub descent: i32,
+    pub cap_height: i32,
+    pub bbox: [i32; 4],
+    pub italic_angle: f32,
+    pub bold: bool,
+}
+
+impl TrueTypeFont {
+    /// Read the metrics of a TrueType (glyf-based) font file
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let font = Tables::read(&data)?;
+        let head = font.table(b"head")?;
+        let units = read_u16(head, 18)? as i32;
+        if units == 0 {
+            return Err("Font has no units per em".to_string());
+        }
+        let scale = |value: i32| value * 1000 / units;
+        let bbox = [
+            scale(read_i16(head, 36)? as i32),
+            scale(read_i16(head, 38)? as i32),
+            scale(read_i16(head, 40)? as i32),
+            scale(read_i16(head, 42)? as i32),
+        ];
+
+        let hhea = font.table(b"hhea")?;
+        let ascent = scale(read_i16(hhea, 4)? as i32);
+        let descent = scale(read_i16(hhea, 6)? as i32);
+        let metrics = read_u16(hhea, 34)? as usize;
+        let hmtx = font.table(b"hmtx")?;
+        let advance = |glyph: u16| -> Result<u16, String> {
+            let index = (glyph as usize).min(metrics.saturating_sub(1));
+            read_u16(hmtx, index * 4)
+        };
+
+        let mut cap_height = ascent;
+        let mut bold = read_u16(head, 44)? & 1 != 0;
+        if let Ok(os2) = font.table(b"OS/2") {
+            // Fonts may forbid embedding; only "restricted" does so outright
+            if read_u16(os2, 8)? & 0x000F == 0x0002 {
+                return Err("Font does not allow embedding".to_string());
+            }
+            bold |= read_u16(os2, 4)? >= 600;
+            if read_u16(os2, 0)? >= 2 {
+                cap_height = scale(read_i16(os2, 88)? as i32);
+            }
+        }
+        let italic_angle = match font.table(b"post") {
+            Ok(post) => read_i32(post, 4)? as f32 / 65536.0,
+            Err(_) => 0.0,
+        };
+
+        let cmap = CharMap::read(font.table(b"cmap")?)?;
+        let mut widths = [0u16; 224];
+        for (code, width) in (FIRST_CHAR..=255).zip(widths.iter_mut()) {
+            if let Some(ch) = decode(code) {
+                let glyph = cmap.glyph(ch as u32)?;
+                *width = (advance(glyph)? as i32 * 1000 / units) as u16;
+            }
+        }
+
+        let name = font
+            .table(b"name")
+            .ok()
+            .and_then(postscript_name)
+            .unwrap_or_else(|| "GenomeForgeFont".to_string());
+
+        Ok(TrueTypeFont {
+            data,
+            name,
+            widths,
+            ascent,
+            descent,
+            cap_height,
+            bbox,
+            italic_angle,
+            bold,
+        })
+    }
+}
+
+/// Widths of the standard Helvetica font, bold or regular
+pub fn helvetica_widths(bold: bool) -> Widths {
+    let ascii = if bold { &HELVETICA_BOLD } else { &HELVETICA };
+    let mut widths = [HELVETICA_OTHER; 224];
+    widths[..ascii.len()].copy_from_slice(ascii);
+    widths
+}
+
+/// Encode text as WinAnsi, replacing what it cannot represent
+///
+/// Control characters, including tabs and line breaks, become spaces.
+pub fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match ch as u32 {
+            0..=31 => b' ',
+            code @ (32..=126 | 160..=255) => code as u8,
+            code => WIN_ANSI_HIGH
+                .iter()
+                .position(|&high| high != 0 && high as u32 == code)
+                .map_or(REPLACEMENT, |index| 0x80 + index as u8),
+        })
+        .collect()
+}
+
+/// Character of a WinAnsi code, if it has one
+pub fn decode(code: u8) -> Option<char> {
+    match code {
+        0..=31 | 127 => None,
+        0x80..=0x9F => match WIN_ANSI_HIGH[(code - 0x80) as usize] {
+            0 => None,
+            high => char::from_u32(high as u32),
+        },
+        _ => Some(code as char),
+    }
+}
+
+/// Width of encoded text at a font size, in points
+pub fn text_width(widths: &Widths, text: &[u8], size: f32) -> f32 {
+    let units: u32 = text
+        .iter()
+        .map(|&code| match code.checked_sub(FIRST_CHAR) {
+            Some(index) => widths[index as usize] as u32,
+            None => 0,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+// Helper functions
+
+/// Table directory of a font file
+struct Tables<'a> {
+    data: &'a [u8],
+    records: Vec<([u8; 4], usize, usize)>,
+}
+
+impl<'a> Tables<'a> {
+    fn read(data: &'a [u8]) -> Result<Self, String> {
+        match data.get(..4) {
+            Some([0, 1, 0, 0]) | Some(b"true") => {}
+            Some(b"OTTO") => {
+                return Err("OpenType fonts with CFF outlines are not supported".to_string())
+            }
+            _ => return Err("Not a TrueType font".to_string()),
+        }
+        let count = read_u16(data, 4)? as usize;
+        let records = (0..count)
+            .map(|index| {
+                let record = 12 + index * 16;
+                let tag = data
+                    .get(record..record + 4)
+                    .ok_or_else(truncated)?
+                    .try_into()
+                    .unwrap();
+                let offset = read_u32(data, record + 8)? as usize;
+                let length = read_u32(data, record + 12)? as usize;
+                Ok((tag, offset, length))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Tables { data, records })
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> Result<&'a [u8], String> {
+        let (_, offset, length) = self
+            .records
+            .iter()
+            .find(|(found, _, _)| found == tag)
+            .ok_or_else(|| format!("Font has no {} table", String::from_utf8_lossy(tag)))?;
+        self.data
+            .get(*offset..offset + length)
+            .ok_or_else(truncated)
+    }
+}
+
+/// A format 4 Unicode character map
+struct CharMap<'a> {
+    subtable: &'a [u8],
+    segments: usize,
+}
+
+impl<'a> CharMap<'a> {
+    fn read(cmap: &'a [u8]) -> Result<Self, String> {
+        let count = read_u16(cmap, 2)? as usize;
+        for index in 0..count {
+            let record = 4 + index * 8;
+            let platform = read_u16(cmap, record)?;
+            let encoding = read_u16(cmap, record + 2)?;
+            let offset = read_u32(cmap, record + 4)? as usize;
+            let unicode = platform == 0 || (platform == 3 && encoding == 1);
+            if unicode && read_u16(cmap, offset)? == 4 {
+                let subtable = cmap.get(offset..).ok_or_else(truncated)?;
+                let segments = read_u16(subtable, 6)? as usize / 2;
+                return Ok(CharMap { subtable, segments });
+            }
+        }
+        Err("Font has no Unicode character map".to_string())
+    }
+
+    /// Glyph of a character, 0 when the font lacks it
+    fn glyph(&self, ch: u32) -> Result<u16, String> {
+        let table = self.subtable;
+        let n = self.segments;
+        let ends = 14;
+        let starts = ends + n * 2 + 2;
+        let deltas = starts + n * 2;
+        let range_offsets = deltas + n * 2;
+        for segment in 0..n {
+            if read_u16(table, ends + segment * 2)? as u32 >= ch {
+                let start = read_u16(table, starts + segment * 2)? as u32;
+                if start > ch {
+                    return Ok(0);
+                }
+                let delta = read_u16(table, deltas + segment * 2)?;
+                let range_offset_at = range_offsets + segment * 2;
+                let range_offset = read_u16(table, range_offset_at)? as usize;
+                if range_offset == 0 {
+                    return Ok((ch as u16).wrapping_add(delta));
+                }
+                let at = range_offset_at + range_offset + (ch - start) as usize * 2;
+                return Ok(match read_u16(table, at)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                });
+            }
+        }
+        Ok(0)
+    }
+}
+
+/// PostScript name from a name table, limited to safe characters
+fn postscript_name(name: &[u8]) -> Option<String> {
+    let count = read_u16(name, 2).ok()? as usize;
+    let strings = read_u16(name, 4).ok()? as usize;
+    (0..count).find_map(|index| {
+        let record = 6 + index * 12;
+        let platform = read_u16(name, record).ok()?;
+        if read_u16(name, record + 6).ok()? != 6 {
+            return None;
+        }
+        let length = read_u16(name, record + 8).ok()? as usize;
+        let offset = strings + read_u16(name, record + 10).ok()? as usize;
+        let raw = name.get(offset..offset + length)?;
+        let text: String = match platform {
+            // UTF-16BE
+            0 | 3 => char::decode_utf16(
+                raw.chunks_exact(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+            )
+            .filter_map(Result::ok)
+            .collect(),
+            _ => raw.iter().map(|&b| b as char).collect(),
+        };
+        let text: String = text
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+fn read_u16(data: &[u8], at: usize) -> Result<u16, String> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(truncated)
+}
+
+fn read_i16(data: &[u8], at: usize) -> Result<i16, String> {
+    read_u16(data, at).map(|value| value as i16)
+}
+
+fn read_u32(data: &[u8], at: usize) -> Result<u32, String> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(truncated)
+}
+
+fn read_i32(data: &[u8], at: usize) -> Result<i32, String> {
+    read_u32(data, at).map(|value| value as i32)
+}
+
+fn truncated() -> String {
+    "Font file is truncated".to_string()
+}
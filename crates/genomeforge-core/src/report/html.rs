@@ -0,0 +1,133 @@
+//! Self-contained HTML rendering of reports
+//!
+//! The page carries its styles inline and a content security policy that
+//! blocks every external resource, so opening a report never makes a
+//! network request that could reveal it was read.
+
+use super::{Block, Fact, Report, Table};
+use std::fmt::Write;
+
+const STYLE: &str = "\
+body{font-family:\"Segoe UI\",Arial,Helvetica,sans-serif;color:#22262e;margin:0;background:#f4f6f8}\
+main{max-width:920px;margin:0 auto;padding:40px 48px;background:#fff}\
+h1{color:#17456f;font-size:28px;margin:0 0 4px}\
+h2{color:#17456f;font-size:20px;border-bottom:1px solid #c7cfd9;padding-bottom:4px;margin:36px 0 12px}\
+h3{font-size:15px;margin:20px 0 8px}\
+.subtitle{color:#6b737f;margin:0 0 16px}\
+.notice{background:#fff8e1;border-left:4px solid #deb859;padding:10px 14px;margin:12px 0;font-size:14px}\
+dl.facts{display:grid;grid-template-columns:max-content 1fr;gap:4px 20px;margin:12px 0}\
+dl.facts dt{color:#6b737f;font-weight:600}\
+dl.facts dd{margin:0}\
+table{border-collapse:collapse;width:100%;margin:12px 0;font-size:13px}\
+th{background:#e0ebf5;text-align:left}\
+th,td{padding:6px 8px;border-bottom:1px solid #c7cfd9;vertical-align:top}\
+tbody tr:nth-child(even){background:#f5f7fa}\
+footer{color:#6b737f;font-size:12px;border-top:1px solid #c7cfd9;margin-top:40px;padding-top:8px}\
+.page-break{break-before:page}\
+@media print{body{background:#fff}main{padding:0}}";
+
+/// Render a report as a standalone HTML document
+pub fn render(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">",
+        escape(report.language.as_deref().unwrap_or("en"))
+    );
+    out.push_str("<meta http-equiv=\"Content-Security-Policy\" content=\"default-src 'none'; style-src 'unsafe-inline'\">\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    let _ = writeln!(out, "<title>{}</title>", escape(&report.title));
+    let _ = writeln!(out, "<style>{}</style>\n</head>\n<body>\n<main>", STYLE);
+
+    let _ = writeln!(out, "<h1>{}</h1>", escape(&report.title));
+    if let Some(subtitle) = &report.subtitle {
+        let _ = writeln!(out, "<p class=\"subtitle\">{}</p>", escape(subtitle));
+    }
+    if !report.details.is_empty() {
+        facts(&mut out, &report.details);
+    }
+
+    for section in &report.sections {
+        let class = if section.new_page {
+            " class=\"page-break\""
+        } else {
+            ""
+        };
+        let _ = writeln!(out, "<section{}>", class);
+        let _ = writeln!(out, "<h2>{}</h2>", escape(&section.title));
+        for block in &section.blocks {
+            match block {
+                Block::Subheading { text } => {
+                    let _ = writeln!(out, "<h3>{}</h3>", escape(text));
+                }
+                Block::Paragraph { text } => {
+                    let _ = writeln!(out, "<p>{}</p>", lines(text));
+                }
+                Block::Notice { text } => {
+                    let _ = writeln!(out, "<div class=\"notice\">{}</div>", lines(text));
+                }
+                Block::Facts { facts: list } => facts(&mut out, list),
+                Block::Table(table) => self::table(&mut out, table),
+            }
+        }
+        out.push_str("</section>\n");
+    }
+
+    if let Some(footer) = &report.footer {
+        let _ = writeln!(out, "<footer>{}</footer>", escape(footer));
+    }
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+/// Escape text for HTML element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+// Helper functions
+
+/// Escaped text with its line breaks kept
+fn lines(text: &str) -> String {
+    escape(text).replace('\n', "<br>\n")
+}
+
+fn facts(out: &mut String, facts: &[Fact]) {
+    out.push_str("<dl class=\"facts\">\n");
+    for fact in facts {
+        let _ = writeln!(
+            out,
+            "<dt>{}</dt><dd>{}</dd>",
+            escape(&fact.label),
+            lines(&fact.value)
+        );
+    }
+    out.push_str("</dl>\n");
+}
+
+fn table(out: &mut String, table: &Table) {
+    out.push_str("<table>\n<thead><tr>");
+    for column in &table.columns {
+        let _ = write!(out, "<th>{}</th>", escape(column));
+    }
+    out.push_str("</tr></thead>\n<tbody>\n");
+    for row in &table.rows {
+        out.push_str("<tr>");
+        for cell in row {
+            let _ = write!(out, "<td>{}</td>", lines(cell));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
@@ -0,0 +1,129 @@
+//! Localization of generated reports
+//!
+//! Applications keep a string catalog per locale: a table of keys and
+//! text, where text may hold `{{name}}` placeholders as templates do. A
+//! [`Translator`] looks keys up in the catalog of its locale and falls back
+//! to the English one for keys it lacks, so a partial translation still
+//! gives a complete report. Numbers are grouped the way the locale writes
+//! them. Text taken from the annotation databases, such as condition names,
+//! is not translated.
+
+use super::template;
+use serde::{Deserialize, Serialize};
+
+/// A string catalog: keys and their text
+pub type Catalog = [(&'static str, &'static str)];
+
+/// Language of a report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+    De,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Es, Locale::De];
+
+    /// BCP 47 language tag, e.g. "de"
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+            Locale::De => "de",
+        }
+    }
+
+    /// The locale of a language tag, ignoring its region, e.g. "es-MX"
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.tag() == language)
+    }
+
+    /// A count with the locale's digit grouping, e.g. "612,043" or "612.043"
+    pub fn number(&self, n: usize) -> String {
+        let separator = match self {
+            Locale::En => ',',
+            Locale::Es | Locale::De => '.',
+        };
+        let digits = n.to_string();
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                out.push(separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// A decimal number with the locale's decimal separator
+    pub fn decimal(&self, value: f64) -> String {
+        let text = value.to_string();
+        match self {
+            Locale::En => text,
+            Locale::Es | Locale::De => text.replace('.', ","),
+        }
+    }
+}
+
+/// Looks up text in the catalog of a locale
+#[derive(Debug, Clone, Copy)]
+pub struct Translator<'a> {
+    pub locale: Locale,
+    catalog: &'a Catalog,
+    fallback: &'a Catalog,
+}
+
+impl<'a> Translator<'a> {
+    /// `fallback` is consulted for keys missing from `catalog`
+    pub fn new(locale: Locale, catalog: &'a Catalog, fallback: &'a Catalog) -> Self {
+        Translator {
+            locale,
+            catalog,
+            fallback,
+        }
+    }
+
+    /// Text of a key in either catalog
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        lookup(self.catalog, key).or_else(|| lookup(self.fallback, key))
+    }
+
+    /// Text of a key; the key itself when neither catalog has it
+    pub fn text(&self, key: &'a str) -> &'a str {
+        self.get(key).unwrap_or(key)
+    }
+
+    /// Text of a key with its placeholders filled in
+    pub fn format(&self, key: &'a str, values: &[(&str, &str)]) -> String {
+        template::fill(self.text(key), values)
+    }
+
+    /// A count grouped for the locale
+    pub fn number(&self, n: usize) -> String {
+        self.locale.number(n)
+    }
+}
+
+/// Keys of `reference` missing from `catalog`
+pub fn missing_keys<'a>(catalog: &Catalog, reference: &'a Catalog) -> Vec<&'a str> {
+    reference
+        .iter()
+        .map(|(key, _)| *key)
+        .filter(|key| lookup(catalog, key).is_none())
+        .collect()
+}
+
+// Helper functions
+
+fn lookup<'a>(catalog: &'a Catalog, key: &str) -> Option<&'a str> {
+    catalog
+        .iter()
+        .find(|(candidate, _)| *candidate == key)
+        .map(|(_, text)| *text)
+}
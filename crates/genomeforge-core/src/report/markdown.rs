@@ -0,0 +1,90 @@
+//! Markdown rendering of reports
+//!
+//! Tables are written in the GitHub-flavored pipe syntax that most editors
+//! and converters understand; notices become block quotes.
+
+use super::{Block, Fact, Report, Table};
+use std::fmt::Write;
+
+/// Render a report as a Markdown document
+pub fn render(report: &Report) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", escape(&report.title));
+    if let Some(subtitle) = &report.subtitle {
+        let _ = writeln!(out, "_{}_\n", escape(subtitle));
+    }
+    if !report.details.is_empty() {
+        facts(&mut out, &report.details);
+    }
+
+    for section in &report.sections {
+        let _ = writeln!(out, "## {}\n", escape(&section.title));
+        for block in &section.blocks {
+            match block {
+                Block::Subheading { text } => {
+                    let _ = writeln!(out, "### {}\n", escape(text));
+                }
+                Block::Paragraph { text } => {
+                    let _ = writeln!(out, "{}\n", lines(text, "\n\n"));
+                }
+                Block::Notice { text } => {
+                    let _ = writeln!(out, "> {}\n", lines(text, "\n>\n> "));
+                }
+                Block::Facts { facts: list } => facts(&mut out, list),
+                Block::Table(table) => self::table(&mut out, table),
+            }
+        }
+    }
+
+    if let Some(footer) = &report.footer {
+        let _ = writeln!(out, "---\n\n{}", escape(footer));
+    }
+    out
+}
+
+/// Escape the characters Markdown gives a meaning to
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+// Helper functions
+
+/// Escaped text with its line breaks replaced by `separator`
+fn lines(text: &str, separator: &str) -> String {
+    text.lines().map(escape).collect::<Vec<_>>().join(separator)
+}
+
+fn facts(out: &mut String, facts: &[Fact]) {
+    for fact in facts {
+        let _ = writeln!(
+            out,
+            "- **{}:** {}",
+            escape(&fact.label),
+            lines(&fact.value, " ")
+        );
+    }
+    out.push('\n');
+}
+
+fn table(out: &mut String, table: &Table) {
+    let row = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| lines(cell, "<br>")).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    out.push_str(&row(&table.columns));
+    let _ = writeln!(out, "|{}", " --- |".repeat(table.columns.len()));
+    for cells in &table.rows {
+        out.push_str(&row(cells));
+    }
+    out.push('\n');
+}
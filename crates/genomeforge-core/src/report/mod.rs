@@ -0,0 +1,152 @@
+//! Format-independent model of a generated report
+//!
+//! Applications describe a report once as titled sections of paragraphs,
+//! notices, label/value facts and tables, and render it with one of the
+//! format writers: [`pdf`], [`html`] or [`markdown`]. The model only holds
+//! display text, so writers need no knowledge of findings or databases.
+//! A [`template`] picks and orders the sections of a report, and [`i18n`]
+//! translates its text.
+//! [`Table`]s are also written on their own as CSV or TSV by
+//! [`delimited`], and the variants behind a report as annotated VCF by
+//! [`vcf`].
+
+pub mod delimited;
+pub mod font;
+pub mod html;
+pub mod i18n;
+pub mod markdown;
+pub mod pdf;
+pub mod template;
+pub mod vcf;
+
+use serde::{Deserialize, Serialize};
+
+/// A complete report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Report {
+    pub title: String,
+    pub subtitle: Option<String>,
+    /// Shown under the title, e.g. who the report is for and when it was
+    /// generated
+    pub details: Vec<Fact>,
+    pub sections: Vec<Section>,
+    /// Repeated at the foot of every page
+    pub footer: Option<String>,
+    /// BCP 47 tag of the text's language; English when missing
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Page numbers of paged formats, with `{{page}}` and `{{pages}}`
+    /// placeholders; "Page {{page}} of {{pages}}" when missing
+    #[serde(default)]
+    pub page_numbers: Option<String>,
+}
+
+/// A labelled value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fact {
+    pub label: String,
+    pub value: String,
+}
+
+impl Fact {
+    pub fn new(label: impl Into<String>, value: impl Into<String>) -> Self {
+        Fact {
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// A titled part of a report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Section {
+    pub title: String,
+    pub blocks: Vec<Block>,
+    /// Start the section on a new page
+    #[serde(default)]
+    pub new_page: bool,
+}
+
+impl Section {
+    pub fn new(title: impl Into<String>) -> Self {
+        Section {
+            title: title.into(),
+            ..Section::default()
+        }
+    }
+
+    pub fn push(&mut self, block: Block) -> &mut Self {
+        self.blocks.push(block);
+        self
+    }
+}
+
+/// Content of a section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Subheading {
+        text: String,
+    },
+    Paragraph {
+        text: String,
+    },
+    /// Text set apart from the rest, such as a caveat
+    Notice {
+        text: String,
+    },
+    Facts {
+        facts: Vec<Fact>,
+    },
+    Table(Table),
+}
+
+/// Rows of text under column headings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Table {
+    pub columns: Vec<String>,
+    /// Each row has one cell per column
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new<S: Into<String>>(columns: impl IntoIterator<Item = S>) -> Self {
+        Table {
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row, padded or cut to the number of columns
+    pub fn push_row<S: Into<String>>(&mut self, cells: impl IntoIterator<Item = S>) {
+        let mut row: Vec<String> = cells.into_iter().map(Into::into).collect();
+        row.resize(self.columns.len(), String::new());
+        self.rows.push(row);
+    }
+}
+
+/// UTC date and time of seconds since the Unix epoch, in RFC 3339 form,
+/// e.g. "2026-10-14T07:10:00Z"
+pub fn timestamp(secs: u64) -> String {
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
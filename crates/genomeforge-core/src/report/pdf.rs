@@ -0,0 +1,886 @@
+//! PDF rendering of reports
+//!
+//! Reports are laid out on A4 pages: a title block, then each section with
+//! its heading, wrapped text, shaded notices and tables whose header row is
+//! repeated on every page they continue on. Every page carries the footer
+//! and a page number. Text is set in embedded TrueType fonts when given,
+//! otherwise in Helvetica.
+//!
+//! With a password the document is encrypted by the standard security
+//! handler with AES-256 (revision 6, as defined by PDF 2.0), so any
+//! current viewer opens it once the password is entered.
+
+use super::font::{self, TrueTypeFont, Widths, FIRST_CHAR};
+use super::{template, Block, Fact, Report, Section, Table};
+use crate::crypto::{self, Key, Zeroizing};
+use aes::cipher::{BlockEncrypt, BlockSizeUser, KeyInit};
+use aes::{Aes128, Aes256, Block as AesBlock};
+use flate2::write::ZlibEncoder;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::io::Write;
+
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 54.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const FOOTER_BASELINE: f32 = 30.0;
+
+/// Page numbering when the report brings none
+const PAGE_NUMBERS: &str = "Page {{page}} of {{pages}}";
+
+const TITLE_SIZE: f32 = 20.0;
+const SUBTITLE_SIZE: f32 = 11.0;
+const HEADING_SIZE: f32 = 14.0;
+const SUBHEADING_SIZE: f32 = 11.0;
+const BODY_SIZE: f32 = 10.0;
+const NOTICE_SIZE: f32 = 9.0;
+const TABLE_SIZE: f32 = 8.5;
+const FOOTER_SIZE: f32 = 7.5;
+
+/// Line height as a multiple of the font size
+const LEADING: f32 = 1.3;
+const CELL_PADDING: f32 = 4.0;
+/// Lines shown of one table cell; longer cells end in an ellipsis
+const MAX_CELL_LINES: usize = 12;
+
+const TEXT: [f32; 3] = [0.13, 0.15, 0.18];
+const MUTED: [f32; 3] = [0.42, 0.45, 0.5];
+const ACCENT: [f32; 3] = [0.09, 0.27, 0.45];
+const HEADER_FILL: [f32; 3] = [0.88, 0.92, 0.96];
+const STRIPE_FILL: [f32; 3] = [0.96, 0.97, 0.98];
+const NOTICE_FILL: [f32; 3] = [1.0, 0.97, 0.88];
+const NOTICE_BORDER: [f32; 3] = [0.87, 0.72, 0.35];
+const RULE: [f32; 3] = [0.78, 0.81, 0.85];
+
+/// Permissions granted once the document is opened: everything
+const PERMISSIONS: i32 = -4;
+
+/// Regular and bold TrueType fonts to embed
+#[derive(Debug, Clone)]
+pub struct Fonts {
+    pub regular: TrueTypeFont,
+    pub bold: TrueTypeFont,
+}
+
+/// How a report is written
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PdfOptions<'a> {
+    /// Fonts to embed; Helvetica is used when not given
+    pub fonts: Option<&'a Fonts>,
+    /// Password needed to open the document
+    pub password: Option<&'a str>,
+}
+
+/// Render a report as a PDF document
+pub fn render(report: &Report, options: &PdfOptions<'_>) -> Result<Vec<u8>, String> {
+    let faces = [
+        Face::new(options.fonts.map(|fonts| &fonts.regular), false),
+        Face::new(options.fonts.map(|fonts| &fonts.bold), true),
+    ];
+    let mut layout = Layout::new(&faces);
+    layout.report(report);
+    let pages = layout.finish(report.footer.as_deref(), report.page_numbers.as_deref());
+
+    let security = options.password.map(Security::new).transpose()?;
+    write_document(report, &faces, &pages, security.as_ref())
+}
+
+// Helper functions
+
+const REGULAR: usize = 0;
+const BOLD: usize = 1;
+
+/// A font as used in the document
+struct Face<'a> {
+    widths: Widths,
+    embedded: Option<&'a TrueTypeFont>,
+    bold: bool,
+}
+
+impl<'a> Face<'a> {
+    fn new(embedded: Option<&'a TrueTypeFont>, bold: bool) -> Self {
+        Face {
+            widths: embedded.map_or_else(|| font::helvetica_widths(bold), |font| font.widths),
+            embedded,
+            bold,
+        }
+    }
+}
+
+/// Places report content on pages, top to bottom
+struct Layout<'a> {
+    faces: &'a [Face<'a>; 2],
+    pages: Vec<Vec<u8>>,
+    /// Top of the free space on the current page
+    y: f32,
+}
+
+impl<'a> Layout<'a> {
+    fn new(faces: &'a [Face<'a>; 2]) -> Self {
+        Layout {
+            faces,
+            pages: Vec::new(),
+            y: 0.0,
+        }
+    }
+
+    fn report(&mut self, report: &Report) {
+        self.new_page();
+        self.lines(
+            &report.title,
+            BOLD,
+            TITLE_SIZE,
+            ACCENT,
+            MARGIN,
+            CONTENT_WIDTH,
+        );
+        if let Some(subtitle) = &report.subtitle {
+            self.y -= 2.0;
+            self.lines(
+                subtitle,
+                REGULAR,
+                SUBTITLE_SIZE,
+                MUTED,
+                MARGIN,
+                CONTENT_WIDTH,
+            );
+        }
+        self.y -= 10.0;
+        if !report.details.is_empty() {
+            self.facts(&report.details);
+        }
+        self.rule(1.5, ACCENT);
+        self.y -= 14.0;
+
+        for section in &report.sections {
+            self.section(section);
+        }
+    }
+
+    fn section(&mut self, section: &Section) {
+        if section.new_page && !self.at_top() {
+            self.new_page();
+        }
+        // Keep a heading with the start of what follows it
+        self.ensure(HEADING_SIZE * LEADING + 3.0 * BODY_SIZE * LEADING);
+        self.lines(
+            &section.title,
+            BOLD,
+            HEADING_SIZE,
+            ACCENT,
+            MARGIN,
+            CONTENT_WIDTH,
+        );
+        self.y -= 2.0;
+        self.rule(0.6, RULE);
+        self.y -= 8.0;
+
+        for block in &section.blocks {
+            match block {
+                Block::Subheading { text } => {
+                    self.ensure(SUBHEADING_SIZE * LEADING + 2.0 * BODY_SIZE * LEADING);
+                    self.y -= 2.0;
+                    self.lines(text, BOLD, SUBHEADING_SIZE, TEXT, MARGIN, CONTENT_WIDTH);
+                    self.y -= 3.0;
+                }
+                Block::Paragraph { text } => {
+                    self.lines(text, REGULAR, BODY_SIZE, TEXT, MARGIN, CONTENT_WIDTH);
+                    self.y -= 6.0;
+                }
+                Block::Notice { text } => self.notice(text),
+                Block::Facts { facts } => self.facts(facts),
+                Block::Table(table) => self.table(table),
+            }
+        }
+        self.y -= 10.0;
+    }
+
+    /// Wrapped text, continued on new pages as needed
+    fn lines(&mut self, text: &str, face: usize, size: f32, color: [f32; 3], x: f32, width: f32) {
+        let height = size * LEADING;
+        for line in self.wrap(text, face, size, width) {
+            self.ensure(height);
+            self.text(x, self.y - size, face, size, color, &line);
+            self.y -= height;
+        }
+    }
+
+    fn notice(&mut self, text: &str) {
+        let height = NOTICE_SIZE * LEADING;
+        let padding = 7.0;
+        let lines = self.wrap(text, REGULAR, NOTICE_SIZE, CONTENT_WIDTH - 2.0 * padding);
+        let mut remaining = &lines[..];
+        while !remaining.is_empty() {
+            self.ensure(height + 2.0 * padding);
+            let fits = (((self.y - MARGIN - 2.0 * padding) / height) as usize).max(1);
+            let (chunk, rest) = remaining.split_at(fits.min(remaining.len()));
+            let box_height = chunk.len() as f32 * height + 2.0 * padding;
+            let bottom = self.y - box_height;
+            self.fill(NOTICE_FILL, MARGIN, bottom, CONTENT_WIDTH, box_height);
+            self.fill(NOTICE_BORDER, MARGIN, bottom, 2.5, box_height);
+            let mut y = self.y - padding;
+            for line in chunk {
+                self.text(
+                    MARGIN + padding,
+                    y - NOTICE_SIZE,
+                    REGULAR,
+                    NOTICE_SIZE,
+                    TEXT,
+                    line,
+                );
+                y -= height;
+            }
+            self.y = bottom;
+            remaining = rest;
+        }
+        self.y -= 8.0;
+    }
+
+    fn facts(&mut self, facts: &[Fact]) {
+        let size = BODY_SIZE;
+        let height = size * LEADING;
+        let label_width = facts
+            .iter()
+            .map(|fact| self.width(&fact.label, BOLD, size) + 12.0)
+            .fold(0.0, f32::max)
+            .min(CONTENT_WIDTH * 0.4);
+        let value_width = CONTENT_WIDTH - label_width;
+
+        for fact in facts {
+            let labels = self.wrap(&fact.label, BOLD, size, label_width - 12.0);
+            let values = self.wrap(&fact.value, REGULAR, size, value_width);
+            let rows = labels.len().max(values.len());
+            self.ensure(rows as f32 * height);
+            for (index, line) in labels.iter().enumerate() {
+                let baseline = self.y - size - index as f32 * height;
+                self.text(MARGIN, baseline, BOLD, size, MUTED, line);
+            }
+            for (index, line) in values.iter().enumerate() {
+                let baseline = self.y - size - index as f32 * height;
+                self.text(MARGIN + label_width, baseline, REGULAR, size, TEXT, line);
+            }
+            self.y -= rows as f32 * height + 2.0;
+        }
+        self.y -= 6.0;
+    }
+
+    fn table(&mut self, table: &Table) {
+        if table.columns.is_empty() {
+            return;
+        }
+        let widths = self.column_widths(table);
+        let header = self.cells(&table.columns, &widths, BOLD);
+        self.ensure(row_height(&header) + TABLE_SIZE * LEADING + 2.0 * CELL_PADDING);
+        self.table_row(&header, &widths, BOLD, Some(HEADER_FILL));
+
+        for (index, row) in table.rows.iter().enumerate() {
+            let cells = self.cells(row, &widths, REGULAR);
+            if self.y - row_height(&cells) < MARGIN {
+                self.new_page();
+                self.table_row(&header, &widths, BOLD, Some(HEADER_FILL));
+            }
+            let fill = (index % 2 == 1).then_some(STRIPE_FILL);
+            self.table_row(&cells, &widths, REGULAR, fill);
+        }
+        self.y -= 10.0;
+    }
+
+    /// Wrapped lines of each cell of a row
+    fn cells(&self, row: &[String], widths: &[f32], face: usize) -> Vec<Vec<Vec<u8>>> {
+        row.iter()
+            .zip(widths)
+            .map(|(cell, width)| {
+                let mut lines = self.wrap(cell, face, TABLE_SIZE, width - 2.0 * CELL_PADDING);
+                if lines.len() > MAX_CELL_LINES {
+                    lines.truncate(MAX_CELL_LINES);
+                    lines[MAX_CELL_LINES - 1].extend_from_slice(&font::encode("…"));
+                }
+                lines
+            })
+            .collect()
+    }
+
+    fn table_row(
+        &mut self,
+        cells: &[Vec<Vec<u8>>],
+        widths: &[f32],
+        face: usize,
+        fill: Option<[f32; 3]>,
+    ) {
+        let height = row_height(cells);
+        let bottom = self.y - height;
+        if let Some(fill) = fill {
+            self.fill(fill, MARGIN, bottom, CONTENT_WIDTH, height);
+        }
+        let mut x = MARGIN;
+        for (lines, width) in cells.iter().zip(widths) {
+            let mut baseline = self.y - CELL_PADDING - TABLE_SIZE;
+            for line in lines {
+                self.text(x + CELL_PADDING, baseline, face, TABLE_SIZE, TEXT, line);
+                baseline -= TABLE_SIZE * LEADING;
+            }
+            x += width;
+        }
+        self.fill(RULE, MARGIN, bottom, CONTENT_WIDTH, 0.4);
+        self.y = bottom;
+    }
+
+    /// Widths that give each column room in proportion to its content
+    fn column_widths(&self, table: &Table) -> Vec<f32> {
+        let padding = 2.0 * CELL_PADDING;
+        let cells = |column: usize| {
+            std::iter::once((&table.columns[column], BOLD)).chain(
+                table
+                    .rows
+                    .iter()
+                    .filter_map(move |row| row.get(column))
+                    .map(|cell| (cell, REGULAR)),
+            )
+        };
+        let mut natural = Vec::new();
+        let mut minimum = Vec::new();
+        for column in 0..table.columns.len() {
+            let mut widest = 0.0f32;
+            let mut longest_word = 0.0f32;
+            for (cell, face) in cells(column) {
+                widest = widest.max(self.width(cell, face, TABLE_SIZE));
+                for word in cell.split_whitespace() {
+                    longest_word = longest_word.max(self.width(word, face, TABLE_SIZE));
+                }
+            }
+            let cap = CONTENT_WIDTH * 0.5;
+            natural.push((widest + padding).min(cap));
+            minimum.push((longest_word.max(24.0) + padding).min(cap / 2.0));
+        }
+
+        let total: f32 = natural.iter().sum();
+        if total <= CONTENT_WIDTH {
+            return natural
+                .iter()
+                .map(|width| width * CONTENT_WIDTH / total)
+                .collect();
+        }
+        let least: f32 = minimum.iter().sum();
+        if least >= CONTENT_WIDTH {
+            return minimum
+                .iter()
+                .map(|width| width * CONTENT_WIDTH / least)
+                .collect();
+        }
+        // Share out what the minimums leave by how much more each wants
+        let spare = CONTENT_WIDTH - least;
+        let wanted: f32 = natural.iter().zip(&minimum).map(|(n, m)| n - m).sum();
+        natural
+            .iter()
+            .zip(&minimum)
+            .map(|(n, m)| m + (n - m) * spare / wanted)
+            .collect()
+    }
+
+    /// Break text into lines no wider than `width`
+    ///
+    /// Line breaks in the text are kept, and words too long for a line are
+    /// split.
+    fn wrap(&self, text: &str, face: usize, size: f32, width: f32) -> Vec<Vec<u8>> {
+        let widths = &self.faces[face].widths;
+        let measure = |text: &[u8]| font::text_width(widths, text, size);
+        let space = measure(b" ");
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let encoded = font::encode(paragraph);
+            let mut line: Vec<u8> = Vec::new();
+            let mut line_width = 0.0;
+            for mut word in encoded
+                .split(|&b| b == b' ')
+                .filter(|word| !word.is_empty())
+            {
+                let mut word_width = measure(word);
+                if !line.is_empty() {
+                    if line_width + space + word_width <= width {
+                        line.push(b' ');
+                        line.extend_from_slice(word);
+                        line_width += space + word_width;
+                        continue;
+                    }
+                    lines.push(std::mem::take(&mut line));
+                }
+                while word_width > width && word.len() > 1 {
+                    let mut end = 1;
+                    while end < word.len() && measure(&word[..end + 1]) <= width {
+                        end += 1;
+                    }
+                    lines.push(word[..end].to_vec());
+                    word = &word[end..];
+                    word_width = measure(word);
+                }
+                line = word.to_vec();
+                line_width = word_width;
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn width(&self, text: &str, face: usize, size: f32) -> f32 {
+        font::text_width(&self.faces[face].widths, &font::encode(text), size)
+    }
+
+    fn at_top(&self) -> bool {
+        self.y >= PAGE_HEIGHT - MARGIN
+    }
+
+    /// Start a new page unless `height` fits on this one
+    fn ensure(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.at_top() {
+            self.new_page();
+        }
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn rule(&mut self, thickness: f32, color: [f32; 3]) {
+        self.fill(color, MARGIN, self.y - thickness, CONTENT_WIDTH, thickness);
+        self.y -= thickness;
+    }
+
+    fn text(
+        &mut self,
+        x: f32,
+        baseline: f32,
+        face: usize,
+        size: f32,
+        color: [f32; 3],
+        text: &[u8],
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        let page = self.pages.last_mut().expect("a page is started first");
+        let [r, g, b] = color;
+        let _ = write!(
+            page,
+            "BT {:.3} {:.3} {:.3} rg /F{} {:.2} Tf {:.2} {:.2} Td ",
+            r,
+            g,
+            b,
+            face + 1,
+            size,
+            x,
+            baseline
+        );
+        literal(page, text);
+        page.extend_from_slice(b" Tj ET\n");
+    }
+
+    fn fill(&mut self, color: [f32; 3], x: f32, y: f32, width: f32, height: f32) {
+        let page = self.pages.last_mut().expect("a page is started first");
+        let [r, g, b] = color;
+        let _ = writeln!(
+            page,
+            "{:.3} {:.3} {:.3} rg {:.2} {:.2} {:.2} {:.2} re f",
+            r, g, b, x, y, width, height
+        );
+    }
+
+    /// The pages, each with the footer and its number added
+    fn finish(mut self, footer: Option<&str>, page_numbers: Option<&str>) -> Vec<Vec<u8>> {
+        let count = self.pages.len().to_string();
+        let numbers: Vec<Vec<u8>> = (1..=self.pages.len())
+            .map(|number| {
+                let number = number.to_string();
+                let values = [("page", number.as_str()), ("pages", count.as_str())];
+                font::encode(&template::fill(
+                    page_numbers.unwrap_or(PAGE_NUMBERS),
+                    &values,
+                ))
+            })
+            .collect();
+        let footer = footer
+            .and_then(|footer| {
+                self.wrap(footer, REGULAR, FOOTER_SIZE, CONTENT_WIDTH - 70.0)
+                    .into_iter()
+                    .next()
+            })
+            .unwrap_or_default();
+
+        let mut pages = std::mem::take(&mut self.pages);
+        for (page, number) in pages.iter_mut().zip(&numbers) {
+            self.pages.push(std::mem::take(page));
+            self.fill(RULE, MARGIN, FOOTER_BASELINE + 10.0, CONTENT_WIDTH, 0.5);
+            self.text(
+                MARGIN,
+                FOOTER_BASELINE,
+                REGULAR,
+                FOOTER_SIZE,
+                MUTED,
+                &footer,
+            );
+            let width = font::text_width(&self.faces[REGULAR].widths, number, FOOTER_SIZE);
+            let x = PAGE_WIDTH - MARGIN - width;
+            self.text(x, FOOTER_BASELINE, REGULAR, FOOTER_SIZE, MUTED, number);
+            *page = self.pages.pop().unwrap_or_default();
+        }
+        pages
+    }
+}
+
+fn row_height(cells: &[Vec<Vec<u8>>]) -> f32 {
+    let lines = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+    lines as f32 * TABLE_SIZE * LEADING + 2.0 * CELL_PADDING
+}
+
+/// Append text as a PDF literal string
+fn literal(out: &mut Vec<u8>, text: &[u8]) {
+    out.push(b'(');
+    for &byte in text {
+        if matches!(byte, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(byte);
+    }
+    out.push(b')');
+}
+
+fn compress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("Failed to compress PDF stream: {}", e))
+}
+
+/// Serializes numbered objects and the cross-reference table
+struct Writer<'a> {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+    security: Option<&'a Security>,
+}
+
+impl Writer<'_> {
+    fn begin(&mut self, id: usize) {
+        if self.offsets.len() < id {
+            self.offsets.resize(id, 0);
+        }
+        self.offsets[id - 1] = self.out.len();
+        let _ = writeln!(self.out, "{} 0 obj", id);
+    }
+
+    fn object(&mut self, id: usize, body: &[u8]) {
+        self.begin(id);
+        self.out.extend_from_slice(body);
+        self.out.extend_from_slice(b"\nendobj\n");
+    }
+
+    /// A stream, compressed and, when securing the document, encrypted
+    fn stream(&mut self, id: usize, entries: &str, data: &[u8]) -> Result<(), String> {
+        let mut data = compress(data)?;
+        if let Some(security) = self.security {
+            data = security.encrypt(&data);
+        }
+        self.begin(id);
+        let _ = write!(
+            self.out,
+            "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+            entries,
+            data.len()
+        );
+        self.out.extend_from_slice(&data);
+        self.out.extend_from_slice(b"\nendstream\nendobj\n");
+        Ok(())
+    }
+
+    /// A text string, in UTF-16 unless plain ASCII
+    fn string(&self, text: &str) -> Vec<u8> {
+        let bytes: Vec<u8> = if text.is_ascii() {
+            text.bytes().filter(|b| !b.is_ascii_control()).collect()
+        } else {
+            [0xFE, 0xFF]
+                .into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect()
+        };
+        match self.security {
+            Some(security) => format!("<{}>", hex::encode(security.encrypt(&bytes))).into_bytes(),
+            None => {
+                let mut out = Vec::new();
+                literal(&mut out, &bytes);
+                out
+            }
+        }
+    }
+}
+
+fn write_document(
+    report: &Report,
+    faces: &[Face<'_>; 2],
+    pages: &[Vec<u8>],
+    security: Option<&Security>,
+) -> Result<Vec<u8>, String> {
+    const CATALOG: usize = 1;
+    const PAGES: usize = 2;
+    const INFO: usize = 3;
+    const FONTS: usize = 4;
+
+    let mut writer = Writer {
+        out: b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+        offsets: Vec::new(),
+        security,
+    };
+    let mut next = FONTS + faces.len();
+
+    let mut catalog = format!("<< /Type /Catalog /Pages {} 0 R", PAGES);
+    if security.is_some() {
+        // AES-256 revision 6 came to PDF 1.7 as Adobe extension level 8
+        catalog.push_str(" /Extensions << /ADBE << /BaseVersion /1.7 /ExtensionLevel 8 >> >>");
+    }
+    catalog.push_str(" >>");
+    writer.object(CATALOG, catalog.as_bytes());
+
+    let mut info = b"<< /Title ".to_vec();
+    info.extend(writer.string(&report.title));
+    info.extend_from_slice(b" /Producer ");
+    info.extend(writer.string("GenomeForge"));
+    info.extend_from_slice(b" >>");
+    writer.object(INFO, &info);
+
+    for (index, face) in faces.iter().enumerate() {
+        let id = FONTS + index;
+        match face.embedded {
+            None => {
+                let name = if face.bold {
+                    "Helvetica-Bold"
+                } else {
+                    "Helvetica"
+                };
+                let body = format!(
+                    "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                    name
+                );
+                writer.object(id, body.as_bytes());
+            }
+            Some(embedded) => {
+                let (descriptor, file) = (next, next + 1);
+                next += 2;
+                let widths: Vec<String> = face.widths.iter().map(u16::to_string).collect();
+                let body = format!(
+                    "<< /Type /Font /Subtype /TrueType /BaseFont /{} /FirstChar {} /LastChar 255 /Widths [{}] /FontDescriptor {} 0 R /Encoding /WinAnsiEncoding >>",
+                    embedded.name,
+                    FIRST_CHAR,
+                    widths.join(" "),
+                    descriptor
+                );
+                writer.object(id, body.as_bytes());
+                let [x0, y0, x1, y1] = embedded.bbox;
+                let body = format!(
+                    "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [{} {} {} {}] /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV {} /FontFile2 {} 0 R >>",
+                    embedded.name,
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    embedded.italic_angle,
+                    embedded.ascent,
+                    embedded.descent,
+                    embedded.cap_height,
+                    if embedded.bold { 120 } else { 80 },
+                    file
+                );
+                writer.object(descriptor, body.as_bytes());
+                let entries = format!("/Length1 {}", embedded.data.len());
+                writer.stream(file, &entries, &embedded.data)?;
+            }
+        }
+    }
+
+    let encrypt = security.map(|security| {
+        let id = next;
+        next += 1;
+        writer.object(id, security.dictionary.as_bytes());
+        id
+    });
+
+    let mut kids = Vec::with_capacity(pages.len());
+    for content in pages {
+        let (page, contents) = (next, next + 1);
+        next += 2;
+        kids.push(format!("{} 0 R", page));
+        let body = format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 {} 0 R /F2 {} 0 R >> >> /Contents {} 0 R >>",
+            PAGES,
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            FONTS + REGULAR,
+            FONTS + BOLD,
+            contents
+        );
+        writer.object(page, body.as_bytes());
+        writer.stream(contents, "", content)?;
+    }
+    let body = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.join(" "),
+        kids.len()
+    );
+    writer.object(PAGES, body.as_bytes());
+
+    let xref = writer.out.len();
+    let mut out = writer.out;
+    let _ = write!(
+        out,
+        "xref\n0 {}\n0000000000 65535 f \n",
+        writer.offsets.len() + 1
+    );
+    for offset in &writer.offsets {
+        let _ = writeln!(out, "{:010} 00000 n ", offset);
+    }
+    let id = hex::encode(crypto::random_bytes::<16>());
+    let _ = write!(
+        out,
+        "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R /ID [<{}> <{}>]",
+        writer.offsets.len() + 1,
+        CATALOG,
+        INFO,
+        id,
+        id
+    );
+    if let Some(encrypt) = encrypt {
+        let _ = write!(out, " /Encrypt {} 0 R", encrypt);
+    }
+    let _ = write!(out, " >>\nstartxref\n{}\n%%EOF\n", xref);
+    Ok(out)
+}
+
+/// Standard security handler, AES-256 revision 6
+struct Security {
+    key: Key,
+    dictionary: String,
+}
+
+impl Security {
+    fn new(password: &str) -> Result<Self, String> {
+        if password.is_empty() {
+            return Err("Password must not be empty".to_string());
+        }
+        let key = Key::generate();
+        // Passwords are limited to 127 bytes of UTF-8
+        let user = truncate(password.as_bytes());
+        // No one knows the owner password, so no one can lift the
+        // protection without the document password
+        let owner: Zeroizing<[u8; 32]> = Zeroizing::new(crypto::random_bytes());
+
+        let (u, ue) = password_entries(user, &key, &[]);
+        let (o, oe) = password_entries(&owner[..], &key, &u);
+
+        let mut perms = [0u8; 16];
+        perms[..4].copy_from_slice(&PERMISSIONS.to_le_bytes());
+        perms[4..8].fill(0xFF);
+        perms[8..12].copy_from_slice(b"Tadb");
+        perms[12..].copy_from_slice(&crypto::random_bytes::<4>());
+        let mut block = AesBlock::from(perms);
+        Aes256::new(key.as_bytes().into()).encrypt_block(&mut block);
+
+        let dictionary = format!(
+            "<< /Filter /Standard /V 5 /R 6 /Length 256 /CF << /StdCF << /AuthEvent /DocOpen /CFM /AESV3 /Length 32 >> >> /StmF /StdCF /StrF /StdCF /O <{}> /U <{}> /OE <{}> /UE <{}> /P {} /Perms <{}> /EncryptMetadata true >>",
+            hex::encode(o),
+            hex::encode(u),
+            hex::encode(oe),
+            hex::encode(ue),
+            PERMISSIONS,
+            hex::encode(block)
+        );
+        Ok(Security { key, dictionary })
+    }
+
+    /// AES-256-CBC with a random IV in front and PKCS#7 padding
+    fn encrypt(&self, data: &[u8]) -> Vec<u8> {
+        let iv: [u8; 16] = crypto::random_bytes();
+        let pad = 16 - data.len() % 16;
+        let mut padded = Vec::with_capacity(data.len() + pad);
+        padded.extend_from_slice(data);
+        padded.resize(data.len() + pad, pad as u8);
+        let cipher = Aes256::new(self.key.as_bytes().into());
+        let mut out = iv.to_vec();
+        out.extend(cbc_encrypt(&cipher, &iv, &padded));
+        out
+    }
+}
+
+fn truncate(password: &[u8]) -> &[u8] {
+    &password[..password.len().min(127)]
+}
+
+/// Validation entry (U or O) and wrapped file key (UE or OE) of a password
+fn password_entries(password: &[u8], key: &Key, user_entry: &[u8]) -> ([u8; 48], [u8; 32]) {
+    let validation_salt: [u8; 8] = crypto::random_bytes();
+    let key_salt: [u8; 8] = crypto::random_bytes();
+
+    let mut entry = [0u8; 48];
+    entry[..32].copy_from_slice(&hardened_hash(password, &validation_salt, user_entry)[..]);
+    entry[32..40].copy_from_slice(&validation_salt);
+    entry[40..].copy_from_slice(&key_salt);
+
+    let intermediate = hardened_hash(password, &key_salt, user_entry);
+    let cipher = Aes256::new((&*intermediate).into());
+    let wrapped = cbc_encrypt(&cipher, &[0; 16], key.as_bytes());
+    (entry, wrapped.try_into().expect("a key is two blocks"))
+}
+
+/// The revision 6 password hash (ISO 32000-2, algorithm 2.B)
+fn hardened_hash(password: &[u8], salt: &[u8], user_entry: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut k = Zeroizing::new(
+        Sha256::new()
+            .chain_update(password)
+            .chain_update(salt)
+            .chain_update(user_entry)
+            .finalize()
+            .to_vec(),
+    );
+    let mut round = 0u32;
+    loop {
+        let block_len = password.len() + k.len() + user_entry.len();
+        let mut k1 = Zeroizing::new(Vec::with_capacity(block_len * 64));
+        for _ in 0..64 {
+            k1.extend_from_slice(password);
+            k1.extend_from_slice(&k);
+            k1.extend_from_slice(user_entry);
+        }
+        let cipher = Aes128::new(k[..16].into());
+        let e = Zeroizing::new(cbc_encrypt(&cipher, &k[16..32], &k1));
+        // The first 16 bytes as a number modulo 3, which is their sum's
+        let hash = e[..16].iter().map(|&b| b as u32).sum::<u32>() % 3;
+        *k = match hash {
+            0 => Sha256::digest(&*e).to_vec(),
+            1 => Sha384::digest(&*e).to_vec(),
+            _ => Sha512::digest(&*e).to_vec(),
+        };
+        round += 1;
+        if round >= 64 && e[e.len() - 1] as u32 <= round - 32 {
+            break;
+        }
+    }
+    let mut hash = Zeroizing::new([0u8; 32]);
+    hash.copy_from_slice(&k[..32]);
+    hash
+}
+
+/// CBC encryption of whole blocks, without padding
+fn cbc_encrypt<C>(cipher: &C, iv: &[u8], data: &[u8]) -> Vec<u8>
+where
+    C: BlockEncrypt + BlockSizeUser<BlockSize = aes::cipher::consts::U16>,
+{
+    let mut previous = AesBlock::clone_from_slice(iv);
+    let mut out = Vec::with_capacity(data.len());
+    for chunk in data.chunks_exact(16) {
+        let mut block = AesBlock::clone_from_slice(chunk);
+        for (byte, prior) in block.iter_mut().zip(previous.iter()) {
+            *byte ^= prior;
+        }
+        cipher.encrypt_block(&mut block);
+        out.extend_from_slice(&block);
+        previous = block;
+    }
+    out
+}
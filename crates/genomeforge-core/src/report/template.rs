@@ -0,0 +1,232 @@
+//! Report templates
+//!
+//! A template decides which sections a report holds and in which order,
+//! and may retitle them, start them on a new page or open them with an
+//! introduction. Templates are JSON files, so users can write their own
+//! without touching code. Sections are named by ids the application
+//! defines; text may use `{{name}}` placeholders that are filled in from
+//! values the application provides, such as the report date. Text is
+//! either a plain string or an object of translations by language tag,
+//! e.g. `{"en": "Medication response", "de": "Arzneimittelwirkung"}`.
+
+use super::i18n::Locale;
+use super::{Block, Report, Section};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension of template files
+pub const EXTENSION: &str = "json";
+
+/// A report layout
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportTemplate {
+    /// Lowercase letters, digits and `-`, e.g. "clinical-summary"
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Replaces the report's title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Text>,
+    /// Replaces the report's subtitle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<Text>,
+    pub sections: Vec<TemplateSection>,
+}
+
+/// A section of a template
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSection {
+    /// Which of the application's sections to include
+    pub id: String,
+    /// Replaces the section's title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Text>,
+    /// Paragraph shown before the section's own content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub introduction: Option<Text>,
+    /// Overrides whether the section starts on a new page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_page: Option<bool>,
+}
+
+impl ReportTemplate {
+    /// Parse a template from its JSON form
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid report template: {}", e))
+    }
+
+    /// Check the template only uses the section ids in `known`
+    pub fn validate(&self, known: &[&str]) -> Result<(), String> {
+        if !valid_id(&self.id) {
+            return Err(format!(
+                "Template id must be lowercase letters, digits and '-': {:?}",
+                self.id
+            ));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Template {} has no name", self.id));
+        }
+        if self.sections.is_empty() {
+            return Err(format!("Template {} has no sections", self.id));
+        }
+        for (index, section) in self.sections.iter().enumerate() {
+            if !known.contains(&section.id.as_str()) {
+                return Err(format!(
+                    "Template {} has an unknown section: {}",
+                    self.id, section.id
+                ));
+            }
+            if self.sections[..index].iter().any(|s| s.id == section.id) {
+                return Err(format!(
+                    "Template {} lists section {} twice",
+                    self.id, section.id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lay out `report` by the template
+    ///
+    /// `build` returns the sections for an id, which may be none when there
+    /// is nothing to report; `values` fill in the placeholders of the text
+    /// in `locale`.
+    pub fn apply<F>(
+        &self,
+        mut report: Report,
+        locale: Locale,
+        values: &[(&str, &str)],
+        mut build: F,
+    ) -> Report
+    where
+        F: FnMut(&str) -> Vec<Section>,
+    {
+        let localize = |text: &Text| fill(text.get(locale), values);
+        if let Some(title) = &self.title {
+            report.title = localize(title);
+        }
+        if let Some(subtitle) = &self.subtitle {
+            report.subtitle = Some(localize(subtitle));
+        }
+        report.sections.clear();
+        for layout in &self.sections {
+            let mut sections = build(&layout.id);
+            if let Some(first) = sections.first_mut() {
+                if let Some(title) = &layout.title {
+                    first.title = localize(title);
+                }
+                if let Some(new_page) = layout.new_page {
+                    first.new_page = new_page;
+                }
+                if let Some(introduction) = &layout.introduction {
+                    first.blocks.insert(
+                        0,
+                        Block::Paragraph {
+                            text: localize(introduction),
+                        },
+                    );
+                }
+            }
+            report.sections.extend(sections);
+        }
+        report
+    }
+}
+
+/// Template text, plain or translated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Text {
+    Plain(String),
+    /// Translations by language tag
+    Localized(BTreeMap<String, String>),
+}
+
+impl Text {
+    /// The text in `locale`, else in English, else in any language
+    pub fn get(&self, locale: Locale) -> &str {
+        match self {
+            Text::Plain(text) => text,
+            Text::Localized(translations) => translations
+                .get(locale.tag())
+                .or_else(|| translations.get(Locale::En.tag()))
+                .or_else(|| translations.values().next())
+                .map_or("", String::as_str),
+        }
+    }
+}
+
+/// Replace `{{name}}` placeholders with their values
+///
+/// Unknown placeholders are left as they are, so a typo shows in the
+/// report instead of silently disappearing.
+pub fn fill(text: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let name = after[..end].trim();
+        match values.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Templates saved in `dir`, sorted by name; a missing directory has none
+pub fn load_dir(dir: &Path) -> Result<Vec<ReportTemplate>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let template =
+            ReportTemplate::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        templates.push(template);
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Save a template to `dir` as `<id>.json`
+pub fn save(dir: &Path, template: &ReportTemplate) -> Result<PathBuf, String> {
+    if !valid_id(&template.id) {
+        return Err(format!("Invalid template id: {:?}", template.id));
+    }
+    let json = serde_json::to_string_pretty(template)
+        .map_err(|e| format!("Failed to serialize template: {}", e))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.{}", template.id, EXTENSION));
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+// Helper functions
+
+/// Also a safe file name
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
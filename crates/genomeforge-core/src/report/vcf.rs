@@ -0,0 +1,306 @@
+//! Annotated VCF output of a genome
+//!
+//! Variants are written back out as a single-sample VCF 4.3 file, each
+//! carrying the ClinVar, PharmGKB and GWAS Catalog annotations found for
+//! it as INFO tags, so the data can be taken to other tools. Tags are named
+//! after their ClinVar counterparts where one exists. Values follow the
+//! ClinVar conventions too: spaces become underscores, several values of a
+//! tag are separated by commas, and the remaining reserved characters are
+//! percent-encoded.
+//!
+//! A VCF record needs a reference allele, so array calls are only written
+//! once their alleles have been resolved, e.g. from dbSNP; D/I indel calls
+//! and sites without a known reference are skipped and counted.
+
+use crate::genome::{GenomeBuild, Genotype, Variant};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// An INFO tag definition
+#[derive(Debug, Clone, Copy)]
+pub struct InfoField {
+    pub id: &'static str,
+    pub number: &'static str,
+    pub kind: &'static str,
+    pub description: &'static str,
+}
+
+/// INFO tags an annotated VCF may carry
+pub const INFO_FIELDS: [InfoField; 12] = [
+    InfoField {
+        id: "GENE",
+        number: ".",
+        kind: "String",
+        description: "Gene symbols",
+    },
+    InfoField {
+        id: "CLNSIG",
+        number: ".",
+        kind: "String",
+        description: "ClinVar clinical significance",
+    },
+    InfoField {
+        id: "CLNREVSTAT",
+        number: ".",
+        kind: "String",
+        description: "ClinVar review status",
+    },
+    InfoField {
+        id: "CLNDN",
+        number: ".",
+        kind: "String",
+        description: "ClinVar conditions, separated by |",
+    },
+    InfoField {
+        id: "CLNVID",
+        number: ".",
+        kind: "Integer",
+        description: "ClinVar variation ID",
+    },
+    InfoField {
+        id: "PGKB_DRUG",
+        number: ".",
+        kind: "String",
+        description: "Drugs with a PharmGKB clinical annotation",
+    },
+    InfoField {
+        id: "PGKB_LEVEL",
+        number: ".",
+        kind: "String",
+        description: "PharmGKB level of evidence, one per PGKB_DRUG",
+    },
+    InfoField {
+        id: "PGKB_ID",
+        number: ".",
+        kind: "String",
+        description: "PharmGKB clinical annotation ID, one per PGKB_DRUG",
+    },
+    InfoField {
+        id: "PGX_PHENOTYPE",
+        number: ".",
+        kind: "String",
+        description: "CPIC phenotype of the gene's diplotype",
+    },
+    InfoField {
+        id: "GWAS_TRAIT",
+        number: ".",
+        kind: "String",
+        description: "GWAS Catalog traits",
+    },
+    InfoField {
+        id: "GWAS_RISK_ALLELE",
+        number: ".",
+        kind: "String",
+        description: "GWAS Catalog risk allele, one per GWAS_TRAIT",
+    },
+    InfoField {
+        id: "GWAS_PVALUE",
+        number: ".",
+        kind: "Float",
+        description: "GWAS Catalog p-value, one per GWAS_TRAIT",
+    },
+];
+
+/// Annotations of one variant
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Annotation {
+    /// Values of each tag in the order they were added
+    pub info: Vec<(&'static str, Vec<String>)>,
+}
+
+impl Annotation {
+    /// Append a value to a tag
+    pub fn add(&mut self, id: &'static str, value: impl Into<String>) -> &mut Self {
+        let value = value.into();
+        match self.info.iter_mut().find(|(existing, _)| *existing == id) {
+            Some((_, values)) => values.push(value),
+            None => self.info.push((id, vec![value])),
+        }
+        self
+    }
+}
+
+/// What goes into the header
+#[derive(Debug, Clone, Default)]
+pub struct VcfOptions<'a> {
+    pub sample: &'a str,
+    pub genome_build: Option<GenomeBuild>,
+    /// Seconds since the Unix epoch, for `##fileDate`
+    pub created: u64,
+    /// Program written to `##source`
+    pub source: &'a str,
+    /// Only write variants that have an annotation
+    pub annotated_only: bool,
+}
+
+/// What [`write`] wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VcfStats {
+    pub written: usize,
+    pub annotated: usize,
+    /// Variants without a reference allele or with calls a VCF cannot hold
+    pub skipped: usize,
+}
+
+/// Write `variants` to `out`, annotated by rsid
+pub fn write<W: Write>(
+    mut out: W,
+    variants: &[Variant],
+    annotations: &HashMap<String, Annotation>,
+    options: &VcfOptions<'_>,
+) -> Result<VcfStats, String> {
+    let io = |e: std::io::Error| format!("Failed to write VCF: {}", e);
+    for annotation in annotations.values() {
+        if let Some((id, _)) = annotation
+            .info
+            .iter()
+            .find(|(id, _)| !INFO_FIELDS.iter().any(|field| field.id == *id))
+        {
+            return Err(format!("Undeclared INFO tag: {}", id));
+        }
+    }
+
+    let timestamp = super::timestamp(options.created);
+    let mut header = String::from("##fileformat=VCFv4.3\n");
+    header.push_str(&format!(
+        "##fileDate={}\n",
+        timestamp[..10].replace('-', "")
+    ));
+    header.push_str(&format!("##source={}\n", options.source));
+    if let Some(build) = options.genome_build {
+        header.push_str(&format!("##reference={:?}\n", build));
+    }
+    let mut contigs: Vec<&str> = Vec::new();
+    for variant in variants {
+        if !contigs.contains(&variant.chromosome.as_str()) {
+            contigs.push(&variant.chromosome);
+        }
+    }
+    for contig in contigs {
+        header.push_str(&format!("##contig=<ID={}>\n", contig));
+    }
+    for field in &INFO_FIELDS {
+        header.push_str(&format!(
+            "##INFO=<ID={},Number={},Type={},Description=\"{}\">\n",
+            field.id, field.number, field.kind, field.description
+        ));
+    }
+    header.push_str("##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n");
+    header.push_str(&format!(
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\t{}\n",
+        options.sample
+    ));
+    out.write_all(header.as_bytes()).map_err(io)?;
+
+    let mut stats = VcfStats::default();
+    for variant in variants {
+        let annotation = variant.rsid.as_ref().and_then(|rsid| annotations.get(rsid));
+        if options.annotated_only && annotation.is_none() {
+            continue;
+        }
+        let Some(line) = record(variant, annotation) else {
+            stats.skipped += 1;
+            continue;
+        };
+        out.write_all(line.as_bytes()).map_err(io)?;
+        stats.written += 1;
+        stats.annotated += usize::from(annotation.is_some());
+    }
+    out.flush().map_err(io)?;
+    Ok(stats)
+}
+
+// Helper functions
+
+/// The data line of a variant, if a VCF can represent it
+fn record(variant: &Variant, annotation: Option<&Annotation>) -> Option<String> {
+    let reference = variant.reference.as_deref().filter(|r| is_allele(r))?;
+    let mut alleles: Vec<&str> = std::iter::once(reference)
+        .chain(variant.alternates.iter().map(String::as_str))
+        .collect();
+    // Called alleles missing from the site become alternates
+    for allele in variant.genotype.alleles() {
+        if !is_allele(allele) {
+            return None;
+        }
+        if !alleles.contains(&allele) {
+            alleles.push(allele);
+        }
+    }
+    let index = |allele: &str| {
+        alleles
+            .iter()
+            .position(|candidate| *candidate == allele)
+            .unwrap_or(0)
+    };
+    let gt = match &variant.genotype {
+        Genotype::NoCall => "./.".to_string(),
+        Genotype::Haploid(allele) => index(allele).to_string(),
+        Genotype::Diploid {
+            first,
+            second,
+            phased,
+        } => format!(
+            "{}{}{}",
+            index(first),
+            if *phased { '|' } else { '/' },
+            index(second)
+        ),
+    };
+
+    let alternates = if alleles.len() > 1 {
+        alleles[1..].join(",")
+    } else {
+        ".".to_string()
+    };
+    let info = match annotation {
+        Some(annotation) if !annotation.info.is_empty() => annotation
+            .info
+            .iter()
+            .map(|(id, values)| {
+                let values: Vec<String> = values.iter().map(|value| encode(value)).collect();
+                format!("{}={}", id, values.join(","))
+            })
+            .collect::<Vec<_>>()
+            .join(";"),
+        _ => ".".to_string(),
+    };
+    Some(format!(
+        "{}\t{}\t{}\t{}\t{}\t.\t.\t{}\tGT\t{}\n",
+        variant.chromosome,
+        variant.position,
+        variant.rsid.as_deref().unwrap_or("."),
+        reference,
+        alternates,
+        info,
+        gt
+    ))
+}
+
+/// A sequence of bases, as opposed to a D/I indel call or a symbol
+fn is_allele(allele: &str) -> bool {
+    !allele.is_empty()
+        && allele
+            .bytes()
+            .all(|base| matches!(base.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'N'))
+}
+
+/// An INFO value with its reserved characters escaped
+fn encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            ' ' => out.push('_'),
+            '%' => out.push_str("%25"),
+            ':' => out.push_str("%3A"),
+            ';' => out.push_str("%3B"),
+            '=' => out.push_str("%3D"),
+            ',' => out.push_str("%2C"),
+            '\t' => out.push_str("%09"),
+            '\n' => out.push_str("%0A"),
+            '\r' => out.push_str("%0D"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
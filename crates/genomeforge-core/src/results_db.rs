@@ -0,0 +1,376 @@
+//! Database of a profile's analysis results
+//!
+//! A [`ResultsDb`] keeps every analysis saved to it, not only the latest,
+//! so past results can be reloaded without analyzing again, compared run
+//! against run, and searched across runs by gene, section, category,
+//! significance and date. Each run's result is sealed in a file of its own
+//! and an index of the runs and their findings in another, both with
+//! [`crypto::write_file`] under the device key, since together they say what
+//! a person carries. The index is read whole when the database is opened
+//! and rewritten on every change, which stays quick at the few hundred runs
+//! a profile collects.
+//!
+//! Callers that only save, list and load runs go through [`ResultsStore`],
+//! which hands out owned values, so a store kept in another form, such as
+//! an SQL database, can take the place of these files without changing them.
+
+use crate::crypto::{self, Key, KeySource, Zeroizing};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the index
+pub const INDEX_FILE: &str = "index.gfdb";
+
+/// Extension of the files holding the results of runs
+pub const EXTENSION: &str = "gfrun";
+
+/// Runs kept per genome file; the oldest are removed beyond this
+pub const MAX_RUNS_PER_SOURCE: usize = 50;
+
+/// Kind recorded in the header of the index
+const INDEX_KIND: &str = "results-index";
+
+/// Kind recorded in the header of run files
+const RUN_KIND: &str = "analysis-run";
+
+/// An analysis saved in the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub id: u64,
+    /// Hex SHA-256 of the genome file analyzed
+    pub source_hash: String,
+    /// Name of the genome file, for telling runs apart
+    pub source_name: String,
+    /// Seconds since the Unix epoch
+    pub analyzed_at: u64,
+    pub clinvar_release: Option<String>,
+    pub finding_count: usize,
+}
+
+/// What the index holds of a finding, enough to query by
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FindingRow {
+    /// Part of the result the finding is in, e.g. "clinical"
+    pub section: String,
+    /// Position of the finding in its section
+    pub index: usize,
+    pub genes: Vec<String>,
+    /// e.g. "pathogenic"
+    pub significances: Vec<String>,
+    /// e.g. "cardiovascular"
+    pub categories: Vec<String>,
+}
+
+/// A run to add to the database
+#[derive(Debug)]
+pub struct NewRun<'a> {
+    pub source_hash: &'a str,
+    pub source_name: &'a str,
+    pub analyzed_at: u64,
+    pub clinvar_release: Option<String>,
+    pub findings: Vec<FindingRow>,
+    /// The result, serialized
+    pub result: &'a [u8],
+}
+
+/// Findings to look for; every criterion given must hold, and text is
+/// compared ignoring case
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ResultQuery {
+    pub gene: Option<String>,
+    pub section: Option<String>,
+    pub category: Option<String>,
+    pub significance: Option<String>,
+    /// Runs analyzed at or after this time, in seconds since the Unix epoch
+    pub from: Option<u64>,
+    /// Runs analyzed at or before this time
+    pub to: Option<u64>,
+    pub source_hash: Option<String>,
+    pub run: Option<u64>,
+}
+
+impl ResultQuery {
+    fn matches(&self, run: &Run, finding: &FindingRow) -> bool {
+        let any = |wanted: &Option<String>, values: &[String]| {
+            wanted.as_ref().is_none_or(|wanted| {
+                values
+                    .iter()
+                    .any(|value| value.eq_ignore_ascii_case(wanted))
+            })
+        };
+        self.run.is_none_or(|id| id == run.id)
+            && self
+                .source_hash
+                .as_ref()
+                .is_none_or(|hash| hash.eq_ignore_ascii_case(&run.source_hash))
+            && self.from.is_none_or(|from| run.analyzed_at >= from)
+            && self.to.is_none_or(|to| run.analyzed_at <= to)
+            && any(&self.section, std::slice::from_ref(&finding.section))
+            && any(&self.gene, &finding.genes)
+            && any(&self.significance, &finding.significances)
+            && any(&self.category, &finding.categories)
+    }
+}
+
+/// A store of saved analyses, of which [`ResultsDb`] is the one in use
+pub trait ResultsStore: Send {
+    /// Saved runs, most recent first
+    fn runs(&self) -> Result<Vec<Run>, String>;
+
+    fn run(&self, id: u64) -> Result<Option<Run>, String>;
+
+    /// Save a run, removing the oldest of its genome file beyond
+    /// [`MAX_RUNS_PER_SOURCE`]
+    fn insert(&mut self, new: NewRun<'_>) -> Result<Run, String>;
+
+    /// The result saved with a run, as it was serialized
+    fn result(&self, id: u64) -> Result<Zeroizing<Vec<u8>>, String>;
+
+    /// The run of the same genome file before this one
+    fn previous(&self, id: u64) -> Result<Option<Run>, String>;
+
+    /// Findings matching `query`, most recent run first and in result
+    /// order within a run
+    fn query(&self, query: &ResultQuery) -> Result<Vec<(Run, FindingRow)>, String>;
+
+    /// Remove a run and its result
+    fn delete(&mut self, id: u64) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Index {
+    next_id: u64,
+    /// Oldest first
+    runs: Vec<IndexedRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedRun {
+    run: Run,
+    findings: Vec<FindingRow>,
+}
+
+#[derive(Serialize)]
+struct IndexInfo {
+    runs: usize,
+}
+
+/// Readable header of a run file, which names nothing
+#[derive(Serialize)]
+struct RunInfo {
+    id: u64,
+    analyzed_at: u64,
+}
+
+/// Saved analyses in a directory
+pub struct ResultsDb {
+    dir: PathBuf,
+    key: Key,
+    index: Index,
+}
+
+impl ResultsDb {
+    /// Open the database in `dir`, sealed with `key`; a directory without
+    /// one holds an empty database, created on the first write
+    pub fn open(dir: &Path, key: Key) -> Result<Self, String> {
+        let path = dir.join(INDEX_FILE);
+        let index = if path.exists() {
+            let (_, plaintext) =
+                crypto::read_file::<serde_json::Value>(&path, INDEX_KIND, KeySource::Device(&key))?;
+            serde_json::from_slice(&plaintext)
+                .map_err(|e| format!("Results index is corrupt: {}", e))?
+        } else {
+            Index::default()
+        };
+        Ok(ResultsDb {
+            dir: dir.to_path_buf(),
+            key,
+            index,
+        })
+    }
+
+    /// Saved runs, most recent first
+    pub fn runs(&self) -> Vec<&Run> {
+        self.index.runs.iter().rev().map(|run| &run.run).collect()
+    }
+
+    pub fn run(&self, id: u64) -> Option<&Run> {
+        self.indexed(id).map(|run| &run.run)
+    }
+
+    /// Save a run, removing the oldest of its genome file beyond
+    /// [`MAX_RUNS_PER_SOURCE`]
+    pub fn insert(&mut self, new: NewRun<'_>) -> Result<Run, String> {
+        if !valid_hash(new.source_hash) {
+            return Err(format!("Invalid file hash: {:?}", new.source_hash));
+        }
+        let run = Run {
+            id: self.index.next_id,
+            source_hash: new.source_hash.to_ascii_lowercase(),
+            source_name: new.source_name.to_string(),
+            analyzed_at: new.analyzed_at,
+            clinvar_release: new.clinvar_release,
+            finding_count: new.findings.len(),
+        };
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create {}: {}", self.dir.display(), e))?;
+        let path = self.run_path(run.id);
+        let info = RunInfo {
+            id: run.id,
+            analyzed_at: run.analyzed_at,
+        };
+        crypto::write_file(
+            &path,
+            RUN_KIND,
+            &info,
+            new.result,
+            KeySource::Device(&self.key),
+        )?;
+
+        // Changed on a copy, so a failed write leaves the index as it was
+        let mut index = self.index.clone();
+        index.next_id += 1;
+        index.runs.push(IndexedRun {
+            run: run.clone(),
+            findings: new.findings,
+        });
+        let mut of_source: Vec<u64> = index
+            .runs
+            .iter()
+            .filter(|indexed| indexed.run.source_hash == run.source_hash)
+            .map(|indexed| indexed.run.id)
+            .collect();
+        let stale = of_source.len().saturating_sub(MAX_RUNS_PER_SOURCE);
+        of_source.truncate(stale);
+        index
+            .runs
+            .retain(|indexed| !of_source.contains(&indexed.run.id));
+        if let Err(error) = self.save(&index) {
+            let _ = fs::remove_file(&path);
+            return Err(error);
+        }
+        self.index = index;
+        for id in of_source {
+            let _ = fs::remove_file(self.run_path(id));
+        }
+        Ok(run)
+    }
+
+    /// The result saved with a run, as it was serialized
+    pub fn result(&self, id: u64) -> Result<Zeroizing<Vec<u8>>, String> {
+        if self.indexed(id).is_none() {
+            return Err(format!("Unknown analysis run: {}", id));
+        }
+        let (_, plaintext) = crypto::read_file::<serde_json::Value>(
+            &self.run_path(id),
+            RUN_KIND,
+            KeySource::Device(&self.key),
+        )?;
+        Ok(plaintext)
+    }
+
+    /// The run of the same genome file before this one
+    pub fn previous(&self, id: u64) -> Option<&Run> {
+        let run = self.run(id)?;
+        self.runs()
+            .into_iter()
+            .find(|other| other.id < id && other.source_hash == run.source_hash)
+    }
+
+    /// Findings matching `query`, most recent run first and in result
+    /// order within a run
+    pub fn query(&self, query: &ResultQuery) -> Vec<(&Run, &FindingRow)> {
+        self.index
+            .runs
+            .iter()
+            .rev()
+            .flat_map(|indexed| {
+                indexed
+                    .findings
+                    .iter()
+                    .map(move |finding| (&indexed.run, finding))
+            })
+            .filter(|(run, finding)| query.matches(run, finding))
+            .collect()
+    }
+
+    /// Remove a run and its result
+    pub fn delete(&mut self, id: u64) -> Result<(), String> {
+        if self.indexed(id).is_none() {
+            return Err(format!("Unknown analysis run: {}", id));
+        }
+        let mut index = self.index.clone();
+        index.runs.retain(|indexed| indexed.run.id != id);
+        self.save(&index)?;
+        self.index = index;
+        let path = self.run_path(id);
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+    }
+
+    fn indexed(&self, id: u64) -> Option<&IndexedRun> {
+        self.index.runs.iter().find(|indexed| indexed.run.id == id)
+    }
+
+    fn run_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{}.{}", id, EXTENSION))
+    }
+
+    fn save(&self, index: &Index) -> Result<(), String> {
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(index)
+                .map_err(|e| format!("Failed to serialize results index: {}", e))?,
+        );
+        let info = IndexInfo {
+            runs: index.runs.len(),
+        };
+        crypto::write_file(
+            &self.dir.join(INDEX_FILE),
+            INDEX_KIND,
+            &info,
+            &plaintext,
+            KeySource::Device(&self.key),
+        )
+        .map(|_| ())
+    }
+}
+
+impl ResultsStore for ResultsDb {
+    fn runs(&self) -> Result<Vec<Run>, String> {
+        Ok(ResultsDb::runs(self).into_iter().cloned().collect())
+    }
+
+    fn run(&self, id: u64) -> Result<Option<Run>, String> {
+        Ok(ResultsDb::run(self, id).cloned())
+    }
+
+    fn insert(&mut self, new: NewRun<'_>) -> Result<Run, String> {
+        ResultsDb::insert(self, new)
+    }
+
+    fn result(&self, id: u64) -> Result<Zeroizing<Vec<u8>>, String> {
+        ResultsDb::result(self, id)
+    }
+
+    fn previous(&self, id: u64) -> Result<Option<Run>, String> {
+        Ok(ResultsDb::previous(self, id).cloned())
+    }
+
+    fn query(&self, query: &ResultQuery) -> Result<Vec<(Run, FindingRow)>, String> {
+        Ok(ResultsDb::query(self, query)
+            .into_iter()
+            .map(|(run, finding)| (run.clone(), finding.clone()))
+            .collect())
+    }
+
+    fn delete(&mut self, id: u64) -> Result<(), String> {
+        ResultsDb::delete(self, id)
+    }
+}
+
+// Helper functions
+
+fn valid_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,493 @@
+//! Filter expressions over findings
+//!
+//! A small language for rules such as
+//!
+//! ```text
+//! zygosity == "homozygous" and gene in ["BRCA1", "BRCA2"] and af < 0.01
+//! ```
+//!
+//! evaluated against a finding as JSON. Names are the finding's fields,
+//! with `.` reaching into nested ones (`confidence.score`); a field the
+//! finding lacks is `null`. Conditions combine with `and`, `or`, `not` and
+//! parentheses, and compare with `==`, `!=`, `<`, `<=`, `>`, `>=`,
+//! `in [...]`, `not in [...]` and `contains`.
+//!
+//! Text compares ignoring case, and a list field meets a comparison when
+//! any of its items does, so `genes == "APOE"` holds for a finding of APOE
+//! and APOC1. Comparing values of different types is false rather than an
+//! error, and a name alone holds when the field is present and not false,
+//! zero or empty. Evaluation cannot fail or loop, so rules from anywhere
+//! are safe to run.
+
+use serde_json::Value;
+
+/// Longest expression accepted, in bytes
+pub const MAX_LENGTH: usize = 4096;
+
+/// Deepest nesting of `not` and parentheses accepted
+const MAX_DEPTH: usize = 64;
+
+static NULL: Value = Value::Null;
+
+/// A parsed filter expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(Node);
+
+impl Expr {
+    /// Parse an expression, reporting the column of the first error
+    pub fn parse(text: &str) -> Result<Self, String> {
+        if text.len() > MAX_LENGTH {
+            return Err(format!(
+                "Expression is longer than {} characters",
+                MAX_LENGTH
+            ));
+        }
+        let tokens = lex(text)?;
+        if tokens.len() == 1 {
+            return Err("Expression is empty".to_string());
+        }
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            depth: 0,
+        };
+        let node = parser.any()?;
+        match parser.peek() {
+            Token::End => Ok(Expr(node)),
+            _ => Err(parser.unexpected()),
+        }
+    }
+
+    /// Whether a finding meets the expression
+    pub fn matches(&self, finding: &Value) -> bool {
+        self.0.eval(finding)
+    }
+
+    /// Fields the expression reads, in the order it names them
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields = Vec::new();
+        self.0.fields(&mut fields);
+        fields
+    }
+}
+
+/// The value at a `.`-separated field path of a finding
+pub fn field<'a>(finding: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(finding, |value, key| value.get(key))
+        .filter(|value| !value.is_null())
+}
+
+// Helper functions
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    All(Vec<Node>),
+    Any(Vec<Node>),
+    Not(Box<Node>),
+    Compare(Operand, Op, Operand),
+    /// A value standing alone as a condition
+    Present(Operand),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Field(String),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    NotIn,
+    Contains,
+}
+
+impl Node {
+    fn eval(&self, finding: &Value) -> bool {
+        match self {
+            Node::All(nodes) => nodes.iter().all(|node| node.eval(finding)),
+            Node::Any(nodes) => nodes.iter().any(|node| node.eval(finding)),
+            Node::Not(node) => !node.eval(finding),
+            Node::Compare(left, op, right) => {
+                let (left, right) = (left.resolve(finding), right.resolve(finding));
+                match op {
+                    Op::Eq => equal(left, right),
+                    Op::Ne => !equal(left, right),
+                    Op::Lt => ordered(left, right, |a, b| a < b),
+                    Op::Le => ordered(left, right, |a, b| a <= b),
+                    Op::Gt => ordered(left, right, |a, b| a > b),
+                    Op::Ge => ordered(left, right, |a, b| a >= b),
+                    Op::In => items(right).iter().any(|item| equal(left, item)),
+                    Op::NotIn => !items(right).iter().any(|item| equal(left, item)),
+                    Op::Contains => contains(left, right),
+                }
+            }
+            Node::Present(operand) => present(operand.resolve(finding)),
+        }
+    }
+
+    fn fields<'a>(&'a self, fields: &mut Vec<&'a str>) {
+        let mut add = |operand: &'a Operand| {
+            if let Operand::Field(name) = operand {
+                if !fields.contains(&name.as_str()) {
+                    fields.push(name);
+                }
+            }
+        };
+        match self {
+            Node::All(nodes) | Node::Any(nodes) => {
+                nodes.iter().for_each(|node| node.fields(fields))
+            }
+            Node::Not(node) => node.fields(fields),
+            Node::Compare(left, _, right) => {
+                add(left);
+                add(right);
+            }
+            Node::Present(operand) => add(operand),
+        }
+    }
+}
+
+impl Operand {
+    fn resolve<'a>(&'a self, finding: &'a Value) -> &'a Value {
+        match self {
+            Operand::Field(path) => field(finding, path).unwrap_or(&NULL),
+            Operand::Literal(value) => value,
+        }
+    }
+}
+
+fn items(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(items) => items,
+        other => std::slice::from_ref(other),
+    }
+}
+
+/// Lists are equal item by item; a list and a single value when any item
+/// equals it
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        (Value::Array(items), other) | (other, Value::Array(items)) => {
+            items.iter().any(|item| equal(item, other))
+        }
+        (Value::String(a), Value::String(b)) => a.eq_ignore_ascii_case(b),
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (a, b) => a == b,
+    }
+}
+
+/// Numbers only; a list when any item compares so
+fn ordered(a: &Value, b: &Value, compare: fn(f64, f64) -> bool) -> bool {
+    items(a).iter().any(|a| {
+        items(b)
+            .iter()
+            .any(|b| matches!((a.as_f64(), b.as_f64()), (Some(a), Some(b)) if compare(a, b)))
+    })
+}
+
+/// Text holding other text, ignoring case; a list when any item does
+fn contains(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Array(items), _) => items.iter().any(|item| contains(item, b)),
+        (Value::String(a), Value::String(b)) => a.to_lowercase().contains(&b.to_lowercase()),
+        (a, b) => equal(a, b),
+    }
+}
+
+fn present(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::Number(number) => number.as_f64() != Some(0.0),
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(f64),
+    Text(String),
+    Symbol(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 10] = ["==", "!=", "<=", ">=", "<", ">", "(", ")", "[", "]"];
+
+/// Tokens with the column each starts at, ending with [`Token::End`]
+fn lex(text: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == ',' {
+            tokens.push((Token::Symbol(","), column));
+            i += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            let name: String = chars[start..i].iter().collect();
+            if name.split('.').any(str::is_empty) {
+                return Err(format!("Invalid field name {} at column {}", name, column));
+            }
+            tokens.push((Token::Name(name), column));
+        } else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let start = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric()
+                    || chars[i] == '.'
+                    || (matches!(chars[i], '+' | '-') && matches!(chars[i - 1], 'e' | 'E')))
+            {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("Invalid number {} at column {}", number, column))?;
+            tokens.push((Token::Number(value), column));
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("Unterminated text at column {}", column)),
+                    Some(&end) if end == c => break,
+                    Some('\\') => {
+                        let escaped = match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some(&other @ ('\\' | '"' | '\'')) => other,
+                            _ => {
+                                return Err(format!("Invalid escape at column {}", i + 1));
+                            }
+                        };
+                        value.push(escaped);
+                        i += 2;
+                    }
+                    Some(&other) => {
+                        value.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Text(value), column));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(*symbol))
+                .ok_or_else(|| format!("Unexpected '{}' at column {}", c, column))?;
+            tokens.push((Token::Symbol(symbol), column));
+            i += symbol.len();
+        }
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// Keywords, which cannot name fields
+const KEYWORDS: [&str; 8] = [
+    "and", "or", "not", "in", "contains", "true", "false", "null",
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self) -> String {
+        let (token, column) = &self.tokens[self.position];
+        match token {
+            Token::End => format!("Unexpected end of expression at column {}", column),
+            Token::Name(name) => format!("Unexpected {} at column {}", name, column),
+            Token::Number(_) => format!("Unexpected number at column {}", column),
+            Token::Text(_) => format!("Unexpected text at column {}", column),
+            Token::Symbol(symbol) => format!("Unexpected '{}' at column {}", symbol, column),
+        }
+    }
+
+    /// Consume the keyword if it is next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Token::Name(name) if name.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Token::Symbol(next) if *next == symbol);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.symbol(symbol) {
+            Ok(())
+        } else {
+            let column = self.tokens[self.position].1;
+            Err(format!("Expected '{}' at column {}", symbol, column))
+        }
+    }
+
+    fn nest(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("Expression nests deeper than {} levels", MAX_DEPTH));
+        }
+        Ok(())
+    }
+
+    fn any(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.all()?];
+        while self.keyword("or") {
+            nodes.push(self.all()?);
+        }
+        Ok(single(nodes, Node::Any))
+    }
+
+    fn all(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.not()?];
+        while self.keyword("and") {
+            nodes.push(self.not()?);
+        }
+        Ok(single(nodes, Node::All))
+    }
+
+    fn not(&mut self) -> Result<Node, String> {
+        if self.keyword("not") {
+            self.nest()?;
+            let node = Node::Not(Box::new(self.not()?));
+            self.depth -= 1;
+            return Ok(node);
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Node, String> {
+        if self.symbol("(") {
+            self.nest()?;
+            let node = self.any()?;
+            self.expect(")")?;
+            self.depth -= 1;
+            return Ok(node);
+        }
+        let left = self.operand()?;
+        let op = match self.peek() {
+            Token::Symbol("==") => Op::Eq,
+            Token::Symbol("!=") => Op::Ne,
+            Token::Symbol("<") => Op::Lt,
+            Token::Symbol("<=") => Op::Le,
+            Token::Symbol(">") => Op::Gt,
+            Token::Symbol(">=") => Op::Ge,
+            Token::Name(name) if name.eq_ignore_ascii_case("in") => Op::In,
+            Token::Name(name) if name.eq_ignore_ascii_case("contains") => Op::Contains,
+            Token::Name(name) if name.eq_ignore_ascii_case("not") => {
+                self.position += 1;
+                if !matches!(self.peek(), Token::Name(name) if name.eq_ignore_ascii_case("in")) {
+                    return Err(self.unexpected());
+                }
+                Op::NotIn
+            }
+            _ => return Ok(Node::Present(left)),
+        };
+        self.position += 1;
+        let right = self.operand()?;
+        Ok(Node::Compare(left, op, right))
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        if self.symbol("[") {
+            let mut items = Vec::new();
+            if !self.symbol("]") {
+                loop {
+                    items.push(self.literal()?);
+                    if self.symbol("]") {
+                        break;
+                    }
+                    self.expect(",")?;
+                }
+            }
+            return Ok(Operand::Literal(Value::Array(items)));
+        }
+        if let Token::Name(name) = self.peek() {
+            if !KEYWORDS
+                .iter()
+                .any(|keyword| name.eq_ignore_ascii_case(keyword))
+            {
+                let name = name.clone();
+                self.position += 1;
+                return Ok(Operand::Field(name));
+            }
+        }
+        self.literal().map(Operand::Literal)
+    }
+
+    fn literal(&mut self) -> Result<Value, String> {
+        let value = match self.peek() {
+            Token::Number(number) => serde_json::Number::from_f64(*number).map(Value::Number),
+            Token::Text(text) => Some(Value::String(text.clone())),
+            Token::Name(name) if name.eq_ignore_ascii_case("true") => Some(Value::Bool(true)),
+            Token::Name(name) if name.eq_ignore_ascii_case("false") => Some(Value::Bool(false)),
+            Token::Name(name) if name.eq_ignore_ascii_case("null") => Some(Value::Null),
+            _ => None,
+        };
+        match value {
+            Some(value) => {
+                self.next();
+                Ok(value)
+            }
+            None => Err(self.unexpected()),
+        }
+    }
+}
+
+/// The node itself when there is only one
+fn single(mut nodes: Vec<Node>, combine: fn(Vec<Node>) -> Node) -> Node {
+    if nodes.len() == 1 {
+        nodes.remove(0)
+    } else {
+        combine(nodes)
+    }
+}
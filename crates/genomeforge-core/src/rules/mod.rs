@@ -0,0 +1,141 @@
+//! Named sets of user-defined rules
+//!
+//! A rule set keeps filter [`expr`]essions under names, such as "rare
+//! homozygous calls in my genes", so they can be applied to any analysis.
+//! Sets are saved as JSON files, one per set, named by id.
+
+pub mod expr;
+
+use expr::Expr;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File extension of rule set files
+pub const EXTENSION: &str = "json";
+
+/// Named filter rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// Lowercase letters, digits and `-`, e.g. "rare-homozygous"
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub rules: Vec<Rule>,
+}
+
+/// A filter expression and what it is called
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    /// e.g. `zygosity == "homozygous" and af < 0.01`
+    pub expression: String,
+}
+
+/// A rule with its expression parsed
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl RuleSet {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid rule set: {}", e))
+    }
+
+    /// Check the set and parse its rules
+    pub fn compile(&self) -> Result<Vec<CompiledRule>, String> {
+        if !valid_id(&self.id) {
+            return Err(format!(
+                "Rule set id must be lowercase letters, digits and '-': {:?}",
+                self.id
+            ));
+        }
+        if self.name.trim().is_empty() {
+            return Err(format!("Rule set {} has no name", self.id));
+        }
+        if self.rules.is_empty() {
+            return Err(format!("Rule set {} has no rules", self.id));
+        }
+        let mut compiled: Vec<CompiledRule> = Vec::new();
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() {
+                return Err(format!("Rule set {} has a rule with no name", self.id));
+            }
+            if compiled.iter().any(|other| other.name == rule.name) {
+                return Err(format!(
+                    "Rule set {} has two rules named {}",
+                    self.id, rule.name
+                ));
+            }
+            let expr = Expr::parse(&rule.expression)
+                .map_err(|e| format!("Rule {} of {}: {}", rule.name, self.id, e))?;
+            compiled.push(CompiledRule {
+                name: rule.name.clone(),
+                expr,
+            });
+        }
+        Ok(compiled)
+    }
+}
+
+/// Load every rule set in `dir`, by name; none when it does not exist
+pub fn load_dir(dir: &Path) -> Result<Vec<RuleSet>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut sets = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        sets.push(RuleSet::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))?);
+    }
+    sets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(sets)
+}
+
+/// Save a rule set to `dir` as `<id>.json`, replacing any of the same id
+pub fn save(dir: &Path, set: &RuleSet) -> Result<PathBuf, String> {
+    set.compile()?;
+    let json = serde_json::to_string_pretty(set)
+        .map_err(|e| format!("Failed to serialize rule set: {}", e))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.{}", set.id, EXTENSION));
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Delete the rule set with this id from `dir`
+pub fn delete(dir: &Path, id: &str) -> Result<(), String> {
+    if !valid_id(id) {
+        return Err(format!("Invalid rule set id: {:?}", id));
+    }
+    let path = dir.join(format!("{}.{}", id, EXTENSION));
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(format!("Unknown rule set: {}", id))
+        }
+        Err(e) => Err(format!("Failed to delete {}: {}", path.display(), e)),
+    }
+}
+
+// Helper functions
+
+/// Also a safe file name
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
@@ -0,0 +1,158 @@
+//! Ranked text search and pagination over analysis results
+//!
+//! Findings are matched on a few short fields, such as the gene symbol,
+//! rsid, condition and drug, rather than indexed as free text. Each word of
+//! a query must match one of those fields. An exact match ranks above a
+//! prefix match, which ranks above a match at the start of a later word,
+//! which ranks above a match anywhere else. Gene symbols and rsids weigh
+//! more than drug names, which weigh more than condition names.
+
+use serde::Serialize;
+
+/// Results returned when a page size is not given
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Largest page that can be requested
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// A searchable field of a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchField {
+    Gene,
+    Rsid,
+    Drug,
+    Condition,
+}
+
+impl SearchField {
+    fn weight(&self) -> u32 {
+        match self {
+            SearchField::Gene | SearchField::Rsid => 3,
+            SearchField::Drug => 2,
+            SearchField::Condition => 1,
+        }
+    }
+}
+
+/// How well a query matched a finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SearchMatch {
+    /// Higher is better
+    pub score: u32,
+    /// Field the best-matching word was found in
+    pub field: SearchField,
+}
+
+/// A query split into lower-case words
+#[derive(Debug, Clone)]
+pub struct Query {
+    terms: Vec<String>,
+}
+
+impl Query {
+    pub fn new(raw: &str) -> Self {
+        Query {
+            terms: raw.split_whitespace().map(str::to_lowercase).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Score a finding's fields, or `None` if a word matches none of them
+    pub fn score(&self, fields: &[(SearchField, &str)]) -> Option<SearchMatch> {
+        if self.is_empty() {
+            return None;
+        }
+        let fields: Vec<(SearchField, String)> = fields
+            .iter()
+            .map(|(field, text)| (*field, text.to_lowercase()))
+            .collect();
+
+        let mut total = 0;
+        let mut best: Option<(u32, SearchField)> = None;
+        for term in &self.terms {
+            let (score, field) = fields
+                .iter()
+                .filter_map(|(field, text)| {
+                    match_quality(term, text).map(|quality| (quality * field.weight(), *field))
+                })
+                .max_by_key(|(score, _)| *score)?;
+            total += score;
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, field));
+            }
+        }
+        best.map(|(_, field)| SearchMatch {
+            score: total,
+            field,
+        })
+    }
+}
+
+/// One page of a longer list
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Items in the whole list
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl<T> Page<T> {
+    /// Take `limit` items from `offset`; a zero or missing limit means
+    /// [`DEFAULT_PAGE_SIZE`], and limits above [`MAX_PAGE_SIZE`] are capped
+    pub fn new(items: Vec<T>, offset: usize, limit: Option<usize>) -> Self {
+        let limit = limit
+            .filter(|limit| *limit > 0)
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .min(MAX_PAGE_SIZE);
+        let total = items.len();
+        let items = items.into_iter().skip(offset).take(limit).collect();
+        Page {
+            items,
+            total,
+            offset,
+            limit,
+        }
+    }
+
+    /// Convert the items of the page, stopping at the first error
+    pub fn try_map<U, E>(self, f: impl FnMut(T) -> Result<U, E>) -> Result<Page<U>, E> {
+        Ok(Page {
+            items: self.items.into_iter().map(f).collect::<Result<_, _>>()?,
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+        })
+    }
+
+    /// Whether items follow this page
+    pub fn has_more(&self) -> bool {
+        self.offset + self.items.len() < self.total
+    }
+}
+
+// Helper functions
+
+/// 4 for an exact match, 3 for a prefix, 2 for the start of another word
+/// and 1 for anywhere else
+fn match_quality(term: &str, text: &str) -> Option<u32> {
+    if text == term {
+        Some(4)
+    } else if text.starts_with(term) {
+        Some(3)
+    } else if text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(term))
+    {
+        Some(2)
+    } else if text.contains(term) {
+        Some(1)
+    } else {
+        None
+    }
+}
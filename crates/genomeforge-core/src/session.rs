@@ -0,0 +1,153 @@
+//! Encrypted session files
+//!
+//! A session holds a parsed genome and, once analyzed, its results, so the
+//! application can reopen it without parsing and analyzing again. Sessions
+//! are written with [`crypto::write_file`]; the header describing them is
+//! left readable, so they can be listed without a key, while the variants
+//! and findings are gzip compressed and sealed.
+
+use crate::crypto::{self, KeySource, Protection, Zeroizing};
+use crate::genome::GenomeBuild;
+use crate::parser::detect::FileFormat;
+use crate::store::LoadedGenome;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Extension of session files
+pub const EXTENSION: &str = "gfsession";
+
+/// Kind recorded in the header of session files
+const KIND: &str = "session";
+
+/// Readable description of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub saved_at: u64,
+    pub format: FileFormat,
+    pub genome_build: Option<GenomeBuild>,
+    pub variant_count: usize,
+    /// Whether analysis results were saved with the genome
+    pub has_results: bool,
+    /// Hex SHA-256 of the genome file the session was loaded from
+    #[serde(default)]
+    pub source_sha256: Option<String>,
+}
+
+/// A session file found by [`list`] or just written
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEntry {
+    pub path: PathBuf,
+    pub protection: Protection,
+    #[serde(flatten)]
+    pub info: SessionInfo,
+}
+
+/// A session read back from disk
+#[derive(Debug)]
+pub struct Session<T> {
+    pub info: SessionInfo,
+    pub genome: LoadedGenome,
+    pub results: Option<T>,
+}
+
+#[derive(Serialize)]
+struct Payload<'a, T> {
+    genome: &'a LoadedGenome,
+    results: Option<&'a T>,
+}
+
+#[derive(Deserialize)]
+struct OwnedPayload<T> {
+    genome: LoadedGenome,
+    results: Option<T>,
+}
+
+/// Write a session, replacing any file already at `path`
+pub fn write<T: Serialize>(
+    path: &Path,
+    name: &str,
+    genome: &LoadedGenome,
+    results: Option<&T>,
+    key: KeySource<'_>,
+) -> Result<SessionEntry, String> {
+    let info = SessionInfo {
+        name: name.to_string(),
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0),
+        format: genome.file.format,
+        genome_build: genome.file.genome_build,
+        variant_count: genome.len(),
+        has_results: results.is_some(),
+        source_sha256: genome
+            .file
+            .fingerprint
+            .as_ref()
+            .map(|fingerprint| fingerprint.sha256.clone()),
+    };
+
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    serde_json::to_writer(&mut encoder, &Payload { genome, results })
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    let compressed = Zeroizing::new(
+        encoder
+            .finish()
+            .map_err(|e| format!("Failed to compress session: {}", e))?,
+    );
+
+    let protection = crypto::write_file(path, KIND, &info, &compressed, key)?;
+    Ok(SessionEntry {
+        path: path.to_path_buf(),
+        protection,
+        info,
+    })
+}
+
+/// Read a session's description without decrypting it
+pub fn read_info(path: &Path) -> Result<SessionEntry, String> {
+    let envelope = crypto::read_header::<SessionInfo>(path, KIND)?;
+    Ok(SessionEntry {
+        path: path.to_path_buf(),
+        protection: envelope.protection,
+        info: envelope.metadata,
+    })
+}
+
+/// Decrypt and read a whole session
+pub fn read<T: DeserializeOwned>(path: &Path, key: KeySource<'_>) -> Result<Session<T>, String> {
+    let (envelope, compressed) = crypto::read_file::<SessionInfo>(path, KIND, key)?;
+    let payload: OwnedPayload<T> = serde_json::from_reader(GzDecoder::new(&compressed[..]))
+        .map_err(|e| format!("Failed to read session: {}", e))?;
+    Ok(Session {
+        info: envelope.metadata,
+        genome: payload.genome,
+        results: payload.results,
+    })
+}
+
+/// Session files in a directory, most recently saved first
+///
+/// Files whose header cannot be read are skipped.
+pub fn list(dir: &Path) -> Result<Vec<SessionEntry>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut sessions: Vec<SessionEntry> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| read_info(&path).ok())
+        .collect();
+    sessions.sort_by_key(|entry| std::cmp::Reverse(entry.info.saved_at));
+    Ok(sessions)
+}
@@ -0,0 +1,188 @@
+//! User settings of the desktop apps
+//!
+//! Settings are one JSON object in `settings.json`, carrying the version
+//! of the schema it was written with. A file from an older version is
+//! brought up to date by [`MIGRATIONS`] when it is read; fields it lacks
+//! take their defaults and fields no longer known are dropped.
+
+use crate::annotation::consent::{ConsentPolicy, FindingCategory};
+use crate::annotation::manager::DatabaseKind;
+use crate::download;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the settings file
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Version of the schema settings are written with
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Step bringing settings from one version to the next
+pub type Migration = fn(&mut Map<String, Value>);
+
+/// Migrations in order; the one at index `n` upgrades version `n`
+pub const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [from_unversioned];
+
+/// Colour scheme of the interface
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Follow the system setting
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// What the user has agreed to be told
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Consent {
+    /// Screen the ACMG secondary findings genes
+    pub secondary_findings: bool,
+    /// Report APOE and other late-onset Alzheimer's disease findings
+    pub late_onset: bool,
+    /// Categories never to analyze
+    pub excluded: BTreeSet<FindingCategory>,
+}
+
+impl Consent {
+    /// The exclusions as a policy for the analysis
+    pub fn policy(&self) -> ConsentPolicy {
+        ConsentPolicy::excluding(self.excluded.iter().copied())
+    }
+}
+
+/// Everything the user can configure
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Schema version; always [`SCHEMA_VERSION`] once read
+    pub version: u32,
+    /// Folder report exports are suggested in
+    pub export_dir: Option<PathBuf>,
+    /// Leave out clinical findings more frequent than this (0.0 - 1.0) in
+    /// gnomAD; all are kept when unset
+    pub max_allele_frequency: Option<f64>,
+    pub consent: Consent,
+    pub theme: Theme,
+    /// Directory database releases are installed in instead of the app's
+    pub database_dir: Option<PathBuf>,
+    /// Database files to load instead of the installed releases
+    pub database_paths: HashMap<DatabaseKind, PathBuf>,
+    /// Ids of the installed analysis plugins to run
+    pub enabled_plugins: BTreeSet<String>,
+    /// Folder new genome files are imported from as they arrive; none is
+    /// watched when unset
+    pub watch_dir: Option<PathBuf>,
+    /// Most bytes per second release downloads may use; unlimited when
+    /// unset
+    pub download_limit: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SCHEMA_VERSION,
+            export_dir: None,
+            max_allele_frequency: None,
+            consent: Consent::default(),
+            theme: Theme::default(),
+            database_dir: None,
+            database_paths: HashMap::new(),
+            enabled_plugins: BTreeSet::new(),
+            watch_dir: None,
+            download_limit: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings in `dir`; without a settings file, the defaults
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Settings::from_json(&json)
+    }
+
+    /// Parse settings of any schema version up to [`SCHEMA_VERSION`]
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| format!("Settings are corrupt: {}", e))?;
+        let Value::Object(mut fields) = value else {
+            return Err("Settings are corrupt: not a JSON object".to_string());
+        };
+        let version = match fields.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or("Settings are corrupt: invalid version")?,
+        };
+        if version > SCHEMA_VERSION {
+            return Err(format!(
+                "Settings were saved by a newer version of GenomeForge (schema {})",
+                version
+            ));
+        }
+        for migrate in &MIGRATIONS[version as usize..] {
+            migrate(&mut fields);
+        }
+        fields.insert("version".to_string(), SCHEMA_VERSION.into());
+        let settings: Settings = serde_json::from_value(Value::Object(fields))
+            .map_err(|e| format!("Settings are corrupt: {}", e))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Write the settings to `dir`
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        self.validate()?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::create_dir_all(dir)
+            .and_then(|_| fs::write(dir.join(SETTINGS_FILE), json))
+            .map_err(|e| format!("Failed to write settings: {}", e))
+    }
+
+    /// Check that every value is in range
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != SCHEMA_VERSION {
+            return Err(format!(
+                "Settings version must be {}, not {}",
+                SCHEMA_VERSION, self.version
+            ));
+        }
+        if self
+            .max_allele_frequency
+            .is_some_and(|af| !(0.0..=1.0).contains(&af))
+        {
+            return Err("max_allele_frequency must be between 0 and 1".to_string());
+        }
+        if self.watch_dir.as_ref().is_some_and(|dir| dir.is_relative()) {
+            return Err("watch_dir must be an absolute path".to_string());
+        }
+        if self
+            .download_limit
+            .is_some_and(|limit| limit < download::MIN_RATE_LIMIT)
+        {
+            return Err(format!(
+                "download_limit must be at least {} bytes per second",
+                download::MIN_RATE_LIMIT
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Helper functions
+
+/// Files from before the schema was versioned hold the fields of version 1
+fn from_unversioned(_: &mut Map<String, Value>) {}
@@ -0,0 +1,227 @@
+//! Genetic sex inference and sex chromosome checks
+//!
+//! Sex is inferred from heterozygosity on the X chromosome outside the
+//! pseudoautosomal regions, where males carry a single copy, and from the
+//! share of Y sites called, which females lack. A declared sex that
+//! disagrees suggests a sample swap. A heterozygous X with Y calls suggests
+//! XXY, and an X without heterozygosity or Y calls a single X (45,X) or
+//! loss of heterozygosity on X.
+//!
+//! Arrays such as AncestryDNA report a male's X calls as homozygous pairs.
+//! [`to_hemizygous`] reads them as the single copies they are, and should
+//! only be run when [`SexCheck::is_consistent`] says the male is one.
+
+use crate::genome::{GenomeBuild, Genotype, Variant};
+use crate::store::LoadedGenome;
+use serde::{Deserialize, Serialize};
+
+/// Fewest called X sites for X heterozygosity to be read
+pub const MIN_X_SITES: usize = 50;
+
+/// Fewest Y sites for the Y call rate to be read
+pub const MIN_Y_SITES: usize = 10;
+
+/// X heterozygosity up to which a single X is inferred; above genotyping
+/// error
+pub const MAX_SINGLE_X_HETEROZYGOSITY: f64 = 0.05;
+
+/// X heterozygosity from which two X chromosomes are inferred
+pub const MIN_TWO_X_HETEROZYGOSITY: f64 = 0.15;
+
+/// Share of Y sites called from which a Y chromosome is inferred
+pub const MIN_Y_CALL_RATE: f64 = 0.5;
+
+/// Share of Y sites called up to which no Y chromosome is inferred; arrays
+/// call a few Y probes that cross-hybridize in females
+pub const MAX_NO_Y_CALL_RATE: f64 = 0.1;
+
+/// Pseudoautosomal regions of GRCh36, GRCh37 and GRCh38, inclusive
+const PSEUDOAUTOSOMAL: [(GenomeBuild, &str, u64, u64); 12] = [
+    (GenomeBuild::GRCh36, "X", 1, 2_709_520),
+    (GenomeBuild::GRCh36, "X", 154_584_238, 154_913_754),
+    (GenomeBuild::GRCh36, "Y", 1, 2_709_520),
+    (GenomeBuild::GRCh36, "Y", 57_443_438, 57_772_954),
+    (GenomeBuild::GRCh37, "X", 60_001, 2_699_520),
+    (GenomeBuild::GRCh37, "X", 154_931_044, 155_260_560),
+    (GenomeBuild::GRCh37, "Y", 10_001, 2_649_520),
+    (GenomeBuild::GRCh37, "Y", 59_034_050, 59_363_566),
+    (GenomeBuild::GRCh38, "X", 10_001, 2_781_479),
+    (GenomeBuild::GRCh38, "X", 155_701_383, 156_030_895),
+    (GenomeBuild::GRCh38, "Y", 10_001, 2_781_479),
+    (GenomeBuild::GRCh38, "Y", 56_887_903, 57_217_415),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+/// Something about the sex chromosomes to check before relying on them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SexWarning {
+    /// The declared sex is not the genetic one; the file may be someone
+    /// else's
+    DeclaredMismatch,
+    /// A heterozygous X with Y calls, as in XXY (Klinefelter syndrome) or a
+    /// mix of two samples
+    PossibleXxy,
+    /// An X without heterozygosity and no Y calls, as in 45,X (Turner
+    /// syndrome) or loss of heterozygosity on X
+    PossibleSingleX,
+    /// Too few sex chromosome sites, or values between the sexes
+    Inconclusive,
+}
+
+/// Genetic sex and what it rests on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SexCheck {
+    pub inferred: Option<Sex>,
+    pub declared: Option<Sex>,
+    /// Called X sites outside the pseudoautosomal regions
+    pub x_sites: usize,
+    /// Share of those calls that are heterozygous
+    pub x_heterozygosity: Option<f64>,
+    /// Y sites outside the pseudoautosomal regions, called or not
+    pub y_sites: usize,
+    /// Share of those sites called
+    pub y_call_rate: Option<f64>,
+    pub warnings: Vec<SexWarning>,
+}
+
+impl SexCheck {
+    /// Whether sex was inferred without anything to check, so that
+    /// sex-dependent analyses can rely on it
+    pub fn is_consistent(&self) -> bool {
+        self.inferred.is_some() && self.warnings.is_empty()
+    }
+}
+
+/// Infer sex from the sex chromosomes and check it against `declared`
+pub fn check(genome: &LoadedGenome, declared: Option<Sex>) -> SexCheck {
+    let build = genome.file.genome_build;
+    let (mut x_sites, mut x_heterozygous, mut y_sites, mut y_called) = (0, 0, 0, 0);
+    for variant in genome.variants() {
+        if is_pseudoautosomal(variant, build) {
+            continue;
+        }
+        match variant.chromosome.as_str() {
+            "X" if !variant.genotype.is_no_call() => {
+                x_sites += 1;
+                x_heterozygous += usize::from(is_heterozygous(&variant.genotype));
+            }
+            "Y" => {
+                y_sites += 1;
+                y_called += usize::from(!variant.genotype.is_no_call());
+            }
+            _ => {}
+        }
+    }
+    let x_heterozygosity = (x_sites > 0).then(|| x_heterozygous as f64 / x_sites as f64);
+    let y_call_rate = (y_sites > 0).then(|| y_called as f64 / y_sites as f64);
+
+    // Two X chromosomes, and a Y chromosome, when the calls say so either way
+    let two_x = x_heterozygosity
+        .filter(|_| x_sites >= MIN_X_SITES)
+        .and_then(|het| {
+            if het >= MIN_TWO_X_HETEROZYGOSITY {
+                Some(true)
+            } else if het <= MAX_SINGLE_X_HETEROZYGOSITY {
+                Some(false)
+            } else {
+                None
+            }
+        });
+    let has_y = y_call_rate
+        .filter(|_| y_sites >= MIN_Y_SITES)
+        .and_then(|rate| {
+            if rate >= MIN_Y_CALL_RATE {
+                Some(true)
+            } else if rate <= MAX_NO_Y_CALL_RATE {
+                Some(false)
+            } else {
+                None
+            }
+        });
+
+    let mut warnings = Vec::new();
+    let inferred = match (two_x, has_y) {
+        (Some(false), Some(true) | None) => Some(Sex::Male),
+        (Some(true), Some(false) | None) => Some(Sex::Female),
+        // Without enough X calls the Y chromosome decides
+        (None, Some(true)) if x_sites < MIN_X_SITES => Some(Sex::Male),
+        (None, Some(false)) if x_sites < MIN_X_SITES => Some(Sex::Female),
+        (Some(true), Some(true)) => {
+            warnings.push(SexWarning::PossibleXxy);
+            None
+        }
+        (Some(false), Some(false)) => {
+            warnings.push(SexWarning::PossibleSingleX);
+            None
+        }
+        _ => {
+            warnings.push(SexWarning::Inconclusive);
+            None
+        }
+    };
+    if declared.is_some() && inferred.is_some() && declared != inferred {
+        warnings.push(SexWarning::DeclaredMismatch);
+    }
+
+    SexCheck {
+        inferred,
+        declared,
+        x_sites,
+        x_heterozygosity,
+        y_sites,
+        y_call_rate,
+        warnings,
+    }
+}
+
+/// Copy of a male's genome with homozygous X and Y calls outside the
+/// pseudoautosomal regions read as single hemizygous copies, and how many
+/// were
+pub fn to_hemizygous(genome: &LoadedGenome) -> (LoadedGenome, usize) {
+    let build = genome.file.genome_build;
+    let mut converted = 0;
+    let variants = genome
+        .variants()
+        .iter()
+        .map(|variant| {
+            let mut variant = variant.clone();
+            let sex_chromosome = matches!(variant.chromosome.as_str(), "X" | "Y");
+            if sex_chromosome && !is_pseudoautosomal(&variant, build) {
+                if let Genotype::Diploid { first, second, .. } = &variant.genotype {
+                    if first == second {
+                        variant.genotype = Genotype::Haploid(first.clone());
+                        converted += 1;
+                    }
+                }
+            }
+            variant
+        })
+        .collect();
+    let genome = LoadedGenome::from_variants(genome.file.clone(), genome.summary.clone(), variants);
+    (genome, converted)
+}
+
+/// Whether a variant lies in a pseudoautosomal region, where X and Y pair
+/// like autosomes; on an unknown build, in that of any build
+pub fn is_pseudoautosomal(variant: &Variant, build: Option<GenomeBuild>) -> bool {
+    PSEUDOAUTOSOMAL
+        .iter()
+        .any(|&(region_build, chromosome, start, end)| {
+            build.is_none_or(|build| build == region_build)
+                && variant.chromosome == chromosome
+                && (start..=end).contains(&variant.position)
+        })
+}
+
+// Helper functions
+
+fn is_heterozygous(genotype: &Genotype) -> bool {
+    matches!(genotype, Genotype::Diploid { first, second, .. } if first != second)
+}
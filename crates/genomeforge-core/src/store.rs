@@ -0,0 +1,272 @@
+//! In-memory store for the currently loaded genome
+//!
+//! A [`LoadedGenome`] keeps every parsed variant together with lookup
+//! indexes by rsid and by (chromosome, position), so the analysis engines
+//! can match database records without re-reading the file. Structural
+//! variants are held beside the variants, unindexed.
+
+use crate::genome::{GenomeFile, Genotype, Region, StructuralVariant, Variant};
+use crate::parser::{normalize_chromosome, ParseSummary, SummaryBuilder, VariantSource};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zeroize::Zeroize;
+
+/// Records parsed between calls to a load checkpoint
+pub const CHECKPOINT_INTERVAL: usize = 10_000;
+
+/// A fully parsed genome with lookup indexes
+///
+/// Serializes without the indexes, which are rebuilt when it is read back.
+#[derive(Debug)]
+pub struct LoadedGenome {
+    pub file: GenomeFile,
+    pub summary: ParseSummary,
+    variants: Vec<Variant>,
+    structural: Vec<StructuralVariant>,
+    by_rsid: HashMap<String, usize>,
+    by_position: HashMap<(String, u64), usize>,
+}
+
+impl LoadedGenome {
+    /// Drain a variant source into memory and build the indexes
+    pub fn load(source: &mut dyn VariantSource) -> Result<Self, String> {
+        Self::load_with(source, |_| Ok(()))
+    }
+
+    /// Like [`LoadedGenome::load`], calling `checkpoint` with the number of
+    /// records parsed every [`CHECKPOINT_INTERVAL`] records
+    ///
+    /// An error from `checkpoint` stops the load and is returned as is,
+    /// which is how callers report progress and honour cancellation.
+    pub fn load_with<F>(source: &mut dyn VariantSource, mut checkpoint: F) -> Result<Self, String>
+    where
+        F: FnMut(usize) -> Result<(), String>,
+    {
+        let mut builder = SummaryBuilder::default();
+        let mut variants = Vec::new();
+        for variant in &mut *source {
+            let variant = variant?;
+            builder.add(&variant);
+            variants.push(variant);
+            if variants.len() % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(variants.len())?;
+            }
+        }
+        checkpoint(variants.len())?;
+        let summary = builder.finish(source.skipped_lines());
+        Ok(
+            Self::from_variants(source.genome_file().clone(), summary, variants)
+                .with_structural_variants(source.take_structural_variants()),
+        )
+    }
+
+    /// Index variants that were already parsed; the genome holds no
+    /// structural variants
+    pub fn from_variants(file: GenomeFile, summary: ParseSummary, variants: Vec<Variant>) -> Self {
+        let mut by_rsid = HashMap::with_capacity(variants.len());
+        let mut by_position = HashMap::with_capacity(variants.len());
+
+        // The first record wins when a file lists the same site twice
+        for (index, variant) in variants.iter().enumerate() {
+            if let Some(rsid) = &variant.rsid {
+                by_rsid.entry(rsid.clone()).or_insert(index);
+            }
+            by_position
+                .entry((variant.chromosome.clone(), variant.position))
+                .or_insert(index);
+        }
+
+        LoadedGenome {
+            file,
+            summary,
+            variants,
+            structural: Vec::new(),
+            by_rsid,
+            by_position,
+        }
+    }
+
+    /// The genome holding these structural variants
+    pub fn with_structural_variants(mut self, structural: Vec<StructuralVariant>) -> Self {
+        self.structural = structural;
+        self
+    }
+
+    /// All variants in file order
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    /// Structural variants and copy-number changes in file order
+    pub fn structural_variants(&self) -> &[StructuralVariant] {
+        &self.structural
+    }
+
+    /// Number of variants held
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    /// Whether the genome holds no variants
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Look up a variant by rsid, e.g. "rs429358"
+    pub fn get_by_rsid(&self, rsid: &str) -> Option<&Variant> {
+        self.by_rsid.get(rsid).map(|&index| &self.variants[index])
+    }
+
+    /// The variants in a region, as a genome of their own
+    pub fn region(&self, region: &Region) -> LoadedGenome {
+        let variants: Vec<Variant> = self
+            .variants
+            .iter()
+            .filter(|variant| region.contains(variant))
+            .cloned()
+            .collect();
+        let mut builder = SummaryBuilder::default();
+        for variant in &variants {
+            builder.add(variant);
+        }
+        LoadedGenome::from_variants(self.file.clone(), builder.finish(0), variants)
+    }
+
+    /// Look up a variant by chromosome and 1-based position
+    pub fn get_at(&self, chromosome: &str, position: u64) -> Option<&Variant> {
+        self.by_position
+            .get(&(normalize_chromosome(chromosome), position))
+            .map(|&index| &self.variants[index])
+    }
+
+    /// The call at the site of another genome's variant, looked up by
+    /// position and then by rsid
+    pub fn same_site(&self, variant: &Variant) -> Option<&Variant> {
+        self.get_at(&variant.chromosome, variant.position)
+            .or_else(|| self.get_by_rsid(variant.rsid.as_deref()?))
+    }
+
+    /// Overwrite the genome's calls in memory and empty it
+    ///
+    /// For a genome being discarded, so the calls do not stay readable in
+    /// freed memory.
+    pub fn zeroize(&mut self) {
+        for variant in &mut self.variants {
+            variant.rsid.zeroize();
+            variant.chromosome.zeroize();
+            variant.reference.zeroize();
+            variant.alternates.zeroize();
+            match &mut variant.genotype {
+                Genotype::NoCall => {}
+                Genotype::Haploid(allele) => allele.zeroize(),
+                Genotype::Diploid { first, second, .. } => {
+                    first.zeroize();
+                    second.zeroize();
+                }
+            }
+        }
+        self.variants.clear();
+        for variant in &mut self.structural {
+            variant.id.zeroize();
+            variant.chromosome.zeroize();
+            variant.start.zeroize();
+            variant.end.zeroize();
+        }
+        self.structural.clear();
+        for (mut rsid, _) in self.by_rsid.drain() {
+            rsid.zeroize();
+        }
+        for ((mut chromosome, _), _) in self.by_position.drain() {
+            chromosome.zeroize();
+        }
+    }
+}
+
+/// The parts of a [`LoadedGenome`] that are written out
+#[derive(Serialize)]
+struct StoredGenome<'a> {
+    file: &'a GenomeFile,
+    summary: &'a ParseSummary,
+    variants: &'a [Variant],
+    structural: &'a [StructuralVariant],
+}
+
+#[derive(Deserialize)]
+struct OwnedGenome {
+    file: GenomeFile,
+    summary: ParseSummary,
+    variants: Vec<Variant>,
+    #[serde(default)]
+    structural: Vec<StructuralVariant>,
+}
+
+impl Serialize for LoadedGenome {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredGenome {
+            file: &self.file,
+            summary: &self.summary,
+            variants: &self.variants,
+            structural: &self.structural,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LoadedGenome {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let stored = OwnedGenome::deserialize(deserializer)?;
+        Ok(
+            LoadedGenome::from_variants(stored.file, stored.summary, stored.variants)
+                .with_structural_variants(stored.structural),
+        )
+    }
+}
+
+/// Holds the genome currently loaded in the application
+///
+/// Readers get a cheap [`Arc`] handle so long-running analyses never hold
+/// the lock while a new file is being loaded.
+#[derive(Debug, Default)]
+pub struct GenomeStore {
+    loaded: Mutex<Option<Arc<LoadedGenome>>>,
+}
+
+impl GenomeStore {
+    /// Replace the loaded genome, returning a handle to it
+    pub fn replace(&self, genome: LoadedGenome) -> Arc<LoadedGenome> {
+        let genome = Arc::new(genome);
+        *self.lock() = Some(Arc::clone(&genome));
+        genome
+    }
+
+    /// Load a genome that is already shared, such as one taken earlier
+    pub fn put(&self, genome: Arc<LoadedGenome>) {
+        *self.lock() = Some(genome);
+    }
+
+    /// Unload the genome, returning it
+    pub fn take(&self) -> Option<Arc<LoadedGenome>> {
+        self.lock().take()
+    }
+
+    /// Handle to the loaded genome, if any
+    pub fn current(&self) -> Option<Arc<LoadedGenome>> {
+        self.lock().clone()
+    }
+
+    /// Whether a genome has been loaded
+    pub fn is_loaded(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Drop the loaded genome
+    pub fn clear(&self) {
+        *self.lock() = None;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Arc<LoadedGenome>>> {
+        // A panic while holding the lock cannot leave the Option half-written
+        self.loaded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
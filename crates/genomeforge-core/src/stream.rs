@@ -0,0 +1,307 @@
+//! Memory-budgeted streaming of whole genomes
+//!
+//! A whole-genome VCF holds millions of records, of which the annotation
+//! databases know only a small fraction. [`stream_sites`] reads a file in
+//! chunks and keeps just the variants at sites in a [`SiteFilter`], so an
+//! analysis runs on a genome far smaller than the file while the parse
+//! summary still counts every record.
+//!
+//! Kept variants are held in a [`SpillStore`], which moves them to an
+//! encrypted temporary file once they outgrow their share of the memory
+//! budget. The file is sealed under a random key that only lives in memory
+//! and is deleted when the store is dropped.
+
+use crate::crypto::{self, Key, Zeroizing};
+use crate::genome::Variant;
+use crate::parser::{normalize_chromosome, SummaryBuilder, VariantSource};
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// Memory budget when none is given: 512 MiB
+pub const DEFAULT_MEMORY_BUDGET: u64 = 512 * 1024 * 1024;
+
+/// Rough bytes a parsed variant takes in memory, indexes included
+pub const VARIANT_BYTES: u64 = 256;
+
+/// Fewest records read at a time, however small the budget
+const MIN_CHUNK_RECORDS: usize = 1_000;
+
+/// Associated data of spilled frames
+const SPILL_CONTEXT: &[u8] = b"genomeforge-spill";
+
+/// How much memory streaming may use, and where it may spill
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Bytes; half goes to the chunk being read, half to kept variants
+    pub memory_budget: u64,
+    /// Directory for spill files
+    pub spill_dir: PathBuf,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        StreamOptions {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            spill_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+impl StreamOptions {
+    /// Records read into memory at a time
+    pub fn chunk_records(&self) -> usize {
+        ((self.memory_budget / 2 / VARIANT_BYTES) as usize).max(MIN_CHUNK_RECORDS)
+    }
+
+    /// Bytes of kept variants held in memory before they spill
+    pub fn spill_threshold(&self) -> usize {
+        (self.memory_budget / 2) as usize
+    }
+}
+
+/// What [`stream_sites`] did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStats {
+    pub records: usize,
+    pub kept: usize,
+    pub chunks: usize,
+    /// Bytes written to the spill file, sealed
+    pub spilled_bytes: u64,
+}
+
+/// Sites some database can match, by rsid or by position
+#[derive(Debug, Clone, Default)]
+pub struct SiteFilter {
+    rsids: HashSet<String>,
+    positions: HashSet<(String, u64)>,
+}
+
+impl SiteFilter {
+    pub fn add_rsid(&mut self, rsid: &str) {
+        self.rsids.insert(rsid.to_string());
+    }
+
+    /// A position on any build; 1-based
+    pub fn add_position(&mut self, chromosome: &str, position: u64) {
+        self.positions
+            .insert((normalize_chromosome(chromosome), position));
+    }
+
+    /// Whether a variant is at one of the sites
+    pub fn contains(&self, variant: &Variant) -> bool {
+        variant
+            .rsid
+            .as_ref()
+            .is_some_and(|rsid| self.rsids.contains(rsid))
+            || self
+                .positions
+                .contains(&(variant.chromosome.clone(), variant.position))
+    }
+
+    /// Number of rsids and positions
+    pub fn len(&self) -> usize {
+        self.rsids.len() + self.positions.len()
+    }
+
+    /// Whether no site was added
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Items kept in memory up to a threshold, then in an encrypted file
+///
+/// Items are written as JSON lines; each spill seals the buffered lines as
+/// one frame, numbered so that frames cannot be reordered unnoticed.
+pub struct SpillStore<T> {
+    key: Key,
+    path: PathBuf,
+    file: Option<BufWriter<File>>,
+    /// Whether the spill file was created, and so must be deleted
+    created: bool,
+    pending: Vec<u8>,
+    threshold: usize,
+    frames: u64,
+    len: usize,
+    spilled_bytes: u64,
+    _items: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> SpillStore<T> {
+    /// A store spilling to a new file in `dir` past `threshold` bytes
+    pub fn new(dir: &Path, threshold: usize) -> Self {
+        let name = format!(
+            "genomeforge-spill-{}.bin",
+            hex::encode(crypto::random_bytes::<8>())
+        );
+        SpillStore {
+            key: Key::generate(),
+            path: dir.join(name),
+            file: None,
+            created: false,
+            pending: Vec::new(),
+            threshold,
+            frames: 0,
+            len: 0,
+            spilled_bytes: 0,
+            _items: PhantomData,
+        }
+    }
+
+    pub fn push(&mut self, item: &T) -> Result<(), String> {
+        serde_json::to_writer(&mut self.pending, item)
+            .map_err(|e| format!("Failed to buffer item: {}", e))?;
+        self.pending.push(b'\n');
+        self.len += 1;
+        if self.pending.len() > self.threshold {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Number of items pushed
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes written to the spill file so far
+    pub fn spilled_bytes(&self) -> u64 {
+        self.spilled_bytes
+    }
+
+    /// Every item in the order pushed
+    pub fn into_vec(mut self) -> Result<Vec<T>, String> {
+        let mut items = Vec::with_capacity(self.len);
+        if let Some(mut file) = self.file.take() {
+            file.flush()
+                .map_err(|e| format!("Failed to write spill file: {}", e))?;
+            let mut reader = BufReader::new(
+                File::open(&self.path).map_err(|e| format!("Failed to read spill file: {}", e))?,
+            );
+            for frame in 0..self.frames {
+                let mut length = [0u8; 8];
+                reader
+                    .read_exact(&mut length)
+                    .map_err(|e| format!("Failed to read spill file: {}", e))?;
+                let mut sealed = vec![0u8; u64::from_le_bytes(length) as usize];
+                reader
+                    .read_exact(&mut sealed)
+                    .map_err(|e| format!("Failed to read spill file: {}", e))?;
+                let lines = crypto::open(&self.key, &sealed, &frame_context(frame))?;
+                parse_lines(&lines, &mut items)?;
+            }
+        }
+        parse_lines(&self.pending, &mut items)?;
+        Ok(items)
+    }
+
+    fn spill(&mut self) -> Result<(), String> {
+        let io = |e: std::io::Error| format!("Failed to write spill file: {}", e);
+        if self.file.is_none() {
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&self.path)
+                .map_err(io)?;
+            self.created = true;
+            self.file = Some(BufWriter::new(file));
+        }
+        let sealed = crypto::seal(&self.key, &self.pending, &frame_context(self.frames))?;
+        let file = self.file.as_mut().expect("spill file is open");
+        file.write_all(&(sealed.len() as u64).to_le_bytes())
+            .and_then(|_| file.write_all(&sealed))
+            .map_err(io)?;
+        self.frames += 1;
+        self.spilled_bytes += 8 + sealed.len() as u64;
+        // The plaintext must not linger in freed memory
+        drop(Zeroizing::new(std::mem::take(&mut self.pending)));
+        Ok(())
+    }
+}
+
+impl<T> Drop for SpillStore<T> {
+    fn drop(&mut self) {
+        // Closed first, as Windows cannot delete an open file
+        self.file.take();
+        if self.created {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Stream `source`, keeping the variants at `sites`
+///
+/// The returned genome holds the kept variants, every structural variant,
+/// which are few, and the summary of the whole file. `checkpoint` is called with the number of records read
+/// every [`CHECKPOINT_INTERVAL`] records and stops the stream when it
+/// returns an error.
+pub fn stream_sites<F>(
+    source: &mut dyn VariantSource,
+    sites: &SiteFilter,
+    options: &StreamOptions,
+    mut checkpoint: F,
+) -> Result<(LoadedGenome, StreamStats), String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let chunk_records = options.chunk_records();
+    let mut kept = SpillStore::new(&options.spill_dir, options.spill_threshold());
+    let mut builder = SummaryBuilder::default();
+    let mut stats = StreamStats::default();
+    let mut chunk = Vec::with_capacity(chunk_records.min(1 << 20));
+
+    loop {
+        chunk.clear();
+        for variant in (&mut *source).take(chunk_records) {
+            chunk.push(variant?);
+            stats.records += 1;
+            if stats.records % CHECKPOINT_INTERVAL == 0 {
+                checkpoint(stats.records)?;
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+        stats.chunks += 1;
+        for variant in &chunk {
+            builder.add(variant);
+            if sites.contains(variant) {
+                kept.push(variant)?;
+            }
+        }
+    }
+    checkpoint(stats.records)?;
+
+    stats.kept = kept.len();
+    stats.spilled_bytes = kept.spilled_bytes();
+    let summary = builder.finish(source.skipped_lines());
+    let genome =
+        LoadedGenome::from_variants(source.genome_file().clone(), summary, kept.into_vec()?)
+            .with_structural_variants(source.take_structural_variants());
+    Ok((genome, stats))
+}
+
+// Helper functions
+
+fn frame_context(frame: u64) -> Vec<u8> {
+    let mut context = SPILL_CONTEXT.to_vec();
+    context.extend_from_slice(&frame.to_le_bytes());
+    context
+}
+
+fn parse_lines<T: DeserializeOwned>(lines: &[u8], items: &mut Vec<T>) -> Result<(), String> {
+    for line in lines.split(|&b| b == b'\n').filter(|line| !line.is_empty()) {
+        items.push(serde_json::from_slice(line).map_err(|e| format!("Corrupt spill file: {}", e))?);
+    }
+    Ok(())
+}
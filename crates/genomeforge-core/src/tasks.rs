@@ -0,0 +1,176 @@
+//! Registry of cancellable background tasks
+//!
+//! Long-running work such as parsing or annotation registers itself with a
+//! [`TaskRegistry`] and receives a [`TaskHandle`]. The work loop calls
+//! [`TaskHandle::checkpoint`] regularly; once the task is cancelled the next
+//! checkpoint returns an error and the loop unwinds through its normal
+//! error path. Dropping the handle removes the task from the registry.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Identifier of a registered task
+pub type TaskId = u64;
+
+/// Error returned by a checkpoint once the task was cancelled
+pub const CANCELLED: &str = "Task cancelled";
+
+/// Flag checked by long-running loops to stop early
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    /// Ask the running task to stop at its next checkpoint
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation was requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Kind of work a task performs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskKind {
+    Parse,
+    Analysis,
+    DatabaseUpdate,
+    ReferenceInstall,
+    /// Several genome files analyzed and reported on in one go
+    Batch,
+}
+
+/// Snapshot of a running task
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    pub task_id: TaskId,
+    pub kind: TaskKind,
+    pub elapsed_seconds: f64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug)]
+struct TaskEntry {
+    kind: TaskKind,
+    started: Instant,
+    cancel: CancelFlag,
+}
+
+type TaskMap = Arc<Mutex<HashMap<TaskId, TaskEntry>>>;
+
+/// Tracks the background tasks currently running
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    next_id: AtomicU64,
+    tasks: TaskMap,
+}
+
+impl TaskRegistry {
+    /// Register a new task
+    pub fn start(&self, kind: TaskKind) -> TaskHandle {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = CancelFlag::default();
+        lock(&self.tasks).insert(
+            id,
+            TaskEntry {
+                kind,
+                started: Instant::now(),
+                cancel: cancel.clone(),
+            },
+        );
+        TaskHandle {
+            id,
+            kind,
+            cancel,
+            tasks: Arc::clone(&self.tasks),
+        }
+    }
+
+    /// Request cancellation of a task, returning whether it was running
+    pub fn cancel(&self, id: TaskId) -> bool {
+        match lock(&self.tasks).get(&id) {
+            Some(entry) => {
+                entry.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every running task of one kind, returning how many there were
+    pub fn cancel_kind(&self, kind: TaskKind) -> usize {
+        let tasks = lock(&self.tasks);
+        let matching = tasks.values().filter(|entry| entry.kind == kind);
+        matching.map(|entry| entry.cancel.cancel()).count()
+    }
+
+    /// Running tasks, oldest first
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let mut tasks: Vec<TaskInfo> = lock(&self.tasks)
+            .iter()
+            .map(|(&task_id, entry)| TaskInfo {
+                task_id,
+                kind: entry.kind,
+                elapsed_seconds: entry.started.elapsed().as_secs_f64(),
+                cancelled: entry.cancel.is_cancelled(),
+            })
+            .collect();
+        tasks.sort_by_key(|task| task.task_id);
+        tasks
+    }
+}
+
+/// A registered task; dropping it unregisters the task
+#[derive(Debug)]
+pub struct TaskHandle {
+    id: TaskId,
+    kind: TaskKind,
+    cancel: CancelFlag,
+    tasks: TaskMap,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    pub fn kind(&self) -> TaskKind {
+        self.kind
+    }
+
+    /// Flag shared with the registry, for work that runs on another thread
+    pub fn cancel_flag(&self) -> CancelFlag {
+        self.cancel.clone()
+    }
+
+    /// Cancellation point for work loops
+    pub fn checkpoint(&self) -> Result<(), String> {
+        checkpoint(&self.cancel)
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        lock(&self.tasks).remove(&self.id);
+    }
+}
+
+/// Cancellation point for code that only holds the flag
+pub fn checkpoint(cancel: &CancelFlag) -> Result<(), String> {
+    if cancel.is_cancelled() {
+        Err(CANCELLED.to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn lock(tasks: &TaskMap) -> MutexGuard<'_, HashMap<TaskId, TaskEntry>> {
+    // Entries are inserted and removed whole, so a poisoned map is consistent
+    tasks.lock().unwrap_or_else(|e| e.into_inner())
+}
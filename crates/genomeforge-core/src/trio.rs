@@ -0,0 +1,308 @@
+//! Trio analysis of a child and both parents
+//!
+//! Comparing a child's genotypes with both parents' shows three things a
+//! single genome cannot. Child genotypes neither parent could have passed
+//! on are Mendelian inconsistencies: a few point to genotyping errors, many
+//! to a sample mix-up or misattributed parentage. Pathogenic variants the
+//! child inherited from different parents in one recessive gene sit on
+//! opposite copies of it, so they are compound heterozygous candidates. And
+//! a recessive condition both parents carry may affect each further child.
+//!
+//! Only autosomal sites called in all three genomes are checked for
+//! consistency, as X and Y calls depend on sex. The genomes must be on the
+//! same build, since sites are matched by position before rsid.
+
+use crate::annotation::alternate_copies;
+use crate::annotation::carrier::{CarrierGene, CarrierInheritance, CarrierResult, CarrierStatus};
+use crate::annotation::clinvar::ClinVarMatch;
+use crate::annotation::zygosity::{self, InheritanceMode};
+use crate::genome::Genotype;
+use crate::parser::is_autosome;
+use crate::store::{LoadedGenome, CHECKPOINT_INTERVAL};
+use serde::Serialize;
+
+/// Inconsistent sites listed in full; the rest are only counted
+pub const MAX_LISTED_ERRORS: usize = 1_000;
+
+/// Share of inconsistent sites above which a parent is unlikely to be the
+/// biological parent or the samples were swapped, rather than the calls
+/// being wrong; arrays miscall well under 1% of sites
+pub const PARENTAGE_ERROR_RATE: f64 = 0.01;
+
+/// Which parent a variant came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Parent {
+    Mother,
+    Father,
+}
+
+/// A child genotype neither parent could have passed on
+#[derive(Debug, Clone, Serialize)]
+pub struct MendelianError {
+    pub rsid: Option<String>,
+    pub chromosome: String,
+    pub position: u64,
+    pub child: Genotype,
+    pub mother: Genotype,
+    pub father: Genotype,
+}
+
+/// Mendelian consistency of a trio
+#[derive(Debug, Clone, Serialize)]
+pub struct MendelianCheck {
+    /// Autosomal sites called in all three genomes
+    pub sites_compared: usize,
+    pub error_count: usize,
+    /// Fraction of compared sites inconsistent (0.0 - 1.0)
+    pub error_rate: f64,
+    /// The first [`MAX_LISTED_ERRORS`] inconsistent sites
+    pub errors: Vec<MendelianError>,
+    /// Whether the error rate exceeds [`PARENTAGE_ERROR_RATE`]
+    pub parentage_doubtful: bool,
+}
+
+/// Pathogenic variants in one recessive gene, by parent of origin
+#[derive(Debug, Clone)]
+pub struct CompoundHetCandidate<'a> {
+    pub gene: String,
+    pub maternal: Vec<ClinVarMatch<'a>>,
+    pub paternal: Vec<ClinVarMatch<'a>>,
+    /// Variants both or neither parent carry, whose origin is unknown
+    pub unresolved: Vec<ClinVarMatch<'a>>,
+}
+
+impl CompoundHetCandidate<'_> {
+    /// Whether variants from each parent were found, and so are on
+    /// opposite copies of the gene
+    pub fn in_trans(&self) -> bool {
+        !self.maternal.is_empty() && !self.paternal.is_empty()
+    }
+}
+
+/// Chance that a child of the two parents is affected by a condition
+#[derive(Debug, Clone, Serialize)]
+pub struct CoupleRisk {
+    pub gene: &'static CarrierGene,
+    pub mother: Option<CarrierStatus>,
+    pub father: Option<CarrierStatus>,
+    /// Chance each child is affected (0.0 - 1.0); for X-linked conditions
+    /// sons are at higher risk than this average
+    pub risk_per_child: f64,
+    /// Chance each son is affected, for X-linked conditions
+    pub risk_per_son: Option<f64>,
+}
+
+/// Check every autosomal site called in all three genomes
+///
+/// `checkpoint` is called with the number of child variants checked every
+/// [`CHECKPOINT_INTERVAL`] variants and stops the check when it returns an
+/// error.
+pub fn mendelian_check<F>(
+    child: &LoadedGenome,
+    mother: &LoadedGenome,
+    father: &LoadedGenome,
+    mut checkpoint: F,
+) -> Result<MendelianCheck, String>
+where
+    F: FnMut(usize) -> Result<(), String>,
+{
+    let mut sites_compared = 0;
+    let mut error_count = 0;
+    let mut errors = Vec::new();
+
+    for (index, variant) in child.variants().iter().enumerate() {
+        if index % CHECKPOINT_INTERVAL == 0 {
+            checkpoint(index)?;
+        }
+        if !is_autosome(&variant.chromosome) {
+            continue;
+        }
+        let (Some(from_mother), Some(from_father)) =
+            (mother.same_site(variant), father.same_site(variant))
+        else {
+            continue;
+        };
+        let (Some(child_alleles), Some(mother_alleles), Some(father_alleles)) = (
+            variant.genotype.diploid(),
+            from_mother.genotype.diploid(),
+            from_father.genotype.diploid(),
+        ) else {
+            continue;
+        };
+
+        sites_compared += 1;
+        let (a, b) = child_alleles;
+        let passed_on = |mother_allele: &str, father_allele: &str| {
+            [mother_alleles.0, mother_alleles.1].contains(&mother_allele)
+                && [father_alleles.0, father_alleles.1].contains(&father_allele)
+        };
+        if passed_on(a, b) || passed_on(b, a) {
+            continue;
+        }
+        error_count += 1;
+        if errors.len() < MAX_LISTED_ERRORS {
+            errors.push(MendelianError {
+                rsid: variant.rsid.clone(),
+                chromosome: variant.chromosome.clone(),
+                position: variant.position,
+                child: variant.genotype.clone(),
+                mother: from_mother.genotype.clone(),
+                father: from_father.genotype.clone(),
+            });
+        }
+    }
+    checkpoint(child.len())?;
+
+    let error_rate = if sites_compared == 0 {
+        0.0
+    } else {
+        error_count as f64 / sites_compared as f64
+    };
+    Ok(MendelianCheck {
+        sites_compared,
+        error_count,
+        error_rate,
+        errors,
+        parentage_doubtful: error_rate > PARENTAGE_ERROR_RATE,
+    })
+}
+
+/// Which parent passed on the allele of a match, when only one carries it
+pub fn parent_of_origin(
+    found: &ClinVarMatch<'_>,
+    mother: &LoadedGenome,
+    father: &LoadedGenome,
+) -> Option<Parent> {
+    let carries = |parent: &LoadedGenome| {
+        parent.same_site(found.variant).and_then(|variant| {
+            alternate_copies(
+                variant,
+                &found.strand.orient(&found.record.reference),
+                &found.strand.orient(&found.record.alternate),
+            )
+        })
+    };
+    match (carries(mother)?, carries(father)?) {
+        (m, 0) if m > 0 => Some(Parent::Mother),
+        (0, f) if f > 0 => Some(Parent::Father),
+        _ => None,
+    }
+}
+
+/// Recessive genes in which the child has two or more heterozygous
+/// pathogenic variants, with the parent each came from
+///
+/// `child_matches` are the child's ClinVar matches. Candidates with a
+/// variant from each parent come first.
+pub fn compound_heterozygous<'a>(
+    child_matches: &[ClinVarMatch<'a>],
+    mother: &LoadedGenome,
+    father: &LoadedGenome,
+) -> Vec<CompoundHetCandidate<'a>> {
+    let mut candidates: Vec<CompoundHetCandidate<'a>> = Vec::new();
+    for found in child_matches {
+        let recessive =
+            zygosity::inheritance(found.record) == Some(InheritanceMode::AutosomalRecessive);
+        if !found.record.significance.is_pathogenic()
+            || !recessive
+            || !found.variant.genotype.is_heterozygous()
+            || found.alternate_copies != 1
+        {
+            continue;
+        }
+        let origin = parent_of_origin(found, mother, father);
+        for gene in &found.record.genes {
+            let candidate = match candidates.iter_mut().position(|c| &c.gene == gene) {
+                Some(index) => &mut candidates[index],
+                None => {
+                    candidates.push(CompoundHetCandidate {
+                        gene: gene.clone(),
+                        maternal: Vec::new(),
+                        paternal: Vec::new(),
+                        unresolved: Vec::new(),
+                    });
+                    candidates.last_mut().expect("just pushed")
+                }
+            };
+            match origin {
+                Some(Parent::Mother) => candidate.maternal.push(*found),
+                Some(Parent::Father) => candidate.paternal.push(*found),
+                None => candidate.unresolved.push(*found),
+            }
+        }
+    }
+
+    candidates.retain(|c| c.maternal.len() + c.paternal.len() + c.unresolved.len() > 1);
+    candidates.sort_by_key(|c| !c.in_trans());
+    candidates
+}
+
+/// Conditions the parents' carrier results put their children at risk of
+///
+/// Takes the results of [`carrier::screen`](crate::annotation::carrier::screen)
+/// for each parent. An autosomal recessive condition needs both parents to
+/// pass on a pathogenic variant; an X-linked one only the mother, for sons.
+pub fn carrier_couples(
+    mother: &[CarrierResult<'_>],
+    father: &[CarrierResult<'_>],
+) -> Vec<CoupleRisk> {
+    let status = |results: &[CarrierResult<'_>], gene: &str| {
+        results
+            .iter()
+            .find(|result| result.gene.gene == gene)
+            .map(|result| result.status)
+    };
+    let mut genes: Vec<&'static CarrierGene> = Vec::new();
+    for result in mother.iter().chain(father) {
+        if !genes.iter().any(|gene| gene.gene == result.gene.gene) {
+            genes.push(result.gene);
+        }
+    }
+
+    let mut risks: Vec<CoupleRisk> = genes
+        .into_iter()
+        .filter_map(|gene| {
+            let from_mother = status(mother, gene.gene);
+            let from_father = status(father, gene.gene);
+            let p_mother = from_mother.map_or(0.0, transmission);
+            let p_father = from_father.map_or(0.0, transmission);
+            let (risk_per_child, risk_per_son) = match gene.inheritance {
+                CarrierInheritance::AutosomalRecessive => (p_mother * p_father, None),
+                // Sons get their only X from their mother; daughters are
+                // affected only if their father is as well
+                CarrierInheritance::XLinkedRecessive => {
+                    let daughters = if from_father == Some(CarrierStatus::Hemizygous) {
+                        p_mother
+                    } else {
+                        0.0
+                    };
+                    ((p_mother + daughters) / 2.0, Some(p_mother))
+                }
+            };
+            (risk_per_child > 0.0).then_some(CoupleRisk {
+                gene,
+                mother: from_mother,
+                father: from_father,
+                risk_per_child,
+                risk_per_son,
+            })
+        })
+        .collect();
+    risks.sort_by(|a, b| b.risk_per_child.total_cmp(&a.risk_per_child));
+    risks
+}
+
+// Helper functions
+
+/// Chance a parent with this status passes on a pathogenic variant
+///
+/// Two different variants are taken to be on the same copy, as genotypes
+/// cannot tell; on opposite copies every child would inherit one of them.
+fn transmission(status: CarrierStatus) -> f64 {
+    match status {
+        CarrierStatus::Carrier => 0.5,
+        CarrierStatus::PossibleCompoundHeterozygous => 0.5,
+        CarrierStatus::Homozygous | CarrierStatus::Hemizygous => 1.0,
+    }
+}
@@ -0,0 +1,120 @@
+//! New files in a watched folder
+//!
+//! [`FolderWatch`] polls a folder rather than subscribing to change
+//! notifications, which network shares often do not deliver. A file is
+//! reported once it has stopped changing between two polls, so one still
+//! being copied in is not picked up half-written, and again only if it is
+//! replaced. Files already in the folder when watching starts are not
+//! reported, nor are hidden files and the partial files of downloads in
+//! progress.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Extensions of files still being downloaded or written
+const PARTIAL_EXTENSIONS: [&str; 5] = ["part", "partial", "crdownload", "download", "tmp"];
+
+/// Polls a folder for files added to it
+#[derive(Debug)]
+pub struct FolderWatch {
+    dir: PathBuf,
+    /// Files reported or there from the start, as last seen
+    known: HashMap<PathBuf, Snapshot>,
+    /// New files not yet seen unchanged
+    settling: HashMap<PathBuf, Snapshot>,
+}
+
+impl FolderWatch {
+    /// Start watching `dir`, taking the files in it as known
+    pub fn new(dir: &Path) -> Result<Self, String> {
+        let known = scan(dir)?;
+        Ok(FolderWatch {
+            dir: dir.to_path_buf(),
+            known,
+            settling: HashMap::new(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether a file is new and has not yet stopped changing
+    pub fn settling(&self, path: &Path) -> bool {
+        self.settling.contains_key(path)
+    }
+
+    /// Files added or replaced since they were last seen that have stopped
+    /// changing, in name order
+    pub fn poll(&mut self) -> Result<Vec<PathBuf>, String> {
+        let current = scan(&self.dir)?;
+        self.known.retain(|path, _| current.contains_key(path));
+        self.settling.retain(|path, _| current.contains_key(path));
+
+        let mut ready = Vec::new();
+        for (path, snapshot) in current {
+            if self.known.get(&path) == Some(&snapshot) {
+                continue;
+            }
+            match self.settling.insert(path.clone(), snapshot) {
+                Some(previous) if previous == snapshot && snapshot.len > 0 => {
+                    self.settling.remove(&path);
+                    self.known.insert(path.clone(), snapshot);
+                    ready.push(path);
+                }
+                _ => {}
+            }
+        }
+        ready.sort();
+        Ok(ready)
+    }
+}
+
+// Helper functions
+
+/// What tells a file has changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Snapshot {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The files directly in `dir` that may be genome files
+fn scan(dir: &Path) -> Result<HashMap<PathBuf, Snapshot>, String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files = HashMap::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() || !candidate(&path) {
+            continue;
+        }
+        let snapshot = Snapshot {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        };
+        files.insert(path, snapshot);
+    }
+    Ok(files)
+}
+
+fn candidate(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let partial = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            PARTIAL_EXTENSIONS
+                .iter()
+                .any(|partial| ext.eq_ignore_ascii_case(partial))
+        });
+    !name.starts_with('.') && !name.starts_with("~$") && !partial
+}
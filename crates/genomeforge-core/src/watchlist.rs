@@ -0,0 +1,270 @@
+//! Variants and regions the user keeps watch on
+//!
+//! A [`Watchlist`] names rsids, genes and regions whose calls every analysis
+//! reports whether or not a database has anything to say about them, and
+//! flags those the raw data does not cover, so someone following up a
+//! variant found in a relative learns whether their own test even typed it.
+//! Which variants a person watches says what they fear they carry, so the
+//! list is sealed with [`crypto::write_file`] like the notebook.
+
+use crate::annotation::genes;
+use crate::crypto::{self, KeySource, Zeroizing};
+use crate::genome::{GenomeBuild, Region, Variant};
+use crate::store::LoadedGenome;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File name of a profile's watchlist
+pub const FILE_NAME: &str = "watchlist.gfwatch";
+
+/// Most entries a watchlist holds
+pub const MAX_ENTRIES: usize = 500;
+
+/// Most calls reported for one gene or region
+pub const MAX_REGION_CALLS: usize = 100;
+
+/// Longest label kept, in characters
+pub const MAX_LABEL_LENGTH: usize = 200;
+
+/// Kind recorded in the header of watchlists
+const KIND: &str = "watchlist";
+
+/// A variant, gene or region on the watchlist
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchEntry {
+    /// An rsid such as "rs80357906", a gene symbol, or a region on the
+    /// genome file's build such as "17:43044295-43125483"
+    pub target: String,
+    /// Why the entry is watched, e.g. "Mother's BRCA1 variant"
+    #[serde(default)]
+    pub label: String,
+}
+
+/// What an entry's target names
+#[derive(Debug, Clone)]
+pub enum Target {
+    Rsid(String),
+    Gene(&'static genes::GeneLocation),
+    Region(Region),
+}
+
+impl WatchEntry {
+    pub fn new(target: &str, label: &str) -> Result<Self, String> {
+        let label = label.trim();
+        if label.chars().count() > MAX_LABEL_LENGTH {
+            return Err(format!(
+                "Labels are limited to {} characters",
+                MAX_LABEL_LENGTH
+            ));
+        }
+        let entry = WatchEntry {
+            target: target.trim().to_string(),
+            label: label.to_string(),
+        };
+        entry.target()?;
+        Ok(entry)
+    }
+
+    /// What the target names; a bare word is a gene unless it names a
+    /// chromosome
+    pub fn target(&self) -> Result<Target, String> {
+        let target = self.target.trim();
+        let lower = target.to_ascii_lowercase();
+        if lower.len() > 2
+            && lower.starts_with("rs")
+            && lower[2..].bytes().all(|b| b.is_ascii_digit())
+        {
+            return Ok(Target::Rsid(lower));
+        }
+        if let Some(gene) = genes::find(target) {
+            return Ok(Target::Gene(gene));
+        }
+        let region: Region = target.parse()?;
+        let chromosome = region.chromosome.as_str();
+        let is_chromosome = chromosome
+            .parse::<u8>()
+            .is_ok_and(|n| (1..=22).contains(&n))
+            || ["X", "Y", "MT"].contains(&chromosome);
+        if !is_chromosome {
+            return Err(format!(
+                "Unknown gene {}; watch an rsid or a range such as 17:43044295-43125483",
+                target
+            ));
+        }
+        Ok(Target::Region(region))
+    }
+}
+
+/// A profile's watchlist
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub entries: Vec<WatchEntry>,
+}
+
+#[derive(Serialize)]
+struct ListInfo {
+    entries: usize,
+}
+
+impl Watchlist {
+    /// Read the watchlist at `path`; a missing file is an empty list
+    pub fn open(path: &Path, key: KeySource<'_>) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let (_, plaintext) = crypto::read_file::<serde_json::Value>(path, KIND, key)?;
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Watchlist is corrupt: {}", e))
+    }
+
+    /// Write the watchlist to `path`
+    pub fn save(&self, path: &Path, key: KeySource<'_>) -> Result<(), String> {
+        let plaintext = Zeroizing::new(
+            serde_json::to_vec(self)
+                .map_err(|e| format!("Failed to serialize watchlist: {}", e))?,
+        );
+        let info = ListInfo {
+            entries: self.entries.len(),
+        };
+        crypto::write_file(path, KIND, &info, &plaintext, key).map(|_| ())
+    }
+
+    /// Add an entry, or relabel the one watching the same target
+    pub fn add(&mut self, entry: WatchEntry) -> Result<(), String> {
+        if let Some(existing) = self
+            .entries
+            .iter_mut()
+            .find(|existing| existing.target.eq_ignore_ascii_case(&entry.target))
+        {
+            existing.label = entry.label;
+            return Ok(());
+        }
+        if self.entries.len() >= MAX_ENTRIES {
+            return Err(format!("A watchlist holds at most {} entries", MAX_ENTRIES));
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Remove the entry watching `target`, returning whether there was one
+    pub fn remove(&mut self, target: &str) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|entry| !entry.target.eq_ignore_ascii_case(target.trim()));
+        self.entries.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The calls at every entry, in list order; genes are located on
+    /// `build`, the genome's own
+    pub fn check(&self, genome: &LoadedGenome, build: Option<GenomeBuild>) -> Vec<WatchResult> {
+        self.entries
+            .iter()
+            .map(|entry| check_entry(genome, entry, build))
+            .collect()
+    }
+}
+
+/// How well the raw data covers an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStatus {
+    /// Genotyped, at one site at least for a gene or region
+    Called,
+    /// In the data, but without a genotype at any site
+    NoCall,
+    /// Not in the data at all
+    Missing,
+    /// A gene that cannot be located, the genome build being unknown
+    Unresolved,
+}
+
+/// A call at a watched site
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchedCall {
+    pub rsid: Option<String>,
+    pub chromosome: String,
+    pub position: u64,
+    /// e.g. "AG", or "--" for no call
+    pub genotype: String,
+}
+
+/// What the genome holds for a watchlist entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchResult {
+    #[serde(flatten)]
+    pub entry: WatchEntry,
+    pub status: WatchStatus,
+    /// Where a gene or region was looked for
+    pub region: Option<Region>,
+    /// At most [`MAX_REGION_CALLS`], in position order
+    pub calls: Vec<WatchedCall>,
+    /// Calls in the region beyond those reported
+    #[serde(default)]
+    pub omitted: usize,
+}
+
+// Helper functions
+
+fn check_entry(
+    genome: &LoadedGenome,
+    entry: &WatchEntry,
+    build: Option<GenomeBuild>,
+) -> WatchResult {
+    let region = match entry.target() {
+        Ok(Target::Rsid(rsid)) => {
+            let sites: Vec<&Variant> = genome.get_by_rsid(&rsid).into_iter().collect();
+            return result(entry, None, sites);
+        }
+        Ok(Target::Gene(gene)) => build.and_then(|build| gene.region(build)),
+        Ok(Target::Region(region)) => Some(region),
+        // Entries are checked when added, so only a list written by hand
+        // gets here
+        Err(_) => None,
+    };
+    let Some(region) = region else {
+        return WatchResult {
+            entry: entry.clone(),
+            status: WatchStatus::Unresolved,
+            region: None,
+            calls: Vec::new(),
+            omitted: 0,
+        };
+    };
+    let mut sites: Vec<&Variant> = genome
+        .variants()
+        .iter()
+        .filter(|variant| region.contains(variant))
+        .collect();
+    sites.sort_by_key(|variant| variant.position);
+    result(entry, Some(region), sites)
+}
+
+fn result(entry: &WatchEntry, region: Option<Region>, sites: Vec<&Variant>) -> WatchResult {
+    let status = if sites.is_empty() {
+        WatchStatus::Missing
+    } else if sites.iter().all(|variant| variant.genotype.is_no_call()) {
+        WatchStatus::NoCall
+    } else {
+        WatchStatus::Called
+    };
+    let omitted = sites.len().saturating_sub(MAX_REGION_CALLS);
+    WatchResult {
+        entry: entry.clone(),
+        status,
+        region,
+        calls: sites.into_iter().take(MAX_REGION_CALLS).map(call).collect(),
+        omitted,
+    }
+}
+
+fn call(variant: &Variant) -> WatchedCall {
+    WatchedCall {
+        rsid: variant.rsid.clone(),
+        chromosome: variant.chromosome.clone(),
+        position: variant.position,
+        genotype: variant.genotype.to_string(),
+    }
+}
@@ -0,0 +1,168 @@
+//! Pre-flight estimates of parsing and analysing a genome file
+//!
+//! Before committing to a heavy operation, the start of the file is parsed
+//! for real: the bytes the first variants took extrapolate to the variant
+//! count of the whole file, and the time they took to the duration on this
+//! machine, whatever its disk and processor. Memory follows from the
+//! variant count. The figures are rough: headers, long indels and a file
+//! denser at its end than at its start all throw them off.
+
+use crate::parser::{self, progress::ByteCounter};
+use serde::Serialize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Variants parsed to sample a file
+pub const SAMPLE_VARIANTS: usize = 200_000;
+
+/// Bytes in memory per parsed and indexed variant, roughly
+pub const MEMORY_PER_VARIANT: u64 = 250;
+
+/// How long annotating a variant takes on one thread compared with parsing
+/// it, roughly
+pub const ANNOTATION_COST: f64 = 1.5;
+
+/// Share of the available memory a parse may use before a warning
+pub const MEMORY_HEADROOM: f64 = 0.8;
+
+/// The start of a file, parsed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub variants: usize,
+    /// Bytes read from disk for them
+    pub bytes_read: u64,
+    pub elapsed: Duration,
+    /// Whether the whole file was parsed, so the count is exact
+    pub complete: bool,
+}
+
+impl Sample {
+    /// Parse up to `limit` variants from the start of the file at `path`
+    pub fn read(path: &Path, limit: usize) -> Result<Self, String> {
+        let counter = ByteCounter::default();
+        let started = Instant::now();
+        let mut source = parser::open_genome_counted(path, &counter)?;
+        let mut variants = 0;
+        let mut complete = true;
+        for variant in source.by_ref() {
+            variant?;
+            variants += 1;
+            if variants >= limit {
+                complete = false;
+                break;
+            }
+        }
+        Ok(Sample {
+            variants,
+            bytes_read: counter.get(),
+            elapsed: started.elapsed(),
+            complete,
+        })
+    }
+}
+
+/// The memory and processors of a machine, as far as they are known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Machine {
+    pub memory_total: Option<u64>,
+    pub memory_available: Option<u64>,
+    pub cpu_cores: usize,
+}
+
+/// What parsing and analysing a file is expected to take
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkloadEstimate {
+    /// Bytes on disk
+    pub size: u64,
+    pub sampled_variants: usize,
+    /// Variants in the file; exact when the sample covered it all
+    pub variants: u64,
+    pub exact: bool,
+    /// Rough memory the parsed genome needs
+    pub memory_needed: u64,
+    pub memory_available: Option<u64>,
+    pub memory_total: Option<u64>,
+    pub cpu_cores: usize,
+    /// Whether the genome fits in the available memory; unknown when that
+    /// is
+    pub memory_sufficient: Option<bool>,
+    /// Rough seconds parsing takes
+    pub parse_seconds: f64,
+    /// Rough seconds annotating the parsed genome takes
+    pub analysis_seconds: f64,
+    pub warnings: Vec<String>,
+}
+
+impl WorkloadEstimate {
+    /// Extrapolate from the sample of a file of `size` bytes
+    pub fn new(size: u64, sample: &Sample, machine: &Machine) -> Self {
+        let variants = if sample.complete || sample.bytes_read == 0 {
+            sample.variants as u64
+        } else {
+            (sample.variants as f64 * size as f64 / sample.bytes_read as f64).round() as u64
+        };
+        let scale = if sample.variants == 0 {
+            0.0
+        } else {
+            variants as f64 / sample.variants as f64
+        };
+        let parse_seconds = sample.elapsed.as_secs_f64() * scale;
+        let analysis_seconds = parse_seconds * ANNOTATION_COST / machine.cpu_cores.max(1) as f64;
+        let memory_needed = variants.saturating_mul(MEMORY_PER_VARIANT);
+        let memory_sufficient = machine
+            .memory_available
+            .map(|available| memory_needed as f64 <= available as f64 * MEMORY_HEADROOM);
+
+        let mut warnings = Vec::new();
+        if memory_sufficient == Some(false) {
+            let available = machine.memory_available.unwrap_or_default();
+            let advice = if machine
+                .memory_total
+                .is_some_and(|total| memory_needed > total)
+            {
+                "This computer may not have enough memory for it; importing a region or a smaller file avoids running out."
+            } else {
+                "Closing other applications before continuing makes it less likely to run out of memory."
+            };
+            warnings.push(format!(
+                "This genome needs about {} of memory, and {} is available. {}",
+                gigabytes(memory_needed),
+                gigabytes(available),
+                advice
+            ));
+        }
+        if sample.variants == 0 {
+            warnings.push("No variants were found at the start of the file.".to_string());
+        }
+
+        WorkloadEstimate {
+            size,
+            sampled_variants: sample.variants,
+            variants,
+            exact: sample.complete,
+            memory_needed,
+            memory_available: machine.memory_available,
+            memory_total: machine.memory_total,
+            cpu_cores: machine.cpu_cores,
+            memory_sufficient,
+            parse_seconds,
+            analysis_seconds,
+            warnings,
+        }
+    }
+}
+
+/// Sample the file at `path` and estimate what it takes on `machine`
+pub fn estimate(path: &Path, machine: &Machine) -> Result<WorkloadEstimate, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    let sample = Sample::read(path, SAMPLE_VARIANTS)?;
+    Ok(WorkloadEstimate::new(size, &sample, machine))
+}
+
+// Helper functions
+
+fn gigabytes(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / 1e9)
+}
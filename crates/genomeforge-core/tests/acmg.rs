@@ -0,0 +1,57 @@
+//! ACMG secondary findings screening tests
+
+mod common;
+
+use common::{load_calls, load_database};
+use genomeforge_core::annotation::acmg::{self, Inheritance};
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+17\t43045712\t17661\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357906\n\
+1\t45331556\t5294\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=MUTYH-associated_polyposis;GENEINFO=MUTYH:4595;RS=36053993\n\
+6\t26092913\t9\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=Hemochromatosis_type_1;GENEINFO=HFE:3077;RS=1800562\n\
+6\t26091179\t10\tC\tG\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Hemochromatosis_type_1;GENEINFO=HFE:3077;RS=1799945\n\
+1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Homocystinuria;GENEINFO=MTHFR:4524;RS=1801133\n";
+
+#[test]
+fn lists_the_acmg_sf_genes() {
+    assert_eq!(acmg::GENES.len(), 81);
+    let mutyh = acmg::gene("mutyh").unwrap();
+    assert_eq!(mutyh.inheritance, Inheritance::Recessive);
+    assert_eq!(acmg::gene("HFE").unwrap().only_rsid, Some("rs1800562"));
+    assert!(acmg::gene("MTHFR").is_none());
+}
+
+#[test]
+fn screens_by_inheritance() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let heterozygous = load_calls(
+        38,
+        &[
+            ("rs80357906", "17", 43045712, "AG"),
+            ("rs36053993", "1", 45331556, "CT"),
+            ("rs1800562", "6", 26092913, "AG"),
+            ("rs1799945", "6", 26091179, "CG"),
+            ("rs1801133", "1", 11796321, "AA"),
+        ],
+    );
+    let matches = db.annotate(&heterozygous, |_| Ok(())).unwrap();
+    let findings = acmg::screen(&matches);
+    // One BRCA1 allele is reportable; one MUTYH allele is not, HFE only
+    // counts p.C282Y and MTHFR is not on the list
+    let genes: Vec<&str> = findings.iter().map(|f| f.gene.gene).collect();
+    assert_eq!(genes, ["BRCA1"]);
+
+    let homozygous = load_calls(
+        38,
+        &[
+            ("rs36053993", "1", 45331556, "TT"),
+            ("rs1800562", "6", 26092913, "AA"),
+        ],
+    );
+    let matches = db.annotate(&homozygous, |_| Ok(())).unwrap();
+    let findings = acmg::screen(&matches);
+    let genes: Vec<&str> = findings.iter().map(|f| f.gene.gene).collect();
+    assert_eq!(genes, ["MUTYH", "HFE"]);
+}
@@ -0,0 +1,115 @@
+//! Ancestry composition tests
+
+mod common;
+
+use genomeforge_core::admixture::{ReferencePanel, MIN_SITES};
+use genomeforge_core::LoadedGenome;
+
+/// Frequencies of the panel allele in Northern and Southern European and
+/// West African populations, cycled over the sites
+const PATTERNS: [[f64; 3]; 4] = [
+    [0.9, 0.8, 0.1],
+    [0.1, 0.2, 0.9],
+    [0.8, 0.3, 0.5],
+    [0.3, 0.8, 0.4],
+];
+
+fn panel_text(sites: usize) -> String {
+    let mut contents = "##genome_build=GRCh37\n\
+rsid\tchromosome\tposition\tallele\tother_allele\tEuropean/Northern\tEuropean/Southern\tAfrican/West\n"
+        .to_string();
+    for site in 0..sites {
+        let [north, south, west] = PATTERNS[site % PATTERNS.len()];
+        contents.push_str(&format!(
+            "rs{}\t1\t{}\tA\tG\t{}\t{}\t{}\n",
+            site + 1,
+            1000 + site,
+            north,
+            south,
+            west
+        ));
+    }
+    // A/T SNPs are never used
+    contents.push_str("rs999999\t2\t500\tA\tT\t0.9\t0.9\t0.1\n");
+    contents
+}
+
+/// Genome carrying the likelier genotype of population `k` at every site
+fn load_genome(sites: usize, k: usize) -> LoadedGenome {
+    let mut contents = "# build 37\n# rsid\tchromosome\tposition\tgenotype\n".to_string();
+    for site in 0..sites {
+        let frequency = PATTERNS[site % PATTERNS.len()][k];
+        let genotype = match frequency {
+            f if f > 0.6 => "AA",
+            f if f < 0.4 => "GG",
+            // Reported on the opposite strand
+            _ => "CT",
+        };
+        contents.push_str(&format!(
+            "rs{}\t1\t{}\t{}\n",
+            site + 1,
+            1000 + site,
+            genotype
+        ));
+    }
+    contents.push_str("rs999999\t2\t500\tAA\n");
+    common::load_genome("genome.txt", &contents)
+}
+
+#[test]
+fn reads_populations_from_the_header() {
+    let panel = ReferencePanel::from_reader(panel_text(8).as_bytes()).unwrap();
+    assert_eq!(panel.len(), 9);
+    assert_eq!(
+        panel.genome_build,
+        Some(genomeforge_core::GenomeBuild::GRCh37)
+    );
+    let names: Vec<(&str, &str)> = panel
+        .populations
+        .iter()
+        .map(|p| (p.continent.as_str(), p.name.as_str()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("European", "Northern"),
+            ("European", "Southern"),
+            ("African", "West")
+        ]
+    );
+    assert_eq!(panel.sites[1].frequencies, [0.1, 0.2, 0.9]);
+
+    let invalid = "rsid\tallele\tother_allele\tEuropean\nrs1\tA\tG\t1.5\n";
+    assert!(ReferencePanel::from_reader(invalid.as_bytes()).is_err());
+}
+
+#[test]
+fn estimates_proportions_with_intervals() {
+    let panel = ReferencePanel::from_reader(panel_text(400).as_bytes()).unwrap();
+    let estimate = panel
+        .estimate(&load_genome(400, 2), 20, |_| Ok(()))
+        .unwrap();
+    assert_eq!(estimate.sites_used, 400);
+    assert_eq!(estimate.palindromic_skipped, 1);
+    assert_eq!(estimate.bootstrap_replicates, 20);
+
+    let continents: Vec<&str> = estimate
+        .continental
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(continents, ["European", "African"]);
+    let african = &estimate.continental[1];
+    assert!(african.proportion > 0.9, "{:?}", estimate);
+    assert!(african.lower <= african.proportion && african.proportion <= african.upper);
+    let total: f64 = estimate.regional.iter().map(|p| p.proportion).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+    assert_eq!(estimate.regional[0].continent.as_deref(), Some("European"));
+
+    let northern = panel.estimate(&load_genome(400, 0), 0, |_| Ok(())).unwrap();
+    assert!(northern.regional[0].proportion > northern.regional[1].proportion);
+    assert_eq!(northern.regional[0].lower, northern.regional[0].proportion);
+
+    let small = ReferencePanel::from_reader(panel_text(MIN_SITES - 1).as_bytes()).unwrap();
+    assert!(small.estimate(&load_genome(10, 0), 0, |_| Ok(())).is_err());
+}
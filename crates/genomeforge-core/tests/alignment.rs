@@ -0,0 +1,202 @@
+//! Alignment pileup tests
+
+use genomeforge_core::alignment::{self, BamFile, PileupOptions, Target};
+use genomeforge_core::genome::GenomeBuild;
+use genomeforge_core::parser::bgzf::{BgzfWriter, VirtualOffset};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// rs429358 and rs7412 on GRCh37, 0-based
+const RS429358: u32 = 45411940;
+const RS7412: u32 = 45412078;
+
+struct Read<'a> {
+    position: u32,
+    mapping_quality: u8,
+    flags: u16,
+    /// (length, operation) with operations coded as in BAM
+    cigar: &'a [(u32, u32)],
+    sequence: &'a str,
+}
+
+fn read_at(position: u32, base: char) -> Read<'static> {
+    let sequence: &'static str = match base {
+        'T' => "AAAAATAAAA",
+        'C' => "AAAAACAAAA",
+        _ => "AAAAAGAAAA",
+    };
+    Read {
+        position: position - 5,
+        mapping_quality: 60,
+        flags: 0,
+        cigar: &[(10, 0)],
+        sequence,
+    }
+}
+
+fn encode(read: &Read<'_>) -> Vec<u8> {
+    let code = |base: u8| "=ACMGRSVTWYHKDBN".bytes().position(|b| b == base).unwrap() as u8;
+    let bases = read.sequence.as_bytes();
+    let mut body = Vec::new();
+    body.extend_from_slice(&1i32.to_le_bytes()); // chromosome 19
+    body.extend_from_slice(&(read.position as i32).to_le_bytes());
+    body.push(5); // name "read" with its terminator
+    body.push(read.mapping_quality);
+    body.extend_from_slice(&0u16.to_le_bytes());
+    body.extend_from_slice(&(read.cigar.len() as u16).to_le_bytes());
+    body.extend_from_slice(&read.flags.to_le_bytes());
+    body.extend_from_slice(&(bases.len() as i32).to_le_bytes());
+    body.extend_from_slice(&(-1i32).to_le_bytes());
+    body.extend_from_slice(&(-1i32).to_le_bytes());
+    body.extend_from_slice(&0i32.to_le_bytes());
+    body.extend_from_slice(b"read\0");
+    for (length, op) in read.cigar {
+        body.extend_from_slice(&(length << 4 | op).to_le_bytes());
+    }
+    for pair in bases.chunks(2) {
+        body.push(code(pair[0]) << 4 | pair.get(1).map_or(0, |&b| code(b)));
+    }
+    body.extend(std::iter::repeat_n(30u8, bases.len()));
+    let mut record = (body.len() as i32).to_le_bytes().to_vec();
+    record.extend_from_slice(&body);
+    record
+}
+
+/// Write a BAM of reads on chromosome 19, returning the span of the reads
+fn write_bam(path: &Path, reads: &[Read<'_>]) -> (VirtualOffset, VirtualOffset) {
+    let mut writer = BgzfWriter::new(std::fs::File::create(path).unwrap());
+    let header = "@HD\tVN:1.6\tSO:coordinate\n@RG\tID:1\tSM:patient\n";
+    writer.write_all(b"BAM\x01").unwrap();
+    writer
+        .write_all(&(header.len() as i32).to_le_bytes())
+        .unwrap();
+    writer.write_all(header.as_bytes()).unwrap();
+    writer.write_all(&2i32.to_le_bytes()).unwrap();
+    for (name, length) in [("chr1", 249_250_621i32), ("chr19", 59_128_983)] {
+        writer
+            .write_all(&(name.len() as i32 + 1).to_le_bytes())
+            .unwrap();
+        writer.write_all(name.as_bytes()).unwrap();
+        writer.write_all(&[0]).unwrap();
+        writer.write_all(&length.to_le_bytes()).unwrap();
+    }
+    writer.flush().unwrap();
+    let start = writer.virtual_position();
+    for read in reads {
+        writer.write_all(&encode(read)).unwrap();
+    }
+    let end = writer.virtual_position();
+    writer.finish().unwrap();
+    (start, end)
+}
+
+fn reads() -> Vec<Read<'static>> {
+    let mut reads: Vec<Read<'static>> = Vec::new();
+    reads.extend((0..6).map(|_| read_at(RS429358, 'T')));
+    reads.extend((0..4).map(|_| read_at(RS429358, 'C')));
+    // Soft clip and deletion before the site: the base is the eighth
+    reads.push(Read {
+        position: RS429358 - 6,
+        mapping_quality: 60,
+        flags: 0,
+        cigar: &[(3, 4), (4, 0), (2, 2), (10, 0)],
+        sequence: "GGGAAAACAAAAAAAAA",
+    });
+    // A duplicate and a poorly mapped read are not counted
+    reads.push(Read {
+        flags: 0x400,
+        ..read_at(RS429358, 'G')
+    });
+    reads.push(Read {
+        mapping_quality: 3,
+        ..read_at(RS429358, 'G')
+    });
+    reads.extend((0..3).map(|_| read_at(RS7412, 'C')));
+    reads
+}
+
+#[test]
+fn calls_genotypes_from_reads_at_clinical_sites() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("patient.bam");
+    write_bam(&path, &reads());
+    assert!(alignment::is_alignment(&path).unwrap());
+
+    let bam = BamFile::open(&path).unwrap();
+    assert!(!bam.is_indexed());
+    assert_eq!(bam.genome_build(), Some(GenomeBuild::GRCh37));
+    assert_eq!(bam.samples(), ["patient"]);
+
+    let targets = Target::clinical(GenomeBuild::GRCh37);
+    let calls = bam
+        .pileup(&targets, &PileupOptions::default(), |_| Ok(()))
+        .unwrap();
+    let apoe = &calls[0];
+    assert_eq!(apoe.target.rsid.as_deref(), Some("rs429358"));
+    assert_eq!(apoe.counts, [0, 5, 0, 6]);
+    assert_eq!(apoe.genotype.to_string(), "CT");
+    // Three reads are too few to call
+    assert_eq!(calls[1].depth, 3);
+    assert!(calls[1].genotype.is_no_call());
+
+    let genome = bam
+        .load_targets(&targets, &PileupOptions::default(), |_| Ok(()))
+        .unwrap();
+    assert_eq!(genome.len(), targets.len());
+    assert_eq!(genome.get_by_rsid("rs429358").unwrap().position, 45411941);
+    assert_eq!(genome.file.genome_build, Some(GenomeBuild::GRCh37));
+}
+
+#[test]
+fn reads_only_indexed_chunks_and_refuses_cram() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("patient.bam");
+    let (start, end) = write_bam(&path, &reads());
+
+    // One chunk in the root bin of chromosome 19 holds every read
+    let mut bai = b"BAI\x01".to_vec();
+    bai.extend_from_slice(&2i32.to_le_bytes());
+    // No bins or windows for chromosome 1
+    bai.extend_from_slice(&0i32.to_le_bytes());
+    bai.extend_from_slice(&0i32.to_le_bytes());
+    for value in [1i32, 0, 1] {
+        bai.extend_from_slice(&value.to_le_bytes());
+    }
+    bai.extend_from_slice(&start.0.to_le_bytes());
+    bai.extend_from_slice(&end.0.to_le_bytes());
+    bai.extend_from_slice(&0i32.to_le_bytes());
+    std::fs::write(PathBuf::from(format!("{}.bai", path.display())), bai).unwrap();
+
+    let bam = BamFile::open(&path).unwrap();
+    assert!(bam.is_indexed());
+    let calls = bam
+        .pileup(
+            &Target::clinical(GenomeBuild::GRCh37),
+            &PileupOptions::default(),
+            |_| Ok(()),
+        )
+        .unwrap();
+    assert_eq!(calls[0].genotype.to_string(), "CT");
+    assert!(bam
+        .pileup(
+            &Target::clinical(GenomeBuild::GRCh37),
+            &PileupOptions::default(),
+            |_| { Err("Cancelled".into()) }
+        )
+        .is_err());
+
+    let cram = dir.path().join("patient.cram");
+    std::fs::write(&cram, b"CRAM\x03\x00rest").unwrap();
+    assert!(alignment::is_alignment(&cram).unwrap());
+    assert!(BamFile::open(&cram).unwrap_err().contains("samtools"));
+    let text = dir.path().join("genome.txt");
+    std::fs::write(&text, "# rsid\tchromosome\tposition\tgenotype\n").unwrap();
+    assert!(!alignment::is_alignment(&text).unwrap());
+
+    assert_eq!(
+        alignment::call_genotype(&[0, 0, 20, 1], 8).to_string(),
+        "GG"
+    );
+    assert!(alignment::call_genotype(&[5, 5, 5, 0], 8).is_no_call());
+}
@@ -0,0 +1,70 @@
+//! APOE genotype tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::apoe::{self, ApoeAllele, ApoeRisk};
+
+fn call(rs429358: &str, rs7412: &str) -> apoe::ApoeCall {
+    let genome = load_calls(
+        37,
+        &[
+            ("rs429358", "19", 45411941, rs429358),
+            ("rs7412", "19", 45412079, rs7412),
+        ],
+    );
+    apoe::call(&genome).unwrap()
+}
+
+#[test]
+fn calls_diplotypes_from_both_snps() {
+    let typical = call("TT", "CC");
+    assert_eq!(typical.alleles, [ApoeAllele::E3, ApoeAllele::E3]);
+    assert_eq!(typical.risk, ApoeRisk::Typical);
+
+    let carrier = call("CT", "CC");
+    assert_eq!(carrier.diplotype, "ε3/ε4");
+    assert_eq!((carrier.e4_copies, carrier.risk), (1, ApoeRisk::Increased));
+    assert_eq!(call("CC", "CC").risk, ApoeRisk::High);
+    assert_eq!(call("TT", "CT").risk, ApoeRisk::Reduced);
+
+    // Unphased, one copy of each minor allele is ε2/ε4 or ε1/ε3
+    let ambiguous = call("CT", "CT");
+    assert_eq!(ambiguous.diplotype, "ε2/ε4");
+    assert_eq!(ambiguous.alternative.as_deref(), Some("ε1/ε3"));
+
+    // Minus-strand calls are complemented
+    assert_eq!(call("AG", "GG").diplotype, "ε3/ε4");
+    assert_eq!(carrier.rs429358, "CT");
+}
+
+#[test]
+fn needs_both_snps_and_matches_by_position() {
+    let by_position = load_calls(
+        38,
+        &[
+            ("i6000001", "19", 44908684, "CC"),
+            ("i6000002", "19", 44908822, "CC"),
+        ],
+    );
+    assert_eq!(apoe::call(&by_position).unwrap().diplotype, "ε4/ε4");
+
+    let missing = load_calls(
+        37,
+        &[
+            ("rs429358", "19", 45411941, "CT"),
+            ("rs7412", "19", 45412079, "--"),
+        ],
+    );
+    assert!(apoe::call(&missing).is_none());
+
+    assert!(apoe::is_apoe_site(Some("rs7412"), &[]));
+    assert!(apoe::is_apoe_site(None, &["apoe".to_string()]));
+    assert!(!apoe::is_apoe_site(
+        Some("rs1801133"),
+        &["MTHFR".to_string()]
+    ));
+    assert!(apoe::is_late_onset_trait(
+        "Alzheimer's disease (late onset)"
+    ));
+}
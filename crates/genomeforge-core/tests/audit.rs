@@ -0,0 +1,119 @@
+//! Audit log tests
+
+use genomeforge_core::audit::{AuditAction, AuditEntry, AuditLog, GENESIS};
+use genomeforge_core::crypto::{self, Key, KeySource};
+use std::path::Path;
+use tempfile::TempDir;
+
+fn details(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Rewrite the entries of a saved log with the key, as someone holding it
+/// could, keeping the header
+fn tamper(path: &Path, key: &Key, change: impl FnOnce(&mut Vec<AuditEntry>)) {
+    let (envelope, plaintext) =
+        crypto::read_file::<serde_json::Value>(path, "audit", KeySource::Device(key)).unwrap();
+    let mut entries: Vec<AuditEntry> = serde_json::from_slice(&plaintext).unwrap();
+    change(&mut entries);
+    let plaintext = serde_json::to_vec(&entries).unwrap();
+    crypto::write_file(
+        path,
+        "audit",
+        &envelope.metadata,
+        &plaintext,
+        KeySource::Device(key),
+    )
+    .unwrap();
+}
+
+#[test]
+fn chains_entries_across_saves() {
+    let dir = TempDir::new().unwrap();
+    let key = Key::generate();
+    let path = dir.path().join("audit.bin");
+
+    let mut log = AuditLog::open(&path, KeySource::Device(&key)).unwrap();
+    assert!(log.is_empty());
+    assert_eq!(log.head(), GENESIS);
+    let first = log
+        .append(
+            AuditAction::FileLoaded,
+            "genome.txt",
+            details(&[("format", "23andme")]),
+        )
+        .clone();
+    assert_eq!(first.previous_hash, GENESIS);
+    log.append(AuditAction::Analysis, "variants", []);
+    log.save(&path, KeySource::Device(&key)).unwrap();
+
+    let mut log = AuditLog::open(&path, KeySource::Device(&key)).unwrap();
+    log.append(
+        AuditAction::Export,
+        "report.pdf",
+        details(&[("format", "pdf")]),
+    );
+    log.save(&path, KeySource::Device(&key)).unwrap();
+
+    let log = AuditLog::open(&path, KeySource::Device(&key)).unwrap();
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.entries()[0], first);
+    assert_eq!(log.entries()[2].previous_hash, log.entries()[1].hash);
+    assert_eq!(log.entries()[2].sequence, 2);
+    let verification = log.verify();
+    assert!(verification.valid);
+    assert_eq!(verification.entries, 3);
+    assert!(AuditLog::open(&path, KeySource::Device(&Key::generate())).is_err());
+}
+
+#[test]
+fn detects_edited_and_dropped_entries() {
+    let dir = TempDir::new().unwrap();
+    let key = Key::generate();
+    let path = dir.path().join("audit.bin");
+    let mut log = AuditLog::default();
+    log.append(AuditAction::FileLoaded, "genome.txt", []);
+    log.append(AuditAction::Export, "report.pdf", []);
+    log.append(AuditAction::DatabaseUpdate, "clinvar", []);
+    log.save(&path, KeySource::Device(&key)).unwrap();
+
+    tamper(&path, &key, |entries| {
+        entries[1].subject = "nothing.txt".to_string();
+    });
+    let verification = AuditLog::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .verify();
+    assert!(!verification.valid);
+    assert_eq!(verification.first_invalid, Some(1));
+
+    // Rehashing the edit still breaks the link to the entry after it
+    tamper(&path, &key, |entries| {
+        entries[1].hash = entries[1].expected_hash();
+    });
+    let verification = AuditLog::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .verify();
+    assert_eq!(verification.first_invalid, Some(2));
+
+    log.save(&path, KeySource::Device(&key)).unwrap();
+    tamper(&path, &key, |entries| {
+        entries.pop();
+    });
+    let verification = AuditLog::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .verify();
+    assert_eq!(verification.first_invalid, Some(2));
+    assert!(verification.reason.unwrap().contains("end"));
+
+    log.save(&path, KeySource::Device(&key)).unwrap();
+    tamper(&path, &key, |entries| {
+        entries.remove(0);
+    });
+    let verification = AuditLog::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .verify();
+    assert_eq!(verification.first_invalid, Some(0));
+}
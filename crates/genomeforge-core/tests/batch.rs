@@ -0,0 +1,70 @@
+//! Batched delivery tests
+
+use genomeforge_core::batch::{Batcher, Window};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn batches_by_count_and_age() {
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let mut batcher = Batcher::new(3, Duration::from_millis(250));
+    assert_eq!(batcher.wait_time(start), None);
+
+    assert_eq!(batcher.push(1, at(0)), None);
+    assert_eq!(batcher.push(2, at(10)), None);
+    assert_eq!(batcher.push(3, at(20)), Some(vec![1, 2, 3]));
+    assert!(batcher.is_empty());
+
+    assert_eq!(batcher.push(4, at(100)), None);
+    assert_eq!(batcher.wait_time(at(200)), Some(Duration::from_millis(150)));
+    assert_eq!(batcher.poll(at(300)), None);
+    // Due 250 ms after its first item, however few it holds
+    assert_eq!(batcher.poll(at(350)), Some(vec![4]));
+    assert_eq!(batcher.poll(at(1000)), None);
+
+    assert_eq!(batcher.push(5, at(1000)), None);
+    assert_eq!(batcher.take(), vec![5]);
+    assert_eq!(batcher.wait_time(at(1000)), None);
+}
+
+#[test]
+fn holds_the_sender_to_the_receiver() {
+    // A receiver that never acknowledges is not waited for
+    let window = Window::new(2, Duration::from_secs(5));
+    let started = Instant::now();
+    let sequences: Vec<u64> = (0..10).map(|_| window.next_sequence()).collect();
+    assert_eq!(sequences, (0..10).collect::<Vec<_>>());
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    let window = Arc::new(Window::new(2, Duration::from_secs(5)));
+    assert_eq!(window.next_sequence(), 0);
+    assert_eq!(window.next_sequence(), 1);
+    window.acknowledge(0);
+    assert_eq!(window.next_sequence(), 2);
+    let receiver = {
+        let window = window.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            window.acknowledge(1);
+        })
+    };
+    let started = Instant::now();
+    // Two batches are unacknowledged, so the third waits for the receiver
+    assert_eq!(window.next_sequence(), 3);
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    receiver.join().unwrap();
+
+    // One that goes quiet is waited for once, then no longer
+    let window = Window::new(1, Duration::from_millis(50));
+    window.acknowledge(0);
+    window.next_sequence();
+    window.next_sequence();
+    let started = Instant::now();
+    window.next_sequence();
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    let started = Instant::now();
+    window.next_sequence();
+    assert!(started.elapsed() < Duration::from_millis(50));
+    assert_eq!(window.sent(), 4);
+}
@@ -0,0 +1,57 @@
+//! Benchmark fixture tests: the generated genomes parse as described and
+//! throughput stays above a floor no supported machine falls below
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::benchmark::{self, CLINVAR_EVERY};
+use genomeforge_core::parser::detect::FileFormat;
+use genomeforge_core::GenomeBuild;
+use tempfile::TempDir;
+
+/// Far below any real machine, even in a debug build, so only a
+/// regression by orders of magnitude fails
+const MIN_MB_PER_SECOND: f64 = 0.5;
+
+#[test]
+fn synthetic_fixtures_parse_to_every_site() {
+    let mut vcf = Vec::new();
+    benchmark::synthetic_vcf(&mut vcf, 5_000).unwrap();
+    let mut twenty_three_and_me = Vec::new();
+    benchmark::synthetic_23andme(&mut twenty_three_and_me, 5_000).unwrap();
+
+    let genome = load_genome("synthetic.vcf", &String::from_utf8(vcf).unwrap());
+    assert_eq!(genome.file.format, FileFormat::Vcf);
+    assert_eq!(genome.file.genome_build, Some(GenomeBuild::GRCh38));
+    assert_eq!(genome.len(), 5_000);
+    assert_eq!(genome.summary.chromosome_counts.len(), 22);
+    assert!(genome.summary.no_call_count > 0);
+
+    let array = load_genome(
+        "synthetic.txt",
+        &String::from_utf8(twenty_three_and_me).unwrap(),
+    );
+    assert_eq!(array.file.format, FileFormat::TwentyThreeAndMe);
+    assert_eq!(array.file.genome_build, Some(GenomeBuild::GRCh37));
+    assert_eq!(array.len(), 5_000);
+
+    let called = genome.len() - genome.summary.no_call_count;
+    let clinvar = benchmark::synthetic_clinvar(&genome);
+    assert!(clinvar.len() <= called.div_ceil(CLINVAR_EVERY));
+    assert!(!clinvar.is_empty());
+}
+
+#[test]
+fn self_benchmark_reports_throughput_and_cleans_up() {
+    let dir = TempDir::new().unwrap();
+    let report = benchmark::run(dir.path(), 20_000).unwrap();
+
+    for stage in [report.vcf, report.twenty_three_and_me] {
+        assert_eq!(stage.variants, 20_000);
+        assert!(stage.bytes > 0);
+        assert!(stage.mb_per_second > MIN_MB_PER_SECOND, "{:?}", stage);
+    }
+    assert!(report.annotation.variants_per_second > 0.0);
+    assert!(report.clinvar_matches > 0);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,83 @@
+//! Blood group prediction tests
+
+mod common;
+
+use common::{load_calls, load_genome};
+use genomeforge_core::annotation::blood_type::{self, AboGroup};
+
+#[test]
+fn predicts_abo_rh_and_extended_antigens() {
+    let genome = load_calls(
+        37,
+        &[
+            ("rs8176719", "9", 136132908, "DI"),
+            ("rs8176746", "9", 136131322, "GG"),
+            ("rs8176747", "9", 136131315, "CC"),
+            ("i4000001", "1", 25600000, "AG"),
+            ("i4000002", "1", 25610000, "CC"),
+            ("i4000003", "1", 25620000, "TT"),
+            ("rs8176058", "7", 142655008, "GG"),
+            ("rs1058396", "18", 43319519, "GA"),
+            ("rs12075", "1", 159175354, "AA"),
+            ("rs2814778", "1", 159174683, "CC"),
+        ],
+    );
+    let prediction = blood_type::predict(&genome);
+    assert_eq!(prediction.blood_type.as_deref(), Some("A+"));
+    let abo = prediction.abo.unwrap();
+    assert_eq!((abo.group, abo.genotype.as_str()), (AboGroup::A, "AO"));
+    assert_eq!(abo.confidence, 0.95);
+    let rh = prediction.rh.unwrap();
+    assert!(rh.d_positive);
+    assert_eq!((rh.probes_called, rh.probes_total), (3, 3));
+
+    let antigens: Vec<&str> = prediction
+        .antigens
+        .iter()
+        .map(|antigen| antigen.phenotype.as_str())
+        .collect();
+    // The GATA box variant leaves FY*B unexpressed
+    assert_eq!(antigens, ["K-k+", "Jk(a+b+)", "Fy(a-b-)"]);
+    assert!(prediction.missing.is_empty());
+
+    // The O deletion as the shorter allele of a VCF record, B on one allele
+    let vcf = load_genome(
+        "genome.vcf",
+        "##fileformat=VCFv4.2\n\
+         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tS1\n\
+         9\t136132908\trs8176719\tT\tTC\t.\tPASS\t.\tGT\t0/1\n\
+         9\t136131322\trs8176746\tG\tT\t.\tPASS\t.\tGT\t0/1\n",
+    );
+    let abo = blood_type::predict(&vcf).abo.unwrap();
+    assert_eq!((abo.group, abo.genotype.as_str()), (AboGroup::B, "BO"));
+    assert_eq!(abo.confidence, 0.85);
+}
+
+#[test]
+fn falls_back_to_proxies_and_flags_missing_markers() {
+    let genome = load_calls(
+        37,
+        &[
+            ("rs505922", "9", 136149229, "TT"),
+            ("i4000001", "1", 25600000, "--"),
+            ("i4000002", "1", 25610000, "--"),
+            ("i4000003", "1", 25620000, "--"),
+        ],
+    );
+    let prediction = blood_type::predict(&genome);
+    assert_eq!(prediction.blood_type.as_deref(), Some("O-"));
+    let abo = prediction.abo.unwrap();
+    assert_eq!((abo.group, abo.confidence), (AboGroup::O, 0.75));
+    assert!(abo.notes[0].contains("rs505922"));
+    assert!(!prediction.rh.unwrap().d_positive);
+    assert!(prediction.antigens.is_empty());
+    assert_eq!(prediction.missing.len(), 3);
+
+    // Without a B allele SNP a non-O genotype cannot be told A or B, and
+    // without RHD probes RhD is not called
+    let prediction = blood_type::predict(&load_calls(37, &[("rs8176719", "9", 136132908, "II")]));
+    assert!(prediction.abo.is_none() && prediction.rh.is_none());
+    assert!(prediction.blood_type.is_none());
+    assert!(prediction.missing[0].contains("A and B cannot be told apart"));
+    assert!(prediction.missing[1].contains("probes inside RHD"));
+}
@@ -0,0 +1,81 @@
+//! Parsed genome cache tests
+
+use genomeforge_core::cache::GenomeCache;
+use genomeforge_core::crypto::Key;
+use genomeforge_core::fingerprint;
+use genomeforge_core::{open_genome, LoadedGenome};
+use tempfile::TempDir;
+
+const GENOME_23ANDME: &str = "# This data file generated by 23andMe\n\
+# build 37\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs80357906\t17\t41197694\tAG\n\
+i3000001\t1\t11856378\tA\n\
+rs429358\t19\t45411941\t--\n\
+rs1801133\t1\t11856378\tAA\n";
+
+const GENOME_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr17\t43045712\t.\tG\tA,T\t.\tPASS\t.\tGT\t1|2\n\
+chrX\t100\trs0100\tAC\tA\t.\tPASS\t.\tGT\t1\n";
+
+fn load(dir: &TempDir, name: &str, contents: &str) -> (String, LoadedGenome) {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    let mut source = open_genome(&path).unwrap();
+    let genome = LoadedGenome::load(source.as_mut()).unwrap();
+    (fingerprint::sha256_file(&path).unwrap(), genome)
+}
+
+fn same(a: &LoadedGenome, b: &LoadedGenome) {
+    assert_eq!(
+        serde_json::to_value(a).unwrap(),
+        serde_json::to_value(b).unwrap()
+    );
+}
+
+#[test]
+fn round_trips_parsed_genomes() {
+    let dir = TempDir::new().unwrap();
+    let cache = GenomeCache::new(&dir.path().join("cache"), Key::generate());
+
+    for (name, contents) in [("genome.txt", GENOME_23ANDME), ("genome.vcf", GENOME_VCF)] {
+        let (hash, genome) = load(&dir, name, contents);
+        assert!(cache.get(&hash).unwrap().is_none());
+        let path = cache.put(&hash, &genome).unwrap();
+        // Sealed, so no variant shows in the file
+        assert!(!std::fs::read(&path)
+            .unwrap()
+            .windows(9)
+            .any(|w| w == b"rs8035790"));
+
+        let cached = cache.get(&hash).unwrap().unwrap();
+        same(&cached, &genome);
+        // The indexes are rebuilt
+        let first = &genome.variants()[0];
+        assert!(cached.get_at(&first.chromosome, first.position).is_some());
+    }
+    assert_eq!(cache.clear().unwrap(), 2);
+}
+
+#[test]
+fn changed_files_and_unreadable_entries_miss() {
+    let dir = TempDir::new().unwrap();
+    let cache_dir = dir.path().join("cache");
+    let cache = GenomeCache::new(&cache_dir, Key::generate());
+    let (hash, genome) = load(&dir, "genome.txt", GENOME_23ANDME);
+    cache.put(&hash, &genome).unwrap();
+
+    // Editing the file changes its hash
+    let (edited, _) = load(&dir, "genome.txt", &GENOME_23ANDME.replace("AG", "GG"));
+    assert_ne!(edited, hash);
+    assert!(cache.get(&edited).unwrap().is_none());
+
+    // An entry sealed under another key is dropped rather than an error
+    let other = GenomeCache::new(&cache_dir, Key::generate());
+    assert!(other.get(&hash).unwrap().is_none());
+    assert!(!cache.entry_path(&hash).exists());
+
+    assert!(cache.get("../../etc/passwd").is_err());
+}
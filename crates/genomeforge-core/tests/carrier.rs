@@ -0,0 +1,70 @@
+//! Carrier screening tests
+
+mod common;
+
+use common::{load_calls, load_database};
+use genomeforge_core::annotation::acmg;
+use genomeforge_core::annotation::carrier::{self, CarrierInheritance, CarrierStatus};
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+7\t117559590\t7105\tATCT\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Cystic_fibrosis;GENEINFO=CFTR:1080;RS=113993960\n\
+7\t117587806\t7106\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Cystic_fibrosis;GENEINFO=CFTR:1080;RS=75527207\n\
+11\t5227002\t15333\tT\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=Sickle_cell_anemia;GENEINFO=HBB:3043;RS=334\n\
+X\t154536002\t10367\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=G6PD_deficiency;GENEINFO=G6PD:2539;RS=5030868\n\
+11\t5226774\t15400\tC\tT\t.\t.\tCLNSIG=Uncertain_significance;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=not_specified;GENEINFO=HBB:3043;RS=35004220\n";
+
+#[test]
+fn lists_recessive_genes_outside_acmg() {
+    let cftr = carrier::gene("cftr").unwrap();
+    assert_eq!(cftr.inheritance, CarrierInheritance::AutosomalRecessive);
+    assert!(cftr.caveat.is_some());
+    assert_eq!(
+        carrier::gene("DMD").unwrap().inheritance,
+        CarrierInheritance::XLinkedRecessive
+    );
+    assert!(carrier::gene("BRCA1").is_none());
+    // Recessive ACMG genes such as MUTYH and HFE are screened there
+    assert!(carrier::GENES
+        .iter()
+        .all(|entry| acmg::gene(entry.gene).is_none()));
+}
+
+#[test]
+fn distinguishes_carriers_from_affected_genotypes() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let genome = load_calls(
+        38,
+        &[
+            ("rs113993960", "7", 117559590, "DI"),
+            ("rs75527207", "7", 117587806, "AG"),
+            ("rs334", "11", 5227002, "AA"),
+            ("rs35004220", "11", 5226774, "CT"),
+            ("rs5030868", "X", 154536002, "T"),
+        ],
+    );
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+    let results = carrier::screen(&matches);
+    let statuses: Vec<(&str, CarrierStatus, usize)> = results
+        .iter()
+        .map(|r| (r.gene.gene, r.status, r.matches.len()))
+        .collect();
+    // The HBB variant of uncertain significance does not count
+    assert_eq!(
+        statuses,
+        [
+            ("CFTR", CarrierStatus::PossibleCompoundHeterozygous, 2),
+            ("HBB", CarrierStatus::Homozygous, 1),
+            ("G6PD", CarrierStatus::Hemizygous, 1),
+        ]
+    );
+    assert!(results[2].status.is_affected());
+    assert!(!results[0].status.is_affected());
+
+    let heterozygous = load_calls(38, &[("rs334", "11", 5227002, "AT")]);
+    let matches = db.annotate(&heterozygous, |_| Ok(())).unwrap();
+    let results = carrier::screen(&matches);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status, CarrierStatus::Carrier);
+}
@@ -0,0 +1,191 @@
+//! ClinVar loading and matching tests
+
+mod common;
+
+use common::{load_database, load_genome};
+use genomeforge_core::annotation::clinvar::{ClinVarDatabase, ClinicalSignificance, ReviewStatus};
+use genomeforge_core::GenomeBuild;
+use tempfile::TempDir;
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##fileDate=20240107\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+17\t43045712\t17661\tG\tA\t.\t.\tCLNSIG=Pathogenic/Likely_pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome|not_provided;GENEINFO=BRCA1:672;RS=80357906\n\
+19\t44908684\t17864\tT\tC\t.\t.\tCLNSIG=risk_factor;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Alzheimer_disease;GENEINFO=APOE:348;RS=429358\n\
+1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Benign;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=not_specified;GENEINFO=MTHFR:4524;RS=1801133\n\
+1\t100\t99\tA\t.\t.\t.\tCLNSIG=Benign\n\
+2\t200\t98\tC\tT\t.\t.\tCLNREVSTAT=no_classification_provided\n";
+
+const VARIANT_SUMMARY: &str = "#AlleleID\tType\tName\tGeneID\tGeneSymbol\tClinicalSignificance\tRS# (dbSNP)\tPhenotypeList\tAssembly\tChromosome\tStart\tReviewStatus\tVariationID\tPositionVCF\tReferenceAlleleVCF\tAlternateAlleleVCF\n\
+32649\tsingle nucleotide variant\tNM_007294.4(BRCA1)\t672\tBRCA1\tPathogenic\t80357906\tBreast-ovarian cancer, familial 1|not provided\tGRCh37\t17\t41197694\tcriteria provided, multiple submitters, no conflicts\t17661\t41197694\tG\tA\n\
+32649\tsingle nucleotide variant\tNM_007294.4(BRCA1)\t672\tBRCA1\tPathogenic\t80357906\tBreast-ovarian cancer, familial 1|not provided\tGRCh38\t17\t43045712\tcriteria provided, multiple submitters, no conflicts\t17661\t43045712\tG\tA\n\
+1\tcopy number gain\tGRCh38/hg38 1p36\t-1\t-\tPathogenic\t-1\tnot provided\tGRCh38\t1\t1\tno assertion criteria provided\t1\tna\tna\tna\n";
+
+const GENOME_23ANDME: &str = "# This data file generated by 23andMe\n\
+# build 37\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs80357906\t17\t41197694\tAG\n\
+rs429358\t19\t45411941\tTT\n\
+rs1801133\t1\t11856378\tAA\n";
+
+const GENOME_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr17\t43045712\t.\tG\tA\t.\tPASS\t.\tGT\t1/1\n\
+chr19\t44908684\t.\tT\tC\t.\tPASS\t.\tGT\t0/0\n";
+
+#[test]
+fn loads_clinvar_vcf_release() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    assert_eq!(db.len(), 3);
+    assert_eq!(db.release_date(), Some("2024-01-07"));
+
+    let brca1 = db.lookup_rsid("rs80357906")[0];
+    assert_eq!(brca1.significance, ClinicalSignificance::LikelyPathogenic);
+    assert_eq!(brca1.review_status, ReviewStatus::ExpertPanel);
+    assert_eq!(brca1.review_status.stars(), 3);
+    assert_eq!(
+        brca1.conditions,
+        vec!["Hereditary breast ovarian cancer syndrome"]
+    );
+    assert_eq!(brca1.genes, vec!["BRCA1"]);
+    assert_eq!(brca1.variation_id, Some(17661));
+
+    let by_allele = db.lookup_allele(GenomeBuild::GRCh38, "chr17", 43045712, "G", "A");
+    assert_eq!(by_allele.len(), 1);
+    assert!(db
+        .lookup_allele(GenomeBuild::GRCh37, "17", 43045712, "G", "A")
+        .is_empty());
+}
+
+#[test]
+fn loads_variant_summary_release() {
+    let db = load_database("variant_summary.txt", VARIANT_SUMMARY);
+    assert_eq!(db.len(), 2);
+
+    let records = db.lookup_rsid("rs80357906");
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].significance, ClinicalSignificance::Pathogenic);
+    assert_eq!(records[0].review_status.stars(), 2);
+    assert_eq!(
+        records[0].conditions,
+        vec!["Breast-ovarian cancer, familial 1"]
+    );
+    assert!(!db
+        .lookup_allele(GenomeBuild::GRCh37, "17", 41197694, "G", "A")
+        .is_empty());
+}
+
+#[test]
+fn reads_condition_terms_and_submissions() {
+    let vcf = CLINVAR_VCF.replace(
+        "GENEINFO=APOE:348",
+        "GENEINFO=APOE:348;CLNDISDB=MONDO:MONDO:0004975,MedGen:C0002395|Human_Phenotype_Ontology:HP:0002511;CLNSIGCONF=risk_factor(2)|Uncertain_significance(1)",
+    );
+    let db = load_database("clinvar.vcf", &vcf);
+    let apoe = db.lookup_rsid("rs429358")[0];
+    assert_eq!(
+        apoe.condition_ids,
+        vec!["MONDO:0004975", "MedGen:C0002395", "HP:0002511"]
+    );
+    assert_eq!(
+        apoe.conflicting_classifications,
+        vec!["risk factor (2)", "Uncertain significance (1)"]
+    );
+    assert!(db.lookup_rsid("rs80357906")[0].condition_ids.is_empty());
+
+    let summary = VARIANT_SUMMARY
+        .replace(
+            "AlternateAlleleVCF\n",
+            "AlternateAlleleVCF\tPhenotypeIDS\tNumberSubmitters\tLastEvaluated\n",
+        )
+        .replace(
+            "17661\t43045712\tG\tA\n",
+            "17661\t43045712\tG\tA\tMONDO:MONDO:0011450,OMIM:604370\t12\tJan 07, 2024\n",
+        )
+        .replace("na\tna\tna\n", "na\tna\tna\t-\t1\t-\n")
+        .replace(
+            "41197694\tG\tA\n",
+            "41197694\tG\tA\tMONDO:MONDO:0011450\t12\t-\n",
+        );
+    let db = load_database("variant_summary.txt", &summary);
+    let brca1 = db.lookup_allele(GenomeBuild::GRCh38, "17", 43045712, "G", "A")[0];
+    assert_eq!(brca1.condition_ids, vec!["MONDO:0011450", "OMIM:604370"]);
+    assert_eq!(brca1.submitter_count, Some(12));
+    assert_eq!(brca1.last_evaluated.as_deref(), Some("Jan 07, 2024"));
+    let grch37 = db.lookup_allele(GenomeBuild::GRCh37, "17", 41197694, "G", "A")[0];
+    assert_eq!(grch37.last_evaluated, None);
+}
+
+#[test]
+fn rejects_unrelated_tsv() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("other.tsv");
+    std::fs::write(&path, "a\tb\n1\t2\n").unwrap();
+    let err = ClinVarDatabase::load(&path).unwrap_err();
+    assert!(err.contains("Not a ClinVar variant summary"), "{}", err);
+}
+
+#[test]
+fn matches_array_genotypes_by_rsid() {
+    let db = load_database("variant_summary.txt", VARIANT_SUMMARY);
+    let genome = load_genome("genome.txt", GENOME_23ANDME);
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+
+    // One match despite the record being listed for two builds
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].alternate_copies, 1);
+    assert_eq!(matches[0].record.genome_build, Some(GenomeBuild::GRCh37));
+}
+
+#[test]
+fn only_reports_carried_alleles() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let genome = load_genome("genome.txt", GENOME_23ANDME);
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+
+    let rsids: Vec<_> = matches
+        .iter()
+        .map(|m| m.record.rsid.as_deref().unwrap())
+        .collect();
+    // rs429358 is TT, which does not carry the C risk allele
+    assert_eq!(rsids, vec!["rs80357906", "rs1801133"]);
+}
+
+#[test]
+fn matches_vcf_genotypes_by_allele() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let genome = load_genome("sample.vcf", GENOME_VCF);
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].alternate_copies, 2);
+    assert_eq!(matches[0].record.genes, vec!["BRCA1"]);
+}
+
+#[test]
+fn parses_significance_terms() {
+    let cases = [
+        ("Pathogenic", ClinicalSignificance::Pathogenic),
+        (
+            "Pathogenic, low penetrance",
+            ClinicalSignificance::Pathogenic,
+        ),
+        ("Likely_pathogenic", ClinicalSignificance::LikelyPathogenic),
+        (
+            "Conflicting_interpretations_of_pathogenicity",
+            ClinicalSignificance::Conflicting,
+        ),
+        (
+            "Uncertain_significance",
+            ClinicalSignificance::UncertainSignificance,
+        ),
+        ("Benign/Likely_benign", ClinicalSignificance::LikelyBenign),
+        ("drug_response", ClinicalSignificance::DrugResponse),
+        ("not_provided", ClinicalSignificance::Other),
+    ];
+    for (raw, expected) in cases {
+        assert_eq!(ClinicalSignificance::parse(raw), expected, "{}", raw);
+    }
+}
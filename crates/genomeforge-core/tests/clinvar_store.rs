@@ -0,0 +1,89 @@
+//! On-disk ClinVar store tests: lookups match the in-memory index and
+//! stores are rebuilt when their schema or release changes
+
+mod common;
+
+use common::{load_database, load_genome};
+use genomeforge_core::annotation::clinvar_store::{self, ClinVarStore, SCHEMA_VERSION};
+use genomeforge_core::GenomeBuild;
+use std::io::{Seek, SeekFrom, Write};
+use tempfile::TempDir;
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##fileDate=20240107\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+17\t43045712\t17661\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357906\n\
+17\t43045712\t17662\tG\tT\t.\t.\tCLNSIG=Uncertain_significance;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=not_provided;GENEINFO=BRCA1:672;RS=80357906\n\
+19\t44908684\t17864\tT\tC\t.\t.\tCLNSIG=risk_factor;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Alzheimer_disease;GENEINFO=APOE:348;RS=429358\n\
+1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Benign;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=not_specified;GENEINFO=MTHFR:4524;RS=1801133\n";
+
+const GENOME_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr17\t43045712\t.\tG\tA\t.\tPASS\t.\tGT\t0/1\n\
+chr1\t11796321\trs1801133\tG\tA\t.\tPASS\t.\tGT\t0/0\n\
+chr2\t500\t.\tC\tT\t.\tPASS\t.\tGT\t1/1\n";
+
+fn write(dir: &TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn store_lookups_and_subset_match_the_release() {
+    let dir = TempDir::new().unwrap();
+    let database = load_database("clinvar.vcf", CLINVAR_VCF);
+    let path = dir.path().join(clinvar_store::STORE_FILE);
+    ClinVarStore::write(&path, &database).unwrap();
+    let store = ClinVarStore::open(&path).unwrap();
+
+    assert_eq!(store.len(), 4);
+    assert_eq!(store.release_date(), Some("2024-01-07"));
+    let brca1 = store
+        .lookup_allele(GenomeBuild::GRCh38, "chr17", 43045712, "g", "A")
+        .unwrap();
+    assert_eq!(brca1.len(), 1);
+    assert_eq!(brca1[0].variation_id, Some(17661));
+    assert_eq!(store.lookup_rsid("rs80357906").unwrap().len(), 2);
+    assert!(store.lookup_rsid("rs1").unwrap().is_empty());
+    assert!(store
+        .lookup_allele(GenomeBuild::GRCh37, "17", 43045712, "G", "A")
+        .unwrap()
+        .is_empty());
+
+    let genome = load_genome("genome.vcf", GENOME_VCF);
+    let subset = store.subset(&genome, |_| Ok(())).unwrap();
+    assert_eq!(subset.len(), 3);
+    let from_store = subset.annotate(&genome, |_| Ok(())).unwrap();
+    let from_memory = database.annotate(&genome, |_| Ok(())).unwrap();
+    let ids = |matches: &[genomeforge_core::annotation::clinvar::ClinVarMatch]| {
+        matches
+            .iter()
+            .map(|found| found.record.variation_id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&from_store), ids(&from_memory));
+    assert_eq!(ids(&from_store), vec![Some(17661)]);
+}
+
+#[test]
+fn stores_of_another_schema_are_rebuilt() {
+    let dir = TempDir::new().unwrap();
+    let release = write(&dir, "clinvar.vcf", CLINVAR_VCF);
+    let path = dir.path().join(clinvar_store::STORE_FILE);
+    ClinVarStore::open_or_build(&path, &release).unwrap();
+    assert_eq!(clinvar_store::schema_version(&path), Some(SCHEMA_VERSION));
+
+    let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.seek(SeekFrom::Start(4)).unwrap();
+    file.write_all(&(SCHEMA_VERSION + 1).to_le_bytes()).unwrap();
+    drop(file);
+    assert!(ClinVarStore::open(&path).unwrap_err().contains("schema"));
+
+    let store = ClinVarStore::open_or_build(&path, &release).unwrap();
+    assert_eq!(store.len(), 4);
+    assert_eq!(clinvar_store::schema_version(&path), Some(SCHEMA_VERSION));
+    assert_eq!(clinvar_store::schema_version(&release), None);
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the integration tests
+
+// Each test file uses only some of them
+#![allow(dead_code)]
+
+use genomeforge_core::annotation::clinvar::ClinVarDatabase;
+use genomeforge_core::{open_genome, LoadedGenome};
+use tempfile::TempDir;
+
+/// Load a genome from a file named `name` holding `contents`
+pub fn load_genome(name: &str, contents: &str) -> LoadedGenome {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    let mut source = open_genome(&path).unwrap();
+    LoadedGenome::load(source.as_mut()).unwrap()
+}
+
+/// Load a 23andMe-style genome on GRCh`build` from
+/// (rsid, chromosome, position, genotype) calls
+pub fn load_calls(build: u32, calls: &[(&str, &str, u64, &str)]) -> LoadedGenome {
+    let mut contents = format!(
+        "# build {}\n# rsid\tchromosome\tposition\tgenotype\n",
+        build
+    );
+    for (rsid, chromosome, position, genotype) in calls {
+        contents.push_str(&format!(
+            "{}\t{}\t{}\t{}\n",
+            rsid, chromosome, position, genotype
+        ));
+    }
+    load_genome("genome.txt", &contents)
+}
+
+/// Load a ClinVar release from a file named `name` holding `contents`
+pub fn load_database(name: &str, contents: &str) -> ClinVarDatabase {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    ClinVarDatabase::load(&path).unwrap()
+}
@@ -0,0 +1,61 @@
+//! Genome file comparison tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::compare::{self, same_call};
+use genomeforge_core::genome::Genotype;
+
+const GENOME_23ANDME: &str = "# This data file generated by 23andMe\n\
+# build 37\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs4477212\t1\t82154\tAG\n\
+rs3094315\t1\t752566\tAA\n\
+rs3131972\t1\t752721\t--\n\
+rs12124819\t1\t776546\tAG\n\
+rs11240777\t2\t798959\tCT\n\
+rs5030868\tX\t154536002\tT\n\
+i3000001\t1\t11856378\tA\n";
+
+const GENOME_ANCESTRY: &str = "#AncestryDNA raw data download\n\
+rsid\tchromosome\tposition\tallele1\tallele2\n\
+rs4477212\t1\t82154\tG\tA\n\
+rs3094315\t1\t752566\tA\tA\n\
+rs3131972\t1\t752721\tA\tG\n\
+rs12124819\t1\t776546\tA\tA\n\
+rs11240777\t2\t798959\tC\tT\n\
+rs5030868\t23\t154536002\tT\tT\n\
+rs6681049\t1\t800007\tC\tC\n";
+
+#[test]
+fn reconciles_files_from_two_companies() {
+    let first = load_genome("genome_23andme.txt", GENOME_23ANDME);
+    let second = load_genome("AncestryDNA.txt", GENOME_ANCESTRY);
+
+    let comparison = compare::compare(&first, &second, |_| Ok(())).unwrap();
+    assert_eq!(comparison.shared_rsids, 6);
+    assert_eq!(comparison.no_calls, 1);
+    assert_eq!(comparison.compared, 5);
+    assert_eq!(comparison.discordant, 1);
+    assert!((comparison.concordance_rate - 0.8).abs() < 1e-9);
+    assert_eq!(comparison.discordant_sites[0].rsid, "rs12124819");
+
+    let chromosomes: Vec<(&str, usize, usize)> = comparison
+        .by_chromosome
+        .iter()
+        .map(|c| (c.chromosome.as_str(), c.compared, c.discordant))
+        .collect();
+    assert_eq!(chromosomes, [("1", 3, 1), ("2", 1, 0), ("X", 1, 0)]);
+    assert!((comparison.by_chromosome[0].discordance_rate - 1.0 / 3.0).abs() < 1e-9);
+
+    assert!(compare::compare(&first, &second, |_| Err("Cancelled".into())).is_err());
+}
+
+#[test]
+fn matches_calls_regardless_of_allele_order() {
+    let call = Genotype::from_array_call;
+    assert!(same_call(&call("AG"), &call("GA")));
+    assert!(same_call(&call("T"), &call("TT")));
+    assert!(!same_call(&call("T"), &call("CT")));
+    assert!(!same_call(&call("AA"), &call("AG")));
+}
@@ -0,0 +1,109 @@
+//! Chromosome coverage and data completeness tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::completeness::{self, Analysis, GenePanel, Unavailable};
+use genomeforge_core::LoadedGenome;
+
+fn array(rows: &str) -> LoadedGenome {
+    let contents = format!(
+        "# build 37\n# rsid\tchromosome\tposition\tgenotype\n{}",
+        rows
+    );
+    load_genome("genome.txt", &contents)
+}
+
+#[test]
+fn reports_call_rates_and_the_analyses_the_calls_are_too_sparse_for() {
+    let mut rows =
+        "rs1\t1\t1000\tAG\nrs2\t1\t2000\t--\nrs3\t1\t3000\tCC\nrs4\t1\t4000\tTT\n".to_string();
+    for index in 0..60 {
+        rows.push_str(&format!(
+            "rs{}\tX\t{}\tAG\n",
+            100 + index,
+            10_000_000 + index * 1000
+        ));
+    }
+    // A female sample's Y probes, almost all no-calls
+    for index in 0..20 {
+        let genotype = if index == 0 { "C" } else { "--" };
+        rows.push_str(&format!(
+            "rs{}\tY\t{}\t{}\n",
+            200 + index,
+            5_000_000 + index * 1000,
+            genotype
+        ));
+    }
+    let quality = completeness::report(&array(&rows));
+
+    assert_eq!((quality.variant_count, quality.no_call_count), (84, 20));
+    assert!((quality.no_call_rate - 20.0 / 84.0).abs() < 1e-9);
+    let chromosomes: Vec<(&str, usize, f64)> = quality
+        .chromosomes
+        .iter()
+        .map(|c| (c.chromosome.as_str(), c.variant_count, c.no_call_rate))
+        .collect();
+    assert_eq!(
+        chromosomes,
+        [("1", 4, 0.25), ("X", 60, 0.0), ("Y", 20, 0.95)]
+    );
+    assert_eq!(quality.missing_chromosomes.len(), 22);
+    assert!(quality.missing_chromosomes.contains(&"MT".to_string()));
+    assert!(!quality.missing_chromosomes.contains(&"1".to_string()));
+
+    let reasons: Vec<(Analysis, Option<Unavailable>)> = quality
+        .analyses
+        .iter()
+        .map(|availability| (availability.analysis, availability.reason))
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            (Analysis::MaternalHaplogroup, Some(Unavailable::NoSites)),
+            (Analysis::PaternalHaplogroup, Some(Unavailable::LowCallRate)),
+            (Analysis::GeneticSex, None),
+            (
+                Analysis::StructuralVariants,
+                Some(Unavailable::NoStructuralRecords)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn counts_the_sites_of_each_panel_gene() {
+    let mut rows =
+        "rs1\t10\t96522500\tAG\nrs2\t10\t96600000\t--\nrs3\t17\t41200000\tCT\n".to_string();
+    // Too few mitochondrial calls for a haplogroup
+    for index in 0..5 {
+        rows.push_str(&format!("rs{}\tMT\t{}\tA\n", 100 + index, 100 + index * 50));
+    }
+    let quality = completeness::report(&array(&rows));
+
+    let panel = |panel: GenePanel| {
+        quality
+            .panels
+            .iter()
+            .find(|coverage| coverage.panel == panel)
+            .unwrap()
+    };
+    let pharmacogenes = panel(GenePanel::Pharmacogenes);
+    assert_eq!(pharmacogenes.genes.len(), 9);
+    assert_eq!(pharmacogenes.genes_covered(), 1);
+    let cyp2c19 = pharmacogenes
+        .genes
+        .iter()
+        .find(|gene| gene.gene == "CYP2C19")
+        .unwrap();
+    assert_eq!((cyp2c19.sites, cyp2c19.called_sites), (2, 1));
+    let secondary = panel(GenePanel::SecondaryFindings);
+    let covered: Vec<&str> = secondary
+        .genes
+        .iter()
+        .filter(|gene| gene.called_sites > 0)
+        .map(|gene| gene.gene.as_str())
+        .collect();
+    assert_eq!(covered, ["BRCA1"]);
+    assert_eq!(quality.analyses[0].reason, Some(Unavailable::TooFewCalls));
+}
@@ -0,0 +1,74 @@
+//! Finding confidence tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::nutrigenomics;
+use genomeforge_core::annotation::pharmgkb::EvidenceLevel;
+use genomeforge_core::annotation::probes::{self, ProbeMask};
+use genomeforge_core::annotation::strand::Strand;
+use genomeforge_core::confidence::{self, Confidence, ConfidenceLevel, Evidence};
+use genomeforge_core::quality::QualityIssue;
+
+#[test]
+fn weighs_the_evidence_by_the_reliability_of_the_call() {
+    let reviewed = Confidence::new(Evidence::ClinVar(4), 1.0);
+    assert_eq!(
+        (reviewed.score, reviewed.level),
+        (1.0, ConfidenceLevel::High)
+    );
+    let unreviewed = Confidence::new(Evidence::ClinVar(0), 1.0);
+    assert_eq!(unreviewed.level, ConfidenceLevel::Low);
+
+    // A well-reviewed finding on a flagged probe ranks below a weaker one
+    // on a clean call
+    let flagged =
+        confidence::call_reliability(false, &[QualityIssue::UnreliableProbe], Strand::Forward);
+    let on_flagged_probe = Confidence::new(Evidence::ClinVar(3), flagged);
+    let level_2a = Confidence::new(Evidence::PharmGkb(EvidenceLevel::Level2A), 1.0);
+    assert!(on_flagged_probe.score < level_2a.score);
+    assert_eq!(on_flagged_probe.level, ConfidenceLevel::Moderate);
+
+    let doubtful = confidence::call_reliability(true, &[QualityIssue::LowDepth], Strand::Ambiguous);
+    assert!((doubtful - 0.8 * 0.8 * 0.8).abs() < 1e-9);
+    let gwas = Confidence::new(Evidence::Gwas(0.9), doubtful);
+    assert!((gwas.score - 0.9 * doubtful).abs() < 1e-9);
+    assert_eq!((gwas.evidence, gwas.call), (0.9, doubtful));
+
+    // Results stored before the call was weighed held a bare score
+    let stored: Confidence = serde_json::from_str("0.55").unwrap();
+    assert_eq!((stored.score, stored.call), (0.55, 1.0));
+    assert_eq!(stored.level, ConfidenceLevel::Moderate);
+    let round_trip: Confidence =
+        serde_json::from_value(serde_json::to_value(gwas).unwrap()).unwrap();
+    assert_eq!(round_trip, gwas);
+}
+
+#[test]
+fn findings_carry_the_confidence_of_their_calls() {
+    let contents = "# This data file generated by 23andMe\n# chip version: v5\n\
+                    # rsid\tchromosome\tposition\tgenotype\n\
+                    rs1801133\t1\t11856378\tAG\n\
+                    rs4988235\t2\t136608646\tAG\n";
+    let genome = load_genome("genome.txt", contents);
+    let mask = ProbeMask::from_reader(
+        "vendor\tversion\tprobe\taction\n23andme\tv5\trs1801133\tflag\n".as_bytes(),
+    )
+    .unwrap();
+    let chip = probes::infer_chip(&genome).unwrap();
+    let (genome, _) = mask.apply(&genome, &chip, |_| Ok(())).unwrap();
+
+    let findings = nutrigenomics::call(&genome);
+    let confidence = |rsid: &str| {
+        findings
+            .iter()
+            .find(|finding| finding.rsid == rsid)
+            .unwrap()
+            .confidence
+    };
+    let mthfr = confidence("rs1801133");
+    assert_eq!((mthfr.evidence, mthfr.call), (0.6, 0.5));
+    assert_eq!(mthfr.level, ConfidenceLevel::Low);
+    let lactase = confidence("rs4988235");
+    assert_eq!((lactase.call, lactase.level), (1.0, ConfidenceLevel::High));
+}
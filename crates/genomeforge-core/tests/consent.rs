@@ -0,0 +1,89 @@
+//! Consent policy tests
+
+mod common;
+
+use common::{load_database, load_genome};
+use genomeforge_core::annotation::consent::{ConsentPolicy, FindingCategory};
+use genomeforge_core::annotation::gwas::{GwasAssociation, TraitCategory};
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+7\t117587806\t7106\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Cystic_fibrosis;GENEINFO=CFTR:1080;RS=75527207\n\
+17\t43057062\t55407\tT\tG\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357906\n\
+14\t73173663\t18135\tA\tG\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Alzheimer_disease_3;GENEINFO=PSEN1:5663;RS=63750526\n\
+1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Uncertain_significance;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Homocystinuria;GENEINFO=MTHFR:4524;RS=1801133\n";
+
+const GENOME: &str = "# build 38\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs75527207\t7\t117587806\tAG\n\
+rs80357906\t17\t43057062\tTG\n\
+rs63750526\t14\t73173663\tAG\n\
+rs1801133\t1\t11796321\tGA\n";
+
+fn association(trait_name: &str, genes: &[&str]) -> GwasAssociation {
+    GwasAssociation {
+        rsid: "rs1".to_string(),
+        risk_allele: "A".to_string(),
+        trait_name: trait_name.to_string(),
+        mapped_trait: None,
+        category: TraitCategory::Neurological,
+        p_value: 1e-10,
+        effect: None,
+        risk_allele_frequency: None,
+        genes: genes.iter().map(|gene| gene.to_string()).collect(),
+        chromosome: None,
+        position: None,
+        pubmed_id: None,
+        study: None,
+        added: None,
+    }
+}
+
+#[test]
+fn drops_matches_of_excluded_categories() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let genome = load_genome("genome.txt", GENOME);
+    let genes = |policy: &ConsentPolicy| {
+        let mut matches = db.annotate(&genome, |_| Ok(())).unwrap();
+        let dropped = policy.retain_matches(&mut matches);
+        let mut genes: Vec<String> = matches
+            .iter()
+            .map(|found| found.record.genes[0].clone())
+            .collect();
+        genes.sort();
+        (genes, dropped)
+    };
+
+    assert_eq!(genes(&ConsentPolicy::default()).1, 0);
+    let policy = ConsentPolicy::excluding([
+        FindingCategory::Neurodegenerative,
+        FindingCategory::CarrierStatus,
+    ]);
+    assert_eq!(genes(&policy), (vec!["BRCA1".into(), "MTHFR".into()], 2));
+    let policy = ConsentPolicy::excluding([FindingCategory::SecondaryFindings]);
+    assert_eq!(
+        genes(&policy),
+        (vec!["CFTR".into(), "MTHFR".into(), "PSEN1".into()], 1)
+    );
+}
+
+#[test]
+fn filters_associations_and_refuses_excluded_analyses() {
+    let policy = ConsentPolicy::excluding([FindingCategory::Neurodegenerative]);
+    assert!(policy.allows_association(&association("Height", &["HMGA2"])));
+    assert!(!policy.allows_association(&association("Parkinson's disease", &[])));
+    assert!(!policy.allows_association(&association("Cognitive decline", &["APOE"])));
+    assert!(policy.require(FindingCategory::Paternity).is_ok());
+
+    let policy = ConsentPolicy::excluding([FindingCategory::Traits, FindingCategory::Paternity]);
+    assert!(!policy.allows_association(&association("Height", &["HMGA2"])));
+    assert!(policy
+        .require(FindingCategory::Paternity)
+        .unwrap_err()
+        .contains("consent"));
+    assert_eq!(
+        serde_json::to_value(&policy).unwrap(),
+        serde_json::json!({ "excluded": ["traits", "paternity"] })
+    );
+}
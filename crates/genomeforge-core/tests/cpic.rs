@@ -0,0 +1,167 @@
+//! CPIC star-allele calling tests
+
+mod common;
+
+use genomeforge_core::annotation::cpic::{
+    AlleleFunction, CpicDatabase, GeneDefinition, DEFINITION_SUFFIX, FUNCTIONALITY_SUFFIX,
+    RECOMMENDATIONS_FILE,
+};
+use genomeforge_core::annotation::manager::{find_installed, DatabaseKind};
+use genomeforge_core::LoadedGenome;
+use tempfile::TempDir;
+
+const CYP2C19_DEFINITIONS: &str = "GENE: CYP2C19\n\
+Nucleotide change per gene from http://www.pharmvar.org\tc.-806C>T\tc.681G>A\tc.636G>A\tc.1A>G\n\
+Position at NC_000010.11 (Homo sapiens chromosome 10, GRCh38.p2)\tg.94761900C>T\tg.94781859G>A\tg.94780653G>A\tg.94762706A>G\n\
+rsID\trs12248560\trs4244285\trs4986893\trs28399504\n\
+CYP2C19 Allele\t\t\t\t\n\
+*1\tC\tG\tG\tA\n\
+*2\t\tA\t\t\n\
+*3\t\t\tA\t\n\
+*4\tT\t\t\tG\n\
+*17\tT\t\t\t\n\
+\n\
+NOTES:\tHaplotypes are listed per PharmVar\n";
+
+const CYP2C19_FUNCTIONALITY: &str = "CYP2C19 Allele Functionality Table\n\
+Allele\tActivity Value\tAllele Clinical Functional Status (Required)\n\
+*1\tn/a\tNormal function\n\
+*2\tn/a\tNo function\n\
+*3\tn/a\tNo function\n\
+*4\tn/a\tNo function\n\
+*17\tn/a\tIncreased function\n";
+
+const RECOMMENDATIONS: &str = "Gene\tDrug\tPhenotype\tImplication\tRecommendation\tClassification\n\
+CYP2C19\tclopidogrel\tCYP2C19 Intermediate Metabolizer\tReduced platelet inhibition\tAvoid standard dose clopidogrel if possible\tModerate\n\
+CYP2C19\tclopidogrel\tCYP2C19 Poor Metabolizer\tSignificantly reduced platelet inhibition\tAvoid clopidogrel\tStrong\n\
+CYP2C19\tvoriconazole\tCYP2C19 Ultrarapid Metabolizer\tLow trough concentrations\tChoose an alternative agent\tModerate\n";
+
+fn load_genome(calls: &[(&str, &str)]) -> LoadedGenome {
+    let mut contents = "# rsid\tchromosome\tposition\tgenotype\n".to_string();
+    for (index, (rsid, genotype)) in calls.iter().enumerate() {
+        contents.push_str(&format!("{}\t10\t{}\t{}\n", rsid, 1000 + index, genotype));
+    }
+    common::load_genome("genome.txt", &contents)
+}
+
+fn load_release() -> (TempDir, CpicDatabase) {
+    let dir = TempDir::new().unwrap();
+    let tables = dir.path().join("cpic");
+    std::fs::create_dir(&tables).unwrap();
+    let write = |name: String, contents: &str| std::fs::write(tables.join(name), contents);
+    write(format!("CYP2C19{}", DEFINITION_SUFFIX), CYP2C19_DEFINITIONS).unwrap();
+    write(
+        format!("CYP2C19{}", FUNCTIONALITY_SUFFIX),
+        CYP2C19_FUNCTIONALITY,
+    )
+    .unwrap();
+    write(RECOMMENDATIONS_FILE.to_string(), RECOMMENDATIONS).unwrap();
+    let database = CpicDatabase::load_dir(&tables).unwrap();
+    (dir, database)
+}
+
+#[test]
+fn reads_definition_and_functionality_tables() {
+    let (dir, database) = load_release();
+    assert_eq!(database.len(), 5);
+    assert_eq!(
+        find_installed(dir.path(), DatabaseKind::Cpic),
+        Some(dir.path().join("cpic"))
+    );
+
+    let gene = database.gene("cyp2c19").unwrap();
+    assert_eq!(gene.sites.len(), 4);
+    assert_eq!(gene.sites[1].rsid.as_deref(), Some("rs4244285"));
+    assert_eq!(gene.sites[1].chromosome.as_deref(), Some("10"));
+    assert_eq!(gene.sites[1].position, Some(94781859));
+    // The label row and the notes are not alleles
+    let names: Vec<&str> = gene.alleles.iter().map(|a| a.name.as_str()).collect();
+    assert_eq!(names, ["*1", "*2", "*3", "*4", "*17"]);
+    assert_eq!(gene.alleles[1].variants[1].as_deref(), Some("A"));
+    assert_eq!(gene.alleles[4].function, Some(AlleleFunction::Increased));
+    assert_eq!(gene.alleles[4].activity_value, None);
+}
+
+#[test]
+fn calls_most_specific_diplotype_and_phenotype() {
+    let (_dir, database) = load_release();
+    let heterozygous = load_genome(&[
+        ("rs12248560", "CT"),
+        ("rs4244285", "AG"),
+        ("rs4986893", "GG"),
+        ("rs28399504", "AA"),
+    ]);
+    let calls = database.call_diplotypes(&heterozygous, |_| Ok(())).unwrap();
+    assert_eq!(calls.len(), 1);
+    let call = &calls[0];
+    assert_eq!(call.diplotype, "*2/*17");
+    assert_eq!(call.phenotype, "Intermediate Metabolizer");
+    assert!(call.alternatives.is_empty());
+    assert_eq!((call.sites_genotyped, call.sites_total), (4, 4));
+
+    let recommendations = database.recommendations("CYP2C19", &call.phenotype);
+    assert_eq!(recommendations.len(), 1);
+    assert_eq!(recommendations[0].drug, "clopidogrel");
+    assert_eq!(
+        recommendations[0].classification.as_deref(),
+        Some("Moderate")
+    );
+
+    // Without rs28399504, *4 cannot be called and *17/*17 is the best fit
+    let missing_site = load_genome(&[("rs12248560", "TT"), ("rs4244285", "GG")]);
+    let call = database
+        .gene("CYP2C19")
+        .unwrap()
+        .call(&missing_site)
+        .unwrap();
+    assert_eq!(call.diplotype, "*17/*17");
+    assert_eq!(call.phenotype, "Ultrarapid Metabolizer");
+    assert_eq!(call.sites_genotyped, 2);
+
+    // A call reported on the opposite strand is complemented
+    let minus_strand = load_genome(&[("rs4244285", "TT"), ("rs4986893", "CC")]);
+    let call = database
+        .gene("CYP2C19")
+        .unwrap()
+        .call(&minus_strand)
+        .unwrap();
+    assert_eq!(call.diplotype, "*2/*2");
+    assert_eq!(call.phenotype, "Poor Metabolizer");
+
+    assert!(database
+        .call_diplotypes(&load_genome(&[("rs1", "AA")]), |_| Ok(()))
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn scores_activity_genes_and_falls_back_from_likely_phenotypes() {
+    let definitions = "GENE: CYP2D6\n\
+Position at NC_000022.11 (Homo sapiens chromosome 22, GRCh38.p2)\tg.42130692G>A\tg.42128945C>T\n\
+rsID\trs1065852\trs3892097\n\
+*1\tG\tC\n\
+*4\tA\tT\n\
+*10\tA\t\n";
+    let functionality = "Allele\tActivity Value\tAllele Clinical Functional Status\n\
+*1\t1\tNormal function\n\
+*4\t0\tNo function\n\
+*10\t0.25\tDecreased function\n";
+    let mut gene = GeneDefinition::from_table(definitions, None).unwrap();
+    gene.read_functionality(functionality).unwrap();
+
+    let genome = load_genome(&[("rs1065852", "AG"), ("rs3892097", "CC")]);
+    let call = gene.call(&genome).unwrap();
+    assert_eq!(call.diplotype, "*1/*10");
+    assert_eq!(call.activity_score, Some(1.25));
+    assert_eq!(call.phenotype, "Normal Metabolizer");
+
+    let genome = load_genome(&[("rs1065852", "AA"), ("rs3892097", "CT")]);
+    let call = gene.call(&genome).unwrap();
+    assert_eq!(call.diplotype, "*4/*10");
+    assert_eq!(call.phenotype, "Intermediate Metabolizer");
+
+    let (_dir, database) = load_release();
+    let likely = database.recommendations("CYP2C19", "Likely Poor Metabolizer");
+    assert_eq!(likely.len(), 1);
+    assert_eq!(likely[0].recommendation, "Avoid clopidogrel");
+}
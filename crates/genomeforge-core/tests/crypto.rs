@@ -0,0 +1,85 @@
+//! Encryption and key derivation tests
+
+use genomeforge_core::crypto::{self, argon2, KdfParams, Key, KeySource};
+use tempfile::TempDir;
+
+#[test]
+fn argon2id_matches_rfc_9106_test_vector() {
+    let params = argon2::Params {
+        memory_kib: 32,
+        iterations: 3,
+        parallelism: 4,
+    };
+    let mut tag = [0u8; 32];
+    argon2::hash(params, &[1; 32], &[2; 16], &[3; 8], &[4; 12], &mut tag).unwrap();
+    assert_eq!(
+        hex::encode(tag),
+        "0d640df58d78766c08c037a34a8b53c9d01ef0452d75b65eb52520e96b01e659"
+    );
+}
+
+#[test]
+fn encrypted_file_needs_its_key_and_intact_contents() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("cache.bin");
+    crypto::write_file(
+        &path,
+        "cache",
+        &"v1",
+        b"rs429358 TC",
+        KeySource::Passphrase("hunter2"),
+    )
+    .unwrap();
+
+    let (envelope, plaintext) =
+        crypto::read_file::<String>(&path, "cache", KeySource::Passphrase("hunter2")).unwrap();
+    assert_eq!(envelope.metadata, "v1");
+    assert_eq!(&plaintext[..], b"rs429358 TC");
+    let error =
+        crypto::read_file::<String>(&path, "cache", KeySource::Passphrase("hunter3")).unwrap_err();
+    assert!(error.contains("wrong passphrase"));
+    let error = crypto::read_file::<String>(&path, "session", KeySource::Passphrase("hunter2"))
+        .unwrap_err();
+    assert!(error.contains("Not a GenomeForge session file"));
+
+    let key = Key::generate();
+    crypto::write_file(
+        &path,
+        "cache",
+        &"v1",
+        b"rs429358 TC",
+        KeySource::Device(&key),
+    )
+    .unwrap();
+    let mut contents = std::fs::read(&path).unwrap();
+    let last = contents.len() - 1;
+    contents[last] ^= 1;
+    std::fs::write(&path, &contents).unwrap();
+    assert!(crypto::read_file::<String>(&path, "cache", KeySource::Device(&key)).is_err());
+
+    // The costs in a header are refused before any memory is set aside
+    let header = serde_json::json!({
+        "kind": "cache",
+        "protection": {
+            "kind": "passphrase",
+            "salt": "00".repeat(16),
+            "kdf": { "memory_kib": u32::MAX, "iterations": 3, "parallelism": 1 },
+        },
+        "metadata": "v1",
+    })
+    .to_string();
+    let mut contents = crypto::MAGIC.to_vec();
+    contents.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    contents.extend_from_slice(header.as_bytes());
+    contents.extend_from_slice(&[0; 32]);
+    std::fs::write(&path, &contents).unwrap();
+    let error =
+        crypto::read_file::<String>(&path, "cache", KeySource::Passphrase("hunter2")).unwrap_err();
+    assert!(error.contains("exceed the supported maximum"), "{}", error);
+    let slow = KdfParams {
+        iterations: u32::MAX,
+        ..KdfParams::default()
+    };
+    assert!(slow.check().is_err());
+    assert!(KdfParams::default().check().is_ok());
+}
@@ -0,0 +1,99 @@
+//! Custom annotation table tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::clinvar::ClinicalSignificance;
+use genomeforge_core::annotation::consent::{ConsentPolicy, FindingCategory};
+use genomeforge_core::annotation::custom::{CustomAnnotations, DEFAULT_EVIDENCE};
+use genomeforge_core::annotation::manager::{DatabaseKind, LoadedDatabase};
+use std::path::Path;
+use tempfile::TempDir;
+
+const TABLE: &str =
+    "rsid\tGenotype\tgene\tinterpretation\tsignificance\tcategory\tevidence\tsource\n\
+rs80357906\t*\tBRCA1\tFounder variant, confirmed by Sanger\tpathogenic\t\t0.9\tLab A\n\
+rs1801133\tTT\tMTHFR\tReduced enzyme activity\trisk_factor\tnutrigenomics\t\t\n\
+rs1801133\tCC\tMTHFR\tTypical enzyme activity\tbenign\tnutrigenomics\t\t\n\
+rs63750847\t\tAPP\tProtective against Alzheimer's disease\t\t\t\t\n\
+rs3892097\tAG\tCYP2D6\tReduced CYP2D6 function\t\tpharmacogenomics\t\t\n";
+
+const CALLS: &[(&str, &str, u64, &str)] = &[
+    ("rs80357906", "17", 41209079, "TC"),
+    ("rs1801133", "1", 11856378, "TT"),
+    ("rs63750847", "21", 27269932, "AG"),
+    ("rs3892097", "22", 42524947, "GA"),
+];
+
+#[test]
+fn reads_tab_and_comma_separated_tables() {
+    let table = CustomAnnotations::from_reader(TABLE.as_bytes()).unwrap();
+    assert_eq!(table.len(), 5);
+
+    let csv = "RSID,interpretation,evidence\nrs80357906,\"Founder variant, BRCA1\",\n";
+    let genome = load_calls(37, CALLS);
+    let findings = CustomAnnotations::from_reader(csv.as_bytes())
+        .unwrap()
+        .annotate(&genome, &ConsentPolicy::default());
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].interpretation, "Founder variant, BRCA1");
+    assert_eq!(findings[0].significance, ClinicalSignificance::Other);
+    assert_eq!(findings[0].genotype, "TC");
+    assert!(findings[0].confidence.score <= DEFAULT_EVIDENCE);
+
+    let missing = CustomAnnotations::from_reader("rsid\tgene\nrs1\tBRCA1\n".as_bytes());
+    assert!(missing.unwrap_err().contains("interpretation"));
+    for row in ["chr1\tSomething\n", "rs1\tSomething\t1.5\n"] {
+        let table = format!("rsid\tinterpretation\tevidence\n{}", row);
+        let error = CustomAnnotations::from_reader(table.as_bytes()).unwrap_err();
+        assert!(error.starts_with("line 2:"), "{}", error);
+    }
+}
+
+#[test]
+fn annotates_matching_calls_within_consent() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("lab.csv");
+    std::fs::write(&path, TABLE).unwrap();
+    let file_name = DatabaseKind::Custom.file_name_for(&path);
+    assert_eq!(file_name, "custom_annotations.csv");
+    assert_eq!(
+        DatabaseKind::Custom.file_name_for(Path::new("lab.tsv.gz")),
+        "custom_annotations.tsv.gz"
+    );
+    assert!(!DatabaseKind::Custom.is_published());
+    let LoadedDatabase::Custom(table) = LoadedDatabase::load(DatabaseKind::Custom, &path).unwrap()
+    else {
+        panic!("expected a custom table");
+    };
+
+    let genome = load_calls(37, CALLS);
+    let findings = table.annotate(&genome, &ConsentPolicy::default());
+    let interpretations: Vec<&str> = findings
+        .iter()
+        .map(|finding| finding.interpretation.as_str())
+        .collect();
+    // The CYP2D6 entry matches its call written in the other allele order
+    assert_eq!(
+        interpretations,
+        [
+            "Founder variant, confirmed by Sanger",
+            "Reduced enzyme activity",
+            "Protective against Alzheimer's disease",
+            "Reduced CYP2D6 function",
+        ]
+    );
+    assert_eq!(findings[0].source.as_deref(), Some("Lab A"));
+    assert_eq!(findings[1].significance, ClinicalSignificance::RiskFactor);
+    assert!(findings[0].confidence.score > findings[1].confidence.score);
+
+    let policy = ConsentPolicy::excluding([
+        FindingCategory::Neurodegenerative,
+        FindingCategory::Pharmacogenomics,
+    ]);
+    let findings = table.annotate(&genome, &policy);
+    assert_eq!(findings.len(), 2);
+    assert!(findings
+        .iter()
+        .all(|finding| finding.gene.as_deref() != Some("APP")));
+}
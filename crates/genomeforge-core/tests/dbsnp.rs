@@ -0,0 +1,113 @@
+//! dbSNP resolution tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::dbsnp::DbSnpIndex;
+use genomeforge_core::annotation::pharmgkb::{PharmGkbDatabase, ALLELES_FILE, ANNOTATIONS_FILE};
+use genomeforge_core::GenomeBuild;
+use tempfile::TempDir;
+
+const DBSNP_VCF: &str = "##fileformat=VCFv4.2\n\
+##fileDate=20230424\n\
+##reference=GRCh37.p13\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+NC_000016.9\t31107689\trs9923231\tC\tT\t.\t.\tRS=9923231\n\
+NC_000001.10\t11856378\trs1801133\tG\tA\t.\t.\tRS=1801133\n\
+NC_000023.10\t153764217\trs5030868\tG\tA,C\t.\t.\tRS=5030868\n\
+NC_000007.13\t117199646\trs113993960\tATCT\tA\t.\t.\tRS=113993960\n\
+NT_167244.1\t100\trs1\tA\tG\t.\t.\tRS=1\n";
+
+const ARRAY: &str = "# reference human assembly build 37 (GRCh37.p13)\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs9923231\t16\t31107689\tCT\n\
+rs1801133\t1\t11856378\tTT\n\
+rs113993960\t7\t117199646\tDI\n\
+rs5030868\tX\t153764217\t--\n";
+
+const VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh37\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr16\t31107689\t.\tC\tT\t.\tPASS\t.\tGT\t0/1\n\
+chr1\t11856378\t.\tG\tC\t.\tPASS\t.\tGT\t0/1\n\
+chrX\t153764217\t.\tG\tC\t.\tPASS\t.\tGT\t1\n";
+
+const ANNOTATIONS: &str = "Clinical Annotation ID\tVariant/Haplotypes\tGene\tLevel of Evidence\tLevel Override\tLevel Modifiers\tScore\tPhenotype Category\tPMID Count\tEvidence Count\tDrug(s)\tPhenotype(s)\tLatest History Date (YYYY-MM-DD)\tURL\tSpecialty Population\n\
+1183614743\trs9923231\tVKORC1\t1A\t\t\t100\tDosage\t50\t60\twarfarin\t\t2021-03-24\t\t\n";
+
+const ALLELES: &str = "Clinical Annotation ID\tGenotype/Allele\tAnnotation Text\tAllele Function\n\
+1183614743\tCT\tPatients with the CT genotype may require a lower warfarin dose.\t\n";
+
+fn load_index() -> DbSnpIndex {
+    DbSnpIndex::from_vcf(DBSNP_VCF.as_bytes()).unwrap()
+}
+
+#[test]
+fn loads_refseq_chromosomes() {
+    let index = load_index();
+    // The unplaced NT_ contig is skipped
+    assert_eq!(index.len(), 4);
+    assert_eq!(index.genome_build(), Some(GenomeBuild::GRCh37));
+    assert_eq!(index.release_date(), Some("2023-04-24"));
+
+    let record = index.lookup_rsid("rs5030868")[0];
+    assert_eq!(record.chromosome, "X");
+    assert_eq!(record.alternates, vec!["A", "C"]);
+    assert_eq!(
+        index.lookup_position("chr16", 31107689)[0].rsid,
+        "rs9923231"
+    );
+    let alternates = ["C".to_string()];
+    assert_eq!(index.resolve_rsid("1", 11856378, "G", &alternates), None);
+}
+
+#[test]
+fn fills_alleles_for_array_calls() {
+    let genome = load_genome("genome.txt", ARRAY);
+    let (normalized, stats) = load_index().normalize(&genome, |_| Ok(())).unwrap();
+    assert_eq!(stats.rsids_added, 0);
+    assert_eq!(stats.alleles_added, 1);
+
+    let vkorc1 = normalized.get_by_rsid("rs9923231").unwrap();
+    assert_eq!(vkorc1.reference.as_deref(), Some("C"));
+    assert_eq!(vkorc1.alternates, vec!["T"]);
+
+    // TT does not fit a G/A site (likely the other strand), indels stay
+    // in D/I form and no-calls are left alone
+    assert_eq!(normalized.get_by_rsid("rs1801133").unwrap().reference, None);
+    assert_eq!(
+        normalized.get_by_rsid("rs113993960").unwrap().reference,
+        None
+    );
+    assert_eq!(normalized.get_by_rsid("rs5030868").unwrap().reference, None);
+}
+
+#[test]
+fn fills_rsids_for_vcf_sites_so_rsid_databases_match() {
+    let genome = load_genome("genome.vcf", VCF);
+    assert!(genome.get_by_rsid("rs9923231").is_none());
+
+    let (normalized, stats) = load_index().normalize(&genome, |_| Ok(())).unwrap();
+    // The chr1 site carries an alternate dbSNP does not list
+    assert_eq!(stats.rsids_added, 2);
+    assert!(normalized.get_at("1", 11856378).unwrap().rsid.is_none());
+    assert_eq!(
+        normalized.get_at("X", 153764217).unwrap().rsid.as_deref(),
+        Some("rs5030868")
+    );
+
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join(ANNOTATIONS_FILE), ANNOTATIONS).unwrap();
+    std::fs::write(dir.path().join(ALLELES_FILE), ALLELES).unwrap();
+    let pharmgkb = PharmGkbDatabase::load_dir(dir.path()).unwrap();
+    assert!(pharmgkb.annotate(&genome, |_| Ok(())).unwrap().is_empty());
+    let matches = pharmgkb.annotate(&normalized, |_| Ok(())).unwrap();
+    assert_eq!(matches.len(), 1);
+}
+
+#[test]
+fn skips_positions_from_another_build() {
+    let genome = load_genome("genome.vcf", &VCF.replace("GRCh37", "GRCh38"));
+    let (_, stats) = load_index().normalize(&genome, |_| Ok(())).unwrap();
+    assert_eq!(stats.rsids_added, 0);
+}
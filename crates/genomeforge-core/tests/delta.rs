@@ -0,0 +1,112 @@
+//! ClinVar release delta tests: added, removed and reclassified records are
+//! counted, and the genes they are in are annotated again in full
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::clinvar::{ClinVarDatabase, ClinVarMatch};
+use genomeforge_core::annotation::delta::ReleaseDelta;
+use genomeforge_core::LoadedGenome;
+use std::io::Cursor;
+
+const HEADER: &str = "##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n";
+
+const BRCA1_PATHOGENIC: &str = "17\t43045712\t17661\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357906\n";
+const BRCA1_OTHER: &str = "17\t43047643\t55555\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357000\n";
+const APOE: &str = "19\t44908684\t17864\tT\tC\t.\t.\tCLNSIG=risk_factor;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Alzheimer_disease;GENEINFO=APOE:348;RS=429358\n";
+const MTHFR: &str = "1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Benign;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=not_specified;GENEINFO=MTHFR:4524;RS=1801133\n";
+
+const GENOME_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr17\t43045712\t.\tG\tA\t.\tPASS\t.\tGT\t0/1\n\
+chr17\t43047643\t.\tC\tT\t.\tPASS\t.\tGT\t0/1\n\
+chr19\t44908684\trs429358\tT\tC\t.\tPASS\t.\tGT\t0/1\n\
+chr1\t11796321\trs1801133\tG\tA\t.\tPASS\t.\tGT\t0/1\n\
+chr2\t500\t.\tC\tT\t.\tPASS\t.\tGT\t1/1\n";
+
+fn release(date: &str, lines: &[&str]) -> ClinVarDatabase {
+    let vcf = format!(
+        "##fileformat=VCFv4.1\n##fileDate={}\n{}{}",
+        date,
+        HEADER,
+        lines.concat()
+    );
+    ClinVarDatabase::from_vcf(Cursor::new(vcf)).unwrap()
+}
+
+/// The older release, and a newer one that reclassifies the second BRCA1
+/// variant, drops MTHFR and adds a record at an unclassified site
+fn releases() -> (ClinVarDatabase, ClinVarDatabase) {
+    let previous = release("20240107", &[BRCA1_PATHOGENIC, BRCA1_OTHER, APOE, MTHFR]);
+    let reclassified = BRCA1_OTHER.replace("CLNSIG=Pathogenic", "CLNSIG=Uncertain_significance");
+    let added = "2\t500\t99999\tC\tT\t.\t.\tCLNSIG=Likely_pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Example_syndrome;RS=999\n";
+    let current = release("20240204", &[BRCA1_PATHOGENIC, &reclassified, APOE, added]);
+    (previous, current)
+}
+
+fn genome() -> LoadedGenome {
+    load_genome("genome.vcf", GENOME_VCF)
+}
+
+fn ids(matches: &[ClinVarMatch<'_>]) -> Vec<Option<u64>> {
+    let mut ids: Vec<_> = matches
+        .iter()
+        .map(|found| found.record.variation_id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn changed_records_are_counted_and_their_genes_kept() {
+    let (previous, current) = releases();
+    let delta = ReleaseDelta::between(&previous, &current);
+
+    assert_eq!((delta.added(), delta.removed(), delta.changed()), (1, 1, 1));
+    assert!(!delta.is_empty());
+    assert_eq!(delta.from_release(), Some("2024-01-07"));
+    assert_eq!(delta.to_release(), Some("2024-02-04"));
+    // The unchanged BRCA1 record comes along with the reclassified one
+    assert_eq!(delta.previous().len(), 3);
+    assert_eq!(delta.current().len(), 3);
+    assert!(delta.previous().lookup_rsid("rs429358").is_empty());
+
+    let touched = delta.touched(&genome());
+    let positions: Vec<_> = touched
+        .variants()
+        .iter()
+        .map(|variant| (variant.chromosome.as_str(), variant.position))
+        .collect();
+    assert_eq!(
+        positions,
+        [
+            ("17", 43045712),
+            ("17", 43047643),
+            ("1", 11796321),
+            ("2", 500)
+        ]
+    );
+    assert_eq!(touched.file.genome_build, genome().file.genome_build);
+
+    assert!(ReleaseDelta::between(&current, &current).is_empty());
+}
+
+#[test]
+fn touched_variants_match_as_in_the_full_genome() {
+    let (previous, current) = releases();
+    let delta = ReleaseDelta::between(&previous, &current);
+    let genome = genome();
+    let touched = delta.touched(&genome);
+
+    for (subset, full) in [(delta.previous(), &previous), (delta.current(), &current)] {
+        let mut expected = ids(&full.annotate(&genome, |_| Ok(())).unwrap());
+        // The APOE record is the same in both releases and not looked at
+        expected.retain(|id| *id != Some(17864));
+        assert_eq!(
+            ids(&subset.annotate(&touched, |_| Ok(())).unwrap()),
+            expected
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Log privacy filter, rotation and diagnostic bundle tests
+
+use genomeforge_core::diagnostics::{
+    self, LogEntry, LogFile, LogLevel, KEPT_LOGS, LOG_FILE, REDACTED,
+};
+use std::io::Read;
+use tempfile::TempDir;
+
+#[test]
+fn scrubs_genetic_data_and_paths() {
+    assert_eq!(
+        diagnostics::scrub("rs429358 at 19:44908684 is CT, and i5000123 is A/G."),
+        "[rsid] at [position] is [genotype], and [rsid] is [genotype]."
+    );
+    assert_eq!(
+        diagnostics::scrub("Found NM_007294.4:c.68_69delAG (p.Glu23fs) in chr17:43044295-43125483"),
+        "Found [variant] ([variant]) in [position]"
+    );
+    assert_eq!(
+        diagnostics::scrub("Failed to open C:\\Users\\jane\\genome.txt: denied; call 0|1"),
+        "Failed to open [path]: denied; call [genotype]"
+    );
+    // Ordinary words, numbers and errors with line numbers are kept
+    let kept = "line 12: expected 10 columns, found 8 in 2.5 s (A passphrase is required)";
+    assert_eq!(diagnostics::scrub(kept), kept);
+
+    let entry = LogEntry::new(
+        LogLevel::Info,
+        "genomeforge::commands",
+        "parsed /home/jane/raw.txt",
+        [
+            ("genotype".to_string(), "AG".to_string()),
+            ("variants".to_string(), "638000".to_string()),
+        ],
+    );
+    assert_eq!(entry.message, "parsed [path]");
+    assert_eq!(entry.fields["genotype"], REDACTED);
+    assert_eq!(entry.fields["variants"], "638000");
+}
+
+#[test]
+fn rotates_logs_and_bundles_them_scrubbed() {
+    let dir = TempDir::new().unwrap();
+    let mut log = LogFile::open(&dir.path().join("logs"))
+        .unwrap()
+        .with_max_bytes(400);
+    for index in 0..40 {
+        let level = if index % 10 == 0 {
+            LogLevel::Warn
+        } else {
+            LogLevel::Info
+        };
+        let message = format!("entry {}", index);
+        log.append(&LogEntry::new(level, "test", &message, []))
+            .unwrap();
+    }
+    let log_dir = dir.path().join("logs");
+    let files = diagnostics::log_files(&log_dir);
+    assert_eq!(files.len(), KEPT_LOGS + 1);
+    assert!(files.last().unwrap().ends_with(LOG_FILE));
+
+    let latest = diagnostics::recent(&log_dir, 2, LogLevel::Trace).unwrap();
+    assert_eq!(latest[0].message, "entry 39");
+    assert_eq!(latest[1].message, "entry 38");
+    let warnings = diagnostics::recent(&log_dir, 10, LogLevel::Warn).unwrap();
+    assert!(warnings.iter().all(|entry| entry.level == LogLevel::Warn));
+    assert_eq!(warnings[0].message, "entry 30");
+
+    // A line written by an older version that skipped the filter is
+    // scrubbed on its way into the bundle
+    std::fs::write(
+        log_dir.join(LOG_FILE),
+        "{\"timestamp\":1,\"level\":\"info\",\"target\":\"t\",\"message\":\"rs123 is TT\"}\nnot json at 1:12345\n",
+    )
+    .unwrap();
+    let path = dir.path().join("bundle.zip");
+    let bundle = diagnostics::write_bundle(
+        &path,
+        &log_dir,
+        &[("system.json", "{\"home\": \"/home/jane\"}".to_string())],
+    )
+    .unwrap();
+    assert_eq!(bundle.files[0], "README.txt");
+    assert_eq!(bundle.files.last().unwrap(), "system.json");
+    assert_eq!(bundle.size, std::fs::metadata(&path).unwrap().len());
+
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let mut read = |name: &str| {
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    };
+    let current = read(&format!("logs/{}", LOG_FILE));
+    assert!(current.contains("[rsid] is [genotype]"));
+    assert!(current.contains("not json at [position]"));
+    assert_eq!(read("system.json"), "{\"home\": \"[path]\"}");
+}
@@ -0,0 +1,78 @@
+//! Resumable download tests
+
+use genomeforge_core::annotation::manager::{download_path, DatabaseKind, Release};
+use genomeforge_core::download::{self, Backoff, Throttle};
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn release(sha256: &str) -> Release {
+    Release {
+        database: DatabaseKind::ClinVar,
+        version: "2024-05".to_string(),
+        url: "https://example.org/clinvar.vcf.gz".to_string(),
+        file_name: "clinvar.vcf.gz".to_string(),
+        sha256: sha256.to_string(),
+        size: None,
+    }
+}
+
+#[test]
+fn paces_and_spaces_out_attempts() {
+    let backoff = Backoff::default();
+    let delays: Vec<u64> = (1..=7)
+        .map(|attempt| backoff.delay(attempt).as_secs())
+        .collect();
+    assert_eq!(delays, [0, 1, 2, 4, 8, 16, 30]);
+    assert_eq!(backoff.delay(200), backoff.max);
+
+    let mut unlimited = Throttle::new(None);
+    assert_eq!(unlimited.record(10_000_000, Duration::ZERO), Duration::ZERO);
+    // 100 KB at 50 KB/s is due after two seconds
+    let mut throttle = Throttle::new(Some(50_000));
+    assert_eq!(
+        throttle.record(60_000, Duration::from_millis(200)),
+        Duration::from_millis(1000)
+    );
+    assert_eq!(
+        throttle.record(40_000, Duration::from_millis(1500)),
+        Duration::from_millis(500)
+    );
+    // Time lost to a slow network is not made up for later
+    assert_eq!(
+        throttle.record(50_000, Duration::from_secs(10)),
+        Duration::ZERO
+    );
+}
+
+#[test]
+fn resumes_where_the_download_stopped() {
+    assert_eq!(download::range_header(1024), "bytes=1024-");
+    assert_eq!(
+        download::resume_offset(206, Some("bytes 1024-4095/4096"), 1024),
+        Ok(1024)
+    );
+    assert_eq!(
+        download::resume_offset(206, Some("bytes 1024-4095/*"), 1024),
+        Ok(1024)
+    );
+    // A server that ignores the range sends the whole file again
+    assert_eq!(download::resume_offset(200, None, 1024), Ok(0));
+    assert!(download::resume_offset(206, Some("bytes 0-4095/4096"), 1024).is_err());
+    assert!(download::resume_offset(206, None, 1024).is_err());
+    assert!(download::resume_offset(404, None, 0).is_err());
+    assert!(download::is_retryable(503) && download::is_retryable(429));
+    assert!(!download::is_retryable(404));
+
+    let dir = TempDir::new().unwrap();
+    let old = download_path(dir.path(), &release(&"a".repeat(64))).unwrap();
+    std::fs::write(&old, b"partial").unwrap();
+    assert_eq!(
+        download_path(dir.path(), &release(&"A".repeat(64))).unwrap(),
+        old
+    );
+    assert!(old.is_file());
+    let new = download_path(dir.path(), &release(&"b".repeat(64))).unwrap();
+    assert_ne!(new, old);
+    assert!(!old.exists());
+    assert!(download_path(dir.path(), &release("../../etc")).is_err());
+}
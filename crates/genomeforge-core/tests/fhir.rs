@@ -0,0 +1,146 @@
+//! FHIR genomics report tests
+
+use genomeforge_core::annotation::clinvar::ClinicalSignificance;
+use genomeforge_core::annotation::zygosity::Zygosity;
+use genomeforge_core::fhir::{self, DrugImplication, GenomicsReport, VariantCall};
+use genomeforge_core::GenomeBuild;
+use serde_json::Value;
+
+fn report() -> GenomicsReport {
+    GenomicsReport {
+        id: "report-1".to_string(),
+        issued: 1_760_425_800,
+        genome_build: Some(GenomeBuild::GRCh38),
+        variants: vec![VariantCall {
+            rsid: Some("rs80357906".to_string()),
+            gene: Some("BRCA1".to_string()),
+            chromosome: Some("17".to_string()),
+            position: Some(43_057_062),
+            zygosity: Some(Zygosity::Heterozygous),
+            significance: Some(ClinicalSignificance::Pathogenic),
+            conditions: vec!["Hereditary breast and ovarian cancer syndrome".to_string()],
+        }],
+        implications: vec![
+            DrugImplication {
+                drug: "clopidogrel".to_string(),
+                gene: "CYP2C19".to_string(),
+                diplotype: Some("*1/*2".to_string()),
+                phenotype: Some("Intermediate Metabolizer".to_string()),
+                implication: "Reduced active metabolite formation".to_string(),
+                evidence: Some("CPIC A".to_string()),
+                ..DrugImplication::default()
+            },
+            DrugImplication {
+                drug: "voriconazole".to_string(),
+                gene: "CYP2C19".to_string(),
+                diplotype: Some("*1/*2".to_string()),
+                implication: "Lower exposure".to_string(),
+                ..DrugImplication::default()
+            },
+            DrugImplication {
+                drug: "warfarin".to_string(),
+                gene: "VKORC1".to_string(),
+                rsid: Some("rs9923231".to_string()),
+                implication: "Lower dose requirement".to_string(),
+                ..DrugImplication::default()
+            },
+        ],
+        conclusion: None,
+    }
+}
+
+fn profile(resource: &Value) -> &str {
+    resource["meta"]["profile"][0]
+        .as_str()
+        .unwrap()
+        .rsplit('/')
+        .next()
+        .unwrap()
+}
+
+fn component<'a>(resource: &'a Value, code: &str) -> &'a Value {
+    resource["component"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|component| component["code"]["coding"][0]["code"] == code)
+        .unwrap()
+}
+
+#[test]
+fn bundles_observations_under_a_diagnostic_report() {
+    let bundle = fhir::bundle(&report());
+    fhir::validate(&bundle).unwrap();
+    let resources: Vec<&Value> = bundle["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| &entry["resource"])
+        .collect();
+    let count = |name: &str| {
+        resources
+            .iter()
+            .filter(|resource| resource["meta"]["profile"].is_array() && profile(resource) == name)
+            .count()
+    };
+    // The shared CYP2C19 diplotype becomes one genotype observation
+    assert_eq!(count("variant"), 2);
+    assert_eq!(count("genotype"), 1);
+    assert_eq!(count("diagnostic-implication"), 1);
+    assert_eq!(count("therapeutic-implication"), 3);
+
+    let report = resources.last().unwrap();
+    assert_eq!(report["resourceType"], "DiagnosticReport");
+    assert_eq!(report["issued"], "2025-10-14T07:10:00Z");
+    assert_eq!(report["result"].as_array().unwrap().len(), 7);
+
+    let variant = resources
+        .iter()
+        .find(|resource| resource["meta"]["profile"].is_array() && profile(resource) == "variant")
+        .unwrap();
+    assert_eq!(
+        component(variant, "48013-7")["valueCodeableConcept"]["coding"][0]["code"],
+        "NC_000017.11"
+    );
+    assert_eq!(
+        component(variant, "53034-5")["valueCodeableConcept"]["coding"][0]["code"],
+        "LA6706-1"
+    );
+    assert_eq!(
+        component(variant, "81254-5")["valueRange"]["low"]["value"],
+        43_057_062
+    );
+}
+
+#[test]
+fn rejects_bundles_breaking_profile_constraints() {
+    let mut bundle = fhir::bundle(&report());
+    bundle["entry"][2]["resource"]["derivedFrom"][0]["reference"] = "urn:uuid:missing".into();
+    assert!(fhir::validate(&bundle).unwrap_err().contains("derived"));
+
+    let mut bundle = fhir::bundle(&report());
+    bundle["entry"][1]["resource"]["code"]["coding"][0]["code"] = "00000-0".into();
+    assert!(fhir::validate(&bundle).is_err());
+
+    let mut bundle = fhir::bundle(&report());
+    bundle["entry"][1]["resource"]["subject"]["reference"] = "Patient/other".into();
+    assert!(fhir::validate(&bundle).unwrap_err().contains("subject"));
+}
+
+#[test]
+fn maps_chromosomes_to_refseq_accessions() {
+    assert_eq!(
+        fhir::refseq_accession("1", GenomeBuild::GRCh37).as_deref(),
+        Some("NC_000001.10")
+    );
+    assert_eq!(
+        fhir::refseq_accession("X", GenomeBuild::GRCh38).as_deref(),
+        Some("NC_000023.11")
+    );
+    assert_eq!(
+        fhir::refseq_accession("MT", GenomeBuild::GRCh38).as_deref(),
+        Some("NC_012920.1")
+    );
+    assert_eq!(fhir::refseq_accession("23", GenomeBuild::GRCh38), None);
+    assert_eq!(fhir::refseq_accession("1", GenomeBuild::GRCh36), None);
+}
@@ -0,0 +1,44 @@
+//! File fingerprint tests
+
+use genomeforge_core::crypto::{Key, KeySource};
+use genomeforge_core::fingerprint::{self, FileFingerprint, FingerprintLog};
+use tempfile::TempDir;
+
+#[test]
+fn hashes_file_contents() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("genome.txt");
+    std::fs::write(&path, "abc").unwrap();
+
+    let fingerprint = FileFingerprint::of(&path).unwrap();
+    assert_eq!(
+        fingerprint.sha256,
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(fingerprint.size, 3);
+    assert_eq!(fingerprint::sha256_file(&path).unwrap(), fingerprint.sha256);
+    assert!(FileFingerprint::of(&dir.path().join("missing.txt")).is_err());
+}
+
+#[test]
+fn log_flags_changed_contents() {
+    let dir = TempDir::new().unwrap();
+    let key = Key::generate();
+    let log_path = dir.path().join("fingerprints.bin");
+    let genome = dir.path().join("genome.txt");
+
+    std::fs::write(&genome, "rs1\t1\t100\tAG\n").unwrap();
+    let mut log = FingerprintLog::open(&log_path, KeySource::Device(&key)).unwrap();
+    assert!(log.record(FileFingerprint::of(&genome).unwrap()).is_none());
+    // Loading the same contents again is not a change
+    assert!(log.record(FileFingerprint::of(&genome).unwrap()).is_none());
+    log.save(&log_path, KeySource::Device(&key)).unwrap();
+
+    std::fs::write(&genome, "rs1\t1\t100\tGG\n").unwrap();
+    let mut log = FingerprintLog::open(&log_path, KeySource::Device(&key)).unwrap();
+    assert_eq!(log.len(), 1);
+    let original = log.get(&genome).unwrap().sha256.clone();
+    let previous = log.record(FileFingerprint::of(&genome).unwrap()).unwrap();
+    assert_eq!(previous.sha256, original);
+    assert_ne!(log.get(&genome).unwrap().sha256, original);
+}
@@ -0,0 +1,49 @@
+//! Fitness panel tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::fitness;
+use genomeforge_core::annotation::gwas::{EffectDirection, TraitCategory};
+use genomeforge_core::LoadedGenome;
+
+/// Calls at consecutive positions on chromosome 1
+fn array(calls: &[(&str, &str)]) -> LoadedGenome {
+    let calls: Vec<_> = calls
+        .iter()
+        .enumerate()
+        .map(|(index, &(rsid, genotype))| (rsid, "1", 1000 + index as u64, genotype))
+        .collect();
+    load_calls(37, &calls)
+}
+
+#[test]
+fn bundles_every_marker_as_a_cited_fitness_association() {
+    let catalog = fitness::catalog();
+    assert_eq!(catalog.len(), fitness::MARKERS.len());
+    for marker in &fitness::MARKERS {
+        let associations = catalog.lookup_rsid(marker.rsid);
+        assert_eq!(associations.len(), 1);
+        let association = associations[0];
+        assert_eq!(association.category, TraitCategory::Fitness);
+        assert!(association.effect.is_some());
+        assert_eq!(association.pubmed_id.as_deref(), Some(marker.pubmed_id));
+    }
+}
+
+#[test]
+fn reports_carriers_of_the_effect_allele_only() {
+    let genome = array(&[("rs1815739", "CC"), ("rs4343", "GG"), ("rs1800012", "CA")]);
+    let catalog = fitness::catalog();
+    let matches = catalog.annotate(&genome, 1.0, |_| Ok(())).unwrap();
+    let found: Vec<(&str, usize)> = matches
+        .iter()
+        .map(|found| (found.association.rsid.as_str(), found.risk_allele_copies))
+        .collect();
+    // Ordered by p-value; GG carries no ACE insertion allele
+    assert_eq!(found, [("rs1815739", 2), ("rs1800012", 1)]);
+    assert_eq!(
+        matches[1].association.direction(),
+        EffectDirection::Decreased
+    );
+}
@@ -0,0 +1,46 @@
+//! Region parsing and gene coordinate tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::genes;
+use genomeforge_core::{GenomeBuild, Region};
+
+#[test]
+fn parses_regions_and_locates_genes() {
+    let region: Region = "chr17:43,044,295-43,125,483".parse().unwrap();
+    assert_eq!(region, Region::new("17", 43044295, 43125483));
+    assert_eq!(region.to_string(), "17:43044295-43125483");
+    assert_eq!("19:44908684".parse::<Region>().unwrap().end, 44908684);
+    assert_eq!("chrM".parse::<Region>().unwrap().chromosome, "MT");
+    assert!("17:200-100".parse::<Region>().is_err());
+    assert!("17:abc".parse::<Region>().is_err());
+
+    assert_eq!(genes::locate("brca1", GenomeBuild::GRCh38), Some(region));
+    let grch37 = genes::locate("BRCA1", GenomeBuild::GRCh37).unwrap();
+    assert_eq!((grch37.start, grch37.end), (41196312, 41277500));
+    assert!(genes::locate("BRCA1", GenomeBuild::GRCh36).is_none());
+    assert!(genes::find("NOTAGENE").is_none());
+}
+
+#[test]
+fn selects_variants_in_a_region() {
+    let genome = load_genome(
+        "genome.txt",
+        "# build 37\n# rsid\tchromosome\tposition\tgenotype\n\
+         rs429358\t19\t45411941\tCT\n\
+         rs7412\t19\t45412079\tCC\n\
+         rs1801133\t1\t11856378\tAG\n",
+    );
+
+    let apoe = genes::locate("APOE", GenomeBuild::GRCh37).unwrap();
+    let region = genome.region(&apoe);
+    let found: Vec<_> = region
+        .variants()
+        .iter()
+        .filter_map(|variant| variant.rsid.as_deref())
+        .collect();
+    assert_eq!(found, ["rs429358", "rs7412"]);
+    assert_eq!(region.summary.variant_count, 2);
+    assert_eq!(genome.region(&"1".parse().unwrap()).len(), 1);
+}
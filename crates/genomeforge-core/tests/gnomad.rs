@@ -0,0 +1,56 @@
+//! gnomAD frequency loading and lookup tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::gnomad::{GnomadDatabase, Population};
+use genomeforge_core::GenomeBuild;
+
+const GNOMAD_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t11796321\trs1801133\tG\tA\t.\tPASS\tAF=0.31;AF_afr=0.11;AF_eas=0.35;AF_nfe=0.34\n\
+chr6\t26092913\trs1800562\tG\tA\t.\tPASS\tAF=0.02;AF_nfe=0.057;AF_eas=.\n\
+chr17\t43045712\trs80357906\tG\tA,T\t.\tPASS\tAF=0.00001,.\n";
+
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\n\
+rs1801133\t1\t11856378\tAG\n";
+
+fn load_database() -> GnomadDatabase {
+    GnomadDatabase::from_vcf(GNOMAD_VCF.as_bytes()).unwrap()
+}
+
+#[test]
+fn loads_global_and_population_frequencies() {
+    let db = load_database();
+    // The T allele has no AF and is skipped
+    assert_eq!(db.len(), 3);
+    assert_eq!(db.genome_build(), Some(GenomeBuild::GRCh38));
+
+    let mthfr = &db
+        .lookup_allele("1", 11796321, "G", "A")
+        .unwrap()
+        .frequencies;
+    assert_eq!(mthfr.global, 0.31);
+    assert_eq!(mthfr.population(Population::Afr), Some(0.11));
+    assert_eq!(mthfr.population(Population::Sas), None);
+    assert_eq!(mthfr.max_frequency(), 0.35);
+
+    // A missing population value is left out rather than read as zero
+    let hfe = &db.lookup_rsid("rs1800562")[0].frequencies;
+    assert_eq!(hfe.populations.len(), 1);
+    assert_eq!(hfe.max_frequency(), 0.057);
+    assert!(db.lookup_allele("chr17", 43045712, "G", "T").is_none());
+}
+
+#[test]
+fn falls_back_to_rsid_across_builds() {
+    let genome = load_genome("genome.txt", GENOME);
+    let variant = genome.get_by_rsid("rs1801133").unwrap();
+
+    let db = load_database();
+    let build = Some(GenomeBuild::GRCh37);
+    let frequency = db.frequency(variant, build, "G", "A").unwrap();
+    assert_eq!(frequency.global, 0.31);
+    assert!(db.frequency(variant, build, "G", "C").is_none());
+}
@@ -0,0 +1,60 @@
+//! Bundled CPIC guideline tests: recommendations are found by gene and
+//! phenotype, and a release's own take precedence
+
+use genomeforge_core::annotation::cpic::{CpicDatabase, Recommendation};
+use genomeforge_core::annotation::guidelines;
+
+#[test]
+fn bundled_recommendations_match_called_phenotypes() {
+    let poor = guidelines::recommendations("cyp2c19", "Poor Metabolizer");
+    let drugs: Vec<_> = poor.iter().map(|found| found.drug.as_str()).collect();
+    assert_eq!(drugs, ["clopidogrel", "citalopram"]);
+    assert_eq!(poor[0].classification.as_deref(), Some("Strong"));
+    assert_eq!(poor[0].version.as_deref(), Some("2022 update"));
+    assert!(poor[0]
+        .guideline
+        .as_deref()
+        .unwrap()
+        .contains("clopidogrel"));
+
+    // No bundled row for the "Likely" phenotype, so the confirmed one is used
+    let likely = guidelines::recommendations("SLCO1B1", "Likely Poor Function");
+    assert_eq!(likely.len(), 1);
+    assert_eq!(likely[0].phenotype, "Poor Function");
+    assert!(guidelines::recommendations("CYP2C19", "Indeterminate").is_empty());
+
+    // Every phenotype a guideline covers is listed once per drug
+    let mut keys: Vec<_> = guidelines::RECOMMENDATIONS
+        .iter()
+        .map(|row| (row.gene, row.drug, row.phenotype))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), guidelines::RECOMMENDATIONS.len());
+}
+
+#[test]
+fn release_recommendations_take_precedence() {
+    let release = Recommendation {
+        gene: "CYP2C19".to_string(),
+        drug: "Clopidogrel".to_string(),
+        phenotype: "CYP2C19 Poor Metabolizer".to_string(),
+        implication: None,
+        recommendation: "Avoid clopidogrel".to_string(),
+        classification: Some("Strong".to_string()),
+        guideline: None,
+        version: None,
+    };
+    let database = CpicDatabase::from_parts(Vec::new(), vec![release]);
+
+    let found = database.recommendations_with_bundled("CYP2C19", "Poor Metabolizer");
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].recommendation, "Avoid clopidogrel");
+    assert_eq!(found[1].drug, "citalopram");
+    assert_eq!(
+        database
+            .recommendations("CYP2C19", "Poor Metabolizer")
+            .len(),
+        1
+    );
+}
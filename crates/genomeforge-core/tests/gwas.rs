@@ -0,0 +1,103 @@
+//! GWAS Catalog loading and matching tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::gwas::{
+    EffectDirection, EffectSize, GwasCatalog, TraitCategory, GENOME_WIDE_SIGNIFICANCE,
+};
+use tempfile::TempDir;
+
+const CATALOG: &str = "DATE ADDED TO CATALOG\tPUBMEDID\tSTUDY\tDISEASE/TRAIT\tCHR_ID\tCHR_POS\tREPORTED GENE(S)\tMAPPED_GENE\tSTRONGEST SNP-RISK ALLELE\tRISK ALLELE FREQUENCY\tP-VALUE\tOR or BETA\t95% CI (TEXT)\n\
+2008-06-16\t17463246\tStudy A\tType 2 diabetes\t10\t112998590\tTCF7L2\tTCF7L2\trs7903146-T\t0.3\t1E-48\t1.37\t[1.31-1.43]\n\
+2018-01-01\t29892013\tStudy B\tType 2 diabetes\t10\t112998590\tTCF7L2\tTCF7L2\trs7903146-T\t0.3\t2E-20\t1.30\t[1.2-1.4]\n\
+2019-05-01\t30000001\tStudy C\tHeight\t6\t34000000\tHMGA1\tLINC01512 - HMGA1\trs1776897-G\tNR\t3E-12\t0.04\t[0.03-0.05] cm decrease\n\
+2020-02-02\t30000002\tStudy D\tCoronary artery disease\t9\t22125504\tCDKN2B-AS1\tCDKN2B-AS1\trs1333049-C\t0.47\t1E-6\t1.2\t[1.1-1.3]\n\
+2021-03-03\t30000003\tStudy E\tEye color\t15\t28365618\tHERC2\tHERC2\trs12913832-?\tNR\t1E-100\t\t\n\
+2022-04-04\t30000004\tStudy F\tAsthma\t17\t39910119\tORMDL3\tORMDL3\trs7216389-T x rs2305480-G\tNR\t1E-10\t1.4\t[1.2-1.6]\n";
+
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\n\
+rs7903146\t10\t114758349\tCT\n\
+rs1776897\t6\t34204362\tGG\n\
+rs1333049\t9\t22125503\tCC\n";
+
+fn load_catalog() -> GwasCatalog {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("gwas_catalog.tsv");
+    std::fs::write(&path, CATALOG).unwrap();
+    GwasCatalog::load(&path).unwrap()
+}
+
+#[test]
+fn loads_single_snp_associations() {
+    let catalog = load_catalog();
+    // Unknown risk alleles and SNP interactions are skipped
+    assert_eq!(catalog.len(), 4);
+    assert_eq!(catalog.release_date(), Some("2020-02-02"));
+
+    let t2d = catalog.lookup_rsid("rs7903146")[0];
+    assert_eq!(t2d.risk_allele, "T");
+    assert_eq!(t2d.category, TraitCategory::Metabolic);
+    assert_eq!(t2d.effect, Some(EffectSize::OddsRatio(1.37)));
+    assert_eq!(t2d.direction(), EffectDirection::Increased);
+
+    let height = catalog.lookup_rsid("rs1776897")[0];
+    assert_eq!(height.effect, Some(EffectSize::Beta(-0.04)));
+    assert_eq!(height.direction(), EffectDirection::Decreased);
+    assert_eq!(height.genes, vec!["LINC01512", "HMGA1"]);
+    assert_eq!(height.category, TraitCategory::Anthropometric);
+}
+
+#[test]
+fn matches_carried_risk_alleles_at_significance_threshold() {
+    let catalog = load_catalog();
+    let genome = load_genome("genome.txt", GENOME);
+    let matches = catalog
+        .annotate(&genome, GENOME_WIDE_SIGNIFICANCE, |_| Ok(()))
+        .unwrap();
+
+    // The coronary association is not genome-wide significant
+    assert_eq!(matches.len(), 2);
+    assert_eq!(
+        matches[0].association.pubmed_id.as_deref(),
+        Some("17463246")
+    );
+    assert_eq!(matches[0].risk_allele_copies, 1);
+    assert_eq!(matches[1].risk_allele_copies, 2);
+
+    let relaxed = catalog.annotate(&genome, 1e-5, |_| Ok(())).unwrap();
+    assert_eq!(relaxed.len(), 3);
+    assert_eq!(
+        relaxed[2].association.category,
+        TraitCategory::Cardiovascular
+    );
+}
+
+#[test]
+fn confidence_grows_with_significance_and_effect() {
+    let catalog = load_catalog();
+    let strong = catalog.lookup_rsid("rs7903146")[0].confidence();
+    let weak = catalog.lookup_rsid("rs1333049")[0].confidence();
+    assert!(strong > weak, "{} <= {}", strong, weak);
+    assert!((0.0..=1.0).contains(&strong));
+}
+
+#[test]
+fn classifies_traits_by_keyword() {
+    assert_eq!(
+        TraitCategory::classify("Breast cancer"),
+        TraitCategory::Cancer
+    );
+    assert_eq!(
+        TraitCategory::classify("Systolic blood pressure"),
+        TraitCategory::Cardiovascular
+    );
+    assert_eq!(
+        TraitCategory::classify("Schizophrenia"),
+        TraitCategory::Psychiatric
+    );
+    assert_eq!(
+        TraitCategory::classify("Left-handedness"),
+        TraitCategory::Other
+    );
+}
@@ -0,0 +1,98 @@
+//! Haplogroup calling tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::haplogroup::{HaplogroupDatabase, Lineage, YData};
+use genomeforge_core::annotation::manager::{DatabaseKind, LoadedDatabase};
+use tempfile::TempDir;
+
+const TREE: &str = "haplogroup\tparent\tmarker\trsid\tchromosome\tgrch37_position\tgrch38_position\tancestral\tderived\n\
+P\t.\tM45\trs2032631\tY\t.\t.\tG\tA\n\
+R\tP\tM207\trs2032658\tY\t.\t.\tA\tG\n\
+R1\tR\tM173\t.\tY\t15026424\t12894538\tA\tC\n\
+R1a\tR1\tM420\t.\tY\t23473201\t21311315\tT\tA\n\
+R1b\tR1\tM343\t.\tY\t2887824\t2755865\tC\tA\n\
+Q\tP\tM242\trs8179021\tY\t.\t.\tC\tT\n\
+L3\t.\t.\t.\tMT\t.\t.\t.\t.\n\
+N\tL3\tT8701A\t.\tMT\t8701\t8701\tA\tG\n\
+R0\tN\tT12705C\t.\tMT\t12705\t12705\tT\tC\n\
+HV\tR0\tT14766C\t.\tMT\t14766\t14766\tC\tT\n\
+H\tHV\tG2706A\t.\tMT\t2706\t2706\tG\tA\n\
+H1\tH\tG3010A\t.\tMT\t3010\t3010\tG\tA\n";
+
+#[test]
+fn reads_both_trees_from_one_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("haplogroups.tsv");
+    std::fs::write(&path, TREE).unwrap();
+    let LoadedDatabase::Haplogroups(database) =
+        LoadedDatabase::load(DatabaseKind::Haplogroups, &path).unwrap()
+    else {
+        panic!("expected haplogroup trees");
+    };
+    assert_eq!(database.len(), 11);
+    assert_eq!(database.paternal.marker_count(), 6);
+    assert_eq!(database.maternal.lineage, Lineage::Maternal);
+    // A haplogroup without markers of its own is still part of the tree
+    assert!(database.maternal.node("L3").unwrap().markers.is_empty());
+    let r1b = database.paternal.node("R1b").unwrap();
+    assert_eq!(r1b.parent.as_deref(), Some("R1"));
+    assert_eq!(r1b.markers[0].grch38_position, Some(2755865));
+
+    let bad = "haplogroup\tparent\tchromosome\tancestral\tderived\nA\t.\t1\tA\tG\n";
+    assert!(HaplogroupDatabase::from_reader(bad.as_bytes()).is_err());
+}
+
+#[test]
+fn calls_paternal_and_maternal_haplogroups() {
+    let database = HaplogroupDatabase::from_reader(TREE.as_bytes()).unwrap();
+    let male = load_calls(
+        37,
+        &[
+            ("rs2032631", "Y", 21764431, "A"),
+            ("rs2032658", "Y", 14969634, "G"),
+            ("i3000043", "Y", 15026424, "C"),
+            ("i4000001", "Y", 23473201, "T"),
+            ("i4000002", "Y", 2887824, "A"),
+            ("rs8179021", "Y", 21758672, "C"),
+            ("i5000001", "MT", 8701, "G"),
+            ("i5000002", "MT", 12705, "C"),
+            // Minus-strand call of the derived T
+            ("i5000003", "MT", 14766, "A"),
+            ("i5000004", "MT", 2706, "A"),
+            ("i5000005", "MT", 3010, "G"),
+        ],
+    );
+    let report = database.report(&male);
+    assert_eq!(report.y_data, YData::Present);
+    let paternal = report.paternal.unwrap();
+    assert_eq!(paternal.haplogroup, "R1b");
+    assert_eq!(paternal.path, ["P", "R", "R1", "R1b"]);
+    assert_eq!(paternal.derived_markers, ["M45", "M207", "M173", "M343"]);
+    assert!(paternal.conflicting_markers.is_empty());
+    assert_eq!((paternal.markers_genotyped, paternal.markers_total), (6, 6));
+    let maternal = report.maternal.unwrap();
+    assert_eq!(maternal.haplogroup, "H");
+    assert_eq!(maternal.path, ["L3", "N", "R0", "HV", "H"]);
+
+    // Female arrays report Y markers as no-calls
+    let female = load_calls(
+        37,
+        &[
+            ("rs2032631", "Y", 21764431, "--"),
+            ("rs2032658", "Y", 14969634, "--"),
+            ("rs8179021", "Y", 21758672, "C"),
+            ("i5000004", "MT", 2706, "A"),
+        ],
+    );
+    let report = database.report(&female);
+    assert_eq!(report.y_data, YData::NoCalls);
+    assert!(report.paternal.is_none());
+    assert_eq!(report.maternal.unwrap().haplogroup, "H");
+
+    let no_y = load_calls(37, &[("rs1", "1", 1000, "AG")]);
+    let report = database.report(&no_y);
+    assert_eq!(report.y_data, YData::Absent);
+    assert!(report.maternal.is_none());
+}
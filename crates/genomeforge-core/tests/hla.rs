@@ -0,0 +1,77 @@
+//! HLA risk allele tests: typed records are read directly and proxy SNPs
+//! stand in for them otherwise
+
+mod common;
+
+use common::{load_calls, load_genome};
+use genomeforge_core::annotation::hla::{self, HlaEvidence};
+use genomeforge_core::LoadedGenome;
+
+/// Calls on chromosome 6
+fn array(calls: &[(&str, u64, &str)]) -> LoadedGenome {
+    let calls: Vec<_> = calls
+        .iter()
+        .map(|&(rsid, position, genotype)| (rsid, "6", position, genotype))
+        .collect();
+    load_calls(37, &calls)
+}
+
+#[test]
+fn proxy_snps_tag_risk_alleles() {
+    let calls = hla::call(&array(&[("rs2395029", 31431780, "TG")]));
+    assert_eq!(calls.len(), 1);
+    let call = &calls[0];
+    assert_eq!(call.allele, "HLA-B*57:01");
+    assert_eq!((call.copies, call.evidence), (1, HlaEvidence::Proxy));
+    assert!(call.is_carrier());
+    assert_eq!(call.drugs, ["abacavir"]);
+    assert!(call.caveat.as_deref().unwrap().contains("European"));
+
+    // Reported on the minus strand, and found by position without an rsid
+    let minus = hla::call(&array(&[("i5000101", 31431780, "AA")]));
+    assert_eq!(minus[0].copies, 0);
+    assert_eq!(minus[0].marker, "rs2395029");
+    let both = hla::call(&array(&[
+        ("rs2395029", 31431780, "TT"),
+        ("rs144012689", 31000000, "AA"),
+    ]));
+    assert_eq!(
+        both.iter()
+            .map(|call| (call.allele.as_str(), call.copies))
+            .collect::<Vec<_>>(),
+        [("HLA-B*57:01", 0), ("HLA-B*15:02", 2)]
+    );
+    assert!(hla::call(&array(&[("rs1", 100, "AA")])).is_empty());
+}
+
+#[test]
+fn typed_records_take_precedence_over_proxies() {
+    let vcf = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+chr6\t31353872\tHLA_B*57:01:01\tA\tP\t.\tPASS\t.\tGT\t0/0\n\
+chr6\t31353873\tHLA_B_1502\tA\tT\t.\tPASS\t.\tGT\t0/1\n\
+chr6\t31464003\trs2395029\tT\tG\t.\tPASS\t.\tGT\t0/1\n";
+    let calls = hla::call(&load_genome("typed.vcf", vcf));
+    assert_eq!(calls.len(), 2);
+    assert_eq!(
+        (calls[0].marker.as_str(), calls[0].copies, calls[0].evidence),
+        ("HLA_B*57:01:01", 0, HlaEvidence::Typed)
+    );
+    assert!(calls[0].caveat.is_none());
+    assert_eq!(
+        (calls[1].allele.as_str(), calls[1].copies),
+        ("HLA-B*15:02", 1)
+    );
+
+    assert_eq!(
+        hla::typed_allele("HLA-DRB1*15:01").as_deref(),
+        Some("HLA-DRB1*15:01")
+    );
+    assert_eq!(
+        hla::typed_allele("hla_a_3101").as_deref(),
+        Some("HLA-A*31:01")
+    );
+    assert_eq!(hla::typed_allele("HLA_B_57"), None);
+    assert_eq!(hla::typed_allele("rs2395029"), None);
+}
@@ -0,0 +1,120 @@
+//! Imputed genotype parsing and filtering tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::cache::GenomeCache;
+use genomeforge_core::crypto::Key;
+use genomeforge_core::imputation::{self, ImputationFilter, Rejection};
+use genomeforge_core::prs::{MissingStrategy, ScoringFile};
+use genomeforge_core::LoadedGenome;
+use tempfile::TempDir;
+
+/// Michigan Imputation Server output: a typed site, a well and a poorly
+/// imputed one, and one with Phred-scaled probabilities from another tool
+const IMPUTED_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh37\n\
+##INFO=<ID=IMPUTED,Number=0,Type=Flag,Description=\"Marker was imputed\">\n\
+##INFO=<ID=TYPED,Number=0,Type=Flag,Description=\"Marker was genotyped\">\n\
+##INFO=<ID=R2,Number=1,Type=Float,Description=\"Estimated Imputation Accuracy\">\n\
+##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n\
+##FORMAT=<ID=DS,Number=1,Type=Float,Description=\"Estimated Alternate Allele Dosage\">\n\
+##FORMAT=<ID=GP,Number=G,Type=Float,Description=\"Estimated Posterior Probabilities\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+1\t100\trs1\tG\tA\t.\tPASS\tTYPED;R2=0.99\tGT:DS:GP\t0|1:1.000:0,1,0\n\
+1\t200\trs2\tC\tT\t.\tPASS\tIMPUTED;R2=0.92\tGT:DS:GP\t1|1:1.900:0.002,0.096,0.902\n\
+1\t300\trs3\tA\tG\t.\tPASS\tIMPUTED;R2=0.21\tGT:DS:GP\t0|1:0.800:0.3,0.6,0.1\n\
+2\t400\trs4\tT\tC\t.\tPASS\tDR2=0.95\tGT:GP\t0/0:0,20,40\n\
+2\t500\trs5\tA\tC\t.\tPASS\t.\tGT\t0/1\n";
+
+fn load() -> LoadedGenome {
+    load_genome("chr1.dose.vcf", IMPUTED_VCF)
+}
+
+#[test]
+fn reads_imputation_marks_and_scores() {
+    let dir = TempDir::new().unwrap();
+    let genome = load();
+    let quality = |rsid: &str| genome.get_by_rsid(rsid).unwrap().quality.clone();
+
+    let typed = quality("rs1").unwrap();
+    assert!(!typed.imputed);
+    assert_eq!(typed.info_score, Some(0.99));
+    let imputed = quality("rs2").unwrap();
+    assert!(imputed.imputed);
+    assert_eq!(
+        (imputed.info_score, imputed.probability, imputed.dosage),
+        (Some(0.92), Some(0.902), Some(1.9))
+    );
+    // A score without a flag marks an imputed site; Phred values are
+    // converted to probabilities
+    let beagle = quality("rs4").unwrap();
+    assert!(beagle.imputed);
+    assert_eq!(beagle.probability, Some(1.0));
+    assert!(quality("rs5").is_none());
+    assert!(!genome.get_by_rsid("rs5").unwrap().is_imputed());
+
+    // The marks survive the genome cache
+    let cache = GenomeCache::new(&dir.path().join("cache"), Key::generate());
+    let hash = "0".repeat(64);
+    cache.put(&hash, &genome).unwrap();
+    let cached = cache.get(&hash).unwrap().unwrap();
+    assert_eq!(cached.get_by_rsid("rs2").unwrap().quality, Some(imputed));
+    assert!(cached.get_by_rsid("rs5").unwrap().quality.is_none());
+}
+
+#[test]
+fn filters_low_confidence_calls_and_scores_dosages() {
+    let genome = load();
+
+    let filter = ImputationFilter {
+        min_info_score: Some(0.3),
+        min_probability: Some(0.9),
+        exclude_imputed: false,
+    };
+    assert!(filter.validate().is_ok());
+    assert_eq!(
+        filter.rejects(genome.get_by_rsid("rs3").unwrap()),
+        Some(Rejection::LowInfoScore)
+    );
+    let (filtered, stats) = imputation::filter_genome(&genome, &filter, |_| Ok(())).unwrap();
+    assert_eq!((stats.imputed, stats.typed), (3, 2));
+    assert_eq!(stats.excluded_low_info_score, 1);
+    assert_eq!(stats.excluded(), 1);
+    assert!(filtered.get_by_rsid("rs3").unwrap().genotype.is_no_call());
+    assert!(!filtered.get_by_rsid("rs2").unwrap().genotype.is_no_call());
+    assert_eq!(filtered.summary.no_call_count, 1);
+
+    let (typed_only, stats) = imputation::filter_genome(
+        &genome,
+        &ImputationFilter {
+            exclude_imputed: true,
+            ..ImputationFilter::default()
+        },
+        |_| Ok(()),
+    )
+    .unwrap();
+    assert_eq!(stats.excluded_imputed, 3);
+    assert!(typed_only.get_by_rsid("rs4").unwrap().genotype.is_no_call());
+    assert!(ImputationFilter {
+        min_probability: Some(1.5),
+        ..ImputationFilter::default()
+    }
+    .validate()
+    .is_err());
+
+    // The imputed dosage counts instead of the rounded call: 1.9 copies of
+    // T at rs2 and 2 - 0.8 copies of A at rs3
+    let scoring = ScoringFile::from_reader(
+        "rsID\teffect_allele\tother_allele\teffect_weight\n\
+rs2\tT\tC\t1.0\n\
+rs3\tA\tG\t1.0\n"
+            .as_bytes(),
+    )
+    .unwrap();
+    let result = scoring
+        .compute(&genome, MissingStrategy::Skip, None, |_| Ok(()))
+        .unwrap();
+    assert!((result.raw_score - 3.1).abs() < 1e-6);
+    assert_eq!(result.coverage.imputed_dosages, 2);
+}
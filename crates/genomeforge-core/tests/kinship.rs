@@ -0,0 +1,108 @@
+//! Relatedness estimation tests
+
+mod common;
+
+use genomeforge_core::kinship::{self, Relationship};
+use genomeforge_core::LoadedGenome;
+
+const SITES: u64 = 3_000;
+
+/// Deterministic pseudo-random bits
+struct Lcg(u64);
+
+impl Lcg {
+    fn bit(&mut self) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize & 1
+    }
+}
+
+/// Alleles of one person at each site
+type Alleles = Vec<[usize; 2]>;
+
+fn founder(rng: &mut Lcg) -> Alleles {
+    (0..SITES).map(|_| [rng.bit(), rng.bit()]).collect()
+}
+
+fn child_of(rng: &mut Lcg, mother: &Alleles, father: &Alleles) -> Alleles {
+    mother
+        .iter()
+        .zip(father)
+        .map(|(m, f)| [m[rng.bit()], f[rng.bit()]])
+        .collect()
+}
+
+fn load_genome(alleles: &Alleles) -> LoadedGenome {
+    let mut contents = "# build 37\n# rsid\tchromosome\tposition\tgenotype\n".to_string();
+    for (site, pair) in alleles.iter().enumerate() {
+        let call: String = pair.iter().map(|&a| ['A', 'G'][a]).collect();
+        contents.push_str(&format!(
+            "rs{}\t{}\t{}\t{}\n",
+            site + 1,
+            site % 22 + 1,
+            site * 100 + 1,
+            call
+        ));
+    }
+    // Sex chromosome calls are ignored
+    contents.push_str("rs999999\tX\t5000\tAA\n");
+    common::load_genome("genome.txt", &contents)
+}
+
+#[test]
+fn classifies_family_relationships() {
+    let mut rng = Lcg(7);
+    let (mother, father, stranger) = (founder(&mut rng), founder(&mut rng), founder(&mut rng));
+    let (first, second) = (
+        child_of(&mut rng, &mother, &father),
+        child_of(&mut rng, &mother, &father),
+    );
+    let grandchild = child_of(&mut rng, &first, &stranger);
+    let [mother, father, stranger, first, second, grandchild] =
+        [&mother, &father, &stranger, &first, &second, &grandchild].map(load_genome);
+
+    let estimate =
+        |a: &LoadedGenome, b: &LoadedGenome| kinship::estimate(a, b, |_| Ok(())).unwrap();
+    let parent = estimate(&mother, &first);
+    assert_eq!(parent.sites_compared, SITES as usize);
+    assert_eq!(parent.ibs0, 0);
+    assert!((parent.kinship - 0.25).abs() < 0.04, "{}", parent.kinship);
+    assert_eq!(parent.relationship, Relationship::ParentChild);
+
+    assert_eq!(
+        estimate(&first, &second).relationship,
+        Relationship::FullSiblings
+    );
+    assert_eq!(
+        estimate(&mother, &grandchild).relationship,
+        Relationship::SecondDegree
+    );
+    assert_eq!(
+        estimate(&mother, &father).relationship,
+        Relationship::Unrelated
+    );
+    assert_eq!(
+        estimate(&stranger, &second).relationship,
+        Relationship::Unrelated
+    );
+    let same = estimate(&first, &first);
+    assert!((same.kinship - 0.5).abs() < 1e-9);
+    assert_eq!(same.relationship, Relationship::Duplicate);
+}
+
+#[test]
+fn needs_enough_shared_sites() {
+    let mut rng = Lcg(11);
+    let genome = load_genome(&founder(&mut rng));
+    let few = load_genome(&founder(&mut rng)[..500].to_vec());
+    let error = kinship::estimate(&genome, &few, |_| Ok(())).unwrap_err();
+    assert!(error.contains("500 sites"), "{}", error);
+
+    let stopped = kinship::estimate(&genome, &genome, |_| Err("Cancelled".into()));
+    assert!(stopped.is_err());
+    assert_eq!(kinship::classify(0.12, 0.02), Relationship::SecondDegree);
+    assert_eq!(kinship::classify(0.06, 0.05), Relationship::ThirdDegree);
+}
@@ -0,0 +1,90 @@
+//! Build detection and liftover tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::liftover::{detect_build, Lifted, Liftover};
+use genomeforge_core::{GenomeBuild, Genotype};
+
+const CHAIN: &str = "chain 1000 chr1 1000 + 100 400 chr1 1000 + 200 500 1\n\
+100 50 60\n\
+150\n\
+\n\
+chain 500 chr2 1000 + 0 100 chr2 1000 - 0 100 2\n\
+100\n\
+\n\
+chain 10 chr1 1000 + 120 130 chr5 1000 + 0 10 3\n\
+10\n";
+
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\n\
+rs1\t1\t150\tAG\n\
+rs2\t1\t220\tCC\n\
+rs3\t1\t125\tTT\n\
+rs4\t2\t10\tAG\n\
+rs5\t3\t10\tAA\n";
+
+fn load_chain() -> Liftover {
+    Liftover::from_chain(CHAIN.as_bytes(), GenomeBuild::GRCh37, GenomeBuild::GRCh38).unwrap()
+}
+
+#[test]
+fn maps_positions_through_chain_blocks() {
+    let liftover = load_chain();
+    let mapped = |chromosome: &str, position: u64, reverse: bool| Lifted::Mapped {
+        chromosome: chromosome.to_string(),
+        position,
+        reverse,
+    };
+
+    assert_eq!(liftover.lift("chr1", 150), mapped("1", 250, false));
+    // Second block, past a 50 base source gap and a 60 base target gap
+    assert_eq!(liftover.lift("1", 260), mapped("1", 370, false));
+    assert_eq!(liftover.lift("1", 220), Lifted::Unmapped);
+    assert_eq!(liftover.lift("1", 125), Lifted::Ambiguous);
+    assert_eq!(liftover.lift("2", 10), mapped("2", 991, true));
+    assert_eq!(liftover.lift("3", 10), Lifted::Unmapped);
+}
+
+#[test]
+fn lifts_genomes_and_counts_outcomes() {
+    let genome = load_genome("genome.txt", GENOME);
+    let (lifted, stats) = load_chain().lift_genome(&genome, |_| Ok(())).unwrap();
+
+    assert_eq!((stats.lifted, stats.dropped, stats.ambiguous), (2, 2, 1));
+    assert_eq!(lifted.file.genome_build, Some(GenomeBuild::GRCh38));
+    assert_eq!(
+        lifted.get_at("1", 250).unwrap().rsid.as_deref(),
+        Some("rs1")
+    );
+
+    // The reverse strand chain flips the call
+    let reverse = lifted.get_by_rsid("rs4").unwrap();
+    assert_eq!(reverse.position, 991);
+    assert_eq!(reverse.genotype, Genotype::from_array_call("TC"));
+    assert!(lifted.get_by_rsid("rs2").is_none());
+}
+
+#[test]
+fn detects_build_from_marker_positions() {
+    let grch37 = load_genome(
+        "genome.txt",
+        "# rsid\tchromosome\tposition\tgenotype\n\
+rs429358\t19\t45411941\tTT\n\
+rs7412\t19\t45412079\tCC\n",
+    );
+    assert_eq!(grch37.file.genome_build, None);
+    assert_eq!(detect_build(&grch37), Some(GenomeBuild::GRCh37));
+
+    let grch38 = load_genome(
+        "genome.txt",
+        "# rsid\tchromosome\tposition\tgenotype\n\
+rs429358\t19\t44908684\tTT\n",
+    );
+    assert_eq!(detect_build(&grch38), Some(GenomeBuild::GRCh38));
+
+    let unknown = load_genome(
+        "genome.txt",
+        "# rsid\tchromosome\tposition\tgenotype\nrs1\t1\t100\tAA\n",
+    );
+    assert_eq!(detect_build(&unknown), None);
+}
@@ -0,0 +1,328 @@
+//! Database release verification and installation tests
+
+use ed25519_dalek::{Signer, SigningKey};
+use genomeforge_core::annotation::manager::{
+    find_installed, install_release, sha256_file, sidecar_checksum, stage_local, staging_path,
+    verify_checksum, verify_installed, DatabaseKind, InstalledReleases, IntegrityStatus,
+    LoadedDatabase, Release, ReleaseManifest,
+};
+use genomeforge_core::annotation::pharmgkb::{ALLELES_FILE, ANNOTATIONS_FILE};
+use std::io::Write;
+use tempfile::TempDir;
+
+const GWAS_HEADER: &str = "DATE ADDED TO CATALOG\tPUBMEDID\tSTUDY\tDISEASE/TRAIT\tCHR_ID\tCHR_POS\tREPORTED GENE(S)\tMAPPED_GENE\tSTRONGEST SNP-RISK ALLELE\tRISK ALLELE FREQUENCY\tP-VALUE\tOR or BETA\t95% CI (TEXT)\n";
+
+const GWAS_T2D: &str = "2008-06-16\t17463246\tStudy A\tType 2 diabetes\t10\t112998590\tTCF7L2\tTCF7L2\trs7903146-T\t0.3\t1E-48\t1.37\t[1.31-1.43]\n";
+
+const GWAS_HEIGHT: &str = "2019-05-01\t30000001\tStudy C\tHeight\t6\t34000000\tHMGA1\tHMGA1\trs1776897-G\tNR\t3E-12\t0.04\t[0.03-0.05] cm decrease\n";
+
+const ANNOTATIONS: &str = "Clinical Annotation ID\tVariant/Haplotypes\tGene\tLevel of Evidence\tLevel Override\tLevel Modifiers\tScore\tPhenotype Category\tPMID Count\tEvidence Count\tDrug(s)\tPhenotype(s)\tLatest History Date (YYYY-MM-DD)\tURL\tSpecialty Population\n\
+1183614743\trs9923231\tVKORC1\t1A\t\t\t100\tDosage\t50\t60\twarfarin\t\t2021-03-24\t\t\n";
+
+const ALLELES: &str = "Clinical Annotation ID\tGenotype/Allele\tAnnotation Text\tAllele Function\n\
+1183614743\tCT\tPatients with the CT genotype may require a lower warfarin dose.\t\n";
+
+const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+fn stage(dir: &TempDir, kind: DatabaseKind, rows: &[&str]) -> std::path::PathBuf {
+    let path = staging_path(dir.path(), kind).unwrap();
+    std::fs::write(
+        &path,
+        [GWAS_HEADER]
+            .iter()
+            .chain(rows)
+            .copied()
+            .collect::<String>(),
+    )
+    .unwrap();
+    path
+}
+
+#[test]
+fn verifies_checksums_and_signatures() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("empty");
+    std::fs::write(&path, "").unwrap();
+    assert_eq!(sha256_file(&path).unwrap(), EMPTY_SHA256);
+    assert!(verify_checksum(&path, &EMPTY_SHA256.to_uppercase()).is_ok());
+    assert!(verify_checksum(&path, &"0".repeat(64)).is_err());
+
+    let key = SigningKey::from_bytes(&[7; 32]);
+    let public_key = hex::encode(key.verifying_key().to_bytes());
+    let manifest = format!(
+        r#"{{"releases":[{{"database":"gwas","version":"2024-01","url":"https://example.org/gwas.tsv","file_name":"gwas_catalog.tsv","sha256":"{}","size":null}}]}}"#,
+        EMPTY_SHA256
+    );
+    let signature = hex::encode(key.sign(manifest.as_bytes()).to_bytes());
+
+    let parsed =
+        ReleaseManifest::parse_signed(manifest.as_bytes(), &signature, &public_key).unwrap();
+    let release = parsed.release(DatabaseKind::Gwas).unwrap();
+    assert_eq!(release.version, "2024-01");
+    assert!(parsed.release(DatabaseKind::ClinVar).is_none());
+
+    let tampered = manifest.replace("2024-01", "2024-02");
+    assert!(ReleaseManifest::parse_signed(tampered.as_bytes(), &signature, &public_key).is_err());
+    let other_key = hex::encode(SigningKey::from_bytes(&[8; 32]).verifying_key().to_bytes());
+    assert!(ReleaseManifest::parse_signed(manifest.as_bytes(), &signature, &other_key).is_err());
+}
+
+#[test]
+fn installs_and_replaces_releases() {
+    let dir = TempDir::new().unwrap();
+    let first = stage(&dir, DatabaseKind::Gwas, &[GWAS_T2D]);
+    let installed = install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &first,
+        "gwas_catalog.tsv",
+        Some("v1"),
+        None,
+    )
+    .unwrap();
+    assert_eq!(installed.database.len(), 1);
+    assert!(!first.exists());
+
+    let second = stage(&dir, DatabaseKind::Gwas, &[GWAS_T2D, GWAS_HEIGHT]);
+    let sha256 = sha256_file(&second).unwrap();
+    let installed = install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &second,
+        "gwas_catalog_associations.tsv",
+        Some("v2"),
+        Some(&sha256),
+    )
+    .unwrap();
+    assert_eq!(installed.database.len(), 2);
+
+    // The release under the old name is removed so the new one is found
+    let path = find_installed(dir.path(), DatabaseKind::Gwas).unwrap();
+    assert_eq!(path, dir.path().join("gwas_catalog_associations.tsv"));
+    assert!(!dir.path().join("gwas_catalog.tsv").exists());
+
+    let record = InstalledReleases::read(dir.path()).unwrap();
+    let record = record.get(DatabaseKind::Gwas).unwrap();
+    assert_eq!(record.version.as_deref(), Some("v2"));
+    assert_eq!(record.sha256, sha256);
+
+    let LoadedDatabase::Gwas(catalog) = LoadedDatabase::load(DatabaseKind::Gwas, &path).unwrap()
+    else {
+        panic!("expected a GWAS catalog");
+    };
+    assert_eq!(catalog.len(), 2);
+}
+
+#[test]
+fn rejected_releases_keep_the_current_one() {
+    let dir = TempDir::new().unwrap();
+    let first = stage(&dir, DatabaseKind::Gwas, &[GWAS_T2D]);
+    install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &first,
+        "gwas_catalog.tsv",
+        None,
+        None,
+    )
+    .unwrap();
+
+    let corrupt = stage(&dir, DatabaseKind::Gwas, &[GWAS_T2D, GWAS_HEIGHT]);
+    let err = install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &corrupt,
+        "gwas_catalog.tsv",
+        None,
+        Some(EMPTY_SHA256),
+    )
+    .unwrap_err();
+    assert!(err.contains("Checksum mismatch"));
+    assert!(!corrupt.exists());
+
+    let invalid = staging_path(dir.path(), DatabaseKind::Gwas).unwrap();
+    std::fs::write(&invalid, "not a catalog\n").unwrap();
+    assert!(install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &invalid,
+        "gwas_catalog.tsv",
+        None,
+        None
+    )
+    .is_err());
+
+    let path = find_installed(dir.path(), DatabaseKind::Gwas).unwrap();
+    let catalog = std::fs::read_to_string(path).unwrap();
+    assert_eq!(catalog, format!("{}{}", GWAS_HEADER, GWAS_T2D));
+}
+
+#[test]
+fn installs_pharmgkb_from_zip_archive() {
+    let dir = TempDir::new().unwrap();
+    let archive = staging_path(dir.path(), DatabaseKind::PharmGkb).unwrap();
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive).unwrap());
+    let options = zip::write::SimpleFileOptions::default();
+    for (name, contents) in [
+        (ANNOTATIONS_FILE, ANNOTATIONS),
+        (ALLELES_FILE, ALLELES),
+        ("README.pdf", ""),
+    ] {
+        zip.start_file(format!("clinicalAnnotations/{}", name), options)
+            .unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let installed = install_release(
+        dir.path(),
+        DatabaseKind::PharmGkb,
+        &archive,
+        "pharmgkb",
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(installed.database.len(), 1);
+
+    let tables = find_installed(dir.path(), DatabaseKind::PharmGkb).unwrap();
+    assert!(tables.join(ALLELES_FILE).is_file());
+    assert!(!tables.join("README.pdf").exists());
+}
+
+#[test]
+fn imports_local_files_without_touching_them() {
+    let dir = TempDir::new().unwrap();
+    let media = TempDir::new().unwrap();
+    let source = media.path().join("gwas_catalog.tsv");
+    std::fs::write(&source, format!("{}{}", GWAS_HEADER, GWAS_T2D)).unwrap();
+    assert_eq!(sidecar_checksum(&source).unwrap(), None);
+
+    let digest = sha256_file(&source).unwrap();
+    std::fs::write(
+        media.path().join("gwas_catalog.tsv.sha256"),
+        format!("{}  gwas_catalog.tsv\n", digest),
+    )
+    .unwrap();
+    let expected = sidecar_checksum(&source).unwrap().unwrap();
+    assert_eq!(expected, digest);
+
+    let file_name = DatabaseKind::Gwas.file_name_for(&source);
+    assert_eq!(file_name, "gwas_catalog.tsv");
+    let renamed = std::path::Path::new("clinvar_20240101.vcf.gz");
+    assert_eq!(
+        DatabaseKind::ClinVar.file_name_for(renamed),
+        "clinvar.vcf.gz"
+    );
+
+    let staged = stage_local(dir.path(), DatabaseKind::Gwas, &source).unwrap();
+    install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &staged,
+        "gwas_catalog.tsv",
+        None,
+        Some(&expected),
+    )
+    .unwrap();
+    assert!(source.is_file());
+
+    // Only staged copies are ever consumed
+    let err = install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &source,
+        "gwas_catalog.tsv",
+        None,
+        None,
+    )
+    .unwrap_err();
+    assert!(err.contains("not staged"));
+    assert!(source.is_file());
+}
+
+#[test]
+fn flags_releases_damaged_on_disk() {
+    let dir = TempDir::new().unwrap();
+    let staged = stage(&dir, DatabaseKind::Gwas, &[GWAS_T2D]);
+    let sha256 = sha256_file(&staged).unwrap();
+    install_release(
+        dir.path(),
+        DatabaseKind::Gwas,
+        &staged,
+        "gwas_catalog.tsv",
+        Some("2024-01"),
+        None,
+    )
+    .unwrap();
+    let media = TempDir::new().unwrap();
+    std::fs::write(media.path().join(ANNOTATIONS_FILE), ANNOTATIONS).unwrap();
+    std::fs::write(media.path().join(ALLELES_FILE), ALLELES).unwrap();
+    let staged = stage_local(dir.path(), DatabaseKind::PharmGkb, media.path()).unwrap();
+    install_release(
+        dir.path(),
+        DatabaseKind::PharmGkb,
+        &staged,
+        "pharmgkb",
+        None,
+        None,
+    )
+    .unwrap();
+
+    let checks = verify_installed(dir.path(), None).unwrap();
+    let statuses: Vec<(DatabaseKind, IntegrityStatus)> = checks
+        .iter()
+        .map(|(kind, check)| (*kind, check.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            (DatabaseKind::PharmGkb, IntegrityStatus::Intact),
+            (DatabaseKind::Gwas, IntegrityStatus::Intact),
+        ]
+    );
+    assert!(!checks[1].1.against_manifest);
+
+    // A table of a directory release is checked on its own
+    let tables = find_installed(dir.path(), DatabaseKind::PharmGkb).unwrap();
+    std::fs::write(tables.join(ALLELES_FILE), "Clinical Annotation ID\n").unwrap();
+    // The signed manifest is believed over a record changed with the file
+    let gwas = dir.path().join("gwas_catalog.tsv");
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&gwas)
+        .unwrap()
+        .write_all(GWAS_HEIGHT.as_bytes())
+        .unwrap();
+    let record = std::fs::read_to_string(dir.path().join("installed.json")).unwrap();
+    let forged = record.replace(&sha256, &sha256_file(&gwas).unwrap());
+    std::fs::write(dir.path().join("installed.json"), forged).unwrap();
+    let manifest = ReleaseManifest {
+        releases: vec![Release {
+            database: DatabaseKind::Gwas,
+            version: "2024-01".to_string(),
+            url: "https://example.org/gwas.tsv".to_string(),
+            file_name: "gwas_catalog.tsv".to_string(),
+            sha256: sha256.clone(),
+            size: None,
+        }],
+        references: Vec::new(),
+    };
+    let checks = verify_installed(dir.path(), Some(&manifest)).unwrap();
+    assert!(checks
+        .iter()
+        .all(|(_, check)| check.status == IntegrityStatus::Corrupt));
+    assert!(checks[0].1.problem.as_ref().unwrap().contains(ALLELES_FILE));
+    assert!(checks[1].1.against_manifest);
+    assert!(checks[1].1.problem.as_ref().unwrap().contains(&sha256));
+
+    std::fs::remove_file(&gwas).unwrap();
+    verify_installed(dir.path(), Some(&manifest)).unwrap();
+    let installed = InstalledReleases::read(dir.path()).unwrap();
+    let integrity = installed
+        .get(DatabaseKind::Gwas)
+        .and_then(|record| record.integrity.as_ref())
+        .unwrap();
+    assert_eq!(integrity.status, IntegrityStatus::Missing);
+    assert!(integrity.is_damaged());
+}
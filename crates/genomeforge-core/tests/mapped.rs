@@ -0,0 +1,113 @@
+//! Memory-mapped release tests: lookups and subsets give what the
+//! in-memory databases do
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::dbsnp::DbSnpIndex;
+use genomeforge_core::annotation::gnomad::{GnomadDatabase, Population};
+use genomeforge_core::annotation::manager::{DatabaseKind, LoadedDatabase};
+use genomeforge_core::annotation::mapped::{MappedDatabase, MappedRecord};
+use genomeforge_core::GenomeBuild;
+use tempfile::TempDir;
+
+const GNOMAD_VCF: &str = "##fileformat=VCFv4.2\n\
+##fileDate=20240301\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+chr1\t11796321\trs1801133\tG\tA\t.\tPASS\tAF=0.31;AF_afr=0.11;AF_eas=0.35;AF_nfe=0.34\n\
+chr6\t26092913\trs1800562\tG\tA\t.\tPASS\tAF=0.02;AF_nfe=0.057\n\
+chr17\t43045712\trs80357906\tG\tA,T\t.\tPASS\tAF=0.00001,0.002\n\
+chrUn_KI270742v1\t100\trs1\tA\tG\t.\tPASS\tAF=0.5\n";
+
+const DBSNP_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh37.p13\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+NC_000016.9\t31107689\trs9923231\tC\tT\t.\t.\tRS=9923231\n\
+NC_000001.10\t11856378\trs1801133\tG\tA\t.\t.\tRS=1801133\n\
+NC_000023.10\t153764217\trs5030868\tG\tA,C\t.\t.\tRS=5030868\n";
+
+const ARRAY: &str = "# reference human assembly build 37 (GRCh37.p13)\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs9923231\t16\t31107689\tCT\n\
+rs1801133\t1\t11856378\tTT\n";
+
+fn mapped(dir: &TempDir, name: &str, database: &LoadedDatabase) -> MappedDatabase {
+    let path = dir.path().join(name);
+    match database {
+        LoadedDatabase::Gnomad(db) => MappedDatabase::write_gnomad(&path, db).unwrap(),
+        LoadedDatabase::DbSnp(db) => MappedDatabase::write_dbsnp(&path, db).unwrap(),
+        _ => unreachable!(),
+    }
+    MappedDatabase::open(&path).unwrap()
+}
+
+#[test]
+fn mapped_gnomad_lookups_match_the_release() {
+    let dir = TempDir::new().unwrap();
+    let release = GnomadDatabase::from_vcf(GNOMAD_VCF.as_bytes()).unwrap();
+    assert_eq!(release.len(), 5);
+    let db = mapped(&dir, "gnomad.gfma", &LoadedDatabase::Gnomad(release));
+
+    // The site on an unplaced contig is left out
+    assert_eq!(db.len(), 4);
+    assert_eq!(db.kind(), DatabaseKind::Gnomad);
+    assert_eq!(db.genome_build(), Some(GenomeBuild::GRCh38));
+    assert_eq!(db.release_date(), Some("2024-03-01"));
+
+    let brca1 = db.lookup_position("17", 43045712);
+    assert_eq!(brca1.len(), 2);
+    let MappedRecord::Gnomad(mthfr) = &db.lookup_rsid("rs1801133")[0] else {
+        panic!("not a gnomAD record");
+    };
+    assert_eq!(mthfr.position, 11796321);
+    assert!((mthfr.frequencies.global - 0.31).abs() < 1e-6);
+    let eas = mthfr.frequencies.population(Population::Eas).unwrap();
+    assert!((eas - 0.35).abs() < 1e-6);
+    assert_eq!(mthfr.frequencies.population(Population::Fin), None);
+    assert!(db.lookup_position("2", 11796321).is_empty());
+    assert!(db.lookup_rsid("rs999").is_empty());
+
+    // A header splitting each entry in two, too short to hold the
+    // frequencies, is refused rather than read past on lookup
+    let path = dir.path().join("gnomad.gfma");
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[10..12].copy_from_slice(&10u16.to_le_bytes());
+    let corrupt = dir.path().join("corrupt.gfma");
+    std::fs::write(&corrupt, &bytes).unwrap();
+    assert!(MappedDatabase::open(&corrupt)
+        .unwrap_err()
+        .contains("corrupt"));
+}
+
+#[test]
+fn mapped_dbsnp_subset_resolves_a_genome() {
+    let dir = TempDir::new().unwrap();
+    let release = DbSnpIndex::from_vcf(DBSNP_VCF.as_bytes()).unwrap();
+    let db = mapped(&dir, "dbsnp.gfma", &LoadedDatabase::DbSnp(release));
+    assert_eq!(db.len(), 3);
+
+    let genome = load_genome("genome.txt", ARRAY);
+    let LoadedDatabase::DbSnp(subset) = db.subset(&genome, |_| Ok(())).unwrap() else {
+        panic!("not a dbSNP subset");
+    };
+    assert_eq!(subset.len(), 2);
+    assert_eq!(subset.genome_build(), Some(GenomeBuild::GRCh37));
+    let x = &subset.lookup_rsid("rs9923231")[0];
+    assert_eq!(
+        (x.reference.as_str(), x.alternates.clone()),
+        ("C", vec!["T".to_string()])
+    );
+    assert!(subset.lookup_rsid("rs5030868").is_empty());
+
+    let MappedRecord::DbSnp(multi) = &db.lookup_position("X", 153764217)[0] else {
+        panic!("not a dbSNP record");
+    };
+    assert_eq!(multi.alternates, vec!["A", "C"]);
+
+    let mut bytes = std::fs::read(dir.path().join("dbsnp.gfma")).unwrap();
+    bytes[10..12].copy_from_slice(&8u16.to_le_bytes());
+    let corrupt = dir.path().join("corrupt.gfma");
+    std::fs::write(&corrupt, &bytes).unwrap();
+    assert!(MappedDatabase::open(&corrupt).is_err());
+}
@@ -0,0 +1,67 @@
+//! Medication review tests: entered names become generic drug names, and
+//! recommendations that change therapy rank first
+
+use genomeforge_core::annotation::guidelines;
+use genomeforge_core::annotation::medications::{self, ReviewPriority};
+use genomeforge_core::annotation::pharmgkb::EvidenceLevel;
+
+#[test]
+fn entered_names_become_generic_names() {
+    assert_eq!(medications::generic_name("Plavix 75mg"), "clopidogrel");
+    assert_eq!(
+        medications::generic_name("  Simvastatin 20 mg tablet"),
+        "simvastatin"
+    );
+    assert_eq!(
+        medications::generic_name("Tylenol with Codeine #3"),
+        "codeine"
+    );
+    assert_eq!(medications::generic_name("metformin ER"), "metformin");
+
+    assert!(medications::names_drug(
+        "Citalopram, escitalopram",
+        "escitalopram"
+    ));
+    assert!(!medications::names_drug("escitalopram", "citalopram"));
+}
+
+#[test]
+fn therapy_changes_rank_above_standard_dosing() {
+    let priority = |gene: &str, phenotype: &str, drug: &str| {
+        let recommendation = guidelines::recommendations(gene, phenotype)
+            .into_iter()
+            .find(|recommendation| recommendation.drug == drug)
+            .unwrap();
+        ReviewPriority::of_recommendation(&recommendation)
+    };
+    assert_eq!(
+        priority("CYP2C19", "Poor Metabolizer", "clopidogrel"),
+        ReviewPriority::High
+    );
+    assert_eq!(
+        priority("CYP2C19", "Intermediate Metabolizer", "clopidogrel"),
+        ReviewPriority::Moderate
+    );
+    assert_eq!(
+        priority("CYP2C9", "Poor Metabolizer", "celecoxib"),
+        ReviewPriority::Moderate
+    );
+    assert_eq!(
+        priority("CYP2C19", "Normal Metabolizer", "clopidogrel"),
+        ReviewPriority::Low
+    );
+    assert_eq!(
+        priority("SLCO1B1", "Normal Function", "simvastatin"),
+        ReviewPriority::Low
+    );
+
+    assert_eq!(
+        ReviewPriority::of_evidence(EvidenceLevel::Level1A),
+        ReviewPriority::Moderate
+    );
+    assert_eq!(
+        ReviewPriority::of_evidence(EvidenceLevel::Level3),
+        ReviewPriority::Informational
+    );
+    assert!(ReviewPriority::High < ReviewPriority::Informational);
+}
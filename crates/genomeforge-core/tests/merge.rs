@@ -0,0 +1,127 @@
+//! Genome file merging tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::merge::{self, Agreement};
+
+const GENOME_23ANDME: &str = "# This data file generated by 23andMe\n\
+# build 37\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs4477212\t1\t82154\tAG\n\
+rs3094315\t1\t752566\tAA\n\
+rs3131972\t1\t752721\t--\n\
+rs12124819\t1\t776546\tAG\n\
+i3000001\t2\t11856378\tCC\n";
+
+const GENOME_ANCESTRY: &str = "#AncestryDNA raw data download\n\
+rsid\tchromosome\tposition\tallele1\tallele2\n\
+rs4477212\t1\t82154\tG\tA\n\
+rs3094315\t1\t752566\tA\tG\n\
+rs3131972\t1\t752721\tA\tG\n\
+rs12124819\t1\t776546\tA\tA\n\
+rs2000001\t2\t11856378\tC\tC\n\
+rs6681049\t1\t800007\tC\tC\n";
+
+const GENOME_SECOND_KIT: &str = "# This data file generated by 23andMe\n\
+# build 37\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs4477212\t1\t82154\tAG\n\
+rs3094315\t1\t752566\t--\n\
+rs12124819\t1\t776546\tGA\n\
+rs9999999\t3\t100\t--\n";
+
+#[test]
+fn resolves_conflicts_by_majority_then_call_rate() {
+    let first = load_genome("genome_23andme.txt", GENOME_23ANDME);
+    let ancestry = load_genome("AncestryDNA.txt", GENOME_ANCESTRY);
+    let second = load_genome("genome_kit2.txt", GENOME_SECOND_KIT);
+
+    let merged = merge::merge(&[&first, &ancestry, &second], |_| Ok(())).unwrap();
+    let genome = &merged.genome;
+    // rs3131972 is called only by AncestryDNA; rs6681049 is only there
+    assert_eq!(
+        genome
+            .get_by_rsid("rs3131972")
+            .unwrap()
+            .genotype
+            .to_string(),
+        "AG"
+    );
+    assert_eq!(
+        genome
+            .get_by_rsid("rs6681049")
+            .unwrap()
+            .genotype
+            .to_string(),
+        "CC"
+    );
+    // Two files outvote one
+    let index = |rsid: &str| {
+        genome
+            .variants()
+            .iter()
+            .position(|v| v.rsid.as_deref() == Some(rsid))
+            .unwrap()
+    };
+    let twelve = merged.provenance[index("rs12124819")];
+    assert_eq!(twelve.agreement, Agreement::Majority);
+    assert_eq!(twelve.sources(), [0, 1, 2]);
+    assert_eq!(
+        genome
+            .get_by_rsid("rs12124819")
+            .unwrap()
+            .genotype
+            .to_string(),
+        "AG"
+    );
+    // A tie goes to the file with the higher call rate, AncestryDNA
+    let tie = merged.provenance[index("rs3094315")];
+    assert_eq!((tie.agreement, tie.source), (Agreement::Tie, 1));
+    assert_eq!(
+        genome
+            .get_by_rsid("rs3094315")
+            .unwrap()
+            .genotype
+            .to_string(),
+        "AG"
+    );
+    // Sites are matched by position when the rsids differ
+    assert_eq!(
+        merged.provenance[index("i3000001")].agreement,
+        Agreement::Unanimous
+    );
+    assert!(genome.get_by_rsid("rs2000001").is_none());
+    assert_eq!(
+        merged.provenance[index("rs9999999")].agreement,
+        Agreement::NoCall
+    );
+
+    assert_eq!(merged.stats.sites, 7);
+    assert_eq!(
+        (
+            merged.stats.unanimous,
+            merged.stats.majority,
+            merged.stats.tie
+        ),
+        (2, 1, 1)
+    );
+    assert_eq!(merged.stats.unique_by_source, [0, 2, 0]);
+    assert_eq!(merged.conflicts.len(), 2);
+    assert_eq!(merged.sources[1].variant_count, 6);
+    assert_eq!(genome.file.fingerprint, None);
+}
+
+#[test]
+fn refuses_mismatched_inputs() {
+    let first = load_genome("genome_23andme.txt", GENOME_23ANDME);
+    assert!(merge::merge(&[&first], |_| Ok(())).is_err());
+
+    let build38 = load_genome(
+        "genome_38.txt",
+        &GENOME_23ANDME.replace("build 37", "build 38"),
+    );
+    let error = merge::merge(&[&first, &build38], |_| Ok(())).unwrap_err();
+    assert!(error.contains("different reference builds"));
+    assert!(merge::merge(&[&first, &first], |_| Err("Cancelled".into())).is_err());
+}
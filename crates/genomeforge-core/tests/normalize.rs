@@ -0,0 +1,110 @@
+//! Variant normalization tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::genome::Genotype;
+use genomeforge_core::normalize::{normalize_genome, IndexedFasta, ReferenceSequence};
+use genomeforge_core::LoadedGenome;
+use tempfile::TempDir;
+
+/// chr1 with a CA repeat at positions 4-9, wrapped at five bases a line
+const REFERENCE_FASTA: &str = ">chr1 test contig\nGGGCA\nCACAG\nTTTT\n>chr2\nACGT\n";
+
+fn load_vcf(records: &str) -> LoadedGenome {
+    let contents = format!(
+        "##fileformat=VCFv4.2\n\
+         ##reference=GRCh38\n\
+         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n{}",
+        records
+    );
+    load_genome("genome.vcf", &contents)
+}
+
+fn diploid(first: &str, second: &str) -> Genotype {
+    Genotype::Diploid {
+        first: first.to_string(),
+        second: second.to_string(),
+        phased: false,
+    }
+}
+
+#[test]
+fn splits_multiallelic_records_and_trims_shared_bases() {
+    let genome = load_vcf(
+        "1\t100\t.\tGATT\tGAT,GATTT\t.\tPASS\t.\tGT\t1/2\n\
+         1\t200\trs1\tACG\tATG\t.\tPASS\t.\tGT\t0/1\n\
+         1\t300\t.\tC\t<DEL>\t.\tPASS\t.\tGT\t0/1\n",
+    );
+    let (normalized, stats) = normalize_genome(&genome, None, |_| Ok(())).unwrap();
+    let variants = normalized.variants();
+    assert_eq!(variants.len(), 3);
+
+    // Each alternate gets its own record; the other alternate reads as
+    // the reference allele
+    assert_eq!(
+        (variants[0].position, variants[0].reference.as_deref()),
+        (101, Some("AT"))
+    );
+    assert_eq!(variants[0].alternates, ["A"]);
+    assert_eq!(variants[0].genotype, diploid("A", "AT"));
+    assert_eq!(
+        (variants[1].position, variants[1].reference.as_deref()),
+        (101, Some("A"))
+    );
+    assert_eq!(variants[1].alternates, ["AT"]);
+    assert_eq!(variants[1].genotype, diploid("A", "AT"));
+
+    // A padded SNV shrinks to the changed base
+    assert_eq!(
+        (variants[2].position, variants[2].reference.as_deref()),
+        (201, Some("C"))
+    );
+    assert_eq!(variants[2].genotype, diploid("C", "T"));
+    assert_eq!(variants[2].rsid.as_deref(), Some("rs1"));
+    // Symbolic alleles are set apart as structural variants
+    assert_eq!(genome.structural_variants().len(), 1);
+
+    assert_eq!(stats.records_split, 1);
+    assert_eq!(stats.records_trimmed, 3);
+    assert_eq!(stats.indels_left_aligned, 0);
+}
+
+#[test]
+fn left_aligns_indels_against_an_indexed_reference() {
+    let dir = TempDir::new().unwrap();
+    let fasta_path = dir.path().join("reference.fa");
+    std::fs::write(&fasta_path, REFERENCE_FASTA).unwrap();
+    let fasta = IndexedFasta::open(&fasta_path).unwrap();
+    assert_eq!(fasta.bases("1", 4, 10).unwrap().as_deref(), Some("CACACAG"));
+    assert_eq!(fasta.bases("chr2", 1, 4).unwrap().as_deref(), Some("ACGT"));
+    assert_eq!(fasta.bases("1", 10, 20).unwrap(), None);
+
+    // The same deletion of one CA unit, written at the right end of the
+    // repeat, plus a record whose REF disagrees with the reference
+    let genome = load_vcf(
+        "1\t7\t.\tACA\tA\t.\tPASS\t.\tGT\t0/1\n\
+         1\t11\t.\tG\tGT\t.\tPASS\t.\tGT\t0/1\n",
+    );
+    let (normalized, stats) = normalize_genome(&genome, Some(&fasta), |_| Ok(())).unwrap();
+    let deletion = &normalized.variants()[0];
+    assert_eq!(
+        (deletion.position, deletion.reference.as_deref()),
+        (3, Some("GCA"))
+    );
+    assert_eq!(deletion.alternates, ["G"]);
+    assert_eq!(deletion.genotype, diploid("GCA", "G"));
+    assert_eq!(normalized.variants()[1].position, 11);
+    assert_eq!(stats.indels_left_aligned, 1);
+    assert_eq!(stats.reference_mismatches, 1);
+
+    // A samtools index next to the FASTA is used as is
+    let mut index = fasta_path.as_os_str().to_owned();
+    index.push(".fai");
+    std::fs::write(index, "chr1\t14\t18\t5\t6\nchr2\t4\t40\t4\t5\n").unwrap();
+    let indexed = IndexedFasta::open(&fasta_path).unwrap();
+    assert_eq!(
+        indexed.bases("1", 1, 14).unwrap().as_deref(),
+        Some("GGGCACACAGTTTT")
+    );
+}
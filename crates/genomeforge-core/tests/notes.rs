@@ -0,0 +1,82 @@
+//! Finding bookmark and note tests
+
+use genomeforge_core::crypto::{Key, KeySource};
+use genomeforge_core::notes::{self, FindingRef, NoteBook};
+use tempfile::TempDir;
+
+fn finding(section: &str, key: &str) -> FindingRef {
+    FindingRef {
+        section: section.to_string(),
+        key: key.to_string(),
+    }
+}
+
+#[test]
+fn bookmarks_and_notes_findings() {
+    let mut book = NoteBook::default();
+    let brca1 = finding("clinical", "17:41197694:55407:rs80357906");
+    let entry = book
+        .bookmark(brca1.clone(), "BRCA1 rs80357906", true, 10)
+        .unwrap();
+    assert!(entry.bookmarked && entry.note.is_empty());
+
+    let entry = book
+        .set_note(brca1.clone(), "BRCA1", "  Discuss cascade testing  ", 20)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (entry.note.as_str(), entry.label.as_str(), entry.updated_at),
+        ("Discuss cascade testing", "BRCA1", 20)
+    );
+    // Unstarred, the note keeps the entry; without the note it goes
+    assert!(book.bookmark(brca1.clone(), "BRCA1", false, 30).is_some());
+    assert!(book
+        .set_note(brca1.clone(), "BRCA1", " ", 40)
+        .unwrap()
+        .is_none());
+    assert!(book.get(&brca1).is_none() && book.is_empty());
+    assert!(book.bookmark(brca1, "BRCA1", false, 50).is_none());
+
+    let long = "x".repeat(notes::MAX_NOTE_LENGTH + 1);
+    let cyp = finding("drug_response", "PA1:CYP2C19:clopidogrel");
+    assert!(book.set_note(cyp.clone(), "CYP2C19", &long, 60).is_err());
+    assert!(book.get(&cyp).is_none());
+}
+
+#[test]
+fn seals_notebooks() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(notes::FILE_NAME);
+    let key = Key::generate();
+    assert!(NoteBook::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .is_empty());
+
+    let mut book = NoteBook::default();
+    book.set_note(
+        finding("carrier", "CFTR:Cystic fibrosis"),
+        "CFTR: Cystic fibrosis",
+        "Partner should be tested",
+        1,
+    )
+    .unwrap();
+    book.bookmark(finding("trait", "rs4988235:Lactose"), "rs4988235", true, 2);
+    book.save(&path, KeySource::Device(&key)).unwrap();
+    let written = std::fs::read(&path).unwrap();
+    assert!(!written.windows(7).any(|w| w == b"Partner"));
+
+    let read = NoteBook::open(&path, KeySource::Device(&key)).unwrap();
+    assert_eq!(read.len(), 2);
+    let sections: Vec<&str> = read
+        .entries()
+        .map(|entry| entry.finding.section.as_str())
+        .collect();
+    assert_eq!(sections, ["carrier", "trait"]);
+    assert_eq!(
+        read.get(&finding("carrier", "CFTR:Cystic fibrosis"))
+            .unwrap()
+            .note,
+        "Partner should be tested"
+    );
+    assert!(NoteBook::open(&path, KeySource::Device(&Key::generate())).is_err());
+}
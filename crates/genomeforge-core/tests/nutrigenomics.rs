@@ -0,0 +1,55 @@
+//! Nutrigenomics panel tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::nutrigenomics::{self, NutrientArea, NutritionEvidence};
+
+#[test]
+fn counts_effect_alleles_and_labels_evidence() {
+    let genome = load_calls(
+        37,
+        &[
+            ("rs1801133", "1", 11856378, "AA"),
+            ("rs4988235", "2", 136608646, "AG"),
+            ("rs671", "12", 112241766, "--"),
+            ("rs9939609", "16", 53820527, "AT"),
+        ],
+    );
+    let findings = nutrigenomics::call(&genome);
+    let genes: Vec<&str> = findings.iter().map(|f| f.gene.as_str()).collect();
+    // No-calls are left out
+    assert_eq!(genes, ["MTHFR", "LCT", "FTO"]);
+
+    let mthfr = &findings[0];
+    assert_eq!((mthfr.name.as_str(), mthfr.effect_copies), ("C677T", 2));
+    assert_eq!(mthfr.area, NutrientArea::Folate);
+    assert!(mthfr.effect.contains("a third of typical"));
+    assert!(findings[1].effect.starts_with("Lactase persistence"));
+    assert_eq!(findings[1].evidence, NutritionEvidence::Established);
+    assert_eq!(findings[2].evidence, NutritionEvidence::Limited);
+}
+
+#[test]
+fn matches_by_position_and_complements_minus_strand_calls() {
+    let genome = load_calls(
+        38,
+        &[
+            ("i7000001", "12", 111803962, "GA"),
+            ("rs762551", "15", 74749576, "GG"),
+        ],
+    );
+    let findings = nutrigenomics::call(&genome);
+    assert_eq!(findings.len(), 2);
+    assert_eq!(
+        (findings[0].rsid.as_str(), findings[0].effect_copies),
+        ("rs671", 1)
+    );
+    // GG on the minus strand is CC on the plus strand
+    assert_eq!(findings[1].gene, "CYP1A2");
+    assert_eq!(findings[1].effect_copies, 2);
+
+    // Positions are read in the genome's build only
+    let other_build = load_calls(37, &[("i7000001", "12", 111803962, "GA")]);
+    assert!(nutrigenomics::call(&other_build).is_empty());
+}
@@ -0,0 +1,57 @@
+//! Condition ontology tests: ClinVar names and identifiers map to MONDO
+//! terms, which are found by the names of their body system
+
+use genomeforge_core::annotation::ontology::{self, BodySystem};
+use genomeforge_core::search::{Query, SearchField};
+
+#[test]
+fn clinvar_conditions_map_by_name_or_identifier() {
+    let hcm = ontology::map_condition("Hypertrophic cardiomyopathy 1", &[]).unwrap();
+    assert_eq!(hcm.id, "MONDO:0005045");
+    assert_eq!(hcm.system, BodySystem::Cardiovascular);
+    assert_eq!(
+        ontology::map_condition("Hereditary breast ovarian cancer syndrome", &[])
+            .unwrap()
+            .system,
+        BodySystem::Neoplasm
+    );
+    assert_eq!(
+        ontology::map_condition("MEN1", &[]).unwrap().id,
+        "MONDO:0007540"
+    );
+    assert_eq!(
+        ontology::map_condition("Cardiomyopathy, familial", &["OMIM:192600".to_string()])
+            .unwrap()
+            .id,
+        "MONDO:0005045"
+    );
+    assert!(ontology::map_condition("not specified", &["MedGen:CN169374".to_string()]).is_none());
+    assert_eq!(
+        ontology::term_by_id("mondo:0009061").unwrap().name,
+        "Cystic fibrosis"
+    );
+
+    let ids: std::collections::HashSet<_> = ontology::TERMS.iter().map(|term| term.id).collect();
+    assert_eq!(ids.len(), ontology::TERMS.len());
+}
+
+#[test]
+fn body_system_names_find_their_conditions() {
+    let query = Query::new("heart disease");
+    let found: Vec<_> = ontology::TERMS
+        .iter()
+        .filter(|term| {
+            let fields: Vec<_> = ontology::search_names(term)
+                .map(|name| (SearchField::Condition, name))
+                .collect();
+            query.score(&fields).is_some()
+        })
+        .map(|term| term.name)
+        .collect();
+    assert!(found.contains(&"Hypertrophic cardiomyopathy"));
+    assert!(found.contains(&"Long QT syndrome"));
+    assert!(!found.contains(&"Cystic fibrosis"));
+    assert!(found.iter().all(
+        |name| ontology::map_condition(name, &[]).unwrap().system == BodySystem::Cardiovascular
+    ));
+}
@@ -0,0 +1,196 @@
+//! Offline database pack tests
+
+use ed25519_dalek::{Signer, SigningKey};
+use genomeforge_core::annotation::manager::{install_release, sha256_file, DatabaseKind};
+use genomeforge_core::annotation::pack;
+use genomeforge_core::genome::GenomeBuild;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const GWAS: &str = "DATE ADDED TO CATALOG\tPUBMEDID\tSTUDY\tDISEASE/TRAIT\tCHR_ID\tCHR_POS\tREPORTED GENE(S)\tMAPPED_GENE\tSTRONGEST SNP-RISK ALLELE\tRISK ALLELE FREQUENCY\tP-VALUE\tOR or BETA\t95% CI (TEXT)\n\
+2008-06-16\t17463246\tStudy A\tType 2 diabetes\t10\t112998590\tTCF7L2\tTCF7L2\trs7903146-T\t0.3\t1E-48\t1.37\t[1.31-1.43]\n";
+
+const FASTA: &str = ">MT\nGATCACAGGTCTATCACCCTATTAACCACTCACGGGAGCTCTCCATGCATTTGGTATTTT\n";
+
+struct Fixture {
+    dir: TempDir,
+    key: SigningKey,
+    gwas: PathBuf,
+    fasta: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let dir = TempDir::new().unwrap();
+        let gwas = dir.path().join("gwas.tsv");
+        std::fs::write(&gwas, GWAS).unwrap();
+        let fasta = dir.path().join("chrMT.fa");
+        std::fs::write(&fasta, FASTA).unwrap();
+        Fixture {
+            dir,
+            key: SigningKey::from_bytes(&[7; 32]),
+            gwas,
+            fasta,
+        }
+    }
+
+    fn public_key(&self) -> String {
+        hex::encode(self.key.verifying_key().to_bytes())
+    }
+
+    fn manifest(&self) -> String {
+        format!(
+            r#"{{"format":1,"version":"2024.06","releases":[{{"database":"gwas","version":"2024-05","url":"databases/gwas_catalog.tsv","file_name":"gwas_catalog.tsv","sha256":"{}","size":{}}}],"references":[{{"build":"GRCh37","version":"GRCh37.p13","url":"references/chrMT.fa","sha256":"{}","size":null}}]}}"#,
+            sha256_file(&self.gwas).unwrap(),
+            GWAS.len(),
+            sha256_file(&self.fasta).unwrap()
+        )
+    }
+
+    fn write(&self, name: &str, manifest: &str, files: &[(&str, &Path)]) -> PathBuf {
+        let signature = hex::encode(self.key.sign(manifest.as_bytes()).to_bytes());
+        let path = self.dir.path().join(name);
+        pack::write(&path, manifest.as_bytes(), &signature, files).unwrap();
+        path
+    }
+
+    fn files(&self) -> Vec<(&'static str, &Path)> {
+        vec![
+            ("databases/gwas_catalog.tsv", self.gwas.as_path()),
+            ("references/chrMT.fa", self.fasta.as_path()),
+        ]
+    }
+}
+
+#[test]
+fn extracts_and_installs_signed_packs() {
+    let fixture = Fixture::new();
+    let path = fixture.write("pack.tar", &fixture.manifest(), &fixture.files());
+    let databases = TempDir::new().unwrap();
+
+    let mut read = 0;
+    let extracted = pack::extract(&path, databases.path(), &fixture.public_key(), |bytes| {
+        read = bytes;
+        Ok(())
+    })
+    .unwrap();
+    assert!(read > 0);
+    assert_eq!(extracted.manifest.version, "2024.06");
+    let (release, staged) = &extracted.releases[0];
+    assert_eq!(release.database, DatabaseKind::Gwas);
+    let installed = install_release(
+        databases.path(),
+        release.database,
+        staged,
+        &release.file_name,
+        Some(&release.version),
+        Some(&release.sha256),
+    )
+    .unwrap();
+    assert_eq!(installed.database.len(), 1);
+    let (reference, staged) = &extracted.references[0];
+    assert_eq!(reference.build, GenomeBuild::GRCh37);
+    assert_eq!(std::fs::read_to_string(staged).unwrap(), FASTA);
+
+    // gzip-compressed packs read the same
+    let compressed = fixture.dir.path().join("pack.tar.gz");
+    let mut encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(&compressed).unwrap(),
+        flate2::Compression::fast(),
+    );
+    encoder.write_all(&std::fs::read(&path).unwrap()).unwrap();
+    encoder.finish().unwrap();
+    let extracted = pack::extract(&compressed, databases.path(), &fixture.public_key(), |_| {
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(extracted.releases.len() + extracted.references.len(), 2);
+    extracted.discard();
+    assert!(!extracted.references[0].1.exists());
+
+    // So do Zstandard-compressed ones
+    let compressed = fixture.dir.path().join("pack.tar.zst");
+    std::fs::write(&compressed, zstd_frame(&std::fs::read(&path).unwrap())).unwrap();
+    let extracted = pack::extract(&compressed, databases.path(), &fixture.public_key(), |_| {
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&extracted.references[0].1).unwrap(),
+        FASTA
+    );
+    extracted.discard();
+}
+
+#[test]
+fn rejects_packs_that_do_not_match_their_manifest() {
+    let fixture = Fixture::new();
+    let databases = TempDir::new().unwrap();
+    let extract = |path: &Path| {
+        pack::extract(path, databases.path(), &fixture.public_key(), |_| Ok(())).unwrap_err()
+    };
+    let manifest = fixture.manifest();
+
+    let other_key = SigningKey::from_bytes(&[8; 32]);
+    let signature = hex::encode(other_key.sign(manifest.as_bytes()).to_bytes());
+    let forged = fixture.dir.path().join("forged.tar");
+    pack::write(&forged, manifest.as_bytes(), &signature, &fixture.files()).unwrap();
+    assert!(extract(&forged).starts_with("Signature"));
+
+    let files = fixture.files();
+    let missing = fixture.write("missing.tar", &manifest, &files[..1]);
+    assert!(extract(&missing).contains("references/chrMT.fa"));
+
+    // Counting the entries is not enough when one of them is repeated
+    let repeated = vec![files[0], files[0]];
+    let repeated = fixture.write("repeated.tar", &manifest, &repeated);
+    assert!(extract(&repeated).contains("more than once"));
+
+    let mut unlisted = fixture.files();
+    unlisted.push(("extra.txt", fixture.gwas.as_path()));
+    let unlisted = fixture.write("unlisted.tar", &manifest, &unlisted);
+    assert!(extract(&unlisted).contains("extra.txt"));
+
+    let swapped = vec![
+        ("databases/gwas_catalog.tsv", fixture.fasta.as_path()),
+        ("references/chrMT.fa", fixture.gwas.as_path()),
+    ];
+    let swapped = fixture.write("swapped.tar", &manifest, &swapped);
+    assert!(extract(&swapped).contains("size"));
+
+    let newer = fixture.write(
+        "newer.tar",
+        &manifest.replace(r#""format":1"#, r#""format":2"#),
+        &files,
+    );
+    assert!(extract(&newer).contains("format 2"));
+
+    let zstd = fixture.dir.path().join("pack.tar.zst");
+    std::fs::write(&zstd, [0x28, 0xb5, 0x2f, 0xfd, 0, 0, 0, 0]).unwrap();
+    assert!(extract(&zstd).contains("truncated"));
+    assert!(extract(&fixture.gwas).starts_with("Not a data pack"));
+    let empty = fixture.write("empty.tar", &manifest, &[]);
+    assert!(extract(&empty).contains("databases/gwas_catalog.tsv"));
+
+    // Nothing is left staged by a failed extraction
+    let staging = databases.path().join(".staging");
+    let leftovers = std::fs::read_dir(&staging).map_or(0, |entries| entries.count());
+    assert_eq!(leftovers, 0);
+}
+
+// Helper functions
+
+/// Wrap `data` in a Zstandard frame of stored blocks
+fn zstd_frame(data: &[u8]) -> Vec<u8> {
+    // No content size or checksum, and a 128 KiB window
+    let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x00, 0x38];
+    let blocks: Vec<&[u8]> = data.chunks(128 * 1024).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        let last = (index + 1 == blocks.len()) as u32;
+        let header = (block.len() as u32) << 3 | last;
+        frame.extend_from_slice(&header.to_le_bytes()[..3]);
+        frame.extend_from_slice(block);
+    }
+    frame
+}
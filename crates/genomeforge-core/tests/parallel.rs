@@ -0,0 +1,87 @@
+//! Parallel annotation tests
+
+mod common;
+
+use genomeforge_core::annotation::clinvar::{ClinVarDatabase, ClinVarMatch};
+use genomeforge_core::parallel;
+use genomeforge_core::LoadedGenome;
+
+const CHROMOSOMES: [&str; 3] = ["1", "2", "3"];
+const PER_CHROMOSOME: usize = 25_000;
+
+/// A 23andMe file of 75,000 variants over three chromosomes
+fn load_genome() -> LoadedGenome {
+    let mut contents = String::from("# rsid\tchromosome\tposition\tgenotype\n");
+    for (c, chromosome) in CHROMOSOMES.iter().enumerate() {
+        for i in 0..PER_CHROMOSOME {
+            let rsid = c * PER_CHROMOSOME + i + 1;
+            contents.push_str(&format!("rs{}\t{}\t{}\tAG\n", rsid, chromosome, i + 1));
+        }
+    }
+    common::load_genome("genome.txt", &contents)
+}
+
+/// A ClinVar VCF classifying every 500th variant of the genome
+fn load_clinvar() -> ClinVarDatabase {
+    let mut contents = String::from(
+        "##fileformat=VCFv4.1\n##reference=GRCh38\n#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n",
+    );
+    for (c, chromosome) in CHROMOSOMES.iter().enumerate() {
+        for i in (0..PER_CHROMOSOME).step_by(500) {
+            let rsid = c * PER_CHROMOSOME + i + 1;
+            contents.push_str(&format!(
+                "{}\t{}\t{}\tA\tG\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;RS={}\n",
+                chromosome,
+                i + 1,
+                rsid,
+                rsid
+            ));
+        }
+    }
+    common::load_database("clinvar.vcf", &contents)
+}
+
+#[test]
+fn shards_cover_every_variant_once() {
+    let genome = load_genome();
+
+    let shards = parallel::chromosome_shards(genome.variants());
+    assert_eq!(shards.len(), CHROMOSOMES.len());
+    assert_eq!(shards[0], 0..PER_CHROMOSOME);
+    assert!(shards.windows(2).all(|pair| pair[0].end == pair[1].start));
+    assert_eq!(shards.last().unwrap().end, genome.len());
+
+    let chunks = parallel::chunks(genome.len(), 4);
+    assert!(chunks.len() > 1);
+    assert_eq!(
+        chunks.iter().map(|chunk| chunk.len()).sum::<usize>(),
+        genome.len()
+    );
+    assert!(parallel::chunks(0, 4).is_empty());
+}
+
+#[test]
+fn parallel_annotation_matches_sequential() {
+    let genome = load_genome();
+    let clinvar = load_clinvar();
+
+    let key = |found: &ClinVarMatch<'_>| (found.variant.rsid.clone(), found.record.variation_id);
+    let sequential: Vec<_> = clinvar
+        .annotate(&genome, |_| Ok(()))
+        .unwrap()
+        .iter()
+        .map(key)
+        .collect();
+    let parallel: Vec<_> = clinvar
+        .annotate_parallel(&genome, 4, |_| Ok(()))
+        .unwrap()
+        .iter()
+        .map(key)
+        .collect();
+    assert_eq!(sequential.len(), CHROMOSOMES.len() * PER_CHROMOSOME / 500);
+    assert_eq!(parallel, sequential);
+
+    // A checkpoint error stops every thread
+    let cancelled = clinvar.annotate_parallel(&genome, 4, |_| Err("Cancelled".to_string()));
+    assert_eq!(cancelled.err().as_deref(), Some("Cancelled"));
+}
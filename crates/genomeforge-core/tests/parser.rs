@@ -0,0 +1,227 @@
+//! Parser tests covering format detection and streaming for every supported input
+
+use flate2::write::GzEncoder;
+use genomeforge_core::parser::compression::Compression;
+use genomeforge_core::parser::detect::FileFormat;
+use genomeforge_core::parser::progress::{ByteCounter, ParseProgress};
+use genomeforge_core::parser::{open_genome, open_genome_counted, summarize};
+use genomeforge_core::{GenomeBuild, Genotype};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Depth, summed\">\n\
+##contig=<ID=chr1,length=248956422>\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE1\n\
+chr1\t10177\trs367896724\tA\tAC\t100\tPASS\tDP=12\tGT\t0/1\n\
+chr1\t10352\t.\tT\tTA,G\t50\tPASS\tDP=8;DB\tGT\t1|2\n\
+chrX\t2781479\trs311165\tC\tT\t.\t.\t.\tGT\t./.\n";
+
+const TWENTY_THREE_AND_ME: &str =
+    "# This data file generated by 23andMe at: Mon Jan 01 00:00:00 2024\n\
+# More information on reference human assembly build 37 (a.k.a. GRCh37):\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs4477212\t1\t82154\tAA\n\
+rs3094315\t1\t752566\tAG\n\
+i713426\tX\t2700157\tA\n\
+rs3131972\t1\t752721\t--\n\
+not a data line\n";
+
+const ANCESTRY: &str = "#AncestryDNA raw data download\n\
+#Data was collected using AncestryDNA array version: V2.0\n\
+#human reference build 37.1\n\
+rsid\tchromosome\tposition\tallele1\tallele2\n\
+rs3131972\t1\t752721\tA\tG\n\
+rs12562034\t23\t768448\tG\tG\n\
+rs4040617\t26\t779322\t0\t0\n";
+
+const MYHERITAGE: &str = "# MyHeritage DNA raw data.\n\
+\"RSID\",\"CHROMOSOME\",\"POSITION\",\"RESULT\"\n\
+\"rs4477212\",\"1\",\"82154\",\"AA\"\n\
+\"rs3094315\",\"1\",\"752566\",\"--\"\n";
+
+const FTDNA: &str = "RSID,CHROMOSOME,POSITION,RESULT\n\
+rs4477212,1,82154,AA\n\
+rs6681049,XY,800007,CT\n";
+
+fn write(dir: &TempDir, name: &str, contents: &str) -> PathBuf {
+    let path = dir.path().join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn write_gzip(dir: &TempDir, name: &str, contents: &str) -> PathBuf {
+    let path = dir.path().join(name);
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(contents.as_bytes()).unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+    path
+}
+
+#[test]
+fn parses_vcf_with_multiallelic_sites_and_no_calls() {
+    let dir = TempDir::new().unwrap();
+    let mut source = open_genome(&write(&dir, "sample.vcf", VCF)).unwrap();
+    let file = source.genome_file().clone();
+    let summary = summarize(source.as_mut()).unwrap();
+
+    assert_eq!(file.format, FileFormat::Vcf);
+    assert_eq!(file.format_version.as_deref(), Some("VCFv4.2"));
+    assert_eq!(file.genome_build, Some(GenomeBuild::GRCh38));
+    assert_eq!(file.samples, vec!["SAMPLE1"]);
+    assert_eq!(summary.variant_count, 3);
+    assert_eq!(summary.multiallelic_count, 1);
+    assert_eq!(summary.no_call_count, 1);
+    assert_eq!(summary.chromosome_counts[0].chromosome, "1");
+    assert_eq!(summary.chromosome_counts[1].chromosome, "X");
+}
+
+#[test]
+fn resolves_vcf_genotypes_against_alleles() {
+    let dir = TempDir::new().unwrap();
+    let source = open_genome(&write(&dir, "sample.vcf", VCF)).unwrap();
+    let variants: Vec<_> = source.map(Result::unwrap).collect();
+
+    assert_eq!(variants[0].genotype.to_string(), "A/AC");
+    assert_eq!(variants[1].genotype.to_string(), "TA|G");
+    assert!(variants[2].genotype.is_no_call());
+    assert_eq!(variants[0].chromosome, "1");
+}
+
+#[test]
+fn decompresses_gzip_vcf() {
+    let dir = TempDir::new().unwrap();
+    let mut source = open_genome(&write_gzip(&dir, "sample.vcf.gz", VCF)).unwrap();
+    assert_eq!(source.genome_file().compression, Compression::Gzip);
+    assert_eq!(summarize(source.as_mut()).unwrap().variant_count, 3);
+}
+
+#[test]
+fn rejects_vcf_record_with_bad_position() {
+    let dir = TempDir::new().unwrap();
+    let broken = VCF.replace("10177", "abc");
+    let mut source = open_genome(&write(&dir, "broken.vcf", &broken)).unwrap();
+    let err = summarize(source.as_mut()).unwrap_err();
+    assert!(err.contains("invalid position"), "{}", err);
+}
+
+#[test]
+fn parses_23andme_with_build_and_no_calls() {
+    let dir = TempDir::new().unwrap();
+    let mut source = open_genome(&write(&dir, "genome.txt", TWENTY_THREE_AND_ME)).unwrap();
+    let file = source.genome_file().clone();
+    let summary = summarize(source.as_mut()).unwrap();
+
+    assert_eq!(file.format, FileFormat::TwentyThreeAndMe);
+    assert_eq!(file.genome_build, Some(GenomeBuild::GRCh37));
+    assert_eq!(file.chip_version.as_deref(), Some("v3"));
+    assert_eq!(summary.variant_count, 4);
+    assert_eq!(summary.no_call_count, 1);
+    assert_eq!(summary.skipped_lines, 1);
+    assert_eq!(summary.chromosome_counts[0].call_rate, 2.0 / 3.0);
+}
+
+#[test]
+fn parses_ancestry_numeric_chromosomes() {
+    let dir = TempDir::new().unwrap();
+    let mut source = open_genome(&write(&dir, "ancestry.txt", ANCESTRY)).unwrap();
+    assert_eq!(source.genome_file().format, FileFormat::AncestryDna);
+    assert_eq!(source.genome_file().chip_version.as_deref(), Some("V2.0"));
+
+    let variants: Vec<_> = source.by_ref().map(Result::unwrap).collect();
+    assert_eq!(variants[1].chromosome, "X");
+    assert_eq!(variants[2].chromosome, "MT");
+    assert!(variants[2].genotype.is_no_call());
+    assert_eq!(variants[0].genotype, Genotype::from_array_call("AG"));
+}
+
+#[test]
+fn distinguishes_myheritage_from_ftdna() {
+    let dir = TempDir::new().unwrap();
+    let myheritage = open_genome(&write(&dir, "mh.csv", MYHERITAGE)).unwrap();
+    assert_eq!(myheritage.genome_file().format, FileFormat::MyHeritage);
+
+    let ftdna = open_genome(&write(&dir, "ftdna.csv", FTDNA)).unwrap();
+    assert_eq!(ftdna.genome_file().format, FileFormat::FamilyTreeDna);
+    let variants: Vec<_> = ftdna.map(Result::unwrap).collect();
+    assert_eq!(variants[1].chromosome, "X");
+}
+
+#[test]
+fn detects_compressed_raw_exports() {
+    let dir = TempDir::new().unwrap();
+    let source = open_genome(&write_gzip(&dir, "genome.txt.gz", TWENTY_THREE_AND_ME)).unwrap();
+    assert_eq!(source.genome_file().format, FileFormat::TwentyThreeAndMe);
+    assert_eq!(source.genome_file().compression, Compression::Gzip);
+}
+
+#[test]
+fn rejects_unrecognized_files() {
+    let dir = TempDir::new().unwrap();
+    let path = write(&dir, "notes.txt", "shopping list\neggs\nmilk\n");
+    assert!(open_genome(&path).is_err());
+}
+
+#[test]
+fn genotype_round_trips_through_serde() {
+    for call in ["AG", "A", "--", "A/AC", "T|G"] {
+        let genotype: Genotype = call.parse().unwrap();
+        let json = serde_json::to_string(&genotype).unwrap();
+        let back: Genotype = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, genotype);
+        assert_eq!(back.to_string(), call);
+    }
+}
+
+#[test]
+fn genotype_serde_keeps_every_call_apart() {
+    let diploid = |first: &str, second: &str, phased| Genotype::Diploid {
+        first: first.to_string(),
+        second: second.to_string(),
+        phased,
+    };
+    let calls = [
+        (Genotype::Haploid("A".to_string()), "\"A\""),
+        // Indels on male X/Y from a VCF, which once came back as diploid
+        // calls or no-calls
+        (Genotype::Haploid("AT".to_string()), "\"AT/\""),
+        (Genotype::Haploid("ATT".to_string()), "\"ATT/\""),
+        (Genotype::Haploid("-".to_string()), "\"-/\""),
+        (diploid("A", "G", false), "\"AG\""),
+        (diploid("A", "G", true), "\"A|G\""),
+        (diploid("A", "AT", false), "\"A/AT\""),
+        (diploid("ATT", "A", true), "\"ATT|A\""),
+        (diploid("-", "A", false), "\"-/A\""),
+        (diploid("a", "g", false), "\"a/g\""),
+        (Genotype::NoCall, "\"--\""),
+    ];
+    for (genotype, expected) in calls {
+        let json = serde_json::to_string(&genotype).unwrap();
+        assert_eq!(json, expected);
+        let back: Genotype = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, genotype, "{}", json);
+    }
+}
+
+#[test]
+fn counts_bytes_read_from_disk() {
+    let dir = TempDir::new().unwrap();
+    let path = write_gzip(&dir, "sample.vcf.gz", VCF);
+    let counter = ByteCounter::default();
+    let mut source = open_genome_counted(&path, &counter).unwrap();
+    summarize(source.as_mut()).unwrap();
+    assert_eq!(counter.get(), std::fs::metadata(&path).unwrap().len());
+}
+
+#[test]
+fn estimates_remaining_time_from_read_rate() {
+    let progress = ParseProgress::new(250, 1000, 10, Duration::from_secs(5));
+    assert_eq!(progress.fraction, 0.25);
+    assert_eq!(progress.eta_seconds, Some(15.0));
+
+    let starting = ParseProgress::new(1, 1000, 0, Duration::from_millis(10));
+    assert_eq!(starting.eta_seconds, None);
+}
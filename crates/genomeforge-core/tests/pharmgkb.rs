@@ -0,0 +1,96 @@
+//! PharmGKB loading and matching tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::pharmgkb::{
+    EvidenceLevel, PharmGkbDatabase, PhenotypeCategory, ALLELES_FILE, ANNOTATIONS_FILE,
+    EVIDENCE_FILE,
+};
+use tempfile::TempDir;
+
+const ANNOTATIONS: &str = "Clinical Annotation ID\tVariant/Haplotypes\tGene\tLevel of Evidence\tLevel Override\tLevel Modifiers\tScore\tPhenotype Category\tPMID Count\tEvidence Count\tDrug(s)\tPhenotype(s)\tLatest History Date (YYYY-MM-DD)\tURL\tSpecialty Population\n\
+1183614743\trs9923231\tVKORC1\t1A\t\t\t100\tDosage\t50\t60\twarfarin\t\t2021-03-24\thttps://www.pharmgkb.org/clinicalAnnotation/1183614743\t\n\
+1449309937\trs4149056\tSLCO1B1\t1A\t\t\t80\tToxicity\t30\t40\tsimvastatin\tMyopathy\t2023-06-01\thttps://www.pharmgkb.org/clinicalAnnotation/1449309937\t\n\
+1447954390\tCYP2C19*1, CYP2C19*2\tCYP2C19\t1A\t\t\t90\tMetabolism/PK;Efficacy\t20\t30\tclopidogrel\t\t2022-01-01\t\t\n\
+981419260\trs1045642\tABCB1\t3\t\t\t5\tOther\t2\t2\tdigoxin\t\t2019-01-01\t\t\n";
+
+const ALLELES: &str = "Clinical Annotation ID\tGenotype/Allele\tAnnotation Text\tAllele Function\n\
+1183614743\tCC\tPatients with the CC genotype may require a higher warfarin dose.\t\n\
+1183614743\tCT\tPatients with the CT genotype may require a lower warfarin dose.\t\n\
+1183614743\tTT\tPatients with the TT genotype may require a much lower warfarin dose.\t\n\
+1449309937\tTT\tTT carriers have a typical risk of myopathy.\t\n\
+1449309937\tCT\tCT carriers have an increased risk of myopathy.\t\n\
+1447954390\t*2\tThe *2 allele is associated with reduced function.\tNo function\n";
+
+const EVIDENCE: &str = "Clinical Annotation ID\tEvidence ID\tEvidence Type\tEvidence URL\tPMID\tSummary\tScore\n\
+1183614743\tPA166104949\tGuideline Annotation\t\t\tAnnotation of CPIC Guideline for warfarin and CYP2C9, CYP4F2, VKORC1\t\n\
+1183614743\tPA166104950\tLabel Annotation\t\t\tAnnotation of FDA Label for warfarin and CYP2C9, VKORC1\t\n\
+1449309937\tPA166105005\tGuideline Annotation\t\t\tAnnotation of DPWG Guideline for simvastatin and SLCO1B1\t\n";
+
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\n\
+rs9923231\t16\t31107689\tTC\n\
+rs4149056\t12\t21331549\tTT\n\
+rs1045642\t7\t87138645\t--\n";
+
+fn load_database(with_evidence: bool) -> PharmGkbDatabase {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join(ANNOTATIONS_FILE), ANNOTATIONS).unwrap();
+    std::fs::write(dir.path().join(ALLELES_FILE), ALLELES).unwrap();
+    if with_evidence {
+        std::fs::write(dir.path().join(EVIDENCE_FILE), EVIDENCE).unwrap();
+    }
+    PharmGkbDatabase::load_dir(dir.path()).unwrap()
+}
+
+#[test]
+fn loads_annotations_with_alleles_and_guidelines() {
+    let db = load_database(true);
+    assert_eq!(db.len(), 4);
+    assert_eq!(db.release_date(), Some("2023-06-01"));
+
+    let vkorc1 = db.lookup_rsid("rs9923231")[0];
+    assert_eq!(vkorc1.evidence_level, EvidenceLevel::Level1A);
+    assert_eq!(vkorc1.phenotype_categories, vec![PhenotypeCategory::Dosage]);
+    assert_eq!(vkorc1.alleles.len(), 3);
+    assert_eq!(vkorc1.guideline_sources, vec!["CPIC", "FDA label"]);
+
+    // Haplotype annotations are only reachable by gene
+    let cyp2c19 = db.lookup_gene("CYP2C19");
+    assert_eq!(cyp2c19.len(), 1);
+    assert_eq!(cyp2c19[0].rsid, None);
+    assert_eq!(cyp2c19[0].phenotype_categories.len(), 2);
+}
+
+#[test]
+fn evidence_table_is_optional() {
+    let db = load_database(false);
+    assert!(db.lookup_rsid("rs9923231")[0].guideline_sources.is_empty());
+}
+
+#[test]
+fn matches_genotypes_regardless_of_allele_order() {
+    let db = load_database(true);
+    let genome = load_genome("genome.txt", GENOME);
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+
+    // rs1045642 is a no-call and has no allele rows
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].allele.genotype, "CT");
+    assert_eq!(matches[0].variant.genotype.to_string(), "TC");
+    assert_eq!(matches[1].annotation.drugs, vec!["simvastatin"]);
+    assert!(matches[1].allele.text.contains("typical risk"));
+}
+
+#[test]
+fn rejects_tables_without_required_columns() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join(ANNOTATIONS_FILE), "ID\tGene\n1\tCYP2D6\n").unwrap();
+    std::fs::write(dir.path().join(ALLELES_FILE), ALLELES).unwrap();
+    let err = PharmGkbDatabase::load_dir(dir.path()).unwrap_err();
+    assert!(
+        err.contains("Not a PharmGKB clinical annotation table"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,145 @@
+//! PLINK binary fileset tests
+
+use genomeforge_core::parser::detect::{self, FileFormat};
+use genomeforge_core::parser::plink::BED_MAGIC;
+use genomeforge_core::parser::progress::ByteCounter;
+use genomeforge_core::parser::{self, open_sample_counted};
+use genomeforge_core::{open_genome, LoadedGenome};
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+/// Six samples, so the fifth and sixth sit in each variant's second byte
+const FAM: &str = "fam1 mother 0 0 2 -9\n\
+fam1 father 0 0 1 -9\n\
+fam1 child father mother 1 -9\n\
+fam2 s4 0 0 0 -9\n\
+fam2 s5 0 0 2 -9\n\
+fam2 s6 0 0 1 -9\n";
+
+const BIM: &str = "1\trs3094315\t0\t752566\tG\tA\n\
+1\trs12124819\t0\t776546\tA\tG\n\
+23\trs5939319\t0\t2700027\tG\tA\n\
+0\trs0000001\t0\t0\tA\tG\n\
+26\trs2853515\t0\t10238\t0\tT\n";
+
+/// Two-bit codes per variant, one per sample in `.fam` order:
+/// 0 homozygous first allele, 1 missing, 2 heterozygous, 3 homozygous second
+const CODES: [[u8; 6]; 5] = [
+    [0, 2, 3, 1, 0, 2],
+    [3, 3, 3, 0, 2, 1],
+    [2, 0, 0, 0, 0, 3],
+    [0, 0, 0, 0, 0, 0],
+    [3, 3, 0, 3, 3, 3],
+];
+
+fn bed(codes: &[[u8; 6]]) -> Vec<u8> {
+    let mut bytes = BED_MAGIC.to_vec();
+    for variant in codes {
+        for chunk in variant.chunks(4) {
+            let byte = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (i, code)| byte | code << (i * 2));
+            bytes.push(byte);
+        }
+    }
+    bytes
+}
+
+fn write_fileset(dir: &Path, bed_bytes: &[u8]) -> PathBuf {
+    let stem = dir.join("cohort");
+    std::fs::write(stem.with_extension("bed"), bed_bytes).unwrap();
+    std::fs::write(stem.with_extension("bim"), BIM).unwrap();
+    std::fs::write(stem.with_extension("fam"), FAM).unwrap();
+    stem.with_extension("bed")
+}
+
+fn genotype(genome: &LoadedGenome, rsid: &str) -> String {
+    genome.get_by_rsid(rsid).unwrap().genotype.to_string()
+}
+
+#[test]
+fn reads_the_chosen_sample_of_a_fileset() {
+    let dir = TempDir::new().unwrap();
+    let bed_path = write_fileset(dir.path(), &bed(&CODES));
+    let bim_path = bed_path.with_extension("bim");
+
+    // Any of the three files names the fileset
+    assert_eq!(
+        detect::detect_format(&bim_path).unwrap().format,
+        FileFormat::Plink
+    );
+    assert_eq!(
+        parser::list_samples(&bed_path).unwrap(),
+        ["mother", "father", "child", "s4", "s5", "s6"]
+    );
+
+    let mut source = open_genome(&bim_path).unwrap();
+    let first = LoadedGenome::load(source.as_mut()).unwrap();
+    assert_eq!(first.file.format, FileFormat::Plink);
+    assert_eq!(first.file.samples.len(), 6);
+    assert_eq!(genotype(&first, "rs3094315"), "GG");
+    assert_eq!(genotype(&first, "rs12124819"), "GG");
+    assert_eq!(genotype(&first, "rs5939319"), "GA");
+    // Unplaced variants are skipped; chromosome codes become names
+    assert_eq!(first.len(), 4);
+    assert_eq!(first.summary.skipped_lines, 1);
+    assert_eq!(first.get_by_rsid("rs5939319").unwrap().chromosome, "X");
+    assert_eq!(first.get_by_rsid("rs2853515").unwrap().chromosome, "MT");
+
+    let mut source = open_sample_counted(&bed_path, Some("s5"), &ByteCounter::default()).unwrap();
+    let fifth = LoadedGenome::load(source.as_mut()).unwrap();
+    assert_eq!(genotype(&fifth, "rs3094315"), "GG");
+    assert_eq!(genotype(&fifth, "rs12124819"), "AG");
+    assert_eq!(genotype(&fifth, "rs2853515"), "TT");
+
+    let mut source = open_sample_counted(&bed_path, Some("s6"), &ByteCounter::default()).unwrap();
+    let sixth = LoadedGenome::load(source.as_mut()).unwrap();
+    assert_eq!(genotype(&sixth, "rs3094315"), "GA");
+    assert!(sixth
+        .get_by_rsid("rs12124819")
+        .unwrap()
+        .genotype
+        .is_no_call());
+    assert_eq!(genotype(&sixth, "rs5939319"), "AA");
+
+    // A call needing the allele PLINK did not see is a no-call
+    let mut source =
+        open_sample_counted(&bed_path, Some("child"), &ByteCounter::default()).unwrap();
+    let child = LoadedGenome::load(source.as_mut()).unwrap();
+    assert!(child
+        .get_by_rsid("rs2853515")
+        .unwrap()
+        .genotype
+        .is_no_call());
+}
+
+#[test]
+fn refuses_broken_filesets() {
+    let dir = TempDir::new().unwrap();
+    let bed_path = write_fileset(dir.path(), &bed(&CODES));
+    let counter = ByteCounter::default();
+
+    let error = open_sample_counted(&bed_path, Some("nobody"), &counter)
+        .err()
+        .unwrap();
+    assert!(error.contains("nobody"));
+
+    // Fewer .bed blocks than .bim lines
+    write_fileset(dir.path(), &bed(&CODES[..2]));
+    let mut source = open_genome(&bed_path).unwrap();
+    assert!(LoadedGenome::load(source.as_mut()).is_err());
+
+    // The sample-major layout of PLINK 0.99
+    let mut sample_major = bed(&CODES);
+    sample_major[2] = 0x00;
+    write_fileset(dir.path(), &sample_major);
+    let error = open_sample_counted(&bed_path, None, &counter)
+        .err()
+        .unwrap();
+    assert!(error.contains("sample-major"));
+
+    write_fileset(dir.path(), &bed(&CODES));
+    std::fs::remove_file(bed_path.with_extension("fam")).unwrap();
+    assert!(open_sample_counted(&bed_path, None, &counter).is_err());
+}
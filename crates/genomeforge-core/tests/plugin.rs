@@ -0,0 +1,252 @@
+//! WebAssembly interpreter and analysis plugin tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::clinvar::ClinicalSignificance;
+use genomeforge_core::annotation::consent::{ConsentPolicy, FindingCategory};
+use genomeforge_core::plugin::wasm::{Instance, Module, Value};
+use genomeforge_core::plugin::{self, WasmPlugin};
+use tempfile::TempDir;
+
+fn leb(mut value: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn sleb(mut value: i64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return bytes;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn name(name: &str) -> Vec<u8> {
+    [leb(name.len() as u32), name.as_bytes().to_vec()].concat()
+}
+
+/// A section holding a vector of entries
+fn section(id: u8, entries: Vec<Vec<u8>>) -> Vec<u8> {
+    let contents = [leb(entries.len() as u32), entries.concat()].concat();
+    [vec![id], leb(contents.len() as u32), contents].concat()
+}
+
+fn module(sections: Vec<Vec<u8>>) -> Vec<u8> {
+    [b"\0asm\x01\0\0\0".to_vec(), sections.concat()].concat()
+}
+
+/// A function body without locals beyond the given i32s
+fn body(i32_locals: u32, code: &[u8]) -> Vec<u8> {
+    let locals = if i32_locals == 0 {
+        vec![0]
+    } else {
+        [vec![1], leb(i32_locals), vec![0x7f]].concat()
+    };
+    let body = [locals, code.to_vec()].concat();
+    [leb(body.len() as u32), body].concat()
+}
+
+fn export(export_name: &str, kind: u8, index: u32) -> Vec<u8> {
+    [name(export_name), vec![kind], leb(index)].concat()
+}
+
+#[test]
+fn runs_modules_in_a_sandbox() {
+    let types = section(1, vec![vec![0x60, 1, 0x7f, 1, 0x7f], vec![0x60, 0, 0]]);
+    let code = section(
+        10,
+        vec![
+            // fib(n): n < 2 ? n : fib(n - 1) + fib(n - 2)
+            body(
+                0,
+                &[
+                    0x20, 0, 0x41, 2, 0x48, 0x04, 0x7f, 0x20, 0, 0x05, 0x20, 0, 0x41, 1, 0x6b,
+                    0x10, 0, 0x20, 0, 0x41, 2, 0x6b, 0x10, 0, 0x6a, 0x0b, 0x0b,
+                ],
+            ),
+            // spin(): loop forever
+            body(0, &[0x03, 0x40, 0x0c, 0, 0x0b, 0x0b]),
+            // divide(n): 100 / n
+            body(0, &[0x41, 0xe4, 0, 0x20, 0, 0x6d, 0x0b]),
+            // sum(n): n + (n - 1) + ... + 1, in a loop
+            body(
+                1,
+                &[
+                    0x02, 0x40, 0x03, 0x40, 0x20, 0, 0x45, 0x0d, 1, 0x20, 1, 0x20, 0, 0x6a, 0x21,
+                    1, 0x20, 0, 0x41, 1, 0x6b, 0x21, 0, 0x0c, 0, 0x0b, 0x0b, 0x20, 1, 0x0b,
+                ],
+            ),
+            // grow(pages): memory.grow
+            body(0, &[0x20, 0, 0x40, 0, 0x0b]),
+        ],
+    );
+    let bytes = module(vec![
+        types.clone(),
+        section(3, vec![vec![0], vec![1], vec![0], vec![0], vec![0]]),
+        section(5, vec![vec![0, 1]]),
+        section(
+            7,
+            vec![
+                export("fib", 0, 0),
+                export("spin", 0, 1),
+                export("divide", 0, 2),
+                export("sum", 0, 3),
+                export("grow", 0, 4),
+                export("memory", 2, 0),
+            ],
+        ),
+        code,
+    ]);
+    let module = Module::decode(&bytes).unwrap();
+    let mut instance = Instance::new(&module, 10_000_000).unwrap();
+    assert_eq!(
+        instance.invoke("fib", &[Value::I32(20)]).unwrap(),
+        [Value::I32(6765)]
+    );
+    assert_eq!(
+        instance.invoke("sum", &[Value::I32(100)]).unwrap(),
+        [Value::I32(5050)]
+    );
+    assert_eq!(
+        instance.invoke("divide", &[Value::I32(-7)]).unwrap(),
+        [Value::I32(-14)]
+    );
+    let error = instance.invoke("divide", &[Value::I32(0)]).unwrap_err();
+    assert!(error.contains("integer divide by zero"), "{}", error);
+    assert_eq!(
+        instance.invoke("grow", &[Value::I32(1)]).unwrap(),
+        [Value::I32(1)]
+    );
+    // Far past the memory plugins may use
+    assert_eq!(
+        instance.invoke("grow", &[Value::I32(5000)]).unwrap(),
+        [Value::I32(-1)]
+    );
+    assert!(instance.invoke("fib", &[Value::I64(1)]).is_err());
+
+    let mut limited = Instance::new(&module, 10_000).unwrap();
+    let error = limited.invoke("spin", &[]).unwrap_err();
+    assert!(error.contains("ran out of fuel"), "{}", error);
+    assert_eq!(limited.fuel(), 0);
+
+    let importing = module_with_import(&types);
+    let error = Module::decode(&importing).unwrap_err();
+    assert!(error.contains("imports env.clock"), "{}", error);
+    assert!(Module::decode(b"not wasm").is_err());
+}
+
+fn module_with_import(types: &[u8]) -> Vec<u8> {
+    let import = [name("env"), name("clock"), vec![0, 0]].concat();
+    module(vec![types.to_vec(), section(2, vec![import])])
+}
+
+/// A plugin returning fixed findings from a data segment
+fn panel_module(output: &str) -> Vec<u8> {
+    let packed = (1024i64 << 32) | output.len() as i64;
+    let analyze = [&[0x42][..], &sleb(packed), &[0x0b]].concat();
+    let data = [
+        vec![0, 0x41],
+        sleb(1024),
+        vec![0x0b],
+        leb(output.len() as u32),
+        output.as_bytes().to_vec(),
+    ]
+    .concat();
+    module(vec![
+        section(
+            1,
+            vec![
+                vec![0x60, 1, 0x7f, 1, 0x7f],
+                vec![0x60, 2, 0x7f, 0x7f, 1, 0x7e],
+            ],
+        ),
+        section(3, vec![vec![0], vec![1]]),
+        section(5, vec![vec![0, 1]]),
+        section(
+            7,
+            vec![
+                export("memory", 2, 0),
+                export("alloc", 0, 0),
+                export("analyze", 0, 1),
+            ],
+        ),
+        section(
+            10,
+            vec![
+                body(0, &[[0x41].as_slice(), &sleb(4096), &[0x0b]].concat()),
+                body(0, &analyze),
+            ],
+        ),
+        section(11, vec![data]),
+    ])
+}
+
+#[test]
+fn runs_plugins_with_the_consent_policy_applied() {
+    let output = r#"{"findings": [
+        {"title": "Raised homocysteine", "genes": ["MTHFR"], "rsid": "rs1801133",
+         "significance": "risk_factor", "evidence": 0.8, "references": ["PMID:9545397"]},
+        {"title": "Alzheimer's disease risk", "genes": ["APOE"], "rsid": "rs429358",
+         "significance": "risk_factor", "evidence": 0.9},
+        {"title": "Carrier of a recessive condition", "genes": ["CFTR"],
+         "significance": "pathogenic", "categories": ["carrier_status"], "evidence": 1.0}
+    ]}"#;
+    let dir = TempDir::new().unwrap();
+    let plugin_dir = dir.path().join("panel");
+    std::fs::create_dir(&plugin_dir).unwrap();
+    std::fs::write(
+        plugin_dir.join(plugin::MANIFEST_FILE),
+        r#"{"id": "test-panel", "name": "Test panel", "version": "1.0.0",
+            "api_version": 1, "sites": ["rs1801133", "rs429358"]}"#,
+    )
+    .unwrap();
+    std::fs::write(plugin_dir.join("plugin.wasm"), panel_module(output)).unwrap();
+    let panel = WasmPlugin::load(&plugin_dir).unwrap();
+
+    let genome = load_genome(
+        "genome.txt",
+        "# This data file generated by 23andMe\n\
+         # rsid\tchromosome\tposition\tgenotype\n\
+         rs1801133\t1\t11856378\tAG\n\
+         rs429358\t19\t45411941\tTC\n",
+    );
+
+    let policy = ConsentPolicy::excluding([FindingCategory::Neurodegenerative]);
+    let findings = plugin::run(&panel, &genome, &policy).unwrap();
+    assert_eq!(findings.len(), 2);
+    let mthfr = &findings[0];
+    assert_eq!(mthfr.plugin, "test-panel");
+    assert_eq!(mthfr.genotype.as_deref(), Some("AG"));
+    assert_eq!(mthfr.significance, ClinicalSignificance::RiskFactor);
+    assert!((mthfr.confidence.score - 0.8).abs() < 1e-9);
+    assert_eq!(findings[1].genotype, None);
+
+    let policy = ConsentPolicy::excluding([FindingCategory::CarrierStatus]);
+    let findings = plugin::run(&panel, &genome, &policy).unwrap();
+    let titles: Vec<&str> = findings.iter().map(|f| f.title.as_str()).collect();
+    assert_eq!(titles, ["Raised homocysteine", "Alzheimer's disease risk"]);
+
+    // The module must stay inside the plugin's directory
+    std::fs::write(
+        plugin_dir.join(plugin::MANIFEST_FILE),
+        r#"{"id": "test-panel", "name": "Test panel", "version": "1.0.0",
+            "api_version": 1, "module": "../plugin.wasm"}"#,
+    )
+    .unwrap();
+    let error = WasmPlugin::load(&plugin_dir).unwrap_err();
+    assert!(error.contains("in its own directory"), "{}", error);
+}
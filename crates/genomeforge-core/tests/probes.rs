@@ -0,0 +1,82 @@
+//! Chip inference and unreliable probe masking tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::probes::{self, Chip, ProbeAction, ProbeMask};
+use genomeforge_core::parser::detect::FileFormat;
+use genomeforge_core::quality::{self, QualityIssue};
+
+const MASK: &str = "vendor\tversion\tprobe\taction\treason\n\
+23andme\tv5\ti4000377\texclude\tFalse BRCA1 185delAG calls\n\
+23andme\t*\trs429358\tflag\tCross-hybridizes\n\
+*\t*\trs429358\texclude\tMisplaced on some chips\n\
+ancestrydna\tv2.0\trs7412\tflag\tOverlaps a common indel\n";
+
+fn mask() -> ProbeMask {
+    ProbeMask::from_reader(MASK.as_bytes()).unwrap()
+}
+
+#[test]
+fn infers_the_chip_and_finds_the_probes_masked_on_it() {
+    let v5 = load_genome(
+        "genome.txt",
+        "# This data file generated by 23andMe\n# chip version: v5\n\
+         # rsid\tchromosome\tposition\tgenotype\nrs1\t1\t1000\tAG\n",
+    );
+    let chip = probes::infer_chip(&v5).unwrap();
+    assert_eq!(
+        chip,
+        Chip {
+            vendor: FileFormat::TwentyThreeAndMe,
+            version: Some("v5".to_string()),
+        }
+    );
+
+    let mask = mask();
+    assert_eq!(mask.len(), 4);
+    let masked = mask.lookup(&chip, "I4000377").unwrap();
+    assert_eq!(masked.action, ProbeAction::Exclude);
+    // An exclusion for every chip outweighs a flag for this vendor
+    assert_eq!(
+        mask.lookup(&chip, "rs429358").unwrap().action,
+        ProbeAction::Exclude
+    );
+    assert!(mask.lookup(&chip, "rs7412").is_none());
+    let v4 = Chip {
+        version: Some("v4".to_string()),
+        ..chip
+    };
+    assert!(mask.lookup(&v4, "i4000377").is_none());
+
+    let error =
+        ProbeMask::from_reader("vendor\tversion\tprobe\taction\nacme\t*\trs1\tflag\n".as_bytes())
+            .unwrap_err();
+    assert!(error.contains("unknown vendor acme"), "{}", error);
+}
+
+#[test]
+fn excludes_and_flags_masked_calls() {
+    let ancestry = load_genome(
+        "genome.txt",
+        "#This file was generated by AncestryDNA\n\
+         #Data was collected using AncestryDNA array version: V2.0\n\
+         rsid\tchromosome\tposition\tallele1\tallele2\n\
+         rs429358\t19\t45411941\tT\tC\n\
+         rs7412\t19\t45412079\tC\tT\n\
+         rs1\t1\t1000\tA\tG\n",
+    );
+    let chip = probes::infer_chip(&ancestry).unwrap();
+    assert_eq!(chip.to_string(), "AncestryDNA v2.0");
+
+    let (masked, stats) = mask().apply(&ancestry, &chip, |_| Ok(())).unwrap();
+    assert_eq!((stats.excluded, stats.flagged), (1, 1));
+    let variants = masked.variants();
+    assert!(variants[0].genotype.is_no_call());
+    assert_eq!(
+        quality::borderline(&variants[1]),
+        [QualityIssue::UnreliableProbe]
+    );
+    assert!(quality::borderline(&variants[2]).is_empty());
+    assert_eq!(masked.summary.no_call_count, 1);
+}
@@ -0,0 +1,120 @@
+//! Polygenic risk score tests
+
+mod common;
+
+use genomeforge_core::prs::{MissingStrategy, ReferenceDistribution, ScoringFile};
+use genomeforge_core::{GenomeBuild, LoadedGenome};
+
+const SCORING_FILE: &str = "###PGS CATALOG SCORING FILE - see https://www.pgscatalog.org\n\
+#format_version=2.0\n\
+##POLYGENIC SCORE (PGS) INFORMATION\n\
+#pgs_id=PGS000999\n\
+#pgs_name=TEST_PRS\n\
+#trait_reported=Example trait\n\
+#genome_build=GRCh37\n\
+#variants_number=6\n\
+rsID\tchr_name\tchr_position\teffect_allele\tother_allele\teffect_weight\tallelefrequency_effect\n\
+rs1\t1\t100\tA\tG\t0.5\t0.2\n\
+rs2\t1\t200\tC\tT\t-0.25\t0.5\n\
+rs3\t2\t300\tA\tT\t1.0\t0.1\n\
+rs4\t2\t400\tG\tA\t0.2\t0.4\n\
+rs5\t3\t500\tT\tC\t0.3\t0.3\n\
+rs6\t3\t600\tG\tC\t0.1\t0.5\n";
+
+// rs1 direct, rs2 at the GRCh37 position only, rs3 palindromic,
+// rs4 on the opposite strand, rs5 a no-call, rs6 not genotyped
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\n\
+rs1\t1\t100\tAG\n\
+i200\t1\t200\tCC\n\
+rs3\t2\t300\tAA\n\
+rs4\t2\t400\tCC\n\
+rs5\t3\t500\t--\n";
+
+fn load_genome(build: Option<GenomeBuild>) -> LoadedGenome {
+    let mut genome = common::load_genome("genome.txt", GENOME);
+    genome.file.genome_build = build;
+    genome
+}
+
+#[test]
+fn reads_header_metadata_and_harmonized_columns() {
+    let scoring = ScoringFile::from_reader(SCORING_FILE.as_bytes()).unwrap();
+    assert_eq!(scoring.pgs_id.as_deref(), Some("PGS000999"));
+    assert_eq!(scoring.trait_reported.as_deref(), Some("Example trait"));
+    assert_eq!(scoring.genome_build, Some(GenomeBuild::GRCh37));
+    assert_eq!(scoring.variants.len(), 6);
+    assert_eq!(scoring.variants[1].effect_weight, -0.25);
+
+    let harmonized = "#pgs_id=PGS000001\n\
+#genome_build=NR\n\
+#HmPOS_build=GRCh38\n\
+rsID\tchr_name\tchr_position\teffect_allele\teffect_weight\thm_rsID\thm_chr\thm_pos\n\
+rs9\t1\t100\tt\t0.1\trs9\t1\t150\n";
+    let scoring = ScoringFile::from_reader(harmonized.as_bytes()).unwrap();
+    assert_eq!(scoring.genome_build, Some(GenomeBuild::GRCh38));
+    assert_eq!(scoring.variants[0].position, Some(150));
+    assert_eq!(scoring.variants[0].effect_allele, "T");
+    assert!(scoring.variants[0].other_allele.is_none());
+
+    assert!(ScoringFile::from_reader("rsID\tweight\nrs1\t1\n".as_bytes()).is_err());
+}
+
+#[test]
+fn scores_with_strand_flips_and_counts_coverage() {
+    let scoring = ScoringFile::from_reader(SCORING_FILE.as_bytes()).unwrap();
+    let genome = load_genome(Some(GenomeBuild::GRCh37));
+
+    let result = scoring
+        .compute(&genome, MissingStrategy::Skip, None, |_| Ok(()))
+        .unwrap();
+    // rs1 1 x 0.5, rs2 2 x -0.25, rs3 2 x 1.0, rs4 flipped to GG 2 x 0.2
+    assert!((result.raw_score - 2.4).abs() < 1e-9);
+    let coverage = result.coverage;
+    assert_eq!(coverage.matched, 4);
+    assert_eq!(coverage.strand_flipped, 1);
+    assert_eq!(coverage.palindromic, 1);
+    assert_eq!((coverage.no_calls, coverage.not_genotyped), (1, 1));
+    assert!((coverage.fraction - 4.0 / 6.0).abs() < 1e-9);
+
+    // Mean imputation adds 2 x AF x weight for rs5 and rs6
+    let imputed = scoring
+        .compute(&genome, MissingStrategy::Mean, None, |_| Ok(()))
+        .unwrap();
+    assert!((imputed.raw_score - (2.4 + 0.18 + 0.1)).abs() < 1e-9);
+
+    // On another build only rsids match, so rs2 is missing
+    let other_build = load_genome(Some(GenomeBuild::GRCh38));
+    let result = scoring
+        .compute(&other_build, MissingStrategy::Skip, None, |_| Ok(()))
+        .unwrap();
+    assert_eq!(result.coverage.matched, 3);
+}
+
+#[test]
+fn ranks_scores_against_reference_distributions() {
+    let normal = ReferenceDistribution::Normal {
+        mean: 1.0,
+        standard_deviation: 0.5,
+    };
+    assert!((normal.percentile(1.0).unwrap() - 50.0).abs() < 1e-6);
+    assert!((normal.percentile(2.0).unwrap() - 97.725).abs() < 1e-2);
+
+    let empirical = ReferenceDistribution::Empirical {
+        scores: vec![0.0, 1.0, 2.0, 3.0],
+    };
+    assert_eq!(empirical.percentile(2.5), Some(75.0));
+    assert_eq!(empirical.percentile(1.0), Some(37.5));
+
+    // Without a reference the frequencies in the scoring file are used
+    let scoring = ScoringFile::from_reader(SCORING_FILE.as_bytes()).unwrap();
+    let genome = load_genome(Some(GenomeBuild::GRCh37));
+    let result = scoring
+        .compute(&genome, MissingStrategy::Skip, None, |_| Ok(()))
+        .unwrap();
+    let percentile = result.percentile.unwrap();
+    assert!(percentile > 50.0 && percentile < 100.0);
+    let ranked = scoring
+        .compute(&genome, MissingStrategy::Skip, Some(&empirical), |_| Ok(()))
+        .unwrap();
+    assert_eq!(ranked.percentile, Some(75.0));
+}
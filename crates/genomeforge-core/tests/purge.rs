@@ -0,0 +1,49 @@
+//! Secure deletion tests
+
+use genomeforge_core::purge::purge;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn removes_directories_and_reports_each_file() {
+    let dir = TempDir::new().unwrap();
+    let profile = dir.path().join("profiles").join("default");
+    fs::create_dir_all(profile.join("sessions")).unwrap();
+    fs::write(profile.join("sessions").join("a.gfs"), [1u8; 100]).unwrap();
+    fs::write(profile.join("fingerprints.bin"), [2u8; 20]).unwrap();
+    let cache = dir.path().join("cache.bin");
+    fs::write(&cache, [3u8; 5]).unwrap();
+
+    let missing = dir.path().join("missing");
+    let report = purge([dir.path().join("profiles").as_path(), &cache, &missing]);
+
+    assert!(report.is_complete());
+    assert_eq!(report.files.len(), 3);
+    assert_eq!(report.bytes(), 125);
+    assert!(report.files.iter().all(|file| file.overwritten));
+    assert_eq!(report.directories.len(), 3);
+    assert_eq!(
+        report.directories.last(),
+        Some(&dir.path().join("profiles"))
+    );
+    assert!(!dir.path().join("profiles").exists());
+    assert!(!cache.exists());
+}
+
+#[test]
+fn overwrites_contents_before_deleting() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("genome.txt");
+    fs::write(&path, b"rs429358\t19\t45411941\tTC\n").unwrap();
+    // A second link to the same data shows what was left on disk
+    let link = dir.path().join("link.txt");
+    fs::hard_link(&path, &link).unwrap();
+
+    let report = purge([path.as_path()]);
+
+    assert!(report.is_complete());
+    assert!(!path.exists());
+    let left = fs::read(&link).unwrap();
+    assert_eq!(left.len(), 24);
+    assert!(left.iter().all(|&b| b == 0));
+}
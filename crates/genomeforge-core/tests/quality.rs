@@ -0,0 +1,113 @@
+//! Sequencing call quality parsing and filtering tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::cache::GenomeCache;
+use genomeforge_core::crypto::Key;
+use genomeforge_core::quality::{self, QualityFilter, QualityIssue};
+use genomeforge_core::LoadedGenome;
+use tempfile::TempDir;
+
+/// Output of a variant caller: a clean call, one that failed two filters,
+/// a low-QUAL one, a low-GQ one and a shallow one covered only by the
+/// site's depth
+const CALLED_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh37\n\
+##FILTER=<ID=LowQual,Description=\"Low quality\">\n\
+##FILTER=<ID=StrandBias,Description=\"Strand bias\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+1\t100\trs1\tG\tA\t812.6\tPASS\tDP=40\tGT:GQ:DP\t0/1:99:38\n\
+1\t200\trs2\tC\tT\t45.0\tLowQual;StrandBias\t.\tGT:GQ:DP\t0/1:60:30\n\
+1\t300\trs3\tA\tG\t12.5\tPASS\t.\tGT:GQ:DP\t1/1:50:25\n\
+1\t400\trs4\tT\tC\t95.0\tPASS\t.\tGT:GQ:DP\t0/1:14:22\n\
+2\t500\trs5\tA\tC\t120.0\t.\tDP=6\tGT\t0/1\n";
+
+fn load() -> LoadedGenome {
+    load_genome("sample.vcf", CALLED_VCF)
+}
+
+#[test]
+fn reads_filter_qual_gq_and_depth() {
+    let dir = TempDir::new().unwrap();
+    let genome = load();
+    let quality = |rsid: &str| genome.get_by_rsid(rsid).unwrap().quality.clone().unwrap();
+
+    let clean = quality("rs1");
+    assert!(clean.filters.is_empty() && !clean.imputed);
+    assert_eq!(
+        (clean.site_quality, clean.genotype_quality, clean.depth),
+        (Some(812.6), Some(99.0), Some(38))
+    );
+    assert_eq!(quality("rs2").filters, ["LowQual", "StrandBias"]);
+    // Without a sample depth the site's is used
+    assert_eq!(quality("rs5").depth, Some(6));
+
+    let borderline = |rsid: &str| quality::borderline(genome.get_by_rsid(rsid).unwrap());
+    assert!(borderline("rs1").is_empty());
+    assert_eq!(borderline("rs2"), [QualityIssue::FailedFilter]);
+    assert_eq!(borderline("rs3"), [QualityIssue::LowSiteQuality]);
+    assert_eq!(borderline("rs4"), [QualityIssue::LowGenotypeQuality]);
+    assert_eq!(borderline("rs5"), [QualityIssue::LowDepth]);
+
+    // The fields survive the genome cache
+    let cache = GenomeCache::new(&dir.path().join("cache"), Key::generate());
+    let hash = "0".repeat(64);
+    cache.put(&hash, &genome).unwrap();
+    let cached = cache.get(&hash).unwrap().unwrap();
+    assert_eq!(
+        cached.get_by_rsid("rs2").unwrap().quality,
+        Some(quality("rs2"))
+    );
+    assert_eq!(
+        cached.get_by_rsid("rs5").unwrap().quality,
+        Some(quality("rs5"))
+    );
+}
+
+#[test]
+fn filters_calls_below_the_thresholds() {
+    let genome = load();
+    assert!(quality::has_quality_fields(&genome));
+
+    let filter = QualityFilter {
+        require_pass: true,
+        min_site_quality: Some(20.0),
+        min_genotype_quality: Some(20.0),
+        min_depth: Some(10),
+    };
+    assert!(filter.is_active());
+    let (filtered, stats) = quality::filter_genome(&genome, &filter, |_| Ok(())).unwrap();
+    assert_eq!(stats.assessed, 5);
+    assert_eq!(
+        (
+            stats.excluded_failed_filter,
+            stats.excluded_low_site_quality,
+            stats.excluded_low_genotype_quality,
+            stats.excluded_low_depth,
+        ),
+        (1, 1, 1, 1)
+    );
+    assert_eq!(stats.excluded(), 4);
+    assert_eq!(stats.excluded_by_filter["StrandBias"], 1);
+    assert_eq!(stats.borderline, 0);
+    let called: Vec<&str> = filtered
+        .variants()
+        .iter()
+        .filter(|variant| !variant.genotype.is_no_call())
+        .filter_map(|variant| variant.rsid.as_deref())
+        .collect();
+    assert_eq!(called, ["rs1"]);
+    assert_eq!(filtered.summary.no_call_count, 4);
+
+    // Kept by a default filter, the questionable calls count as borderline
+    let (_, stats) =
+        quality::filter_genome(&genome, &QualityFilter::default(), |_| Ok(())).unwrap();
+    assert_eq!((stats.excluded(), stats.borderline), (0, 4));
+    assert!(QualityFilter {
+        min_genotype_quality: Some(-1.0),
+        ..QualityFilter::default()
+    }
+    .validate()
+    .is_err());
+}
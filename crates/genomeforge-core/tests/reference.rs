@@ -0,0 +1,91 @@
+//! Reference FASTA management tests
+
+use flate2::write::GzEncoder;
+use genomeforge_core::annotation::manager::sha256_file;
+use genomeforge_core::genome::GenomeBuild;
+use genomeforge_core::normalize::ReferenceSequence;
+use genomeforge_core::reference::ReferenceManager;
+use std::io::Write;
+use tempfile::TempDir;
+
+const REFERENCE_FASTA: &str = ">chr1 test contig\nGGGCA\nCACAG\nTTTT\n>chrM\nACGT\n";
+
+fn gzipped(dir: &TempDir, contents: &str) -> std::path::PathBuf {
+    let path = dir.path().join("reference.fa.gz");
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(contents.as_bytes()).unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+    path
+}
+
+#[test]
+fn imports_a_compressed_fasta_and_serves_its_bases() {
+    let dir = TempDir::new().unwrap();
+    let source = gzipped(&dir, REFERENCE_FASTA);
+    let published = sha256_file(&source).unwrap();
+    let manager = ReferenceManager::new(dir.path().join("references"));
+    assert!(manager.open(GenomeBuild::GRCh38).unwrap().is_none());
+
+    let mut copied = 0;
+    let installed = manager
+        .import(
+            GenomeBuild::GRCh38,
+            &source,
+            Some("p14"),
+            Some(&published),
+            |bytes| {
+                copied = bytes;
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert_eq!(copied, REFERENCE_FASTA.len() as u64);
+    assert_eq!((installed.sequences, installed.bases), (2, 18));
+    assert_eq!(installed.source_sha256, published);
+    assert_eq!(
+        installed.sha256,
+        sha256_file(&manager.path(GenomeBuild::GRCh38)).unwrap()
+    );
+    assert_eq!(manager.installed().unwrap(), [installed]);
+
+    // The index is written next to the FASTA with the names as given
+    let index = std::fs::read_to_string(dir.path().join("references/GRCh38.fa.fai")).unwrap();
+    assert!(index.starts_with("chr1\t14\t"));
+    let fasta = manager.open(GenomeBuild::GRCh38).unwrap().unwrap();
+    assert_eq!(fasta.bases("1", 4, 10).unwrap().as_deref(), Some("CACACAG"));
+    assert_eq!(fasta.bases("MT", 1, 4).unwrap().as_deref(), Some("ACGT"));
+    drop(fasta);
+
+    assert!(manager.verify(GenomeBuild::GRCh38).unwrap());
+    std::fs::write(manager.path(GenomeBuild::GRCh38), ">chr1\nAAAA\n").unwrap();
+    assert!(!manager.verify(GenomeBuild::GRCh38).unwrap());
+    assert!(manager.remove(GenomeBuild::GRCh38).unwrap());
+    assert!(manager.installed().unwrap().is_empty());
+    assert!(!manager.path(GenomeBuild::GRCh38).exists());
+}
+
+#[test]
+fn rejects_imports_that_fail_their_checks() {
+    let dir = TempDir::new().unwrap();
+    let source = gzipped(&dir, REFERENCE_FASTA);
+    let manager = ReferenceManager::new(dir.path().join("references"));
+    let import = |source: &std::path::Path, sha256: Option<&str>| {
+        manager.import(GenomeBuild::GRCh37, source, None, sha256, |_| Ok(()))
+    };
+
+    let error = import(&source, Some(&"0".repeat(64))).unwrap_err();
+    assert!(error.contains("Checksum mismatch"), "{}", error);
+    let empty = dir.path().join("empty.fa");
+    std::fs::write(&empty, "not a fasta\n").unwrap();
+    assert!(import(&empty, None).is_err());
+    let cancelled = manager.import(GenomeBuild::GRCh37, &source, None, None, |_| {
+        Err("Cancelled".to_string())
+    });
+    assert_eq!(cancelled.unwrap_err(), "Cancelled");
+
+    // Nothing is installed and nothing is left behind
+    assert!(manager.get(GenomeBuild::GRCh37).unwrap().is_none());
+    assert!(!manager.path(GenomeBuild::GRCh37).exists());
+    let staged = std::fs::read_dir(dir.path().join("references/.staging")).unwrap();
+    assert_eq!(staged.count(), 0);
+}
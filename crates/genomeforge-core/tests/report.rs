@@ -0,0 +1,328 @@
+//! Report rendering tests
+
+use flate2::read::ZlibDecoder;
+use genomeforge_core::parser::vcf::VcfReader;
+use genomeforge_core::report::delimited::{self, Delimiter};
+use genomeforge_core::report::font::{self, TrueTypeFont};
+use genomeforge_core::report::i18n::{self, Locale, Translator};
+use genomeforge_core::report::pdf::{self, PdfOptions};
+use genomeforge_core::report::template::{self, ReportTemplate};
+use genomeforge_core::report::vcf::{self, Annotation, VcfOptions};
+use genomeforge_core::report::{html, markdown};
+use genomeforge_core::report::{Block, Fact, Report, Section, Table};
+use genomeforge_core::{GenomeBuild, Genotype, Variant};
+use std::collections::HashMap;
+use std::io::Read;
+
+fn report(rows: usize) -> Report {
+    let mut findings = Table::new(["Gene", "Variant", "Significance", "Condition"]);
+    for row in 0..rows {
+        findings.push_row([
+            "BRCA2".to_string(),
+            format!("rs{}", 80359000 + row),
+            "Pathogenic".to_string(),
+            "Hereditary breast and ovarian cancer syndrome (Müller)".to_string(),
+        ]);
+    }
+    let mut section = Section::new("Clinical findings");
+    section
+        .push(Block::Notice {
+            text: "Not a diagnostic test.".to_string(),
+        })
+        .push(Block::Table(findings));
+    Report {
+        title: "Genome Report".to_string(),
+        subtitle: Some("Confidential".to_string()),
+        details: vec![Fact::new("Variants", "612,000")],
+        sections: vec![section],
+        footer: Some("For research use only".to_string()),
+        ..Report::default()
+    }
+}
+
+/// Decompressed contents of every stream in a document
+fn streams(document: &[u8]) -> Vec<Vec<u8>> {
+    let mut found = Vec::new();
+    let mut rest = document;
+    while let Some(start) = find(rest, b"stream\n") {
+        let body = &rest[start + 7..];
+        let end = find(body, b"\nendstream").unwrap();
+        let mut inflated = Vec::new();
+        if ZlibDecoder::new(&body[..end])
+            .read_to_end(&mut inflated)
+            .is_ok()
+        {
+            found.push(inflated);
+        }
+        rest = &body[end + b"\nendstream".len()..];
+    }
+    found
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[test]
+fn lays_out_tables_across_pages_with_valid_cross_references() {
+    let document = pdf::render(&report(120), &PdfOptions::default()).unwrap();
+    assert!(document.starts_with(b"%PDF-1.7"));
+    assert!(document.ends_with(b"%%EOF\n"));
+
+    // Every cross-reference entry points at its object
+    let tail = document.len() - 40 + find(&document[document.len() - 40..], b"startxref").unwrap();
+    let startxref: usize = String::from_utf8_lossy(&document[tail..])
+        .lines()
+        .nth(1)
+        .and_then(|offset| offset.parse().ok())
+        .unwrap();
+    assert!(document[startxref..].starts_with(b"xref\n"));
+    let xref = String::from_utf8_lossy(&document[startxref..tail]);
+    for (id, entry) in xref
+        .lines()
+        .skip(3)
+        .take_while(|entry| entry.ends_with(" n "))
+        .enumerate()
+    {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(document[offset..].starts_with(format!("{} 0 obj", id + 1).as_bytes()));
+    }
+
+    let pages = streams(&document);
+    assert!(pages.len() > 2, "120 rows fill several pages");
+    let first = String::from_utf8_lossy(&pages[0]);
+    assert!(first.contains("(Genome Report)"));
+    assert!(first.contains(&format!("(Page 1 of {})", pages.len())));
+    // The header row is repeated where the table continues
+    let last = String::from_utf8_lossy(pages.last().unwrap());
+    assert!(last.contains("(Significance)"));
+    // Text is WinAnsi encoded
+    assert!(pages[0].windows(6).any(|w| w == b"M\xFCller"));
+}
+
+#[test]
+fn encrypts_with_password() {
+    let options = PdfOptions {
+        password: Some("correct horse"),
+        ..PdfOptions::default()
+    };
+    let document = pdf::render(&report(3), &options).unwrap();
+    let text = String::from_utf8_lossy(&document);
+    assert!(text.contains("/Filter /Standard /V 5 /R 6"));
+    assert!(text.contains("/Encrypt"));
+    // Contents and metadata are unreadable without the password
+    assert!(streams(&document).is_empty());
+    assert!(find(&document, b"Genome Report").is_none());
+
+    let options = PdfOptions {
+        password: Some(""),
+        ..PdfOptions::default()
+    };
+    assert!(pdf::render(&report(3), &options).is_err());
+}
+
+#[test]
+fn encodes_text_and_rejects_unsupported_fonts() {
+    assert_eq!(font::encode("é – “x” ✓\t"), b"\xE9 \x96 \x93x\x94 ? ");
+    assert_eq!(font::decode(0x80), Some('€'));
+    assert_eq!(font::decode(0x81), None);
+
+    let error = TrueTypeFont::parse(b"OTTO\0\0\0\0".to_vec()).unwrap_err();
+    assert!(error.contains("CFF"));
+    assert!(TrueTypeFont::parse(b"not a font".to_vec()).is_err());
+    assert!(TrueTypeFont::parse(vec![0, 1, 0, 0, 0]).is_err());
+}
+
+#[test]
+fn renders_self_contained_html() {
+    let mut report = report(2);
+    report.sections[0].blocks.push(Block::Paragraph {
+        text: "<script>alert(1)</script>".to_string(),
+    });
+    let page = html::render(&report);
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("default-src 'none'"));
+    assert!(!page.contains("<script>"));
+    assert!(page.contains("&lt;script&gt;"));
+    assert!(!page.contains("http://") && !page.contains("https://"));
+    assert_eq!(page.matches("<tr>").count(), 3);
+    assert!(page.contains("<td>Hereditary breast and ovarian cancer syndrome (Müller)</td>"));
+}
+
+#[test]
+fn renders_markdown_tables() {
+    let mut report = report(1);
+    report.sections[0].blocks.push(Block::Table({
+        let mut table = Table::new(["Trait", "Effect"]);
+        table.push_row(["Height | cm", "Taller\nby 1 cm"]);
+        table
+    }));
+    let text = markdown::render(&report);
+    assert!(text.starts_with("# Genome Report\n"));
+    assert!(text.contains("## Clinical findings\n"));
+    assert!(text.contains("> Not a diagnostic test."));
+    assert!(
+        text.contains("| Gene | Variant | Significance | Condition |\n| --- | --- | --- | --- |\n")
+    );
+    assert!(text.contains("| Height \\| cm | Taller<br>by 1 cm |"));
+    assert!(text.contains("- **Variants:** 612,000"));
+}
+
+#[test]
+fn writes_delimited_tables_and_archives() {
+    let mut table = Table::new(["gene", "note", "beta"]);
+    table.push_row(["BRCA2", "says \"hi\", twice", "-0.12"]);
+    table.push_row(["=HYPERLINK(\"x\")", "line\tone\nline two", "-"]);
+
+    let csv = delimited::render(&table, Delimiter::Comma);
+    assert_eq!(
+        csv,
+        "\u{FEFF}gene,note,beta\r\nBRCA2,\"says \"\"hi\"\", twice\",-0.12\r\n\"'=HYPERLINK(\"\"x\"\")\",\"line\tone\nline two\",-\r\n"
+    );
+    let tsv = delimited::render(&table, Delimiter::Tab);
+    assert!(tsv.ends_with("'=HYPERLINK(\"x\")\tline one line two\t-\r\n"));
+
+    let archive = delimited::archive(
+        &[("clinical", table.clone()), ("traits", table)],
+        Delimiter::Tab,
+    )
+    .unwrap();
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).unwrap();
+    assert_eq!(zip.len(), 2);
+    let mut contents = String::new();
+    zip.by_name("traits.tsv")
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    assert_eq!(contents, tsv);
+}
+
+#[test]
+fn writes_annotated_vcf() {
+    let variant = |rsid: &str, position: u64, reference: Option<&str>, call: &str| Variant {
+        rsid: Some(rsid.to_string()),
+        chromosome: "17".to_string(),
+        position,
+        reference: reference.map(str::to_string),
+        alternates: Vec::new(),
+        genotype: Genotype::from_array_call(call),
+        quality: None,
+    };
+    let variants = vec![
+        variant("rs80357906", 43057062, Some("G"), "GA"),
+        variant("rs1042522", 7676154, Some("G"), "GG"),
+        variant("rs1799966", 43071077, None, "AG"),
+        variant("rs8176719", 136132908, Some("T"), "DI"),
+    ];
+    let mut annotation = Annotation::default();
+    annotation
+        .add("GENE", "BRCA1")
+        .add("CLNSIG", "Pathogenic")
+        .add("CLNDN", "Breast-ovarian cancer, familial; type 1");
+    let annotations = HashMap::from([("rs80357906".to_string(), annotation)]);
+    let options = VcfOptions {
+        sample: "SAMPLE",
+        genome_build: Some(GenomeBuild::GRCh38),
+        created: 1_760_425_800,
+        source: "GenomeForge",
+        annotated_only: false,
+    };
+
+    let mut out = Vec::new();
+    let stats = vcf::write(&mut out, &variants, &annotations, &options).unwrap();
+    assert_eq!((stats.written, stats.annotated, stats.skipped), (2, 1, 2));
+    let reader = VcfReader::new(out.as_slice()).unwrap();
+    assert_eq!(reader.header().file_format, "VCFv4.3");
+    let records: Vec<_> = reader.map(Result::unwrap).collect();
+    assert_eq!(records[0].alternates, ["A"]);
+    assert_eq!(records[0].genotype(0).to_string(), "GA");
+    assert_eq!(
+        records[0].info_value("CLNDN"),
+        Some("Breast-ovarian_cancer%2C_familial%3B_type_1")
+    );
+    assert!(records[1].alternates.is_empty());
+    assert_eq!(records[1].info_value("CLNSIG"), None);
+
+    let options = VcfOptions {
+        annotated_only: true,
+        ..options
+    };
+    let stats = vcf::write(Vec::new(), &variants, &annotations, &options).unwrap();
+    assert_eq!((stats.written, stats.skipped), (1, 0));
+    let mut undeclared = Annotation::default();
+    undeclared.add("AF", "0.1");
+    let annotations = HashMap::from([("rs1042522".to_string(), undeclared)]);
+    assert!(vcf::write(Vec::new(), &variants, &annotations, &options).is_err());
+}
+
+#[test]
+fn lays_out_reports_by_template() {
+    let template = ReportTemplate::from_json(
+        r#"{
+            "id": "clinical-summary",
+            "name": "Clinical summary",
+            "title": "Summary for {{ name }}",
+            "sections": [
+                { "id": "empty" },
+                { "id": "findings", "title": "Findings", "introduction": "As of {{date}}.", "new_page": true }
+            ]
+        }"#,
+    )
+    .unwrap();
+    template.validate(&["findings", "empty"]).unwrap();
+    assert!(template.validate(&["findings"]).is_err());
+
+    let values = [("name", "J. Doe"), ("date", "2026-10-14")];
+    let laid_out = template.apply(report(1), Locale::En, &values, |id| match id {
+        "findings" => report(1).sections,
+        _ => Vec::new(),
+    });
+    assert_eq!(laid_out.title, "Summary for J. Doe");
+    assert_eq!(laid_out.sections.len(), 1);
+    let section = &laid_out.sections[0];
+    assert_eq!(section.title, "Findings");
+    assert!(section.new_page);
+    assert!(matches!(&section.blocks[0], Block::Paragraph { text } if text == "As of 2026-10-14."));
+    assert_eq!(
+        template::fill("{{unknown}} {{date", &values),
+        "{{unknown}} {{date"
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    template::save(dir.path(), &template).unwrap();
+    assert_eq!(template::load_dir(dir.path()).unwrap(), [template]);
+    let mut traversal =
+        ReportTemplate::from_json(r#"{"id": "x", "name": "x", "sections": []}"#).unwrap();
+    traversal.id = "../x".to_string();
+    assert!(template::save(dir.path(), &traversal).is_err());
+}
+
+#[test]
+fn translates_with_english_fallback() {
+    const EN: [(&str, &str); 2] = [("title", "Summary"), ("count", "{{n}} findings")];
+    const DE: [(&str, &str); 1] = [("title", "Zusammenfassung")];
+    let locale = Locale::from_tag("de-AT").unwrap();
+    let t = Translator::new(locale, &DE, &EN);
+    assert_eq!(t.text("title"), "Zusammenfassung");
+    assert_eq!(
+        t.format("count", &[("n", &t.number(612043))]),
+        "612.043 findings"
+    );
+    assert_eq!(t.text("missing"), "missing");
+    assert_eq!(i18n::missing_keys(&DE, &EN), ["count"]);
+    assert_eq!(Locale::from_tag("fr"), None);
+    assert_eq!(Locale::Es.decimal(1.5), "1,5");
+
+    let template = ReportTemplate::from_json(
+        r#"{"id": "t", "name": "T", "title": {"en": "Report", "de": "Bericht"},
+            "sections": [{"id": "findings"}]}"#,
+    )
+    .unwrap();
+    let title = |locale| template.apply(report(1), locale, &[], |_| Vec::new()).title;
+    assert_eq!(title(Locale::De), "Bericht");
+    assert_eq!(title(Locale::Es), "Report");
+
+    let mut report = report(1);
+    report.language = Some(locale.tag().to_string());
+    assert!(html::render(&report).contains("<html lang=\"de\">"));
+}
@@ -0,0 +1,205 @@
+//! Results database tests
+
+use genomeforge_core::crypto::Key;
+use genomeforge_core::results_db::{
+    self, FindingRow, NewRun, ResultQuery, ResultsDb, ResultsStore,
+};
+use tempfile::TempDir;
+
+const GENOME_A: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+const GENOME_B: &str = "bb00000000000000000000000000000000000000000000000000000000000000";
+
+fn row(section: &str, index: usize, gene: &str, significance: &str) -> FindingRow {
+    FindingRow {
+        section: section.to_string(),
+        index,
+        genes: vec![gene.to_string()],
+        significances: vec![significance.to_string()],
+        categories: vec!["cancer".to_string()],
+    }
+}
+
+fn run<'a>(hash: &'a str, at: u64, findings: Vec<FindingRow>, result: &'a [u8]) -> NewRun<'a> {
+    NewRun {
+        source_hash: hash,
+        source_name: "genome.txt",
+        analyzed_at: at,
+        clinvar_release: Some("2026-09".to_string()),
+        findings,
+        result,
+    }
+}
+
+#[test]
+fn saves_and_queries_runs() {
+    let dir = TempDir::new().unwrap();
+    let key = Key::generate();
+    let mut db = ResultsDb::open(dir.path(), key.clone()).unwrap();
+    assert!(db.runs().is_empty());
+
+    let first = db
+        .insert(run(
+            GENOME_A,
+            100,
+            vec![row("clinical", 0, "BRCA1", "pathogenic")],
+            b"{\"run\":1}",
+        ))
+        .unwrap();
+    let second = db
+        .insert(run(
+            GENOME_A,
+            200,
+            vec![
+                row("clinical", 0, "BRCA1", "likely_pathogenic"),
+                row("carrier", 0, "CFTR", "pathogenic"),
+            ],
+            b"{\"run\":2}",
+        ))
+        .unwrap();
+    let other = db
+        .insert(run(GENOME_B, 300, Vec::new(), b"{\"run\":3}"))
+        .unwrap();
+    assert_eq!(second.finding_count, 2);
+
+    // Reopened from disk, sealed so no gene shows in the index
+    let index = std::fs::read(dir.path().join(results_db::INDEX_FILE)).unwrap();
+    assert!(!index.windows(5).any(|w| w == b"BRCA1"));
+    let mut db = ResultsDb::open(dir.path(), key.clone()).unwrap();
+    let ids: Vec<u64> = db.runs().iter().map(|run| run.id).collect();
+    assert_eq!(ids, [other.id, second.id, first.id]);
+    assert_eq!(&*db.result(second.id).unwrap(), b"{\"run\":2}");
+    assert_eq!(db.previous(second.id), Some(&first));
+    assert_eq!(db.previous(first.id), None);
+    assert_eq!(db.previous(other.id), None);
+
+    let found = |query: ResultQuery| -> Vec<(u64, String)> {
+        db.query(&query)
+            .iter()
+            .map(|(run, row)| (run.id, row.genes[0].clone()))
+            .collect()
+    };
+    let brca1 = ResultQuery {
+        gene: Some("brca1".to_string()),
+        ..ResultQuery::default()
+    };
+    assert_eq!(
+        found(brca1.clone()),
+        [
+            (second.id, "BRCA1".to_string()),
+            (first.id, "BRCA1".to_string())
+        ]
+    );
+    assert_eq!(
+        found(ResultQuery {
+            significance: Some("Pathogenic".to_string()),
+            ..ResultQuery::default()
+        }),
+        [
+            (second.id, "CFTR".to_string()),
+            (first.id, "BRCA1".to_string())
+        ]
+    );
+    assert_eq!(
+        found(ResultQuery {
+            from: Some(150),
+            ..brca1.clone()
+        }),
+        [(second.id, "BRCA1".to_string())]
+    );
+    assert_eq!(
+        found(ResultQuery {
+            section: Some("carrier".to_string()),
+            to: Some(150),
+            ..ResultQuery::default()
+        }),
+        []
+    );
+    assert_eq!(
+        found(ResultQuery {
+            category: Some("CANCER".to_string()),
+            source_hash: Some(GENOME_B.to_string()),
+            ..ResultQuery::default()
+        }),
+        []
+    );
+
+    db.delete(first.id).unwrap();
+    assert!(db.result(first.id).is_err());
+    assert!(db.delete(first.id).is_err());
+    assert_eq!(db.previous(second.id), None);
+    assert!(ResultsDb::open(dir.path(), Key::generate()).is_err());
+}
+
+#[test]
+fn keeps_a_bounded_number_of_runs_per_genome() {
+    let dir = TempDir::new().unwrap();
+    let mut db = ResultsDb::open(dir.path(), Key::generate()).unwrap();
+    assert!(db.insert(run("not-a-hash", 1, Vec::new(), b"{}")).is_err());
+
+    let kept = db.insert(run(GENOME_B, 0, Vec::new(), b"{}")).unwrap();
+    for at in 0..results_db::MAX_RUNS_PER_SOURCE as u64 + 2 {
+        db.insert(run(GENOME_A, at, Vec::new(), b"{}")).unwrap();
+    }
+    let of_a = db
+        .runs()
+        .iter()
+        .filter(|run| run.source_hash == GENOME_A)
+        .count();
+    assert_eq!(of_a, results_db::MAX_RUNS_PER_SOURCE);
+    assert!(db.run(kept.id).is_some());
+    // The two oldest runs of the first genome and their files are gone
+    assert!(db.run(1).is_none() && db.run(2).is_none() && db.run(3).is_some());
+    let files = std::fs::read_dir(dir.path()).unwrap().count();
+    assert_eq!(files, results_db::MAX_RUNS_PER_SOURCE + 2);
+
+    // A directory where the new index is staged makes writing it fail,
+    // which leaves the runs listed and their files where they were
+    let blocker = dir
+        .path()
+        .join(format!("{}.partial", results_db::INDEX_FILE));
+    std::fs::create_dir(&blocker).unwrap();
+    let before: Vec<u64> = db.runs().iter().map(|run| run.id).collect();
+    assert!(db.insert(run(GENOME_A, 999, Vec::new(), b"{}")).is_err());
+    assert!(db.delete(kept.id).is_err());
+    let after: Vec<u64> = db.runs().iter().map(|run| run.id).collect();
+    assert_eq!(after, before);
+    assert!(db.result(3).is_ok() && db.result(kept.id).is_ok());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), files + 1);
+
+    std::fs::remove_dir(&blocker).unwrap();
+    let next = db.insert(run(GENOME_A, 1000, Vec::new(), b"{}")).unwrap();
+    assert_eq!(next.id, *before.iter().max().unwrap() + 1);
+    assert!(db.run(3).is_none() && db.result(4).is_ok());
+}
+
+#[test]
+fn works_behind_the_store_trait() {
+    let dir = TempDir::new().unwrap();
+    let mut store: Box<dyn ResultsStore> =
+        Box::new(ResultsDb::open(dir.path(), Key::generate()).unwrap());
+    let first = store
+        .insert(run(
+            GENOME_A,
+            100,
+            vec![row("clinical", 0, "TP53", "pathogenic")],
+            b"{}",
+        ))
+        .unwrap();
+    let second = store
+        .insert(run(GENOME_A, 200, Vec::new(), b"{\"run\":2}"))
+        .unwrap();
+
+    assert_eq!(store.runs().unwrap(), [second.clone(), first.clone()]);
+    assert_eq!(store.run(first.id).unwrap(), Some(first.clone()));
+    assert_eq!(store.previous(second.id).unwrap(), Some(first.clone()));
+    assert_eq!(&*store.result(second.id).unwrap(), b"{\"run\":2}");
+    let found = store.query(&ResultQuery::default()).unwrap();
+    assert_eq!(
+        found,
+        [(first.clone(), row("clinical", 0, "TP53", "pathogenic"))]
+    );
+
+    store.delete(first.id).unwrap();
+    assert_eq!(store.run(first.id).unwrap(), None);
+    assert!(store.query(&ResultQuery::default()).unwrap().is_empty());
+}
@@ -0,0 +1,104 @@
+//! Filter expression and rule set tests
+
+use genomeforge_core::rules::expr::Expr;
+use genomeforge_core::rules::{self, Rule, RuleSet};
+use serde_json::json;
+use tempfile::TempDir;
+
+#[test]
+fn evaluates_filter_expressions_over_findings() {
+    let finding = json!({
+        "rsid": "rs80357906",
+        "gene": "BRCA1",
+        "genes": ["BRCA1", "NBR2"],
+        "zygosity": "homozygous",
+        "significance": "pathogenic",
+        "conditions": ["Hereditary breast ovarian cancer syndrome"],
+        "af": 0.0004,
+        "imputed": false,
+        "position": null,
+        "confidence": { "score": 0.82, "level": "high" }
+    });
+    let holds = |text: &str| Expr::parse(text).unwrap().matches(&finding);
+
+    assert!(holds(
+        r#"zygosity == "homozygous" and gene in ["BRCA1", "BRCA2"] and af < 0.01"#
+    ));
+    assert!(holds("gene == 'brca1'"));
+    assert!(holds(r#"genes == "NBR2" and genes != "TP53""#));
+    assert!(holds(r#"conditions contains "ovarian""#));
+    assert!(holds("confidence.score >= 0.8 AND NOT imputed"));
+    assert!(holds(
+        r#"gene not in ["TP53"] and (af > 0.5 or confidence.level == "high")"#
+    ));
+    assert!(holds("position == null and missing == null and af"));
+    assert!(holds("af < 1e-3 and af > -1"));
+    assert!(!holds("af > 0.01"));
+    assert!(!holds("gene < 5"));
+    assert!(!holds("missing or imputed or position"));
+    assert!(holds(r#"genes == ["brca1", "NBR2"]"#));
+    assert!(!holds(r#"genes == ["BRCA1"]"#));
+
+    let expr = Expr::parse("gene == 'APOE' or confidence.score > 0.5 and gene").unwrap();
+    assert_eq!(expr.fields(), ["gene", "confidence.score"]);
+
+    for (text, error) in [
+        ("", "empty"),
+        ("gene ==", "end of expression at column 8"),
+        ("gene == 'APOE", "Unterminated text at column 9"),
+        ("(gene == 'APOE'", "Expected ')' at column 16"),
+        ("gene = 'APOE'", "Unexpected '=' at column 6"),
+        ("gene not 'APOE'", "Unexpected text at column 10"),
+        ("af < 1x", "Invalid number 1x"),
+        ("a..b", "Invalid field name"),
+    ] {
+        let message = Expr::parse(text).unwrap_err();
+        assert!(message.contains(error), "{}: {}", text, message);
+    }
+    let deep = format!("{}gene{}", "(".repeat(100), ")".repeat(100));
+    assert!(Expr::parse(&deep).unwrap_err().contains("nests deeper"));
+}
+
+#[test]
+fn saves_and_checks_rule_sets() {
+    let dir = TempDir::new().unwrap();
+    let mut set = RuleSet {
+        id: "rare-homozygous".to_string(),
+        name: "Rare homozygous".to_string(),
+        description: String::new(),
+        rules: vec![
+            Rule {
+                name: "Rare".to_string(),
+                expression: "af < 0.01".to_string(),
+            },
+            Rule {
+                name: "Homozygous".to_string(),
+                expression: "zygosity == 'homozygous'".to_string(),
+            },
+        ],
+    };
+    rules::save(dir.path(), &set).unwrap();
+    assert_eq!(rules::load_dir(dir.path()).unwrap(), [set.clone()]);
+    let compiled = set.compile().unwrap();
+    assert_eq!(compiled[1].name, "Homozygous");
+    assert!(compiled[0].expr.matches(&json!({ "af": 0.001 })));
+
+    set.rules[1].expression = "zygosity ==".to_string();
+    let error = rules::save(dir.path(), &set).unwrap_err();
+    assert!(
+        error.contains("Rule Homozygous of rare-homozygous"),
+        "{}",
+        error
+    );
+    set.rules[1].name = "Rare".to_string();
+    assert!(set.compile().unwrap_err().contains("two rules named Rare"));
+    set.id = "../escape".to_string();
+    assert!(set.compile().is_err());
+
+    rules::delete(dir.path(), "rare-homozygous").unwrap();
+    assert!(rules::load_dir(dir.path()).unwrap().is_empty());
+    assert!(rules::delete(dir.path(), "rare-homozygous").is_err());
+    assert!(rules::load_dir(&dir.path().join("missing"))
+        .unwrap()
+        .is_empty());
+}
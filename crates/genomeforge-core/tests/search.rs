@@ -0,0 +1,58 @@
+//! Finding search and pagination tests
+
+use genomeforge_core::search::{Page, Query, SearchField, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+
+#[test]
+fn ranks_exact_gene_matches_above_partial_condition_matches() {
+    let brca1 = [
+        (SearchField::Gene, "BRCA1"),
+        (SearchField::Rsid, "rs80357906"),
+        (
+            SearchField::Condition,
+            "Hereditary breast ovarian cancer syndrome",
+        ),
+    ];
+    let brca2 = [
+        (SearchField::Gene, "BRCA2"),
+        (SearchField::Condition, "Breast-ovarian cancer, familial 2"),
+    ];
+    let warfarin = [
+        (SearchField::Gene, "CYP2C9"),
+        (SearchField::Drug, "warfarin"),
+    ];
+
+    let query = Query::new("brca1");
+    let exact = query.score(&brca1).unwrap();
+    assert_eq!(exact.field, SearchField::Gene);
+    assert!(query.score(&brca2).is_none());
+    assert!(exact.score > Query::new("brca").score(&brca2).unwrap().score);
+
+    // Every word has to match, and a gene beats a condition
+    let breast = Query::new("Breast cancer");
+    assert!(breast.score(&brca1).is_some());
+    assert!(breast.score(&warfarin).is_none());
+    assert_eq!(
+        Query::new("ovarian").score(&brca1).unwrap().field,
+        SearchField::Condition
+    );
+    assert!(
+        Query::new("CYP2C").score(&warfarin).unwrap().score
+            > Query::new("farin").score(&warfarin).unwrap().score
+    );
+    assert!(Query::new("  ").score(&brca1).is_none());
+}
+
+#[test]
+fn pages_through_results() {
+    let items: Vec<usize> = (0..120).collect();
+    let first = Page::new(items.clone(), 0, None);
+    assert_eq!(first.items.len(), DEFAULT_PAGE_SIZE);
+    assert_eq!(first.total, 120);
+    assert!(first.has_more());
+
+    let last = Page::new(items.clone(), 100, Some(50));
+    assert_eq!(last.items, (100..120).collect::<Vec<_>>());
+    assert!(!last.has_more());
+    assert!(Page::new(items.clone(), 500, Some(10)).items.is_empty());
+    assert_eq!(Page::new(items, 0, Some(10_000)).limit, MAX_PAGE_SIZE);
+}
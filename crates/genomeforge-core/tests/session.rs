@@ -0,0 +1,82 @@
+//! Session file tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::crypto::{Key, KeySource, Protection};
+use genomeforge_core::session;
+use genomeforge_core::LoadedGenome;
+use tempfile::TempDir;
+
+const GENOME: &str = "# This data file generated by 23andMe\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs429358\t19\t45411941\tTC\n\
+rs7412\t19\t45412079\tCC\n";
+
+fn load() -> LoadedGenome {
+    load_genome("genome.txt", GENOME)
+}
+
+#[test]
+fn round_trips_genome_and_results_with_device_key() {
+    let dir = TempDir::new().unwrap();
+    let genome = load();
+    let key = Key::generate();
+    let path = dir.path().join("mine.gfsession");
+    let results = vec!["APOE e3/e4".to_string()];
+
+    session::write(
+        &path,
+        "Mine",
+        &genome,
+        Some(&results),
+        KeySource::Device(&key),
+    )
+    .unwrap();
+    let listed = session::list(dir.path()).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].info.name, "Mine");
+    assert_eq!(listed[0].info.variant_count, 2);
+    assert_eq!(listed[0].protection, Protection::DeviceKey);
+
+    let restored = session::read::<Vec<String>>(&path, KeySource::Device(&key)).unwrap();
+    assert_eq!(restored.results, Some(results));
+    assert_eq!(restored.genome.summary.variant_count, 2);
+    // Indexes are rebuilt on load
+    let apoe = restored.genome.get_at("19", 45411941).unwrap();
+    assert_eq!(apoe.genotype.to_string(), "TC");
+
+    let other = Key::generate();
+    assert!(session::read::<Vec<String>>(&path, KeySource::Device(&other)).is_err());
+
+    // The plaintext header is authenticated with the payload
+    let mut contents = std::fs::read(&path).unwrap();
+    let at = contents.windows(4).position(|w| w == b"Mine").unwrap();
+    contents[at + 3] = b'd';
+    std::fs::write(&path, &contents).unwrap();
+    assert_eq!(session::read_info(&path).unwrap().info.name, "Mind");
+    assert!(session::read::<Vec<String>>(&path, KeySource::Device(&key)).is_err());
+}
+
+#[test]
+fn rejects_wrong_passphrase_and_key_kind() {
+    let dir = TempDir::new().unwrap();
+    let genome = load();
+    let path = dir.path().join("locked.gfsession");
+    let entry = session::write::<()>(
+        &path,
+        "Locked",
+        &genome,
+        None,
+        KeySource::Passphrase("hunter2"),
+    )
+    .unwrap();
+    assert!(matches!(entry.protection, Protection::Passphrase { .. }));
+    assert!(!entry.info.has_results);
+
+    let error = session::read::<()>(&path, KeySource::Passphrase("hunter3")).unwrap_err();
+    assert!(error.contains("wrong passphrase"));
+    let key = Key::generate();
+    let error = session::read::<()>(&path, KeySource::Device(&key)).unwrap_err();
+    assert!(error.contains("protected with a passphrase"));
+}
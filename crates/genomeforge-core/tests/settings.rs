@@ -0,0 +1,51 @@
+//! Settings tests
+
+use genomeforge_core::annotation::manager::DatabaseKind;
+use genomeforge_core::settings::{Settings, Theme, SCHEMA_VERSION, SETTINGS_FILE};
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+#[test]
+fn saves_and_reads_back_settings() {
+    let dir = TempDir::new().unwrap();
+    assert_eq!(Settings::load(dir.path()).unwrap(), Settings::default());
+
+    let mut settings = Settings {
+        max_allele_frequency: Some(0.01),
+        theme: Theme::Dark,
+        ..Settings::default()
+    };
+    settings.consent.late_onset = true;
+    settings
+        .database_paths
+        .insert(DatabaseKind::ClinVar, PathBuf::from("clinvar.vcf.gz"));
+    settings.save(dir.path()).unwrap();
+    assert_eq!(Settings::load(dir.path()).unwrap(), settings);
+
+    settings.max_allele_frequency = Some(5.0);
+    assert!(settings.save(dir.path()).is_err());
+    settings.max_allele_frequency = None;
+    settings.watch_dir = Some(PathBuf::from("incoming"));
+    assert!(settings.validate().unwrap_err().contains("watch_dir"));
+}
+
+#[test]
+fn migrates_older_schemas_and_rejects_newer() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join(SETTINGS_FILE),
+        r#"{"theme": "light", "consent": {"secondary_findings": true}, "removed": 1}"#,
+    )
+    .unwrap();
+    let settings = Settings::load(dir.path()).unwrap();
+    assert_eq!(settings.version, SCHEMA_VERSION);
+    assert_eq!(settings.theme, Theme::Light);
+    assert!(settings.consent.secondary_findings);
+    assert!(!settings.consent.late_onset);
+
+    let newer = format!(r#"{{"version": {}}}"#, SCHEMA_VERSION + 1);
+    assert!(Settings::from_json(&newer)
+        .unwrap_err()
+        .contains("newer version"));
+    assert!(Settings::from_json("[]").is_err());
+}
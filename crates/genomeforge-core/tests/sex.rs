@@ -0,0 +1,81 @@
+//! Sex inference and sex chromosome check tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::sex::{self, Sex, SexWarning};
+use genomeforge_core::{Genotype, LoadedGenome};
+
+/// An array genome with 60 X sites, `x_heterozygous` of them heterozygous,
+/// and 20 Y sites, `y_called` of them called
+fn array(x_heterozygous: usize, y_called: usize) -> LoadedGenome {
+    let mut contents = "# build 37\n# rsid\tchromosome\tposition\tgenotype\n".to_string();
+    contents.push_str("rs1\t1\t1000\tAG\n");
+    // In the pseudoautosomal region, which is left out
+    contents.push_str("rs2\tX\t100000\tAG\n");
+    for index in 0..60 {
+        let genotype = if index < x_heterozygous { "AG" } else { "AA" };
+        let position = 10_000_000 + index * 1000;
+        contents.push_str(&format!(
+            "rs{}\tX\t{}\t{}\n",
+            1000 + index,
+            position,
+            genotype
+        ));
+    }
+    for index in 0..20 {
+        let genotype = if index < y_called { "CC" } else { "--" };
+        let position = 5_000_000 + index * 1000;
+        contents.push_str(&format!(
+            "rs{}\tY\t{}\t{}\n",
+            2000 + index,
+            position,
+            genotype
+        ));
+    }
+    load_genome("genome.txt", &contents)
+}
+
+#[test]
+fn infers_sex_from_x_heterozygosity_and_y_calls() {
+    let male = sex::check(&array(1, 19), Some(Sex::Male));
+    assert_eq!(male.inferred, Some(Sex::Male));
+    assert_eq!((male.x_sites, male.y_sites), (60, 20));
+    assert!(male.is_consistent());
+
+    let female = sex::check(&array(18, 1), None);
+    assert_eq!(female.inferred, Some(Sex::Female));
+    assert_eq!(female.x_heterozygosity, Some(0.3));
+    assert_eq!(female.y_call_rate, Some(0.05));
+    assert!(female.is_consistent());
+
+    // A declared sex the genome disagrees with
+    let swapped = sex::check(&array(18, 0), Some(Sex::Male));
+    assert_eq!(swapped.inferred, Some(Sex::Female));
+    assert_eq!(swapped.warnings, [SexWarning::DeclaredMismatch]);
+    assert!(!swapped.is_consistent());
+}
+
+#[test]
+fn flags_anomalies_and_reads_male_x_as_hemizygous() {
+    let xxy = sex::check(&array(18, 20), None);
+    assert_eq!(xxy.inferred, None);
+    assert_eq!(xxy.warnings, [SexWarning::PossibleXxy]);
+    assert_eq!(
+        sex::check(&array(0, 0), None).warnings,
+        [SexWarning::PossibleSingleX]
+    );
+    assert_eq!(
+        sex::check(&array(6, 19), None).warnings,
+        [SexWarning::Inconclusive]
+    );
+
+    let genome = array(0, 20);
+    let (hemizygous, converted) = sex::to_hemizygous(&genome);
+    assert_eq!(converted, 80);
+    let genotype = |rsid: &str| hemizygous.get_by_rsid(rsid).unwrap().genotype.clone();
+    assert_eq!(genotype("rs1000"), Genotype::Haploid("A".to_string()));
+    assert_eq!(genotype("rs2000"), Genotype::Haploid("C".to_string()));
+    assert!(matches!(genotype("rs2"), Genotype::Diploid { .. }));
+    assert!(matches!(genotype("rs1"), Genotype::Diploid { .. }));
+}
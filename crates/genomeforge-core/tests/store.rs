@@ -0,0 +1,80 @@
+//! Variant store tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::tasks::{checkpoint, CancelFlag, CANCELLED};
+use genomeforge_core::{open_genome, GenomeStore, LoadedGenome};
+use tempfile::TempDir;
+
+const GENOME: &str = "# This data file generated by 23andMe\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs429358\t19\t45411941\tTC\n\
+rs7412\t19\t45412079\tCC\n\
+rs7412\t19\t45412079\tTT\n\
+i5000001\tMT\t16519\t--\n";
+
+fn load() -> LoadedGenome {
+    load_genome("genome.txt", GENOME)
+}
+
+#[test]
+fn indexes_variants_by_rsid_and_position() {
+    let genome = load();
+    assert_eq!(genome.len(), 4);
+    assert_eq!(genome.summary.no_call_count, 1);
+
+    let apoe = genome.get_by_rsid("rs429358").unwrap();
+    assert_eq!(apoe.genotype.to_string(), "TC");
+    assert_eq!(genome.get_at("chr19", 45411941).unwrap().rsid, apoe.rsid);
+    assert!(genome.get_at("chrM", 16519).unwrap().genotype.is_no_call());
+    assert!(genome.get_by_rsid("rs1").is_none());
+}
+
+#[test]
+fn keeps_first_record_for_duplicate_sites() {
+    let genome = load();
+    assert_eq!(
+        genome.get_by_rsid("rs7412").unwrap().genotype.to_string(),
+        "CC"
+    );
+    assert_eq!(
+        genome.get_at("19", 45412079).unwrap().genotype.to_string(),
+        "CC"
+    );
+}
+
+#[test]
+fn store_replaces_and_clears_genome() {
+    let store = GenomeStore::default();
+    assert!(store.current().is_none());
+
+    let handle = store.replace(load());
+    assert!(store.is_loaded());
+    store.clear();
+    assert!(!store.is_loaded());
+    // Handles taken before clearing stay valid
+    assert_eq!(handle.len(), 4);
+}
+
+#[test]
+fn checkpoint_error_aborts_load() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("genome.txt");
+    std::fs::write(&path, GENOME).unwrap();
+    let mut source = open_genome(&path).unwrap();
+
+    let cancel = CancelFlag::default();
+    cancel.cancel();
+    let result = LoadedGenome::load_with(source.as_mut(), |_| checkpoint(&cancel));
+    assert_eq!(result.unwrap_err(), CANCELLED);
+}
+
+#[test]
+fn zeroize_empties_genome() {
+    let mut genome = load();
+    genome.zeroize();
+    assert!(genome.is_empty());
+    assert!(genome.get_by_rsid("rs429358").is_none());
+    assert!(genome.get_at("19", 45411941).is_none());
+}
@@ -0,0 +1,138 @@
+//! Strand resolution tests
+
+mod common;
+
+use common::load_calls;
+use genomeforge_core::annotation::gnomad::GnomadDatabase;
+use genomeforge_core::annotation::gwas::{self, GwasAssociation, GwasCatalog, TraitCategory};
+use genomeforge_core::annotation::strand::{self, Strand};
+use genomeforge_core::LoadedGenome;
+
+/// rs1 and rs2 are A/G SNPs, rs3 and rs4 A/T SNPs
+const GNOMAD_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh37\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+1\t1000\trs1\tG\tA\t.\tPASS\tAF=0.3\n\
+1\t2000\trs2\tG\tA\t.\tPASS\tAF=0.2\n\
+1\t3000\trs3\tA\tT\t.\tPASS\tAF=0.1\n\
+1\t4000\trs4\tA\tT\t.\tPASS\tAF=0.48\n";
+
+/// Calls at the sites of `GNOMAD_VCF`, in order
+fn array(calls: &[(&str, &str)]) -> LoadedGenome {
+    let calls: Vec<_> = calls
+        .iter()
+        .enumerate()
+        .map(|(index, &(rsid, genotype))| (rsid, "1", 1000 * (index as u64 + 1), genotype))
+        .collect();
+    load_calls(37, &calls)
+}
+
+fn association(rsid: &str, risk_allele: &str, frequency: f64) -> GwasAssociation {
+    GwasAssociation {
+        rsid: rsid.to_string(),
+        risk_allele: risk_allele.to_string(),
+        trait_name: format!("Trait of {}", rsid),
+        mapped_trait: None,
+        category: TraitCategory::Other,
+        p_value: 1e-10,
+        effect: None,
+        risk_allele_frequency: Some(frequency),
+        genes: Vec::new(),
+        chromosome: None,
+        position: None,
+        pubmed_id: None,
+        study: None,
+        added: None,
+    }
+}
+
+#[test]
+fn reads_minus_strand_alleles_from_the_opposite_strand() {
+    let genome = array(&[("rs1", "AG"), ("rs2", "AA"), ("rs3", "AT"), ("rs4", "CC")]);
+    let variant = |rsid: &str| genome.get_by_rsid(rsid).unwrap();
+
+    assert_eq!(strand::resolve(variant("rs1"), &["A"]), Strand::Forward);
+    // T and C are not alleles of an A/G call, their complements are
+    assert_eq!(strand::resolve(variant("rs1"), &["T"]), Strand::Flipped);
+    assert_eq!(
+        strand::resolve(variant("rs1"), &["T", "C"]),
+        Strand::Flipped
+    );
+    assert_eq!(Strand::Flipped.orient("T"), "A");
+    // An A/T call reads the same on both strands
+    assert_eq!(strand::resolve(variant("rs3"), &["A"]), Strand::Ambiguous);
+    // Neither G nor its complement fits: taken as reported, no copies
+    assert_eq!(strand::resolve(variant("rs4"), &["A"]), Strand::Forward);
+    assert_eq!(
+        strand::resolve(variant("rs1"), &["D", "I"]),
+        Strand::Forward
+    );
+
+    let catalog = GwasCatalog::from_associations(vec![
+        association("rs1", "T", 0.3),
+        association("rs2", "C", 0.2),
+        association("rs4", "A", 0.5),
+    ]);
+    let matches = catalog.annotate(&genome, 1.0, |_| Ok(())).unwrap();
+    let found: Vec<(&str, usize, Strand)> = matches
+        .iter()
+        .map(|found| {
+            (
+                found.association.rsid.as_str(),
+                found.risk_allele_copies,
+                found.strand,
+            )
+        })
+        .collect();
+    // rs2's homozygous AA call cannot tell a C risk allele's strand, but G
+    // is no complement of A, so it is not carried either way
+    assert_eq!(found, [("rs1", 1, Strand::Flipped)]);
+}
+
+#[test]
+fn settles_palindromic_snps_by_allele_frequency() {
+    let genome = array(&[("rs3", "AA"), ("rs4", "AT"), ("rs5", "TT")]);
+    let gnomad = GnomadDatabase::from_vcf(GNOMAD_VCF.as_bytes()).unwrap();
+    let variant = |rsid: &str| genome.get_by_rsid(rsid).unwrap();
+    let settle = |rsid: &str, allele: &str, frequency: f64| {
+        strand::resolve_by_frequency(
+            Strand::Ambiguous,
+            variant(rsid),
+            allele,
+            Some(frequency),
+            &gnomad,
+        )
+    };
+    // gnomAD puts T at 10% on the plus strand
+    assert_eq!(settle("rs3", "T", 0.12), Strand::FrequencyForward);
+    assert_eq!(settle("rs3", "T", 0.88), Strand::FrequencyFlipped);
+    // Too close to 50% to tell; rs5 is not in gnomAD and stays as it was
+    assert_eq!(settle("rs4", "T", 0.1), Strand::Ambiguous);
+    assert_eq!(settle("rs5", "T", 0.1), Strand::Ambiguous);
+    assert_eq!(
+        strand::resolve_by_frequency(Strand::Forward, variant("rs5"), "T", None, &gnomad),
+        Strand::Forward
+    );
+
+    // Only gnomAD shows the homozygous rs3 call to be at an A/T SNP, where A
+    // is common. A risk allele A reported at 10% is the rare plus-strand T
+    // read from the minus strand, so the AA call carries none of it.
+    let catalog = GwasCatalog::from_associations(vec![
+        association("rs3", "A", 0.1),
+        association("rs4", "A", 0.5),
+    ]);
+    let mut matches = catalog.annotate(&genome, 1.0, |_| Ok(())).unwrap();
+    let mut strands: Vec<(&str, Strand)> = matches
+        .iter()
+        .map(|found| (found.association.rsid.as_str(), found.strand))
+        .collect();
+    strands.sort_unstable_by_key(|(rsid, _)| *rsid);
+    assert_eq!(
+        strands,
+        [("rs3", Strand::Forward), ("rs4", Strand::Ambiguous)]
+    );
+    gwas::settle_strands(&mut matches, &gnomad);
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].association.rsid, "rs4");
+    assert_eq!(matches[0].strand, Strand::Ambiguous);
+}
@@ -0,0 +1,73 @@
+//! Streaming analysis tests
+
+use genomeforge_core::annotation::DatabaseSnapshot;
+use genomeforge_core::open_genome;
+use genomeforge_core::stream::{self, SiteFilter, SpillStore, StreamOptions};
+use genomeforge_core::Variant;
+use tempfile::TempDir;
+
+const GENOME: &str = "# This data file generated by 23andMe\n\
+# rsid\tchromosome\tposition\tgenotype\n\
+rs429358\t19\t45411941\tTC\n\
+rs7412\t19\t45412079\tCC\n\
+rs1000\t1\t1000\tAG\n\
+i5000001\t2\t5000\tGG\n\
+rs2000\tMT\t16519\t--\n";
+
+#[test]
+fn streams_only_known_sites_but_counts_every_record() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("genome.txt");
+    std::fs::write(&path, GENOME).unwrap();
+    let mut source = open_genome(&path).unwrap();
+
+    // Without databases only APOE and the build markers are of interest
+    let mut sites = DatabaseSnapshot::default().sites();
+    sites.add_position("chr2", 5000);
+    let options = StreamOptions {
+        spill_dir: dir.path().to_path_buf(),
+        ..StreamOptions::default()
+    };
+    let (genome, stats) =
+        stream::stream_sites(source.as_mut(), &sites, &options, |_| Ok(())).unwrap();
+
+    assert_eq!((stats.records, stats.kept, stats.chunks), (5, 3, 1));
+    assert_eq!(genome.summary.variant_count, 5);
+    assert_eq!(genome.summary.no_call_count, 1);
+    assert!(genome.get_by_rsid("rs429358").is_some());
+    assert!(genome.get_at("2", 5000).is_some());
+    assert!(genome.get_by_rsid("rs1000").is_none());
+}
+
+#[test]
+fn spills_encrypted_and_reads_back_in_order() {
+    let dir = TempDir::new().unwrap();
+    let variant = |n: u64| Variant {
+        rsid: Some(format!("rs{}", n)),
+        chromosome: "1".to_string(),
+        position: n,
+        reference: None,
+        alternates: Vec::new(),
+        genotype: "AG".parse().unwrap(),
+        quality: None,
+    };
+    let mut store = SpillStore::new(dir.path(), 200);
+    for n in 1..=20 {
+        store.push(&variant(n)).unwrap();
+    }
+    assert!(store.spilled_bytes() > 0);
+
+    let spilled = std::fs::read_dir(dir.path()).unwrap().next().unwrap();
+    let bytes = std::fs::read(spilled.unwrap().path()).unwrap();
+    assert!(!bytes.windows(4).any(|window| window == b"rs12"));
+
+    let variants = store.into_vec().unwrap();
+    let positions: Vec<u64> = variants.iter().map(|v| v.position).collect();
+    assert_eq!(positions, (1..=20).collect::<Vec<_>>());
+    // The spill file is gone once the store is
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    let mut sites = SiteFilter::default();
+    sites.add_rsid("rs3");
+    assert!(sites.contains(&variant(3)) && !sites.contains(&variant(4)));
+}
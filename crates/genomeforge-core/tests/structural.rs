@@ -0,0 +1,118 @@
+//! Structural variant parsing and ClinGen dosage annotation tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::annotation::clingen::{ClinGenDatabase, DosageScore};
+use genomeforge_core::cache::GenomeCache;
+use genomeforge_core::crypto::Key;
+use genomeforge_core::genome::{GenomeBuild, StructuralKind};
+use genomeforge_core::LoadedGenome;
+use std::io::Cursor;
+use tempfile::TempDir;
+
+/// A whole-genome VCF: SNVs, a gVCF reference block, a deletion, a tandem
+/// duplication, a copy-number record, a homozygous deletion and a
+/// deletion the sample does not carry
+const WGS_VCF: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+##ALT=<ID=DEL,Description=\"Deletion\">\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n\
+1\t100\trs1\tG\tA\t90\tPASS\t.\tGT\t0/1\n\
+1\t150\t.\tA\t<NON_REF>\t.\t.\tEND=180\tGT\t0/0\n\
+1\t1000\tsv1\tN\t<DEL>\t60\tPASS\tSVTYPE=DEL;END=5000\tGT\t0/1\n\
+2\t2000\tsv2\tN\t<DUP:TANDEM>\t60\tPASS\tEND=9000\tGT\t0/1\n\
+3\t3000\tcnv1\tN\t<CNV>\t60\tLowQual\tEND=4000\tGT:CN\t./.:3\n\
+4\t1000\tsv3\tN\t<DEL>\t60\tPASS\tEND=3000\tGT\t1/1\n\
+5\t1000\tsv4\tN\t<DEL>\t60\tPASS\tEND=3000\tGT\t0/0\n\
+22\t500\trs2\tC\tT\t90\tPASS\t.\tGT\t1/1\n";
+
+/// Gene curation list in the layout ClinGen publishes
+const DOSAGE_MAP: &str = "#ClinGen Gene Curation Results\n\
+#Genomic Locations are reported on GRCh38 (hg38): GCF_000001405.36\n\
+#Gene Symbol\tGene ID\tcytoBand\tGenomic Location\tHaploinsufficiency Score\tTriplosensitivity Score\tHaploinsufficiency Disease ID\tTriplosensitivity Disease ID\n\
+GENEA\t1\t1p36\tchr1:2000-3000\t3\t1\tMONDO:0000001\t\n\
+GENEB\t2\t1p36\tchr1:4000-8000\t2\t0\t\t\n\
+GENEC\t3\t2q11\tchr2:5000-6000\t40\t3\t\tMONDO:0000002\n\
+GENED\t4\t3p21\tchr3:3500-3600\t1\t0\t\t\n\
+GENEE\t5\t4q12\tchr4:1500-2500\t30\t0\t\t\n\
+GENEF\t6\t5q12\tchr5:1500-2500\t3\t0\t\t\n\
+GENEG\t7\t6p21\ttbd\t3\t0\t\t\n";
+
+fn load() -> LoadedGenome {
+    load_genome("sample.vcf", WGS_VCF)
+}
+
+#[test]
+fn sets_structural_records_apart_from_variants() {
+    let dir = TempDir::new().unwrap();
+    let genome = load();
+
+    // The gVCF placeholder stays a variant; the symbolic records do not
+    assert_eq!(genome.len(), 3);
+    assert!(genome.get_by_rsid("rs2").is_some());
+    let structural = genome.structural_variants();
+    let kinds: Vec<StructuralKind> = structural.iter().map(|sv| sv.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            StructuralKind::Deletion,
+            StructuralKind::Duplication,
+            StructuralKind::Duplication,
+            StructuralKind::Deletion,
+            StructuralKind::Deletion,
+        ]
+    );
+    // Affected bases start after the padding base
+    let deletion = &structural[0];
+    assert_eq!(
+        (deletion.start, deletion.end, deletion.length()),
+        (1001, 5000, 4000)
+    );
+    assert_eq!(deletion.copies, Some(1));
+    // A copy number of three makes a duplication without a genotype
+    let cnv = &structural[2];
+    assert_eq!((cnv.copies, cnv.copy_number), (None, Some(3)));
+    assert!(cnv.is_carried() && !cnv.is_homozygous());
+    assert_eq!(cnv.filters, ["LowQual"]);
+    assert!(structural[3].is_homozygous());
+    assert!(!structural[4].is_carried());
+
+    // They survive the genome cache
+    let cache = GenomeCache::new(&dir.path().join("cache"), Key::generate());
+    let hash = "0".repeat(64);
+    cache.put(&hash, &genome).unwrap();
+    let cached = cache.get(&hash).unwrap().unwrap();
+    assert_eq!(cached.structural_variants(), structural);
+}
+
+#[test]
+fn matches_deletions_and_duplications_to_dosage_sensitive_genes() {
+    let genome = load();
+    let database = ClinGenDatabase::from_reader(Cursor::new(DOSAGE_MAP)).unwrap();
+    // The unplaced gene is left out
+    assert_eq!(database.len(), 6);
+    assert_eq!(database.build(), Some(GenomeBuild::GRCh38));
+
+    let matches = database.annotate(genome.structural_variants());
+    let found: Vec<(&str, DosageScore, bool)> = matches
+        .iter()
+        .map(|found| (found.region.name.as_str(), found.score, found.is_whole()))
+        .collect();
+    // GENEC is matched by its triplosensitivity, not its haploinsufficiency;
+    // GENED's score is too weak; GENEE needs both copies lost, which sv3
+    // takes; GENEF's deletion is not carried
+    assert_eq!(
+        found,
+        [
+            ("GENEA", DosageScore::Sufficient, true),
+            ("GENEB", DosageScore::Emerging, false),
+            ("GENEC", DosageScore::Sufficient, true),
+            ("GENEE", DosageScore::AutosomalRecessive, true),
+        ]
+    );
+    assert_eq!(matches[0].disease.as_deref(), Some("MONDO:0000001"));
+    assert_eq!(matches[2].disease.as_deref(), Some("MONDO:0000002"));
+    // 1001-5000 covers 4000-5000 of GENEB's 4000-8000
+    assert!((matches[1].overlap - 1001.0 / 4001.0).abs() < 1e-9);
+}
@@ -0,0 +1,131 @@
+//! BGZF and tabix index tests
+
+use genomeforge_core::parser::bgzf;
+use genomeforge_core::parser::tabix::{self, IndexFormat, IndexedVcf};
+use genomeforge_core::{GenomeBuild, Region};
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+const HEADER: &str = "##fileformat=VCFv4.2\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tSAMPLE\n";
+
+/// Sites every 1,000 bp on two chromosomes, enough for many BGZF blocks and
+/// 16 kb windows, plus a deletion and a symbolic allele spanning others
+fn records() -> Vec<(&'static str, u64, String, String)> {
+    let mut records = Vec::new();
+    for chromosome in ["chr1", "chr2"] {
+        for site in 1..=3_000u64 {
+            let position = site * 1_000;
+            let (reference, info) = match site {
+                100 => ("ACGTACGTAC", "."),
+                200 => ("N", "SVTYPE=DEL;END=250500"),
+                _ => ("A", "."),
+            };
+            let alternate = if site == 200 { "<DEL>" } else { "G" };
+            let line = format!(
+                "{}\t{}\trs{}\t{}\t{}\t.\tPASS\t{}\tGT\t0/1\n",
+                chromosome, position, site, reference, alternate, info
+            );
+            records.push((chromosome, position, reference.to_string(), line));
+        }
+    }
+    records
+}
+
+fn bgzipped_vcf(dir: &TempDir) -> PathBuf {
+    let plain = dir.path().join("genome.vcf");
+    let mut contents = HEADER.to_string();
+    for (_, _, _, line) in records() {
+        contents.push_str(&line);
+    }
+    std::fs::write(&plain, contents).unwrap();
+    let compressed = dir.path().join("genome.vcf.gz");
+    bgzf::compress_file(&plain, &compressed).unwrap();
+    compressed
+}
+
+#[test]
+fn builds_an_index_and_answers_region_queries() {
+    let dir = TempDir::new().unwrap();
+    let path = bgzipped_vcf(&dir);
+    let vcf = IndexedVcf::open(&path).unwrap();
+    assert_eq!(vcf.index().format(), IndexFormat::Tabix);
+    assert_eq!(vcf.index().chromosomes(), ["chr1", "chr2"]);
+    let index_path = tabix::index_path(&path, IndexFormat::Tabix);
+    assert!(index_path.is_file());
+
+    let saved = IndexedVcf::open(&path).unwrap();
+    for (chromosome, start, end) in [
+        ("1", 1, 2_000),
+        ("chr1", 100_005, 100_005),
+        ("2", 240_000, 240_000),
+        ("2", 1_234_567, 1_750_001),
+        ("1", 2_999_000, 5_000_000),
+        ("X", 1, 1_000_000),
+    ] {
+        let expected: Vec<u64> = records()
+            .into_iter()
+            .filter(|(name, position, reference, _)| {
+                let last = if *position == 200_000 {
+                    250_500
+                } else {
+                    position + reference.len() as u64 - 1
+                };
+                name.trim_start_matches("chr") == chromosome.trim_start_matches("chr")
+                    && *position <= end
+                    && last >= start
+            })
+            .map(|(_, position, _, _)| position)
+            .collect();
+        for opened in [&vcf, &saved] {
+            let found: Vec<u64> = opened
+                .query(chromosome, start, end)
+                .unwrap()
+                .iter()
+                .map(|record| record.position)
+                .collect();
+            assert_eq!(found, expected, "{}:{}-{}", chromosome, start, end);
+        }
+    }
+
+    let region = vcf
+        .load_region(&Region::new("chr1", 99_999, 100_001))
+        .unwrap();
+    assert_eq!(region.len(), 1);
+    assert_eq!(region.variants()[0].chromosome, "1");
+    assert!(region.get_by_rsid("rs100").is_some());
+    assert_eq!(region.file.genome_build, Some(GenomeBuild::GRCh38));
+}
+
+#[test]
+fn needs_bgzip_and_a_sorted_file() {
+    let dir = TempDir::new().unwrap();
+    let plain = dir.path().join("plain.vcf");
+    std::fs::write(
+        &plain,
+        format!("{}1\t10\t.\tA\tG\t.\t.\t.\tGT\t0/1\n", HEADER),
+    )
+    .unwrap();
+    assert!(IndexedVcf::open(&plain).unwrap_err().contains("bgzip"));
+
+    let unsorted = dir.path().join("unsorted.vcf");
+    std::fs::write(
+        &unsorted,
+        format!(
+            "{}1\t20\t.\tA\tG\t.\t.\t.\tGT\t0/1\n1\t10\t.\tA\tG\t.\t.\t.\tGT\t0/1\n",
+            HEADER
+        ),
+    )
+    .unwrap();
+    let compressed = dir.path().join("unsorted.vcf.gz");
+    bgzf::compress_file(&unsorted, &compressed).unwrap();
+    assert!(IndexedVcf::open(&compressed)
+        .unwrap_err()
+        .contains("sorted"));
+
+    // A decompressed copy of a BGZF file reads back unchanged
+    let mut contents = String::new();
+    std::io::Read::read_to_string(&mut bgzf::open(&compressed).unwrap(), &mut contents).unwrap();
+    assert_eq!(contents, std::fs::read_to_string(&unsorted).unwrap());
+}
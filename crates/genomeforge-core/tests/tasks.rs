@@ -0,0 +1,39 @@
+//! Task registry tests
+
+use genomeforge_core::tasks::{TaskKind, TaskRegistry, CANCELLED};
+
+#[test]
+fn cancels_registered_task_at_next_checkpoint() {
+    let registry = TaskRegistry::default();
+    let task = registry.start(TaskKind::Analysis);
+    assert!(task.checkpoint().is_ok());
+
+    assert!(registry.cancel(task.id()));
+    assert_eq!(task.checkpoint().unwrap_err(), CANCELLED);
+    assert!(registry.list()[0].cancelled);
+}
+
+#[test]
+fn dropping_handle_unregisters_task() {
+    let registry = TaskRegistry::default();
+    let first = registry.start(TaskKind::Parse);
+    let second = registry.start(TaskKind::Analysis);
+    assert_ne!(first.id(), second.id());
+    assert_eq!(registry.list().len(), 2);
+
+    let id = first.id();
+    drop(first);
+    assert_eq!(registry.list().len(), 1);
+    assert!(!registry.cancel(id));
+}
+
+#[test]
+fn cancels_all_tasks_of_a_kind() {
+    let registry = TaskRegistry::default();
+    let parse = registry.start(TaskKind::Parse);
+    let analysis = registry.start(TaskKind::Analysis);
+
+    assert_eq!(registry.cancel_kind(TaskKind::Parse), 1);
+    assert!(parse.checkpoint().is_err());
+    assert!(analysis.checkpoint().is_ok());
+}
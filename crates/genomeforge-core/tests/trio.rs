@@ -0,0 +1,135 @@
+//! Trio analysis tests
+
+mod common;
+
+use common::{load_calls, load_database};
+use genomeforge_core::annotation::carrier::{self, CarrierStatus};
+use genomeforge_core::trio::{self, Parent};
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+7\t117540230\t7107\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Cystic_fibrosis;GENEINFO=CFTR:1080;RS=77834169\n\
+7\t117587806\t7106\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Cystic_fibrosis;GENEINFO=CFTR:1080;RS=75527207\n\
+11\t5227002\t15333\tT\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=Sickle_cell_anemia;GENEINFO=HBB:3043;RS=334\n\
+X\t154536002\t10367\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=G6PD_deficiency;GENEINFO=G6PD:2539;RS=5030868\n";
+
+#[test]
+fn flags_genotypes_neither_parent_could_pass_on() {
+    let child = load_calls(
+        38,
+        &[
+            ("rs1", "1", 1000, "AG"),
+            ("rs2", "2", 2000, "TT"),
+            ("rs3", "3", 3000, "CC"),
+            ("rs4", "4", 4000, "--"),
+            ("rs5", "X", 5000, "AA"),
+            ("rs6", "6", 6000, "GG"),
+        ],
+    );
+    let mother = load_calls(
+        38,
+        &[
+            ("rs1", "1", 1000, "AA"),
+            ("rs2", "2", 2000, "CT"),
+            ("rs3", "3", 3000, "CC"),
+            ("rs4", "4", 4000, "AA"),
+            ("rs5", "X", 5000, "GG"),
+        ],
+    );
+    // Matched by rsid where the position differs
+    let father = load_calls(
+        38,
+        &[
+            ("rs1", "1", 1000, "GG"),
+            ("rs2", "2", 2000, "CC"),
+            ("rs3", "3", 3001, "CT"),
+            ("rs4", "4", 4000, "AA"),
+            ("rs5", "X", 5000, "A"),
+            ("rs6", "6", 6000, "GG"),
+        ],
+    );
+
+    let mut checked = 0;
+    let check = trio::mendelian_check(&child, &mother, &father, |n| {
+        checked = n;
+        Ok(())
+    })
+    .unwrap();
+    // The no-call, the X site and the site the mother lacks are skipped
+    assert_eq!(check.sites_compared, 3);
+    assert_eq!(check.error_count, 1);
+    assert_eq!(check.errors[0].rsid.as_deref(), Some("rs2"));
+    assert_eq!(checked, child.len());
+    assert!(check.parentage_doubtful);
+
+    let stopped = trio::mendelian_check(&child, &mother, &father, |_| Err("Cancelled".into()));
+    assert!(stopped.is_err());
+}
+
+#[test]
+fn traces_recessive_variants_to_each_parent() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let child = load_calls(
+        38,
+        &[
+            ("rs77834169", "7", 117540230, "CT"),
+            ("rs75527207", "7", 117587806, "AG"),
+            ("rs334", "11", 5227002, "AT"),
+        ],
+    );
+    let mother = load_calls(
+        38,
+        &[
+            ("rs77834169", "7", 117540230, "CT"),
+            ("rs75527207", "7", 117587806, "GG"),
+            ("rs334", "11", 5227002, "AT"),
+            ("rs5030868", "X", 154536002, "CT"),
+        ],
+    );
+    let father = load_calls(
+        38,
+        &[
+            ("rs77834169", "7", 117540230, "CC"),
+            ("rs75527207", "7", 117587806, "AG"),
+            ("rs334", "11", 5227002, "AT"),
+            ("rs5030868", "X", 154536002, "C"),
+        ],
+    );
+
+    let matches = db.annotate(&child, |_| Ok(())).unwrap();
+    let candidates = trio::compound_heterozygous(&matches, &mother, &father);
+    // A single HBB variant is not a candidate
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].gene, "CFTR");
+    assert!(candidates[0].in_trans());
+    assert_eq!(
+        trio::parent_of_origin(&candidates[0].maternal[0], &mother, &father),
+        Some(Parent::Mother)
+    );
+    assert_eq!(candidates[0].paternal[0].variant.position, 117587806);
+
+    let mother_matches = db.annotate(&mother, |_| Ok(())).unwrap();
+    let father_matches = db.annotate(&father, |_| Ok(())).unwrap();
+    let risks = trio::carrier_couples(
+        &carrier::screen(&mother_matches),
+        &carrier::screen(&father_matches),
+    );
+    let risks: Vec<(&str, f64, Option<f64>)> = risks
+        .iter()
+        .map(|risk| (risk.gene.gene, risk.risk_per_child, risk.risk_per_son))
+        .collect();
+    // Each parent carries one CFTR variant; only the mother carries G6PD
+    assert_eq!(
+        risks,
+        [
+            ("CFTR", 0.25, None),
+            ("HBB", 0.25, None),
+            ("G6PD", 0.25, Some(0.5)),
+        ]
+    );
+    assert_eq!(
+        carrier::screen(&mother_matches)[2].status,
+        CarrierStatus::Carrier
+    );
+}
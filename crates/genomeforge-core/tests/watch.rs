@@ -0,0 +1,61 @@
+//! Watched folder tests
+
+use genomeforge_core::watch::FolderWatch;
+use std::fs;
+use tempfile::TempDir;
+
+const GENOME: &str = "# rsid\tchromosome\tposition\tgenotype\nrs1801133\t1\t11856378\tAG\n";
+
+#[test]
+fn reports_files_once_they_stop_changing() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("existing.txt"), GENOME).unwrap();
+    let mut watch = FolderWatch::new(dir.path()).unwrap();
+    assert!(watch.poll().unwrap().is_empty());
+
+    // Still being copied in on the first poll
+    let new = dir.path().join("customer-17.txt");
+    fs::write(&new, &GENOME[..20]).unwrap();
+    assert!(watch.poll().unwrap().is_empty());
+    assert!(watch.settling(&new));
+    fs::write(&new, GENOME).unwrap();
+    assert!(watch.poll().unwrap().is_empty());
+    assert_eq!(watch.poll().unwrap(), std::slice::from_ref(&new));
+    assert!(watch.poll().unwrap().is_empty());
+
+    // Replaced with another file of the same name
+    fs::write(&new, format!("{}rs429358\t19\t45411941\tTC\n", GENOME)).unwrap();
+    assert!(watch.poll().unwrap().is_empty());
+    assert_eq!(watch.poll().unwrap(), [new]);
+
+    // An empty file is waited on until it has contents
+    let empty = dir.path().join("empty.txt");
+    fs::write(&empty, "").unwrap();
+    watch.poll().unwrap();
+    assert!(watch.poll().unwrap().is_empty());
+}
+
+#[test]
+fn skips_partial_and_hidden_files() {
+    let dir = TempDir::new().unwrap();
+    let mut watch = FolderWatch::new(dir.path()).unwrap();
+    for name in [
+        "genome.txt.crdownload",
+        "upload.PART",
+        ".hidden.vcf",
+        "~$notes.txt",
+    ] {
+        fs::write(dir.path().join(name), GENOME).unwrap();
+    }
+    fs::create_dir(dir.path().join("folder.vcf")).unwrap();
+    fs::write(dir.path().join("b.vcf"), GENOME).unwrap();
+    fs::write(dir.path().join("a.txt"), GENOME).unwrap();
+    watch.poll().unwrap();
+    assert_eq!(
+        watch.poll().unwrap(),
+        [dir.path().join("a.txt"), dir.path().join("b.vcf")]
+    );
+
+    assert_eq!(watch.dir(), dir.path());
+    assert!(FolderWatch::new(&dir.path().join("missing")).is_err());
+}
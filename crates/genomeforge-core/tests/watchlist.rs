@@ -0,0 +1,102 @@
+//! Variant watchlist tests
+
+mod common;
+
+use common::load_genome;
+use genomeforge_core::crypto::{Key, KeySource};
+use genomeforge_core::watchlist::{self, Target, WatchEntry, WatchStatus, Watchlist};
+use genomeforge_core::{GenomeBuild, LoadedGenome};
+use tempfile::TempDir;
+
+fn genome() -> LoadedGenome {
+    load_genome(
+        "genome.txt",
+        "# build 37\n# rsid\tchromosome\tposition\tgenotype\n\
+         rs80357906\t17\t41209079\tCC\n\
+         rs80357713\t17\t41276045\t--\n\
+         rs429358\t19\t45411941\tTC\n\
+         rs7412\t19\t45412079\t--\n",
+    )
+}
+
+fn list(targets: &[&str]) -> Watchlist {
+    let mut list = Watchlist::default();
+    for target in targets {
+        list.add(WatchEntry::new(target, "").unwrap()).unwrap();
+    }
+    list
+}
+
+#[test]
+fn reports_calls_and_gaps_at_watched_sites() {
+    let genome = genome();
+    let list = list(&[
+        "RS429358",
+        "rs7412",
+        "rs397507444",
+        "BRCA1",
+        "19:45411000-45412500",
+        "X:1-1000",
+    ]);
+    let results = list.check(&genome, Some(GenomeBuild::GRCh37));
+    let statuses: Vec<WatchStatus> = results.iter().map(|result| result.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            WatchStatus::Called,
+            WatchStatus::NoCall,
+            WatchStatus::Missing,
+            WatchStatus::Called,
+            WatchStatus::Called,
+            WatchStatus::Missing,
+        ]
+    );
+    assert_eq!(results[0].calls[0].genotype, "TC");
+    assert_eq!(results[0].entry.target, "RS429358");
+    let brca1: Vec<(&str, u64)> = results[3]
+        .calls
+        .iter()
+        .map(|call| (call.genotype.as_str(), call.position))
+        .collect();
+    assert_eq!(brca1, [("CC", 41209079), ("--", 41276045)]);
+    assert_eq!(results[4].calls.len(), 2);
+
+    // Genes cannot be located without the build
+    let unknown = list.check(&genome, None);
+    assert_eq!(unknown[3].status, WatchStatus::Unresolved);
+    assert_eq!(unknown[0].status, WatchStatus::Called);
+}
+
+#[test]
+fn edits_and_seals_watchlists() {
+    assert!(matches!(
+        WatchEntry::new(" rs80357906 ", "Mother's variant").unwrap().target(),
+        Ok(Target::Rsid(rsid)) if rsid == "rs80357906"
+    ));
+    assert!(WatchEntry::new("NOTAGENE", "").is_err());
+    assert!(WatchEntry::new("17:500-100", "").is_err());
+    let long = "x".repeat(watchlist::MAX_LABEL_LENGTH + 1);
+    assert!(WatchEntry::new("rs1", &long).is_err());
+
+    let mut list = list(&["rs80357906", "BRCA2"]);
+    list.add(WatchEntry::new("brca2", "Father's side").unwrap())
+        .unwrap();
+    assert_eq!(list.entries.len(), 2);
+    assert_eq!(list.entries[1].label, "Father's side");
+    assert!(list.remove("RS80357906") && !list.remove("rs80357906"));
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(watchlist::FILE_NAME);
+    let key = Key::generate();
+    assert!(Watchlist::open(&path, KeySource::Device(&key))
+        .unwrap()
+        .is_empty());
+    list.save(&path, KeySource::Device(&key)).unwrap();
+    let written = std::fs::read(&path).unwrap();
+    assert!(!written.windows(5).any(|w| w == b"BRCA2"));
+    assert_eq!(
+        Watchlist::open(&path, KeySource::Device(&key)).unwrap(),
+        list
+    );
+    assert!(Watchlist::open(&path, KeySource::Device(&Key::generate())).is_err());
+}
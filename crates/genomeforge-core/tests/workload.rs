@@ -0,0 +1,78 @@
+//! Workload estimate tests
+
+use genomeforge_core::benchmark;
+use genomeforge_core::workload::{self, Machine, Sample, WorkloadEstimate, MEMORY_PER_VARIANT};
+use std::time::Duration;
+use tempfile::TempDir;
+
+const MACHINE: Machine = Machine {
+    memory_total: Some(16_000_000_000),
+    memory_available: Some(8_000_000_000),
+    cpu_cores: 4,
+};
+
+#[test]
+fn extrapolates_from_the_start_of_a_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("genome.txt");
+    benchmark::synthetic_23andme(std::fs::File::create(&path).unwrap(), 100_000).unwrap();
+
+    let sample = Sample::read(&path, 40_000).unwrap();
+    assert_eq!(sample.variants, 40_000);
+    assert!(!sample.complete);
+    let size = std::fs::metadata(&path).unwrap().len();
+    let estimate = WorkloadEstimate::new(size, &sample, &MACHINE);
+    assert!(!estimate.exact);
+    // Reads run ahead of the parse by a buffer, so the count comes out low
+    assert!(
+        (70_000..=110_000).contains(&estimate.variants),
+        "{}",
+        estimate.variants
+    );
+    assert!(estimate.parse_seconds >= sample.elapsed.as_secs_f64());
+
+    let estimate = workload::estimate(&path, &MACHINE).unwrap();
+    assert!(estimate.exact);
+    assert_eq!(estimate.variants, 100_000);
+    assert_eq!(estimate.memory_needed, 100_000 * MEMORY_PER_VARIANT);
+    assert_eq!(estimate.memory_sufficient, Some(true));
+    assert!(estimate.warnings.is_empty());
+}
+
+#[test]
+fn warns_when_memory_runs_short() {
+    let sample = Sample {
+        variants: 1_000_000,
+        bytes_read: 25_000_000,
+        elapsed: Duration::from_secs(2),
+        complete: false,
+    };
+    let small = Machine {
+        memory_total: Some(4_000_000_000),
+        memory_available: Some(1_000_000_000),
+        cpu_cores: 2,
+    };
+    let estimate = WorkloadEstimate::new(250_000_000, &sample, &small);
+    assert_eq!(estimate.variants, 10_000_000);
+    assert_eq!(estimate.parse_seconds, 20.0);
+    assert_eq!(estimate.analysis_seconds, 15.0);
+    assert_eq!(estimate.memory_sufficient, Some(false));
+    assert!(estimate.warnings[0].contains("2.5 GB"));
+    assert!(estimate.warnings[0].contains("Closing other applications"));
+
+    let tiny = Machine {
+        memory_total: Some(2_000_000_000),
+        ..small
+    };
+    let estimate = WorkloadEstimate::new(250_000_000, &sample, &tiny);
+    assert!(estimate.warnings[0].contains("may not have enough memory"));
+
+    let unknown = Machine {
+        memory_total: None,
+        memory_available: None,
+        cpu_cores: 1,
+    };
+    let estimate = WorkloadEstimate::new(250_000_000, &sample, &unknown);
+    assert_eq!(estimate.memory_sufficient, None);
+    assert!(estimate.warnings.is_empty());
+}
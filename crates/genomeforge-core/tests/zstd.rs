@@ -0,0 +1,126 @@
+//! Zstandard decompression tests
+//!
+//! The frames below were written by the `zstd` tool from the data the
+//! helpers at the end of this file generate.
+
+use genomeforge_core::parser::zstd::ZstdDecoder;
+use std::io::Read;
+
+/// `genome()` at level 1 without a checksum
+const GENOME_FAST: &str = "\
+28b52ffd0048fd0900e69c3a12a0a5a039fe7f1e7ba589880811fbdf01eb0a39003200330022133c3a5c81218cbe03f5\
+2c8ab6db5205d1355d2986a66f1824849e4956a3b43c160c180400040b83743709060c0202c11111348f08060c024797\
+30e9d58157556bf5f1d08a566dbc8102a92e4e44c7a9891fa928f570de105b0b9f9921eb728b11464d1e2646063ab804\
+7738c89a04fb168b969c6d974449ccae43082265d3ad50217ba606cad8f2dd858add4977adcd8767a5dd6e66d5b0d963\
+6c1a2a0100c11614eee33213e66ee315736377f150d29aba8957a434740fa7d0cecc2dfca8edc85dceb69cdee4736c0c\
+efe0366c90082520906226f83fe27d48eae5c49bd32e27dd9c72e494134ef6b9d9ebb2dfc98847502f413e827e090242\
+90c4473cf1c1c40389ff25de477c0ff13ce27789d7253e27c4a37332a3882f7b328278c85e4ef6a0";
+
+/// `genome()` at level 19 with a checksum
+const GENOME_BEST: &str = "\
+28b52ffd0468d50800e61c3a12a015a439ab6a1a71af89880811fbdf416a0c3600320033002646063ab8884cf0e87005\
+8630fa0ed4b328da6e4b1544d774a5189abe6190107a26598dd2f25840b030487713802038681e111c100863360d7574\
+091be9d58157155bab8f87d645ab36de4081541727a21ea7267ea450947a38af88ad85cfcc9075b9c508a3268f773848\
+3309f62d169d92b3ed922849ccae433891b2e956a00ad933359432b67c1742c5eea4ab6b6d3e3cb1d26e378b6ad8ec11\
+0001c11614eee3323361ee365e31bcb1bb7828b99aba89578468e81e4ea133730b3f6ae8c85dceb638bdc9e724867770\
+1b46900828a81046842bcf078097d418208418f601d5db8942eeb5c0ed531006821b6a32e9a4390d09a426ff11c5bb54\
+2fb04c91fe8bd1";
+
+/// `repeats()` at level 19, three blocks matching back across each other
+const REPEATS: &str = "\
+28b52ffd04682c020084026368723009300a6368723109310a6368723209320a33343536303132333435363031323334\
+35360a13a810e8ebdf01d07335101abdffffffdf016aff163ac41def4e01540000000100fdffa4ffb906024500000001\
+00dd131d0001152bfb86";
+
+/// 200,000 zero bytes at level 19, ending in a run-length block
+const ZEROS: &str = "28b52ffd04684c000008000100fcff391002036a0800c4e97470";
+
+#[test]
+fn decompresses_frames_written_by_zstd() {
+    assert_eq!(decompress(&frame(GENOME_FAST)).unwrap(), genome());
+    assert_eq!(decompress(&frame(GENOME_BEST)).unwrap(), genome());
+    assert_eq!(decompress(&frame(REPEATS)).unwrap(), repeats());
+    assert_eq!(decompress(&frame(ZEROS)).unwrap(), vec![0; 200_000]);
+}
+
+#[test]
+fn reads_concatenated_and_skippable_frames() {
+    let mut stream = frame(GENOME_FAST);
+    // A skippable frame holding five bytes
+    stream.extend_from_slice(&[0x53, 0x2a, 0x4d, 0x18, 5, 0, 0, 0]);
+    stream.extend_from_slice(b"notes");
+    stream.extend(frame(ZEROS));
+
+    let mut expected = genome();
+    expected.extend(vec![0; 200_000]);
+    assert_eq!(decompress(&stream).unwrap(), expected);
+    assert!(decompress(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn rejects_damaged_frames() {
+    let original = frame(GENOME_BEST);
+
+    let mut checksum = original.clone();
+    *checksum.last_mut().unwrap() ^= 1;
+    assert!(decompress(&checksum).unwrap_err().contains("checksum"));
+
+    let truncated = &original[..original.len() - 10];
+    assert!(decompress(truncated).unwrap_err().contains("truncated"));
+
+    let mut magic = original.clone();
+    magic[0] = 0;
+    assert!(decompress(&magic)
+        .unwrap_err()
+        .contains("not a Zstandard frame"));
+
+    // Damage inside the compressed block is caught one way or another
+    for at in [12, 100, 250] {
+        let mut damaged = original.clone();
+        damaged[at] ^= 0x10;
+        assert!(decompress(&damaged).is_err_and(|e| e.contains("corrupt")));
+    }
+}
+
+// Helper functions
+
+fn frame(hex: &str) -> Vec<u8> {
+    hex::decode(hex).unwrap()
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    ZstdDecoder::new(data)
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    Ok(out)
+}
+
+/// Forty 23andMe-style calls
+fn genome() -> Vec<u8> {
+    let genotypes = ["AA", "AG", "GG", "CT"];
+    (0..40u64)
+        .map(|i| {
+            format!(
+                "rs{}\t{}\t{}\t{}\n",
+                1000 + i * 37,
+                1 + i % 22,
+                50_000 + i * 1013,
+                genotypes[(i * i % 4) as usize]
+            )
+        })
+        .collect::<String>()
+        .into_bytes()
+}
+
+/// 300,000 bytes of a short cycle of lines
+fn repeats() -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while out.len() < 300_000 {
+        out.extend(format!("chr{}\t{}\n", i % 3, i % 7).bytes());
+        i += 1;
+    }
+    out.truncate(300_000);
+    out
+}
@@ -0,0 +1,95 @@
+//! Zygosity-aware interpretation tests
+
+mod common;
+
+use common::{load_calls, load_database};
+use genomeforge_core::annotation::zygosity::{self, InheritanceMode, Interpretation, Zygosity};
+
+const CLINVAR_VCF: &str = "##fileformat=VCFv4.1\n\
+##reference=GRCh38\n\
+#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+17\t43045712\t17661\tG\tA\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=reviewed_by_expert_panel;CLNDN=Hereditary_breast_ovarian_cancer_syndrome;GENEINFO=BRCA1:672;RS=80357906\n\
+13\t20189547\t17004\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=Deafness,_autosomal_recessive_1A;GENEINFO=OTOF:9381;RS=80356586\n\
+13\t20189600\t17005\tG\tA\t.\t.\tCLNSIG=Likely_pathogenic;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Deafness,_autosomal_recessive_1A;GENEINFO=OTOF:9381;RS=80356587\n\
+X\t31478000\t11100\tC\tT\t.\t.\tCLNSIG=Pathogenic;CLNREVSTAT=criteria_provided,_multiple_submitters,_no_conflicts;CLNDN=Duchenne_muscular_dystrophy;GENEINFO=DMD:1756;RS=128626231\n\
+1\t11796321\t3520\tG\tA\t.\t.\tCLNSIG=Uncertain_significance;CLNREVSTAT=criteria_provided,_single_submitter;CLNDN=Homocystinuria;GENEINFO=MTHFR:4524;RS=1801133\n";
+
+#[test]
+fn takes_inheritance_from_gene_lists_and_condition_names() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let genome = load_calls(
+        38,
+        &[
+            ("rs80357906", "17", 43045712, "AG"),
+            ("rs80356586", "13", 20189547, "CT"),
+            ("rs128626231", "X", 31478000, "T"),
+            ("rs1801133", "1", 11796321, "AA"),
+        ],
+    );
+    let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+    let modes: Vec<Option<InheritanceMode>> = matches
+        .iter()
+        .map(|found| zygosity::inheritance(found.record))
+        .collect();
+    assert_eq!(
+        modes,
+        [
+            Some(InheritanceMode::AutosomalDominant),
+            Some(InheritanceMode::AutosomalRecessive),
+            Some(InheritanceMode::XLinkedRecessive),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn labels_heterozygous_recessive_hits_as_carriers() {
+    let db = load_database("clinvar.vcf", CLINVAR_VCF);
+    let interpret = |calls: &[(&str, &str, u64, &str)]| {
+        let genome = load_calls(38, calls);
+        let matches = db.annotate(&genome, |_| Ok(())).unwrap();
+        let counts = zygosity::pathogenic_counts(&matches);
+        matches
+            .iter()
+            .map(|found| zygosity::interpret(found, &counts))
+            .map(|call| (call.zygosity, call.interpretation))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        interpret(&[
+            ("rs80357906", "17", 43045712, "AG"),
+            ("rs80356586", "13", 20189547, "CT"),
+            ("rs128626231", "X", 31478000, "T"),
+            ("rs1801133", "1", 11796321, "AG"),
+        ]),
+        [
+            (Zygosity::Heterozygous, Some(Interpretation::Affected)),
+            (Zygosity::Heterozygous, Some(Interpretation::Carrier)),
+            (Zygosity::Hemizygous, Some(Interpretation::Affected)),
+            // Uncertain significance is not interpreted
+            (Zygosity::Heterozygous, None),
+        ]
+    );
+    assert_eq!(
+        interpret(&[("rs80356586", "13", 20189547, "TT")]),
+        [(Zygosity::Homozygous, Some(Interpretation::Affected))]
+    );
+    // Two different variants in one recessive gene may be on both copies
+    assert_eq!(
+        interpret(&[
+            ("rs80356586", "13", 20189547, "CT"),
+            ("rs80356587", "13", 20189600, "AG"),
+        ]),
+        [
+            (
+                Zygosity::Heterozygous,
+                Some(Interpretation::PossiblyAffected)
+            ),
+            (
+                Zygosity::Heterozygous,
+                Some(Interpretation::PossiblyAffected)
+            ),
+        ]
+    );
+}